@@ -2,25 +2,73 @@
 //!
 //! This script uses `tonic-build` to generate Rust code from `.proto` files
 //! for all Acton DX microservices.
+//!
+//! Version 1 protos live directly in `proto/`; later API versions live in a
+//! directory per version, e.g. `proto/v2/cedar.proto` declaring
+//! `package acton.dx.cedar.v2;`. Every proto found is compiled, so a new
+//! version only needs its proto file and a module in `lib.rs`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Root of the proto tree, also the import path
+const PROTO_ROOT: &str = "proto";
+
+/// `.proto` files in `dir`, sorted so the generated code is stable
+fn protos_in(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut protos = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "proto") {
+            protos.push(path);
+        }
+    }
+    protos.sort();
+    Ok(protos)
+}
+
+/// Whether `dir` holds the protos of an API version, like `v2`
+fn is_version_dir(dir: &Path) -> bool {
+    dir.is_dir()
+        && dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix('v'))
+            .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let proto_files = [
-        "proto/auth.proto",
-        "proto/data.proto",
-        "proto/cedar.proto",
-        "proto/cache.proto",
-        "proto/email.proto",
-        "proto/file.proto",
-    ];
-
-    tonic_build::configure()
+    let root = Path::new(PROTO_ROOT);
+    let mut proto_files = protos_in(root)?;
+    let mut version_dirs: Vec<PathBuf> = fs::read_dir(root)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    version_dirs.retain(|dir| is_version_dir(dir));
+    version_dirs.sort();
+    for dir in &version_dirs {
+        proto_files.extend(protos_in(dir)?);
+    }
+
+    let mut builder = tonic_build::configure()
         .build_server(true)
-        .build_client(true)
-        .compile_protos(&proto_files, &["proto/"])?;
+        .build_client(true);
+
+    // JSON uses the proto field and oneof names, and rejects unknown fields
+    // so a renamed field breaks fixtures instead of being silently dropped
+    if std::env::var_os("CARGO_FEATURE_SERDE").is_some() {
+        builder = builder
+            .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
+            .message_attribute(".", "#[serde(default, deny_unknown_fields)]")
+            .enum_attribute(".", "#[serde(rename_all = \"snake_case\")]");
+    }
+
+    // Imports resolve from the root, so a v2 proto can import "error.proto"
+    builder.compile_protos(&proto_files, &[root])?;
 
-    // Re-run build if any proto file changes
+    // Re-run build if any proto file changes, or a new one is added
+    println!("cargo:rerun-if-changed={PROTO_ROOT}");
     for proto in &proto_files {
-        println!("cargo:rerun-if-changed={proto}");
+        println!("cargo:rerun-if-changed={}", proto.display());
     }
 
     Ok(())
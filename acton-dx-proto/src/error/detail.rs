@@ -0,0 +1,117 @@
+//! Building and reading [`ErrorDetail`]s.
+
+use super::v1::{ErrorDetail, FieldViolation, HelpLink};
+use prost::Message;
+use std::time::Duration;
+use tonic::codegen::Bytes;
+use tonic::{Code, Status};
+
+impl ErrorDetail {
+    /// Error detail with a machine-readable code and a message.
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Add a field that failed validation.
+    pub fn with_field_violation(
+        mut self,
+        field: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.field_violations.push(FieldViolation {
+            field: field.into(),
+            description: description.into(),
+        });
+        self
+    }
+
+    /// Ask the caller to retry after `retry_after`.
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after_ms = Some(u64::try_from(retry_after.as_millis()).unwrap_or(u64::MAX));
+        self
+    }
+
+    /// Add a link to documentation about the error.
+    pub fn with_help_link(
+        mut self,
+        description: impl Into<String>,
+        url: impl Into<String>,
+    ) -> Self {
+        self.help_links.push(HelpLink {
+            description: description.into(),
+            url: url.into(),
+        });
+        self
+    }
+
+    /// How long the caller should wait before retrying, if at all.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after_ms.map(Duration::from_millis)
+    }
+
+    /// Status with `code`, this detail's message, and this detail attached.
+    pub fn into_status(self, code: Code) -> Status {
+        let message = self.message.clone();
+        Status::with_details(code, message, Bytes::from(self.encode_to_vec()))
+    }
+
+    /// Detail attached to `status`, if it carries one.
+    pub fn from_status(status: &Status) -> Option<Self> {
+        let details = status.details();
+        if details.is_empty() {
+            return None;
+        }
+        Self::decode(details).ok()
+    }
+}
+
+/// `INVALID_ARGUMENT` status for a single invalid request field.
+///
+/// The detail code is `INVALID_FIELD` and the message is `description`.
+pub fn invalid_field(field: impl Into<String>, description: impl Into<String>) -> Status {
+    let description = description.into();
+    ErrorDetail::new("INVALID_FIELD", description.clone())
+        .with_field_violation(field, description)
+        .into_status(Code::InvalidArgument)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detail_round_trips_through_status() {
+        let status = ErrorDetail::new("QUOTA_EXCEEDED", "Storage quota exceeded")
+            .with_retry_after(Duration::from_secs(30))
+            .with_help_link("Quotas", "https://acton.dev/docs/quotas")
+            .into_status(Code::ResourceExhausted);
+
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(status.message(), "Storage quota exceeded");
+
+        let detail = ErrorDetail::from_status(&status).expect("detail attached");
+        assert_eq!(detail.code, "QUOTA_EXCEEDED");
+        assert_eq!(detail.retry_after(), Some(Duration::from_secs(30)));
+        assert_eq!(detail.help_links[0].url, "https://acton.dev/docs/quotas");
+    }
+
+    #[test]
+    fn test_invalid_field() {
+        let status = invalid_field("password", "password cannot be empty");
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "password cannot be empty");
+
+        let detail = ErrorDetail::from_status(&status).expect("detail attached");
+        assert_eq!(detail.field_violations.len(), 1);
+        assert_eq!(detail.field_violations[0].field, "password");
+    }
+
+    #[test]
+    fn test_plain_status_has_no_detail() {
+        assert!(ErrorDetail::from_status(&Status::internal("boom")).is_none());
+    }
+}
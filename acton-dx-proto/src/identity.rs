@@ -0,0 +1,328 @@
+//! Verification of identity tokens attached by the web tier.
+//!
+//! The web tier can exchange the user's session for a short-lived access
+//! token and send it as `authorization: Bearer <token>` metadata on every
+//! call. Services check it with an [`IdentityVerifier`] built from their
+//! [`IdentityConfig`]; tokens are verified by the auth service and cached
+//! until they expire, so only the first call with a token costs a round trip.
+//!
+//! Without an auth service endpoint verification is off and every caller is
+//! trusted. Trusted callers act for no tenant unless they name one with
+//! [`TENANT_METADATA_KEY`] metadata; users act for the tenant in their token.
+
+use crate::auth::v1::{token_service_client::TokenServiceClient, VerifyAccessTokenRequest};
+use crate::error::v1::ErrorDetail;
+use crate::time::unix_now;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tonic::metadata::MetadataMap;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Status};
+use tracing::{info, warn};
+
+/// gRPC metadata key carrying the identity token.
+pub const IDENTITY_METADATA_KEY: &str = "authorization";
+
+/// gRPC metadata key a trusted caller names the tenant it acts for with.
+pub const TENANT_METADATA_KEY: &str = "x-tenant-id";
+
+/// How long clients should wait before retrying when the auth service is
+/// unreachable.
+const AUTH_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Verification of identity tokens the web tier attaches to calls.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdentityConfig {
+    /// Auth service that verifies tokens; unset trusts every caller.
+    #[serde(default)]
+    pub auth_endpoint: Option<String>,
+    /// Audience identity tokens must be issued for.
+    #[serde(default = "default_audience")]
+    pub audience: String,
+    /// Reject calls that carry no identity token.
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl Default for IdentityConfig {
+    fn default() -> Self {
+        Self {
+            auth_endpoint: None,
+            audience: default_audience(),
+            required: false,
+        }
+    }
+}
+
+fn default_audience() -> String {
+    "internal".to_string()
+}
+
+/// The user a call was made on behalf of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// Verified user ID.
+    pub user_id: i64,
+    /// Scopes granted to the token.
+    pub scopes: Vec<String>,
+    /// Tenant the token was issued for, if the user acts for one.
+    pub tenant_id: Option<String>,
+}
+
+impl Identity {
+    /// Tenant the user acts for: the one in their token, or otherwise the
+    /// user alone, keyed by user ID.
+    #[must_use]
+    pub fn tenant(&self) -> String {
+        self.tenant_id
+            .clone()
+            .unwrap_or_else(|| self.user_id.to_string())
+    }
+}
+
+/// A verified identity and the Unix time its token expires.
+type Verified = (Identity, i64);
+
+/// Verifies identity tokens against the auth service.
+#[derive(Debug, Clone)]
+pub struct IdentityVerifier {
+    tokens: Option<TokenServiceClient<Channel>>,
+    audience: String,
+    required: bool,
+    verified: Arc<Mutex<HashMap<String, Verified>>>,
+}
+
+impl IdentityVerifier {
+    /// A verifier that trusts every caller.
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self {
+            tokens: None,
+            audience: String::new(),
+            required: false,
+            verified: Arc::default(),
+        }
+    }
+
+    /// Verify tokens issued for `audience` through the auth service at
+    /// `endpoint`, connecting on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the endpoint URI is invalid.
+    pub fn connect_lazy(
+        endpoint: String,
+        audience: impl Into<String>,
+    ) -> Result<Self, tonic::transport::Error> {
+        let channel = Endpoint::try_from(endpoint)?.connect_lazy();
+        Ok(Self {
+            tokens: Some(TokenServiceClient::new(channel)),
+            audience: audience.into(),
+            required: false,
+            verified: Arc::default(),
+        })
+    }
+
+    /// Verifier for `config`: disabled without an auth service endpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the endpoint URI is invalid.
+    pub fn from_config(config: &IdentityConfig) -> Result<Self, tonic::transport::Error> {
+        let Some(endpoint) = &config.auth_endpoint else {
+            return Ok(Self::disabled());
+        };
+        info!(
+            required = config.required,
+            "Identity token verification enabled"
+        );
+        Ok(
+            Self::connect_lazy(endpoint.clone(), config.audience.clone())?
+                .with_required(config.required),
+        )
+    }
+
+    /// Reject calls without an identity token (default: false).
+    #[must_use]
+    pub const fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Whether tokens are verified at all.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.tokens.is_some()
+    }
+
+    /// Identity of the caller, if the call carries a token.
+    ///
+    /// # Errors
+    ///
+    /// Returns `unauthenticated` for an invalid token, or for a missing one
+    /// when tokens are required, and `unavailable` if the auth service
+    /// cannot be reached.
+    pub async fn authenticate(&self, metadata: &MetadataMap) -> Result<Option<Identity>, Status> {
+        let Some(tokens) = &self.tokens else {
+            return Ok(None);
+        };
+        let Some(value) = metadata.get(IDENTITY_METADATA_KEY) else {
+            if self.required {
+                return Err(Status::unauthenticated("Identity token required"));
+            }
+            return Ok(None);
+        };
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("Malformed identity token"))?;
+
+        let now = unix_now();
+        if let Some(identity) = self.cached(token, now) {
+            return Ok(Some(identity));
+        }
+
+        let response = tokens
+            .clone()
+            .verify_access_token(VerifyAccessTokenRequest {
+                access_token: token.to_string(),
+                audience: Some(self.audience.clone()),
+            })
+            .await
+            .map_err(|e| {
+                warn!(error = %e, "Identity token verification failed");
+                ErrorDetail::new("IDENTITY_UNAVAILABLE", "Identity verification unavailable")
+                    .with_retry_after(AUTH_RETRY_AFTER)
+                    .into_status(Code::Unavailable)
+            })?
+            .into_inner();
+        let claims = response
+            .claims
+            .filter(|_| response.valid)
+            .ok_or_else(|| Status::unauthenticated("Invalid identity token"))?;
+
+        let identity = Identity {
+            user_id: claims.user_id,
+            scopes: claims.scopes,
+            tenant_id: claims.tenant_id,
+        };
+        self.remember(token, identity.clone(), claims.expires_at, now);
+        Ok(Some(identity))
+    }
+
+    fn cached(&self, token: &str, now: i64) -> Option<Identity> {
+        self.verified
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(token)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(identity, _)| identity.clone())
+    }
+
+    fn remember(&self, token: &str, identity: Identity, expires_at: i64, now: i64) {
+        let mut verified = self.verified.lock().unwrap_or_else(PoisonError::into_inner);
+        verified.retain(|_, (_, expires_at)| *expires_at > now);
+        verified.insert(token.to_string(), (identity, expires_at));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::metadata::MetadataValue;
+
+    fn verifier() -> IdentityVerifier {
+        IdentityVerifier::connect_lazy("http://127.0.0.1:1".to_string(), "internal").unwrap()
+    }
+
+    fn bearer(token: &'static str) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(IDENTITY_METADATA_KEY, MetadataValue::from_static(token));
+        metadata
+    }
+
+    #[tokio::test]
+    async fn test_default_config() {
+        let config = IdentityConfig::default();
+        assert!(config.auth_endpoint.is_none());
+        assert_eq!(config.audience, "internal");
+        assert!(!config.required);
+        assert!(!IdentityVerifier::from_config(&config).unwrap().is_enabled());
+
+        let config = IdentityConfig {
+            auth_endpoint: Some("http://127.0.0.1:1".to_string()),
+            ..IdentityConfig::default()
+        };
+        assert!(IdentityVerifier::from_config(&config).unwrap().is_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_trusts_callers() {
+        let verifier = IdentityVerifier::disabled().with_required(true);
+        assert_eq!(
+            verifier.authenticate(&MetadataMap::new()).await.unwrap(),
+            None
+        );
+        assert_eq!(verifier.authenticate(&bearer("junk")).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_missing_and_malformed_tokens() {
+        let anonymous = verifier().authenticate(&MetadataMap::new()).await.unwrap();
+        assert_eq!(anonymous, None);
+
+        let required = verifier().with_required(true);
+        let status = required
+            .authenticate(&MetadataMap::new())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let status = verifier()
+            .authenticate(&bearer("Basic abc"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_tenant_defaults_to_the_user() {
+        let mut identity = Identity {
+            user_id: 42,
+            scopes: Vec::new(),
+            tenant_id: Some("acme".to_string()),
+        };
+        assert_eq!(identity.tenant(), "acme");
+        identity.tenant_id = None;
+        assert_eq!(identity.tenant(), "42");
+    }
+
+    #[tokio::test]
+    async fn test_verified_tokens_are_cached_until_expiry() {
+        let verifier = verifier();
+        let identity = Identity {
+            user_id: 42,
+            scopes: vec!["read".to_string()],
+            tenant_id: Some("acme".to_string()),
+        };
+        let now = unix_now();
+        verifier.remember("live", identity.clone(), now + 60, now);
+        verifier.remember("stale", identity.clone(), now - 1, now - 120);
+
+        assert_eq!(
+            verifier.authenticate(&bearer("Bearer live")).await.unwrap(),
+            Some(identity)
+        );
+        // Not cached, so it goes to the (unreachable) auth service
+        let status = verifier
+            .authenticate(&bearer("Bearer stale"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        let detail = ErrorDetail::from_status(&status).expect("detail attached");
+        assert_eq!(detail.retry_after(), Some(AUTH_RETRY_AFTER));
+    }
+}
@@ -12,6 +12,19 @@
 //! - [`email`] - Email sending and validation
 //! - [`file`] - File storage, uploads, and serving
 //!
+//! Failed calls carry an [`error::v1::ErrorDetail`] in their status details;
+//! see [`error`] for building and reading them. Services verify the caller's
+//! identity token with [`identity::IdentityVerifier`] and stamp records with
+//! [`time::unix_now`].
+//!
+//! # Versions
+//!
+//! Each service module holds one module per API version, generated from the
+//! `acton.dx.<service>.<version>` proto package. Version 1 protos live in
+//! `proto/`, later versions in `proto/v2/` and so on, and a new version sits
+//! next to the old one (`cedar::v2` beside `cedar::v1`) instead of replacing
+//! it. [`versioning`] serves both versions from one server.
+//!
 //! # Generated Code
 //!
 //! All types in this crate are auto-generated from Protocol Buffer definitions
@@ -92,3 +105,25 @@ pub mod file {
         tonic::include_proto!("acton.dx.file.v1");
     }
 }
+
+/// Error details shared by all services.
+///
+/// Services attach an [`ErrorDetail`](error::v1::ErrorDetail) to failed
+/// calls with [`ErrorDetail::into_status`](error::v1::ErrorDetail::into_status)
+/// and clients read it back with
+/// [`ErrorDetail::from_status`](error::v1::ErrorDetail::from_status).
+pub mod error {
+    /// Version 1 of the error details.
+    #[allow(missing_docs)]
+    pub mod v1 {
+        tonic::include_proto!("acton.dx.error.v1");
+    }
+
+    mod detail;
+
+    pub use detail::invalid_field;
+}
+
+pub mod identity;
+pub mod time;
+pub mod versioning;
@@ -0,0 +1,39 @@
+//! Wall-clock time as Unix timestamps.
+//!
+//! Services stamp records and compare expiry times in signed Unix time, the
+//! representation the protos use. A clock set before the epoch reads as 0.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Current time in Unix seconds.
+#[must_use]
+pub fn unix_now() -> i64 {
+    since_epoch(|elapsed| elapsed.as_secs().into())
+}
+
+/// Current time in Unix milliseconds.
+#[must_use]
+pub fn unix_millis() -> i64 {
+    since_epoch(|elapsed| elapsed.as_millis())
+}
+
+fn since_epoch(units: impl FnOnce(Duration) -> u128) -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| {
+            i64::try_from(units(elapsed)).unwrap_or(i64::MAX)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unix_now_and_millis_agree() {
+        let seconds = unix_now();
+        let millis = unix_millis();
+        assert!(seconds > 1_600_000_000);
+        assert!((millis / 1000 - seconds).abs() <= 1);
+    }
+}
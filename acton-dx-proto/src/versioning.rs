@@ -0,0 +1,314 @@
+//! Serving several versions of a service API side by side.
+//!
+//! Breaking changes to a service go in a new proto package, such as
+//! `acton.dx.cedar.v2` in `proto/v2/cedar.proto`, instead of changing the
+//! `v1` messages in place. gRPC paths include the package, so both versions
+//! can be mounted on one server with [`VersionedRoutes`] while clients move
+//! over:
+//!
+//! ```rust,ignore
+//! use acton_dx_proto::cedar::{v1, v2};
+//! use acton_dx_proto::versioning::VersionedRoutes;
+//!
+//! let core = Arc::new(CedarCore::new(policies));
+//! let routes = VersionedRoutes::new()
+//!     .add_service(v1::cedar_service_server::CedarServiceServer::new(CedarV1::new(core.clone())))
+//!     .add_service(v2::cedar_service_server::CedarServiceServer::new(CedarV2::new(core)));
+//!
+//! Server::builder().add_routes(routes.into_routes()).serve(addr).await?;
+//! ```
+//!
+//! Business logic stays in one place: each version's implementation is a
+//! thin adapter over shared code. RPCs whose meaning did not change can
+//! delegate to the other version's implementation with [`adapt`], converting
+//! the messages with `From` impls between the versions.
+
+use std::convert::Infallible;
+use std::future::Future;
+
+use tonic::body::Body;
+use tonic::codegen::{http, Service};
+use tonic::server::NamedService;
+use tonic::service::Routes;
+use tonic::{Request, Response, Status};
+
+/// A gRPC service mounted by [`VersionedRoutes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServedService {
+    /// Fully qualified service name, e.g. `acton.dx.cedar.v2.CedarService`.
+    pub name: &'static str,
+    /// Proto package without the version, e.g. `acton.dx.cedar`.
+    pub package: &'static str,
+    /// API version, e.g. `v2`, or empty for an unversioned package.
+    pub version: &'static str,
+}
+
+impl ServedService {
+    /// Split a fully qualified service name into its package and version.
+    pub fn parse(name: &'static str) -> Self {
+        let package = name.rsplit_once('.').map_or("", |(package, _)| package);
+        let (base, version) = package
+            .rsplit_once('.')
+            .map_or((package, ""), |split| split);
+        if is_version(version) {
+            Self {
+                name,
+                package: base,
+                version,
+            }
+        } else {
+            Self {
+                name,
+                package,
+                version: "",
+            }
+        }
+    }
+}
+
+/// Whether a package segment names an API version, like `v2`.
+fn is_version(segment: &str) -> bool {
+    segment
+        .strip_prefix('v')
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// gRPC services of one or more API versions, mounted side by side.
+#[derive(Debug, Clone, Default)]
+pub struct VersionedRoutes {
+    routes: Routes,
+    served: Vec<ServedService>,
+}
+
+impl VersionedRoutes {
+    /// No services yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mount a generated service server.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a service with the same fully qualified name, including
+    /// its version, is already mounted.
+    pub fn add_service<S>(mut self, service: S) -> Self
+    where
+        S: Service<http::Request<Body>, Response = http::Response<Body>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        assert!(
+            self.served.iter().all(|served| served.name != S::NAME),
+            "{} is already served",
+            S::NAME
+        );
+        self.served.push(ServedService::parse(S::NAME));
+        self.routes = self.routes.add_service(service);
+        self
+    }
+
+    /// Mounted services, in the order they were added.
+    pub fn served(&self) -> &[ServedService] {
+        &self.served
+    }
+
+    /// Versions mounted for a package such as `acton.dx.cedar`.
+    pub fn versions(&self, package: &str) -> Vec<&'static str> {
+        let mut versions: Vec<&'static str> = self
+            .served
+            .iter()
+            .filter(|served| served.package == package)
+            .map(|served| served.version)
+            .collect();
+        versions.sort_by_key(|version| (version.len(), *version));
+        versions.dedup();
+        versions
+    }
+
+    /// Routes for `Server::builder().add_routes(..)`.
+    pub fn into_routes(self) -> Routes {
+        self.routes
+    }
+}
+
+/// Handle a call with an implementation written against another version.
+///
+/// The request message is converted with `From`, keeping its metadata and
+/// extensions, and the response message is converted back.
+///
+/// ```rust,ignore
+/// async fn is_authorized(
+///     &self,
+///     request: Request<v2::AuthzRequest>,
+/// ) -> Result<Response<v2::AuthzResponse>, Status> {
+///     adapt(request, |request| self.v1.is_authorized(request)).await
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns the status of the wrapped call.
+pub async fn adapt<Req, InnerReq, InnerRes, Res, F, Fut>(
+    request: Request<Req>,
+    call: F,
+) -> Result<Response<Res>, Status>
+where
+    Req: Into<InnerReq>,
+    InnerRes: Into<Res>,
+    F: FnOnce(Request<InnerReq>) -> Fut,
+    Fut: Future<Output = Result<Response<InnerRes>, Status>>,
+{
+    let response = call(request.map(Into::into)).await?;
+    Ok(response.map(Into::into))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::{Context, Poll};
+
+    /// A service answering every call with its version number
+    #[derive(Clone)]
+    struct Named<const VERSION: u8>;
+
+    impl NamedService for Named<1> {
+        const NAME: &'static str = "acton.dx.test.v1.TestService";
+    }
+
+    impl NamedService for Named<2> {
+        const NAME: &'static str = "acton.dx.test.v2.TestService";
+    }
+
+    impl<const VERSION: u8> Service<http::Request<Body>> for Named<VERSION> {
+        type Response = http::Response<Body>;
+        type Error = Infallible;
+        type Future = std::future::Ready<Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: http::Request<Body>) -> Self::Future {
+            let response = http::Response::builder()
+                .header("x-version", VERSION.to_string())
+                .body(Body::empty())
+                .unwrap();
+            std::future::ready(Ok(response))
+        }
+    }
+
+    async fn version_of(routes: &Routes, path: &str) -> Option<String> {
+        let request = http::Request::builder()
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        let response = routes.clone().call(request).await.unwrap();
+        response
+            .headers()
+            .get("x-version")
+            .map(|version| version.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_versions_are_served_side_by_side() {
+        let routes = VersionedRoutes::new()
+            .add_service(Named::<1>)
+            .add_service(Named::<2>);
+        assert_eq!(routes.versions("acton.dx.test"), ["v1", "v2"]);
+
+        let routes = routes.into_routes();
+        assert_eq!(
+            version_of(&routes, "/acton.dx.test.v1.TestService/Call").await,
+            Some("1".to_string())
+        );
+        assert_eq!(
+            version_of(&routes, "/acton.dx.test.v2.TestService/Call").await,
+            Some("2".to_string())
+        );
+        assert_eq!(
+            version_of(&routes, "/acton.dx.test.v3.TestService/Call").await,
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "acton.dx.test.v1.TestService is already served")]
+    fn test_same_version_twice_panics() {
+        let _ = VersionedRoutes::new()
+            .add_service(Named::<1>)
+            .add_service(Named::<1>);
+    }
+
+    #[test]
+    fn test_parse_service_name() {
+        assert_eq!(
+            ServedService::parse("acton.dx.cedar.v10.CedarService"),
+            ServedService {
+                name: "acton.dx.cedar.v10.CedarService",
+                package: "acton.dx.cedar",
+                version: "v10",
+            }
+        );
+        let unversioned = ServedService::parse("grpc.health.Health");
+        assert_eq!(unversioned.package, "grpc.health");
+        assert_eq!(unversioned.version, "");
+    }
+
+    struct V1Request(u32);
+    struct V2Request {
+        id: u64,
+    }
+    struct V1Response(String);
+    struct V2Response {
+        name: String,
+    }
+
+    impl From<V2Request> for V1Request {
+        fn from(request: V2Request) -> Self {
+            Self(u32::try_from(request.id).unwrap())
+        }
+    }
+
+    impl From<V1Response> for V2Response {
+        fn from(response: V1Response) -> Self {
+            Self { name: response.0 }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_adapt_converts_messages_and_keeps_metadata() {
+        let mut request = Request::new(V2Request { id: 7 });
+        request
+            .metadata_mut()
+            .insert("x-request-id", "abc".parse().unwrap());
+
+        let response: Response<V2Response> =
+            adapt(request, |request: Request<V1Request>| async move {
+                assert_eq!(request.metadata().get("x-request-id").unwrap(), "abc");
+                Ok(Response::new(V1Response(format!(
+                    "user-{}",
+                    request.get_ref().0
+                ))))
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.get_ref().name, "user-7");
+    }
+
+    #[tokio::test]
+    async fn test_adapt_passes_errors_through() {
+        let result: Result<Response<V2Response>, Status> = adapt(
+            Request::new(V2Request { id: 1 }),
+            |_: Request<V1Request>| async {
+                Err::<Response<V1Response>, _>(Status::not_found("missing"))
+            },
+        )
+        .await;
+        assert_eq!(result.err().unwrap().code(), tonic::Code::NotFound);
+    }
+}
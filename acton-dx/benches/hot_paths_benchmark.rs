@@ -0,0 +1,278 @@
+//! Performance benchmarks for request hot paths
+//!
+//! These benchmarks measure the work done on most requests:
+//! - Session load/save round trip through `SessionManagerAgent`
+//! - CSRF token issue and validation through `CsrfManagerAgent`
+//! - Template rendering with and without the `TemplateRegistry` cache
+//! - Job queue enqueue/dequeue
+//! - IPC frame encode/decode (requires the `microservices` feature)
+//!
+//! Run with: `cargo bench --features bench-internals --bench hot_paths_benchmark`
+//!
+//! Compare against a saved baseline between releases:
+//! `cargo bench --features bench-internals --bench hot_paths_benchmark -- --save-baseline v1.0` and
+//! `cargo bench --features bench-internals --bench hot_paths_benchmark -- --baseline v1.0`
+#![allow(missing_docs)]
+
+use acton_dx::htmx::agents::{
+    CsrfManagerAgent, GetOrCreateToken, LoadSession, SaveSession, SessionManagerAgent,
+    ValidateToken,
+};
+use acton_dx::htmx::auth::{SessionData, SessionId};
+use acton_dx::htmx::jobs::agent::queue::{JobQueue, QueuedJob};
+use acton_dx::htmx::jobs::JobId;
+use acton_dx::htmx::template::TemplateRegistry;
+use acton_reactive::prelude::*;
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::time::Duration;
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+/// Session data of a signed-in user with a few stored values
+fn signed_in_session() -> SessionData {
+    let mut data = SessionData::new();
+    data.user_id = Some(42);
+    data.user_name = Some("bench@example.com".to_string());
+    data.set("cart".to_string(), vec![1, 2, 3]).unwrap();
+    data.set("theme".to_string(), "dark").unwrap();
+    data
+}
+
+/// Benchmark saving a session and loading it back
+fn bench_session_round_trip(c: &mut Criterion) {
+    let rt = runtime();
+    let handle = rt.block_on(async {
+        let mut runtime = ActonApp::launch_async().await;
+        SessionManagerAgent::spawn(&mut runtime).await.unwrap()
+    });
+
+    let session_id = SessionId::generate();
+    let data = signed_in_session();
+
+    c.bench_function("session/save_load_round_trip", |b| {
+        b.to_async(&rt).iter(|| async {
+            let (save, saved) = SaveSession::with_confirmation(session_id.clone(), data.clone());
+            handle.send(save).await;
+            saved.await.expect("Should confirm save");
+
+            let (load, loaded) = LoadSession::with_response(session_id.clone());
+            handle.send(load).await;
+            loaded.await.expect("Should get session")
+        });
+    });
+
+    c.bench_function("session/load_missing", |b| {
+        b.to_async(&rt).iter(|| async {
+            let (load, loaded) = LoadSession::with_response(SessionId::generate());
+            handle.send(load).await;
+            loaded.await.expect("Should get response")
+        });
+    });
+}
+
+/// Benchmark issuing a CSRF token and validating it
+///
+/// Validation rotates the token, so every iteration fetches the current one
+/// first, as a form render followed by its submission does.
+fn bench_csrf_validation(c: &mut Criterion) {
+    let rt = runtime();
+    let handle = rt.block_on(async {
+        let mut runtime = ActonApp::launch_async().await;
+        CsrfManagerAgent::spawn(&mut runtime).await.unwrap()
+    });
+
+    let session_id = SessionId::generate();
+
+    c.bench_function("csrf/issue_and_validate", |b| {
+        b.to_async(&rt).iter(|| async {
+            let (issue, issued) = GetOrCreateToken::new(session_id.clone());
+            handle.send(issue).await;
+            let token = issued.await.expect("Should get token");
+
+            let (validate, valid) = ValidateToken::new(session_id.clone(), token);
+            handle.send(validate).await;
+            assert!(valid.await.expect("Should get result"));
+        });
+    });
+
+    c.bench_function("csrf/reject_invalid", |b| {
+        b.to_async(&rt).iter(|| async {
+            let (validate, valid) = ValidateToken::new(
+                session_id.clone(),
+                acton_dx::htmx::agents::CsrfToken::generate(),
+            );
+            handle.send(validate).await;
+            valid.await.expect("Should get result")
+        });
+    });
+}
+
+const POST_LIST_TEMPLATE: &str = r#"<ul id="posts" hx-target="this" hx-swap="outerHTML">
+{% for post in posts %}
+  <li id="post-{{ post.id }}">
+    <a href="/posts/{{ post.id }}" hx-get="/posts/{{ post.id }}">{{ post.title }}</a>
+    {% if post.published %}<span class="badge">Published</span>{% endif %}
+  </li>
+{% endfor %}
+</ul>"#;
+
+/// Benchmark rendering a list fragment, with the registry cache on and off
+fn bench_template_render(c: &mut Criterion) {
+    let mut env = minijinja::Environment::new();
+    env.add_template("posts/list.html", POST_LIST_TEMPLATE).unwrap();
+    let posts: Vec<_> = (0..25)
+        .map(|id| {
+            minijinja::context! {
+                id => id,
+                title => format!("Post number {id}"),
+                published => id % 2 == 0,
+            }
+        })
+        .collect();
+    let ctx = minijinja::context! { posts => posts };
+
+    let render = || {
+        env.get_template("posts/list.html")
+            .unwrap()
+            .render(&ctx)
+            .unwrap()
+    };
+
+    let mut group = c.benchmark_group("template/render");
+    for cache_enabled in [false, true] {
+        let registry = TemplateRegistry::with_caching(cache_enabled);
+        let id = if cache_enabled { "cached" } else { "uncached" };
+        group.bench_function(BenchmarkId::from_parameter(id), |b| {
+            b.iter(|| {
+                registry.get("posts/list.html").unwrap_or_else(|| {
+                    let html = render();
+                    registry.insert("posts/list.html".to_string(), html.clone());
+                    html
+                })
+            });
+        });
+    }
+    group.finish();
+}
+
+fn queued_job(priority: i32, tenant: Option<&str>) -> QueuedJob {
+    QueuedJob {
+        id: JobId::new(),
+        job_type: "SendWelcomeEmail".to_string(),
+        payload: br#"{"user_id":42,"template":"welcome"}"#.to_vec(),
+        priority,
+        max_retries: 3,
+        timeout: Duration::from_secs(30),
+        enqueued_at: Utc::now(),
+        attempt: 0,
+        tenant_id: tenant.map(ToString::to_string),
+    }
+}
+
+/// Benchmark job enqueue/dequeue, with and without tenant lanes
+fn bench_job_queue(c: &mut Criterion) {
+    let mut group = c.benchmark_group("jobs/enqueue_dequeue");
+
+    let tenants: Vec<String> = (0..10).map(|t| format!("tenant-{t}")).collect();
+
+    for batch in [1_usize, 100, 1000] {
+        group.throughput(Throughput::Elements(u64::try_from(batch).unwrap()));
+        group.bench_with_input(BenchmarkId::new("single_lane", batch), &batch, |b, &batch| {
+            b.iter(|| {
+                let mut queue = JobQueue::new(10_000);
+                for priority in (0..10).cycle().take(batch) {
+                    queue.enqueue(queued_job(priority, None)).unwrap();
+                }
+                while queue.dequeue(|_| true).is_some() {}
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("ten_tenants", batch), &batch, |b, &batch| {
+            b.iter(|| {
+                let mut queue = JobQueue::new(10_000);
+                for (priority, tenant) in (0..10).cycle().zip(tenants.iter().cycle()).take(batch) {
+                    queue.enqueue(queued_job(priority, Some(tenant))).unwrap();
+                }
+                while queue.dequeue(|_| true).is_some() {}
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// Benchmark encoding request frames and decoding response frames
+#[cfg(feature = "microservices")]
+fn bench_ipc_frames(c: &mut Criterion) {
+    use acton_dx::htmx::clients::ipc::bench::{decode_response, encode_request};
+    use acton_dx::htmx::clients::ipc::{IpcEnvelope, IpcResponse};
+
+    let envelope = IpcEnvelope::new_request(
+        "auth_service",
+        "ValidateSession",
+        serde_json::json!({ "session_id": "550e8400-e29b-41d4-a716-446655440000" }),
+    );
+    let response = IpcResponse {
+        correlation_id: envelope.correlation_id.clone(),
+        success: true,
+        error: None,
+        error_code: None,
+        payload: Some(serde_json::json!({
+            "session_id": "550e8400-e29b-41d4-a716-446655440000",
+            "user_id": 42,
+            "data": { "cart": [1, 2, 3], "theme": "dark" },
+            "expires_at": 1_760_000_000,
+        })),
+    };
+    // Protocol version, response message type, JSON format, then the payload
+    let mut response_frame = vec![0x02, 0x02, 0x01];
+    response_frame.extend(serde_json::to_vec(&response).unwrap());
+
+    c.bench_function("ipc/encode_request", |b| {
+        b.iter(|| encode_request(&envelope).unwrap());
+    });
+    c.bench_function("ipc/decode_response", |b| {
+        b.iter(|| decode_response(&response_frame).unwrap());
+    });
+}
+
+criterion_group!(
+    name = agent_benches;
+    config = Criterion::default()
+        .sample_size(100)
+        .measurement_time(Duration::from_secs(5));
+    targets =
+        bench_session_round_trip,
+        bench_csrf_validation,
+);
+
+criterion_group!(
+    name = render_benches;
+    config = Criterion::default().sample_size(200);
+    targets = bench_template_render,
+);
+
+criterion_group!(
+    name = queue_benches;
+    config = Criterion::default().sample_size(100);
+    targets = bench_job_queue,
+);
+
+#[cfg(feature = "microservices")]
+criterion_group!(
+    name = ipc_benches;
+    config = Criterion::default().sample_size(200);
+    targets = bench_ipc_frames,
+);
+
+#[cfg(feature = "microservices")]
+criterion_main!(agent_benches, render_benches, queue_benches, ipc_benches);
+
+#[cfg(not(feature = "microservices"))]
+criterion_main!(agent_benches, render_benches, queue_benches);
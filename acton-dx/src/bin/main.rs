@@ -7,16 +7,27 @@
 //! acton-dx htmx new my-app
 //! acton-dx htmx dev
 //! acton-dx htmx scaffold crud Post title:string content:text
+//!
+//! # Machine-readable results
+//! acton-dx --output json htmx services status
+//!
+//! # Shell completions
+//! source <(acton-dx completions bash)
 //! ```
 
+use acton_dx::cli::{OutputFormat, Shell};
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(name = "acton-dx")]
 #[command(version)]
 #[command(about = "Acton DX - Developer experience focused web framework for Rust", long_about = None)]
 struct Cli {
+    /// Output format (json is supported by services status, jobs list and db migrate)
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    output: OutputFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -28,12 +39,27 @@ enum Commands {
         #[command(subcommand)]
         command: acton_dx::cli::HtmxCommand,
     },
+    /// Print a shell completion script
+    Completions {
+        /// Shell to generate the script for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Htmx { command } => acton_dx::cli::htmx::run(command),
+        Commands::Htmx { command } => acton_dx::cli::htmx::run(command, cli.output),
+        Commands::Completions { shell } => {
+            acton_dx::cli::completions::generate(
+                shell,
+                &mut Cli::command(),
+                "acton-dx",
+                &mut std::io::stdout().lock(),
+            )?;
+            Ok(())
+        }
     }
 }
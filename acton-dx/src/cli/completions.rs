@@ -0,0 +1,84 @@
+//! Shell completion scripts
+//!
+//! Generated by `clap_complete` from the clap command tree, so new commands
+//! and flags complete without changes here:
+//!
+//! ```bash
+//! # Bash (~/.bashrc)
+//! source <(acton-dx completions bash)
+//!
+//! # Zsh (~/.zshrc, after compinit)
+//! source <(acton-dx completions zsh)
+//!
+//! # Fish
+//! acton-dx completions fish > ~/.config/fish/completions/acton-dx.fish
+//!
+//! # PowerShell ($PROFILE)
+//! acton-dx completions powershell | Out-String | Invoke-Expression
+//! ```
+
+use clap::Command;
+use std::io::{self, Write};
+
+pub use clap_complete::Shell;
+
+/// Write the completion script for `command`, invoked as `bin_name`
+///
+/// # Errors
+///
+/// Returns an error if the script cannot be written.
+pub fn generate(
+    shell: Shell,
+    command: &mut Command,
+    bin_name: &str,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    // clap_complete panics on write errors, so render to a buffer first
+    let mut script = Vec::new();
+    clap_complete::generate(shell, command, bin_name, &mut script);
+    out.write_all(&script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Parser, Subcommand};
+
+    #[derive(Parser)]
+    #[command(name = "tool")]
+    struct Cli {
+        #[command(subcommand)]
+        command: Commands,
+    }
+
+    #[derive(Subcommand)]
+    enum Commands {
+        /// Show service logs
+        Logs {
+            /// Follow output
+            #[arg(long, short)]
+            follow: bool,
+        },
+    }
+
+    fn script(shell: Shell) -> String {
+        let mut out = Vec::new();
+        generate(
+            shell,
+            &mut <Cli as clap::CommandFactory>::command(),
+            "tool",
+            &mut out,
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_scripts_cover_subcommands_and_flags() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let script = script(shell);
+            assert!(script.contains("logs"), "{shell}");
+            assert!(script.contains("follow"), "{shell}");
+        }
+    }
+}
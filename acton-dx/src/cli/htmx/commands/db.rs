@@ -2,8 +2,49 @@
 
 use anyhow::{Context, Result};
 use console::style;
+use serde::Serialize;
 use std::process::{Command, Stdio};
 
+use super::super::manifest::ProjectManifest;
+use crate::cli::output::{self, OutputFormat};
+
+/// Result of `db migrate --output json`
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+struct MigrateReport {
+    /// Migrations applied by this run, oldest first
+    applied: Vec<AppliedMigration>,
+}
+
+/// A migration reported as applied by `sqlx migrate run`
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct AppliedMigration {
+    version: i64,
+    description: String,
+}
+
+impl MigrateReport {
+    /// Parse the `Applied <version>/<kind> <description> (<elapsed>)` lines
+    /// printed by `sqlx migrate run`
+    fn parse(stdout: &str) -> Self {
+        let applied = stdout
+            .lines()
+            .filter_map(|line| {
+                let rest = line.trim().strip_prefix("Applied ")?;
+                let (version, rest) = rest.split_once('/')?;
+                let (_kind, rest) = rest.split_once(' ')?;
+                let description = rest
+                    .rsplit_once(" (")
+                    .map_or(rest, |(description, _elapsed)| description);
+                Some(AppliedMigration {
+                    version: version.parse().ok()?,
+                    description: description.to_string(),
+                })
+            })
+            .collect();
+        Self { applied }
+    }
+}
+
 /// Database command variants
 pub enum DbCommand {
     /// Run pending migrations
@@ -15,6 +56,12 @@ pub enum DbCommand {
         /// Name of the migration to create
         name: String,
     },
+    /// Print or diff the live schema
+    #[cfg(feature = "microservices")]
+    Schema(super::SchemaCommand),
+    /// Copy schema and data from SQLite to Postgres
+    #[cfg(feature = "postgres")]
+    Transfer(super::TransferCommand),
 }
 
 impl DbCommand {
@@ -25,9 +72,28 @@ impl DbCommand {
     /// Returns an error if:
     /// - `sqlx-cli` is not installed
     /// - Database operations fail
-    pub fn execute(&self) -> Result<()> {
+    ///
+    /// The migrations directory and database URL come from `manifest`.
+    pub fn execute(&self, output: OutputFormat, manifest: &ProjectManifest) -> Result<()> {
+        // Schema inspection goes through data-service, not sqlx-cli
+        #[cfg(feature = "microservices")]
+        if let Self::Schema(command) = self {
+            return command.execute();
+        }
+        // Transfers connect directly and need no sqlx-cli
+        #[cfg(feature = "postgres")]
+        if let Self::Transfer(command) = self {
+            return command.execute();
+        }
+
         // Check if sqlx-cli is installed
         if !Self::is_sqlx_cli_installed() {
+            if output.is_json() {
+                anyhow::bail!(
+                    "sqlx-cli is required for database commands \
+                     (cargo install sqlx-cli --no-default-features --features postgres)"
+                );
+            }
             println!(
                 "{} is not installed.",
                 style("sqlx-cli").yellow().bold()
@@ -43,15 +109,21 @@ impl DbCommand {
             anyhow::bail!("sqlx-cli is required for database commands");
         }
 
+        let sqlx = Sqlx { manifest };
         match self {
-            Self::Migrate => Self::migrate(),
-            Self::Reset => Self::reset(),
-            Self::Create { name } => Self::create(name),
+            Self::Migrate if output.is_json() => Self::migrate_json(&sqlx),
+            Self::Migrate => Self::migrate(&sqlx),
+            Self::Reset => Self::reset(&sqlx),
+            Self::Create { name } => Self::create(&sqlx, name),
+            #[cfg(feature = "microservices")]
+            Self::Schema(command) => command.execute(),
+            #[cfg(feature = "postgres")]
+            Self::Transfer(command) => command.execute(),
         }
     }
 
     /// Run pending migrations
-    fn migrate() -> Result<()> {
+    fn migrate(sqlx: &Sqlx<'_>) -> Result<()> {
         println!(
             "{} {}",
             style("Running").green().bold(),
@@ -59,8 +131,8 @@ impl DbCommand {
         );
         println!();
 
-        let status = Command::new("sqlx")
-            .args(["migrate", "run"])
+        let status = sqlx
+            .migrate("run")
             .status()
             .context("Failed to run migrations")?;
 
@@ -74,8 +146,26 @@ impl DbCommand {
         Ok(())
     }
 
+    /// Run pending migrations, printing the applied ones as JSON
+    fn migrate_json(sqlx: &Sqlx<'_>) -> Result<()> {
+        // sqlx's own progress goes to stderr so stdout stays one document
+        let result = sqlx
+            .migrate("run")
+            .stderr(Stdio::inherit())
+            .output()
+            .context("Failed to run migrations")?;
+
+        if !result.status.success() {
+            anyhow::bail!("Migration failed");
+        }
+
+        output::print_json(&MigrateReport::parse(&String::from_utf8_lossy(
+            &result.stdout,
+        )))
+    }
+
     /// Reset database (drop, create, migrate)
-    fn reset() -> Result<()> {
+    fn reset(sqlx: &Sqlx<'_>) -> Result<()> {
         println!(
             "{} {}",
             style("Resetting").yellow().bold(),
@@ -85,8 +175,9 @@ impl DbCommand {
 
         // Drop database
         println!("  {} Dropping database...", style("1.").cyan());
-        let status = Command::new("sqlx")
-            .args(["database", "drop", "-y"])
+        let status = sqlx
+            .database("drop")
+            .arg("-y")
             .status()
             .context("Failed to drop database")?;
 
@@ -96,8 +187,8 @@ impl DbCommand {
 
         // Create database
         println!("  {} Creating database...", style("2.").cyan());
-        let status = Command::new("sqlx")
-            .args(["database", "create"])
+        let status = sqlx
+            .database("create")
             .status()
             .context("Failed to create database")?;
 
@@ -107,8 +198,8 @@ impl DbCommand {
 
         // Run migrations
         println!("  {} Running migrations...", style("3.").cyan());
-        let status = Command::new("sqlx")
-            .args(["migrate", "run"])
+        let status = sqlx
+            .migrate("run")
             .status()
             .context("Failed to run migrations")?;
 
@@ -123,7 +214,7 @@ impl DbCommand {
     }
 
     /// Create a new migration file
-    fn create(name: &str) -> Result<()> {
+    fn create(sqlx: &Sqlx<'_>, name: &str) -> Result<()> {
         println!(
             "{} {}",
             style("Creating").green().bold(),
@@ -131,8 +222,8 @@ impl DbCommand {
         );
         println!();
 
-        let status = Command::new("sqlx")
-            .args(["migrate", "add", name])
+        let status = sqlx
+            .migrate_add(name)
             .status()
             .context("Failed to create migration")?;
 
@@ -143,7 +234,12 @@ impl DbCommand {
         println!();
         println!(
             "{}",
-            style("✓ Migration file created in migrations/").green().bold()
+            style(format!(
+                "✓ Migration file created in {}/",
+                sqlx.manifest.migrations_dir().display()
+            ))
+            .green()
+            .bold()
         );
 
         Ok(())
@@ -156,7 +252,112 @@ impl DbCommand {
             .stdout(Stdio::null())
             .stderr(Stdio::null())
             .status()
-            .map(|status| status.success())
-            .unwrap_or(false)
+            .is_ok_and(|status| status.success())
+    }
+}
+
+/// Builds `sqlx` invocations for the project's database and migrations
+struct Sqlx<'a> {
+    manifest: &'a ProjectManifest,
+}
+
+impl Sqlx<'_> {
+    /// `sqlx migrate <subcommand>`, against the project's migrations
+    fn migrate(&self, subcommand: &str) -> Command {
+        let mut command = Command::new("sqlx");
+        command
+            .args(["migrate", subcommand, "--source"])
+            .arg(self.manifest.migrations_dir());
+        self.with_database_url(&mut command);
+        command
+    }
+
+    /// `sqlx migrate add <name>`, which takes no database URL
+    fn migrate_add(&self, name: &str) -> Command {
+        let mut command = Command::new("sqlx");
+        command
+            .args(["migrate", "add", "--source"])
+            .arg(self.manifest.migrations_dir())
+            .arg(name);
+        command
+    }
+
+    /// `sqlx database <subcommand>`
+    fn database(&self, subcommand: &str) -> Command {
+        let mut command = Command::new("sqlx");
+        command.args(["database", subcommand]);
+        self.with_database_url(&mut command);
+        command
+    }
+
+    /// Pass the manifest's URL; without one, sqlx reads `$DATABASE_URL`
+    fn with_database_url(&self, command: &mut Command) {
+        if let Some(url) = &self.manifest.database.url {
+            command.args(["--database-url", url]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_applied_migrations() {
+        let stdout = "Applied 20240101000000/migrate create users (1.234ms)\n\
+                      Applied 20240102000000/migrate add posts (987.1µs)\n";
+        let report = MigrateReport::parse(stdout);
+        assert_eq!(
+            report.applied,
+            [
+                AppliedMigration {
+                    version: 20_240_101_000_000,
+                    description: "create users".to_string(),
+                },
+                AppliedMigration {
+                    version: 20_240_102_000_000,
+                    description: "add posts".to_string(),
+                },
+            ]
+        );
+    }
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_sqlx_arguments_from_manifest() {
+        let manifest: ProjectManifest = toml::from_str(
+            "[database]\nurl = \"sqlite:data/dev.db\"\nmigrations = \"db/migrations\"\n",
+        )
+        .unwrap();
+        let sqlx = Sqlx {
+            manifest: &manifest,
+        };
+        assert_eq!(
+            args(&sqlx.migrate("run")),
+            ["migrate", "run", "--source", "db/migrations", "--database-url", "sqlite:data/dev.db"]
+        );
+        assert_eq!(
+            args(&sqlx.migrate_add("create_posts")),
+            ["migrate", "add", "--source", "db/migrations", "create_posts"]
+        );
+
+        let default = ProjectManifest::default();
+        let sqlx = Sqlx { manifest: &default };
+        assert_eq!(args(&sqlx.database("create")), ["database", "create"]);
+    }
+
+    #[test]
+    fn test_parse_nothing_applied() {
+        assert_eq!(MigrateReport::parse(""), MigrateReport::default());
+        assert_eq!(
+            serde_json::to_string(&MigrateReport::default()).unwrap(),
+            r#"{"applied":[]}"#
+        );
     }
 }
@@ -3,9 +3,20 @@
 use anyhow::{Context, Result};
 use clap::Subcommand;
 use console::{style, Emoji};
-use std::path::PathBuf;
+use minijinja::Environment;
+use serde_json::{json, Value};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use super::super::static_templates::{
+    DEB_CONTROL, DEB_POSTINST, PACKAGE_APP_ENV, PACKAGE_SERVICE_ENV, SYSTEMD_APP_UNIT,
+    SYSTEMD_IPC_SOCKET, SYSTEMD_SANDBOXING, SYSTEMD_SERVICE_UNIT,
+};
+use super::super::manifest::ProjectManifest;
+use super::ServiceName;
+
 static ROCKET: Emoji = Emoji("🚀", ">>>");
 static SUCCESS: Emoji = Emoji("✓", "√");
 static ERROR: Emoji = Emoji("✗", "x");
@@ -20,7 +31,7 @@ pub enum DeployCommand {
     ///   acton htmx deploy docker --registry=ghcr.io/myorg
     ///   acton htmx deploy docker --tag=v1.0.0
     Docker {
-        /// Docker registry to push to (e.g., ghcr.io/myorg, docker.io/username)
+        /// Docker registry to push to (e.g., ghcr.io/myorg, docker.io/username; default: `deploy.registry` in acton.toml)
         #[arg(long)]
         registry: Option<String>,
 
@@ -28,7 +39,7 @@ pub enum DeployCommand {
         #[arg(long, default_value = "latest")]
         tag: String,
 
-        /// Build platform (e.g., linux/amd64,linux/arm64)
+        /// Build platform (e.g., linux/amd64,linux/arm64; default: `deploy.platform` in acton.toml)
         #[arg(long)]
         platform: Option<String>,
 
@@ -40,6 +51,45 @@ pub enum DeployCommand {
         #[arg(long, default_value = "Dockerfile")]
         dockerfile: PathBuf,
     },
+
+    /// Build release binaries and package them with systemd units for VMs
+    ///
+    /// Produces a tarball laid out for extraction at `/`, and with `--deb`
+    /// a Debian package built from the same tree.
+    ///
+    /// Examples:
+    ///   acton htmx deploy package
+    ///   acton htmx deploy package --services=auth,data,cedar
+    ///   acton htmx deploy package --services=auth --ipc --deb
+    Package {
+        /// Services to ship alongside the application (auth, data, cedar, cache, email, file; default: from acton.toml)
+        #[arg(long, value_delimiter = ',')]
+        services: Vec<ServiceName>,
+
+        /// Use the IPC transport, with systemd activating its Unix socket (default: `deploy.ipc` in acton.toml)
+        #[arg(long)]
+        ipc: bool,
+
+        /// System user the units run as (default: `deploy.user` in acton.toml, or project name)
+        #[arg(long)]
+        user: Option<String>,
+
+        /// Package version (default: version in Cargo.toml)
+        #[arg(long = "package-version")]
+        version: Option<String>,
+
+        /// Also build a .deb with dpkg-deb
+        #[arg(long)]
+        deb: bool,
+
+        /// Package the binaries already in target/release
+        #[arg(long)]
+        no_build: bool,
+
+        /// Output directory
+        #[arg(long, default_value = "target/package")]
+        output: PathBuf,
+    },
 }
 
 impl DeployCommand {
@@ -51,7 +101,10 @@ impl DeployCommand {
     /// - Docker is not installed
     /// - Build fails
     /// - Push fails
-    pub fn execute(&self) -> Result<()> {
+    /// - Packaging fails
+    ///
+    /// Settings not given as flags are read from `manifest`.
+    pub fn execute(&self, manifest: &ProjectManifest) -> Result<()> {
         match self {
             Self::Docker {
                 registry,
@@ -59,7 +112,50 @@ impl DeployCommand {
                 platform,
                 no_push,
                 dockerfile,
-            } => Self::deploy_docker(registry.as_ref(), tag, platform.as_ref(), *no_push, dockerfile),
+            } => Self::deploy_docker(
+                registry.as_ref().or(manifest.deploy.registry.as_ref()),
+                tag,
+                platform.as_ref().or(manifest.deploy.platform.as_ref()),
+                *no_push,
+                dockerfile,
+                manifest,
+            ),
+            Self::Package {
+                services,
+                ipc,
+                user,
+                version,
+                deb,
+                no_build,
+                output,
+            } => {
+                let project_name = Self::get_project_name(manifest)?;
+                let mut assets = manifest.template_dirs();
+                assets.extend([
+                    manifest.resolve(Path::new("static")),
+                    manifest.migrations_dir(),
+                    manifest.resolve(Path::new("config")),
+                ]);
+                let layout = PackageLayout {
+                    user: user
+                        .clone()
+                        .or_else(|| manifest.deploy.user.clone())
+                        .unwrap_or_else(|| project_name.clone()),
+                    version: match version {
+                        Some(version) => version.clone(),
+                        None => Self::get_project_version()?,
+                    },
+                    project_name,
+                    services: if services.is_empty() {
+                        manifest.deploy_services()
+                    } else {
+                        services.clone()
+                    },
+                    ipc: *ipc || manifest.deploy.ipc,
+                    assets,
+                };
+                Self::deploy_package(&layout, *deb, *no_build, output)
+            }
         }
     }
 
@@ -69,6 +165,7 @@ impl DeployCommand {
         platform: Option<&String>,
         no_push: bool,
         dockerfile: &PathBuf,
+        manifest: &ProjectManifest,
     ) -> Result<()> {
         // Check if Docker is installed
         Self::check_docker()?;
@@ -81,8 +178,8 @@ impl DeployCommand {
             );
         }
 
-        // Get project name from Cargo.toml
-        let project_name = Self::get_project_name()?;
+        // Get project name from acton.toml or Cargo.toml
+        let project_name = Self::get_project_name(manifest)?;
 
         // Build image name
         let image_name = registry.map_or_else(
@@ -164,6 +261,95 @@ impl DeployCommand {
         Ok(())
     }
 
+    fn deploy_package(
+        layout: &PackageLayout,
+        deb: bool,
+        no_build: bool,
+        output: &Path,
+    ) -> Result<()> {
+        println!(
+            "{} Packaging {} {}",
+            ROCKET,
+            style(&layout.project_name).cyan(),
+            layout.version
+        );
+        println!();
+
+        if !no_build {
+            Self::cargo_build_release(&layout.project_name)?;
+            for service in &layout.services {
+                Self::cargo_build_release(service.binary_name())?;
+            }
+        }
+
+        let root = output.join(layout.package_name());
+        if root.exists() {
+            fs::remove_dir_all(&root)
+                .with_context(|| format!("Failed to clear {}", root.display()))?;
+        }
+        layout.assemble(&root, Path::new("target/release"))?;
+        println!("  {SUCCESS} Assembled package tree: {}", style(root.display()).green());
+
+        let tarball = output.join(format!("{}.tar.gz", layout.package_name()));
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(&tarball)
+            .arg("--exclude=./DEBIAN")
+            .arg("-C")
+            .arg(&root)
+            .arg(".")
+            .status()
+            .context("Failed to execute tar")?;
+        if !status.success() {
+            anyhow::bail!("Failed to create {}", tarball.display());
+        }
+        println!("  {SUCCESS} Created: {}", style(tarball.display()).green());
+
+        if deb {
+            let package = output.join(format!("{}.deb", layout.package_name()));
+            let status = Command::new("dpkg-deb")
+                .arg("--root-owner-group")
+                .arg("--build")
+                .arg(&root)
+                .arg(&package)
+                .status()
+                .context("Failed to execute dpkg-deb. Is it installed?")?;
+            if !status.success() {
+                anyhow::bail!("Failed to build {}", package.display());
+            }
+            println!("  {SUCCESS} Created: {}", style(package.display()).green());
+        }
+
+        println!();
+        println!("{}", style("Release package ready!").green().bold());
+        println!();
+        println!("Next steps:");
+        println!("  1. Copy the package to the server and install it");
+        println!(
+            "  2. Edit the environment files in /etc/{}/",
+            layout.project_name
+        );
+        println!(
+            "  3. Start it: systemctl enable --now {}.service",
+            layout.project_name
+        );
+
+        Ok(())
+    }
+
+    fn cargo_build_release(package: &str) -> Result<()> {
+        let status = Command::new("cargo")
+            .args(["build", "--release", "-p", package])
+            .status()
+            .with_context(|| format!("Failed to build {package}"))?;
+
+        if !status.success() {
+            anyhow::bail!("Failed to build {package}");
+        }
+        println!("  {SUCCESS} Built {package}");
+        Ok(())
+    }
+
     fn check_docker() -> Result<()> {
         let output = Command::new("docker")
             .arg("--version")
@@ -177,7 +363,11 @@ impl DeployCommand {
         Ok(())
     }
 
-    fn get_project_name() -> Result<String> {
+    fn get_project_name(manifest: &ProjectManifest) -> Result<String> {
+        if let Some(name) = manifest.project_name() {
+            return Ok(name.to_string());
+        }
+
         let cargo_toml = std::fs::read_to_string("Cargo.toml")
             .context("Failed to read Cargo.toml. Are you in a project directory?")?;
 
@@ -192,10 +382,347 @@ impl DeployCommand {
 
         anyhow::bail!("Could not find project name in Cargo.toml");
     }
+
+    fn get_project_version() -> Result<String> {
+        let cargo_toml = std::fs::read_to_string("Cargo.toml")
+            .context("Failed to read Cargo.toml. Are you in a project directory?")?;
+
+        cargo_toml
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(key, _)| key.trim() == "version")
+            .map(|(_, version)| version.trim().trim_matches('"').to_string())
+            .context("Could not find project version in Cargo.toml. Pass --package-version")
+    }
+}
+
+/// Files of a release package, laid out as they are installed
+#[derive(Debug, Clone)]
+struct PackageLayout {
+    project_name: String,
+    version: String,
+    user: String,
+    services: Vec<ServiceName>,
+    ipc: bool,
+    /// Directories installed under `/usr/share/<project>`, by file name
+    assets: Vec<PathBuf>,
+}
+
+impl PackageLayout {
+    /// Base name of the tarball and deb, e.g. `my-app_1.0.0_amd64`
+    fn package_name(&self) -> String {
+        format!("{}_{}_{}", self.project_name, self.version, deb_arch())
+    }
+
+    /// systemd units as `(file name, contents)`
+    fn units(&self) -> Result<Vec<(String, String)>> {
+        let env = template_env();
+        let service_units: Vec<String> = self
+            .services
+            .iter()
+            .map(|service| self.service_unit(*service))
+            .collect();
+
+        let mut units = vec![(
+            format!("{}.service", self.project_name),
+            env.render_str(
+                SYSTEMD_APP_UNIT,
+                self.context(json!({ "service_units": service_units })),
+            )?,
+        )];
+        for service in &self.services {
+            units.push((
+                self.service_unit(*service),
+                env.render_str(SYSTEMD_SERVICE_UNIT, self.context(service_context(*service)))?,
+            ));
+        }
+        if self.ipc {
+            units.push((
+                format!("{}-ipc.socket", self.project_name),
+                env.render_str(SYSTEMD_IPC_SOCKET, self.context(json!({})))?,
+            ));
+        }
+        Ok(units)
+    }
+
+    /// Environment files for `/etc/<project>` as `(file name, contents)`
+    fn env_files(&self) -> Result<Vec<(String, String)>> {
+        let env = template_env();
+        let services: Vec<Value> = self.services.iter().map(|s| service_context(*s)).collect();
+
+        let mut files = vec![(
+            format!("{}.env", self.project_name),
+            env.render_str(PACKAGE_APP_ENV, self.context(json!({ "services": services })))?,
+        )];
+        for service in &self.services {
+            files.push((
+                format!("{}.env", service.binary_name()),
+                env.render_str(PACKAGE_SERVICE_ENV, self.context(service_context(*service)))?,
+            ));
+        }
+        Ok(files)
+    }
+
+    /// Write the package tree under `root`, copying binaries from `binaries`
+    fn assemble(&self, root: &Path, binaries: &Path) -> Result<()> {
+        let bin_dir = root.join("usr/bin");
+        let unit_dir = root.join("lib/systemd/system");
+        let etc_dir = root.join("etc").join(&self.project_name);
+        let share_dir = root.join("usr/share").join(&self.project_name);
+        let debian_dir = root.join("DEBIAN");
+        for dir in [&bin_dir, &unit_dir, &etc_dir, &share_dir, &debian_dir] {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+
+        let mut names = vec![self.project_name.clone()];
+        names.extend(self.services.iter().map(|s| s.binary_name().to_string()));
+        for name in &names {
+            let binary = binaries.join(name);
+            fs::copy(&binary, bin_dir.join(name)).with_context(|| {
+                format!("Failed to copy {}. Build it or drop --no-build", binary.display())
+            })?;
+        }
+
+        for asset in &self.assets {
+            if let Some(name) = asset.file_name() {
+                copy_dir(asset, &share_dir.join(name))?;
+            }
+        }
+        for service in &self.services {
+            let config = Path::new("services").join(service.binary_name()).join("config");
+            copy_dir(&config, &etc_dir.join(service.binary_name()).join("config"))?;
+        }
+
+        for (name, contents) in self.units()? {
+            write_file(&unit_dir.join(name), &contents)?;
+        }
+        let env_files = self.env_files()?;
+        for (name, contents) in &env_files {
+            write_file(&etc_dir.join(name), contents)?;
+        }
+
+        let env = template_env();
+        let context = self.context(json!({ "arch": deb_arch() }));
+        write_file(&debian_dir.join("control"), &env.render_str(DEB_CONTROL, &context)?)?;
+        let mut conffiles = String::new();
+        for (name, _) in &env_files {
+            writeln!(conffiles, "/etc/{}/{name}", self.project_name)?;
+        }
+        write_file(&debian_dir.join("conffiles"), &conffiles)?;
+        let postinst = debian_dir.join("postinst");
+        write_file(&postinst, &env.render_str(DEB_POSTINST, &context)?)?;
+        set_executable(&postinst)?;
+
+        Ok(())
+    }
+
+    fn service_unit(&self, service: ServiceName) -> String {
+        format!("{}-{}.service", self.project_name, service.binary_name())
+    }
+
+    /// Template context shared by every file, merged with `extra`
+    fn context(&self, extra: Value) -> Value {
+        let mut context = json!({
+            "project_name": self.project_name,
+            "version": self.version,
+            "user": self.user,
+            "ipc": self.ipc,
+            "sandboxing": SYSTEMD_SANDBOXING,
+        });
+        if let (Some(context), Value::Object(extra)) = (context.as_object_mut(), extra) {
+            context.extend(extra);
+        }
+        context
+    }
+}
+
+fn service_context(service: ServiceName) -> Value {
+    let env_prefix = service.binary_name().to_uppercase().replace('-', "_");
+    let short_upper = env_prefix.trim_end_matches("_SERVICE").to_string();
+    json!({
+        "binary": service.binary_name(),
+        "display_name": service.display_name(),
+        "port": service.default_port(),
+        "env_prefix": format!("{env_prefix}_"),
+        "short_upper": short_upper,
+    })
+}
+
+fn template_env() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|_| minijinja::AutoEscape::None);
+    env.set_keep_trailing_newline(true);
+    env
+}
+
+/// Debian name of the architecture the CLI was built for
+fn deb_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "i386",
+        "arm" => "armhf",
+        other => other,
+    }
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to make {} executable", path.display()))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Copy a directory tree if it exists
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    if !from.is_dir() {
+        return Ok(());
+    }
+    fs::create_dir_all(to).with_context(|| format!("Failed to create {}", to.display()))?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn layout(ipc: bool) -> PackageLayout {
+        PackageLayout {
+            project_name: "my-app".to_string(),
+            version: "1.2.0".to_string(),
+            user: "my-app".to_string(),
+            services: vec![ServiceName::Auth, ServiceName::Cedar],
+            ipc,
+            assets: ["templates", "static", "migrations", "config"]
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+        }
+    }
+
+    fn file<'a>(files: &'a [(String, String)], name: &str) -> &'a str {
+        &files.iter().find(|(file, _)| file == name).unwrap().1
+    }
+
+    #[test]
+    fn test_package_units_are_sandboxed() {
+        let units = layout(false).units().unwrap();
+        let names: Vec<&str> = units.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["my-app.service", "my-app-auth-service.service", "my-app-cedar-service.service"]
+        );
+
+        let app = file(&units, "my-app.service");
+        assert!(app.contains("After=network-online.target my-app-auth-service.service"));
+        assert!(app.contains("EnvironmentFile=/etc/my-app/my-app.env"));
+        assert!(app.contains("ExecStart=/usr/bin/my-app\n"));
+        assert!(!app.contains("Sockets="));
+
+        let cedar = file(&units, "my-app-cedar-service.service");
+        assert!(cedar.contains("ExecStart=/usr/bin/cedar-service"));
+        assert!(cedar.contains("PartOf=my-app.service"));
+        for (_, unit) in &units {
+            assert!(unit.contains("User=my-app"));
+            assert!(unit.contains("ProtectSystem=strict"));
+            assert!(unit.contains("NoNewPrivileges=yes"));
+        }
+    }
+
+    #[test]
+    fn test_package_ipc_socket_activation() {
+        let layout = layout(true);
+        let units = layout.units().unwrap();
+        let socket = file(&units, "my-app-ipc.socket");
+        assert!(socket.contains("ListenStream=/run/my-app/ipc.sock"));
+        assert!(socket.contains("Service=my-app.service"));
+
+        let app = file(&units, "my-app.service");
+        assert!(app.contains("Requires=my-app-ipc.socket"));
+        assert!(app.contains("Sockets=my-app-ipc.socket"));
+
+        let env_files = layout.env_files().unwrap();
+        let env = file(&env_files, "my-app.env");
+        assert!(env.contains("ACTON_SERVICES__TRANSPORT=ipc"));
+        assert!(env.contains("ACTON_SERVICES__IPC__SOCKET_PATH=/run/my-app/ipc.sock"));
+    }
+
+    #[test]
+    fn test_package_env_files_point_at_services() {
+        let env_files = layout(false).env_files().unwrap();
+        let app = file(&env_files, "my-app.env");
+        assert!(app.contains("ACTON_SERVICES__TRANSPORT=grpc"));
+        assert!(app.contains("ACTON_SERVICES__GRPC__AUTH_ENDPOINT=http://127.0.0.1:50051"));
+        assert!(app.contains("ACTON_SERVICES__GRPC__CEDAR_ENDPOINT=http://127.0.0.1:50053"));
+
+        let cedar = file(&env_files, "cedar-service.env");
+        assert!(cedar.contains("CEDAR_SERVICE_SERVICE__PORT=50053"));
+    }
+
+    #[test]
+    fn test_package_assemble_layout() {
+        let binaries = tempdir().unwrap();
+        for name in ["my-app", "auth-service", "cedar-service"] {
+            fs::write(binaries.path().join(name), name).unwrap();
+        }
+        let root = tempdir().unwrap();
+        layout(true).assemble(root.path(), binaries.path()).unwrap();
+
+        let root = root.path();
+        assert!(root.join("usr/bin/my-app").exists());
+        assert!(root.join("usr/bin/cedar-service").exists());
+        assert!(root.join("lib/systemd/system/my-app-ipc.socket").exists());
+        assert!(root.join("etc/my-app/auth-service.env").exists());
+
+        let control = fs::read_to_string(root.join("DEBIAN/control")).unwrap();
+        assert!(control.contains("Package: my-app\nVersion: 1.2.0\n"));
+        let conffiles = fs::read_to_string(root.join("DEBIAN/conffiles")).unwrap();
+        assert_eq!(
+            conffiles,
+            "/etc/my-app/my-app.env\n/etc/my-app/auth-service.env\n/etc/my-app/cedar-service.env\n"
+        );
+    }
+
+    #[test]
+    fn test_package_assemble_requires_binaries() {
+        let binaries = tempdir().unwrap();
+        let root = tempdir().unwrap();
+        let error = layout(false)
+            .assemble(root.path(), binaries.path())
+            .unwrap_err();
+        assert!(error.to_string().contains("Failed to copy"));
+    }
+
+    #[test]
+    fn test_project_name_from_manifest() {
+        let manifest = ProjectManifest::for_new_project(
+            "blog",
+            super::super::super::DatabaseBackend::Sqlite,
+        );
+        assert_eq!(DeployCommand::get_project_name(&manifest).unwrap(), "blog");
+    }
+
     #[test]
     fn test_get_project_name_valid() {
         // This test would need to be in a mock project directory
@@ -11,6 +11,7 @@ use console::{style, Emoji};
 use std::path::Path;
 use std::process::{Command, Stdio};
 
+use super::super::manifest::ProjectManifest;
 use super::services::ServiceName;
 
 static INFO: Emoji<'_, '_> = Emoji("ℹ", "i");
@@ -44,6 +45,31 @@ impl Default for DevOptions {
     }
 }
 
+impl DevOptions {
+    /// Options set by a project's `acton.toml`, defaults elsewhere
+    #[must_use]
+    pub fn from_manifest(manifest: &ProjectManifest) -> Self {
+        let defaults = Self::default();
+        Self {
+            embedded_services: manifest.services.embedded,
+            services: (!manifest.services.enabled.is_empty()).then(|| {
+                manifest
+                    .services
+                    .enabled
+                    .iter()
+                    .map(|service| service.name().to_string())
+                    .collect()
+            }),
+            services_port: manifest
+                .services
+                .base_port
+                .unwrap_or(defaults.services_port),
+            app_port: manifest.dev.port.unwrap_or(defaults.app_port),
+            host: manifest.dev.host.clone().unwrap_or(defaults.host),
+        }
+    }
+}
+
 /// Start development server with hot reload
 pub struct DevCommand;
 
@@ -335,6 +361,23 @@ mod tests {
         assert_eq!(options.host, "127.0.0.1");
     }
 
+    #[test]
+    fn test_options_from_manifest() {
+        let manifest: ProjectManifest = toml::from_str(
+            "[services]\nenabled = [\"auth\", \"data\"]\nembedded = true\n\n[dev]\nport = 8080\n",
+        )
+        .unwrap();
+        let options = DevOptions::from_manifest(&manifest);
+        assert!(options.embedded_services);
+        assert_eq!(
+            options.services,
+            Some(vec!["auth".to_string(), "data".to_string()])
+        );
+        assert_eq!(options.app_port, 8080);
+        assert_eq!(options.services_port, 50051);
+        assert_eq!(options.host, "127.0.0.1");
+    }
+
     #[test]
     fn test_build_env_vars_standard() {
         let options = DevOptions::default();
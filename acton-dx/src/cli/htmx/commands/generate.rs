@@ -10,8 +10,14 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use super::super::static_templates::{
-    DEPLOYMENT_README, DOCKER_COMPOSE, DOCKERIGNORE, DOCKERFILE, ENV_PRODUCTION, JOB_TEMPLATE,
-    NGINX_CONF,
+    ACCOUNT_DATA_EXPORT_TEMPLATE, ACCOUNT_EMAIL_CONFIRMED_TEMPLATE, ACCOUNT_EMAIL_TEMPLATE,
+    ACCOUNT_HANDLER_TEMPLATE, ACCOUNT_INDEX_TEMPLATE, ACCOUNT_MIGRATION, ACCOUNT_PASSWORD_TEMPLATE,
+    ACCOUNT_PROFILE_TEMPLATE, ACCOUNT_SECURITY_TEMPLATE, ACCOUNT_SESSIONS_TEMPLATE,
+    ADMIN_USERS_AUDIT_TEMPLATE, ADMIN_USERS_EDIT_TEMPLATE, ADMIN_USERS_HANDLER_TEMPLATE,
+    ADMIN_USERS_INDEX_TEMPLATE, ADMIN_USERS_MIGRATION, ADMIN_USERS_PANEL_TEMPLATE,
+    ADMIN_USERS_ROWS_TEMPLATE, ADMIN_USERS_SESSIONS_TEMPLATE, AUDIT_LOG_HANDLER_TEMPLATE,
+    AUDIT_LOG_INDEX_TEMPLATE, AUDIT_LOG_MIGRATION, AUDIT_LOG_ROWS_TEMPLATE, DEPLOYMENT_README,
+    DOCKER_COMPOSE, DOCKERIGNORE, DOCKERFILE, ENV_PRODUCTION, JOB_TEMPLATE, NGINX_CONF,
 };
 
 static SUCCESS: Emoji = Emoji("✓", "√");
@@ -66,6 +72,84 @@ pub enum GenerateCommand {
         #[arg(short, long, default_value = ".")]
         output: PathBuf,
     },
+
+    /// Generate admin user management pages
+    ///
+    /// Produces handlers, HTMX templates and a migration for listing and
+    /// searching users, editing roles, forcing password resets, locking
+    /// accounts, and viewing sessions and audit events.
+    ///
+    /// Examples:
+    ///   acton htmx generate admin-users
+    ///   acton htmx generate admin-users --prefix=/staff/users --roles=user,support,admin
+    AdminUsers {
+        /// Path the pages are mounted under (default: /admin/users)
+        #[arg(long, default_value = "/admin/users")]
+        prefix: String,
+
+        /// Roles that can be assigned, comma separated (must include `admin`)
+        #[arg(long, value_delimiter = ',', default_value = "user,moderator,admin")]
+        roles: Vec<String>,
+
+        /// Users shown per page (default: 25)
+        #[arg(long, default_value = "25")]
+        page_size: u32,
+
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+
+    /// Generate account settings pages for signed-in users
+    ///
+    /// Produces handlers, HTMX templates and a migration for editing the
+    /// profile, changing email (confirmed from the new address), changing
+    /// password, managing passkeys, revoking sessions and exporting data.
+    ///
+    /// Examples:
+    ///   acton htmx generate account
+    ///   acton htmx generate account --prefix=/settings --email-change-hours=2
+    Account {
+        /// Path the pages are mounted under (default: /account)
+        #[arg(long, default_value = "/account")]
+        prefix: String,
+
+        /// Hours an email change confirmation link stays valid (default: 24)
+        #[arg(long, default_value = "24")]
+        email_change_hours: u32,
+
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+
+    /// Generate an admin audit log viewer
+    ///
+    /// Produces handlers, HTMX templates and a migration for browsing the
+    /// admin audit trail with filters, exporting it as CSV and purging
+    /// events past the retention period. Builds on the `admin_audit_events`
+    /// table from `generate admin-users`.
+    ///
+    /// Examples:
+    ///   acton htmx generate audit-log
+    ///   acton htmx generate audit-log --prefix=/staff/audit --retention-days=90
+    AuditLog {
+        /// Path the page is mounted under (default: /admin/audit)
+        #[arg(long, default_value = "/admin/audit")]
+        prefix: String,
+
+        /// Events shown per page (default: 50)
+        #[arg(long, default_value = "50")]
+        page_size: u32,
+
+        /// Days events are kept before the retention job deletes them (default: 365)
+        #[arg(long, default_value = "365")]
+        retention_days: u32,
+
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
 }
 
 impl GenerateCommand {
@@ -93,6 +177,23 @@ impl GenerateCommand {
                 deployment_type,
                 output,
             } => Self::generate_deployment(deployment_type, output),
+            Self::AdminUsers {
+                prefix,
+                roles,
+                page_size,
+                output,
+            } => Self::generate_admin_users(prefix, roles, *page_size, output),
+            Self::Account {
+                prefix,
+                email_change_hours,
+                output,
+            } => Self::generate_account(prefix, *email_change_hours, output),
+            Self::AuditLog {
+                prefix,
+                page_size,
+                retention_days,
+                output,
+            } => Self::generate_audit_log(prefix, *page_size, *retention_days, output),
         }
     }
 
@@ -332,6 +433,323 @@ impl GenerateCommand {
         Ok(())
     }
 
+    fn generate_admin_users(
+        prefix: &str,
+        roles: &[String],
+        page_size: u32,
+        output: &Path,
+    ) -> Result<()> {
+        println!(
+            "\n{} Generating admin user management pages",
+            style("🛡").bold()
+        );
+
+        Self::validate_admin_users(prefix, roles, page_size)?;
+        let handler = Self::render_admin_users_handler(prefix, roles, page_size)?;
+
+        let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+        let files = [
+            (PathBuf::from("src/handlers/admin_users.rs"), handler.as_str()),
+            (
+                PathBuf::from(format!("migrations/{timestamp}_admin_user_management.sql")),
+                ADMIN_USERS_MIGRATION,
+            ),
+            (PathBuf::from("templates/admin/users/index.html"), ADMIN_USERS_INDEX_TEMPLATE),
+            (PathBuf::from("templates/admin/users/_rows.html"), ADMIN_USERS_ROWS_TEMPLATE),
+            (PathBuf::from("templates/admin/users/edit.html"), ADMIN_USERS_EDIT_TEMPLATE),
+            (PathBuf::from("templates/admin/users/_panel.html"), ADMIN_USERS_PANEL_TEMPLATE),
+            (PathBuf::from("templates/admin/users/_sessions.html"), ADMIN_USERS_SESSIONS_TEMPLATE),
+            (PathBuf::from("templates/admin/users/_audit.html"), ADMIN_USERS_AUDIT_TEMPLATE),
+        ];
+
+        println!();
+        Self::write_files(output, &files)?;
+
+        // Show next steps
+        println!();
+        println!("{}", style("Next steps:").bold().underlined());
+        println!("  1. Add to src/handlers/mod.rs:");
+        println!("     {}", style("pub mod admin_users;").cyan());
+        println!();
+        println!("  2. Enable the `microservices` feature of acton-dx and give AppState an auth client:");
+        println!(
+            "     {}",
+            style("pub fn auth(&self) -> &tokio::sync::Mutex<AuthClient>").cyan()
+        );
+        println!();
+        println!("  3. Mount the routes in src/main.rs:");
+        println!(
+            "     {}",
+            style(".nest(handlers::admin_users::BASE_PATH, handlers::admin_users::routes())")
+                .cyan()
+        );
+        println!();
+        println!("  4. In the login handler, reject accounts using admin_users::login_status");
+        println!("     and record sessions with admin_users::track_session");
+        println!();
+        println!("  5. Run migrations:");
+        println!("     {}", style("acton htmx db migrate").cyan());
+        println!();
+
+        Ok(())
+    }
+
+    fn validate_admin_users(prefix: &str, roles: &[String], page_size: u32) -> Result<()> {
+        if !Self::is_valid_prefix(prefix) {
+            bail!("Invalid prefix: '{prefix}'. Expected a path like '/admin/users'");
+        }
+
+        if let Some(role) = roles.iter().find(|role| {
+            role.is_empty()
+                || !role
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        }) {
+            bail!("Invalid role: '{role}'. Roles must be lowercase letters, digits or '_'");
+        }
+        if !roles.iter().any(|role| role == "admin") {
+            bail!("Roles must include 'admin' so admins can keep access to these pages");
+        }
+
+        if page_size == 0 {
+            bail!("Page size must be at least 1");
+        }
+
+        Ok(())
+    }
+
+    fn render_admin_users_handler(prefix: &str, roles: &[String], page_size: u32) -> Result<String> {
+        let context = json!({
+            "route_prefix": prefix,
+            "roles": roles,
+            "page_size": page_size,
+        });
+
+        let mut env = Environment::new();
+        env.set_auto_escape_callback(|_| minijinja::AutoEscape::None);
+        env.set_keep_trailing_newline(true);
+
+        env.render_str(ADMIN_USERS_HANDLER_TEMPLATE, context)
+            .context("Failed to render admin users handler template")
+    }
+
+    fn generate_account(prefix: &str, email_change_hours: u32, output: &Path) -> Result<()> {
+        println!(
+            "\n{} Generating account settings pages",
+            style("👤").bold()
+        );
+
+        Self::validate_account(prefix, email_change_hours)?;
+        let handler = Self::render_account_handler(prefix, email_change_hours)?;
+
+        let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+        let files = [
+            (PathBuf::from("src/handlers/account.rs"), handler.as_str()),
+            (
+                PathBuf::from(format!("migrations/{timestamp}_account_settings.sql")),
+                ACCOUNT_MIGRATION,
+            ),
+            (PathBuf::from("templates/account/index.html"), ACCOUNT_INDEX_TEMPLATE),
+            (PathBuf::from("templates/account/_profile.html"), ACCOUNT_PROFILE_TEMPLATE),
+            (PathBuf::from("templates/account/_email.html"), ACCOUNT_EMAIL_TEMPLATE),
+            (
+                PathBuf::from("templates/account/email_confirmed.html"),
+                ACCOUNT_EMAIL_CONFIRMED_TEMPLATE,
+            ),
+            (PathBuf::from("templates/account/_password.html"), ACCOUNT_PASSWORD_TEMPLATE),
+            (PathBuf::from("templates/account/_security.html"), ACCOUNT_SECURITY_TEMPLATE),
+            (PathBuf::from("templates/account/_sessions.html"), ACCOUNT_SESSIONS_TEMPLATE),
+            (PathBuf::from("templates/account/_data_export.html"), ACCOUNT_DATA_EXPORT_TEMPLATE),
+        ];
+
+        println!();
+        Self::write_files(output, &files)?;
+
+        // Show next steps
+        println!();
+        println!("{}", style("Next steps:").bold().underlined());
+        println!("  1. Add to src/handlers/mod.rs:");
+        println!("     {}", style("pub mod account;").cyan());
+        println!();
+        println!("  2. Enable the `microservices` feature of acton-dx and give AppState:");
+        println!(
+            "     {}",
+            style("pub fn auth(&self) -> &tokio::sync::Mutex<AuthClient>").cyan()
+        );
+        println!("     {}", style("pub fn mailer(&self) -> &dyn EmailSender").cyan());
+        println!(
+            "     {}",
+            style("pub fn base_url(&self) -> &str  // e.g. https://example.com").cyan()
+        );
+        println!();
+        println!("  3. Mount the routes in src/main.rs, with the passkey routes for the 2FA tab:");
+        println!(
+            "     {}",
+            style(".nest(handlers::account::BASE_PATH, handlers::account::routes())").cyan()
+        );
+        println!("     {}", style(".merge(acton_dx::auth::passkey::routes())").cyan());
+        println!();
+        println!("  4. Install acton_dx::privacy with your modules for the data export tab,");
+        println!("     and record sessions in the login handler with account::track_session");
+        println!();
+        println!("  5. Run migrations:");
+        println!("     {}", style("acton htmx db migrate").cyan());
+        println!();
+
+        Ok(())
+    }
+
+    fn validate_account(prefix: &str, email_change_hours: u32) -> Result<()> {
+        if !Self::is_valid_prefix(prefix) {
+            bail!("Invalid prefix: '{prefix}'. Expected a path like '/account'");
+        }
+        if !(1..=168).contains(&email_change_hours) {
+            bail!("Email change links must expire within 1 to 168 hours");
+        }
+        Ok(())
+    }
+
+    fn render_account_handler(prefix: &str, email_change_hours: u32) -> Result<String> {
+        let context = json!({
+            "route_prefix": prefix,
+            "email_change_hours": email_change_hours,
+        });
+
+        let mut env = Environment::new();
+        env.set_auto_escape_callback(|_| minijinja::AutoEscape::None);
+        env.set_keep_trailing_newline(true);
+
+        env.render_str(ACCOUNT_HANDLER_TEMPLATE, context)
+            .context("Failed to render account handler template")
+    }
+
+    fn generate_audit_log(
+        prefix: &str,
+        page_size: u32,
+        retention_days: u32,
+        output: &Path,
+    ) -> Result<()> {
+        println!("\n{} Generating audit log viewer", style("📜").bold());
+
+        Self::validate_audit_log(prefix, page_size, retention_days)?;
+        let handler = Self::render_audit_log_handler(prefix, page_size, retention_days)?;
+
+        let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+        let files = [
+            (PathBuf::from("src/handlers/audit_log.rs"), handler.as_str()),
+            (
+                PathBuf::from(format!("migrations/{timestamp}_audit_log_viewer.sql")),
+                AUDIT_LOG_MIGRATION,
+            ),
+            (PathBuf::from("templates/admin/audit/index.html"), AUDIT_LOG_INDEX_TEMPLATE),
+            (PathBuf::from("templates/admin/audit/_rows.html"), AUDIT_LOG_ROWS_TEMPLATE),
+        ];
+
+        println!();
+        Self::write_files(output, &files)?;
+
+        // Show next steps
+        println!();
+        println!("{}", style("Next steps:").bold().underlined());
+        println!("  1. Generate the admin user pages first if you haven't; the viewer reads");
+        println!("     their audit trail and uses their admin check:");
+        println!("     {}", style("acton htmx generate admin-users").cyan());
+        println!();
+        println!("  2. Add to src/handlers/mod.rs:");
+        println!("     {}", style("pub mod audit_log;").cyan());
+        println!();
+        println!("  3. Mount the routes in src/main.rs:");
+        println!(
+            "     {}",
+            style(".nest(handlers::audit_log::BASE_PATH, handlers::audit_log::routes())").cyan()
+        );
+        println!();
+        println!("  4. Schedule the retention job daily, with a database pool in its context:");
+        println!(
+            "     {}",
+            style("handlers::audit_log::PurgeAuditEventsJob::default()").cyan()
+        );
+        println!();
+        println!("  5. Record your own events with audit_log::record_event");
+        println!();
+        println!("  6. Run migrations:");
+        println!("     {}", style("acton htmx db migrate").cyan());
+        println!();
+
+        Ok(())
+    }
+
+    fn validate_audit_log(prefix: &str, page_size: u32, retention_days: u32) -> Result<()> {
+        if !Self::is_valid_prefix(prefix) {
+            bail!("Invalid prefix: '{prefix}'. Expected a path like '/admin/audit'");
+        }
+        if page_size == 0 {
+            bail!("Page size must be at least 1");
+        }
+        if !(1..=36_500).contains(&retention_days) {
+            bail!("Retention must be between 1 and 36500 days");
+        }
+        Ok(())
+    }
+
+    fn render_audit_log_handler(prefix: &str, page_size: u32, retention_days: u32) -> Result<String> {
+        let context = json!({
+            "route_prefix": prefix,
+            "page_size": page_size,
+            "retention_days": retention_days,
+        });
+
+        let mut env = Environment::new();
+        env.set_auto_escape_callback(|_| minijinja::AutoEscape::None);
+        env.set_keep_trailing_newline(true);
+
+        env.render_str(AUDIT_LOG_HANDLER_TEMPLATE, context)
+            .context("Failed to render audit log handler template")
+    }
+
+    /// Whether `prefix` is a mount path like `/admin/users`
+    fn is_valid_prefix(prefix: &str) -> bool {
+        prefix.len() > 1
+            && prefix.starts_with('/')
+            && !prefix.ends_with('/')
+            && prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_'))
+    }
+
+    /// Write generated files under `output`, refusing to overwrite any
+    ///
+    /// Every path is checked before anything is written, so a clash leaves
+    /// the project untouched.
+    fn write_files(output: &Path, files: &[(PathBuf, &str)]) -> Result<()> {
+        if let Some((relative, _)) = files
+            .iter()
+            .find(|(relative, _)| output.join(relative).exists())
+        {
+            bail!(
+                "Refusing to overwrite existing file: {}",
+                output.join(relative).display()
+            );
+        }
+        for (relative, contents) in files {
+            let file_path = output.join(relative);
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent).with_context(|| {
+                    format!("Failed to create directory: {}", parent.display())
+                })?;
+            }
+            fs::write(&file_path, contents)
+                .with_context(|| format!("Failed to write file: {}", file_path.display()))?;
+
+            println!(
+                "  {} Created: {}",
+                SUCCESS,
+                style(file_path.display()).green()
+            );
+        }
+        Ok(())
+    }
+
     fn get_project_name() -> Result<String> {
         // Try to read project name from Cargo.toml
         let cargo_toml = fs::read_to_string("Cargo.toml")
@@ -388,4 +806,192 @@ mod tests {
         let result = GenerateCommand::map_type("unsupported");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_validate_admin_users() {
+        let roles = vec!["user".to_string(), "admin".to_string()];
+        assert!(GenerateCommand::validate_admin_users("/admin/users", &roles, 25).is_ok());
+        assert!(GenerateCommand::validate_admin_users("admin", &roles, 25).is_err());
+        assert!(GenerateCommand::validate_admin_users("/admin/", &roles, 25).is_err());
+        assert!(GenerateCommand::validate_admin_users("/admin", &roles[..1], 25).is_err());
+        assert!(GenerateCommand::validate_admin_users("/admin", &["Admin".to_string()], 25).is_err());
+        assert!(GenerateCommand::validate_admin_users("/admin", &roles, 0).is_err());
+    }
+
+    #[test]
+    fn test_render_admin_users_handler() {
+        let roles = vec!["user".to_string(), "support".to_string(), "admin".to_string()];
+        let rendered =
+            GenerateCommand::render_admin_users_handler("/staff/users", &roles, 50).unwrap();
+        assert!(rendered.contains(r#"pub const BASE_PATH: &str = "/staff/users";"#));
+        assert!(rendered.contains(r#"&["user", "support", "admin"]"#));
+        assert!(rendered.contains("const PAGE_SIZE: i64 = 50;"));
+        assert!(!rendered.contains("{{"));
+    }
+
+    #[test]
+    fn test_validate_account() {
+        assert!(GenerateCommand::validate_account("/account", 24).is_ok());
+        assert!(GenerateCommand::validate_account("/settings/account", 1).is_ok());
+        assert!(GenerateCommand::validate_account("account", 24).is_err());
+        assert!(GenerateCommand::validate_account("/account/", 24).is_err());
+        assert!(GenerateCommand::validate_account("/account", 0).is_err());
+        assert!(GenerateCommand::validate_account("/account", 169).is_err());
+    }
+
+    #[test]
+    fn test_render_account_handler() {
+        let rendered = GenerateCommand::render_account_handler("/settings", 2).unwrap();
+        assert!(rendered.contains(r#"pub const BASE_PATH: &str = "/settings";"#));
+        assert!(rendered.contains("const EMAIL_CHANGE_HOURS: i32 = 2;"));
+        assert!(rendered.contains("/// GET /settings/email/confirm/{token}"));
+        assert!(!rendered.contains("{{"));
+    }
+
+    #[test]
+    fn test_generate_account_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        GenerateCommand::generate_account("/account", 24, dir.path()).unwrap();
+        assert!(dir.path().join("src/handlers/account.rs").exists());
+        assert!(dir.path().join("templates/account/_sessions.html").exists());
+
+        let profile = dir.path().join("templates/account/_profile.html");
+        fs::write(&profile, "customized").unwrap();
+        assert!(GenerateCommand::generate_account("/account", 24, dir.path()).is_err());
+        assert_eq!(fs::read_to_string(&profile).unwrap(), "customized");
+    }
+
+    /// Host crate the generated account module is compiled in
+    const ACCOUNT_HOST_MANIFEST: &str = r#"[package]
+name = "account-host"
+version = "0.1.0"
+edition = "2021"
+
+[workspace]
+
+[dependencies]
+acton-dx = { path = "{acton_dx}", features = ["microservices"] }
+askama = "0.14"
+axum = "0.8"
+serde = { version = "1", features = ["derive"] }
+sqlx = { version = "0.8", features = ["runtime-tokio", "postgres"] }
+tokio = { version = "1", features = ["full"] }
+tracing = "0.1"
+"#;
+
+    /// The `AppState` the generated account module expects from its crate
+    const ACCOUNT_HOST_LIB: &str = r#"use acton_dx::email::ConsoleBackend;
+use acton_dx::htmx::clients::AuthClient;
+use acton_dx::state::ActonHtmxState;
+use axum::extract::FromRef;
+use std::sync::Arc;
+
+pub mod handlers {
+    pub mod account;
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    htmx: ActonHtmxState,
+    auth: Arc<tokio::sync::Mutex<AuthClient>>,
+    db: sqlx::PgPool,
+    mailer: ConsoleBackend,
+}
+
+impl AppState {
+    pub fn auth(&self) -> &tokio::sync::Mutex<AuthClient> {
+        &self.auth
+    }
+
+    pub fn db(&self) -> &sqlx::PgPool {
+        &self.db
+    }
+
+    pub fn mailer(&self) -> &ConsoleBackend {
+        &self.mailer
+    }
+
+    pub fn base_url(&self) -> &str {
+        "http://localhost:3000"
+    }
+}
+
+impl FromRef<AppState> for ActonHtmxState {
+    fn from_ref(state: &AppState) -> Self {
+        state.htmx.clone()
+    }
+}
+"#;
+
+    #[test]
+    #[ignore = "builds a project against acton-dx, which takes minutes"]
+    fn test_generated_account_compiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            ACCOUNT_HOST_MANIFEST.replace("{acton_dx}", manifest_dir),
+        )
+        .unwrap();
+        // Pin the versions this workspace already builds with
+        let lockfile = Path::new(manifest_dir).join("../Cargo.lock");
+        if lockfile.exists() {
+            fs::copy(lockfile, dir.path().join("Cargo.lock")).unwrap();
+        }
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), ACCOUNT_HOST_LIB).unwrap();
+        // Stand-ins for the layout and passkey partial the app provides
+        for templates in ["templates/layouts", "templates/passkey"] {
+            fs::create_dir_all(dir.path().join(templates)).unwrap();
+        }
+        fs::write(
+            dir.path().join("templates/layouts/app.html"),
+            "<title>{% block title %}{% endblock %}</title>\n{% block content %}{% endblock %}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("templates/passkey/manage.html"),
+            "<section data-csrf-token=\"{{ csrf_token }}\"></section>\n",
+        )
+        .unwrap();
+        GenerateCommand::generate_account("/account", 24, dir.path()).unwrap();
+
+        // Build next to this test binary so the dependencies are reused
+        // between runs, but not under the target dir cargo holds locked
+        let target_dir = std::env::current_exe()
+            .unwrap()
+            .ancestors()
+            .nth(3)
+            .unwrap()
+            .join("generated-account");
+        let status = std::process::Command::new("cargo")
+            .args(["test", "--lib"])
+            .current_dir(dir.path())
+            .env("CARGO_TARGET_DIR", target_dir)
+            .status()
+            .unwrap();
+        assert!(
+            status.success(),
+            "generated account module failed to build or test"
+        );
+    }
+
+    #[test]
+    fn test_validate_audit_log() {
+        assert!(GenerateCommand::validate_audit_log("/admin/audit", 50, 365).is_ok());
+        assert!(GenerateCommand::validate_audit_log("admin/audit", 50, 365).is_err());
+        assert!(GenerateCommand::validate_audit_log("/admin/audit", 0, 365).is_err());
+        assert!(GenerateCommand::validate_audit_log("/admin/audit", 50, 0).is_err());
+        assert!(GenerateCommand::validate_audit_log("/admin/audit", 50, 36_501).is_err());
+    }
+
+    #[test]
+    fn test_render_audit_log_handler() {
+        let rendered = GenerateCommand::render_audit_log_handler("/staff/audit", 20, 90).unwrap();
+        assert!(rendered.contains(r#"pub const BASE_PATH: &str = "/staff/audit";"#));
+        assert!(rendered.contains("const PAGE_SIZE: i64 = 20;"));
+        assert!(rendered.contains("pub const RETENTION_DAYS: i32 = 90;"));
+        assert!(rendered.contains("/// GET /staff/audit/export.csv"));
+        assert!(!rendered.contains("{{"));
+    }
 }
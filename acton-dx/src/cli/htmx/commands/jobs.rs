@@ -3,19 +3,21 @@
 use anyhow::{Context, Result};
 use clap::Subcommand;
 use console::{style, Emoji};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::output::{self, OutputFormat};
 
 static SUCCESS: Emoji = Emoji("✓", "√");
 static INFO: Emoji = Emoji("ℹ", "i");
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 struct JobListResponse {
     jobs: Vec<JobInfo>,
     total: usize,
     message: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 struct JobInfo {
     id: String,
     job_type: String,
@@ -125,8 +127,9 @@ impl JobsCommand {
     /// - Failed to connect to job service
     /// - Failed to execute job operation
     /// - Invalid job ID provided
-    pub fn execute(&self) -> Result<()> {
+    pub fn execute(&self, output: OutputFormat) -> Result<()> {
         match self {
+            Self::List { .. } if output.is_json() => Self::list_json(),
             Self::List { status, limit } => {
                 Self::list(status.as_deref(), *limit);
                 Ok(())
@@ -225,6 +228,24 @@ impl JobsCommand {
         }
     }
 
+    /// Print the job list as JSON; unlike `list`, failures are errors
+    fn list_json() -> Result<()> {
+        let base_url = std::env::var("ACTON_HTMX_API_URL")
+            .unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let url = format!("{base_url}/admin/jobs/list");
+
+        let body = ureq::get(&url)
+            .call()
+            .with_context(|| format!("Failed to connect to API at {base_url}"))?
+            .into_body()
+            .read_to_string()
+            .context("Failed to read response")?;
+        let response: JobListResponse =
+            serde_json::from_str(&body).context("Failed to parse job list response")?;
+
+        output::print_json(&response)
+    }
+
     fn stats() {
         println!("\n{INFO} Job Statistics");
         println!();
@@ -511,7 +532,17 @@ mod tests {
         };
 
         // Should not panic
-        let _ = cmd.execute();
+        let _ = cmd.execute(OutputFormat::Human);
+    }
+
+    #[test]
+    fn test_job_list_round_trips_as_json() {
+        let body = r#"{"jobs":[{"id":"j1","job_type":"email","status":"pending","created_at":"2024-01-01T00:00:00Z","priority":5}],"total":1,"message":""}"#;
+        let response: JobListResponse = serde_json::from_str(body).unwrap();
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["total"], 1);
+        assert_eq!(json["jobs"][0]["job_type"], "email");
+        assert_eq!(json["jobs"][0]["priority"], 5);
     }
 
     #[test]
@@ -519,7 +550,7 @@ mod tests {
         let cmd = JobsCommand::Stats;
 
         // Should not panic
-        let _ = cmd.execute();
+        let _ = cmd.execute(OutputFormat::Human);
     }
 
     #[test]
@@ -529,6 +560,6 @@ mod tests {
         };
 
         // Should not panic
-        let _ = cmd.execute();
+        let _ = cmd.execute(OutputFormat::Human);
     }
 }
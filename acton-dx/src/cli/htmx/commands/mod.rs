@@ -8,9 +8,13 @@ pub mod jobs;
 pub mod new;
 pub mod oauth2;
 pub mod scaffold;
+#[cfg(feature = "microservices")]
+pub mod schema;
 pub mod serve;
 pub mod services;
 pub mod templates;
+#[cfg(feature = "postgres")]
+pub mod transfer;
 
 pub use db::DbCommand;
 pub use deploy::DeployCommand;
@@ -20,6 +24,10 @@ pub use jobs::JobsCommand;
 pub use new::NewCommand;
 pub use oauth2::OAuth2Command;
 pub use scaffold::ScaffoldCommand;
+#[cfg(feature = "microservices")]
+pub use schema::SchemaCommand;
 pub use serve::ServeCommand;
 pub use services::{ServiceName, ServicesCommand};
 pub use templates::TemplatesCommand;
+#[cfg(feature = "postgres")]
+pub use transfer::TransferCommand;
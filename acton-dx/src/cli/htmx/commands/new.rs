@@ -6,6 +6,7 @@ use indicatif::{ProgressBar, ProgressStyle};
 use std::fs;
 use std::path::PathBuf;
 
+use super::super::manifest::{ProjectManifest, MANIFEST_FILE};
 use super::super::{DatabaseBackend, ProjectTemplateManager};
 
 /// Create a new acton-dx project
@@ -141,6 +142,14 @@ impl NewCommand {
         // Generate project from templates
         template_manager.generate_project(&self.name, &self.output_dir, self.database)?;
 
+        // Describe the project for the other commands
+        let manifest_path = self.output_dir.join(MANIFEST_FILE);
+        fs::write(
+            &manifest_path,
+            ProjectManifest::for_new_project(&self.name, self.database).to_toml()?,
+        )
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
         Ok(())
     }
 
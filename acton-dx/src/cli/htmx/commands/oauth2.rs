@@ -204,23 +204,18 @@ OAUTH2_OIDC_USERINFO_URL="https://your-provider.com/oauth2/userinfo"
 
         let route_file = examples_dir.join(format!("{provider}_oauth_routes.rs"));
 
-        let route_content = format!(r#"//! Example OAuth2 routes for {}
+        let route_content = format!(r"//! Example OAuth2 routes for {}
 //!
 //! Add these routes to your main.rs router
 
-use axum::{{routing::{{get, post}}, Router}};
-use acton_htmx::oauth2::handlers::{{
-    initiate_oauth,
-    handle_oauth_callback,
-    unlink_oauth_account,
-}};
+use axum::Router;
+use acton_htmx::auth::oauth2;
 use acton_htmx::state::ActonHtmxState;
 
+/// Serves /auth/{}, /auth/{}/callback and /auth/{}/unlink, plus the
+/// routes of every other provider configured under [oauth2]
 pub fn {}_oauth_routes() -> Router<ActonHtmxState> {{
-    Router::new()
-        .route("/auth/{}", get(initiate_oauth))
-        .route("/auth/{}/callback", get(handle_oauth_callback))
-        .route("/auth/{}/unlink", post(unlink_oauth_account))
+    oauth2::routes()
 }}
 
 // In your main.rs:
@@ -231,7 +226,7 @@ pub fn {}_oauth_routes() -> Router<ActonHtmxState> {{
 //     .merge({}_oauth_routes())
 //     // ... other routes
 //     .with_state(state);
-"#,
+",
             provider.to_uppercase(),
             provider,
             provider,
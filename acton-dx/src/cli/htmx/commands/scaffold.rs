@@ -34,13 +34,54 @@ pub struct ScaffoldCommand {
     model: String,
     /// Field definitions (e.g., `title:string`, `author:references:User`)
     fields: Vec<String>,
+    /// Existing table to derive fields from, and the data service endpoint
+    #[cfg(feature = "microservices")]
+    from_table: Option<(String, Option<String>)>,
 }
 
 impl ScaffoldCommand {
     /// Create a new ScaffoldCommand with the given model name and field definitions
     #[must_use]
     pub const fn new(model: String, fields: Vec<String>) -> Self {
-        Self { model, fields }
+        Self {
+            model,
+            fields,
+            #[cfg(feature = "microservices")]
+            from_table: None,
+        }
+    }
+
+    /// Derive fields from an existing table's live schema
+    ///
+    /// Explicit fields are appended to the derived ones, and no migration is
+    /// generated since the table already exists.
+    #[cfg(feature = "microservices")]
+    #[must_use]
+    pub fn with_from_table(mut self, table: String, endpoint: Option<String>) -> Self {
+        self.from_table = Some((table, endpoint));
+        self
+    }
+
+    /// Field definitions, including any derived from `--from-table`
+    #[cfg(feature = "microservices")]
+    fn resolve_fields(&self) -> Result<Vec<String>> {
+        if let Some((table, endpoint)) = &self.from_table {
+            let endpoint = super::schema::resolve_endpoint(endpoint.as_deref());
+            let (_, tables) = super::schema::fetch_schema(&endpoint, vec![table.clone()])?;
+            let schema = tables
+                .first()
+                .with_context(|| format!("Table {table} not found in the live schema"))?;
+            let mut fields = super::schema::field_specs(schema);
+            println!(
+                "  {} {} fields from table {}",
+                style("Read").cyan(),
+                fields.len(),
+                style(table).green()
+            );
+            fields.extend(self.fields.iter().cloned());
+            return Ok(fields);
+        }
+        Ok(self.fields.clone())
     }
 
     /// Execute the scaffold command
@@ -64,9 +105,13 @@ impl ScaffoldCommand {
             .context("Failed to get current directory")?;
 
         // Create generator
+        #[cfg(feature = "microservices")]
+        let fields = self.resolve_fields()?;
+        #[cfg(not(feature = "microservices"))]
+        let fields = self.fields.clone();
         let generator = ScaffoldGenerator::new(
             self.model.clone(),
-            &self.fields,
+            &fields,
             project_root.clone(),
         )
         .context("Failed to create scaffold generator")?;
@@ -75,6 +120,13 @@ impl ScaffoldCommand {
         let files = generator.generate()
             .context("Failed to generate scaffold files")?;
 
+        // A table read with --from-table already exists, so skip its migration
+        #[cfg(feature = "microservices")]
+        let files: Vec<_> = files
+            .into_iter()
+            .filter(|file| self.from_table.is_none() || !file.path.starts_with("migrations"))
+            .collect();
+
         println!(
             "\n{} {} files:",
             style("Generated").green().bold(),
@@ -118,12 +170,18 @@ impl ScaffoldCommand {
         println!("     {}", style(format!("src/forms/mod.rs: pub mod {model_snake};")).yellow());
         println!("     {}", style(format!("src/handlers/mod.rs: pub mod {plural};")).yellow());
         println!("  2. Run the migration: {}", style("acton htmx db migrate").yellow());
-        println!("  3. Add routes to your router:");
-        println!("     {}", style(format!(".route(\"{route_path}\", get(handlers::{plural}::list).post(handlers::{plural}::create))", route_path = TemplateHelpers::to_route_path(&self.model))).yellow());
-        println!("     {}", style(format!(".route(\"{route_path}/new\", get(handlers::{plural}::new))", route_path = TemplateHelpers::to_route_path(&self.model))).yellow());
-        println!("     {}", style(format!(".route(\"{route_path}/:id\", get(handlers::{plural}::show).put(handlers::{plural}::update).delete(handlers::{plural}::delete))", route_path = TemplateHelpers::to_route_path(&self.model))).yellow());
-        println!("     {}", style(format!(".route(\"{route_path}/:id/edit\", get(handlers::{plural}::edit))", route_path = TemplateHelpers::to_route_path(&self.model))).yellow());
-        println!("     {}", style(format!(".route(\"{route_path}/search\", get(handlers::{plural}::search))", route_path = TemplateHelpers::to_route_path(&self.model))).yellow());
+        println!("  3. Mount the routes in your router:");
+        println!("     {}", style(format!(".merge(handlers::{plural}::routes())")).yellow());
+        println!("     In development, mount them in a RouteTable instead to re-mount them");
+        println!("     as the module changes, without restarting the server:");
+        println!(
+            "     {}",
+            style(format!(
+                "table.mount(ModuleRoutes::new(\"{plural}\", \"{route_path}\", factory).with_sources([\"src/handlers/{plural}.rs\", \"templates/{plural}\"]))",
+                route_path = TemplateHelpers::to_route_path(&self.model)
+            ))
+            .yellow()
+        );
         println!("  4. Test your application: {}", style("cargo test").yellow());
 
         Ok(())
@@ -0,0 +1,653 @@
+//! Live database schema inspection
+//!
+//! Reads the schema from data-service's `DescribeSchema` RPC to print it,
+//! diff it against the tables the migrations create, or derive scaffold
+//! field definitions from an existing table.
+
+use super::super::scaffold::TemplateHelpers;
+use crate::htmx::clients::{DataClient, TableSchema};
+use anyhow::{Context, Result};
+use console::style;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Data service endpoint used when neither `--endpoint` nor
+/// `ACTON_DATA_SERVICE_URL` is set
+pub const DEFAULT_DATA_ENDPOINT: &str = "http://localhost:50052";
+
+/// Columns every scaffolded model already defines
+const SCAFFOLD_MANAGED_COLUMNS: [&str; 3] = ["id", "created_at", "updated_at"];
+
+/// `acton-dx htmx db schema`
+pub struct SchemaCommand {
+    /// Tables to show (all when empty)
+    pub tables: Vec<String>,
+    /// Diff against migrations instead of printing
+    pub diff: bool,
+    /// Migrations directory
+    pub migrations: PathBuf,
+    /// Data service endpoint
+    pub endpoint: Option<String>,
+}
+
+impl SchemaCommand {
+    /// Execute the command
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data service is unreachable, the migrations
+    /// cannot be read, or `--diff` finds drift.
+    pub fn execute(&self) -> Result<()> {
+        let endpoint = resolve_endpoint(self.endpoint.as_deref());
+        let (dialect, tables) = fetch_schema(&endpoint, self.tables.clone())?;
+
+        if !self.diff {
+            print_schema(&dialect, &tables);
+            return Ok(());
+        }
+
+        let mut expected = MigrationSchema::from_dir(&self.migrations)?;
+        if !self.tables.is_empty() {
+            expected.tables.retain(|name, _| self.tables.contains(name));
+        }
+        let drift = expected.diff(&tables);
+        if drift.is_empty() {
+            println!(
+                "{}",
+                style("✓ Live schema matches migrations").green().bold()
+            );
+            return Ok(());
+        }
+
+        println!(
+            "{} {}",
+            style("Schema drift").yellow().bold(),
+            style(format!("against {}:", self.migrations.display())).bold()
+        );
+        for item in &drift {
+            println!("  {item}");
+        }
+        anyhow::bail!("{} schema difference(s) found", drift.len())
+    }
+}
+
+/// Endpoint from the flag, `ACTON_DATA_SERVICE_URL`, or the default
+#[must_use]
+pub fn resolve_endpoint(endpoint: Option<&str>) -> String {
+    endpoint.map_or_else(
+        || {
+            std::env::var("ACTON_DATA_SERVICE_URL")
+                .unwrap_or_else(|_| DEFAULT_DATA_ENDPOINT.to_string())
+        },
+        str::to_string,
+    )
+}
+
+/// Fetch the live schema from data-service, returning the dialect and tables
+///
+/// # Errors
+///
+/// Returns an error if the service is unreachable or the call fails.
+pub fn fetch_schema(endpoint: &str, tables: Vec<String>) -> Result<(String, Vec<TableSchema>)> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start async runtime")?;
+    runtime.block_on(async {
+        let mut client = DataClient::connect(endpoint)
+            .await
+            .with_context(|| format!("Failed to connect to data service at {endpoint}"))?;
+        client
+            .describe_schema(tables)
+            .await
+            .context("Failed to describe schema")
+    })
+}
+
+fn print_schema(dialect: &str, tables: &[TableSchema]) {
+    println!(
+        "{} {}",
+        style("Schema").cyan().bold(),
+        style(format!("({dialect}, {} tables)", tables.len())).dim()
+    );
+    for table in tables {
+        println!();
+        println!("{}", style(&table.name).green().bold());
+        let width = table
+            .columns
+            .iter()
+            .map(|c| c.name.len())
+            .max()
+            .unwrap_or(0);
+        for column in &table.columns {
+            let mut attributes = Vec::new();
+            if column.primary_key {
+                attributes.push("PRIMARY KEY".to_string());
+            }
+            if !column.nullable {
+                attributes.push("NOT NULL".to_string());
+            }
+            if let Some(default) = &column.default_value {
+                attributes.push(format!("DEFAULT {default}"));
+            }
+            println!(
+                "  {:width$}  {}  {}",
+                column.name,
+                style(&column.data_type).cyan(),
+                style(attributes.join(" ")).dim()
+            );
+        }
+        for index in &table.indexes {
+            let kind = if index.unique {
+                "unique index"
+            } else {
+                "index"
+            };
+            println!(
+                "  {} {} ({})",
+                style(kind).dim(),
+                index.name,
+                index.columns.join(", ")
+            );
+        }
+        for key in &table.foreign_keys {
+            println!(
+                "  {} ({}) → {}({}) on delete {}",
+                style("foreign key").dim(),
+                key.columns.join(", "),
+                key.referenced_table,
+                key.referenced_columns.join(", "),
+                key.on_delete.to_lowercase()
+            );
+        }
+    }
+}
+
+/// A difference between the live schema and the migrations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    /// A migration creates the table but the database lacks it
+    MissingTable(String),
+    /// The database has a table no migration creates
+    UnexpectedTable(String),
+    /// A migration adds the column but the database lacks it
+    MissingColumn {
+        /// Table name
+        table: String,
+        /// Column name
+        column: String,
+    },
+    /// The database has a column no migration adds
+    UnexpectedColumn {
+        /// Table name
+        table: String,
+        /// Column name
+        column: String,
+    },
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTable(table) => write!(f, "- table {table} is missing from the database"),
+            Self::UnexpectedTable(table) => {
+                write!(f, "+ table {table} exists but no migration creates it")
+            }
+            Self::MissingColumn { table, column } => {
+                write!(f, "- column {table}.{column} is missing from the database")
+            }
+            Self::UnexpectedColumn { table, column } => {
+                write!(
+                    f,
+                    "+ column {table}.{column} exists but no migration adds it"
+                )
+            }
+        }
+    }
+}
+
+/// Tables and columns the migrations produce, replayed from their SQL
+///
+/// Understands `CREATE TABLE`, `DROP TABLE`, and `ALTER TABLE` with
+/// `ADD`/`DROP`/`RENAME COLUMN` and `RENAME TO`; anything else (data
+/// changes, functions, triggers) is ignored. Names are compared lowercased.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationSchema {
+    /// Columns by table, in creation order
+    pub tables: BTreeMap<String, Vec<String>>,
+}
+
+impl MigrationSchema {
+    /// Replay every `*.sql` migration in `dir` in filename order
+    ///
+    /// Down migrations (`*.down.sql`) are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or a migration cannot be read.
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read migrations in {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+                    && !name.to_lowercase().ends_with(".down.sql")
+            })
+            .collect();
+        paths.sort();
+
+        let mut schema = Self::default();
+        for path in paths {
+            let sql = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            schema.apply(&sql);
+        }
+        Ok(schema)
+    }
+
+    /// Apply the DDL in `sql`
+    pub fn apply(&mut self, sql: &str) {
+        for statement in split_top_level(sql, ';') {
+            let lowered = statement.to_lowercase();
+            let tokens: Vec<&str> = lowered.split_whitespace().collect();
+            match tokens.as_slice() {
+                ["create", rest @ ..] => self.create(&lowered, rest),
+                ["drop", "table", rest @ ..] => {
+                    for name in skip_words(rest, &["if", "exists"]).join(" ").split(',') {
+                        if let Some(name) = identifier(name) {
+                            self.tables.remove(&name);
+                        }
+                    }
+                }
+                ["alter", "table", rest @ ..] => self.alter(rest),
+                _ => {}
+            }
+        }
+    }
+
+    fn create(&mut self, statement: &str, tokens: &[&str]) {
+        let tokens = skip_words(tokens, &["temporary", "temp", "unlogged"]);
+        let Some(("table", rest)) = tokens.split_first().map(|(f, r)| (*f, r)) else {
+            return;
+        };
+        let Some(name) = skip_words(rest, &["if", "not", "exists"])
+            .first()
+            .and_then(|name| identifier(name.split('(').next().unwrap_or(name)))
+        else {
+            return;
+        };
+        let Some(body) = parenthesized(statement) else {
+            return;
+        };
+        let columns = split_top_level(body, ',')
+            .into_iter()
+            .filter_map(|item| {
+                let first = item.split_whitespace().next()?;
+                if is_constraint_keyword(first) {
+                    None
+                } else {
+                    identifier(first)
+                }
+            })
+            .collect();
+        self.tables.insert(name, columns);
+    }
+
+    fn alter(&mut self, tokens: &[&str]) {
+        let tokens = skip_words(tokens, &["if", "exists", "only"]);
+        let Some((name, actions)) = tokens.split_first() else {
+            return;
+        };
+        let Some(mut table) = identifier(name) else {
+            return;
+        };
+        for action in split_top_level(&actions.join(" "), ',') {
+            let words: Vec<&str> = action.split_whitespace().collect();
+            let Some(columns) = self.tables.get_mut(&table) else {
+                return;
+            };
+            match words.as_slice() {
+                ["rename", "to", new_name, ..] => {
+                    if let Some(new_name) = identifier(new_name) {
+                        let columns = self.tables.remove(&table).unwrap_or_default();
+                        self.tables.insert(new_name.clone(), columns);
+                        table = new_name;
+                    }
+                }
+                ["rename", rest @ ..] => {
+                    if let [from, "to", to, ..] = skip_words(rest, &["column"]) {
+                        if let (Some(from), Some(to)) = (identifier(from), identifier(to)) {
+                            for column in columns.iter_mut().filter(|c| **c == from) {
+                                column.clone_from(&to);
+                            }
+                        }
+                    }
+                }
+                ["add", rest @ ..] => {
+                    let rest = skip_words(rest, &["column", "if", "not", "exists"]);
+                    if let Some(column) = rest
+                        .first()
+                        .filter(|word| !is_constraint_keyword(word))
+                        .and_then(|word| identifier(word))
+                    {
+                        if !columns.contains(&column) {
+                            columns.push(column);
+                        }
+                    }
+                }
+                ["drop", rest @ ..] => {
+                    let rest = skip_words(rest, &["column", "if", "exists"]);
+                    if let Some(column) = rest
+                        .first()
+                        .filter(|word| !is_constraint_keyword(word))
+                        .and_then(|word| identifier(word))
+                    {
+                        columns.retain(|c| *c != column);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Compare against the live tables
+    ///
+    /// sqlx's own `_sqlx_migrations` bookkeeping table is ignored.
+    #[must_use]
+    pub fn diff(&self, live: &[TableSchema]) -> Vec<Drift> {
+        let live: BTreeMap<String, Vec<String>> = live
+            .iter()
+            .filter(|table| !table.name.starts_with("_sqlx"))
+            .map(|table| {
+                let columns = table
+                    .columns
+                    .iter()
+                    .map(|c| c.name.to_lowercase())
+                    .collect();
+                (table.name.to_lowercase(), columns)
+            })
+            .collect();
+
+        let mut drift = Vec::new();
+        for (table, expected) in &self.tables {
+            let Some(actual) = live.get(table) else {
+                drift.push(Drift::MissingTable(table.clone()));
+                continue;
+            };
+            for column in expected.iter().filter(|c| !actual.contains(c)) {
+                drift.push(Drift::MissingColumn {
+                    table: table.clone(),
+                    column: column.clone(),
+                });
+            }
+            for column in actual.iter().filter(|c| !expected.contains(c)) {
+                drift.push(Drift::UnexpectedColumn {
+                    table: table.clone(),
+                    column: column.clone(),
+                });
+            }
+        }
+        for table in live.keys().filter(|t| !self.tables.contains_key(*t)) {
+            drift.push(Drift::UnexpectedTable(table.clone()));
+        }
+        drift
+    }
+}
+
+/// Scaffold field definitions (`name:type[:modifier]*`) for a live table
+///
+/// Columns the generated model already has (`id`, `created_at`,
+/// `updated_at`) are skipped, single-column foreign keys become
+/// `references:Model`, and nullability and single-column indexes carry over
+/// as `optional`, `unique` and `indexed`.
+#[must_use]
+pub fn field_specs(table: &TableSchema) -> Vec<String> {
+    table
+        .columns
+        .iter()
+        .filter(|column| !column.primary_key)
+        .filter(|column| !SCAFFOLD_MANAGED_COLUMNS.contains(&column.name.as_str()))
+        .map(|column| {
+            let reference = table
+                .foreign_keys
+                .iter()
+                .find(|key| key.columns.len() == 1 && key.columns[0] == column.name);
+            let (name, field_type) = reference.map_or_else(
+                || {
+                    (
+                        column.name.clone(),
+                        field_type(&column.data_type).to_string(),
+                    )
+                },
+                |key| {
+                    let model = TemplateHelpers::to_pascal_case(&TemplateHelpers::singularize(
+                        &key.referenced_table,
+                    ));
+                    let name = column.name.strip_suffix("_id").unwrap_or(&column.name);
+                    (name.to_string(), format!("references:{model}"))
+                },
+            );
+
+            let mut spec = format!("{name}:{field_type}");
+            if column.nullable {
+                spec.push_str(":optional");
+            }
+            let index = table
+                .indexes
+                .iter()
+                .find(|index| index.columns.len() == 1 && index.columns[0] == column.name);
+            match index {
+                Some(index) if index.unique => spec.push_str(":unique"),
+                Some(_) if reference.is_none() => spec.push_str(":indexed"),
+                _ => {}
+            }
+            spec
+        })
+        .collect()
+}
+
+/// Scaffold field type for a database column type
+fn field_type(data_type: &str) -> &'static str {
+    let data_type = data_type.to_lowercase();
+    let base = data_type.split('(').next().unwrap_or(&data_type).trim();
+    match base {
+        "text" | "clob" => "text",
+        "bigint" | "int8" | "bigserial" => "bigint",
+        "integer" | "int" | "int4" | "int2" | "smallint" | "serial" | "smallserial" => "integer",
+        "boolean" | "bool" => "boolean",
+        "real" | "float4" | "float" => "float",
+        "double precision" | "double" | "float8" => "double",
+        "numeric" | "decimal" => "decimal",
+        "date" => "date",
+        "timestamp with time zone" | "timestamptz" => "timestamp",
+        "timestamp" | "timestamp without time zone" | "datetime" => "datetime",
+        "json" | "jsonb" => "json",
+        "uuid" => "uuid",
+        _ => "string",
+    }
+}
+
+/// Split on `separator` outside parentheses and quotes
+fn split_top_level(input: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0_usize;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, c) if c == separator && depth == 0 => {
+                parts.push(input[start..i].trim());
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(input[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Contents of the first top-level parenthesized group
+fn parenthesized(statement: &str) -> Option<&str> {
+    let open = statement.find('(')?;
+    let mut depth = 0_usize;
+    for (i, c) in statement[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&statement[open + 1..open + i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn skip_words<'a, 'b>(tokens: &'b [&'a str], words: &[&str]) -> &'b [&'a str] {
+    let mut tokens = tokens;
+    while let Some((first, rest)) = tokens.split_first() {
+        if !words.contains(first) {
+            break;
+        }
+        tokens = rest;
+    }
+    tokens
+}
+
+fn is_constraint_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "constraint" | "primary" | "foreign" | "unique" | "check" | "exclude" | "key" | "index"
+    )
+}
+
+/// Normalize a possibly schema-qualified, quoted identifier
+fn identifier(token: &str) -> Option<String> {
+    let token = token.trim();
+    let name = token.rsplit('.').next().unwrap_or(token);
+    let name = name.trim_matches(|c| c == '"' || c == '`' || c == '[' || c == ']');
+    (!name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_'))
+        .then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::clients::{ColumnSchema, ForeignKeySchema, IndexSchema};
+
+    fn column(name: &str, data_type: &str, nullable: bool) -> ColumnSchema {
+        ColumnSchema {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable,
+            default_value: None,
+            primary_key: name == "id",
+        }
+    }
+
+    fn posts() -> TableSchema {
+        TableSchema {
+            name: "posts".to_string(),
+            columns: vec![
+                column("id", "bigint", false),
+                column("title", "character varying", false),
+                column("slug", "text", false),
+                column("author_id", "bigint", false),
+                column("published_at", "timestamp with time zone", true),
+                column("created_at", "timestamp with time zone", false),
+            ],
+            indexes: vec![IndexSchema {
+                name: "posts_slug_key".to_string(),
+                columns: vec!["slug".to_string()],
+                unique: true,
+            }],
+            foreign_keys: vec![ForeignKeySchema {
+                columns: vec!["author_id".to_string()],
+                referenced_table: "users".to_string(),
+                referenced_columns: vec!["id".to_string()],
+                on_delete: "CASCADE".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_migration_replay() {
+        let mut schema = MigrationSchema::default();
+        schema.apply(
+            r#"CREATE TABLE IF NOT EXISTS public."Posts" (
+                id BIGSERIAL PRIMARY KEY,
+                title VARCHAR(255) NOT NULL DEFAULT 'a, b',
+                body TEXT,
+                CONSTRAINT title_len CHECK (length(title) > 0),
+                UNIQUE (title)
+            );
+            CREATE INDEX idx_posts_title ON posts (title);
+            CREATE TABLE drafts (id INTEGER);
+            ALTER TABLE posts ADD COLUMN slug TEXT, DROP COLUMN body;
+            ALTER TABLE posts RENAME COLUMN title TO headline;
+            ALTER TABLE drafts RENAME TO scratch;
+            DROP TABLE IF EXISTS scratch;"#,
+        );
+        assert_eq!(
+            schema.tables,
+            BTreeMap::from([(
+                "posts".to_string(),
+                vec!["id".to_string(), "headline".to_string(), "slug".to_string()]
+            )])
+        );
+    }
+
+    #[test]
+    fn test_diff_reports_drift() {
+        let mut schema = MigrationSchema::default();
+        schema.apply(
+            "CREATE TABLE posts (id BIGINT, title TEXT, slug TEXT, author_id BIGINT, \
+             published_at TIMESTAMPTZ, created_at TIMESTAMPTZ, summary TEXT);
+             CREATE TABLE tags (id BIGINT);",
+        );
+        let mut live = vec![posts()];
+        live[0].columns.retain(|c| c.name != "published_at");
+        live[0].columns.push(column("legacy_flag", "boolean", true));
+        live.push(TableSchema {
+            name: "_sqlx_migrations".to_string(),
+            columns: vec![column("version", "bigint", false)],
+            indexes: Vec::new(),
+            foreign_keys: Vec::new(),
+        });
+
+        let drift: Vec<String> = schema.diff(&live).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            drift,
+            [
+                "- column posts.published_at is missing from the database",
+                "- column posts.summary is missing from the database",
+                "+ column posts.legacy_flag exists but no migration adds it",
+                "- table tags is missing from the database",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_field_specs_from_table() {
+        assert_eq!(
+            field_specs(&posts()),
+            [
+                "title:string",
+                "slug:text:unique",
+                "author:references:User",
+                "published_at:timestamp:optional",
+            ]
+        );
+    }
+}
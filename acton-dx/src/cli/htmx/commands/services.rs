@@ -5,17 +5,26 @@
 //! - `stop` - Stop one or more services
 //! - `status` - Show status of all services
 //! - `logs` - View service logs
+//! - `migrate-sessions` - Copy embedded sessions into the auth service
 
 use anyhow::{Context, Result};
-use clap::Subcommand;
+use chrono::{DateTime, Utc};
+use clap::{Subcommand, ValueEnum};
 use console::{style, Emoji};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
+use std::io::BufRead;
 use std::path::PathBuf;
 use std::process::{Child, Stdio};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use crate::cli::output::{self, OutputFormat};
+use super::super::manifest::ProjectManifest;
+use super::super::process::{self, LogFollower};
+use super::super::service_log::{self, LogFilter, LogOptions, RotatingLog};
+
 static SUCCESS: Emoji<'_, '_> = Emoji("✓", "√");
 static ERROR: Emoji<'_, '_> = Emoji("✗", "x");
 static INFO: Emoji<'_, '_> = Emoji("ℹ", "i");
@@ -23,8 +32,17 @@ static RUNNING: Emoji<'_, '_> = Emoji("🟢", "[+]");
 static STOPPED: Emoji<'_, '_> = Emoji("🔴", "[-]");
 static STARTING: Emoji<'_, '_> = Emoji("🟡", "[~]");
 
+/// How long a service gets to shut down before it is killed
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How often `logs --follow` checks the log file for new lines
+const LOG_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
 /// Available microservices
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, clap::ValueEnum, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum ServiceName {
     /// Authentication service (sessions, passwords, CSRF)
     Auth,
@@ -41,6 +59,19 @@ pub enum ServiceName {
 }
 
 impl ServiceName {
+    /// Get the short name for this service, as written on the command line
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::Data => "data",
+            Self::Cedar => "cedar",
+            Self::Cache => "cache",
+            Self::Email => "email",
+            Self::File => "file",
+        }
+    }
+
     /// Get the binary name for this service
     #[must_use]
     pub const fn binary_name(&self) -> &'static str {
@@ -113,13 +144,27 @@ struct ServiceProcess {
 static SERVICE_MANAGER: std::sync::LazyLock<Arc<Mutex<HashMap<ServiceName, ServiceProcess>>>> =
     std::sync::LazyLock::new(|| Arc::new(Mutex::new(HashMap::new())));
 
+/// A service's state, as printed by `status --output json`
+#[derive(Debug, Serialize)]
+struct ServiceStatus {
+    service: &'static str,
+    binary: &'static str,
+    port: u16,
+    running: bool,
+    pid: Option<u32>,
+}
+
+/// Parse `logs --since`, relative to the current time
+fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    service_log::parse_since(value, Utc::now())
+}
+
 /// Services management commands
 #[derive(Debug, Subcommand)]
 pub enum ServicesCommand {
     /// Start one or more microservices
     Start {
-        /// Services to start (auth, data, cedar, cache, email, file)
-        #[arg(required = true)]
+        /// Services to start (auth, data, cedar, cache, email, file; default: `services.enabled` in acton.toml)
         services: Vec<ServiceName>,
 
         /// Build services before starting (runs cargo build)
@@ -129,6 +174,10 @@ pub enum ServicesCommand {
         /// Run in foreground (don't daemonize)
         #[arg(long, short)]
         foreground: bool,
+
+        /// Log settings for services started in the background
+        #[command(flatten)]
+        log: LogOptions,
     },
 
     /// Stop one or more microservices
@@ -156,13 +205,64 @@ pub enum ServicesCommand {
         /// Number of lines to show
         #[arg(long, short, default_value = "50")]
         lines: usize,
+
+        /// Only show lines written since this time (e.g. 30m, 2h, 7d, 2024-01-31)
+        #[arg(long, value_parser = parse_since)]
+        since: Option<DateTime<Utc>>,
+
+        /// Only show lines matching this regular expression
+        #[arg(long, value_parser = Regex::new)]
+        grep: Option<Regex>,
     },
 
     /// Restart one or more services
     Restart {
-        /// Services to restart
-        #[arg(required = true)]
+        /// Services to restart (default: `services.enabled` in acton.toml)
         services: Vec<ServiceName>,
+
+        /// Log settings for the restarted services
+        #[command(flatten)]
+        log: LogOptions,
+    },
+
+    /// Write a service's output, read from stdin, to its rotating log file
+    ///
+    /// Started by `start` for each background service.
+    #[command(hide = true)]
+    WriteLog {
+        /// Service whose output is written
+        service: ServiceName,
+
+        /// Log settings
+        #[command(flatten)]
+        log: LogOptions,
+    },
+
+    /// Copy sessions from an application in embedded mode into the auth service
+    ///
+    /// Run while the application dual-writes sessions
+    /// (`session_migration.dual_write`), then switch it to microservices mode.
+    #[cfg(feature = "microservices")]
+    MigrateSessions {
+        /// Application URL (default: `$ACTON_HTMX_API_URL` or <http://localhost:3000>)
+        #[arg(long)]
+        app_url: Option<String>,
+
+        /// Token from `session_migration.export_tokens` (default: `$ACTON_SESSION_EXPORT_TOKEN`)
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Auth service endpoint (default: <http://localhost:50051>)
+        #[arg(long)]
+        auth_endpoint: Option<String>,
+
+        /// Replace sessions the auth service already holds
+        #[arg(long)]
+        replace: bool,
+
+        /// Count the sessions to migrate without importing them
+        #[arg(long)]
+        dry_run: bool,
     },
 }
 
@@ -175,28 +275,159 @@ impl ServicesCommand {
     /// - Failed to start/stop service processes
     /// - Failed to connect to services
     /// - Invalid service name provided
-    pub fn execute(&self) -> Result<()> {
+    ///
+    /// Services and ports not given on the command line are read from
+    /// `manifest`.
+    pub fn execute(&self, output: OutputFormat, manifest: &ProjectManifest) -> Result<()> {
         match self {
             Self::Start {
                 services,
                 build,
                 foreground,
-            } => Self::start(services, *build, *foreground),
+                log,
+            } => Self::start(
+                &Self::services_or_enabled(services, manifest)?,
+                *build,
+                *foreground,
+                log,
+                manifest,
+            ),
             Self::Stop { services, all } => Self::stop(services, *all),
+            Self::Status if output.is_json() => {
+                output::print_json(&Self::service_statuses(manifest))
+            }
             Self::Status => {
-                Self::status();
+                Self::status(manifest);
                 Ok(())
             }
             Self::Logs {
                 service,
                 follow,
                 lines,
-            } => Self::logs(*service, *follow, *lines),
-            Self::Restart { services } => Self::restart(services),
+                since,
+                grep,
+            } => Self::logs(
+                *service,
+                *follow,
+                *lines,
+                LogFilter::new(*since, grep.clone()),
+            ),
+            Self::Restart { services, log } => Self::restart(
+                &Self::services_or_enabled(services, manifest)?,
+                log,
+                manifest,
+            ),
+            Self::WriteLog { service, log } => Self::write_log(*service, log),
+            #[cfg(feature = "microservices")]
+            Self::MigrateSessions {
+                app_url,
+                token,
+                auth_endpoint,
+                replace,
+                dry_run,
+            } => Self::migrate_sessions(
+                app_url.as_deref(),
+                token.as_deref(),
+                auth_endpoint.as_deref(),
+                *replace,
+                *dry_run,
+            ),
+        }
+    }
+
+    #[cfg(feature = "microservices")]
+    fn migrate_sessions(
+        app_url: Option<&str>,
+        token: Option<&str>,
+        auth_endpoint: Option<&str>,
+        replace: bool,
+        dry_run: bool,
+    ) -> Result<()> {
+        use crate::htmx::auth::session_migration::{session_record, ExportedSession, EXPORT_PATH};
+        use crate::htmx::clients::AuthClient;
+
+        let app_url = app_url.map_or_else(
+            || {
+                std::env::var("ACTON_HTMX_API_URL")
+                    .unwrap_or_else(|_| "http://localhost:3000".to_string())
+            },
+            str::to_string,
+        );
+        let token = token
+            .map(str::to_string)
+            .or_else(|| std::env::var("ACTON_SESSION_EXPORT_TOKEN").ok())
+            .context("An export token is required: pass --token or set ACTON_SESSION_EXPORT_TOKEN")?;
+        let auth_endpoint = auth_endpoint.map_or_else(
+            || format!("http://localhost:{}", ServiceName::Auth.default_port()),
+            str::to_string,
+        );
+
+        println!("\n{INFO} Exporting sessions from {}...", style(&app_url).cyan());
+        let url = format!("{}{EXPORT_PATH}", app_url.trim_end_matches('/'));
+        let response = ureq::get(&url)
+            .header("Authorization", format!("Bearer {token}"))
+            .call()
+            .with_context(|| format!("Failed to export sessions from {url}"))?;
+        let sessions: Vec<ExportedSession> =
+            serde_json::from_reader(response.into_body().into_reader())
+                .context("Failed to parse exported sessions")?;
+        println!("  {SUCCESS} {} session(s) exported", sessions.len());
+
+        if dry_run {
+            println!("\n{INFO} Dry run, nothing imported");
+            return Ok(());
+        }
+
+        println!("\n{INFO} Importing into {}...", style(&auth_endpoint).cyan());
+        let records = sessions.iter().map(session_record).collect();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to start async runtime")?;
+        let response = runtime.block_on(async {
+            let mut client = AuthClient::connect(auth_endpoint.clone())
+                .await
+                .with_context(|| format!("Failed to connect to auth service at {auth_endpoint}"))?;
+            client
+                .import_sessions(records, replace)
+                .await
+                .context("Failed to import sessions")
+        })?;
+
+        println!("  {SUCCESS} {} session(s) imported", response.imported);
+        if response.skipped > 0 {
+            println!(
+                "  {INFO} {} session(s) skipped (expired or already in the auth service)",
+                response.skipped
+            );
         }
+        println!(
+            "\n{INFO} Switch the application to microservices mode to finish the migration"
+        );
+        Ok(())
     }
 
-    fn start(services: &[ServiceName], build: bool, foreground: bool) -> Result<()> {
+    /// The services named, or those enabled in `acton.toml` if none are
+    fn services_or_enabled(
+        services: &[ServiceName],
+        manifest: &ProjectManifest,
+    ) -> Result<Vec<ServiceName>> {
+        if !services.is_empty() {
+            return Ok(services.to_vec());
+        }
+        if manifest.services.enabled.is_empty() {
+            anyhow::bail!("Name the services, or list them in `services.enabled` in acton.toml");
+        }
+        Ok(manifest.services.enabled.clone())
+    }
+
+    fn start(
+        services: &[ServiceName],
+        build: bool,
+        foreground: bool,
+        log: &LogOptions,
+        manifest: &ProjectManifest,
+    ) -> Result<()> {
         println!("\n{INFO} Starting services...");
         println!();
 
@@ -221,10 +452,10 @@ impl ServicesCommand {
         // Start each service
         for service in services {
             let binary = service.binary_name();
-            let port = service.default_port();
+            let port = manifest.service_port(*service);
 
             // Check if already running
-            if Self::is_service_running(*service) {
+            if Self::is_service_running(*service, port) {
                 println!(
                     "  {INFO} {} already running on port {port}",
                     style(service.display_name()).cyan(),
@@ -245,14 +476,14 @@ impl ServicesCommand {
                 Self::run_foreground(&binary_path, port)?;
             } else {
                 // Start as background process
-                Self::start_background(*service, &binary_path, port)?;
+                Self::start_background(*service, &binary_path, port, log)?;
             }
         }
 
         // Show status after starting
         if !foreground || services.len() > 1 {
             println!();
-            Self::status();
+            Self::status(manifest);
         }
 
         Ok(())
@@ -288,7 +519,7 @@ impl ServicesCommand {
         Ok(())
     }
 
-    fn status() {
+    fn status(manifest: &ProjectManifest) {
         println!("\n{INFO} Service Status");
         println!();
         println!(
@@ -298,8 +529,8 @@ impl ServicesCommand {
         println!("{}", "─".repeat(60));
 
         for service in ServiceName::all() {
-            let port = service.default_port();
-            let (status_emoji, status_text, pid) = if Self::is_service_running(*service) {
+            let port = manifest.service_port(*service);
+            let (status_emoji, status_text, pid) = if Self::is_service_running(*service, port) {
                 let pid = Self::get_service_pid(*service)
                     .map_or_else(|| "?".to_string(), |p| p.to_string());
                 (RUNNING, style("Running").green(), pid)
@@ -320,14 +551,31 @@ impl ServicesCommand {
         println!();
 
         // Check if any services are running via ports
-        Self::check_port_status();
+        Self::check_port_status(manifest);
+    }
+
+    fn service_statuses(manifest: &ProjectManifest) -> Vec<ServiceStatus> {
+        ServiceName::all()
+            .iter()
+            .map(|service| {
+                let port = manifest.service_port(*service);
+                let running = Self::is_service_running(*service, port);
+                ServiceStatus {
+                    service: service.name(),
+                    binary: service.binary_name(),
+                    port,
+                    running,
+                    pid: running.then(|| Self::get_service_pid(*service)).flatten(),
+                }
+            })
+            .collect()
     }
 
-    fn logs(service: ServiceName, follow: bool, lines: usize) -> Result<()> {
-        let log_dir = Self::get_log_dir();
-        let log_file = log_dir.join(format!("{}.log", service.binary_name()));
+    fn logs(service: ServiceName, follow: bool, lines: usize, mut filter: LogFilter) -> Result<()> {
+        let log_file = Self::get_log_file(service);
+        let files = service_log::log_files(&log_file);
 
-        if !log_file.exists() {
+        if files.is_empty() {
             println!(
                 "{ERROR} No logs found for {}",
                 style(service.display_name()).cyan()
@@ -336,39 +584,56 @@ impl ServicesCommand {
             return Ok(());
         }
 
-        if follow {
-            // Follow mode - use tail -f
-            let mut cmd = std::process::Command::new("tail")
-                .args(["-f", "-n", &lines.to_string()])
-                .arg(&log_file)
-                .spawn()
-                .context("Failed to tail log file")?;
-
-            // Wait for Ctrl+C
-            cmd.wait()?;
+        let follower = if follow {
+            Some(LogFollower::new(&log_file).context("Failed to open log file")?)
         } else {
-            // Show last N lines
-            let file =
-                std::fs::File::open(&log_file).context("Failed to open log file")?;
-            let reader = BufReader::new(file);
-            let all_lines: Vec<String> = reader.lines().map_while(Result::ok).collect();
-            let start = all_lines.len().saturating_sub(lines);
-
             println!(
                 "{INFO} Last {lines} lines from {}:",
                 style(service.display_name()).cyan()
             );
             println!();
+            None
+        };
+
+        for line in service_log::tail_lines(&files, lines, &mut filter)? {
+            println!("{line}");
+        }
 
-            for line in all_lines.iter().skip(start) {
-                println!("{line}");
+        // Follow until Ctrl+C
+        if let Some(mut follower) = follower {
+            loop {
+                for line in follower.poll().context("Failed to read log file")? {
+                    if filter.accept(&line) {
+                        println!("{line}");
+                    }
+                }
+                std::thread::sleep(LOG_POLL_INTERVAL);
             }
         }
 
         Ok(())
     }
 
-    fn restart(services: &[ServiceName]) -> Result<()> {
+    fn write_log(service: ServiceName, options: &LogOptions) -> Result<()> {
+        let log_file = Self::get_log_file(service);
+        let mut log = RotatingLog::open(&log_file, service.binary_name(), options.clone())
+            .context("Failed to open log file")?;
+
+        // Until the service exits and closes its end of the pipe
+        for line in std::io::stdin().lock().split(b'\n') {
+            let line = line.context("Failed to read service output")?;
+            let line = String::from_utf8_lossy(&line);
+            log.write_line(line.trim_end_matches('\r'), Utc::now())
+                .context("Failed to write log file")?;
+        }
+        Ok(())
+    }
+
+    fn restart(
+        services: &[ServiceName],
+        log: &LogOptions,
+        manifest: &ProjectManifest,
+    ) -> Result<()> {
         println!("\n{INFO} Restarting services...");
         println!();
 
@@ -386,9 +651,9 @@ impl ServicesCommand {
 
             // Start again
             let binary = service.binary_name();
-            let port = service.default_port();
+            let port = manifest.service_port(*service);
             let binary_path = Self::find_binary(binary)?;
-            Self::start_background(*service, &binary_path, port)?;
+            Self::start_background(*service, &binary_path, port, log)?;
 
             println!(
                 "  {SUCCESS} Restarted {} on port {port}",
@@ -397,33 +662,29 @@ impl ServicesCommand {
         }
 
         println!();
-        Self::status();
+        Self::status(manifest);
         Ok(())
     }
 
     // Helper functions
 
     fn find_binary(name: &str) -> Result<PathBuf> {
+        let file_name = process::executable_name(name);
+
         // Check release build first
-        let release_path = PathBuf::from(format!("target/release/{name}"));
+        let release_path = PathBuf::from("target/release").join(&file_name);
         if release_path.exists() {
             return Ok(release_path);
         }
 
         // Check debug build
-        let debug_path = PathBuf::from(format!("target/debug/{name}"));
+        let debug_path = PathBuf::from("target/debug").join(&file_name);
         if debug_path.exists() {
             return Ok(debug_path);
         }
 
-        // Try to find in PATH using which command
-        if let Ok(output) = std::process::Command::new("which").arg(name).output() {
-            if output.status.success() {
-                let path = String::from_utf8_lossy(&output.stdout)
-                    .trim()
-                    .to_string();
-                return Ok(PathBuf::from(path));
-            }
+        if let Some(path) = process::find_in_path(name) {
+            return Ok(path);
         }
 
         anyhow::bail!(
@@ -448,25 +709,41 @@ impl ServicesCommand {
         service: ServiceName,
         binary_path: &PathBuf,
         port: u16,
+        log: &LogOptions,
     ) -> Result<()> {
         let log_dir = Self::get_log_dir();
         std::fs::create_dir_all(&log_dir)?;
 
-        let log_file = log_dir.join(format!("{}.log", service.binary_name()));
-        let log_handle = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_file)
-            .context("Failed to open log file")?;
-
-        let child = std::process::Command::new(binary_path)
+        // Output goes through a log writer, which rotates the log file
+        let service_arg = service
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        let mut writer = std::process::Command::new(
+            std::env::current_exe().context("Failed to locate the acton-dx executable")?,
+        );
+        writer
+            .args(["htmx", "services", "write-log", &service_arg])
+            .args(log.to_args())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        process::detach(&mut writer);
+        let mut writer = writer.spawn().context("Failed to start log writer")?;
+        let pipe = writer.stdin.take().context("Log writer has no stdin")?;
+        let (stdout, stderr) =
+            process::shared_output(pipe).context("Failed to connect service output")?;
+
+        let mut command = std::process::Command::new(binary_path);
+        command
             .env("SERVICE_PORT", port.to_string())
-            .stdout(Stdio::from(
-                log_handle.try_clone().context("Failed to clone log handle")?,
-            ))
-            .stderr(Stdio::from(log_handle))
-            .spawn()
-            .context("Failed to start service")?;
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr);
+        process::detach(&mut command);
+        let child = command.spawn().context("Failed to start service")?;
+        // The service now holds the only write end; the writer exits with it
+        drop(command);
 
         let pid = child.id();
 
@@ -501,49 +778,24 @@ impl ServicesCommand {
             return false;
         };
 
-        if let Some(mut process) = manager.remove(&service) {
+        if let Some(mut tracked) = manager.remove(&service) {
             drop(manager); // Release lock before waiting
-            let _ = process.child.kill();
-            let _ = process.child.wait();
+            let _ = process::stop_child(&mut tracked.child, STOP_GRACE_PERIOD);
             Self::cleanup_pid_file(service);
             return true;
         }
 
         drop(manager); // Release lock before file operations
 
-        // Try to stop via PID file
-        let pid_file = Self::get_pid_file(service);
-        if pid_file.exists() {
-            if let Ok(pid_str) = std::fs::read_to_string(&pid_file) {
-                if let Ok(pid) = pid_str.trim().parse::<u32>() {
-                    // Send termination signal
-                    Self::terminate_process(pid);
-                }
-            }
-            Self::cleanup_pid_file(service);
-            return true;
-        }
-
-        false
-    }
-
-    #[cfg(unix)]
-    fn terminate_process(pid: u32) {
-        // Use kill command to send SIGTERM - safe approach without unsafe blocks
-        let _ = std::process::Command::new("kill")
-            .args(["-TERM", &pid.to_string()])
-            .status();
-    }
-
-    #[cfg(not(unix))]
-    fn terminate_process(pid: u32) {
-        // On Windows, use taskkill
-        let _ = std::process::Command::new("taskkill")
-            .args(["/PID", &pid.to_string(), "/F"])
-            .status();
+        // Try to stop via PID file, ignoring one left behind by a dead process
+        let stopped = Self::get_service_pid(service).is_some_and(|pid| {
+            process::is_alive(pid) && process::terminate(pid, STOP_GRACE_PERIOD)
+        });
+        Self::cleanup_pid_file(service);
+        stopped
     }
 
-    fn is_service_running(service: ServiceName) -> bool {
+    fn is_service_running(service: ServiceName, port: u16) -> bool {
         // Check manager first
         if let Ok(manager) = SERVICE_MANAGER.lock() {
             if manager.contains_key(&service) {
@@ -556,30 +808,13 @@ impl ServicesCommand {
         if pid_file.exists() {
             if let Ok(pid_str) = std::fs::read_to_string(&pid_file) {
                 if let Ok(pid) = pid_str.trim().parse::<u32>() {
-                    return Self::is_process_alive(pid);
+                    return process::is_alive(pid);
                 }
             }
         }
 
         // Check if port is in use
-        Self::is_port_in_use(service.default_port())
-    }
-
-    #[cfg(unix)]
-    fn is_process_alive(pid: u32) -> bool {
-        // Use kill -0 to check if process exists - safe approach
-        std::process::Command::new("kill")
-            .args(["-0", &pid.to_string()])
-            .output()
-            .is_ok_and(|o| o.status.success())
-    }
-
-    #[cfg(not(unix))]
-    fn is_process_alive(pid: u32) -> bool {
-        std::process::Command::new("tasklist")
-            .args(["/FI", &format!("PID eq {pid}"), "/NH"])
-            .output()
-            .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).contains(&pid.to_string()))
+        Self::is_port_in_use(port)
     }
 
     fn get_service_pid(service: ServiceName) -> Option<u32> {
@@ -605,12 +840,12 @@ impl ServicesCommand {
         std::net::TcpListener::bind(format!("127.0.0.1:{port}")).is_err()
     }
 
-    fn check_port_status() {
+    fn check_port_status(manifest: &ProjectManifest) {
         let mut issues = Vec::new();
 
         for service in ServiceName::all() {
-            let port = service.default_port();
-            if Self::is_port_in_use(port) && !Self::is_service_running(*service) {
+            let port = manifest.service_port(*service);
+            if Self::is_port_in_use(port) && !Self::is_service_running(*service, port) {
                 issues.push(format!(
                     "Port {port} is in use but {} is not tracked (external process?)",
                     service.display_name()
@@ -628,15 +863,11 @@ impl ServicesCommand {
     }
 
     fn get_log_dir() -> PathBuf {
-        let base = std::env::var("XDG_STATE_HOME").map_or_else(
-            |_| {
-                dirs::home_dir()
-                    .unwrap_or_else(|| PathBuf::from("."))
-                    .join(".local/state")
-            },
-            PathBuf::from,
-        );
-        base.join("acton-dx/services/logs")
+        process::state_dir().join("acton-dx/services/logs")
+    }
+
+    fn get_log_file(service: ServiceName) -> PathBuf {
+        Self::get_log_dir().join(format!("{}.log", service.binary_name()))
     }
 
     fn get_pid_file(service: ServiceName) -> PathBuf {
@@ -684,7 +915,34 @@ mod tests {
     #[test]
     fn test_status_command_does_not_panic() {
         // Should not panic even without running services
-        let _ = ServicesCommand::Status.execute();
+        let _ = ServicesCommand::Status.execute(OutputFormat::Human, &ProjectManifest::default());
+    }
+
+    #[test]
+    fn test_service_statuses_json() {
+        let statuses = ServicesCommand::service_statuses(&ProjectManifest::default());
+        assert_eq!(statuses.len(), ServiceName::all().len());
+
+        let json = serde_json::to_value(&statuses).unwrap();
+        assert_eq!(json[0]["service"], "auth");
+        assert_eq!(json[0]["binary"], "auth-service");
+        assert_eq!(json[0]["port"], 50051);
+        assert!(json[0]["running"].is_boolean());
+    }
+
+    #[test]
+    fn test_services_default_to_manifest() {
+        let manifest: ProjectManifest =
+            toml::from_str("[services]\nenabled = [\"auth\", \"cedar\"]\n").unwrap();
+        assert_eq!(
+            ServicesCommand::services_or_enabled(&[], &manifest).unwrap(),
+            [ServiceName::Auth, ServiceName::Cedar]
+        );
+        assert_eq!(
+            ServicesCommand::services_or_enabled(&[ServiceName::Data], &manifest).unwrap(),
+            [ServiceName::Data]
+        );
+        assert!(ServicesCommand::services_or_enabled(&[], &ProjectManifest::default()).is_err());
     }
 
     #[test]
@@ -700,4 +958,32 @@ mod tests {
             .file_name()
             .is_some_and(|name| name == "acton-dx-auth-service.pid"));
     }
+
+    #[test]
+    fn test_log_writer_arguments_round_trip() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(subcommand)]
+            command: ServicesCommand,
+        }
+
+        let options = LogOptions {
+            format: service_log::LogFormat::Json,
+            max_size_mib: 0,
+            rotate: service_log::RotationPeriod::Hourly,
+            retain: 2,
+        };
+        let args = ["services", "write-log", "auth"]
+            .into_iter()
+            .map(str::to_string)
+            .chain(options.to_args());
+        let cli = <Cli as clap::Parser>::try_parse_from(args).unwrap();
+        match cli.command {
+            ServicesCommand::WriteLog { service, log } => {
+                assert_eq!(service, ServiceName::Auth);
+                assert_eq!(log, options);
+            }
+            other => panic!("unexpected command {other:?}"),
+        }
+    }
 }
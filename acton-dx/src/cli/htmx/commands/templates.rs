@@ -45,6 +45,7 @@ const TEMPLATE_NAMES: &[&str] = &[
     "errors/404.html",
     "errors/422.html",
     "errors/500.html",
+    "oauth/consent.html",
 ];
 
 /// GitHub base URL for framework templates
@@ -0,0 +1,810 @@
+//! SQLite to Postgres data transfer
+//!
+//! Copies tables, indexes, foreign keys and rows from a SQLite database into
+//! Postgres, for apps that start on SQLite and move to Postgres for
+//! production. Column types are mapped from SQLite's declared types using its
+//! affinity rules, and every row is written inside one Postgres transaction,
+//! so a failed transfer leaves the target untouched.
+//!
+//! With `--data-only` the Postgres schema is expected to exist already (e.g.
+//! from running the Postgres migrations), and tables are filled parents
+//! first so foreign keys hold throughout.
+
+use anyhow::{Context, Result};
+use console::style;
+use futures_util::TryStreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::{Postgres, Row, Transaction, TypeInfo, ValueRef};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+/// Progress bar layout for each table
+#[allow(clippy::literal_string_with_formatting_args)]
+const PROGRESS_TEMPLATE: &str = "  {msg:24} [{bar:30.green}] {pos}/{len}";
+
+/// Postgres accepts at most this many bind parameters per statement
+const MAX_BIND_PARAMS: usize = 65_535;
+
+/// Table recording applied sqlx migrations
+const MIGRATIONS_TABLE: &str = "_sqlx_migrations";
+
+/// `acton-dx htmx db transfer`
+pub struct TransferCommand {
+    /// Source database URL (`sqlite:...`)
+    pub from: String,
+    /// Target database URL (`postgres://...`)
+    pub to: String,
+    /// Tables to copy (all when empty)
+    pub tables: Vec<String>,
+    /// Rows per `INSERT`
+    pub batch_size: usize,
+    /// Copy rows into an existing schema instead of creating it
+    pub data_only: bool,
+}
+
+impl TransferCommand {
+    /// Execute the command
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either database is unreachable, the URLs are not
+    /// SQLite and Postgres respectively, or any statement fails; the target
+    /// is then left unchanged.
+    pub fn execute(&self) -> Result<()> {
+        if !self.from.starts_with("sqlite:") {
+            anyhow::bail!("--from must be a SQLite URL (sqlite:...)");
+        }
+        if !(self.to.starts_with("postgres://") || self.to.starts_with("postgresql://")) {
+            anyhow::bail!("--to must be a Postgres URL (postgres://...)");
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to start async runtime")?;
+        runtime.block_on(self.transfer())
+    }
+
+    async fn transfer(&self) -> Result<()> {
+        let source = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(
+                SqliteConnectOptions::from_str(&self.from)
+                    .context("Invalid SQLite URL")?
+                    .read_only(true),
+            )
+            .await
+            .with_context(|| format!("Failed to open {}", self.from))?;
+        let target = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&self.to)
+            .await
+            .context("Failed to connect to Postgres")?;
+
+        let mut tables = read_tables(&source, &self.tables).await?;
+        if self.data_only {
+            tables.retain(|table| table.name != MIGRATIONS_TABLE);
+        }
+        if tables.is_empty() {
+            anyhow::bail!("No tables to transfer");
+        }
+
+        println!(
+            "{} {}",
+            style("Transferring").green().bold(),
+            style(format!("{} tables to Postgres", tables.len())).bold()
+        );
+        println!();
+
+        let copied = self.copy(&source, &target, &tables).await?;
+
+        println!();
+        println!(
+            "{}",
+            style(format!("✓ Transferred {copied} rows")).green().bold()
+        );
+        Ok(())
+    }
+
+    async fn copy(&self, source: &SqlitePool, target: &PgPool, tables: &[Table]) -> Result<u64> {
+        let mut tx = target.begin().await?;
+        sqlx::query("SET LOCAL TIME ZONE 'UTC'")
+            .execute(&mut *tx)
+            .await?;
+
+        if !self.data_only {
+            for table in tables {
+                sqlx::query(&table.create_sql())
+                    .execute(&mut *tx)
+                    .await
+                    .with_context(|| format!("Failed to create table {}", table.name))?;
+            }
+        }
+
+        let mut copied = 0;
+        for table in dependency_order(tables) {
+            copied += copy_rows(source, &mut tx, table, self.batch_size).await?;
+        }
+
+        if !self.data_only {
+            for table in tables {
+                for statement in table.index_sql().iter().chain(&table.foreign_key_sql()) {
+                    sqlx::query(statement)
+                        .execute(&mut *tx)
+                        .await
+                        .with_context(|| format!("Failed to run: {statement}"))?;
+                }
+            }
+        }
+
+        tx.commit().await.context("Failed to commit transfer")?;
+        Ok(copied)
+    }
+}
+
+/// Copy one table's rows in batches, returning the row count
+async fn copy_rows(
+    source: &SqlitePool,
+    tx: &mut Transaction<'_, Postgres>,
+    table: &Table,
+    batch_size: usize,
+) -> Result<u64> {
+    let columns = table.column_names();
+    let (total,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", quote(&table.name)))
+        .fetch_one(source)
+        .await?;
+
+    let progress = ProgressBar::new(u64::try_from(total).unwrap_or(0));
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template(PROGRESS_TEMPLATE)
+            .context("Failed to set progress style")?,
+    );
+    progress.set_message(table.name.clone());
+
+    let batch_size = batch_size.clamp(1, MAX_BIND_PARAMS / table.columns.len().max(1));
+    let types: Vec<PgType> = table
+        .columns
+        .iter()
+        .map(|c| pg_type(&c.decl_type))
+        .collect();
+    let select = format!("SELECT {columns} FROM {}", quote(&table.name));
+    let mut rows = sqlx::query(&select).fetch(source);
+    let mut batch: Vec<Vec<Cell>> = Vec::with_capacity(batch_size);
+    let mut copied = 0;
+
+    while let Some(row) = rows.try_next().await? {
+        batch.push(read_row(&row)?);
+        if batch.len() == batch_size {
+            insert_batch(tx, table, &types, &batch).await?;
+            copied += batch.len() as u64;
+            progress.inc(batch.len() as u64);
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        insert_batch(tx, table, &types, &batch).await?;
+        copied += batch.len() as u64;
+        progress.inc(batch.len() as u64);
+    }
+
+    if let Some(column) = table.rowid_alias() {
+        sqlx::query(&format!(
+            "SELECT setval(pg_get_serial_sequence($1, $2), COALESCE(MAX({col}), 0) + 1, false) \
+             FROM {table}",
+            col = quote(column),
+            table = quote(&table.name),
+        ))
+        .bind(quote(&table.name))
+        .bind(column)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to reset the id sequence of {}", table.name))?;
+    }
+
+    progress.finish();
+    Ok(copied)
+}
+
+async fn insert_batch(
+    tx: &mut Transaction<'_, Postgres>,
+    table: &Table,
+    types: &[PgType],
+    rows: &[Vec<Cell>],
+) -> Result<()> {
+    let sql = insert_sql(&table.name, &table.column_names(), types, rows.len());
+    let mut query = sqlx::query(&sql);
+    // Each column is bound with one Rust type so the prepared statement,
+    // which sqlx caches per SQL text, sees the same parameter types in
+    // every batch
+    for row in rows {
+        for ((cell, ty), column) in row.iter().zip(types).zip(&table.columns) {
+            let unconvertible = || {
+                anyhow::anyhow!(
+                    "Cannot convert {cell:?} in {}.{} to {}",
+                    table.name,
+                    column.name,
+                    ty.as_sql()
+                )
+            };
+            query = match ty {
+                PgType::BigInt if *cell == Cell::Null => query.bind(None::<i64>),
+                PgType::BigInt => query.bind(cell.as_i64().ok_or_else(unconvertible)?),
+                PgType::Boolean if *cell == Cell::Null => query.bind(None::<bool>),
+                PgType::Boolean => query.bind(cell.as_bool().ok_or_else(unconvertible)?),
+                PgType::Double if *cell == Cell::Null => query.bind(None::<f64>),
+                PgType::Double => query.bind(cell.as_f64().ok_or_else(unconvertible)?),
+                PgType::Bytea => query.bind(cell.as_bytes()),
+                _ => query.bind(cell.as_text()),
+            };
+        }
+    }
+    query
+        .execute(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to insert into {}", table.name))?;
+    Ok(())
+}
+
+/// A SQLite value by storage class
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Null,
+    Int(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Cell {
+    /// The value as an integer, `None` for NULL or if it has no integer form
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Null | Self::Blob(_) => None,
+            Self::Int(value) => Some(*value),
+            #[allow(clippy::cast_possible_truncation)]
+            Self::Real(value) => Some(value.round() as i64),
+            Self::Text(value) => value.trim().parse().ok(),
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Null | Self::Real(_) | Self::Blob(_) => None,
+            Self::Int(value) => Some(*value != 0),
+            Self::Text(value) => match value.trim().to_lowercase().as_str() {
+                "1" | "t" | "true" | "y" | "yes" => Some(true),
+                "0" | "f" | "false" | "n" | "no" => Some(false),
+                _ => None,
+            },
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Null | Self::Blob(_) => None,
+            #[allow(clippy::cast_precision_loss)]
+            Self::Int(value) => Some(*value as f64),
+            Self::Real(value) => Some(*value),
+            Self::Text(value) => value.trim().parse().ok(),
+        }
+    }
+
+    fn as_bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            Self::Null => None,
+            Self::Blob(value) => Some(value.clone()),
+            other => other.as_text().map(String::into_bytes),
+        }
+    }
+
+    fn as_text(&self) -> Option<String> {
+        match self {
+            Self::Null => None,
+            Self::Int(value) => Some(value.to_string()),
+            Self::Real(value) => Some(value.to_string()),
+            Self::Text(value) => Some(value.clone()),
+            Self::Blob(value) => Some(String::from_utf8_lossy(value).into_owned()),
+        }
+    }
+}
+
+fn read_row(row: &SqliteRow) -> Result<Vec<Cell>> {
+    (0..row.len())
+        .map(|i| {
+            let raw = row.try_get_raw(i)?;
+            if raw.is_null() {
+                return Ok(Cell::Null);
+            }
+            let storage = raw.type_info().name().to_string();
+            Ok(match storage.as_str() {
+                "INTEGER" => Cell::Int(row.try_get_unchecked(i)?),
+                "REAL" => Cell::Real(row.try_get_unchecked(i)?),
+                "BLOB" => Cell::Blob(row.try_get_unchecked(i)?),
+                _ => Cell::Text(row.try_get_unchecked(i)?),
+            })
+        })
+        .collect()
+}
+
+/// Postgres column types SQLite declarations map to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PgType {
+    BigInt,
+    Boolean,
+    Double,
+    Numeric,
+    Text,
+    Bytea,
+    Date,
+    Timestamp,
+    Json,
+    Uuid,
+}
+
+impl PgType {
+    const fn as_sql(self) -> &'static str {
+        match self {
+            Self::BigInt => "BIGINT",
+            Self::Boolean => "BOOLEAN",
+            Self::Double => "DOUBLE PRECISION",
+            Self::Numeric => "NUMERIC",
+            Self::Text => "TEXT",
+            Self::Bytea => "BYTEA",
+            Self::Date => "DATE",
+            Self::Timestamp => "TIMESTAMPTZ",
+            Self::Json => "JSONB",
+            Self::Uuid => "UUID",
+        }
+    }
+}
+
+/// Map a declared SQLite type, following its affinity rules for the rest
+fn pg_type(decl: &str) -> PgType {
+    let decl = decl.to_uppercase();
+    let has = |needle: &str| decl.contains(needle);
+    if has("BOOL") {
+        PgType::Boolean
+    } else if has("UUID") {
+        PgType::Uuid
+    } else if has("JSON") {
+        PgType::Json
+    } else if has("TIMESTAMP") || has("DATETIME") {
+        PgType::Timestamp
+    } else if has("DATE") {
+        PgType::Date
+    } else if has("INT") {
+        PgType::BigInt
+    } else if has("CHAR") || has("CLOB") || has("TEXT") {
+        PgType::Text
+    } else if has("BLOB") || decl.trim().is_empty() {
+        PgType::Bytea
+    } else if has("REAL") || has("FLOA") || has("DOUB") {
+        PgType::Double
+    } else {
+        PgType::Numeric
+    }
+}
+
+/// Translate a SQLite column default, or drop it if it has no equivalent
+fn pg_default(default: &str, ty: PgType) -> Option<String> {
+    let trimmed = default.trim();
+    let trimmed = trimmed
+        .strip_prefix('(')
+        .and_then(|inner| inner.strip_suffix(')'))
+        .unwrap_or(trimmed);
+    let upper = trimmed.to_uppercase();
+    match upper.as_str() {
+        "NULL" => None,
+        "CURRENT_TIMESTAMP" | "CURRENT_DATE" | "CURRENT_TIME" | "TRUE" | "FALSE" => Some(upper),
+        "DATETIME('NOW')" => Some("CURRENT_TIMESTAMP".to_string()),
+        "0" | "1" if ty == PgType::Boolean => {
+            Some(if upper == "1" { "TRUE" } else { "FALSE" }.to_string())
+        }
+        _ if trimmed.parse::<f64>().is_ok() => Some(trimmed.to_string()),
+        _ if trimmed.len() >= 2 && trimmed.starts_with('\'') && trimmed.ends_with('\'') => {
+            Some(trimmed.to_string())
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    name: String,
+    decl_type: String,
+    not_null: bool,
+    default: Option<String>,
+    /// 1-based position in the primary key, 0 if not part of it
+    primary_key: i64,
+}
+
+#[derive(Debug, Clone)]
+struct ForeignKey {
+    columns: Vec<String>,
+    table: String,
+    referenced: Vec<String>,
+    on_delete: String,
+}
+
+#[derive(Debug, Clone)]
+struct Index {
+    name: String,
+    columns: Vec<String>,
+    unique: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Table {
+    name: String,
+    columns: Vec<Column>,
+    foreign_keys: Vec<ForeignKey>,
+    indexes: Vec<Index>,
+}
+
+impl Table {
+    fn column_names(&self) -> String {
+        self.columns
+            .iter()
+            .map(|c| quote(&c.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn primary_key(&self) -> Vec<&str> {
+        let mut columns: Vec<&Column> = self.columns.iter().filter(|c| c.primary_key > 0).collect();
+        columns.sort_by_key(|c| c.primary_key);
+        columns.into_iter().map(|c| c.name.as_str()).collect()
+    }
+
+    /// The `INTEGER PRIMARY KEY` column aliasing SQLite's rowid, if any
+    fn rowid_alias(&self) -> Option<&str> {
+        match self.primary_key().as_slice() {
+            [name] => self
+                .columns
+                .iter()
+                .find(|c| c.name == *name && c.decl_type.eq_ignore_ascii_case("INTEGER"))
+                .map(|c| c.name.as_str()),
+            _ => None,
+        }
+    }
+
+    fn create_sql(&self) -> String {
+        let rowid = self.rowid_alias();
+        let mut lines: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let ty = pg_type(&column.decl_type);
+                let mut line = format!("{} {}", quote(&column.name), ty.as_sql());
+                if rowid == Some(column.name.as_str()) {
+                    line.push_str(" GENERATED BY DEFAULT AS IDENTITY");
+                }
+                if column.not_null {
+                    line.push_str(" NOT NULL");
+                }
+                if let Some(default) = column.default.as_deref().and_then(|d| pg_default(d, ty)) {
+                    let _ = write!(line, " DEFAULT {default}");
+                }
+                line
+            })
+            .collect();
+        let primary_key = self.primary_key();
+        if !primary_key.is_empty() {
+            lines.push(format!("PRIMARY KEY ({})", quote_all(&primary_key)));
+        }
+        format!(
+            "CREATE TABLE {} (\n    {}\n)",
+            quote(&self.name),
+            lines.join(",\n    ")
+        )
+    }
+
+    fn index_sql(&self) -> Vec<String> {
+        self.indexes
+            .iter()
+            .map(|index| {
+                format!(
+                    "CREATE {}INDEX {} ON {} ({})",
+                    if index.unique { "UNIQUE " } else { "" },
+                    quote(&index.name),
+                    quote(&self.name),
+                    quote_all(&index.columns)
+                )
+            })
+            .collect()
+    }
+
+    fn foreign_key_sql(&self) -> Vec<String> {
+        self.foreign_keys
+            .iter()
+            .map(|fk| {
+                let mut sql = format!(
+                    "ALTER TABLE {} ADD FOREIGN KEY ({}) REFERENCES {}",
+                    quote(&self.name),
+                    quote_all(&fk.columns),
+                    quote(&fk.table)
+                );
+                // No referenced columns means the parent's primary key
+                if !fk.referenced.is_empty() {
+                    let _ = write!(sql, " ({})", quote_all(&fk.referenced));
+                }
+                if fk.on_delete != "NO ACTION" {
+                    let _ = write!(sql, " ON DELETE {}", fk.on_delete);
+                }
+                sql
+            })
+            .collect()
+    }
+}
+
+/// Read table definitions from SQLite, all user tables when `only` is empty
+async fn read_tables(pool: &SqlitePool, only: &[String]) -> Result<Vec<Table>> {
+    let names: Vec<(String,)> = sqlx::query_as(
+        "SELECT name FROM sqlite_master \
+         WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to list SQLite tables")?;
+
+    let mut tables = Vec::new();
+    for (name,) in names {
+        if !only.is_empty() && !only.contains(&name) {
+            continue;
+        }
+        tables.push(read_table(pool, name).await?);
+    }
+    if let Some(missing) = only.iter().find(|n| !tables.iter().any(|t| &t.name == *n)) {
+        anyhow::bail!("Table {missing} not found in the SQLite database");
+    }
+    Ok(tables)
+}
+
+async fn read_table(pool: &SqlitePool, name: String) -> Result<Table> {
+    let table = quote(&name);
+    let columns = sqlx::query(&format!("PRAGMA table_info({table})"))
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(Column {
+                name: row.try_get("name")?,
+                decl_type: row.try_get("type")?,
+                not_null: row.try_get::<i64, _>("notnull")? != 0,
+                default: row.try_get("dflt_value")?,
+                primary_key: row.try_get("pk")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+    let mut foreign_keys: BTreeMap<i64, ForeignKey> = BTreeMap::new();
+    for row in sqlx::query(&format!("PRAGMA foreign_key_list({table})"))
+        .fetch_all(pool)
+        .await?
+    {
+        let entry = foreign_keys
+            .entry(row.try_get("id")?)
+            .or_insert_with(|| ForeignKey {
+                columns: Vec::new(),
+                table: row.try_get("table").unwrap_or_default(),
+                referenced: Vec::new(),
+                on_delete: row.try_get("on_delete").unwrap_or_default(),
+            });
+        entry.columns.push(row.try_get("from")?);
+        if let Some(to) = row.try_get::<Option<String>, _>("to")? {
+            entry.referenced.push(to);
+        }
+    }
+
+    let mut indexes = Vec::new();
+    for row in sqlx::query(&format!("PRAGMA index_list({table})"))
+        .fetch_all(pool)
+        .await?
+    {
+        let origin: String = row.try_get("origin")?;
+        if origin != "c" {
+            continue;
+        }
+        let index: String = row.try_get("name")?;
+        let columns = sqlx::query(&format!("PRAGMA index_info({})", quote(&index)))
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|row| row.try_get::<Option<String>, _>("name"))
+            .collect::<Result<Option<Vec<String>>, _>>()?;
+        // Expression indexes have no column names and are not portable
+        if let Some(columns) = columns {
+            indexes.push(Index {
+                name: index,
+                columns,
+                unique: row.try_get::<i64, _>("unique")? != 0,
+            });
+        }
+    }
+
+    Ok(Table {
+        name,
+        columns,
+        foreign_keys: foreign_keys.into_values().collect(),
+        indexes,
+    })
+}
+
+/// Order tables so each comes after the tables it references
+///
+/// Self references are ignored and tables in a reference cycle keep their
+/// relative order at the end.
+fn dependency_order(tables: &[Table]) -> Vec<&Table> {
+    let mut remaining: Vec<&Table> = tables.iter().collect();
+    let mut ordered: Vec<&Table> = Vec::with_capacity(tables.len());
+    loop {
+        let (ready, blocked): (Vec<&Table>, Vec<&Table>) = remaining.iter().partition(|table| {
+            table.foreign_keys.iter().all(|fk| {
+                fk.table == table.name
+                    || ordered.iter().any(|t| t.name == fk.table)
+                    || !tables.iter().any(|t| t.name == fk.table)
+            })
+        });
+        if ready.is_empty() {
+            ordered.extend(blocked);
+            return ordered;
+        }
+        ordered.extend(ready);
+        remaining = blocked;
+    }
+}
+
+/// `INSERT` for `rows` rows, casting each parameter to its column type
+fn insert_sql(table: &str, columns: &str, types: &[PgType], rows: usize) -> String {
+    let mut sql = format!("INSERT INTO {} ({columns}) VALUES ", quote(table));
+    let mut param = 0;
+    for row in 0..rows {
+        if row > 0 {
+            sql.push_str(", ");
+        }
+        sql.push('(');
+        for (i, ty) in types.iter().enumerate() {
+            param += 1;
+            if i > 0 {
+                sql.push_str(", ");
+            }
+            let _ = write!(sql, "${param}::{}", ty.as_sql());
+        }
+        sql.push(')');
+    }
+    sql
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn quote_all<S: AsRef<str>>(identifiers: &[S]) -> String {
+    identifiers
+        .iter()
+        .map(|i| quote(i.as_ref()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pg_type() {
+        assert_eq!(pg_type("INTEGER"), PgType::BigInt);
+        assert_eq!(pg_type("bigint"), PgType::BigInt);
+        assert_eq!(pg_type("VARCHAR(255)"), PgType::Text);
+        assert_eq!(pg_type("BOOLEAN"), PgType::Boolean);
+        assert_eq!(pg_type("DATETIME"), PgType::Timestamp);
+        assert_eq!(pg_type("date"), PgType::Date);
+        assert_eq!(pg_type("REAL"), PgType::Double);
+        assert_eq!(pg_type("DECIMAL(10,2)"), PgType::Numeric);
+        assert_eq!(pg_type(""), PgType::Bytea);
+        assert_eq!(pg_type("JSON"), PgType::Json);
+    }
+
+    #[test]
+    fn test_pg_default() {
+        assert_eq!(
+            pg_default("CURRENT_TIMESTAMP", PgType::Timestamp).as_deref(),
+            Some("CURRENT_TIMESTAMP")
+        );
+        assert_eq!(
+            pg_default("(datetime('now'))", PgType::Timestamp).as_deref(),
+            Some("CURRENT_TIMESTAMP")
+        );
+        assert_eq!(pg_default("1", PgType::Boolean).as_deref(), Some("TRUE"));
+        assert_eq!(pg_default("-2.5", PgType::Double).as_deref(), Some("-2.5"));
+        assert_eq!(
+            pg_default("'draft'", PgType::Text).as_deref(),
+            Some("'draft'")
+        );
+        assert_eq!(pg_default("NULL", PgType::Text), None);
+        assert_eq!(pg_default("(random())", PgType::BigInt), None);
+    }
+
+    #[test]
+    fn test_insert_sql() {
+        assert_eq!(
+            insert_sql(
+                "users",
+                "\"id\", \"ok\"",
+                &[PgType::BigInt, PgType::Boolean],
+                2
+            ),
+            "INSERT INTO \"users\" (\"id\", \"ok\") VALUES \
+             ($1::BIGINT, $2::BOOLEAN), ($3::BIGINT, $4::BOOLEAN)"
+        );
+    }
+
+    #[test]
+    fn test_cell_conversions() {
+        assert_eq!(Cell::Text(" 42 ".to_string()).as_i64(), Some(42));
+        assert_eq!(Cell::Null.as_i64(), None);
+        assert_eq!(Cell::Blob(vec![1]).as_i64(), None);
+        assert_eq!(Cell::Int(1).as_bool(), Some(true));
+        assert_eq!(Cell::Text("f".to_string()).as_bool(), Some(false));
+        assert_eq!(Cell::Text("maybe".to_string()).as_bool(), None);
+        assert_eq!(Cell::Int(3).as_f64(), Some(3.0));
+        assert_eq!(Cell::Int(7).as_text().as_deref(), Some("7"));
+        assert_eq!(
+            Cell::Text("ab".to_string()).as_bytes(),
+            Some(b"ab".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_tables() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        for statement in [
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email VARCHAR(255) NOT NULL, \
+             active BOOLEAN NOT NULL DEFAULT 1, created_at DATETIME DEFAULT CURRENT_TIMESTAMP)",
+            "CREATE UNIQUE INDEX idx_users_email ON users (email)",
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY, \
+             user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE, body TEXT)",
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+
+        let tables = read_tables(&pool, &[]).await.unwrap();
+        let order: Vec<&str> = dependency_order(&tables)
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect();
+        assert_eq!(order, ["users", "posts"]);
+
+        let users = tables.iter().find(|t| t.name == "users").unwrap();
+        assert_eq!(
+            users.create_sql(),
+            "CREATE TABLE \"users\" (\n    \
+             \"id\" BIGINT GENERATED BY DEFAULT AS IDENTITY,\n    \
+             \"email\" TEXT NOT NULL,\n    \
+             \"active\" BOOLEAN NOT NULL DEFAULT TRUE,\n    \
+             \"created_at\" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,\n    \
+             PRIMARY KEY (\"id\")\n)"
+        );
+        assert_eq!(
+            users.index_sql(),
+            ["CREATE UNIQUE INDEX \"idx_users_email\" ON \"users\" (\"email\")"]
+        );
+
+        let posts = tables.iter().find(|t| t.name == "posts").unwrap();
+        assert_eq!(
+            posts.foreign_key_sql(),
+            ["ALTER TABLE \"posts\" ADD FOREIGN KEY (\"user_id\") REFERENCES \"users\" (\"id\") \
+              ON DELETE CASCADE"]
+        );
+
+        assert!(read_tables(&pool, &["missing".to_string()]).await.is_err());
+    }
+}
@@ -0,0 +1,374 @@
+//! Project manifest (`acton.toml`)
+//!
+//! One file at the project root describes what a project uses, so commands
+//! read it instead of guessing from the directory layout, and settings that
+//! several commands share are written once:
+//!
+//! ```toml
+//! [project]
+//! name = "my-app"
+//!
+//! [database]
+//! kind = "postgres"
+//! url = "postgres://localhost/my_app"
+//! migrations = "migrations"
+//!
+//! [services]
+//! enabled = ["auth", "data", "cedar"]
+//! embedded = false
+//! base_port = 50051
+//!
+//! [services.ports]
+//! cedar = 50063
+//!
+//! [dev]
+//! host = "127.0.0.1"
+//! port = 3000
+//!
+//! [templates]
+//! dirs = ["templates"]
+//!
+//! [deploy]
+//! registry = "ghcr.io/myorg"
+//! platform = "linux/amd64"
+//! user = "my-app"
+//! ipc = false
+//! ```
+//!
+//! Every key is optional. Flags given on the command line win over the
+//! manifest, which wins over the built-in defaults. Commands look for
+//! `acton.toml` in the project directory and its parents, and resolve the
+//! paths in it against the directory holding it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::commands::ServiceName;
+use super::DatabaseBackend;
+
+/// File name of the project manifest
+pub const MANIFEST_FILE: &str = "acton.toml";
+
+/// Project manifest, read from `acton.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectManifest {
+    /// `[project]`
+    pub project: ProjectSection,
+    /// `[database]`
+    pub database: DatabaseSection,
+    /// `[services]`
+    pub services: ServicesSection,
+    /// `[dev]`
+    pub dev: DevSection,
+    /// `[templates]`
+    pub templates: TemplatesSection,
+    /// `[deploy]`
+    pub deploy: DeploySection,
+    /// Directory holding the manifest, if one was found
+    #[serde(skip)]
+    root: Option<PathBuf>,
+}
+
+/// `[project]`: what the project is called
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectSection {
+    /// Project (and binary) name; defaults to the package name in `Cargo.toml`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// `[database]`: the database the project uses
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSection {
+    /// Database backend
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<DatabaseBackend>,
+    /// Database URL for `db` commands; defaults to `$DATABASE_URL`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Migrations directory; defaults to `migrations`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub migrations: Option<PathBuf>,
+}
+
+/// `[services]`: the microservices the project uses
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServicesSection {
+    /// Services started by `services start` and `dev`, and shipped by
+    /// `deploy package`, when none are named
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enabled: Vec<ServiceName>,
+    /// Run services embedded in the application during `dev`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub embedded: bool,
+    /// Base port of embedded services
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_port: Option<u16>,
+    /// Ports of external services, where they differ from the defaults
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub ports: BTreeMap<ServiceName, u16>,
+}
+
+/// `[dev]`: the development server
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DevSection {
+    /// Host the application binds to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Port the application listens on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+/// `[templates]`: where the project keeps its templates
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplatesSection {
+    /// Template directories; defaults to `templates`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dirs: Vec<PathBuf>,
+}
+
+/// `[deploy]`: deployment targets
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeploySection {
+    /// Registry `deploy docker` pushes to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Platforms `deploy docker` builds for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    /// System user `deploy package` units run as
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Package the IPC transport with `deploy package`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub ipc: bool,
+    /// Services `deploy package` ships; defaults to `services.enabled`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub services: Option<Vec<ServiceName>>,
+}
+
+impl ProjectManifest {
+    /// Manifest for a project created by `htmx new`
+    #[must_use]
+    pub fn for_new_project(name: &str, database: DatabaseBackend) -> Self {
+        Self {
+            project: ProjectSection {
+                name: Some(name.to_string()),
+            },
+            database: DatabaseSection {
+                kind: Some(database),
+                migrations: Some(PathBuf::from("migrations")),
+                ..DatabaseSection::default()
+            },
+            templates: TemplatesSection {
+                dirs: vec![PathBuf::from("templates")],
+            },
+            ..Self::default()
+        }
+    }
+
+    /// Find `acton.toml` in `start` or its parents and read it
+    ///
+    /// Returns the default manifest if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a manifest is found but cannot be read or parsed.
+    pub fn discover(start: &Path) -> Result<Self> {
+        let start = start
+            .canonicalize()
+            .with_context(|| format!("Directory not found: {}", start.display()))?;
+        start
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE))
+            .find(|path| path.is_file())
+            .map_or_else(|| Ok(Self::default()), |path| Self::load(&path))
+    }
+
+    /// Find `acton.toml` in the current directory or its parents
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a manifest is found but cannot be read or parsed.
+    pub fn discover_current() -> Result<Self> {
+        Self::discover(Path::new("."))
+    }
+
+    /// Read the manifest at `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut manifest: Self = toml::from_str(&contents)
+            .with_context(|| format!("Invalid {}", path.display()))?;
+        manifest.root = path.parent().map(Path::to_path_buf);
+        Ok(manifest)
+    }
+
+    /// The manifest as TOML
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest cannot be serialized.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize acton.toml")
+    }
+
+    /// Directory holding `acton.toml`, if one was found
+    #[must_use]
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /// Resolve a path from the manifest against its directory
+    #[must_use]
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        match &self.root {
+            Some(root) if path.is_relative() => root.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Project name, if set
+    #[must_use]
+    pub fn project_name(&self) -> Option<&str> {
+        self.project.name.as_deref()
+    }
+
+    /// Migrations directory
+    #[must_use]
+    pub fn migrations_dir(&self) -> PathBuf {
+        self.resolve(
+            self.database
+                .migrations
+                .as_deref()
+                .unwrap_or_else(|| Path::new("migrations")),
+        )
+    }
+
+    /// Template directories
+    #[must_use]
+    pub fn template_dirs(&self) -> Vec<PathBuf> {
+        if self.templates.dirs.is_empty() {
+            vec![self.resolve(Path::new("templates"))]
+        } else {
+            self.templates
+                .dirs
+                .iter()
+                .map(|dir| self.resolve(dir))
+                .collect()
+        }
+    }
+
+    /// Port of an external service
+    #[must_use]
+    pub fn service_port(&self, service: ServiceName) -> u16 {
+        self.services
+            .ports
+            .get(&service)
+            .copied()
+            .unwrap_or_else(|| service.default_port())
+    }
+
+    /// Services `deploy package` ships
+    #[must_use]
+    pub fn deploy_services(&self) -> Vec<ServiceName> {
+        self.deploy
+            .services
+            .clone()
+            .unwrap_or_else(|| self.services.enabled.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[project]
+name = "blog"
+
+[database]
+kind = "postgres"
+url = "postgres://localhost/blog"
+
+[services]
+enabled = ["auth", "cedar"]
+
+[services.ports]
+cedar = 50063
+
+[dev]
+port = 8080
+
+[deploy]
+registry = "ghcr.io/acme"
+"#;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest: ProjectManifest = toml::from_str(MANIFEST).unwrap();
+        assert_eq!(manifest.project_name(), Some("blog"));
+        assert!(matches!(
+            manifest.database.kind,
+            Some(DatabaseBackend::Postgres)
+        ));
+        assert_eq!(
+            manifest.services.enabled,
+            [ServiceName::Auth, ServiceName::Cedar]
+        );
+        assert_eq!(manifest.service_port(ServiceName::Cedar), 50063);
+        assert_eq!(manifest.service_port(ServiceName::Auth), 50051);
+        assert_eq!(manifest.dev.port, Some(8080));
+        assert_eq!(manifest.dev.host, None);
+        assert_eq!(manifest.deploy_services(), manifest.services.enabled);
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys() {
+        let result = toml::from_str::<ProjectManifest>("[dev]\nprot = 8080\n");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_defaults_without_manifest() {
+        let manifest = ProjectManifest::default();
+        assert_eq!(manifest.migrations_dir(), PathBuf::from("migrations"));
+        assert_eq!(manifest.template_dirs(), [PathBuf::from("templates")]);
+        assert!(manifest.deploy_services().is_empty());
+    }
+
+    #[test]
+    fn test_discover_in_parent_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(MANIFEST_FILE), MANIFEST).unwrap();
+        let nested = dir.path().join("src/handlers");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        let manifest = ProjectManifest::discover(&nested).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(manifest.root(), Some(root.as_path()));
+        assert_eq!(manifest.migrations_dir(), root.join("migrations"));
+    }
+
+    #[test]
+    fn test_new_project_round_trip() {
+        let manifest = ProjectManifest::for_new_project("blog", DatabaseBackend::Sqlite);
+        let toml = manifest.to_toml().unwrap();
+        assert!(toml.contains("kind = \"sqlite\""));
+        assert_eq!(toml::from_str::<ProjectManifest>(&toml).unwrap(), manifest);
+    }
+}
@@ -0,0 +1,361 @@
+//! Cross-platform process management for the services commands
+//!
+//! Liveness checks, termination, binary lookup, output piping and log
+//! following that behave the same on Linux, macOS and Windows. Logs are
+//! followed in Rust rather than through `tail`, and binaries are looked up on
+//! `PATH` rather than through `which`.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+/// How often [`terminate`] checks whether a process has exited
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether a process with this PID is running
+#[cfg(unix)]
+#[must_use]
+pub fn is_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Whether a process with this PID is running
+#[cfg(windows)]
+#[must_use]
+pub fn is_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/FO", "CSV", "/NH"])
+        .output()
+        .is_ok_and(|output| tasklist_has_pid(&String::from_utf8_lossy(&output.stdout), pid))
+}
+
+/// Whether `tasklist /FO CSV /NH` output lists a process with this PID
+///
+/// Matches the PID column exactly: `tasklist` prints an informational message
+/// instead of rows when nothing matches, and other columns may contain digits.
+#[cfg(any(windows, test))]
+fn tasklist_has_pid(output: &str, pid: u32) -> bool {
+    let pid = pid.to_string();
+    output
+        .lines()
+        .filter_map(|line| line.split("\",\"").nth(1))
+        .any(|column| column == pid)
+}
+
+/// Ask a process to exit, and force it after `grace`
+///
+/// Returns `true` once the process is gone.
+#[must_use]
+pub fn terminate(pid: u32, grace: Duration) -> bool {
+    request_exit(pid);
+    if wait_for_exit(pid, grace) {
+        return true;
+    }
+    force_exit(pid);
+    wait_for_exit(pid, grace)
+}
+
+/// Ask a child process to exit, and kill it after `grace`
+///
+/// Unlike [`terminate`], the child is reaped, so it does not linger as a
+/// zombie that still looks alive.
+///
+/// # Errors
+///
+/// Returns an error if the child cannot be waited on.
+pub fn stop_child(child: &mut Child, grace: Duration) -> io::Result<ExitStatus> {
+    request_exit(child.id());
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        std::thread::sleep(EXIT_POLL_INTERVAL);
+    }
+    child.kill()?;
+    child.wait()
+}
+
+fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while is_alive(pid) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(EXIT_POLL_INTERVAL);
+    }
+    true
+}
+
+#[cfg(unix)]
+fn request_exit(pid: u32) {
+    let _ = Command::new("kill")
+        .args(["-TERM", &pid.to_string()])
+        .status();
+}
+
+#[cfg(unix)]
+fn force_exit(pid: u32) {
+    let _ = Command::new("kill")
+        .args(["-KILL", &pid.to_string()])
+        .status();
+}
+
+#[cfg(windows)]
+fn request_exit(pid: u32) {
+    let _ = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T"])
+        .output();
+}
+
+#[cfg(windows)]
+fn force_exit(pid: u32) {
+    let _ = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .output();
+}
+
+/// Keep a background process running after the CLI exits
+///
+/// On Unix the process gets its own process group, so Ctrl+C in the terminal
+/// that started it does not stop it. On Windows it is detached from the
+/// console, which would otherwise close it along with the window.
+pub fn detach(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+}
+
+/// File name of an executable on this platform, e.g. `auth-service.exe`
+#[must_use]
+pub fn executable_name(name: &str) -> String {
+    format!("{name}{}", std::env::consts::EXE_SUFFIX)
+}
+
+/// Look up an executable on `PATH`
+#[must_use]
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let file_name = executable_name(name);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// Per-user directory for state such as logs
+///
+/// `$XDG_STATE_HOME` when set, otherwise `~/.local/state` on Unix and the
+/// local application data directory on Windows.
+#[must_use]
+pub fn state_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("XDG_STATE_HOME") {
+        return PathBuf::from(dir);
+    }
+    let dir = if cfg!(windows) {
+        dirs::data_local_dir()
+    } else {
+        dirs::home_dir().map(|home| home.join(".local/state"))
+    };
+    dir.unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Two handles to one pipe, for a child's stdout and stderr
+///
+/// Lets both streams of a service go to the same reader, such as the log
+/// writer's stdin, in the order they were written.
+///
+/// # Errors
+///
+/// Returns an error if the pipe handle cannot be duplicated.
+pub fn shared_output(pipe: ChildStdin) -> io::Result<(Stdio, Stdio)> {
+    #[cfg(unix)]
+    let handle = std::os::fd::OwnedFd::from(pipe);
+    #[cfg(windows)]
+    let handle = std::os::windows::io::OwnedHandle::from(pipe);
+    let duplicate = handle.try_clone()?;
+    Ok((Stdio::from(handle), Stdio::from(duplicate)))
+}
+
+/// Follows a growing log file, like `tail -f`
+///
+/// The file is reopened on every [`poll`](Self::poll), so it can be rotated
+/// or truncated while followed, and no handle is held open between polls.
+#[derive(Debug)]
+pub struct LogFollower {
+    path: PathBuf,
+    position: u64,
+    partial: Vec<u8>,
+}
+
+impl LogFollower {
+    /// Start following at the current end of the file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let position = std::fs::metadata(&path)?.len();
+        Ok(Self {
+            path,
+            position,
+            partial: Vec::new(),
+        })
+    }
+
+    /// Complete lines written since the last poll
+    ///
+    /// A line still being written is held back until its newline arrives.
+    /// If the file shrank, it was truncated or replaced and is read again
+    /// from the start.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    pub fn poll(&mut self) -> io::Result<Vec<String>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            // Between rotation and the writer creating the new file
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let len = file.metadata()?.len();
+        if len < self.position {
+            self.position = 0;
+            self.partial.clear();
+        }
+        if len == self.position {
+            return Ok(Vec::new());
+        }
+
+        file.seek(SeekFrom::Start(self.position))?;
+        let mut buf = std::mem::take(&mut self.partial);
+        let read = file.take(len - self.position).read_to_end(&mut buf)?;
+        self.position += read as u64;
+
+        let complete = buf.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        self.partial = buf.split_off(complete);
+        Ok(String::from_utf8_lossy(&buf)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn append(path: &Path, text: &str) {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_follower_reads_new_complete_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("service.log");
+        append(&path, "before\n");
+
+        let mut follower = LogFollower::new(&path).unwrap();
+        assert!(follower.poll().unwrap().is_empty());
+
+        append(&path, "first\nsec");
+        assert_eq!(follower.poll().unwrap(), ["first"]);
+        append(&path, "ond\n");
+        assert_eq!(follower.poll().unwrap(), ["second"]);
+    }
+
+    #[test]
+    fn test_follower_restarts_after_truncation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("service.log");
+        append(&path, "a long line before rotation\n");
+
+        let mut follower = LogFollower::new(&path).unwrap();
+        std::fs::write(&path, "fresh\n").unwrap();
+        assert_eq!(follower.poll().unwrap(), ["fresh"]);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(follower.poll().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tasklist_pid_matching() {
+        let output = "\"auth-service.exe\",\"4242\",\"Console\",\"1\",\"12,345 K\"\r\n";
+        assert!(tasklist_has_pid(output, 4242));
+        assert!(!tasklist_has_pid(output, 424));
+        assert!(!tasklist_has_pid(
+            "INFO: No tasks are running which match the specified criteria.",
+            4242
+        ));
+    }
+
+    #[test]
+    fn test_find_in_path_missing() {
+        assert!(find_in_path("acton-dx-no-such-binary").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shared_output() {
+        let mut reader = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (stdout, stderr) = shared_output(reader.stdin.take().unwrap()).unwrap();
+        let status = Command::new("sh")
+            .args(["-c", "echo out; echo err >&2"])
+            .stdout(stdout)
+            .stderr(stderr)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let output = reader.wait_with_output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "out\nerr\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stop_child() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let status = stop_child(&mut child, Duration::from_secs(5)).unwrap();
+        assert!(!status.success());
+        assert!(!is_alive(child.id()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_child() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        assert!(is_alive(pid));
+
+        // Reap the child as soon as it exits, as its real parent would
+        let reaper = std::thread::spawn(move || child.wait());
+        assert!(terminate(pid, Duration::from_secs(5)));
+        assert!(!reaper.join().unwrap().unwrap().success());
+    }
+}
@@ -104,7 +104,7 @@ pub enum FieldType {
     /// Array of values
     Array {
         /// Element type
-        element_type: Box<FieldType>,
+        element_type: Box<Self>,
     },
     /// Enum type
     Enum {
@@ -710,6 +710,10 @@ mod tests {
         assert!(generated.content.contains("pub async fn update("));
         assert!(generated.content.contains("pub async fn delete("));
         assert!(generated.content.contains("pub async fn search("));
+        assert!(generated.content.contains("pub fn routes() -> Router<AppState>"));
+        assert!(generated
+            .content
+            .contains(r#".route("/posts/{id}", get(show).put(update).delete(delete))"#));
     }
 
     #[test]
@@ -297,7 +297,8 @@ use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
-    Form,
+    routing::get,
+    Form, Router,
 };
 use serde::Deserialize;
 use validator::Validate;
@@ -308,6 +309,20 @@ use crate::{
     state::AppState,
 };
 
+/// {{ model_name }} routes
+///
+/// Paths are complete, so mount with `.merge(routes())`, or in development
+/// through an `acton_htmx::routing::RouteTable` to re-mount them as this
+/// module changes.
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("{{ route_path }}", get(list).post(create))
+        .route("{{ route_path }}/new", get(new))
+        .route("{{ route_path }}/search", get(search))
+        .route("{{ route_path }}/{id}", get(show).put(update).delete(delete))
+        .route("{{ route_path }}/{id}/edit", get(edit))
+}
+
 /// List all {{ plural_title }}
 pub async fn list(
     State(state): State<AppState>,
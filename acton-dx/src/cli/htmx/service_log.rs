@@ -0,0 +1,558 @@
+//! Rotating log files for background services
+//!
+//! `services start` pipes each service's output through a small writer
+//! process (`services write-log`) that stamps every line with the time it was
+//! written and appends it to `<service>.log`. The file is rotated when it
+//! grows past a size limit or a new hour or day begins, and a fixed number of
+//! rotated files is kept, `<service>.log.1` being the newest. `services logs`
+//! reads the rotated files back in order and filters them by time and pattern.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, TimeZone, Timelike, Utc};
+use clap::ValueEnum;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// The service's output, each line prefixed with an RFC 3339 timestamp
+    #[default]
+    Text,
+    /// One JSON object per line with `timestamp`, `service` and `message`
+    Json,
+}
+
+/// When log files are rotated regardless of their size
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum RotationPeriod {
+    /// Only rotate by size
+    Never,
+    /// Start a new file every hour
+    Hourly,
+    /// Start a new file every day
+    #[default]
+    Daily,
+}
+
+impl RotationPeriod {
+    /// Start of the period containing `time`, or `None` if logs are not
+    /// rotated by time
+    fn start_of(self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let hour = time.date_naive().and_hms_opt(time.hour(), 0, 0)?;
+        match self {
+            Self::Never => None,
+            Self::Hourly => Some(Utc.from_utc_datetime(&hour)),
+            Self::Daily => Some(Utc.from_utc_datetime(&time.date_naive().and_hms_opt(0, 0, 0)?)),
+        }
+    }
+}
+
+/// Log settings for background services
+#[derive(Debug, Clone, PartialEq, Eq, clap::Args)]
+pub struct LogOptions {
+    /// Format of the service log files
+    #[arg(long = "log-format", value_enum, default_value_t)]
+    pub format: LogFormat,
+
+    /// Rotate a log file once it grows past this many MiB (0 disables)
+    #[arg(long = "log-max-size", value_name = "MIB", default_value_t = 10)]
+    pub max_size_mib: u64,
+
+    /// Also rotate log files every hour or day
+    #[arg(long = "log-rotate", value_enum, default_value_t)]
+    pub rotate: RotationPeriod,
+
+    /// Number of rotated log files to keep per service
+    #[arg(long = "log-retain", value_name = "FILES", default_value_t = 5)]
+    pub retain: usize,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            max_size_mib: 10,
+            rotate: RotationPeriod::default(),
+            retain: 5,
+        }
+    }
+}
+
+impl LogOptions {
+    /// Size at which a log file is rotated, if any
+    #[must_use]
+    pub const fn max_bytes(&self) -> Option<u64> {
+        match self.max_size_mib {
+            0 => None,
+            mib => Some(mib.saturating_mul(1024 * 1024)),
+        }
+    }
+
+    /// Command-line arguments that reproduce these options
+    #[must_use]
+    pub fn to_args(&self) -> Vec<String> {
+        let name = |value: Option<clap::builder::PossibleValue>| {
+            value
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default()
+        };
+        vec![
+            "--log-format".to_string(),
+            name(self.format.to_possible_value()),
+            "--log-max-size".to_string(),
+            self.max_size_mib.to_string(),
+            "--log-rotate".to_string(),
+            name(self.rotate.to_possible_value()),
+            "--log-retain".to_string(),
+            self.retain.to_string(),
+        ]
+    }
+}
+
+/// An append-only log file that rotates itself
+#[derive(Debug)]
+pub struct RotatingLog {
+    path: PathBuf,
+    service: String,
+    options: LogOptions,
+    file: File,
+    size: u64,
+    opened: DateTime<Utc>,
+}
+
+impl RotatingLog {
+    /// Open `path` for appending, continuing an existing file
+    ///
+    /// An existing file last written in an earlier period is rotated before
+    /// the first new line is written to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn open(path: impl Into<PathBuf>, service: &str, options: LogOptions) -> io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        let opened = metadata
+            .modified()
+            .map_or_else(|_| Utc::now(), DateTime::<Utc>::from);
+        Ok(Self {
+            path,
+            service: service.to_string(),
+            options,
+            file,
+            size: metadata.len(),
+            opened,
+        })
+    }
+
+    /// Append one line of service output, written at `now`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the line cannot be written or the file cannot be
+    /// rotated.
+    pub fn write_line(&mut self, line: &str, now: DateTime<Utc>) -> io::Result<()> {
+        let record = self.record(line, now);
+        let len = record.len() as u64;
+        if self.needs_rotation(len, now) {
+            self.rotate(now)?;
+        } else if self.size == 0 {
+            self.opened = now;
+        }
+        self.file.write_all(record.as_bytes())?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn record(&self, line: &str, now: DateTime<Utc>) -> String {
+        let timestamp = now.to_rfc3339_opts(SecondsFormat::Millis, true);
+        match self.options.format {
+            LogFormat::Text => format!("{timestamp} {line}\n"),
+            LogFormat::Json => {
+                // Services that already log JSON keep their own fields
+                let record = match serde_json::from_str::<serde_json::Value>(line) {
+                    Ok(serde_json::Value::Object(mut fields)) => {
+                        fields
+                            .entry("timestamp")
+                            .or_insert_with(|| timestamp.into());
+                        fields.insert("service".to_string(), self.service.clone().into());
+                        serde_json::Value::Object(fields)
+                    }
+                    _ => serde_json::json!({
+                        "timestamp": timestamp,
+                        "service": self.service,
+                        "message": line,
+                    }),
+                };
+                format!("{record}\n")
+            }
+        }
+    }
+
+    fn needs_rotation(&self, incoming: u64, now: DateTime<Utc>) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_large = self
+            .options
+            .max_bytes()
+            .is_some_and(|max| self.size + incoming > max);
+        let period = self.options.rotate;
+        too_large || period.start_of(self.opened) != period.start_of(now)
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        let retain = self.options.retain;
+        if retain == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            remove_if_exists(&rotated_path(&self.path, retain))?;
+            for index in (1..retain).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    std::fs::rename(&from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.opened = now;
+        Ok(())
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Path of the `index`th rotated file, e.g. `auth-service.log.1`
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+/// The current log file and its rotated predecessors that exist, oldest first
+#[must_use]
+pub fn log_files(path: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut rotated = rotated_path(path, 1);
+    while rotated.exists() {
+        files.push(rotated);
+        rotated = rotated_path(path, files.len() + 1);
+    }
+    files.reverse();
+    if path.exists() {
+        files.push(path.to_path_buf());
+    }
+    files
+}
+
+/// When a log line was written, from its timestamp prefix or JSON field
+#[must_use]
+pub fn line_timestamp(line: &str) -> Option<DateTime<Utc>> {
+    let timestamp = if line.starts_with('{') {
+        let record: serde_json::Value = serde_json::from_str(line).ok()?;
+        DateTime::parse_from_rfc3339(record.get("timestamp")?.as_str()?).ok()?
+    } else {
+        DateTime::parse_from_rfc3339(line.split_once(' ')?.0).ok()?
+    };
+    Some(timestamp.with_timezone(&Utc))
+}
+
+/// Parse a `--since` value relative to `now`
+///
+/// Accepts an age such as `30s`, `15m`, `2h` or `7d`, an RFC 3339 timestamp,
+/// or a date, which means midnight UTC.
+///
+/// # Errors
+///
+/// Returns an error if the value is none of these.
+pub fn parse_since(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(Utc.from_utc_datetime(&date.and_time(chrono::NaiveTime::MIN)));
+    }
+
+    let invalid = || format!("invalid time '{value}' (expected e.g. 30m, 2h, 7d or 2024-01-31)");
+    let split = value.len().checked_sub(1).ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at_checked(split).ok_or_else(invalid)?;
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    let age = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        _ => None,
+    }
+    .filter(|age| *age >= Duration::zero())
+    .ok_or_else(invalid)?;
+    now.checked_sub_signed(age).ok_or_else(invalid)
+}
+
+/// Selects log lines by time and pattern
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    since: Option<DateTime<Utc>>,
+    pattern: Option<Regex>,
+    last_timestamp: Option<DateTime<Utc>>,
+}
+
+impl LogFilter {
+    /// Lines written at or after `since` that match `pattern`
+    #[must_use]
+    pub const fn new(since: Option<DateTime<Utc>>, pattern: Option<Regex>) -> Self {
+        Self {
+            since,
+            pattern,
+            last_timestamp: None,
+        }
+    }
+
+    /// Whether to show `line`, the next line of the log
+    ///
+    /// Lines without a timestamp, such as the rest of a multi-line panic
+    /// message, take the time of the line before them.
+    pub fn accept(&mut self, line: &str) -> bool {
+        if let Some(timestamp) = line_timestamp(line) {
+            self.last_timestamp = Some(timestamp);
+        }
+        let recent = self
+            .since
+            .is_none_or(|since| self.last_timestamp.is_some_and(|time| time >= since));
+        recent
+            && self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(line))
+    }
+}
+
+/// The last `count` lines of `files`, read in order, that pass `filter`
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read.
+pub fn tail_lines(files: &[PathBuf], count: usize, filter: &mut LogFilter) -> Result<Vec<String>> {
+    let mut lines = VecDeque::with_capacity(count.min(1024));
+    for path in files {
+        let file =
+            File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        for line in BufReader::new(file).lines() {
+            let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+            if !filter.accept(&line) {
+                continue;
+            }
+            if lines.len() == count {
+                lines.pop_front();
+            }
+            if count > 0 {
+                lines.push_back(line);
+            }
+        }
+    }
+    Ok(lines.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn options(max_size_mib: u64, rotate: RotationPeriod, retain: usize) -> LogOptions {
+        LogOptions {
+            format: LogFormat::Text,
+            max_size_mib,
+            rotate,
+            retain,
+        }
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_text_lines_are_timestamped() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("auth-service.log");
+        let mut log = RotatingLog::open(&path, "auth-service", LogOptions::default()).unwrap();
+        log.write_line("listening on 50051", Utc::now()).unwrap();
+
+        let contents = read(&path);
+        let line = contents.lines().next().unwrap();
+        assert!(line.ends_with(" listening on 50051"));
+        assert!(line_timestamp(line).is_some());
+    }
+
+    #[test]
+    fn test_json_records() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("auth-service.log");
+        let options = LogOptions {
+            format: LogFormat::Json,
+            ..LogOptions::default()
+        };
+        let mut log = RotatingLog::open(&path, "auth-service", options).unwrap();
+        let now = at("2026-01-02T03:04:05.678Z");
+        log.write_line("plain output", now).unwrap();
+        log.write_line(r#"{"level":"INFO","fields":{"message":"ready"}}"#, now)
+            .unwrap();
+
+        let records: Vec<serde_json::Value> = read(&path)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            records[0],
+            serde_json::json!({
+                "timestamp": "2026-01-02T03:04:05.678Z",
+                "service": "auth-service",
+                "message": "plain output",
+            })
+        );
+        assert_eq!(records[1]["level"], "INFO");
+        assert_eq!(records[1]["service"], "auth-service");
+        assert_eq!(
+            line_timestamp(read(&path).lines().nth(1).unwrap()),
+            Some(now)
+        );
+    }
+
+    #[test]
+    fn test_rotates_by_size_and_keeps_retained_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data-service.log");
+        let mut log =
+            RotatingLog::open(&path, "data-service", options(1, RotationPeriod::Never, 2)).unwrap();
+        let now = Utc::now();
+        let line = "x".repeat(400 * 1024);
+        for _ in 0..8 {
+            log.write_line(&line, now).unwrap();
+        }
+
+        // Two lines fit in 1 MiB, so eight lines fill four files
+        assert_eq!(
+            log_files(&path),
+            [rotated_path(&path, 2), rotated_path(&path, 1), path.clone()]
+        );
+        assert!(!rotated_path(&path, 3).exists());
+        for file in log_files(&path) {
+            assert_eq!(read(&file).lines().count(), 2);
+        }
+    }
+
+    #[test]
+    fn test_rotates_when_period_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cache-service.log");
+        let mut log =
+            RotatingLog::open(&path, "cache-service", options(0, RotationPeriod::Daily, 5))
+                .unwrap();
+        log.write_line("monday", at("2026-01-05T23:59:00Z"))
+            .unwrap();
+        log.write_line("still monday", at("2026-01-05T23:59:30Z"))
+            .unwrap();
+        log.write_line("tuesday", at("2026-01-06T00:00:01Z"))
+            .unwrap();
+
+        assert_eq!(read(&rotated_path(&path, 1)).lines().count(), 2);
+        assert!(read(&path).contains("tuesday"));
+    }
+
+    #[test]
+    fn test_zero_retention_discards_old_logs() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file-service.log");
+        let mut log =
+            RotatingLog::open(&path, "file-service", options(1, RotationPeriod::Never, 0)).unwrap();
+        let line = "x".repeat(700 * 1024);
+        log.write_line(&line, Utc::now()).unwrap();
+        log.write_line("after", Utc::now()).unwrap();
+        log.write_line(&line, Utc::now()).unwrap();
+
+        assert_eq!(log_files(&path), std::slice::from_ref(&path));
+        assert!(!read(&path).contains("after"));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = at("2026-01-02T12:00:00Z");
+        assert_eq!(parse_since("90s", now), Ok(at("2026-01-02T11:58:30Z")));
+        assert_eq!(parse_since("15m", now), Ok(at("2026-01-02T11:45:00Z")));
+        assert_eq!(parse_since("2h", now), Ok(at("2026-01-02T10:00:00Z")));
+        assert_eq!(parse_since("1d", now), Ok(at("2026-01-01T12:00:00Z")));
+        assert_eq!(
+            parse_since("2025-12-31", now),
+            Ok(at("2025-12-31T00:00:00Z"))
+        );
+        assert_eq!(
+            parse_since("2026-01-02T13:00:00+02:00", now),
+            Ok(at("2026-01-02T11:00:00Z"))
+        );
+        for invalid in ["", "h", "2w", "-1h", "yesterday", "99999999999999d"] {
+            assert!(parse_since(invalid, now).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_filter_by_time_and_pattern() {
+        let lines = [
+            "2026-01-02T10:00:00.000Z started",
+            "2026-01-02T11:00:00.000Z ERROR request failed",
+            "thread 'main' panicked at src/main.rs:1:1",
+            r#"{"timestamp":"2026-01-02T12:00:00.000Z","message":"ERROR again"}"#,
+        ];
+
+        let mut recent = LogFilter::new(Some(at("2026-01-02T10:30:00Z")), None);
+        let shown: Vec<_> = lines.iter().filter(|line| recent.accept(line)).collect();
+        assert_eq!(shown, [&lines[1], &lines[2], &lines[3]]);
+
+        let mut errors = LogFilter::new(None, Some(Regex::new("ERROR|panicked").unwrap()));
+        let shown: Vec<_> = lines.iter().filter(|line| errors.accept(line)).collect();
+        assert_eq!(shown, [&lines[1], &lines[2], &lines[3]]);
+    }
+
+    #[test]
+    fn test_tail_lines_across_rotated_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("email-service.log");
+        std::fs::write(rotated_path(&path, 2), "one\ntwo\n").unwrap();
+        std::fs::write(rotated_path(&path, 1), "three\n").unwrap();
+        std::fs::write(&path, "four\nfive\n").unwrap();
+
+        let files = log_files(&path);
+        let mut filter = LogFilter::default();
+        assert_eq!(
+            tail_lines(&files, 4, &mut filter).unwrap(),
+            ["two", "three", "four", "five"]
+        );
+        let mut filter = LogFilter::new(None, Some(Regex::new("^(one|two|four)$").unwrap()));
+        assert_eq!(
+            tail_lines(&files, 10, &mut filter).unwrap(),
+            ["one", "two", "four"]
+        );
+    }
+}
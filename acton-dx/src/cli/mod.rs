@@ -5,7 +5,12 @@
 //! # Subcommands
 //!
 //! - `htmx` - HTMX web framework commands
+//! - `completions` - Shell completion scripts
 
+pub mod completions;
 pub mod htmx;
+pub mod output;
 
+pub use completions::Shell;
 pub use htmx::{DatabaseBackend, HtmxCommand};
+pub use output::OutputFormat;
@@ -0,0 +1,44 @@
+//! Output mode shared by all commands
+//!
+//! `--output json` is a global flag. Commands that support it print a single
+//! JSON document on stdout and nothing else, so scripts and CI pipelines can
+//! consume results without parsing colored text:
+//!
+//! ```bash
+//! acton-dx --output json htmx services status | jq '.[] | select(.running)'
+//! ```
+//!
+//! Failures are reported through the exit status and stderr, as in human
+//! mode.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// Format of command results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Colored text for terminals (default)
+    #[default]
+    Human,
+    /// One JSON document on stdout
+    Json,
+}
+
+impl OutputFormat {
+    /// Whether results are printed as JSON
+    #[must_use]
+    pub const fn is_json(self) -> bool {
+        matches!(self, Self::Json)
+    }
+}
+
+/// Print `value` as pretty JSON on stdout
+///
+/// # Errors
+///
+/// Returns an error if `value` cannot be serialized.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).context("Failed to serialize output")?;
+    println!("{json}");
+    Ok(())
+}
@@ -11,10 +11,11 @@
 //! - Cryptographically secure (32 bytes of randomness)
 //! - Stored per-session (one active token per session)
 //! - Automatically rotated on successful validation
+//! - Masked with a fresh random pad each time they are rendered
 //! - Validated against POST/PUT/DELETE/PATCH requests
 
 use crate::htmx::agents::request_reply::{create_request_reply, send_response, ResponseChannel};
-use crate::htmx::agents::default_actor_config;
+use crate::htmx::agents::{default_actor_config, ChildSpec};
 use crate::htmx::auth::session::SessionId;
 use acton_reactive::prelude::*;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
@@ -51,6 +52,44 @@ impl CsrfToken {
     pub const fn from_string(s: String) -> Self {
         Self(s)
     }
+
+    /// Encode the token for rendering into a response
+    ///
+    /// The token is XORed with a fresh random pad, which is sent ahead of it,
+    /// so every render differs and a compressed response reveals nothing
+    /// about the token to a BREACH attacker. [`CsrfToken::matches`] accepts
+    /// the masked form.
+    #[must_use]
+    pub fn masked(&self) -> String {
+        let token = self.0.as_bytes();
+        let mut pad = vec![0u8; token.len()];
+        rand::rng().fill(pad.as_mut_slice());
+        let masked: Vec<u8> = pad
+            .iter()
+            .copied()
+            .chain(pad.iter().zip(token).map(|(pad, byte)| pad ^ byte))
+            .collect();
+        URL_SAFE_NO_PAD.encode(masked)
+    }
+
+    /// Whether a submitted token is this token, as issued or masked
+    #[must_use]
+    pub fn matches(&self, submitted: &Self) -> bool {
+        if self == submitted {
+            return true;
+        }
+        let Ok(masked) = URL_SAFE_NO_PAD.decode(submitted.as_str()) else {
+            return false;
+        };
+        let (pad, token) = masked.split_at(masked.len() / 2);
+        pad.len() == token.len()
+            && token.len() == self.0.len()
+            && pad
+                .iter()
+                .zip(token)
+                .map(|(pad, byte)| pad ^ byte)
+                .eq(self.0.bytes())
+    }
 }
 
 impl std::fmt::Display for CsrfToken {
@@ -197,6 +236,18 @@ impl CsrfManagerAgent {
         Self::configure_handlers(builder).await
     }
 
+    /// Supervision spec for the CSRF manager
+    ///
+    /// Tokens are held in memory, so forms rendered before a restart fail
+    /// validation once and must be resubmitted.
+    #[must_use]
+    pub fn child_spec() -> ChildSpec {
+        ChildSpec::new("csrf_manager", |runtime, config| {
+            let builder = runtime.new_actor_with_config::<Self>(config);
+            Box::pin(Self::configure_handlers(builder))
+        })
+    }
+
     /// Configure all message handlers for the CSRF manager
     async fn configure_handlers(mut builder: CsrfActorBuilder) -> anyhow::Result<ActorHandle> {
         builder
@@ -279,8 +330,7 @@ impl CsrfManagerAgent {
         let valid = model
             .tokens
             .get(session_id)
-            .filter(|data| !data.is_expired() && &data.token == token)
-            .is_some();
+            .is_some_and(|data| !data.is_expired() && data.token.matches(token));
 
         if valid {
             let new_token = CsrfToken::generate();
@@ -323,6 +373,22 @@ mod tests {
         assert_eq!(token.as_str(), original);
     }
 
+    #[test]
+    fn test_csrf_token_masking() {
+        let token = CsrfToken::generate();
+        let first = token.masked();
+        let second = token.masked();
+
+        assert_ne!(first, second);
+        assert!(!first.contains(token.as_str()));
+        assert!(token.matches(&CsrfToken::from_string(first)));
+        assert!(token.matches(&CsrfToken::from_string(second)));
+        assert!(token.matches(&token));
+        assert!(!token.matches(&CsrfToken::from_string(CsrfToken::generate().masked())));
+        assert!(!token.matches(&CsrfToken::generate()));
+        assert!(!token.matches(&CsrfToken::from_string("not base64!".to_string())));
+    }
+
     #[test]
     fn test_csrf_token_data_creation() {
         let token = CsrfToken::generate();
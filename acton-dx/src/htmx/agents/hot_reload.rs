@@ -7,6 +7,7 @@
 //! - Templates (Askama templates)
 //! - Configuration files
 //! - Cedar policies
+//! - Route modules (see [`RouteTable`](crate::htmx::routing::RouteTable))
 //!
 //! Features:
 //! - Debouncing to prevent excessive reloads
@@ -35,13 +36,21 @@ pub enum ReloadType {
     Policies,
     /// Static assets
     Assets,
+    /// Sources of hot-mountable route modules
+    Routes,
 }
 
 impl ReloadType {
     /// Get all reload types
     #[must_use]
     pub const fn all() -> &'static [Self] {
-        &[Self::Templates, Self::Config, Self::Policies, Self::Assets]
+        &[
+            Self::Templates,
+            Self::Config,
+            Self::Policies,
+            Self::Assets,
+            Self::Routes,
+        ]
     }
 
     /// Get the display name for this reload type
@@ -52,6 +61,7 @@ impl ReloadType {
             Self::Config => "config",
             Self::Policies => "policies",
             Self::Assets => "assets",
+            Self::Routes => "routes",
         }
     }
 }
@@ -514,7 +524,7 @@ mod tests {
     #[test]
     fn test_reload_type_all() {
         let all = ReloadType::all();
-        assert_eq!(all.len(), 4);
+        assert_eq!(all.len(), 5);
     }
 
     #[test]
@@ -523,6 +533,7 @@ mod tests {
         assert_eq!(format!("{}", ReloadType::Config), "config");
         assert_eq!(format!("{}", ReloadType::Policies), "policies");
         assert_eq!(format!("{}", ReloadType::Assets), "assets");
+        assert_eq!(format!("{}", ReloadType::Routes), "routes");
     }
 
     #[test]
@@ -0,0 +1,725 @@
+//! Bounded mailboxes with overflow policies
+//!
+//! An actor's inbox makes senders wait once it is full, so a flooded agent
+//! holds every request that is talking to it. A [`Mailbox`] sits in front of
+//! an agent with its own bounded queue and never waits: when the queue is
+//! full, its [`OverflowPolicy`] decides whether the oldest message is
+//! dropped, the new one is rejected, or the overflow is spilled to Redis.
+//! Overload then costs failed requests instead of memory.
+//!
+//! Dropping a request-reply message closes its response channel, so the
+//! waiting caller fails at once instead of timing out.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::agents::{Mailbox, MailboxConfig, OverflowPolicy};
+//!
+//! let config = MailboxConfig::default()
+//!     .with_capacity(256)
+//!     .with_policy(OverflowPolicy::DropOldest);
+//! let mailbox = Mailbox::spawn("session_manager", config, handle);
+//!
+//! mailbox.send(SaveSession::new(id, data)).await?;
+//! ```
+
+use acton_reactive::prelude::{ActonMessage, ActorHandle, ActorHandleInterface};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+#[cfg(feature = "redis")]
+use deadpool_redis::Pool as RedisPool;
+#[cfg(feature = "redis")]
+use std::any::{Any, TypeId};
+#[cfg(feature = "redis")]
+use std::collections::HashMap;
+#[cfg(feature = "redis")]
+use std::sync::OnceLock;
+
+/// What a full mailbox does with a new message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued message to make room
+    DropOldest,
+    /// Reject the new message with [`MailboxError::Full`]
+    #[default]
+    Reject,
+    /// Push overflow to a Redis list and deliver it once the queue drains
+    ///
+    /// Only message types registered with [`Mailbox::spillable`] can be
+    /// spilled; others are rejected, as they are when no Redis pool is set.
+    SpillToRedis,
+}
+
+/// Mailbox bounds for one agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MailboxConfig {
+    /// Messages held in memory before the overflow policy applies
+    pub capacity: usize,
+    /// What to do with messages past the capacity
+    pub policy: OverflowPolicy,
+}
+
+impl Default for MailboxConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            policy: OverflowPolicy::Reject,
+        }
+    }
+}
+
+impl MailboxConfig {
+    /// Set the capacity
+    #[must_use]
+    pub const fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set the overflow policy
+    #[must_use]
+    pub const fn with_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+/// Error returned when a mailbox does not accept a message
+#[derive(Debug, thiserror::Error)]
+pub enum MailboxError {
+    /// The mailbox is full and its policy rejects new messages
+    #[error("mailbox {name} is full ({capacity} messages)")]
+    Full {
+        /// Mailbox name
+        name: String,
+        /// Mailbox capacity
+        capacity: usize,
+    },
+    /// The message could not be spilled to Redis
+    #[error("mailbox {name} could not spill to Redis: {reason}")]
+    Spill {
+        /// Mailbox name
+        name: String,
+        /// What went wrong
+        reason: String,
+    },
+}
+
+/// Snapshot of a mailbox's queue depth and counters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MailboxStats {
+    /// Mailbox name, usually the agent's
+    pub name: String,
+    /// Configured capacity
+    pub capacity: usize,
+    /// Configured overflow policy
+    pub policy: OverflowPolicy,
+    /// Messages queued in memory
+    pub depth: usize,
+    /// Messages waiting in Redis
+    pub spilled: usize,
+    /// Messages accepted since start
+    pub accepted: u64,
+    /// Messages dropped by [`OverflowPolicy::DropOldest`] since start
+    pub dropped: u64,
+    /// Messages rejected since start
+    pub rejected: u64,
+    /// Messages spilled to Redis since start
+    pub spilled_total: u64,
+}
+
+type Delivery = Pin<Box<dyn Future<Output = ()> + Send>>;
+type Deliver = Box<dyn FnOnce(ActorHandle) -> Delivery + Send>;
+
+/// Queued message, type-erased so one mailbox serves all of an agent's messages
+struct Envelope {
+    deliver: Deliver,
+}
+
+impl Envelope {
+    fn new<M: ActonMessage + 'static>(message: M) -> Self {
+        Self {
+            deliver: Box::new(move |handle: ActorHandle| {
+                Box::pin(async move { handle.send(message).await })
+            }),
+        }
+    }
+}
+
+/// Encoder and decoder for one spillable message type
+#[cfg(feature = "redis")]
+#[derive(Clone, Copy)]
+struct SpillCodec {
+    kind: &'static str,
+    encode: fn(&dyn Any) -> serde_json::Result<serde_json::Value>,
+    decode: fn(serde_json::Value) -> serde_json::Result<Envelope>,
+}
+
+#[cfg(feature = "redis")]
+fn encode_as<M: Serialize + 'static>(message: &dyn Any) -> serde_json::Result<serde_json::Value> {
+    message
+        .downcast_ref::<M>()
+        .map_or(Ok(serde_json::Value::Null), serde_json::to_value)
+}
+
+#[cfg(feature = "redis")]
+fn decode_as<M>(value: serde_json::Value) -> serde_json::Result<Envelope>
+where
+    M: ActonMessage + serde::de::DeserializeOwned + 'static,
+{
+    serde_json::from_value::<M>(value).map(Envelope::new)
+}
+
+/// Message as stored in the Redis spill list
+#[cfg(feature = "redis")]
+#[derive(Serialize, Deserialize)]
+struct SpilledMessage {
+    kind: String,
+    message: serde_json::Value,
+}
+
+struct Inner {
+    name: String,
+    config: MailboxConfig,
+    target: Mutex<ActorHandle>,
+    queue: Mutex<VecDeque<Envelope>>,
+    notify: Notify,
+    closed: AtomicBool,
+    spilled: AtomicUsize,
+    accepted: AtomicU64,
+    dropped: AtomicU64,
+    rejected: AtomicU64,
+    spilled_total: AtomicU64,
+    #[cfg(feature = "redis")]
+    redis: OnceLock<RedisPool>,
+    #[cfg(feature = "redis")]
+    codecs: Mutex<HashMap<TypeId, SpillCodec>>,
+}
+
+/// Stops the forwarder once the last mailbox handle is dropped
+struct CloseOnDrop(Arc<Inner>);
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        self.0.closed.store(true, Ordering::Release);
+        self.0.notify.notify_one();
+    }
+}
+
+/// Bounded, non-blocking queue in front of an agent
+///
+/// Cheap to clone; all clones share the queue. The forwarding task stops
+/// when the last clone is dropped.
+#[derive(Clone)]
+pub struct Mailbox {
+    inner: Arc<Inner>,
+    _guard: Arc<CloseOnDrop>,
+}
+
+impl std::fmt::Debug for Mailbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mailbox")
+            .field("name", &self.inner.name)
+            .field("config", &self.inner.config)
+            .field("depth", &self.depth())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mailbox {
+    /// Create a mailbox for `handle` and start forwarding to it
+    ///
+    /// Must be called from within a Tokio runtime.
+    #[must_use]
+    pub fn spawn(name: impl Into<String>, config: MailboxConfig, handle: ActorHandle) -> Self {
+        let inner = Arc::new(Inner {
+            name: name.into(),
+            config,
+            target: Mutex::new(handle),
+            queue: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+            closed: AtomicBool::new(false),
+            spilled: AtomicUsize::new(0),
+            accepted: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            spilled_total: AtomicU64::new(0),
+            #[cfg(feature = "redis")]
+            redis: OnceLock::new(),
+            #[cfg(feature = "redis")]
+            codecs: Mutex::new(HashMap::new()),
+        });
+        tokio::spawn(forward(Arc::clone(&inner)));
+        Self {
+            _guard: Arc::new(CloseOnDrop(Arc::clone(&inner))),
+            inner,
+        }
+    }
+
+    /// Mailbox name
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Mailbox configuration
+    #[must_use]
+    pub fn config(&self) -> &MailboxConfig {
+        &self.inner.config
+    }
+
+    /// Deliver to `handle` from now on, for example after the agent restarts
+    pub fn retarget(&self, handle: ActorHandle) {
+        *self.inner.target.lock() = handle;
+    }
+
+    /// Messages queued in memory
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.inner.queue.lock().len()
+    }
+
+    /// Queue the message for the agent without waiting
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MailboxError::Full`] when the mailbox is full and its
+    /// policy does not make room, and [`MailboxError::Spill`] when a message
+    /// could not be written to Redis.
+    #[cfg_attr(not(feature = "redis"), allow(clippy::unused_async))] // Spills await Redis
+    pub async fn send<M: ActonMessage + 'static>(&self, message: M) -> Result<(), MailboxError> {
+        let inner = &self.inner;
+        {
+            let mut queue = inner.queue.lock();
+            // Once anything is spilled, new messages queue behind it in Redis
+            let spilling = inner.spilled.load(Ordering::Acquire) > 0;
+            if !spilling && queue.len() < inner.config.capacity {
+                queue.push_back(Envelope::new(message));
+                drop(queue);
+                self.accepted();
+                return Ok(());
+            }
+            if inner.config.policy == OverflowPolicy::DropOldest {
+                queue.pop_front();
+                queue.push_back(Envelope::new(message));
+                drop(queue);
+                inner.dropped.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(mailbox = %inner.name, "Mailbox full, dropped oldest message");
+                self.accepted();
+                return Ok(());
+            }
+        }
+
+        #[cfg(feature = "redis")]
+        if inner.config.policy == OverflowPolicy::SpillToRedis {
+            if let Some(result) = self.spill(&message).await {
+                if result.is_ok() {
+                    self.accepted();
+                }
+                return result;
+            }
+        }
+
+        inner.rejected.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(mailbox = %inner.name, "Mailbox full, rejected message");
+        Err(MailboxError::Full {
+            name: inner.name.clone(),
+            capacity: inner.config.capacity,
+        })
+    }
+
+    fn accepted(&self) {
+        self.inner.accepted.fetch_add(1, Ordering::Relaxed);
+        self.inner.notify.notify_one();
+    }
+
+    /// Current depth and counters
+    #[must_use]
+    pub fn stats(&self) -> MailboxStats {
+        let inner = &self.inner;
+        MailboxStats {
+            name: inner.name.clone(),
+            capacity: inner.config.capacity,
+            policy: inner.config.policy,
+            depth: self.depth(),
+            spilled: inner.spilled.load(Ordering::Relaxed),
+            accepted: inner.accepted.load(Ordering::Relaxed),
+            dropped: inner.dropped.load(Ordering::Relaxed),
+            rejected: inner.rejected.load(Ordering::Relaxed),
+            spilled_total: inner.spilled_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+impl Mailbox {
+    /// Allow messages of type `M` to be spilled to Redis as `kind`
+    ///
+    /// `kind` is stored with each spilled message and must be unique within
+    /// the mailbox and stable across deploys.
+    #[must_use]
+    pub fn spillable<M>(self, kind: &'static str) -> Self
+    where
+        M: ActonMessage + Serialize + serde::de::DeserializeOwned + 'static,
+    {
+        self.inner.codecs.lock().insert(
+            TypeId::of::<M>(),
+            SpillCodec {
+                kind,
+                encode: encode_as::<M>,
+                decode: decode_as::<M>,
+            },
+        );
+        self
+    }
+
+    /// Redis list overflow is spilled to
+    #[must_use]
+    pub fn spill_key(&self) -> String {
+        format!("acton:mailbox:{}", self.inner.name)
+    }
+
+    /// Spill overflow to `pool`
+    ///
+    /// Messages left in the list by a previous process are delivered too.
+    /// Only the first pool set is used.
+    pub fn set_redis_pool(&self, pool: RedisPool) {
+        if self.inner.redis.set(pool.clone()).is_err() {
+            return;
+        }
+        let inner = Arc::clone(&self.inner);
+        let key = self.spill_key();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                let Ok(mut conn) = pool.get().await else {
+                    return;
+                };
+                let waiting: redis::RedisResult<usize> =
+                    redis::cmd("LLEN").arg(&key).query_async(&mut *conn).await;
+                if let Ok(waiting @ 1..) = waiting {
+                    tracing::info!(mailbox = %inner.name, waiting, "Resuming spilled messages");
+                    inner.spilled.fetch_add(waiting, Ordering::AcqRel);
+                    inner.notify.notify_one();
+                }
+            });
+        }
+    }
+
+    /// Push the message to Redis, or `None` if it cannot be spilled
+    async fn spill<M: ActonMessage + 'static>(
+        &self,
+        message: &M,
+    ) -> Option<Result<(), MailboxError>> {
+        let inner = &self.inner;
+        let pool = inner.redis.get()?;
+        let codec = inner.codecs.lock().get(&TypeId::of::<M>()).copied()?;
+        let error = |reason: String| MailboxError::Spill {
+            name: inner.name.clone(),
+            reason,
+        };
+
+        let result = async {
+            let spilled = SpilledMessage {
+                kind: codec.kind.to_string(),
+                message: (codec.encode)(message).map_err(|e| error(e.to_string()))?,
+            };
+            let json = serde_json::to_string(&spilled).map_err(|e| error(e.to_string()))?;
+            let mut conn = pool.get().await.map_err(|e| error(e.to_string()))?;
+            redis::cmd("RPUSH")
+                .arg(self.spill_key())
+                .arg(json)
+                .query_async::<()>(&mut *conn)
+                .await
+                .map_err(|e| error(e.to_string()))
+        }
+        .await;
+
+        match &result {
+            Ok(()) => {
+                inner.spilled.fetch_add(1, Ordering::AcqRel);
+                inner.spilled_total.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                inner.rejected.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(error = %e, "Failed to spill mailbox message");
+            }
+        }
+        Some(result)
+    }
+}
+
+#[cfg(feature = "redis")]
+impl Inner {
+    /// Pop the oldest spilled message, `Ok(None)` once the list is empty
+    async fn unspill(&self) -> Result<Option<Envelope>, String> {
+        let Some(pool) = self.redis.get() else {
+            return Ok(None);
+        };
+        let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+        let json: Option<String> = redis::cmd("LPOP")
+            .arg(format!("acton:mailbox:{}", self.name))
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        let Some(json) = json else {
+            return Ok(None);
+        };
+        self.spilled
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                Some(n.saturating_sub(1))
+            })
+            .ok();
+
+        let spilled: SpilledMessage = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+        let codec = self
+            .codecs
+            .lock()
+            .values()
+            .find(|codec| codec.kind == spilled.kind)
+            .copied()
+            .ok_or_else(|| format!("unknown spilled message kind {}", spilled.kind))?;
+        (codec.decode)(spilled.message)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Inner {
+    fn target(&self) -> ActorHandle {
+        self.target.lock().clone()
+    }
+}
+
+/// Deliver queued messages to the agent, waiting on its inbox as needed
+async fn forward(inner: Arc<Inner>) {
+    loop {
+        let next = inner.queue.lock().pop_front();
+        if let Some(envelope) = next {
+            (envelope.deliver)(inner.target()).await;
+            continue;
+        }
+
+        #[cfg(feature = "redis")]
+        if inner.spilled.load(Ordering::Acquire) > 0 {
+            match inner.unspill().await {
+                Ok(Some(envelope)) => (envelope.deliver)(inner.target()).await,
+                Ok(None) => inner.spilled.store(0, Ordering::Release),
+                Err(e) => {
+                    tracing::warn!(mailbox = %inner.name, error = %e, "Failed to read spilled message");
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                }
+            }
+            continue;
+        }
+
+        if inner.closed.load(Ordering::Acquire) {
+            break;
+        }
+        inner.notify.notified().await;
+    }
+}
+
+/// Metric name, help text and value of one Prometheus metric family
+type Family = (&'static str, &'static str, fn(&MailboxStats) -> u64);
+
+/// Render mailbox stats in Prometheus text format
+#[must_use]
+pub fn render_prometheus(stats: &[MailboxStats]) -> String {
+    use std::fmt::Write;
+
+    let gauges: [Family; 2] = [
+        (
+            "agent_mailbox_depth",
+            "Messages queued in memory per agent mailbox",
+            |s| s.depth as u64,
+        ),
+        (
+            "agent_mailbox_spilled",
+            "Messages waiting in Redis per agent mailbox",
+            |s| s.spilled as u64,
+        ),
+    ];
+    let counters: [Family; 4] = [
+        (
+            "agent_mailbox_accepted_total",
+            "Messages accepted per agent mailbox",
+            |s| s.accepted,
+        ),
+        (
+            "agent_mailbox_dropped_total",
+            "Messages dropped per agent mailbox",
+            |s| s.dropped,
+        ),
+        (
+            "agent_mailbox_rejected_total",
+            "Messages rejected per agent mailbox",
+            |s| s.rejected,
+        ),
+        (
+            "agent_mailbox_spilled_total",
+            "Messages spilled to Redis per agent mailbox",
+            |s| s.spilled_total,
+        ),
+    ];
+
+    let mut output = String::new();
+    let families = gauges
+        .iter()
+        .map(|family| ("gauge", family))
+        .chain(counters.iter().map(|family| ("counter", family)));
+    for (kind, (metric, help, value)) in families {
+        let _ = writeln!(output, "# HELP {metric} {help}");
+        let _ = writeln!(output, "# TYPE {metric} {kind}");
+        for s in stats {
+            let _ = writeln!(output, "{metric}{{agent=\"{}\"}} {}", s.name, value(s));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acton_reactive::prelude::*;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    struct Ping(usize);
+
+    #[derive(Debug, Clone)]
+    struct GetSeen(Arc<Mutex<Option<tokio::sync::oneshot::Sender<Vec<usize>>>>>);
+
+    #[derive(Debug, Default, Clone)]
+    struct Recorder {
+        seen: Vec<usize>,
+    }
+
+    async fn recorder(runtime: &mut ActorRuntime) -> ActorHandle {
+        let mut builder = runtime.new_actor::<Recorder>();
+        builder
+            .mutate_on::<Ping>(|actor, context| {
+                actor.model.seen.push(context.message().0);
+                Reply::ready()
+            })
+            .act_on::<GetSeen>(|actor, context| {
+                let seen = actor.model.seen.clone();
+                let tx = context.message().0.lock().take();
+                if let Some(tx) = tx {
+                    let _ = tx.send(seen);
+                }
+                Reply::ready()
+            });
+        builder.start().await
+    }
+
+    async fn seen(handle: &ActorHandle) -> Vec<usize> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        handle.send(GetSeen(Arc::new(Mutex::new(Some(tx))))).await;
+        tokio::time::timeout(Duration::from_secs(1), rx)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn drained(mailbox: &Mailbox) {
+        while mailbox.depth() > 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[test]
+    fn test_policy_names() {
+        let config: MailboxConfig =
+            serde_json::from_str(r#"{"capacity": 8, "policy": "drop-oldest"}"#).unwrap();
+        assert_eq!(config.policy, OverflowPolicy::DropOldest);
+        let config: MailboxConfig =
+            serde_json::from_str(r#"{"policy": "spill-to-redis"}"#).unwrap();
+        assert_eq!(config.policy, OverflowPolicy::SpillToRedis);
+        assert_eq!(config.capacity, 1024);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_delivers_in_order() {
+        let mut runtime = ActonApp::launch_async().await;
+        let handle = recorder(&mut runtime).await;
+        let mailbox = Mailbox::spawn("recorder", MailboxConfig::default(), handle.clone());
+
+        for i in 0..10 {
+            mailbox.send(Ping(i)).await.unwrap();
+        }
+        drained(&mailbox).await;
+
+        assert_eq!(seen(&handle).await, (0..10).collect::<Vec<_>>());
+        assert_eq!(mailbox.stats().accepted, 10);
+        runtime.shutdown_all().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_reject_when_full() {
+        let mut runtime = ActonApp::launch_async().await;
+        let handle = recorder(&mut runtime).await;
+        let config = MailboxConfig::default().with_capacity(2);
+        let mailbox = Mailbox::spawn("recorder", config, handle.clone());
+
+        // The forwarder cannot run until this task yields
+        mailbox.send(Ping(0)).await.unwrap();
+        mailbox.send(Ping(1)).await.unwrap();
+        let error = mailbox.send(Ping(2)).await.unwrap_err();
+        assert!(matches!(error, MailboxError::Full { capacity: 2, .. }));
+
+        let stats = mailbox.stats();
+        assert_eq!((stats.depth, stats.accepted, stats.rejected), (2, 2, 1));
+
+        drained(&mailbox).await;
+        assert_eq!(seen(&handle).await, vec![0, 1]);
+        runtime.shutdown_all().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_drop_oldest_when_full() {
+        let mut runtime = ActonApp::launch_async().await;
+        let handle = recorder(&mut runtime).await;
+        let config = MailboxConfig::default()
+            .with_capacity(2)
+            .with_policy(OverflowPolicy::DropOldest);
+        let mailbox = Mailbox::spawn("recorder", config, handle.clone());
+
+        for i in 0..4 {
+            mailbox.send(Ping(i)).await.unwrap();
+        }
+        assert_eq!(mailbox.stats().dropped, 2);
+
+        drained(&mailbox).await;
+        assert_eq!(seen(&handle).await, vec![2, 3]);
+        runtime.shutdown_all().await.unwrap();
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let stats = MailboxStats {
+            name: "job_agent".to_string(),
+            capacity: 8,
+            policy: OverflowPolicy::Reject,
+            depth: 3,
+            spilled: 0,
+            accepted: 10,
+            dropped: 0,
+            rejected: 2,
+            spilled_total: 0,
+        };
+        let output = render_prometheus(&[stats]);
+        assert!(output.contains("# TYPE agent_mailbox_depth gauge"));
+        assert!(output.contains("agent_mailbox_depth{agent=\"job_agent\"} 3"));
+        assert!(output.contains("agent_mailbox_rejected_total{agent=\"job_agent\"} 2"));
+    }
+}
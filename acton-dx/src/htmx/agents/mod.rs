@@ -7,10 +7,12 @@ use acton_reactive::prelude::{ActorConfig, Ern};
 
 pub mod csrf_manager;
 pub mod hot_reload;
+pub mod mailbox;
 pub mod rate_limiter;
 pub mod request_reply;
 pub mod service_coordinator;
 pub mod session_manager;
+pub mod supervisor;
 
 // Re-export public types for use by middleware and extractors
 pub use csrf_manager::{
@@ -22,6 +24,10 @@ pub use hot_reload::{
     HotReloadCoordinatorAgent, HotReloadStats, ReloadEvent, ReloadType, Subscribe as HotReloadSubscribe,
     TriggerPendingReloads, UpdateConfig as HotReloadUpdateConfig,
 };
+pub use mailbox::{
+    render_prometheus as render_mailbox_metrics, Mailbox, MailboxConfig, MailboxError,
+    MailboxStats, OverflowPolicy,
+};
 pub use request_reply::{create_request_reply, send_response, ResponseChannel};
 pub use rate_limiter::{
     CheckRateLimit, CleanupExpired as RateLimiterCleanupExpired, GetStats as RateLimiterGetStats,
@@ -36,8 +42,11 @@ pub use service_coordinator::{
 };
 pub use session_manager::{
     // Unified messages (support both web handler and agent-to-agent patterns)
-    AddFlash, CleanupExpired, DeleteSession, LoadSession, SaveSession, SessionManagerAgent,
-    TakeFlashes,
+    AddFlash, CleanupExpired, DeleteSession, DeleteUserSessions, ExportSessions, LoadSession,
+    ReplicateSessions, SaveSession, SessionManagerAgent, TakeFlashes,
+};
+pub use supervisor::{
+    ChildSpec, Escalation, StartFuture, SupervisedAgent, Supervisor, SupervisorAgent,
 };
 
 /// Create a default actor configuration with the given name
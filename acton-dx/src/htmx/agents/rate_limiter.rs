@@ -9,9 +9,13 @@
 //! - Per-key rate limiting (IP, user, route)
 //! - Configurable bucket size and refill rate
 //! - Automatic cleanup of expired buckets
+//! - Optional shared buckets in cache-service for multi-node deployments
+//!   (`microservices` feature), falling back to local buckets on failure
 
-use crate::htmx::agents::default_actor_config;
+use crate::htmx::agents::{default_actor_config, ChildSpec};
 use crate::htmx::agents::request_reply::{create_request_reply, send_response, ResponseChannel};
+#[cfg(feature = "microservices")]
+use crate::htmx::clients::{CacheClient, TokenBucketResult};
 use acton_reactive::prelude::*;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
@@ -29,6 +33,10 @@ const DEFAULT_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
 /// Default bucket expiration (seconds without activity)
 const DEFAULT_BUCKET_EXPIRATION: Duration = Duration::from_secs(300);
 
+/// Default time to stay on local buckets after a shared bucket failure
+#[cfg(feature = "microservices")]
+const DEFAULT_SHARED_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Token bucket for rate limiting
 #[derive(Debug, Clone)]
 pub struct TokenBucket {
@@ -115,6 +123,12 @@ pub struct RateLimiterConfig {
     pub bucket_expiration: Duration,
     /// Whether rate limiting is enabled
     pub enabled: bool,
+    /// Cache-service client holding shared bucket state (local buckets if `None`)
+    #[cfg(feature = "microservices")]
+    pub shared: Option<CacheClient>,
+    /// How long to use local buckets after the shared store fails
+    #[cfg(feature = "microservices")]
+    pub shared_retry_interval: Duration,
 }
 
 impl Default for RateLimiterConfig {
@@ -125,6 +139,10 @@ impl Default for RateLimiterConfig {
             cleanup_interval: DEFAULT_CLEANUP_INTERVAL,
             bucket_expiration: DEFAULT_BUCKET_EXPIRATION,
             enabled: true,
+            #[cfg(feature = "microservices")]
+            shared: None,
+            #[cfg(feature = "microservices")]
+            shared_retry_interval: DEFAULT_SHARED_RETRY_INTERVAL,
         }
     }
 }
@@ -170,6 +188,26 @@ impl RateLimiterConfig {
         self.enabled = enabled;
         self
     }
+
+    /// Keep bucket state in cache-service so all nodes share the same limits
+    ///
+    /// Buckets are consumed with an atomic script on the cache service. When it
+    /// is unreachable the agent falls back to local buckets and retries the
+    /// shared store after `shared_retry_interval`.
+    #[cfg(feature = "microservices")]
+    #[must_use]
+    pub fn with_shared_buckets(mut self, client: CacheClient) -> Self {
+        self.shared = Some(client);
+        self
+    }
+
+    /// Set how long to stay on local buckets after a shared store failure
+    #[cfg(feature = "microservices")]
+    #[must_use]
+    pub const fn with_shared_retry_interval(mut self, interval: Duration) -> Self {
+        self.shared_retry_interval = interval;
+        self
+    }
 }
 
 // Type alias for the actor builder
@@ -188,6 +226,13 @@ pub struct RateLimiterAgent {
     allowed_count: u64,
     /// Total requests denied
     denied_count: u64,
+    /// Requests decided by the shared cache-service buckets
+    shared_count: u64,
+    /// Requests that fell back to local buckets after a shared store failure
+    fallback_count: u64,
+    /// Local buckets are used until this instant after a shared store failure
+    #[cfg(feature = "microservices")]
+    shared_backoff_until: Option<Instant>,
 }
 
 impl Default for RateLimiterAgent {
@@ -204,6 +249,10 @@ impl Clone for RateLimiterAgent {
             request_count: self.request_count,
             allowed_count: self.allowed_count,
             denied_count: self.denied_count,
+            shared_count: self.shared_count,
+            fallback_count: self.fallback_count,
+            #[cfg(feature = "microservices")]
+            shared_backoff_until: self.shared_backoff_until,
         }
     }
 }
@@ -294,6 +343,10 @@ pub struct RateLimiterStats {
     pub bucket_count: usize,
     /// Whether rate limiting is enabled
     pub enabled: bool,
+    /// Requests decided by shared cache-service buckets
+    pub shared_count: u64,
+    /// Requests that fell back to local buckets after a shared store failure
+    pub fallback_count: u64,
 }
 
 /// Trigger cleanup of expired buckets
@@ -330,6 +383,17 @@ impl ResetBucket {
     }
 }
 
+/// Outcome of a shared bucket check, sent back to the agent for bookkeeping
+#[cfg(feature = "microservices")]
+#[derive(Clone, Debug)]
+struct SharedCheckComplete {
+    key: String,
+    tokens: u32,
+    /// `None` if the cache service call failed
+    outcome: Option<TokenBucketResult>,
+    response_tx: Option<ResponseChannel<RateLimitResult>>,
+}
+
 impl RateLimiterAgent {
     /// Create a new rate limiter with the given configuration
     #[must_use]
@@ -340,6 +404,10 @@ impl RateLimiterAgent {
             request_count: 0,
             allowed_count: 0,
             denied_count: 0,
+            shared_count: 0,
+            fallback_count: 0,
+            #[cfg(feature = "microservices")]
+            shared_backoff_until: None,
         }
     }
 
@@ -369,6 +437,19 @@ impl RateLimiterAgent {
         Self::configure_handlers(builder).await
     }
 
+    /// Supervision spec for the rate limiter
+    ///
+    /// Buckets are held in memory, so a restarted rate limiter starts every
+    /// client with a full bucket.
+    #[must_use]
+    pub fn child_spec(config: RateLimiterConfig) -> ChildSpec {
+        ChildSpec::new("rate_limiter", move |runtime, actor_config| {
+            let mut builder = runtime.new_actor_with_config::<Self>(actor_config);
+            builder.model.config = config.clone();
+            Box::pin(Self::configure_handlers(builder))
+        })
+    }
+
     /// Configure all message handlers
     async fn configure_handlers(mut builder: RateLimiterActorBuilder) -> anyhow::Result<ActorHandle> {
         Self::configure_rate_limit_handlers(&mut builder);
@@ -399,32 +480,73 @@ impl RateLimiterAgent {
                 return Reply::ready();
             }
 
-            // Get or create bucket
-            let bucket = actor
-                .model
-                .buckets
-                .entry(msg.key.clone())
-                .or_insert_with(|| {
-                    TokenBucket::new(
-                        actor.model.config.bucket_capacity,
-                        actor.model.config.refill_rate,
-                    )
+            #[cfg(feature = "microservices")]
+            if let Some(client) = actor.model.shared_client() {
+                let config = &actor.model.config;
+                let capacity = config.bucket_capacity;
+                let refill_rate = config.refill_rate;
+                let key = msg.key.clone();
+                let tokens = msg.tokens;
+                let response_tx = msg.response_tx.clone();
+                let agent = actor.handle().clone();
+
+                // gRPC futures are not `Sync`, so the call runs on its own task
+                tokio::spawn(async move {
+                    let mut client = client;
+                    let outcome = match client
+                        .consume_tokens(&key, capacity, refill_rate, tokens)
+                        .await
+                    {
+                        Ok(result) => Some(result),
+                        Err(e) => {
+                            tracing::warn!(
+                                error = %e,
+                                key = %key,
+                                "Shared rate limit bucket unavailable, using local bucket"
+                            );
+                            None
+                        }
+                    };
+                    agent
+                        .send(SharedCheckComplete {
+                            key,
+                            tokens,
+                            outcome,
+                            response_tx,
+                        })
+                        .await;
                 });
+                return Reply::ready();
+            }
 
-            // Try to consume tokens
-            let allowed = bucket.try_consume(msg.tokens);
-            let remaining_tokens = bucket.available_tokens();
+            let result = actor.model.consume_local(&msg.key, msg.tokens);
 
-            if allowed {
-                actor.model.allowed_count += 1;
+            if let Some(tx) = msg.response_tx.clone() {
+                Reply::pending(async move {
+                    let _ = send_response(tx, result).await;
+                })
             } else {
-                actor.model.denied_count += 1;
+                Reply::ready()
             }
+        });
+
+        #[cfg(feature = "microservices")]
+        builder.mutate_on::<SharedCheckComplete>(|actor, context| {
+            let msg = context.message();
 
-            let result = RateLimitResult {
-                allowed,
-                remaining_tokens,
-                key: msg.key.clone(),
+            let result = if let Some(outcome) = &msg.outcome {
+                actor.model.shared_count += 1;
+                actor.model.record_outcome(outcome.allowed);
+                RateLimitResult {
+                    allowed: outcome.allowed,
+                    remaining_tokens: outcome.remaining,
+                    key: msg.key.clone(),
+                }
+            } else {
+                actor.model.fallback_count += 1;
+                actor.model.shared_backoff_until =
+                    Some(Instant::now() + actor.model.config.shared_retry_interval);
+                actor.model.consume_local(&msg.key, msg.tokens)
             };
 
             if let Some(tx) = msg.response_tx.clone() {
@@ -437,6 +559,44 @@ impl RateLimiterAgent {
         });
     }
 
+    /// Consume tokens from the in-process bucket for `key`
+    fn consume_local(&mut self, key: &str, tokens: u32) -> RateLimitResult {
+        let bucket = self.buckets.entry(key.to_string()).or_insert_with(|| {
+            TokenBucket::new(self.config.bucket_capacity, self.config.refill_rate)
+        });
+
+        let allowed = bucket.try_consume(tokens);
+        let remaining_tokens = bucket.available_tokens();
+        self.record_outcome(allowed);
+
+        RateLimitResult {
+            allowed,
+            remaining_tokens,
+            key: key.to_string(),
+        }
+    }
+
+    /// Update allowed/denied counters
+    const fn record_outcome(&mut self, allowed: bool) {
+        if allowed {
+            self.allowed_count += 1;
+        } else {
+            self.denied_count += 1;
+        }
+    }
+
+    /// Shared bucket client, unless unset or backing off after a failure
+    #[cfg(feature = "microservices")]
+    fn shared_client(&self) -> Option<CacheClient> {
+        if self
+            .shared_backoff_until
+            .is_some_and(|until| Instant::now() < until)
+        {
+            return None;
+        }
+        self.config.shared.clone()
+    }
+
     /// Configure admin handlers
     fn configure_admin_handlers(builder: &mut RateLimiterActorBuilder) {
         builder
@@ -451,6 +611,8 @@ impl RateLimiterAgent {
                     denied_count: actor.model.denied_count,
                     bucket_count: actor.model.buckets.len(),
                     enabled: actor.model.config.enabled,
+                    shared_count: actor.model.shared_count,
+                    fallback_count: actor.model.fallback_count,
                 };
 
                 Reply::pending(async move {
@@ -475,6 +637,10 @@ impl RateLimiterAgent {
             })
             .mutate_on::<UpdateConfig>(|actor, context| {
                 actor.model.config = context.message().config.clone();
+                #[cfg(feature = "microservices")]
+                {
+                    actor.model.shared_backoff_until = None;
+                }
                 tracing::info!("Rate limiter configuration updated");
                 Reply::ready()
             })
@@ -821,4 +987,37 @@ mod tests {
         let stats = rx.await.expect("Should get stats");
         assert!(!stats.enabled);
     }
+
+    #[cfg(feature = "microservices")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rate_limiter_shared_falls_back_to_local() {
+        // Nothing listens on this port, so every shared check fails
+        let client = CacheClient::connect_lazy("http://127.0.0.1:1").unwrap();
+        let config = RateLimiterConfig::new()
+            .with_bucket_capacity(2)
+            .with_refill_rate(0.1)
+            .with_shared_buckets(client)
+            .with_shared_retry_interval(Duration::from_secs(60));
+
+        let mut runtime = ActonApp::launch_async().await;
+        let handle = RateLimiterAgent::spawn_with_config(&mut runtime, config)
+            .await
+            .unwrap();
+
+        let mut allowed = Vec::new();
+        for _ in 0..3 {
+            let (request, rx) = CheckRateLimit::new("shared".to_string(), 1);
+            handle.send(request).await;
+            allowed.push(rx.await.expect("Should get result").allowed);
+        }
+        assert_eq!(allowed, vec![true, true, false]);
+
+        let (request, rx) = GetStats::new();
+        handle.send(request).await;
+        let stats = rx.await.expect("Should get stats");
+        assert_eq!(stats.shared_count, 0);
+        // Only the first failure falls back; later checks stay local during backoff
+        assert_eq!(stats.fallback_count, 1);
+        assert_eq!(stats.bucket_count, 1);
+    }
 }
@@ -10,8 +10,9 @@
 //! Messages with optional `response_tx` fields can be used from both contexts.
 
 use crate::htmx::agents::request_reply::{create_request_reply, send_response, ResponseChannel};
-use crate::htmx::agents::default_actor_config;
+use crate::htmx::agents::{default_actor_config, ChildSpec};
 use crate::htmx::auth::session::{FlashMessage, SessionData, SessionId};
+use crate::htmx::auth::session_migration::SessionReplica;
 use acton_reactive::prelude::*;
 use chrono::{DateTime, Duration, Utc};
 use std::cmp::Reverse;
@@ -34,6 +35,8 @@ pub struct SessionManagerAgent {
     /// Optional Redis backend for distributed sessions
     #[cfg(feature = "redis")]
     redis: Option<RedisPool>,
+    /// Copy of every session change, while dual-writing to auth-service
+    replica: Option<SessionReplica>,
 }
 
 // ============================================================================
@@ -153,10 +156,43 @@ pub struct DeleteSession {
     pub session_id: SessionId,
 }
 
+/// Message to delete every session signed in as a user
+#[derive(Clone, Debug)]
+pub struct DeleteUserSessions {
+    /// The user whose sessions are deleted
+    pub user_id: i64,
+}
+
 /// Message to trigger cleanup of expired sessions
 #[derive(Clone, Debug)]
 pub struct CleanupExpired;
 
+/// List every unexpired session, for migrating them to auth-service
+#[derive(Clone, Debug)]
+pub struct ExportSessions {
+    /// Optional response channel for web handlers
+    pub response_tx: Option<ResponseChannel<Vec<(SessionId, SessionData)>>>,
+}
+
+impl ExportSessions {
+    /// Create a new export request with response channel
+    #[must_use]
+    pub fn with_response() -> (Self, oneshot::Receiver<Vec<(SessionId, SessionData)>>) {
+        let (response_tx, rx) = create_request_reply();
+        let request = Self {
+            response_tx: Some(response_tx),
+        };
+        (request, rx)
+    }
+}
+
+/// Message to copy every later session change to a replica
+#[derive(Clone, Debug)]
+pub struct ReplicateSessions {
+    /// Replica receiving saved and deleted sessions
+    pub replica: SessionReplica,
+}
+
 /// Message to add a flash message to a session
 #[derive(Clone, Debug)]
 pub struct AddFlash {
@@ -180,6 +216,18 @@ impl SessionManagerAgent {
         Self::configure_handlers(builder).await
     }
 
+    /// Supervision spec for the session manager
+    ///
+    /// Sessions are held in memory, so a restarted session manager starts
+    /// empty and signed-in users must sign in again.
+    #[must_use]
+    pub fn child_spec() -> ChildSpec {
+        ChildSpec::new("session_manager", |runtime, config| {
+            let builder = runtime.new_actor_with_config::<Self>(config);
+            Box::pin(Self::configure_handlers(builder))
+        })
+    }
+
     /// Spawn session manager with Redis backend
     ///
     /// Uses Redis for distributed session storage with in-memory caching.
@@ -238,6 +286,7 @@ impl SessionManagerAgent {
                     .model
                     .sessions
                     .insert(session_id.clone(), data.clone());
+                actor.model.replicate(&session_id);
                 actor
                     .model
                     .expiry_queue
@@ -262,6 +311,9 @@ impl SessionManagerAgent {
                     .get_mut(&session_id)
                     .map(|session| std::mem::take(&mut session.flash_messages))
                     .unwrap_or_default();
+                if !messages.is_empty() {
+                    actor.model.replicate(&session_id);
+                }
 
                 Reply::pending(async move {
                     // Send response to web handler if channel provided
@@ -274,7 +326,7 @@ impl SessionManagerAgent {
                 })
             })
             .mutate_on::<DeleteSession>(|actor, context| {
-                actor.model.sessions.remove(&context.message().session_id);
+                actor.model.delete(&context.message().session_id);
                 Reply::ready()
             })
             .mutate_on::<CleanupExpired>(|actor, _context| {
@@ -309,13 +361,75 @@ impl SessionManagerAgent {
 
                 if let Some(session) = actor.model.sessions.get_mut(&session_id) {
                     session.flash_messages.push(message);
+                    actor.model.replicate(&session_id);
                 }
 
                 Reply::ready()
             });
+        Self::configure_sign_out_handlers(&mut builder);
+        Self::configure_migration_handlers(&mut builder);
 
         Ok(builder.start().await)
     }
+
+    /// Configure the handlers that sign a user out everywhere
+    fn configure_sign_out_handlers(builder: &mut SessionActorBuilder) {
+        builder.mutate_on::<DeleteUserSessions>(|actor, context| {
+            let user_id = Some(context.message().user_id);
+            let signed_in: Vec<_> = actor
+                .model
+                .sessions
+                .iter()
+                .filter(|(_, data)| data.user_id == user_id)
+                .map(|(id, _)| id.clone())
+                .collect();
+            for session_id in &signed_in {
+                actor.model.delete(session_id);
+            }
+            Reply::ready()
+        });
+    }
+
+    /// Configure the handlers that move sessions to auth-service
+    fn configure_migration_handlers(builder: &mut SessionActorBuilder) {
+        builder
+            .act_on::<ExportSessions>(|actor, context| {
+                let response_tx = context.message().response_tx.clone();
+                let sessions: Vec<_> = actor
+                    .model
+                    .sessions
+                    .iter()
+                    .filter(|(_, data)| !data.is_expired())
+                    .map(|(id, data)| (id.clone(), data.clone()))
+                    .collect();
+
+                Reply::pending(async move {
+                    if let Some(tx) = response_tx {
+                        let _ = send_response(tx, sessions).await;
+                    }
+                })
+            })
+            .mutate_on::<ReplicateSessions>(|actor, context| {
+                actor.model.replica = Some(context.message().replica.clone());
+                Reply::ready()
+            });
+    }
+
+    /// Remove a session, telling the replica if there is one
+    fn delete(&mut self, session_id: &SessionId) {
+        if self.sessions.remove(session_id).is_some() {
+            if let Some(replica) = &self.replica {
+                replica.deleted(session_id);
+            }
+        }
+    }
+
+    /// Copy a changed session to the replica, if there is one
+    fn replicate(&self, session_id: &SessionId) {
+        if let (Some(replica), Some(session)) = (&self.replica, self.sessions.get(session_id)) {
+            replica.saved(session_id, session);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -435,6 +549,44 @@ mod tests {
         runtime.shutdown_all().await.expect("Failed to shutdown");
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_delete_user_sessions() {
+        let mut runtime = ActonApp::launch_async().await;
+        let session_manager = SessionManagerAgent::spawn(&mut runtime).await.unwrap();
+
+        let mut signed_in = SessionData::new();
+        signed_in.user_id = Some(7);
+        let mut other_user = SessionData::new();
+        other_user.user_id = Some(8);
+        let laptop = SessionId::generate();
+        let phone = SessionId::generate();
+        let other = SessionId::generate();
+        for (session_id, data) in [
+            (&laptop, &signed_in),
+            (&phone, &signed_in),
+            (&other, &other_user),
+        ] {
+            let (save, rx) = SaveSession::with_confirmation(session_id.clone(), data.clone());
+            session_manager.send(save).await;
+            assert!(rx.await.unwrap());
+        }
+
+        session_manager.send(DeleteUserSessions { user_id: 7 }).await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        for (session_id, exists) in [(laptop, false), (phone, false), (other, true)] {
+            let (request, rx) = LoadSession::with_response(session_id);
+            session_manager.send(request).await;
+            let result = tokio::time::timeout(tokio::time::Duration::from_secs(1), rx)
+                .await
+                .expect("Timeout")
+                .expect("Channel closed");
+            assert_eq!(result.is_some(), exists);
+        }
+
+        runtime.shutdown_all().await.expect("Failed to shutdown");
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_flash_messages_with_verification() {
         let mut runtime = ActonApp::launch_async().await;
@@ -0,0 +1,641 @@
+//! Supervision of the framework's agents
+//!
+//! acton-reactive catches a panic in a message handler and keeps the agent
+//! running, but an agent can still stop: its task dies, its inbox closes, or
+//! it is stopped by mistake. Every handle to it then quietly drops messages,
+//! which for the session manager means every request loses its session.
+//!
+//! The [`Supervisor`] restarts agents that stop abnormally, with bounded
+//! retries and exponential backoff from a [`RestartLimiterConfig`]. After a
+//! restart it runs the agent's re-hydration hook, points the agent's
+//! [`SupervisedAgent`] and its attached [`Mailbox`]es at the new actor, and once the restart
+//! limit is exceeded it stops trying and calls the escalation handlers.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::agents::{SessionManagerAgent, Supervisor};
+//!
+//! let supervisor = Supervisor::spawn(&mut runtime, RestartLimiterConfig::default()).await?;
+//! supervisor
+//!     .on_escalate(|escalation| tracing::error!(agent = %escalation.agent, "Agent is down"))
+//!     .await;
+//! let sessions = supervisor
+//!     .supervise(&mut runtime, SessionManagerAgent::child_spec())
+//!     .await?;
+//! sessions.handle().send(request).await;
+//! ```
+
+use crate::htmx::agents::Mailbox;
+use acton_reactive::prelude::*;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Future returned by a [`ChildSpec`] start function
+pub type StartFuture = Pin<Box<dyn Future<Output = anyhow::Result<ActorHandle>> + Send>>;
+
+type StartFn = Arc<dyn Fn(&mut ActorRuntime, ActorConfig) -> StartFuture + Send + Sync>;
+type RehydrateFn = Arc<
+    dyn Fn(ActorHandle) -> Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>> + Send + Sync,
+>;
+
+/// How to start, and restart, one supervised agent
+#[derive(Clone)]
+pub struct ChildSpec {
+    name: String,
+    restart_policy: RestartPolicy,
+    start: StartFn,
+    rehydrate: Option<RehydrateFn>,
+}
+
+impl std::fmt::Debug for ChildSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChildSpec")
+            .field("name", &self.name)
+            .field("restart_policy", &self.restart_policy)
+            .field("rehydrate", &self.rehydrate.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ChildSpec {
+    /// Create a spec whose `start` function builds the agent from the given
+    /// runtime and actor configuration and starts it
+    ///
+    /// The configuration names the agent and makes the supervisor its
+    /// parent; pass it to `ActorRuntime::new_actor_with_config` unchanged.
+    /// Agents restart on abnormal termination
+    /// ([`RestartPolicy::Transient`]) unless set otherwise.
+    #[must_use]
+    pub fn new<F>(name: impl Into<String>, start: F) -> Self
+    where
+        F: Fn(&mut ActorRuntime, ActorConfig) -> StartFuture + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            restart_policy: RestartPolicy::Transient,
+            start: Arc::new(start),
+            rehydrate: None,
+        }
+    }
+
+    /// Agent name
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Set when the agent is restarted
+    #[must_use]
+    pub const fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
+    /// Run `hook` on the new agent after each restart, to reload state it
+    /// lost (for example pending jobs from Redis)
+    ///
+    /// An error is logged; the agent stays up.
+    #[must_use]
+    pub fn with_rehydrate<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ActorHandle) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        self.rehydrate = Some(Arc::new(move |handle| Box::pin(hook(handle))));
+        self
+    }
+
+    /// ERN and actor configuration of one incarnation of the agent
+    fn incarnation(
+        &self,
+        supervisor: &ActorHandle,
+        generation: u64,
+    ) -> anyhow::Result<(Ern, ActorConfig)> {
+        // Only the parts of a child ERN are kept; the runtime sets the root
+        let id = Ern::with_root(self.name.as_str())?
+            .add_part(self.name.as_str())?
+            .add_part(generation.to_string())?;
+        let config = ActorConfig::new(id.clone(), Some(supervisor.clone()), None)?
+            .with_restart_policy(self.restart_policy);
+        Ok((supervisor.id() + id, config))
+    }
+}
+
+/// Handle to a supervised agent that follows it across restarts
+///
+/// Clone it freely; every clone sees the current agent.
+#[derive(Clone)]
+pub struct SupervisedAgent {
+    name: Arc<str>,
+    current: Arc<RwLock<ActorHandle>>,
+    mailboxes: Arc<RwLock<Vec<Mailbox>>>,
+    restarts: Arc<AtomicUsize>,
+}
+
+impl std::fmt::Debug for SupervisedAgent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SupervisedAgent")
+            .field("name", &self.name)
+            .field("restarts", &self.restarts())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SupervisedAgent {
+    /// Wrap an agent handle
+    ///
+    /// A handle that is not registered with a [`Supervisor`] never changes.
+    #[must_use]
+    pub fn new(name: impl Into<Arc<str>>, handle: ActorHandle) -> Self {
+        Self {
+            name: name.into(),
+            current: Arc::new(RwLock::new(handle)),
+            mailboxes: Arc::new(RwLock::new(Vec::new())),
+            restarts: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Agent name
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Handle to the running agent
+    #[must_use]
+    pub fn handle(&self) -> ActorHandle {
+        self.current.read().clone()
+    }
+
+    /// Times the agent has been restarted
+    #[must_use]
+    pub fn restarts(&self) -> usize {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Point `mailbox` at the agent, now and after every restart
+    pub fn attach(&self, mailbox: &Mailbox) {
+        let mut mailboxes = self.mailboxes.write();
+        mailbox.retarget(self.handle());
+        mailboxes.push(mailbox.clone());
+    }
+
+    fn replace(&self, handle: &ActorHandle) {
+        *self.current.write() = handle.clone();
+        for mailbox in self.mailboxes.read().iter() {
+            mailbox.retarget(handle.clone());
+        }
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// An agent the supervisor gave up on
+#[derive(Debug, Clone)]
+pub struct Escalation {
+    /// Agent name
+    pub agent: String,
+    /// Why the agent stopped the last time
+    pub reason: TerminationReason,
+    /// Restarts within the window when the limit was hit
+    pub restarts: usize,
+    /// Length of the restart window in seconds
+    pub window_secs: u64,
+}
+
+/// Callback run when an agent exceeds its restart limit
+#[derive(Clone)]
+struct EscalationHandler(Arc<dyn Fn(&Escalation) + Send + Sync>);
+
+impl std::fmt::Debug for EscalationHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EscalationHandler")
+    }
+}
+
+/// Register an escalation handler
+#[derive(Clone, Debug)]
+struct AddEscalationHandler(EscalationHandler);
+
+/// Register a started agent
+#[derive(Clone, Debug)]
+struct RegisterChild {
+    id: String,
+    spec: ChildSpec,
+    agent: SupervisedAgent,
+}
+
+/// Supervised agent and its restart bookkeeping
+#[derive(Debug)]
+struct Child {
+    spec: ChildSpec,
+    agent: SupervisedAgent,
+    limiter: RestartLimiter,
+    generation: u64,
+}
+
+/// Supervisor actor model
+#[derive(Default)]
+pub struct SupervisorAgent {
+    runtime: Option<ActorRuntime>,
+    limits: RestartLimiterConfig,
+    children: HashMap<String, Child>,
+    escalation: Vec<EscalationHandler>,
+}
+
+impl std::fmt::Debug for SupervisorAgent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SupervisorAgent")
+            .field("limits", &self.limits)
+            .field("children", &self.children.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Handle to a running supervisor
+#[derive(Clone, Debug)]
+pub struct Supervisor {
+    handle: ActorHandle,
+}
+
+impl Supervisor {
+    /// Spawn a supervisor that restarts agents within `limits`
+    ///
+    /// Stopping the supervisor, including through
+    /// `ActorRuntime::shutdown_all`, stops every agent it supervises.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if actor initialization fails
+    pub async fn spawn(
+        runtime: &mut ActorRuntime,
+        limits: RestartLimiterConfig,
+    ) -> anyhow::Result<Self> {
+        let config = super::default_actor_config("supervisor")?;
+        let mut builder = runtime.new_actor_with_config::<SupervisorAgent>(config);
+        builder.model.runtime = Some(runtime.clone());
+        builder.model.limits = limits;
+
+        builder
+            .mutate_on::<RegisterChild>(|actor, context| {
+                let message = context.message().clone();
+                let child = Child {
+                    spec: message.spec,
+                    agent: message.agent,
+                    limiter: RestartLimiter::new(actor.model.limits.clone()),
+                    generation: 0,
+                };
+                actor.model.children.insert(message.id, child);
+                Reply::ready()
+            })
+            .mutate_on::<AddEscalationHandler>(|actor, context| {
+                actor.model.escalation.push(context.message().0.clone());
+                Reply::ready()
+            })
+            .mutate_on::<ChildTerminated>(|actor, context| {
+                let supervisor = actor.handle().clone();
+                let runtime = actor.model.runtime.clone();
+                let escalation = actor.model.escalation.clone();
+                let notification = context.message().clone();
+                handle_termination(
+                    &mut actor.model.children,
+                    &escalation,
+                    runtime,
+                    supervisor,
+                    notification,
+                );
+                Reply::ready()
+            })
+            .before_stop(|actor| {
+                let agents: Vec<ActorHandle> = actor
+                    .model
+                    .children
+                    .values()
+                    .map(|child| child.agent.handle())
+                    .collect();
+                async move {
+                    for agent in agents {
+                        let _ = agent.stop().await;
+                    }
+                }
+            });
+
+        Ok(Self {
+            handle: builder.start().await,
+        })
+    }
+
+    /// Supervisor actor handle
+    #[must_use]
+    pub const fn handle(&self) -> &ActorHandle {
+        &self.handle
+    }
+
+    /// Call `handler` whenever an agent exceeds its restart limit
+    pub async fn on_escalate<F>(&self, handler: F)
+    where
+        F: Fn(&Escalation) + Send + Sync + 'static,
+    {
+        self.handle
+            .send(AddEscalationHandler(EscalationHandler(Arc::new(handler))))
+            .await;
+    }
+
+    /// Start the agent described by `spec` under supervision
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the agent fails to start
+    pub async fn supervise(
+        &self,
+        runtime: &mut ActorRuntime,
+        spec: ChildSpec,
+    ) -> anyhow::Result<SupervisedAgent> {
+        let (_, config) = spec.incarnation(&self.handle, 0)?;
+        let handle = (spec.start)(runtime, config).await?;
+        let agent = SupervisedAgent::new(spec.name.as_str(), handle.clone());
+
+        // Registered before the watcher runs, so its report is never early
+        self.handle
+            .send(RegisterChild {
+                id: child_key(&handle.id()),
+                spec: spec.clone(),
+                agent: agent.clone(),
+            })
+            .await;
+        watch(handle, self.handle.clone(), spec.restart_policy);
+        Ok(agent)
+    }
+}
+
+/// Decide what to do about a stopped agent
+fn handle_termination(
+    children: &mut HashMap<String, Child>,
+    escalation: &[EscalationHandler],
+    runtime: Option<ActorRuntime>,
+    supervisor: ActorHandle,
+    notification: ChildTerminated,
+) {
+    // Reports about agents already replaced or stopped are stale
+    let Some(mut child) = children.remove(&child_key(&notification.child_id)) else {
+        return;
+    };
+    let name = child.spec.name.clone();
+    let reason = notification.reason;
+
+    if !notification.restart_policy.should_restart(&reason) {
+        tracing::info!(agent = %name, ?reason, "Supervised agent stopped");
+        return;
+    }
+    if let Err(exceeded) = child.limiter.can_restart() {
+        tracing::error!(
+            agent = %name,
+            ?reason,
+            restarts = exceeded.attempts,
+            window_secs = exceeded.window_secs,
+            "Agent exceeded its restart limit, escalating"
+        );
+        let escalation_event = Escalation {
+            agent: name,
+            reason,
+            restarts: exceeded.attempts,
+            window_secs: exceeded.window_secs,
+        };
+        for handler in escalation {
+            (handler.0)(&escalation_event);
+        }
+        return;
+    }
+    let Some(mut runtime) = runtime else {
+        return;
+    };
+
+    let backoff = child.limiter.record_restart();
+    child.generation += 1;
+    let (id, config) = match child.spec.incarnation(&supervisor, child.generation) {
+        Ok(incarnation) => incarnation,
+        Err(e) => {
+            tracing::error!(agent = %name, error = %e, "Failed to configure restarted agent");
+            return;
+        }
+    };
+    tracing::warn!(agent = %name, ?reason, ?backoff, "Restarting supervised agent");
+
+    // Keyed by the new incarnation before it exists, so its reports find it
+    let spec = child.spec.clone();
+    let agent = child.agent.clone();
+    children.insert(child_key(&id), child);
+
+    tokio::spawn(async move {
+        tokio::time::sleep(backoff).await;
+        let handle = match (spec.start)(&mut runtime, config).await {
+            Ok(handle) => handle,
+            Err(e) => {
+                tracing::error!(agent = %spec.name, error = %e, "Failed to restart agent");
+                // Counts against the restart limit like any other failure
+                supervisor
+                    .send(ChildTerminated::new(
+                        id,
+                        TerminationReason::Panic(e.to_string()),
+                        spec.restart_policy,
+                    ))
+                    .await;
+                return;
+            }
+        };
+        if let Some(rehydrate) = &spec.rehydrate {
+            if let Err(e) = rehydrate(handle.clone()).await {
+                tracing::error!(agent = %spec.name, error = %e, "Failed to re-hydrate restarted agent");
+            }
+        }
+        agent.replace(&handle);
+        tracing::info!(agent = %spec.name, restarts = agent.restarts(), "Supervised agent restarted");
+        watch(handle, supervisor, spec.restart_policy);
+    });
+}
+
+/// Key of a supervised agent: the name and generation parts of its ERN
+///
+/// The runtime derives its own root for child ERNs, so only the parts are
+/// compared.
+fn child_key(id: &Ern) -> String {
+    let id = id.to_string();
+    match id.split_once('/') {
+        Some((_, parts)) => parts.to_string(),
+        None => id,
+    }
+}
+
+/// Report the agent to the supervisor if its task ends without saying so
+///
+/// An agent that stops on its own reports first, which makes this report
+/// stale.
+fn watch(handle: ActorHandle, supervisor: ActorHandle, policy: RestartPolicy) {
+    tokio::spawn(async move {
+        handle.tracker().wait().await;
+        supervisor
+            .send(ChildTerminated::new(
+                handle.id().clone(),
+                TerminationReason::Panic("agent task ended".to_string()),
+                policy,
+            ))
+            .await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    struct Ping;
+
+    #[derive(Debug, Clone)]
+    struct GetPings(Arc<parking_lot::Mutex<Option<tokio::sync::oneshot::Sender<usize>>>>);
+
+    #[derive(Debug, Default, Clone)]
+    struct Counter {
+        pings: usize,
+    }
+
+    fn counter_spec() -> ChildSpec {
+        ChildSpec::new("counter", |runtime, config| {
+            let mut builder = runtime.new_actor_with_config::<Counter>(config);
+            builder
+                .mutate_on::<Ping>(|actor, _context| {
+                    actor.model.pings += 1;
+                    Reply::ready()
+                })
+                .act_on::<GetPings>(|actor, context| {
+                    let pings = actor.model.pings;
+                    let tx = context.message().0.lock().take();
+                    if let Some(tx) = tx {
+                        let _ = tx.send(pings);
+                    }
+                    Reply::ready()
+                });
+            Box::pin(async move { Ok(builder.start().await) })
+        })
+    }
+
+    fn fast_limits(max_restarts: u32) -> RestartLimiterConfig {
+        RestartLimiterConfig {
+            max_restarts,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 1,
+            ..RestartLimiterConfig::default()
+        }
+    }
+
+    async fn pings(handle: &ActorHandle) -> usize {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        handle
+            .send(GetPings(Arc::new(parking_lot::Mutex::new(Some(tx)))))
+            .await;
+        tokio::time::timeout(Duration::from_secs(1), rx)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn restarted(agent: &SupervisedAgent, restarts: usize) {
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while agent.restarts() < restarts {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("agent was not restarted");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_restarts_stopped_agent() {
+        let mut runtime = ActonApp::launch_async().await;
+        let supervisor = Supervisor::spawn(&mut runtime, fast_limits(5))
+            .await
+            .unwrap();
+        let rehydrated = Arc::new(AtomicUsize::new(0));
+        let hook_count = Arc::clone(&rehydrated);
+        let spec = counter_spec()
+            .with_restart_policy(RestartPolicy::Permanent)
+            .with_rehydrate(move |handle| {
+                hook_count.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    handle.send(Ping).await;
+                    Ok(())
+                }
+            });
+        let agent = supervisor.supervise(&mut runtime, spec).await.unwrap();
+        let mailbox = Mailbox::spawn(
+            "counter",
+            crate::htmx::agents::MailboxConfig::default(),
+            agent.handle(),
+        );
+        agent.attach(&mailbox);
+
+        let first = agent.handle();
+        let _ = first.stop().await;
+        restarted(&agent, 1).await;
+
+        let second = agent.handle();
+        assert_ne!(first.id(), second.id());
+        assert_eq!(rehydrated.load(Ordering::SeqCst), 1);
+
+        mailbox.send(Ping).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pings(&second).await, 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_escalates_past_restart_limit() {
+        let mut runtime = ActonApp::launch_async().await;
+        let supervisor = Supervisor::spawn(&mut runtime, fast_limits(1))
+            .await
+            .unwrap();
+        let escalated = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&escalated);
+        supervisor
+            .on_escalate(move |escalation| {
+                assert_eq!(escalation.agent, "counter");
+                flag.store(true, Ordering::SeqCst);
+            })
+            .await;
+        let spec = counter_spec().with_restart_policy(RestartPolicy::Permanent);
+        let agent = supervisor.supervise(&mut runtime, spec).await.unwrap();
+
+        let _ = agent.handle().stop().await;
+        restarted(&agent, 1).await;
+        let _ = agent.handle().stop().await;
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while !escalated.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("escalation handler was not called");
+        assert_eq!(agent.restarts(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_transient_agent_stopped_normally_stays_down() {
+        let mut runtime = ActonApp::launch_async().await;
+        let supervisor = Supervisor::spawn(&mut runtime, fast_limits(5))
+            .await
+            .unwrap();
+        let agent = supervisor
+            .supervise(&mut runtime, counter_spec())
+            .await
+            .unwrap();
+
+        let _ = agent.handle().stop().await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(agent.restarts(), 0);
+    }
+}
@@ -0,0 +1,241 @@
+//! Analytics store backed by data-service
+
+use async_trait::async_trait;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{day_of, AnalyticsError, AnalyticsEvent, AnalyticsReport, AnalyticsStore, DailyCount};
+use crate::htmx::clients::{DataClient, Row, Value};
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+/// Migration creating the analytics event table
+pub const ANALYTICS_MIGRATION: &str = r"CREATE TABLE IF NOT EXISTS analytics_events (
+    name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    referrer TEXT,
+    visitor TEXT NOT NULL,
+    properties TEXT NOT NULL,
+    occurred_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_analytics_events_occurred ON analytics_events (occurred_at);
+CREATE INDEX IF NOT EXISTS idx_analytics_events_name ON analytics_events (name, occurred_at);";
+
+const COLUMNS: usize = 6;
+
+/// Analytics store writing batches to `analytics_events`
+///
+/// Each batch is one multi-row `INSERT`; reports are aggregated in SQL.
+#[derive(Debug, Clone)]
+pub struct DataServiceAnalyticsStore {
+    client: Arc<RwLock<DataClient>>,
+}
+
+impl DataServiceAnalyticsStore {
+    /// Create a store
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<DataClient>>) -> Self {
+        Self { client }
+    }
+
+    async fn query(&self, sql: &str, params: Vec<Value>) -> Result<Vec<Row>, AnalyticsError> {
+        self.client
+            .write()
+            .await
+            .query(sql, params, None)
+            .await
+            .map_err(|e| AnalyticsError::Store(e.to_string()))
+    }
+
+    async fn top(
+        &self,
+        column: &str,
+        filter: &str,
+        since: i64,
+        until: i64,
+        limit: usize,
+    ) -> Result<Vec<(String, u64)>, AnalyticsError> {
+        let rows = self
+            .query(
+                &format!(
+                    "SELECT {column} AS key, COUNT(*) AS count FROM analytics_events \
+                     WHERE occurred_at >= $1 AND occurred_at < $2 AND {filter} \
+                     GROUP BY {column} ORDER BY count DESC, {column} LIMIT $3"
+                ),
+                vec![
+                    int(since),
+                    int(until),
+                    int(i64::try_from(limit).unwrap_or(i64::MAX)),
+                ],
+            )
+            .await?;
+        rows.iter()
+            .map(|row| Ok((string(row, "key")?, count(row, "count")?)))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl AnalyticsStore for DataServiceAnalyticsStore {
+    async fn insert(&self, events: &[AnalyticsEvent]) -> Result<(), AnalyticsError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let mut sql = String::from(
+            "INSERT INTO analytics_events \
+             (name, path, referrer, visitor, properties, occurred_at) VALUES ",
+        );
+        let mut params = Vec::with_capacity(events.len() * COLUMNS);
+        for (i, event) in events.iter().enumerate() {
+            let first = i * COLUMNS + 1;
+            let placeholders = (first..first + COLUMNS)
+                .map(|n| format!("${n}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let separator = if i == 0 { "" } else { ", " };
+            let _ = write!(sql, "{separator}({placeholders})");
+            let properties = serde_json::to_string(&event.properties)
+                .map_err(|e| AnalyticsError::Store(e.to_string()))?;
+            params.extend([
+                text(event.name.clone()),
+                text(event.path.clone()),
+                event.referrer.clone().map_or(
+                    Value {
+                        value: Some(ValueKind::NullValue(true)),
+                    },
+                    text,
+                ),
+                text(event.visitor.clone()),
+                text(properties),
+                int(event.occurred_at),
+            ]);
+        }
+        self.client
+            .write()
+            .await
+            .execute(&sql, params, None)
+            .await
+            .map_err(|e| AnalyticsError::Store(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn report(
+        &self,
+        since: i64,
+        until: i64,
+        limit: usize,
+    ) -> Result<AnalyticsReport, AnalyticsError> {
+        let period = || vec![int(since), int(until)];
+        let totals = self
+            .query(
+                "SELECT COUNT(CASE WHEN name = 'pageview' THEN 1 END) AS page_views, \
+                 COUNT(CASE WHEN name <> 'pageview' THEN 1 END) AS events, \
+                 COUNT(DISTINCT visitor) AS visitors FROM analytics_events \
+                 WHERE occurred_at >= $1 AND occurred_at < $2",
+                period(),
+            )
+            .await?;
+        let daily = self
+            .query(
+                "SELECT occurred_at / 86400 AS day, COUNT(*) AS page_views, \
+                 COUNT(DISTINCT visitor) AS visitors FROM analytics_events \
+                 WHERE occurred_at >= $1 AND occurred_at < $2 AND name = 'pageview' \
+                 GROUP BY occurred_at / 86400 ORDER BY day",
+                period(),
+            )
+            .await?;
+
+        let mut report = AnalyticsReport::default();
+        if let Some(row) = totals.first() {
+            report.page_views = count(row, "page_views")?;
+            report.events = count(row, "events")?;
+            report.visitors = count(row, "visitors")?;
+        }
+        report.daily = daily
+            .iter()
+            .map(|row| {
+                Ok(DailyCount {
+                    date: day_of(integer(row, "day")? * 86_400),
+                    page_views: count(row, "page_views")?,
+                    visitors: count(row, "visitors")?,
+                })
+            })
+            .collect::<Result<_, AnalyticsError>>()?;
+        report.top_pages = self
+            .top("path", "name = 'pageview'", since, until, limit)
+            .await?;
+        report.top_referrers = self
+            .top(
+                "referrer",
+                "name = 'pageview' AND referrer IS NOT NULL",
+                since,
+                until,
+                limit,
+            )
+            .await?;
+        report.top_events = self
+            .top("name", "name <> 'pageview'", since, until, limit)
+            .await?;
+        Ok(report)
+    }
+}
+
+fn integer(row: &Row, name: &str) -> Result<i64, AnalyticsError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::IntValue(value)) => Ok(*value),
+        _ => Err(AnalyticsError::Store(format!(
+            "invalid analytics column {name}"
+        ))),
+    }
+}
+
+fn count(row: &Row, name: &str) -> Result<u64, AnalyticsError> {
+    u64::try_from(integer(row, name)?)
+        .map_err(|_| AnalyticsError::Store(format!("invalid analytics column {name}")))
+}
+
+fn string(row: &Row, name: &str) -> Result<String, AnalyticsError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::StringValue(value)) => Ok(value.clone()),
+        _ => Err(AnalyticsError::Store(format!(
+            "invalid analytics column {name}"
+        ))),
+    }
+}
+
+const fn int(value: i64) -> Value {
+    Value {
+        value: Some(ValueKind::IntValue(value)),
+    }
+}
+
+const fn text(value: String) -> Value {
+    Value {
+        value: Some(ValueKind::StringValue(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_helpers() {
+        let row = Row {
+            columns: [
+                ("key".to_string(), text("/docs".to_string())),
+                ("count".to_string(), int(42)),
+                ("day".to_string(), int(20_148)),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        assert_eq!(string(&row, "key").unwrap(), "/docs");
+        assert_eq!(count(&row, "count").unwrap(), 42);
+        assert_eq!(
+            day_of(integer(&row, "day").unwrap() * 86_400).to_string(),
+            "2025-03-01"
+        );
+        assert!(count(&row, "key").is_err());
+    }
+}
@@ -0,0 +1,993 @@
+//! First-party, anonymized analytics
+//!
+//! Page views and custom events are sent by a small script to a beacon
+//! endpoint on the app itself, so there is no third-party tracker and no
+//! cookie. Visitors are counted with a hash of their IP address and user
+//! agent salted with a key that rotates daily; the raw values are never
+//! stored and the same visitor can't be followed from one day to the next.
+//! Page URLs are stored without query strings and referrers as a host name.
+//!
+//! - [`Analytics::routes`] serves the script and the beacon endpoint
+//! - [`snippet`] renders the script tag for the base layout
+//! - events are buffered and written to an [`AnalyticsStore`] in batches,
+//!   when [`AnalyticsConfig::batch_size`] events are waiting or on the
+//!   interval run by [`Analytics::spawn_flusher`]
+//! - [`Analytics::dashboard_routes`] serves an HTMX dashboard partial with
+//!   visitors, page views, top pages, referrers and events
+//!
+//! With [`AnalyticsConfig::require_consent`] (the default), events are only
+//! recorded for visitors whose [`Consent`] allows the configured category,
+//! so [`ConsentManager::middleware`](crate::htmx::consent::ConsentManager::middleware)
+//! must run in front of the beacon. Browsers sending `DNT` or `Sec-GPC`
+//! are ignored unless [`AnalyticsConfig::honor_do_not_track`] is off.
+//!
+//! The beacon is posted by script without a CSRF token: add
+//! [`BEACON_PATH`] to `CsrfConfig::skip_paths` when CSRF protection is on.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::analytics::{self, Analytics, MemoryAnalyticsStore};
+//!
+//! let analytics = Analytics::new(config.analytics.clone(), Arc::new(MemoryAnalyticsStore::new()))
+//!     .with_secret(&config.security.secret_key);
+//! analytics.spawn_flusher();
+//!
+//! let app = Router::new()
+//!     .route("/", get(home))
+//!     .merge(analytics.routes())
+//!     .nest("/admin", analytics.dashboard_routes().layer(require_admin))
+//!     .layer(from_fn_with_state(consent.clone(), ConsentManager::middleware));
+//!
+//! // In the layout template: {{ analytics_snippet|safe }}
+//! let analytics_snippet = analytics::snippet();
+//! ```
+//!
+//! Custom events are sent from the page with
+//! `actonAnalytics.track("signup", {plan: "pro"})`, or declaratively with a
+//! `data-analytics-event="signup"` attribute on any clickable element.
+
+#[cfg(feature = "microservices")]
+mod data_service;
+
+#[cfg(feature = "microservices")]
+pub use data_service::{DataServiceAnalyticsStore, ANALYTICS_MIGRATION};
+
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::{OriginalUri, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+pub use crate::htmx::config::AnalyticsConfig;
+use crate::htmx::consent::Consent;
+use crate::htmx::middleware::client_ip;
+use crate::htmx::template::helpers::escape_html;
+
+/// Path the script is served from
+pub const SCRIPT_PATH: &str = "/_acton/analytics.js";
+
+/// Path events are posted to
+pub const BEACON_PATH: &str = "/_acton/analytics";
+
+/// Event name of page views
+pub const PAGE_VIEW: &str = "pageview";
+
+/// Largest accepted beacon body
+const MAX_BEACON_BYTES: usize = 4096;
+const MAX_NAME_LEN: usize = 64;
+const MAX_PATH_LEN: usize = 512;
+const MAX_PROPERTY_LEN: usize = 256;
+
+/// Batches kept in memory while the store is failing
+const MAX_BUFFERED_BATCHES: usize = 10;
+
+/// Analytics errors
+#[derive(Debug, thiserror::Error)]
+pub enum AnalyticsError {
+    /// The backing store failed
+    #[error("Analytics store error: {0}")]
+    Store(String),
+}
+
+impl IntoResponse for AnalyticsError {
+    fn into_response(self) -> Response {
+        warn!(error = %self, "Analytics store failed");
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+/// One recorded page view or custom event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalyticsEvent {
+    /// [`PAGE_VIEW`] or the custom event name
+    pub name: String,
+    /// Page path, without query string or fragment
+    pub path: String,
+    /// Host of an external referrer
+    pub referrer: Option<String>,
+    /// Daily visitor hash
+    pub visitor: String,
+    /// Custom event properties
+    pub properties: BTreeMap<String, String>,
+    /// Unix time (seconds) the event was received
+    pub occurred_at: i64,
+}
+
+impl AnalyticsEvent {
+    /// Whether the event is a page view
+    #[must_use]
+    pub fn is_page_view(&self) -> bool {
+        self.name == PAGE_VIEW
+    }
+}
+
+/// Visitors and page views on one day (UTC)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyCount {
+    /// The day
+    pub date: NaiveDate,
+    /// Page views that day
+    pub page_views: u64,
+    /// Distinct visitors that day
+    pub visitors: u64,
+}
+
+/// Aggregates over a period
+///
+/// Visitor hashes rotate daily, so `visitors` counts someone who came back
+/// on three days three times.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalyticsReport {
+    /// Page views
+    pub page_views: u64,
+    /// Distinct daily visitors
+    pub visitors: u64,
+    /// Custom events
+    pub events: u64,
+    /// Per-day counts, oldest first, for days with page views
+    pub daily: Vec<DailyCount>,
+    /// Most viewed paths with their page views
+    pub top_pages: Vec<(String, u64)>,
+    /// Most common referrer hosts with their page views
+    pub top_referrers: Vec<(String, u64)>,
+    /// Most common custom events with their counts
+    pub top_events: Vec<(String, u64)>,
+}
+
+impl AnalyticsReport {
+    /// Aggregate `events`, keeping `limit` entries in each top list
+    #[must_use]
+    pub fn from_events<'a>(
+        events: impl IntoIterator<Item = &'a AnalyticsEvent>,
+        limit: usize,
+    ) -> Self {
+        let mut report = Self::default();
+        let mut visitors = HashSet::new();
+        let mut days: BTreeMap<NaiveDate, (u64, HashSet<&str>)> = BTreeMap::new();
+        let mut pages = HashMap::new();
+        let mut referrers = HashMap::new();
+        let mut custom = HashMap::new();
+        for event in events {
+            visitors.insert(event.visitor.as_str());
+            if event.is_page_view() {
+                report.page_views += 1;
+                let day = days.entry(day_of(event.occurred_at)).or_default();
+                day.0 += 1;
+                day.1.insert(event.visitor.as_str());
+                *pages.entry(event.path.clone()).or_default() += 1;
+                if let Some(referrer) = &event.referrer {
+                    *referrers.entry(referrer.clone()).or_default() += 1;
+                }
+            } else {
+                report.events += 1;
+                *custom.entry(event.name.clone()).or_default() += 1;
+            }
+        }
+        report.visitors = visitors.len() as u64;
+        report.daily = days
+            .into_iter()
+            .map(|(date, (page_views, visitors))| DailyCount {
+                date,
+                page_views,
+                visitors: visitors.len() as u64,
+            })
+            .collect();
+        report.top_pages = top(pages, limit);
+        report.top_referrers = top(referrers, limit);
+        report.top_events = top(custom, limit);
+        report
+    }
+}
+
+fn top(counts: HashMap<String, u64>, limit: usize) -> Vec<(String, u64)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(limit);
+    counts
+}
+
+fn day_of(timestamp: i64) -> NaiveDate {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .date_naive()
+}
+
+/// Storage for recorded events
+#[async_trait]
+pub trait AnalyticsStore: Send + Sync {
+    /// Append a batch of events
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AnalyticsError::Store`] on failure.
+    async fn insert(&self, events: &[AnalyticsEvent]) -> Result<(), AnalyticsError>;
+
+    /// Aggregate events received in `since..until` (Unix seconds), keeping
+    /// `limit` entries in each top list
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AnalyticsError::Store`] on failure.
+    async fn report(
+        &self,
+        since: i64,
+        until: i64,
+        limit: usize,
+    ) -> Result<AnalyticsReport, AnalyticsError>;
+}
+
+/// In-process analytics store
+#[derive(Debug, Default)]
+pub struct MemoryAnalyticsStore {
+    events: Mutex<Vec<AnalyticsEvent>>,
+}
+
+impl MemoryAnalyticsStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every stored event, oldest first
+    #[must_use]
+    pub fn events(&self) -> Vec<AnalyticsEvent> {
+        self.events.lock().clone()
+    }
+}
+
+#[async_trait]
+impl AnalyticsStore for MemoryAnalyticsStore {
+    async fn insert(&self, events: &[AnalyticsEvent]) -> Result<(), AnalyticsError> {
+        self.events.lock().extend_from_slice(events);
+        Ok(())
+    }
+
+    async fn report(
+        &self,
+        since: i64,
+        until: i64,
+        limit: usize,
+    ) -> Result<AnalyticsReport, AnalyticsError> {
+        let events = self.events.lock();
+        Ok(AnalyticsReport::from_events(
+            events
+                .iter()
+                .filter(|event| (since..until).contains(&event.occurred_at)),
+            limit,
+        ))
+    }
+}
+
+/// Source of the daily visitor salt
+#[derive(Default)]
+struct Salt {
+    secret: Option<Vec<u8>>,
+    /// Random salt for the current day when there is no secret
+    random: Mutex<Option<(NaiveDate, [u8; 32])>>,
+}
+
+impl Salt {
+    fn for_day(&self, day: NaiveDate) -> [u8; 32] {
+        if let Some(secret) = &self.secret {
+            let mut hasher = Sha256::new();
+            hasher.update(secret);
+            hasher.update(day.to_string());
+            return hasher.finalize().into();
+        }
+        let mut random = self.random.lock();
+        match *random {
+            Some((current, salt)) if current == day => salt,
+            _ => {
+                let mut salt = [0u8; 32];
+                rand::rng().fill(&mut salt);
+                *random = Some((day, salt));
+                salt
+            }
+        }
+    }
+}
+
+/// Collects events from the beacon and writes them in batches
+#[derive(Clone)]
+pub struct Analytics {
+    config: Arc<AnalyticsConfig>,
+    store: Arc<dyn AnalyticsStore>,
+    buffer: Arc<Mutex<Vec<AnalyticsEvent>>>,
+    salt: Arc<Salt>,
+}
+
+impl std::fmt::Debug for Analytics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Analytics")
+            .field("config", &self.config)
+            .field("buffered", &self.buffer.lock().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Event posted by the script
+#[derive(Debug, Deserialize)]
+struct Beacon {
+    #[serde(default = "page_view_name")]
+    name: String,
+    url: String,
+    #[serde(default)]
+    referrer: Option<String>,
+    #[serde(default)]
+    props: BTreeMap<String, serde_json::Value>,
+}
+
+fn page_view_name() -> String {
+    PAGE_VIEW.to_string()
+}
+
+/// Dashboard query string
+#[derive(Debug, Deserialize)]
+struct DashboardQuery {
+    days: Option<u32>,
+}
+
+impl Analytics {
+    /// Create a collector writing to `store`
+    ///
+    /// Without [`Analytics::with_secret`] the daily salt is random per
+    /// process, so each instance behind a load balancer counts visitors
+    /// separately.
+    #[must_use]
+    pub fn new(config: AnalyticsConfig, store: Arc<dyn AnalyticsStore>) -> Self {
+        Self {
+            config: Arc::new(config),
+            store,
+            buffer: Arc::new(Mutex::new(Vec::new())),
+            salt: Arc::new(Salt::default()),
+        }
+    }
+
+    /// Derive the daily salt from `secret`, so every instance sharing it
+    /// hashes a visitor the same way
+    #[must_use]
+    pub fn with_secret(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.salt = Arc::new(Salt {
+            secret: Some(secret.as_ref().to_vec()),
+            random: Mutex::new(None),
+        });
+        self
+    }
+
+    /// Collection settings
+    #[must_use]
+    pub fn config(&self) -> &AnalyticsConfig {
+        &self.config
+    }
+
+    /// Routes: `GET /_acton/analytics.js` (the script) and
+    /// `POST /_acton/analytics` (the beacon)
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route(SCRIPT_PATH, get(script_handler))
+            .route(BEACON_PATH, post(beacon_handler))
+            .with_state(self.clone())
+    }
+
+    /// Routes: `GET /analytics` (dashboard partial, `?days=` defaults to 30)
+    ///
+    /// The dashboard is not protected: nest it under admin-only routes.
+    pub fn dashboard_routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/analytics", get(dashboard_handler))
+            .with_state(self.clone())
+    }
+
+    /// Buffer an event, writing the buffer when it reaches the batch size
+    pub async fn record(&self, event: AnalyticsEvent) {
+        let batch = {
+            let mut buffer = self.buffer.lock();
+            buffer.push(event);
+            (buffer.len() >= self.config.batch_size).then(|| std::mem::take(&mut *buffer))
+        };
+        if let Some(batch) = batch {
+            self.write(batch).await;
+        }
+    }
+
+    /// Write buffered events now, returning how many were written
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails; the events stay buffered.
+    pub async fn flush(&self) -> Result<usize, AnalyticsError> {
+        let batch = std::mem::take(&mut *self.buffer.lock());
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        match self.store.insert(&batch).await {
+            Ok(()) => Ok(batch.len()),
+            Err(e) => {
+                self.requeue(batch);
+                Err(e)
+            }
+        }
+    }
+
+    /// Run [`Analytics::flush`] every `flush_interval_secs` on a background
+    /// task
+    #[allow(clippy::must_use_candidate)] // the task runs detached
+    pub fn spawn_flusher(&self) -> tokio::task::JoinHandle<()> {
+        let analytics = self.clone();
+        let interval = self.config.flush_interval().max(Duration::from_secs(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = analytics.flush().await {
+                    warn!(error = %e, "Analytics flush failed");
+                }
+            }
+        })
+    }
+
+    /// Aggregates for the last `days` days (UTC), including today
+    ///
+    /// Buffered events are counted once written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    pub async fn report(&self, days: u32) -> Result<AnalyticsReport, AnalyticsError> {
+        let now = Utc::now();
+        let start = now.date_naive() - chrono::Days::new(u64::from(days.max(1)) - 1);
+        let since = start
+            .and_hms_opt(0, 0, 0)
+            .map_or(0, |start| start.and_utc().timestamp());
+        self.store
+            .report(since, now.timestamp() + 1, 10)
+            .await
+    }
+
+    /// Anonymized visitor id: a hash of the salt for `day`, the site host,
+    /// the client IP and the user agent
+    fn visitor(&self, day: NaiveDate, host: &str, ip: &str, user_agent: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.for_day(day));
+        for part in [host, ip, user_agent] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.finalize()[..8]
+            .iter()
+            .fold(String::with_capacity(16), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+
+    /// Whether collection is allowed for this request
+    fn collects(&self, consent: &Consent, headers: &HeaderMap) -> bool {
+        if self.config.require_consent && !consent.allows(&self.config.consent_category) {
+            return false;
+        }
+        let opted_out = ["dnt", "sec-gpc"]
+            .iter()
+            .any(|name| headers.get(*name).is_some_and(|value| value == "1"));
+        !(self.config.honor_do_not_track && opted_out)
+    }
+
+    /// Build an event from a beacon, or `None` if it is malformed
+    fn event(&self, beacon: Beacon, headers: &HeaderMap, ip: &str) -> Option<AnalyticsEvent> {
+        let name = beacon.name.trim();
+        if !valid_name(name) {
+            return None;
+        }
+        let path = page_path(&beacon.url)?;
+        let host = header_str(headers, header::HOST);
+        let host = host.split(':').next().unwrap_or_default();
+        let referrer = beacon
+            .referrer
+            .as_deref()
+            .and_then(referrer_host)
+            .filter(|referrer| referrer != host);
+        let properties = if name == PAGE_VIEW {
+            BTreeMap::new()
+        } else {
+            beacon
+                .props
+                .into_iter()
+                .filter(|(key, _)| valid_name(key))
+                .filter_map(|(key, value)| {
+                    let value = match value {
+                        serde_json::Value::String(value) => value,
+                        serde_json::Value::Number(_) | serde_json::Value::Bool(_) => {
+                            value.to_string()
+                        }
+                        _ => return None,
+                    };
+                    Some((key, truncate(&value, MAX_PROPERTY_LEN)))
+                })
+                .take(self.config.max_properties)
+                .collect()
+        };
+        let now = Utc::now();
+        Some(AnalyticsEvent {
+            name: name.to_string(),
+            path,
+            referrer,
+            visitor: self.visitor(
+                now.date_naive(),
+                host,
+                ip,
+                header_str(headers, header::USER_AGENT),
+            ),
+            properties,
+            occurred_at: now.timestamp(),
+        })
+    }
+
+    async fn write(&self, batch: Vec<AnalyticsEvent>) {
+        if let Err(e) = self.store.insert(&batch).await {
+            warn!(error = %e, events = batch.len(), "Failed to write analytics events");
+            self.requeue(batch);
+        }
+    }
+
+    /// Put a failed batch back in front of newer events, dropping the
+    /// oldest once too many are waiting
+    fn requeue(&self, mut batch: Vec<AnalyticsEvent>) {
+        let mut buffer = self.buffer.lock();
+        batch.append(&mut buffer);
+        let limit = self.config.batch_size.max(1) * MAX_BUFFERED_BATCHES;
+        if batch.len() > limit {
+            batch.drain(..batch.len() - limit);
+        }
+        *buffer = batch;
+    }
+}
+
+/// Script tag for the base layout
+#[must_use]
+pub fn snippet() -> String {
+    format!(r#"<script src="{SCRIPT_PATH}" data-endpoint="{BEACON_PATH}" defer></script>"#)
+}
+
+/// Dashboard partial for `report` over `days` days
+///
+/// `base` is the dashboard's own path, used by the period links.
+#[must_use]
+pub fn dashboard_partial(report: &AnalyticsReport, days: u32, base: &str) -> String {
+    let mut html = String::from(r#"<div id="analytics-dashboard" class="analytics-dashboard">"#);
+    html.push_str(r#"<nav class="analytics-periods">"#);
+    for period in [7, 30, 90] {
+        let current = if period == days {
+            r#" aria-current="true""#
+        } else {
+            ""
+        };
+        let _ = write!(
+            html,
+            r##"<a href="{base}?days={period}" hx-get="{base}?days={period}" hx-target="#analytics-dashboard" hx-swap="outerHTML"{current}>Last {period} days</a>"##,
+            base = escape_html(base),
+        );
+    }
+    html.push_str("</nav>");
+
+    html.push_str(r#"<dl class="analytics-summary">"#);
+    for (label, value) in [
+        ("Visitors", report.visitors),
+        ("Page views", report.page_views),
+        ("Events", report.events),
+    ] {
+        let _ = write!(html, "<div><dt>{label}</dt><dd>{value}</dd></div>");
+    }
+    html.push_str("</dl>");
+
+    let busiest = report
+        .daily
+        .iter()
+        .map(|day| day.page_views)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    html.push_str(r#"<table class="analytics-daily"><thead><tr><th>Day</th><th>Visitors</th><th>Page views</th></tr></thead><tbody>"#);
+    for day in &report.daily {
+        let _ = write!(
+            html,
+            r#"<tr><td>{}</td><td>{}</td><td><span class="analytics-bar" style="width: {}%"></span>{}</td></tr>"#,
+            day.date,
+            day.visitors,
+            day.page_views * 100 / busiest,
+            day.page_views
+        );
+    }
+    html.push_str("</tbody></table>");
+
+    for (title, column, rows) in [
+        ("Top pages", "Page", &report.top_pages),
+        ("Top referrers", "Referrer", &report.top_referrers),
+        ("Top events", "Event", &report.top_events),
+    ] {
+        let _ = write!(
+            html,
+            r#"<table class="analytics-top"><caption>{title}</caption><thead><tr><th>{column}</th><th>Count</th></tr></thead><tbody>"#
+        );
+        if rows.is_empty() {
+            html.push_str(r#"<tr><td colspan="2">No data yet</td></tr>"#);
+        }
+        for (key, count) in rows {
+            let _ = write!(html, "<tr><td>{}</td><td>{count}</td></tr>", escape_html(key));
+        }
+        html.push_str("</tbody></table>");
+    }
+    html.push_str("</div>");
+    html
+}
+
+/// Event and property names: letters, digits and `_ - . :`
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+}
+
+/// Path of a page URL, without query string or fragment
+fn page_path(url: &str) -> Option<String> {
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => &rest[rest.find('/').unwrap_or(rest.len())..],
+        None => url,
+    };
+    let path = rest.split(['?', '#']).next().unwrap_or_default();
+    let path = if path.is_empty() { "/" } else { path };
+    path.starts_with('/').then(|| truncate(path, MAX_PATH_LEN))
+}
+
+/// Lowercase host of a referrer URL
+fn referrer_host(referrer: &str) -> Option<String> {
+    let (_, rest) = referrer.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default();
+    (!host.is_empty()).then(|| truncate(&host.to_ascii_lowercase(), MAX_NAME_LEN * 4))
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    value.chars().take(max_chars).collect()
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> &str {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+}
+
+#[allow(clippy::unused_async)] // axum handler
+async fn script_handler() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "application/javascript"),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        SCRIPT,
+    )
+}
+
+/// Accepts an event; always `204` unless the body is malformed, so the
+/// response doesn't reveal whether the visitor is being counted
+async fn beacon_handler(
+    State(analytics): State<Analytics>,
+    consent: Consent,
+    request: Request,
+) -> StatusCode {
+    let (parts, body) = request.into_parts();
+    if !analytics.collects(&consent, &parts.headers) {
+        return StatusCode::NO_CONTENT;
+    }
+    let Ok(body): Result<Bytes, _> = axum::body::to_bytes(body, MAX_BEACON_BYTES).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE;
+    };
+    // sendBeacon may post JSON as text/plain, so the content type is ignored
+    let Ok(beacon) = serde_json::from_slice::<Beacon>(&body) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    let ip = client_ip(&parts.extensions).map(|ip| ip.to_string()).unwrap_or_default();
+    let Some(event) = analytics.event(beacon, &parts.headers, &ip) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    analytics.record(event).await;
+    StatusCode::NO_CONTENT
+}
+
+async fn dashboard_handler(
+    State(analytics): State<Analytics>,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Html<String>, AnalyticsError> {
+    let days = query.days.unwrap_or(30).clamp(1, 366);
+    let report = analytics.report(days).await?;
+    Ok(Html(dashboard_partial(&report, days, uri.path())))
+}
+
+const SCRIPT: &str = r#"(function () {
+    "use strict";
+    var script = document.currentScript;
+    var endpoint = (script && script.dataset.endpoint) || "/_acton/analytics";
+    var referrer = document.referrer || null;
+    var lastPath = null;
+
+    function send(name, props) {
+        var body = JSON.stringify({
+            name: name,
+            url: location.pathname,
+            referrer: referrer,
+            props: props || {}
+        });
+        if (navigator.sendBeacon && navigator.sendBeacon(endpoint, body)) {
+            return;
+        }
+        fetch(endpoint, {
+            method: "POST",
+            body: body,
+            credentials: "same-origin",
+            keepalive: true
+        }).catch(function () {});
+    }
+
+    function pageview() {
+        if (location.pathname === lastPath) {
+            return;
+        }
+        lastPath = location.pathname;
+        send("pageview");
+        // Later navigations are internal
+        referrer = null;
+    }
+
+    window.actonAnalytics = {
+        track: function (name, props) {
+            send(String(name), props);
+        }
+    };
+
+    document.addEventListener("click", function (evt) {
+        var el = evt.target.closest && evt.target.closest("[data-analytics-event]");
+        if (el) {
+            send(el.getAttribute("data-analytics-event"));
+        }
+    });
+    document.addEventListener("htmx:pushedIntoHistory", pageview);
+    document.addEventListener("htmx:replacedInHistory", pageview);
+    window.addEventListener("popstate", pageview);
+    pageview();
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::config::ConsentConfig;
+    use crate::htmx::consent::ConsentManager;
+    use axum::body::Body;
+    use axum::middleware::from_fn_with_state;
+    use tower::ServiceExt;
+
+    fn collector(config: AnalyticsConfig) -> (Analytics, Arc<MemoryAnalyticsStore>) {
+        let store = Arc::new(MemoryAnalyticsStore::new());
+        (Analytics::new(config, store.clone()).with_secret("secret"), store)
+    }
+
+    fn event(name: &str, path: &str, visitor: &str, occurred_at: i64) -> AnalyticsEvent {
+        AnalyticsEvent {
+            name: name.to_string(),
+            path: path.to_string(),
+            referrer: None,
+            visitor: visitor.to_string(),
+            properties: BTreeMap::new(),
+            occurred_at,
+        }
+    }
+
+    #[test]
+    fn test_url_cleaning() {
+        assert_eq!(page_path("/docs?q=secret#top").as_deref(), Some("/docs"));
+        assert_eq!(
+            page_path("https://example.com/a/b?token=x").as_deref(),
+            Some("/a/b")
+        );
+        assert_eq!(page_path("https://example.com").as_deref(), Some("/"));
+        assert!(page_path("javascript:alert(1)").is_none());
+
+        assert_eq!(
+            referrer_host("https://user:pw@News.Example.com:443/item?id=1").as_deref(),
+            Some("news.example.com")
+        );
+        assert!(referrer_host("android-app").is_none());
+
+        assert!(valid_name("signup"));
+        assert!(valid_name("checkout:step-2"));
+        assert!(!valid_name(""));
+        assert!(!valid_name("<script>"));
+    }
+
+    #[test]
+    fn test_visitor_hash_rotates_daily() {
+        let (analytics, _) = collector(AnalyticsConfig::default());
+        let day = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let next = day.succ_opt().unwrap();
+        let id = analytics.visitor(day, "example.com", "203.0.113.9", "Firefox");
+        assert_eq!(id.len(), 16);
+        assert!(!id.contains("203"));
+        assert_eq!(
+            id,
+            analytics.visitor(day, "example.com", "203.0.113.9", "Firefox")
+        );
+        assert_ne!(
+            id,
+            analytics.visitor(next, "example.com", "203.0.113.9", "Firefox")
+        );
+        assert_ne!(
+            id,
+            analytics.visitor(day, "example.com", "203.0.113.10", "Firefox")
+        );
+
+        // Instances sharing the secret agree
+        let (other, _) = collector(AnalyticsConfig::default());
+        assert_eq!(
+            id,
+            other.visitor(day, "example.com", "203.0.113.9", "Firefox")
+        );
+    }
+
+    #[test]
+    fn test_report_from_events() {
+        let day = 1_740_787_200; // 2025-03-01
+        let mut referred = event(PAGE_VIEW, "/", "a", day + 10);
+        referred.referrer = Some("news.example.com".to_string());
+        let events = [
+            referred,
+            event(PAGE_VIEW, "/pricing", "a", day + 20),
+            event(PAGE_VIEW, "/", "b", day + 30),
+            event("signup", "/pricing", "a", day + 40),
+            event(PAGE_VIEW, "/", "c", day + 86_400),
+        ];
+        let report = AnalyticsReport::from_events(&events, 1);
+        assert_eq!(report.page_views, 4);
+        assert_eq!(report.visitors, 3);
+        assert_eq!(report.events, 1);
+        assert_eq!(report.daily.len(), 2);
+        assert_eq!(report.daily[0].page_views, 3);
+        assert_eq!(report.daily[0].visitors, 2);
+        assert_eq!(report.top_pages, [("/".to_string(), 3)]);
+        assert_eq!(report.top_referrers, [("news.example.com".to_string(), 1)]);
+        assert_eq!(report.top_events, [("signup".to_string(), 1)]);
+
+        let html = dashboard_partial(&report, 7, "/admin/analytics");
+        assert!(html.contains(r#"hx-get="/admin/analytics?days=30""#));
+        assert!(html.contains(r##"?days=7" hx-target="#analytics-dashboard" hx-swap="outerHTML" aria-current="true""##));
+        assert!(html.contains("<dt>Visitors</dt><dd>3</dd>"));
+        assert!(html.contains("<td>news.example.com</td>"));
+    }
+
+    #[tokio::test]
+    async fn test_buffer_flushes_in_batches() {
+        let (analytics, store) = collector(AnalyticsConfig {
+            batch_size: 2,
+            ..AnalyticsConfig::default()
+        });
+        analytics.record(event(PAGE_VIEW, "/", "a", 1)).await;
+        assert!(store.events().is_empty());
+        analytics.record(event(PAGE_VIEW, "/", "b", 2)).await;
+        assert_eq!(store.events().len(), 2);
+
+        analytics.record(event(PAGE_VIEW, "/", "c", 3)).await;
+        assert_eq!(analytics.flush().await.unwrap(), 1);
+        assert_eq!(analytics.flush().await.unwrap(), 0);
+        assert_eq!(store.events().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_beacon_respects_consent_and_do_not_track() {
+        let (analytics, store) = collector(AnalyticsConfig {
+            batch_size: 1,
+            ..AnalyticsConfig::default()
+        });
+        let consent = ConsentManager::new(ConsentConfig::default()).with_secure_cookie(false);
+        let app = analytics
+            .routes()
+            .layer(from_fn_with_state(consent, ConsentManager::middleware));
+
+        let beacon = |cookie: Option<&str>, dnt: bool, body: &str| {
+            let mut builder = Request::post(BEACON_PATH)
+                .header(header::HOST, "example.com")
+                .header(header::USER_AGENT, "Firefox");
+            if let Some(cookie) = cookie {
+                builder = builder.header(header::COOKIE, cookie);
+            }
+            if dnt {
+                builder = builder.header("dnt", "1");
+            }
+            builder.body(Body::from(body.to_string())).unwrap()
+        };
+        let page = r#"{"url":"/docs?q=x","referrer":"https://news.example.com/item"}"#;
+        let granted = "consent=1:analytics+necessary:1700000000";
+
+        let response = app.clone().oneshot(beacon(None, false, page)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(store.events().is_empty());
+
+        let response = app.clone().oneshot(beacon(Some(granted), true, page)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(store.events().is_empty());
+
+        let response = app
+            .clone()
+            .oneshot(beacon(Some(granted), false, page))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let custom = r#"{"name":"signup","url":"/pricing","props":{"plan":"pro","seats":3,"nested":{}}}"#;
+        app.clone()
+            .oneshot(beacon(Some(granted), false, custom))
+            .await
+            .unwrap();
+
+        let events = store.events();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].is_page_view());
+        assert_eq!(events[0].path, "/docs");
+        assert_eq!(events[0].referrer.as_deref(), Some("news.example.com"));
+        assert_eq!(events[1].name, "signup");
+        assert_eq!(
+            events[1].properties.iter().collect::<Vec<_>>(),
+            [(&"plan".to_string(), &"pro".to_string()), (&"seats".to_string(), &"3".to_string())]
+        );
+        assert_eq!(events[0].visitor, events[1].visitor);
+
+        let response = app
+            .oneshot(beacon(Some(granted), false, r#"{"name":"<b>","url":"/"}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
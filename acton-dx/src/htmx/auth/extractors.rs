@@ -100,6 +100,11 @@ where
                 _ => AuthenticationError::DatabaseError(e),
             })?;
 
+        // A session that outlived its user's deactivation is signed out
+        if !user.active {
+            return Err(AuthenticationError::not_authenticated(is_htmx));
+        }
+
         Ok(Self(user))
     }
 }
@@ -153,7 +158,8 @@ where
         // Load user from database
         let user = User::find_by_id(user_id, app_state.database_pool())
             .await
-            .ok(); // Convert Result to Option - failures return None
+            .ok() // Convert Result to Option - failures return None
+            .filter(|user| user.active);
 
         Ok(Self(user))
     }
@@ -1,7 +1,8 @@
 //! Authentication handlers (login, register, logout)
 //!
-//! This module provides basic handler scaffolds for authentication.
-//! Full database integration and template rendering will be added in later phases.
+//! This module provides basic handler scaffolds for authentication. For
+//! complete flows with throttling, session regeneration and email
+//! verification, use [`AuthKit`](super::kit::AuthKit).
 //!
 //! # Example
 //!
@@ -138,6 +139,7 @@ pub async fn login_post(
 
     // Set user ID in session
     session.set_user_id(Some(user.id));
+    session.data_mut().timezone = user.timezone;
 
     // Add success flash message
     session.add_flash(FlashMessage::success("Successfully logged in!"));
@@ -164,6 +166,7 @@ pub async fn login_post(
         .map_err(|_| AuthHandlerError::InvalidCredentials)?;
 
     session.set_user_id(Some(user.id));
+    session.data_mut().timezone = user.timezone;
     session.add_flash(FlashMessage::success("Successfully logged in!"));
 
     Ok(Redirect::to("/").into_response())
@@ -0,0 +1,1796 @@
+//! Login, logout, registration and password change over HTMX
+//!
+//! [`AuthKit`] serves the complete account flow:
+//!
+//! - Registration creates the account and emails a verification link
+//! - Login is throttled per email address and per client IP through
+//!   [`RateLimiterAgent`]s, and moves the session to a fresh ID so a session
+//!   planted before login is useless
+//! - Logout clears the session and moves it to a fresh ID
+//! - Password change checks the current password first, counting wrong
+//!   guesses against the login limits, and signs the user out of their
+//!   other sessions when the kit has the session manager
+//!
+//! Accounts live in an [`AccountStore`]: [`DatabaseAccounts`] over the
+//! `users` table (with `postgres` or `sqlite`), or [`MemoryAccountStore`]
+//! for tests and prototypes. [`AuthHooks`] let applications react to each
+//! step, refuse a login, or replace the built-in form partials.
+//!
+//! Verification links carry a token signed with HMAC-SHA256 over the
+//! account's ID and email address, so no token table is needed and a link
+//! stops working once the address changes. Set a stable secret with
+//! [`AuthKit::with_secret`]; the default random secret invalidates
+//! outstanding links on restart.
+//!
+//! Forms post with htmx. Success answers with `HX-Redirect` (plain form
+//! posts get `303 See Other`); errors re-render the form, with status `200`
+//! for htmx requests since htmx does not swap error responses by default.
+//! Every form carries the session's CSRF token once the kit has the CSRF
+//! manager, so the routes work behind [`CsrfLayer`](crate::htmx::middleware::CsrfLayer).
+//!
+//! The routes need the session middleware, which saves the session from the
+//! response and honours [`RegenerateSession`].
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::auth::kit::{AuthKit, DatabaseAccounts, LoginThrottle};
+//!
+//! let throttle = LoginThrottle::spawn(&mut runtime).await?;
+//! let auth = AuthKit::new(Arc::new(DatabaseAccounts::new(pool.clone())), throttle)
+//!     .with_csrf_manager(state.csrf_manager())
+//!     .with_hooks(Arc::new(AuditHooks))
+//!     .with_sender(Arc::new(email_backend))
+//!     .with_base_url("https://app.example.com")
+//!     .with_secret(config.auth_secret.as_bytes())
+//!     .require_verified_email();
+//!
+//! let app = Router::new()
+//!     .merge(auth.routes())
+//!     .layer(CsrfLayer::new(&state))
+//!     .layer(SessionLayer::new(&state));
+//! ```
+
+use acton_reactive::prelude::{ActorHandle, ActorHandleInterface, ActorRuntime};
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Path, State},
+    http::{request::Parts, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::net::IpAddr;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::htmx::agents::{
+    CheckRateLimit, DeleteUserSessions, GetOrCreateToken, RateLimiterAgent, RateLimiterConfig,
+};
+use crate::htmx::auth::handlers::{LoginForm, RegisterForm};
+use crate::htmx::auth::password::{hash_password, verify_password};
+use crate::htmx::auth::session::{FlashMessage, SessionData, SessionId};
+use crate::htmx::auth::user::{
+    validate_password_strength, CreateUser, EmailAddress, User, UserError,
+};
+use crate::htmx::email::{Email, EmailError, EmailSender, EmailTemplate};
+use crate::htmx::middleware::compression::BreachGuard;
+use crate::htmx::middleware::{client_ip, is_htmx_request, RegenerateSession};
+use crate::htmx::template::helpers::escape_html;
+use crate::htmx::util::unix_now;
+
+/// Default lifetime of email verification links
+pub const DEFAULT_VERIFICATION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Failed logins allowed per email address in a window
+const EMAIL_FAILURES: u32 = 5;
+
+/// Failed logins allowed per client IP in a window
+const IP_FAILURES: u32 = 20;
+
+/// Window over which failed logins are counted
+const FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Hash verified against when no account matches, so unknown addresses take
+/// as long to reject as wrong passwords
+static DUMMY_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("no-such-account").unwrap_or_default());
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Auth kit errors
+#[derive(Debug, thiserror::Error)]
+pub enum AuthKitError {
+    /// Input was rejected
+    #[error("{0}")]
+    Invalid(String),
+
+    /// Email address or password is wrong
+    #[error("Invalid email or password")]
+    InvalidCredentials,
+
+    /// An account already uses the email address
+    #[error("An account with this email address already exists")]
+    EmailTaken,
+
+    /// Too many failed attempts; retry after the duration
+    #[error("Too many attempts. Try again in {} minute(s).", .0.as_secs().div_ceil(60))]
+    RateLimited(Duration),
+
+    /// Account must verify its email address before signing in
+    #[error("Confirm your email address before signing in")]
+    EmailNotVerified,
+
+    /// Account was deactivated, e.g. by the identity provider over SCIM
+    #[error("This account has been deactivated")]
+    Deactivated,
+
+    /// Request has no authenticated user
+    #[error("Sign in to continue")]
+    Unauthenticated,
+
+    /// Verification link is malformed, tampered with or expired
+    #[error("This link is invalid or has expired")]
+    InvalidToken,
+
+    /// Email could not be sent
+    #[error("Failed to send email: {0}")]
+    Delivery(String),
+
+    /// Backing store failed
+    #[error("Account store error: {0}")]
+    Store(String),
+}
+
+impl AuthKitError {
+    /// HTTP status for the error
+    #[must_use]
+    pub const fn status(&self) -> StatusCode {
+        match self {
+            Self::Invalid(_) | Self::EmailTaken => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::InvalidCredentials | Self::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::EmailNotVerified | Self::Deactivated => StatusCode::FORBIDDEN,
+            Self::InvalidToken => StatusCode::GONE,
+            Self::Delivery(_) => StatusCode::BAD_GATEWAY,
+            Self::Store(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+impl IntoResponse for AuthKitError {
+    fn into_response(self) -> Response {
+        if let Self::Store(ref e) = self {
+            warn!(error = %e, "Account store failed");
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+        let mut response = (self.status(), self.to_string()).into_response();
+        if let Self::RateLimited(retry_after) = self {
+            if let Ok(value) = retry_after.as_secs().max(1).to_string().parse() {
+                response.headers_mut().insert("Retry-After", value);
+            }
+        }
+        response
+    }
+}
+
+impl From<EmailError> for AuthKitError {
+    fn from(error: EmailError) -> Self {
+        Self::Delivery(error.to_string())
+    }
+}
+
+impl From<UserError> for AuthKitError {
+    fn from(error: UserError) -> Self {
+        match error {
+            UserError::InvalidEmail(_)
+            | UserError::WeakPassword(_)
+            | UserError::ValidationFailed(_) => Self::Invalid(error.to_string()),
+            UserError::InvalidCredentials | UserError::NotFound => Self::InvalidCredentials,
+            UserError::Deactivated => Self::Deactivated,
+            UserError::DatabaseError(sqlx::Error::Database(ref e)) if e.is_unique_violation() => {
+                Self::EmailTaken
+            }
+            UserError::DatabaseError(_) | UserError::PasswordHashingFailed(_) => {
+                Self::Store(error.to_string())
+            }
+        }
+    }
+}
+
+/// Accounts the kit signs in
+#[async_trait]
+pub trait AccountStore: Send + Sync {
+    /// Account with the email address, if any
+    async fn find_by_email(&self, email: &EmailAddress) -> Result<Option<User>, AuthKitError>;
+
+    /// Account with the ID, if any
+    async fn find_by_id(&self, id: i64) -> Result<Option<User>, AuthKitError>;
+
+    /// Create an unverified account, hashing its password
+    ///
+    /// Returns [`AuthKitError::EmailTaken`] if the address is in use and
+    /// [`AuthKitError::Invalid`] if the password is too weak.
+    async fn create(&self, data: CreateUser) -> Result<User, AuthKitError>;
+
+    /// Replace an account's password
+    async fn update_password(&self, id: i64, password: &str) -> Result<(), AuthKitError>;
+
+    /// Mark an account's email address verified
+    async fn mark_email_verified(&self, id: i64) -> Result<(), AuthKitError>;
+}
+
+/// Accounts in the `users` table, through [`User`]
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[derive(Debug, Clone)]
+pub struct DatabaseAccounts {
+    pool: DatabasePool,
+}
+
+#[cfg(feature = "postgres")]
+type DatabasePool = sqlx::PgPool;
+
+#[cfg(all(feature = "sqlite", not(feature = "postgres")))]
+type DatabasePool = sqlx::SqlitePool;
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+impl DatabaseAccounts {
+    /// Store accounts in `pool`
+    #[must_use]
+    pub const fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn found(result: Result<User, UserError>) -> Result<Option<User>, AuthKitError> {
+    match result {
+        Ok(user) => Ok(Some(user)),
+        Err(UserError::NotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[async_trait]
+impl AccountStore for DatabaseAccounts {
+    async fn find_by_email(&self, email: &EmailAddress) -> Result<Option<User>, AuthKitError> {
+        found(User::find_by_email(email, &self.pool).await)
+    }
+
+    async fn find_by_id(&self, id: i64) -> Result<Option<User>, AuthKitError> {
+        found(User::find_by_id(id, &self.pool).await)
+    }
+
+    async fn create(&self, data: CreateUser) -> Result<User, AuthKitError> {
+        Ok(User::create(data, &self.pool).await?)
+    }
+
+    async fn update_password(&self, id: i64, password: &str) -> Result<(), AuthKitError> {
+        Ok(User::update_password(id, password, &self.pool).await?)
+    }
+
+    async fn mark_email_verified(&self, id: i64) -> Result<(), AuthKitError> {
+        Ok(User::mark_email_verified(id, &self.pool).await?)
+    }
+}
+
+/// In-memory accounts for tests and prototypes
+#[derive(Debug, Default)]
+pub struct MemoryAccountStore {
+    users: Mutex<Vec<User>>,
+}
+
+impl MemoryAccountStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AccountStore for MemoryAccountStore {
+    async fn find_by_email(&self, email: &EmailAddress) -> Result<Option<User>, AuthKitError> {
+        Ok(self
+            .users
+            .lock()
+            .iter()
+            .find(|u| u.email == *email)
+            .cloned())
+    }
+
+    async fn find_by_id(&self, id: i64) -> Result<Option<User>, AuthKitError> {
+        Ok(self.users.lock().iter().find(|u| u.id == id).cloned())
+    }
+
+    async fn create(&self, data: CreateUser) -> Result<User, AuthKitError> {
+        validate_password_strength(&data.password)?;
+        let password_hash = hash_password(&data.password).map_err(UserError::from)?;
+        let mut users = self.users.lock();
+        if users.iter().any(|u| u.email == data.email) {
+            return Err(AuthKitError::EmailTaken);
+        }
+        let now = Utc::now();
+        let user = User {
+            id: users.iter().map(|u| u.id).max().unwrap_or(0) + 1,
+            email: data.email,
+            password_hash,
+            roles: vec!["user".to_string()],
+            permissions: Vec::new(),
+            email_verified: false,
+            timezone: None,
+            active: true,
+            created_at: now,
+            updated_at: now,
+        };
+        users.push(user.clone());
+        drop(users);
+        Ok(user)
+    }
+
+    async fn update_password(&self, id: i64, password: &str) -> Result<(), AuthKitError> {
+        validate_password_strength(password)?;
+        let password_hash = hash_password(password).map_err(UserError::from)?;
+        let mut users = self.users.lock();
+        let user = users
+            .iter_mut()
+            .find(|u| u.id == id)
+            .ok_or(AuthKitError::InvalidCredentials)?;
+        user.password_hash = password_hash;
+        user.updated_at = Utc::now();
+        drop(users);
+        Ok(())
+    }
+
+    async fn mark_email_verified(&self, id: i64) -> Result<(), AuthKitError> {
+        let mut users = self.users.lock();
+        let user = users
+            .iter_mut()
+            .find(|u| u.id == id)
+            .ok_or(AuthKitError::InvalidToken)?;
+        user.email_verified = true;
+        user.updated_at = Utc::now();
+        drop(users);
+        Ok(())
+    }
+}
+
+/// Page or partial the kit renders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthView<'a> {
+    /// Login form, refilled with the submitted address
+    Login {
+        /// Submitted email address
+        email: &'a str,
+        /// Why the last attempt failed
+        error: Option<&'a str>,
+        /// Token the form posts as `_csrf_token`
+        csrf_token: &'a str,
+    },
+    /// Registration form, refilled with the submitted address
+    Register {
+        /// Submitted email address
+        email: &'a str,
+        /// Why the last attempt failed
+        error: Option<&'a str>,
+        /// Token the form posts as `_csrf_token`
+        csrf_token: &'a str,
+    },
+    /// Login refused until the address is verified; offers a new link
+    Unverified {
+        /// Address awaiting verification
+        email: &'a str,
+        /// Token the resend form posts as `_csrf_token`
+        csrf_token: &'a str,
+    },
+    /// Verification link sent (or, for unknown addresses, pretended to be)
+    VerificationSent {
+        /// Address the link went to
+        email: &'a str,
+    },
+    /// Verification link accepted
+    EmailVerified,
+    /// Verification link rejected; offers a new one
+    VerificationFailed {
+        /// Token the resend form posts as `_csrf_token`
+        csrf_token: &'a str,
+    },
+    /// Password change form
+    ChangePassword {
+        /// Why the last attempt failed
+        error: Option<&'a str>,
+        /// Whether the password was just changed
+        changed: bool,
+        /// Token the form posts as `_csrf_token`
+        csrf_token: &'a str,
+    },
+}
+
+/// Extension points for applications using the kit
+///
+/// Every method has a default, so implement only what you need.
+#[async_trait]
+pub trait AuthHooks: Send + Sync {
+    /// Called after an account is created, before its verification email
+    async fn registered(&self, _user: &User) -> Result<(), AuthKitError> {
+        Ok(())
+    }
+
+    /// Called after the password checks out, before the user is signed in
+    ///
+    /// Add data to the new session, or return an error to refuse the login
+    /// (e.g. for suspended accounts).
+    async fn logging_in(
+        &self,
+        _user: &User,
+        _session: &mut SessionData,
+    ) -> Result<(), AuthKitError> {
+        Ok(())
+    }
+
+    /// Called after a user signs out
+    async fn logged_out(&self, _user_id: i64) {}
+
+    /// Called after a user changes their password
+    ///
+    /// Without [`AuthKit::with_session_manager`] other sessions of the user
+    /// stay signed in; revoke them here if your session store can find them.
+    async fn password_changed(&self, _user: &User) {}
+
+    /// Called after a user verifies their email address
+    async fn email_verified(&self, _user: &User) {}
+
+    /// Render a view; defaults to [`auth_partial`]
+    ///
+    /// Forms must post the view's `csrf_token` in a `_csrf_token` field, or
+    /// the CSRF middleware rejects them.
+    fn render(&self, view: &AuthView<'_>) -> String {
+        auth_partial(view)
+    }
+}
+
+/// Hooks that change nothing
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultAuthHooks;
+
+impl AuthHooks for DefaultAuthHooks {}
+
+/// Failed logins per email address and per client IP
+///
+/// Failures are counted by two [`RateLimiterAgent`]s, one per kind of key.
+/// Each failure takes a token from the key's bucket, which holds the allowed
+/// failures and refills over the window; a key with an empty bucket is
+/// refused until a token comes back. Give the agents shared buckets
+/// ([`RateLimiterConfig::with_shared_buckets`]) so every node counts the
+/// same failures.
+#[derive(Debug, Clone)]
+pub struct LoginThrottle {
+    per_email: Limiter,
+    per_ip: Limiter,
+}
+
+/// One rate limiter and how long its buckets take to refill a token
+#[derive(Debug, Clone)]
+struct Limiter {
+    handle: ActorHandle,
+    retry_after: Duration,
+}
+
+impl LoginThrottle {
+    /// Rate limiter configuration allowing `max_failures` every `window`
+    #[must_use]
+    pub fn config(max_failures: u32, window: Duration) -> RateLimiterConfig {
+        RateLimiterConfig::new()
+            .with_bucket_capacity(max_failures)
+            .with_refill_rate(f64::from(max_failures) / window.as_secs_f64().max(1.0))
+            .with_bucket_expiration(window)
+    }
+
+    /// Spawn rate limiters allowing 5 failures per email address and 20 per
+    /// client IP every 15 minutes, in this node's memory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an agent cannot be spawned.
+    pub async fn spawn(runtime: &mut ActorRuntime) -> anyhow::Result<Self> {
+        Self::spawn_with_config(
+            runtime,
+            Self::config(EMAIL_FAILURES, FAILURE_WINDOW),
+            Self::config(IP_FAILURES, FAILURE_WINDOW),
+        )
+        .await
+    }
+
+    /// Spawn rate limiters for email addresses and client IPs
+    ///
+    /// Build each configuration with [`LoginThrottle::config`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an agent cannot be spawned.
+    pub async fn spawn_with_config(
+        runtime: &mut ActorRuntime,
+        per_email: RateLimiterConfig,
+        per_ip: RateLimiterConfig,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            per_email: Limiter::spawn(runtime, per_email).await?,
+            per_ip: Limiter::spawn(runtime, per_ip).await?,
+        })
+    }
+
+    /// Check that the address and `ip` may try again
+    async fn check(&self, email_key: &str, ip_key: Option<&str>) -> Result<(), AuthKitError> {
+        self.per_email
+            .check(&format!("login:email:{email_key}"))
+            .await?;
+        if let Some(ip_key) = ip_key {
+            self.check_ip(ip_key).await?;
+        }
+        Ok(())
+    }
+
+    /// Check that `ip` may try again
+    async fn check_ip(&self, ip_key: &str) -> Result<(), AuthKitError> {
+        self.per_ip.check(&format!("login:ip:{ip_key}")).await
+    }
+
+    /// Count a failure against the address and `ip`
+    async fn record_failure(&self, email_key: &str, ip_key: Option<&str>) {
+        self.per_email
+            .take(&format!("login:email:{email_key}"))
+            .await;
+        if let Some(ip_key) = ip_key {
+            self.record_ip(ip_key).await;
+        }
+    }
+
+    /// Count an attempt against `ip`
+    async fn record_ip(&self, ip_key: &str) {
+        self.per_ip.take(&format!("login:ip:{ip_key}")).await;
+    }
+}
+
+impl Limiter {
+    async fn spawn(runtime: &mut ActorRuntime, config: RateLimiterConfig) -> anyhow::Result<Self> {
+        let retry_after = if config.refill_rate > 0.0 {
+            Duration::from_secs_f64(1.0 / config.refill_rate)
+        } else {
+            config.bucket_expiration
+        };
+        let handle = RateLimiterAgent::spawn_with_config(runtime, config).await?;
+        Ok(Self {
+            handle,
+            retry_after,
+        })
+    }
+
+    /// Refuse `key` once its bucket is empty
+    ///
+    /// Asks for no tokens, so the check itself costs nothing. Logins go
+    /// ahead if the agent does not answer.
+    async fn check(&self, key: &str) -> Result<(), AuthKitError> {
+        let (request, rx) = CheckRateLimit::new(key.to_string(), 0);
+        self.handle.send(request).await;
+        match rx.await {
+            Ok(result) if result.remaining_tokens == 0 => {
+                Err(AuthKitError::RateLimited(self.retry_after))
+            }
+            Ok(_) => Ok(()),
+            Err(_) => {
+                warn!(key, "Login rate limiter did not answer");
+                Ok(())
+            }
+        }
+    }
+
+    /// Take a token from `key`'s bucket, waiting so the next check sees it
+    async fn take(&self, key: &str) {
+        let (request, rx) = CheckRateLimit::new(key.to_string(), 1);
+        self.handle.send(request).await;
+        let _ = rx.await;
+    }
+}
+
+/// Email verification message
+///
+/// Renders a short HTML and plain text message with the verification link.
+#[derive(Debug, Clone)]
+pub struct VerificationEmail {
+    /// Application name shown in the subject and body
+    pub app_name: String,
+    /// Link that verifies the address
+    pub verify_url: String,
+    /// Hours until the link expires
+    pub expires_in_hours: u64,
+}
+
+impl VerificationEmail {
+    /// Subject line
+    #[must_use]
+    pub fn subject(&self) -> String {
+        format!("Confirm your email address for {}", self.app_name)
+    }
+}
+
+impl EmailTemplate for VerificationEmail {
+    fn render_email(&self) -> Result<(Option<String>, Option<String>), EmailError> {
+        let text = format!(
+            "Confirm your email address for {app}: {url}\n\n\
+             This link expires in {hours} hour(s). If you didn't sign up, ignore this email.\n",
+            app = self.app_name,
+            url = self.verify_url,
+            hours = self.expires_in_hours,
+        );
+        let html = format!(
+            r#"<p>Confirm your email address for {app}.</p>
+<p><a href="{url}">Confirm email address</a></p>
+<p>This link expires in {hours} hour(s). If you didn't sign up, ignore this email.</p>"#,
+            app = escape_html(&self.app_name),
+            url = escape_html(&self.verify_url),
+            hours = self.expires_in_hours,
+        );
+        Ok((Some(html), Some(text)))
+    }
+}
+
+/// Login, logout, registration and password change handlers
+#[derive(Clone)]
+pub struct AuthKit {
+    store: Arc<dyn AccountStore>,
+    hooks: Arc<dyn AuthHooks>,
+    sender: Option<Arc<dyn EmailSender>>,
+    sessions: Option<ActorHandle>,
+    csrf: Option<ActorHandle>,
+    throttle: LoginThrottle,
+    secret: Arc<[u8]>,
+    verification_ttl: Duration,
+    require_verified: bool,
+    after_login: String,
+    after_logout: String,
+    base_url: String,
+    from: Option<String>,
+    app_name: String,
+}
+
+impl std::fmt::Debug for AuthKit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthKit")
+            .field("require_verified", &self.require_verified)
+            .field("verification_ttl", &self.verification_ttl)
+            .field("after_login", &self.after_login)
+            .field("after_logout", &self.after_logout)
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuthKit {
+    /// Create a kit signing in accounts from `store`, limiting failed
+    /// logins with `throttle`
+    ///
+    /// Users land on `/` after login and `/login` after logout.
+    #[must_use]
+    pub fn new(store: Arc<dyn AccountStore>, throttle: LoginThrottle) -> Self {
+        let mut secret = [0u8; 32];
+        rand::rng().fill(&mut secret);
+        Self {
+            store,
+            hooks: Arc::new(DefaultAuthHooks),
+            sender: None,
+            sessions: None,
+            csrf: None,
+            throttle,
+            secret: Arc::from(secret.as_slice()),
+            verification_ttl: DEFAULT_VERIFICATION_TTL,
+            require_verified: false,
+            after_login: "/".to_string(),
+            after_logout: "/login".to_string(),
+            base_url: String::new(),
+            from: None,
+            app_name: "our app".to_string(),
+        }
+    }
+
+    /// Use `hooks` for lifecycle events and rendering
+    #[must_use]
+    pub fn with_hooks(mut self, hooks: Arc<dyn AuthHooks>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Deliver verification emails through `sender`
+    ///
+    /// Without a sender, verification links are only logged, and only in
+    /// debug builds.
+    #[must_use]
+    pub fn with_sender(mut self, sender: Arc<dyn EmailSender>) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// Sign users out of their other sessions after a password change
+    ///
+    /// Pass the handle from `ActonHtmxState::session_manager`.
+    #[must_use]
+    pub fn with_session_manager(mut self, sessions: ActorHandle) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Render the session's CSRF token into every form
+    ///
+    /// Pass the handle from `ActonHtmxState::csrf_manager`, the same agent
+    /// the CSRF middleware validates against.
+    #[must_use]
+    pub fn with_csrf_manager(mut self, csrf: ActorHandle) -> Self {
+        self.csrf = Some(csrf);
+        self
+    }
+
+    /// Key for signing verification links; keep it stable across restarts
+    #[must_use]
+    pub fn with_secret(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.secret = Arc::from(secret.as_ref());
+        self
+    }
+
+    /// Set how long verification links stay valid (default 24 hours)
+    #[must_use]
+    pub const fn with_verification_ttl(mut self, ttl: Duration) -> Self {
+        self.verification_ttl = ttl;
+        self
+    }
+
+    /// Refuse logins until the email address is verified
+    ///
+    /// New accounts are then not signed in on registration either.
+    #[must_use]
+    pub const fn require_verified_email(mut self) -> Self {
+        self.require_verified = true;
+        self
+    }
+
+    /// Where users land after login and after logout
+    #[must_use]
+    pub fn with_redirects(
+        mut self,
+        after_login: impl Into<String>,
+        after_logout: impl Into<String>,
+    ) -> Self {
+        self.after_login = after_login.into();
+        self.after_logout = after_logout.into();
+        self
+    }
+
+    /// Absolute URL prefix for verification links (`https://app.example.com`)
+    #[must_use]
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sender address for verification emails
+    #[must_use]
+    pub fn with_from(mut self, from: impl Into<String>) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// Application name used in verification emails
+    #[must_use]
+    pub fn with_app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = app_name.into();
+        self
+    }
+
+    /// Create an account and send its verification link
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthKitError::Invalid`] for a bad address, a weak password
+    /// or a mismatched confirmation, [`AuthKitError::EmailTaken`], or a
+    /// store, hook or delivery error.
+    pub async fn register(
+        &self,
+        email: &str,
+        password: &str,
+        password_confirm: &str,
+    ) -> Result<User, AuthKitError> {
+        let email = EmailAddress::parse(email)?;
+        if password != password_confirm {
+            return Err(AuthKitError::Invalid("Passwords do not match".to_string()));
+        }
+        let user = self
+            .store
+            .create(CreateUser {
+                email,
+                password: password.to_string(),
+            })
+            .await?;
+        self.hooks.registered(&user).await?;
+        self.send_verification(&user).await?;
+        Ok(user)
+    }
+
+    /// Check an email address and password, counting failures
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthKitError::RateLimited`] once the address or `ip` has
+    /// failed too often, [`AuthKitError::InvalidCredentials`],
+    /// [`AuthKitError::Deactivated`] for a deactivated account, or
+    /// [`AuthKitError::EmailNotVerified`] when verification is required.
+    pub async fn authenticate(
+        &self,
+        email: &str,
+        password: &str,
+        ip: Option<IpAddr>,
+    ) -> Result<User, AuthKitError> {
+        let email_key = email.trim().to_lowercase();
+        let ip_key = ip.map(|ip| ip.to_string());
+        self.throttle.check(&email_key, ip_key.as_deref()).await?;
+
+        let user = match EmailAddress::parse(email) {
+            Ok(email) => self.store.find_by_email(&email).await?,
+            Err(_) => None,
+        };
+        let hash = user
+            .as_ref()
+            .map_or(DUMMY_HASH.as_str(), |u| u.password_hash.as_str());
+        let valid = verify_password(password, hash).unwrap_or(false);
+
+        let Some(user) = user.filter(|_| valid) else {
+            self.throttle
+                .record_failure(&email_key, ip_key.as_deref())
+                .await;
+            return Err(AuthKitError::InvalidCredentials);
+        };
+
+        if !user.active {
+            return Err(AuthKitError::Deactivated);
+        }
+        if self.require_verified && !user.email_verified {
+            return Err(AuthKitError::EmailNotVerified);
+        }
+        Ok(user)
+    }
+
+    /// Sign `user` into `session` after the [`AuthHooks::logging_in`] hook
+    ///
+    /// Pair with [`RegenerateSession`] on the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthKitError::Deactivated`] for a deactivated user, or the
+    /// error the hook refused the login with.
+    pub async fn sign_in(
+        &self,
+        user: &User,
+        session: &mut SessionData,
+    ) -> Result<(), AuthKitError> {
+        if !user.active {
+            return Err(AuthKitError::Deactivated);
+        }
+        self.hooks.logging_in(user, session).await?;
+        session.user_id = Some(user.id);
+        session.timezone.clone_from(&user.timezone);
+        Ok(())
+    }
+
+    /// Change a signed-in user's password after checking the current one
+    ///
+    /// Wrong current passwords count against the login limits of the
+    /// account's address and `ip`. After the change every session of the
+    /// user is deleted if the kit has the session manager; the handler
+    /// keeps the caller signed in by moving their session to a fresh ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthKitError::RateLimited`] once the address or `ip` has
+    /// failed too often, [`AuthKitError::InvalidCredentials`] if `current`
+    /// is wrong, [`AuthKitError::Invalid`] for a weak or mismatched new
+    /// password, or a store error.
+    pub async fn change_password(
+        &self,
+        user_id: i64,
+        current: &str,
+        new: &str,
+        new_confirm: &str,
+        ip: Option<IpAddr>,
+    ) -> Result<User, AuthKitError> {
+        let user = self
+            .store
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AuthKitError::Unauthenticated)?;
+        let email_key = user.email.as_str().to_lowercase();
+        let ip_key = ip.map(|ip| ip.to_string());
+        self.throttle.check(&email_key, ip_key.as_deref()).await?;
+        if !verify_password(current, &user.password_hash).unwrap_or(false) {
+            self.throttle
+                .record_failure(&email_key, ip_key.as_deref())
+                .await;
+            return Err(AuthKitError::InvalidCredentials);
+        }
+        if new != new_confirm {
+            return Err(AuthKitError::Invalid("Passwords do not match".to_string()));
+        }
+        self.store.update_password(user.id, new).await?;
+        if let Some(sessions) = &self.sessions {
+            sessions.send(DeleteUserSessions { user_id: user.id }).await;
+        }
+        self.hooks.password_changed(&user).await;
+        Ok(user)
+    }
+
+    /// Signed token verifying `user`'s current email address
+    #[must_use]
+    pub fn verification_token(&self, user: &User) -> String {
+        let expires = unix_now().saturating_add(self.verification_ttl.as_secs());
+        let signature = URL_SAFE_NO_PAD.encode(self.sign(user, expires).finalize().into_bytes());
+        format!("{}.{expires}.{signature}", user.id)
+    }
+
+    /// Link verifying `user`'s email address
+    #[must_use]
+    pub fn verification_url(&self, user: &User) -> String {
+        format!(
+            "{}/verify-email/{}",
+            self.base_url,
+            self.verification_token(user)
+        )
+    }
+
+    fn sign(&self, user: &User, expires: u64) -> HmacSha256 {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"verify-email\0");
+        mac.update(user.id.to_string().as_bytes());
+        mac.update(b"\0");
+        mac.update(user.email.as_str().as_bytes());
+        mac.update(b"\0");
+        mac.update(expires.to_string().as_bytes());
+        mac
+    }
+
+    /// Email `user` a verification link
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the email cannot be rendered or sent.
+    pub async fn send_verification(&self, user: &User) -> Result<(), AuthKitError> {
+        let verify_url = self.verification_url(user);
+        let Some(sender) = &self.sender else {
+            if cfg!(debug_assertions) {
+                info!(url = %verify_url, "No email sender configured; verification link not sent");
+            }
+            return Ok(());
+        };
+        let template = VerificationEmail {
+            app_name: self.app_name.clone(),
+            verify_url,
+            expires_in_hours: self.verification_ttl.as_secs().div_ceil(3600),
+        };
+        let mut email = Email::from_template(&template)?
+            .to(user.email.as_str())
+            .subject(&template.subject());
+        if let Some(from) = &self.from {
+            email = email.from(from);
+        }
+        sender.send(email).await?;
+        Ok(())
+    }
+
+    /// Send a new verification link to `email` if it belongs to an
+    /// unverified account
+    ///
+    /// Succeeds for unknown addresses too, so the response does not reveal
+    /// which addresses have accounts. Counts against `ip`'s login limit.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthKitError::RateLimited`], or a store or delivery error.
+    pub async fn resend_verification(
+        &self,
+        email: &str,
+        ip: Option<IpAddr>,
+    ) -> Result<(), AuthKitError> {
+        let ip_key = ip.map(|ip| ip.to_string());
+        if let Some(ip_key) = &ip_key {
+            self.throttle.check_ip(ip_key).await?;
+            self.throttle.record_ip(ip_key).await;
+        }
+        let Ok(email) = EmailAddress::parse(email) else {
+            return Ok(());
+        };
+        match self.store.find_by_email(&email).await? {
+            Some(user) if !user.email_verified => self.send_verification(&user).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Verify the email address a token was issued for
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthKitError::InvalidToken`] for malformed, tampered or
+    /// expired tokens and tokens issued for a previous address.
+    pub async fn verify_email(&self, token: &str) -> Result<User, AuthKitError> {
+        let mut parts = token.splitn(3, '.');
+        let (Some(id), Some(expires), Some(signature)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(AuthKitError::InvalidToken);
+        };
+        let id: i64 = id.parse().map_err(|_| AuthKitError::InvalidToken)?;
+        let expires: u64 = expires.parse().map_err(|_| AuthKitError::InvalidToken)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| AuthKitError::InvalidToken)?;
+        if expires < unix_now() {
+            return Err(AuthKitError::InvalidToken);
+        }
+
+        let mut user = self
+            .store
+            .find_by_id(id)
+            .await?
+            .ok_or(AuthKitError::InvalidToken)?;
+        self.sign(&user, expires)
+            .verify_slice(&signature)
+            .map_err(|_| AuthKitError::InvalidToken)?;
+
+        if !user.email_verified {
+            self.store.mark_email_verified(user.id).await?;
+            user.email_verified = true;
+            self.hooks.email_verified(&user).await;
+        }
+        Ok(user)
+    }
+
+    /// Login, logout, registration and password change routes
+    ///
+    /// - `GET /login`, `POST /login` (form `email`, `password`)
+    /// - `POST /logout`
+    /// - `GET /register`, `POST /register` (form `email`, `password`,
+    ///   `password_confirm`)
+    /// - `GET /verify-email/{token}`
+    /// - `POST /verify-email/resend` (form `email`)
+    /// - `GET /account/password`, `POST /account/password` (form
+    ///   `current_password`, `new_password`, `new_password_confirm`)
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/login", get(login_page).post(login))
+            .route("/logout", post(logout))
+            .route("/register", get(register_page).post(register))
+            .route("/verify-email/{token}", get(verify_email))
+            .route("/verify-email/resend", post(resend_verification))
+            .route(
+                "/account/password",
+                get(password_page).post(change_password),
+            )
+            .with_state(self.clone())
+    }
+
+    fn page(&self, view: &AuthView<'_>) -> Response {
+        Html(self.hooks.render(view)).into_response()
+    }
+
+    /// Re-render a form after `error`; htmx only swaps successful responses
+    fn form_error(&self, caller: &Caller, error: &AuthKitError, view: &AuthView<'_>) -> Response {
+        if let AuthKitError::Store(e) = error {
+            warn!(error = %e, "Account store failed");
+        }
+        let status = if caller.htmx {
+            StatusCode::OK
+        } else {
+            error.status()
+        };
+        (status, Html(self.hooks.render(view))).into_response()
+    }
+}
+
+/// Body of `POST /account/password`
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordForm {
+    /// Password the user signs in with now
+    pub current_password: String,
+    /// Replacement password
+    pub new_password: String,
+    /// Replacement password again
+    pub new_password_confirm: String,
+}
+
+/// Body of `POST /verify-email/resend`
+#[derive(Debug, Deserialize)]
+pub struct ResendForm {
+    /// Address to send a new link to
+    pub email: String,
+}
+
+/// Request details the handlers need
+struct Caller {
+    session: SessionData,
+    ip: Option<IpAddr>,
+    htmx: bool,
+    /// Token for the forms the response renders; empty without the CSRF
+    /// manager
+    csrf_token: String,
+}
+
+impl FromRequestParts<AuthKit> for Caller {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, kit: &AuthKit) -> Result<Self, Self::Rejection> {
+        let session = parts
+            .extensions
+            .get::<SessionData>()
+            .cloned()
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Session not initialized"))?;
+        let csrf_token = match (&kit.csrf, parts.extensions.get::<SessionId>()) {
+            (Some(csrf), Some(session_id)) => {
+                let (request, rx) = GetOrCreateToken::new(session_id.clone());
+                csrf.send(request).await;
+                let token = rx
+                    .await
+                    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "CSRF token unavailable"))?;
+                // The token is rendered into the response, so keep it out of
+                // compression
+                if let Some(guard) = parts.extensions.get::<BreachGuard>() {
+                    guard.mark();
+                }
+                token.masked()
+            }
+            _ => String::new(),
+        };
+        Ok(Self {
+            session,
+            ip: client_ip(&parts.extensions),
+            htmx: is_htmx_request(&parts.headers),
+            csrf_token,
+        })
+    }
+}
+
+impl Caller {
+    /// Send the browser to `to`
+    fn redirect(&self, to: &str) -> Response {
+        if self.htmx {
+            (StatusCode::OK, [("HX-Redirect", to.to_string())]).into_response()
+        } else {
+            Redirect::to(to).into_response()
+        }
+    }
+}
+
+/// Have the session middleware save `session` under a fresh ID
+fn regenerate(mut response: Response, session: SessionData) -> Response {
+    response.extensions_mut().insert(session);
+    response.extensions_mut().insert(RegenerateSession);
+    response
+}
+
+async fn login_page(State(kit): State<AuthKit>, caller: Caller) -> Response {
+    if caller.session.user_id.is_some() {
+        return caller.redirect(&kit.after_login);
+    }
+    kit.page(&AuthView::Login {
+        email: "",
+        error: None,
+        csrf_token: &caller.csrf_token,
+    })
+}
+
+async fn login(
+    State(kit): State<AuthKit>,
+    caller: Caller,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let signed_in = match kit
+        .authenticate(&form.email, &form.password, caller.ip)
+        .await
+    {
+        Ok(user) => {
+            let mut session = caller.session.clone();
+            kit.sign_in(&user, &mut session).await.map(|()| session)
+        }
+        Err(e) => Err(e),
+    };
+    match signed_in {
+        Ok(session) => regenerate(caller.redirect(&kit.after_login), session),
+        Err(AuthKitError::EmailNotVerified) => kit.form_error(
+            &caller,
+            &AuthKitError::EmailNotVerified,
+            &AuthView::Unverified {
+                email: &form.email,
+                csrf_token: &caller.csrf_token,
+            },
+        ),
+        Err(e) => {
+            let message = e.to_string();
+            let view = AuthView::Login {
+                email: &form.email,
+                error: Some(&message),
+                csrf_token: &caller.csrf_token,
+            };
+            kit.form_error(&caller, &e, &view)
+        }
+    }
+}
+
+async fn logout(State(kit): State<AuthKit>, caller: Caller) -> Response {
+    if let Some(user_id) = caller.session.user_id {
+        kit.hooks.logged_out(user_id).await;
+    }
+    let mut session = SessionData::new();
+    session
+        .flash_messages
+        .push(FlashMessage::info("You have been logged out."));
+    regenerate(caller.redirect(&kit.after_logout), session)
+}
+
+async fn register_page(State(kit): State<AuthKit>, caller: Caller) -> Response {
+    if caller.session.user_id.is_some() {
+        return caller.redirect(&kit.after_login);
+    }
+    kit.page(&AuthView::Register {
+        email: "",
+        error: None,
+        csrf_token: &caller.csrf_token,
+    })
+}
+
+async fn register(
+    State(kit): State<AuthKit>,
+    caller: Caller,
+    Form(form): Form<RegisterForm>,
+) -> Response {
+    let user = match kit
+        .register(&form.email, &form.password, &form.password_confirm)
+        .await
+    {
+        Ok(user) => user,
+        Err(e) => {
+            let message = e.to_string();
+            let view = AuthView::Register {
+                email: &form.email,
+                error: Some(&message),
+                csrf_token: &caller.csrf_token,
+            };
+            return kit.form_error(&caller, &e, &view);
+        }
+    };
+
+    if kit.require_verified {
+        return kit.page(&AuthView::VerificationSent { email: &form.email });
+    }
+    let mut session = caller.session.clone();
+    match kit.sign_in(&user, &mut session).await {
+        Ok(()) => {
+            session
+                .flash_messages
+                .push(FlashMessage::success("Account created. Welcome!"));
+            regenerate(caller.redirect(&kit.after_login), session)
+        }
+        // The account exists; let the user sign in once the hook allows it
+        Err(e) => {
+            let message = e.to_string();
+            let view = AuthView::Login {
+                email: &form.email,
+                error: Some(&message),
+                csrf_token: &caller.csrf_token,
+            };
+            kit.form_error(&caller, &e, &view)
+        }
+    }
+}
+
+async fn verify_email(
+    State(kit): State<AuthKit>,
+    caller: Caller,
+    Path(token): Path<String>,
+) -> Response {
+    match kit.verify_email(&token).await {
+        Ok(_) => kit.page(&AuthView::EmailVerified),
+        Err(e) => {
+            let view = AuthView::VerificationFailed {
+                csrf_token: &caller.csrf_token,
+            };
+            kit.form_error(&caller, &e, &view)
+        }
+    }
+}
+
+async fn resend_verification(
+    State(kit): State<AuthKit>,
+    caller: Caller,
+    Form(form): Form<ResendForm>,
+) -> Response {
+    match kit.resend_verification(&form.email, caller.ip).await {
+        Ok(()) => kit.page(&AuthView::VerificationSent { email: &form.email }),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn password_page(State(kit): State<AuthKit>, caller: Caller) -> Response {
+    if caller.session.user_id.is_none() {
+        return caller.redirect("/login");
+    }
+    kit.page(&AuthView::ChangePassword {
+        error: None,
+        changed: false,
+        csrf_token: &caller.csrf_token,
+    })
+}
+
+async fn change_password(
+    State(kit): State<AuthKit>,
+    caller: Caller,
+    Form(form): Form<ChangePasswordForm>,
+) -> Response {
+    let Some(user_id) = caller.session.user_id else {
+        return caller.redirect("/login");
+    };
+    match kit
+        .change_password(
+            user_id,
+            &form.current_password,
+            &form.new_password,
+            &form.new_password_confirm,
+            caller.ip,
+        )
+        .await
+    {
+        Ok(_) => {
+            let response = kit.page(&AuthView::ChangePassword {
+                error: None,
+                changed: true,
+                csrf_token: &caller.csrf_token,
+            });
+            regenerate(response, caller.session)
+        }
+        Err(e) => {
+            let message = e.to_string();
+            let view = AuthView::ChangePassword {
+                error: Some(&message),
+                changed: false,
+                csrf_token: &caller.csrf_token,
+            };
+            kit.form_error(&caller, &e, &view)
+        }
+    }
+}
+
+/// Built-in markup for each view
+///
+/// Every view renders into `<div id="auth-form">`, which its forms target,
+/// so the partials work both as whole pages and swapped into a layout.
+#[must_use]
+pub fn auth_partial(view: &AuthView<'_>) -> String {
+    let body = match *view {
+        AuthView::Login {
+            email,
+            error,
+            csrf_token,
+        } => format!(
+            r##"<h1>Log in</h1>{error}<form method="post" action="/login" hx-post="/login" hx-target="#auth-form" hx-swap="outerHTML">{csrf}<label>Email <input type="email" name="email" value="{email}" autocomplete="username" required></label><label>Password <input type="password" name="password" autocomplete="current-password" required></label><button type="submit">Log in</button></form><p><a href="/register">Create an account</a></p>"##,
+            error = error_message(error),
+            csrf = csrf_field(csrf_token),
+            email = escape_html(email),
+        ),
+        AuthView::Register {
+            email,
+            error,
+            csrf_token,
+        } => format!(
+            r##"<h1>Create an account</h1>{error}<form method="post" action="/register" hx-post="/register" hx-target="#auth-form" hx-swap="outerHTML">{csrf}<label>Email <input type="email" name="email" value="{email}" autocomplete="username" required></label><label>Password <input type="password" name="password" autocomplete="new-password" minlength="8" required></label><label>Confirm password <input type="password" name="password_confirm" autocomplete="new-password" minlength="8" required></label><button type="submit">Create account</button></form><p><a href="/login">Already have an account? Log in</a></p>"##,
+            error = error_message(error),
+            csrf = csrf_field(csrf_token),
+            email = escape_html(email),
+        ),
+        AuthView::Unverified { email, csrf_token } => format!(
+            r"<h1>Confirm your email address</h1><p>We sent a confirmation link to {email}. Follow it, then log in.</p>{resend}",
+            email = escape_html(email),
+            resend = resend_form(email, csrf_token),
+        ),
+        AuthView::VerificationSent { email } => format!(
+            r#"<h1>Check your email</h1><p>If {email} needs confirming, a link is on its way.</p><p><a href="/login">Log in</a></p>"#,
+            email = escape_html(email),
+        ),
+        AuthView::EmailVerified => {
+            r#"<h1>Email address confirmed</h1><p><a href="/login">Log in</a></p>"#.to_string()
+        }
+        AuthView::VerificationFailed { csrf_token } => format!(
+            r"<h1>Link expired</h1><p>This confirmation link is invalid or has expired.</p>{}",
+            resend_form("", csrf_token)
+        ),
+        AuthView::ChangePassword {
+            error,
+            changed,
+            csrf_token,
+        } => format!(
+            r##"<h1>Change password</h1>{notice}<form method="post" action="/account/password" hx-post="/account/password" hx-target="#auth-form" hx-swap="outerHTML">{csrf}<label>Current password <input type="password" name="current_password" autocomplete="current-password" required></label><label>New password <input type="password" name="new_password" autocomplete="new-password" minlength="8" required></label><label>Confirm new password <input type="password" name="new_password_confirm" autocomplete="new-password" minlength="8" required></label><button type="submit">Change password</button></form>"##,
+            notice = if changed {
+                r#"<p class="success" role="status">Your password was changed.</p>"#.to_string()
+            } else {
+                error_message(error)
+            },
+            csrf = csrf_field(csrf_token),
+        ),
+    };
+    format!(r#"<div id="auth-form" class="auth-form">{body}</div>"#)
+}
+
+fn error_message(error: Option<&str>) -> String {
+    error.map_or_else(String::new, |error| {
+        format!(r#"<p class="error" role="alert">{}</p>"#, escape_html(error))
+    })
+}
+
+fn resend_form(email: &str, csrf_token: &str) -> String {
+    format!(
+        r##"<form method="post" action="/verify-email/resend" hx-post="/verify-email/resend" hx-target="#auth-form" hx-swap="outerHTML">{}<label>Email <input type="email" name="email" value="{}" required></label><button type="submit">Send a new link</button></form>"##,
+        csrf_field(csrf_token),
+        escape_html(email)
+    )
+}
+
+fn csrf_field(csrf_token: &str) -> String {
+    format!(
+        r#"<input type="hidden" name="_csrf_token" value="{}">"#,
+        escape_html(csrf_token)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acton_reactive::prelude::ActonApp;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    const PASSWORD: &str = "Correct-Horse-1";
+
+    async fn kit() -> AuthKit {
+        kit_with_limits(5, 20).await
+    }
+
+    /// A kit allowing `per_email` and `per_ip` failures a minute
+    async fn kit_with_limits(per_email: u32, per_ip: u32) -> AuthKit {
+        let mut runtime = ActonApp::launch_async().await;
+        let window = Duration::from_secs(60);
+        let throttle = LoginThrottle::spawn_with_config(
+            &mut runtime,
+            LoginThrottle::config(per_email, window),
+            LoginThrottle::config(per_ip, window),
+        )
+        .await
+        .unwrap();
+        AuthKit::new(Arc::new(MemoryAccountStore::new()), throttle).with_secret("test-secret")
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_register_and_authenticate() {
+        let kit = kit().await;
+        let user = kit
+            .register("Ada@Example.com", PASSWORD, PASSWORD)
+            .await
+            .unwrap();
+        assert_eq!(user.email.as_str(), "ada@example.com");
+
+        assert!(matches!(
+            kit.register("ada@example.com", PASSWORD, PASSWORD).await,
+            Err(AuthKitError::EmailTaken)
+        ));
+        assert!(matches!(
+            kit.register("bob@example.com", PASSWORD, "different").await,
+            Err(AuthKitError::Invalid(_))
+        ));
+
+        let signed_in = kit
+            .authenticate("ada@example.com", PASSWORD, None)
+            .await
+            .unwrap();
+        assert_eq!(signed_in.id, user.id);
+        assert!(matches!(
+            kit.authenticate("ada@example.com", "Wrong-Password-1", None)
+                .await,
+            Err(AuthKitError::InvalidCredentials)
+        ));
+        assert!(matches!(
+            kit.authenticate("nobody@example.com", PASSWORD, None).await,
+            Err(AuthKitError::InvalidCredentials)
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_deactivated_user_cannot_sign_in() {
+        let store = Arc::new(MemoryAccountStore::new());
+        let mut runtime = ActonApp::launch_async().await;
+        let throttle = LoginThrottle::spawn(&mut runtime).await.unwrap();
+        let kit = AuthKit::new(store.clone(), throttle).with_secret("test-secret");
+        let user = kit
+            .register("ada@example.com", PASSWORD, PASSWORD)
+            .await
+            .unwrap();
+        store.users.lock()[0].active = false;
+
+        assert!(matches!(
+            kit.authenticate("ada@example.com", PASSWORD, None).await,
+            Err(AuthKitError::Deactivated)
+        ));
+        // A wrong password still reads as wrong, not as deactivated
+        assert!(matches!(
+            kit.authenticate("ada@example.com", "Wrong-Password-1", None)
+                .await,
+            Err(AuthKitError::InvalidCredentials)
+        ));
+
+        let deactivated = User {
+            active: false,
+            ..user
+        };
+        let mut session = SessionData::new();
+        assert!(matches!(
+            kit.sign_in(&deactivated, &mut session).await,
+            Err(AuthKitError::Deactivated)
+        ));
+        assert!(session.user_id.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_failed_logins_are_throttled() {
+        let kit = kit_with_limits(2, 10).await;
+        kit.register("ada@example.com", PASSWORD, PASSWORD)
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                kit.authenticate("ada@example.com", "Wrong-Password-1", None)
+                    .await,
+                Err(AuthKitError::InvalidCredentials)
+            ));
+        }
+        // Even the right password is refused until the window passes
+        assert!(matches!(
+            kit.authenticate("ada@example.com", PASSWORD, None).await,
+            Err(AuthKitError::RateLimited(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_email_verification() {
+        let kit = kit().await.require_verified_email();
+        let user = kit
+            .register("ada@example.com", PASSWORD, PASSWORD)
+            .await
+            .unwrap();
+        assert!(matches!(
+            kit.authenticate("ada@example.com", PASSWORD, None).await,
+            Err(AuthKitError::EmailNotVerified)
+        ));
+
+        let token = kit.verification_token(&user);
+        let tampered = token.replacen(&user.id.to_string(), "99", 1);
+        assert!(matches!(
+            kit.verify_email(&tampered).await,
+            Err(AuthKitError::InvalidToken)
+        ));
+        let other_key = kit.clone().with_secret("other-secret");
+        assert!(matches!(
+            other_key.verify_email(&token).await,
+            Err(AuthKitError::InvalidToken)
+        ));
+
+        assert!(kit.verify_email(&token).await.unwrap().email_verified);
+        assert!(kit
+            .authenticate("ada@example.com", PASSWORD, None)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_change_password() {
+        let kit = kit().await;
+        let user = kit
+            .register("ada@example.com", PASSWORD, PASSWORD)
+            .await
+            .unwrap();
+        let new = "Battery-Staple-2";
+
+        assert!(matches!(
+            kit.change_password(user.id, "Wrong-Password-1", new, new, None)
+                .await,
+            Err(AuthKitError::InvalidCredentials)
+        ));
+        kit.change_password(user.id, PASSWORD, new, new, None)
+            .await
+            .unwrap();
+        assert!(kit.authenticate("ada@example.com", new, None).await.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_change_password_is_throttled() {
+        let kit = kit_with_limits(2, 10).await;
+        let user = kit
+            .register("ada@example.com", PASSWORD, PASSWORD)
+            .await
+            .unwrap();
+        let new = "Battery-Staple-2";
+
+        for _ in 0..2 {
+            assert!(matches!(
+                kit.change_password(user.id, "Wrong-Password-1", new, new, None)
+                    .await,
+                Err(AuthKitError::InvalidCredentials)
+            ));
+        }
+        assert!(matches!(
+            kit.change_password(user.id, PASSWORD, new, new, None).await,
+            Err(AuthKitError::RateLimited(_))
+        ));
+        // Guesses through the password form lock the login form too
+        assert!(matches!(
+            kit.authenticate("ada@example.com", PASSWORD, None).await,
+            Err(AuthKitError::RateLimited(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_change_password_signs_out_other_sessions() {
+        use crate::htmx::agents::{LoadSession, SaveSession, SessionManagerAgent};
+        use crate::htmx::auth::session::SessionId;
+
+        let mut runtime = ActonApp::launch_async().await;
+        let sessions = SessionManagerAgent::spawn(&mut runtime).await.unwrap();
+        let kit = kit().await.with_session_manager(sessions.clone());
+        let user = kit
+            .register("ada@example.com", PASSWORD, PASSWORD)
+            .await
+            .unwrap();
+
+        let other = SessionId::generate();
+        let mut data = SessionData::new();
+        data.user_id = Some(user.id);
+        let (save, rx) = SaveSession::with_confirmation(other.clone(), data);
+        sessions.send(save).await;
+        assert!(rx.await.unwrap());
+
+        let new = "Battery-Staple-2";
+        kit.change_password(user.id, PASSWORD, new, new, None)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let (load, rx) = LoadSession::with_response(other);
+        sessions.send(load).await;
+        assert!(rx.await.unwrap().is_none());
+
+        runtime.shutdown_all().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_htmx_login_regenerates_session() {
+        let kit = kit().await;
+        let user = kit
+            .register("ada@example.com", PASSWORD, PASSWORD)
+            .await
+            .unwrap();
+        let app = kit
+            .routes::<()>()
+            .layer(axum::Extension(SessionData::new()));
+
+        let login = |password: &str| {
+            Request::post("/login")
+                .header("HX-Request", "true")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(Body::from(format!(
+                    "email=ada%40example.com&password={password}"
+                )))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(login("nope")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("HX-Redirect").is_none());
+        assert!(response.extensions().get::<RegenerateSession>().is_none());
+
+        let response = app.oneshot(login(PASSWORD)).await.unwrap();
+        assert_eq!(response.headers()["HX-Redirect"], "/");
+        assert!(response.extensions().get::<RegenerateSession>().is_some());
+        let session = response.extensions().get::<SessionData>().unwrap();
+        assert_eq!(session.user_id, Some(user.id));
+    }
+
+    #[test]
+    fn test_partials_escape_input() {
+        let html = auth_partial(&AuthView::Login {
+            email: "\"><script>",
+            error: Some("<b>"),
+            csrf_token: "tok",
+        });
+        assert!(html.contains("&quot;&gt;&lt;script&gt;"));
+        assert!(html.contains("&lt;b&gt;"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_every_form_carries_the_csrf_token() {
+        let csrf_token = "tok";
+        let views = [
+            AuthView::Login {
+                email: "",
+                error: None,
+                csrf_token,
+            },
+            AuthView::Register {
+                email: "",
+                error: None,
+                csrf_token,
+            },
+            AuthView::Unverified {
+                email: "ada@example.com",
+                csrf_token,
+            },
+            AuthView::VerificationFailed { csrf_token },
+            AuthView::ChangePassword {
+                error: None,
+                changed: false,
+                csrf_token,
+            },
+        ];
+        for view in &views {
+            let html = auth_partial(view);
+            let field = r#"<input type="hidden" name="_csrf_token" value="tok">"#;
+            assert_eq!(html.matches("<form").count(), 1, "{html}");
+            assert_eq!(html.matches(field).count(), 1, "{html}");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_pages_render_the_session_csrf_token() {
+        use crate::htmx::agents::{CsrfManagerAgent, CsrfToken};
+        use crate::htmx::auth::session::SessionId;
+
+        let mut runtime = ActonApp::launch_async().await;
+        let csrf = CsrfManagerAgent::spawn(&mut runtime).await.unwrap();
+        let session_id = SessionId::generate();
+        let (request, rx) = GetOrCreateToken::new(session_id.clone());
+        csrf.send(request).await;
+        let token = rx.await.unwrap();
+        let app = kit()
+            .await
+            .with_csrf_manager(csrf)
+            .routes::<()>()
+            .layer(axum::Extension(session_id))
+            .layer(axum::Extension(SessionData::new()));
+
+        let response = app
+            .oneshot(Request::get("/login").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        let field = r#"name="_csrf_token" value=""#;
+        let start = html.find(field).expect(&html) + field.len();
+        let rendered = &html[start..start + html[start..].find('"').unwrap()];
+        assert!(!html.contains(token.as_str()), "{html}");
+        assert!(token.matches(&CsrfToken::from_string(rendered.to_string())));
+    }
+}
@@ -1,17 +1,40 @@
 //! Authentication and session management
 //!
 //! This module provides session-based authentication with secure HTTP-only cookies.
+//! The `kit` module serves complete login, logout, registration, email
+//! verification and password change flows over HTMX.
+//! With the `microservices` feature, the `passkey` module adds passwordless login
+//! through the auth service, the `token` module exchanges sessions for JWT
+//! access tokens, and the `oidc_provider` module lets internal tools sign users
+//! in with the application over OpenID Connect. The `session_migration`
+//! module moves embedded sessions to the auth service. Social login with
+//! Google, GitHub or any OpenID Connect provider lives in [`oauth2`].
 
 pub mod extractors;
 pub mod handlers;
+pub mod kit;
+#[cfg(feature = "microservices")]
+pub mod oidc_provider;
+#[cfg(feature = "microservices")]
+pub mod passkey;
 pub mod password;
 pub mod session;
+pub mod session_migration;
+#[cfg(feature = "microservices")]
+pub mod token;
 pub mod user;
 
+pub use crate::htmx::oauth2;
 pub use extractors::{Authenticated, AuthenticationError, OptionalAuth};
 pub use handlers::{
     login_form, logout_post, register_form, AuthHandlerError, LoginForm, RegisterForm,
 };
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub use kit::DatabaseAccounts;
+pub use kit::{
+    AccountStore, AuthHooks, AuthKit, AuthKitError, AuthView, DefaultAuthHooks, LoginThrottle,
+    MemoryAccountStore,
+};
 
 // Database-dependent handlers are only available with postgres or sqlite
 #[cfg(any(feature = "postgres", feature = "sqlite"))]
@@ -0,0 +1,567 @@
+//! OpenID Connect provider endpoints ("Sign in with" this application)
+//!
+//! Small internal tools can use the application as their identity provider
+//! with the authorization code flow. The auth service registers clients,
+//! issues codes and signs ID tokens; these handlers serve the browser and
+//! client facing endpoints and render the consent screen from the
+//! `oauth/consent.html` framework template.
+//!
+//! Users who are not logged in are sent to `/login` with the request saved as
+//! the session's `return_url`, where the login handlers pick it up. The
+//! token and userinfo
+//! endpoints are called by clients without a session, so exempt
+//! [`CSRF_EXEMPT_PATHS`] from CSRF protection. ID tokens are verified with
+//! the keys from `/.well-known/jwks.json`, so merge [`token::routes`] too.
+//!
+//! [`token::routes`]: super::token::routes
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use acton_htmx::auth::{oidc_provider, token};
+//! use acton_htmx::middleware::CsrfConfig;
+//!
+//! let csrf = CsrfConfig::default().skip_paths(
+//!     oidc_provider::CSRF_EXEMPT_PATHS.iter().map(ToString::to_string).collect(),
+//! );
+//! let app = Router::new()
+//!     .merge(oidc_provider::routes())
+//!     .merge(token::routes())
+//!     .with_state(state);
+//! ```
+
+use acton_dx_proto::auth::v1::{OidcAuthorizeRequest, OidcExchangeCodeRequest};
+use axum::{
+    extract::{OriginalUri, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::htmx::auth::SessionId;
+use crate::htmx::clients::{AuthClient, ClientError};
+use crate::htmx::extractors::{CsrfTokenExtractor, SessionExtractor};
+use crate::htmx::routing::encode_into;
+use crate::htmx::state::ActonHtmxState;
+
+/// Endpoints called by clients rather than browsers
+pub const CSRF_EXEMPT_PATHS: &[&str] = &["/oauth/token", "/oauth/userinfo"];
+
+/// Page users who are not logged in are sent to
+const LOGIN_PATH: &str = "/login";
+
+/// Session key the login handlers redirect back to
+const RETURN_URL_SESSION_KEY: &str = "return_url";
+
+/// OpenID Connect provider routes
+///
+/// - `GET /oauth/authorize` asks the logged-in user for consent, or sends
+///   the code straight back if they already gave it
+/// - `POST /oauth/authorize` handles the consent form
+/// - `POST /oauth/token` exchanges a code for tokens
+/// - `GET|POST /oauth/userinfo` returns the claims of the token's user
+/// - `GET /.well-known/openid-configuration` serves the discovery document
+pub fn routes() -> Router<ActonHtmxState> {
+    Router::new()
+        .route("/oauth/authorize", get(authorize).post(consent))
+        .route("/oauth/token", post(token))
+        .route("/oauth/userinfo", get(userinfo).post(userinfo))
+        .route("/.well-known/openid-configuration", get(metadata))
+}
+
+/// Query of `GET /oauth/authorize`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuthorizeParams {
+    /// Must be `code`
+    pub response_type: String,
+    /// Client asking to sign the user in
+    pub client_id: String,
+    /// Where to send the code; must be registered for the client
+    pub redirect_uri: String,
+    /// Space-separated scopes; must include `openid`
+    #[serde(default)]
+    pub scope: String,
+    /// Opaque value returned to the client with the code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    /// Value echoed in the ID token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// PKCE challenge; required for public clients
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_challenge: Option<String>,
+    /// PKCE method; only `S256` is supported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_challenge_method: Option<String>,
+}
+
+/// Body of `POST /oauth/authorize`, posted by the consent screen
+#[derive(Debug, Deserialize)]
+pub struct ConsentForm {
+    /// The original authorization request
+    #[serde(flatten)]
+    pub params: AuthorizeParams,
+    /// `allow` or `deny`
+    pub decision: String,
+}
+
+/// Body of `POST /oauth/token`
+#[derive(Debug, Deserialize)]
+pub struct TokenForm {
+    /// Must be `authorization_code`
+    pub grant_type: String,
+    /// Code from the authorization response
+    pub code: String,
+    /// Redirect URI the code was sent to
+    pub redirect_uri: String,
+    /// Client ID, unless sent with HTTP Basic authentication
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// Client secret, unless sent with HTTP Basic authentication
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// PKCE verifier
+    #[serde(default)]
+    pub code_verifier: Option<String>,
+}
+
+/// OAuth 2.0 error response (RFC 6749 §5.2)
+#[derive(Debug, Serialize)]
+pub struct OAuthError {
+    /// HTTP status of the response
+    #[serde(skip)]
+    pub status: StatusCode,
+    /// Error code, e.g. `invalid_grant`
+    pub error: &'static str,
+    /// Human-readable detail
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+impl OAuthError {
+    const fn new(status: StatusCode, error: &'static str) -> Self {
+        Self {
+            status,
+            error,
+            error_description: None,
+        }
+    }
+
+    /// OAuth error for a failed auth service call
+    fn from_client(error: &ClientError) -> Self {
+        let ClientError::ServiceError { code, message } = error else {
+            return Self::new(StatusCode::BAD_GATEWAY, "server_error");
+        };
+        let (status, error) = if *code == tonic::Code::Unauthenticated.to_string() {
+            (StatusCode::UNAUTHORIZED, "invalid_client")
+        } else if *code == tonic::Code::FailedPrecondition.to_string() {
+            (StatusCode::BAD_REQUEST, "invalid_grant")
+        } else if *code == tonic::Code::InvalidArgument.to_string() {
+            (StatusCode::BAD_REQUEST, "invalid_request")
+        } else {
+            return Self::new(StatusCode::BAD_GATEWAY, "server_error");
+        };
+        Self {
+            status,
+            error,
+            error_description: Some(message.clone()),
+        }
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            [(header::CACHE_CONTROL, "no-store")],
+            Json(self),
+        )
+            .into_response()
+    }
+}
+
+fn auth_client(state: &ActonHtmxState) -> Result<Arc<RwLock<AuthClient>>, StatusCode> {
+    state
+        .services()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?
+        .auth()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+}
+
+/// `url` with `params` appended to its query
+fn with_query(url: &str, params: &[(&str, &str)]) -> String {
+    let mut url = url.to_string();
+    let mut separator = if url.contains('?') { '&' } else { '?' };
+    for (name, value) in params {
+        url.push(separator);
+        url.push_str(name);
+        url.push('=');
+        encode_into(&mut url, value, false);
+        separator = '&';
+    }
+    url
+}
+
+/// Redirect back to the client with `result` and the request's `state`
+fn redirect_to_client(params: &AuthorizeParams, result: (&str, &str)) -> Response {
+    let mut query = vec![result];
+    if let Some(state) = &params.state {
+        query.push(("state", state));
+    }
+    Redirect::to(&with_query(&params.redirect_uri, &query)).into_response()
+}
+
+/// What a scope lets the client do, for the consent screen
+fn describe_scope(scope: &str) -> &'static str {
+    match scope {
+        "openid" => "Know who you are",
+        "profile" => "See your name",
+        "email" => "See your email address",
+        _ => "Access your account",
+    }
+}
+
+fn render_error(state: &ActonHtmxState, message: &str) -> Response {
+    let rendered = state.templates().render(
+        "errors/400.html",
+        minijinja::context! { message => message, home_url => "/" },
+    );
+    rendered.map_or_else(
+        |_| (StatusCode::BAD_REQUEST, message.to_string()).into_response(),
+        |html| (StatusCode::BAD_REQUEST, Html(html)).into_response(),
+    )
+}
+
+async fn authorize(
+    State(state): State<ActonHtmxState>,
+    SessionExtractor(session_id, mut session): SessionExtractor,
+    csrf: CsrfTokenExtractor,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<AuthorizeParams>,
+) -> Response {
+    if session.user_id.is_none() {
+        let return_url = uri.path_and_query().map_or("/", |pq| pq.as_str());
+        // Serializing a string cannot fail
+        let _ = session.set(RETURN_URL_SESSION_KEY.to_string(), return_url);
+        let mut response = Redirect::to(LOGIN_PATH).into_response();
+        // The session middleware saves the session from the response
+        response.extensions_mut().insert(session);
+        return response;
+    }
+    run_authorize(&state, &session_id, Some(&csrf), &params, false).await
+}
+
+async fn consent(
+    State(state): State<ActonHtmxState>,
+    SessionExtractor(session_id, session): SessionExtractor,
+    Form(form): Form<ConsentForm>,
+) -> Response {
+    if session.user_id.is_none() {
+        return Redirect::to(LOGIN_PATH).into_response();
+    }
+    if form.decision == "allow" {
+        return run_authorize(&state, &session_id, None, &form.params, true).await;
+    }
+
+    // Only send the refusal to a redirect URI registered for the client
+    let Ok(auth) = auth_client(&state) else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let clients = auth.write().await.list_oidc_clients().await;
+    let registered = clients.is_ok_and(|clients| {
+        clients.iter().any(|client| {
+            client.client_id == form.params.client_id
+                && client.redirect_uris.contains(&form.params.redirect_uri)
+        })
+    });
+    if !registered {
+        return render_error(&state, "Unknown client");
+    }
+    redirect_to_client(&form.params, ("error", "access_denied"))
+}
+
+async fn run_authorize(
+    state: &ActonHtmxState,
+    session_id: &SessionId,
+    csrf: Option<&CsrfTokenExtractor>,
+    params: &AuthorizeParams,
+    consent: bool,
+) -> Response {
+    if params.response_type != "code" {
+        return render_error(state, "Only the authorization code flow is supported");
+    }
+    let auth = match auth_client(state) {
+        Ok(auth) => auth,
+        Err(status) => return status.into_response(),
+    };
+
+    let response = auth
+        .write()
+        .await
+        .oidc_authorize(OidcAuthorizeRequest {
+            session_id: session_id.as_str().to_string(),
+            client_id: params.client_id.clone(),
+            redirect_uri: params.redirect_uri.clone(),
+            scopes: params
+                .scope
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            nonce: params.nonce.clone(),
+            code_challenge: params.code_challenge.clone(),
+            code_challenge_method: params.code_challenge_method.clone(),
+            consent,
+        })
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        // The client or redirect URI may be forged, so never redirect errors
+        Err(ClientError::ServiceError { message, .. }) => {
+            tracing::debug!(client_id = %params.client_id, %message, "Authorization refused");
+            return render_error(state, &message);
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to authorize client");
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    };
+
+    if let Some(code) = &response.code {
+        return redirect_to_client(params, ("code", code));
+    }
+    let Some(csrf) = csrf else {
+        return render_error(state, "Consent was not recorded");
+    };
+
+    let scopes: Vec<_> = response
+        .scopes
+        .iter()
+        .map(|scope| minijinja::context! { name => scope, description => describe_scope(scope) })
+        .collect();
+    let fields: Vec<_> = serde_json::to_value(params)
+        .ok()
+        .and_then(|value| value.as_object().cloned())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(name, value)| {
+            value
+                .as_str()
+                .map(|value| minijinja::context! { name => name, value => value })
+        })
+        .collect();
+    let rendered = state.templates().render(
+        "oauth/consent.html",
+        minijinja::context! {
+            client_name => response.client_name,
+            scopes => scopes,
+            fields => fields,
+            csrf_token => csrf.token(),
+            action => "/oauth/authorize",
+        },
+    );
+    match rendered {
+        // The consent screen must not be framed by the client (clickjacking)
+        Ok(html) => ([(header::X_FRAME_OPTIONS, "DENY")], Html(html)).into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to render consent screen");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Client credentials from HTTP Basic authentication (RFC 6749 §2.3.1)
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (client_id, secret) = decoded.split_once(':')?;
+    Some((client_id.to_string(), secret.to_string()))
+}
+
+async fn token(
+    State(state): State<ActonHtmxState>,
+    headers: HeaderMap,
+    Form(form): Form<TokenForm>,
+) -> Response {
+    if form.grant_type != "authorization_code" {
+        return OAuthError::new(StatusCode::BAD_REQUEST, "unsupported_grant_type").into_response();
+    }
+    let (client_id, client_secret) = match basic_credentials(&headers) {
+        Some(credentials) => credentials,
+        None => match form.client_id {
+            Some(client_id) => (client_id, form.client_secret.unwrap_or_default()),
+            None => {
+                return OAuthError::new(StatusCode::UNAUTHORIZED, "invalid_client").into_response()
+            }
+        },
+    };
+
+    let auth = match auth_client(&state) {
+        Ok(auth) => auth,
+        Err(status) => return status.into_response(),
+    };
+    let issued = auth
+        .write()
+        .await
+        .oidc_exchange_code(OidcExchangeCodeRequest {
+            client_id,
+            client_secret,
+            code: form.code,
+            redirect_uri: form.redirect_uri,
+            code_verifier: form.code_verifier,
+        })
+        .await;
+
+    match issued {
+        Ok(issued) => (
+            [(header::CACHE_CONTROL, "no-store")],
+            Json(serde_json::json!({
+                "access_token": issued.access_token,
+                "id_token": issued.id_token,
+                "token_type": issued.token_type,
+                "expires_in": issued.expires_in,
+                "scope": issued.scope,
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::debug!(error = %e, "Token request refused");
+            OAuthError::from_client(&e).into_response()
+        }
+    }
+}
+
+async fn userinfo(State(state): State<ActonHtmxState>, headers: HeaderMap) -> Response {
+    let unauthorized = || {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, r#"Bearer error="invalid_token""#)],
+        )
+            .into_response()
+    };
+    let Some(access_token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return unauthorized();
+    };
+
+    let auth = match auth_client(&state) {
+        Ok(auth) => auth,
+        Err(status) => return status.into_response(),
+    };
+    let claims = auth.write().await.oidc_userinfo(access_token).await;
+    match claims {
+        Ok(claims) => (
+            [
+                (header::CONTENT_TYPE, "application/json"),
+                (header::CACHE_CONTROL, "no-store"),
+            ],
+            claims,
+        )
+            .into_response(),
+        Err(ClientError::ServiceError { .. }) => unauthorized(),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to fetch userinfo");
+            StatusCode::BAD_GATEWAY.into_response()
+        }
+    }
+}
+
+async fn metadata(State(state): State<ActonHtmxState>) -> Result<Response, StatusCode> {
+    let metadata = auth_client(&state)?
+        .write()
+        .await
+        .oidc_metadata()
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to fetch OpenID configuration");
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        metadata,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_query() {
+        assert_eq!(
+            with_query(
+                "https://wiki.example.com/cb",
+                &[("code", "a b"), ("state", "x/y")]
+            ),
+            "https://wiki.example.com/cb?code=a%20b&state=x%2Fy"
+        );
+        assert_eq!(
+            with_query("https://wiki.example.com/cb?tenant=1", &[("code", "c")]),
+            "https://wiki.example.com/cb?tenant=1&code=c"
+        );
+    }
+
+    #[test]
+    fn test_basic_credentials() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(basic_credentials(&headers), None);
+
+        let encoded = STANDARD.encode("wiki:s3cret:with-colon");
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Basic {encoded}").parse().unwrap(),
+        );
+        assert_eq!(
+            basic_credentials(&headers),
+            Some(("wiki".to_string(), "s3cret:with-colon".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_oauth_error_from_service_status() {
+        let refused = ClientError::from(tonic::Status::failed_precondition("code expired"));
+        let error = OAuthError::from_client(&refused);
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error, "invalid_grant");
+        assert_eq!(error.error_description.as_deref(), Some("code expired"));
+
+        let bad_client = ClientError::from(tonic::Status::unauthenticated("nope"));
+        assert_eq!(
+            OAuthError::from_client(&bad_client).status,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            OAuthError::from_client(&ClientError::Timeout).error,
+            "server_error"
+        );
+    }
+
+    #[test]
+    fn test_consent_form_carries_authorize_params() {
+        let form: ConsentForm = serde_json::from_value(serde_json::json!({
+            "response_type": "code",
+            "client_id": "wiki",
+            "redirect_uri": "https://wiki.example.com/cb",
+            "scope": "openid email",
+            "state": "xyz",
+            "decision": "allow",
+        }))
+        .unwrap();
+        assert_eq!(form.params.state.as_deref(), Some("xyz"));
+        assert_eq!(form.params.nonce, None);
+        assert_eq!(form.decision, "allow");
+    }
+}
@@ -0,0 +1,369 @@
+//! Passkey (WebAuthn) login and registration handlers
+//!
+//! Passwordless login backed by the auth service's passkey ceremonies. The
+//! handlers only relay challenge options and browser credentials; the auth
+//! service verifies them and stores the credentials. The current ceremony ID
+//! lives in the session, so a ceremony can only be finished by the browser
+//! that started it.
+//!
+//! Pair the routes with the `passkey/login_button.html` and
+//! `passkey/manage.html` template partials, which drive
+//! `navigator.credentials` from the browser.
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use acton_htmx::auth::passkey;
+//!
+//! let app = Router::new()
+//!     .merge(passkey::routes())
+//!     .with_state(state);
+//! ```
+
+use acton_dx_proto::auth::v1::PasskeyInfo;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::DateTime;
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::htmx::auth::{FlashMessage, SessionData};
+use crate::htmx::clients::AuthClient;
+use crate::htmx::extractors::SessionExtractor;
+use crate::htmx::state::ActonHtmxState;
+use crate::htmx::template::helpers::escape_html;
+
+/// Session key holding the ceremony the browser is completing
+const CEREMONY_SESSION_KEY: &str = "passkey_ceremony";
+
+/// Passkey routes
+///
+/// - `POST /auth/passkey/login/options` starts a login for `{"email": ...}`
+/// - `POST /auth/passkey/login` finishes it and logs the user in
+/// - `POST /auth/passkey/register/options` starts registering a passkey for
+///   the logged-in user
+/// - `POST /auth/passkey/register` finishes it
+/// - `GET /auth/passkeys` lists the user's passkeys as an HTML partial
+/// - `DELETE /auth/passkeys/{credential_id}` removes one and re-renders the list
+pub fn routes() -> Router<ActonHtmxState> {
+    Router::new()
+        .route("/auth/passkey/login/options", post(login_options))
+        .route("/auth/passkey/login", post(login))
+        .route("/auth/passkey/register/options", post(register_options))
+        .route("/auth/passkey/register", post(register))
+        .route("/auth/passkeys", get(passkey_list))
+        .route("/auth/passkeys/{credential_id}", delete(delete_passkey))
+}
+
+/// Body of `POST /auth/passkey/login/options`
+#[derive(Debug, Deserialize)]
+pub struct LoginOptionsRequest {
+    /// Email of the account to log in to
+    pub email: String,
+}
+
+/// Body of `POST /auth/passkey/register`
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    /// Label for the passkey, e.g. "MacBook Touch ID"
+    #[serde(default)]
+    pub name: String,
+    /// `PublicKeyCredential` returned by `navigator.credentials.create()`
+    pub credential: serde_json::Value,
+}
+
+/// Body of `POST /auth/passkey/login`
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    /// `PublicKeyCredential` returned by `navigator.credentials.get()`
+    pub credential: serde_json::Value,
+}
+
+fn auth_client(state: &ActonHtmxState) -> Result<Arc<RwLock<AuthClient>>, StatusCode> {
+    state
+        .services()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?
+        .auth()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+}
+
+fn require_user(session: &SessionData) -> Result<i64, StatusCode> {
+    session.user_id.ok_or(StatusCode::UNAUTHORIZED)
+}
+
+fn remember_ceremony(session: &mut SessionData, ceremony_id: String) -> Result<(), StatusCode> {
+    session
+        .set(CEREMONY_SESSION_KEY.to_string(), ceremony_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn take_ceremony(session: &mut SessionData) -> Result<String, StatusCode> {
+    let ceremony_id = session
+        .get::<String>(CEREMONY_SESSION_KEY)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    session.remove(CEREMONY_SESSION_KEY);
+    Ok(ceremony_id)
+}
+
+/// Challenge options are already JSON; pass them through untouched
+fn json_options(options_json: String) -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], options_json).into_response()
+}
+
+/// The session middleware saves the session from the response
+fn with_session(mut response: Response, session: SessionData) -> Response {
+    response.extensions_mut().insert(session);
+    response
+}
+
+async fn login_options(
+    State(state): State<ActonHtmxState>,
+    SessionExtractor(_, mut session): SessionExtractor,
+    Json(request): Json<LoginOptionsRequest>,
+) -> Result<Response, StatusCode> {
+    let client = auth_client(&state)?;
+    let mut client = client.write().await;
+
+    // Unknown accounts and accounts without passkeys look the same to the browser
+    let user = client
+        .get_user_by_email(request.email.trim())
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to look up user for passkey login");
+            StatusCode::BAD_GATEWAY
+        })?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let started = client
+        .start_passkey_authentication(user.id)
+        .await
+        .map_err(|e| {
+            tracing::debug!(user_id = user.id, error = %e, "Passkey login unavailable");
+            StatusCode::BAD_REQUEST
+        })?;
+    drop(client);
+
+    remember_ceremony(&mut session, started.ceremony_id)?;
+    Ok(with_session(json_options(started.options_json), session))
+}
+
+async fn login(
+    State(state): State<ActonHtmxState>,
+    SessionExtractor(_, mut session): SessionExtractor,
+    Json(request): Json<LoginRequest>,
+) -> Result<Response, StatusCode> {
+    let ceremony_id = take_ceremony(&mut session)?;
+    let client = auth_client(&state)?;
+
+    let result = client
+        .write()
+        .await
+        .finish_passkey_authentication(&ceremony_id, &request.credential.to_string())
+        .await
+        .map_err(|e| {
+            tracing::warn!(error = %e, "Passkey login failed");
+            StatusCode::UNAUTHORIZED
+        })?;
+
+    // Users deactivated over SCIM keep their passkeys but may not sign in
+    #[cfg(feature = "postgres")]
+    if let Some(pool) = state.pg_pool() {
+        let deactivated = crate::htmx::auth::user::User::is_deactivated(result.user_id, pool)
+            .await
+            .map_err(|e| {
+                tracing::error!(user_id = result.user_id, error = %e, "Failed to check user status");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        if deactivated {
+            tracing::warn!(
+                user_id = result.user_id,
+                "Refused passkey login for a deactivated user"
+            );
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+
+    session.user_id = Some(result.user_id);
+    session
+        .flash_messages
+        .push(FlashMessage::success("Successfully logged in!"));
+    let redirect = session
+        .get::<String>("return_url")
+        .unwrap_or_else(|| "/".to_string());
+    session.remove("return_url");
+
+    tracing::info!(user_id = result.user_id, "User authenticated via passkey");
+    let response = Json(serde_json::json!({ "redirect": redirect })).into_response();
+    Ok(with_session(response, session))
+}
+
+async fn register_options(
+    State(state): State<ActonHtmxState>,
+    SessionExtractor(_, mut session): SessionExtractor,
+) -> Result<Response, StatusCode> {
+    let user_id = require_user(&session)?;
+    let client = auth_client(&state)?;
+    let mut client = client.write().await;
+
+    let user = client
+        .get_user(user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(user_id, error = %e, "Failed to look up user for passkey registration");
+            StatusCode::BAD_GATEWAY
+        })?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let started = client
+        .start_passkey_registration(user.id, &user.email, &user.name)
+        .await
+        .map_err(|e| {
+            tracing::error!(user_id, error = %e, "Failed to start passkey registration");
+            StatusCode::BAD_GATEWAY
+        })?;
+    drop(client);
+
+    remember_ceremony(&mut session, started.ceremony_id)?;
+    Ok(with_session(json_options(started.options_json), session))
+}
+
+async fn register(
+    State(state): State<ActonHtmxState>,
+    SessionExtractor(_, mut session): SessionExtractor,
+    Json(request): Json<RegisterRequest>,
+) -> Result<Response, StatusCode> {
+    let user_id = require_user(&session)?;
+    let ceremony_id = take_ceremony(&mut session)?;
+    let client = auth_client(&state)?;
+    let mut client = client.write().await;
+
+    let passkey = client
+        .finish_passkey_registration(
+            &ceremony_id,
+            user_id,
+            &request.credential.to_string(),
+            request.name.trim(),
+        )
+        .await
+        .map_err(|e| {
+            tracing::warn!(user_id, error = %e, "Passkey registration failed");
+            StatusCode::BAD_REQUEST
+        })?;
+    tracing::info!(user_id, credential_id = %passkey.credential_id, "Passkey registered");
+
+    let passkeys = list(&mut client, user_id).await?;
+    drop(client);
+    Ok(with_session(
+        Html(render_list(&passkeys)).into_response(),
+        session,
+    ))
+}
+
+async fn passkey_list(
+    State(state): State<ActonHtmxState>,
+    SessionExtractor(_, session): SessionExtractor,
+) -> Result<Html<String>, StatusCode> {
+    let user_id = require_user(&session)?;
+    let client = auth_client(&state)?;
+    let mut client = client.write().await;
+
+    let passkeys = list(&mut client, user_id).await?;
+    drop(client);
+    Ok(Html(render_list(&passkeys)))
+}
+
+async fn delete_passkey(
+    State(state): State<ActonHtmxState>,
+    Path(credential_id): Path<String>,
+    SessionExtractor(_, session): SessionExtractor,
+) -> Result<Html<String>, StatusCode> {
+    let user_id = require_user(&session)?;
+    let client = auth_client(&state)?;
+    let mut client = client.write().await;
+
+    let deleted = client
+        .delete_passkey(user_id, &credential_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(user_id, error = %e, "Failed to delete passkey");
+            StatusCode::BAD_GATEWAY
+        })?;
+    if !deleted {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    tracing::info!(user_id, credential_id = %credential_id, "Passkey deleted");
+
+    let passkeys = list(&mut client, user_id).await?;
+    drop(client);
+    Ok(Html(render_list(&passkeys)))
+}
+
+async fn list(client: &mut AuthClient, user_id: i64) -> Result<Vec<PasskeyInfo>, StatusCode> {
+    client.list_passkeys(user_id).await.map_err(|e| {
+        tracing::error!(user_id, error = %e, "Failed to list passkeys");
+        StatusCode::BAD_GATEWAY
+    })
+}
+
+/// `<ul id="passkey-list">` with a remove button per passkey
+fn render_list(passkeys: &[PasskeyInfo]) -> String {
+    if passkeys.is_empty() {
+        return r#"<ul id="passkey-list" class="passkey-list"><li class="passkey-empty">No passkeys yet.</li></ul>"#
+            .to_string();
+    }
+
+    let mut html = String::from(r#"<ul id="passkey-list" class="passkey-list">"#);
+    for passkey in passkeys {
+        let last_used = passkey.last_used_at.map_or_else(
+            || "Never used".to_string(),
+            |at| format!("Last used {}", date(at)),
+        );
+        let _ = write!(
+            html,
+            r##"<li class="passkey"><span class="passkey-name">{name}</span> <span class="passkey-meta">Added {added} &middot; {last_used}</span> <button type="button" hx-delete="/auth/passkeys/{id}" hx-target="#passkey-list" hx-swap="outerHTML" hx-confirm="Remove this passkey?">Remove</button></li>"##,
+            name = escape_html(&passkey.name),
+            added = date(passkey.created_at),
+            id = escape_html(&passkey.credential_id),
+        );
+    }
+    html.push_str("</ul>");
+    html
+}
+
+fn date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map_or_else(String::new, |t| t.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_list() {
+        let html = render_list(&[PasskeyInfo {
+            credential_id: "abc_-1".to_string(),
+            name: "<Work laptop>".to_string(),
+            created_at: 1_740_000_000,
+            last_used_at: None,
+        }]);
+
+        assert!(html.starts_with(r#"<ul id="passkey-list""#));
+        assert!(html.contains("&lt;Work laptop&gt;"));
+        assert!(html.contains(r#"hx-delete="/auth/passkeys/abc_-1""#));
+        assert!(html.contains(r##"hx-target="#passkey-list""##));
+        assert!(html.contains("Added 2025-02-19"));
+        assert!(html.contains("Never used"));
+    }
+
+    #[test]
+    fn test_render_empty_list() {
+        let html = render_list(&[]);
+        assert!(html.contains("No passkeys yet."));
+    }
+}
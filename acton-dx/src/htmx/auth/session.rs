@@ -2,6 +2,7 @@
 //!
 //! This module provides the core session types used throughout the framework.
 
+use crate::htmx::timezone::{parse_timezone, InvalidTimezone};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -67,6 +68,9 @@ pub struct SessionData {
     pub data: HashMap<String, serde_json::Value>,
     /// Flash messages queued for next request
     pub flash_messages: Vec<FlashMessage>,
+    /// IANA timezone of the user (e.g. `Europe/Berlin`)
+    #[serde(default)]
+    pub timezone: Option<String>,
 }
 
 impl SessionData {
@@ -82,6 +86,7 @@ impl SessionData {
             user_name: None,
             data: HashMap::new(),
             flash_messages: Vec::new(),
+            timezone: None,
         }
     }
 
@@ -97,6 +102,7 @@ impl SessionData {
             user_name: None,
             data: HashMap::new(),
             flash_messages: Vec::new(),
+            timezone: None,
         }
     }
 
@@ -168,11 +174,23 @@ impl SessionData {
         self.data.remove(key)
     }
 
+    /// Set the user's timezone
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `name` is not a known IANA timezone
+    pub fn set_timezone(&mut self, name: &str) -> Result<(), InvalidTimezone> {
+        parse_timezone(name)?;
+        self.timezone = Some(name.trim().to_string());
+        Ok(())
+    }
+
     /// Clear all session data (keeps metadata)
     pub fn clear(&mut self) {
         self.data.clear();
         self.flash_messages.clear();
         self.user_id = None;
+        self.timezone = None;
     }
 }
 
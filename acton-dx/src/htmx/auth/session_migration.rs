@@ -0,0 +1,310 @@
+//! Session migration from embedded mode to auth-service
+//!
+//! Switching an application from embedded sessions to the auth-service
+//! without signing everyone out takes three steps:
+//!
+//! 1. Turn on `session_migration.dual_write` with the auth service
+//!    configured and deploy. Sessions are still served by the embedded
+//!    session manager, and every save, flash and delete is copied to
+//!    auth-service as it happens
+//! 2. Run `acton-dx htmx services migrate-sessions`. It reads the sessions
+//!    that existed before dual-write from [`EXPORT_PATH`] and imports them
+//!    into auth-service, keeping their IDs so session cookies stay valid.
+//!    Sessions dual-write already copied are left alone, since they are
+//!    newer than the export
+//! 3. Switch the application to the microservices session layer
+//!
+//! The export endpoint is served behind the bearer tokens listed in
+//! `session_migration.export_tokens`.
+//!
+//! A session manager restarted by its supervisor starts empty and stops
+//! dual-writing; restart the application to resume it.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::auth::session_migration;
+//!
+//! let app = Router::new()
+//!     .merge(routes)
+//!     .merge(session_migration::routes())
+//!     .with_state(state);
+//! ```
+
+use crate::htmx::agents::ExportSessions;
+use crate::htmx::auth::session::{SessionData, SessionId};
+use crate::htmx::observability::autoscaling::token_matches;
+use crate::htmx::state::ActonHtmxState;
+use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+
+#[cfg(feature = "microservices")]
+use crate::htmx::agents::ReplicateSessions;
+#[cfg(feature = "microservices")]
+use crate::htmx::clients::AuthClient;
+#[cfg(feature = "microservices")]
+use acton_dx_proto::auth::v1::{FlashMessage as ProtoFlashMessage, Session, SessionRecord};
+#[cfg(feature = "microservices")]
+use std::sync::Arc;
+#[cfg(feature = "microservices")]
+use tokio::sync::RwLock;
+
+/// Path of the session export
+pub const EXPORT_PATH: &str = "/_sessions/export";
+
+/// How long to wait for the session manager to list its sessions
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A session with its ID, as served by the export endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedSession {
+    /// Session ID, the value of the session cookie
+    pub session_id: SessionId,
+    /// Session data
+    pub session: SessionData,
+}
+
+/// A change to a session, copied to a replica
+#[derive(Debug, Clone)]
+pub enum SessionChange {
+    /// The session was created or changed
+    Saved(ExportedSession),
+    /// The session was deleted
+    Deleted(SessionId),
+}
+
+/// Receives every change the session manager makes, in order
+///
+/// Set with the [`ReplicateSessions`](crate::htmx::agents::ReplicateSessions) message.
+#[derive(Debug, Clone)]
+pub struct SessionReplica {
+    changes: mpsc::UnboundedSender<SessionChange>,
+}
+
+impl SessionReplica {
+    /// Create a replica and the receiver its changes arrive on
+    #[must_use]
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<SessionChange>) {
+        let (changes, receiver) = mpsc::unbounded_channel();
+        (Self { changes }, receiver)
+    }
+
+    pub(crate) fn saved(&self, session_id: &SessionId, session: &SessionData) {
+        let _ = self.changes.send(SessionChange::Saved(ExportedSession {
+            session_id: session_id.clone(),
+            session: session.clone(),
+        }));
+    }
+
+    pub(crate) fn deleted(&self, session_id: &SessionId) {
+        let _ = self
+            .changes
+            .send(SessionChange::Deleted(session_id.clone()));
+    }
+}
+
+/// List every unexpired session held by the session manager
+///
+/// # Errors
+///
+/// Returns an error if the session manager does not answer in time.
+pub async fn export_sessions(
+    session_manager: &ActorHandle,
+) -> anyhow::Result<Vec<ExportedSession>> {
+    let (request, rx) = ExportSessions::with_response();
+    session_manager.send(request).await;
+    let sessions = tokio::time::timeout(EXPORT_TIMEOUT, rx).await??;
+
+    Ok(sessions
+        .into_iter()
+        .map(|(session_id, session)| ExportedSession {
+            session_id,
+            session,
+        })
+        .collect())
+}
+
+/// Session export route, behind the bearer tokens in
+/// `session_migration.export_tokens`
+pub fn routes() -> Router<ActonHtmxState> {
+    Router::new().route(EXPORT_PATH, get(export))
+}
+
+/// Whether the request carries one of `tokens` as its bearer token
+fn authorized(tokens: &[String], headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| {
+            tokens
+                .iter()
+                .any(|expected| token_matches(expected, token.trim()))
+        })
+}
+
+async fn export(State(state): State<ActonHtmxState>, headers: HeaderMap) -> Response {
+    if !authorized(&state.config().session_migration.export_tokens, &headers) {
+        warn!("Rejected session export request with invalid bearer token");
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+        )
+            .into_response();
+    }
+
+    match export_sessions(&state.session_manager()).await {
+        Ok(sessions) => Json(sessions).into_response(),
+        Err(e) => {
+            error!(error = %e, "Failed to export sessions");
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
+
+/// Convert an exported session for auth-service's `ImportSessions`
+///
+/// Data values are stored as strings the way the microservices session
+/// layer reads them back. The CSRF token is left empty for auth-service to
+/// issue.
+#[cfg(feature = "microservices")]
+#[must_use]
+pub fn session_record(exported: &ExportedSession) -> SessionRecord {
+    let session = &exported.session;
+    SessionRecord {
+        session: Some(Session {
+            session_id: exported.session_id.as_str().to_string(),
+            user_id: session.user_id,
+            user_email: session
+                .data
+                .get("user_email")
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string),
+            user_name: session.user_name.clone(),
+            data: crate::htmx::middleware::session::session_data_to_hashmap(session),
+            csrf_token: String::new(),
+            created_at: session.created_at.timestamp(),
+            expires_at: session.expires_at.timestamp(),
+            absolute_expires_at: 0,
+        }),
+        flash_messages: session
+            .flash_messages
+            .iter()
+            .map(|flash| ProtoFlashMessage {
+                level: flash.level.to_string(),
+                message: flash.message.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Start copying every session change to auth-service
+///
+/// Changes are sent one at a time, in the order the session manager made
+/// them, and replace the copy auth-service holds. A change that cannot be
+/// sent is logged and dropped; the next change to the same session sends
+/// it whole again.
+#[cfg(feature = "microservices")]
+pub fn spawn_dual_write(
+    session_manager: ActorHandle,
+    auth: Arc<RwLock<AuthClient>>,
+) -> tokio::task::JoinHandle<()> {
+    let (replica, mut changes) = SessionReplica::channel();
+    tokio::spawn(async move {
+        session_manager.send(ReplicateSessions { replica }).await;
+        tracing::info!("Dual-writing sessions to auth-service");
+
+        while let Some(change) = changes.recv().await {
+            let result = match &change {
+                SessionChange::Saved(exported) => auth
+                    .write()
+                    .await
+                    .import_sessions(vec![session_record(exported)], true)
+                    .await
+                    .map(|_| ()),
+                SessionChange::Deleted(session_id) => auth
+                    .write()
+                    .await
+                    .destroy_session(session_id.as_str())
+                    .await
+                    .map(|_| ()),
+            };
+            if let Err(e) = result {
+                warn!(error = %e, "Failed to copy session change to auth-service");
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::agents::{DeleteSession, ReplicateSessions, SaveSession, SessionManagerAgent};
+    use acton_reactive::prelude::ActonApp;
+
+    #[test]
+    fn test_export_requires_configured_token() {
+        let tokens = vec!["old".to_string(), "new".to_string()];
+        let mut headers = HeaderMap::new();
+        assert!(!authorized(&tokens, &headers));
+
+        headers.insert(header::AUTHORIZATION, "Bearer new".parse().unwrap());
+        assert!(authorized(&tokens, &headers));
+        assert!(!authorized(&[], &headers));
+
+        headers.insert(header::AUTHORIZATION, "Bearer wrong".parse().unwrap());
+        assert!(!authorized(&tokens, &headers));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export_and_replicate_sessions() {
+        let mut runtime = ActonApp::launch_async().await;
+        let agent = SessionManagerAgent::spawn(&mut runtime).await.unwrap();
+
+        let existing = SessionId::generate();
+        let (save, rx) = SaveSession::with_confirmation(existing.clone(), SessionData::new());
+        agent.send(save).await;
+        assert!(rx.await.unwrap());
+
+        let (replica, mut changes) = SessionReplica::channel();
+        agent.send(ReplicateSessions { replica }).await;
+
+        let added = SessionId::generate();
+        let mut data = SessionData::new();
+        data.user_id = Some(7);
+        let (save, rx) = SaveSession::with_confirmation(added.clone(), data);
+        agent.send(save).await;
+        assert!(rx.await.unwrap());
+        agent
+            .send(DeleteSession {
+                session_id: existing.clone(),
+            })
+            .await;
+
+        let Some(SessionChange::Saved(saved)) = changes.recv().await else {
+            panic!("expected the saved session");
+        };
+        assert_eq!(saved.session_id, added);
+        assert_eq!(saved.session.user_id, Some(7));
+        let Some(SessionChange::Deleted(deleted)) = changes.recv().await else {
+            panic!("expected the deleted session");
+        };
+        assert_eq!(deleted, existing);
+
+        let exported = export_sessions(&agent).await.unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].session_id, added);
+
+        runtime.shutdown_all().await.expect("Failed to shutdown");
+    }
+}
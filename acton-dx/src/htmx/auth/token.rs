@@ -0,0 +1,176 @@
+//! JWT access tokens for first-party API clients
+//!
+//! SPAs and mobile apps of the same product exchange a logged-in session for
+//! a short-lived bearer token, then call the REST API without a session
+//! cookie. The auth service signs the tokens and rotates the signing keys;
+//! APIs verify them with [`AuthClient::verify_access_token`] or against the
+//! published JWKS.
+//!
+//! [`AuthClient::verify_access_token`]: crate::htmx::clients::AuthClient::verify_access_token
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use acton_htmx::auth::token;
+//!
+//! let app = Router::new()
+//!     .merge(token::routes())
+//!     .with_state(state);
+//! ```
+//!
+//! ```text
+//! POST /auth/token
+//! {"audience": "api", "scope": "read write"}
+//!
+//! {"access_token": "eyJ...", "token_type": "Bearer", "expires_in": 900, "scope": "read write"}
+//! ```
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::htmx::clients::ClientError;
+use crate::htmx::extractors::SessionExtractor;
+use crate::htmx::state::ActonHtmxState;
+
+/// Token routes
+///
+/// - `POST /auth/token` mints an access token for the logged-in user
+/// - `GET /.well-known/jwks.json` serves the keys that verify the tokens
+pub fn routes() -> Router<ActonHtmxState> {
+    Router::new()
+        .route("/auth/token", post(issue))
+        .route("/.well-known/jwks.json", get(jwks))
+}
+
+/// Body of `POST /auth/token`
+#[derive(Debug, Default, Deserialize)]
+pub struct TokenRequest {
+    /// Audience the token is for; the auth service's default when absent
+    #[serde(default)]
+    pub audience: Option<String>,
+    /// Space-separated scopes; the auth service's defaults when absent
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+/// Response of `POST /auth/token`, shaped like an OAuth 2.0 token response
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    /// Signed JWT
+    pub access_token: String,
+    /// Always `Bearer`
+    pub token_type: String,
+    /// Seconds until the token expires
+    pub expires_in: i64,
+    /// Granted scopes, space separated
+    pub scope: String,
+}
+
+fn scopes(scope: Option<&str>) -> Vec<String> {
+    scope
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+async fn issue(
+    State(state): State<ActonHtmxState>,
+    SessionExtractor(session_id, session): SessionExtractor,
+    body: Bytes,
+) -> Result<Response, StatusCode> {
+    let user_id = session.user_id.ok_or(StatusCode::UNAUTHORIZED)?;
+    // Clients that want the defaults may post an empty body
+    let request: TokenRequest = if body.is_empty() {
+        TokenRequest::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?
+    };
+
+    let client = state
+        .services()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?
+        .auth()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let issued = client
+        .write()
+        .await
+        .issue_access_token(
+            session_id.as_str(),
+            request.audience.as_deref(),
+            scopes(request.scope.as_deref()),
+        )
+        .await
+        .map_err(|e| {
+            // Unknown audience or scope
+            if matches!(e, ClientError::ServiceError { .. }) {
+                tracing::debug!(user_id, error = %e, "Access token refused");
+                StatusCode::BAD_REQUEST
+            } else {
+                tracing::error!(user_id, error = %e, "Failed to issue access token");
+                StatusCode::BAD_GATEWAY
+            }
+        })?;
+
+    let response = TokenResponse {
+        access_token: issued.access_token,
+        token_type: issued.token_type,
+        expires_in: issued.expires_in,
+        scope: issued.scope,
+    };
+    // Tokens are credentials; keep them out of caches (RFC 6749 §5.1)
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)).into_response())
+}
+
+async fn jwks(State(state): State<ActonHtmxState>) -> Result<Response, StatusCode> {
+    let client = state
+        .services()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?
+        .auth()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let jwks = client
+        .write()
+        .await
+        .access_token_jwks()
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to fetch JWKS");
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    // Short enough that verifiers pick up a rotated key promptly
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (header::CACHE_CONTROL, "public, max-age=300"),
+        ],
+        jwks,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes() {
+        assert_eq!(scopes(Some(" read  write ")), vec!["read", "write"]);
+        assert!(scopes(Some("")).is_empty());
+        assert!(scopes(None).is_empty());
+    }
+
+    #[test]
+    fn test_token_request_fields_are_optional() {
+        let request: TokenRequest = serde_json::from_str("{}").unwrap();
+        assert!(request.audience.is_none());
+        assert!(request.scope.is_none());
+    }
+}
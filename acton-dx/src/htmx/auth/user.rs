@@ -59,6 +59,10 @@ pub enum UserError {
     /// Invalid credentials
     #[error("Invalid email or password")]
     InvalidCredentials,
+
+    /// Account was deactivated and cannot sign in
+    #[error("This account has been deactivated")]
+    Deactivated,
 }
 
 /// Email address newtype for validation
@@ -179,6 +183,8 @@ impl std::str::FromStr for EmailAddress {
 ///     roles TEXT[] NOT NULL DEFAULT '{"user"}',
 ///     permissions TEXT[] NOT NULL DEFAULT '{}',
 ///     email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+///     timezone TEXT,
+///     active BOOLEAN NOT NULL DEFAULT TRUE,
 ///     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
 ///     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 /// );
@@ -214,6 +220,13 @@ pub struct User {
     /// Required for certain actions (e.g., posting content)
     pub email_verified: bool,
 
+    /// IANA timezone used to render timestamps (`None` follows the browser)
+    pub timezone: Option<String>,
+
+    /// `false` once deactivated (by SCIM provisioning); such users cannot sign in
+    #[serde(default = "active_by_default")]
+    pub active: bool,
+
     /// Timestamp when user was created
     pub created_at: DateTime<Utc>,
 
@@ -221,6 +234,11 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Users serialized before `active` existed were active
+const fn active_by_default() -> bool {
+    true
+}
+
 // Custom serialization for EmailAddress in User struct
 fn serialize_email<S>(email: &EmailAddress, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -304,7 +322,7 @@ impl User {
             r"
             INSERT INTO users (email, password_hash, roles, permissions, email_verified)
             VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, email, password_hash, roles, permissions, email_verified, created_at, updated_at
+            RETURNING id, email, password_hash, roles, permissions, email_verified, timezone, active, created_at, updated_at
             ",
         )
         .bind(data.email.as_str())
@@ -344,7 +362,7 @@ impl User {
     ) -> Result<Self, UserError> {
         let user = sqlx::query_as::<_, Self>(
             r"
-            SELECT id, email, password_hash, roles, permissions, email_verified, created_at, updated_at
+            SELECT id, email, password_hash, roles, permissions, email_verified, timezone, active, created_at, updated_at
             FROM users
             WHERE email = $1
             ",
@@ -366,7 +384,7 @@ impl User {
     pub async fn find_by_id(id: i64, pool: &sqlx::PgPool) -> Result<Self, UserError> {
         let user = sqlx::query_as::<_, Self>(
             r"
-            SELECT id, email, password_hash, roles, permissions, email_verified, created_at, updated_at
+            SELECT id, email, password_hash, roles, permissions, email_verified, timezone, active, created_at, updated_at
             FROM users
             WHERE id = $1
             ",
@@ -387,6 +405,9 @@ impl User {
     /// - Email not found
     /// - Password incorrect
     ///
+    /// Returns `UserError::Deactivated` if the password is right but the
+    /// account was deactivated.
+    ///
     /// Returns other errors for database or verification failures
     ///
     /// # Example
@@ -423,10 +444,99 @@ impl User {
         if !valid {
             return Err(UserError::InvalidCredentials);
         }
+        if !user.active {
+            return Err(UserError::Deactivated);
+        }
 
         Ok(user)
     }
 
+    /// Whether the user with `id` was deactivated and must not sign in
+    ///
+    /// Users without a row in `users` (for example ones kept only by the
+    /// auth service) are not deactivated.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the database operation fails
+    #[cfg(feature = "postgres")]
+    pub async fn is_deactivated(id: i64, pool: &sqlx::PgPool) -> Result<bool, UserError> {
+        let active: Option<bool> = sqlx::query_scalar("SELECT active FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(active == Some(false))
+    }
+
+    /// Set or clear the user's timezone
+    ///
+    /// # Errors
+    ///
+    /// Returns `UserError::ValidationFailed` for an unknown timezone and
+    /// `UserError::NotFound` if the user does not exist.
+    #[cfg(feature = "postgres")]
+    pub async fn update_timezone(
+        id: i64,
+        timezone: Option<&str>,
+        pool: &sqlx::PgPool,
+    ) -> Result<(), UserError> {
+        let timezone = validate_timezone(timezone)?;
+        let result = sqlx::query("UPDATE users SET timezone = $1 WHERE id = $2")
+            .bind(timezone)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(UserError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Replace the user's password
+    ///
+    /// # Errors
+    ///
+    /// Returns `UserError::WeakPassword` if the password fails the strength
+    /// rules and `UserError::NotFound` if the user does not exist.
+    #[cfg(feature = "postgres")]
+    pub async fn update_password(
+        id: i64,
+        password: &str,
+        pool: &sqlx::PgPool,
+    ) -> Result<(), UserError> {
+        validate_password_strength(password)?;
+        let password_hash = hash_password(password)?;
+        let result =
+            sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+                .bind(&password_hash)
+                .bind(id)
+                .execute(pool)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(UserError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Mark the user's email address as verified
+    ///
+    /// # Errors
+    ///
+    /// Returns `UserError::NotFound` if the user does not exist.
+    #[cfg(feature = "postgres")]
+    pub async fn mark_email_verified(id: i64, pool: &sqlx::PgPool) -> Result<(), UserError> {
+        let result = sqlx::query(
+            "UPDATE users SET email_verified = TRUE, updated_at = NOW() WHERE id = $1",
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(UserError::NotFound);
+        }
+        Ok(())
+    }
+
     // SQLite implementations
 
     /// Create a new user with hashed password (SQLite)
@@ -476,7 +586,7 @@ impl User {
         pool: &sqlx::SqlitePool,
     ) -> Result<Self, UserError> {
         let row = sqlx::query_as::<_, SqliteUserRow>(
-            r"SELECT id, email, password_hash, roles, permissions, email_verified, created_at, updated_at
+            r"SELECT id, email, password_hash, roles, permissions, email_verified, timezone, created_at, updated_at
               FROM users WHERE email = ?",
         )
         .bind(email.as_str())
@@ -495,7 +605,7 @@ impl User {
     #[cfg(feature = "sqlite")]
     pub async fn find_by_id(id: i64, pool: &sqlx::SqlitePool) -> Result<Self, UserError> {
         let row = sqlx::query_as::<_, SqliteUserRow>(
-            r"SELECT id, email, password_hash, roles, permissions, email_verified, created_at, updated_at
+            r"SELECT id, email, password_hash, roles, permissions, email_verified, timezone, created_at, updated_at
               FROM users WHERE id = ?",
         )
         .bind(id)
@@ -510,7 +620,8 @@ impl User {
     ///
     /// # Errors
     ///
-    /// Returns `UserError::InvalidCredentials` if email not found or password incorrect.
+    /// Returns `UserError::InvalidCredentials` if email not found or password incorrect,
+    /// and `UserError::Deactivated` if the account was deactivated.
     #[cfg(feature = "sqlite")]
     pub async fn authenticate(
         email: &EmailAddress,
@@ -528,9 +639,94 @@ impl User {
         if !valid {
             return Err(UserError::InvalidCredentials);
         }
+        if !user.active {
+            return Err(UserError::Deactivated);
+        }
 
         Ok(user)
     }
+
+    /// Set or clear the user's timezone (SQLite)
+    ///
+    /// # Errors
+    ///
+    /// Returns `UserError::ValidationFailed` for an unknown timezone and
+    /// `UserError::NotFound` if the user does not exist.
+    #[cfg(feature = "sqlite")]
+    pub async fn update_timezone(
+        id: i64,
+        timezone: Option<&str>,
+        pool: &sqlx::SqlitePool,
+    ) -> Result<(), UserError> {
+        let timezone = validate_timezone(timezone)?;
+        let result = sqlx::query("UPDATE users SET timezone = ? WHERE id = ?")
+            .bind(timezone)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(UserError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Replace the user's password (SQLite)
+    ///
+    /// # Errors
+    ///
+    /// Returns `UserError::WeakPassword` if the password fails the strength
+    /// rules and `UserError::NotFound` if the user does not exist.
+    #[cfg(feature = "sqlite")]
+    pub async fn update_password(
+        id: i64,
+        password: &str,
+        pool: &sqlx::SqlitePool,
+    ) -> Result<(), UserError> {
+        validate_password_strength(password)?;
+        let password_hash = hash_password(password)?;
+        let result = sqlx::query(
+            "UPDATE users SET password_hash = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(&password_hash)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(UserError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Mark the user's email address as verified (SQLite)
+    ///
+    /// # Errors
+    ///
+    /// Returns `UserError::NotFound` if the user does not exist.
+    #[cfg(feature = "sqlite")]
+    pub async fn mark_email_verified(id: i64, pool: &sqlx::SqlitePool) -> Result<(), UserError> {
+        let result = sqlx::query(
+            "UPDATE users SET email_verified = 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(UserError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+/// Normalize an optional timezone name, rejecting unknown zones
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn validate_timezone(timezone: Option<&str>) -> Result<Option<String>, UserError> {
+    timezone
+        .map(|name| {
+            crate::htmx::timezone::parse_timezone(name)
+                .map(|tz| tz.name().to_string())
+                .map_err(|e| UserError::ValidationFailed(e.to_string()))
+        })
+        .transpose()
 }
 
 /// Helper struct for SQLite queries (stores arrays as JSON strings)
@@ -543,6 +739,7 @@ struct SqliteUserRow {
     roles: String,       // JSON array stored as text
     permissions: String, // JSON array stored as text
     email_verified: bool,
+    timezone: Option<String>,
     created_at: chrono::DateTime<chrono::Utc>,
     updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -563,6 +760,9 @@ impl SqliteUserRow {
             roles,
             permissions,
             email_verified: self.email_verified,
+            timezone: self.timezone,
+            // SCIM, the only thing that deactivates users, needs Postgres
+            active: true,
             created_at: self.created_at,
             updated_at: self.updated_at,
         })
@@ -606,7 +806,7 @@ pub struct CreateUser {
 /// # Errors
 ///
 /// Returns error if password does not meet requirements
-fn validate_password_strength(password: &str) -> Result<(), UserError> {
+pub(crate) fn validate_password_strength(password: &str) -> Result<(), UserError> {
     if password.len() < 8 {
         return Err(UserError::WeakPassword(
             "Password must be at least 8 characters".to_string(),
@@ -705,6 +905,8 @@ mod tests {
             roles: vec!["user".to_string()],
             permissions: vec![],
             email_verified: false,
+            timezone: None,
+            active: true,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -735,6 +937,8 @@ mod tests {
             roles: vec!["user".to_string()],
             permissions: vec![],
             email_verified: false,
+            timezone: None,
+            active: true,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -0,0 +1,1108 @@
+//! Subscription billing with Stripe
+//!
+//! A scaffold for charging users through Stripe Checkout and the customer
+//! portal:
+//!
+//! - [`StripeClient`] creates customers, checkout sessions and portal links
+//! - [`Billing::routes`] serves `POST /billing/checkout`, `POST /billing/portal`
+//!   and the signed `POST /billing/webhook`
+//! - Webhook events are verified, recorded once by ID and processed by
+//!   [`ProcessStripeEventJob`], so Stripe's retries never apply twice
+//! - [`Entitlements`] exposes the user's plan features to handlers and
+//!   templates; [`Entitlements::require`] gates a handler on one
+//!
+//! Customers, subscriptions and events live in a [`BillingStore`]
+//! (in-memory, or data-service with `microservices`).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::billing::{self, Billing, Entitlements, MemoryBillingStore, Plan, PlanCatalog};
+//!
+//! let catalog = PlanCatalog::new(Plan::new("free").with_features(["projects"]))
+//!     .with_plan(Plan::new("pro").with_price("price_123").with_features(["projects", "exports"]));
+//! let billing = Billing::new(
+//!     Arc::new(MemoryBillingStore::new()),
+//!     StripeClient::new(std::env::var("STRIPE_SECRET_KEY")?),
+//!     catalog,
+//!     std::env::var("STRIPE_WEBHOOK_SECRET")?,
+//! )
+//! .with_job_agent(state.job_agent().clone())
+//! .with_urls("https://app.example.com/billing/done", "https://app.example.com/pricing");
+//! billing::install(billing.clone())?;
+//!
+//! async fn export(entitlements: Entitlements) -> Result<Response, BillingError> {
+//!     entitlements.require("exports")?;
+//!     // ...
+//! }
+//!
+//! let app = Router::new()
+//!     .route("/export", post(export))
+//!     .merge(billing.routes())
+//!     .layer(from_fn_with_state(billing.clone(), Billing::middleware));
+//! ```
+//!
+//! In templates: `{% if entitlements.has("exports") %}`.
+
+mod stripe;
+
+#[cfg(feature = "microservices")]
+mod services;
+
+#[cfg(feature = "microservices")]
+pub use services::{DataServiceBillingStore, BILLING_MIGRATION};
+pub use stripe::{verify_signature, StripeClient, DEFAULT_WEBHOOK_TOLERANCE, STRIPE_API_BASE};
+
+use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::post,
+    Extension, Form, Router,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::htmx::auth::session::SessionData;
+use crate::htmx::jobs::agent::EnqueueJob;
+use crate::htmx::jobs::{Job, JobContext, JobError, JobId, JobResult};
+use crate::htmx::util::unix_now;
+
+static BILLING: OnceLock<Billing> = OnceLock::new();
+
+/// Billing errors
+#[derive(Debug, thiserror::Error)]
+pub enum BillingError {
+    /// Stripe rejected a request
+    #[error("Stripe API error ({status}): {message}")]
+    Api {
+        /// HTTP status
+        status: u16,
+        /// Stripe's error message
+        message: String,
+    },
+
+    /// Stripe could not be reached
+    #[error("Stripe request failed: {0}")]
+    Http(String),
+
+    /// Webhook signature missing or wrong
+    #[error("Invalid webhook signature")]
+    InvalidSignature,
+
+    /// Webhook timestamp outside the tolerance window
+    #[error("Webhook timestamp outside tolerance")]
+    StaleWebhook,
+
+    /// Webhook or API payload could not be understood
+    #[error("Invalid payload: {0}")]
+    Payload(String),
+
+    /// Unknown plan, customer or event
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Request has no authenticated user
+    #[error("Sign in to continue")]
+    Unauthenticated,
+
+    /// The user's plan does not include a feature
+    #[error("Your plan does not include {0}")]
+    FeatureUnavailable(String),
+
+    /// [`install`] was called twice
+    #[error("Billing is already installed")]
+    AlreadyInstalled,
+
+    /// [`billing`] was called before [`install`]
+    #[error("Billing is not installed")]
+    NotInstalled,
+
+    /// Backing store failed
+    #[error("Billing store error: {0}")]
+    Store(String),
+}
+
+impl IntoResponse for BillingError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::InvalidSignature | Self::StaleWebhook | Self::Payload(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Self::FeatureUnavailable(_) => StatusCode::PAYMENT_REQUIRED,
+            Self::Api { .. } | Self::Http(_) => StatusCode::BAD_GATEWAY,
+            Self::AlreadyInstalled | Self::NotInstalled | Self::Store(_) => {
+                warn!(error = %self, "Billing failed");
+                return StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// Stripe subscription status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    /// In a free trial
+    Trialing,
+    /// Paid and current
+    Active,
+    /// Latest payment failed; Stripe is retrying
+    PastDue,
+    /// Retries exhausted without payment
+    Unpaid,
+    /// Ended
+    Canceled,
+    /// First payment not yet made
+    Incomplete,
+    /// First payment never made
+    IncompleteExpired,
+    /// Trial ended without a payment method
+    Paused,
+}
+
+impl SubscriptionStatus {
+    /// Stripe's name for the status
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Trialing => "trialing",
+            Self::Active => "active",
+            Self::PastDue => "past_due",
+            Self::Unpaid => "unpaid",
+            Self::Canceled => "canceled",
+            Self::Incomplete => "incomplete",
+            Self::IncompleteExpired => "incomplete_expired",
+            Self::Paused => "paused",
+        }
+    }
+
+    /// Parse Stripe's name for the status
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            Self::Trialing,
+            Self::Active,
+            Self::PastDue,
+            Self::Unpaid,
+            Self::Canceled,
+            Self::Incomplete,
+            Self::IncompleteExpired,
+            Self::Paused,
+        ]
+        .into_iter()
+        .find(|status| status.as_str() == value)
+    }
+
+    /// Whether the subscription's plan features are available
+    ///
+    /// Past-due subscriptions keep access while Stripe retries payment.
+    #[must_use]
+    pub const fn grants_access(self) -> bool {
+        matches!(self, Self::Trialing | Self::Active | Self::PastDue)
+    }
+}
+
+/// A user's Stripe customer record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Customer {
+    /// Application user
+    pub user_id: i64,
+    /// Stripe customer ID (`cus_...`)
+    pub stripe_id: String,
+    /// Unix time (seconds) the record was created
+    pub created_at: u64,
+}
+
+/// A Stripe subscription as last reported by webhooks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subscription {
+    /// Stripe subscription ID (`sub_...`)
+    pub id: String,
+    /// Stripe customer ID
+    pub customer_id: String,
+    /// Price of the first subscription item
+    pub price_id: String,
+    /// Status
+    pub status: SubscriptionStatus,
+    /// Unix time (seconds) the current period ends
+    pub current_period_end: u64,
+    /// Whether the subscription ends at the period end
+    pub cancel_at_period_end: bool,
+    /// Stripe `created` time of the event that last changed the record
+    pub updated_at: u64,
+}
+
+/// A received webhook event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    /// Stripe event ID (`evt_...`)
+    pub id: String,
+    /// Event type, e.g. `customer.subscription.updated`
+    pub event_type: String,
+    /// Raw JSON payload
+    pub payload: String,
+    /// Unix time (seconds) the event was received
+    pub received_at: u64,
+    /// Unix time (seconds) the event was processed
+    pub processed_at: Option<u64>,
+}
+
+/// Storage for billing records
+#[async_trait]
+pub trait BillingStore: Send + Sync {
+    /// Customer record of a user
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Store`] if the store cannot be read.
+    async fn customer_by_user(&self, user_id: i64) -> Result<Option<Customer>, BillingError>;
+
+    /// Customer record by Stripe customer ID
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Store`] if the store cannot be read.
+    async fn customer_by_stripe_id(
+        &self,
+        stripe_id: &str,
+    ) -> Result<Option<Customer>, BillingError>;
+
+    /// Store a customer record, replacing the user's previous one
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Store`] if the store cannot be written.
+    async fn put_customer(&self, customer: &Customer) -> Result<(), BillingError>;
+
+    /// Subscriptions of a Stripe customer
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Store`] if the store cannot be read.
+    async fn subscriptions(&self, customer_id: &str) -> Result<Vec<Subscription>, BillingError>;
+
+    /// Store a subscription, replacing any record with the same ID
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Store`] if the store cannot be written.
+    async fn put_subscription(&self, subscription: &Subscription) -> Result<(), BillingError>;
+
+    /// Record an event, returning `false` if its ID was already recorded
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Store`] if the store cannot be written.
+    async fn insert_event(&self, event: &WebhookEvent) -> Result<bool, BillingError>;
+
+    /// Look up a recorded event
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Store`] if the store cannot be read.
+    async fn event(&self, id: &str) -> Result<Option<WebhookEvent>, BillingError>;
+
+    /// Mark an event processed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Store`] if the store cannot be written.
+    async fn mark_processed(&self, id: &str, at: u64) -> Result<(), BillingError>;
+}
+
+/// In-process billing store
+#[derive(Debug, Default)]
+pub struct MemoryBillingStore {
+    customers: Mutex<HashMap<i64, Customer>>,
+    subscriptions: Mutex<HashMap<String, Subscription>>,
+    events: Mutex<HashMap<String, WebhookEvent>>,
+}
+
+impl MemoryBillingStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl BillingStore for MemoryBillingStore {
+    async fn customer_by_user(&self, user_id: i64) -> Result<Option<Customer>, BillingError> {
+        Ok(self.customers.lock().get(&user_id).cloned())
+    }
+
+    async fn customer_by_stripe_id(
+        &self,
+        stripe_id: &str,
+    ) -> Result<Option<Customer>, BillingError> {
+        Ok(self
+            .customers
+            .lock()
+            .values()
+            .find(|c| c.stripe_id == stripe_id)
+            .cloned())
+    }
+
+    async fn put_customer(&self, customer: &Customer) -> Result<(), BillingError> {
+        self.customers
+            .lock()
+            .insert(customer.user_id, customer.clone());
+        Ok(())
+    }
+
+    async fn subscriptions(&self, customer_id: &str) -> Result<Vec<Subscription>, BillingError> {
+        let mut subscriptions: Vec<_> = self
+            .subscriptions
+            .lock()
+            .values()
+            .filter(|s| s.customer_id == customer_id)
+            .cloned()
+            .collect();
+        subscriptions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        Ok(subscriptions)
+    }
+
+    async fn put_subscription(&self, subscription: &Subscription) -> Result<(), BillingError> {
+        self.subscriptions
+            .lock()
+            .insert(subscription.id.clone(), subscription.clone());
+        Ok(())
+    }
+
+    async fn insert_event(&self, event: &WebhookEvent) -> Result<bool, BillingError> {
+        let mut events = self.events.lock();
+        if events.contains_key(&event.id) {
+            return Ok(false);
+        }
+        events.insert(event.id.clone(), event.clone());
+        drop(events);
+        Ok(true)
+    }
+
+    async fn event(&self, id: &str) -> Result<Option<WebhookEvent>, BillingError> {
+        Ok(self.events.lock().get(id).cloned())
+    }
+
+    async fn mark_processed(&self, id: &str, at: u64) -> Result<(), BillingError> {
+        if let Some(event) = self.events.lock().get_mut(id) {
+            event.processed_at = Some(at);
+        }
+        Ok(())
+    }
+}
+
+/// A plan and the features it unlocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// Plan name, e.g. `pro`
+    pub name: String,
+    /// Stripe price IDs that subscribe to the plan
+    pub prices: Vec<String>,
+    /// Feature flags included in the plan
+    pub features: BTreeSet<String>,
+}
+
+impl Plan {
+    /// A plan with no prices or features
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            prices: Vec::new(),
+            features: BTreeSet::new(),
+        }
+    }
+
+    /// Add a Stripe price for the plan; the first is used for checkout
+    #[must_use]
+    pub fn with_price(mut self, price_id: impl Into<String>) -> Self {
+        self.prices.push(price_id.into());
+        self
+    }
+
+    /// Add feature flags
+    #[must_use]
+    pub fn with_features<I, F>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = F>,
+        F: Into<String>,
+    {
+        self.features.extend(features.into_iter().map(Into::into));
+        self
+    }
+}
+
+/// The plans on offer, with the plan users without a subscription get
+#[derive(Debug, Clone)]
+pub struct PlanCatalog {
+    default: Plan,
+    plans: Vec<Plan>,
+}
+
+impl PlanCatalog {
+    /// Catalog whose unsubscribed users get `default`
+    #[must_use]
+    pub const fn new(default: Plan) -> Self {
+        Self {
+            default,
+            plans: Vec::new(),
+        }
+    }
+
+    /// Add a paid plan
+    #[must_use]
+    pub fn with_plan(mut self, plan: Plan) -> Self {
+        self.plans.push(plan);
+        self
+    }
+
+    /// Plan by name
+    #[must_use]
+    pub fn plan(&self, name: &str) -> Option<&Plan> {
+        std::iter::once(&self.default)
+            .chain(&self.plans)
+            .find(|plan| plan.name == name)
+    }
+
+    /// Plan a Stripe price subscribes to
+    #[must_use]
+    pub fn plan_for_price(&self, price_id: &str) -> Option<&Plan> {
+        self.plans
+            .iter()
+            .find(|plan| plan.prices.iter().any(|p| p == price_id))
+    }
+
+    /// Plan for users without a subscription
+    #[must_use]
+    pub const fn default_plan(&self) -> &Plan {
+        &self.default
+    }
+}
+
+/// The current user's plan and features
+///
+/// Inserted by [`Billing::middleware`]; extraction never fails and yields
+/// an empty set of features when the middleware is absent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Entitlements {
+    /// Plan name
+    pub plan: String,
+    /// Subscription status, `None` on the default plan
+    pub status: Option<SubscriptionStatus>,
+    /// Included feature flags
+    pub features: BTreeSet<String>,
+}
+
+impl Entitlements {
+    fn for_plan(plan: &Plan, status: Option<SubscriptionStatus>) -> Self {
+        Self {
+            plan: plan.name.clone(),
+            status,
+            features: plan.features.clone(),
+        }
+    }
+
+    /// Whether the plan includes `feature`
+    #[must_use]
+    pub fn has(&self, feature: &str) -> bool {
+        self.features.contains(feature)
+    }
+
+    /// Require `feature`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::FeatureUnavailable`] (402) otherwise.
+    pub fn require(&self, feature: &str) -> Result<(), BillingError> {
+        if self.has(feature) {
+            Ok(())
+        } else {
+            Err(BillingError::FeatureUnavailable(feature.to_string()))
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Entitlements
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_default())
+    }
+}
+
+/// Billing manager
+#[derive(Clone)]
+pub struct Billing {
+    store: Arc<dyn BillingStore>,
+    stripe: StripeClient,
+    catalog: Arc<PlanCatalog>,
+    webhook_secret: Arc<str>,
+    tolerance: Duration,
+    job_agent: Option<ActorHandle>,
+    success_url: Arc<str>,
+    cancel_url: Arc<str>,
+}
+
+impl std::fmt::Debug for Billing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Billing")
+            .field("catalog", &self.catalog)
+            .field("tolerance", &self.tolerance)
+            .field("success_url", &self.success_url)
+            .field("cancel_url", &self.cancel_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Billing {
+    /// Create a manager
+    ///
+    /// `webhook_secret` is the endpoint's signing secret (`whsec_...`).
+    /// Without a job agent, webhook events are processed inline.
+    #[must_use]
+    pub fn new(
+        store: Arc<dyn BillingStore>,
+        stripe: StripeClient,
+        catalog: PlanCatalog,
+        webhook_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            store,
+            stripe,
+            catalog: Arc::new(catalog),
+            webhook_secret: webhook_secret.into().into(),
+            tolerance: DEFAULT_WEBHOOK_TOLERANCE,
+            job_agent: None,
+            success_url: "/".into(),
+            cancel_url: "/".into(),
+        }
+    }
+
+    /// Process webhook events with [`ProcessStripeEventJob`] on this agent
+    #[must_use]
+    pub fn with_job_agent(mut self, job_agent: ActorHandle) -> Self {
+        self.job_agent = Some(job_agent);
+        self
+    }
+
+    /// Set where Stripe sends users after checkout and from the portal
+    #[must_use]
+    pub fn with_urls(mut self, success_url: &str, cancel_url: &str) -> Self {
+        self.success_url = success_url.into();
+        self.cancel_url = cancel_url.into();
+        self
+    }
+
+    /// Set the accepted age of webhook timestamps
+    #[must_use]
+    pub const fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Plan catalog
+    #[must_use]
+    pub fn catalog(&self) -> &PlanCatalog {
+        &self.catalog
+    }
+
+    /// The user's Stripe customer, created on first use
+    ///
+    /// # Errors
+    ///
+    /// Returns a Stripe or store error.
+    pub async fn customer_for(
+        &self,
+        user_id: i64,
+        email: Option<&str>,
+    ) -> Result<Customer, BillingError> {
+        if let Some(customer) = self.store.customer_by_user(user_id).await? {
+            return Ok(customer);
+        }
+        let customer = Customer {
+            user_id,
+            stripe_id: self.stripe.create_customer(user_id, email).await?,
+            created_at: unix_now(),
+        };
+        self.store.put_customer(&customer).await?;
+        Ok(customer)
+    }
+
+    /// Stripe Checkout URL subscribing the user to `plan`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::NotFound`] for plans without a price.
+    pub async fn checkout_url(&self, user_id: i64, plan: &str) -> Result<String, BillingError> {
+        let price = self
+            .catalog
+            .plan(plan)
+            .and_then(|plan| plan.prices.first())
+            .ok_or_else(|| BillingError::NotFound(format!("plan {plan}")))?;
+        let customer = self.customer_for(user_id, None).await?;
+        self.stripe
+            .checkout_session(
+                &customer.stripe_id,
+                price,
+                user_id,
+                &self.success_url,
+                &self.cancel_url,
+            )
+            .await
+    }
+
+    /// Stripe customer portal URL for managing the subscription
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::NotFound`] for users who never checked out.
+    pub async fn portal_url(&self, user_id: i64) -> Result<String, BillingError> {
+        let customer = self
+            .store
+            .customer_by_user(user_id)
+            .await?
+            .ok_or_else(|| BillingError::NotFound("customer".to_string()))?;
+        self.stripe
+            .portal_session(&customer.stripe_id, &self.cancel_url)
+            .await
+    }
+
+    /// The user's plan features
+    ///
+    /// Uses the newest subscription that grants access, falling back to the
+    /// catalog's default plan.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Store`] if the store cannot be read.
+    pub async fn entitlements(&self, user_id: i64) -> Result<Entitlements, BillingError> {
+        if let Some(customer) = self.store.customer_by_user(user_id).await? {
+            for subscription in self.store.subscriptions(&customer.stripe_id).await? {
+                if !subscription.status.grants_access() {
+                    continue;
+                }
+                if let Some(plan) = self.catalog.plan_for_price(&subscription.price_id) {
+                    return Ok(Entitlements::for_plan(plan, Some(subscription.status)));
+                }
+            }
+        }
+        Ok(Entitlements::for_plan(self.catalog.default_plan(), None))
+    }
+
+    /// Verify and record a webhook delivery
+    ///
+    /// Returns the event ID if the event is new; redeliveries return `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::InvalidSignature`],
+    /// [`BillingError::StaleWebhook`] or [`BillingError::Payload`].
+    pub async fn receive_webhook(
+        &self,
+        payload: &[u8],
+        signature: &str,
+    ) -> Result<Option<String>, BillingError> {
+        verify_signature(
+            payload,
+            signature,
+            &self.webhook_secret,
+            self.tolerance,
+            unix_now(),
+        )?;
+        let json: Json =
+            serde_json::from_slice(payload).map_err(|e| BillingError::Payload(e.to_string()))?;
+        let id = str_field(&json, "id")?;
+        let event = WebhookEvent {
+            id: id.to_string(),
+            event_type: str_field(&json, "type")?.to_string(),
+            payload: String::from_utf8_lossy(payload).into_owned(),
+            received_at: unix_now(),
+            processed_at: None,
+        };
+        Ok(self
+            .store
+            .insert_event(&event)
+            .await?
+            .then(|| event.id.clone()))
+    }
+
+    /// Apply a recorded event to customers and subscriptions
+    ///
+    /// Processed events are skipped. Handles `checkout.session.completed`
+    /// and `customer.subscription.created|updated|deleted`; other types
+    /// are marked processed without changes. Returns whether the event was
+    /// applied now.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::NotFound`] for unknown events, or a payload
+    /// or store error.
+    pub async fn process_event(&self, id: &str) -> Result<bool, BillingError> {
+        let event = self
+            .store
+            .event(id)
+            .await?
+            .ok_or_else(|| BillingError::NotFound(format!("event {id}")))?;
+        if event.processed_at.is_some() {
+            return Ok(false);
+        }
+        let json: Json = serde_json::from_str(&event.payload)
+            .map_err(|e| BillingError::Payload(e.to_string()))?;
+        let object = &json["data"]["object"];
+        match event.event_type.as_str() {
+            "checkout.session.completed" => self.link_checkout(object).await?,
+            "customer.subscription.created"
+            | "customer.subscription.updated"
+            | "customer.subscription.deleted" => {
+                let created = json["created"].as_u64().unwrap_or_else(unix_now);
+                self.apply_subscription(object, created).await?;
+            }
+            other => debug!(event_type = other, "Ignoring Stripe event"),
+        }
+        self.store.mark_processed(id, unix_now()).await?;
+        Ok(true)
+    }
+
+    async fn link_checkout(&self, session: &Json) -> Result<(), BillingError> {
+        let (Some(user_id), Some(stripe_id)) = (
+            session["client_reference_id"]
+                .as_str()
+                .and_then(|id| id.parse().ok()),
+            session["customer"].as_str(),
+        ) else {
+            return Ok(());
+        };
+        if self.store.customer_by_user(user_id).await?.is_none() {
+            self.store
+                .put_customer(&Customer {
+                    user_id,
+                    stripe_id: stripe_id.to_string(),
+                    created_at: unix_now(),
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn apply_subscription(&self, object: &Json, created: u64) -> Result<(), BillingError> {
+        let status = str_field(object, "status")?;
+        let subscription = Subscription {
+            id: str_field(object, "id")?.to_string(),
+            customer_id: str_field(object, "customer")?.to_string(),
+            price_id: object["items"]["data"][0]["price"]["id"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            status: SubscriptionStatus::parse(status)
+                .ok_or_else(|| BillingError::Payload(format!("unknown status {status}")))?,
+            current_period_end: object["current_period_end"]
+                .as_u64()
+                .or_else(|| object["items"]["data"][0]["current_period_end"].as_u64())
+                .unwrap_or_default(),
+            cancel_at_period_end: object["cancel_at_period_end"].as_bool().unwrap_or(false),
+            updated_at: created,
+        };
+        let stale = self
+            .store
+            .subscriptions(&subscription.customer_id)
+            .await?
+            .iter()
+            .any(|s| s.id == subscription.id && s.updated_at > subscription.updated_at);
+        if stale {
+            debug!(subscription = %subscription.id, "Skipping out-of-order Stripe event");
+            return Ok(());
+        }
+        self.store.put_subscription(&subscription).await
+    }
+
+    async fn dispatch(&self, event_id: String) -> Result<(), BillingError> {
+        let Some(agent) = &self.job_agent else {
+            self.process_event(&event_id).await?;
+            return Ok(());
+        };
+        let job = ProcessStripeEventJob { event_id };
+        let payload = serde_json::to_vec(&job).map_err(|e| BillingError::Payload(e.to_string()))?;
+        agent
+            .send(EnqueueJob {
+                id: JobId::new(),
+                job_type: job.job_type().to_string(),
+                payload,
+                priority: job.priority(),
+                max_retries: job.max_retries(),
+                timeout: job.timeout(),
+                tenant_id: None,
+            })
+            .await;
+        Ok(())
+    }
+
+    /// Middleware inserting [`Entitlements`] for the signed-in user
+    ///
+    /// Use with `axum::middleware::from_fn_with_state`. Store failures are
+    /// logged and fall back to the default plan.
+    pub async fn middleware(
+        State(billing): State<Self>,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        let user_id = request
+            .extensions()
+            .get::<SessionData>()
+            .and_then(|session| session.user_id);
+        let entitlements = match user_id {
+            Some(user_id) => billing.entitlements(user_id).await.unwrap_or_else(|e| {
+                warn!(error = %e, user_id, "Entitlement lookup failed");
+                Entitlements::for_plan(billing.catalog.default_plan(), None)
+            }),
+            None => Entitlements::for_plan(billing.catalog.default_plan(), None),
+        };
+        request.extensions_mut().insert(entitlements);
+        next.run(request).await
+    }
+
+    /// Billing routes
+    ///
+    /// - `POST /billing/checkout` (form `plan`) redirects to Stripe Checkout
+    /// - `POST /billing/portal` redirects to the customer portal
+    /// - `POST /billing/webhook` receives Stripe events; exempt it from CSRF
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/billing/checkout", post(checkout))
+            .route("/billing/portal", post(portal))
+            .route("/billing/webhook", post(webhook))
+            .with_state(self.clone())
+    }
+}
+
+/// Register the manager used by [`ProcessStripeEventJob`]
+///
+/// # Errors
+///
+/// Returns [`BillingError::AlreadyInstalled`] on a second call.
+pub fn install(billing: Billing) -> Result<(), BillingError> {
+    BILLING
+        .set(billing)
+        .map_err(|_| BillingError::AlreadyInstalled)
+}
+
+/// The manager registered with [`install`]
+///
+/// # Errors
+///
+/// Returns [`BillingError::NotInstalled`] before [`install`] is called.
+pub fn billing() -> Result<&'static Billing, BillingError> {
+    BILLING.get().ok_or(BillingError::NotInstalled)
+}
+
+/// Background job applying a recorded Stripe event
+///
+/// Uses the manager registered with [`install`]. Safe to retry: processed
+/// events are skipped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessStripeEventJob {
+    /// Stripe event ID
+    pub event_id: String,
+}
+
+#[async_trait]
+impl Job for ProcessStripeEventJob {
+    type Result = bool;
+
+    async fn execute(&self, _ctx: &JobContext) -> JobResult<Self::Result> {
+        let billing = billing().map_err(|e| JobError::ExecutionFailed(e.to_string()))?;
+        billing
+            .process_event(&self.event_id)
+            .await
+            .map_err(|e| JobError::ExecutionFailed(e.to_string()))
+    }
+
+    fn max_retries(&self) -> u32 {
+        5
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckoutForm {
+    plan: String,
+}
+
+fn user_id(session: Option<&Extension<SessionData>>) -> Result<i64, BillingError> {
+    session
+        .and_then(|Extension(session)| session.user_id)
+        .ok_or(BillingError::Unauthenticated)
+}
+
+fn redirect(url: &str) -> Response {
+    (
+        StatusCode::SEE_OTHER,
+        [("HX-Redirect", url), ("Location", url)],
+    )
+        .into_response()
+}
+
+async fn checkout(
+    State(billing): State<Billing>,
+    session: Option<Extension<SessionData>>,
+    Form(form): Form<CheckoutForm>,
+) -> Result<Response, BillingError> {
+    let url = billing
+        .checkout_url(user_id(session.as_ref())?, &form.plan)
+        .await?;
+    Ok(redirect(&url))
+}
+
+async fn portal(
+    State(billing): State<Billing>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Response, BillingError> {
+    let url = billing.portal_url(user_id(session.as_ref())?).await?;
+    Ok(redirect(&url))
+}
+
+async fn webhook(
+    State(billing): State<Billing>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, BillingError> {
+    let signature = headers
+        .get("Stripe-Signature")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(BillingError::InvalidSignature)?;
+    if let Some(event_id) = billing.receive_webhook(&body, signature).await? {
+        billing.dispatch(event_id).await?;
+    }
+    Ok(StatusCode::OK)
+}
+
+fn str_field<'a>(json: &'a Json, field: &str) -> Result<&'a str, BillingError> {
+    json[field]
+        .as_str()
+        .ok_or_else(|| BillingError::Payload(format!("missing {field}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "whsec_test";
+
+    fn billing() -> Billing {
+        let catalog = PlanCatalog::new(Plan::new("free").with_features(["projects"])).with_plan(
+            Plan::new("pro")
+                .with_price("price_pro")
+                .with_features(["projects", "exports"]),
+        );
+        Billing::new(
+            Arc::new(MemoryBillingStore::new()),
+            StripeClient::new("sk_test"),
+            catalog,
+            SECRET,
+        )
+    }
+
+    fn subscription_event(id: &str, status: &str, created: u64) -> String {
+        serde_json::json!({
+            "id": id,
+            "type": "customer.subscription.updated",
+            "created": created,
+            "data": {"object": {
+                "id": "sub_1",
+                "customer": "cus_1",
+                "status": status,
+                "cancel_at_period_end": false,
+                "current_period_end": 2_000_000_000_u64,
+                "items": {"data": [{"price": {"id": "price_pro"}}]},
+            }},
+        })
+        .to_string()
+    }
+
+    async fn deliver(billing: &Billing, payload: &str) -> Option<String> {
+        let signature = stripe::sign(payload.as_bytes(), SECRET, unix_now());
+        billing
+            .receive_webhook(payload.as_bytes(), &signature)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_subscription_events_update_entitlements() {
+        let billing = billing();
+        billing
+            .store
+            .put_customer(&Customer {
+                user_id: 7,
+                stripe_id: "cus_1".to_string(),
+                created_at: 1,
+            })
+            .await
+            .unwrap();
+        assert_eq!(billing.entitlements(7).await.unwrap().plan, "free");
+
+        let id = deliver(&billing, &subscription_event("evt_1", "active", 100))
+            .await
+            .unwrap();
+        assert!(billing.process_event(&id).await.unwrap());
+        let entitlements = billing.entitlements(7).await.unwrap();
+        assert_eq!(entitlements.plan, "pro");
+        assert!(entitlements.require("exports").is_ok());
+
+        // An older event arriving late does not override the newer state
+        let id = deliver(&billing, &subscription_event("evt_0", "canceled", 50))
+            .await
+            .unwrap();
+        billing.process_event(&id).await.unwrap();
+        assert_eq!(billing.entitlements(7).await.unwrap().plan, "pro");
+
+        let id = deliver(&billing, &subscription_event("evt_2", "canceled", 200))
+            .await
+            .unwrap();
+        billing.process_event(&id).await.unwrap();
+        let entitlements = billing.entitlements(7).await.unwrap();
+        assert_eq!(entitlements.plan, "free");
+        assert!(matches!(
+            entitlements.require("exports"),
+            Err(BillingError::FeatureUnavailable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_is_idempotent() {
+        let billing = billing();
+        let payload = subscription_event("evt_1", "active", 100);
+        let id = deliver(&billing, &payload).await.unwrap();
+        assert!(deliver(&billing, &payload).await.is_none());
+        assert!(billing.process_event(&id).await.unwrap());
+        assert!(!billing.process_event(&id).await.unwrap());
+
+        let forged = stripe::sign(payload.as_bytes(), "whsec_other", unix_now());
+        assert!(matches!(
+            billing.receive_webhook(payload.as_bytes(), &forged).await,
+            Err(BillingError::InvalidSignature)
+        ));
+    }
+}
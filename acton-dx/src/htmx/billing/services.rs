@@ -0,0 +1,292 @@
+//! Billing store backed by data-service
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{BillingError, BillingStore, Customer, Subscription, SubscriptionStatus, WebhookEvent};
+use crate::htmx::clients::{DataClient, Row, Value};
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+/// Migration creating the billing tables
+pub const BILLING_MIGRATION: &str = r"CREATE TABLE IF NOT EXISTS billing_customers (
+    user_id BIGINT PRIMARY KEY,
+    stripe_id TEXT NOT NULL UNIQUE,
+    created_at BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS billing_subscriptions (
+    id TEXT PRIMARY KEY,
+    customer_id TEXT NOT NULL,
+    price_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    current_period_end BIGINT NOT NULL,
+    cancel_at_period_end BOOLEAN NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_billing_subscriptions_customer
+    ON billing_subscriptions (customer_id, updated_at);
+CREATE TABLE IF NOT EXISTS billing_events (
+    id TEXT PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    received_at BIGINT NOT NULL,
+    processed_at BIGINT
+);";
+
+const SUBSCRIPTION_COLUMNS: &str =
+    "id, customer_id, price_id, status, current_period_end, cancel_at_period_end, updated_at";
+
+/// Billing store persisting to data-service
+///
+/// Apply [`BILLING_MIGRATION`] first.
+#[derive(Debug, Clone)]
+pub struct DataServiceBillingStore {
+    client: Arc<RwLock<DataClient>>,
+}
+
+impl DataServiceBillingStore {
+    /// Create a store
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<DataClient>>) -> Self {
+        Self { client }
+    }
+
+    async fn query(&self, sql: &str, params: Vec<Value>) -> Result<Vec<Row>, BillingError> {
+        self.client
+            .write()
+            .await
+            .query(sql, params, None)
+            .await
+            .map_err(|e| BillingError::Store(e.to_string()))
+    }
+
+    async fn execute(&self, sql: &str, params: Vec<Value>) -> Result<i64, BillingError> {
+        let result = self
+            .client
+            .write()
+            .await
+            .execute(sql, params, None)
+            .await
+            .map_err(|e| BillingError::Store(e.to_string()))?;
+        Ok(result.rows_affected)
+    }
+
+    async fn customer(&self, filter: &str, param: Value) -> Result<Option<Customer>, BillingError> {
+        let sql =
+            format!("SELECT user_id, stripe_id, created_at FROM billing_customers WHERE {filter}");
+        let rows = self.query(&sql, vec![param]).await?;
+        rows.first().map(customer_from_row).transpose()
+    }
+}
+
+#[async_trait]
+impl BillingStore for DataServiceBillingStore {
+    async fn customer_by_user(&self, user_id: i64) -> Result<Option<Customer>, BillingError> {
+        self.customer("user_id = $1", int(user_id)).await
+    }
+
+    async fn customer_by_stripe_id(
+        &self,
+        stripe_id: &str,
+    ) -> Result<Option<Customer>, BillingError> {
+        self.customer("stripe_id = $1", text(stripe_id)).await
+    }
+
+    async fn put_customer(&self, customer: &Customer) -> Result<(), BillingError> {
+        self.execute(
+            "INSERT INTO billing_customers (user_id, stripe_id, created_at) VALUES ($1, $2, $3) \
+             ON CONFLICT (user_id) DO UPDATE SET stripe_id = $2",
+            vec![
+                int(customer.user_id),
+                text(&customer.stripe_id),
+                uint(customer.created_at),
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn subscriptions(&self, customer_id: &str) -> Result<Vec<Subscription>, BillingError> {
+        self.query(
+            &format!(
+                "SELECT {SUBSCRIPTION_COLUMNS} FROM billing_subscriptions \
+                 WHERE customer_id = $1 ORDER BY updated_at DESC"
+            ),
+            vec![text(customer_id)],
+        )
+        .await?
+        .iter()
+        .map(subscription_from_row)
+        .collect()
+    }
+
+    async fn put_subscription(&self, subscription: &Subscription) -> Result<(), BillingError> {
+        self.execute(
+            &format!(
+                "INSERT INTO billing_subscriptions ({SUBSCRIPTION_COLUMNS}) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7) \
+                 ON CONFLICT (id) DO UPDATE SET price_id = $3, status = $4, \
+                 current_period_end = $5, cancel_at_period_end = $6, updated_at = $7"
+            ),
+            subscription_params(subscription),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn insert_event(&self, event: &WebhookEvent) -> Result<bool, BillingError> {
+        let inserted = self
+            .execute(
+                "INSERT INTO billing_events (id, event_type, payload, received_at) \
+                 VALUES ($1, $2, $3, $4) ON CONFLICT (id) DO NOTHING",
+                vec![
+                    text(&event.id),
+                    text(&event.event_type),
+                    text(&event.payload),
+                    uint(event.received_at),
+                ],
+            )
+            .await?;
+        Ok(inserted > 0)
+    }
+
+    async fn event(&self, id: &str) -> Result<Option<WebhookEvent>, BillingError> {
+        let rows = self
+            .query(
+                "SELECT id, event_type, payload, received_at, processed_at FROM billing_events \
+                 WHERE id = $1",
+                vec![text(id)],
+            )
+            .await?;
+        rows.first()
+            .map(|row| {
+                Ok(WebhookEvent {
+                    id: column_text(row, "id")?,
+                    event_type: column_text(row, "event_type")?,
+                    payload: column_text(row, "payload")?,
+                    received_at: column_uint(row, "received_at")?,
+                    processed_at: column_uint(row, "processed_at").ok(),
+                })
+            })
+            .transpose()
+    }
+
+    async fn mark_processed(&self, id: &str, at: u64) -> Result<(), BillingError> {
+        self.execute(
+            "UPDATE billing_events SET processed_at = $1 WHERE id = $2",
+            vec![uint(at), text(id)],
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+fn customer_from_row(row: &Row) -> Result<Customer, BillingError> {
+    Ok(Customer {
+        user_id: column_int(row, "user_id")?,
+        stripe_id: column_text(row, "stripe_id")?,
+        created_at: column_uint(row, "created_at")?,
+    })
+}
+
+fn subscription_params(subscription: &Subscription) -> Vec<Value> {
+    vec![
+        text(&subscription.id),
+        text(&subscription.customer_id),
+        text(&subscription.price_id),
+        text(subscription.status.as_str()),
+        uint(subscription.current_period_end),
+        boolean(subscription.cancel_at_period_end),
+        uint(subscription.updated_at),
+    ]
+}
+
+fn subscription_from_row(row: &Row) -> Result<Subscription, BillingError> {
+    let status = column_text(row, "status")?;
+    Ok(Subscription {
+        id: column_text(row, "id")?,
+        customer_id: column_text(row, "customer_id")?,
+        price_id: column_text(row, "price_id")?,
+        status: SubscriptionStatus::parse(&status)
+            .ok_or_else(|| BillingError::Store(format!("unknown status {status}")))?,
+        current_period_end: column_uint(row, "current_period_end")?,
+        cancel_at_period_end: matches!(
+            row.columns
+                .get("cancel_at_period_end")
+                .and_then(|v| v.value.as_ref()),
+            Some(ValueKind::BoolValue(true))
+        ),
+        updated_at: column_uint(row, "updated_at")?,
+    })
+}
+
+fn column_int(row: &Row, name: &str) -> Result<i64, BillingError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::IntValue(v)) => Ok(*v),
+        other => Err(BillingError::Store(format!(
+            "expected integer {name}, got {other:?}"
+        ))),
+    }
+}
+
+fn column_uint(row: &Row, name: &str) -> Result<u64, BillingError> {
+    let value = column_int(row, name)?;
+    u64::try_from(value).map_err(|_| BillingError::Store(format!("negative timestamp {name}")))
+}
+
+fn column_text(row: &Row, name: &str) -> Result<String, BillingError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::StringValue(v)) => Ok(v.clone()),
+        other => Err(BillingError::Store(format!(
+            "expected text {name}, got {other:?}"
+        ))),
+    }
+}
+
+const fn int(value: i64) -> Value {
+    Value {
+        value: Some(ValueKind::IntValue(value)),
+    }
+}
+
+fn uint(value: u64) -> Value {
+    int(i64::try_from(value).unwrap_or(i64::MAX))
+}
+
+const fn boolean(value: bool) -> Value {
+    Value {
+        value: Some(ValueKind::BoolValue(value)),
+    }
+}
+
+fn text(value: &str) -> Value {
+    Value {
+        value: Some(ValueKind::StringValue(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_row_round_trip() {
+        let subscription = Subscription {
+            id: "sub_1".to_string(),
+            customer_id: "cus_1".to_string(),
+            price_id: "price_pro".to_string(),
+            status: SubscriptionStatus::PastDue,
+            current_period_end: 2_000,
+            cancel_at_period_end: true,
+            updated_at: 100,
+        };
+        let row = Row {
+            columns: SUBSCRIPTION_COLUMNS
+                .split(", ")
+                .map(str::to_string)
+                .zip(subscription_params(&subscription))
+                .collect(),
+        };
+        assert_eq!(subscription_from_row(&row).unwrap(), subscription);
+    }
+}
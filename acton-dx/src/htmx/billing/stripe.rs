@@ -0,0 +1,256 @@
+//! Stripe API client and webhook signature verification
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::BillingError;
+
+/// Stripe API base URL
+pub const STRIPE_API_BASE: &str = "https://api.stripe.com/v1";
+
+/// Accepted age of webhook timestamps (Stripe's recommended 5 minutes)
+pub const DEFAULT_WEBHOOK_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// Minimal Stripe API client for checkout and the customer portal
+///
+/// Requests are form-encoded as the Stripe API expects; responses are read
+/// as JSON and only the fields billing needs are extracted.
+#[derive(Clone)]
+pub struct StripeClient {
+    http: reqwest::Client,
+    secret_key: Arc<str>,
+    base_url: Arc<str>,
+}
+
+impl std::fmt::Debug for StripeClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StripeClient")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StripeClient {
+    /// Create a client using a secret API key (`sk_...`)
+    #[must_use]
+    pub fn new(secret_key: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            secret_key: secret_key.into().into(),
+            base_url: STRIPE_API_BASE.into(),
+        }
+    }
+
+    /// Send requests to another base URL, e.g. `stripe-mock`
+    #[must_use]
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').into();
+        self
+    }
+
+    /// Create a customer tagged with the application user ID
+    ///
+    /// Returns the Stripe customer ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Api`] or [`BillingError::Http`].
+    pub async fn create_customer(
+        &self,
+        user_id: i64,
+        email: Option<&str>,
+    ) -> Result<String, BillingError> {
+        let user_id = user_id.to_string();
+        let mut form = vec![("metadata[user_id]", user_id.as_str())];
+        if let Some(email) = email {
+            form.push(("email", email));
+        }
+        let customer = self.post("customers", &form).await?;
+        field(&customer, "id")
+    }
+
+    /// Create a subscription checkout session
+    ///
+    /// The user ID is sent as `client_reference_id` so the completed session
+    /// can be linked back. Returns the hosted checkout URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Api`] or [`BillingError::Http`].
+    pub async fn checkout_session(
+        &self,
+        customer_id: &str,
+        price_id: &str,
+        user_id: i64,
+        success_url: &str,
+        cancel_url: &str,
+    ) -> Result<String, BillingError> {
+        let user_id = user_id.to_string();
+        let session = self
+            .post(
+                "checkout/sessions",
+                &[
+                    ("mode", "subscription"),
+                    ("customer", customer_id),
+                    ("client_reference_id", &user_id),
+                    ("line_items[0][price]", price_id),
+                    ("line_items[0][quantity]", "1"),
+                    ("success_url", success_url),
+                    ("cancel_url", cancel_url),
+                ],
+            )
+            .await?;
+        field(&session, "url")
+    }
+
+    /// Create a customer portal session, returning its URL
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BillingError::Api`] or [`BillingError::Http`].
+    pub async fn portal_session(
+        &self,
+        customer_id: &str,
+        return_url: &str,
+    ) -> Result<String, BillingError> {
+        let session = self
+            .post(
+                "billing_portal/sessions",
+                &[("customer", customer_id), ("return_url", return_url)],
+            )
+            .await?;
+        field(&session, "url")
+    }
+
+    async fn post(
+        &self,
+        path: &str,
+        form: &[(&str, &str)],
+    ) -> Result<serde_json::Value, BillingError> {
+        let response = self
+            .http
+            .post(format!("{}/{path}", self.base_url))
+            .bearer_auth(&*self.secret_key)
+            .form(form)
+            .send()
+            .await
+            .map_err(|e| BillingError::Http(e.to_string()))?;
+        let status = response.status();
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| BillingError::Payload(e.to_string()))?;
+        if !status.is_success() {
+            return Err(BillingError::Api {
+                status: status.as_u16(),
+                message: body["error"]["message"]
+                    .as_str()
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            });
+        }
+        Ok(body)
+    }
+}
+
+/// Verify a `Stripe-Signature` header against the raw request body
+///
+/// The header carries a timestamp `t` and one or more `v1` signatures, each
+/// an HMAC-SHA256 of `"{t}.{payload}"` keyed by the endpoint secret. Any
+/// matching `v1` is accepted, which covers secret rolls.
+///
+/// # Errors
+///
+/// Returns [`BillingError::InvalidSignature`] if no signature matches, or
+/// [`BillingError::StaleWebhook`] if `t` is further than `tolerance` from
+/// `now` (Unix seconds).
+pub fn verify_signature(
+    payload: &[u8],
+    header: &str,
+    secret: &str,
+    tolerance: Duration,
+    now: u64,
+) -> Result<(), BillingError> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<u64>().ok(),
+            Some(("v1", value)) => signatures.extend(hex::decode(value).ok()),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or(BillingError::InvalidSignature)?;
+    let valid = signatures.iter().any(|signature| {
+        mac(payload, secret, timestamp).is_ok_and(|mac| mac.verify_slice(signature).is_ok())
+    });
+    if !valid {
+        return Err(BillingError::InvalidSignature);
+    }
+    if now.abs_diff(timestamp) > tolerance.as_secs() {
+        return Err(BillingError::StaleWebhook);
+    }
+    Ok(())
+}
+
+fn mac(payload: &[u8], secret: &str, timestamp: u64) -> Result<Hmac<Sha256>, BillingError> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes())
+        .map_err(|_| BillingError::InvalidSignature)?;
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    Ok(mac)
+}
+
+/// Build a `Stripe-Signature` header, as Stripe does
+#[cfg(test)]
+pub(super) fn sign(payload: &[u8], secret: &str, timestamp: u64) -> String {
+    let signature = mac(payload, secret, timestamp)
+        .unwrap()
+        .finalize()
+        .into_bytes();
+    format!("t={timestamp},v1={}", hex::encode(signature))
+}
+
+fn field(json: &serde_json::Value, name: &str) -> Result<String, BillingError> {
+    json[name]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| BillingError::Payload(format!("Stripe response missing {name}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_signature() {
+        let payload = br#"{"id":"evt_1"}"#;
+        let header = sign(payload, "whsec_a", 1_000);
+        let tolerance = DEFAULT_WEBHOOK_TOLERANCE;
+
+        assert!(verify_signature(payload, &header, "whsec_a", tolerance, 1_100).is_ok());
+        // A rolled secret leaves both signatures in the header
+        let rolled = format!("{header},v1={}", "00".repeat(32));
+        assert!(verify_signature(payload, &rolled, "whsec_a", tolerance, 1_000).is_ok());
+
+        assert!(matches!(
+            verify_signature(payload, &header, "whsec_b", tolerance, 1_000),
+            Err(BillingError::InvalidSignature)
+        ));
+        assert!(matches!(
+            verify_signature(b"{}", &header, "whsec_a", tolerance, 1_000),
+            Err(BillingError::InvalidSignature)
+        ));
+        assert!(matches!(
+            verify_signature(payload, &header, "whsec_a", tolerance, 2_000),
+            Err(BillingError::StaleWebhook)
+        ));
+        assert!(matches!(
+            verify_signature(payload, "v1=abc", "whsec_a", tolerance, 1_000),
+            Err(BillingError::InvalidSignature)
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+//! Bulk authorizer backed by the Cedar service
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{BulkAuthorizer, BulkError, BulkPrincipal};
+use crate::htmx::clients::{AuthorizationRequest, CedarClient};
+
+/// Requests sent per batch call
+const BATCH_SIZE: usize = 500;
+
+/// Authorizes each row as a `resource_type` entity through the Cedar
+/// service's batch API
+#[derive(Debug, Clone)]
+pub struct CedarBulkAuthorizer {
+    client: Arc<RwLock<CedarClient>>,
+    resource_type: String,
+}
+
+impl CedarBulkAuthorizer {
+    /// Create an authorizer for rows of `resource_type`
+    #[must_use]
+    pub fn new(client: Arc<RwLock<CedarClient>>, resource_type: impl Into<String>) -> Self {
+        Self {
+            client,
+            resource_type: resource_type.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl BulkAuthorizer for CedarBulkAuthorizer {
+    async fn authorize(
+        &self,
+        principal: &BulkPrincipal,
+        action: &str,
+        ids: &[String],
+    ) -> Result<Vec<bool>, BulkError> {
+        let mut decisions = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(BATCH_SIZE) {
+            let requests = chunk
+                .iter()
+                .map(|id| AuthorizationRequest {
+                    principal_type: principal.entity_type.clone(),
+                    principal_id: principal.id.clone(),
+                    action: action.to_string(),
+                    resource_type: self.resource_type.clone(),
+                    resource_id: id.clone(),
+                    context: HashMap::new(),
+                })
+                .collect();
+            let results = self
+                .client
+                .write()
+                .await
+                .batch_authorize(requests)
+                .await
+                .map_err(|e| BulkError::Authorization(e.to_string()))?;
+            decisions.extend(results.into_iter().map(|result| result.allowed));
+        }
+        Ok(decisions)
+    }
+}
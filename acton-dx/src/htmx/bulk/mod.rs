@@ -0,0 +1,703 @@
+//! Bulk actions for tables
+//!
+//! Selecting rows and applying one action to all of them (delete, export,
+//! tag) takes three pieces:
+//!
+//! - [`BulkToolbar`] renders the form with a select-all checkbox, the
+//!   selected count and one button per action, and [`BulkCheckbox`] the
+//!   checkbox of each row; [`routes`] serves the small script that keeps
+//!   them in sync
+//! - [`BulkSelection`] extracts the posted `action` and row `ids`
+//! - [`BulkActions`] checks every row against a [`BulkAuthorizer`] in one
+//!   batch, then runs small selections inline and enqueues a job for large
+//!   ones, returning the [operation progress partial](crate::htmx::handlers::operations)
+//!
+//! With the `microservices` feature, [`CedarBulkAuthorizer`] authorizes
+//! rows through the Cedar service's batch API.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_dx::htmx::bulk::{BulkActions, BulkPrincipal, BulkSelection, CedarBulkAuthorizer};
+//!
+//! let bulk = BulkActions::new(CedarBulkAuthorizer::new(cedar, "Document"))
+//!     .confirm_action("delete", "Delete", "Delete the selected documents?")
+//!     .action("export", "Export");
+//!
+//! async fn documents_bulk(
+//!     State(state): State<ActonHtmxState>,
+//!     Extension(bulk): Extension<BulkActions>,
+//!     Extension(operations): Extension<Operations>,
+//!     Authenticated(user): Authenticated<User>,
+//!     selection: BulkSelection,
+//! ) -> Result<Response, BulkError> {
+//!     let selection = bulk.authorize(&BulkPrincipal::user(user.id), selection).await?;
+//!     bulk.run(
+//!         &state,
+//!         &operations,
+//!         selection,
+//!         |selection| async move {
+//!             delete_documents(&selection.allowed).await;
+//!             Html(selection.summary("Deleted"))
+//!         },
+//!         |selection| DeleteDocumentsJob { ids: selection.allowed },
+//!     )
+//!     .await
+//! }
+//! ```
+
+#[cfg(feature = "microservices")]
+mod cedar;
+
+#[cfg(feature = "microservices")]
+pub use cedar::CedarBulkAuthorizer;
+
+use askama::Template;
+use async_trait::async_trait;
+use axum::{
+    extract::{Form, FromRequest, Request},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::{self, Write as _};
+use std::future::Future;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::htmx::feedback::error_fragment;
+use crate::htmx::handlers::operations::Operations;
+use crate::htmx::jobs::Job;
+use crate::htmx::state::ActonHtmxState;
+use crate::htmx::template::helpers::escape_html;
+
+/// Path of the selection script
+pub const SCRIPT_PATH: &str = "/_acton/bulk.js";
+
+/// Form field naming the action
+pub const ACTION_FIELD: &str = "action";
+
+/// Form field repeated once per selected row
+pub const IDS_FIELD: &str = "ids";
+
+/// Bulk action errors
+#[derive(Debug, thiserror::Error)]
+pub enum BulkError {
+    /// The form could not be read
+    #[error("Invalid bulk selection: {0}")]
+    Invalid(String),
+
+    /// No rows were selected
+    #[error("Select at least one row")]
+    Empty,
+
+    /// More rows were selected than allowed
+    #[error("Too many rows selected: {selected} (at most {max})")]
+    TooMany {
+        /// Rows selected
+        selected: usize,
+        /// Most rows allowed
+        max: usize,
+    },
+
+    /// The action is not one of the configured actions
+    #[error("Unknown bulk action: {0}")]
+    UnknownAction(String),
+
+    /// The principal may not act on any selected row
+    #[error("You don't have permission to change the selected rows")]
+    Forbidden,
+
+    /// The authorizer failed
+    #[error("Bulk authorization failed: {0}")]
+    Authorization(String),
+
+    /// The job could not be enqueued
+    #[error("Failed to start bulk action")]
+    Enqueue(StatusCode),
+}
+
+impl IntoResponse for BulkError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Self::Invalid(_) | Self::Empty | Self::TooMany { .. } | Self::UnknownAction(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::Authorization(_) => {
+                warn!(error = %self, "Bulk action authorization failed");
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            Self::Enqueue(status) => *status,
+        };
+        (status, Html(error_fragment(status, &self.to_string()))).into_response()
+    }
+}
+
+/// Rows selected for a bulk action
+///
+/// Extracted from a form posting `action=<name>` and one `ids=<id>` per
+/// row. Ids are deduplicated, keeping the first occurrence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkSelection {
+    /// Action name
+    pub action: String,
+    /// Selected row ids in table order
+    pub ids: Vec<String>,
+}
+
+impl<S> FromRequest<S> for BulkSelection
+where
+    S: Send + Sync,
+{
+    type Rejection = BulkError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Form(fields) = Form::<Vec<(String, String)>>::from_request(req, state)
+            .await
+            .map_err(|e| BulkError::Invalid(e.body_text()))?;
+        let mut action = None;
+        let mut seen = HashSet::new();
+        let mut ids = Vec::new();
+        for (name, value) in fields {
+            if name == ACTION_FIELD {
+                action = Some(value);
+            } else if name == IDS_FIELD && !value.is_empty() && seen.insert(value.clone()) {
+                ids.push(value);
+            }
+        }
+        let action = action.ok_or_else(|| BulkError::Invalid("missing action".to_string()))?;
+        Ok(Self { action, ids })
+    }
+}
+
+/// Who is performing a bulk action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkPrincipal {
+    /// Entity type, e.g. `User`
+    pub entity_type: String,
+    /// Entity id
+    pub id: String,
+}
+
+impl BulkPrincipal {
+    /// A `User` principal
+    #[must_use]
+    pub fn user(id: impl fmt::Display) -> Self {
+        Self {
+            entity_type: "User".to_string(),
+            id: id.to_string(),
+        }
+    }
+}
+
+/// Per-row authorization of bulk actions
+#[async_trait]
+pub trait BulkAuthorizer: Send + Sync {
+    /// Whether `principal` may perform `action` on each row, in `ids` order
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BulkError::Authorization`] if the decision cannot be made.
+    async fn authorize(
+        &self,
+        principal: &BulkPrincipal,
+        action: &str,
+        ids: &[String],
+    ) -> Result<Vec<bool>, BulkError>;
+}
+
+/// A selection split into the rows the principal may and may not act on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuthorizedSelection {
+    /// Action name
+    pub action: String,
+    /// Rows to act on
+    pub allowed: Vec<String>,
+    /// Rows skipped because the principal may not act on them
+    pub denied: Vec<String>,
+}
+
+impl AuthorizedSelection {
+    /// Result partial for an inline action, e.g. `summary("Deleted")`
+    #[must_use]
+    pub fn summary(&self, done: &str) -> String {
+        let mut html = format!(
+            r#"<p class="bulk-summary">{} {} {}."#,
+            escape_html(done),
+            self.allowed.len(),
+            rows(self.allowed.len())
+        );
+        if !self.denied.is_empty() {
+            let _ = write!(
+                html,
+                " Skipped {} {} you don't have permission to change.",
+                self.denied.len(),
+                rows(self.denied.len())
+            );
+        }
+        html.push_str("</p>");
+        html
+    }
+}
+
+const fn rows(count: usize) -> &'static str {
+    if count == 1 {
+        "row"
+    } else {
+        "rows"
+    }
+}
+
+/// A bulk action offered by the toolbar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkAction {
+    /// Name posted as `action`
+    pub name: String,
+    /// Button text
+    pub label: String,
+    /// Confirmation prompt shown before posting
+    pub confirm: Option<String>,
+}
+
+/// Bulk action endpoint helper
+///
+/// Defaults: at most 10,000 rows per request, and selections of more than
+/// 100 allowed rows go to a background job.
+#[derive(Clone)]
+pub struct BulkActions {
+    authorizer: Arc<dyn BulkAuthorizer>,
+    actions: Vec<BulkAction>,
+    inline_limit: usize,
+    max_selection: usize,
+}
+
+impl fmt::Debug for BulkActions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BulkActions")
+            .field("actions", &self.actions)
+            .field("inline_limit", &self.inline_limit)
+            .field("max_selection", &self.max_selection)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BulkActions {
+    /// Create a helper checking rows with `authorizer`
+    pub fn new(authorizer: impl BulkAuthorizer + 'static) -> Self {
+        Self {
+            authorizer: Arc::new(authorizer),
+            actions: Vec::new(),
+            inline_limit: 100,
+            max_selection: 10_000,
+        }
+    }
+
+    /// Offer an action
+    #[must_use]
+    pub fn action(mut self, name: impl Into<String>, label: impl Into<String>) -> Self {
+        self.actions.push(BulkAction {
+            name: name.into(),
+            label: label.into(),
+            confirm: None,
+        });
+        self
+    }
+
+    /// Offer an action that asks for confirmation first
+    #[must_use]
+    pub fn confirm_action(
+        mut self,
+        name: impl Into<String>,
+        label: impl Into<String>,
+        prompt: impl Into<String>,
+    ) -> Self {
+        self.actions.push(BulkAction {
+            name: name.into(),
+            label: label.into(),
+            confirm: Some(prompt.into()),
+        });
+        self
+    }
+
+    /// Set the most allowed rows run inline rather than as a job
+    #[must_use]
+    pub const fn inline_limit(mut self, inline_limit: usize) -> Self {
+        self.inline_limit = inline_limit;
+        self
+    }
+
+    /// Set the most rows accepted in one request
+    #[must_use]
+    pub const fn max_selection(mut self, max_selection: usize) -> Self {
+        self.max_selection = max_selection;
+        self
+    }
+
+    /// The offered actions
+    #[must_use]
+    pub fn actions(&self) -> &[BulkAction] {
+        &self.actions
+    }
+
+    /// Toolbar for a table, posting to `url`
+    #[must_use]
+    pub fn toolbar(&self, id: impl Into<String>, url: impl Into<String>) -> BulkToolbar {
+        BulkToolbar {
+            id: id.into(),
+            url: url.into(),
+            actions: self.actions.clone(),
+            csrf_token: None,
+        }
+    }
+
+    /// Check a selection and every selected row
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the action is unknown, the selection is empty or
+    /// too large, the principal may act on none of the rows, or the
+    /// authorizer fails.
+    pub async fn authorize(
+        &self,
+        principal: &BulkPrincipal,
+        selection: BulkSelection,
+    ) -> Result<AuthorizedSelection, BulkError> {
+        if !self.actions.iter().any(|a| a.name == selection.action) {
+            return Err(BulkError::UnknownAction(selection.action));
+        }
+        if selection.ids.is_empty() {
+            return Err(BulkError::Empty);
+        }
+        if selection.ids.len() > self.max_selection {
+            return Err(BulkError::TooMany {
+                selected: selection.ids.len(),
+                max: self.max_selection,
+            });
+        }
+        let decisions = self
+            .authorizer
+            .authorize(principal, &selection.action, &selection.ids)
+            .await?;
+        if decisions.len() != selection.ids.len() {
+            return Err(BulkError::Authorization(format!(
+                "expected {} decisions, got {}",
+                selection.ids.len(),
+                decisions.len()
+            )));
+        }
+        let (allowed, denied): (Vec<_>, Vec<_>) = selection
+            .ids
+            .into_iter()
+            .zip(decisions)
+            .partition(|(_, allowed)| *allowed);
+        if allowed.is_empty() {
+            return Err(BulkError::Forbidden);
+        }
+        Ok(AuthorizedSelection {
+            action: selection.action,
+            allowed: allowed.into_iter().map(|(id, _)| id).collect(),
+            denied: denied.into_iter().map(|(id, _)| id).collect(),
+        })
+    }
+
+    /// Whether a selection goes to a background job
+    #[must_use]
+    pub fn is_large(&self, selection: &AuthorizedSelection) -> bool {
+        selection.allowed.len() > self.inline_limit
+    }
+
+    /// Run an authorized selection
+    ///
+    /// Small selections are passed to `inline`, whose response is returned
+    /// as-is. Large ones are turned into a job by `job` and enqueued through
+    /// `operations`, returning its progress partial; the job reports its
+    /// result through [`JobContext::operation`](crate::htmx::jobs::JobContext::operation).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BulkError::Enqueue`] if the job cannot be enqueued.
+    pub async fn run<F, Fut, R, G, J>(
+        &self,
+        state: &ActonHtmxState,
+        operations: &Operations,
+        selection: AuthorizedSelection,
+        inline: F,
+        job: G,
+    ) -> Result<Response, BulkError>
+    where
+        F: FnOnce(AuthorizedSelection) -> Fut,
+        Fut: Future<Output = R>,
+        R: IntoResponse,
+        G: FnOnce(AuthorizedSelection) -> J,
+        J: Job + Serialize,
+    {
+        if !self.is_large(&selection) {
+            return Ok(inline(selection).await.into_response());
+        }
+        let html = operations
+            .start(state, &job(selection))
+            .await
+            .map_err(BulkError::Enqueue)?;
+        Ok(html.into_response())
+    }
+}
+
+/// Bulk action toolbar partial (`partials/bulk_toolbar.html`)
+///
+/// Results and progress partials are swapped into `#<id>-status`.
+#[derive(Debug, Clone, Template)]
+#[template(path = "partials/bulk_toolbar.html")]
+pub struct BulkToolbar {
+    /// Form id, referenced by the row checkboxes
+    pub id: String,
+    /// Bulk action endpoint
+    pub url: String,
+    /// Action buttons
+    pub actions: Vec<BulkAction>,
+    /// CSRF token field value
+    pub csrf_token: Option<String>,
+}
+
+impl BulkToolbar {
+    /// Include a CSRF token field
+    #[must_use]
+    pub fn csrf_token(mut self, token: impl Into<String>) -> Self {
+        self.csrf_token = Some(token.into());
+        self
+    }
+
+    /// Checkbox selecting the row `id` for this toolbar
+    #[must_use]
+    pub fn checkbox(&self, id: impl fmt::Display) -> BulkCheckbox {
+        BulkCheckbox {
+            form: self.id.clone(),
+            id: id.to_string(),
+        }
+    }
+}
+
+/// Row selection checkbox partial (`partials/bulk_checkbox.html`)
+///
+/// Linked to the toolbar form with the `form` attribute, so it can sit in
+/// any table cell.
+#[derive(Debug, Clone, Template)]
+#[template(path = "partials/bulk_checkbox.html")]
+pub struct BulkCheckbox {
+    /// Toolbar form id
+    pub form: String,
+    /// Row id
+    pub id: String,
+}
+
+/// Route: `GET /_acton/bulk.js`
+///
+/// Include `<script src="/_acton/bulk.js" defer></script>` on pages with a
+/// bulk toolbar.
+pub fn routes<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new().route(
+        SCRIPT_PATH,
+        get(|| async {
+            (
+                [
+                    (header::CONTENT_TYPE, "application/javascript"),
+                    (header::CACHE_CONTROL, "public, max-age=3600"),
+                ],
+                SCRIPT,
+            )
+        }),
+    )
+}
+
+const SCRIPT: &str = r##"(function () {
+    "use strict";
+
+    function matching(attribute, form) {
+        return document.querySelectorAll("[" + attribute + '="' + CSS.escape(form) + '"]');
+    }
+
+    function refresh(form) {
+        var boxes = Array.prototype.slice.call(matching("data-bulk-row", form));
+        var count = boxes.filter(function (box) { return box.checked; }).length;
+        matching("data-bulk-count", form).forEach(function (el) {
+            el.textContent = String(count);
+        });
+        matching("data-bulk-select-all", form).forEach(function (all) {
+            all.checked = count > 0 && count === boxes.length;
+            all.indeterminate = count > 0 && count < boxes.length;
+        });
+        document.querySelectorAll("#" + CSS.escape(form) + " [data-bulk-action]").forEach(function (button) {
+            button.disabled = count === 0;
+        });
+    }
+
+    document.addEventListener("change", function (event) {
+        var target = event.target;
+        if (!(target instanceof HTMLInputElement)) {
+            return;
+        }
+        if (target.dataset.bulkSelectAll !== undefined) {
+            matching("data-bulk-row", target.dataset.bulkSelectAll).forEach(function (box) {
+                box.checked = target.checked;
+            });
+            refresh(target.dataset.bulkSelectAll);
+        } else if (target.dataset.bulkRow !== undefined) {
+            refresh(target.dataset.bulkRow);
+        }
+    });
+
+    // Swaps add and remove rows (paging, deletes), so recount after each
+    document.addEventListener("htmx:afterSettle", function () {
+        document.querySelectorAll("[data-bulk-select-all]").forEach(function (all) {
+            refresh(all.dataset.bulkSelectAll);
+        });
+    });
+})();
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::post};
+    use tower::ServiceExt;
+
+    /// Allows rows whose id is even
+    struct EvenRows;
+
+    #[async_trait]
+    impl BulkAuthorizer for EvenRows {
+        async fn authorize(
+            &self,
+            principal: &BulkPrincipal,
+            action: &str,
+            ids: &[String],
+        ) -> Result<Vec<bool>, BulkError> {
+            assert_eq!(principal, &BulkPrincipal::user(7));
+            assert_eq!(action, "delete");
+            Ok(ids
+                .iter()
+                .map(|id| id.parse::<u32>().is_ok_and(|n| n % 2 == 0))
+                .collect())
+        }
+    }
+
+    fn bulk() -> BulkActions {
+        BulkActions::new(EvenRows)
+            .confirm_action("delete", "Delete", "Delete them?")
+            .max_selection(4)
+    }
+
+    fn selection(ids: &[&str]) -> BulkSelection {
+        BulkSelection {
+            action: "delete".to_string(),
+            ids: ids.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_selection_extractor() {
+        let app = Router::new().route(
+            "/",
+            post(|selection: BulkSelection| async move {
+                format!("{}:{}", selection.action, selection.ids.join(","))
+            }),
+        );
+        let request = |body: &'static str| {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let response = app
+            .clone()
+            .oneshot(request("_csrf_token=t&ids=3&action=tag&ids=1&ids=3&ids="))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"tag:3,1");
+
+        let response = app.oneshot(request("ids=1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_authorize_splits_rows() {
+        let principal = BulkPrincipal::user(7);
+        let authorized = bulk()
+            .authorize(&principal, selection(&["1", "2", "4"]))
+            .await
+            .unwrap();
+        assert_eq!(authorized.allowed, ["2", "4"]);
+        assert_eq!(authorized.denied, ["1"]);
+        assert_eq!(
+            authorized.summary("Deleted"),
+            r#"<p class="bulk-summary">Deleted 2 rows. Skipped 1 row you don't have permission to change.</p>"#
+        );
+
+        let result = bulk().authorize(&principal, selection(&["1", "3"])).await;
+        assert!(matches!(result, Err(BulkError::Forbidden)));
+        let result = bulk().authorize(&principal, selection(&[])).await;
+        assert!(matches!(result, Err(BulkError::Empty)));
+        let result = bulk()
+            .authorize(&principal, selection(&["2", "4", "6", "8", "10"]))
+            .await;
+        assert!(matches!(
+            result,
+            Err(BulkError::TooMany {
+                selected: 5,
+                max: 4
+            })
+        ));
+        let mut export = selection(&["2"]);
+        export.action = "export".to_string();
+        let result = bulk().authorize(&principal, export).await;
+        assert!(matches!(result, Err(BulkError::UnknownAction(_))));
+    }
+
+    #[tokio::test]
+    async fn test_large_selections_go_to_jobs() {
+        let bulk = bulk().inline_limit(2);
+        let authorized = bulk
+            .authorize(&BulkPrincipal::user(7), selection(&["2", "4"]))
+            .await
+            .unwrap();
+        assert!(!bulk.is_large(&authorized));
+        let authorized = bulk
+            .authorize(&BulkPrincipal::user(7), selection(&["2", "4", "6"]))
+            .await
+            .unwrap();
+        assert!(bulk.is_large(&authorized));
+    }
+
+    #[test]
+    fn test_partials() {
+        let toolbar = bulk().toolbar("docs-bulk", "/docs/bulk").csrf_token("tok");
+        let html = toolbar.render().unwrap();
+        assert!(
+            html.contains(r##"hx-target="#docs-bulk-status""##),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"value="delete" hx-post="/docs/bulk""#),
+            "{html}"
+        );
+        assert!(html.contains(r#"hx-confirm="Delete them?""#), "{html}");
+        assert!(html.contains(r#"name="_csrf_token" value="tok""#), "{html}");
+
+        let checkbox = toolbar.checkbox("a\"b").render().unwrap();
+        assert!(
+            checkbox.contains(r#"value="a&#34;b" form="docs-bulk" data-bulk-row="docs-bulk""#),
+            "{checkbox}"
+        );
+    }
+}
@@ -2,26 +2,40 @@
 
 use super::error::ClientError;
 use acton_dx_proto::auth::v1::{
-    csrf_service_client::CsrfServiceClient, password_service_client::PasswordServiceClient,
-    session_service_client::SessionServiceClient, user_service_client::UserServiceClient,
-    AddFlashMessageRequest, CreateSessionRequest, CreateUserRequest, DeleteUserRequest,
-    DestroySessionRequest, FlashMessage, GenerateTokenRequest, GetFlashMessagesRequest,
-    GetUserByEmailRequest, GetUserRequest, HashPasswordRequest, Session, UpdateSessionRequest,
-    UpdateUserRequest, User, ValidateSessionRequest, ValidateTokenRequest, VerifyPasswordRequest,
+    api_key_service_client::ApiKeyServiceClient, csrf_service_client::CsrfServiceClient, oidc_provider_service_client::OidcProviderServiceClient, passkey_service_client::PasskeyServiceClient,
+    password_service_client::PasswordServiceClient, security_event_service_client::SecurityEventServiceClient,
+    session_service_client::SessionServiceClient, token_service_client::TokenServiceClient,
+    user_service_client::UserServiceClient, AccessTokenClaims, AddFlashMessageRequest, ApiKey,
+    CreateApiKeyRequest, CreateApiKeyResponse, CreateSessionRequest, CreateUserRequest, DeletePasskeyRequest,
+    DeleteOidcClientRequest, DeleteUserRequest, DestroySessionRequest, ExportSessionsRequest, FinishPasskeyAuthenticationRequest,
+    FinishPasskeyAuthenticationResponse, FinishPasskeyRegistrationRequest, FlashMessage,
+    GenerateTokenRequest, GetFlashMessagesRequest, GetJwksRequest, GetOidcMetadataRequest, GetUserByEmailRequest, GetUserRequest,
+    HashPasswordRequest, ImportSessionsRequest, ListApiKeysRequest, ImportSessionsResponse, IssueAccessTokenRequest, IssueAccessTokenResponse, ListOidcClientsRequest, ListPasskeysRequest, OidcAuthorizeRequest, OidcAuthorizeResponse, OidcClient, OidcExchangeCodeRequest, OidcTokenResponse, OidcUserInfoRequest, PasskeyInfo, RefreshSessionRequest, RegisterOidcClientRequest, RegisterOidcClientResponse, ReportSecurityEventRequest,
+    RevokeApiKeyRequest, SecurityEvent, Session, SessionRecord, StartPasskeyAuthenticationRequest,
+    StartPasskeyAuthenticationResponse, StartPasskeyRegistrationRequest,
+    StartPasskeyRegistrationResponse, SubscribeSecurityEventsRequest, UpdateSessionRequest,
+    UpdateUserRequest, User, ValidateApiKeyRequest, ValidateApiKeyResponse, ValidateSessionRequest, ValidateTokenRequest, VerifyAccessTokenRequest,
+    VerifyPasswordRequest,
 };
 use std::collections::HashMap;
-use tonic::transport::Channel;
+use tonic::{transport::Channel, Streaming};
 
 /// Client for the auth service.
 ///
 /// Provides access to session management, password hashing/verification,
-/// CSRF token handling, and user CRUD operations.
+/// passkeys, CSRF token handling, JWT access tokens, API keys, the OpenID
+/// Connect provider, user CRUD operations, and security events.
 #[derive(Debug, Clone)]
 pub struct AuthClient {
     sessions: SessionServiceClient<Channel>,
     passwords: PasswordServiceClient<Channel>,
+    passkeys: PasskeyServiceClient<Channel>,
     csrf: CsrfServiceClient<Channel>,
+    tokens: TokenServiceClient<Channel>,
+    api_keys: ApiKeyServiceClient<Channel>,
+    oidc: OidcProviderServiceClient<Channel>,
     users: UserServiceClient<Channel>,
+    security: SecurityEventServiceClient<Channel>,
 }
 
 impl AuthClient {
@@ -40,8 +54,13 @@ impl AuthClient {
         Ok(Self {
             sessions: SessionServiceClient::new(channel.clone()),
             passwords: PasswordServiceClient::new(channel.clone()),
+            passkeys: PasskeyServiceClient::new(channel.clone()),
             csrf: CsrfServiceClient::new(channel.clone()),
-            users: UserServiceClient::new(channel),
+            tokens: TokenServiceClient::new(channel.clone()),
+            api_keys: ApiKeyServiceClient::new(channel.clone()),
+            oidc: OidcProviderServiceClient::new(channel.clone()),
+            users: UserServiceClient::new(channel.clone()),
+            security: SecurityEventServiceClient::new(channel),
         })
     }
 
@@ -64,6 +83,7 @@ impl AuthClient {
                 user_id,
                 ttl_seconds,
                 initial_data,
+                issue_refresh_token: false,
             })
             .await?;
 
@@ -73,6 +93,72 @@ impl AuthClient {
             .ok_or_else(|| ClientError::ResponseError("No session in response".to_string()))
     }
 
+    /// Create a new session along with a refresh token for it.
+    ///
+    /// The refresh token keeps the session alive past its TTL, up to the
+    /// service's absolute timeout. Store it somewhere longer lived than the
+    /// session cookie.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn create_session_with_refresh_token(
+        &mut self,
+        user_id: Option<i64>,
+        ttl_seconds: i64,
+        initial_data: HashMap<String, String>,
+    ) -> Result<(Session, String), ClientError> {
+        let response = self
+            .sessions
+            .create_session(CreateSessionRequest {
+                user_id,
+                ttl_seconds,
+                initial_data,
+                issue_refresh_token: true,
+            })
+            .await?
+            .into_inner();
+
+        match (response.session, response.refresh_token) {
+            (Some(session), Some(refresh_token)) => Ok((session, refresh_token)),
+            (None, _) => Err(ClientError::ResponseError(
+                "No session in response".to_string(),
+            )),
+            (Some(_), None) => Err(ClientError::ResponseError(
+                "No refresh token in response".to_string(),
+            )),
+        }
+    }
+
+    /// Exchange a refresh token for a session with a new ID and CSRF token.
+    ///
+    /// The old session ID and refresh token stop working; use the returned
+    /// ones from now on. A `ttl_seconds` of zero uses the service default.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the refresh token is invalid or expired, or the
+    /// service call fails.
+    pub async fn refresh_session(
+        &mut self,
+        refresh_token: &str,
+        ttl_seconds: i64,
+    ) -> Result<(Session, String), ClientError> {
+        let response = self
+            .sessions
+            .refresh_session(RefreshSessionRequest {
+                refresh_token: refresh_token.to_string(),
+                ttl_seconds,
+            })
+            .await?
+            .into_inner();
+
+        let session = response
+            .session
+            .ok_or_else(|| ClientError::ResponseError("No session in response".to_string()))?;
+        Ok((session, response.refresh_token))
+    }
+
     /// Validate an existing session.
     ///
     /// # Errors
@@ -185,6 +271,45 @@ impl AuthClient {
         Ok(response.into_inner().messages)
     }
 
+    /// Stream every unexpired session with its pending flash messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn export_sessions(&mut self) -> Result<Streaming<SessionRecord>, ClientError> {
+        let response = self
+            .sessions
+            .export_sessions(ExportSessionsRequest {})
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Import sessions, keeping their IDs.
+    ///
+    /// Expired sessions are skipped, as are sessions already stored unless
+    /// `replace_existing` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a record is invalid or the service call fails.
+    pub async fn import_sessions(
+        &mut self,
+        records: Vec<SessionRecord>,
+        replace_existing: bool,
+    ) -> Result<ImportSessionsResponse, ClientError> {
+        let requests = records.into_iter().map(move |record| ImportSessionsRequest {
+            record: Some(record),
+            replace_existing,
+        });
+        let response = self
+            .sessions
+            .import_sessions(tokio_stream::iter(requests))
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
     // ==================== Password Operations ====================
 
     /// Hash a password using Argon2.
@@ -218,12 +343,177 @@ impl AuthClient {
             .verify_password(VerifyPasswordRequest {
                 password: password.to_string(),
                 hash: hash.to_string(),
+                ..VerifyPasswordRequest::default()
+            })
+            .await?;
+
+        Ok(response.into_inner().valid)
+    }
+
+    /// Verify a login attempt's password against a hash.
+    ///
+    /// Unlike [`Self::verify_password`], the service records the attempt for
+    /// `subject` (e.g. the email entered), publishing failed-login,
+    /// brute-force and IP-change security events.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn verify_login(
+        &mut self,
+        subject: &str,
+        password: &str,
+        hash: &str,
+        user_id: Option<i64>,
+        ip_address: Option<&str>,
+    ) -> Result<bool, ClientError> {
+        let response = self
+            .passwords
+            .verify_password(VerifyPasswordRequest {
+                password: password.to_string(),
+                hash: hash.to_string(),
+                subject: Some(subject.to_string()),
+                user_id,
+                ip_address: ip_address.map(str::to_string),
             })
             .await?;
 
         Ok(response.into_inner().valid)
     }
 
+    // ==================== Passkey Operations ====================
+
+    /// Start registering a passkey for a user.
+    ///
+    /// Pass the response's `options_json` to `navigator.credentials.create()`
+    /// and keep its `ceremony_id` for [`Self::finish_passkey_registration`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn start_passkey_registration(
+        &mut self,
+        user_id: i64,
+        user_name: &str,
+        display_name: &str,
+    ) -> Result<StartPasskeyRegistrationResponse, ClientError> {
+        let response = self
+            .passkeys
+            .start_registration(StartPasskeyRegistrationRequest {
+                user_id,
+                user_name: user_name.to_string(),
+                display_name: display_name.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Finish registering a passkey with the browser's credential JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the credential is invalid, the ceremony expired or
+    /// belongs to another user, or the service call fails.
+    pub async fn finish_passkey_registration(
+        &mut self,
+        ceremony_id: &str,
+        user_id: i64,
+        credential_json: &str,
+        name: &str,
+    ) -> Result<PasskeyInfo, ClientError> {
+        let response = self
+            .passkeys
+            .finish_registration(FinishPasskeyRegistrationRequest {
+                ceremony_id: ceremony_id.to_string(),
+                user_id,
+                credential_json: credential_json.to_string(),
+                name: name.to_string(),
+            })
+            .await?;
+
+        response
+            .into_inner()
+            .passkey
+            .ok_or_else(|| ClientError::ResponseError("No passkey in response".to_string()))
+    }
+
+    /// Start a passkey login for a user.
+    ///
+    /// Pass the response's `options_json` to `navigator.credentials.get()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the user has no passkeys or the service call fails.
+    pub async fn start_passkey_authentication(
+        &mut self,
+        user_id: i64,
+    ) -> Result<StartPasskeyAuthenticationResponse, ClientError> {
+        let response = self
+            .passkeys
+            .start_authentication(StartPasskeyAuthenticationRequest { user_id })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Finish a passkey login with the browser's credential JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the assertion does not verify, the ceremony expired,
+    /// or the service call fails.
+    pub async fn finish_passkey_authentication(
+        &mut self,
+        ceremony_id: &str,
+        credential_json: &str,
+    ) -> Result<FinishPasskeyAuthenticationResponse, ClientError> {
+        let response = self
+            .passkeys
+            .finish_authentication(FinishPasskeyAuthenticationRequest {
+                ceremony_id: ceremony_id.to_string(),
+                credential_json: credential_json.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// List a user's passkeys.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn list_passkeys(&mut self, user_id: i64) -> Result<Vec<PasskeyInfo>, ClientError> {
+        let response = self
+            .passkeys
+            .list_passkeys(ListPasskeysRequest { user_id })
+            .await?;
+
+        Ok(response.into_inner().passkeys)
+    }
+
+    /// Delete one of a user's passkeys.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn delete_passkey(
+        &mut self,
+        user_id: i64,
+        credential_id: &str,
+    ) -> Result<bool, ClientError> {
+        let response = self
+            .passkeys
+            .delete_passkey(DeletePasskeyRequest {
+                user_id,
+                credential_id: credential_id.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().success)
+    }
+
     // ==================== CSRF Operations ====================
 
     /// Generate a CSRF token for a session.
@@ -263,6 +553,275 @@ impl AuthClient {
         Ok(response.into_inner().valid)
     }
 
+    // ==================== Access Token Operations ====================
+
+    /// Mint a JWT access token from a logged-in session.
+    ///
+    /// Without an audience the service's first configured audience is used;
+    /// without scopes its default scopes are granted.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the session is not logged in, the audience or a scope
+    /// is not configured, or the service call fails.
+    pub async fn issue_access_token(
+        &mut self,
+        session_id: &str,
+        audience: Option<&str>,
+        scopes: Vec<String>,
+    ) -> Result<IssueAccessTokenResponse, ClientError> {
+        let response = self
+            .tokens
+            .issue_access_token(IssueAccessTokenRequest {
+                session_id: session_id.to_string(),
+                audience: audience.map(str::to_string),
+                scopes,
+            })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Verify an access token, returning its claims if it is valid.
+    ///
+    /// Without an audience, any configured audience is accepted.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn verify_access_token(
+        &mut self,
+        access_token: &str,
+        audience: Option<&str>,
+    ) -> Result<Option<AccessTokenClaims>, ClientError> {
+        let response = self
+            .tokens
+            .verify_access_token(VerifyAccessTokenRequest {
+                access_token: access_token.to_string(),
+                audience: audience.map(str::to_string),
+            })
+            .await?
+            .into_inner();
+
+        Ok(response.valid.then_some(response.claims).flatten())
+    }
+
+    /// Fetch the JSON Web Key Set used to verify access tokens.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn access_token_jwks(&mut self) -> Result<String, ClientError> {
+        let response = self.tokens.get_jwks(GetJwksRequest {}).await?;
+
+        Ok(response.into_inner().jwks_json)
+    }
+
+    // ==================== API Key Operations ====================
+
+    /// Create an API key, optionally acting for a user and expiring after
+    /// `ttl_seconds`.
+    ///
+    /// The response carries the full key, which is only returned once.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the name, a scope or the TTL is invalid, or the
+    /// service call fails.
+    pub async fn create_api_key(
+        &mut self,
+        name: &str,
+        user_id: Option<i64>,
+        scopes: Vec<String>,
+        ttl_seconds: Option<i64>,
+    ) -> Result<CreateApiKeyResponse, ClientError> {
+        let response = self
+            .api_keys
+            .create_api_key(CreateApiKeyRequest {
+                name: name.to_string(),
+                user_id,
+                scopes,
+                ttl_seconds,
+            })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// List API keys, all or those of one user.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn list_api_keys(
+        &mut self,
+        user_id: Option<i64>,
+        include_revoked: bool,
+    ) -> Result<Vec<ApiKey>, ClientError> {
+        let response = self
+            .api_keys
+            .list_api_keys(ListApiKeysRequest {
+                user_id,
+                include_revoked,
+            })
+            .await?;
+
+        Ok(response.into_inner().keys)
+    }
+
+    /// Revoke an API key.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn revoke_api_key(&mut self, key_id: &str) -> Result<bool, ClientError> {
+        let response = self
+            .api_keys
+            .revoke_api_key(RevokeApiKeyRequest {
+                key_id: key_id.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().revoked)
+    }
+
+    /// Validate a presented API key and the scopes it must grant.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn validate_api_key(
+        &mut self,
+        api_key: &str,
+        required_scopes: Vec<String>,
+    ) -> Result<ValidateApiKeyResponse, ClientError> {
+        let response = self
+            .api_keys
+            .validate_api_key(ValidateApiKeyRequest {
+                api_key: api_key.to_string(),
+                required_scopes,
+            })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    // ==================== OpenID Connect Provider Operations ====================
+
+    /// Register a client that may sign users in with this application.
+    ///
+    /// The response carries the client secret, which is only returned once;
+    /// public clients get none and must use PKCE.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the name or a redirect URI is invalid, or the service
+    /// call fails.
+    pub async fn register_oidc_client(
+        &mut self,
+        name: &str,
+        redirect_uris: Vec<String>,
+        public: bool,
+    ) -> Result<RegisterOidcClientResponse, ClientError> {
+        let response = self
+            .oidc
+            .register_client(RegisterOidcClientRequest {
+                name: name.to_string(),
+                redirect_uris,
+                public,
+            })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// List registered clients.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn list_oidc_clients(&mut self) -> Result<Vec<OidcClient>, ClientError> {
+        let response = self.oidc.list_clients(ListOidcClientsRequest {}).await?;
+
+        Ok(response.into_inner().clients)
+    }
+
+    /// Delete a client, revoking its pending codes and consents.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn delete_oidc_client(&mut self, client_id: &str) -> Result<bool, ClientError> {
+        let response = self
+            .oidc
+            .delete_client(DeleteOidcClientRequest {
+                client_id: client_id.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().deleted)
+    }
+
+    /// Validate an authorization request for a logged-in session.
+    ///
+    /// Returns an authorization code once the user has approved the scopes,
+    /// or asks for the consent screen.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the session is not logged in, the request is invalid,
+    /// or the service call fails.
+    pub async fn oidc_authorize(
+        &mut self,
+        request: OidcAuthorizeRequest,
+    ) -> Result<OidcAuthorizeResponse, ClientError> {
+        let response = self.oidc.authorize(request).await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Exchange an authorization code for an access token and ID token.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the client credentials or the code are invalid, or
+    /// the service call fails.
+    pub async fn oidc_exchange_code(
+        &mut self,
+        request: OidcExchangeCodeRequest,
+    ) -> Result<OidcTokenResponse, ClientError> {
+        let response = self.oidc.exchange_code(request).await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// UserInfo claims, as JSON, for an access token issued to a client.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the token is invalid or the service call fails.
+    pub async fn oidc_userinfo(&mut self, access_token: &str) -> Result<String, ClientError> {
+        let response = self
+            .oidc
+            .get_user_info(OidcUserInfoRequest {
+                access_token: access_token.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().claims_json)
+    }
+
+    /// OpenID Provider Configuration document, as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn oidc_metadata(&mut self) -> Result<String, ClientError> {
+        let response = self.oidc.get_metadata(GetOidcMetadataRequest {}).await?;
+
+        Ok(response.into_inner().metadata_json)
+    }
+
     // ==================== User Operations ====================
 
     /// Create a new user.
@@ -350,4 +909,56 @@ impl AuthClient {
 
         Ok(response.into_inner().success)
     }
+
+    // ==================== Security Events ====================
+
+    /// Subscribe to security events.
+    ///
+    /// An empty `kinds` receives every kind; `user_id` limits events to one
+    /// user. The stream only carries events published after subscribing.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn subscribe_security_events(
+        &mut self,
+        kinds: Vec<String>,
+        user_id: Option<i64>,
+    ) -> Result<Streaming<SecurityEvent>, ClientError> {
+        let response = self
+            .security
+            .subscribe(SubscribeSecurityEventsRequest { kinds, user_id })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Report a security event the auth service cannot observe itself,
+    /// such as a `password_changed` event.
+    ///
+    /// Returns the event ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the kind is unknown or the service call fails.
+    pub async fn report_security_event(
+        &mut self,
+        kind: &str,
+        user_id: Option<i64>,
+        ip_address: Option<&str>,
+        details: HashMap<String, String>,
+    ) -> Result<String, ClientError> {
+        let response = self
+            .security
+            .report(ReportSecurityEventRequest {
+                kind: kind.to_string(),
+                user_id,
+                subject: None,
+                ip_address: ip_address.map(str::to_string),
+                details,
+            })
+            .await?;
+
+        Ok(response.into_inner().id)
+    }
 }
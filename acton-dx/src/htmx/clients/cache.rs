@@ -1,21 +1,49 @@
 //! Cache service client for Redis operations.
 
 use super::error::ClientError;
+use super::local_cache::{
+    Invalidation, LocalCache, INVALIDATION_CHANNEL, PREFIX_INVALIDATION_CHANNEL,
+};
 use acton_dx_proto::cache::v1::{
-    cache_service_client::CacheServiceClient, DeleteRequest, ExistsRequest, GetRequest,
-    HGetAllRequest, HGetRequest, HSetRequest, IncrementRequest, LPushRequest, LRangeRequest,
-    RPopRequest, RateLimitRequest, SetRequest,
+    cache_service_client::CacheServiceClient, ConsumeTokensRequest, DeleteRequest, ExistsRequest,
+    GetRequest, HGetAllRequest, HGetRequest, HSetRequest, IncrementRequest,
+    InvalidatePrefixRequest, LPushRequest, LRangeRequest, PubSubMessage, PublishRequest,
+    RPopRequest, RateLimitRequest, ScoreRange, ScoredMember, SetRequest, SubscribeRequest,
+    ZAddRequest, ZIncrByRequest, ZRangeRequest, ZRemRangeByScoreRequest,
 };
+use futures_util::{Stream, StreamExt};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::ops::{Bound, RangeBounds};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use tonic::transport::Channel;
+use tonic::Streaming;
+use tracing::warn;
+
+/// Wait before resubscribing after the invalidation subscription ends.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
 
 /// Client for the cache service.
 ///
 /// Provides Redis operations including key-value storage, rate limiting,
-/// hash operations, and list operations.
+/// hash, list and sorted set operations.
+///
+/// Keys can be scoped with [`with_namespace`](Self::with_namespace), e.g. per
+/// tenant, and a whole namespace dropped with
+/// [`clear_namespace`](Self::clear_namespace).
+///
+/// [`with_local_cache`](Self::with_local_cache) adds an in-process tier for
+/// key-value reads; see [`LocalCache`].
 #[derive(Debug, Clone)]
 pub struct CacheClient {
     client: CacheServiceClient<Channel>,
+    namespace: String,
+    local: Option<Arc<LocalCache>>,
 }
 
 impl CacheClient {
@@ -33,9 +61,155 @@ impl CacheClient {
 
         Ok(Self {
             client: CacheServiceClient::new(channel),
+            namespace: String::new(),
+            local: None,
+        })
+    }
+
+    /// Create a client that connects on first use.
+    ///
+    /// Useful when the cache service is optional and may not be reachable at
+    /// startup; calls fail with a transport error until it comes up.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the endpoint URI is invalid.
+    pub fn connect_lazy(endpoint: impl Into<String>) -> Result<Self, ClientError> {
+        let endpoint = endpoint.into();
+        let channel = Channel::from_shared(endpoint)
+            .map_err(|e| ClientError::ConnectionFailed(e.to_string()))?
+            .connect_lazy();
+
+        Ok(Self {
+            client: CacheServiceClient::new(channel),
+            namespace: String::new(),
+            local: None,
         })
     }
 
+    /// Scope every key under `namespace`, nested inside any existing one
+    ///
+    /// ```rust,ignore
+    /// let tenant_cache = cache.clone().with_namespace(&format!("tenant:{id}"));
+    /// tenant_cache.set_string("settings", "...", None).await?; // "tenant:7:settings"
+    /// ```
+    #[must_use]
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        let namespace = namespace.trim_matches(':');
+        if !namespace.is_empty() {
+            self.namespace = format!("{}{namespace}:", self.namespace);
+        }
+        self
+    }
+
+    /// The current key prefix, empty or ending in `:`
+    #[must_use]
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.namespace)
+    }
+
+    // ==================== Local Tier ====================
+
+    /// Serve `get` from an in-process LRU, filled on reads and writes
+    ///
+    /// Run [`spawn_invalidation_listener`](Self::spawn_invalidation_listener)
+    /// so writes from other instances evict local entries.
+    ///
+    /// ```rust,ignore
+    /// let cache = CacheClient::connect(endpoint)
+    ///     .await?
+    ///     .with_local_cache(LocalCache::new(10_000).with_max_ttl(Duration::from_secs(30)));
+    /// cache.spawn_invalidation_listener();
+    /// ```
+    #[must_use]
+    pub fn with_local_cache(mut self, local: LocalCache) -> Self {
+        self.local = Some(Arc::new(local));
+        self
+    }
+
+    /// The in-process tier, if any
+    #[must_use]
+    pub fn local_cache(&self) -> Option<&LocalCache> {
+        self.local.as_deref()
+    }
+
+    /// Keep the local tier in sync on a background task
+    ///
+    /// Subscribes to the configured [`Invalidation`] source and evicts the
+    /// entries other instances write. Whenever the subscription is
+    /// (re)established the local tier is cleared, since writes may have been
+    /// missed in between. Returns `None` without a local tier.
+    #[allow(clippy::must_use_candidate)] // the task runs detached
+    pub fn spawn_invalidation_listener(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let local = self.local.clone()?;
+        // Entries are keyed by full key, whatever this clone's namespace
+        let mut client = self.clone();
+        client.namespace.clear();
+        Some(tokio::spawn(async move {
+            loop {
+                let subscription = match local.invalidation() {
+                    Invalidation::PubSub => {
+                        client
+                            .subscribe(&[INVALIDATION_CHANNEL, PREFIX_INVALIDATION_CHANNEL], &[])
+                            .await
+                    }
+                    Invalidation::Keyspace => client.subscribe_keyspace(&[], &["*"]).await,
+                };
+                match subscription {
+                    Ok(mut messages) => {
+                        local.clear();
+                        while let Some(message) = messages.next().await {
+                            match message {
+                                Ok(message) => local.apply(&message),
+                                Err(e) => {
+                                    warn!(error = %e, "Cache invalidation subscription failed");
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "Failed to subscribe to cache invalidations"),
+                }
+                local.clear();
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        }))
+    }
+
+    /// Evict `key` (a full key) locally and tell other instances to
+    ///
+    /// Publishing is best effort: the write already succeeded, and other
+    /// instances drop their copies within the local tier's max TTL anyway.
+    async fn invalidate_local(&mut self, key: &str, prefix: bool) {
+        let Some(local) = self.local.clone() else {
+            return;
+        };
+        let channel = if prefix {
+            local.remove_prefix(key);
+            PREFIX_INVALIDATION_CHANNEL
+        } else {
+            local.remove(key);
+            INVALIDATION_CHANNEL
+        };
+        if local.invalidation() != Invalidation::PubSub {
+            return;
+        }
+        if let Err(e) = self
+            .client
+            .publish(PublishRequest {
+                channel: channel.to_string(),
+                payload: local.message(key),
+            })
+            .await
+        {
+            warn!(error = %e, key, "Failed to publish cache invalidation");
+        }
+    }
+
     // ==================== Key-Value Operations ====================
 
     /// Get a value by key.
@@ -44,19 +218,23 @@ impl CacheClient {
     ///
     /// Returns error if the service call fails.
     pub async fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>, ClientError> {
-        let response = self
-            .client
-            .get(GetRequest {
-                key: key.to_string(),
-            })
-            .await?;
+        let key = self.key(key);
+        if let Some(value) = self.local.as_ref().and_then(|local| local.get(&key)) {
+            return Ok(Some(value));
+        }
+        let response = self.client.get(GetRequest { key: key.clone() }).await?;
 
         let inner = response.into_inner();
-        if inner.found {
-            Ok(inner.value)
-        } else {
-            Ok(None)
+        if !inner.found {
+            return Ok(None);
         }
+        if let (Some(local), Some(value)) = (&self.local, &inner.value) {
+            let ttl = inner
+                .ttl_ms
+                .map(|ms| Duration::from_millis(u64::try_from(ms).unwrap_or_default()));
+            local.insert(&key, value.clone(), ttl);
+        }
+        Ok(inner.value)
     }
 
     /// Get a string value by key.
@@ -85,15 +263,22 @@ impl CacheClient {
         value: &[u8],
         ttl_seconds: Option<i64>,
     ) -> Result<bool, ClientError> {
+        let key = self.key(key);
         let response = self
             .client
             .set(SetRequest {
-                key: key.to_string(),
+                key: key.clone(),
                 value: value.to_vec(),
                 ttl_seconds,
             })
             .await?;
 
+        self.invalidate_local(&key, false).await;
+        if let Some(local) = &self.local {
+            let ttl = ttl_seconds
+                .map(|secs| Duration::from_secs(u64::try_from(secs).unwrap_or_default()));
+            local.insert(&key, value.to_vec(), ttl);
+        }
         Ok(response.into_inner().success)
     }
 
@@ -117,13 +302,13 @@ impl CacheClient {
     ///
     /// Returns error if the service call fails.
     pub async fn delete(&mut self, key: &str) -> Result<bool, ClientError> {
+        let key = self.key(key);
         let response = self
             .client
-            .delete(DeleteRequest {
-                key: key.to_string(),
-            })
+            .delete(DeleteRequest { key: key.clone() })
             .await?;
 
+        self.invalidate_local(&key, false).await;
         Ok(response.into_inner().deleted)
     }
 
@@ -135,14 +320,72 @@ impl CacheClient {
     pub async fn exists(&mut self, key: &str) -> Result<bool, ClientError> {
         let response = self
             .client
-            .exists(ExistsRequest {
-                key: key.to_string(),
-            })
+            .exists(ExistsRequest { key: self.key(key) })
             .await?;
 
         Ok(response.into_inner().exists)
     }
 
+    /// Get a JSON value, computing and caching it on a miss.
+    ///
+    /// Fresh values are stored for the policy's jittered TTL so keys written
+    /// together don't all expire together. When the policy sets a
+    /// [`negative_ttl`](CachePolicy::negative_ttl), a `None` from `compute`
+    /// is remembered too, so repeated lookups of missing rows stop reaching
+    /// the data-service until it expires.
+    ///
+    /// ```rust,ignore
+    /// let policy = CachePolicy::new(Duration::from_secs(300))
+    ///     .with_jitter(0.1)
+    ///     .with_negative_ttl(Duration::from_secs(15));
+    /// let user: Option<User> = cache
+    ///     .get_or_compute(&format!("user:{id}"), &policy, || load_user(id))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `compute`, or a converted [`ClientError`] if
+    /// the cache call or serialization fails.
+    pub async fn get_or_compute<T, E, F, Fut>(
+        &mut self,
+        key: &str,
+        policy: &CachePolicy,
+        compute: F,
+    ) -> Result<Option<T>, E>
+    where
+        T: Serialize + DeserializeOwned,
+        E: From<ClientError>,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<T>, E>>,
+    {
+        if let Some(bytes) = self.get(key).await? {
+            // JSON is never empty, so an empty value marks a cached miss
+            if bytes.is_empty() {
+                return Ok(None);
+            }
+            // An undecodable entry predates a type change; recompute it
+            if let Ok(value) = serde_json::from_slice(&bytes) {
+                return Ok(Some(value));
+            }
+        }
+
+        let value = compute().await?;
+        match &value {
+            Some(value) => {
+                let bytes = serde_json::to_vec(value)
+                    .map_err(|e| ClientError::SerializationError(e.to_string()))?;
+                self.set(key, &bytes, Some(policy.ttl_seconds())).await?;
+            }
+            None => {
+                if let Some(ttl) = policy.negative_ttl_seconds() {
+                    self.set(key, &[], Some(ttl)).await?;
+                }
+            }
+        }
+        Ok(value)
+    }
+
     // ==================== Rate Limiting ====================
 
     /// Check if an action is within rate limits.
@@ -159,7 +402,7 @@ impl CacheClient {
         let response = self
             .client
             .check_rate_limit(RateLimitRequest {
-                key: key.to_string(),
+                key: self.key(key),
                 limit,
                 window_seconds,
             })
@@ -173,6 +416,39 @@ impl CacheClient {
         })
     }
 
+    /// Atomically refill and consume from a shared token bucket.
+    ///
+    /// Bucket state lives in Redis, so every node sharing the cache service
+    /// enforces the same limit for `key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn consume_tokens(
+        &mut self,
+        key: &str,
+        capacity: u32,
+        refill_per_second: f64,
+        tokens: u32,
+    ) -> Result<TokenBucketResult, ClientError> {
+        let response = self
+            .client
+            .consume_tokens(ConsumeTokensRequest {
+                key: self.key(key),
+                capacity,
+                refill_per_second,
+                tokens,
+            })
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(TokenBucketResult {
+            allowed: inner.allowed,
+            remaining: inner.remaining,
+            retry_after_ms: inner.retry_after_ms,
+        })
+    }
+
     /// Increment a counter.
     ///
     /// # Errors
@@ -184,15 +460,17 @@ impl CacheClient {
         amount: i64,
         ttl_seconds: Option<i64>,
     ) -> Result<i64, ClientError> {
+        let key = self.key(key);
         let response = self
             .client
             .increment_counter(IncrementRequest {
-                key: key.to_string(),
+                key: key.clone(),
                 amount,
                 ttl_seconds,
             })
             .await?;
 
+        self.invalidate_local(&key, false).await;
         Ok(response.into_inner().new_value)
     }
 
@@ -207,7 +485,7 @@ impl CacheClient {
         let response = self
             .client
             .h_get(HGetRequest {
-                key: key.to_string(),
+                key: self.key(key),
                 field: field.to_string(),
             })
             .await?;
@@ -234,7 +512,7 @@ impl CacheClient {
         let response = self
             .client
             .h_set(HSetRequest {
-                key: key.to_string(),
+                key: self.key(key),
                 field: field.to_string(),
                 value: value.to_vec(),
             })
@@ -251,9 +529,7 @@ impl CacheClient {
     pub async fn hgetall(&mut self, key: &str) -> Result<HashMap<String, Vec<u8>>, ClientError> {
         let response = self
             .client
-            .h_get_all(HGetAllRequest {
-                key: key.to_string(),
-            })
+            .h_get_all(HGetAllRequest { key: self.key(key) })
             .await?;
 
         Ok(response.into_inner().fields)
@@ -270,7 +546,7 @@ impl CacheClient {
         let response = self
             .client
             .l_push(LPushRequest {
-                key: key.to_string(),
+                key: self.key(key),
                 value: value.to_vec(),
             })
             .await?;
@@ -286,9 +562,7 @@ impl CacheClient {
     pub async fn rpop(&mut self, key: &str) -> Result<Option<Vec<u8>>, ClientError> {
         let response = self
             .client
-            .r_pop(RPopRequest {
-                key: key.to_string(),
-            })
+            .r_pop(RPopRequest { key: self.key(key) })
             .await?;
 
         Ok(response.into_inner().value)
@@ -308,7 +582,7 @@ impl CacheClient {
         let response = self
             .client
             .l_range(LRangeRequest {
-                key: key.to_string(),
+                key: self.key(key),
                 start,
                 stop,
             })
@@ -316,6 +590,454 @@ impl CacheClient {
 
         Ok(response.into_inner().values)
     }
+
+    // ==================== Sorted Set Operations ====================
+
+    /// Add members with their scores, or update the scores of existing ones.
+    ///
+    /// Returns the number of members that were not in the set before.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, no members are given or a
+    /// score is `NaN`.
+    pub async fn zadd(
+        &mut self,
+        key: &str,
+        members: &[(&[u8], f64)],
+        ttl_seconds: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        let response = self
+            .client
+            .z_add(ZAddRequest {
+                key: self.key(key),
+                members: members
+                    .iter()
+                    .map(|(member, score)| ScoredMember {
+                        member: member.to_vec(),
+                        score: *score,
+                    })
+                    .collect(),
+                ttl_seconds,
+            })
+            .await?;
+
+        Ok(response.into_inner().added)
+    }
+
+    /// Add `increment` to a member's score, adding the member if needed.
+    ///
+    /// Returns the new score.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn zincrby(
+        &mut self,
+        key: &str,
+        member: &[u8],
+        increment: f64,
+    ) -> Result<f64, ClientError> {
+        let response = self
+            .client
+            .z_incr_by(ZIncrByRequest {
+                key: self.key(key),
+                member: member.to_vec(),
+                increment,
+            })
+            .await?;
+
+        Ok(response.into_inner().score)
+    }
+
+    /// Get a page of members with their scores.
+    ///
+    /// ```rust,ignore
+    /// // Top ten of the leaderboard
+    /// let top = cache.zrange("leaderboard", &ZRange::new().rev().page(0, 10)).await?;
+    ///
+    /// // Events in the last hour, oldest first
+    /// let since = (now - 3600) as f64;
+    /// let recent = cache.zrange("events", &ZRange::new().scores(since..)).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn zrange(&mut self, key: &str, range: &ZRange) -> Result<ZRangePage, ClientError> {
+        let response = self
+            .client
+            .z_range(ZRangeRequest {
+                key: self.key(key),
+                scores: range.scores,
+                reverse: range.reverse,
+                offset: range.offset,
+                limit: range.limit,
+            })
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(ZRangePage {
+            members: inner.members,
+            total: inner.total,
+        })
+    }
+
+    /// Remove members whose score is within `scores`, e.g. `..cutoff` to
+    /// trim a time-ordered index.
+    ///
+    /// Returns the number of members removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn zrem_range_by_score(
+        &mut self,
+        key: &str,
+        scores: impl RangeBounds<f64>,
+    ) -> Result<i64, ClientError> {
+        let response = self
+            .client
+            .z_rem_range_by_score(ZRemRangeByScoreRequest {
+                key: self.key(key),
+                scores: Some(score_range(&scores)),
+            })
+            .await?;
+
+        Ok(response.into_inner().removed)
+    }
+
+    // ==================== Bulk Invalidation ====================
+
+    /// Delete every key starting with `prefix` within this namespace.
+    ///
+    /// The service walks the keyspace with `SCAN` and `UNLINK`s matches in
+    /// batches of `batch_size` (server default when `None`), so large
+    /// invalidations never block Redis. Returns the number of keys deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails or the resulting prefix is
+    /// empty.
+    pub async fn invalidate_prefix(
+        &mut self,
+        prefix: &str,
+        batch_size: Option<u32>,
+    ) -> Result<u64, ClientError> {
+        let prefix = self.key(prefix);
+        let response = self
+            .client
+            .invalidate_prefix(InvalidatePrefixRequest {
+                prefix: prefix.clone(),
+                batch_size,
+            })
+            .await?;
+
+        self.invalidate_local(&prefix, true).await;
+        Ok(response.into_inner().deleted)
+    }
+
+    /// Delete every key in this client's namespace, e.g. all cache for a tenant.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails or the client has no
+    /// namespace, which would otherwise clear the entire cache.
+    pub async fn clear_namespace(&mut self) -> Result<u64, ClientError> {
+        if self.namespace.is_empty() {
+            return Err(ClientError::RequestFailed(
+                "refusing to clear an unnamespaced cache".to_string(),
+            ));
+        }
+        self.invalidate_prefix("", None).await
+    }
+
+    // ==================== Pub/Sub ====================
+
+    /// Publish a message on a channel within this namespace.
+    ///
+    /// Returns the number of subscribers it was delivered to; messages
+    /// published with no subscribers are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn publish(&mut self, channel: &str, payload: &[u8]) -> Result<i64, ClientError> {
+        let response = self
+            .client
+            .publish(PublishRequest {
+                channel: self.key(channel),
+                payload: payload.to_vec(),
+            })
+            .await?;
+
+        Ok(response.into_inner().receivers)
+    }
+
+    /// Subscribe to channels and glob patterns within this namespace.
+    ///
+    /// Messages arrive with the namespace stripped from their channel. The
+    /// subscription lasts until it is dropped; if it ends on its own the
+    /// service lost its Redis connection and the caller should subscribe
+    /// again.
+    ///
+    /// ```rust,ignore
+    /// let mut invalidations = cache.subscribe(&[], &["invalidate:*"]).await?;
+    /// while let Some(message) = invalidations.next().await {
+    ///     local_cache.remove(message?.channel.trim_start_matches("invalidate:"));
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, no channel or pattern is
+    /// given, or the service does not serve subscriptions.
+    pub async fn subscribe(
+        &mut self,
+        channels: &[&str],
+        patterns: &[&str],
+    ) -> Result<Subscription, ClientError> {
+        let response = self
+            .client
+            .subscribe(SubscribeRequest {
+                channels: channels.iter().map(|channel| self.key(channel)).collect(),
+                patterns: patterns
+                    .iter()
+                    .map(|pattern| format!("{}{pattern}", escape_glob(&self.namespace)))
+                    .collect(),
+                keyspace: false,
+            })
+            .await?;
+
+        Ok(Subscription {
+            messages: response.into_inner(),
+            namespace: self.namespace.clone(),
+        })
+    }
+
+    /// Subscribe to Redis keyspace notifications for keys and key glob
+    /// patterns within this namespace.
+    ///
+    /// Each message's channel is the key that changed and its payload the
+    /// event, e.g. `set`, `del` or `expired`. Redis must have
+    /// `notify-keyspace-events` enabled with `K` and the events of interest.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, no key or pattern is given,
+    /// or the service does not serve subscriptions.
+    pub async fn subscribe_keyspace(
+        &mut self,
+        keys: &[&str],
+        patterns: &[&str],
+    ) -> Result<Subscription, ClientError> {
+        let response = self
+            .client
+            .subscribe(SubscribeRequest {
+                channels: keys.iter().map(|key| self.key(key)).collect(),
+                patterns: patterns
+                    .iter()
+                    .map(|pattern| format!("{}{pattern}", escape_glob(&self.namespace)))
+                    .collect(),
+                keyspace: true,
+            })
+            .await?;
+
+        Ok(Subscription {
+            messages: response.into_inner(),
+            namespace: self.namespace.clone(),
+        })
+    }
+}
+
+/// Messages from [`CacheClient::subscribe`], with the namespace stripped
+#[derive(Debug)]
+pub struct Subscription {
+    messages: Streaming<PubSubMessage>,
+    namespace: String,
+}
+
+impl Stream for Subscription {
+    type Item = Result<PubSubMessage, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.messages).poll_next(cx) {
+            Poll::Ready(Some(Ok(mut message))) => {
+                strip_namespace(&mut message, &self.namespace);
+                Poll::Ready(Some(Ok(message)))
+            }
+            Poll::Ready(Some(Err(status))) => Poll::Ready(Some(Err(status.into()))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Remove the client namespace from a message's channel and pattern
+fn strip_namespace(message: &mut PubSubMessage, namespace: &str) {
+    if let Some(channel) = message.channel.strip_prefix(namespace) {
+        message.channel = channel.to_string();
+    }
+    let escaped = escape_glob(namespace);
+    if let Some(pattern) = message
+        .pattern
+        .as_deref()
+        .and_then(|pattern| pattern.strip_prefix(escaped.as_str()))
+    {
+        message.pattern = Some(pattern.to_string());
+    }
+}
+
+/// Escape Redis glob metacharacters so `value` matches literally
+fn escape_glob(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Which members [`CacheClient::zrange`] returns.
+///
+/// By default every member, lowest score first.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ZRange {
+    scores: Option<ScoreRange>,
+    reverse: bool,
+    offset: u64,
+    limit: u32,
+}
+
+impl ZRange {
+    /// Every member, lowest score first.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highest scores first, e.g. for leaderboards.
+    #[must_use]
+    pub const fn rev(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+
+    /// Only members scored within `scores`, e.g. `10.0..=20.0` or `since..`.
+    #[must_use]
+    pub fn scores(mut self, scores: impl RangeBounds<f64>) -> Self {
+        self.scores = Some(score_range(&scores));
+        self
+    }
+
+    /// Skip `offset` members and return at most `limit` (0 for no limit).
+    #[must_use]
+    pub const fn page(mut self, offset: u64, limit: u32) -> Self {
+        self.offset = offset;
+        self.limit = limit;
+        self
+    }
+}
+
+/// Members returned by [`CacheClient::zrange`].
+#[derive(Debug, Clone)]
+pub struct ZRangePage {
+    /// Members in the page, in range order.
+    pub members: Vec<ScoredMember>,
+    /// Members within the score range, ignoring paging.
+    pub total: u64,
+}
+
+/// Score bounds for the service from a Rust range.
+fn score_range(scores: &impl RangeBounds<f64>) -> ScoreRange {
+    let bound = |bound: Bound<&f64>| match bound {
+        Bound::Included(value) => (Some(*value), false),
+        Bound::Excluded(value) => (Some(*value), true),
+        Bound::Unbounded => (None, false),
+    };
+    let (min, min_exclusive) = bound(scores.start_bound());
+    let (max, max_exclusive) = bound(scores.end_bound());
+    ScoreRange {
+        min,
+        max,
+        min_exclusive,
+        max_exclusive,
+    }
+}
+
+/// Expiry policy for [`CacheClient::get_or_compute`].
+#[derive(Debug, Clone, Copy)]
+pub struct CachePolicy {
+    /// Base lifetime of cached values.
+    pub ttl: Duration,
+    /// Fraction of the TTL randomly added or removed, from 0.0 to 1.0.
+    pub jitter: f64,
+    /// Lifetime of cached not-found results; `None` disables negative caching.
+    pub negative_ttl: Option<Duration>,
+}
+
+impl CachePolicy {
+    /// Cache values for `ttl`, without jitter or negative caching.
+    #[must_use]
+    pub const fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            jitter: 0.0,
+            negative_ttl: None,
+        }
+    }
+
+    /// Spread expirations by up to `jitter` (e.g. 0.1 for ±10%) of the TTL.
+    #[must_use]
+    pub const fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Remember not-found results for `ttl`, which should be short.
+    #[must_use]
+    pub const fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = Some(ttl);
+        self
+    }
+
+    /// A jittered TTL for a fresh value, in whole seconds.
+    #[must_use]
+    pub fn ttl_seconds(&self) -> i64 {
+        ttl_seconds(jitter_ttl(self.ttl, self.jitter))
+    }
+
+    /// A jittered TTL for a not-found result, if negative caching is enabled.
+    #[must_use]
+    pub fn negative_ttl_seconds(&self) -> Option<i64> {
+        self.negative_ttl
+            .map(|ttl| ttl_seconds(jitter_ttl(ttl, self.jitter)))
+    }
+}
+
+/// Randomly stretch or shrink `ttl` by up to `jitter` of its length.
+///
+/// Entries cached at the same moment with the same TTL otherwise expire in
+/// the same instant, sending a burst of misses to the backing service.
+/// `jitter` is clamped to 0.0–1.0.
+#[must_use]
+pub fn jitter_ttl(ttl: Duration, jitter: f64) -> Duration {
+    let jitter = if jitter.is_finite() {
+        jitter.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    if jitter == 0.0 {
+        return ttl;
+    }
+    ttl.mul_f64(1.0 + rand::rng().random_range(-jitter..=jitter))
+}
+
+/// Whole seconds for a Redis TTL, at least one so the key still expires.
+fn ttl_seconds(ttl: Duration) -> i64 {
+    i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX).max(1)
 }
 
 /// Result of a rate limit check.
@@ -328,3 +1050,97 @@ pub struct RateLimitResult {
     /// Seconds until the rate limit resets.
     pub reset_in_seconds: i32,
 }
+
+/// Result of consuming from a shared token bucket.
+#[derive(Debug, Clone)]
+pub struct TokenBucketResult {
+    /// Whether the tokens were consumed.
+    pub allowed: bool,
+    /// Whole tokens left in the bucket.
+    pub remaining: u32,
+    /// Milliseconds until enough tokens refill (0 when allowed).
+    pub retry_after_ms: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_namespaces_nest() {
+        let client = CacheClient::connect_lazy("http://127.0.0.1:1").unwrap();
+        assert_eq!(client.key("k"), "k");
+
+        let tenant = client.with_namespace("app").with_namespace(":tenant:7:");
+        assert_eq!(tenant.namespace(), "app:tenant:7:");
+        assert_eq!(tenant.key("settings"), "app:tenant:7:settings");
+        assert_eq!(tenant.with_namespace("").namespace(), "app:tenant:7:");
+    }
+
+    #[test]
+    fn test_jitter_ttl_stays_in_bounds() {
+        let ttl = Duration::from_secs(100);
+        assert_eq!(jitter_ttl(ttl, 0.0), ttl);
+        assert_eq!(jitter_ttl(ttl, f64::NAN), ttl);
+        for _ in 0..100 {
+            let jittered = jitter_ttl(ttl, 0.2);
+            assert!(jittered >= Duration::from_secs(80) && jittered <= Duration::from_secs(120));
+            assert!(jitter_ttl(ttl, 5.0) <= Duration::from_secs(200));
+        }
+    }
+
+    #[test]
+    fn test_cache_policy_ttls() {
+        let policy = CachePolicy::new(Duration::from_secs(60));
+        assert_eq!(policy.ttl_seconds(), 60);
+        assert_eq!(policy.negative_ttl_seconds(), None);
+
+        let policy = policy
+            .with_jitter(0.5)
+            .with_negative_ttl(Duration::from_millis(10));
+        assert!((30..=90).contains(&policy.ttl_seconds()));
+        assert_eq!(policy.negative_ttl_seconds(), Some(1));
+    }
+
+    #[test]
+    fn test_strip_namespace() {
+        let mut message = PubSubMessage {
+            channel: "app:t[1]:invalidate:users".to_string(),
+            payload: vec![],
+            pattern: Some("app:t\\[1\\]:invalidate:*".to_string()),
+        };
+        strip_namespace(&mut message, "app:t[1]:");
+        assert_eq!(message.channel, "invalidate:users");
+        assert_eq!(message.pattern.as_deref(), Some("invalidate:*"));
+    }
+
+    #[test]
+    fn test_zrange_options() {
+        assert_eq!(ZRange::new().scores, None);
+
+        let range = ZRange::new().rev().scores(10.0..20.0).page(40, 20);
+        assert_eq!(
+            range.scores,
+            Some(ScoreRange {
+                min: Some(10.0),
+                max: Some(20.0),
+                min_exclusive: false,
+                max_exclusive: true,
+            })
+        );
+        assert!(range.reverse);
+        assert_eq!((range.offset, range.limit), (40, 20));
+
+        let open = score_range(&(..=5.0));
+        assert_eq!((open.min, open.max, open.max_exclusive), (None, Some(5.0), false));
+    }
+
+    #[tokio::test]
+    async fn test_clear_namespace_requires_namespace() {
+        let mut client = CacheClient::connect_lazy("http://127.0.0.1:1").unwrap();
+        assert!(matches!(
+            client.clear_namespace().await,
+            Err(ClientError::RequestFailed(_))
+        ));
+    }
+}
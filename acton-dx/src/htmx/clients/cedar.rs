@@ -2,8 +2,10 @@
 
 use super::error::ClientError;
 use acton_dx_proto::cedar::v1::{
-    cedar_service_client::CedarServiceClient, AuthzRequest, BatchAuthzRequest, Entity,
-    ReloadPoliciesRequest, ValidatePolicyRequest,
+    cedar_service_client::CedarServiceClient, ActivatePolicyVersionRequest,
+    ActivatePolicyVersionResponse, AuthzRequest, BatchAuthzRequest, Entity,
+    GetPolicyVersionRequest, ListPolicyVersionsRequest, ReloadPoliciesRequest,
+    RollbackPoliciesRequest, SavePolicyVersionRequest, ValidatePolicyRequest,
 };
 use std::collections::HashMap;
 use tonic::transport::Channel;
@@ -69,6 +71,7 @@ impl CedarClient {
             allowed: inner.allowed,
             decision_reason: inner.decision_reason,
             diagnostics: inner.diagnostics,
+            policy_version: inner.policy_version,
         })
     }
 
@@ -112,6 +115,7 @@ impl CedarClient {
                 allowed: r.allowed,
                 decision_reason: r.decision_reason,
                 diagnostics: r.diagnostics,
+                policy_version: r.policy_version,
             })
             .collect())
     }
@@ -132,6 +136,7 @@ impl CedarClient {
             success: inner.success,
             policies_loaded: inner.policies_loaded,
             message: inner.message,
+            active_version: inner.active_version,
         })
     }
 
@@ -157,6 +162,129 @@ impl CedarClient {
             errors: inner.errors,
         })
     }
+
+    /// List the stored policy versions and recent activations.
+    ///
+    /// Listed versions do not include their policy text.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn list_policy_versions(&mut self) -> Result<PolicyHistory, ClientError> {
+        let response = self
+            .client
+            .list_policy_versions(ListPolicyVersionsRequest {})
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(PolicyHistory {
+            active_version: inner.active_version,
+            versions: inner
+                .versions
+                .into_iter()
+                .map(PolicyVersion::from)
+                .collect(),
+            activations: inner
+                .activations
+                .into_iter()
+                .map(|a| PolicyActivation {
+                    version: a.version,
+                    previous_version: a.previous_version,
+                    actor: a.actor,
+                    activated_at: a.activated_at,
+                    rollback: a.rollback,
+                })
+                .collect(),
+        })
+    }
+
+    /// Get a stored policy version, or the active one when `version` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails or the version does not exist.
+    pub async fn get_policy_version(
+        &mut self,
+        version: Option<u64>,
+    ) -> Result<PolicyVersion, ClientError> {
+        let response = self
+            .client
+            .get_policy_version(GetPolicyVersionRequest { version })
+            .await?;
+
+        Ok(response.into_inner().into())
+    }
+
+    /// Save policies as a new, inactive version.
+    ///
+    /// Invalid policies are not saved; the result carries their errors.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn save_policy_version(
+        &mut self,
+        policy_text: &str,
+        author: &str,
+        comment: &str,
+    ) -> Result<SaveResult, ClientError> {
+        let response = self
+            .client
+            .save_policy_version(SavePolicyVersionRequest {
+                policy_text: policy_text.to_string(),
+                author: author.to_string(),
+                comment: comment.to_string(),
+            })
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(SaveResult {
+            valid: inner.valid,
+            errors: inner.errors,
+            version: inner.version.map(PolicyVersion::from),
+        })
+    }
+
+    /// Make a stored version the active policy set.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails or the version does not exist.
+    pub async fn activate_policy_version(
+        &mut self,
+        version: u64,
+        actor: &str,
+    ) -> Result<ActivationResult, ClientError> {
+        let response = self
+            .client
+            .activate_policy_version(ActivatePolicyVersionRequest {
+                version,
+                actor: actor.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().into())
+    }
+
+    /// Re-activate the version that was active before the current one.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails or there is nothing to roll
+    /// back to.
+    pub async fn rollback_policies(
+        &mut self,
+        actor: &str,
+    ) -> Result<ActivationResult, ClientError> {
+        let response = self
+            .client
+            .rollback_policies(RollbackPoliciesRequest {
+                actor: actor.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().into())
+    }
 }
 
 /// Authorization request for batch operations.
@@ -185,6 +313,8 @@ pub struct AuthorizationResult {
     pub decision_reason: String,
     /// Diagnostic messages.
     pub diagnostics: Vec<String>,
+    /// Policy version the decision was made with.
+    pub policy_version: u64,
 }
 
 /// Result of a policy reload.
@@ -196,6 +326,8 @@ pub struct ReloadResult {
     pub policies_loaded: i32,
     /// Status message.
     pub message: String,
+    /// Policy version active after the reload.
+    pub active_version: u64,
 }
 
 /// Result of policy validation.
@@ -206,3 +338,94 @@ pub struct ValidationResult {
     /// Validation errors.
     pub errors: Vec<String>,
 }
+
+/// A stored version of the policy set.
+#[derive(Debug, Clone)]
+pub struct PolicyVersion {
+    /// Version number.
+    pub version: u64,
+    /// Cedar source; empty in listings.
+    pub policy_text: String,
+    /// Who saved the version.
+    pub author: String,
+    /// Why the version was saved.
+    pub comment: String,
+    /// When the version was saved (Unix seconds).
+    pub created_at: i64,
+    /// Number of policies in the set.
+    pub policies_count: i32,
+    /// Whether this is the active version.
+    pub active: bool,
+}
+
+impl From<acton_dx_proto::cedar::v1::PolicyVersion> for PolicyVersion {
+    fn from(v: acton_dx_proto::cedar::v1::PolicyVersion) -> Self {
+        Self {
+            version: v.version,
+            policy_text: v.policy_text,
+            author: v.author,
+            comment: v.comment,
+            created_at: v.created_at,
+            policies_count: v.policies_count,
+            active: v.active,
+        }
+    }
+}
+
+/// An activation of a policy version.
+#[derive(Debug, Clone)]
+pub struct PolicyActivation {
+    /// Version that became active.
+    pub version: u64,
+    /// Version that was active before.
+    pub previous_version: u64,
+    /// Who activated the version.
+    pub actor: String,
+    /// When the version was activated (Unix seconds).
+    pub activated_at: i64,
+    /// Whether this was a rollback.
+    pub rollback: bool,
+}
+
+/// Stored policy versions and activations.
+#[derive(Debug, Clone)]
+pub struct PolicyHistory {
+    /// The active version.
+    pub active_version: u64,
+    /// Versions, newest first.
+    pub versions: Vec<PolicyVersion>,
+    /// Activations, newest first.
+    pub activations: Vec<PolicyActivation>,
+}
+
+/// Result of saving a policy version.
+#[derive(Debug, Clone)]
+pub struct SaveResult {
+    /// Whether the policies are valid.
+    pub valid: bool,
+    /// Validation errors.
+    pub errors: Vec<String>,
+    /// The saved version, if the policies were valid.
+    pub version: Option<PolicyVersion>,
+}
+
+/// Result of activating or rolling back a policy version.
+#[derive(Debug, Clone)]
+pub struct ActivationResult {
+    /// The version now active.
+    pub active_version: u64,
+    /// The version active before.
+    pub previous_version: u64,
+    /// Number of policies loaded.
+    pub policies_loaded: i32,
+}
+
+impl From<ActivatePolicyVersionResponse> for ActivationResult {
+    fn from(r: ActivatePolicyVersionResponse) -> Self {
+        Self {
+            active_version: r.active_version,
+            previous_version: r.previous_version,
+            policies_loaded: r.policies_loaded,
+        }
+    }
+}
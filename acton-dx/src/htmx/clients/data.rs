@@ -1,12 +1,28 @@
 //! Data service client for database operations.
 
+use super::cache::CacheClient;
 use super::error::ClientError;
+use super::identity::IdentityToken;
+use super::query_cache::{tables_written, QueryCache};
+use super::query_log::QueryLog;
+use crate::htmx::pagination::{Cursor, CursorPage, SortKey};
+use acton_dx_proto::data::v1::value::Value as ValueKind;
 use acton_dx_proto::data::v1::{
-    data_service_client::DataServiceClient, BeginTransactionRequest, CommitTransactionRequest,
-    ExecuteRequest, MigrationInfo, MigrationStatusRequest, PingRequest, QueryRequest,
-    RollbackTransactionRequest, Row, RunMigrationsRequest, TransactionExecuteRequest, Value,
+    data_service_client::DataServiceClient, ApplyMigrationsRequest, BeginTransactionRequest,
+    CommitTransactionRequest, DescribeSchemaRequest, ExecuteRequest, ExplainQueryRequest,
+    MigrationInfo, MigrationPlan, MigrationStatusRequest, NamedQueryRequest, PingRequest,
+    PlanMigrationsRequest, QueryRequest, QueryStreamRequest, RollbackMigrationRequest,
+    RollbackTransactionRequest, Row, RowChunk, RunMigrationsRequest, TableSchema,
+    TransactionExecuteRequest, Value,
 };
+use futures_util::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use tonic::transport::Channel;
+use tonic::Streaming;
+use tracing::warn;
 
 /// Client for the data service.
 ///
@@ -14,8 +30,16 @@ use tonic::transport::Channel;
 #[derive(Debug, Clone)]
 pub struct DataClient {
     client: DataServiceClient<Channel>,
+    query_cache: Option<QueryCache>,
+    /// Session variables sent with every query and statement
+    session_vars: Vec<(String, String)>,
+    /// Read from the primary rather than a read replica
+    read_primary: bool,
 }
 
+/// gRPC metadata key carrying one `name=value` session variable per entry
+pub const SESSION_VAR_METADATA_KEY: &str = "x-acton-session-var";
+
 impl DataClient {
     /// Connect to the data service.
     ///
@@ -31,9 +55,100 @@ impl DataClient {
 
         Ok(Self {
             client: DataServiceClient::new(channel),
+            query_cache: None,
+            session_vars: Vec::new(),
+            read_primary: false,
         })
     }
 
+    /// Create a client that connects on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the endpoint URI is invalid.
+    pub fn connect_lazy(endpoint: impl Into<String>) -> Result<Self, ClientError> {
+        let endpoint = endpoint.into();
+        let channel = Channel::from_shared(endpoint)
+            .map_err(|e| ClientError::ConnectionFailed(e.to_string()))?
+            .connect_lazy();
+
+        Ok(Self {
+            client: DataServiceClient::new(channel),
+            query_cache: None,
+            session_vars: Vec::new(),
+            read_primary: false,
+        })
+    }
+
+    /// Enable [`query_cached`](Self::query_cached) through the cache service
+    ///
+    /// Once enabled, every `execute` invalidates cached results tagged with
+    /// the tables it writes to, so writes through this client (or its clones)
+    /// are visible to the next cached read.
+    #[must_use]
+    pub fn with_query_cache(mut self, cache: CacheClient) -> Self {
+        self.query_cache = Some(QueryCache::new(cache));
+        self
+    }
+
+    /// Set a Postgres session variable for row-level security policies
+    ///
+    /// The data service applies it with `set_config(name, value, true)` in a
+    /// transaction around each query and statement, so policies can read it
+    /// via `current_setting('app.user_id')` and it never leaks to the next
+    /// request on the pooled connection. The name must be allowlisted in the
+    /// service's `database.session_variables`.
+    ///
+    /// ```rust,ignore
+    /// let mut data = state.data().clone()
+    ///     .with_session_var("app.user_id", user.id.to_string())
+    ///     .with_session_var("app.tenant_id", user.tenant_id.to_string());
+    /// ```
+    #[must_use]
+    pub fn with_session_var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        self.session_vars.retain(|(existing, _)| *existing != name);
+        self.session_vars.push((name, value.into()));
+        self
+    }
+
+    /// Send every query to the primary database, never a read replica
+    ///
+    /// Replicas lag the primary, so a read straight after a write may not
+    /// see it. Use a client with primary reads for read-your-writes:
+    ///
+    /// ```rust,ignore
+    /// data.execute("UPDATE users SET name = $1 WHERE id = $2", params, None).await?;
+    /// let user = data
+    ///     .clone()
+    ///     .with_primary_reads()
+    ///     .query_one("SELECT * FROM users WHERE id = $1", vec![id], None)
+    ///     .await?;
+    /// ```
+    #[must_use]
+    pub const fn with_primary_reads(mut self) -> Self {
+        self.read_primary = true;
+        self
+    }
+
+    /// Wrap a message in a request carrying the session variables and the
+    /// identity token in scope
+    fn request<T>(&self, message: T) -> Result<tonic::Request<T>, ClientError> {
+        let mut request = tonic::Request::new(message);
+        IdentityToken::attach(&mut request);
+        for (name, value) in &self.session_vars {
+            let invalid =
+                || ClientError::RequestFailed(format!("session variable {name} must be ASCII"));
+            let entry = format!("{name}={value}");
+            if !entry.is_ascii() {
+                return Err(invalid());
+            }
+            let entry = entry.parse().map_err(|_| invalid())?;
+            request.metadata_mut().append(SESSION_VAR_METADATA_KEY, entry);
+        }
+        Ok(request)
+    }
+
     // ==================== Query Operations ====================
 
     /// Execute a query and return multiple rows.
@@ -47,18 +162,150 @@ impl DataClient {
         params: Vec<Value>,
         transaction_id: Option<String>,
     ) -> Result<Vec<Row>, ClientError> {
+        let pending = QueryLog::start(&params);
         let response = self
             .client
-            .query(QueryRequest {
+            .query(self.request(QueryRequest {
                 sql: sql.to_string(),
                 params,
                 transaction_id,
-            })
+                read_primary: self.read_primary,
+            })?)
             .await?;
+        if let Some(pending) = pending {
+            pending.finish(sql);
+        }
 
         Ok(response.into_inner().rows)
     }
 
+    /// Stream the rows of a query too large to buffer.
+    ///
+    /// The service sends rows in chunks of `chunk_rows` (zero uses its
+    /// default) and reads only a few chunks ahead, so rows arrive as fast
+    /// as the caller consumes them. Dropping the stream cancels the query.
+    /// Streams do not run inside interactive transactions.
+    ///
+    /// ```rust,ignore
+    /// use futures_util::TryStreamExt;
+    ///
+    /// let mut rows = data.query_stream("SELECT * FROM events", vec![], 0).await?;
+    /// while let Some(row) = rows.try_next().await? {
+    ///     writer.write_row(&row)?;
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service rejects the query. Errors while reading
+    /// are yielded by the stream.
+    pub async fn query_stream(
+        &mut self,
+        sql: &str,
+        params: Vec<Value>,
+        chunk_rows: u32,
+    ) -> Result<RowStream, ClientError> {
+        let response = self
+            .client
+            .query_stream(self.request(QueryStreamRequest {
+                sql: sql.to_string(),
+                params,
+                read_primary: self.read_primary,
+                chunk_rows,
+            })?)
+            .await?;
+
+        Ok(RowStream::new(response.into_inner()))
+    }
+
+    /// Execute a query, serving repeat calls from the cache service
+    ///
+    /// Results are cached for `ttl` under `tags`, which should name the
+    /// tables the query reads; a write to any of them, or
+    /// [`invalidate_tables`](Self::invalidate_tables), drops the entry.
+    /// Without [`with_query_cache`](Self::with_query_cache), or if the cache
+    /// is unreachable, this is a plain [`query`](Self::query). Session
+    /// variables are part of the cache key, since row-level security makes
+    /// results depend on them.
+    ///
+    /// ```rust,ignore
+    /// let rows = data
+    ///     .query_cached(
+    ///         "SELECT status, COUNT(*) AS n FROM orders GROUP BY status",
+    ///         vec![],
+    ///         Duration::from_secs(60),
+    ///         &["orders"],
+    ///     )
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the data service call fails.
+    pub async fn query_cached(
+        &mut self,
+        sql: &str,
+        params: Vec<Value>,
+        ttl: Duration,
+        tags: &[&str],
+    ) -> Result<Vec<Row>, ClientError> {
+        let Some(mut cache) = self.query_cache.clone() else {
+            return self.query(sql, params, None).await;
+        };
+
+        let key = match cache.lookup(sql, &params, &self.session_vars, tags).await {
+            Ok((_, Some(rows))) => return Ok(rows),
+            Ok((key, None)) => Some(key),
+            Err(e) => {
+                warn!(error = %e, "Query cache lookup failed");
+                None
+            }
+        };
+
+        let rows = self.query(sql, params, None).await?;
+        if let Some(key) = key {
+            if let Err(e) = cache.store(&key, &rows, ttl).await {
+                warn!(error = %e, "Query cache store failed");
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Drop cached query results tagged with any of `tables`
+    ///
+    /// Only needed for writes that bypass this client, e.g. another service
+    /// or a raw migration; a no-op without a query cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the cache service call fails.
+    pub async fn invalidate_tables(&mut self, tables: &[&str]) -> Result<(), ClientError> {
+        match self.query_cache.as_mut() {
+            Some(cache) => cache.invalidate(tables).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Invalidate cached results for tables written by `sql`
+    ///
+    /// Inside a transaction the tables are invalidated again on commit, so a
+    /// read that re-cached pre-commit data in between is dropped too.
+    async fn invalidate_written(&self, sql: &str, transaction_id: Option<&str>) {
+        let Some(mut cache) = self.query_cache.clone() else {
+            return;
+        };
+        let tables = tables_written(sql);
+        if tables.is_empty() {
+            return;
+        }
+        if let Err(e) = cache.invalidate(&tables).await {
+            warn!(error = %e, ?tables, "Query cache invalidation failed");
+        }
+        if let Some(transaction_id) = transaction_id {
+            cache.record_pending(transaction_id, tables);
+        }
+    }
+
     /// Execute a query and return a single row.
     ///
     /// # Errors
@@ -70,18 +317,84 @@ impl DataClient {
         params: Vec<Value>,
         transaction_id: Option<String>,
     ) -> Result<Option<Row>, ClientError> {
+        let pending = QueryLog::start(&params);
         let response = self
             .client
-            .query_one(QueryRequest {
+            .query_one(self.request(QueryRequest {
                 sql: sql.to_string(),
                 params,
                 transaction_id,
-            })
+                read_primary: self.read_primary,
+            })?)
             .await?;
+        if let Some(pending) = pending {
+            pending.finish(sql);
+        }
 
         Ok(response.into_inner().row)
     }
 
+    /// Fetch one page of a query in keyset order.
+    ///
+    /// `sql` selects the rows to page through, with any filters, and `order`
+    /// names the columns to sort by. The last sort column must be unique,
+    /// such as the primary key, and none may be `NULL`. The query is wrapped
+    /// so that it returns the `limit` rows after `after`, and the page's
+    /// cursor holds the sort columns of its last row.
+    ///
+    /// ```rust,ignore
+    /// let page = data
+    ///     .query_page(
+    ///         "SELECT id, title, created_at FROM posts WHERE author_id = $1",
+    ///         vec![author_id],
+    ///         &[SortKey::desc("created_at"), SortKey::desc("id")],
+    ///         params.after.as_ref(),
+    ///         params.limit(20, 100),
+    ///     )
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a sort column is not a plain identifier, the cursor
+    /// does not match the sort columns, a row lacks a sort column, or the
+    /// service call fails.
+    pub async fn query_page(
+        &mut self,
+        sql: &str,
+        mut params: Vec<Value>,
+        order: &[SortKey],
+        after: Option<&Cursor>,
+        limit: usize,
+    ) -> Result<CursorPage<Row>, ClientError> {
+        let limit = limit.max(1);
+        let after = after
+            .map(|cursor| cursor_params(cursor, order.len()))
+            .transpose()?;
+        let sql = keyset_sql(sql, order, params.len(), after.is_some(), limit)?;
+        params.extend(after.unwrap_or_default());
+
+        let mut rows = self.query(&sql, params, None).await?;
+        if rows.len() <= limit {
+            return Ok(CursorPage {
+                items: rows,
+                next: None,
+            });
+        }
+        rows.truncate(limit);
+        let next = rows
+            .last()
+            .map(|row| {
+                order
+                    .iter()
+                    .map(|key| cursor_key(row, &key.column))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .map(|keys| Cursor::encode(&keys));
+        Ok(CursorPage { items: rows, next })
+    }
+
     /// Execute a statement (INSERT, UPDATE, DELETE).
     ///
     /// # Errors
@@ -93,14 +406,93 @@ impl DataClient {
         params: Vec<Value>,
         transaction_id: Option<String>,
     ) -> Result<ExecuteResult, ClientError> {
+        let pending = QueryLog::start(&params);
         let response = self
             .client
-            .execute(ExecuteRequest {
+            .execute(self.request(ExecuteRequest {
                 sql: sql.to_string(),
                 params,
-                transaction_id,
-            })
+                transaction_id: transaction_id.clone(),
+            })?)
+            .await?;
+        if let Some(pending) = pending {
+            pending.finish(sql);
+        }
+        self.invalidate_written(sql, transaction_id.as_deref()).await;
+
+        let inner = response.into_inner();
+        Ok(ExecuteResult {
+            rows_affected: inner.rows_affected,
+            last_insert_id: inner.last_insert_id,
+        })
+    }
+
+    // ==================== Named Query Operations ====================
+
+    /// Run a query the data service loaded from its query directory.
+    ///
+    /// The service checks `params` against the types the query declares,
+    /// so only values travel over the wire, never SQL.
+    ///
+    /// ```rust,ignore
+    /// let rows = data
+    ///     .query_named("user_by_email", vec![json::value_from_json(json!(email))])
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, no query has that name, or
+    /// a parameter has the wrong type.
+    pub async fn query_named(
+        &mut self,
+        name: &str,
+        params: Vec<Value>,
+    ) -> Result<Vec<Row>, ClientError> {
+        let pending = QueryLog::start(&params);
+        let response = self
+            .client
+            .query_named(self.request(NamedQueryRequest {
+                name: name.to_string(),
+                params,
+                read_primary: self.read_primary,
+            })?)
+            .await?;
+        if let Some(pending) = pending {
+            pending.finish(name);
+        }
+
+        Ok(response.into_inner().rows)
+    }
+
+    /// Run a named statement (INSERT, UPDATE, DELETE).
+    ///
+    /// The client never sees the SQL, so unlike [`execute`](Self::execute)
+    /// this cannot invalidate cached query results; call
+    /// [`invalidate_tables`](Self::invalidate_tables) with the tables the
+    /// statement writes.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, no query has that name, or
+    /// a parameter has the wrong type.
+    pub async fn execute_named(
+        &mut self,
+        name: &str,
+        params: Vec<Value>,
+    ) -> Result<ExecuteResult, ClientError> {
+        let pending = QueryLog::start(&params);
+        let response = self
+            .client
+            .execute_named(self.request(NamedQueryRequest {
+                name: name.to_string(),
+                params,
+                read_primary: false,
+            })?)
             .await?;
+        if let Some(pending) = pending {
+            pending.finish(name);
+        }
 
         let inner = response.into_inner();
         Ok(ExecuteResult {
@@ -113,14 +505,34 @@ impl DataClient {
 
     /// Begin a new transaction.
     ///
+    /// The transaction stays open on the service until committed or rolled
+    /// back; if no call uses it for the service's idle timeout it is rolled
+    /// back automatically.
+    ///
     /// # Errors
     ///
     /// Returns error if the service call fails.
     pub async fn begin_transaction(&mut self) -> Result<String, ClientError> {
-        let response = self
-            .client
-            .begin_transaction(BeginTransactionRequest {})
-            .await?;
+        self.begin(None).await
+    }
+
+    /// Begin a new transaction that is rolled back after `timeout` idle.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails or `timeout` exceeds the
+    /// service's maximum.
+    pub async fn begin_transaction_with_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<String, ClientError> {
+        let timeout_ms = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+        self.begin(Some(timeout_ms)).await
+    }
+
+    async fn begin(&mut self, timeout_ms: Option<u64>) -> Result<String, ClientError> {
+        let request = self.request(BeginTransactionRequest { timeout_ms })?;
+        let response = self.client.begin_transaction(request).await?;
 
         Ok(response.into_inner().transaction_id)
     }
@@ -138,6 +550,13 @@ impl DataClient {
             })
             .await?;
 
+        if let Some(cache) = self.query_cache.as_mut() {
+            let tables = cache.take_pending(transaction_id);
+            if let Err(e) = cache.invalidate(&tables).await {
+                warn!(error = %e, ?tables, "Query cache invalidation failed");
+            }
+        }
+
         Ok(response.into_inner().success)
     }
 
@@ -157,6 +576,10 @@ impl DataClient {
             })
             .await?;
 
+        if let Some(cache) = &self.query_cache {
+            let _ = cache.take_pending(transaction_id);
+        }
+
         Ok(response.into_inner().success)
     }
 
@@ -171,14 +594,14 @@ impl DataClient {
         sql: &str,
         params: Vec<Value>,
     ) -> Result<ExecuteResult, ClientError> {
-        let response = self
-            .client
-            .execute_in_transaction(TransactionExecuteRequest {
-                transaction_id: transaction_id.to_string(),
-                sql: sql.to_string(),
-                params,
-            })
-            .await?;
+        let mut request = tonic::Request::new(TransactionExecuteRequest {
+            transaction_id: transaction_id.to_string(),
+            sql: sql.to_string(),
+            params,
+        });
+        IdentityToken::attach(&mut request);
+        let response = self.client.execute_in_transaction(request).await?;
+        self.invalidate_written(sql, Some(transaction_id)).await;
 
         let inner = response.into_inner();
         Ok(ExecuteResult {
@@ -187,6 +610,59 @@ impl DataClient {
         })
     }
 
+    // ==================== Introspection Operations ====================
+
+    /// Describe the live database schema.
+    ///
+    /// Returns the dialect (`"postgres"` or `"sqlite"`) and the columns,
+    /// indexes and foreign keys of `tables`, or of every table when empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails or the database dialect
+    /// does not support introspection.
+    pub async fn describe_schema(
+        &mut self,
+        tables: Vec<String>,
+    ) -> Result<(String, Vec<TableSchema>), ClientError> {
+        let response = self
+            .client
+            .describe_schema(DescribeSchemaRequest { tables })
+            .await?;
+
+        let inner = response.into_inner();
+        Ok((inner.dialect, inner.tables))
+    }
+
+    /// Get the query plan for a statement, one line per entry.
+    ///
+    /// With `analyze` the statement is executed (`EXPLAIN ANALYZE` on
+    /// Postgres) inside a transaction the service always rolls back. The
+    /// service only answers when started with `service.explain_enabled`,
+    /// which is meant for development.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, explain is disabled, or the
+    /// statement cannot be planned.
+    pub async fn explain(
+        &mut self,
+        sql: &str,
+        params: Vec<Value>,
+        analyze: bool,
+    ) -> Result<Vec<String>, ClientError> {
+        let response = self
+            .client
+            .explain_query(self.request(ExplainQueryRequest {
+                sql: sql.to_string(),
+                params,
+                analyze,
+            })?)
+            .await?;
+
+        Ok(response.into_inner().plan)
+    }
+
     // ==================== Migration Operations ====================
 
     /// Run database migrations.
@@ -227,6 +703,67 @@ impl DataClient {
         Ok(response.into_inner().migrations)
     }
 
+    /// List the migrations the service would apply up to and including
+    /// `target_version`, or all pending ones, with their SQL, without
+    /// running them.
+    ///
+    /// The service plans from its own migrations directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, the service has no
+    /// migrations directory, or the target names no migration.
+    pub async fn plan_migrations(
+        &mut self,
+        target_version: Option<i64>,
+    ) -> Result<MigrationPlan, ClientError> {
+        let response = self
+            .client
+            .plan_migrations(PlanMigrationsRequest { target_version })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Apply pending migrations up to and including `target_version`, or
+    /// all of them, returning the ones applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, the service has no
+    /// migrations directory, the target names no migration, or a migration
+    /// fails. Migrations before a failed one stay applied.
+    pub async fn apply_migrations(
+        &mut self,
+        target_version: Option<i64>,
+    ) -> Result<MigrationPlan, ClientError> {
+        let response = self
+            .client
+            .apply_migrations(ApplyMigrationsRequest { target_version })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Revert applied migrations above `target_version`, or only the
+    /// latest one, returning the ones reverted.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, the service has no
+    /// migrations directory, or a migration to revert has no down script.
+    pub async fn rollback_migration(
+        &mut self,
+        target_version: Option<i64>,
+    ) -> Result<MigrationPlan, ClientError> {
+        let response = self
+            .client
+            .rollback_migration(RollbackMigrationRequest { target_version })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
     // ==================== Health Operations ====================
 
     /// Ping the database to check health.
@@ -245,6 +782,154 @@ impl DataClient {
     }
 }
 
+/// Rows of a streamed query, one at a time.
+///
+/// Returned by [`DataClient::query_stream`]; the next chunk is only
+/// requested once the rows of the current one have been taken.
+#[derive(Debug)]
+pub struct RowStream {
+    chunks: Streaming<RowChunk>,
+    buffered: VecDeque<Row>,
+}
+
+impl RowStream {
+    const fn new(chunks: Streaming<RowChunk>) -> Self {
+        Self {
+            chunks,
+            buffered: VecDeque::new(),
+        }
+    }
+}
+
+impl Stream for RowStream {
+    type Item = Result<Row, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(row) = self.buffered.pop_front() {
+                return Poll::Ready(Some(Ok(row)));
+            }
+            match Pin::new(&mut self.chunks).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self.buffered.extend(chunk.rows),
+                Poll::Ready(Some(Err(status))) => return Poll::Ready(Some(Err(status.into()))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Wrap `sql` in a keyset query returning up to `limit + 1` rows sorted by
+/// `order`, after the cursor values bound from parameter `first_param + 1`
+fn keyset_sql(
+    sql: &str,
+    order: &[SortKey],
+    first_param: usize,
+    after: bool,
+    limit: usize,
+) -> Result<String, ClientError> {
+    if order.is_empty() {
+        return Err(ClientError::RequestFailed(
+            "keyset pagination needs at least one sort column".to_string(),
+        ));
+    }
+    for key in order {
+        let mut chars = key.column.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(ClientError::RequestFailed(format!(
+                "invalid sort column {:?}",
+                key.column
+            )));
+        }
+    }
+
+    let mut query = format!(
+        "SELECT * FROM ({}) AS page",
+        sql.trim().trim_end_matches(';')
+    );
+    if after {
+        // (a > $1) OR (a = $1 AND b > $2) OR ..., with < for descending keys
+        let clauses: Vec<String> = (0..order.len())
+            .map(|i| {
+                let mut terms: Vec<String> = order[..i]
+                    .iter()
+                    .enumerate()
+                    .map(|(j, key)| format!("{} = ${}", key.column, first_param + j + 1))
+                    .collect();
+                let key = &order[i];
+                let op = if key.descending { '<' } else { '>' };
+                terms.push(format!("{} {op} ${}", key.column, first_param + i + 1));
+                format!("({})", terms.join(" AND "))
+            })
+            .collect();
+        query.push_str(" WHERE ");
+        query.push_str(&clauses.join(" OR "));
+    }
+    let order_by: Vec<String> = order
+        .iter()
+        .map(|key| {
+            let direction = if key.descending { "DESC" } else { "ASC" };
+            format!("{} {direction}", key.column)
+        })
+        .collect();
+    Ok(format!(
+        "{query} ORDER BY {} LIMIT {}",
+        order_by.join(", "),
+        limit + 1
+    ))
+}
+
+/// Sort column of a row as a cursor key
+fn cursor_key(row: &Row, column: &str) -> Result<serde_json::Value, ClientError> {
+    let key = match row
+        .columns
+        .get(column)
+        .and_then(|value| value.value.as_ref())
+    {
+        Some(ValueKind::IntValue(value)) => Some(serde_json::Value::from(*value)),
+        Some(ValueKind::FloatValue(value)) => {
+            serde_json::Number::from_f64(*value).map(serde_json::Value::Number)
+        }
+        Some(ValueKind::StringValue(value)) => Some(serde_json::Value::from(value.clone())),
+        Some(ValueKind::BoolValue(value)) => Some(serde_json::Value::from(*value)),
+        Some(ValueKind::NullValue(_) | ValueKind::BytesValue(_)) | None => None,
+    };
+    key.ok_or_else(|| {
+        ClientError::ResponseError(format!(
+            "sort column {column} is missing, NULL or not a scalar"
+        ))
+    })
+}
+
+/// Query parameters for the keys of a cursor over `columns` sort columns
+fn cursor_params(cursor: &Cursor, columns: usize) -> Result<Vec<Value>, ClientError> {
+    let invalid = || ClientError::RequestFailed("cursor does not match the sort columns".into());
+    let keys: Vec<serde_json::Value> = cursor.decode().map_err(|_| invalid())?;
+    if keys.len() != columns {
+        return Err(invalid());
+    }
+    keys.into_iter()
+        .map(|key| {
+            let value = match key {
+                serde_json::Value::Number(n) => n
+                    .as_i64()
+                    .map(ValueKind::IntValue)
+                    .or_else(|| n.as_f64().map(ValueKind::FloatValue)),
+                serde_json::Value::String(s) => Some(ValueKind::StringValue(s)),
+                serde_json::Value::Bool(b) => Some(ValueKind::BoolValue(b)),
+                _ => None,
+            };
+            value
+                .map(|value| Value { value: Some(value) })
+                .ok_or_else(invalid)
+        })
+        .collect()
+}
+
 /// Result of an execute operation.
 #[derive(Debug, Clone)]
 pub struct ExecuteResult {
@@ -273,3 +958,101 @@ pub struct PingResult {
     /// Latency in milliseconds.
     pub latency_ms: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> DataClient {
+        DataClient::connect_lazy("http://127.0.0.1:1").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_carries_session_vars() {
+        let data = client()
+            .with_session_var("app.user_id", "1")
+            .with_session_var("app.tenant_id", "7")
+            .with_session_var("app.user_id", "42");
+        let request = data.request(()).unwrap();
+        let entries: Vec<_> = request
+            .metadata()
+            .get_all(SESSION_VAR_METADATA_KEY)
+            .iter()
+            .map(|value| value.to_str().unwrap().to_string())
+            .collect();
+        assert_eq!(entries, ["app.tenant_id=7", "app.user_id=42"]);
+
+        let data = client().with_session_var("app.name", "Zoë");
+        assert!(matches!(
+            data.request(()),
+            Err(ClientError::RequestFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_keyset_sql() {
+        let order = [SortKey::desc("created_at"), SortKey::asc("id")];
+        assert_eq!(
+            keyset_sql(
+                "SELECT * FROM posts WHERE author_id = $1;",
+                &order,
+                1,
+                true,
+                20
+            )
+            .unwrap(),
+            "SELECT * FROM (SELECT * FROM posts WHERE author_id = $1) AS page \
+             WHERE (created_at < $2) OR (created_at = $2 AND id > $3) \
+             ORDER BY created_at DESC, id ASC LIMIT 21"
+        );
+        assert_eq!(
+            keyset_sql("SELECT * FROM posts", &order[1..], 0, false, 5).unwrap(),
+            "SELECT * FROM (SELECT * FROM posts) AS page ORDER BY id ASC LIMIT 6"
+        );
+        assert!(keyset_sql("SELECT 1", &[SortKey::asc("id; DROP")], 0, false, 5).is_err());
+        assert!(keyset_sql("SELECT 1", &[], 0, false, 5).is_err());
+    }
+
+    #[test]
+    fn test_cursor_keys_round_trip() {
+        let row = Row {
+            columns: [
+                (
+                    "created_at".to_string(),
+                    Value {
+                        value: Some(ValueKind::IntValue(1_700)),
+                    },
+                ),
+                (
+                    "slug".to_string(),
+                    Value {
+                        value: Some(ValueKind::StringValue("hello".into())),
+                    },
+                ),
+                (
+                    "deleted".to_string(),
+                    Value {
+                        value: Some(ValueKind::NullValue(true)),
+                    },
+                ),
+            ]
+            .into(),
+        };
+        let keys = vec![
+            cursor_key(&row, "created_at").unwrap(),
+            cursor_key(&row, "slug").unwrap(),
+        ];
+        assert!(cursor_key(&row, "deleted").is_err());
+        assert!(cursor_key(&row, "missing").is_err());
+
+        let cursor = Cursor::encode(&keys);
+        let params = cursor_params(&cursor, 2).unwrap();
+        assert_eq!(params[0].value, Some(ValueKind::IntValue(1_700)));
+        assert_eq!(
+            params[1].value,
+            Some(ValueKind::StringValue("hello".into()))
+        );
+        assert!(cursor_params(&cursor, 3).is_err());
+        assert!(cursor_params(&Cursor::from_token("junk"), 2).is_err());
+    }
+}
@@ -1,9 +1,12 @@
 //! Email service client for sending emails.
 
 use super::error::ClientError;
+use super::ics::IcsEvent;
 use acton_dx_proto::email::v1::{
-    email_service_client::EmailServiceClient, Attachment, Email, EmailAddress, SendBatchRequest,
-    SendEmailRequest, ValidateAddressRequest,
+    email_service_client::EmailServiceClient, Attachment, Email, EmailAddress, EmailTemplate,
+    GetEmailStatsRequest, GetEmailStatusRequest, ListDevMailboxRequest, ListSuppressedRequest,
+    PreviewEmailRequest, SendBatchRequest, SendEmailRequest, SendTemplatedEmailRequest,
+    SuppressAddressRequest, UnsuppressAddressRequest, ValidateAddressRequest,
 };
 use tonic::transport::Channel;
 
@@ -55,6 +58,41 @@ impl EmailClient {
         })
     }
 
+    /// Send an email rendered from a template registered with the service.
+    ///
+    /// `email` supplies the sender, recipients, attachments and headers;
+    /// its subject and bodies are replaced by the rendered template, and it
+    /// is tagged with the template name for delivery stats. `context` must
+    /// be a JSON object.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, including when no template
+    /// has that name or `context` is not an object. Rendering errors are
+    /// reported in [`SendResult::error`].
+    pub async fn send_templated(
+        &mut self,
+        template: &str,
+        context: &serde_json::Value,
+        email: EmailMessage,
+    ) -> Result<SendResult, ClientError> {
+        let response = self
+            .client
+            .send_templated_email(SendTemplatedEmailRequest {
+                template: template.to_string(),
+                context_json: context.to_string(),
+                email: Some(email.into_proto()),
+            })
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(SendResult {
+            success: inner.success,
+            message_id: inner.message_id,
+            error: inner.error,
+        })
+    }
+
     /// Send multiple emails in batch.
     ///
     /// # Errors
@@ -108,6 +146,193 @@ impl EmailClient {
             reason: inner.reason,
         })
     }
+
+    /// Render an email template without sending it.
+    ///
+    /// Template parts use MiniJinja syntax; `context` must be a JSON object.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails. Template errors are
+    /// reported in [`PreviewResult::error`].
+    pub async fn preview(
+        &mut self,
+        template: EmailTemplateSource,
+        context: &serde_json::Value,
+    ) -> Result<PreviewResult, ClientError> {
+        let response = self
+            .client
+            .preview_email(PreviewEmailRequest {
+                template: Some(template.into_proto()),
+                context_json: context.to_string(),
+            })
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(PreviewResult {
+            success: inner.success,
+            subject: inner.subject,
+            html_body: inner.html_body,
+            text_body: inner.text_body,
+            error: inner.error,
+        })
+    }
+
+    /// Delivery stats by template and recipient domain since the service started.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn stats(&mut self) -> Result<EmailStats, ClientError> {
+        let response = self.client.get_email_stats(GetEmailStatsRequest {}).await?;
+
+        let inner = response.into_inner();
+        Ok(EmailStats {
+            sent: inner.sent,
+            failed: inner.failed,
+            retried: inner.retried,
+            entries: inner
+                .entries
+                .into_iter()
+                .map(|entry| EmailStatsEntry {
+                    template: entry.template,
+                    domain: entry.domain,
+                    sent: entry.sent,
+                    failed: entry.failed,
+                    retried: entry.retried,
+                })
+                .collect(),
+        })
+    }
+
+    /// List emails captured by the service in dry-run mode, newest first.
+    ///
+    /// `limit` 0 returns all captured emails.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn list_dev_mailbox(&mut self, limit: u32) -> Result<DevMailbox, ClientError> {
+        let response = self
+            .client
+            .list_dev_mailbox(ListDevMailboxRequest { limit })
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(DevMailbox {
+            dry_run: inner.dry_run,
+            messages: inner
+                .messages
+                .into_iter()
+                .map(|entry| CapturedEmail {
+                    message_id: entry.message_id,
+                    captured_at: entry.captured_at,
+                    email: entry.email.map(EmailMessage::from_proto).unwrap_or_default(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Delivery status of an email the service accepted.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails, including when the
+    /// message ID is unknown or its status has been evicted.
+    pub async fn status(&mut self, message_id: &str) -> Result<DeliveryStatus, ClientError> {
+        let response = self
+            .client
+            .get_email_status(GetEmailStatusRequest {
+                message_id: message_id.to_string(),
+            })
+            .await?;
+
+        let inner = response.into_inner();
+        let state = match inner.state.as_str() {
+            "queued" => DeliveryState::Queued,
+            "sent" => DeliveryState::Sent,
+            "failed" => DeliveryState::Failed,
+            other => {
+                return Err(ClientError::ResponseError(format!(
+                    "unknown delivery state {other}"
+                )))
+            }
+        };
+        Ok(DeliveryStatus {
+            message_id: inner.message_id,
+            state,
+            attempts: inner.attempts,
+            last_error: inner.last_error,
+            queued_at: inner.queued_at,
+            next_attempt_at: inner.next_attempt_at,
+            completed_at: inner.completed_at,
+        })
+    }
+
+    /// A page of the suppression list in address order (`limit` 0 = all).
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn list_suppressed(
+        &mut self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<SuppressionPage, ClientError> {
+        let response = self
+            .client
+            .list_suppressed(ListSuppressedRequest { limit, offset })
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(SuppressionPage {
+            total: inner.total,
+            addresses: inner
+                .addresses
+                .into_iter()
+                .map(|address| SuppressedAddress {
+                    email: address.email,
+                    reason: address.reason,
+                    suppressed_at: address.suppressed_at,
+                })
+                .collect(),
+        })
+    }
+
+    /// Never email `email` again, e.g. after a bounce webhook reports it.
+    ///
+    /// Returns `false` if it was already suppressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails or the address is invalid.
+    pub async fn suppress(&mut self, email: &str, reason: &str) -> Result<bool, ClientError> {
+        let response = self
+            .client
+            .suppress_address(SuppressAddressRequest {
+                email: email.to_string(),
+                reason: reason.to_string(),
+            })
+            .await?;
+        Ok(response.into_inner().added)
+    }
+
+    /// Remove `email` from the suppression list.
+    ///
+    /// Returns `false` if it was not suppressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn unsuppress(&mut self, email: &str) -> Result<bool, ClientError> {
+        let response = self
+            .client
+            .unsuppress_address(UnsuppressAddressRequest {
+                email: email.to_string(),
+            })
+            .await?;
+        Ok(response.into_inner().removed)
+    }
 }
 
 /// An email message to send.
@@ -133,6 +358,10 @@ pub struct EmailMessage {
     pub attachments: Vec<EmailAttachment>,
     /// Additional headers.
     pub headers: std::collections::HashMap<String, String>,
+    /// Template name, used to break down delivery stats.
+    pub template: Option<String>,
+    /// Meeting invite sent with the message.
+    pub ics_event: Option<IcsEvent>,
 }
 
 impl EmailMessage {
@@ -142,6 +371,13 @@ impl EmailMessage {
         Self::default()
     }
 
+    /// Tag the message with its template name for delivery stats.
+    #[must_use]
+    pub fn template(mut self, name: impl Into<String>) -> Self {
+        self.template = Some(name.into());
+        self
+    }
+
     /// Set the from address.
     #[must_use]
     pub fn from(mut self, email: impl Into<String>) -> Self {
@@ -214,8 +450,48 @@ impl EmailMessage {
         self
     }
 
+    /// Send a meeting invite with the message.
+    ///
+    /// Mail clients show it as an invitation with accept/decline buttons.
+    #[must_use]
+    pub fn invite(mut self, event: IcsEvent) -> Self {
+        self.ics_event = Some(event);
+        self
+    }
+
+    /// Convert from proto message.
+    fn from_proto(email: Email) -> Self {
+        Self {
+            from: email.from.map(EmailAddr::from_proto).unwrap_or_default(),
+            to: email.to.into_iter().map(EmailAddr::from_proto).collect(),
+            cc: email.cc.into_iter().map(EmailAddr::from_proto).collect(),
+            bcc: email.bcc.into_iter().map(EmailAddr::from_proto).collect(),
+            reply_to: email.reply_to.map(EmailAddr::from_proto),
+            subject: email.subject,
+            text_body: email.text_body,
+            html_body: email.html_body,
+            attachments: email
+                .attachments
+                .into_iter()
+                .map(|a| EmailAttachment {
+                    filename: a.filename,
+                    content: a.content,
+                    content_type: a.content_type,
+                })
+                .collect(),
+            headers: email.headers,
+            template: email.template,
+            // Rendered invites are not parsed back
+            ics_event: None,
+        }
+    }
+
     /// Convert to proto message.
     fn into_proto(self) -> Email {
+        let calendar = self.ics_event.as_ref().map(|event| {
+            let attendees: Vec<EmailAddr> = self.to.iter().chain(&self.cc).cloned().collect();
+            event.render(&self.from, &attendees, chrono::Utc::now())
+        });
         Email {
             from: Some(self.from.into_proto()),
             to: self.to.into_iter().map(EmailAddr::into_proto).collect(),
@@ -231,6 +507,8 @@ impl EmailMessage {
                 .map(EmailAttachment::into_proto)
                 .collect(),
             headers: self.headers,
+            template: self.template,
+            calendar,
         }
     }
 }
@@ -245,6 +523,13 @@ pub struct EmailAddr {
 }
 
 impl EmailAddr {
+    fn from_proto(addr: EmailAddress) -> Self {
+        Self {
+            email: addr.email,
+            name: addr.name,
+        }
+    }
+
     fn into_proto(self) -> EmailAddress {
         EmailAddress {
             email: self.email,
@@ -306,3 +591,254 @@ pub struct ValidationResult {
     /// Reason if invalid.
     pub reason: Option<String>,
 }
+
+/// Email template to preview; each part is a MiniJinja template.
+#[derive(Debug, Clone, Default)]
+pub struct EmailTemplateSource {
+    /// Subject template.
+    pub subject: String,
+    /// HTML body template (values are HTML-escaped).
+    pub html_body: Option<String>,
+    /// Plain text body template.
+    pub text_body: Option<String>,
+}
+
+impl EmailTemplateSource {
+    fn into_proto(self) -> EmailTemplate {
+        EmailTemplate {
+            subject: self.subject,
+            html_body: self.html_body,
+            text_body: self.text_body,
+        }
+    }
+}
+
+/// Result of rendering an email preview.
+#[derive(Debug, Clone)]
+pub struct PreviewResult {
+    /// Whether rendering succeeded.
+    pub success: bool,
+    /// Rendered subject.
+    pub subject: String,
+    /// Rendered HTML body.
+    pub html_body: Option<String>,
+    /// Rendered plain text body.
+    pub text_body: Option<String>,
+    /// Template or context error if rendering failed.
+    pub error: Option<String>,
+}
+
+/// Emails captured by the service in dry-run mode.
+#[derive(Debug, Clone)]
+pub struct DevMailbox {
+    /// Whether the service is in dry-run mode.
+    pub dry_run: bool,
+    /// Captured emails, newest first.
+    pub messages: Vec<CapturedEmail>,
+}
+
+/// An email captured instead of being sent.
+#[derive(Debug, Clone)]
+pub struct CapturedEmail {
+    /// Message ID returned to the sender.
+    pub message_id: String,
+    /// Unix timestamp (seconds) when the email was captured.
+    pub captured_at: i64,
+    /// The captured email.
+    pub email: EmailMessage,
+}
+
+/// Where an accepted email is in delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryState {
+    /// Waiting for its first or next attempt.
+    Queued,
+    /// Accepted by the SMTP server.
+    Sent,
+    /// Failed permanently or ran out of attempts.
+    Failed,
+}
+
+/// Delivery status of an accepted email.
+#[derive(Debug, Clone)]
+pub struct DeliveryStatus {
+    /// Message ID returned when the email was sent.
+    pub message_id: String,
+    /// Delivery state.
+    pub state: DeliveryState,
+    /// SMTP attempts made so far.
+    pub attempts: u32,
+    /// Error from the latest failed attempt.
+    pub last_error: Option<String>,
+    /// Unix timestamp (seconds) when the email was accepted.
+    pub queued_at: i64,
+    /// Unix timestamp (seconds) of the next attempt, while queued.
+    pub next_attempt_at: Option<i64>,
+    /// Unix timestamp (seconds) when the email was sent or given up on.
+    pub completed_at: Option<i64>,
+}
+
+/// A page of the suppression list.
+#[derive(Debug, Clone)]
+pub struct SuppressionPage {
+    /// Addresses on the page.
+    pub addresses: Vec<SuppressedAddress>,
+    /// Addresses on the list.
+    pub total: u64,
+}
+
+/// An address that is never emailed.
+#[derive(Debug, Clone)]
+pub struct SuppressedAddress {
+    /// Email address.
+    pub email: String,
+    /// Why the address was suppressed.
+    pub reason: String,
+    /// Unix timestamp (seconds) when the address was suppressed.
+    pub suppressed_at: i64,
+}
+
+/// Email delivery stats since the service started.
+#[derive(Debug, Clone, Default)]
+pub struct EmailStats {
+    /// Emails accepted by the SMTP server.
+    pub sent: u64,
+    /// Emails that could not be delivered.
+    pub failed: u64,
+    /// Transient failures that were retried.
+    pub retried: u64,
+    /// Counts by template and recipient domain.
+    pub entries: Vec<EmailStatsEntry>,
+}
+
+/// Delivery counts for one template and recipient domain.
+#[derive(Debug, Clone)]
+pub struct EmailStatsEntry {
+    /// Template name (`none` for emails without one).
+    pub template: String,
+    /// Recipient domain.
+    pub domain: String,
+    /// Emails sent.
+    pub sent: u64,
+    /// Emails failed.
+    pub failed: u64,
+    /// Retries after transient failures.
+    pub retried: u64,
+}
+
+impl EmailStats {
+    /// Render the stats in Prometheus text format.
+    ///
+    /// Append the output to your `/metrics` response to alert on, e.g., a
+    /// spike of failures for one provider domain.
+    #[must_use]
+    pub fn render_prometheus(&self) -> String {
+        let mut output = String::new();
+        self.write_counter(
+            &mut output,
+            "emails_sent_total",
+            "Emails accepted by the SMTP server",
+            self.sent,
+            |e| e.sent,
+        );
+        self.write_counter(
+            &mut output,
+            "emails_failed_total",
+            "Emails that could not be delivered",
+            self.failed,
+            |e| e.failed,
+        );
+        self.write_counter(
+            &mut output,
+            "emails_retried_total",
+            "Transient email failures that were retried",
+            self.retried,
+            |e| e.retried,
+        );
+        output
+    }
+
+    fn write_counter(
+        &self,
+        output: &mut String,
+        name: &str,
+        help: &str,
+        total: u64,
+        value: impl Fn(&EmailStatsEntry) -> u64,
+    ) {
+        use std::fmt::Write;
+
+        let _ = writeln!(output, "# HELP {name} {help}");
+        let _ = writeln!(output, "# TYPE {name} counter");
+        let _ = writeln!(output, "{name} {total}");
+        for entry in &self.entries {
+            let _ = writeln!(
+                output,
+                r#"{name}{{template="{}",domain="{}"}} {}"#,
+                escape_label(&entry.template),
+                escape_label(&entry.domain),
+                value(entry)
+            );
+        }
+        output.push('\n');
+    }
+}
+
+/// Escape a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_email_stats_render_prometheus() {
+        let stats = EmailStats {
+            sent: 10,
+            failed: 2,
+            retried: 1,
+            entries: vec![EmailStatsEntry {
+                template: "welcome".to_string(),
+                domain: "gmail.com".to_string(),
+                sent: 10,
+                failed: 2,
+                retried: 1,
+            }],
+        };
+        let output = stats.render_prometheus();
+        assert!(output.contains("# TYPE emails_failed_total counter"));
+        assert!(output.contains("emails_failed_total 2\n"));
+        assert!(output.contains(r#"emails_failed_total{template="welcome",domain="gmail.com"} 2"#));
+        assert!(output.contains(r#"emails_sent_total{template="welcome",domain="gmail.com"} 10"#));
+    }
+
+    #[test]
+    fn test_invite_defaults_to_message_people() {
+        use chrono::{TimeZone, Utc};
+
+        let start = Utc.with_ymd_and_hms(2025, 3, 1, 14, 0, 0).unwrap();
+        let email = EmailMessage::new()
+            .from("owner@example.com")
+            .to("ada@example.com")
+            .subject("Planning")
+            .invite(IcsEvent::new("meeting-7", "Planning", start, start))
+            .into_proto();
+
+        // Long content lines are folded at 75 octets
+        let calendar = email.calendar.unwrap().replace("\r\n ", "");
+        assert!(calendar.contains("METHOD:REQUEST"));
+        assert!(calendar.contains("ORGANIZER:mailto:owner@example.com"));
+        assert!(calendar.contains(":mailto:ada@example.com"));
+        assert!(EmailMessage::new().into_proto().calendar.is_none());
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+}
@@ -1,6 +1,14 @@
 //! Client error types for microservice communication.
+//!
+//! Services attach an [`ErrorDetail`] to failed calls. [`ClientError`]
+//! decodes it: invalid fields become [`ClientError::InvalidFields`], which
+//! converts to form [`ValidationErrors`], and retry hints become
+//! [`ClientError::RetryLater`].
 
+use crate::htmx::forms::ValidationErrors;
+use acton_dx_proto::error::v1::{ErrorDetail, FieldViolation, HelpLink};
 use std::fmt;
+use std::time::Duration;
 
 /// Error type for service client operations.
 #[derive(Debug)]
@@ -21,6 +29,28 @@ pub enum ClientError {
         /// Human-readable error message.
         message: String,
     },
+    /// Service rejected fields of the request.
+    InvalidFields {
+        /// Machine-readable error code from the service.
+        code: String,
+        /// Human-readable error message.
+        message: String,
+        /// Fields that failed validation.
+        violations: Vec<FieldViolation>,
+        /// Documentation about the error.
+        help_links: Vec<HelpLink>,
+    },
+    /// Service asked the caller to retry later.
+    RetryLater {
+        /// Machine-readable error code from the service.
+        code: String,
+        /// Human-readable error message.
+        message: String,
+        /// How long to wait before retrying.
+        retry_after: Duration,
+        /// Documentation about the error.
+        help_links: Vec<HelpLink>,
+    },
     /// Response parsing failed.
     ResponseError(String),
     /// Circuit breaker is open.
@@ -50,6 +80,19 @@ impl fmt::Display for ClientError {
             Self::ServiceError { code, message } => {
                 write!(f, "Service error [{code}]: {message}")
             }
+            Self::InvalidFields { code, message, .. } => {
+                write!(f, "Invalid request [{code}]: {message}")
+            }
+            Self::RetryLater {
+                code,
+                message,
+                retry_after,
+                ..
+            } => write!(
+                f,
+                "Service error [{code}]: {message} (retry after {}ms)",
+                retry_after.as_millis()
+            ),
             Self::ResponseError(msg) => write!(f, "Response error: {msg}"),
             Self::CircuitOpen(service) => write!(f, "Circuit breaker open for: {service}"),
             Self::Timeout => write!(f, "Request timed out"),
@@ -64,6 +107,45 @@ impl fmt::Display for ClientError {
 
 impl std::error::Error for ClientError {}
 
+impl ClientError {
+    /// Field errors to show next to form inputs, if the service rejected
+    /// fields of the request.
+    #[must_use]
+    pub fn validation_errors(&self) -> Option<ValidationErrors> {
+        let Self::InvalidFields {
+            code, violations, ..
+        } = self
+        else {
+            return None;
+        };
+        let mut errors = ValidationErrors::new();
+        for violation in violations {
+            errors.add_with_code(&violation.field, &violation.description, code);
+        }
+        Some(errors)
+    }
+
+    /// How long to wait before retrying, if the service said.
+    #[must_use]
+    pub const fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RetryLater { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+
+    /// Documentation links the service attached to the error.
+    #[must_use]
+    pub fn help_links(&self) -> &[HelpLink] {
+        match self {
+            Self::InvalidFields { help_links, .. } | Self::RetryLater { help_links, .. } => {
+                help_links
+            }
+            _ => &[],
+        }
+    }
+}
+
 impl From<tonic::transport::Error> for ClientError {
     fn from(err: tonic::transport::Error) -> Self {
         Self::ConnectionFailed(err.to_string())
@@ -72,9 +154,24 @@ impl From<tonic::transport::Error> for ClientError {
 
 impl From<tonic::Status> for ClientError {
     fn from(status: tonic::Status) -> Self {
-        Self::ServiceError {
-            code: status.code().to_string(),
-            message: status.message().to_string(),
+        match ErrorDetail::from_status(&status) {
+            Some(detail) if !detail.field_violations.is_empty() => Self::InvalidFields {
+                code: detail.code,
+                message: detail.message,
+                violations: detail.field_violations,
+                help_links: detail.help_links,
+            },
+            Some(detail) if detail.retry_after_ms.is_some() => Self::RetryLater {
+                retry_after: detail.retry_after().unwrap_or_default(),
+                code: detail.code,
+                message: detail.message,
+                help_links: detail.help_links,
+            },
+            // Other details add nothing the status code and message don't say
+            _ => Self::ServiceError {
+                code: status.code().to_string(),
+                message: status.message().to_string(),
+            },
         }
     }
 }
@@ -84,3 +181,48 @@ impl From<std::io::Error> for ClientError {
         Self::IoError(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acton_dx_proto::error::invalid_field;
+    use tonic::{Code, Status};
+
+    #[test]
+    fn test_field_violations_become_validation_errors() {
+        let error = ClientError::from(invalid_field("email", "email is already taken"));
+
+        let errors = error.validation_errors().expect("invalid fields");
+        assert_eq!(
+            errors.for_field("email")[0].message,
+            "email is already taken"
+        );
+        assert_eq!(
+            errors.for_field("email")[0].code.as_deref(),
+            Some("INVALID_FIELD")
+        );
+        assert_eq!(error.retry_after(), None);
+    }
+
+    #[test]
+    fn test_retry_after_is_decoded() {
+        let status = ErrorDetail::new("DATABASE_UNAVAILABLE", "Database unavailable")
+            .with_retry_after(Duration::from_secs(2))
+            .with_help_link("Runbook", "https://acton.dev/runbook")
+            .into_status(Code::Unavailable);
+        let error = ClientError::from(status);
+
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(2)));
+        assert_eq!(error.help_links()[0].description, "Runbook");
+        assert!(error.validation_errors().is_none());
+    }
+
+    #[test]
+    fn test_plain_status_stays_service_error() {
+        let error = ClientError::from(Status::not_found("Transaction not found"));
+        assert!(matches!(
+            error,
+            ClientError::ServiceError { ref code, .. } if *code == Code::NotFound.to_string()
+        ));
+    }
+}
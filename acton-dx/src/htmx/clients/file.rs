@@ -1,14 +1,17 @@
 //! File service client for file storage operations.
 
 use super::error::ClientError;
+use super::identity::IdentityToken;
 use acton_dx_proto::file::v1::{
     file_service_client::FileServiceClient, DeleteRequest, DownloadRequest, FileMetadata,
-    GetMetadataRequest, GetSignedUrlRequest, GetUrlRequest, ListFilesRequest, UploadMetadata,
-    UploadRequest,
+    GetMetadataRequest, GetSignedUrlRequest, GetUrlRequest, GetUsageRequest, ListFilesRequest,
+    UploadMetadata, UploadRequest, VerifySignedUrlRequest,
 };
+use acton_dx_proto::identity::TENANT_METADATA_KEY;
 use futures_util::StreamExt;
 use std::collections::HashMap;
 use tokio_stream::wrappers::ReceiverStream;
+use tonic::metadata::{Ascii, MetadataValue};
 use tonic::transport::Channel;
 
 /// Client for the file service.
@@ -19,6 +22,7 @@ use tonic::transport::Channel;
 pub struct FileClient {
     client: FileServiceClient<Channel>,
     chunk_size: usize,
+    tenant: Option<MetadataValue<Ascii>>,
 }
 
 impl FileClient {
@@ -49,9 +53,39 @@ impl FileClient {
         Ok(Self {
             client: FileServiceClient::new(channel),
             chunk_size,
+            tenant: None,
         })
     }
 
+    /// Act for `tenant` on calls made without an identity token.
+    ///
+    /// Calls made for a signed-in user act for the tenant in their token
+    /// instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the tenant cannot be sent as metadata.
+    pub fn with_tenant(mut self, tenant: &str) -> Result<Self, ClientError> {
+        let tenant = tenant
+            .parse()
+            .map_err(|_| ClientError::RequestFailed("malformed tenant".to_string()))?;
+        self.tenant = Some(tenant);
+        Ok(self)
+    }
+
+    /// Wrap a message in a request carrying the identity token in scope and
+    /// the tenant the client acts for
+    fn request<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        IdentityToken::attach(&mut request);
+        if let Some(tenant) = &self.tenant {
+            request
+                .metadata_mut()
+                .insert(TENANT_METADATA_KEY, tenant.clone());
+        }
+        request
+    }
+
     /// Upload a file.
     ///
     /// # Errors
@@ -99,7 +133,7 @@ impl FileClient {
         drop(tx);
 
         let stream = ReceiverStream::new(rx);
-        let response = self.client.upload(stream).await?;
+        let response = self.client.upload(self.request(stream)).await?;
 
         let inner = response.into_inner();
         if inner.success {
@@ -139,11 +173,11 @@ impl FileClient {
     ) -> Result<DownloadResult, ClientError> {
         let response = self
             .client
-            .download(DownloadRequest {
+            .download(self.request(DownloadRequest {
                 file_id: file_id.to_string(),
                 range_start,
                 range_end,
-            })
+            }))
             .await?;
 
         let mut stream = response.into_inner();
@@ -179,9 +213,9 @@ impl FileClient {
     pub async fn delete(&mut self, file_id: &str) -> Result<bool, ClientError> {
         let response = self
             .client
-            .delete(DeleteRequest {
+            .delete(self.request(DeleteRequest {
                 file_id: file_id.to_string(),
-            })
+            }))
             .await?;
 
         Ok(response.into_inner().success)
@@ -195,9 +229,9 @@ impl FileClient {
     pub async fn get_metadata(&mut self, file_id: &str) -> Result<StoredFileInfo, ClientError> {
         let response = self
             .client
-            .get_metadata(GetMetadataRequest {
+            .get_metadata(self.request(GetMetadataRequest {
                 file_id: file_id.to_string(),
-            })
+            }))
             .await?;
 
         Ok(response.into_inner().into())
@@ -216,11 +250,11 @@ impl FileClient {
     ) -> Result<ListResult, ClientError> {
         let response = self
             .client
-            .list_files(ListFilesRequest {
+            .list_files(self.request(ListFilesRequest {
                 path_prefix,
                 limit,
                 cursor,
-            })
+            }))
             .await?;
 
         let inner = response.into_inner();
@@ -238,15 +272,15 @@ impl FileClient {
     pub async fn get_public_url(&mut self, file_id: &str) -> Result<String, ClientError> {
         let response = self
             .client
-            .get_public_url(GetUrlRequest {
+            .get_public_url(self.request(GetUrlRequest {
                 file_id: file_id.to_string(),
-            })
+            }))
             .await?;
 
         Ok(response.into_inner().url)
     }
 
-    /// Get a signed URL for a file.
+    /// Get a signed URL for downloading a file.
     ///
     /// # Errors
     ///
@@ -255,13 +289,33 @@ impl FileClient {
         &mut self,
         file_id: &str,
         expires_in_seconds: i64,
+    ) -> Result<SignedUrlResult, ClientError> {
+        self.get_scoped_signed_url(file_id, expires_in_seconds, "GET", None)
+            .await
+    }
+
+    /// Get a signed URL that only allows `method` (`GET` or `PUT`), and only
+    /// from `ip_address` if given.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the method or IP address is invalid or the service
+    /// call fails.
+    pub async fn get_scoped_signed_url(
+        &mut self,
+        file_id: &str,
+        expires_in_seconds: i64,
+        method: &str,
+        ip_address: Option<&str>,
     ) -> Result<SignedUrlResult, ClientError> {
         let response = self
             .client
-            .get_signed_url(GetSignedUrlRequest {
+            .get_signed_url(self.request(GetSignedUrlRequest {
                 file_id: file_id.to_string(),
                 expires_in_seconds,
-            })
+                method: Some(method.to_string()),
+                ip_address: ip_address.map(str::to_string),
+            }))
             .await?;
 
         let inner = response.into_inner();
@@ -270,6 +324,68 @@ impl FileClient {
             expires_at: inner.expires_at,
         })
     }
+
+    /// Check a signed URL presented with `method` from `ip_address`.
+    ///
+    /// `url` may be the full URL or just its path and query.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn verify_signed_url(
+        &mut self,
+        url: &str,
+        method: &str,
+        ip_address: Option<&str>,
+    ) -> Result<SignedUrlVerification, ClientError> {
+        let response = self
+            .client
+            .verify_signed_url(self.request(VerifySignedUrlRequest {
+                url: url.to_string(),
+                method: method.to_string(),
+                ip_address: ip_address.map(str::to_string),
+            }))
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(if inner.valid {
+            SignedUrlVerification::Valid {
+                file_id: inner.file_id,
+                expires_at: inner.expires_at,
+            }
+        } else {
+            SignedUrlVerification::Invalid {
+                reason: inner.reason.unwrap_or_default(),
+            }
+        })
+    }
+
+    /// Get the storage used by a tenant and its quota.
+    ///
+    /// `None` asks for the caller's own tenant.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the service call fails.
+    pub async fn get_usage(
+        &mut self,
+        tenant_id: Option<String>,
+    ) -> Result<StorageUsage, ClientError> {
+        let response = self
+            .client
+            .get_usage(self.request(GetUsageRequest { tenant_id }))
+            .await?;
+
+        let inner = response.into_inner();
+        Ok(StorageUsage {
+            tenant_id: inner.tenant_id,
+            used_bytes: inner.used_bytes,
+            file_count: inner.file_count,
+            soft_limit: inner.soft_limit,
+            hard_limit: inner.hard_limit,
+            over_soft_limit: inner.over_soft_limit,
+        })
+    }
 }
 
 /// Result of an upload operation.
@@ -345,3 +461,38 @@ pub struct SignedUrlResult {
     /// Expiration timestamp.
     pub expires_at: Option<i64>,
 }
+
+/// Outcome of checking a signed URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignedUrlVerification {
+    /// The URL grants the request.
+    Valid {
+        /// File the URL grants access to.
+        file_id: String,
+        /// Expiration timestamp.
+        expires_at: Option<i64>,
+    },
+    /// The URL does not grant the request.
+    Invalid {
+        /// Why: `malformed`, `bad_signature`, `expired`,
+        /// `method_not_allowed` or `ip_mismatch`.
+        reason: String,
+    },
+}
+
+/// Storage used by a tenant.
+#[derive(Debug, Clone)]
+pub struct StorageUsage {
+    /// Tenant ID.
+    pub tenant_id: String,
+    /// Bytes stored.
+    pub used_bytes: i64,
+    /// Files stored.
+    pub file_count: i64,
+    /// Soft limit in bytes, if any.
+    pub soft_limit: Option<i64>,
+    /// Hard limit in bytes, if any.
+    pub hard_limit: Option<i64>,
+    /// Whether usage is past the soft limit.
+    pub over_soft_limit: bool,
+}
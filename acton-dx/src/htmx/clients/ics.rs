@@ -0,0 +1,295 @@
+//! iCalendar meeting invites for outgoing email.
+
+use super::email::EmailAddr;
+use chrono::{DateTime, Utc};
+
+/// Longest content line in octets before folding (RFC 5545 §3.1).
+const MAX_LINE_OCTETS: usize = 75;
+
+/// A meeting invite sent with an email.
+///
+/// Rendered as a `text/calendar; method=REQUEST` part, so mail clients show
+/// accept/decline buttons instead of an opaque attachment. Send an update by
+/// reusing the `uid` with a higher `sequence`.
+#[derive(Debug, Clone)]
+pub struct IcsEvent {
+    /// Globally unique event ID, stable across updates.
+    pub uid: String,
+    /// Event title.
+    pub summary: String,
+    /// Start time.
+    pub start: DateTime<Utc>,
+    /// End time.
+    pub end: DateTime<Utc>,
+    /// Event description.
+    pub description: Option<String>,
+    /// Location or dial-in.
+    pub location: Option<String>,
+    /// Link to the event in the application.
+    pub url: Option<String>,
+    /// Organizer; defaults to the email's sender.
+    pub organizer: Option<EmailAddr>,
+    /// Attendees; default to the email's `to` and `cc` recipients.
+    pub attendees: Vec<EmailAddr>,
+    /// Revision number; increase it for every update.
+    pub sequence: u32,
+}
+
+impl IcsEvent {
+    /// Create an invite.
+    #[must_use]
+    pub fn new(
+        uid: impl Into<String>,
+        summary: impl Into<String>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            uid: uid.into(),
+            summary: summary.into(),
+            start,
+            end,
+            description: None,
+            location: None,
+            url: None,
+            organizer: None,
+            attendees: Vec::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Set the description.
+    #[must_use]
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the location.
+    #[must_use]
+    pub fn location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Set the link to the event.
+    #[must_use]
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the organizer.
+    #[must_use]
+    pub fn organizer(mut self, email: impl Into<String>, name: Option<String>) -> Self {
+        self.organizer = Some(EmailAddr {
+            email: email.into(),
+            name,
+        });
+        self
+    }
+
+    /// Add an attendee.
+    #[must_use]
+    pub fn attendee(mut self, email: impl Into<String>, name: Option<String>) -> Self {
+        self.attendees.push(EmailAddr {
+            email: email.into(),
+            name,
+        });
+        self
+    }
+
+    /// Set the revision number.
+    #[must_use]
+    pub const fn sequence(mut self, sequence: u32) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Render the `METHOD:REQUEST` calendar.
+    ///
+    /// `organizer` and `attendees` are used when the event sets none.
+    #[must_use]
+    pub fn render(
+        &self,
+        organizer: &EmailAddr,
+        attendees: &[EmailAddr],
+        now: DateTime<Utc>,
+    ) -> String {
+        let organizer = self.organizer.as_ref().unwrap_or(organizer);
+        let attendees = if self.attendees.is_empty() {
+            attendees
+        } else {
+            &self.attendees
+        };
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "PRODID:-//Acton DX//Email//EN".to_string(),
+            "VERSION:2.0".to_string(),
+            "CALSCALE:GREGORIAN".to_string(),
+            "METHOD:REQUEST".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", escape_text(&self.uid)),
+            format!("DTSTAMP:{}", timestamp(now)),
+            format!("DTSTART:{}", timestamp(self.start)),
+            format!("DTEND:{}", timestamp(self.end)),
+            format!("SEQUENCE:{}", self.sequence),
+            format!("SUMMARY:{}", escape_text(&self.summary)),
+        ];
+        if let Some(description) = &self.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        if let Some(location) = &self.location {
+            lines.push(format!("LOCATION:{}", escape_text(location)));
+        }
+        if let Some(url) = &self.url {
+            lines.push(format!("URL:{url}"));
+        }
+        lines.push(format!(
+            "ORGANIZER{}:mailto:{}",
+            common_name(organizer),
+            organizer.email
+        ));
+        for attendee in attendees {
+            lines.push(format!(
+                "ATTENDEE{};ROLE=REQ-PARTICIPANT;PARTSTAT=NEEDS-ACTION;RSVP=TRUE:mailto:{}",
+                common_name(attendee),
+                attendee.email
+            ));
+        }
+        lines.push("STATUS:CONFIRMED".to_string());
+        lines.push("END:VEVENT".to_string());
+        lines.push("END:VCALENDAR".to_string());
+
+        let mut ics = String::new();
+        for line in &lines {
+            fold(&mut ics, line);
+        }
+        ics
+    }
+}
+
+/// UTC date-time such as `20250301T140000Z`.
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// `;CN=...` parameter for an address with a name.
+fn common_name(addr: &EmailAddr) -> String {
+    addr.name.as_ref().map_or_else(String::new, |name| {
+        // Parameter values cannot contain quotes; quoting covers `:;,`
+        format!(";CN=\"{}\"", name.replace('"', "'"))
+    })
+}
+
+/// Escape a TEXT value.
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+/// Append `line` folded into 75-octet lines, each ending in CRLF.
+fn fold(out: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            // The leading space counts towards the next line
+            octets = 1;
+        }
+        out.push(c);
+        octets += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn addr(email: &str, name: Option<&str>) -> EmailAddr {
+        EmailAddr {
+            email: email.to_string(),
+            name: name.map(str::to_string),
+        }
+    }
+
+    fn event() -> IcsEvent {
+        IcsEvent::new(
+            "meeting-7@app.example.com",
+            "Planning; Q3, roadmap",
+            Utc.with_ymd_and_hms(2025, 3, 1, 14, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2025, 3, 1, 15, 0, 0).unwrap(),
+        )
+        .description("Agenda:\nReview goals")
+        .location("Room 4")
+    }
+
+    #[test]
+    fn test_render_request() {
+        let now = Utc.with_ymd_and_hms(2025, 2, 20, 9, 30, 0).unwrap();
+        let ics = event().render(
+            &addr("owner@example.com", Some("Doe, Jane")),
+            &[addr("ada@example.com", None)],
+            now,
+        );
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        let ics = ics.replace("\r\n ", "");
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        for line in [
+            "METHOD:REQUEST",
+            "UID:meeting-7@app.example.com",
+            "DTSTAMP:20250220T093000Z",
+            "DTSTART:20250301T140000Z",
+            "DTEND:20250301T150000Z",
+            "SEQUENCE:0",
+            "SUMMARY:Planning\\; Q3\\, roadmap",
+            "DESCRIPTION:Agenda:\\nReview goals",
+            "LOCATION:Room 4",
+            "ORGANIZER;CN=\"Doe, Jane\":mailto:owner@example.com",
+            "ATTENDEE;ROLE=REQ-PARTICIPANT;PARTSTAT=NEEDS-ACTION;RSVP=TRUE:mailto:ada@example.com",
+        ] {
+            assert!(ics.contains(&format!("\r\n{line}\r\n")), "missing {line}");
+        }
+    }
+
+    #[test]
+    fn test_event_people_override_defaults() {
+        let now = Utc::now();
+        let ics = event()
+            .organizer("host@example.com", None)
+            .attendee("bob@example.com", Some("Bob".to_string()))
+            .sequence(2)
+            .render(
+                &addr("owner@example.com", None),
+                &[addr("ada@example.com", None)],
+                now,
+            );
+
+        assert!(ics.contains("ORGANIZER:mailto:host@example.com"));
+        assert!(ics.contains("ATTENDEE;CN=\"Bob\";"));
+        assert!(!ics.contains("ada@example.com"));
+        assert!(ics.contains("SEQUENCE:2"));
+    }
+
+    #[test]
+    fn test_long_lines_are_folded() {
+        let mut out = String::new();
+        let line = format!("DESCRIPTION:{}", "é".repeat(60));
+        fold(&mut out, &line);
+
+        for physical in out.split("\r\n").filter(|l| !l.is_empty()) {
+            assert!(physical.len() <= MAX_LINE_OCTETS);
+        }
+        let unfolded = out.replace("\r\n ", "");
+        assert_eq!(unfolded, format!("{line}\r\n"));
+    }
+}
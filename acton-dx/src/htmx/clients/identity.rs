@@ -0,0 +1,97 @@
+//! Identity tokens attached to outgoing service calls.
+//!
+//! [`IdentityToken::scope`] runs a future with the signed-in user's token in
+//! task-local storage; every [`DataClient`](super::DataClient) and
+//! [`FileClient`](super::FileClient) call issued inside it carries the token
+//! as `authorization: Bearer <token>` metadata. The data and file services
+//! verify it with the auth service and authorize the user themselves. The
+//! [`IdentityTokens`](crate::htmx::middleware::IdentityTokens) middleware
+//! mints a token for each request from the user's session.
+
+use super::error::ClientError;
+use std::future::Future;
+use tonic::metadata::{Ascii, MetadataValue};
+
+pub use acton_dx_proto::identity::IDENTITY_METADATA_KEY;
+
+tokio::task_local! {
+    static IDENTITY_TOKEN: IdentityToken;
+}
+
+/// An access token identifying the user a call is made for
+#[derive(Clone)]
+pub struct IdentityToken {
+    header: MetadataValue<Ascii>,
+}
+
+impl std::fmt::Debug for IdentityToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdentityToken").finish_non_exhaustive()
+    }
+}
+
+impl IdentityToken {
+    /// Wrap an access token minted by the auth service
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the token cannot be sent as metadata.
+    pub fn new(access_token: &str) -> Result<Self, ClientError> {
+        let mut header: MetadataValue<Ascii> = format!("Bearer {access_token}")
+            .parse()
+            .map_err(|_| ClientError::RequestFailed("malformed identity token".to_string()))?;
+        header.set_sensitive(true);
+        Ok(Self { header })
+    }
+
+    /// Run `future` with service calls carrying this token
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        IDENTITY_TOKEN.scope(self, future).await
+    }
+
+    /// Token in scope for the current task, if any
+    #[must_use]
+    pub fn current() -> Option<Self> {
+        IDENTITY_TOKEN.try_with(Clone::clone).ok()
+    }
+
+    /// Add the token in scope, if any, to an outgoing request
+    pub(crate) fn attach<T>(request: &mut tonic::Request<T>) {
+        if let Some(token) = Self::current() {
+            request
+                .metadata_mut()
+                .insert(IDENTITY_METADATA_KEY, token.header);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_attach_only_in_scope() {
+        let mut outside = tonic::Request::new(());
+        IdentityToken::attach(&mut outside);
+        assert!(outside.metadata().get(IDENTITY_METADATA_KEY).is_none());
+
+        let token = IdentityToken::new("eyJ.abc.def").unwrap();
+        let inside = token
+            .scope(async {
+                let mut request = tonic::Request::new(());
+                IdentityToken::attach(&mut request);
+                request
+            })
+            .await;
+        assert_eq!(
+            inside.metadata().get(IDENTITY_METADATA_KEY).unwrap(),
+            "Bearer eyJ.abc.def"
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsendable_tokens() {
+        assert!(IdentityToken::new("line\nbreak").is_err());
+        assert!(!format!("{:?}", IdentityToken::new("secret").unwrap()).contains("secret"));
+    }
+}
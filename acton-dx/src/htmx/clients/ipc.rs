@@ -52,7 +52,7 @@ use super::transport::IpcTransportConfig;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -67,10 +67,15 @@ mod msg_type {
     pub const REQUEST: u8 = 0x01;
     pub const RESPONSE: u8 = 0x02;
     pub const ERROR: u8 = 0x03;
+    #[allow(dead_code)]
     pub const HEARTBEAT: u8 = 0x04;
+    #[allow(dead_code)]
     pub const PUSH: u8 = 0x05;
+    #[allow(dead_code)]
     pub const SUBSCRIBE: u8 = 0x06;
+    #[allow(dead_code)]
     pub const UNSUBSCRIBE: u8 = 0x07;
+    #[allow(dead_code)]
     pub const DISCOVERY: u8 = 0x08;
 }
 
@@ -103,7 +108,7 @@ pub struct IpcEnvelope {
     pub response_timeout_ms: u64,
 }
 
-fn default_timeout() -> u64 {
+const fn default_timeout() -> u64 {
     30_000
 }
 
@@ -244,7 +249,6 @@ impl IpcClientConfig {
 pub struct IpcClient {
     config: IpcClientConfig,
     stream: Arc<Mutex<Option<UnixStream>>>,
-    request_counter: AtomicU64,
 }
 
 impl IpcClient {
@@ -254,7 +258,6 @@ impl IpcClient {
         Self {
             config,
             stream: Arc::new(Mutex::new(None)),
-            request_counter: AtomicU64::new(0),
         }
     }
 
@@ -291,6 +294,7 @@ impl IpcClient {
                         "IPC client connected"
                     );
                     *stream_guard = Some(stream);
+                    drop(stream_guard);
                     return Ok(());
                 }
                 Err(e) => {
@@ -306,9 +310,7 @@ impl IpcClient {
         }
 
         Err(ClientError::ConnectionFailed(
-            last_error
-                .map(|e| e.to_string())
-                .unwrap_or_else(|| "Unknown error".to_string()),
+            last_error.map_or_else(|| "Unknown error".to_string(), |e| e.to_string()),
         ))
     }
 
@@ -333,6 +335,7 @@ impl IpcClient {
             .ok_or_else(|| ClientError::ConnectionFailed("Not connected".to_string()))?;
 
         write_frame(stream, msg_type::REQUEST, &envelope).await?;
+        drop(stream_guard);
 
         Ok(())
     }
@@ -351,7 +354,7 @@ impl IpcClient {
         self.ensure_connected().await?;
 
         let envelope = IpcEnvelope::new_request(target, message_type, payload)
-            .with_timeout(self.config.timeout.as_millis() as u64);
+            .with_timeout(u64::try_from(self.config.timeout.as_millis()).unwrap_or(u64::MAX));
 
         let mut stream_guard = self.stream.lock().await;
         let stream = stream_guard
@@ -367,6 +370,7 @@ impl IpcClient {
         })
         .await
         .map_err(|_| ClientError::Timeout)??;
+        drop(stream_guard);
 
         Ok(response)
     }
@@ -397,35 +401,28 @@ impl IpcClient {
     pub fn socket_exists(&self) -> bool {
         self.config.socket_path.exists()
     }
-
-    /// Generate a unique correlation ID for requests.
-    fn next_correlation_id(&self) -> String {
-        let counter = self.request_counter.fetch_add(1, Ordering::SeqCst);
-        format!("req_{counter:016x}")
-    }
 }
 
 // ============================================================================
 // Wire Protocol Implementation
 // ============================================================================
 
-/// Write a framed message to the stream.
-async fn write_frame<T: Serialize>(
-    stream: &mut UnixStream,
-    msg_type: u8,
-    payload: &T,
-) -> Result<(), ClientError> {
+/// Encode a framed message, length prefix included.
+fn encode_frame<T: Serialize>(msg_type: u8, payload: &T) -> Result<Vec<u8>, ClientError> {
     let payload_bytes = serde_json::to_vec(payload)
         .map_err(|e| ClientError::SerializationError(e.to_string()))?;
 
     // Calculate frame length (excludes the 4-byte length field itself)
     let frame_len = 3 + payload_bytes.len(); // version + msg_type + format + payload
+    let frame_len_bytes = u32::try_from(frame_len)
+        .map_err(|_| ClientError::SerializationError("Payload too large".to_string()))?
+        .to_be_bytes();
 
     // Build the frame
     let mut frame = Vec::with_capacity(4 + frame_len);
 
     // Frame length (4 bytes, big-endian)
-    frame.extend_from_slice(&(frame_len as u32).to_be_bytes());
+    frame.extend_from_slice(&frame_len_bytes);
 
     // Protocol version (1 byte)
     frame.push(PROTOCOL_VERSION);
@@ -439,6 +436,17 @@ async fn write_frame<T: Serialize>(
     // Payload
     frame.extend_from_slice(&payload_bytes);
 
+    Ok(frame)
+}
+
+/// Write a framed message to the stream.
+async fn write_frame<T: Serialize + Sync>(
+    stream: &mut UnixStream,
+    msg_type: u8,
+    payload: &T,
+) -> Result<(), ClientError> {
+    let frame = encode_frame(msg_type, payload)?;
+
     // Write the entire frame
     stream
         .write_all(&frame)
@@ -468,12 +476,6 @@ async fn read_response(
         )));
     }
 
-    if frame_len < 3 {
-        return Err(ClientError::ResponseError(
-            "Frame too small".to_string(),
-        ));
-    }
-
     // Read the rest of the frame
     let mut frame = vec![0u8; frame_len];
     stream
@@ -481,10 +483,21 @@ async fn read_response(
         .await
         .map_err(|e| ClientError::IoError(e.to_string()))?;
 
+    decode_response(&frame)
+}
+
+/// Decode a response frame, without its 4-byte length prefix.
+fn decode_response(frame: &[u8]) -> Result<IpcResponse, ClientError> {
+    if frame.len() < 3 {
+        return Err(ClientError::ResponseError(
+            "Frame too small".to_string(),
+        ));
+    }
+
     // Parse header
-    let _version = frame[0];
+    // frame[0] is the protocol version and frame[2] the payload format;
+    // responses are always JSON
     let msg_type = frame[1];
-    let _format = frame[2];
 
     // Parse payload
     let payload_bytes = &frame[3..];
@@ -509,12 +522,37 @@ fn generate_correlation_id() -> String {
     let counter = COUNTER.fetch_add(1, Ordering::SeqCst);
     let timestamp = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_millis())
-        .unwrap_or(0);
+        .map_or(0, |d| d.as_millis());
 
     format!("req_{timestamp:x}_{counter:08x}")
 }
 
+/// Framing entry points for the hot path benchmarks; not a stable API.
+#[cfg(feature = "bench-internals")]
+#[doc(hidden)]
+pub mod bench {
+    use super::{ClientError, IpcEnvelope, IpcResponse};
+
+    /// Encode a request envelope as a complete frame, length prefix included.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the envelope cannot be serialized.
+    pub fn encode_request(envelope: &IpcEnvelope) -> Result<Vec<u8>, ClientError> {
+        super::encode_frame(super::msg_type::REQUEST, envelope)
+    }
+
+    /// Decode a response frame, without its 4-byte length prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the frame is truncated, is not a response, or its
+    /// payload cannot be deserialized.
+    pub fn decode_response(frame: &[u8]) -> Result<IpcResponse, ClientError> {
+        super::decode_response(frame)
+    }
+}
+
 // ============================================================================
 // Service-Specific IPC Clients
 // ============================================================================
@@ -531,7 +569,7 @@ pub struct IpcAuthClient {
 impl IpcAuthClient {
     /// Create a new auth client from a shared IPC client.
     #[must_use]
-    pub fn new(client: Arc<IpcClient>) -> Self {
+    pub const fn new(client: Arc<IpcClient>) -> Self {
         Self { client }
     }
 
@@ -805,6 +843,11 @@ mod tests {
 
     #[test]
     fn test_ipc_response_extract_success() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct TestPayload {
+            value: i32,
+        }
+
         let response = IpcResponse {
             correlation_id: "req_001".to_string(),
             success: true,
@@ -813,11 +856,6 @@ mod tests {
             payload: Some(serde_json::json!({"value": 42})),
         };
 
-        #[derive(Debug, Deserialize, PartialEq)]
-        struct TestPayload {
-            value: i32,
-        }
-
         let extracted: Result<TestPayload, _> = response.extract();
         assert!(extracted.is_ok());
         assert_eq!(extracted.unwrap().value, 42);
@@ -825,6 +863,12 @@ mod tests {
 
     #[test]
     fn test_ipc_response_extract_error() {
+        #[derive(Debug, Deserialize)]
+        struct TestPayload {
+            #[allow(dead_code)]
+            value: i32,
+        }
+
         let response = IpcResponse {
             correlation_id: "req_001".to_string(),
             success: false,
@@ -833,12 +877,6 @@ mod tests {
             payload: None,
         };
 
-        #[derive(Debug, Deserialize)]
-        struct TestPayload {
-            #[allow(dead_code)]
-            value: i32,
-        }
-
         let extracted: Result<TestPayload, _> = response.extract();
         assert!(extracted.is_err());
 
@@ -871,4 +909,38 @@ mod tests {
         assert_eq!(config.retry_delay, Duration::from_millis(100));
         assert_eq!(config.max_message_size, 1_048_576);
     }
+
+    #[test]
+    fn test_encode_request_frame() {
+        let envelope =
+            IpcEnvelope::new_request("auth_service", "ValidateSession", serde_json::json!({}));
+        let frame = encode_frame(msg_type::REQUEST, &envelope).unwrap();
+
+        let frame_len = u32::from_be_bytes(frame[..4].try_into().unwrap()) as usize;
+        assert_eq!(frame_len, frame.len() - 4);
+        assert_eq!(&frame[4..7], &[PROTOCOL_VERSION, msg_type::REQUEST, format::JSON]);
+
+        let decoded: IpcEnvelope = serde_json::from_slice(&frame[7..]).unwrap();
+        assert_eq!(decoded.correlation_id, envelope.correlation_id);
+    }
+
+    #[test]
+    fn test_decode_response_frame() {
+        let response = IpcResponse {
+            correlation_id: "req_001".to_string(),
+            success: true,
+            error: None,
+            error_code: None,
+            payload: Some(serde_json::json!({"valid": true})),
+        };
+        let frame = encode_frame(msg_type::RESPONSE, &response).unwrap();
+
+        let decoded = decode_response(&frame[4..]).unwrap();
+        assert_eq!(decoded.correlation_id, "req_001");
+        assert!(decoded.is_success());
+
+        assert!(decode_response(&frame[4..6]).is_err());
+        let request = encode_frame(msg_type::REQUEST, &response).unwrap();
+        assert!(decode_response(&request[4..]).is_err());
+    }
 }
@@ -0,0 +1,359 @@
+//! JSON representations of service messages.
+//!
+//! Admin pages and REST endpoints that expose service data convert it with
+//! [`to_value`] and [`from_value`] instead of mapping every message by hand.
+//! The JSON forms are plain serde structs:
+//!
+//! - Unix-second timestamps become RFC 3339 strings, and `null` when unset
+//! - [`Row`] becomes a flat object of column values; bytes are base64
+//! - Secrets are left out: a [`User`]'s password hash and a [`Session`]'s
+//!   CSRF token are never serialized and come back empty
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_dx::htmx::clients::json;
+//!
+//! let user = auth.get_user(42).await?;
+//! Json(json::to_value(&user)?)
+//! ```
+
+use super::file::StoredFileInfo;
+use acton_dx_proto::auth::v1::{Session, User};
+use acton_dx_proto::data::v1::{value::Value as ValueKind, Row, Value};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A message with a JSON representation.
+pub trait JsonMessage: Sized {
+    /// The JSON form of the message.
+    type Json: Serialize + DeserializeOwned;
+
+    /// Convert to the JSON form.
+    fn to_json(&self) -> Self::Json;
+
+    /// Convert from the JSON form. Fields the JSON form leaves out are empty.
+    fn from_json(json: Self::Json) -> Self;
+}
+
+/// Serialize a message to JSON.
+///
+/// # Errors
+///
+/// Returns an error if serialization fails.
+pub fn to_value<M: JsonMessage>(message: &M) -> serde_json::Result<serde_json::Value> {
+    serde_json::to_value(message.to_json())
+}
+
+/// Deserialize a message from JSON.
+///
+/// # Errors
+///
+/// Returns an error if the JSON does not match the message's JSON form.
+pub fn from_value<M: JsonMessage>(value: serde_json::Value) -> serde_json::Result<M> {
+    serde_json::from_value(value).map(M::from_json)
+}
+
+/// JSON form of a [`Session`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionJson {
+    /// Session ID.
+    pub session_id: String,
+    /// Signed-in user, if any.
+    pub user_id: Option<i64>,
+    /// Signed-in user's email.
+    pub user_email: Option<String>,
+    /// Signed-in user's name.
+    pub user_name: Option<String>,
+    /// Session data.
+    #[serde(default)]
+    pub data: HashMap<String, String>,
+    /// When the session was created.
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the session expires.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// When the session can no longer be refreshed.
+    #[serde(default)]
+    pub absolute_expires_at: Option<DateTime<Utc>>,
+}
+
+impl JsonMessage for Session {
+    type Json = SessionJson;
+
+    fn to_json(&self) -> SessionJson {
+        SessionJson {
+            session_id: self.session_id.clone(),
+            user_id: self.user_id,
+            user_email: self.user_email.clone(),
+            user_name: self.user_name.clone(),
+            data: self.data.clone(),
+            created_at: timestamp(self.created_at),
+            expires_at: timestamp(self.expires_at),
+            absolute_expires_at: timestamp(self.absolute_expires_at),
+        }
+    }
+
+    fn from_json(json: SessionJson) -> Self {
+        Self {
+            session_id: json.session_id,
+            user_id: json.user_id,
+            user_email: json.user_email,
+            user_name: json.user_name,
+            data: json.data,
+            csrf_token: String::new(),
+            created_at: unix_seconds(json.created_at),
+            expires_at: unix_seconds(json.expires_at),
+            absolute_expires_at: unix_seconds(json.absolute_expires_at),
+        }
+    }
+}
+
+/// JSON form of a [`User`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserJson {
+    /// User ID.
+    pub id: i64,
+    /// Email address.
+    pub email: String,
+    /// Display name.
+    pub name: String,
+    /// When the user was created.
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the user was last updated.
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl JsonMessage for User {
+    type Json = UserJson;
+
+    fn to_json(&self) -> UserJson {
+        UserJson {
+            id: self.id,
+            email: self.email.clone(),
+            name: self.name.clone(),
+            created_at: timestamp(self.created_at),
+            updated_at: timestamp(self.updated_at),
+        }
+    }
+
+    fn from_json(json: UserJson) -> Self {
+        Self {
+            id: json.id,
+            email: json.email,
+            name: json.name,
+            password_hash: String::new(),
+            created_at: unix_seconds(json.created_at),
+            updated_at: unix_seconds(json.updated_at),
+        }
+    }
+}
+
+/// JSON form of a [`StoredFileInfo`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileJson {
+    /// File ID.
+    pub id: String,
+    /// Original filename.
+    pub filename: String,
+    /// MIME content type.
+    pub content_type: String,
+    /// File size in bytes.
+    pub size: i64,
+    /// SHA-256 checksum.
+    pub checksum: String,
+    /// When the file was stored.
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the file was last updated.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Custom metadata.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl JsonMessage for StoredFileInfo {
+    type Json = FileJson;
+
+    fn to_json(&self) -> FileJson {
+        FileJson {
+            id: self.id.clone(),
+            filename: self.filename.clone(),
+            content_type: self.content_type.clone(),
+            size: self.size,
+            checksum: self.checksum.clone(),
+            created_at: timestamp(self.created_at),
+            updated_at: timestamp(self.updated_at),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    fn from_json(json: FileJson) -> Self {
+        Self {
+            id: json.id,
+            filename: json.filename,
+            content_type: json.content_type,
+            size: json.size,
+            checksum: json.checksum,
+            created_at: unix_seconds(json.created_at),
+            updated_at: unix_seconds(json.updated_at),
+            metadata: json.metadata,
+        }
+    }
+}
+
+impl JsonMessage for Row {
+    type Json = serde_json::Map<String, serde_json::Value>;
+
+    fn to_json(&self) -> Self::Json {
+        self.columns
+            .iter()
+            .map(|(name, value)| (name.clone(), value_to_json(value)))
+            .collect()
+    }
+
+    fn from_json(json: Self::Json) -> Self {
+        Self {
+            columns: json
+                .into_iter()
+                .map(|(name, value)| (name, value_from_json(value)))
+                .collect(),
+        }
+    }
+}
+
+/// Convert a column value to JSON. Bytes become a base64 string.
+#[must_use]
+pub fn value_to_json(value: &Value) -> serde_json::Value {
+    match &value.value {
+        Some(ValueKind::BoolValue(v)) => serde_json::Value::from(*v),
+        Some(ValueKind::IntValue(v)) => serde_json::Value::from(*v),
+        Some(ValueKind::FloatValue(v)) => serde_json::Value::from(*v),
+        Some(ValueKind::StringValue(v)) => serde_json::Value::from(v.as_str()),
+        Some(ValueKind::BytesValue(v)) => serde_json::Value::from(STANDARD.encode(v)),
+        Some(ValueKind::NullValue(_)) | None => serde_json::Value::Null,
+    }
+}
+
+/// Convert JSON to a column value.
+///
+/// Whole numbers become integers and other numbers floats. Strings stay
+/// strings, since base64 bytes cannot be told apart from text; arrays and
+/// objects are stored as their JSON text.
+#[must_use]
+pub fn value_from_json(json: serde_json::Value) -> Value {
+    let value = match json {
+        serde_json::Value::Null => ValueKind::NullValue(true),
+        serde_json::Value::Bool(v) => ValueKind::BoolValue(v),
+        serde_json::Value::Number(n) => n.as_i64().map_or_else(
+            || ValueKind::FloatValue(n.as_f64().unwrap_or_default()),
+            ValueKind::IntValue,
+        ),
+        serde_json::Value::String(v) => ValueKind::StringValue(v),
+        other => ValueKind::StringValue(other.to_string()),
+    };
+    Value { value: Some(value) }
+}
+
+/// Timestamp for Unix seconds; zero means unset.
+fn timestamp(seconds: i64) -> Option<DateTime<Utc>> {
+    (seconds != 0)
+        .then(|| DateTime::from_timestamp(seconds, 0))
+        .flatten()
+}
+
+fn unix_seconds(timestamp: Option<DateTime<Utc>>) -> i64 {
+    timestamp.map_or(0, |timestamp| timestamp.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trip_drops_csrf_token() {
+        let session = Session {
+            session_id: "s1".to_string(),
+            user_id: Some(7),
+            user_email: None,
+            user_name: Some("Ada".to_string()),
+            data: HashMap::from([("theme".to_string(), "dark".to_string())]),
+            csrf_token: "secret".to_string(),
+            created_at: 1_700_000_000,
+            expires_at: 0,
+            absolute_expires_at: 0,
+        };
+
+        let json = to_value(&session).unwrap();
+        assert_eq!(json["created_at"], "2023-11-14T22:13:20Z");
+        assert!(json["expires_at"].is_null());
+        assert!(json.get("csrf_token").is_none());
+
+        let back: Session = from_value(json).unwrap();
+        assert_eq!(
+            back,
+            Session {
+                csrf_token: String::new(),
+                ..session
+            }
+        );
+    }
+
+    #[test]
+    fn test_user_never_exposes_password_hash() {
+        let user = User {
+            id: 1,
+            email: "ada@example.com".to_string(),
+            name: "Ada".to_string(),
+            password_hash: "$argon2id$...".to_string(),
+            created_at: 1_700_000_000,
+            updated_at: 1_700_000_000,
+        };
+
+        let json = to_value(&user).unwrap();
+        assert!(!json.to_string().contains("argon2"));
+        let back: User = from_value(json).unwrap();
+        assert_eq!(back.email, "ada@example.com");
+        assert!(back.password_hash.is_empty());
+    }
+
+    #[test]
+    fn test_row_values() {
+        let row = Row {
+            columns: HashMap::from([
+                ("id".to_string(), value_from_json(serde_json::json!(3))),
+                ("price".to_string(), value_from_json(serde_json::json!(9.5))),
+                (
+                    "name".to_string(),
+                    value_from_json(serde_json::json!("tea")),
+                ),
+                ("note".to_string(), value_from_json(serde_json::Value::Null)),
+                (
+                    "blob".to_string(),
+                    Value {
+                        value: Some(ValueKind::BytesValue(vec![1, 2, 3])),
+                    },
+                ),
+            ]),
+        };
+
+        let json = to_value(&row).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "id": 3,
+                "price": 9.5,
+                "name": "tea",
+                "note": null,
+                "blob": "AQID",
+            })
+        );
+
+        let back: Row = from_value(serde_json::json!({"id": 3, "tags": ["a"]})).unwrap();
+        assert_eq!(back.columns["id"].value, Some(ValueKind::IntValue(3)));
+        assert_eq!(
+            back.columns["tags"].value,
+            Some(ValueKind::StringValue(r#"["a"]"#.to_string()))
+        );
+    }
+}
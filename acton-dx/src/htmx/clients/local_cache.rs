@@ -0,0 +1,340 @@
+//! In-process cache tier for [`CacheClient`](super::CacheClient).
+//!
+//! A bounded LRU of key-value entries kept in front of the cache service, so
+//! hot keys are read without a network round trip. Entries live no longer
+//! than the key's remaining TTL in Redis, capped at
+//! [`LocalCache::with_max_ttl`], which also bounds how stale an entry can be
+//! if an invalidation is missed.
+//!
+//! Other instances' writes evict local entries through one of two
+//! [`Invalidation`] sources:
+//!
+//! - [`Invalidation::PubSub`]: clients with a local tier publish every
+//!   `set`, `delete`, `increment` and prefix invalidation on a shared
+//!   channel. Writes made by other means are only picked up when entries
+//!   expire.
+//! - [`Invalidation::Keyspace`]: Redis keyspace notifications, which see
+//!   every write including expiries and evictions. Redis must run with
+//!   `notify-keyspace-events` including `K`, `g`, `$`, `x` and `e`
+//!   (e.g. `Kg$xe`), and every instance receives a message for every write.
+
+use acton_dx_proto::cache::v1::PubSubMessage;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Channel carrying single-key invalidations, outside any namespace.
+pub const INVALIDATION_CHANNEL: &str = "acton:cache:invalidate";
+
+/// Channel carrying prefix invalidations, outside any namespace.
+pub const PREFIX_INVALIDATION_CHANNEL: &str = "acton:cache:invalidate-prefix";
+
+/// How a [`LocalCache`] learns about writes from other instances
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Invalidation {
+    /// Messages published by clients with a local tier
+    #[default]
+    PubSub,
+    /// Redis keyspace notifications
+    Keyspace,
+}
+
+/// Hit and miss counts for a [`LocalCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LocalCacheStats {
+    /// Reads served from memory
+    pub hits: u64,
+    /// Reads that went to the cache service
+    pub misses: u64,
+    /// Entries held
+    pub entries: usize,
+}
+
+/// Bounded in-process LRU in front of the cache service
+///
+/// Shared by every clone of the [`CacheClient`](super::CacheClient) it is
+/// attached to, including clones with other namespaces.
+pub struct LocalCache {
+    capacity: usize,
+    max_ttl: Duration,
+    invalidation: Invalidation,
+    /// Marks this cache's own invalidation messages
+    origin: String,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl std::fmt::Debug for LocalCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalCache")
+            .field("capacity", &self.capacity)
+            .field("max_ttl", &self.max_ttl)
+            .field("invalidation", &self.invalidation)
+            .field("entries", &self.entries.lock().map.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    /// Keys by last use, least recent first
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+struct Entry {
+    value: Vec<u8>,
+    expires_at: Instant,
+    used: u64,
+}
+
+impl Entries {
+    fn touch(&mut self, key: &str) -> u64 {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(entry) = self.map.get_mut(key) {
+            self.recency.remove(&entry.used);
+            entry.used = tick;
+            self.recency.insert(tick, key.to_string());
+        }
+        tick
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.map.remove(key) {
+            self.recency.remove(&entry.used);
+        }
+    }
+}
+
+impl LocalCache {
+    /// Hold up to `capacity` entries for at most a minute each
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            max_ttl: Duration::from_secs(60),
+            invalidation: Invalidation::default(),
+            origin: format!("{:016x}", rand::random::<u64>()),
+            entries: Mutex::new(Entries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Longest an entry is served from memory (default: one minute)
+    #[must_use]
+    pub const fn with_max_ttl(mut self, max_ttl: Duration) -> Self {
+        self.max_ttl = max_ttl;
+        self
+    }
+
+    /// How writes from other instances are noticed (default: pub/sub)
+    #[must_use]
+    pub const fn with_invalidation(mut self, invalidation: Invalidation) -> Self {
+        self.invalidation = invalidation;
+        self
+    }
+
+    /// The configured invalidation source
+    #[must_use]
+    pub const fn invalidation(&self) -> Invalidation {
+        self.invalidation
+    }
+
+    /// Hit and miss counts since creation
+    #[must_use]
+    pub fn stats(&self) -> LocalCacheStats {
+        LocalCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().map.len(),
+        }
+    }
+
+    /// Drop every entry
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock();
+        entries.map.clear();
+        entries.recency.clear();
+    }
+
+    /// A live entry, marking it recently used
+    pub(super) fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let value = {
+            let mut entries = self.entries.lock();
+            let value = match entries.map.get(key) {
+                Some(entry) if entry.expires_at > Instant::now() => Some(entry.value.clone()),
+                Some(_) => {
+                    entries.remove(key);
+                    None
+                }
+                None => None,
+            };
+            if value.is_some() {
+                entries.touch(key);
+            }
+            value
+        };
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Store a value that Redis holds for `ttl` (`None` for no expiry),
+    /// evicting the least recently used entry when full
+    pub(super) fn insert(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) {
+        let ttl = ttl.map_or(self.max_ttl, |ttl| ttl.min(self.max_ttl));
+        if ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        entries.remove(key);
+        while entries.map.len() >= self.capacity {
+            let Some((_, oldest)) = entries.recency.pop_first() else {
+                break;
+            };
+            entries.map.remove(&oldest);
+        }
+        let used = entries.touch(key);
+        entries.recency.insert(used, key.to_string());
+        entries.map.insert(
+            key.to_string(),
+            Entry {
+                value,
+                expires_at: Instant::now() + ttl,
+                used,
+            },
+        );
+    }
+
+    /// Drop one entry
+    pub(super) fn remove(&self, key: &str) {
+        self.entries.lock().remove(key);
+    }
+
+    /// Drop every entry whose key starts with `prefix`
+    pub(super) fn remove_prefix(&self, prefix: &str) {
+        let mut entries = self.entries.lock();
+        let keys: Vec<String> = entries
+            .map
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect();
+        for key in keys {
+            entries.remove(&key);
+        }
+    }
+
+    /// Payload announcing a write to `key` from this cache
+    pub(super) fn message(&self, key: &str) -> Vec<u8> {
+        format!("{} {key}", self.origin).into_bytes()
+    }
+
+    /// Apply an invalidation message, ignoring this cache's own
+    pub(super) fn apply(&self, message: &PubSubMessage) {
+        if self.invalidation == Invalidation::Keyspace {
+            self.remove(&message.channel);
+            return;
+        }
+        let Ok(payload) = std::str::from_utf8(&message.payload) else {
+            return;
+        };
+        let Some((origin, key)) = payload.split_once(' ') else {
+            return;
+        };
+        if origin == self.origin {
+            return;
+        }
+        match message.channel.as_str() {
+            INVALIDATION_CHANNEL => self.remove(key),
+            PREFIX_INVALIDATION_CHANNEL => self.remove_prefix(key),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(channel: &str, payload: &str) -> PubSubMessage {
+        PubSubMessage {
+            channel: channel.to_string(),
+            payload: payload.as_bytes().to_vec(),
+            pattern: None,
+        }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = LocalCache::new(2);
+        cache.insert("a", b"1".to_vec(), None);
+        cache.insert("b", b"2".to_vec(), None);
+        assert_eq!(cache.get("a").as_deref(), Some(&b"1"[..]));
+
+        cache.insert("c", b"3".to_vec(), None);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("c").is_some());
+
+        // Replacing a key doesn't evict another
+        cache.insert("c", b"4".to_vec(), None);
+        assert_eq!(cache.get("c").as_deref(), Some(&b"4"[..]));
+        assert!(cache.get("a").is_some());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (5, 1, 2));
+    }
+
+    #[test]
+    fn test_ttl_is_capped() {
+        let cache = LocalCache::new(10).with_max_ttl(Duration::from_secs(30));
+        cache.insert("expired", b"x".to_vec(), Some(Duration::ZERO));
+        assert!(cache.get("expired").is_none());
+
+        cache.insert("short", b"x".to_vec(), Some(Duration::from_millis(1)));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(cache.get("short").is_none());
+        assert_eq!(cache.stats().entries, 0);
+
+        let cache = LocalCache::new(10).with_max_ttl(Duration::ZERO);
+        cache.insert("never", b"x".to_vec(), None);
+        assert!(cache.get("never").is_none());
+    }
+
+    #[test]
+    fn test_applies_invalidations_from_other_instances() {
+        let cache = LocalCache::new(10);
+        for key in ["user:1", "user:2", "tenant:7:settings"] {
+            cache.insert(key, b"x".to_vec(), None);
+        }
+
+        // Own messages are ignored
+        cache.apply(&message(
+            INVALIDATION_CHANNEL,
+            std::str::from_utf8(&cache.message("user:1")).unwrap(),
+        ));
+        assert!(cache.get("user:1").is_some());
+
+        cache.apply(&message(INVALIDATION_CHANNEL, "0123456789abcdef user:1"));
+        assert!(cache.get("user:1").is_none());
+
+        cache.apply(&message(PREFIX_INVALIDATION_CHANNEL, "0123456789abcdef tenant:7:"));
+        assert!(cache.get("tenant:7:settings").is_none());
+        assert!(cache.get("user:2").is_some());
+
+        let keyspace = LocalCache::new(10).with_invalidation(Invalidation::Keyspace);
+        keyspace.insert("user:2", b"x".to_vec(), None);
+        keyspace.apply(&message("user:2", "set"));
+        assert!(keyspace.get("user:2").is_none());
+    }
+}
@@ -13,10 +13,14 @@
 //! - [`AuthClient`] - Authentication, sessions, passwords, CSRF tokens, and users (gRPC)
 //! - [`DataClient`] - Database queries, transactions, and migrations (gRPC)
 //! - [`CedarClient`] - Cedar-based authorization (gRPC)
-//! - [`CacheClient`] - Redis caching and rate limiting (gRPC)
+//! - [`CacheClient`] - Redis caching and rate limiting (gRPC), with an
+//!   optional in-process [`LocalCache`] tier
 //! - [`EmailClient`] - Email sending (gRPC)
 //! - [`FileClient`] - File storage and retrieval (gRPC)
 //!
+//! [`json`] converts sessions, users, rows and file info to and from plain
+//! JSON for admin pages and REST endpoints.
+//!
 //! ## IPC Clients
 //!
 //! - [`ipc::IpcAuthClient`] - Auth operations over IPC
@@ -97,22 +101,54 @@ mod data;
 mod email;
 mod error;
 mod file;
+mod ics;
+mod identity;
 pub mod ipc;
+pub mod json;
+mod local_cache;
+mod query_cache;
+mod query_log;
 mod registry;
 pub mod transport;
 
 pub use auth::AuthClient;
-pub use cache::{CacheClient, RateLimitResult};
-pub use cedar::{AuthorizationRequest, AuthorizationResult, CedarClient, ReloadResult, ValidationResult};
-pub use data::{DataClient, ExecuteResult, MigrationResult, PingResult};
-pub use email::{BatchSendResult, EmailAddr, EmailAttachment, EmailClient, EmailMessage, SendResult};
+pub use cache::{
+    jitter_ttl, CacheClient, CachePolicy, RateLimitResult, Subscription, TokenBucketResult,
+    ZRange, ZRangePage,
+};
+pub use cedar::{
+    ActivationResult, AuthorizationRequest, AuthorizationResult, CedarClient, PolicyActivation,
+    PolicyHistory, PolicyVersion, ReloadResult, SaveResult, ValidationResult,
+};
+pub use data::{
+    DataClient, ExecuteResult, MigrationResult, PingResult, RowStream, SESSION_VAR_METADATA_KEY,
+};
+pub use email::{
+    BatchSendResult, CapturedEmail, DeliveryState, DeliveryStatus, DevMailbox, EmailAddr,
+    EmailAttachment, EmailClient, EmailMessage, EmailStats, EmailStatsEntry, EmailTemplateSource,
+    PreviewResult, SendResult, SuppressedAddress, SuppressionPage,
+};
 pub use error::ClientError;
-pub use file::{DownloadResult, FileClient, ListResult, SignedUrlResult, StoredFileInfo, UploadResult};
+pub use file::{
+    DownloadResult, FileClient, ListResult, SignedUrlResult, SignedUrlVerification, StorageUsage,
+    StoredFileInfo, UploadResult,
+};
+pub use ics::IcsEvent;
+pub use identity::{IdentityToken, IDENTITY_METADATA_KEY};
+pub use local_cache::{
+    Invalidation, LocalCache, LocalCacheStats, INVALIDATION_CHANNEL, PREFIX_INVALIDATION_CHANNEL,
+};
+pub use query_cache::{tables_written, QueryCache, QUERY_CACHE_NAMESPACE};
+pub use query_log::{CapturedQuery, QueryLog};
 pub use registry::{ServiceRegistry, ServicesConfig};
 pub use transport::{
     FallbackConfig, GrpcTransportConfig, IpcTransportConfig, TransportConfig, TransportType,
 };
 
 // Re-export proto types that might be useful for users
-pub use acton_dx_proto::auth::v1::{FlashMessage, Session, User};
-pub use acton_dx_proto::data::v1::{MigrationInfo, Row, Value};
+pub use acton_dx_proto::auth::v1::{FlashMessage, ImportSessionsResponse, Session, SessionRecord, User};
+pub use acton_dx_proto::cache::v1::{PubSubMessage, ScoredMember};
+pub use acton_dx_proto::data::v1::{
+    ColumnSchema, ForeignKeySchema, IndexSchema, MigrationInfo, MigrationPlan, MigrationStep, Row,
+    TableSchema, Value,
+};
@@ -0,0 +1,303 @@
+//! Read-through cache for data-service queries.
+//!
+//! Results are stored in the cache service under a hash of the SQL, its
+//! parameters, and the current version of each tag. Invalidating a tag bumps
+//! its version, so every entry cached under the old version becomes
+//! unreachable at once and simply expires; no key scan is needed.
+//!
+//! Tags are table names by convention: [`DataClient`](super::DataClient)
+//! invalidates the tables an `Execute` statement writes to (see
+//! [`tables_written`]) so cached reads of those tables are never served stale
+//! after a write through the same client.
+
+use super::cache::CacheClient;
+use super::error::ClientError;
+use acton_dx_proto::data::v1::{QueryResponse, Row, Value};
+use parking_lot::Mutex;
+use prost::Message;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Namespace for cached results and tag versions
+pub const QUERY_CACHE_NAMESPACE: &str = "query";
+
+/// Read-through query cache shared by clones of a [`DataClient`](super::DataClient)
+#[derive(Debug, Clone)]
+pub struct QueryCache {
+    cache: CacheClient,
+    /// Tables written inside open transactions, invalidated again on commit
+    pending: Arc<Mutex<HashMap<String, HashSet<String>>>>,
+}
+
+impl QueryCache {
+    /// Cache results through `cache`, under the `query` namespace
+    #[must_use]
+    pub fn new(cache: CacheClient) -> Self {
+        Self {
+            cache: cache.with_namespace(QUERY_CACHE_NAMESPACE),
+            pending: Arc::default(),
+        }
+    }
+
+    /// Look up cached rows, returning the entry key for a later [`store`](Self::store)
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the cache service call fails.
+    pub async fn lookup(
+        &mut self,
+        sql: &str,
+        params: &[Value],
+        session_vars: &[(String, String)],
+        tags: &[&str],
+    ) -> Result<(String, Option<Vec<Row>>), ClientError> {
+        let mut versions = Vec::with_capacity(tags.len());
+        for tag in tags {
+            let version = self.cache.get_string(&tag_key(tag)).await?;
+            versions.push(version.unwrap_or_default());
+        }
+        let key = entry_key(sql, params, session_vars, tags, &versions);
+        let rows = self
+            .cache
+            .get(&key)
+            .await?
+            .and_then(|bytes| QueryResponse::decode(bytes.as_slice()).ok())
+            .map(|response| response.rows);
+        Ok((key, rows))
+    }
+
+    /// Store rows under a key returned by [`lookup`](Self::lookup)
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the cache service call fails.
+    pub async fn store(
+        &mut self,
+        key: &str,
+        rows: &[Row],
+        ttl: Duration,
+    ) -> Result<(), ClientError> {
+        let response = QueryResponse {
+            rows: rows.to_vec(),
+            rows_returned: i64::try_from(rows.len()).unwrap_or(i64::MAX),
+        };
+        let ttl = i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX).max(1);
+        self.cache
+            .set(key, &response.encode_to_vec(), Some(ttl))
+            .await?;
+        Ok(())
+    }
+
+    /// Invalidate every cached result tagged with any of `tags`
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the cache service call fails.
+    pub async fn invalidate<S: AsRef<str> + Sync>(
+        &mut self,
+        tags: &[S],
+    ) -> Result<(), ClientError> {
+        for tag in tags {
+            self.cache
+                .increment(&tag_key(tag.as_ref()), 1, None)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Remember tables written inside a transaction
+    pub fn record_pending(&self, transaction_id: &str, tables: Vec<String>) {
+        if tables.is_empty() {
+            return;
+        }
+        self.pending
+            .lock()
+            .entry(transaction_id.to_string())
+            .or_default()
+            .extend(tables);
+    }
+
+    /// Take the tables written inside a finished transaction
+    #[must_use]
+    pub fn take_pending(&self, transaction_id: &str) -> Vec<String> {
+        self.pending
+            .lock()
+            .remove(transaction_id)
+            .map(|tables| tables.into_iter().collect())
+            .unwrap_or_default()
+    }
+}
+
+fn tag_key(tag: &str) -> String {
+    format!("tag:{tag}")
+}
+
+/// Hash the statement, its parameters, session variables, and the tag
+/// versions into a cache key
+fn entry_key(
+    sql: &str,
+    params: &[Value],
+    session_vars: &[(String, String)],
+    tags: &[&str],
+    versions: &[String],
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(sql.as_bytes());
+    for param in params {
+        hasher.update([0]);
+        hasher.update(param.encode_to_vec());
+    }
+    for (name, value) in session_vars {
+        hasher.update([2]);
+        hasher.update(name.as_bytes());
+        hasher.update([b'=']);
+        hasher.update(value.as_bytes());
+    }
+    for (tag, version) in tags.iter().zip(versions) {
+        hasher.update([1]);
+        hasher.update(tag.as_bytes());
+        hasher.update([b'=']);
+        hasher.update(version.as_bytes());
+    }
+    format!("rows:{}", hex::encode(hasher.finalize()))
+}
+
+/// Tables a statement writes to, lowercased and without schema or quotes
+///
+/// Recognizes `INSERT INTO`, `REPLACE INTO`, `MERGE INTO`, `UPDATE`,
+/// `DELETE FROM`, `TRUNCATE`, `ALTER TABLE` and `DROP TABLE`, anywhere in
+/// the statement so writes inside CTEs are caught too. `ON CONFLICT DO
+/// UPDATE SET` is not mistaken for a write to a table named `set`.
+#[must_use]
+pub fn tables_written(sql: &str) -> Vec<String> {
+    let tokens: Vec<String> = sql
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == ';')
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut tables: Vec<String> = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let rest = &tokens[i + 1..];
+        let names: &[String] = match (token.as_str(), rest.first().map(String::as_str)) {
+            ("insert" | "replace" | "merge", Some("into"))
+            | ("delete", Some("from"))
+            | ("alter" | "drop" | "truncate", Some("table")) => &rest[1..],
+            ("update" | "truncate", _) => rest,
+            _ => continue,
+        };
+        let names = skip_modifiers(names);
+        let take = if token == "truncate" { names.len() } else { 1 };
+        for name in names.iter().take(take) {
+            let is_last = !name.ends_with(',');
+            if let Some(table) = table_name(name) {
+                if !tables.contains(&table) {
+                    tables.push(table);
+                }
+            }
+            if is_last {
+                break;
+            }
+        }
+    }
+    tables
+}
+
+fn skip_modifiers(tokens: &[String]) -> &[String] {
+    let mut tokens = tokens;
+    while let Some((first, rest)) = tokens.split_first() {
+        match first.as_str() {
+            "only" | "if" | "exists" | "ignore" | "low_priority" | "quick" => tokens = rest,
+            _ => break,
+        }
+    }
+    tokens
+}
+
+fn table_name(token: &str) -> Option<String> {
+    let name = token.trim_end_matches(',');
+    let name = name.rsplit('.').next().unwrap_or(name);
+    let name = name.trim_matches(|c| c == '"' || c == '`' || c == '[' || c == ']');
+    let valid = !name.is_empty()
+        && name != "set"
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+    #[test]
+    fn test_tables_written() {
+        assert_eq!(
+            tables_written("INSERT INTO orders(id, total) VALUES ($1, $2)"),
+            ["orders"]
+        );
+        assert_eq!(
+            tables_written("UPDATE public.\"Users\" SET name = $1 WHERE id = $2"),
+            ["users"]
+        );
+        assert_eq!(
+            tables_written("DELETE FROM ONLY sessions WHERE id = $1"),
+            ["sessions"]
+        );
+        assert_eq!(tables_written("TRUNCATE TABLE a, b"), ["a", "b"]);
+        assert_eq!(tables_written("DROP TABLE IF EXISTS tmp"), ["tmp"]);
+        assert_eq!(
+            tables_written(
+                "INSERT INTO counts (k, n) VALUES ($1, 1) ON CONFLICT (k) DO UPDATE SET n = counts.n + 1"
+            ),
+            ["counts"]
+        );
+        assert_eq!(
+            tables_written("WITH moved AS (DELETE FROM inbox RETURNING *) INSERT INTO archive SELECT * FROM moved"),
+            ["inbox", "archive"]
+        );
+        assert!(tables_written("SELECT * FROM orders").is_empty());
+    }
+
+    #[test]
+    fn test_entry_key_varies_with_params_and_versions() {
+        let param = |n| Value {
+            value: Some(ValueKind::IntValue(n)),
+        };
+        let sql = "SELECT * FROM orders WHERE team_id = $1";
+        let base = entry_key(sql, &[param(1)], &[], &["orders"], &[String::new()]);
+        let user = [("app.user_id".to_string(), "42".to_string())];
+
+        assert_eq!(
+            base,
+            entry_key(sql, &[param(1)], &[], &["orders"], &[String::new()])
+        );
+        assert_ne!(
+            base,
+            entry_key(sql, &[param(2)], &[], &["orders"], &[String::new()])
+        );
+        assert_ne!(
+            base,
+            entry_key(sql, &[param(1)], &[], &["orders"], &["1".to_string()])
+        );
+        assert_ne!(
+            base,
+            entry_key(sql, &[param(1)], &user, &["orders"], &[String::new()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pending_tables_per_transaction() {
+        let cache = QueryCache::new(CacheClient::connect_lazy("http://127.0.0.1:1").unwrap());
+        cache.record_pending("tx1", vec!["orders".to_string()]);
+        cache.record_pending("tx1", vec!["orders".to_string(), "items".to_string()]);
+        cache.record_pending("tx2", Vec::new());
+
+        let mut tables = cache.take_pending("tx1");
+        tables.sort();
+        assert_eq!(tables, ["items", "orders"]);
+        assert!(cache.take_pending("tx1").is_empty());
+        assert!(cache.take_pending("tx2").is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! Per-request capture of data-service queries.
+//!
+//! [`QueryLog::scope`] runs a future with a fresh log in task-local storage;
+//! every [`DataClient`](super::DataClient) query or statement issued inside
+//! it is recorded with its timing. Outside a scope recording is a no-op, so
+//! the shared client costs nothing extra in normal operation. The
+//! [`DebugToolbar`](crate::htmx::middleware::DebugToolbar) middleware uses
+//! this to show query plans for the current request.
+
+use acton_dx_proto::data::v1::Value;
+use parking_lot::Mutex;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+tokio::task_local! {
+    static QUERY_LOG: QueryLog;
+}
+
+/// A query executed while a [`QueryLog`] was in scope
+#[derive(Debug, Clone)]
+pub struct CapturedQuery {
+    /// SQL text
+    pub sql: String,
+    /// Bound parameters
+    pub params: Vec<Value>,
+    /// Round-trip time to the data service
+    pub elapsed: Duration,
+}
+
+/// Queries captured during one request
+#[derive(Debug, Clone, Default)]
+pub struct QueryLog {
+    entries: Arc<Mutex<Vec<CapturedQuery>>>,
+}
+
+impl QueryLog {
+    /// Create an empty log
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `future` with this log capturing its queries
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        QUERY_LOG.scope(self.clone(), future).await
+    }
+
+    /// Record a query in the log in scope, if any
+    pub fn record(sql: &str, params: &[Value], elapsed: Duration) {
+        let _ = QUERY_LOG.try_with(|log| {
+            log.entries.lock().push(CapturedQuery {
+                sql: sql.to_string(),
+                params: params.to_vec(),
+                elapsed,
+            });
+        });
+    }
+
+    /// Whether a log is in scope for the current task
+    #[must_use]
+    pub fn is_active() -> bool {
+        QUERY_LOG.try_with(|_| ()).is_ok()
+    }
+
+    /// Start timing a query if a log is in scope
+    pub(crate) fn start(params: &[Value]) -> Option<PendingQuery> {
+        Self::is_active().then(|| PendingQuery {
+            started: Instant::now(),
+            params: params.to_vec(),
+        })
+    }
+
+    /// Queries captured so far, in execution order
+    #[must_use]
+    pub fn entries(&self) -> Vec<CapturedQuery> {
+        self.entries.lock().clone()
+    }
+}
+
+/// A query being timed for the log in scope
+pub struct PendingQuery {
+    started: Instant,
+    params: Vec<Value>,
+}
+
+impl PendingQuery {
+    /// Record the query now that it has completed
+    pub fn finish(self, sql: &str) {
+        QueryLog::record(sql, &self.params, self.started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_records_only_in_scope() {
+        QueryLog::record("SELECT 0", &[], Duration::ZERO);
+        assert!(!QueryLog::is_active());
+
+        let log = QueryLog::new();
+        log.scope(async {
+            assert!(QueryLog::is_active());
+            QueryLog::record("SELECT 1", &[], Duration::from_millis(2));
+            QueryLog::record("SELECT 2", &[], Duration::from_millis(3));
+        })
+        .await;
+
+        let sql: Vec<_> = log.entries().into_iter().map(|q| q.sql).collect();
+        assert_eq!(sql, ["SELECT 1", "SELECT 2"]);
+    }
+}
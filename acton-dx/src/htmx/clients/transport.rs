@@ -136,19 +136,13 @@ impl IpcTransportConfig {
             return path.clone();
         }
 
-        // XDG-compliant default: $XDG_RUNTIME_DIR/acton/<app_name>/ipc.sock
-        if let Some(runtime_dir) = dirs::runtime_dir() {
-            runtime_dir
-                .join("acton")
-                .join(&self.app_name)
-                .join("ipc.sock")
-        } else {
-            // Fallback to /tmp if XDG_RUNTIME_DIR is not set
-            PathBuf::from("/tmp")
-                .join("acton")
-                .join(&self.app_name)
-                .join("ipc.sock")
-        }
+        // XDG-compliant default: $XDG_RUNTIME_DIR/acton/<app_name>/ipc.sock,
+        // falling back to /tmp if XDG_RUNTIME_DIR is not set
+        dirs::runtime_dir()
+            .unwrap_or_else(|| PathBuf::from("/tmp"))
+            .join("acton")
+            .join(&self.app_name)
+            .join("ipc.sock")
     }
 
     /// Create a new config with a specific socket path.
@@ -268,7 +262,7 @@ impl GrpcTransportConfig {
     #[must_use]
     pub fn localhost(base_port: u16) -> Self {
         Self {
-            auth_endpoint: Some(format!("http://localhost:{}", base_port)),
+            auth_endpoint: Some(format!("http://localhost:{base_port}")),
             data_endpoint: Some(format!("http://localhost:{}", base_port + 1)),
             cedar_endpoint: Some(format!("http://localhost:{}", base_port + 2)),
             cache_endpoint: Some(format!("http://localhost:{}", base_port + 3)),
@@ -353,7 +347,7 @@ impl TransportConfig {
 
     /// Configure fallback behavior.
     #[must_use]
-    pub fn with_fallback(mut self, fallback: FallbackConfig) -> Self {
+    pub const fn with_fallback(mut self, fallback: FallbackConfig) -> Self {
         self.fallback = fallback;
         self
     }
@@ -238,6 +238,835 @@ impl Default for RateLimitConfig {
     }
 }
 
+/// Response compression configuration
+///
+/// Compresses text responses above a size threshold, preferring brotli.
+/// Responses that embed a CSRF token are left uncompressed by default to
+/// avoid BREACH-style length oracles. Tokens rendered by the CSRF extractor
+/// and the auth kit are masked afresh per response, so
+/// `compress_csrf_responses` is safe when every token is rendered that way.
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [compression]
+/// enabled = true
+/// min_size = 1024                 # Don't compress bodies smaller than this (bytes)
+/// brotli = true
+/// gzip = true
+/// content_types = ["text/html", "application/json"]
+/// compress_csrf_responses = false
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)] // Independent config toggles
+pub struct CompressionConfig {
+    /// Enable response compression
+    pub enabled: bool,
+
+    /// Minimum body size in bytes before compressing
+    pub min_size: u16,
+
+    /// Offer brotli encoding
+    pub brotli: bool,
+
+    /// Offer gzip encoding
+    pub gzip: bool,
+
+    /// Content type prefixes eligible for compression
+    ///
+    /// Already-compressed formats (images, video, archives) should not be listed.
+    pub content_types: Vec<String>,
+
+    /// Compress responses that embed a CSRF token
+    ///
+    /// Only safe when tokens are masked with a fresh random value per response,
+    /// as [`CsrfToken::masked`](crate::htmx::agents::CsrfToken::masked) does.
+    pub compress_csrf_responses: bool,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size: 1024,
+            brotli: true,
+            gzip: true,
+            content_types: vec![
+                "text/html".to_string(),
+                "text/css".to_string(),
+                "text/plain".to_string(),
+                "text/javascript".to_string(),
+                "application/javascript".to_string(),
+                "application/json".to_string(),
+                "image/svg+xml".to_string(),
+            ],
+            compress_csrf_responses: false,
+        }
+    }
+}
+
+/// TLS certificate configuration
+///
+/// Shared by every listener that terminates TLS (including the HTTP/3 listener).
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [tls]
+/// cert_path = "/etc/acton/tls/fullchain.pem"
+/// key_path = "/etc/acton/tls/privkey.pem"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// PEM-encoded certificate chain
+    pub cert_path: Option<PathBuf>,
+
+    /// PEM-encoded private key
+    pub key_path: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Whether both a certificate and key are configured
+    #[must_use]
+    pub const fn is_configured(&self) -> bool {
+        self.cert_path.is_some() && self.key_path.is_some()
+    }
+}
+
+/// HTTP/3 listener configuration (requires `http3` feature)
+///
+/// The QUIC listener runs alongside the TCP listener and uses the
+/// certificates from [`TlsConfig`]. TCP responses advertise it with an
+/// `Alt-Svc` header so browsers upgrade on their next request.
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [http3]
+/// enabled = true
+/// bind_addr = "0.0.0.0:443"
+/// alt_svc_max_age_secs = 86400
+/// ```
+#[cfg(feature = "http3")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Http3Config {
+    /// Start the HTTP/3 listener and advertise it via `Alt-Svc`
+    pub enabled: bool,
+
+    /// UDP address to listen on
+    pub bind_addr: String,
+
+    /// Port advertised in `Alt-Svc` (defaults to the `bind_addr` port)
+    ///
+    /// Set this when a load balancer forwards a different public port.
+    pub advertised_port: Option<u16>,
+
+    /// How long clients may remember the `Alt-Svc` advertisement
+    pub alt_svc_max_age_secs: u64,
+
+    /// Maximum request body size accepted over HTTP/3
+    pub max_request_body_bytes: usize,
+}
+
+#[cfg(feature = "http3")]
+impl Default for Http3Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_addr: "0.0.0.0:443".to_string(),
+            advertised_port: None,
+            alt_svc_max_age_secs: 86400,
+            max_request_body_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+#[cfg(feature = "http3")]
+impl Http3Config {
+    /// Port to advertise in `Alt-Svc`
+    #[must_use]
+    pub fn alt_svc_port(&self) -> Option<u16> {
+        self.advertised_port.or_else(|| {
+            self.bind_addr
+                .parse::<std::net::SocketAddr>()
+                .ok()
+                .map(|addr| addr.port())
+        })
+    }
+}
+
+/// SCIM 2.0 provisioning configuration (requires `scim` feature)
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [scim]
+/// tokens = ["<long random token shared with the IdP>"]
+/// max_results = 100
+///
+/// [scim.mapping]
+/// email = "primary_email"
+/// default_roles = ["user"]
+///
+/// [scim.mapping.group_roles]
+/// "Acme Admins" = "admin"
+/// ```
+#[cfg(feature = "scim")]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScimConfig {
+    /// Bearer tokens accepted from identity providers
+    ///
+    /// Requests are rejected while the list is empty. Keep the old token
+    /// listed while rotating to a new one.
+    pub tokens: Vec<String>,
+
+    /// Largest page returned by list endpoints
+    pub max_results: usize,
+
+    /// How SCIM attributes map onto application users
+    pub mapping: ScimAttributeMapping,
+}
+
+#[cfg(feature = "scim")]
+impl Default for ScimConfig {
+    fn default() -> Self {
+        Self {
+            tokens: Vec::new(),
+            max_results: 100,
+            mapping: ScimAttributeMapping::default(),
+        }
+    }
+}
+
+#[cfg(feature = "scim")]
+impl std::fmt::Debug for ScimConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScimConfig")
+            .field("tokens", &format_args!("[{} redacted]", self.tokens.len()))
+            .field("max_results", &self.max_results)
+            .field("mapping", &self.mapping)
+            .finish()
+    }
+}
+
+/// Mapping of SCIM attributes onto application users (requires `scim` feature)
+#[cfg(feature = "scim")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScimAttributeMapping {
+    /// SCIM attribute holding the user's login email
+    pub email: ScimEmailSource,
+
+    /// Roles given to users when they are provisioned
+    pub default_roles: Vec<String>,
+
+    /// Application role granted to members of a SCIM group, by group `displayName`
+    ///
+    /// These roles are managed by the IdP: they are added and removed as
+    /// group memberships change. Other roles are left alone.
+    pub group_roles: HashMap<String, String>,
+}
+
+#[cfg(feature = "scim")]
+impl Default for ScimAttributeMapping {
+    fn default() -> Self {
+        Self {
+            email: ScimEmailSource::default(),
+            default_roles: vec!["user".to_string()],
+            group_roles: HashMap::new(),
+        }
+    }
+}
+
+/// SCIM attribute used as the login email (requires `scim` feature)
+#[cfg(feature = "scim")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScimEmailSource {
+    /// `userName`, which most IdPs set to the user's email
+    #[default]
+    UserName,
+    /// The primary (or first) entry of `emails`, falling back to `userName`
+    PrimaryEmail,
+}
+
+/// Reverse proxy trust configuration
+///
+/// Governs how the client IP, scheme and host are derived for the whole
+/// middleware stack (rate limiting, authorization context, redirects).
+/// Forwarding headers (`Forwarded`, `X-Forwarded-*`) are only honoured when
+/// the direct peer is listed in `trusted_proxies`.
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [proxy]
+/// trusted_proxies = ["10.0.0.0/8", "127.0.0.1"]
+/// proxy_protocol = true           # Expect PROXY protocol v2 from trusted peers
+/// proxy_protocol_timeout_ms = 3000
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    /// IP addresses or CIDR ranges of trusted reverse proxies
+    pub trusted_proxies: Vec<String>,
+
+    /// Read PROXY protocol v2 headers from trusted peers on the web listener
+    pub proxy_protocol: bool,
+
+    /// How long to wait for a PROXY protocol header after accepting a connection
+    pub proxy_protocol_timeout_ms: u64,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            trusted_proxies: Vec::new(),
+            proxy_protocol: false,
+            proxy_protocol_timeout_ms: 3000,
+        }
+    }
+}
+
+/// Sitemap and robots.txt configuration
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [sitemap]
+/// base_url = "https://example.com"
+/// urls_per_file = 50000
+/// cache_ttl_secs = 3600
+///
+/// [sitemap.robots]
+/// disallow = ["/admin", "/api"]
+/// disallow_all_environments = ["staging"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SitemapConfig {
+    /// Public origin prepended to relative sitemap URLs
+    pub base_url: String,
+
+    /// Maximum URLs per sitemap file (the protocol limit is 50,000)
+    pub urls_per_file: usize,
+
+    /// How long generated sitemaps are cached, in seconds
+    pub cache_ttl_secs: u64,
+
+    /// robots.txt settings
+    pub robots: RobotsConfig,
+}
+
+impl Default for SitemapConfig {
+    fn default() -> Self {
+        Self {
+            base_url: String::new(),
+            urls_per_file: 50_000,
+            cache_ttl_secs: 3600,
+            robots: RobotsConfig::default(),
+        }
+    }
+}
+
+/// robots.txt configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RobotsConfig {
+    /// Paths crawlers should not visit
+    pub disallow: Vec<String>,
+
+    /// Paths explicitly allowed inside disallowed prefixes
+    pub allow: Vec<String>,
+
+    /// Optional `Crawl-delay` in seconds
+    pub crawl_delay: Option<u32>,
+
+    /// Block all crawlers regardless of environment
+    pub disallow_all: bool,
+
+    /// Environments (from `ACTON_ENV`) in which all crawlers are blocked
+    pub disallow_all_environments: Vec<String>,
+
+    /// Deployment environment; falls back to the `ACTON_ENV` variable
+    pub environment: Option<String>,
+}
+
+impl Default for RobotsConfig {
+    fn default() -> Self {
+        Self {
+            disallow: Vec::new(),
+            allow: Vec::new(),
+            crawl_delay: None,
+            disallow_all: false,
+            disallow_all_environments: vec!["staging".to_string()],
+            environment: None,
+        }
+    }
+}
+
+impl RobotsConfig {
+    /// Current deployment environment
+    #[must_use]
+    pub fn environment(&self) -> Option<String> {
+        self.environment
+            .clone()
+            .or_else(|| std::env::var("ACTON_ENV").ok())
+    }
+
+    /// Whether robots.txt should block every crawler
+    #[must_use]
+    pub fn blocks_all(&self) -> bool {
+        self.disallow_all
+            || self.environment().is_some_and(|env| {
+                self.disallow_all_environments
+                    .iter()
+                    .any(|blocked| blocked.eq_ignore_ascii_case(&env))
+            })
+    }
+}
+
+/// Background job configuration
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [jobs]
+/// # Encrypt job payloads persisted to Redis (version:base64key, primary first)
+/// payload_keys = "2:BASE64KEY, 1:OLDBASE64KEY"
+///
+/// drain_grace_period_secs = 30
+///
+/// [jobs.tenant_quotas.default]
+/// max_concurrent = 4
+/// max_daily = 10000
+///
+/// [jobs.alerts.queue_depth]
+/// raise = 5000
+/// clear = 1000
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobsConfig {
+    /// Keys for encrypting persisted job payloads, in `version:base64key`
+    /// form; payloads are stored in plaintext when unset
+    pub payload_keys: Option<String>,
+
+    /// Per-tenant concurrency limits and daily quotas
+    pub tenant_quotas: crate::htmx::jobs::agent::TenantQuotas,
+
+    /// How long shutdown waits for running jobs before persisting them, in seconds
+    pub drain_grace_period_secs: u64,
+
+    /// Queue depth, pending age, dead letter and failure rate alert thresholds
+    pub alerts: crate::htmx::jobs::agent::AlertThresholds,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            payload_keys: None,
+            tenant_quotas: crate::htmx::jobs::agent::TenantQuotas::default(),
+            drain_grace_period_secs: 30,
+            alerts: crate::htmx::jobs::agent::AlertThresholds::default(),
+        }
+    }
+}
+
+impl JobsConfig {
+    /// Get the drain grace period as Duration
+    #[must_use]
+    pub const fn drain_grace_period(&self) -> Duration {
+        Duration::from_secs(self.drain_grace_period_secs)
+    }
+
+    /// Build the keyring for payload encryption, if keys are configured
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `payload_keys` is malformed.
+    pub fn payload_keyring(
+        &self,
+    ) -> Result<Option<crate::htmx::encryption::Keyring>, crate::htmx::encryption::EncryptionError>
+    {
+        self.payload_keys
+            .as_deref()
+            .map(crate::htmx::encryption::Keyring::parse)
+            .transpose()
+    }
+}
+
+/// Agent mailbox bounds
+///
+/// Messages from handlers and middleware queue in a bounded mailbox in
+/// front of each agent. Past `capacity`, `policy` is one of `drop-oldest`,
+/// `reject` or `spill-to-redis`.
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [mailboxes.session_manager]
+/// capacity = 4096
+/// policy = "reject"
+///
+/// [mailboxes.job_agent]
+/// capacity = 1024
+/// policy = "spill-to-redis"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MailboxesConfig {
+    /// Session manager mailbox
+    pub session_manager: crate::htmx::agents::MailboxConfig,
+
+    /// Job agent mailbox
+    pub job_agent: crate::htmx::agents::MailboxConfig,
+}
+
+impl Default for MailboxesConfig {
+    fn default() -> Self {
+        Self {
+            session_manager: crate::htmx::agents::MailboxConfig::default().with_capacity(4096),
+            job_agent: crate::htmx::agents::MailboxConfig::default(),
+        }
+    }
+}
+
+/// Agent supervision
+///
+/// The session manager, CSRF manager and job agent run under a supervisor
+/// that restarts them if they stop abnormally. After `max_restarts` within
+/// `window_secs` it stops trying and reports the agent as down.
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [supervision]
+/// enabled = true
+///
+/// [supervision.restart_limits]
+/// max_restarts = 5
+/// window_secs = 60
+/// initial_backoff_ms = 100
+/// max_backoff_ms = 30000
+/// backoff_multiplier = 2.0
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SupervisionConfig {
+    /// Supervise the framework's agents (default: true)
+    pub enabled: bool,
+
+    /// Restart limits and backoff applied to each agent
+    pub restart_limits: acton_reactive::prelude::RestartLimiterConfig,
+}
+
+impl Default for SupervisionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            restart_limits: acton_reactive::prelude::RestartLimiterConfig::default(),
+        }
+    }
+}
+
+/// Moving sessions from embedded mode to auth-service
+///
+/// While `dual_write` is on and the auth service is configured, sessions
+/// are still served by the embedded session manager and every change is
+/// copied to auth-service. `export_tokens` are the bearer tokens accepted by
+/// the session export endpoint that `acton-dx htmx services migrate-sessions`
+/// reads existing sessions from; the endpoint rejects every request while
+/// the list is empty.
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [session_migration]
+/// dual_write = true
+/// export_tokens = ["change-me"]
+/// ```
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionMigrationConfig {
+    /// Copy session changes to auth-service (default: false)
+    pub dual_write: bool,
+
+    /// Bearer tokens accepted by the session export endpoint
+    pub export_tokens: Vec<String>,
+}
+
+impl std::fmt::Debug for SessionMigrationConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionMigrationConfig")
+            .field("dual_write", &self.dual_write)
+            .field(
+                "export_tokens",
+                &format_args!("[{} redacted]", self.export_tokens.len()),
+            )
+            .finish()
+    }
+}
+
+/// Consent and cookie-preference configuration
+///
+/// Bump `version` whenever categories or their descriptions change
+/// materially; visitors who consented to an older version are asked again.
+///
+/// ```toml
+/// [consent]
+/// version = 2
+///
+/// [[consent.categories]]
+/// key = "necessary"
+/// label = "Strictly necessary"
+/// description = "Sign-in and security cookies."
+/// required = true
+///
+/// [[consent.categories]]
+/// key = "analytics"
+/// label = "Analytics"
+/// description = "Anonymous usage statistics."
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsentConfig {
+    /// Current version of the consent definitions
+    pub version: u32,
+
+    /// Name of the preferences cookie
+    pub cookie_name: String,
+
+    /// Lifetime of the preferences cookie, in days
+    pub cookie_max_age_days: u32,
+
+    /// Categories visitors can consent to
+    pub categories: Vec<ConsentCategory>,
+}
+
+impl Default for ConsentConfig {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            cookie_name: "consent".to_string(),
+            cookie_max_age_days: 180,
+            categories: vec![
+                ConsentCategory {
+                    key: "necessary".to_string(),
+                    label: "Strictly necessary".to_string(),
+                    description: "Required for sign-in, security and core features.".to_string(),
+                    required: true,
+                },
+                ConsentCategory {
+                    key: "analytics".to_string(),
+                    label: "Analytics".to_string(),
+                    description: "Helps us understand how the site is used.".to_string(),
+                    required: false,
+                },
+                ConsentCategory {
+                    key: "marketing".to_string(),
+                    label: "Marketing".to_string(),
+                    description: "Used to measure and personalize advertising.".to_string(),
+                    required: false,
+                },
+            ],
+        }
+    }
+}
+
+impl ConsentConfig {
+    /// Look up a category by key
+    #[must_use]
+    pub fn category(&self, key: &str) -> Option<&ConsentCategory> {
+        self.categories.iter().find(|c| c.key == key)
+    }
+}
+
+/// One consent category
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsentCategory {
+    /// Stable identifier checked by handlers and templates (`analytics`)
+    pub key: String,
+
+    /// Short name shown to visitors
+    pub label: String,
+
+    /// Explanation shown to visitors
+    #[serde(default)]
+    pub description: String,
+
+    /// Always granted and cannot be declined
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// First-party analytics settings
+///
+/// ```toml
+/// [analytics]
+/// consent_category = "analytics"
+/// batch_size = 200
+/// flush_interval_secs = 10
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalyticsConfig {
+    /// Record events only from visitors who granted `consent_category`
+    /// (default: true)
+    pub require_consent: bool,
+
+    /// Consent category gating collection
+    pub consent_category: String,
+
+    /// Drop events from browsers sending `DNT: 1` or `Sec-GPC: 1`
+    pub honor_do_not_track: bool,
+
+    /// Buffered events that trigger a write
+    pub batch_size: usize,
+
+    /// Longest an event waits in the buffer before it is written
+    pub flush_interval_secs: u64,
+
+    /// Properties kept per custom event; extra ones are dropped
+    pub max_properties: usize,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            require_consent: true,
+            consent_category: "analytics".to_string(),
+            honor_do_not_track: true,
+            batch_size: 100,
+            flush_interval_secs: 10,
+            max_properties: 10,
+        }
+    }
+}
+
+impl AnalyticsConfig {
+    /// Get the flush interval as Duration
+    #[must_use]
+    pub const fn flush_interval(&self) -> Duration {
+        Duration::from_secs(self.flush_interval_secs)
+    }
+}
+
+/// A/B experiment settings
+///
+/// ```toml
+/// [experiments]
+/// significance = 0.05
+///
+/// [[experiments.definitions]]
+/// key = "checkout-button"
+/// traffic_percent = 50
+/// variants = [
+///     { key = "control", weight = 1 },
+///     { key = "green", weight = 1 },
+/// ]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExperimentsConfig {
+    /// Largest p-value reported as significant
+    pub significance: f64,
+
+    /// Defined experiments
+    pub definitions: Vec<ExperimentConfig>,
+}
+
+impl Default for ExperimentsConfig {
+    fn default() -> Self {
+        Self {
+            significance: 0.05,
+            definitions: Vec::new(),
+        }
+    }
+}
+
+/// One experiment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExperimentConfig {
+    /// Stable identifier checked by handlers and templates
+    pub key: String,
+
+    /// Variants; the first is the control
+    pub variants: Vec<VariantConfig>,
+
+    /// Share of visitors enrolled, 0–100; the rest see the control
+    /// unrecorded
+    #[serde(default = "default_traffic_percent")]
+    pub traffic_percent: u8,
+
+    /// Stopped experiments assign nobody and everyone sees the control
+    #[serde(default = "default_true")]
+    pub active: bool,
+}
+
+impl ExperimentConfig {
+    /// An active experiment enrolling everyone, with no variants yet
+    #[must_use]
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            variants: Vec::new(),
+            traffic_percent: 100,
+            active: true,
+        }
+    }
+
+    /// Add a variant receiving `weight` shares of enrolled traffic
+    #[must_use]
+    pub fn variant(mut self, key: impl Into<String>, weight: u32) -> Self {
+        self.variants.push(VariantConfig {
+            key: key.into(),
+            weight,
+        });
+        self
+    }
+
+    /// Enroll only `percent` of visitors
+    #[must_use]
+    pub const fn with_traffic_percent(mut self, percent: u8) -> Self {
+        self.traffic_percent = percent;
+        self
+    }
+
+    /// The control variant's key
+    #[must_use]
+    pub fn control(&self) -> Option<&str> {
+        self.variants.first().map(|variant| variant.key.as_str())
+    }
+}
+
+/// One experiment variant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariantConfig {
+    /// Stable identifier (`control`, `green`)
+    pub key: String,
+
+    /// Relative share of enrolled traffic
+    #[serde(default = "default_variant_weight")]
+    pub weight: u32,
+}
+
+const fn default_traffic_percent() -> u8 {
+    100
+}
+
+const fn default_variant_weight() -> u32 {
+    1
+}
+
+const fn default_true() -> bool {
+    true
+}
+
 /// Failure mode for rate limit backend errors
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -375,10 +1204,64 @@ pub struct ActonHtmxConfig {
     #[serde(default)]
     pub security: SecuritySettings,
 
+    /// Response compression settings
+    #[serde(default)]
+    pub compression: CompressionConfig,
+
+    /// TLS certificate settings
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// Reverse proxy trust settings
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+
+    /// Sitemap and robots.txt settings
+    #[serde(default)]
+    pub sitemap: SitemapConfig,
+
+    /// Consent categories and preferences cookie settings
+    #[serde(default)]
+    pub consent: ConsentConfig,
+
+    /// First-party analytics collection
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+
+    /// A/B experiments
+    #[serde(default)]
+    pub experiments: ExperimentsConfig,
+
+    /// Background job settings
+    #[serde(default)]
+    pub jobs: JobsConfig,
+
+    /// Agent mailbox bounds and overflow policies
+    #[serde(default)]
+    pub mailboxes: MailboxesConfig,
+
+    /// Agent supervision and restart limits
+    #[serde(default)]
+    pub supervision: SupervisionConfig,
+
+    /// Session migration from embedded mode to auth-service
+    #[serde(default)]
+    pub session_migration: SessionMigrationConfig,
+
+    /// HTTP/3 listener settings (requires http3 feature)
+    #[cfg(feature = "http3")]
+    #[serde(default)]
+    pub http3: Http3Config,
+
     /// OAuth2 configuration
     #[serde(default)]
     pub oauth2: OAuthConfig,
 
+    /// SCIM provisioning settings (requires scim feature)
+    #[cfg(feature = "scim")]
+    #[serde(default)]
+    pub scim: ScimConfig,
+
     /// Services transport configuration
     ///
     /// Configures how the application communicates with microservices.
@@ -664,7 +1547,7 @@ mod tests {
         let temp_dir = std::env::temp_dir();
         let config_path = temp_dir.join("test_config.toml");
 
-        let toml_content = r"
+        let toml_content = r#"
 [htmx]
 request_timeout_ms = 10000
 history_enabled = false
@@ -672,7 +1555,17 @@ history_enabled = false
 [security]
 csrf_enabled = false
 session_max_age_secs = 3600
-";
+
+[mailboxes.job_agent]
+capacity = 64
+policy = "drop-oldest"
+
+[supervision.restart_limits]
+max_restarts = 3
+
+[session_migration]
+dual_write = true
+"#;
 
         let mut file = fs::File::create(&config_path).unwrap();
         file.write_all(toml_content.as_bytes()).unwrap();
@@ -686,6 +1579,17 @@ session_max_age_secs = 3600
         assert!(!config.htmx.history_enabled);
         assert!(!config.security.csrf_enabled);
         assert_eq!(config.security.session_max_age_secs, 3600);
+        assert_eq!(config.mailboxes.job_agent.capacity, 64);
+        assert_eq!(
+            config.mailboxes.job_agent.policy,
+            crate::htmx::agents::OverflowPolicy::DropOldest
+        );
+        assert_eq!(config.mailboxes.session_manager.capacity, 4096);
+        assert!(config.supervision.enabled);
+        assert_eq!(config.supervision.restart_limits.max_restarts, 3);
+        assert_eq!(config.supervision.restart_limits.window_secs, 60);
+        assert!(config.session_migration.dual_write);
+        assert!(config.session_migration.export_tokens.is_empty());
 
         // Cleanup
         fs::remove_file(config_path).ok();
@@ -0,0 +1,139 @@
+//! Consent store backed by data-service
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{ConsentError, ConsentPreferences, ConsentStore};
+use crate::htmx::clients::{DataClient, Row, Value};
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+/// Migration creating the consent record table
+pub const CONSENT_MIGRATION: &str = r"CREATE TABLE IF NOT EXISTS consent_records (
+    user_id BIGINT NOT NULL,
+    version BIGINT NOT NULL,
+    granted TEXT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_consent_records_user ON consent_records (user_id, updated_at);";
+
+/// Consent store appending every choice to `consent_records`
+///
+/// Records are never updated, so the table doubles as proof of consent.
+#[derive(Debug, Clone)]
+pub struct DataServiceConsentStore {
+    client: Arc<RwLock<DataClient>>,
+}
+
+impl DataServiceConsentStore {
+    /// Create a store
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<DataClient>>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl ConsentStore for DataServiceConsentStore {
+    async fn load(&self, user_id: i64) -> Result<Option<ConsentPreferences>, ConsentError> {
+        let row = self
+            .client
+            .write()
+            .await
+            .query_one(
+                "SELECT version, granted, updated_at FROM consent_records \
+                 WHERE user_id = $1 ORDER BY updated_at DESC LIMIT 1",
+                vec![int(user_id)],
+                None,
+            )
+            .await
+            .map_err(|e| ConsentError::Store(e.to_string()))?;
+        row.map(|row| from_row(&row)).transpose()
+    }
+
+    async fn save(
+        &self,
+        user_id: i64,
+        preferences: &ConsentPreferences,
+    ) -> Result<(), ConsentError> {
+        let granted = preferences
+            .granted
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(",");
+        self.client
+            .write()
+            .await
+            .execute(
+                "INSERT INTO consent_records (user_id, version, granted, updated_at) \
+                 VALUES ($1, $2, $3, $4)",
+                vec![
+                    int(user_id),
+                    int(i64::from(preferences.version)),
+                    Value {
+                        value: Some(ValueKind::StringValue(granted)),
+                    },
+                    int(preferences.updated_at),
+                ],
+                None,
+            )
+            .await
+            .map_err(|e| ConsentError::Store(e.to_string()))?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &Row) -> Result<ConsentPreferences, ConsentError> {
+    let column = |name: &str| row.columns.get(name).and_then(|v| v.value.as_ref());
+    let invalid = |name: &str| ConsentError::Store(format!("invalid consent column {name}"));
+    let (
+        Some(ValueKind::IntValue(version)),
+        Some(ValueKind::StringValue(granted)),
+        Some(ValueKind::IntValue(updated_at)),
+    ) = (column("version"), column("granted"), column("updated_at"))
+    else {
+        return Err(invalid("row"));
+    };
+    Ok(ConsentPreferences {
+        version: u32::try_from(*version).map_err(|_| invalid("version"))?,
+        granted: granted
+            .split(',')
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect(),
+        updated_at: *updated_at,
+    })
+}
+
+const fn int(value: i64) -> Value {
+    Value {
+        value: Some(ValueKind::IntValue(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_row() {
+        let row = Row {
+            columns: [
+                ("version".to_string(), int(2)),
+                (
+                    "granted".to_string(),
+                    Value {
+                        value: Some(ValueKind::StringValue("analytics,necessary".to_string())),
+                    },
+                ),
+                ("updated_at".to_string(), int(1_700_000_000)),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let preferences = from_row(&row).unwrap();
+        assert_eq!(preferences.version, 2);
+        assert!(preferences.granted.contains("analytics"));
+    }
+}
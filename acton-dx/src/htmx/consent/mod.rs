@@ -0,0 +1,678 @@
+//! Consent and cookie-preference management
+//!
+//! Categories are defined in configuration ([`ConsentConfig`]) under a
+//! version number. A visitor's choices live in a preferences cookie, and for
+//! signed-in users are also recorded in a [`ConsentStore`] as proof of
+//! consent and so they follow the user across devices.
+//!
+//! - [`ConsentManager::middleware`] resolves the visitor's [`Consent`] for
+//!   every request
+//! - handlers and templates check [`Consent::allows`] before loading
+//!   optional scripts, and [`Consent::needs_prompt`] to show the banner
+//! - [`ConsentManager::routes`] serves the preferences partial and saves
+//!   choices over HTMX
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::consent::{Consent, ConsentManager};
+//! use axum::middleware::from_fn_with_state;
+//!
+//! let consent = ConsentManager::new(config.consent.clone())
+//!     .with_secure_cookie(config.security.secure_cookies);
+//!
+//! let app = Router::new()
+//!     .route("/", get(home))
+//!     .merge(consent.routes())
+//!     .layer(from_fn_with_state(consent.clone(), ConsentManager::middleware));
+//!
+//! async fn home(consent: Consent) -> impl IntoResponse {
+//!     HomeTemplate { consent }
+//! }
+//! ```
+//!
+//! ```html
+//! {% if consent.allows("analytics") %}
+//!   <script src="/analytics.js" defer></script>
+//! {% endif %}
+//! {% if consent.needs_prompt() %}
+//!   <div hx-get="/consent" hx-trigger="load" hx-swap="outerHTML"></div>
+//! {% endif %}
+//! ```
+
+#[cfg(feature = "microservices")]
+mod data_service;
+
+#[cfg(feature = "microservices")]
+pub use data_service::{DataServiceConsentStore, CONSENT_MIGRATION};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::Infallible;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::htmx::auth::session::SessionData;
+use crate::htmx::template::helpers::escape_html;
+pub use crate::htmx::config::{ConsentCategory, ConsentConfig};
+
+/// Consent errors
+#[derive(Debug, thiserror::Error)]
+pub enum ConsentError {
+    /// The backing store failed
+    #[error("Consent store error: {0}")]
+    Store(String),
+}
+
+impl IntoResponse for ConsentError {
+    fn into_response(self) -> Response {
+        warn!(error = %self, "Consent store failed");
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+/// A visitor's recorded choices
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsentPreferences {
+    /// Consent definitions version the choices were made under
+    pub version: u32,
+    /// Granted category keys
+    pub granted: BTreeSet<String>,
+    /// Unix time (seconds) of the choice
+    pub updated_at: i64,
+}
+
+impl ConsentPreferences {
+    /// Preferences granting `granted` under `version`, timestamped now
+    #[must_use]
+    pub fn new(version: u32, granted: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            version,
+            granted: granted.into_iter().collect(),
+            updated_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    /// Cookie encoding: `{version}:{key+key}:{updated_at}`
+    #[must_use]
+    pub fn to_cookie_value(&self) -> String {
+        let granted = self
+            .granted
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("+");
+        format!("{}:{granted}:{}", self.version, self.updated_at)
+    }
+
+    /// Parse a value produced by [`ConsentPreferences::to_cookie_value`]
+    #[must_use]
+    pub fn from_cookie_value(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(3, ':');
+        let version = parts.next()?.parse().ok()?;
+        let granted = parts
+            .next()?
+            .split('+')
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect();
+        let updated_at = parts.next()?.parse().ok()?;
+        Some(Self {
+            version,
+            granted,
+            updated_at,
+        })
+    }
+}
+
+/// Persisted consent records for signed-in users
+#[async_trait]
+pub trait ConsentStore: Send + Sync {
+    /// The user's latest preferences
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConsentError::Store`] on failure.
+    async fn load(&self, user_id: i64) -> Result<Option<ConsentPreferences>, ConsentError>;
+
+    /// Record new preferences; implementations should keep history
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConsentError::Store`] on failure.
+    async fn save(
+        &self,
+        user_id: i64,
+        preferences: &ConsentPreferences,
+    ) -> Result<(), ConsentError>;
+}
+
+/// In-process consent store keeping every record
+#[derive(Debug, Default)]
+pub struct MemoryConsentStore {
+    records: Mutex<HashMap<i64, Vec<ConsentPreferences>>>,
+}
+
+impl MemoryConsentStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every record for a user, oldest first
+    #[must_use]
+    pub fn history(&self, user_id: i64) -> Vec<ConsentPreferences> {
+        self.records
+            .lock()
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl ConsentStore for MemoryConsentStore {
+    async fn load(&self, user_id: i64) -> Result<Option<ConsentPreferences>, ConsentError> {
+        Ok(self
+            .records
+            .lock()
+            .get(&user_id)
+            .and_then(|records| records.last().cloned()))
+    }
+
+    async fn save(
+        &self,
+        user_id: i64,
+        preferences: &ConsentPreferences,
+    ) -> Result<(), ConsentError> {
+        self.records
+            .lock()
+            .entry(user_id)
+            .or_default()
+            .push(preferences.clone());
+        Ok(())
+    }
+}
+
+/// The current visitor's consent
+///
+/// Inserted by [`ConsentManager::middleware`]. Before the visitor has
+/// chosen only required categories are allowed; without the middleware
+/// nothing is.
+#[derive(Debug, Clone, Default)]
+pub struct Consent {
+    granted: BTreeSet<String>,
+    needs_prompt: bool,
+    version: u32,
+}
+
+impl Consent {
+    fn resolve(config: &ConsentConfig, preferences: Option<&ConsentPreferences>) -> Self {
+        let current = preferences.filter(|p| p.version == config.version);
+        let granted = config
+            .categories
+            .iter()
+            .filter(|c| c.required || current.is_some_and(|p| p.granted.contains(&c.key)))
+            .map(|c| c.key.clone())
+            .collect();
+        Self {
+            granted,
+            needs_prompt: current.is_none(),
+            version: config.version,
+        }
+    }
+
+    /// Whether the visitor allows `category`
+    #[must_use]
+    pub fn allows(&self, category: &str) -> bool {
+        self.granted.contains(category)
+    }
+
+    /// Whether the visitor has not chosen under the current version
+    #[must_use]
+    pub const fn needs_prompt(&self) -> bool {
+        self.needs_prompt
+    }
+
+    /// Allowed category keys
+    #[must_use]
+    pub const fn granted(&self) -> &BTreeSet<String> {
+        &self.granted
+    }
+
+    /// Current consent definitions version
+    #[must_use]
+    pub const fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+impl<S> FromRequestParts<S> for Consent
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_default())
+    }
+}
+
+/// Resolves consent per request and serves the preferences endpoints
+#[derive(Clone)]
+pub struct ConsentManager {
+    config: Arc<ConsentConfig>,
+    store: Option<Arc<dyn ConsentStore>>,
+    secure_cookie: bool,
+}
+
+impl std::fmt::Debug for ConsentManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConsentManager")
+            .field("config", &self.config)
+            .field("store", &self.store.is_some())
+            .field("secure_cookie", &self.secure_cookie)
+            .finish()
+    }
+}
+
+impl ConsentManager {
+    /// Create a manager for the configured categories
+    #[must_use]
+    pub fn new(config: ConsentConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            store: None,
+            secure_cookie: !cfg!(debug_assertions),
+        }
+    }
+
+    /// Persist choices of signed-in users
+    #[must_use]
+    pub fn with_store(mut self, store: Arc<dyn ConsentStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Set the `Secure` attribute on the preferences cookie
+    /// (default: on in release builds)
+    #[must_use]
+    pub const fn with_secure_cookie(mut self, secure: bool) -> Self {
+        self.secure_cookie = secure;
+        self
+    }
+
+    /// Configured categories and version
+    #[must_use]
+    pub fn config(&self) -> &ConsentConfig {
+        &self.config
+    }
+
+    /// Middleware inserting [`Consent`] into request extensions
+    ///
+    /// Uses the preferences cookie, falling back to the stored record for
+    /// signed-in users. Use with `axum::middleware::from_fn_with_state`.
+    pub async fn middleware(
+        State(manager): State<Self>,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        let mut preferences = manager.cookie_preferences(request.headers());
+        if preferences.is_none() {
+            if let (Some(store), Some(user_id)) =
+                (&manager.store, session_user(request.extensions().get()))
+            {
+                preferences = store.load(user_id).await.unwrap_or_else(|e| {
+                    warn!(error = %e, user_id, "Failed to load consent record");
+                    None
+                });
+            }
+        }
+        let consent = Consent::resolve(&manager.config, preferences.as_ref());
+        request.extensions_mut().insert(consent);
+        next.run(request).await
+    }
+
+    /// Routes: `GET /consent` (preferences partial), `POST /consent`
+    /// (save checked categories), `POST /consent/accept-all` and
+    /// `POST /consent/reject-all`
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/consent", get(preferences_handler).post(save_handler))
+            .route("/consent/accept-all", post(accept_all_handler))
+            .route("/consent/reject-all", post(reject_all_handler))
+            .with_state(self.clone())
+    }
+
+    /// Record a choice: persists it for signed-in users and returns the
+    /// `Set-Cookie` header value
+    ///
+    /// Unknown keys are dropped and required categories are always granted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    pub async fn record(
+        &self,
+        user_id: Option<i64>,
+        granted: impl IntoIterator<Item = String>,
+    ) -> Result<(ConsentPreferences, String), ConsentError> {
+        let requested: BTreeSet<String> = granted.into_iter().collect();
+        let preferences = ConsentPreferences::new(
+            self.config.version,
+            self.config
+                .categories
+                .iter()
+                .filter(|c| c.required || requested.contains(&c.key))
+                .map(|c| c.key.clone()),
+        );
+        if let (Some(store), Some(user_id)) = (&self.store, user_id) {
+            store.save(user_id, &preferences).await?;
+        }
+        let cookie = self.cookie(&preferences);
+        Ok((preferences, cookie))
+    }
+
+    fn cookie(&self, preferences: &ConsentPreferences) -> String {
+        let mut cookie = format!(
+            "{}={}; Path=/; Max-Age={}; SameSite=Lax",
+            self.config.cookie_name,
+            preferences.to_cookie_value(),
+            u64::from(self.config.cookie_max_age_days) * 86_400
+        );
+        if self.secure_cookie {
+            cookie.push_str("; Secure");
+        }
+        cookie
+    }
+
+    fn cookie_preferences(&self, headers: &HeaderMap) -> Option<ConsentPreferences> {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .find_map(|cookie| {
+                let (name, value) = cookie.trim().split_once('=')?;
+                (name == self.config.cookie_name)
+                    .then(|| ConsentPreferences::from_cookie_value(value))
+                    .flatten()
+            })
+    }
+
+    async fn respond(
+        &self,
+        session: Option<&SessionData>,
+        granted: Vec<String>,
+    ) -> Result<Response, ConsentError> {
+        let (preferences, cookie) = self.record(session_user(session), granted).await?;
+        let consent = Consent::resolve(&self.config, Some(&preferences));
+        Ok((
+            [
+                (header::SET_COOKIE, cookie),
+                (
+                    header::HeaderName::from_static("hx-trigger"),
+                    "consent-updated".to_string(),
+                ),
+            ],
+            Html(saved_partial(&consent)),
+        )
+            .into_response())
+    }
+}
+
+/// Preferences form partial, posting to `/consent` with HTMX
+///
+/// Required categories are shown checked and disabled. Pass the CSRF token
+/// when CSRF protection is enabled.
+#[must_use]
+pub fn preferences_partial(
+    config: &ConsentConfig,
+    consent: &Consent,
+    csrf_token: Option<&str>,
+) -> String {
+    let mut html = String::from(
+        r#"<div id="consent-preferences" class="consent-preferences" role="dialog" aria-label="Cookie preferences">"#,
+    );
+    html.push_str(
+        r##"<form hx-post="/consent" hx-target="#consent-preferences" hx-swap="outerHTML">"##,
+    );
+    if let Some(token) = csrf_token {
+        let _ = write!(
+            html,
+            r#"<input type="hidden" name="_csrf_token" value="{}">"#,
+            escape_html(token)
+        );
+    }
+    for category in &config.categories {
+        let key = escape_html(&category.key);
+        let checked = if consent.allows(&category.key) {
+            " checked"
+        } else {
+            ""
+        };
+        let disabled = if category.required { " disabled" } else { "" };
+        let _ = write!(
+            html,
+            r#"<label class="consent-category"><input type="checkbox" name="{key}" value="on"{checked}{disabled}> <strong>{}</strong> <span>{}</span></label>"#,
+            escape_html(&category.label),
+            escape_html(&category.description),
+        );
+    }
+    html.push_str(r#"<button type="submit">Save preferences</button>"#);
+    for (action, label) in [
+        ("accept-all", "Accept all"),
+        ("reject-all", "Reject optional"),
+    ] {
+        let _ = write!(
+            html,
+            r##"<button type="button" hx-post="/consent/{action}" hx-include="closest form" hx-target="#consent-preferences" hx-swap="outerHTML">{label}</button>"##
+        );
+    }
+    html.push_str("</form></div>");
+    html
+}
+
+/// Confirmation partial replacing the form once preferences are saved
+fn saved_partial(consent: &Consent) -> String {
+    format!(
+        r##"<div id="consent-preferences" class="consent-preferences consent-saved" data-consent="{}">Preferences saved. <a href="#" hx-get="/consent" hx-target="closest div" hx-swap="outerHTML">Change</a></div>"##,
+        escape_html(
+            &consent
+                .granted
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+    )
+}
+
+fn session_user(session: Option<&SessionData>) -> Option<i64> {
+    session.and_then(|session| session.user_id)
+}
+
+#[allow(clippy::unused_async)] // axum handler
+async fn preferences_handler(
+    State(manager): State<ConsentManager>,
+    consent: Consent,
+) -> Html<String> {
+    Html(preferences_partial(&manager.config, &consent, None))
+}
+
+async fn save_handler(
+    State(manager): State<ConsentManager>,
+    session: Option<Extension<SessionData>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Result<Response, ConsentError> {
+    let granted = form
+        .into_iter()
+        .filter(|(key, value)| key != "_csrf_token" && value == "on")
+        .map(|(key, _)| key)
+        .collect();
+    manager.respond(session.as_deref(), granted).await
+}
+
+async fn accept_all_handler(
+    State(manager): State<ConsentManager>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Response, ConsentError> {
+    let all = manager
+        .config
+        .categories
+        .iter()
+        .map(|c| c.key.clone())
+        .collect();
+    manager.respond(session.as_deref(), all).await
+}
+
+async fn reject_all_handler(
+    State(manager): State<ConsentManager>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Response, ConsentError> {
+    manager.respond(session.as_deref(), Vec::new()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::middleware::from_fn_with_state;
+    use tower::ServiceExt;
+
+    fn manager() -> ConsentManager {
+        ConsentManager::new(ConsentConfig::default()).with_secure_cookie(false)
+    }
+
+    #[test]
+    fn test_cookie_round_trip_and_versioning() {
+        let preferences = ConsentPreferences::new(1, ["analytics".to_string()]);
+        let value = preferences.to_cookie_value();
+        assert_eq!(
+            ConsentPreferences::from_cookie_value(&value),
+            Some(preferences.clone())
+        );
+        assert!(ConsentPreferences::from_cookie_value("garbage").is_none());
+
+        let config = ConsentConfig::default();
+        let consent = Consent::resolve(&config, Some(&preferences));
+        assert!(consent.allows("necessary"));
+        assert!(consent.allows("analytics"));
+        assert!(!consent.allows("marketing"));
+        assert!(!consent.needs_prompt());
+
+        // Choices made under an older version no longer count
+        let config = ConsentConfig {
+            version: 2,
+            ..ConsentConfig::default()
+        };
+        let consent = Consent::resolve(&config, Some(&preferences));
+        assert!(consent.needs_prompt());
+        assert!(!consent.allows("analytics"));
+        assert!(consent.allows("necessary"));
+    }
+
+    #[tokio::test]
+    async fn test_record_persists_for_signed_in_users() {
+        let store = Arc::new(MemoryConsentStore::new());
+        let manager = manager().with_store(store.clone());
+        let (preferences, cookie) = manager
+            .record(Some(7), ["marketing".to_string(), "bogus".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            preferences.granted.iter().collect::<Vec<_>>(),
+            ["marketing", "necessary"]
+        );
+        assert!(cookie.starts_with("consent=1:marketing+necessary:"));
+        assert!(!cookie.contains("Secure"));
+        assert_eq!(store.history(7).len(), 1);
+
+        manager.record(None, Vec::new()).await.unwrap();
+        assert_eq!(store.history(7).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_middleware_and_save_route() {
+        let manager = manager();
+        let app = Router::new()
+            .route(
+                "/",
+                get(|consent: Consent| async move {
+                    format!("{} {}", consent.needs_prompt(), consent.allows("analytics"))
+                }),
+            )
+            .merge(manager.routes())
+            .layer(from_fn_with_state(
+                manager.clone(),
+                ConsentManager::middleware,
+            ));
+
+        let body = |response: Response| async {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(body(response).await, "true false");
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/consent")
+                    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(Body::from("analytics=on"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let cookie = response.headers()[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(response.headers()["hx-trigger"], "consent-updated");
+
+        let pair = cookie.split(';').next().unwrap().to_string();
+        let response = app
+            .oneshot(
+                Request::get("/")
+                    .header(header::COOKIE, pair)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(body(response).await, "false true");
+    }
+
+    #[test]
+    fn test_preferences_partial() {
+        let config = ConsentConfig::default();
+        let html = preferences_partial(&config, &Consent::resolve(&config, None), Some("tok"));
+        assert!(html.contains(r#"name="necessary" value="on" checked disabled"#));
+        assert!(html.contains(r#"name="analytics" value="on">"#));
+        assert!(html.contains(r#"value="tok""#));
+        assert!(html.contains(r#"hx-post="/consent/accept-all""#));
+    }
+}
@@ -0,0 +1,267 @@
+//! MIME parsing for inbound messages
+//!
+//! Parsing is done by `mail-parser`, which handles folded headers, RFC 2047
+//! encoded words, nested multiparts, transfer encodings and, through
+//! `encoding_rs`, every charset mail clients send. This module picks out
+//! what [`InboundEmail`](super::InboundEmail) needs.
+
+use mail_parser::{Address, HeaderName, HeaderValue, MessageParser, MessagePart, MimeHeaders};
+
+use super::{InboundAddress, InboundError};
+
+/// A parsed message before attachments are stored
+#[derive(Debug, Clone, Default)]
+pub(super) struct ParsedMessage {
+    pub message_id: Option<String>,
+    pub from: Option<InboundAddress>,
+    pub to: Vec<InboundAddress>,
+    pub cc: Vec<InboundAddress>,
+    /// `Delivered-To`, which names the envelope recipient when we were Bcc'd
+    pub delivered_to: Vec<InboundAddress>,
+    pub subject: String,
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub attachments: Vec<ParsedAttachment>,
+}
+
+/// An attachment still held in memory
+#[derive(Debug, Clone)]
+pub(super) struct ParsedAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content_id: Option<String>,
+    pub data: Vec<u8>,
+}
+
+/// Parse a raw RFC 5322 message
+pub(super) fn parse(raw: &[u8]) -> Result<ParsedMessage, InboundError> {
+    let message = MessageParser::new()
+        .with_minimal_headers()
+        .with_message_ids()
+        .header_address(HeaderName::Other("Delivered-To".into()))
+        .parse(raw)
+        .filter(|message| !message.headers().is_empty())
+        .ok_or_else(|| InboundError::Parse("message has no headers".to_string()))?;
+
+    // mail-parser lists an HTML part as the text body when there is no
+    // plain text alternative (and vice versa); only keep real ones. Later
+    // bodies are usually quoted forwards.
+    let text = message
+        .text_bodies()
+        .find(|part| part.is_text() && !part.is_text_html())
+        .and_then(|part| part.text_contents().map(str::to_string));
+    let html = message
+        .html_bodies()
+        .find(|part| part.is_text_html())
+        .and_then(|part| part.text_contents().map(str::to_string));
+
+    Ok(ParsedMessage {
+        message_id: message.message_id().map(str::to_string),
+        from: message
+            .from()
+            .and_then(|from| addresses(from).into_iter().next()),
+        to: message.to().map(addresses).unwrap_or_default(),
+        cc: message.cc().map(addresses).unwrap_or_default(),
+        delivered_to: message
+            .header_values(HeaderName::Other("Delivered-To".into()))
+            .filter_map(HeaderValue::as_address)
+            .flat_map(addresses)
+            .collect(),
+        subject: message.subject().unwrap_or_default().to_string(),
+        in_reply_to: ids(message.in_reply_to()).into_iter().next(),
+        references: ids(message.references()),
+        text,
+        html,
+        attachments: message.attachments().map(attachment).collect(),
+    })
+}
+
+/// Addresses in a list or group, skipping entries without an `@`
+fn addresses(address: &Address<'_>) -> Vec<InboundAddress> {
+    address
+        .iter()
+        .filter_map(|addr| {
+            let email = addr.address().filter(|email| email.contains('@'))?;
+            Some(InboundAddress {
+                email: email.to_string(),
+                name: addr
+                    .name()
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// Message IDs in a header, without angle brackets
+fn ids(value: &HeaderValue<'_>) -> Vec<String> {
+    match value {
+        HeaderValue::Text(id) => vec![id.to_string()],
+        HeaderValue::TextList(ids) => ids.iter().map(ToString::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn attachment(part: &MessagePart<'_>) -> ParsedAttachment {
+    let content_type = part.content_type().map_or_else(
+        || "text/plain".to_string(),
+        |ct| {
+            ct.subtype().map_or_else(
+                || ct.ctype().to_string(),
+                |sub| format!("{}/{sub}", ct.ctype()),
+            )
+        },
+    );
+    ParsedAttachment {
+        filename: part.attachment_name().unwrap_or("attachment").to_string(),
+        content_type: content_type.to_ascii_lowercase(),
+        content_id: part.content_id().map(str::to_string),
+        data: part.contents().to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MULTIPART: &str = "From: \"Doe, Jane\" <jane@example.com>\r\n\
+To: reply+ticket-42.abc@app.example.com, Support <support@app.example.com>\r\n\
+Delivered-To: hidden@app.example.com\r\n\
+Subject: =?utf-8?B?UmU6IMOcYmVy?=\r\n =?utf-8?Q?_the_plan?=\r\n\
+Message-ID: <msg-1@example.com>\r\n\
+References: <root@app.example.com>\r\n <prev@app.example.com>\r\n\
+Content-Type: multipart/mixed; boundary=\"outer\"\r\n\
+\r\n\
+preamble\r\n\
+--outer\r\n\
+Content-Type: multipart/alternative; boundary=inner\r\n\
+\r\n\
+--inner\r\n\
+Content-Type: text/plain; charset=utf-8\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+\r\n\
+Caf=C3=A9 at noon, a very long line that gets =\r\n\
+soft broken.\r\n\
+--inner\r\n\
+Content-Type: text/html; charset=iso-8859-1\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+\r\n\
+<p>Caf=E9</p>\r\n\
+--inner--\r\n\
+--outer\r\n\
+Content-Type: application/pdf; name=\"invoice.pdf\"\r\n\
+Content-Disposition: attachment; filename=\"=?utf-8?Q?Rechnung_M=C3=A4rz.pdf?=\"\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+JVBERi0x\r\n\
+LjQ=\r\n\
+--outer--\r\n\
+epilogue\r\n";
+
+    #[test]
+    fn test_parse_nested_multipart() {
+        let message = parse(MULTIPART.as_bytes()).unwrap();
+
+        assert_eq!(
+            message.text.as_deref(),
+            Some("Café at noon, a very long line that gets soft broken.")
+        );
+        assert_eq!(message.html.as_deref(), Some("<p>Café</p>"));
+        assert_eq!(message.attachments.len(), 1);
+        let attachment = &message.attachments[0];
+        assert_eq!(attachment.filename, "Rechnung März.pdf");
+        assert_eq!(attachment.content_type, "application/pdf");
+        assert_eq!(attachment.data, b"%PDF-1.4");
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        let message = parse(MULTIPART.as_bytes()).unwrap();
+
+        assert_eq!(message.subject, "Re: Über the plan");
+        let from = message.from.unwrap();
+        assert_eq!(from.email, "jane@example.com");
+        assert_eq!(from.name.as_deref(), Some("Doe, Jane"));
+        let to: Vec<_> = message.to.into_iter().map(|a| a.email).collect();
+        assert_eq!(
+            to,
+            [
+                "reply+ticket-42.abc@app.example.com",
+                "support@app.example.com"
+            ]
+        );
+        assert_eq!(message.delivered_to[0].email, "hidden@app.example.com");
+        assert_eq!(message.message_id.as_deref(), Some("msg-1@example.com"));
+        assert_eq!(
+            message.references,
+            ["root@app.example.com", "prev@app.example.com"]
+        );
+    }
+
+    #[test]
+    fn test_parse_single_part() {
+        let message = parse(b"From: a@example.com\nSubject: Hi\n\nHello\n").unwrap();
+        assert_eq!(message.text.as_deref(), Some("Hello\n"));
+        assert!(message.html.is_none());
+        assert!(message.attachments.is_empty());
+
+        assert!(parse(b"").is_err());
+    }
+
+    #[test]
+    fn test_parse_html_only() {
+        let message =
+            parse(b"From: a@example.com\nContent-Type: text/html; charset=utf-8\n\n<p>Hi</p>\n")
+                .unwrap();
+        assert!(message.text.is_none());
+        assert_eq!(message.html.as_deref(), Some("<p>Hi</p>\n"));
+    }
+
+    #[test]
+    fn test_parse_windows_1252_body() {
+        // 0x80 is the euro sign and 0x93/0x94 are curly quotes in
+        // windows-1252, but C1 control characters in Latin-1
+        let mut raw = b"From: a@example.com\r\n\
+Content-Type: text/plain; charset=windows-1252\r\n\
+\r\n"
+            .to_vec();
+        raw.extend_from_slice(b"\x93Only \x8010\x94");
+        let message = parse(&raw).unwrap();
+        assert_eq!(message.text.as_deref(), Some("\u{201c}Only €10\u{201d}"));
+    }
+
+    #[test]
+    fn test_parse_non_latin_charsets() {
+        // "Привет" in KOI8-R, and "日本" in Shift_JIS
+        let mut raw = b"From: a@example.com\r\n\
+Subject: =?koi8-r?B?8NLJ18XU?=\r\n\
+Content-Type: multipart/alternative; boundary=b\r\n\
+\r\n\
+--b\r\n\
+Content-Type: text/plain; charset=shift_jis\r\n\
+\r\n"
+            .to_vec();
+        raw.extend_from_slice(b"\x93\xfa\x96\x7b\r\n--b\r\n");
+        raw.extend_from_slice(b"Content-Type: text/html; charset=koi8-r\r\n\r\n");
+        raw.extend_from_slice(b"<p>\xf0\xd2\xc9\xd7\xc5\xd4</p>\r\n--b--\r\n");
+        let message = parse(&raw).unwrap();
+        assert_eq!(message.subject, "Привет");
+        assert_eq!(message.text.as_deref(), Some("日本"));
+        assert_eq!(message.html.as_deref(), Some("<p>Привет</p>"));
+    }
+
+    #[test]
+    fn test_parse_group_addresses() {
+        let message = parse(
+            b"From: a@example.com\nTo: team: a@example.com, B <b@example.com>;\nCc: not-an-address\n\nHi\n",
+        )
+        .unwrap();
+        let emails: Vec<_> = message.to.iter().map(|a| a.email.as_str()).collect();
+        assert_eq!(emails, ["a@example.com", "b@example.com"]);
+        assert_eq!(message.to[1].name.as_deref(), Some("B"));
+        assert!(message.cc.is_empty());
+    }
+}
@@ -0,0 +1,531 @@
+//! Inbound email processing
+//!
+//! Receives mail forwarded by an inbound provider (Postmark, Mailgun, SES,
+//! or a relay script) as raw MIME on a signed webhook:
+//!
+//! - [`Inbound::routes`] serves `POST /email/inbound`; the body is the raw
+//!   message and `X-Inbound-Signature: sha256=<hex>` is the HMAC-SHA256 of
+//!   the body with the webhook secret
+//! - Messages are parsed into an [`InboundEmail`]; attachments are stored
+//!   in a [`FileStorage`] (file-service with
+//!   [`MicroservicesFileStorage`](crate::htmx::storage::MicroservicesFileStorage))
+//! - Replies to a [`ReplyAddresses`] address carry the thread they belong to
+//! - Every email is broadcast to [`Inbound::subscribe`]rs, and emails to a
+//!   mailbox registered with [`Inbound::route`] are enqueued as that
+//!   mailbox's job
+//!
+//! Providers retry failed deliveries, so jobs should be idempotent on
+//! [`InboundEmail::message_id`].
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::email::inbound::{Inbound, InboundEmail, ReplyAddresses};
+//!
+//! #[derive(Debug, Serialize, Deserialize)]
+//! struct PostCommentFromEmail { thread: Option<String>, email: InboundEmail }
+//!
+//! let replies = ReplyAddresses::new("app.example.com", std::env::var("REPLY_SECRET")?);
+//! let inbound = Inbound::new(storage, replies.clone(), std::env::var("INBOUND_SECRET")?)
+//!     .with_job_agent(state.job_agent().clone())
+//!     .route("reply", |email| PostCommentFromEmail { thread: email.thread.clone(), email });
+//!
+//! // Outgoing notification: replies come back to the thread
+//! let reply_to = replies.address("ticket-42")?;
+//!
+//! let app = Router::new().merge(inbound.routes());
+//! ```
+
+mod mime;
+mod reply;
+
+pub use reply::ReplyAddresses;
+
+use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::htmx::jobs::agent::EnqueueJob;
+use crate::htmx::jobs::{Job, JobId};
+use crate::htmx::storage::{FileStorage, StorageError, UploadedFile};
+use crate::htmx::util::unix_now;
+
+/// Header carrying the webhook signature
+pub const SIGNATURE_HEADER: &str = "X-Inbound-Signature";
+
+/// Default limit on the size of a raw message (25 MiB)
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 25 * 1024 * 1024;
+
+/// Buffered events per subscriber before the slowest starts missing them
+const EVENT_CAPACITY: usize = 256;
+
+/// Inbound email errors
+#[derive(Debug, thiserror::Error)]
+pub enum InboundError {
+    /// Webhook signature missing or wrong
+    #[error("Invalid webhook signature")]
+    InvalidSignature,
+
+    /// Message could not be parsed
+    #[error("Invalid message: {0}")]
+    Parse(String),
+
+    /// Thread ID cannot be used in a reply address
+    #[error("Invalid thread ID for reply address: {0}")]
+    InvalidThread(String),
+
+    /// Routed job could not be serialized
+    #[error("Failed to serialize job: {0}")]
+    Job(String),
+
+    /// Attachment could not be stored
+    #[error("Attachment storage failed: {0}")]
+    Storage(#[from] StorageError),
+}
+
+impl IntoResponse for InboundError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::InvalidSignature | Self::Parse(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidThread(_) | Self::Job(_) | Self::Storage(_) => {
+                warn!(error = %self, "Inbound email processing failed");
+                return StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// A sender or recipient
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InboundAddress {
+    /// Email address
+    pub email: String,
+    /// Display name
+    pub name: Option<String>,
+}
+
+/// An attachment saved to file storage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InboundAttachment {
+    /// Stored file ID
+    pub file_id: String,
+    /// Filename given by the sender
+    pub filename: String,
+    /// MIME type given by the sender
+    pub content_type: String,
+    /// Size in bytes
+    pub size: u64,
+    /// `Content-ID` of inline parts, referenced as `cid:` in the HTML body
+    pub content_id: Option<String>,
+}
+
+/// A received email
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InboundEmail {
+    /// `Message-ID` without angle brackets
+    pub message_id: Option<String>,
+    /// Sender
+    pub from: Option<InboundAddress>,
+    /// `To` recipients
+    pub to: Vec<InboundAddress>,
+    /// `Cc` recipients
+    pub cc: Vec<InboundAddress>,
+    /// Decoded subject
+    pub subject: String,
+    /// `In-Reply-To` message ID
+    pub in_reply_to: Option<String>,
+    /// `References` message IDs, oldest first
+    pub references: Vec<String>,
+    /// Plain text body
+    pub text: Option<String>,
+    /// HTML body; sanitize before displaying it
+    pub html: Option<String>,
+    /// Stored attachments
+    pub attachments: Vec<InboundAttachment>,
+    /// Local part (before any `+tag`) of the first recipient at the
+    /// application's domain, e.g. `support` or `reply`
+    pub mailbox: Option<String>,
+    /// Thread from a signed reply address, if the email is a reply
+    pub thread: Option<String>,
+    /// Unix time (seconds) the email was received
+    pub received_at: u64,
+}
+
+/// Builds the enqueue message for a routed job
+type JobFactory = Arc<dyn Fn(InboundEmail) -> Result<EnqueueJob, InboundError> + Send + Sync>;
+
+/// Inbound email processor
+///
+/// Cheap to clone; clones share storage, routes and the event channel.
+#[derive(Clone)]
+pub struct Inbound {
+    storage: Arc<dyn FileStorage>,
+    replies: ReplyAddresses,
+    webhook_secret: Arc<str>,
+    max_message_size: usize,
+    job_agent: Option<ActorHandle>,
+    routes: HashMap<String, JobFactory>,
+    events: broadcast::Sender<InboundEmail>,
+}
+
+impl std::fmt::Debug for Inbound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut mailboxes: Vec<_> = self.routes.keys().collect();
+        mailboxes.sort();
+        f.debug_struct("Inbound")
+            .field("replies", &self.replies)
+            .field("max_message_size", &self.max_message_size)
+            .field("routes", &mailboxes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inbound {
+    /// Create a processor
+    ///
+    /// `webhook_secret` signs webhook bodies; `replies` identifies the
+    /// application's domain and verifies reply addresses.
+    #[must_use]
+    pub fn new(
+        storage: Arc<dyn FileStorage>,
+        replies: ReplyAddresses,
+        webhook_secret: impl Into<String>,
+    ) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            storage,
+            replies,
+            webhook_secret: webhook_secret.into().into(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            job_agent: None,
+            routes: HashMap::new(),
+            events,
+        }
+    }
+
+    /// Enqueue routed jobs on this agent
+    ///
+    /// Without one, emails are only broadcast to subscribers.
+    #[must_use]
+    pub fn with_job_agent(mut self, job_agent: ActorHandle) -> Self {
+        self.job_agent = Some(job_agent);
+        self
+    }
+
+    /// Set the largest accepted raw message (default 25 MiB)
+    #[must_use]
+    pub const fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Enqueue the job built by `make` for every email to `mailbox`
+    ///
+    /// Replies to [`ReplyAddresses`] arrive at its mailbox (`reply` by
+    /// default) with [`InboundEmail::thread`] set.
+    #[must_use]
+    pub fn route<J, F>(mut self, mailbox: &str, make: F) -> Self
+    where
+        J: Job,
+        F: Fn(InboundEmail) -> J + Send + Sync + 'static,
+    {
+        let factory: JobFactory = Arc::new(move |email| {
+            let job = make(email);
+            let payload = serde_json::to_vec(&job).map_err(|e| InboundError::Job(e.to_string()))?;
+            Ok(EnqueueJob {
+                id: JobId::new(),
+                job_type: job.job_type().to_string(),
+                payload,
+                priority: job.priority(),
+                max_retries: job.max_retries(),
+                timeout: job.timeout(),
+                tenant_id: None,
+            })
+        });
+        self.routes.insert(mailbox.to_ascii_lowercase(), factory);
+        self
+    }
+
+    /// Reply addresses used to match threads
+    #[must_use]
+    pub const fn replies(&self) -> &ReplyAddresses {
+        &self.replies
+    }
+
+    /// Receive every processed email
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<InboundEmail> {
+        self.events.subscribe()
+    }
+
+    /// Check a webhook body against its signature header
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InboundError::InvalidSignature`] if the header is not
+    /// `sha256=<hex>` of the body's HMAC with the webhook secret.
+    pub fn verify_signature(&self, body: &[u8], header: &str) -> Result<(), InboundError> {
+        let signature = header
+            .trim()
+            .strip_prefix("sha256=")
+            .and_then(|hex| hex::decode(hex).ok())
+            .ok_or(InboundError::InvalidSignature)?;
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.webhook_secret.as_bytes())
+            .map_err(|_| InboundError::InvalidSignature)?;
+        mac.update(body);
+        mac.verify_slice(&signature)
+            .map_err(|_| InboundError::InvalidSignature)
+    }
+
+    /// Parse a raw message, store its attachments and dispatch it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message cannot be parsed, an attachment
+    /// cannot be stored or the routed job cannot be serialized.
+    pub async fn receive(&self, raw: &[u8]) -> Result<InboundEmail, InboundError> {
+        let email = self.process(raw).await?;
+        self.dispatch(&email).await?;
+        Ok(email)
+    }
+
+    async fn process(&self, raw: &[u8]) -> Result<InboundEmail, InboundError> {
+        let message = mime::parse(raw)?;
+
+        let recipients: Vec<_> = message
+            .to
+            .iter()
+            .chain(&message.cc)
+            .chain(&message.delivered_to)
+            .map(|addr| addr.email.to_ascii_lowercase())
+            .collect();
+        let thread = recipients.iter().find_map(|addr| self.replies.thread(addr));
+        let mailbox = recipients.iter().find_map(|addr| {
+            let (local_part, domain) = addr.rsplit_once('@')?;
+            (domain == self.replies.domain()).then(|| {
+                local_part
+                    .split('+')
+                    .next()
+                    .unwrap_or(local_part)
+                    .to_string()
+            })
+        });
+
+        let mut attachments = Vec::with_capacity(message.attachments.len());
+        for attachment in message.attachments {
+            let stored = self
+                .storage
+                .store(UploadedFile::new(
+                    attachment.filename.clone(),
+                    attachment.content_type.clone(),
+                    attachment.data,
+                ))
+                .await?;
+            attachments.push(InboundAttachment {
+                file_id: stored.id,
+                filename: attachment.filename,
+                content_type: attachment.content_type,
+                size: stored.size,
+                content_id: attachment.content_id,
+            });
+        }
+
+        Ok(InboundEmail {
+            message_id: message.message_id,
+            from: message.from,
+            to: message.to,
+            cc: message.cc,
+            subject: message.subject,
+            in_reply_to: message.in_reply_to,
+            references: message.references,
+            text: message.text,
+            html: message.html,
+            attachments,
+            mailbox,
+            thread,
+            received_at: unix_now(),
+        })
+    }
+
+    async fn dispatch(&self, email: &InboundEmail) -> Result<(), InboundError> {
+        // No subscribers is fine
+        let _ = self.events.send(email.clone());
+
+        let Some(factory) = email
+            .mailbox
+            .as_ref()
+            .and_then(|mailbox| self.routes.get(mailbox))
+        else {
+            debug!(mailbox = ?email.mailbox, "No job route for inbound email");
+            return Ok(());
+        };
+        let Some(agent) = &self.job_agent else {
+            warn!(mailbox = ?email.mailbox, "Inbound email route has no job agent");
+            return Ok(());
+        };
+        agent.send(factory(email.clone())?).await;
+        Ok(())
+    }
+
+    /// Inbound routes
+    ///
+    /// - `POST /email/inbound` receives a signed raw message; exempt it
+    ///   from CSRF
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/email/inbound", post(webhook))
+            .layer(DefaultBodyLimit::max(self.max_message_size))
+            .with_state(self.clone())
+    }
+}
+
+async fn webhook(
+    State(inbound): State<Inbound>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, InboundError> {
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(InboundError::InvalidSignature)?;
+    inbound.verify_signature(&body, signature)?;
+    inbound.receive(&body).await?;
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::storage::LocalFileStorage;
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    const SECRET: &str = "inbound-secret";
+
+    fn inbound(dir: &std::path::Path) -> Inbound {
+        let storage = LocalFileStorage::new(dir.to_path_buf()).unwrap();
+        Inbound::new(
+            Arc::new(storage),
+            ReplyAddresses::new("app.example.com", "reply-secret"),
+            SECRET,
+        )
+    }
+
+    fn sign(body: &[u8]) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn reply(to: &str) -> String {
+        format!(
+            "From: Ada <ada@example.com>\r\n\
+To: {to}\r\n\
+Subject: Re: Ticket 42\r\n\
+Message-ID: <reply-1@example.com>\r\n\
+In-Reply-To: <ticket-42@app.example.com>\r\n\
+Content-Type: multipart/mixed; boundary=b\r\n\
+\r\n\
+--b\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+Works for me.\r\n\
+--b\r\n\
+Content-Type: text/csv; name=data.csv\r\n\
+\r\n\
+a,b\r\n\
+--b--\r\n"
+        )
+    }
+
+    #[tokio::test]
+    async fn test_receive_matches_thread_and_stores_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        let inbound = inbound(dir.path());
+        let address = inbound.replies().address("ticket-42").unwrap();
+        let mut events = inbound.subscribe();
+
+        let email = inbound.receive(reply(&address).as_bytes()).await.unwrap();
+        assert_eq!(email.thread.as_deref(), Some("ticket-42"));
+        assert_eq!(email.mailbox.as_deref(), Some("reply"));
+        assert_eq!(email.from.as_ref().unwrap().email, "ada@example.com");
+        assert_eq!(email.message_id.as_deref(), Some("reply-1@example.com"));
+        assert_eq!(
+            email.in_reply_to.as_deref(),
+            Some("ticket-42@app.example.com")
+        );
+        assert_eq!(email.text.as_deref(), Some("Works for me."));
+
+        assert_eq!(email.attachments.len(), 1);
+        let attachment = &email.attachments[0];
+        assert_eq!(attachment.filename, "data.csv");
+        assert_eq!(attachment.size, 3);
+        let stored = inbound.storage.retrieve(&attachment.file_id).await.unwrap();
+        assert_eq!(stored, b"a,b");
+
+        assert_eq!(events.recv().await.unwrap(), email);
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_reply_has_no_thread() {
+        let dir = tempfile::tempdir().unwrap();
+        let inbound = inbound(dir.path());
+
+        let email = inbound
+            .receive(reply("Support <support+billing@App.Example.com>").as_bytes())
+            .await
+            .unwrap();
+        assert!(email.thread.is_none());
+        assert_eq!(email.mailbox.as_deref(), Some("support"));
+
+        let forged = reply("reply+ticket-42.0000000000000000@app.example.com");
+        let email = inbound.receive(forged.as_bytes()).await.unwrap();
+        assert!(email.thread.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_requires_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let app: Router = inbound(dir.path()).routes();
+        let body = reply("support@app.example.com");
+
+        let request = |signature: Option<String>| {
+            let mut builder = Request::post("/email/inbound");
+            if let Some(signature) = signature {
+                builder = builder.header(SIGNATURE_HEADER, signature);
+            }
+            builder.body(Body::from(body.clone())).unwrap()
+        };
+
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app
+            .clone()
+            .oneshot(request(Some(sign(b"other body"))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app
+            .oneshot(request(Some(sign(body.as_bytes()))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
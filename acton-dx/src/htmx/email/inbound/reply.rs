@@ -0,0 +1,175 @@
+//! Signed plus-addressing tokens for reply threading
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::InboundError;
+
+/// Bytes of the HMAC kept in the token (16 hex characters)
+const SIGNATURE_BYTES: usize = 8;
+
+/// Longest local part RFC 5321 allows
+const MAX_LOCAL_PART: usize = 64;
+
+/// Reply-to addresses carrying a signed thread ID
+///
+/// `reply+ticket-42.1f2e3d4c5b6a7988@app.example.com` routes a reply back to
+/// thread `ticket-42`. The signature stops senders from guessing addresses
+/// that post into other threads.
+///
+/// Some mail servers lowercase local parts, so thread IDs are limited to
+/// lowercase ASCII letters, digits, `-` and `_`, and must be short enough to
+/// fit the 64-character local part (a UUID fits).
+#[derive(Clone)]
+pub struct ReplyAddresses {
+    mailbox: String,
+    domain: String,
+    secret: String,
+}
+
+impl std::fmt::Debug for ReplyAddresses {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplyAddresses")
+            .field("mailbox", &self.mailbox)
+            .field("domain", &self.domain)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReplyAddresses {
+    /// Reply addresses `reply+...@domain` signed with `secret`
+    #[must_use]
+    pub fn new(domain: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            mailbox: "reply".to_string(),
+            domain: domain.into().to_ascii_lowercase(),
+            secret: secret.into(),
+        }
+    }
+
+    /// Use a mailbox other than `reply`
+    #[must_use]
+    pub fn with_mailbox(mut self, mailbox: impl Into<String>) -> Self {
+        self.mailbox = mailbox.into().to_ascii_lowercase();
+        self
+    }
+
+    /// Domain the addresses belong to
+    #[must_use]
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// Mailbox (local part before `+`) of the addresses
+    #[must_use]
+    pub fn mailbox(&self) -> &str {
+        &self.mailbox
+    }
+
+    /// Reply address for a thread
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InboundError::InvalidThread`] if the thread ID has other
+    /// characters than lowercase letters, digits, `-` and `_`, or is too long.
+    pub fn address(&self, thread: &str) -> Result<String, InboundError> {
+        let valid = !thread.is_empty()
+            && thread
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_');
+        let local_part = format!("{}+{thread}.{}", self.mailbox, self.sign(thread));
+        if !valid || local_part.len() > MAX_LOCAL_PART {
+            return Err(InboundError::InvalidThread(thread.to_string()));
+        }
+        Ok(format!("{local_part}@{}", self.domain))
+    }
+
+    /// Thread of a reply address, if it is one of ours and correctly signed
+    #[must_use]
+    pub fn thread(&self, address: &str) -> Option<String> {
+        let address = address.trim().to_ascii_lowercase();
+        let (local_part, domain) = address.rsplit_once('@')?;
+        if domain != self.domain {
+            return None;
+        }
+        let (mailbox, token) = local_part.split_once('+')?;
+        if mailbox != self.mailbox {
+            return None;
+        }
+        let (thread, signature) = token.rsplit_once('.')?;
+        let signature = hex::decode(signature).ok()?;
+        if signature.len() != SIGNATURE_BYTES {
+            return None;
+        }
+        self.mac(thread)
+            .verify_truncated_left(&signature)
+            .ok()
+            .map(|()| thread.to_string())
+    }
+
+    fn sign(&self, thread: &str) -> String {
+        let signature = self.mac(thread).finalize().into_bytes();
+        hex::encode(&signature[..SIGNATURE_BYTES])
+    }
+
+    fn mac(&self, thread: &str) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(self.mailbox.as_bytes());
+        mac.update(b"+");
+        mac.update(thread.as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replies() -> ReplyAddresses {
+        ReplyAddresses::new("App.Example.com", "secret")
+    }
+
+    #[test]
+    fn test_address_round_trip() {
+        let replies = replies();
+        let address = replies.address("ticket-42").unwrap();
+        assert!(address.starts_with("reply+ticket-42."));
+        assert!(address.ends_with("@app.example.com"));
+
+        assert_eq!(replies.thread(&address).as_deref(), Some("ticket-42"));
+        // Servers that uppercase the address still match
+        assert_eq!(
+            replies.thread(&address.to_ascii_uppercase()).as_deref(),
+            Some("ticket-42")
+        );
+    }
+
+    #[test]
+    fn test_forged_addresses_are_rejected() {
+        let replies = replies();
+        let address = replies.address("ticket-42").unwrap();
+
+        let forged = address.replace("ticket-42", "ticket-43");
+        assert!(replies.thread(&forged).is_none());
+        let other_domain = address.replace("app.example.com", "evil.example.com");
+        assert!(replies.thread(&other_domain).is_none());
+        let other_secret = ReplyAddresses::new("app.example.com", "other");
+        assert!(other_secret.thread(&address).is_none());
+        let other_mailbox = replies.clone().with_mailbox("support");
+        assert!(other_mailbox.thread(&address).is_none());
+        assert!(replies.thread("reply@app.example.com").is_none());
+    }
+
+    #[test]
+    fn test_invalid_threads() {
+        let replies = replies();
+        assert!(replies.address("Ticket-42").is_err());
+        assert!(replies.address("a.b").is_err());
+        assert!(replies.address("").is_err());
+        assert!(replies
+            .address("3f2b8c1e-9d4a-4b7e-8f6c-2a1d0e9b7c5f")
+            .is_ok());
+        assert!(replies.address(&"x".repeat(60)).is_err());
+    }
+}
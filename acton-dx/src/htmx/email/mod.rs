@@ -5,6 +5,7 @@
 //! - Askama template integration for HTML and plain text emails
 //! - Background job integration for async sending
 //! - Common email flows (welcome, verification, password reset)
+//! - Inbound email webhooks with reply threading ([`inbound`])
 //!
 //! # Examples
 //!
@@ -59,6 +60,7 @@
 mod backend;
 mod builder;
 mod error;
+pub mod inbound;
 mod job;
 mod sender;
 mod template;
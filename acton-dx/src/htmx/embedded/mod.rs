@@ -126,7 +126,7 @@ impl EmbeddedServicesConfig {
 
     /// Get the port for a specific service.
     #[must_use]
-    pub fn port_for(&self, service: ServiceType) -> u16 {
+    pub const fn port_for(&self, service: ServiceType) -> u16 {
         self.base_port + service.port_offset()
     }
 
@@ -297,6 +297,7 @@ impl EmbeddedServices {
     }
 
     /// Spawn a single service task.
+    #[allow(clippy::unused_async)] // Binding the real tonic servers will await
     async fn spawn_service(
         &self,
         service_type: ServiceType,
@@ -322,7 +323,7 @@ impl EmbeddedServices {
             );
 
             // Wait for shutdown signal
-            let _ = shutdown_rx.recv();
+            let _ = shutdown_rx.recv().await;
 
             tracing::info!(
                 service = %service_name,
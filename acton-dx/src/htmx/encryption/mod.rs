@@ -0,0 +1,659 @@
+//! Field-level encryption for sensitive columns
+//!
+//! Values are encrypted with AES-256-GCM before they leave the application
+//! and decrypted on read, so the database (and its backups) only ever holds
+//! ciphertext.
+//!
+//! - [`Keyring`] holds versioned encryption keys. New values use the primary
+//!   key; older keys stay available for decryption until every row has been
+//!   rotated with [`Keyring::rotate`].
+//! - [`EncryptedString`] and [`EncryptedJson`] encrypt transparently through
+//!   sqlx (and data-service values with `microservices`) using the keyring
+//!   registered with [`install`].
+//! - [`Keyring::blind_index`] derives a keyed hash for equality lookups on
+//!   encrypted columns (`WHERE email_index = $1`).
+//!
+//! Ciphertext is stored as text: `v{key version}:{base64(nonce || ciphertext)}`.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::encryption::{self, EncryptedString, Keyring};
+//!
+//! // ACTON_ENCRYPTION_KEYS="2:<base64 key>,1:<base64 key>" (primary first)
+//! // ACTON_BLIND_INDEX_KEY="<base64 key>"
+//! encryption::install(Keyring::from_env()?)?;
+//!
+//! let ssn = EncryptedString::new("123-45-6789");
+//! let index = encryption::keyring()?.blind_index("123-45-6789")?;
+//! sqlx::query("INSERT INTO patients (ssn, ssn_index) VALUES ($1, $2)")
+//!     .bind(&ssn)
+//!     .bind(&index)
+//!     .execute(&pool)
+//!     .await?;
+//! ```
+
+#[cfg(feature = "microservices")]
+mod proto;
+
+#[cfg(feature = "microservices")]
+pub use proto::rotate_column;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// Environment variable holding `version:base64key` pairs, primary first
+pub const KEYS_ENV: &str = "ACTON_ENCRYPTION_KEYS";
+
+/// Environment variable holding the base64 blind index key
+pub const BLIND_INDEX_KEY_ENV: &str = "ACTON_BLIND_INDEX_KEY";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+static KEYRING: OnceLock<Keyring> = OnceLock::new();
+
+/// Encryption errors
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    /// Key material is not a base64-encoded 256-bit key
+    #[error("Invalid encryption key: {0}")]
+    InvalidKey(String),
+
+    /// Ciphertext references a key version the keyring does not have
+    #[error("Unknown encryption key version {0}")]
+    UnknownKeyVersion(u32),
+
+    /// Value is not in the `v{version}:{payload}` format
+    #[error("Malformed ciphertext")]
+    Malformed,
+
+    /// Authentication failed: wrong key or tampered ciphertext
+    #[error("Decryption failed")]
+    Decrypt,
+
+    /// Encrypted JSON could not be serialized or deserialized
+    #[error("Encrypted JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// No blind index key is configured
+    #[error("No blind index key configured")]
+    NoBlindIndexKey,
+
+    /// [`install`] has not been called
+    #[error("No encryption keyring installed")]
+    NotInstalled,
+
+    /// [`install`] was called more than once
+    #[error("An encryption keyring is already installed")]
+    AlreadyInstalled,
+
+    /// Data-service call failed
+    #[error("Data service error: {0}")]
+    Store(String),
+}
+
+/// Versioned encryption keys
+#[derive(Clone)]
+pub struct Keyring {
+    keys: BTreeMap<u32, [u8; KEY_LEN]>,
+    primary: u32,
+    blind_index_key: Option<[u8; KEY_LEN]>,
+}
+
+impl std::fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keyring")
+            .field("versions", &self.keys.keys().collect::<Vec<_>>())
+            .field("primary", &self.primary)
+            .field("blind_index", &self.blind_index_key.is_some())
+            .finish()
+    }
+}
+
+impl Keyring {
+    /// Create a keyring whose primary key is `key` (base64, 32 bytes)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::InvalidKey`] if the key does not decode to
+    /// 32 bytes.
+    pub fn new(version: u32, key: &str) -> Result<Self, EncryptionError> {
+        Ok(Self {
+            keys: BTreeMap::from([(version, decode_key(key)?)]),
+            primary: version,
+            blind_index_key: None,
+        })
+    }
+
+    /// Add an older key used only to decrypt existing values
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::InvalidKey`] if the key does not decode to
+    /// 32 bytes.
+    pub fn with_key(mut self, version: u32, key: &str) -> Result<Self, EncryptionError> {
+        self.keys.insert(version, decode_key(key)?);
+        Ok(self)
+    }
+
+    /// Set the key used for blind indexes
+    ///
+    /// Keep this key separate from the encryption keys: rotating it changes
+    /// every index value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::InvalidKey`] if the key does not decode to
+    /// 32 bytes.
+    pub fn with_blind_index_key(mut self, key: &str) -> Result<Self, EncryptionError> {
+        self.blind_index_key = Some(decode_key(key)?);
+        Ok(self)
+    }
+
+    /// Load keys from [`KEYS_ENV`] and, if set, [`BLIND_INDEX_KEY_ENV`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::InvalidKey`] if the variable is missing or
+    /// malformed.
+    pub fn from_env() -> Result<Self, EncryptionError> {
+        let keys = std::env::var(KEYS_ENV)
+            .map_err(|_| EncryptionError::InvalidKey(format!("{KEYS_ENV} is not set")))?;
+        let keyring = Self::parse(&keys)?;
+        match std::env::var(BLIND_INDEX_KEY_ENV) {
+            Ok(key) => keyring.with_blind_index_key(&key),
+            Err(_) => Ok(keyring),
+        }
+    }
+
+    /// Parse comma-separated `version:base64key` pairs, primary first
+    ///
+    /// This is the [`KEYS_ENV`] format, also used by config files.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::InvalidKey`] if `keys` is empty or malformed.
+    pub fn parse(keys: &str) -> Result<Self, EncryptionError> {
+        let mut keyring: Option<Self> = None;
+        for entry in keys.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (version, key) = entry.split_once(':').ok_or_else(|| {
+                EncryptionError::InvalidKey("expected version:key".to_string())
+            })?;
+            let version = version.parse().map_err(|_| {
+                EncryptionError::InvalidKey(format!("invalid key version {version:?}"))
+            })?;
+            keyring = Some(match keyring {
+                Some(keyring) => keyring.with_key(version, key)?,
+                None => Self::new(version, key)?,
+            });
+        }
+        keyring.ok_or_else(|| EncryptionError::InvalidKey("no keys configured".to_string()))
+    }
+
+    /// Generate a random base64-encoded key
+    #[must_use]
+    pub fn generate_key() -> String {
+        STANDARD.encode(Aes256Gcm::generate_key(OsRng))
+    }
+
+    /// Version of the key used for new values
+    #[must_use]
+    pub const fn primary_version(&self) -> u32 {
+        self.primary
+    }
+
+    /// Encrypt bytes with the primary key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::Decrypt`] if the cipher rejects the input,
+    /// which only happens for inputs larger than AES-GCM allows.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<String, EncryptionError> {
+        let version = self.primary;
+        let cipher = self.cipher(version)?;
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let aad = version.to_be_bytes();
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| EncryptionError::Decrypt)?;
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("v{version}:{}", URL_SAFE_NO_PAD.encode(payload)))
+    }
+
+    /// Decrypt a value produced by [`Keyring::encrypt`] with any known key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::Malformed`], [`EncryptionError::UnknownKeyVersion`]
+    /// or [`EncryptionError::Decrypt`].
+    pub fn decrypt(&self, value: &str) -> Result<Vec<u8>, EncryptionError> {
+        let (version, payload) = parse(value)?;
+        if payload.len() < NONCE_LEN {
+            return Err(EncryptionError::Malformed);
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| EncryptionError::Malformed)?;
+        let aad = version.to_be_bytes();
+        self.cipher(version)?
+            .decrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| EncryptionError::Decrypt)
+    }
+
+    /// Whether a stored value was encrypted with an older key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::Malformed`] if the value is not ciphertext.
+    pub fn needs_rotation(&self, value: &str) -> Result<bool, EncryptionError> {
+        Ok(parse(value)?.0 != self.primary)
+    }
+
+    /// Re-encrypt a stored value with the primary key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value cannot be decrypted.
+    pub fn rotate(&self, value: &str) -> Result<String, EncryptionError> {
+        self.encrypt(&self.decrypt(value)?)
+    }
+
+    /// Keyed hash of `value` for equality lookups (64 hex characters)
+    ///
+    /// Normalize the value first (case, whitespace) if lookups should ignore
+    /// those differences; the index only matches exact input.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::NoBlindIndexKey`] if no index key is set.
+    pub fn blind_index(&self, value: &str) -> Result<String, EncryptionError> {
+        let key = self
+            .blind_index_key
+            .as_ref()
+            .ok_or(EncryptionError::NoBlindIndexKey)?;
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key)
+            .map_err(|e| EncryptionError::InvalidKey(e.to_string()))?;
+        mac.update(value.as_bytes());
+        Ok(hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn cipher(&self, version: u32) -> Result<Aes256Gcm, EncryptionError> {
+        self.keys
+            .get(&version)
+            .map(|key| Aes256Gcm::new(&Key::<Aes256Gcm>::from(*key)))
+            .ok_or(EncryptionError::UnknownKeyVersion(version))
+    }
+}
+
+/// Register the process-wide keyring used by [`EncryptedString`] and
+/// [`EncryptedJson`]
+///
+/// # Errors
+///
+/// Returns [`EncryptionError::AlreadyInstalled`] if called twice.
+pub fn install(keyring: Keyring) -> Result<(), EncryptionError> {
+    KEYRING
+        .set(keyring)
+        .map_err(|_| EncryptionError::AlreadyInstalled)
+}
+
+/// The keyring registered with [`install`]
+///
+/// # Errors
+///
+/// Returns [`EncryptionError::NotInstalled`] before [`install`] is called.
+pub fn keyring() -> Result<&'static Keyring, EncryptionError> {
+    KEYRING.get().ok_or(EncryptionError::NotInstalled)
+}
+
+/// A string stored encrypted
+///
+/// Holds the plaintext in memory; encrypts when bound as a query parameter
+/// and decrypts when read from a row. `Debug` never prints the value.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct EncryptedString(String);
+
+impl EncryptedString {
+    /// Wrap a plaintext value
+    #[must_use]
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The plaintext value
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Consume into the plaintext value
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+
+    /// Encrypt with the installed keyring
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no keyring is installed.
+    pub fn encrypt(&self) -> Result<String, EncryptionError> {
+        keyring()?.encrypt(self.0.as_bytes())
+    }
+
+    /// Decrypt a stored value with the installed keyring
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decryption fails or the plaintext is not UTF-8.
+    pub fn decrypt(stored: &str) -> Result<Self, EncryptionError> {
+        let bytes = keyring()?.decrypt(stored)?;
+        String::from_utf8(bytes)
+            .map(Self)
+            .map_err(|_| EncryptionError::Decrypt)
+    }
+}
+
+impl std::fmt::Debug for EncryptedString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptedString(***)")
+    }
+}
+
+impl From<String> for EncryptedString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for EncryptedString {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+/// A serializable value stored as encrypted JSON
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct EncryptedJson<T>(pub T);
+
+impl<T> EncryptedJson<T> {
+    /// Wrap a value
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Consume into the wrapped value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Serialize> EncryptedJson<T> {
+    /// Serialize and encrypt with the installed keyring
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no keyring is installed or serialization fails.
+    pub fn encrypt(&self) -> Result<String, EncryptionError> {
+        keyring()?.encrypt(&serde_json::to_vec(&self.0)?)
+    }
+}
+
+impl<T: DeserializeOwned> EncryptedJson<T> {
+    /// Decrypt and deserialize a stored value with the installed keyring
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decryption or deserialization fails.
+    pub fn decrypt(stored: &str) -> Result<Self, EncryptionError> {
+        Ok(Self(serde_json::from_slice(&keyring()?.decrypt(stored)?)?))
+    }
+}
+
+impl<T> std::ops::Deref for EncryptedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> std::fmt::Debug for EncryptedJson<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptedJson(***)")
+    }
+}
+
+macro_rules! impl_sqlx_text {
+    ($ty:ty, [$($bounds:tt)*]) => {
+        impl<DB: sqlx::Database, $($bounds)*> sqlx::Type<DB> for $ty
+        where
+            String: sqlx::Type<DB>,
+        {
+            fn type_info() -> DB::TypeInfo {
+                <String as sqlx::Type<DB>>::type_info()
+            }
+
+            fn compatible(ty: &DB::TypeInfo) -> bool {
+                <String as sqlx::Type<DB>>::compatible(ty)
+            }
+        }
+    };
+}
+
+impl_sqlx_text!(EncryptedString, []);
+impl_sqlx_text!(EncryptedJson<T>, [T]);
+
+impl<'q, DB: sqlx::Database> sqlx::Encode<'q, DB> for EncryptedString
+where
+    String: sqlx::Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        self.encrypt()?.encode_by_ref(buf)
+    }
+}
+
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for EncryptedString
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(
+        value: <DB as sqlx::Database>::ValueRef<'r>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let stored = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(Self::decrypt(&stored)?)
+    }
+}
+
+impl<'q, DB: sqlx::Database, T: Serialize> sqlx::Encode<'q, DB> for EncryptedJson<T>
+where
+    String: sqlx::Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        self.encrypt()?.encode_by_ref(buf)
+    }
+}
+
+impl<'r, DB: sqlx::Database, T: DeserializeOwned> sqlx::Decode<'r, DB> for EncryptedJson<T>
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(
+        value: <DB as sqlx::Database>::ValueRef<'r>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let stored = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(Self::decrypt(&stored)?)
+    }
+}
+
+fn decode_key(key: &str) -> Result<[u8; KEY_LEN], EncryptionError> {
+    STANDARD
+        .decode(key.trim())
+        .map_err(|e| EncryptionError::InvalidKey(e.to_string()))?
+        .try_into()
+        .map_err(|_| EncryptionError::InvalidKey(format!("expected {KEY_LEN} bytes")))
+}
+
+fn parse(value: &str) -> Result<(u32, Vec<u8>), EncryptionError> {
+    let (version, payload) = value
+        .strip_prefix('v')
+        .and_then(|rest| rest.split_once(':'))
+        .ok_or(EncryptionError::Malformed)?;
+    let version = version.parse().map_err(|_| EncryptionError::Malformed)?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| EncryptionError::Malformed)?;
+    Ok((version, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    const KEY_1: &str = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+    const KEY_2: &str = "AgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgI=";
+
+    fn installed() -> &'static Keyring {
+        let _ = install(
+            Keyring::new(1, KEY_1)
+                .unwrap()
+                .with_blind_index_key(KEY_2)
+                .unwrap(),
+        );
+        keyring().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_and_tamper() {
+        let keyring = Keyring::new(1, KEY_1).unwrap();
+        let stored = keyring.encrypt(b"secret").unwrap();
+        assert!(stored.starts_with("v1:"));
+        assert_ne!(stored, keyring.encrypt(b"secret").unwrap());
+        assert_eq!(keyring.decrypt(&stored).unwrap(), b"secret");
+
+        let mut tampered = stored.into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert!(matches!(
+            keyring.decrypt(&tampered),
+            Err(EncryptionError::Decrypt | EncryptionError::Malformed)
+        ));
+        assert!(matches!(
+            keyring.decrypt("plaintext"),
+            Err(EncryptionError::Malformed)
+        ));
+        assert!(Keyring::new(1, "c2hvcnQ=").is_err());
+    }
+
+    #[test]
+    fn test_rotation() {
+        let old = Keyring::new(1, KEY_1).unwrap();
+        let stored = old.encrypt(b"secret").unwrap();
+
+        let new = Keyring::parse(&format!("2:{KEY_2}, 1:{KEY_1}")).unwrap();
+        assert_eq!(new.primary_version(), 2);
+        assert!(Keyring::parse(" , ").is_err());
+        assert!(new.needs_rotation(&stored).unwrap());
+        let rotated = new.rotate(&stored).unwrap();
+        assert!(rotated.starts_with("v2:"));
+        assert!(!new.needs_rotation(&rotated).unwrap());
+        assert_eq!(new.decrypt(&rotated).unwrap(), b"secret");
+        assert!(matches!(
+            old.decrypt(&rotated),
+            Err(EncryptionError::UnknownKeyVersion(2))
+        ));
+    }
+
+    #[test]
+    fn test_blind_index() {
+        let keyring = installed();
+        let a = keyring.blind_index("alice@example.com").unwrap();
+        assert_eq!(a.len(), 64);
+        assert_eq!(a, keyring.blind_index("alice@example.com").unwrap());
+        assert_ne!(a, keyring.blind_index("bob@example.com").unwrap());
+        assert!(matches!(
+            Keyring::new(1, KEY_1).unwrap().blind_index("x"),
+            Err(EncryptionError::NoBlindIndexKey)
+        ));
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Card {
+        last4: String,
+    }
+
+    #[tokio::test]
+    async fn test_sqlx_round_trip() {
+        installed();
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query("CREATE TABLE secrets (token TEXT, card TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO secrets (token, card) VALUES ($1, $2)")
+            .bind(EncryptedString::new("tok_123"))
+            .bind(EncryptedJson::new(Card {
+                last4: "4242".into(),
+            }))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let raw: String = sqlx::query_scalar("SELECT token FROM secrets")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(raw.starts_with("v1:"));
+
+        let (token, card): (EncryptedString, EncryptedJson<Card>) =
+            sqlx::query_as("SELECT token, card FROM secrets")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(token.expose(), "tok_123");
+        assert_eq!(card.last4, "4242");
+        assert_eq!(format!("{token:?}"), "EncryptedString(***)");
+    }
+
+    #[test]
+    fn test_installed_helpers() {
+        installed();
+        let secret = EncryptedString::new("hunter2");
+        let stored = secret.encrypt().unwrap();
+        assert_eq!(EncryptedString::decrypt(&stored).unwrap(), secret);
+
+        let card = EncryptedJson::new(Card {
+            last4: "4242".into(),
+        });
+        let stored = card.encrypt().unwrap();
+        assert_eq!(EncryptedJson::<Card>::decrypt(&stored).unwrap().0, card.0);
+    }
+}
@@ -0,0 +1,150 @@
+//! Conversions to and from data-service values, and key rotation
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{keyring, EncryptedJson, EncryptedString, EncryptionError};
+use crate::htmx::clients::{DataClient, Value};
+use crate::htmx::util::validate_identifier;
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+impl EncryptedString {
+    /// Encrypted query parameter
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no keyring is installed.
+    pub fn to_value(&self) -> Result<Value, EncryptionError> {
+        Ok(text(self.encrypt()?))
+    }
+
+    /// Decrypt a data-service column value
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::Malformed`] if the value is not text, or a
+    /// decryption error.
+    pub fn from_value(value: &Value) -> Result<Self, EncryptionError> {
+        Self::decrypt(as_text(value)?)
+    }
+}
+
+impl<T: Serialize> EncryptedJson<T> {
+    /// Encrypted query parameter
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no keyring is installed or serialization fails.
+    pub fn to_value(&self) -> Result<Value, EncryptionError> {
+        Ok(text(self.encrypt()?))
+    }
+}
+
+impl<T: DeserializeOwned> EncryptedJson<T> {
+    /// Decrypt a data-service column value
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::Malformed`] if the value is not text, or a
+    /// decryption error.
+    pub fn from_value(value: &Value) -> Result<Self, EncryptionError> {
+        Self::decrypt(as_text(value)?)
+    }
+}
+
+/// Re-encrypt every value in `table.column` that still uses an older key
+///
+/// Works in batches of `batch_size` rows identified by `id_column`. Each
+/// update only applies if the stored value is unchanged, so concurrent writes
+/// are never overwritten. Returns the number of rows rotated.
+///
+/// # Errors
+///
+/// Returns [`EncryptionError::Store`] for invalid identifiers or failed
+/// data-service calls, or a decryption error for unreadable values.
+pub async fn rotate_column(
+    client: &Arc<RwLock<DataClient>>,
+    table: &str,
+    id_column: &str,
+    column: &str,
+    batch_size: u32,
+) -> Result<i64, EncryptionError> {
+    for identifier in [table, id_column, column] {
+        validate_identifier(identifier).map_err(EncryptionError::Store)?;
+    }
+    let keyring = keyring()?;
+    let current = format!("v{}:%", keyring.primary_version());
+    let select = format!(
+        "SELECT {id_column} AS id, {column} AS value FROM {table} \
+         WHERE {column} IS NOT NULL AND {column} NOT LIKE $1 LIMIT {batch_size}"
+    );
+    let update =
+        format!("UPDATE {table} SET {column} = $1 WHERE {id_column} = $2 AND {column} = $3");
+
+    let mut rotated = 0;
+    loop {
+        let rows = client
+            .write()
+            .await
+            .query(&select, vec![text(current.clone())], None)
+            .await
+            .map_err(|e| EncryptionError::Store(e.to_string()))?;
+        if rows.is_empty() {
+            return Ok(rotated);
+        }
+        let mut progressed = false;
+        for row in rows {
+            let (Some(id), Some(value)) = (row.columns.get("id"), row.columns.get("value")) else {
+                return Err(EncryptionError::Malformed);
+            };
+            let stored = as_text(value)?;
+            let result = client
+                .write()
+                .await
+                .execute(
+                    &update,
+                    vec![text(keyring.rotate(stored)?), id.clone(), value.clone()],
+                    None,
+                )
+                .await
+                .map_err(|e| EncryptionError::Store(e.to_string()))?;
+            rotated += result.rows_affected;
+            progressed |= result.rows_affected > 0;
+        }
+        // Every row in the batch changed underneath us; try again later
+        if !progressed {
+            return Ok(rotated);
+        }
+    }
+}
+
+const fn text(value: String) -> Value {
+    Value {
+        value: Some(ValueKind::StringValue(value)),
+    }
+}
+
+fn as_text(value: &Value) -> Result<&str, EncryptionError> {
+    match &value.value {
+        Some(ValueKind::StringValue(stored)) => Ok(stored),
+        _ => Err(EncryptionError::Malformed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_conversions() {
+        assert!(matches!(
+            EncryptedString::from_value(&Value {
+                value: Some(ValueKind::IntValue(1))
+            }),
+            Err(EncryptionError::Malformed)
+        ));
+        assert!(validate_identifier("public.users").is_ok());
+        assert!(validate_identifier("users; DROP TABLE x").is_err());
+    }
+}
@@ -2,8 +2,12 @@
 
 #![allow(dead_code)]
 
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
 use thiserror::Error;
 
+use super::feedback::error_fragment;
+
 /// Framework error type
 #[derive(Debug, Error)]
 pub enum ActonHtmxError {
@@ -43,3 +47,60 @@ pub enum ActonHtmxError {
     #[error("Not found: {0}")]
     NotFound(String),
 }
+
+/// Responds with an [`error_fragment`], which the feedback script shows as
+/// a toast
+impl IntoResponse for ActonHtmxError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            Self::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            Self::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            // Internal details are logged, not shown to the user
+            error @ (Self::Config(_)
+            | Self::ServerError(_)
+            | Self::Database(_)
+            | Self::OAuth(_)
+            | Self::SessionError(_)) => {
+                tracing::error!(error = %error, "Request failed");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                )
+            }
+        };
+
+        (status, Html(error_fragment(status, &message))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_errors_keep_their_message() {
+        let response =
+            ActonHtmxError::Forbidden("OAuth2 state mismatch".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_response_is_error_fragment() {
+        let response = ActonHtmxError::NotFound("Order 7".to_string()).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(super::super::feedback::ERROR_ATTRIBUTE));
+        assert!(body.contains(">Order 7</div>"));
+    }
+
+    #[test]
+    fn test_server_errors_are_internal() {
+        let response = ActonHtmxError::ServerError("Token exchange failed: secret".to_string())
+            .into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
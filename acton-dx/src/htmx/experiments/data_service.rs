@@ -0,0 +1,142 @@
+//! Experiment store backed by data-service
+
+use async_trait::async_trait;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{ExperimentError, ExperimentEvent, ExperimentStore, VariantCounts};
+use crate::htmx::clients::{DataClient, Row, Value};
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+/// Migration creating the experiment event table
+pub const EXPERIMENTS_MIGRATION: &str = r"CREATE TABLE IF NOT EXISTS experiment_events (
+    experiment TEXT NOT NULL,
+    variant TEXT NOT NULL,
+    unit TEXT NOT NULL,
+    goal TEXT,
+    occurred_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_experiment_events_unit ON experiment_events (experiment, unit, goal);";
+
+const COLUMNS: usize = 5;
+
+/// Experiment store writing batches to `experiment_events`
+///
+/// Each batch is one multi-row `INSERT`; counts are aggregated in SQL.
+#[derive(Debug, Clone)]
+pub struct DataServiceExperimentStore {
+    client: Arc<RwLock<DataClient>>,
+}
+
+impl DataServiceExperimentStore {
+    /// Create a store
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<DataClient>>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl ExperimentStore for DataServiceExperimentStore {
+    async fn insert(&self, events: &[ExperimentEvent]) -> Result<(), ExperimentError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let mut sql = String::from(
+            "INSERT INTO experiment_events (experiment, variant, unit, goal, occurred_at) VALUES ",
+        );
+        let mut params = Vec::with_capacity(events.len() * COLUMNS);
+        for (i, event) in events.iter().enumerate() {
+            let first = i * COLUMNS + 1;
+            let placeholders = (first..first + COLUMNS)
+                .map(|n| format!("${n}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let separator = if i == 0 { "" } else { ", " };
+            let _ = write!(sql, "{separator}({placeholders})");
+            params.extend([
+                text(event.experiment.clone()),
+                text(event.variant.clone()),
+                text(event.unit.clone()),
+                event.goal.clone().map_or(
+                    Value {
+                        value: Some(ValueKind::NullValue(true)),
+                    },
+                    text,
+                ),
+                int(event.occurred_at),
+            ]);
+        }
+        self.client
+            .write()
+            .await
+            .execute(&sql, params, None)
+            .await
+            .map_err(|e| ExperimentError::Store(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn counts(
+        &self,
+        experiment: &str,
+        goal: &str,
+    ) -> Result<Vec<VariantCounts>, ExperimentError> {
+        let rows = self
+            .client
+            .write()
+            .await
+            .query(
+                "SELECT e.variant AS variant, COUNT(DISTINCT e.unit) AS exposures, \
+                 COUNT(DISTINCT c.unit) AS conversions FROM experiment_events e \
+                 LEFT JOIN experiment_events c ON c.experiment = e.experiment \
+                 AND c.unit = e.unit AND c.goal = $2 \
+                 WHERE e.experiment = $1 AND e.goal IS NULL \
+                 GROUP BY e.variant ORDER BY e.variant",
+                vec![text(experiment.to_string()), text(goal.to_string())],
+                None,
+            )
+            .await
+            .map_err(|e| ExperimentError::Store(e.to_string()))?;
+        rows.iter()
+            .map(|row| {
+                Ok(VariantCounts {
+                    variant: string(row, "variant")?,
+                    exposures: count(row, "exposures")?,
+                    conversions: count(row, "conversions")?,
+                })
+            })
+            .collect()
+    }
+}
+
+fn count(row: &Row, name: &str) -> Result<u64, ExperimentError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::IntValue(value)) => u64::try_from(*value)
+            .map_err(|_| ExperimentError::Store(format!("invalid experiment column {name}"))),
+        _ => Err(ExperimentError::Store(format!(
+            "invalid experiment column {name}"
+        ))),
+    }
+}
+
+fn string(row: &Row, name: &str) -> Result<String, ExperimentError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::StringValue(value)) => Ok(value.clone()),
+        _ => Err(ExperimentError::Store(format!(
+            "invalid experiment column {name}"
+        ))),
+    }
+}
+
+const fn int(value: i64) -> Value {
+    Value {
+        value: Some(ValueKind::IntValue(value)),
+    }
+}
+
+const fn text(value: String) -> Value {
+    Value {
+        value: Some(ValueKind::StringValue(value)),
+    }
+}
@@ -0,0 +1,1026 @@
+//! A/B experiments
+//!
+//! Experiments are defined in configuration ([`ExperimentsConfig`]) as
+//! weighted variants, the first being the control. Each visitor is
+//! assigned by hashing the experiment key with their user id, or their
+//! session id before they sign in, so assignment is deterministic and
+//! needs no coordination between instances. Assignments are saved in the
+//! session, so visitors keep their variant when they sign in and when
+//! weights are changed mid-experiment.
+//!
+//! - [`Experiments::middleware`] assigns the visitor and inserts
+//!   [`Variants`] for every request; it must run inside the session layer
+//! - handlers and templates branch with [`Variants::is`], which records an
+//!   exposure the first time an experiment is checked in a request
+//! - [`Variants::convert`] records a goal reached by the visitor
+//! - [`Experiments::report`] compares each variant's conversion rate with
+//!   the control using a two-proportion z-test, and
+//!   [`Experiments::dashboard_routes`] serves it as an HTMX partial
+//!
+//! Events are buffered and written in batches; run
+//! [`Experiments::spawn_flusher`] to write them on an interval.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::experiments::{Experiments, MemoryExperimentStore, Variants};
+//! use axum::middleware::from_fn_with_state;
+//!
+//! let experiments = Experiments::new(config.experiments.clone(), Arc::new(MemoryExperimentStore::new()))?;
+//! experiments.spawn_flusher(Duration::from_secs(10));
+//!
+//! let app = Router::new()
+//!     .route("/checkout", get(checkout).post(place_order))
+//!     .nest("/admin", experiments.dashboard_routes().layer(require_admin))
+//!     .layer(from_fn_with_state(experiments.clone(), Experiments::middleware));
+//!
+//! async fn place_order(variants: Variants) -> impl IntoResponse {
+//!     variants.convert("purchase");
+//!     // ...
+//! }
+//! ```
+//!
+//! ```html
+//! {% if variants.is("checkout-button", "green") %}
+//!   <button class="btn-green">Buy now</button>
+//! {% else %}
+//!   <button class="btn">Buy now</button>
+//! {% endif %}
+//! ```
+
+#[cfg(feature = "microservices")]
+mod data_service;
+
+#[cfg(feature = "microservices")]
+pub use data_service::{DataServiceExperimentStore, EXPERIMENTS_MIGRATION};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Path, Query, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::convert::Infallible;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::htmx::auth::session::{SessionData, SessionId};
+use crate::htmx::template::helpers::escape_html;
+pub use crate::htmx::config::{ExperimentConfig, ExperimentsConfig, VariantConfig};
+
+/// Session key holding the visitor's assignments
+pub const SESSION_KEY: &str = "_experiments";
+
+/// Buffered events that trigger a write
+const BATCH_SIZE: usize = 100;
+
+/// Batches kept in memory while the store is failing
+const MAX_BUFFERED_BATCHES: usize = 10;
+
+/// Experiment errors
+#[derive(Debug, thiserror::Error)]
+pub enum ExperimentError {
+    /// An experiment definition is unusable
+    #[error("Invalid experiment {0}: {1}")]
+    Invalid(String, String),
+
+    /// No experiment has the requested key
+    #[error("Unknown experiment: {0}")]
+    Unknown(String),
+
+    /// The backing store failed
+    #[error("Experiment store error: {0}")]
+    Store(String),
+}
+
+impl IntoResponse for ExperimentError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Unknown(_) => StatusCode::NOT_FOUND.into_response(),
+            Self::Invalid(..) | Self::Store(_) => {
+                warn!(error = %self, "Experiment request failed");
+                StatusCode::SERVICE_UNAVAILABLE.into_response()
+            }
+        }
+    }
+}
+
+/// An exposure or conversion
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExperimentEvent {
+    /// Experiment key
+    pub experiment: String,
+    /// Variant the visitor was assigned
+    pub variant: String,
+    /// Assignment unit: `user:<id>` or `session:<id>`
+    pub unit: String,
+    /// Goal reached, or `None` for an exposure
+    pub goal: Option<String>,
+    /// Unix time (seconds) the event was recorded
+    pub occurred_at: i64,
+}
+
+/// Distinct exposed and converted units for one variant
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VariantCounts {
+    /// Variant key
+    pub variant: String,
+    /// Units exposed to the variant
+    pub exposures: u64,
+    /// Exposed units that reached the goal
+    pub conversions: u64,
+}
+
+/// Storage for experiment events
+#[async_trait]
+pub trait ExperimentStore: Send + Sync {
+    /// Append a batch of events
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExperimentError::Store`] on failure.
+    async fn insert(&self, events: &[ExperimentEvent]) -> Result<(), ExperimentError>;
+
+    /// Exposed and converted units per variant of `experiment` for `goal`
+    ///
+    /// Conversions count only units that were exposed, attributed to the
+    /// variant they were exposed to.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExperimentError::Store`] on failure.
+    async fn counts(
+        &self,
+        experiment: &str,
+        goal: &str,
+    ) -> Result<Vec<VariantCounts>, ExperimentError>;
+}
+
+/// In-process experiment store
+#[derive(Debug, Default)]
+pub struct MemoryExperimentStore {
+    events: Mutex<Vec<ExperimentEvent>>,
+}
+
+impl MemoryExperimentStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every stored event, oldest first
+    #[must_use]
+    pub fn events(&self) -> Vec<ExperimentEvent> {
+        self.events.lock().clone()
+    }
+}
+
+#[async_trait]
+impl ExperimentStore for MemoryExperimentStore {
+    async fn insert(&self, events: &[ExperimentEvent]) -> Result<(), ExperimentError> {
+        self.events.lock().extend_from_slice(events);
+        Ok(())
+    }
+
+    async fn counts(
+        &self,
+        experiment: &str,
+        goal: &str,
+    ) -> Result<Vec<VariantCounts>, ExperimentError> {
+        let mut exposed: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+        let mut converted = HashSet::new();
+        for event in self.events.lock().iter() {
+            if event.experiment != experiment {
+                continue;
+            }
+            match event.goal.as_deref() {
+                None => {
+                    exposed
+                        .entry(event.variant.clone())
+                        .or_default()
+                        .insert(event.unit.clone());
+                }
+                Some(reached) if reached == goal => {
+                    converted.insert(event.unit.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(exposed
+            .into_iter()
+            .map(|(variant, units)| VariantCounts {
+                exposures: units.len() as u64,
+                conversions: units.intersection(&converted).count() as u64,
+                variant,
+            })
+            .collect())
+    }
+}
+
+/// One variant's results against the control
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantResult {
+    /// Variant key
+    pub variant: String,
+    /// Units exposed
+    pub exposures: u64,
+    /// Exposed units that converted
+    pub conversions: u64,
+    /// `conversions / exposures`
+    pub rate: f64,
+    /// Relative change in rate over the control; `None` for the control
+    /// and when the control has not converted
+    pub lift: Option<f64>,
+    /// Two-sided p-value of the difference from the control; `None` for
+    /// the control and when there is too little data
+    pub p_value: Option<f64>,
+    /// Whether `p_value` is at or below the configured significance
+    pub significant: bool,
+}
+
+/// Conversion results of an experiment for one goal
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentReport {
+    /// Experiment key
+    pub experiment: String,
+    /// Goal the conversions are counted for
+    pub goal: String,
+    /// Largest p-value reported as significant
+    pub significance: f64,
+    /// Results in definition order, control first
+    pub variants: Vec<VariantResult>,
+}
+
+impl ExperimentReport {
+    /// Compare every variant of `experiment` with its control
+    #[must_use]
+    pub fn new(
+        experiment: &ExperimentConfig,
+        goal: &str,
+        significance: f64,
+        counts: &[VariantCounts],
+    ) -> Self {
+        let count = |key: &str| {
+            counts
+                .iter()
+                .find(|counts| counts.variant == key)
+                .map_or((0, 0), |counts| (counts.exposures, counts.conversions))
+        };
+        let control = experiment.control().map(count).unwrap_or_default();
+        let variants = experiment
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(i, variant)| {
+                let (exposures, conversions) = count(&variant.key);
+                let rate = rate(conversions, exposures);
+                let (lift, p_value) = if i == 0 {
+                    (None, None)
+                } else {
+                    let control_rate = rate_of(control);
+                    (
+                        (control_rate > 0.0).then(|| (rate - control_rate) / control_rate),
+                        z_test(control, (exposures, conversions)),
+                    )
+                };
+                VariantResult {
+                    variant: variant.key.clone(),
+                    exposures,
+                    conversions,
+                    rate,
+                    lift,
+                    significant: p_value.is_some_and(|p| p <= significance),
+                    p_value,
+                }
+            })
+            .collect();
+        Self {
+            experiment: experiment.key.clone(),
+            goal: goal.to_string(),
+            significance,
+            variants,
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)] // counts are far below f64 precision limits
+fn rate(conversions: u64, exposures: u64) -> f64 {
+    if exposures == 0 {
+        0.0
+    } else {
+        conversions as f64 / exposures as f64
+    }
+}
+
+fn rate_of((exposures, conversions): (u64, u64)) -> f64 {
+    rate(conversions, exposures)
+}
+
+/// Two-sided p-value of a pooled two-proportion z-test on
+/// `(exposures, conversions)` pairs
+#[allow(clippy::cast_precision_loss)] // counts are far below f64 precision limits
+fn z_test(a: (u64, u64), b: (u64, u64)) -> Option<f64> {
+    let (n_a, n_b) = (a.0 as f64, b.0 as f64);
+    if a.0 == 0 || b.0 == 0 {
+        return None;
+    }
+    let pooled = (a.1 + b.1) as f64 / (n_a + n_b);
+    let error = (pooled * (1.0 - pooled) * (1.0 / n_a + 1.0 / n_b)).sqrt();
+    if error == 0.0 {
+        return None;
+    }
+    let z = (rate_of(b) - rate_of(a)) / error;
+    Some(erfc(z.abs() / std::f64::consts::SQRT_2))
+}
+
+/// Complementary error function, accurate to about 1e-7
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / 0.5f64.mul_add(z, 1.0);
+    let coefficients = [
+        -1.265_512_23,
+        1.000_023_68,
+        0.374_091_96,
+        0.096_784_18,
+        -0.186_288_06,
+        0.278_868_07,
+        -1.135_203_98,
+        1.488_515_87,
+        -0.822_152_23,
+        0.170_872_77,
+    ];
+    let poly = coefficients
+        .iter()
+        .rev()
+        .fold(0.0_f64, |acc, coefficient| acc.mul_add(t, *coefficient));
+    let result = t * (-z).mul_add(z, poly).exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+/// Bucket in `0..buckets` for `unit` in `experiment`, from the `part`th
+/// eight bytes of their hash
+fn bucket(experiment: &str, unit: &str, part: usize, buckets: u64) -> u64 {
+    let hash = Sha256::new()
+        .chain_update(experiment)
+        .chain_update([0])
+        .chain_update(unit)
+        .finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[part * 8..part * 8 + 8]);
+    u64::from_be_bytes(bytes) % buckets.max(1)
+}
+
+/// Deterministic assignment of `unit`, or `None` when it falls outside the
+/// experiment's traffic
+fn assign<'a>(experiment: &'a ExperimentConfig, unit: &str) -> Option<&'a str> {
+    if !experiment.active
+        || bucket(&experiment.key, unit, 0, 100) >= u64::from(experiment.traffic_percent)
+    {
+        return None;
+    }
+    let total: u64 = experiment
+        .variants
+        .iter()
+        .map(|variant| u64::from(variant.weight))
+        .sum();
+    let mut point = bucket(&experiment.key, unit, 1, total);
+    experiment
+        .variants
+        .iter()
+        .find(|variant| {
+            let weight = u64::from(variant.weight);
+            if point < weight {
+                return true;
+            }
+            point -= weight;
+            false
+        })
+        .map(|variant| variant.key.as_str())
+}
+
+/// The visitor's variants for this request
+///
+/// Inserted by [`Experiments::middleware`]. Without it, or for visitors
+/// without a session, every experiment shows its control.
+#[derive(Clone, Default)]
+pub struct Variants {
+    experiments: Option<Experiments>,
+    unit: Option<String>,
+    assignments: BTreeMap<String, String>,
+    /// Experiments already exposed in this request
+    exposed: Arc<Mutex<HashSet<String>>>,
+}
+
+impl std::fmt::Debug for Variants {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Variants")
+            .field("unit", &self.unit)
+            .field("assignments", &self.assignments)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Variants {
+    /// The assigned variant of `experiment`, recording an exposure
+    ///
+    /// `None` when the visitor is not enrolled; show the control.
+    #[must_use]
+    pub fn variant(&self, experiment: &str) -> Option<&str> {
+        let variant = self.assignments.get(experiment)?;
+        if let (Some(experiments), Some(unit)) = (&self.experiments, &self.unit) {
+            if self.exposed.lock().insert(experiment.to_string()) {
+                experiments.push(ExperimentEvent {
+                    experiment: experiment.to_string(),
+                    variant: variant.clone(),
+                    unit: unit.clone(),
+                    goal: None,
+                    occurred_at: chrono::Utc::now().timestamp(),
+                });
+            }
+        }
+        Some(variant)
+    }
+
+    /// Whether the visitor sees `variant` of `experiment`, recording an
+    /// exposure; visitors who are not enrolled see the control
+    #[must_use]
+    pub fn is(&self, experiment: &str, variant: &str) -> bool {
+        self.variant(experiment).map_or_else(
+            || {
+                self.experiments
+                    .as_ref()
+                    .and_then(|experiments| experiments.definition(experiment))
+                    .and_then(ExperimentConfig::control)
+                    == Some(variant)
+            },
+            |assigned| assigned == variant,
+        )
+    }
+
+    /// Assignments without recording exposures, experiment to variant
+    #[must_use]
+    pub const fn assignments(&self) -> &BTreeMap<String, String> {
+        &self.assignments
+    }
+
+    /// Record that the visitor reached `goal` in every experiment they are
+    /// enrolled in
+    ///
+    /// Conversions only count for experiments the visitor was exposed to.
+    pub fn convert(&self, goal: &str) {
+        let (Some(experiments), Some(unit)) = (&self.experiments, &self.unit) else {
+            return;
+        };
+        let now = chrono::Utc::now().timestamp();
+        for (experiment, variant) in &self.assignments {
+            experiments.push(ExperimentEvent {
+                experiment: experiment.clone(),
+                variant: variant.clone(),
+                unit: unit.clone(),
+                goal: Some(goal.to_string()),
+                occurred_at: now,
+            });
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Variants
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_default())
+    }
+}
+
+/// Assigns visitors, records events and reports results
+#[derive(Clone)]
+pub struct Experiments {
+    config: Arc<ExperimentsConfig>,
+    store: Arc<dyn ExperimentStore>,
+    buffer: Arc<Mutex<Vec<ExperimentEvent>>>,
+}
+
+impl std::fmt::Debug for Experiments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Experiments")
+            .field("config", &self.config)
+            .field("buffered", &self.buffer.lock().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Dashboard query string
+#[derive(Debug, Deserialize)]
+struct ReportQuery {
+    goal: Option<String>,
+}
+
+impl Experiments {
+    /// Create a manager for the configured experiments
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExperimentError::Invalid`] for duplicate keys, experiments
+    /// without variants or traffic over 100%, and variants with duplicate
+    /// keys or no total weight.
+    pub fn new(
+        config: ExperimentsConfig,
+        store: Arc<dyn ExperimentStore>,
+    ) -> Result<Self, ExperimentError> {
+        let mut keys = HashSet::new();
+        for experiment in &config.definitions {
+            let invalid =
+                |reason: &str| ExperimentError::Invalid(experiment.key.clone(), reason.to_string());
+            if !keys.insert(experiment.key.as_str()) {
+                return Err(invalid("duplicate key"));
+            }
+            if experiment.traffic_percent > 100 {
+                return Err(invalid("traffic_percent is over 100"));
+            }
+            let mut variants = HashSet::new();
+            if experiment
+                .variants
+                .iter()
+                .any(|variant| !variants.insert(variant.key.as_str()))
+            {
+                return Err(invalid("duplicate variant key"));
+            }
+            if experiment
+                .variants
+                .iter()
+                .all(|variant| variant.weight == 0)
+            {
+                return Err(invalid("needs a variant with a weight"));
+            }
+        }
+        Ok(Self {
+            config: Arc::new(config),
+            store,
+            buffer: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    /// Configured experiments
+    #[must_use]
+    pub fn config(&self) -> &ExperimentsConfig {
+        &self.config
+    }
+
+    /// Look up an experiment by key
+    #[must_use]
+    pub fn definition(&self, key: &str) -> Option<&ExperimentConfig> {
+        self.config
+            .definitions
+            .iter()
+            .find(|experiment| experiment.key == key)
+    }
+
+    /// Variants for a session, assigning experiments the session has not
+    /// seen yet
+    ///
+    /// Saved assignments are kept while the experiment is active and the
+    /// variant still exists. Returns `true` when the session changed.
+    pub fn resolve(
+        &self,
+        session: &mut SessionData,
+        session_id: Option<&SessionId>,
+    ) -> (Variants, bool) {
+        let unit = session
+            .user_id
+            .map(|id| format!("user:{id}"))
+            .or_else(|| session_id.map(|id| format!("session:{}", id.as_str())));
+        let saved: BTreeMap<String, String> = session.get(SESSION_KEY).unwrap_or_default();
+        let mut assignments = BTreeMap::new();
+        for experiment in self.config.definitions.iter().filter(|e| e.active) {
+            let kept = saved.get(&experiment.key).filter(|variant| {
+                experiment
+                    .variants
+                    .iter()
+                    .any(|v| &&v.key == variant && v.weight > 0)
+            });
+            let variant = kept
+                .map(String::as_str)
+                .or_else(|| unit.as_deref().and_then(|unit| assign(experiment, unit)));
+            if let Some(variant) = variant {
+                assignments.insert(experiment.key.clone(), variant.to_string());
+            }
+        }
+        let changed = assignments != saved;
+        if changed && session.set(SESSION_KEY.to_string(), &assignments).is_err() {
+            warn!("Failed to save experiment assignments");
+        }
+        let variants = Variants {
+            experiments: Some(self.clone()),
+            unit,
+            assignments,
+            exposed: Arc::default(),
+        };
+        (variants, changed)
+    }
+
+    /// Middleware inserting [`Variants`] into request extensions and
+    /// saving new assignments in the session
+    ///
+    /// Must run inside the session layer. Use with
+    /// `axum::middleware::from_fn_with_state`.
+    pub async fn middleware(
+        State(experiments): State<Self>,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        let Some(mut session) = request.extensions().get::<SessionData>().cloned() else {
+            request.extensions_mut().insert(Variants {
+                experiments: Some(experiments),
+                ..Variants::default()
+            });
+            return next.run(request).await;
+        };
+        let (variants, changed) =
+            experiments.resolve(&mut session, request.extensions().get::<SessionId>());
+        let assignments = variants.assignments.clone();
+        request.extensions_mut().insert(variants);
+        if changed {
+            request.extensions_mut().insert(session.clone());
+        }
+
+        let mut response = next.run(request).await;
+        if changed {
+            // Keep changes the handler made to the session
+            let mut session = response
+                .extensions()
+                .get::<SessionData>()
+                .cloned()
+                .unwrap_or(session);
+            if session.set(SESSION_KEY.to_string(), &assignments).is_ok() {
+                response.extensions_mut().insert(session);
+            }
+        }
+        response
+    }
+
+    /// Routes: `GET /experiments/{key}` (report partial, `?goal=` defaults
+    /// to `conversion`)
+    ///
+    /// The dashboard is not protected: nest it under admin-only routes.
+    pub fn dashboard_routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/experiments/{key}", get(report_handler))
+            .with_state(self.clone())
+    }
+
+    /// Conversion results of `experiment` for `goal`
+    ///
+    /// Buffered events are counted once written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the experiment is unknown or the store fails.
+    pub async fn report(
+        &self,
+        experiment: &str,
+        goal: &str,
+    ) -> Result<ExperimentReport, ExperimentError> {
+        let definition = self
+            .definition(experiment)
+            .ok_or_else(|| ExperimentError::Unknown(experiment.to_string()))?;
+        let counts = self.store.counts(experiment, goal).await?;
+        Ok(ExperimentReport::new(
+            definition,
+            goal,
+            self.config.significance,
+            &counts,
+        ))
+    }
+
+    /// Write buffered events now, returning how many were written
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails; the events stay buffered.
+    pub async fn flush(&self) -> Result<usize, ExperimentError> {
+        let batch = std::mem::take(&mut *self.buffer.lock());
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        match self.store.insert(&batch).await {
+            Ok(()) => Ok(batch.len()),
+            Err(e) => {
+                self.requeue(batch);
+                Err(e)
+            }
+        }
+    }
+
+    /// Run [`Experiments::flush`] every `interval` on a background task
+    #[allow(clippy::must_use_candidate)] // the task runs detached
+    pub fn spawn_flusher(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let experiments = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = experiments.flush().await {
+                    warn!(error = %e, "Experiment event flush failed");
+                }
+            }
+        })
+    }
+
+    /// Buffer an event, writing the buffer in the background when full
+    fn push(&self, event: ExperimentEvent) {
+        let full = {
+            let mut buffer = self.buffer.lock();
+            buffer.push(event);
+            buffer.len() >= BATCH_SIZE
+        };
+        if full {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                let experiments = self.clone();
+                runtime.spawn(async move {
+                    if let Err(e) = experiments.flush().await {
+                        warn!(error = %e, "Experiment event flush failed");
+                    }
+                });
+            }
+        }
+    }
+
+    /// Put a failed batch back in front of newer events, dropping the
+    /// oldest once too many are waiting
+    fn requeue(&self, mut batch: Vec<ExperimentEvent>) {
+        let mut buffer = self.buffer.lock();
+        batch.append(&mut buffer);
+        let limit = BATCH_SIZE * MAX_BUFFERED_BATCHES;
+        if batch.len() > limit {
+            batch.drain(..batch.len() - limit);
+        }
+        *buffer = batch;
+    }
+}
+
+/// Results table partial for a report
+#[must_use]
+pub fn report_partial(report: &ExperimentReport) -> String {
+    let mut html = format!(
+        r#"<div class="experiment-report" id="experiment-{}"><table><caption>{} — goal: {}</caption><thead><tr><th>Variant</th><th>Exposed</th><th>Converted</th><th>Rate</th><th>Lift</th><th>p-value</th></tr></thead><tbody>"#,
+        escape_html(&report.experiment),
+        escape_html(&report.experiment),
+        escape_html(&report.goal),
+    );
+    for (i, variant) in report.variants.iter().enumerate() {
+        let class = if i == 0 {
+            "experiment-control"
+        } else if variant.significant {
+            "experiment-significant"
+        } else {
+            "experiment-variant"
+        };
+        let lift = variant
+            .lift
+            .map_or_else(|| "—".to_string(), |lift| format!("{:+.1}%", lift * 100.0));
+        let p_value = variant
+            .p_value
+            .map_or_else(|| "—".to_string(), |p| format!("{p:.3}"));
+        let _ = write!(
+            html,
+            r#"<tr class="{class}"><td>{}</td><td>{}</td><td>{}</td><td>{:.2}%</td><td>{lift}</td><td>{p_value}</td></tr>"#,
+            escape_html(&variant.variant),
+            variant.exposures,
+            variant.conversions,
+            variant.rate * 100.0,
+        );
+    }
+    let _ = write!(
+        html,
+        r"</tbody></table><p>Significant at p ≤ {}.</p></div>",
+        report.significance
+    );
+    html
+}
+
+async fn report_handler(
+    State(experiments): State<Experiments>,
+    Path(key): Path<String>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Html<String>, ExperimentError> {
+    let goal = query.goal.as_deref().unwrap_or("conversion");
+    let report = experiments.report(&key, goal).await?;
+    Ok(Html(report_partial(&report)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::middleware::from_fn_with_state;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    fn experiments() -> (Experiments, Arc<MemoryExperimentStore>) {
+        let store = Arc::new(MemoryExperimentStore::new());
+        let config = ExperimentsConfig {
+            definitions: vec![ExperimentConfig::new("checkout-button")
+                .variant("control", 1)
+                .variant("green", 1)],
+            ..ExperimentsConfig::default()
+        };
+        (Experiments::new(config, store.clone()).unwrap(), store)
+    }
+
+    #[test]
+    fn test_validation() {
+        let store = Arc::new(MemoryExperimentStore::new());
+        let config = |definitions| ExperimentsConfig {
+            definitions,
+            ..ExperimentsConfig::default()
+        };
+        for invalid in [
+            vec![ExperimentConfig::new("a").variant("x", 0)],
+            vec![ExperimentConfig::new("a").variant("x", 1).variant("x", 1)],
+            vec![ExperimentConfig::new("a")
+                .variant("x", 1)
+                .with_traffic_percent(101)],
+            vec![
+                ExperimentConfig::new("a").variant("x", 1),
+                ExperimentConfig::new("a").variant("y", 1),
+            ],
+        ] {
+            assert!(matches!(
+                Experiments::new(config(invalid), store.clone()),
+                Err(ExperimentError::Invalid(..))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_assignment_is_deterministic_and_weighted() {
+        let experiment = ExperimentConfig::new("pricing")
+            .variant("control", 3)
+            .variant("annual", 1);
+        let mut counts = HashMap::new();
+        for user in 0..4000 {
+            let unit = format!("user:{user}");
+            let variant = assign(&experiment, &unit).unwrap();
+            assert_eq!(assign(&experiment, &unit), Some(variant));
+            *counts.entry(variant).or_insert(0) += 1;
+        }
+        assert!((2800..3200).contains(&counts["control"]), "{counts:?}");
+
+        let partial = experiment.clone().with_traffic_percent(10);
+        let enrolled = (0..4000)
+            .filter(|user| assign(&partial, &format!("user:{user}")).is_some())
+            .count();
+        assert!((300..500).contains(&enrolled), "{enrolled}");
+
+        let stopped = ExperimentConfig {
+            active: false,
+            ..experiment
+        };
+        assert!(assign(&stopped, "user:1").is_none());
+    }
+
+    #[test]
+    fn test_z_test() {
+        // 10% vs 12% with 10k units each is significant
+        let p = z_test((10_000, 1_000), (10_000, 1_200)).unwrap();
+        assert!(p < 0.001, "{p}");
+        // Identical rates are not
+        let p = z_test((500, 50), (500, 50)).unwrap();
+        assert!((p - 1.0).abs() < 1e-6, "{p}");
+        assert!(z_test((0, 0), (10, 1)).is_none());
+        assert!((erfc(0.0) - 1.0).abs() < 1e-6);
+        assert!((erfc(1.0) - 0.157_299_2).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_exposures_conversions_and_report() {
+        let (experiments, _store) = experiments();
+        let mut users = Vec::new();
+        for user in 0..20 {
+            let mut session = SessionData::new();
+            session.user_id = Some(user);
+            let (variants, changed) = experiments.resolve(&mut session, None);
+            assert!(changed);
+            users.push(variants);
+        }
+        let mut conversions = 0;
+        for (i, variants) in users.iter().enumerate() {
+            // Checked twice, exposed once
+            let _ = variants.is("checkout-button", "green");
+            if variants.variant("checkout-button") == Some("green") || i % 4 == 0 {
+                variants.convert("purchase");
+                conversions += 1;
+            }
+        }
+        assert_eq!(experiments.flush().await.unwrap(), 20 + conversions);
+
+        let report = experiments
+            .report("checkout-button", "purchase")
+            .await
+            .unwrap();
+        let control = &report.variants[0];
+        let green = &report.variants[1];
+        assert_eq!(control.variant, "control");
+        assert_eq!(control.exposures + green.exposures, 20);
+        assert_eq!(green.conversions, green.exposures);
+        assert!(control.p_value.is_none());
+        assert!(green.p_value.is_some());
+
+        let html = report_partial(&report);
+        assert!(html.contains("checkout-button — goal: purchase"));
+        assert!(html.contains(r#"<tr class="experiment-control"><td>control</td>"#));
+
+        assert!(matches!(
+            experiments.report("missing", "purchase").await,
+            Err(ExperimentError::Unknown(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_middleware_saves_assignments_in_session() {
+        let (experiments, store) = experiments();
+        let app = Router::new()
+            .route(
+                "/",
+                get(|variants: Variants| async move {
+                    format!("{}", variants.is("checkout-button", "green"))
+                }),
+            )
+            .layer(from_fn_with_state(
+                experiments.clone(),
+                Experiments::middleware,
+            ));
+
+        let mut session = SessionData::new();
+        session.user_id = Some(42);
+        let mut request = Request::get("/").body(Body::empty()).unwrap();
+        request.extensions_mut().insert(session.clone());
+        let response = app.clone().oneshot(request).await.unwrap();
+        let saved = response.extensions().get::<SessionData>().unwrap();
+        let assignments: BTreeMap<String, String> = saved.get(SESSION_KEY).unwrap();
+        let expected = assign(
+            experiments.definition("checkout-button").unwrap(),
+            "user:42",
+        );
+        assert_eq!(
+            assignments.get("checkout-button").map(String::as_str),
+            expected
+        );
+
+        // A saved assignment wins over the hash and is not saved again
+        let mut session = SessionData::new();
+        session.user_id = Some(42);
+        let flipped = if expected == Some("green") {
+            "control"
+        } else {
+            "green"
+        };
+        session
+            .set(
+                SESSION_KEY.to_string(),
+                BTreeMap::from([("checkout-button".to_string(), flipped.to_string())]),
+            )
+            .unwrap();
+        let mut request = Request::get("/").body(Body::empty()).unwrap();
+        request.extensions_mut().insert(session);
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert!(response.extensions().get::<SessionData>().is_none());
+        let body = axum::body::to_bytes(response.into_body(), 16)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], (flipped == "green").to_string().as_bytes());
+
+        // Without a session everyone sees the control, unrecorded
+        let response = app
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), 16)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"false");
+
+        experiments.flush().await.unwrap();
+        assert_eq!(store.events().len(), 2);
+    }
+}
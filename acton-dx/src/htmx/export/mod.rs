@@ -0,0 +1,172 @@
+//! Streaming CSV downloads
+//!
+//! [`csv`] turns a page-at-a-time fetch into a `text/csv` download that is
+//! written to the client as each page arrives, so exporting a large table
+//! never holds more than one page in memory. The fetch gets the cursor
+//! returned with the previous page (`None` for the first) and returns the
+//! page's rows with the cursor to continue from, or `None` when it was the
+//! last page.
+//!
+//! Fields are quoted as needed, and fields starting with `=`, `+`, `-`, `@`
+//! or a tab are prefixed with `'` so spreadsheets don't evaluate them as
+//! formulas.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::export;
+//!
+//! async fn export_orders(State(state): State<AppState>) -> Response {
+//!     let db = state.db().clone();
+//!     export::csv("orders.csv", &["id", "customer", "total"], move |after: Option<i64>| {
+//!         let db = db.clone();
+//!         async move {
+//!             let orders: Vec<(i64, String, String)> = sqlx::query_as(
+//!                 "SELECT id, customer, total::text FROM orders WHERE id > $1 ORDER BY id LIMIT 1000",
+//!             )
+//!             .bind(after.unwrap_or(0))
+//!             .fetch_all(&db)
+//!             .await?;
+//!             let next = orders.last().map(|order| order.0);
+//!             let rows = orders
+//!                 .into_iter()
+//!                 .map(|(id, customer, total)| vec![id.to_string(), customer, total])
+//!                 .collect();
+//!             Ok::<_, sqlx::Error>((rows, next))
+//!         }
+//!     })
+//! }
+//! ```
+
+use axum::{
+    body::Body,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures_util::{stream, StreamExt};
+use std::fmt::Display;
+use std::future::Future;
+
+/// Stream rows fetched a page at a time as a CSV download named `filename`
+///
+/// If a fetch fails after the download has started, the error is logged
+/// and the response body is cut short, so the client sees a failed
+/// download rather than a silently truncated file.
+pub fn csv<C, F, Fut, E>(filename: &str, headers: &[&str], fetch: F) -> Response
+where
+    C: Send + 'static,
+    F: FnMut(Option<C>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(Vec<Vec<String>>, Option<C>), E>> + Send + 'static,
+    E: Display + Send + 'static,
+{
+    let header_row = Bytes::from(csv_record(headers));
+
+    // `Some(cursor)` while there are pages left
+    let pages = stream::unfold((Some(None), fetch), |(cursor, mut fetch)| async move {
+        let cursor = cursor?;
+        match fetch(cursor).await {
+            Ok((rows, next)) => {
+                let chunk: String = rows.iter().map(|row| csv_record(row)).collect();
+                let next = next.filter(|_| !rows.is_empty()).map(Some);
+                Some((Ok(Bytes::from(chunk)), (next, fetch)))
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "CSV export failed");
+                Some((Err(std::io::Error::other(e.to_string())), (None, fetch)))
+            }
+        }
+    });
+    let body = stream::once(async move { Ok(header_row) }).chain(pages);
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename.replace('"', "")),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// One CSV line, `\r\n` terminated, with fields quoted as needed
+#[must_use]
+pub fn csv_record<T: AsRef<str>>(fields: &[T]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|field| csv_field(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_csv_record_quotes_and_neutralizes() {
+        assert_eq!(csv_record(&["a", "b c", ""]), "a,b c,\r\n");
+        assert_eq!(
+            csv_record(&["say \"hi\"", "x,y", "line\nbreak"]),
+            "\"say \"\"hi\"\"\",\"x,y\",\"line\nbreak\"\r\n"
+        );
+        assert_eq!(
+            csv_record(&["=SUM(A1)", "-1", "@cmd"]),
+            "'=SUM(A1),'-1,'@cmd\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_streams_every_page() {
+        let response = csv("users.csv", &["id", "email"], |after: Option<u32>| async move {
+            let start = after.map_or(0, |last| last + 1);
+            let rows: Vec<Vec<String>> = (start..5.min(start + 2))
+                .map(|id| vec![id.to_string(), format!("user{id}@example.com")])
+                .collect();
+            let next = (start + 2 < 5).then_some(start + 1);
+            Ok::<_, std::convert::Infallible>((rows, next))
+        });
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"users.csv\""
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "id,email\r\n0,user0@example.com\r\n1,user1@example.com\r\n\
+             2,user2@example.com\r\n3,user3@example.com\r\n4,user4@example.com\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_fails_body_on_error() {
+        let response = csv("broken.csv", &["id"], |_: Option<u32>| async {
+            Err::<(Vec<Vec<String>>, Option<u32>), _>("database gone")
+        });
+        assert!(axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .is_err());
+    }
+}
@@ -4,6 +4,7 @@
 
 use crate::htmx::agents::{CsrfToken, GetOrCreateToken};
 use crate::htmx::auth::session::SessionId;
+use crate::htmx::middleware::compression::BreachGuard;
 use crate::htmx::state::ActonHtmxState;
 use acton_reactive::prelude::ActorHandleInterface;
 use axum::{
@@ -36,13 +37,21 @@ use std::time::Duration;
 #[derive(Debug, Clone)]
 pub struct CsrfTokenExtractor {
     token: CsrfToken,
+    masked: String,
 }
 
 impl CsrfTokenExtractor {
-    /// Get the CSRF token as a string
+    fn new(token: CsrfToken) -> Self {
+        let masked = token.masked();
+        Self { token, masked }
+    }
+
+    /// Get the CSRF token to render, masked afresh for this request
+    ///
+    /// See [`CsrfToken::masked`].
     #[must_use]
     pub fn token(&self) -> &str {
-        self.token.as_str()
+        &self.masked
     }
 
     /// Get the CSRF token value
@@ -96,7 +105,12 @@ where
                 )
             })?;
 
-        Ok(Self { token })
+        // The token will be rendered into the response, so keep it out of compression
+        if let Some(guard) = parts.extensions.get::<BreachGuard>() {
+            guard.mark();
+        }
+
+        Ok(Self::new(token))
     }
 }
 
@@ -107,18 +121,17 @@ mod tests {
     #[test]
     fn test_csrf_token_extractor_creation() {
         let token = CsrfToken::generate();
-        let extractor = CsrfTokenExtractor {
-            token: token.clone(),
-        };
+        let extractor = CsrfTokenExtractor::new(token.clone());
 
-        assert_eq!(extractor.token(), token.as_str());
+        assert_ne!(extractor.token(), token.as_str());
+        assert!(token.matches(&CsrfToken::from_string(extractor.token().to_string())));
         assert_eq!(extractor.value(), &token);
     }
 
     #[test]
     fn test_csrf_token_extractor_debug() {
         let token = CsrfToken::generate();
-        let extractor = CsrfTokenExtractor { token };
+        let extractor = CsrfTokenExtractor::new(token);
 
         let debug_str = format!("{extractor:?}");
         assert!(debug_str.contains("CsrfTokenExtractor"));
@@ -127,7 +140,7 @@ mod tests {
     #[test]
     fn test_csrf_token_extractor_clone() {
         let token = CsrfToken::generate();
-        let extractor = CsrfTokenExtractor { token };
+        let extractor = CsrfTokenExtractor::new(token);
         let cloned = extractor.clone();
 
         assert_eq!(extractor.token(), cloned.token());
@@ -65,13 +65,15 @@
 //! }
 //! ```
 
-use crate::htmx::storage::UploadedFile;
+use crate::htmx::storage::presets::{NamedPreset, UploadPresets};
+use crate::htmx::storage::{CheckedUpload, StorageError, UploadedFile};
 use axum::{
     extract::{multipart::Field, FromRequest, Multipart, Request},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
 use std::fmt;
+use std::marker::PhantomData;
 
 /// Default maximum file size (10MB)
 pub const DEFAULT_MAX_FILE_SIZE: usize = 10 * 1024 * 1024;
@@ -109,6 +111,12 @@ pub enum FileUploadError {
 
     /// Missing required field (filename or content-type)
     MissingField(String),
+
+    /// File rejected by its upload preset
+    Rejected(StorageError),
+
+    /// Upload preset requires a virus scan but no scanner is configured
+    ScannerUnavailable(String),
 }
 
 impl fmt::Display for FileUploadError {
@@ -124,6 +132,10 @@ impl fmt::Display for FileUploadError {
                 write!(f, "Upload contains {actual} files, maximum is {max}")
             }
             Self::MissingField(field) => write!(f, "Missing required field: {field}"),
+            Self::Rejected(e) => write!(f, "Upload rejected: {e}"),
+            Self::ScannerUnavailable(preset) => {
+                write!(f, "Virus scanning required for {preset} uploads is unavailable")
+            }
         }
     }
 }
@@ -133,7 +145,11 @@ impl std::error::Error for FileUploadError {}
 impl IntoResponse for FileUploadError {
     fn into_response(self) -> Response {
         let status = match self {
-            Self::FileTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::FileTooLarge { .. }
+            | Self::Rejected(StorageError::FileSizeExceeded { .. }) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Rejected(StorageError::InvalidMimeType { .. }) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Rejected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::ScannerUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
             Self::MissingFile | Self::MissingField(_) | Self::MultipleFiles | Self::TooManyFiles { .. } | Self::MultipartError(_) => {
                 StatusCode::BAD_REQUEST
             }
@@ -163,18 +179,12 @@ impl IntoResponse for FileUploadError {
 #[derive(Debug)]
 pub struct FileUpload(pub UploadedFile);
 
-impl<S> FromRequest<S> for FileUpload
-where
-    S: Send + Sync,
-{
-    type Rejection = FileUploadError;
-
-    #[allow(clippy::manual_async_fn)]
-    fn from_request(
-        req: Request,
-        state: &S,
-    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
-        async move {
+impl FileUpload {
+    /// Read exactly one file of at most `max_size` bytes
+    async fn read<S>(req: Request, state: &S, max_size: usize) -> Result<Self, FileUploadError>
+    where
+        S: Send + Sync,
+    {
         let mut multipart = Multipart::from_request(req, state)
             .await
             .map_err(|e| FileUploadError::MultipartError(e.to_string()))?;
@@ -203,7 +213,7 @@ where
                 .to_string();
 
             // Read file data with size limit
-            let data = read_field_data(field, DEFAULT_MAX_FILE_SIZE).await?;
+            let data = read_field_data(field, max_size).await?;
 
             files.push(UploadedFile {
                 filename,
@@ -218,7 +228,21 @@ where
             1 => Ok(Self(files.into_iter().next().unwrap())),
             _ => Err(FileUploadError::MultipleFiles),
         }
-        }
+    }
+}
+
+impl<S> FromRequest<S> for FileUpload
+where
+    S: Send + Sync,
+{
+    type Rejection = FileUploadError;
+
+    #[allow(clippy::manual_async_fn)]
+    fn from_request(
+        req: Request,
+        state: &S,
+    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
+        Self::read(req, state, DEFAULT_MAX_FILE_SIZE)
     }
 }
 
@@ -307,6 +331,60 @@ where
     }
 }
 
+/// Extractor for a single file upload checked against a named preset
+///
+/// Reads the file with the preset's size limit, then applies its type,
+/// content, virus scan and image rules (see
+/// [`UploadPreset::check`](crate::htmx::storage::UploadPreset::check)).
+/// The preset and scanner come from an [`UploadPresets`] request extension;
+/// without one the built-in preset is used, and presets that require a
+/// scan are rejected with 503.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use acton_htmx::extractors::PresetUpload;
+/// use acton_htmx::storage::presets::Document;
+///
+/// async fn attach(PresetUpload(upload, ..): PresetUpload<Document>) -> impl IntoResponse {
+///     format!("Received {} ({})", upload.file.filename, upload.preset)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PresetUpload<P>(pub CheckedUpload, pub PhantomData<P>);
+
+impl<S, P> FromRequest<S> for PresetUpload<P>
+where
+    S: Send + Sync,
+    P: NamedPreset,
+{
+    type Rejection = FileUploadError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let presets = req.extensions().get::<UploadPresets>().cloned();
+        let preset = presets
+            .as_ref()
+            .and_then(|presets| presets.get(P::NAME).cloned())
+            .unwrap_or_else(P::preset);
+        let scanner = presets.as_ref().and_then(UploadPresets::scanner);
+        if preset.requires_scan() && scanner.is_none() {
+            return Err(FileUploadError::ScannerUnavailable(P::NAME.to_string()));
+        }
+
+        let FileUpload(file) = FileUpload::read(
+            req,
+            state,
+            usize::try_from(preset.max_file_size()).unwrap_or(usize::MAX),
+        )
+        .await?;
+        let upload = preset
+            .check(file, scanner)
+            .await
+            .map_err(FileUploadError::Rejected)?;
+        Ok(Self(upload, PhantomData))
+    }
+}
+
 /// Reads field data with size limit enforcement
 ///
 /// This function reads the field data and enforces the maximum size limit
@@ -425,6 +503,23 @@ mod tests {
         assert!(matches!(result.unwrap_err(), FileUploadError::MissingFile));
     }
 
+    #[tokio::test]
+    async fn test_preset_upload_requires_scanner() {
+        use crate::htmx::storage::presets::{Avatar, ImportCsv};
+
+        let req = create_multipart_request(vec![("file", "me.png", b"not an image")]);
+        let result = PresetUpload::<Avatar>::from_request(req, &()).await;
+        assert!(matches!(result.unwrap_err(), FileUploadError::ScannerUnavailable(_)));
+
+        // Import presets allow only text types
+        let req = create_multipart_request(vec![("file", "rows.csv", b"a,b")]);
+        let result = PresetUpload::<ImportCsv>::from_request(req, &()).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            FileUploadError::Rejected(StorageError::InvalidMimeType { .. })
+        ));
+    }
+
     // Note: Testing file size limits with mock multipart requests is complex because
     // creating large binary multipart bodies requires proper encoding. The size validation
     // logic in read_field_data() works correctly, but testing it would require a more
@@ -1,16 +1,22 @@
 //! Axum extractors for acton-dx
 //!
 //! Provides extractors for accessing session data, flash messages,
-//! CSRF tokens, validation, file uploads, and other request context within handlers.
+//! CSRF tokens, validation, sanitization, file uploads, viewer timezone, and other request context within handlers.
 
 mod csrf;
 mod file_upload;
+#[cfg(feature = "sanitize")]
+mod sanitized;
 mod session;
+mod timezone;
 mod validated;
 
 pub use csrf::CsrfTokenExtractor;
-pub use file_upload::{FileUpload, FileUploadError, MultiFileUpload};
+pub use file_upload::{FileUpload, FileUploadError, MultiFileUpload, PresetUpload};
+#[cfg(feature = "sanitize")]
+pub use sanitized::SanitizedForm;
 pub use session::{FlashExtractor, OptionalSession, SessionExtractor};
+pub use timezone::TimezoneExtractor;
 pub use validated::{
     format_validation_errors, validation_errors_json, ValidatedForm, ValidationError,
 };
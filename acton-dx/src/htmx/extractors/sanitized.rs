@@ -0,0 +1,98 @@
+//! Sanitized form extractor
+//!
+//! Like [`ValidatedForm`](super::ValidatedForm), but runs the form's
+//! [`Sanitize`] implementation between deserialization and validation, so
+//! length and required-field rules apply to the cleaned HTML.
+
+use axum::extract::{Form, FromRequest, Request};
+use serde::de::DeserializeOwned;
+use validator::Validate;
+
+use super::ValidationError;
+use crate::htmx::sanitize::Sanitize;
+
+/// Sanitized and validated form extractor
+///
+/// Rejects with [`ValidationError`] exactly like `ValidatedForm`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SanitizedForm<T>(pub T);
+
+impl<T, S> FromRequest<S> for SanitizedForm<T>
+where
+    T: DeserializeOwned + Sanitize + Validate + 'static,
+    S: Send + Sync + 'static,
+{
+    type Rejection = ValidationError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Form(mut data) = Form::<T>::from_request(req, state).await.map_err(|err| {
+            ValidationError::FormRejection(format!("Failed to parse form data: {err}"))
+        })?;
+
+        data.sanitize();
+        data.validate().map_err(ValidationError::Validation)?;
+
+        Ok(Self(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::sanitize::SanitizeProfile;
+    use axum::{body::Body, http::StatusCode, routing::post, Router};
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct CommentForm {
+        #[validate(length(min = 1))]
+        body: String,
+    }
+
+    impl Sanitize for CommentForm {
+        fn sanitize(&mut self) {
+            SanitizeProfile::Comment.apply(&mut self.body);
+        }
+    }
+
+    async fn submit(body: &str) -> (StatusCode, String) {
+        let app = Router::new().route(
+            "/",
+            post(|SanitizedForm(form): SanitizedForm<CommentForm>| async move { form.body }),
+        );
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from(format!("body={}", urlencoding(body))))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    fn urlencoding(value: &str) -> String {
+        use std::fmt::Write;
+        value.bytes().fold(String::new(), |mut out, b| {
+            let _ = write!(out, "%{b:02X}");
+            out
+        })
+    }
+
+    #[tokio::test]
+    async fn test_sanitizes_before_handler() {
+        let (status, body) = submit("<b>hi</b><script>x()</script>").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "<b>hi</b>");
+    }
+
+    #[tokio::test]
+    async fn test_validation_runs_on_sanitized_value() {
+        let (status, _) = submit("<script>x()</script>").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
@@ -0,0 +1,101 @@
+//! Viewer timezone extractor
+
+use crate::htmx::auth::session::SessionData;
+use crate::htmx::timezone::{TzContext, TIMEZONE_COOKIE};
+use axum::{
+    extract::FromRequestParts,
+    http::{header::COOKIE, request::Parts},
+};
+use std::convert::Infallible;
+
+/// Extractor for the viewer's [`TzContext`]
+///
+/// Uses the session timezone, then the `tz` cookie, then UTC. Unknown
+/// timezone names are ignored.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use acton_htmx::extractors::TimezoneExtractor;
+///
+/// async fn show(TimezoneExtractor(tz): TimezoneExtractor) -> impl IntoResponse {
+///     ShowPost { post, tz }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimezoneExtractor(pub TzContext);
+
+impl<S> FromRequestParts<S> for TimezoneExtractor
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let from_session = parts
+            .extensions
+            .get::<SessionData>()
+            .and_then(|session| session.timezone.as_deref())
+            .and_then(|name| TzContext::from_name(name).ok());
+
+        let tz = from_session
+            .or_else(|| cookie_timezone(parts))
+            .unwrap_or_default();
+        Ok(Self(tz))
+    }
+}
+
+/// Read the browser-detected timezone cookie
+fn cookie_timezone(parts: &Parts) -> Option<TzContext> {
+    let cookies = parts.headers.get(COOKIE)?.to_str().ok()?;
+    cookies.split(';').find_map(|cookie| {
+        let (name, value) = cookie.trim().split_once('=')?;
+        if name.trim() != TIMEZONE_COOKIE {
+            return None;
+        }
+        // Browsers percent-encode the slash in names like `Europe/Berlin`
+        let value = value.trim().replace("%2F", "/").replace("%2f", "/");
+        TzContext::from_name(&value).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn extract(request: Request<()>) -> TzContext {
+        let (mut parts, ()) = request.into_parts();
+        TimezoneExtractor::from_request_parts(&mut parts, &())
+            .await
+            .unwrap()
+            .0
+    }
+
+    #[tokio::test]
+    async fn test_session_takes_precedence_over_cookie() {
+        let mut session = SessionData::new();
+        session.timezone = Some("Asia/Tokyo".to_string());
+        let mut request = Request::builder()
+            .header(COOKIE, "tz=Europe%2FBerlin")
+            .body(())
+            .unwrap();
+        request.extensions_mut().insert(session);
+        assert_eq!(extract(request).await.name(), "Asia/Tokyo");
+    }
+
+    #[tokio::test]
+    async fn test_cookie_then_utc() {
+        let request = Request::builder()
+            .header(COOKIE, "session=abc; tz=Europe%2FBerlin")
+            .body(())
+            .unwrap();
+        assert_eq!(extract(request).await.name(), "Europe/Berlin");
+
+        let request = Request::builder()
+            .header(COOKIE, "tz=Not/AZone")
+            .body(())
+            .unwrap();
+        assert_eq!(extract(request).await.name(), "UTC");
+    }
+}
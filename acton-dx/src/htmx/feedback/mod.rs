@@ -0,0 +1,389 @@
+//! Request feedback: progress bar, error toasts and retry
+//!
+//! The browser-side glue for HTMX requests that every project otherwise
+//! writes by hand:
+//!
+//! - a thin progress bar along the top of the page while any HTMX request
+//!   is in flight (shown only after a short delay, so fast requests don't
+//!   flicker)
+//! - a toast for every failed request, using the message from the
+//!   [`error_fragment`] that [`ActonHtmxError`](crate::htmx::error::ActonHtmxError)
+//!   renders, or a generic message for other failures
+//! - a Retry button on each error toast that repeats the failed request
+//!   with the same element, target and parameters
+//! - toasts pushed from the server with an `HX-Trigger` header:
+//!   `{"acton:toast": {"level": "success", "message": "Saved"}}`
+//!
+//! [`routes`] serves the script and stylesheet; [`snippet`] renders the tags
+//! and containers to put once in the base layout, after the HTMX script.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::feedback::{self, FeedbackOptions};
+//!
+//! let app = Router::new()
+//!     .route("/", get(home))
+//!     .merge(feedback::routes());
+//!
+//! // In the layout template: {{ feedback_snippet|safe }}
+//! let feedback_snippet = feedback::snippet(&FeedbackOptions::default());
+//! ```
+
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use std::time::Duration;
+
+use crate::htmx::template::helpers::escape_html;
+
+/// Path the script is served from
+pub const SCRIPT_PATH: &str = "/_acton/feedback.js";
+
+/// Path the stylesheet is served from
+pub const STYLESHEET_PATH: &str = "/_acton/feedback.css";
+
+/// Marker attribute the script looks for in error responses
+pub const ERROR_ATTRIBUTE: &str = "data-acton-error";
+
+/// Browser-side behaviour, passed to the script through [`snippet`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedbackOptions {
+    /// How long a request runs before the progress bar appears
+    pub progress_delay: Duration,
+    /// How long success and info toasts stay up; error toasts stay until
+    /// dismissed or retried
+    pub toast_duration: Duration,
+    /// Offer a Retry button on error toasts
+    pub retry: bool,
+}
+
+impl Default for FeedbackOptions {
+    fn default() -> Self {
+        Self {
+            progress_delay: Duration::from_millis(150),
+            toast_duration: Duration::from_secs(5),
+            retry: true,
+        }
+    }
+}
+
+/// Script, stylesheet and containers for the base layout
+///
+/// Include it once per page, after the HTMX script.
+#[must_use]
+pub fn snippet(options: &FeedbackOptions) -> String {
+    format!(
+        concat!(
+            r#"<link rel="stylesheet" href="{stylesheet}">"#,
+            r#"<div id="acton-progress" class="acton-progress" aria-hidden="true"></div>"#,
+            r#"<div id="acton-toasts" class="acton-toasts" aria-live="polite""#,
+            r#" data-progress-delay-ms="{delay}" data-toast-ms="{toast}" data-retry="{retry}"></div>"#,
+            r#"<script src="{script}" defer></script>"#,
+        ),
+        stylesheet = STYLESHEET_PATH,
+        script = SCRIPT_PATH,
+        delay = options.progress_delay.as_millis(),
+        toast = options.toast_duration.as_millis(),
+        retry = options.retry,
+    )
+}
+
+/// HTML fragment describing a failed request
+///
+/// The feedback script shows `message` in an error toast. It is safe to
+/// swap into the page as-is for apps that don't use the script.
+#[must_use]
+pub fn error_fragment(status: StatusCode, message: &str) -> String {
+    format!(
+        r#"<div class="acton-error" role="alert" {ERROR_ATTRIBUTE} data-status="{}">{}</div>"#,
+        status.as_u16(),
+        escape_html(message)
+    )
+}
+
+/// Routes: `GET /_acton/feedback.js` and `GET /_acton/feedback.css`
+pub fn routes<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route(
+            SCRIPT_PATH,
+            get(|| async { asset("application/javascript", SCRIPT) }),
+        )
+        .route(STYLESHEET_PATH, get(|| async { asset("text/css", STYLES) }))
+}
+
+fn asset(content_type: &'static str, body: &'static str) -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        body,
+    )
+}
+
+const SCRIPT: &str = r#"(function () {
+    "use strict";
+    var toasts = document.getElementById("acton-toasts");
+    var bar = document.getElementById("acton-progress");
+    if (!toasts || !bar || !window.htmx) {
+        return;
+    }
+    var progressDelay = Number(toasts.dataset.progressDelayMs || 150);
+    var toastMs = Number(toasts.dataset.toastMs || 5000);
+    var retryEnabled = toasts.dataset.retry !== "false";
+    var active = 0;
+    var showTimer = null;
+
+    function startProgress() {
+        active += 1;
+        if (active === 1) {
+            showTimer = setTimeout(function () {
+                bar.classList.remove("acton-progress-done");
+                bar.classList.add("acton-progress-active");
+            }, progressDelay);
+        }
+    }
+
+    function finishProgress() {
+        active = Math.max(0, active - 1);
+        if (active === 0) {
+            clearTimeout(showTimer);
+            if (bar.classList.contains("acton-progress-active")) {
+                bar.classList.remove("acton-progress-active");
+                bar.classList.add("acton-progress-done");
+            }
+        }
+    }
+
+    function statusMessage(status) {
+        if (status === 0) return "Can't reach the server. Check your connection.";
+        if (status === 401) return "Your session has expired. Please sign in again.";
+        if (status === 403) return "You don't have permission to do that.";
+        if (status === 404) return "That page or item no longer exists.";
+        if (status === 409) return "Someone else changed this. Reload and try again.";
+        if (status === 429) return "Too many requests. Wait a moment and try again.";
+        if (status >= 500) return "Something went wrong on our end. Please try again.";
+        return "The request failed (" + status + ").";
+    }
+
+    function errorMessage(xhr) {
+        var status = xhr ? xhr.status : 0;
+        var body = xhr && typeof xhr.responseText === "string" ? xhr.responseText : "";
+        if (body.indexOf("data-acton-error") !== -1) {
+            var doc = new DOMParser().parseFromString(body, "text/html");
+            var fragment = doc.querySelector("[data-acton-error]");
+            if (fragment && fragment.textContent.trim()) {
+                return fragment.textContent.trim();
+            }
+        }
+        return statusMessage(status);
+    }
+
+    function showToast(level, message, retry) {
+        var toast = document.createElement("div");
+        toast.className = "acton-toast acton-toast-" + level;
+        toast.setAttribute("role", level === "error" ? "alert" : "status");
+        var text = document.createElement("span");
+        text.className = "acton-toast-message";
+        text.textContent = message;
+        toast.appendChild(text);
+        if (retry) {
+            var again = document.createElement("button");
+            again.type = "button";
+            again.className = "acton-toast-retry";
+            again.textContent = "Retry";
+            again.addEventListener("click", function () {
+                toast.remove();
+                retry();
+            });
+            toast.appendChild(again);
+        }
+        var close = document.createElement("button");
+        close.type = "button";
+        close.className = "acton-toast-close";
+        close.setAttribute("aria-label", "Dismiss");
+        close.textContent = "×";
+        close.addEventListener("click", function () {
+            toast.remove();
+        });
+        toast.appendChild(close);
+        toasts.appendChild(toast);
+        if (level !== "error") {
+            setTimeout(function () {
+                toast.remove();
+            }, toastMs);
+        }
+    }
+
+    function retryFor(detail) {
+        var config = detail.requestConfig;
+        if (!retryEnabled || !config || !document.body.contains(detail.elt)) {
+            return null;
+        }
+        var values = config.parameters;
+        if (values instanceof FormData) {
+            values = Object.fromEntries(values.entries());
+        }
+        return function () {
+            htmx.ajax(config.verb.toUpperCase(), config.path, {
+                source: detail.elt,
+                target: detail.target,
+                values: values,
+            });
+        };
+    }
+
+    function failed(evt) {
+        showToast("error", errorMessage(evt.detail.xhr), retryFor(evt.detail));
+    }
+
+    document.body.addEventListener("htmx:beforeRequest", startProgress);
+    document.body.addEventListener("htmx:afterRequest", finishProgress);
+    document.body.addEventListener("htmx:responseError", failed);
+    document.body.addEventListener("htmx:sendError", failed);
+    document.body.addEventListener("htmx:timeout", failed);
+    document.body.addEventListener("acton:toast", function (evt) {
+        var detail = evt.detail || {};
+        if (detail.message) {
+            showToast(detail.level || "info", String(detail.message), null);
+        }
+    });
+})();
+"#;
+
+const STYLES: &str = r".acton-progress {
+    position: fixed;
+    top: 0;
+    left: 0;
+    height: 3px;
+    width: 0;
+    opacity: 0;
+    z-index: 2000;
+    background: #2563eb;
+    pointer-events: none;
+}
+
+.acton-progress-active {
+    opacity: 1;
+    width: 80%;
+    transition: width 8s cubic-bezier(0.1, 0.7, 0.2, 1);
+}
+
+.acton-progress-done {
+    width: 100%;
+    opacity: 0;
+    transition: width 0.2s ease-out, opacity 0.3s ease-in 0.2s;
+}
+
+.acton-toasts {
+    position: fixed;
+    right: 1rem;
+    bottom: 1rem;
+    z-index: 2000;
+    display: flex;
+    flex-direction: column;
+    gap: 0.5rem;
+    max-width: 400px;
+}
+
+.acton-toast {
+    display: flex;
+    align-items: center;
+    gap: 0.75rem;
+    padding: 0.75rem 1rem;
+    border-radius: 0.5rem;
+    box-shadow: 0 4px 6px rgba(0, 0, 0, 0.1);
+    background: #eff6ff;
+    color: #1e3a8a;
+}
+
+.acton-toast-success {
+    background: #f0fdf4;
+    color: #14532d;
+}
+
+.acton-toast-warning {
+    background: #fffbeb;
+    color: #78350f;
+}
+
+.acton-toast-error {
+    background: #fef2f2;
+    color: #7f1d1d;
+}
+
+.acton-toast-message {
+    flex: 1;
+}
+
+.acton-toast-retry,
+.acton-toast-close {
+    border: none;
+    background: none;
+    color: inherit;
+    cursor: pointer;
+    font: inherit;
+}
+
+.acton-toast-retry {
+    font-weight: 600;
+    text-decoration: underline;
+}
+
+@media (prefers-reduced-motion: reduce) {
+    .acton-progress-active,
+    .acton-progress-done {
+        transition: none;
+    }
+}
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_snippet_passes_options() {
+        let html = snippet(&FeedbackOptions {
+            progress_delay: Duration::from_millis(300),
+            toast_duration: Duration::from_secs(8),
+            retry: false,
+        });
+        assert!(html.contains(r#"data-progress-delay-ms="300""#));
+        assert!(html.contains(r#"data-toast-ms="8000""#));
+        assert!(html.contains(r#"data-retry="false""#));
+        assert!(html.contains(r#"<script src="/_acton/feedback.js" defer></script>"#));
+    }
+
+    #[test]
+    fn test_error_fragment_escapes_message() {
+        let html = error_fragment(StatusCode::FORBIDDEN, "<b>No</b> access");
+        assert!(html.contains(r#"data-status="403""#));
+        assert!(html.contains(ERROR_ATTRIBUTE));
+        assert!(html.contains("&lt;b&gt;No&lt;/b&gt; access"));
+    }
+
+    #[tokio::test]
+    async fn test_routes_serve_assets() {
+        let app = routes::<()>();
+        let response = app
+            .oneshot(Request::get(SCRIPT_PATH).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/javascript"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Dev mailbox page
+//!
+//! Shows emails the email service captured in dry-run mode instead of
+//! sending them, so staging testers can read password resets and receipts
+//! without a real inbox. All routes require the "admin" role.
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use acton_htmx::handlers::dev_mailbox;
+//!
+//! let app = Router::new()
+//!     .merge(dev_mailbox::routes())
+//!     .with_state(state);
+//! ```
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::DateTime;
+use std::fmt::Write as _;
+
+use super::require_admin;
+use crate::htmx::auth::{user::User, Authenticated};
+use crate::htmx::clients::{CapturedEmail, DevMailbox};
+use crate::htmx::state::ActonHtmxState;
+use crate::htmx::template::helpers::escape_html;
+
+/// Maximum emails shown on the mailbox page
+const PAGE_LIMIT: u32 = 100;
+
+/// Dev mailbox routes
+///
+/// - `GET /dev/mailbox` lists captured emails, newest first
+/// - `GET /dev/mailbox/{message_id}` shows one email
+pub fn routes() -> Router<ActonHtmxState> {
+    Router::new()
+        .route("/dev/mailbox", get(mailbox_page))
+        .route("/dev/mailbox/{message_id}", get(message_page))
+}
+
+async fn fetch_mailbox(state: &ActonHtmxState, limit: u32) -> Result<DevMailbox, StatusCode> {
+    let client = state
+        .services()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?
+        .email()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let mut client = client.write().await;
+    client.list_dev_mailbox(limit).await.map_err(|e| {
+        tracing::error!(error = %e, "Failed to list dev mailbox");
+        StatusCode::BAD_GATEWAY
+    })
+}
+
+async fn mailbox_page(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "view dev mailbox")?;
+    let mailbox = fetch_mailbox(&state, PAGE_LIMIT).await?;
+    Ok(Html(format!("<h1>Dev mailbox</h1>{}", mailbox_partial(&mailbox))).into_response())
+}
+
+async fn message_page(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+    Path(message_id): Path<String>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "view dev mailbox")?;
+    let mailbox = fetch_mailbox(&state, 0).await?;
+    let message = mailbox
+        .messages
+        .iter()
+        .find(|m| m.message_id == message_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Html(message_partial(message)).into_response())
+}
+
+/// Table of captured emails
+#[must_use]
+pub fn mailbox_partial(mailbox: &DevMailbox) -> String {
+    let mut html = String::new();
+    if !mailbox.dry_run {
+        html.push_str(
+            r#"<p class="notice">The email service is not in dry-run mode; emails are being sent.</p>"#,
+        );
+    }
+    if mailbox.messages.is_empty() {
+        html.push_str(r#"<p class="empty">No captured emails.</p>"#);
+        return html;
+    }
+    html.push_str(
+        "<table><thead><tr><th>Captured</th><th>To</th><th>Subject</th></tr></thead><tbody>",
+    );
+    for message in &mailbox.messages {
+        let _ = write!(
+            html,
+            r#"<tr><td>{}</td><td>{}</td><td><a href="/dev/mailbox/{}">{}</a></td></tr>"#,
+            captured_at(message),
+            escape_html(&recipients(message)),
+            escape_html(&message.message_id),
+            escape_html(&message.email.subject),
+        );
+    }
+    html.push_str("</tbody></table>");
+    html
+}
+
+/// One captured email, with the HTML body in a sandboxed frame
+#[must_use]
+pub fn message_partial(message: &CapturedEmail) -> String {
+    let email = &message.email;
+    let mut html = format!(
+        r#"<article class="captured-email"><h1>{}</h1><dl><dt>From</dt><dd>{}</dd><dt>To</dt><dd>{}</dd><dt>Captured</dt><dd>{}</dd></dl>"#,
+        escape_html(&email.subject),
+        escape_html(&email.from.email),
+        escape_html(&recipients(message)),
+        captured_at(message),
+    );
+    if let Some(body) = &email.html_body {
+        let _ = write!(
+            html,
+            r#"<iframe sandbox="" srcdoc="{}" title="HTML body"></iframe>"#,
+            escape_html(body)
+        );
+    }
+    if let Some(body) = &email.text_body {
+        let _ = write!(html, "<pre>{}</pre>", escape_html(body));
+    }
+    if !email.attachments.is_empty() {
+        html.push_str("<h2>Attachments</h2><ul>");
+        for attachment in &email.attachments {
+            let _ = write!(
+                html,
+                "<li>{} ({}, {} bytes)</li>",
+                escape_html(&attachment.filename),
+                escape_html(&attachment.content_type),
+                attachment.content.len()
+            );
+        }
+        html.push_str("</ul>");
+    }
+    html.push_str(r#"<a href="/dev/mailbox">Back to mailbox</a></article>"#);
+    html
+}
+
+fn recipients(message: &CapturedEmail) -> String {
+    message
+        .email
+        .to
+        .iter()
+        .map(|addr| addr.email.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn captured_at(message: &CapturedEmail) -> String {
+    DateTime::from_timestamp(message.captured_at, 0)
+        .map_or_else(String::new, |t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::clients::{EmailAddr, EmailMessage};
+
+    fn captured() -> CapturedEmail {
+        CapturedEmail {
+            message_id: "abc-123".to_string(),
+            captured_at: 1_700_000_000,
+            email: EmailMessage {
+                from: EmailAddr {
+                    email: "noreply@example.com".to_string(),
+                    name: None,
+                },
+                to: vec![EmailAddr {
+                    email: "ada@example.com".to_string(),
+                    name: None,
+                }],
+                subject: "Reset <your> password".to_string(),
+                html_body: Some(r#"<a href="https://example.com/reset">Reset</a>"#.to_string()),
+                text_body: Some("Visit https://example.com/reset".to_string()),
+                ..EmailMessage::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_mailbox_partial_lists_messages() {
+        let html = mailbox_partial(&DevMailbox {
+            dry_run: true,
+            messages: vec![captured()],
+        });
+        assert!(html.contains(r#"href="/dev/mailbox/abc-123""#));
+        assert!(html.contains("Reset &lt;your&gt; password"));
+        assert!(html.contains("ada@example.com"));
+        assert!(html.contains("2023-11-14 22:13:20 UTC"));
+        assert!(!html.contains("not in dry-run mode"));
+    }
+
+    #[test]
+    fn test_mailbox_partial_warns_when_sending() {
+        let html = mailbox_partial(&DevMailbox {
+            dry_run: false,
+            messages: Vec::new(),
+        });
+        assert!(html.contains("not in dry-run mode"));
+        assert!(html.contains("No captured emails"));
+    }
+
+    #[test]
+    fn test_message_partial_sandboxes_html_body() {
+        let html = message_partial(&captured());
+        assert!(html.contains(r#"<iframe sandbox="" srcdoc="&lt;a href=&quot;https://example.com/reset&quot;&gt;Reset&lt;/a&gt;""#));
+        assert!(html.contains("<pre>Visit https://example.com/reset</pre>"));
+    }
+}
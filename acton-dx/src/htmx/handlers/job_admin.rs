@@ -22,9 +22,8 @@
 //!     .route("/admin/jobs/stats", get(job_admin::job_stats));
 //! ```
 
-use acton_reactive::prelude::ActorHandleInterface;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
@@ -35,8 +34,8 @@ use std::time::Duration;
 use crate::htmx::auth::{user::User, Authenticated};
 use crate::htmx::jobs::{
     agent::{
-        CancelJobRequest, ClearDeadLetterQueueRequest, GetMetricsRequest, RetryAllFailedRequest,
-        RetryJobRequest,
+        CancelJobRequest, ClearDeadLetterQueueRequest, DeadLetterEntry, GetMetricsRequest,
+        ListDeadLetterQueueRequest, RetryAllFailedRequest, RetryJobRequest,
     },
     JobId,
 };
@@ -187,6 +186,7 @@ pub async fn list_jobs(
 /// - `403 FORBIDDEN` if user is not an admin
 /// - `408 REQUEST_TIMEOUT` if agent doesn't respond within 100ms
 /// - `500 INTERNAL_SERVER_ERROR` if agent response channel fails
+/// - `503 SERVICE_UNAVAILABLE` if the job agent's mailbox is full
 #[allow(clippy::cast_precision_loss)] // Acceptable for metrics
 pub async fn job_stats(
     State(state): State<ActonHtmxState>,
@@ -205,7 +205,10 @@ pub async fn job_stats(
     let (request, rx) = GetMetricsRequest::new();
 
     // Send message to JobAgent (fire-and-forget from handler perspective)
-    state.job_agent().send(request).await;
+    state.job_mailbox().send(request).await.map_err(|e| {
+        tracing::warn!(error = %e, "Job agent mailbox rejected request");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
 
     // Await response with 100ms timeout
     let timeout = Duration::from_millis(100);
@@ -280,6 +283,7 @@ pub async fn job_stats(
 /// - `404 NOT_FOUND` if job is not in dead letter queue
 /// - `408 REQUEST_TIMEOUT` if agent doesn't respond within 100ms
 /// - `500 INTERNAL_SERVER_ERROR` if agent response channel fails
+/// - `503 SERVICE_UNAVAILABLE` if the job agent's mailbox is full
 pub async fn retry_job(
     State(state): State<ActonHtmxState>,
     Authenticated(admin): Authenticated<User>,
@@ -299,7 +303,10 @@ pub async fn retry_job(
     let (request, rx) = RetryJobRequest::new(job_id);
 
     // Send message to JobAgent
-    state.job_agent().send(request).await;
+    state.job_mailbox().send(request).await.map_err(|e| {
+        tracing::warn!(error = %e, "Job agent mailbox rejected request");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
 
     // Await response with 100ms timeout
     let timeout = Duration::from_millis(100);
@@ -364,6 +371,7 @@ pub async fn retry_job(
 /// - `403 FORBIDDEN` if user is not an admin
 /// - `408 REQUEST_TIMEOUT` if agent doesn't respond within 500ms
 /// - `500 INTERNAL_SERVER_ERROR` if agent response channel fails
+/// - `503 SERVICE_UNAVAILABLE` if the job agent's mailbox is full
 pub async fn retry_all_jobs(
     State(state): State<ActonHtmxState>,
     Authenticated(admin): Authenticated<User>,
@@ -381,7 +389,10 @@ pub async fn retry_all_jobs(
     let (request, rx) = RetryAllFailedRequest::new();
 
     // Send message to JobAgent
-    state.job_agent().send(request).await;
+    state.job_mailbox().send(request).await.map_err(|e| {
+        tracing::warn!(error = %e, "Job agent mailbox rejected request");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
 
     // Await response with 500ms timeout (may need to requeue many jobs)
     let timeout = Duration::from_millis(500);
@@ -439,6 +450,7 @@ pub async fn retry_all_jobs(
 /// - `404 NOT_FOUND` if job is not found
 /// - `408 REQUEST_TIMEOUT` if agent doesn't respond within 100ms
 /// - `500 INTERNAL_SERVER_ERROR` if agent response channel fails
+/// - `503 SERVICE_UNAVAILABLE` if the job agent's mailbox is full
 pub async fn cancel_job(
     State(state): State<ActonHtmxState>,
     Authenticated(admin): Authenticated<User>,
@@ -458,7 +470,10 @@ pub async fn cancel_job(
     let (request, rx) = CancelJobRequest::new(job_id);
 
     // Send message to JobAgent
-    state.job_agent().send(request).await;
+    state.job_mailbox().send(request).await.map_err(|e| {
+        tracing::warn!(error = %e, "Job agent mailbox rejected request");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
 
     // Await response with 100ms timeout
     let timeout = Duration::from_millis(100);
@@ -498,6 +513,155 @@ pub async fn cancel_job(
     }
 }
 
+/// Get the job drain status
+///
+/// Reports whether the job agent is running, draining, or drained, so
+/// deploy tooling can wait for running jobs before stopping the process.
+/// Requires admin role.
+///
+/// # Example
+///
+/// ```bash
+/// GET /admin/jobs/drain
+/// ```
+///
+/// Response:
+/// ```json
+/// {
+///   "state": "draining",
+///   "started_at": "2025-11-22T10:00:00Z",
+///   "deadline": "2025-11-22T10:00:30Z",
+///   "running": 2
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns:
+/// - `403 FORBIDDEN` if user is not an admin
+/// - `500 INTERNAL_SERVER_ERROR` if the agent does not respond
+pub async fn drain_status(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+) -> Result<Response, StatusCode> {
+    // Verify admin role
+    if !admin.roles.contains(&"admin".to_string()) {
+        tracing::warn!(
+            admin_id = admin.id,
+            "Non-admin attempted to view job drain status"
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let status = state.get_job_drain_status().await.map_err(|e| {
+        tracing::error!("Job drain status request failed: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok((StatusCode::OK, Json(status)).into_response())
+}
+
+/// Query parameters for listings that include job payloads
+#[derive(Debug, Default, Deserialize)]
+pub struct RevealParams {
+    /// Show payload previews instead of redacting them
+    #[serde(default)]
+    pub reveal: bool,
+}
+
+/// Response for dead letter queue listing endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeadLetterListResponse {
+    /// Jobs in the dead letter queue, oldest first
+    pub jobs: Vec<DeadLetterEntry>,
+    /// Number of jobs in the dead letter queue
+    pub total: usize,
+    /// Whether payload previews are revealed
+    pub revealed: bool,
+}
+
+/// List the dead letter queue
+///
+/// Payloads may contain personal data, so previews are redacted unless
+/// `?reveal=true` is passed. Reveals are logged with the admin's ID.
+/// Requires admin role.
+///
+/// # Example
+///
+/// ```bash
+/// GET /admin/jobs/dead-letter?reveal=true
+/// ```
+///
+/// Response:
+/// ```json
+/// {
+///   "jobs": [{"id": "...", "job_type": "WelcomeEmail", "attempts": 3,
+///             "enqueued_at": "2025-11-22T10:00:00Z",
+///             "payload_preview": "[redacted, 27 bytes]"}],
+///   "total": 1,
+///   "revealed": false
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns:
+/// - `403 FORBIDDEN` if user is not an admin
+/// - `408 REQUEST_TIMEOUT` if agent doesn't respond within 100ms
+/// - `500 INTERNAL_SERVER_ERROR` if agent response channel fails
+/// - `503 SERVICE_UNAVAILABLE` if the job agent's mailbox is full
+pub async fn list_dead_letter_queue(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+    Query(params): Query<RevealParams>,
+) -> Result<Response, StatusCode> {
+    // Verify admin role
+    if !admin.roles.contains(&"admin".to_string()) {
+        tracing::warn!(
+            admin_id = admin.id,
+            "Non-admin attempted to list dead letter queue"
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if params.reveal {
+        tracing::info!(
+            admin_id = admin.id,
+            "Admin revealed dead letter queue payloads"
+        );
+    }
+
+    // Create request with response channel
+    let (request, rx) = ListDeadLetterQueueRequest::new(params.reveal);
+
+    // Send message to JobAgent
+    state.job_mailbox().send(request).await.map_err(|e| {
+        tracing::warn!(error = %e, "Job agent mailbox rejected request");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+
+    // Await response with 100ms timeout
+    let timeout = Duration::from_millis(100);
+    let jobs = tokio::time::timeout(timeout, rx)
+        .await
+        .map_err(|_| {
+            tracing::error!("List dead letter queue timeout");
+            StatusCode::REQUEST_TIMEOUT
+        })?
+        .map_err(|_| {
+            tracing::error!("List dead letter queue channel error");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let response = DeadLetterListResponse {
+        total: jobs.len(),
+        jobs,
+        revealed: params.reveal,
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
 /// Clear the dead letter queue
 ///
 /// Permanently removes all jobs from the dead letter queue.
@@ -523,6 +687,7 @@ pub async fn cancel_job(
 /// - `403 FORBIDDEN` if user is not an admin
 /// - `408 REQUEST_TIMEOUT` if agent doesn't respond within 100ms
 /// - `500 INTERNAL_SERVER_ERROR` if agent response channel fails
+/// - `503 SERVICE_UNAVAILABLE` if the job agent's mailbox is full
 pub async fn clear_dead_letter_queue(
     State(state): State<ActonHtmxState>,
     Authenticated(admin): Authenticated<User>,
@@ -540,7 +705,10 @@ pub async fn clear_dead_letter_queue(
     let (request, rx) = ClearDeadLetterQueueRequest::new();
 
     // Send message to JobAgent
-    state.job_agent().send(request).await;
+    state.job_mailbox().send(request).await.map_err(|e| {
+        tracing::warn!(error = %e, "Job agent mailbox rejected request");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
 
     // Await response with 100ms timeout
     let timeout = Duration::from_millis(100);
@@ -4,12 +4,36 @@
 //! - Cedar policy administration (admin-only endpoints)
 //! - Role management (admin-only endpoints, requires postgres)
 //! - Job management (admin-only endpoints)
+//! - Scheduled job management page (admin-only)
+//! - Background operation progress partials
+//! - Dev mailbox for emails captured in dry-run mode (requires microservices)
+//! - Cedar policy editor with versions and rollback (requires microservices)
 
 #[cfg(feature = "cedar")]
 pub mod cedar_admin;
+#[cfg(feature = "microservices")]
+pub mod dev_mailbox;
 pub mod job_admin;
+pub mod operations;
+#[cfg(feature = "microservices")]
+pub mod policy_admin;
 #[cfg(feature = "postgres")]
 pub mod role_admin;
+pub mod schedule_admin;
+
+use axum::http::StatusCode;
+
+use crate::htmx::auth::user::User;
+
+/// Reject users without the "admin" role, logging the attempted `action`
+pub(crate) fn require_admin(admin: &User, action: &str) -> Result<(), StatusCode> {
+    if admin.roles.contains(&"admin".to_string()) {
+        Ok(())
+    } else {
+        tracing::warn!(admin_id = admin.id, action, "Non-admin attempted an admin action");
+        Err(StatusCode::FORBIDDEN)
+    }
+}
 
 // Re-exports
 #[cfg(feature = "cedar")]
@@ -19,6 +43,12 @@ pub use cedar_admin::{policy_status, reload_policies, PolicyStatusResponse, Relo
 #[allow(unused_imports)]
 pub use job_admin::{job_stats, list_jobs, JobListResponse, JobStatsResponse};
 
+#[allow(unused_imports)]
+pub use operations::Operations;
+
+#[allow(unused_imports)]
+pub use schedule_admin::ScheduleAdmin;
+
 #[cfg(feature = "postgres")]
 #[allow(unused_imports)]
 pub use role_admin::{
@@ -0,0 +1,358 @@
+//! Background operation partials
+//!
+//! The usual flow for a slow action (export, import, report): the handler
+//! enqueues a job and immediately returns a progress partial; the partial
+//! polls the status endpoint, which keeps returning progress until the job
+//! finishes and then swaps in the result or error partial, stopping the
+//! polling.
+//!
+//! Jobs report progress and their result through
+//! [`JobContext::operation`](crate::htmx::jobs::JobContext::operation), so
+//! the worker executing jobs must build their context with
+//! [`with_operations`](crate::htmx::jobs::JobContext::with_operations) and
+//! [`with_job_id`](crate::htmx::jobs::JobContext::with_job_id).
+//!
+//! Operation IDs are random UUIDs; anyone holding one can poll it, so
+//! don't put data in the result partial that the requesting user isn't
+//! allowed to share with whoever they send the link to.
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use acton_htmx::handlers::operations::Operations;
+//! use acton_htmx::jobs::OperationTracker;
+//!
+//! let tracker = OperationTracker::new();
+//! let operations = Operations::new(tracker.clone());
+//!
+//! async fn export(
+//!     State(state): State<ActonHtmxState>,
+//!     Extension(operations): Extension<Operations>,
+//! ) -> Result<Html<String>, StatusCode> {
+//!     operations.start(&state, &ExportContactsJob { format: Format::Csv }).await
+//! }
+//!
+//! let app = Router::new()
+//!     .route("/contacts/export", post(export))
+//!     .merge(operations.routes())
+//!     .layer(Extension(operations))
+//!     .with_state(state);
+//! ```
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Extension, Router,
+};
+use serde::Serialize;
+use std::fmt::Write as _;
+
+use crate::htmx::jobs::{
+    agent::EnqueueJob, Job, JobId, JobStatus, OperationOutcome, OperationProgress,
+    OperationTracker,
+};
+use crate::htmx::state::ActonHtmxState;
+use crate::htmx::template::helpers::escape_html;
+
+/// Path the progress partial polls; `{id}` is the job ID
+pub const STATUS_PATH: &str = "/operations/{id}";
+
+/// Poll interval of the progress partial
+const POLL_INTERVAL: &str = "1s";
+
+/// Starts background operations and serves their status
+#[derive(Debug, Clone)]
+pub struct Operations {
+    tracker: OperationTracker,
+}
+
+impl Operations {
+    /// Serve operations reported to the given tracker
+    #[must_use]
+    pub const fn new(tracker: OperationTracker) -> Self {
+        Self { tracker }
+    }
+
+    /// The tracker jobs report to
+    #[must_use]
+    pub const fn tracker(&self) -> &OperationTracker {
+        &self.tracker
+    }
+
+    /// Status routes
+    ///
+    /// - `GET /operations/{id}` returns the progress partial while the job is
+    ///   pending or running, and the result or error partial once it is done
+    pub fn routes(&self) -> Router<ActonHtmxState> {
+        Router::new()
+            .route(STATUS_PATH, get(operation_status))
+            .layer(Extension(self.clone()))
+    }
+
+    /// Enqueue `job` and return its initial progress partial
+    ///
+    /// # Errors
+    ///
+    /// Returns `INTERNAL_SERVER_ERROR` if the job cannot be serialized, and
+    /// `SERVICE_UNAVAILABLE` if the job agent's mailbox is full.
+    pub async fn start<J>(&self, state: &ActonHtmxState, job: &J) -> Result<Html<String>, StatusCode>
+    where
+        J: Job + Serialize,
+    {
+        let id = self.enqueue(state, job, None).await?;
+        Ok(Html(progress_partial(id, &status_url(id), None)))
+    }
+
+    /// Enqueue `job` on behalf of a tenant and return its progress partial
+    ///
+    /// The job counts against the tenant's quotas.
+    ///
+    /// # Errors
+    ///
+    /// Returns `INTERNAL_SERVER_ERROR` if the job cannot be serialized, and
+    /// `SERVICE_UNAVAILABLE` if the job agent's mailbox is full.
+    pub async fn start_for_tenant<J>(
+        &self,
+        state: &ActonHtmxState,
+        job: &J,
+        tenant_id: impl Into<String>,
+    ) -> Result<Html<String>, StatusCode>
+    where
+        J: Job + Serialize,
+    {
+        let id = self.enqueue(state, job, Some(tenant_id.into())).await?;
+        Ok(Html(progress_partial(id, &status_url(id), None)))
+    }
+
+    async fn enqueue<J>(
+        &self,
+        state: &ActonHtmxState,
+        job: &J,
+        tenant_id: Option<String>,
+    ) -> Result<JobId, StatusCode>
+    where
+        J: Job + Serialize,
+    {
+        let payload = serde_json::to_vec(job).map_err(|e| {
+            tracing::error!(error = %e, job_type = job.job_type(), "Failed to serialize operation job");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        let id = JobId::new();
+        self.tracker.track(id);
+        state
+            .job_mailbox()
+            .send(EnqueueJob {
+                id,
+                job_type: job.job_type().to_string(),
+                payload,
+                priority: job.priority(),
+                max_retries: job.max_retries(),
+                timeout: job.timeout(),
+                tenant_id,
+            })
+            .await
+            .map_err(|e| {
+                tracing::warn!(error = %e, job_type = job.job_type(), "Job agent mailbox rejected operation");
+                StatusCode::SERVICE_UNAVAILABLE
+            })?;
+        Ok(id)
+    }
+}
+
+/// URL the progress partial of `id` polls
+#[must_use]
+pub fn status_url(id: JobId) -> String {
+    format!("/operations/{id}")
+}
+
+async fn operation_status(
+    State(state): State<ActonHtmxState>,
+    Extension(operations): Extension<Operations>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let id = id
+        .parse::<uuid::Uuid>()
+        .map(JobId::from)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let status = state.get_job_status(id).await.map_err(|e| {
+        tracing::error!(error = %e, "Job status request failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let progress = operations.tracker.get(&id);
+    if status.is_none() && progress.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let html = render_operation(id, &status_url(id), status.as_ref(), progress.as_ref());
+    if is_finished(status.as_ref(), progress.as_ref()) {
+        // Polling stops with the final partial, so nobody asks again
+        operations.tracker.remove(&id);
+    }
+    Ok(Html(html).into_response())
+}
+
+/// Whether an operation has reached its final partial
+fn is_finished(status: Option<&JobStatus>, progress: Option<&OperationProgress>) -> bool {
+    progress.is_some_and(|p| p.outcome.is_some())
+        || matches!(
+            status,
+            None | Some(JobStatus::Completed { .. } | JobStatus::Failed { .. } | JobStatus::Cancelled { .. })
+        )
+}
+
+/// Render the partial for an operation's current state
+///
+/// An outcome reported by the job wins over the agent's status. A job the
+/// agent no longer knows about without a reported outcome is shown as
+/// finished.
+#[must_use]
+pub fn render_operation(
+    id: JobId,
+    status_url: &str,
+    status: Option<&JobStatus>,
+    progress: Option<&OperationProgress>,
+) -> String {
+    match (progress.and_then(|p| p.outcome.as_ref()), status) {
+        (Some(OperationOutcome::Succeeded { html }), _) => result_partial(id, html),
+        (Some(OperationOutcome::Failed { error }), _) | (None, Some(JobStatus::Failed { error, .. })) => {
+            error_partial(id, error)
+        }
+        (None, Some(JobStatus::Cancelled { .. })) => error_partial(id, "The operation was cancelled."),
+        (None, Some(JobStatus::Completed { .. }) | None) => {
+            result_partial(id, "<p>Done.</p>")
+        }
+        (None, Some(status @ (JobStatus::Pending | JobStatus::Running { .. } | JobStatus::Retrying { .. }))) => {
+            let mut progress = progress.cloned().unwrap_or_default();
+            if progress.message.is_none() {
+                progress.message = Some(
+                    match status {
+                        JobStatus::Pending => "Waiting to start…",
+                        JobStatus::Retrying { .. } => "Retrying…",
+                        _ => "Working…",
+                    }
+                    .to_string(),
+                );
+            }
+            progress_partial(id, status_url, Some(&progress))
+        }
+    }
+}
+
+/// Progress partial that polls `status_url` and replaces itself
+#[must_use]
+pub fn progress_partial(id: JobId, status_url: &str, progress: Option<&OperationProgress>) -> String {
+    let mut html = format!(
+        r#"<div id="operation-{id}" class="operation operation-running" hx-get="{}" hx-trigger="every {POLL_INTERVAL}" hx-swap="outerHTML" aria-busy="true">"#,
+        escape_html(status_url)
+    );
+    match progress.and_then(|p| p.percent) {
+        Some(percent) => {
+            let _ = write!(html, r#"<progress max="100" value="{percent}">{percent}%</progress>"#);
+        }
+        None => html.push_str("<progress></progress>"),
+    }
+    let message = progress
+        .and_then(|p| p.message.as_deref())
+        .unwrap_or("Starting…");
+    let _ = write!(html, r#"<p class="operation-message">{}</p></div>"#, escape_html(message));
+    html
+}
+
+/// Final partial showing the job's result HTML
+#[must_use]
+pub fn result_partial(id: JobId, html: &str) -> String {
+    format!(r#"<div id="operation-{id}" class="operation operation-succeeded">{html}</div>"#)
+}
+
+/// Final partial showing an error message
+#[must_use]
+pub fn error_partial(id: JobId, error: &str) -> String {
+    format!(
+        r#"<div id="operation-{id}" class="operation operation-failed" role="alert"><p>{}</p></div>"#,
+        escape_html(error)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn progress(percent: Option<u8>, outcome: Option<OperationOutcome>) -> OperationProgress {
+        OperationProgress {
+            percent,
+            message: Some("Exporting <rows>".to_string()),
+            outcome,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_progress_partial_polls() {
+        let id = JobId::new();
+        let url = status_url(id);
+        let html = render_operation(
+            id,
+            &url,
+            Some(&JobStatus::Running { started_at: Utc::now() }),
+            Some(&progress(Some(40), None)),
+        );
+        assert!(html.contains(&format!(r#"hx-get="/operations/{id}""#)));
+        assert!(html.contains(r#"hx-trigger="every 1s""#));
+        assert!(html.contains(r#"value="40""#));
+        assert!(html.contains("Exporting &lt;rows&gt;"));
+    }
+
+    #[test]
+    fn test_pending_without_progress_shows_waiting() {
+        let id = JobId::new();
+        let html = render_operation(id, &status_url(id), Some(&JobStatus::Pending), None);
+        assert!(html.contains("Waiting to start"));
+        assert!(html.contains("<progress></progress>"));
+        assert!(!is_finished(Some(&JobStatus::Pending), None));
+    }
+
+    #[test]
+    fn test_reported_outcome_stops_polling() {
+        let id = JobId::new();
+        let done = progress(
+            Some(100),
+            Some(OperationOutcome::Succeeded {
+                html: r#"<a href="/exports/1.csv">Download</a>"#.to_string(),
+            }),
+        );
+        let status = JobStatus::Running { started_at: Utc::now() };
+        let html = render_operation(id, &status_url(id), Some(&status), Some(&done));
+        assert!(html.contains(r#"<a href="/exports/1.csv">Download</a>"#));
+        assert!(!html.contains("hx-trigger"));
+        assert!(is_finished(Some(&status), Some(&done)));
+    }
+
+    #[test]
+    fn test_failures_render_escaped_error() {
+        let id = JobId::new();
+        let failed = JobStatus::Failed {
+            failed_at: Utc::now(),
+            attempts: 3,
+            error: "timeout <30s>".to_string(),
+        };
+        let html = render_operation(id, &status_url(id), Some(&failed), None);
+        assert!(html.contains(r#"role="alert""#));
+        assert!(html.contains("timeout &lt;30s&gt;"));
+        assert!(!html.contains("hx-trigger"));
+
+        let cancelled = JobStatus::Cancelled { cancelled_at: Utc::now() };
+        assert!(render_operation(id, &status_url(id), Some(&cancelled), None).contains("cancelled"));
+    }
+
+    #[test]
+    fn test_unknown_job_without_outcome_is_done() {
+        let id = JobId::new();
+        let html = render_operation(id, &status_url(id), None, Some(&progress(Some(90), None)));
+        assert!(html.contains("operation-succeeded"));
+        assert!(is_finished(None, None));
+    }
+}
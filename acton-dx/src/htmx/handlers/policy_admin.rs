@@ -0,0 +1,557 @@
+//! Cedar policy editor page
+//!
+//! Lets admins edit the policy set held by the Cedar service without a
+//! deploy. Edits are checked with the service's `ValidatePolicy` RPC as the
+//! admin types and shown as a diff against the active policies. Saving
+//! stores a new version; any version can then be activated, and a rollback
+//! re-activates the version that was active before. All routes require the
+//! "admin" role.
+//!
+//! Saves, activations and rollbacks are logged with the admin who made them,
+//! and the Cedar service records each activation in the history shown on
+//! the page.
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use acton_htmx::handlers::policy_admin;
+//!
+//! let app = Router::new()
+//!     .merge(policy_admin::routes())
+//!     .with_state(state);
+//! ```
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Form, Router,
+};
+use chrono::DateTime;
+use serde::Deserialize;
+use similar::{ChangeTag, TextDiff};
+use std::fmt::Write as _;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::require_admin;
+use crate::htmx::auth::{user::User, Authenticated};
+use crate::htmx::clients::{
+    ActivationResult, CedarClient, ClientError, PolicyHistory, PolicyVersion,
+};
+use crate::htmx::responses::HxResponseTrigger;
+use crate::htmx::state::ActonHtmxState;
+use crate::htmx::template::helpers::escape_html;
+
+/// Event that makes the version list reload itself
+const CHANGED_EVENT: &str = "policies-changed";
+
+/// Unchanged lines shown around each change in diffs
+const DIFF_CONTEXT: usize = 3;
+
+/// Policy editor routes
+///
+/// - `GET /admin/policies` shows the editor and version history
+/// - `POST /admin/policies` (form `policy_text`, `comment`) saves a version
+/// - `POST /admin/policies/validate` (form `policy_text`) validates an edit
+///   and diffs it against the active policies
+/// - `GET /admin/policies/versions` returns the version history partial
+/// - `GET /admin/policies/versions/{version}` shows a version and its diff
+/// - `POST /admin/policies/versions/{version}/activate` activates a version
+/// - `POST /admin/policies/rollback` re-activates the previous version
+///
+/// Activations and rollbacks respond with the refreshed history partial.
+pub fn routes() -> Router<ActonHtmxState> {
+    Router::new()
+        .route("/admin/policies", get(editor_page).post(save_version))
+        .route("/admin/policies/validate", post(validate))
+        .route("/admin/policies/versions", get(versions))
+        .route("/admin/policies/versions/{version}", get(version_page))
+        .route(
+            "/admin/policies/versions/{version}/activate",
+            post(activate_version),
+        )
+        .route("/admin/policies/rollback", post(rollback))
+}
+
+/// Body of the editor form
+#[derive(Debug, Deserialize)]
+struct PolicyForm {
+    /// Cedar source of the whole policy set
+    policy_text: String,
+    /// Why the version was saved
+    #[serde(default)]
+    comment: String,
+}
+
+fn cedar_client(state: &ActonHtmxState) -> Result<Arc<RwLock<CedarClient>>, StatusCode> {
+    state
+        .services()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?
+        .cedar()
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+}
+
+/// Message of an error the Cedar service returned, to show to the admin
+fn rejection(error: ClientError, action: &str) -> Result<String, StatusCode> {
+    match error {
+        ClientError::ServiceError { message, .. } | ClientError::InvalidFields { message, .. } => {
+            Ok(message)
+        }
+        e => {
+            tracing::error!(error = %e, action, "Cedar policy request failed");
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
+async fn fetch_history(client: &RwLock<CedarClient>) -> Result<PolicyHistory, StatusCode> {
+    client
+        .write()
+        .await
+        .list_policy_versions()
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to list policy versions");
+            StatusCode::BAD_GATEWAY
+        })
+}
+
+async fn fetch_version(
+    client: &RwLock<CedarClient>,
+    version: Option<u64>,
+) -> Result<PolicyVersion, StatusCode> {
+    client
+        .write()
+        .await
+        .get_policy_version(version)
+        .await
+        .map_err(|e| match e {
+            ClientError::ServiceError { .. } => StatusCode::NOT_FOUND,
+            e => {
+                tracing::error!(error = %e, "Failed to get policy version");
+                StatusCode::BAD_GATEWAY
+            }
+        })
+}
+
+async fn editor_page(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "view policies")?;
+    let client = cedar_client(&state)?;
+    let active = fetch_version(&client, None).await?;
+    let history = fetch_history(&client).await?;
+    Ok(Html(format!(
+        "<h1>Cedar policies</h1>{}{}",
+        editor_partial(&active),
+        history_partial(&history, None)
+    ))
+    .into_response())
+}
+
+async fn versions(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "view policies")?;
+    let client = cedar_client(&state)?;
+    let history = fetch_history(&client).await?;
+    Ok(Html(history_partial(&history, None)).into_response())
+}
+
+async fn version_page(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+    Path(version): Path<u64>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "view policies")?;
+    let client = cedar_client(&state)?;
+    let shown = fetch_version(&client, Some(version)).await?;
+    let active = fetch_version(&client, None).await?;
+    Ok(Html(version_partial(&shown, &active.policy_text)).into_response())
+}
+
+async fn validate(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+    Form(form): Form<PolicyForm>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "validate policy")?;
+    let client = cedar_client(&state)?;
+    let validation = client
+        .write()
+        .await
+        .validate_policy(&form.policy_text)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to validate policies");
+            StatusCode::BAD_GATEWAY
+        })?;
+    let active = fetch_version(&client, None).await?;
+    Ok(Html(result_partial(
+        None,
+        &validation.errors,
+        &active.policy_text,
+        &form.policy_text,
+    ))
+    .into_response())
+}
+
+async fn save_version(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+    Form(form): Form<PolicyForm>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "save policy")?;
+    let client = cedar_client(&state)?;
+    let active = fetch_version(&client, None).await?;
+
+    let saved = client
+        .write()
+        .await
+        .save_policy_version(&form.policy_text, admin.email.as_str(), form.comment.trim())
+        .await;
+    let saved = match saved {
+        Ok(saved) => saved,
+        Err(e) => {
+            let error = rejection(e, "save")?;
+            let html = result_partial(None, &[error], &active.policy_text, &form.policy_text);
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(html)).into_response());
+        }
+    };
+    let Some(version) = saved.version.filter(|_| saved.valid) else {
+        let html = result_partial(None, &saved.errors, &active.policy_text, &form.policy_text);
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(html)).into_response());
+    };
+
+    tracing::info!(
+        admin_id = admin.id,
+        email = %admin.email,
+        version = version.version,
+        policies = version.policies_count,
+        "Cedar policy version saved"
+    );
+    let message = format!("Saved as version {}", version.version);
+    Ok((
+        HxResponseTrigger::normal([CHANGED_EVENT]),
+        Html(result_partial(
+            Some(&message),
+            &[],
+            &active.policy_text,
+            &form.policy_text,
+        )),
+    )
+        .into_response())
+}
+
+async fn activate_version(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+    Path(version): Path<u64>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "activate policy")?;
+    let client = cedar_client(&state)?;
+    let activated = client
+        .write()
+        .await
+        .activate_policy_version(version, admin.email.as_str())
+        .await;
+    respond_to_activation(&client, &admin, activated, "activate").await
+}
+
+async fn rollback(
+    State(state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "roll back policy")?;
+    let client = cedar_client(&state)?;
+    let activated = client
+        .write()
+        .await
+        .rollback_policies(admin.email.as_str())
+        .await;
+    respond_to_activation(&client, &admin, activated, "rollback").await
+}
+
+async fn respond_to_activation(
+    client: &RwLock<CedarClient>,
+    admin: &User,
+    activated: Result<ActivationResult, ClientError>,
+    action: &str,
+) -> Result<Response, StatusCode> {
+    let (status, error) = match activated {
+        Ok(activated) => {
+            tracing::info!(
+                admin_id = admin.id,
+                email = %admin.email,
+                action,
+                version = activated.active_version,
+                previous_version = activated.previous_version,
+                policies = activated.policies_loaded,
+                "Cedar policy version activated"
+            );
+            (StatusCode::OK, None)
+        }
+        Err(e) => (StatusCode::CONFLICT, Some(rejection(e, action)?)),
+    };
+
+    let history = fetch_history(client).await?;
+    Ok((status, Html(history_partial(&history, error.as_deref()))).into_response())
+}
+
+/// Policy editor form
+///
+/// Typing validates the text and diffs it against the active policies in
+/// `#policy-result`; submitting saves it as a new version.
+#[must_use]
+pub fn editor_partial(active: &PolicyVersion) -> String {
+    format!(
+        r##"<form id="policy-editor" hx-post="/admin/policies" hx-target="#policy-result"><textarea name="policy_text" rows="24" spellcheck="false" aria-label="Cedar policies" hx-post="/admin/policies/validate" hx-trigger="keyup changed delay:500ms" hx-target="#policy-result">{}</textarea><input type="text" name="comment" placeholder="What changed?" aria-label="Comment"><button type="submit">Save version</button></form><div id="policy-result" aria-live="polite"></div>"##,
+        escape_html(&active.policy_text)
+    )
+}
+
+/// Outcome of validating or saving an edit, with its diff
+///
+/// `errors` come from validation; without errors the diff against the
+/// active policies is shown.
+#[must_use]
+pub fn result_partial(
+    message: Option<&str>,
+    errors: &[String],
+    active: &str,
+    draft: &str,
+) -> String {
+    let mut html = String::new();
+    if let Some(message) = message {
+        let _ = write!(html, r#"<p class="notice">{}</p>"#, escape_html(message));
+    }
+    if errors.is_empty() {
+        html.push_str(r#"<p class="valid">Policies are valid.</p>"#);
+        html.push_str(&diff_partial(active, draft));
+    } else {
+        html.push_str(r#"<ul class="error" role="alert">"#);
+        for error in errors {
+            let _ = write!(html, "<li>{}</li>", escape_html(error));
+        }
+        html.push_str("</ul>");
+    }
+    html
+}
+
+/// A stored version with its diff against the active policies
+#[must_use]
+pub fn version_partial(version: &PolicyVersion, active: &str) -> String {
+    let mut html = format!(
+        r#"<article class="policy-version"><h2>Version {}</h2><dl><dt>Saved by</dt><dd>{}</dd><dt>Saved</dt><dd>{}</dd><dt>Comment</dt><dd>{}</dd></dl>"#,
+        version.version,
+        escape_html(&version.author),
+        timestamp(version.created_at),
+        escape_html(&version.comment),
+    );
+    if version.active {
+        html.push_str(r#"<p class="notice">This is the active version.</p>"#);
+    } else {
+        html.push_str(&diff_partial(active, &version.policy_text));
+        let _ = write!(
+            html,
+            r##"<button hx-post="/admin/policies/versions/{}/activate" hx-target="#policy-versions" hx-swap="outerHTML" hx-confirm="Activate version {}?">Activate</button>"##,
+            version.version, version.version
+        );
+    }
+    let _ = write!(
+        html,
+        "<pre>{}</pre></article>",
+        escape_html(&version.policy_text)
+    );
+    html
+}
+
+/// Version history with activate and rollback controls
+///
+/// Reloads itself when a save fires the `policies-changed` event. Actions
+/// swap the whole history, so `error` is shown above it when the Cedar
+/// service refused one.
+#[must_use]
+pub fn history_partial(history: &PolicyHistory, error: Option<&str>) -> String {
+    let mut html = format!(
+        r#"<div id="policy-versions" class="policy-versions" hx-get="/admin/policies/versions" hx-trigger="{CHANGED_EVENT} from:body" hx-swap="outerHTML">"#
+    );
+    if let Some(error) = error {
+        let _ = write!(
+            html,
+            r#"<p class="error" role="alert">{}</p>"#,
+            escape_html(error)
+        );
+    }
+    if !history.activations.is_empty() {
+        html.push_str(
+            r##"<button hx-post="/admin/policies/rollback" hx-target="#policy-versions" hx-swap="outerHTML" hx-confirm="Roll back to the previously active version?">Roll back</button>"##,
+        );
+    }
+
+    html.push_str("<h2>Versions</h2><table><thead><tr><th>Version</th><th>Saved</th><th>By</th><th>Policies</th><th>Comment</th><th></th></tr></thead><tbody>");
+    for version in &history.versions {
+        let action = if version.version == history.active_version {
+            "active".to_string()
+        } else {
+            format!(
+                r##"<button hx-post="/admin/policies/versions/{}/activate" hx-target="#policy-versions" hx-swap="outerHTML" hx-confirm="Activate version {}?">Activate</button>"##,
+                version.version, version.version
+            )
+        };
+        let _ = write!(
+            html,
+            r##"<tr><td><a href="/admin/policies/versions/{}" hx-get="/admin/policies/versions/{}" hx-target="#policy-result">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{action}</td></tr>"##,
+            version.version,
+            version.version,
+            version.version,
+            timestamp(version.created_at),
+            escape_html(&version.author),
+            version.policies_count,
+            escape_html(&version.comment),
+        );
+    }
+    html.push_str("</tbody></table>");
+
+    if !history.activations.is_empty() {
+        html.push_str("<h2>Activations</h2><ul>");
+        for activation in &history.activations {
+            let _ = write!(
+                html,
+                "<li>{}: {} {} version {} (was {})</li>",
+                timestamp(activation.activated_at),
+                escape_html(&activation.actor),
+                if activation.rollback {
+                    "rolled back to"
+                } else {
+                    "activated"
+                },
+                activation.version,
+                activation.previous_version,
+            );
+        }
+        html.push_str("</ul>");
+    }
+    html.push_str("</div>");
+    html
+}
+
+/// Line diff from `active` to `draft`, with unchanged lines around changes
+#[must_use]
+pub fn diff_partial(active: &str, draft: &str) -> String {
+    if active == draft {
+        return r#"<p class="diff-empty">No changes from the active policies.</p>"#.to_string();
+    }
+
+    let diff = TextDiff::from_lines(active, draft);
+    let mut html = String::from(r#"<pre class="policy-diff">"#);
+    for (index, group) in diff.grouped_ops(DIFF_CONTEXT).iter().enumerate() {
+        if index > 0 {
+            html.push_str("<span class=\"diff-gap\">…</span>\n");
+        }
+        for op in group {
+            for change in diff.iter_changes(op) {
+                let (tag, sign) = match change.tag() {
+                    ChangeTag::Delete => ("del", '-'),
+                    ChangeTag::Insert => ("ins", '+'),
+                    ChangeTag::Equal => ("span", ' '),
+                };
+                let line = change.value().trim_end_matches('\n');
+                let _ = writeln!(html, "<{tag}>{sign} {}</{tag}>", escape_html(line));
+            }
+        }
+    }
+    html.push_str("</pre>");
+    html
+}
+
+fn timestamp(secs: i64) -> String {
+    DateTime::from_timestamp(secs, 0).map_or_else(String::new, |t| {
+        t.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::clients::PolicyActivation;
+
+    const ACTIVE: &str = "permit(principal, action == Action::\"read\", resource);\n";
+    const DRAFT: &str = "permit(principal, action == Action::\"read\", resource);\nforbid(principal, action == Action::\"delete\", resource);\n";
+
+    fn version(version: u64, active: bool) -> PolicyVersion {
+        PolicyVersion {
+            version,
+            policy_text: DRAFT.to_string(),
+            author: "ada@example.com".to_string(),
+            comment: "No <deletes>".to_string(),
+            created_at: 1_700_000_000,
+            policies_count: 2,
+            active,
+        }
+    }
+
+    #[test]
+    fn test_diff_partial_marks_changed_lines() {
+        let html = diff_partial(ACTIVE, DRAFT);
+        assert!(html.contains(
+            "<span>  permit(principal, action == Action::&quot;read&quot;, resource);</span>"
+        ));
+        assert!(html.contains(
+            "<ins>+ forbid(principal, action == Action::&quot;delete&quot;, resource);</ins>"
+        ));
+        assert!(!html.contains("<del>"));
+
+        assert!(diff_partial(ACTIVE, ACTIVE).contains("No changes"));
+    }
+
+    #[test]
+    fn test_result_partial_shows_errors_instead_of_diff() {
+        let html = result_partial(None, &["unexpected <token>".to_string()], ACTIVE, "permit(");
+        assert!(html.contains(r#"role="alert"><li>unexpected &lt;token&gt;</li>"#));
+        assert!(!html.contains("policy-diff"));
+
+        let html = result_partial(Some("Saved as version 2"), &[], ACTIVE, DRAFT);
+        assert!(html.contains("Saved as version 2"));
+        assert!(html.contains("policy-diff"));
+    }
+
+    #[test]
+    fn test_history_partial() {
+        let history = PolicyHistory {
+            active_version: 1,
+            versions: vec![version(2, false), version(1, true)],
+            activations: vec![PolicyActivation {
+                version: 1,
+                previous_version: 2,
+                actor: "ada@example.com".to_string(),
+                activated_at: 1_700_000_000,
+                rollback: true,
+            }],
+        };
+        let html = history_partial(&history, Some("No earlier policy version"));
+
+        assert!(html.contains(r#"hx-post="/admin/policies/versions/2/activate""#));
+        assert!(!html.contains("/admin/policies/versions/1/activate"));
+        assert!(html.contains(r#"hx-post="/admin/policies/rollback""#));
+        assert!(html.contains("No &lt;deletes&gt;"));
+        assert!(html
+            .contains("2023-11-14 22:13:20 UTC: ada@example.com rolled back to version 1 (was 2)"));
+        assert!(html.contains(r#"role="alert">No earlier policy version"#));
+    }
+
+    #[test]
+    fn test_version_partial_offers_activation_unless_active() {
+        let html = version_partial(&version(2, false), ACTIVE);
+        assert!(html.contains("/admin/policies/versions/2/activate"));
+        assert!(html.contains("<ins>"));
+
+        let html = version_partial(&version(1, true), DRAFT);
+        assert!(!html.contains("/activate"));
+        assert!(html.contains("active version"));
+    }
+}
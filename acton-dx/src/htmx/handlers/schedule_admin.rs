@@ -0,0 +1,307 @@
+//! Scheduled job admin page
+//!
+//! Lists schedules registered with the [`ScheduledJobAgent`] with their
+//! next-run times and last outcome, and lets admins pause, resume, or
+//! reschedule them without a deploy. All routes require the "admin" role.
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use acton_htmx::handlers::schedule_admin::ScheduleAdmin;
+//! use acton_htmx::jobs::agent::ScheduledJobAgent;
+//!
+//! let scheduler = ScheduledJobAgent::spawn(&mut runtime, state.job_agent().clone()).await?;
+//! let app = Router::new()
+//!     .merge(ScheduleAdmin::new(scheduler).routes())
+//!     .with_state(state);
+//! ```
+//!
+//! [`ScheduledJobAgent`]: crate::htmx::jobs::agent::ScheduledJobAgent
+
+use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use super::require_admin;
+use crate::htmx::auth::{user::User, Authenticated};
+use crate::htmx::jobs::{
+    agent::{
+        ScheduledJobEntry, ScheduledJobMessage, ScheduledJobRequest, ScheduledJobResponse,
+        ScheduledRunOutcome,
+    },
+    JobId, JobSchedule,
+};
+use crate::htmx::state::ActonHtmxState;
+use crate::htmx::template::helpers::escape_html;
+
+/// Admin routes for a [`ScheduledJobAgent`](crate::htmx::jobs::agent::ScheduledJobAgent)
+#[derive(Debug, Clone)]
+pub struct ScheduleAdmin {
+    scheduler: ActorHandle,
+}
+
+impl ScheduleAdmin {
+    /// Create admin routes for the given scheduler handle
+    #[must_use]
+    pub const fn new(scheduler: ActorHandle) -> Self {
+        Self { scheduler }
+    }
+
+    /// Schedule admin routes
+    ///
+    /// - `GET /admin/jobs/schedules` shows the schedule table
+    /// - `POST /admin/jobs/schedules/{id}/pause` disables a schedule
+    /// - `POST /admin/jobs/schedules/{id}/resume` enables it again
+    /// - `POST /admin/jobs/schedules/{id}/schedule` (form `cron` or
+    ///   `interval_secs`) replaces its schedule
+    ///
+    /// Actions respond with the refreshed table partial.
+    pub fn routes(&self) -> Router<ActonHtmxState> {
+        Router::new()
+            .route("/admin/jobs/schedules", get(schedules_page))
+            .route("/admin/jobs/schedules/{id}/pause", post(pause_schedule))
+            .route("/admin/jobs/schedules/{id}/resume", post(resume_schedule))
+            .route("/admin/jobs/schedules/{id}/schedule", post(update_schedule))
+            .layer(Extension(self.clone()))
+    }
+
+    /// Send a message to the scheduler and await its reply
+    async fn request(&self, message: ScheduledJobMessage) -> Result<ScheduledJobResponse, StatusCode> {
+        let (request, rx) = ScheduledJobRequest::new(message);
+        self.scheduler.send(request).await;
+
+        let timeout = Duration::from_millis(100);
+        tokio::time::timeout(timeout, rx)
+            .await
+            .map_err(|_| {
+                tracing::error!("Scheduled job request timeout");
+                StatusCode::REQUEST_TIMEOUT
+            })?
+            .map_err(|_| {
+                tracing::error!("Scheduled job request channel error");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })
+    }
+
+    /// Current schedules, ordered by next run
+    async fn schedules(&self) -> Result<Vec<ScheduledJobEntry>, StatusCode> {
+        match self.request(ScheduledJobMessage::GetScheduledJobs).await? {
+            ScheduledJobResponse::ScheduledJobs(jobs) => Ok(jobs),
+            _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+}
+
+/// Form for replacing a schedule; set exactly one field
+#[derive(Debug, Deserialize)]
+struct ScheduleForm {
+    /// Cron expression (`sec min hour day_of_month month day_of_week`)
+    #[serde(default)]
+    cron: Option<String>,
+    /// Fixed interval in seconds
+    #[serde(default)]
+    interval_secs: Option<u64>,
+}
+
+impl ScheduleForm {
+    fn into_schedule(self) -> Result<JobSchedule, String> {
+        match (self.cron.filter(|c| !c.trim().is_empty()), self.interval_secs) {
+            (Some(cron), None) => JobSchedule::cron(cron.trim()).map_err(|e| e.to_string()),
+            (None, Some(0)) => Err("interval must be at least one second".to_string()),
+            (None, Some(secs)) => Ok(JobSchedule::every(Duration::from_secs(secs))),
+            _ => Err("set either a cron expression or an interval".to_string()),
+        }
+    }
+}
+
+async fn schedules_page(
+    State(_state): State<ActonHtmxState>,
+    Authenticated(admin): Authenticated<User>,
+    Extension(schedules): Extension<ScheduleAdmin>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "view schedules")?;
+    let jobs = schedules.schedules().await?;
+    Ok(Html(format!(
+        "<h1>Scheduled jobs</h1>{}",
+        schedule_table_partial(&jobs, None)
+    ))
+    .into_response())
+}
+
+async fn pause_schedule(
+    Authenticated(admin): Authenticated<User>,
+    Extension(schedules): Extension<ScheduleAdmin>,
+    Path(id): Path<JobId>,
+) -> Result<Response, StatusCode> {
+    set_enabled(&admin, &schedules, id, false).await
+}
+
+async fn resume_schedule(
+    Authenticated(admin): Authenticated<User>,
+    Extension(schedules): Extension<ScheduleAdmin>,
+    Path(id): Path<JobId>,
+) -> Result<Response, StatusCode> {
+    set_enabled(&admin, &schedules, id, true).await
+}
+
+async fn set_enabled(
+    admin: &User,
+    schedules: &ScheduleAdmin,
+    id: JobId,
+    enabled: bool,
+) -> Result<Response, StatusCode> {
+    require_admin(admin, if enabled { "resume schedule" } else { "pause schedule" })?;
+    schedules
+        .request(ScheduledJobMessage::SetScheduledJobEnabled { id, enabled })
+        .await?;
+    tracing::info!(admin_id = admin.id, %id, enabled, "Scheduled job toggled");
+
+    let jobs = schedules.schedules().await?;
+    Ok(Html(schedule_table_partial(&jobs, None)).into_response())
+}
+
+async fn update_schedule(
+    Authenticated(admin): Authenticated<User>,
+    Extension(schedules): Extension<ScheduleAdmin>,
+    Path(id): Path<JobId>,
+    Form(form): Form<ScheduleForm>,
+) -> Result<Response, StatusCode> {
+    require_admin(&admin, "reschedule job")?;
+
+    let (status, error) = match form.into_schedule() {
+        Ok(schedule) => {
+            match schedules
+                .request(ScheduledJobMessage::UpdateSchedule { id, schedule })
+                .await?
+            {
+                ScheduledJobResponse::ScheduleUpdated(entry) => {
+                    tracing::info!(
+                        admin_id = admin.id,
+                        %id,
+                        schedule = %entry.schedule.description(),
+                        "Scheduled job rescheduled"
+                    );
+                    (StatusCode::OK, None)
+                }
+                ScheduledJobResponse::NotFound { .. } => return Err(StatusCode::NOT_FOUND),
+                _ => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            }
+        }
+        Err(error) => (StatusCode::UNPROCESSABLE_ENTITY, Some(error)),
+    };
+
+    let jobs = schedules.schedules().await?;
+    Ok((status, Html(schedule_table_partial(&jobs, error.as_deref()))).into_response())
+}
+
+/// Table of scheduled jobs with pause/resume and reschedule controls
+///
+/// Actions swap the whole table, so `error` is shown above it when a
+/// reschedule was rejected.
+#[must_use]
+pub fn schedule_table_partial(jobs: &[ScheduledJobEntry], error: Option<&str>) -> String {
+    let mut html = String::from(r#"<div id="job-schedules" class="job-schedules">"#);
+    if let Some(error) = error {
+        let _ = write!(html, r#"<p class="error" role="alert">{}</p>"#, escape_html(error));
+    }
+    if jobs.is_empty() {
+        html.push_str("<p>No scheduled jobs.</p></div>");
+        return html;
+    }
+
+    html.push_str("<table><thead><tr><th>Job</th><th>Schedule</th><th>Next run</th><th>Runs</th><th>Last run</th><th></th></tr></thead><tbody>");
+    for job in jobs {
+        let id = job.id;
+        let (next_run, toggle) = if job.enabled {
+            (job.next_execution.format("%Y-%m-%d %H:%M:%S UTC").to_string(), "pause")
+        } else {
+            ("paused".to_string(), "resume")
+        };
+        let last_run = job.last_run.as_ref().map_or_else(
+            || "never".to_string(),
+            |run| {
+                let outcome = match &run.outcome {
+                    ScheduledRunOutcome::Enqueued => "enqueued".to_string(),
+                    ScheduledRunOutcome::Succeeded => "succeeded".to_string(),
+                    ScheduledRunOutcome::Failed { error } => format!("failed: {error}"),
+                };
+                format!("{} ({outcome})", run.enqueued_at.format("%Y-%m-%d %H:%M:%S UTC"))
+            },
+        );
+        let _ = write!(
+            html,
+            r##"<tr id="schedule-{id}"><td>{}</td><td>{}</td><td>{next_run}</td><td>{}</td><td>{}</td><td><button hx-post="/admin/jobs/schedules/{id}/{toggle}" hx-target="#job-schedules" hx-swap="outerHTML">{toggle}</button><form hx-post="/admin/jobs/schedules/{id}/schedule" hx-target="#job-schedules" hx-swap="outerHTML"><input type="text" name="cron" placeholder="0 0 * * * *" aria-label="Cron expression"><input type="number" name="interval_secs" min="1" placeholder="seconds" aria-label="Interval in seconds"><button type="submit">Reschedule</button></form></td></tr>"##,
+            escape_html(&job.job_type),
+            escape_html(&job.schedule.description()),
+            job.execution_count,
+            escape_html(&last_run),
+        );
+    }
+    html.push_str("</tbody></table></div>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::jobs::agent::ScheduledRun;
+    use chrono::Utc;
+
+    fn entry(enabled: bool) -> ScheduledJobEntry {
+        ScheduledJobEntry {
+            id: JobId::new(),
+            job_type: "<Digest>".to_string(),
+            payload: Vec::new(),
+            schedule: JobSchedule::every(Duration::from_secs(60)),
+            priority: 0,
+            max_retries: 3,
+            timeout: Duration::from_secs(300),
+            next_execution: Utc::now(),
+            execution_count: 2,
+            enabled,
+            last_run: Some(ScheduledRun {
+                job_id: JobId::new(),
+                enqueued_at: Utc::now(),
+                outcome: ScheduledRunOutcome::Failed {
+                    error: "smtp timeout".to_string(),
+                },
+            }),
+        }
+    }
+
+    #[test]
+    fn test_schedule_table_partial() {
+        let active = entry(true);
+        let paused = entry(false);
+        let html = schedule_table_partial(&[active.clone(), paused.clone()], Some("bad cron"));
+
+        assert!(html.contains("&lt;Digest&gt;"));
+        assert!(html.contains("every 60s"));
+        assert!(html.contains("failed: smtp timeout"));
+        assert!(html.contains(&format!("/admin/jobs/schedules/{}/pause", active.id)));
+        assert!(html.contains(&format!("/admin/jobs/schedules/{}/resume", paused.id)));
+        assert!(html.contains(r#"role="alert">bad cron"#));
+    }
+
+    #[test]
+    fn test_schedule_form() {
+        let form = |cron: Option<&str>, interval_secs| ScheduleForm {
+            cron: cron.map(str::to_string),
+            interval_secs,
+        };
+        assert!(form(Some("0 */5 * * * *"), None).into_schedule().is_ok());
+        assert!(form(None, Some(30)).into_schedule().is_ok());
+        assert!(form(Some("not cron"), None).into_schedule().is_err());
+        assert!(form(None, Some(0)).into_schedule().is_err());
+        assert!(form(Some("0 * * * * *"), Some(30)).into_schedule().is_err());
+        assert!(form(Some(" "), None).into_schedule().is_err());
+    }
+}
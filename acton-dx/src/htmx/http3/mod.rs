@@ -0,0 +1,358 @@
+//! HTTP/3 (QUIC) listener
+//!
+//! Serves the same axum [`Router`] over HTTP/3 alongside the regular TCP
+//! listener, using `quinn` for QUIC and `h3` for HTTP/3 framing. Certificates
+//! come from the shared [`TlsConfig`].
+//!
+//! Browsers only try HTTP/3 after seeing an `Alt-Svc` advertisement on a
+//! TCP response; add [`AltSvcLayer`] to the router (or use
+//! [`apply_default_layers`](crate::htmx::middleware::apply_default_layers),
+//! which does so when `http3.enabled` is set).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::config::ActonHtmxConfig;
+//! use acton_htmx::http3::serve_http3;
+//! use acton_htmx::middleware::apply_default_layers;
+//!
+//! let config = ActonHtmxConfig::load_for_service("my-app")?;
+//! let app = apply_default_layers(routes(), &config);
+//!
+//! let h3 = tokio::spawn(serve_http3(app.clone(), config.http3.clone(), config.tls.clone()));
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
+//! axum::serve(listener, app).await?;
+//! ```
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, HeaderValue, Request, Response},
+    Router,
+};
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::StreamExt;
+use h3::server::RequestResolver;
+use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::ServiceExt;
+use tracing::{debug, info, warn};
+
+use crate::htmx::config::{Http3Config, TlsConfig};
+
+/// Hop-by-hop headers that are not allowed in HTTP/3 responses
+const CONNECTION_HEADERS: &[header::HeaderName] = &[
+    header::CONNECTION,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// HTTP/3 listener errors
+#[derive(Debug, thiserror::Error)]
+pub enum Http3Error {
+    /// TLS certificate or key could not be loaded
+    #[error("TLS configuration error: {0}")]
+    Tls(String),
+
+    /// Listener address is invalid
+    #[error("Invalid HTTP/3 bind address '{0}'")]
+    InvalidAddress(String),
+
+    /// Failed to bind the UDP socket
+    #[error("Failed to bind HTTP/3 listener: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// HTTP/3 protocol error on a connection or stream
+    #[error("HTTP/3 protocol error: {0}")]
+    Protocol(String),
+
+    /// Request body exceeded the configured limit
+    #[error("Request body exceeds {0} bytes")]
+    BodyTooLarge(usize),
+}
+
+/// Build a QUIC server configuration from the shared TLS settings
+///
+/// # Errors
+///
+/// Returns [`Http3Error::Tls`] if the certificate or key is missing or invalid.
+pub fn quic_server_config(tls: &TlsConfig) -> Result<quinn::ServerConfig, Http3Error> {
+    let (Some(cert_path), Some(key_path)) = (&tls.cert_path, &tls.key_path) else {
+        return Err(Http3Error::Tls(
+            "tls.cert_path and tls.key_path must be set for HTTP/3".to_string(),
+        ));
+    };
+
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .map_err(|e| Http3Error::Tls(format!("{}: {e}", cert_path.display())))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| Http3Error::Tls(format!("{}: {e}", key_path.display())))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut crypto = rustls::ServerConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(|e| Http3Error::Tls(e.to_string()))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| Http3Error::Tls(e.to_string()))?;
+    crypto.alpn_protocols = vec![b"h3".to_vec()];
+
+    let quic = quinn::crypto::rustls::QuicServerConfig::try_from(crypto)
+        .map_err(|e| Http3Error::Tls(e.to_string()))?;
+    Ok(quinn::ServerConfig::with_crypto(Arc::new(quic)))
+}
+
+/// Serve `router` over HTTP/3 until the endpoint is closed
+///
+/// Each QUIC connection and each request runs on its own task. The client
+/// address is exposed to handlers as `ConnectInfo<SocketAddr>`, matching the
+/// TCP listener.
+///
+/// # Errors
+///
+/// Returns an error if TLS setup fails or the UDP socket cannot be bound.
+pub async fn serve_http3(
+    router: Router,
+    config: Http3Config,
+    tls: TlsConfig,
+) -> Result<(), Http3Error> {
+    let addr: SocketAddr = config
+        .bind_addr
+        .parse()
+        .map_err(|_| Http3Error::InvalidAddress(config.bind_addr.clone()))?;
+    let endpoint = quinn::Endpoint::server(quic_server_config(&tls)?, addr)?;
+    info!(addr = %addr, "HTTP/3 listener started");
+
+    let max_body = config.max_request_body_bytes;
+    while let Some(incoming) = endpoint.accept().await {
+        let router = router.clone();
+        tokio::spawn(async move {
+            match incoming.await {
+                Ok(connection) => {
+                    if let Err(e) = handle_connection(connection, router, max_body).await {
+                        debug!(error = %e, "HTTP/3 connection closed with error");
+                    }
+                }
+                Err(e) => debug!(error = %e, "QUIC handshake failed"),
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Accept requests on one QUIC connection
+async fn handle_connection(
+    connection: quinn::Connection,
+    router: Router,
+    max_body: usize,
+) -> Result<(), Http3Error> {
+    let remote = connection.remote_address();
+    let mut h3_conn = h3::server::builder()
+        .build::<_, Bytes>(h3_quinn::Connection::new(connection))
+        .await
+        .map_err(|e| Http3Error::Protocol(e.to_string()))?;
+
+    loop {
+        match h3_conn.accept().await {
+            Ok(Some(resolver)) => {
+                let router = router.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_request(resolver, router, remote, max_body).await {
+                        warn!(error = %e, remote = %remote, "HTTP/3 request failed");
+                    }
+                });
+            }
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(Http3Error::Protocol(e.to_string())),
+        }
+    }
+}
+
+/// Run one HTTP/3 request through the router
+async fn handle_request(
+    resolver: RequestResolver<h3_quinn::Connection, Bytes>,
+    router: Router,
+    remote: SocketAddr,
+    max_body: usize,
+) -> Result<(), Http3Error> {
+    let protocol = |e: h3::error::StreamError| Http3Error::Protocol(e.to_string());
+
+    let (request, mut stream) = resolver.resolve_request().await.map_err(protocol)?;
+
+    let mut body = BytesMut::new();
+    while let Some(mut chunk) = stream.recv_data().await.map_err(protocol)? {
+        if body.len() + chunk.remaining() > max_body {
+            let too_large = Response::builder()
+                .status(axum::http::StatusCode::PAYLOAD_TOO_LARGE)
+                .body(())
+                .map_err(|e| Http3Error::Protocol(e.to_string()))?;
+            stream.send_response(too_large).await.map_err(protocol)?;
+            stream.finish().await.map_err(protocol)?;
+            return Err(Http3Error::BodyTooLarge(max_body));
+        }
+        body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+    }
+
+    let (parts, ()) = request.into_parts();
+    let mut request = Request::from_parts(parts, Body::from(body.freeze()));
+    request.extensions_mut().insert(ConnectInfo(remote));
+
+    let response = match router.oneshot(request).await {
+        Ok(response) => response,
+        Err(infallible) => match infallible {},
+    };
+
+    let (mut parts, body) = response.into_parts();
+    for name in CONNECTION_HEADERS {
+        parts.headers.remove(name);
+    }
+    stream
+        .send_response(Response::from_parts(parts, ()))
+        .await
+        .map_err(protocol)?;
+
+    let mut data = body.into_data_stream();
+    while let Some(chunk) = data.next().await {
+        let chunk = chunk.map_err(|e| Http3Error::Protocol(e.to_string()))?;
+        stream.send_data(chunk).await.map_err(protocol)?;
+    }
+    stream.finish().await.map_err(protocol)
+}
+
+/// `Alt-Svc` header value advertising HTTP/3 on `port`
+#[must_use]
+pub fn alt_svc_value(port: u16, max_age_secs: u64) -> String {
+    format!("h3=\":{port}\"; ma={max_age_secs}")
+}
+
+/// Layer that advertises the HTTP/3 listener via `Alt-Svc`
+#[derive(Clone, Debug)]
+pub struct AltSvcLayer {
+    value: HeaderValue,
+}
+
+impl AltSvcLayer {
+    /// Advertise HTTP/3 on `port` for `max_age_secs`
+    #[must_use]
+    pub fn new(port: u16, max_age_secs: u64) -> Self {
+        Self {
+            value: HeaderValue::from_str(&alt_svc_value(port, max_age_secs))
+                .unwrap_or_else(|_| HeaderValue::from_static("clear")),
+        }
+    }
+
+    /// Build the layer from configuration, if HTTP/3 is enabled
+    #[must_use]
+    pub fn from_config(config: &Http3Config) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        config
+            .alt_svc_port()
+            .map(|port| Self::new(port, config.alt_svc_max_age_secs))
+    }
+}
+
+impl<S> tower::Layer<S> for AltSvcLayer {
+    type Service = AltSvcMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AltSvcMiddleware {
+            inner,
+            value: self.value.clone(),
+        }
+    }
+}
+
+/// Service that adds the `Alt-Svc` header to responses
+#[derive(Clone, Debug)]
+pub struct AltSvcMiddleware<S> {
+    inner: S,
+    value: HeaderValue,
+}
+
+impl<S> tower::Service<Request<Body>> for AltSvcMiddleware<S>
+where
+    S: tower::Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let value = self.value.clone();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            response.headers_mut().entry(header::ALT_SVC).or_insert(value);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+
+    #[test]
+    fn test_alt_svc_value() {
+        assert_eq!(alt_svc_value(443, 86400), "h3=\":443\"; ma=86400");
+    }
+
+    #[test]
+    fn test_alt_svc_layer_from_config() {
+        let mut config = Http3Config::default();
+        assert!(AltSvcLayer::from_config(&config).is_none());
+
+        config.enabled = true;
+        config.bind_addr = "0.0.0.0:8443".to_string();
+        assert_eq!(config.alt_svc_port(), Some(8443));
+
+        config.advertised_port = Some(443);
+        assert_eq!(config.alt_svc_port(), Some(443));
+        assert!(AltSvcLayer::from_config(&config).is_some());
+    }
+
+    #[test]
+    fn test_quic_config_requires_certificates() {
+        let err = quic_server_config(&TlsConfig::default()).unwrap_err();
+        assert!(matches!(err, Http3Error::Tls(_)));
+
+        let tls = TlsConfig {
+            cert_path: Some("/nonexistent/cert.pem".into()),
+            key_path: Some("/nonexistent/key.pem".into()),
+        };
+        assert!(matches!(quic_server_config(&tls), Err(Http3Error::Tls(_))));
+    }
+
+    #[tokio::test]
+    async fn test_alt_svc_header_added() {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(AltSvcLayer::new(443, 3600));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers().get(header::ALT_SVC).unwrap(),
+            "h3=\":443\"; ma=3600"
+        );
+    }
+}
@@ -0,0 +1,1418 @@
+//! Signup invitations and team membership
+//!
+//! A team manager invites an email address to a team with a role. The
+//! invitee receives a link carrying a single-use token; opening it shows the
+//! invitation, and accepting it links the signed-in account (or creates one)
+//! and adds the membership.
+//!
+//! - [`Invitations`] issues, revokes and accepts invitations and manages
+//!   memberships in an [`InvitationStore`] (in-memory, or data-service with
+//!   `microservices`)
+//! - Invitation emails are rendered by [`InvitationEmail`] and delivered
+//!   through any [`EmailSender`], including the email-service backend
+//! - An [`AccountLinker`] decides which account accepts an invitation;
+//!   [`SessionAccountLinker`] requires a signed-in user, and
+//!   `AuthServiceAccounts` (with `microservices`) creates accounts through
+//!   auth-service
+//! - Management actions require a manager role in the team and, with the
+//!   `cedar` feature and [`Invitations::with_cedar`], a Cedar permit for the
+//!   equivalent HTTP action (`POST /teams/{team}/invitations`)
+//!
+//! Only a SHA-256 hash of each token is stored, so a leaked store cannot be
+//! used to accept invitations.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::invitations::{Invitations, MemoryInvitationStore, SessionAccountLinker};
+//!
+//! let invitations = Invitations::new(
+//!     Arc::new(MemoryInvitationStore::new()),
+//!     Arc::new(SessionAccountLinker),
+//! )
+//! .with_sender(Arc::new(email_backend))
+//! .with_base_url("https://app.example.com");
+//!
+//! // When a team is created, make its creator the owner
+//! invitations.add_owner("acme", user_id).await?;
+//!
+//! let app = Router::new().merge(invitations.routes());
+//! ```
+
+#[cfg(feature = "microservices")]
+mod services;
+
+#[cfg(feature = "microservices")]
+pub use services::{AuthServiceAccounts, DataServiceInvitationStore, INVITATIONS_MIGRATION};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::{delete, get, post},
+    Extension, Form, Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::htmx::auth::session::SessionData;
+use crate::htmx::auth::user::User;
+use crate::htmx::email::{Email, EmailError, EmailSender, EmailTemplate};
+#[cfg(feature = "cedar")]
+use crate::htmx::middleware::CedarAuthz;
+use crate::htmx::template::helpers::escape_html;
+use crate::htmx::util::unix_now;
+
+/// Default invitation lifetime
+pub const DEFAULT_INVITATION_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Role given to a team's creator
+pub const OWNER_ROLE: &str = "owner";
+
+/// Invitation errors
+#[derive(Debug, thiserror::Error)]
+pub enum InvitationError {
+    /// No invitation matches the token or ID
+    #[error("Invitation not found")]
+    NotFound,
+
+    /// Invitation is past its expiry
+    #[error("Invitation has expired")]
+    Expired,
+
+    /// Invitation was already accepted
+    #[error("Invitation has already been used")]
+    AlreadyUsed,
+
+    /// Invitation was revoked by a team manager
+    #[error("Invitation has been revoked")]
+    Revoked,
+
+    /// Accepting account's email differs from the invited address
+    #[error("Invitation was sent to a different email address")]
+    EmailMismatch,
+
+    /// Caller may not perform the action
+    #[error("Not permitted to manage this team")]
+    Forbidden,
+
+    /// Request has no authenticated user
+    #[error("Sign in to continue")]
+    Unauthenticated,
+
+    /// Role is not one of the configured team roles
+    #[error("Unknown team role: {0}")]
+    InvalidRole(String),
+
+    /// Input was rejected
+    #[error("Invalid input: {0}")]
+    Invalid(String),
+
+    /// Account could not be created or linked
+    #[error("Account error: {0}")]
+    Account(String),
+
+    /// Invitation email could not be sent
+    #[error("Failed to deliver invitation: {0}")]
+    Delivery(String),
+
+    /// Backing store failed
+    #[error("Invitation store error: {0}")]
+    Store(String),
+}
+
+impl IntoResponse for InvitationError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Expired | Self::AlreadyUsed | Self::Revoked => StatusCode::GONE,
+            Self::EmailMismatch | Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Self::InvalidRole(_) | Self::Invalid(_) | Self::Account(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Self::Delivery(ref e) => {
+                warn!(error = %e, "Invitation delivery failed");
+                StatusCode::BAD_GATEWAY
+            }
+            Self::Store(ref e) => {
+                warn!(error = %e, "Invitation store failed");
+                return StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+impl From<EmailError> for InvitationError {
+    fn from(error: EmailError) -> Self {
+        Self::Delivery(error.to_string())
+    }
+}
+
+/// Lifecycle state of an invitation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvitationState {
+    /// Waiting for the invitee
+    Pending,
+    /// Accepted and turned into a membership
+    Accepted,
+    /// Revoked by a team manager
+    Revoked,
+    /// Past its expiry without being accepted
+    Expired,
+}
+
+/// An invitation to join a team
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invitation {
+    /// Invitation identifier
+    pub id: String,
+    /// SHA-256 hash (hex) of the token sent to the invitee
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    /// Team the invitee joins
+    pub team: String,
+    /// Role granted on acceptance
+    pub role: String,
+    /// Invited email address (lowercase)
+    pub email: String,
+    /// User who sent the invitation
+    pub invited_by: i64,
+    /// Unix time (seconds) the invitation was created
+    pub created_at: u64,
+    /// Unix time (seconds) after which the invitation cannot be accepted
+    pub expires_at: u64,
+    /// Unix time (seconds) the invitation was accepted
+    pub accepted_at: Option<u64>,
+    /// User who accepted the invitation
+    pub accepted_by: Option<i64>,
+    /// Unix time (seconds) the invitation was revoked
+    pub revoked_at: Option<u64>,
+}
+
+impl Invitation {
+    /// State at `now` (Unix seconds)
+    #[must_use]
+    pub const fn state(&self, now: u64) -> InvitationState {
+        if self.accepted_at.is_some() {
+            InvitationState::Accepted
+        } else if self.revoked_at.is_some() {
+            InvitationState::Revoked
+        } else if self.expires_at <= now {
+            InvitationState::Expired
+        } else {
+            InvitationState::Pending
+        }
+    }
+
+    /// Error for accepting or revoking an invitation that is no longer pending
+    const fn check_pending(&self, now: u64) -> Result<(), InvitationError> {
+        match self.state(now) {
+            InvitationState::Pending => Ok(()),
+            InvitationState::Accepted => Err(InvitationError::AlreadyUsed),
+            InvitationState::Revoked => Err(InvitationError::Revoked),
+            InvitationState::Expired => Err(InvitationError::Expired),
+        }
+    }
+}
+
+/// A user's membership in a team
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Membership {
+    /// Team identifier
+    pub team: String,
+    /// Member user ID
+    pub user_id: i64,
+    /// Role within the team
+    pub role: String,
+    /// Unix time (seconds) the user joined
+    pub joined_at: u64,
+}
+
+/// Team management actions subject to authorization
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeamAction {
+    /// Send an invitation
+    Invite,
+    /// List or revoke pending invitations
+    ManageInvitations,
+    /// List members
+    ListMembers,
+    /// Change a member's role
+    ChangeRole,
+    /// Remove another member
+    RemoveMember,
+}
+
+impl TeamAction {
+    /// Equivalent HTTP action used for Cedar checks
+    #[must_use]
+    pub const fn cedar_action(self) -> &'static str {
+        match self {
+            Self::Invite => "POST /teams/{team}/invitations",
+            Self::ManageInvitations => "GET /teams/{team}/invitations",
+            Self::ListMembers => "GET /teams/{team}/members",
+            Self::ChangeRole => "POST /teams/{team}/members/{id}/role",
+            Self::RemoveMember => "DELETE /teams/{team}/members/{id}",
+        }
+    }
+}
+
+/// Storage for invitations and memberships
+#[async_trait]
+pub trait InvitationStore: Send + Sync {
+    /// Store a new invitation
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be written.
+    async fn insert_invitation(&self, invitation: &Invitation) -> Result<(), InvitationError>;
+
+    /// Look up an invitation by ID
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be read.
+    async fn invitation(&self, id: &str) -> Result<Option<Invitation>, InvitationError>;
+
+    /// Look up an invitation by token hash
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be read.
+    async fn invitation_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Invitation>, InvitationError>;
+
+    /// Replace a stored invitation
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be written.
+    async fn update_invitation(&self, invitation: &Invitation) -> Result<(), InvitationError>;
+
+    /// All invitations for a team, newest first
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be read.
+    async fn team_invitations(&self, team: &str) -> Result<Vec<Invitation>, InvitationError>;
+
+    /// Insert or replace a membership
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be written.
+    async fn put_member(&self, membership: &Membership) -> Result<(), InvitationError>;
+
+    /// Remove a membership, returning whether it existed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be written.
+    async fn remove_member(&self, team: &str, user_id: i64) -> Result<bool, InvitationError>;
+
+    /// A user's membership in a team
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be read.
+    async fn member(&self, team: &str, user_id: i64)
+        -> Result<Option<Membership>, InvitationError>;
+
+    /// All members of a team, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be read.
+    async fn members(&self, team: &str) -> Result<Vec<Membership>, InvitationError>;
+
+    /// All of a user's memberships, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Store`] if the store cannot be read.
+    async fn user_memberships(&self, user_id: i64) -> Result<Vec<Membership>, InvitationError>;
+}
+
+/// In-process invitation store
+#[derive(Debug, Default)]
+pub struct MemoryInvitationStore {
+    invitations: Mutex<HashMap<String, Invitation>>,
+    members: Mutex<HashMap<(String, i64), Membership>>,
+}
+
+impl MemoryInvitationStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl InvitationStore for MemoryInvitationStore {
+    async fn insert_invitation(&self, invitation: &Invitation) -> Result<(), InvitationError> {
+        self.invitations
+            .lock()
+            .insert(invitation.id.clone(), invitation.clone());
+        Ok(())
+    }
+
+    async fn invitation(&self, id: &str) -> Result<Option<Invitation>, InvitationError> {
+        Ok(self.invitations.lock().get(id).cloned())
+    }
+
+    async fn invitation_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Invitation>, InvitationError> {
+        Ok(self
+            .invitations
+            .lock()
+            .values()
+            .find(|invitation| invitation.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn update_invitation(&self, invitation: &Invitation) -> Result<(), InvitationError> {
+        self.insert_invitation(invitation).await
+    }
+
+    async fn team_invitations(&self, team: &str) -> Result<Vec<Invitation>, InvitationError> {
+        let mut invitations: Vec<_> = self
+            .invitations
+            .lock()
+            .values()
+            .filter(|invitation| invitation.team == team)
+            .cloned()
+            .collect();
+        invitations.sort_by_key(|invitation| std::cmp::Reverse(invitation.created_at));
+        Ok(invitations)
+    }
+
+    async fn put_member(&self, membership: &Membership) -> Result<(), InvitationError> {
+        self.members.lock().insert(
+            (membership.team.clone(), membership.user_id),
+            membership.clone(),
+        );
+        Ok(())
+    }
+
+    async fn remove_member(&self, team: &str, user_id: i64) -> Result<bool, InvitationError> {
+        Ok(self
+            .members
+            .lock()
+            .remove(&(team.to_string(), user_id))
+            .is_some())
+    }
+
+    async fn member(
+        &self,
+        team: &str,
+        user_id: i64,
+    ) -> Result<Option<Membership>, InvitationError> {
+        Ok(self
+            .members
+            .lock()
+            .get(&(team.to_string(), user_id))
+            .cloned())
+    }
+
+    async fn members(&self, team: &str) -> Result<Vec<Membership>, InvitationError> {
+        let mut members: Vec<_> = self
+            .members
+            .lock()
+            .values()
+            .filter(|membership| membership.team == team)
+            .cloned()
+            .collect();
+        members.sort_by_key(|membership| (membership.joined_at, membership.user_id));
+        Ok(members)
+    }
+
+    async fn user_memberships(&self, user_id: i64) -> Result<Vec<Membership>, InvitationError> {
+        let mut memberships: Vec<_> = self
+            .members
+            .lock()
+            .values()
+            .filter(|membership| membership.user_id == user_id)
+            .cloned()
+            .collect();
+        memberships.sort_by(|a, b| (a.joined_at, &a.team).cmp(&(b.joined_at, &b.team)));
+        Ok(memberships)
+    }
+}
+
+/// Sign-up details submitted with an acceptance
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AcceptForm {
+    /// Display name for a new account
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Password for a new account
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Resolves the account that accepts an invitation
+#[async_trait]
+pub trait AccountLinker: Send + Sync {
+    /// Return the user ID joining the team
+    ///
+    /// `current_user` is the signed-in user, if any. Implementations may
+    /// create an account from `form` when nobody is signed in.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Unauthenticated`] when the invitee must
+    /// sign in first, or another error if the account cannot be linked.
+    async fn link(
+        &self,
+        invitation: &Invitation,
+        current_user: Option<i64>,
+        form: &AcceptForm,
+    ) -> Result<i64, InvitationError>;
+}
+
+/// Links invitations to the signed-in user only
+///
+/// Invitees without an account sign up through the application's normal
+/// flow and then open the invitation link again.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionAccountLinker;
+
+#[async_trait]
+impl AccountLinker for SessionAccountLinker {
+    async fn link(
+        &self,
+        _invitation: &Invitation,
+        current_user: Option<i64>,
+        _form: &AcceptForm,
+    ) -> Result<i64, InvitationError> {
+        current_user.ok_or(InvitationError::Unauthenticated)
+    }
+}
+
+/// Invitation email
+///
+/// Renders a short HTML and plain text message with the accept link.
+#[derive(Debug, Clone)]
+pub struct InvitationEmail {
+    /// Application name shown in the subject and body
+    pub app_name: String,
+    /// Team being joined
+    pub team: String,
+    /// Role granted on acceptance
+    pub role: String,
+    /// Link to the acceptance page
+    pub accept_url: String,
+    /// Days until the invitation expires
+    pub expires_in_days: u64,
+}
+
+impl InvitationEmail {
+    /// Subject line
+    #[must_use]
+    pub fn subject(&self) -> String {
+        format!("You're invited to join {} on {}", self.team, self.app_name)
+    }
+}
+
+impl EmailTemplate for InvitationEmail {
+    fn render_email(&self) -> Result<(Option<String>, Option<String>), EmailError> {
+        let text = format!(
+            "You have been invited to join {team} on {app} as {role}.\n\n\
+             Accept the invitation: {url}\n\n\
+             This link expires in {days} day(s). If you weren't expecting it, ignore this email.\n",
+            team = self.team,
+            app = self.app_name,
+            role = self.role,
+            url = self.accept_url,
+            days = self.expires_in_days,
+        );
+        let html = format!(
+            r#"<p>You have been invited to join <strong>{team}</strong> on {app} as {role}.</p>
+<p><a href="{url}">Accept the invitation</a></p>
+<p>This link expires in {days} day(s). If you weren't expecting it, ignore this email.</p>"#,
+            team = escape_html(&self.team),
+            app = escape_html(&self.app_name),
+            role = escape_html(&self.role),
+            url = escape_html(&self.accept_url),
+            days = self.expires_in_days,
+        );
+        Ok((Some(html), Some(text)))
+    }
+}
+
+/// Invitation and membership manager
+#[derive(Clone)]
+pub struct Invitations {
+    store: Arc<dyn InvitationStore>,
+    accounts: Arc<dyn AccountLinker>,
+    sender: Option<Arc<dyn EmailSender>>,
+    roles: Arc<Vec<String>>,
+    manager_roles: Arc<Vec<String>>,
+    ttl: Duration,
+    base_url: String,
+    from: Option<String>,
+    app_name: String,
+    #[cfg(feature = "cedar")]
+    cedar: Option<CedarAuthz>,
+}
+
+impl std::fmt::Debug for Invitations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Invitations")
+            .field("roles", &self.roles)
+            .field("manager_roles", &self.manager_roles)
+            .field("ttl", &self.ttl)
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Invitations {
+    /// Create a manager backed by `store`, linking accounts with `accounts`
+    ///
+    /// Team roles default to `owner`, `admin` and `member`; `owner` and
+    /// `admin` may manage the team.
+    #[must_use]
+    pub fn new(store: Arc<dyn InvitationStore>, accounts: Arc<dyn AccountLinker>) -> Self {
+        Self {
+            store,
+            accounts,
+            sender: None,
+            roles: Arc::new(vec![
+                OWNER_ROLE.to_string(),
+                "admin".to_string(),
+                "member".to_string(),
+            ]),
+            manager_roles: Arc::new(vec![OWNER_ROLE.to_string(), "admin".to_string()]),
+            ttl: DEFAULT_INVITATION_TTL,
+            base_url: String::new(),
+            from: None,
+            app_name: "our app".to_string(),
+            #[cfg(feature = "cedar")]
+            cedar: None,
+        }
+    }
+
+    /// Deliver invitation emails through `sender`
+    ///
+    /// Without a sender, [`Invitations::invite`] only returns the token.
+    #[must_use]
+    pub fn with_sender(mut self, sender: Arc<dyn EmailSender>) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// Set the team roles that invitations may grant
+    #[must_use]
+    pub fn with_roles<I, R>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: Into<String>,
+    {
+        self.roles = Arc::new(roles.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set the team roles allowed to invite and manage members
+    #[must_use]
+    pub fn with_manager_roles<I, R>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: Into<String>,
+    {
+        self.manager_roles = Arc::new(roles.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set how long invitations stay valid (default 7 days)
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Absolute URL prefix for accept links (`https://app.example.com`)
+    #[must_use]
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sender address for invitation emails
+    #[must_use]
+    pub fn with_from(mut self, from: impl Into<String>) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// Application name used in invitation emails
+    #[must_use]
+    pub fn with_app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = app_name.into();
+        self
+    }
+
+    /// Also require a Cedar permit for every management action
+    ///
+    /// Checks run against the [`User`] in request extensions; requests
+    /// without one are refused.
+    #[cfg(feature = "cedar")]
+    #[must_use]
+    pub fn with_cedar(mut self, cedar: CedarAuthz) -> Self {
+        self.cedar = Some(cedar);
+        self
+    }
+
+    /// Make `user_id` the owner of `team`
+    ///
+    /// Call this when a team is created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be written.
+    pub async fn add_owner(&self, team: &str, user_id: i64) -> Result<Membership, InvitationError> {
+        let membership = Membership {
+            team: team.to_string(),
+            user_id,
+            role: OWNER_ROLE.to_string(),
+            joined_at: unix_now(),
+        };
+        self.store.put_member(&membership).await?;
+        Ok(membership)
+    }
+
+    /// Check that `actor` may perform `action` in `team`
+    ///
+    /// `user` is the actor's [`User`], consulted for Cedar checks.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::Forbidden`] if the actor lacks a manager
+    /// role (or, for [`TeamAction::ListMembers`], any membership) or Cedar
+    /// denies the action.
+    pub async fn authorize(
+        &self,
+        actor: i64,
+        team: &str,
+        action: TeamAction,
+        user: Option<&User>,
+    ) -> Result<Membership, InvitationError> {
+        let membership = self
+            .store
+            .member(team, actor)
+            .await?
+            .ok_or(InvitationError::Forbidden)?;
+        let allowed =
+            action == TeamAction::ListMembers || self.manager_roles.contains(&membership.role);
+        if !allowed {
+            return Err(InvitationError::Forbidden);
+        }
+        #[cfg(not(feature = "cedar"))]
+        let _ = user;
+        #[cfg(feature = "cedar")]
+        if let Some(cedar) = &self.cedar {
+            let permitted = match user {
+                Some(user) => cedar.can_perform(user, action.cedar_action(), None).await,
+                None => false,
+            };
+            if !permitted {
+                return Err(InvitationError::Forbidden);
+            }
+        }
+        Ok(membership)
+    }
+
+    /// Invite `email` to `team` with `role`, sending the invitation email
+    ///
+    /// Returns the invitation and its token. The token is only available
+    /// here; the store keeps its hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::InvalidRole`] or [`InvitationError::Invalid`]
+    /// for bad input, or a store or delivery error.
+    pub async fn invite(
+        &self,
+        invited_by: i64,
+        team: &str,
+        email: &str,
+        role: &str,
+    ) -> Result<(Invitation, String), InvitationError> {
+        if !self.roles.iter().any(|r| r == role) {
+            return Err(InvitationError::InvalidRole(role.to_string()));
+        }
+        let email = email.trim().to_lowercase();
+        if !valid_email(&email) {
+            return Err(InvitationError::Invalid(format!(
+                "{email:?} is not an email address"
+            )));
+        }
+        let token = generate_token();
+        let now = unix_now();
+        let invitation = Invitation {
+            id: uuid::Uuid::new_v4().to_string(),
+            token_hash: hash_token(&token),
+            team: team.to_string(),
+            role: role.to_string(),
+            email,
+            invited_by,
+            created_at: now,
+            expires_at: now.saturating_add(self.ttl.as_secs()),
+            accepted_at: None,
+            accepted_by: None,
+            revoked_at: None,
+        };
+        self.store.insert_invitation(&invitation).await?;
+        self.deliver(&invitation, &token).await?;
+        Ok((invitation, token))
+    }
+
+    /// Link to the acceptance page for `token`
+    #[must_use]
+    pub fn accept_url(&self, token: &str) -> String {
+        format!("{}/invitations/{token}", self.base_url)
+    }
+
+    async fn deliver(&self, invitation: &Invitation, token: &str) -> Result<(), InvitationError> {
+        let Some(sender) = &self.sender else {
+            return Ok(());
+        };
+        let template = InvitationEmail {
+            app_name: self.app_name.clone(),
+            team: invitation.team.clone(),
+            role: invitation.role.clone(),
+            accept_url: self.accept_url(token),
+            expires_in_days: self.ttl.as_secs().div_ceil(86_400),
+        };
+        let mut email = Email::from_template(&template)?
+            .to(&invitation.email)
+            .subject(&template.subject());
+        if let Some(from) = &self.from {
+            email = email.from(from);
+        }
+        sender.send(email).await?;
+        Ok(())
+    }
+
+    /// Pending invitation for `token`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::NotFound`] for unknown tokens, or the
+    /// reason the invitation can no longer be accepted.
+    pub async fn preview(&self, token: &str) -> Result<Invitation, InvitationError> {
+        let invitation = self
+            .store
+            .invitation_by_token(&hash_token(token))
+            .await?
+            .ok_or(InvitationError::NotFound)?;
+        invitation.check_pending(unix_now())?;
+        Ok(invitation)
+    }
+
+    /// Accept the invitation for `token`
+    ///
+    /// The [`AccountLinker`] picks the joining account. A member who already
+    /// holds a manager role keeps it; otherwise the invited role applies.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the invitation cannot be accepted, or an account
+    /// or store error.
+    pub async fn accept(
+        &self,
+        token: &str,
+        current_user: Option<i64>,
+        form: &AcceptForm,
+    ) -> Result<Membership, InvitationError> {
+        let mut invitation = self.preview(token).await?;
+        let user_id = self.accounts.link(&invitation, current_user, form).await?;
+
+        let existing = self.store.member(&invitation.team, user_id).await?;
+        let membership = match existing {
+            Some(existing) if self.manager_roles.contains(&existing.role) => existing,
+            existing => Membership {
+                team: invitation.team.clone(),
+                user_id,
+                role: invitation.role.clone(),
+                joined_at: existing.map_or_else(unix_now, |m| m.joined_at),
+            },
+        };
+        self.store.put_member(&membership).await?;
+
+        invitation.accepted_at = Some(unix_now());
+        invitation.accepted_by = Some(user_id);
+        self.store.update_invitation(&invitation).await?;
+        Ok(membership)
+    }
+
+    /// Revoke a pending invitation
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::NotFound`] if the invitation does not
+    /// belong to `team`, or the reason it is no longer pending.
+    pub async fn revoke(&self, team: &str, id: &str) -> Result<Invitation, InvitationError> {
+        let mut invitation = self
+            .store
+            .invitation(id)
+            .await?
+            .filter(|invitation| invitation.team == team)
+            .ok_or(InvitationError::NotFound)?;
+        invitation.check_pending(unix_now())?;
+        invitation.revoked_at = Some(unix_now());
+        self.store.update_invitation(&invitation).await?;
+        Ok(invitation)
+    }
+
+    /// Pending invitations for a team
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read.
+    pub async fn pending(&self, team: &str) -> Result<Vec<Invitation>, InvitationError> {
+        let now = unix_now();
+        Ok(self
+            .store
+            .team_invitations(team)
+            .await?
+            .into_iter()
+            .filter(|invitation| invitation.state(now) == InvitationState::Pending)
+            .collect())
+    }
+
+    /// Members of a team
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read.
+    pub async fn members(&self, team: &str) -> Result<Vec<Membership>, InvitationError> {
+        self.store.members(team).await
+    }
+
+    /// A user's membership in a team
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read.
+    pub async fn membership(
+        &self,
+        team: &str,
+        user_id: i64,
+    ) -> Result<Option<Membership>, InvitationError> {
+        self.store.member(team, user_id).await
+    }
+
+    /// Teams a user belongs to
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read.
+    pub async fn memberships_of(&self, user_id: i64) -> Result<Vec<Membership>, InvitationError> {
+        self.store.user_memberships(user_id).await
+    }
+
+    /// Remove every member of a team, for deleting the team
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be written.
+    pub async fn disband(&self, team: &str) -> Result<(), InvitationError> {
+        for membership in self.store.members(team).await? {
+            self.store.remove_member(team, membership.user_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Change a member's role
+    ///
+    /// The last owner cannot be demoted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::NotFound`] for non-members,
+    /// [`InvitationError::InvalidRole`] for unknown roles, or
+    /// [`InvitationError::Invalid`] when demoting the last owner.
+    pub async fn change_role(
+        &self,
+        team: &str,
+        user_id: i64,
+        role: &str,
+    ) -> Result<Membership, InvitationError> {
+        if !self.roles.iter().any(|r| r == role) {
+            return Err(InvitationError::InvalidRole(role.to_string()));
+        }
+        let mut membership = self
+            .store
+            .member(team, user_id)
+            .await?
+            .ok_or(InvitationError::NotFound)?;
+        if membership.role == OWNER_ROLE && role != OWNER_ROLE {
+            self.ensure_other_owner(team, user_id).await?;
+        }
+        membership.role = role.to_string();
+        self.store.put_member(&membership).await?;
+        Ok(membership)
+    }
+
+    /// Remove a member from a team
+    ///
+    /// The last owner cannot be removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvitationError::NotFound`] for non-members or
+    /// [`InvitationError::Invalid`] when removing the last owner.
+    pub async fn remove_member(&self, team: &str, user_id: i64) -> Result<(), InvitationError> {
+        let membership = self
+            .store
+            .member(team, user_id)
+            .await?
+            .ok_or(InvitationError::NotFound)?;
+        if membership.role == OWNER_ROLE {
+            self.ensure_other_owner(team, user_id).await?;
+        }
+        self.store.remove_member(team, user_id).await?;
+        Ok(())
+    }
+
+    async fn ensure_other_owner(&self, team: &str, user_id: i64) -> Result<(), InvitationError> {
+        let other_owner = self
+            .store
+            .members(team)
+            .await?
+            .iter()
+            .any(|m| m.role == OWNER_ROLE && m.user_id != user_id);
+        if other_owner {
+            Ok(())
+        } else {
+            Err(InvitationError::Invalid(
+                "a team needs at least one owner".to_string(),
+            ))
+        }
+    }
+
+    /// Invitation and membership routes
+    ///
+    /// - `POST /teams/{team}/invitations` (form `email`, `role`)
+    /// - `GET /teams/{team}/invitations` (JSON, pending only)
+    /// - `POST /teams/{team}/invitations/{id}/revoke`
+    /// - `GET /teams/{team}/members` (JSON)
+    /// - `POST /teams/{team}/members/{user_id}/role` (form `role`)
+    /// - `DELETE /teams/{team}/members/{user_id}` (members may remove themselves)
+    /// - `GET /invitations/{token}` (HTML acceptance partial)
+    /// - `POST /invitations/{token}/accept` (form `name`, `password` for new accounts)
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route(
+                "/teams/{team}/invitations",
+                get(list_invitations).post(create_invitation),
+            )
+            .route(
+                "/teams/{team}/invitations/{id}/revoke",
+                post(revoke_invitation),
+            )
+            .route("/teams/{team}/members", get(list_members))
+            .route(
+                "/teams/{team}/members/{user_id}/role",
+                post(change_member_role),
+            )
+            .route(
+                "/teams/{team}/members/{user_id}",
+                delete(remove_team_member),
+            )
+            .route("/invitations/{token}", get(show_invitation))
+            .route("/invitations/{token}/accept", post(accept_invitation))
+            .with_state(self.clone())
+    }
+}
+
+/// Acceptance page partial for an invitation
+///
+/// Signed-in users see a single accept button; everyone else also gets name
+/// and password fields for creating an account.
+#[must_use]
+pub fn invitation_partial(invitation: &Invitation, token: &str, signed_in: bool) -> String {
+    let team = escape_html(&invitation.team);
+    let role = escape_html(&invitation.role);
+    let email = escape_html(&invitation.email);
+    let token = escape_html(token);
+    let fields = if signed_in {
+        String::new()
+    } else {
+        r#"<label>Name <input type="text" name="name" required></label><label>Password <input type="password" name="password" autocomplete="new-password" required></label>"#.to_string()
+    };
+    format!(
+        r#"<div id="invitation" class="invitation"><p>{email} is invited to join <strong>{team}</strong> as {role}.</p><form hx-post="/invitations/{token}/accept" hx-target="this" hx-swap="outerHTML">{fields}<button type="submit">Accept invitation</button></form></div>"#
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct InviteForm {
+    email: String,
+    role: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoleForm {
+    role: String,
+}
+
+fn actor(session: Option<&Extension<SessionData>>) -> Result<i64, InvitationError> {
+    session
+        .and_then(|Extension(session)| session.user_id)
+        .ok_or(InvitationError::Unauthenticated)
+}
+
+/// Extensions needed to authorize a management request
+struct Caller {
+    session: Option<Extension<SessionData>>,
+    user: Option<Extension<User>>,
+}
+
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for Caller {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            session: parts
+                .extensions
+                .get::<SessionData>()
+                .cloned()
+                .map(Extension),
+            user: parts.extensions.get::<User>().cloned().map(Extension),
+        })
+    }
+}
+
+impl Caller {
+    async fn authorize(
+        &self,
+        invitations: &Invitations,
+        team: &str,
+        action: TeamAction,
+    ) -> Result<i64, InvitationError> {
+        let actor = actor(self.session.as_ref())?;
+        invitations
+            .authorize(
+                actor,
+                team,
+                action,
+                self.user.as_ref().map(|Extension(user)| user),
+            )
+            .await?;
+        Ok(actor)
+    }
+}
+
+async fn create_invitation(
+    State(invitations): State<Invitations>,
+    Path(team): Path<String>,
+    caller: Caller,
+    Form(form): Form<InviteForm>,
+) -> Result<Response, InvitationError> {
+    let actor = caller
+        .authorize(&invitations, &team, TeamAction::Invite)
+        .await?;
+    let (invitation, _) = invitations
+        .invite(actor, &team, &form.email, &form.role)
+        .await?;
+    Ok((
+        StatusCode::CREATED,
+        [("HX-Trigger", "invitation-sent")],
+        Json(invitation),
+    )
+        .into_response())
+}
+
+async fn list_invitations(
+    State(invitations): State<Invitations>,
+    Path(team): Path<String>,
+    caller: Caller,
+) -> Result<Json<Vec<Invitation>>, InvitationError> {
+    caller
+        .authorize(&invitations, &team, TeamAction::ManageInvitations)
+        .await?;
+    Ok(Json(invitations.pending(&team).await?))
+}
+
+async fn revoke_invitation(
+    State(invitations): State<Invitations>,
+    Path((team, id)): Path<(String, String)>,
+    caller: Caller,
+) -> Result<Json<Invitation>, InvitationError> {
+    caller
+        .authorize(&invitations, &team, TeamAction::ManageInvitations)
+        .await?;
+    Ok(Json(invitations.revoke(&team, &id).await?))
+}
+
+async fn list_members(
+    State(invitations): State<Invitations>,
+    Path(team): Path<String>,
+    caller: Caller,
+) -> Result<Json<Vec<Membership>>, InvitationError> {
+    caller
+        .authorize(&invitations, &team, TeamAction::ListMembers)
+        .await?;
+    Ok(Json(invitations.members(&team).await?))
+}
+
+async fn change_member_role(
+    State(invitations): State<Invitations>,
+    Path((team, user_id)): Path<(String, i64)>,
+    caller: Caller,
+    Form(form): Form<RoleForm>,
+) -> Result<Json<Membership>, InvitationError> {
+    caller
+        .authorize(&invitations, &team, TeamAction::ChangeRole)
+        .await?;
+    Ok(Json(
+        invitations.change_role(&team, user_id, &form.role).await?,
+    ))
+}
+
+async fn remove_team_member(
+    State(invitations): State<Invitations>,
+    Path((team, user_id)): Path<(String, i64)>,
+    caller: Caller,
+) -> Result<StatusCode, InvitationError> {
+    // Members may always leave; removing others needs a manager
+    if actor(caller.session.as_ref())? != user_id {
+        caller
+            .authorize(&invitations, &team, TeamAction::RemoveMember)
+            .await?;
+    }
+    invitations.remove_member(&team, user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn show_invitation(
+    State(invitations): State<Invitations>,
+    Path(token): Path<String>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Html<String>, InvitationError> {
+    let invitation = invitations.preview(&token).await?;
+    let signed_in = actor(session.as_ref()).is_ok();
+    Ok(Html(invitation_partial(&invitation, &token, signed_in)))
+}
+
+async fn accept_invitation(
+    State(invitations): State<Invitations>,
+    Path(token): Path<String>,
+    session: Option<Extension<SessionData>>,
+    Form(form): Form<AcceptForm>,
+) -> Result<Response, InvitationError> {
+    let current_user = actor(session.as_ref()).ok();
+    let membership = invitations.accept(&token, current_user, &form).await?;
+    let team = escape_html(&membership.team);
+    Ok((
+        [("HX-Trigger", "invitation-accepted")],
+        Html(format!(
+            r#"<div id="invitation" class="invitation"><p>You joined <strong>{team}</strong>.</p></div>"#
+        )),
+    )
+        .into_response())
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// SHA-256 hash (hex) under which a token is stored
+#[must_use]
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn valid_email(email: &str) -> bool {
+    email.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty() && domain.contains('.') && !email.contains(char::is_whitespace)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::testing::MockEmailSender;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn invitations() -> (Invitations, MockEmailSender) {
+        let sender = MockEmailSender::new();
+        let invitations = Invitations::new(
+            Arc::new(MemoryInvitationStore::new()),
+            Arc::new(SessionAccountLinker),
+        )
+        .with_sender(Arc::new(sender.clone()))
+        .with_from("team@app.test")
+        .with_base_url("https://app.test/");
+        (invitations, sender)
+    }
+
+    #[tokio::test]
+    async fn test_invite_and_accept() {
+        let (invitations, sender) = invitations();
+        invitations.add_owner("acme", 1).await.unwrap();
+
+        let (invitation, token) = invitations
+            .invite(1, "acme", " Bob@Example.com ", "member")
+            .await
+            .unwrap();
+        assert_eq!(invitation.email, "bob@example.com");
+        assert_ne!(invitation.token_hash, token);
+
+        let sent = sender.sent_emails();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0]
+            .text
+            .as_deref()
+            .unwrap()
+            .contains(&format!("https://app.test/invitations/{token}")));
+
+        assert!(matches!(
+            invitations
+                .accept(&token, None, &AcceptForm::default())
+                .await,
+            Err(InvitationError::Unauthenticated)
+        ));
+        let membership = invitations
+            .accept(&token, Some(2), &AcceptForm::default())
+            .await
+            .unwrap();
+        assert_eq!(membership.role, "member");
+        assert_eq!(invitations.members("acme").await.unwrap().len(), 2);
+
+        // Tokens are single use
+        assert!(matches!(
+            invitations
+                .accept(&token, Some(3), &AcceptForm::default())
+                .await,
+            Err(InvitationError::AlreadyUsed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_expiry_and_roles() {
+        let (invitations, _) = invitations();
+        assert!(matches!(
+            invitations.invite(1, "acme", "a@b.co", "superuser").await,
+            Err(InvitationError::InvalidRole(_))
+        ));
+        assert!(matches!(
+            invitations
+                .invite(1, "acme", "not-an-email", "member")
+                .await,
+            Err(InvitationError::Invalid(_))
+        ));
+
+        let (invitation, token) = invitations
+            .invite(1, "acme", "a@b.co", "member")
+            .await
+            .unwrap();
+        assert_eq!(invitations.pending("acme").await.unwrap().len(), 1);
+        invitations.revoke("acme", &invitation.id).await.unwrap();
+        assert!(matches!(
+            invitations.preview(&token).await,
+            Err(InvitationError::Revoked)
+        ));
+
+        let expiring = invitations.clone().with_ttl(Duration::ZERO);
+        let (_, token) = expiring
+            .invite(1, "acme", "c@d.co", "member")
+            .await
+            .unwrap();
+        assert!(matches!(
+            expiring.preview(&token).await,
+            Err(InvitationError::Expired)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_membership_management() {
+        let (invitations, _) = invitations();
+        invitations.add_owner("acme", 1).await.unwrap();
+        assert!(invitations
+            .authorize(1, "acme", TeamAction::Invite, None)
+            .await
+            .is_ok());
+
+        // The last owner can be neither demoted nor removed
+        assert!(matches!(
+            invitations.change_role("acme", 1, "member").await,
+            Err(InvitationError::Invalid(_))
+        ));
+        assert!(invitations.remove_member("acme", 1).await.is_err());
+
+        let (_, token) = invitations
+            .invite(1, "acme", "m@x.co", "member")
+            .await
+            .unwrap();
+        invitations
+            .accept(&token, Some(2), &AcceptForm::default())
+            .await
+            .unwrap();
+        assert!(matches!(
+            invitations
+                .authorize(2, "acme", TeamAction::Invite, None)
+                .await,
+            Err(InvitationError::Forbidden)
+        ));
+        invitations
+            .change_role("acme", 2, OWNER_ROLE)
+            .await
+            .unwrap();
+        invitations.change_role("acme", 1, "member").await.unwrap();
+        invitations.remove_member("acme", 1).await.unwrap();
+        assert_eq!(invitations.members("acme").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_routes_require_manager() {
+        let (invitations, _) = invitations();
+        invitations.add_owner("acme", 1).await.unwrap();
+        let app = invitations.routes::<()>();
+
+        let request = |user_id: i64| {
+            let mut session = SessionData::new();
+            session.user_id = Some(user_id);
+            let mut request = Request::post("/teams/acme/invitations")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(Body::from("email=new%40example.com&role=member"))
+                .unwrap();
+            request.extensions_mut().insert(session);
+            request
+        };
+
+        let response = app.clone().oneshot(request(9)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.oneshot(request(1)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(invitations.pending("acme").await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,396 @@
+//! Invitation store and account linking backed by the built-in services
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{AcceptForm, AccountLinker, Invitation, InvitationError, InvitationStore, Membership};
+use crate::htmx::clients::{AuthClient, DataClient, Row, Value};
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+/// Migration creating the invitation and team membership tables
+pub const INVITATIONS_MIGRATION: &str = r"CREATE TABLE IF NOT EXISTS team_invitations (
+    id TEXT PRIMARY KEY,
+    token_hash TEXT NOT NULL UNIQUE,
+    team TEXT NOT NULL,
+    role TEXT NOT NULL,
+    email TEXT NOT NULL,
+    invited_by BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    accepted_at BIGINT,
+    accepted_by BIGINT,
+    revoked_at BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_team_invitations_team ON team_invitations (team, created_at);
+CREATE TABLE IF NOT EXISTS team_memberships (
+    team TEXT NOT NULL,
+    user_id BIGINT NOT NULL,
+    role TEXT NOT NULL,
+    joined_at BIGINT NOT NULL,
+    PRIMARY KEY (team, user_id)
+);
+CREATE INDEX IF NOT EXISTS idx_team_memberships_user ON team_memberships (user_id);";
+
+const INVITATION_COLUMNS: &str = "id, token_hash, team, role, email, invited_by, created_at, \
+    expires_at, accepted_at, accepted_by, revoked_at";
+
+/// Invitation store persisting to data-service
+///
+/// Apply [`INVITATIONS_MIGRATION`] first.
+#[derive(Debug, Clone)]
+pub struct DataServiceInvitationStore {
+    client: Arc<RwLock<DataClient>>,
+}
+
+impl DataServiceInvitationStore {
+    /// Create a store
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<DataClient>>) -> Self {
+        Self { client }
+    }
+
+    async fn query(&self, sql: &str, params: Vec<Value>) -> Result<Vec<Row>, InvitationError> {
+        self.client
+            .write()
+            .await
+            .query(sql, params, None)
+            .await
+            .map_err(|e| InvitationError::Store(e.to_string()))
+    }
+
+    async fn execute(&self, sql: &str, params: Vec<Value>) -> Result<i64, InvitationError> {
+        let result = self
+            .client
+            .write()
+            .await
+            .execute(sql, params, None)
+            .await
+            .map_err(|e| InvitationError::Store(e.to_string()))?;
+        Ok(result.rows_affected)
+    }
+
+    async fn invitations(
+        &self,
+        filter: &str,
+        param: Value,
+    ) -> Result<Vec<Invitation>, InvitationError> {
+        let sql = format!("SELECT {INVITATION_COLUMNS} FROM team_invitations WHERE {filter}");
+        self.query(&sql, vec![param])
+            .await?
+            .iter()
+            .map(invitation_from_row)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl InvitationStore for DataServiceInvitationStore {
+    async fn insert_invitation(&self, invitation: &Invitation) -> Result<(), InvitationError> {
+        self.execute(
+            &format!(
+                "INSERT INTO team_invitations ({INVITATION_COLUMNS}) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"
+            ),
+            invitation_params(invitation),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn invitation(&self, id: &str) -> Result<Option<Invitation>, InvitationError> {
+        Ok(self.invitations("id = $1", text(id)).await?.pop())
+    }
+
+    async fn invitation_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Invitation>, InvitationError> {
+        Ok(self
+            .invitations("token_hash = $1", text(token_hash))
+            .await?
+            .pop())
+    }
+
+    async fn update_invitation(&self, invitation: &Invitation) -> Result<(), InvitationError> {
+        self.execute(
+            "UPDATE team_invitations SET accepted_at = $1, accepted_by = $2, revoked_at = $3 \
+             WHERE id = $4",
+            vec![
+                invitation.accepted_at.map_or_else(null, uint),
+                invitation.accepted_by.map_or_else(null, int),
+                invitation.revoked_at.map_or_else(null, uint),
+                text(&invitation.id),
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn team_invitations(&self, team: &str) -> Result<Vec<Invitation>, InvitationError> {
+        self.invitations("team = $1 ORDER BY created_at DESC", text(team))
+            .await
+    }
+
+    async fn put_member(&self, membership: &Membership) -> Result<(), InvitationError> {
+        self.execute(
+            "INSERT INTO team_memberships (team, user_id, role, joined_at) VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (team, user_id) DO UPDATE SET role = $3",
+            vec![
+                text(&membership.team),
+                int(membership.user_id),
+                text(&membership.role),
+                uint(membership.joined_at),
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn remove_member(&self, team: &str, user_id: i64) -> Result<bool, InvitationError> {
+        let removed = self
+            .execute(
+                "DELETE FROM team_memberships WHERE team = $1 AND user_id = $2",
+                vec![text(team), int(user_id)],
+            )
+            .await?;
+        Ok(removed > 0)
+    }
+
+    async fn member(
+        &self,
+        team: &str,
+        user_id: i64,
+    ) -> Result<Option<Membership>, InvitationError> {
+        let rows = self
+            .query(
+                "SELECT team, user_id, role, joined_at FROM team_memberships \
+                 WHERE team = $1 AND user_id = $2",
+                vec![text(team), int(user_id)],
+            )
+            .await?;
+        rows.first().map(membership_from_row).transpose()
+    }
+
+    async fn members(&self, team: &str) -> Result<Vec<Membership>, InvitationError> {
+        self.query(
+            "SELECT team, user_id, role, joined_at FROM team_memberships \
+             WHERE team = $1 ORDER BY joined_at, user_id",
+            vec![text(team)],
+        )
+        .await?
+        .iter()
+        .map(membership_from_row)
+        .collect()
+    }
+
+    async fn user_memberships(&self, user_id: i64) -> Result<Vec<Membership>, InvitationError> {
+        self.query(
+            "SELECT team, user_id, role, joined_at FROM team_memberships \
+             WHERE user_id = $1 ORDER BY joined_at, team",
+            vec![int(user_id)],
+        )
+        .await?
+        .iter()
+        .map(membership_from_row)
+        .collect()
+    }
+}
+
+/// Account linker that creates accounts through auth-service
+///
+/// A signed-in user joins if their email matches the invitation. Otherwise a
+/// new account is created from the accept form; if an account already
+/// exists for the invited email, the invitee must sign in first.
+#[derive(Debug, Clone)]
+pub struct AuthServiceAccounts {
+    client: Arc<RwLock<AuthClient>>,
+    require_matching_email: bool,
+}
+
+impl AuthServiceAccounts {
+    /// Create the linker
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<AuthClient>>) -> Self {
+        Self {
+            client,
+            require_matching_email: true,
+        }
+    }
+
+    /// Let signed-in users accept invitations sent to another address
+    #[must_use]
+    pub const fn with_any_email(mut self) -> Self {
+        self.require_matching_email = false;
+        self
+    }
+}
+
+#[async_trait]
+impl AccountLinker for AuthServiceAccounts {
+    async fn link(
+        &self,
+        invitation: &Invitation,
+        current_user: Option<i64>,
+        form: &AcceptForm,
+    ) -> Result<i64, InvitationError> {
+        let account =
+            |e: crate::htmx::clients::ClientError| InvitationError::Account(e.to_string());
+        let mut client = self.client.write().await;
+
+        if let Some(user_id) = current_user {
+            if self.require_matching_email {
+                let user = client.get_user(user_id).await.map_err(account)?;
+                let matches = user.is_some_and(|u| u.email.eq_ignore_ascii_case(&invitation.email));
+                if !matches {
+                    return Err(InvitationError::EmailMismatch);
+                }
+            }
+            return Ok(user_id);
+        }
+
+        if client
+            .get_user_by_email(&invitation.email)
+            .await
+            .map_err(account)?
+            .is_some()
+        {
+            return Err(InvitationError::Unauthenticated);
+        }
+        let (Some(name), Some(password)) = (
+            form.name
+                .as_deref()
+                .map(str::trim)
+                .filter(|n| !n.is_empty()),
+            form.password.as_deref().filter(|p| !p.is_empty()),
+        ) else {
+            return Err(InvitationError::Invalid(
+                "name and password are required".to_string(),
+            ));
+        };
+        let user = client
+            .create_user(&invitation.email, name, password)
+            .await
+            .map_err(account)?
+            .ok_or_else(|| InvitationError::Account("account was not created".to_string()))?;
+        drop(client);
+        Ok(user.id)
+    }
+}
+
+fn invitation_params(invitation: &Invitation) -> Vec<Value> {
+    vec![
+        text(&invitation.id),
+        text(&invitation.token_hash),
+        text(&invitation.team),
+        text(&invitation.role),
+        text(&invitation.email),
+        int(invitation.invited_by),
+        uint(invitation.created_at),
+        uint(invitation.expires_at),
+        invitation.accepted_at.map_or_else(null, uint),
+        invitation.accepted_by.map_or_else(null, int),
+        invitation.revoked_at.map_or_else(null, uint),
+    ]
+}
+
+fn invitation_from_row(row: &Row) -> Result<Invitation, InvitationError> {
+    Ok(Invitation {
+        id: column_text(row, "id")?,
+        token_hash: column_text(row, "token_hash")?,
+        team: column_text(row, "team")?,
+        role: column_text(row, "role")?,
+        email: column_text(row, "email")?,
+        invited_by: column_int(row, "invited_by")?,
+        created_at: column_uint(row, "created_at")?,
+        expires_at: column_uint(row, "expires_at")?,
+        accepted_at: column_uint(row, "accepted_at").ok(),
+        accepted_by: column_int(row, "accepted_by").ok(),
+        revoked_at: column_uint(row, "revoked_at").ok(),
+    })
+}
+
+fn membership_from_row(row: &Row) -> Result<Membership, InvitationError> {
+    Ok(Membership {
+        team: column_text(row, "team")?,
+        user_id: column_int(row, "user_id")?,
+        role: column_text(row, "role")?,
+        joined_at: column_uint(row, "joined_at")?,
+    })
+}
+
+fn column_int(row: &Row, name: &str) -> Result<i64, InvitationError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::IntValue(v)) => Ok(*v),
+        other => Err(InvitationError::Store(format!(
+            "expected integer {name}, got {other:?}"
+        ))),
+    }
+}
+
+fn column_uint(row: &Row, name: &str) -> Result<u64, InvitationError> {
+    let value = column_int(row, name)?;
+    u64::try_from(value).map_err(|_| InvitationError::Store(format!("negative timestamp {name}")))
+}
+
+fn column_text(row: &Row, name: &str) -> Result<String, InvitationError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::StringValue(v)) => Ok(v.clone()),
+        other => Err(InvitationError::Store(format!(
+            "expected text {name}, got {other:?}"
+        ))),
+    }
+}
+
+const fn int(value: i64) -> Value {
+    Value {
+        value: Some(ValueKind::IntValue(value)),
+    }
+}
+
+fn uint(value: u64) -> Value {
+    int(i64::try_from(value).unwrap_or(i64::MAX))
+}
+
+fn text(value: &str) -> Value {
+    Value {
+        value: Some(ValueKind::StringValue(value.to_string())),
+    }
+}
+
+const fn null() -> Value {
+    Value {
+        value: Some(ValueKind::NullValue(true)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invitation_row_round_trip() {
+        let invitation = Invitation {
+            id: "inv-1".to_string(),
+            token_hash: "abc".to_string(),
+            team: "acme".to_string(),
+            role: "member".to_string(),
+            email: "a@b.co".to_string(),
+            invited_by: 1,
+            created_at: 100,
+            expires_at: 200,
+            accepted_at: None,
+            accepted_by: None,
+            revoked_at: Some(150),
+        };
+        let row = Row {
+            columns: INVITATION_COLUMNS
+                .split(", ")
+                .map(str::trim)
+                .map(str::to_string)
+                .zip(invitation_params(&invitation))
+                .collect(),
+        };
+        assert_eq!(invitation_from_row(&row).unwrap(), invitation);
+    }
+}
@@ -0,0 +1,550 @@
+//! Alerts on job queue backlogs and failures.
+//!
+//! [`JobAgent::with_alerts`](super::JobAgent::with_alerts) checks the queue
+//! every [`AlertThresholds::check_interval_secs`] against thresholds on
+//! queue depth, the age of the oldest pending job, dead letter queue size
+//! and the failure rate. Crossing a threshold raises an alert and tells
+//! every [`AlertSink`]; the alert clears, and sinks hear about it again,
+//! only once the value falls back to the threshold's `clear` level. The gap
+//! between the two levels keeps a queue hovering around a limit from
+//! paging on every check.
+//!
+//! # Example Configuration
+//!
+//! ```toml
+//! [jobs.alerts]
+//! check_interval_secs = 30
+//!
+//! [jobs.alerts.queue_depth]
+//! raise = 5000
+//! clear = 1000
+//!
+//! [jobs.alerts.failure_rate]
+//! raise = 0.25
+//! clear = 0.05
+//! ```
+
+use crate::htmx::jobs::{JobError, JobResult};
+use acton_reactive::prelude::ActorHandleInterface;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Levels at which an alert is raised and cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Threshold {
+    /// Raise the alert once the value exceeds this.
+    pub raise: f64,
+    /// Clear a raised alert once the value is at or below this.
+    pub clear: f64,
+}
+
+impl Threshold {
+    /// Raise above `raise`, clear at or below `clear`.
+    #[must_use]
+    pub const fn new(raise: f64, clear: f64) -> Self {
+        Self { raise, clear }
+    }
+}
+
+/// Thresholds for job queue alerts; unset thresholds are not checked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertThresholds {
+    /// Jobs waiting in the queue.
+    pub queue_depth: Option<Threshold>,
+    /// Seconds the oldest pending job has waited.
+    pub oldest_pending_secs: Option<Threshold>,
+    /// Jobs in the dead letter queue.
+    pub dead_letter_size: Option<Threshold>,
+    /// Fraction of jobs finished since the last check that failed (0.0-1.0).
+    pub failure_rate: Option<Threshold>,
+    /// Finished jobs needed before the failure rate is judged; until then
+    /// the window keeps growing.
+    pub min_failure_samples: u64,
+    /// How often to check, in seconds.
+    pub check_interval_secs: u64,
+}
+
+impl Default for AlertThresholds {
+    fn default() -> Self {
+        Self {
+            queue_depth: None,
+            oldest_pending_secs: None,
+            dead_letter_size: None,
+            failure_rate: None,
+            min_failure_samples: 20,
+            check_interval_secs: 30,
+        }
+    }
+}
+
+impl AlertThresholds {
+    /// Get the check interval as Duration.
+    #[must_use]
+    pub const fn check_interval(&self) -> Duration {
+        Duration::from_secs(self.check_interval_secs)
+    }
+
+    /// Whether any threshold is set.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.queue_depth.is_some()
+            || self.oldest_pending_secs.is_some()
+            || self.dead_letter_size.is_some()
+            || self.failure_rate.is_some()
+    }
+}
+
+/// What an alert watches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Jobs waiting in the queue.
+    QueueDepth,
+    /// Seconds the oldest pending job has waited.
+    OldestPending,
+    /// Jobs in the dead letter queue.
+    DeadLetterSize,
+    /// Fraction of recently finished jobs that failed.
+    FailureRate,
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::QueueDepth => "queue depth",
+            Self::OldestPending => "oldest pending job age (s)",
+            Self::DeadLetterSize => "dead letter queue size",
+            Self::FailureRate => "failure rate",
+        })
+    }
+}
+
+/// A threshold crossed in either direction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobAlert {
+    /// What crossed its threshold.
+    pub condition: AlertCondition,
+    /// `true` when raised, `false` when cleared.
+    pub raised: bool,
+    /// Value at the check.
+    pub value: f64,
+    /// The threshold crossed.
+    pub threshold: Threshold,
+    /// When the check ran.
+    pub at: DateTime<Utc>,
+}
+
+impl JobAlert {
+    /// One-line description for logs, email subjects and chat messages.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        if self.raised {
+            format!(
+                "Job alert: {} is {} (above {})",
+                self.condition, self.value, self.threshold.raise
+            )
+        } else {
+            format!(
+                "Job alert cleared: {} is {} (at or below {})",
+                self.condition, self.value, self.threshold.clear
+            )
+        }
+    }
+}
+
+/// Receives raised and cleared alerts.
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    /// Deliver an alert.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError::AlertError`] if the alert cannot be delivered.
+    async fn notify(&self, alert: &JobAlert) -> JobResult<()>;
+}
+
+/// Logs alerts: raised at `warn`, cleared at `info`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogAlertSink;
+
+#[async_trait]
+impl AlertSink for LogAlertSink {
+    async fn notify(&self, alert: &JobAlert) -> JobResult<()> {
+        if alert.raised {
+            warn!(condition = ?alert.condition, value = alert.value, "{}", alert.summary());
+        } else {
+            info!(condition = ?alert.condition, value = alert.value, "{}", alert.summary());
+        }
+        Ok(())
+    }
+}
+
+/// POSTs each alert as JSON to a URL.
+#[derive(Debug, Clone)]
+pub struct WebhookAlertSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookAlertSink {
+    /// Post alerts to `url`.
+    #[must_use]
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookAlertSink {
+    async fn notify(&self, alert: &JobAlert) -> JobResult<()> {
+        self.client
+            .post(&self.url)
+            .timeout(Duration::from_secs(10))
+            .json(alert)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| JobError::AlertError(format!("webhook {}: {e}", self.url)))?;
+        Ok(())
+    }
+}
+
+/// Emails alerts through the email service.
+#[cfg(feature = "microservices")]
+#[derive(Debug, Clone)]
+pub struct EmailAlertSink {
+    client: crate::htmx::clients::EmailClient,
+    from: String,
+    to: Vec<String>,
+}
+
+#[cfg(feature = "microservices")]
+impl EmailAlertSink {
+    /// Email alerts from `from` to each of `to`.
+    #[must_use]
+    pub fn new(
+        client: crate::htmx::clients::EmailClient,
+        from: impl Into<String>,
+        to: Vec<String>,
+    ) -> Self {
+        Self {
+            client,
+            from: from.into(),
+            to,
+        }
+    }
+}
+
+#[cfg(feature = "microservices")]
+#[async_trait]
+impl AlertSink for EmailAlertSink {
+    async fn notify(&self, alert: &JobAlert) -> JobResult<()> {
+        let mut email = crate::htmx::clients::EmailMessage::new()
+            .from(self.from.clone())
+            .subject(alert.summary())
+            .text(format!(
+                "{}\n\nValue: {}\nRaise above: {}\nClear at or below: {}\nChecked at: {}\n",
+                alert.summary(),
+                alert.value,
+                alert.threshold.raise,
+                alert.threshold.clear,
+                alert.at.to_rfc3339()
+            ));
+        for to in &self.to {
+            email = email.to(to.clone());
+        }
+        let result = self
+            .client
+            .clone()
+            .send(email)
+            .await
+            .map_err(|e| JobError::AlertError(format!("email: {e}")))?;
+        if result.success {
+            Ok(())
+        } else {
+            Err(JobError::AlertError(format!(
+                "email: {}",
+                result.error.unwrap_or_default()
+            )))
+        }
+    }
+}
+
+/// Queue state at one check.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct AlertSnapshot {
+    pub queue_depth: usize,
+    pub oldest_pending: Option<Duration>,
+    pub dead_letter_size: usize,
+    pub jobs_completed: u64,
+    pub jobs_failed: u64,
+}
+
+/// Alert thresholds, the sinks to notify and which alerts are raised.
+pub struct JobAlerts {
+    thresholds: AlertThresholds,
+    sinks: Vec<Arc<dyn AlertSink>>,
+    state: Mutex<AlertState>,
+}
+
+impl fmt::Debug for JobAlerts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobAlerts")
+            .field("thresholds", &self.thresholds)
+            .field("sinks", &self.sinks.len())
+            .field("raised", &self.state.lock().raised)
+            .finish()
+    }
+}
+
+impl JobAlerts {
+    /// Check `thresholds`, logging alerts with [`LogAlertSink`].
+    #[must_use]
+    pub fn new(thresholds: AlertThresholds) -> Self {
+        Self {
+            thresholds,
+            sinks: vec![Arc::new(LogAlertSink)],
+            state: Mutex::new(AlertState::default()),
+        }
+    }
+
+    /// Also notify `sink`.
+    #[must_use]
+    pub fn with_sink(mut self, sink: impl AlertSink + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Configured thresholds.
+    #[must_use]
+    pub const fn thresholds(&self) -> &AlertThresholds {
+        &self.thresholds
+    }
+
+    /// Alerts currently raised.
+    #[must_use]
+    pub fn raised(&self) -> Vec<AlertCondition> {
+        self.state.lock().raised.iter().copied().collect()
+    }
+
+    /// Compare `snapshot` with the thresholds, returning the alerts raised
+    /// or cleared by this check.
+    pub(super) fn evaluate(&self, snapshot: &AlertSnapshot, now: DateTime<Utc>) -> Vec<JobAlert> {
+        self.state.lock().evaluate(&self.thresholds, snapshot, now)
+    }
+
+    /// Tell every sink about `alerts`; delivery failures are logged.
+    pub(super) async fn dispatch(&self, alerts: &[JobAlert]) {
+        for alert in alerts {
+            for sink in &self.sinks {
+                if let Err(e) = sink.notify(alert).await {
+                    warn!(condition = ?alert.condition, error = %e, "Failed to deliver job alert");
+                }
+            }
+        }
+    }
+}
+
+/// Raised alerts and the failure counts at the start of the current
+/// failure-rate window.
+#[derive(Debug, Default)]
+struct AlertState {
+    raised: HashSet<AlertCondition>,
+    window_completed: u64,
+    window_failed: u64,
+}
+
+impl AlertState {
+    fn evaluate(
+        &mut self,
+        thresholds: &AlertThresholds,
+        snapshot: &AlertSnapshot,
+        now: DateTime<Utc>,
+    ) -> Vec<JobAlert> {
+        let values = [
+            (
+                AlertCondition::QueueDepth,
+                thresholds.queue_depth,
+                Some(as_value(snapshot.queue_depth)),
+            ),
+            (
+                AlertCondition::OldestPending,
+                thresholds.oldest_pending_secs,
+                Some(snapshot.oldest_pending.map_or(0.0, |age| age.as_secs_f64())),
+            ),
+            (
+                AlertCondition::DeadLetterSize,
+                thresholds.dead_letter_size,
+                Some(as_value(snapshot.dead_letter_size)),
+            ),
+            (
+                AlertCondition::FailureRate,
+                thresholds.failure_rate,
+                self.failure_rate(thresholds.min_failure_samples, snapshot),
+            ),
+        ];
+
+        let mut alerts = Vec::new();
+        for (condition, threshold, value) in values {
+            let (Some(threshold), Some(value)) = (threshold, value) else {
+                continue;
+            };
+            let raised = self.raised.contains(&condition);
+            let crossed = if raised {
+                value <= threshold.clear
+            } else {
+                value > threshold.raise
+            };
+            if !crossed {
+                continue;
+            }
+            if raised {
+                self.raised.remove(&condition);
+            } else {
+                self.raised.insert(condition);
+            }
+            alerts.push(JobAlert {
+                condition,
+                raised: !raised,
+                value,
+                threshold,
+                at: now,
+            });
+        }
+        alerts
+    }
+
+    /// Failure rate since the window started, once it holds enough jobs;
+    /// the window then restarts.
+    fn failure_rate(&mut self, min_samples: u64, snapshot: &AlertSnapshot) -> Option<f64> {
+        let completed = snapshot.jobs_completed.saturating_sub(self.window_completed);
+        let failed = snapshot.jobs_failed.saturating_sub(self.window_failed);
+        let total = completed.saturating_add(failed);
+        if total == 0 || total < min_samples {
+            return None;
+        }
+        self.window_completed = snapshot.jobs_completed;
+        self.window_failed = snapshot.jobs_failed;
+        Some(ratio(failed, total))
+    }
+}
+
+/// A count as an alert value; counts past `u32::MAX` saturate.
+fn as_value(n: impl TryInto<u32>) -> f64 {
+    n.try_into().map_or_else(|_| f64::from(u32::MAX), f64::from)
+}
+
+/// `numerator / denominator` as a fraction.
+fn ratio(numerator: u64, denominator: u64) -> f64 {
+    as_value(numerator) / as_value(denominator)
+}
+
+/// Send [`CheckJobAlerts`](super::CheckJobAlerts) to the job agent every
+/// `interval`.
+pub(super) fn start_alert_loop(handle: acton_reactive::prelude::ActorHandle, interval: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            handle.send(super::CheckJobAlerts).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thresholds() -> AlertThresholds {
+        AlertThresholds {
+            queue_depth: Some(Threshold::new(100.0, 20.0)),
+            failure_rate: Some(Threshold::new(0.5, 0.1)),
+            min_failure_samples: 10,
+            ..AlertThresholds::default()
+        }
+    }
+
+    fn depth(queue_depth: usize) -> AlertSnapshot {
+        AlertSnapshot {
+            queue_depth,
+            ..AlertSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn test_alert_hysteresis() {
+        let alerts = JobAlerts::new(thresholds());
+        let now = Utc::now();
+
+        assert!(alerts.evaluate(&depth(100), now).is_empty());
+
+        let raised = alerts.evaluate(&depth(150), now);
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].condition, AlertCondition::QueueDepth);
+        assert!(raised[0].raised);
+        assert_eq!(alerts.raised(), [AlertCondition::QueueDepth]);
+
+        // Below the raise level but above the clear level: stays raised, no repeat
+        assert!(alerts.evaluate(&depth(150), now).is_empty());
+        assert!(alerts.evaluate(&depth(50), now).is_empty());
+
+        let cleared = alerts.evaluate(&depth(20), now);
+        assert_eq!(cleared.len(), 1);
+        assert!(!cleared[0].raised);
+        assert!(alerts.raised().is_empty());
+    }
+
+    #[test]
+    fn test_failure_rate_waits_for_samples() {
+        let alerts = JobAlerts::new(thresholds());
+        let now = Utc::now();
+        let finished = |jobs_completed, jobs_failed| AlertSnapshot {
+            jobs_completed,
+            jobs_failed,
+            ..AlertSnapshot::default()
+        };
+
+        // Too few jobs to judge
+        assert!(alerts.evaluate(&finished(1, 4), now).is_empty());
+
+        let raised = alerts.evaluate(&finished(3, 8), now);
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].condition, AlertCondition::FailureRate);
+        assert!((raised[0].value - 8.0 / 11.0).abs() < f64::EPSILON);
+
+        // The next window counts only jobs finished since
+        let cleared = alerts.evaluate(&finished(13, 8), now);
+        assert_eq!(cleared.len(), 1);
+        assert!(!cleared[0].raised);
+    }
+
+    #[test]
+    fn test_thresholds_from_toml() {
+        let thresholds: AlertThresholds = toml::from_str(
+            r"
+            check_interval_secs = 10
+
+            [oldest_pending_secs]
+            raise = 300
+            clear = 60
+            ",
+        )
+        .unwrap();
+        assert!(thresholds.is_enabled());
+        assert_eq!(thresholds.check_interval(), Duration::from_secs(10));
+        assert_eq!(thresholds.oldest_pending_secs, Some(Threshold::new(300.0, 60.0)));
+        assert_eq!(thresholds.min_failure_samples, 20);
+    }
+}
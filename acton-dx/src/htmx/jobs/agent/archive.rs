@@ -0,0 +1,424 @@
+//! Archive for job history records evicted from memory.
+//!
+//! [`JobHistoryConfig`](super::history::JobHistoryConfig) bounds the
+//! in-memory history by record count and size. With an archive configured,
+//! records pushed out of memory are appended to it instead of dropped, and
+//! [`GetJobHistoryRequest`](super::GetJobHistoryRequest) pages continue
+//! into the archive once the in-memory records run out.
+//!
+//! Archived records keep only the redacted payload preview; payloads never
+//! reach the archive.
+
+use super::history::JobHistoryRecord;
+use crate::htmx::jobs::{JobError, JobResult};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Storage for job history records evicted from memory.
+#[async_trait]
+pub trait HistoryArchive: Send + Sync {
+    /// Append records, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError::ArchiveError`] if the records cannot be stored.
+    async fn append(&self, records: &[JobHistoryRecord]) -> JobResult<()>;
+
+    /// Archived records matching `search_query`, most recent first.
+    ///
+    /// Skips `offset` matches and returns at most `limit`, along with the
+    /// total number of matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError::ArchiveError`] if the archive cannot be read.
+    async fn page(
+        &self,
+        offset: usize,
+        limit: usize,
+        search_query: Option<&str>,
+    ) -> JobResult<(Vec<JobHistoryRecord>, usize)>;
+}
+
+/// Append-only JSON Lines file, one record per line.
+///
+/// Paging reads the whole file, so rotate it (e.g. with logrotate's
+/// `copytruncate`) if it grows beyond what a history page should scan.
+#[derive(Debug)]
+pub struct JsonlHistoryArchive {
+    path: PathBuf,
+    /// Serializes appends so concurrent batches never interleave lines.
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl JsonlHistoryArchive {
+    /// Archive to the file at `path`, created on first append.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+fn archive_error(error: impl std::fmt::Display) -> JobError {
+    JobError::ArchiveError(error.to_string())
+}
+
+#[async_trait]
+impl HistoryArchive for JsonlHistoryArchive {
+    async fn append(&self, records: &[JobHistoryRecord]) -> JobResult<()> {
+        let mut lines = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut lines, record)?;
+            lines.push(b'\n');
+        }
+
+        let _guard = self.write_lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(archive_error)?;
+        file.write_all(&lines).await.map_err(archive_error)?;
+        file.flush().await.map_err(archive_error)
+    }
+
+    async fn page(
+        &self,
+        offset: usize,
+        limit: usize,
+        search_query: Option<&str>,
+    ) -> JobResult<(Vec<JobHistoryRecord>, usize)> {
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+            Err(e) => return Err(archive_error(e)),
+        };
+
+        let mut total = 0;
+        let mut records = Vec::new();
+        for line in contents.lines().rev().filter(|line| !line.is_empty()) {
+            let record: JobHistoryRecord = match serde_json::from_str(line) {
+                Ok(record) => record,
+                Err(e) => {
+                    warn!(error = %e, "Skipping unreadable job history archive line");
+                    continue;
+                }
+            };
+            if !record.matches_search(search_query.unwrap_or_default()) {
+                continue;
+            }
+            if total >= offset && records.len() < limit {
+                records.push(record);
+            }
+            total += 1;
+        }
+        Ok((records, total))
+    }
+}
+
+/// Postgres table of archived records.
+///
+/// Create the table with [`create_table`](Self::create_table) or an
+/// equivalent migration.
+#[derive(Debug, Clone)]
+pub struct PgHistoryArchive {
+    pool: Arc<PgPool>,
+    table: String,
+}
+
+impl PgHistoryArchive {
+    /// Archive to the `job_history` table.
+    #[must_use]
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self {
+            pool,
+            table: "job_history".to_string(),
+        }
+    }
+
+    /// Archive to another table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `table` is not a plain identifier (ASCII letters, digits
+    /// and `_`), since it is interpolated into SQL.
+    #[must_use]
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        let table = table.into();
+        assert!(
+            !table.is_empty()
+                && table
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "invalid job history table name {table:?}"
+        );
+        self.table = table;
+        self
+    }
+
+    /// Create the archive table and its index if they do not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError::ArchiveError`] if the statements fail.
+    pub async fn create_table(&self) -> JobResult<()> {
+        let table = &self.table;
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {table} (\
+             id TEXT PRIMARY KEY, \
+             job_type TEXT NOT NULL, \
+             error_message TEXT, \
+             finished_at TIMESTAMPTZ NOT NULL, \
+             record JSONB NOT NULL)"
+        ))
+        .execute(&*self.pool)
+        .await
+        .map_err(archive_error)?;
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {table}_finished_at ON {table} (finished_at DESC)"
+        ))
+        .execute(&*self.pool)
+        .await
+        .map_err(archive_error)?;
+        Ok(())
+    }
+}
+
+/// `ILIKE` pattern matching `query` anywhere, with wildcards escaped.
+fn contains_pattern(query: &str) -> String {
+    let escaped = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+#[async_trait]
+impl HistoryArchive for PgHistoryArchive {
+    async fn append(&self, records: &[JobHistoryRecord]) -> JobResult<()> {
+        let sql = format!(
+            "INSERT INTO {} (id, job_type, error_message, finished_at, record) \
+             VALUES ($1, $2, $3, $4, $5) ON CONFLICT (id) DO NOTHING",
+            self.table
+        );
+        let mut tx = self.pool.begin().await.map_err(archive_error)?;
+        for record in records {
+            sqlx::query(&sql)
+                .bind(record.id.to_string())
+                .bind(&record.job_type)
+                .bind(&record.error_message)
+                .bind(record.finished_at)
+                .bind(sqlx::types::Json(record))
+                .execute(&mut *tx)
+                .await
+                .map_err(archive_error)?;
+        }
+        tx.commit().await.map_err(archive_error)
+    }
+
+    async fn page(
+        &self,
+        offset: usize,
+        limit: usize,
+        search_query: Option<&str>,
+    ) -> JobResult<(Vec<JobHistoryRecord>, usize)> {
+        let filter = "($1::text IS NULL OR job_type ILIKE $1 OR id ILIKE $1 OR error_message ILIKE $1)";
+        let pattern = search_query
+            .filter(|query| !query.is_empty())
+            .map(contains_pattern);
+
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {} WHERE {filter}",
+            self.table
+        ))
+        .bind(&pattern)
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(archive_error)?;
+
+        let records: Vec<sqlx::types::Json<JobHistoryRecord>> = sqlx::query_scalar(&format!(
+            "SELECT record FROM {} WHERE {filter} ORDER BY finished_at DESC OFFSET $2 LIMIT $3",
+            self.table
+        ))
+        .bind(&pattern)
+        .bind(i64::try_from(offset).unwrap_or(i64::MAX))
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(archive_error)?;
+
+        Ok((
+            records.into_iter().map(|record| record.0).collect(),
+            usize::try_from(total).unwrap_or(0),
+        ))
+    }
+}
+
+/// Appends evicted records to an archive in the background, in eviction
+/// order.
+pub(super) struct ArchiveWriter {
+    archive: Arc<dyn HistoryArchive>,
+    /// Started on first use, since the history is built outside a runtime.
+    batches: OnceLock<mpsc::UnboundedSender<Vec<JobHistoryRecord>>>,
+}
+
+impl std::fmt::Debug for ArchiveWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveWriter")
+            .field("started", &self.batches.get().is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ArchiveWriter {
+    pub(super) fn new(archive: Arc<dyn HistoryArchive>) -> Self {
+        Self {
+            archive,
+            batches: OnceLock::new(),
+        }
+    }
+
+    pub(super) fn archive(&self) -> Arc<dyn HistoryArchive> {
+        Arc::clone(&self.archive)
+    }
+
+    /// Queue records for the archive.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub(super) fn send(&self, records: Vec<JobHistoryRecord>) {
+        let batches = self.batches.get_or_init(|| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<Vec<JobHistoryRecord>>();
+            let archive = Arc::clone(&self.archive);
+            tokio::spawn(async move {
+                while let Some(batch) = rx.recv().await {
+                    if let Err(e) = archive.append(&batch).await {
+                        warn!(error = %e, count = batch.len(), "Failed to archive job history");
+                    }
+                }
+            });
+            tx
+        });
+        let _ = batches.send(records);
+    }
+}
+
+/// Extend an in-memory history page with archived records.
+///
+/// `jobs` and `memory_total` are the in-memory page and match count; the
+/// page continues into the archive past the last in-memory match. If the
+/// archive cannot be read the in-memory page is returned as is.
+pub(super) async fn with_archived(
+    mut jobs: Vec<JobHistoryRecord>,
+    memory_total: usize,
+    archive: Option<&dyn HistoryArchive>,
+    page: usize,
+    page_size: usize,
+    search_query: Option<&str>,
+) -> (Vec<JobHistoryRecord>, usize) {
+    let Some(archive) = archive else {
+        return (jobs, memory_total);
+    };
+    let start = (page.max(1) - 1) * page_size;
+    let end = start + page_size;
+    let offset = start.saturating_sub(memory_total);
+    let limit = end.saturating_sub(start.max(memory_total));
+
+    match archive.page(offset, limit, search_query).await {
+        Ok((older, archived_total)) => {
+            jobs.extend(older);
+            (jobs, memory_total + archived_total)
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to read job history archive");
+            (jobs, memory_total)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::jobs::JobId;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn record(id: u128, job_type: &str) -> JobHistoryRecord {
+        let now = Utc::now();
+        JobHistoryRecord::completed(
+            JobId::from(Uuid::from_u128(id)),
+            job_type.to_string(),
+            now,
+            now,
+            now,
+            1,
+        )
+    }
+
+    fn ids(records: &[JobHistoryRecord]) -> Vec<u128> {
+        records.iter().map(|r| r.id.as_uuid().as_u128()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_archive_pages_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = JsonlHistoryArchive::new(dir.path().join("history.jsonl"));
+        assert_eq!(archive.page(0, 10, None).await.unwrap().1, 0);
+
+        archive
+            .append(&[record(1, "SendEmail"), record(2, "Resize")])
+            .await
+            .unwrap();
+        archive
+            .append(&[record(3, "SendEmail"), record(4, "Resize")])
+            .await
+            .unwrap();
+
+        let (records, total) = archive.page(1, 2, None).await.unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(ids(&records), [3, 2]);
+
+        let (records, total) = archive.page(0, 10, Some("email")).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(ids(&records), [3, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_pages_continue_into_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = JsonlHistoryArchive::new(dir.path().join("history.jsonl"));
+        let archive: &dyn HistoryArchive = &jsonl;
+        archive
+            .append(&[record(1, "Job"), record(2, "Job"), record(3, "Job")])
+            .await
+            .unwrap();
+
+        // Two records in memory, newest first, then three archived
+        let memory = vec![record(5, "Job"), record(4, "Job")];
+
+        let (page, total) = with_archived(memory.clone(), 2, Some(archive), 1, 3, None).await;
+        assert_eq!(total, 5);
+        assert_eq!(ids(&page), [5, 4, 3]);
+
+        let (page, total) = with_archived(Vec::new(), 2, Some(archive), 2, 3, None).await;
+        assert_eq!(total, 5);
+        assert_eq!(ids(&page), [2, 1]);
+
+        let (page, total) = with_archived(memory, 2, None, 1, 3, None).await;
+        assert_eq!(total, 2);
+        assert_eq!(ids(&page), [5, 4]);
+    }
+
+    #[test]
+    fn test_contains_pattern_escapes_wildcards() {
+        assert_eq!(contains_pattern("50%_off"), "%50\\%\\_off%");
+    }
+}
@@ -0,0 +1,203 @@
+//! Graceful job draining during deploys.
+//!
+//! On shutdown the [`JobAgent`](super::JobAgent) stops handing out jobs,
+//! waits up to a grace period for running jobs to finish, and persists
+//! everything unfinished back to Redis with its attempt counter intact so
+//! the next instance picks it up. Deploy tooling can poll the
+//! [`DrainStatus`] (see [`GetDrainStatusRequest`](super::GetDrainStatusRequest))
+//! to know when it is safe to stop the process.
+
+use super::messages::DrainJobsRequest;
+use acton_reactive::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::info;
+
+/// Drain progress of a job agent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum DrainStatus {
+    /// Processing jobs normally.
+    #[default]
+    Running,
+    /// No new jobs are dequeued; waiting for running jobs.
+    Draining {
+        /// When draining started.
+        started_at: DateTime<Utc>,
+        /// When unfinished jobs will be persisted regardless.
+        deadline: DateTime<Utc>,
+        /// Jobs still running.
+        running: usize,
+    },
+    /// Draining finished; the process can stop.
+    Drained {
+        /// When draining finished.
+        finished_at: DateTime<Utc>,
+        /// Jobs that were queued or still running at the deadline.
+        unfinished: usize,
+        /// Whether unfinished jobs were persisted to Redis.
+        persisted: bool,
+    },
+}
+
+impl DrainStatus {
+    /// Whether the agent has stopped dequeuing jobs.
+    #[must_use]
+    pub const fn is_draining(&self) -> bool {
+        !matches!(self, Self::Running)
+    }
+
+    /// Whether draining has finished.
+    #[must_use]
+    pub const fn is_drained(&self) -> bool {
+        matches!(self, Self::Drained { .. })
+    }
+}
+
+/// Wait for a shutdown signal (Ctrl-C or SIGTERM), then drain the job agent.
+///
+/// Resolves once draining has finished, or with an error if the agent
+/// stopped responding. Run it alongside the server so deploys wait for
+/// running jobs:
+///
+/// ```rust,ignore
+/// use acton_htmx::jobs::agent::drain_on_shutdown;
+///
+/// let grace = config.jobs.drain_grace_period();
+/// let drained = tokio::spawn(drain_on_shutdown(state.job_agent().clone(), grace));
+/// axum::serve(listener, app).with_graceful_shutdown(shutdown_signal()).await?;
+/// drained.await??;
+/// ```
+///
+/// # Errors
+///
+/// Returns error if the agent does not report a drained status within the
+/// grace period plus a short margin.
+pub async fn drain_on_shutdown(
+    job_agent: ActorHandle,
+    grace_period: Duration,
+) -> anyhow::Result<DrainStatus> {
+    shutdown_signal().await;
+    info!("Shutdown signal received, draining jobs (grace period {grace_period:?})");
+
+    let (request, rx) = DrainJobsRequest::new(grace_period);
+    job_agent.send(request).await;
+
+    let margin = Duration::from_secs(5);
+    let status = tokio::time::timeout(grace_period + margin, rx).await??;
+    info!("Job drain finished: {status:?}");
+    Ok(status)
+}
+
+/// Resolve on Ctrl-C, or on SIGTERM on Unix.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {},
+        () = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::queue::QueuedJob;
+    use super::super::JobAgent;
+    use super::*;
+    use crate::htmx::jobs::{JobId, JobStatus};
+
+    fn job(attempt: u32) -> QueuedJob {
+        QueuedJob {
+            id: JobId::new(),
+            job_type: "TestJob".to_string(),
+            payload: Vec::new(),
+            priority: 0,
+            max_retries: 3,
+            timeout: Duration::from_secs(30),
+            enqueued_at: Utc::now(),
+            attempt,
+            tenant_id: None,
+        }
+    }
+
+    #[test]
+    fn test_drain_status_serialization() {
+        let status = DrainStatus::Draining {
+            started_at: Utc::now(),
+            deadline: Utc::now(),
+            running: 2,
+        };
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["state"], "draining");
+        assert_eq!(json["running"], 2);
+        assert!(status.is_draining());
+        assert!(!status.is_drained());
+        assert!(!DrainStatus::Running.is_draining());
+    }
+
+    #[tokio::test]
+    async fn test_finish_drain_collects_unfinished_jobs() {
+        let agent = JobAgent::new();
+        agent.queue.write().enqueue(job(0)).unwrap();
+        let running = job(2);
+        agent.running.write().insert(
+            running.id,
+            JobStatus::Running {
+                started_at: Utc::now(),
+            },
+        );
+        agent.in_flight.write().insert(running.id, running);
+
+        let status = agent.finish_drain(Duration::ZERO).await;
+        assert!(matches!(
+            status,
+            DrainStatus::Drained {
+                unfinished: 2,
+                persisted: false,
+                ..
+            }
+        ));
+        assert!(agent.queue.read().is_empty());
+        assert_eq!(*agent.drain.read(), status);
+    }
+
+    #[tokio::test]
+    async fn test_finish_drain_waits_for_running_jobs() {
+        let agent = JobAgent::new();
+        let running = job(0);
+        let id = running.id;
+        agent.running.write().insert(
+            id,
+            JobStatus::Running {
+                started_at: Utc::now(),
+            },
+        );
+        agent.in_flight.write().insert(id, running);
+
+        let finisher = agent.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            finisher.in_flight.write().remove(&id);
+            finisher.running.write().remove(&id);
+        });
+
+        let status = agent.finish_drain(Duration::from_secs(5)).await;
+        assert!(matches!(status, DrainStatus::Drained { unfinished: 0, .. }));
+    }
+}
@@ -1,9 +1,12 @@
 //! Job history tracking with bounded circular buffer.
 
+use super::archive::{ArchiveWriter, HistoryArchive};
+use super::payload::payload_preview;
 use crate::htmx::jobs::JobId;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
+use std::sync::Arc;
 
 /// Simplified job status for history tracking.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -35,6 +38,12 @@ pub struct JobHistoryRecord {
     pub attempts: u32,
     /// Error message if failed.
     pub error_message: Option<String>,
+    /// Payload preview, redacted unless payloads were revealed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_preview: Option<String>,
+    /// Job payload, kept in memory only to build previews.
+    #[serde(skip)]
+    payload: Vec<u8>,
 }
 
 impl JobHistoryRecord {
@@ -64,6 +73,8 @@ impl JobHistoryRecord {
             duration_ms,
             attempts,
             error_message: None,
+            payload_preview: None,
+            payload: Vec::new(),
         }
     }
 
@@ -94,9 +105,35 @@ impl JobHistoryRecord {
             duration_ms,
             attempts,
             error_message: Some(error_message),
+            payload_preview: None,
+            payload: Vec::new(),
         }
     }
 
+    /// Attach the job payload so listings can show a preview of it.
+    #[must_use]
+    pub fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Fill in the payload preview, redacted unless `reveal` is set.
+    pub(super) fn set_payload_preview(&mut self, reveal: bool) {
+        if !self.payload.is_empty() {
+            self.payload_preview = Some(payload_preview(&self.payload, reveal));
+        }
+    }
+
+    /// Approximate memory held by this record, in bytes.
+    #[must_use]
+    pub fn size_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.job_type.len()
+            + self.error_message.as_ref().map_or(0, String::len)
+            + self.payload_preview.as_ref().map_or(0, String::len)
+            + self.payload.len()
+    }
+
     /// Check if this record matches a search query.
     ///
     /// Searches in job_type, job_id, and error_message fields.
@@ -129,17 +166,88 @@ impl JobHistoryRecord {
     }
 }
 
+/// Limits and archive for the job history kept by [`JobAgent`](super::JobAgent).
+///
+/// The history holds at most `max_records` records and roughly `max_bytes`
+/// of record data, evicting the oldest first. Evicted records go to the
+/// archive if one is set, so history pages can still reach them.
+///
+/// ```rust,ignore
+/// use acton_htmx::jobs::agent::{JobHistoryConfig, JsonlHistoryArchive};
+///
+/// let history = JobHistoryConfig::default()
+///     .max_bytes(4 * 1024 * 1024)
+///     .archive(JsonlHistoryArchive::new("/var/lib/myapp/job-history.jsonl"));
+/// let job_agent = JobAgent::new().with_history(history).start(&mut runtime).await?;
+/// ```
+#[derive(Clone)]
+pub struct JobHistoryConfig {
+    max_records: usize,
+    max_bytes: usize,
+    archive: Option<Arc<dyn HistoryArchive>>,
+}
+
+impl Default for JobHistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_records: 1000,
+            max_bytes: 8 * 1024 * 1024,
+            archive: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for JobHistoryConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobHistoryConfig")
+            .field("max_records", &self.max_records)
+            .field("max_bytes", &self.max_bytes)
+            .field("archive", &self.archive.is_some())
+            .finish()
+    }
+}
+
+impl JobHistoryConfig {
+    /// Keep at most this many records in memory (default 1000).
+    #[must_use]
+    pub const fn max_records(mut self, max_records: usize) -> Self {
+        self.max_records = max_records;
+        self
+    }
+
+    /// Keep at most about this many bytes of records in memory (default
+    /// 8 MiB), as measured by [`JobHistoryRecord::size_bytes`].
+    #[must_use]
+    pub const fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Append evicted records to `archive` instead of dropping them.
+    #[must_use]
+    pub fn archive(mut self, archive: impl HistoryArchive + 'static) -> Self {
+        self.archive = Some(Arc::new(archive));
+        self
+    }
+}
+
 /// Bounded circular buffer for job history.
 ///
-/// Maintains a fixed-size history of completed jobs using a circular buffer.
-/// When capacity is reached, the oldest records are automatically evicted.
+/// Maintains a bounded history of completed jobs using a circular buffer.
+/// When either the record or the byte limit is exceeded, the oldest records
+/// are evicted, and archived if an archive is configured.
 #[derive(Debug)]
 pub(super) struct JobHistory {
     /// Circular buffer of job records.
     records: VecDeque<JobHistoryRecord>,
     /// Maximum number of records to keep.
-    #[allow(dead_code)] // Used for capacity management
     max_records: usize,
+    /// Maximum total size of the records kept.
+    max_bytes: usize,
+    /// Current total size of the records kept.
+    bytes: usize,
+    /// Where evicted records go.
+    archive: Option<ArchiveWriter>,
 }
 
 impl JobHistory {
@@ -151,20 +259,60 @@ impl JobHistory {
     #[must_use]
     pub(super) fn new(max_records: usize) -> Self {
         Self {
-            records: VecDeque::with_capacity(max_records),
+            records: VecDeque::with_capacity(max_records.min(1024)),
             max_records,
+            max_bytes: usize::MAX,
+            bytes: 0,
+            archive: None,
+        }
+    }
+
+    /// Create a job history with the configured limits and archive.
+    #[must_use]
+    pub(super) fn from_config(config: JobHistoryConfig) -> Self {
+        Self {
+            max_bytes: config.max_bytes,
+            archive: config.archive.map(ArchiveWriter::new),
+            ..Self::new(config.max_records)
         }
     }
 
     /// Add a job record to the history.
     ///
-    /// If at capacity, the oldest record is automatically evicted.
-    #[allow(dead_code)] // Will be used when jobs complete
+    /// Evicts the oldest records while over either limit, always keeping
+    /// the newest. Evicted records are sent to the archive with a redacted
+    /// payload preview, so this must run inside a Tokio runtime when an
+    /// archive is configured.
     pub(super) fn add(&mut self, record: JobHistoryRecord) {
-        if self.records.len() >= self.max_records {
-            self.records.pop_front();
-        }
+        self.bytes += record.size_bytes();
         self.records.push_back(record);
+
+        let mut evicted = Vec::new();
+        while self.records.len() > self.max_records
+            || (self.bytes > self.max_bytes && self.records.len() > 1)
+        {
+            let Some(oldest) = self.records.pop_front() else {
+                break;
+            };
+            self.bytes = self.bytes.saturating_sub(oldest.size_bytes());
+            evicted.push(oldest);
+        }
+
+        if let Some(archive) = &self.archive {
+            if !evicted.is_empty() {
+                for record in &mut evicted {
+                    record.set_payload_preview(false);
+                    record.payload = Vec::new();
+                }
+                archive.send(evicted);
+            }
+        }
+    }
+
+    /// Archive holding records evicted from memory, if any.
+    #[must_use]
+    pub(super) fn archive(&self) -> Option<Arc<dyn HistoryArchive>> {
+        self.archive.as_ref().map(ArchiveWriter::archive)
     }
 
     /// Get paginated job history with optional search filter.
@@ -221,6 +369,13 @@ impl JobHistory {
         self.records.len()
     }
 
+    /// Approximate memory held by the records, in bytes.
+    #[must_use]
+    #[allow(dead_code)] // Used in tests and for debugging
+    pub(super) const fn size_bytes(&self) -> usize {
+        self.bytes
+    }
+
     /// Check if history is empty.
     #[must_use]
     #[allow(dead_code)] // Will be used for history management
@@ -256,6 +411,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_payload_preview_redacted_by_default() {
+        let mut record = create_test_record(1, "WelcomeEmail", HistoryStatus::Completed)
+            .with_payload(br#"{"email":"ada@example.com"}"#.to_vec());
+
+        record.set_payload_preview(false);
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(!json.contains("ada@example.com"));
+        assert!(json.contains("[redacted, 27 bytes]"));
+
+        record.set_payload_preview(true);
+        assert!(record.payload_preview.unwrap().contains("ada@example.com"));
+    }
+
     #[test]
     fn test_history_bounded_capacity() {
         let mut history = JobHistory::new(3);
@@ -278,6 +447,68 @@ mod tests {
         assert_eq!(*records[2].id.as_uuid(), Uuid::from_u128(3));
     }
 
+    #[test]
+    fn test_history_bounded_bytes() {
+        let record_size = create_test_record(1, "TestJob", HistoryStatus::Completed).size_bytes();
+        let mut history = JobHistory::from_config(
+            JobHistoryConfig::default()
+                .max_records(100)
+                .max_bytes(record_size * 3),
+        );
+
+        for i in 1..=5 {
+            history.add(create_test_record(i, "TestJob", HistoryStatus::Completed));
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.size_bytes(), record_size * 3);
+
+        // A record larger than the whole budget is still kept on its own
+        history.add(
+            create_test_record(6, "TestJob", HistoryStatus::Completed)
+                .with_payload(vec![0; record_size * 4]),
+        );
+        assert_eq!(history.len(), 1);
+        let (records, _) = history.get_page(1, 10, None);
+        assert_eq!(*records[0].id.as_uuid(), Uuid::from_u128(6));
+    }
+
+    #[tokio::test]
+    async fn test_evicted_records_are_archived() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        let mut history = JobHistory::from_config(
+            JobHistoryConfig::default()
+                .max_records(2)
+                .archive(super::super::archive::JsonlHistoryArchive::new(&path)),
+        );
+
+        for i in 1..=4 {
+            history.add(
+                create_test_record(i, "TestJob", HistoryStatus::Completed)
+                    .with_payload(br#"{"email":"ada@example.com"}"#.to_vec()),
+            );
+        }
+        assert_eq!(history.len(), 2);
+
+        let archive = history.archive().unwrap();
+        let mut archived = (Vec::new(), 0);
+        for _ in 0..50 {
+            archived = archive.page(0, 10, None).await.unwrap();
+            if archived.1 == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let (records, total) = archived;
+        assert_eq!(total, 2);
+        assert_eq!(*records[0].id.as_uuid(), Uuid::from_u128(2));
+        assert_eq!(*records[1].id.as_uuid(), Uuid::from_u128(1));
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("ada@example.com"));
+        assert!(contents.contains("[redacted, 27 bytes]"));
+    }
+
     #[test]
     fn test_history_pagination() {
         let mut history = JobHistory::new(100);
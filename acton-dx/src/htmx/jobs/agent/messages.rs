@@ -27,6 +27,9 @@ pub struct EnqueueJob {
     pub max_retries: u32,
     /// Job execution timeout.
     pub timeout: Duration,
+    /// Tenant that owns the job (`None` = not subject to tenant quotas).
+    #[serde(default)]
+    pub tenant_id: Option<String>,
 }
 
 /// Response to job enqueue request.
@@ -36,6 +39,62 @@ pub struct JobEnqueued {
     pub id: JobId,
 }
 
+/// Take the next job to execute (agent-to-agent pattern).
+///
+/// Tenants are served round-robin and tenants at their concurrency limit
+/// are skipped. The reply is a [`DequeueJobResponse`]; send [`JobFinished`]
+/// once the job is done to free the tenant's slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DequeueJob;
+
+/// A job handed to a worker for execution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DequeuedJob {
+    /// Job ID.
+    pub id: JobId,
+    /// Job type name.
+    pub job_type: String,
+    /// Serialized job payload.
+    pub payload: Vec<u8>,
+    /// Current attempt number (0 = first attempt).
+    pub attempt: u32,
+    /// Job execution timeout.
+    pub timeout: Duration,
+    /// Tenant that owns the job.
+    pub tenant_id: Option<String>,
+}
+
+/// Response to [`DequeueJob`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DequeueJobResponse {
+    /// Next job, or `None` if nothing can run right now.
+    pub job: Option<DequeuedJob>,
+}
+
+/// Report that a dequeued job has finished, successfully or not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobFinished {
+    /// Job ID.
+    pub id: JobId,
+}
+
+/// Record a finished job in the history (fire-and-forget).
+///
+/// Workers send this alongside [`JobFinished`] so the job shows up in
+/// [`GetJobHistoryRequest`] pages.
+#[derive(Debug, Clone)]
+pub struct RecordJobHistory {
+    /// The finished job.
+    pub record: super::history::JobHistoryRecord,
+}
+
+/// Check the queue against the alert thresholds (fire-and-forget).
+///
+/// Sent periodically by the agent itself when alerts are configured; see
+/// [`JobAgent::with_alerts`](super::JobAgent::with_alerts).
+#[derive(Debug, Clone, Copy)]
+pub struct CheckJobAlerts;
+
 /// Get the status of a job (agent-to-agent pattern).
 ///
 /// **Deprecated**: Use [`GetJobStatusRequest`] for web handlers.
@@ -73,7 +132,7 @@ pub struct JobMetrics {
     pub jobs_completed: u64,
     /// Total jobs failed.
     pub jobs_failed: u64,
-    /// Total jobs rejected (queue full).
+    /// Total jobs rejected (queue full or tenant quota exceeded).
     pub jobs_rejected: u64,
     /// Total jobs in dead letter queue.
     pub jobs_in_dlq: u64,
@@ -114,8 +173,11 @@ impl JobMetrics {
         }
 
         // Update average
-        if self.jobs_completed > 0 {
-            self.avg_execution_time_ms = self.total_execution_time_ms / self.jobs_completed;
+        if let Some(avg) = self
+            .total_execution_time_ms
+            .checked_div(self.jobs_completed)
+        {
+            self.avg_execution_time_ms = avg;
         }
 
         // Simple percentile estimation (will be replaced with histogram in production)
@@ -419,6 +481,114 @@ impl ClearDeadLetterQueueRequest {
     }
 }
 
+/// Drain the job agent before shutdown (web handler pattern).
+///
+/// The agent stops dequeuing, waits up to `grace_period` for running jobs,
+/// then persists unfinished jobs to Redis. The reply is sent once draining
+/// has finished. Draining cannot be undone; the process is expected to exit.
+#[derive(Clone, Debug)]
+pub struct DrainJobsRequest {
+    /// How long to wait for running jobs.
+    pub grace_period: Duration,
+    /// Response channel with the final drain status.
+    pub response_tx: ResponseChannel<super::drain::DrainStatus>,
+}
+
+impl DrainJobsRequest {
+    /// Create a new drain request with response channel.
+    ///
+    /// Returns a tuple of (request, receiver) where the request should be
+    /// sent to the agent and the receiver awaited for the response.
+    #[must_use]
+    pub fn new(grace_period: Duration) -> (Self, oneshot::Receiver<super::drain::DrainStatus>) {
+        let (tx, rx) = oneshot::channel();
+        let request = Self {
+            grace_period,
+            response_tx: Arc::new(Mutex::new(Some(tx))),
+        };
+        (request, rx)
+    }
+}
+
+/// Request the current drain status (web handler pattern).
+#[derive(Clone, Debug)]
+pub struct GetDrainStatusRequest {
+    /// Response channel with the drain status.
+    pub response_tx: ResponseChannel<super::drain::DrainStatus>,
+}
+
+impl GetDrainStatusRequest {
+    /// Create a new drain status request with response channel.
+    ///
+    /// Returns a tuple of (request, receiver) where the request should be
+    /// sent to the agent and the receiver awaited for the response.
+    #[must_use]
+    pub fn new() -> (Self, oneshot::Receiver<super::drain::DrainStatus>) {
+        let (tx, rx) = oneshot::channel();
+        let request = Self {
+            response_tx: Arc::new(Mutex::new(Some(tx))),
+        };
+        (request, rx)
+    }
+}
+
+/// A dead letter queue entry as shown in admin listings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterEntry {
+    /// Job ID.
+    pub id: JobId,
+    /// Job type name.
+    pub job_type: String,
+    /// Number of attempts made before the job was dead-lettered.
+    pub attempts: u32,
+    /// When the job was enqueued.
+    pub enqueued_at: chrono::DateTime<chrono::Utc>,
+    /// Payload preview, redacted unless payloads were revealed.
+    pub payload_preview: String,
+}
+
+/// List jobs in the dead letter queue (web handler pattern).
+///
+/// Payload previews are redacted unless `reveal_payloads` is set, since
+/// payloads may contain personal data.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use acton_htmx::jobs::agent::messages::ListDeadLetterQueueRequest;
+///
+/// async fn handler(State(state): State<ActonHtmxState>) -> Result<Response> {
+///     let (request, rx) = ListDeadLetterQueueRequest::new(false);
+///     state.job_agent().send(request).await;
+///
+///     let entries = tokio::time::timeout(Duration::from_millis(100), rx).await??;
+///     Ok(Json(entries).into_response())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ListDeadLetterQueueRequest {
+    /// Show payload previews instead of redacting them.
+    pub reveal_payloads: bool,
+    /// Response channel with the dead letter queue entries.
+    pub response_tx: ResponseChannel<Vec<DeadLetterEntry>>,
+}
+
+impl ListDeadLetterQueueRequest {
+    /// Create a new list dead letter queue request with response channel.
+    ///
+    /// Returns a tuple of (request, receiver) where the request should be
+    /// sent to the agent and the receiver awaited for the response.
+    #[must_use]
+    pub fn new(reveal_payloads: bool) -> (Self, oneshot::Receiver<Vec<DeadLetterEntry>>) {
+        let (tx, rx) = oneshot::channel();
+        let request = Self {
+            reveal_payloads,
+            response_tx: Arc::new(Mutex::new(Some(tx))),
+        };
+        (request, rx)
+    }
+}
+
 /// Job history page response containing records and pagination info.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobHistoryPage {
@@ -527,6 +697,10 @@ pub struct GetJobHistoryRequest {
     pub page_size: usize,
     /// Optional search query to filter results.
     pub search_query: Option<String>,
+    /// Show payload previews instead of redacting them.
+    ///
+    /// Defaults to `false`; set it only for admins who explicitly asked.
+    pub reveal_payloads: bool,
     /// Response channel for history page.
     pub response_tx: ResponseChannel<JobHistoryPage>,
 }
@@ -553,6 +727,7 @@ impl GetJobHistoryRequest {
             page: page.max(1), // Ensure page is at least 1
             page_size: page_size.clamp(1, 100), // Clamp between 1-100
             search_query,
+            reveal_payloads: false,
             response_tx: Arc::new(Mutex::new(Some(tx))),
         };
         (request, rx)
@@ -1,34 +1,61 @@
 //! Job processing agent using acton-reactive.
 
+pub mod alerts;
+pub mod archive;
+pub mod drain;
 pub mod history;
 pub(crate) mod messages;
+pub mod payload;
 pub(crate) mod persistence;
+#[cfg(feature = "bench-internals")]
+#[doc(hidden)]
+pub mod queue;
+#[cfg(not(feature = "bench-internals"))]
 pub(crate) mod queue;
 #[cfg(feature = "redis")]
 pub mod redis_agent;
 pub mod scheduled;
+pub mod tenants;
 
-pub use history::JobHistoryRecord;
+pub use alerts::{
+    AlertCondition, AlertSink, AlertThresholds, JobAlert, JobAlerts, LogAlertSink, Threshold,
+    WebhookAlertSink,
+};
+#[cfg(feature = "microservices")]
+pub use alerts::EmailAlertSink;
+pub use archive::{HistoryArchive, JsonlHistoryArchive, PgHistoryArchive};
+pub use drain::{drain_on_shutdown, DrainStatus};
+pub use history::{JobHistoryConfig, JobHistoryRecord};
 pub use messages::{
-    CancelJobRequest, ClearDeadLetterQueueRequest, EnqueueJob, GetJobHistoryRequest,
-    GetJobStatusRequest, GetMetricsRequest, JobEnqueued, JobHistoryPage, JobMetrics,
-    ResponseChannel, RetryAllFailedRequest, RetryJobRequest,
+    CancelJobRequest, ClearDeadLetterQueueRequest, DeadLetterEntry, DequeueJob,
+    DequeueJobResponse, DequeuedJob, DrainJobsRequest, EnqueueJob, GetDrainStatusRequest,
+    GetJobHistoryRequest, GetJobStatusRequest, GetMetricsRequest, JobEnqueued, JobFinished, JobHistoryPage, JobMetrics,
+    ListDeadLetterQueueRequest, RecordJobHistory, ResponseChannel, RetryAllFailedRequest,
+    RetryJobRequest,
 };
 #[cfg(feature = "redis")]
 pub use redis_agent::RedisPersistenceAgent;
-pub use scheduled::{ScheduledJobAgent, ScheduledJobEntry, ScheduledJobMessage, ScheduledJobResponse, start_scheduler_loop};
+pub use tenants::{TenantLimits, TenantQuotas};
+pub use scheduled::{
+    start_scheduler_loop, ScheduledJobAgent, ScheduledJobEntry, ScheduledJobMessage,
+    ScheduledJobRequest, ScheduledJobResponse, ScheduledRun, ScheduledRunOutcome,
+};
 
 use super::{JobContext, JobId, JobStatus};
+use crate::htmx::agents::ChildSpec;
 use acton_reactive::prelude::*;
 use chrono::Utc;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{debug, warn};
+use std::time::Duration;
+use tracing::{debug, info, warn};
 
-use history::JobHistory;
-use messages::{GetJobStatus, GetMetrics, JobStatusResponse};
+use alerts::AlertSnapshot;
+use history::{HistoryStatus, JobHistory};
+use messages::{CheckJobAlerts, GetJobStatus, GetMetrics, JobStatusResponse};
 use queue::{JobQueue, QueuedJob};
+use tenants::TenantUsage;
 
 // Type alias for the ManagedActor builder type
 type JobActorBuilder = ManagedActor<Idle, JobAgent>;
@@ -37,11 +64,13 @@ type JobActorBuilder = ManagedActor<Idle, JobAgent>;
 ///
 /// Manages a queue of background jobs with:
 /// - Priority-based execution
+/// - Per-tenant quotas with fair round-robin scheduling
 /// - Redis persistence (via dedicated `RedisPersistenceAgent`)
 /// - Automatic retry with exponential backoff
 /// - Dead letter queue for failed jobs
 /// - Job history tracking with pagination
-/// - Graceful shutdown
+/// - Graceful shutdown with job draining (see [`drain`])
+/// - Queue depth and failure alerting (see [`alerts`])
 /// - Service access via [`JobContext`](crate::jobs::JobContext)
 #[derive(Clone)]
 pub struct JobAgent {
@@ -49,12 +78,21 @@ pub struct JobAgent {
     queue: Arc<RwLock<JobQueue>>,
     /// Currently running jobs.
     running: Arc<RwLock<HashMap<JobId, JobStatus>>>,
+    /// Jobs handed out by `DequeueJob` and not yet finished.
+    in_flight: Arc<RwLock<HashMap<JobId, QueuedJob>>>,
     /// Dead letter queue for permanently failed jobs.
     dead_letter: Arc<RwLock<HashMap<JobId, QueuedJob>>>,
-    /// Job history with completed jobs (bounded circular buffer).
+    /// Job history with completed jobs (bounded circular buffer, optionally
+    /// archiving evicted records).
     history: Arc<RwLock<JobHistory>>,
     /// Job metrics.
     metrics: Arc<RwLock<JobMetrics>>,
+    /// Per-tenant quota usage.
+    tenants: Arc<RwLock<TenantUsage>>,
+    /// Drain progress; jobs are only dequeued while `Running`.
+    drain: Arc<RwLock<DrainStatus>>,
+    /// Queue depth and failure alerting, checked periodically once started.
+    alerts: Option<Arc<JobAlerts>>,
     /// Job execution context with services.
     ///
     /// Provides jobs with access to email sender, database pool, file storage, etc.
@@ -73,6 +111,10 @@ impl std::fmt::Debug for JobAgent {
             .field("dead_letter", &self.dead_letter.read().len())
             .field("history", &self.history.read().len())
             .field("metrics", &self.metrics.read())
+            .field("in_flight", &self.in_flight.read().len())
+            .field("tenants", &self.tenants.read())
+            .field("drain", &self.drain.read())
+            .field("alerts", &self.alerts)
             .field("context", &self.context);
 
         #[cfg(feature = "redis")]
@@ -98,9 +140,13 @@ impl JobAgent {
         Self {
             queue: Arc::new(RwLock::new(JobQueue::new(10_000))),
             running: Arc::new(RwLock::new(HashMap::new())),
+            in_flight: Arc::new(RwLock::new(HashMap::new())),
             dead_letter: Arc::new(RwLock::new(HashMap::new())),
-            history: Arc::new(RwLock::new(JobHistory::new(1000))), // Keep last 1000 jobs
+            history: Arc::new(RwLock::new(JobHistory::from_config(JobHistoryConfig::default()))),
             metrics: Arc::new(RwLock::new(JobMetrics::default())),
+            tenants: Arc::new(RwLock::new(TenantUsage::default())),
+            drain: Arc::new(RwLock::new(DrainStatus::default())),
+            alerts: None,
             context: Arc::new(JobContext::new()),
             #[cfg(feature = "redis")]
             redis_persistence: None,
@@ -116,9 +162,13 @@ impl JobAgent {
         Self {
             queue: Arc::new(RwLock::new(JobQueue::new(10_000))),
             running: Arc::new(RwLock::new(HashMap::new())),
+            in_flight: Arc::new(RwLock::new(HashMap::new())),
             dead_letter: Arc::new(RwLock::new(HashMap::new())),
-            history: Arc::new(RwLock::new(JobHistory::new(1000))), // Keep last 1000 jobs
+            history: Arc::new(RwLock::new(JobHistory::from_config(JobHistoryConfig::default()))),
             metrics: Arc::new(RwLock::new(JobMetrics::default())),
+            tenants: Arc::new(RwLock::new(TenantUsage::default())),
+            drain: Arc::new(RwLock::new(DrainStatus::default())),
+            alerts: None,
             context: Arc::new(context),
             #[cfg(feature = "redis")]
             redis_persistence: None,
@@ -160,14 +210,47 @@ impl JobAgent {
         Self {
             queue: Arc::new(RwLock::new(JobQueue::new(10_000))),
             running: Arc::new(RwLock::new(HashMap::new())),
+            in_flight: Arc::new(RwLock::new(HashMap::new())),
             dead_letter: Arc::new(RwLock::new(HashMap::new())),
-            history: Arc::new(RwLock::new(JobHistory::new(1000))), // Keep last 1000 jobs
+            history: Arc::new(RwLock::new(JobHistory::from_config(JobHistoryConfig::default()))),
             metrics: Arc::new(RwLock::new(JobMetrics::default())),
+            tenants: Arc::new(RwLock::new(TenantUsage::default())),
+            drain: Arc::new(RwLock::new(DrainStatus::default())),
+            alerts: None,
             context: Arc::new(context),
             redis_persistence: Some(redis_persistence),
         }
     }
 
+    /// Apply per-tenant concurrency limits and daily quotas.
+    ///
+    /// Jobs over a tenant's daily quota are rejected at enqueue time, and
+    /// [`DequeueJob`] skips tenants already running their maximum.
+    #[must_use]
+    pub fn with_tenant_quotas(mut self, quotas: TenantQuotas) -> Self {
+        self.tenants = Arc::new(RwLock::new(TenantUsage::new(quotas)));
+        self
+    }
+
+    /// Bound the job history by size and archive evicted records.
+    ///
+    /// See [`JobHistoryConfig`].
+    #[must_use]
+    pub fn with_history(mut self, config: JobHistoryConfig) -> Self {
+        self.history = Arc::new(RwLock::new(JobHistory::from_config(config)));
+        self
+    }
+
+    /// Raise alerts when the queue backs up or jobs start failing.
+    ///
+    /// Checks run every [`AlertThresholds::check_interval`] once the agent is
+    /// spawned with [`start`](Self::start).
+    #[must_use]
+    pub fn with_alerts(mut self, alerts: JobAlerts) -> Self {
+        self.alerts = Some(Arc::new(alerts));
+        self
+    }
+
     /// Get the job context.
     ///
     /// This provides access to services configured for job execution.
@@ -192,6 +275,64 @@ impl JobAgent {
         Self::configure_handlers(builder).await
     }
 
+    /// Spawn this agent, keeping the configuration applied with the `with_*`
+    /// builders
+    ///
+    /// # Errors
+    ///
+    /// Returns error if actor initialization fails
+    pub async fn start(self, runtime: &mut ActorRuntime) -> anyhow::Result<ActorHandle> {
+        let alert_interval = self
+            .alerts
+            .as_ref()
+            .map(|alerts| alerts.thresholds().check_interval());
+        let actor_config = ActorConfig::new(Ern::with_root("job_manager")?, None, None)?;
+        let mut builder = runtime.new_actor_with_config::<Self>(actor_config);
+        builder.model = self;
+        let handle = Self::configure_handlers(builder).await?;
+        if let Some(interval) = alert_interval {
+            alerts::start_alert_loop(handle.clone(), interval);
+        }
+        Ok(handle)
+    }
+
+    /// Supervision spec for the job agent
+    ///
+    /// The queue is held in memory; a restarted job agent starts empty.
+    #[must_use]
+    pub fn child_spec() -> ChildSpec {
+        ChildSpec::new("job_manager", |runtime, config| {
+            let mut builder = runtime.new_actor_with_config::<Self>(config);
+            builder.model = Self::new();
+            Box::pin(Self::configure_handlers(builder))
+        })
+    }
+
+    /// Supervision spec for the job agent that re-enqueues the jobs still
+    /// pending in Redis after a restart
+    ///
+    /// See [`RedisPersistenceAgent::restore_pending`].
+    #[cfg(feature = "redis")]
+    #[must_use]
+    pub fn child_spec_restoring(
+        redis_url: impl Into<String>,
+        keyring: Option<crate::htmx::encryption::Keyring>,
+    ) -> ChildSpec {
+        let redis_url: Arc<str> = redis_url.into().into();
+        Self::child_spec().with_rehydrate(move |handle| {
+            let redis_url = Arc::clone(&redis_url);
+            let keyring = keyring.clone();
+            async move {
+                let jobs = RedisPersistenceAgent::restore_pending(&redis_url, keyring.as_ref()).await?;
+                info!(count = jobs.len(), "Re-enqueueing pending jobs after restart");
+                for job in jobs {
+                    handle.send(job).await;
+                }
+                Ok(())
+            }
+        })
+    }
+
     /// Configure all message handlers for the job actor
     #[allow(clippy::too_many_lines)]
     async fn configure_handlers(mut builder: JobActorBuilder) -> anyhow::Result<ActorHandle> {
@@ -203,6 +344,21 @@ impl JobAgent {
 
                 debug!("Enqueueing job {} with priority {}", msg.id, msg.priority);
 
+                let now = Utc::now();
+                if !actor
+                    .model
+                    .tenants
+                    .write()
+                    .try_accept(msg.tenant_id.as_deref(), now.date_naive())
+                {
+                    warn!(
+                        "Rejected job {}: tenant {:?} exceeded its daily quota",
+                        msg.id, msg.tenant_id
+                    );
+                    actor.model.metrics.write().jobs_rejected += 1;
+                    return Reply::ready();
+                }
+
                 let queued_job = QueuedJob {
                     id: msg.id,
                     job_type: msg.job_type,
@@ -210,8 +366,9 @@ impl JobAgent {
                     priority: msg.priority,
                     max_retries: msg.max_retries,
                     timeout: msg.timeout,
-                    enqueued_at: Utc::now(),
+                    enqueued_at: now,
                     attempt: 0,
+                    tenant_id: msg.tenant_id,
                 };
 
                 // Add to in-memory queue
@@ -245,6 +402,142 @@ impl JobAgent {
                     }
                 }
             })
+            // Take the next job, interleaving tenants (actor-to-actor with reply_envelope)
+            .mutate_on::<DequeueJob>(|actor, context| {
+                let reply_envelope = context.reply_envelope();
+
+                // Nothing new starts once draining has begun
+                if actor.model.drain.read().is_draining() {
+                    return Reply::pending(async move {
+                        let _: () = reply_envelope.send(DequeueJobResponse { job: None }).await;
+                    });
+                }
+
+                let job = {
+                    let mut tenants = actor.model.tenants.write();
+                    let job = actor
+                        .model
+                        .queue
+                        .write()
+                        .dequeue(|tenant| tenants.can_start(tenant));
+                    if let Some(job) = &job {
+                        tenants.start(job.id, job.tenant_id.as_deref());
+                    }
+                    job
+                };
+
+                let job = job.map(|job| {
+                    actor.model.in_flight.write().insert(job.id, job.clone());
+                    let running = {
+                        let mut running = actor.model.running.write();
+                        running.insert(
+                            job.id,
+                            JobStatus::Running {
+                                started_at: Utc::now(),
+                            },
+                        );
+                        running.len()
+                    };
+                    let mut metrics = actor.model.metrics.write();
+                    metrics.jobs_dequeued += 1;
+                    metrics.current_running = running;
+                    drop(metrics);
+                    DequeuedJob {
+                        id: job.id,
+                        job_type: job.job_type,
+                        payload: job.payload,
+                        attempt: job.attempt,
+                        timeout: job.timeout,
+                        tenant_id: job.tenant_id,
+                    }
+                });
+
+                Reply::pending(async move {
+                    let _: () = reply_envelope.send(DequeueJobResponse { job }).await;
+                })
+            })
+            // Release a finished job's running slot
+            .mutate_on::<JobFinished>(|actor, context| {
+                let id = context.message().id;
+                actor.model.tenants.write().finish(&id);
+                actor.model.in_flight.write().remove(&id);
+                actor.model.running.write().remove(&id);
+                actor.model.metrics.write().current_running = actor.model.running.read().len();
+                Reply::ready()
+            })
+            // Record a finished job in the history
+            .mutate_on::<RecordJobHistory>(|actor, context| {
+                let record = context.message().record.clone();
+                {
+                    let mut metrics = actor.model.metrics.write();
+                    match record.status {
+                        HistoryStatus::Completed => metrics.jobs_completed += 1,
+                        HistoryStatus::Failed => metrics.jobs_failed += 1,
+                    }
+                }
+                actor.model.history.write().add(record);
+                Reply::ready()
+            })
+            // Check queue depth, age and failures against alert thresholds
+            .act_on::<CheckJobAlerts>(|actor, _context| {
+                let Some(alerts) = actor.model.alerts.clone() else {
+                    return Reply::ready();
+                };
+                let now = Utc::now();
+                let snapshot = actor.model.alert_snapshot(now);
+                let raised_or_cleared = alerts.evaluate(&snapshot, now);
+                if raised_or_cleared.is_empty() {
+                    return Reply::ready();
+                }
+
+                // Sink futures are not `Sync`, so dispatch runs on its own task
+                tokio::spawn(async move {
+                    alerts.dispatch(&raised_or_cleared).await;
+                });
+                Reply::ready()
+            })
+            // Stop dequeuing, wait for running jobs, persist the rest
+            .mutate_on::<DrainJobsRequest>(|actor, context| {
+                let msg = context.message();
+                let response_tx = msg.response_tx.clone();
+                let grace_period = msg.grace_period;
+
+                let current = actor.model.drain.read().clone();
+                if current.is_draining() {
+                    // Already draining; report where it stands
+                    return Reply::pending(async move {
+                        Self::send_drain_response(response_tx, current).await;
+                    });
+                }
+
+                let started_at = Utc::now();
+                let deadline = started_at
+                    + chrono::Duration::from_std(grace_period).unwrap_or(chrono::Duration::MAX);
+                *actor.model.drain.write() = DrainStatus::Draining {
+                    started_at,
+                    deadline,
+                    running: actor.model.running.read().len(),
+                };
+                info!("Draining jobs until {}", deadline);
+
+                let agent = actor.model.clone();
+                Reply::pending(async move {
+                    // Spawned so JobFinished messages keep arriving while we wait
+                    tokio::spawn(async move {
+                        let status = agent.finish_drain(grace_period).await;
+                        Self::send_drain_response(response_tx, status).await;
+                    });
+                })
+            })
+            // Get drain status (web handler pattern with oneshot channel)
+            .act_on::<GetDrainStatusRequest>(|actor, context| {
+                let response_tx = context.message().response_tx.clone();
+                let status = actor.model.drain.read().clone();
+
+                Reply::pending(async move {
+                    Self::send_drain_response(response_tx, status).await;
+                })
+            })
             // Get job status (read-only with reply_envelope)
             .act_on::<GetJobStatus>(|actor, context| {
                 let msg = context.message().clone();
@@ -364,7 +657,12 @@ impl JobAgent {
                     true
                 } else {
                     // If not in queue, check if it's running and mark for cancellation
-                    actor.model.running.write().remove(&job_id).is_some()
+                    let removed = actor.model.running.write().remove(&job_id).is_some();
+                    if removed {
+                        actor.model.tenants.write().finish(&job_id);
+                        actor.model.in_flight.write().remove(&job_id);
+                    }
+                    removed
                 };
 
                 Reply::pending(async move {
@@ -390,6 +688,31 @@ impl JobAgent {
                     Self::send_usize_response(response_tx, count).await;
                 })
             })
+            // List the dead letter queue with redacted payload previews
+            .act_on::<ListDeadLetterQueueRequest>(|actor, context| {
+                let msg = context.message();
+                let response_tx = msg.response_tx.clone();
+                let reveal_payloads = msg.reveal_payloads;
+
+                let mut entries: Vec<DeadLetterEntry> = actor
+                    .model
+                    .dead_letter
+                    .read()
+                    .values()
+                    .map(|job| DeadLetterEntry {
+                        id: job.id,
+                        job_type: job.job_type.clone(),
+                        attempts: job.attempt,
+                        enqueued_at: job.enqueued_at,
+                        payload_preview: payload::payload_preview(&job.payload, reveal_payloads),
+                    })
+                    .collect();
+                entries.sort_by_key(|entry| entry.enqueued_at);
+
+                Reply::pending(async move {
+                    Self::send_dead_letter_response(response_tx, entries).await;
+                })
+            })
             // Get job history with pagination and search
             .act_on::<GetJobHistoryRequest>(|actor, context| {
                 let msg = context.message();
@@ -397,18 +720,34 @@ impl JobAgent {
                 let page = msg.page;
                 let page_size = msg.page_size;
                 let search_query = msg.search_query.clone();
+                let reveal_payloads = msg.reveal_payloads;
 
                 // Get paginated history from the actor's history store
-                let (jobs, total_count) = actor
-                    .model
-                    .history
-                    .read()
-                    .get_page(page, page_size, search_query.as_deref());
+                let (jobs, memory_total, history_archive) = {
+                    let history = actor.model.history.read();
+                    let (jobs, total) = history.get_page(page, page_size, search_query.as_deref());
+                    (jobs, total, history.archive())
+                };
 
-                Reply::pending(async move {
+                // Archive futures are not `Sync`, so the read runs on its own task
+                tokio::spawn(async move {
+                    // Past the in-memory records, the page continues into the archive
+                    let (mut jobs, total_count) = archive::with_archived(
+                        jobs,
+                        memory_total,
+                        history_archive.as_deref(),
+                        page,
+                        page_size,
+                        search_query.as_deref(),
+                    )
+                    .await;
+                    for job in &mut jobs {
+                        job.set_payload_preview(reveal_payloads);
+                    }
                     let history_page = JobHistoryPage::new(jobs, page, page_size, total_count);
                     Self::send_history_response(response_tx, history_page).await;
-                })
+                });
+                Reply::ready()
             });
 
         // Redis persistence is now handled by RedisPersistenceAgent (separate actor)
@@ -417,6 +756,84 @@ impl JobAgent {
         Ok(builder.start().await)
     }
 
+    /// Queue state for alert checks.
+    fn alert_snapshot(&self, now: chrono::DateTime<Utc>) -> AlertSnapshot {
+        let (queue_depth, oldest_enqueued_at) = {
+            let queue = self.queue.read();
+            (queue.len(), queue.oldest_enqueued_at())
+        };
+        let metrics = self.metrics.read();
+        AlertSnapshot {
+            queue_depth,
+            oldest_pending: oldest_enqueued_at.map(|at| (now - at).to_std().unwrap_or_default()),
+            dead_letter_size: self.dead_letter.read().len(),
+            jobs_completed: metrics.jobs_completed,
+            jobs_failed: metrics.jobs_failed,
+        }
+    }
+
+    /// Wait for running jobs, then persist unfinished work.
+    ///
+    /// Polls until no jobs are running or `grace_period` has passed. Queued
+    /// jobs and jobs still running at the deadline are sent to Redis with
+    /// their attempt counters as they are, so they resume on the next
+    /// instance rather than starting over.
+    async fn finish_drain(&self, grace_period: Duration) -> DrainStatus {
+        let start = std::time::Instant::now();
+        loop {
+            let running = self.running.read().len();
+            if let DrainStatus::Draining { running: count, .. } = &mut *self.drain.write() {
+                *count = running;
+            }
+            if running == 0 || start.elapsed() >= grace_period {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let mut unfinished = self.queue.write().drain_all();
+        unfinished.extend(self.in_flight.write().drain().map(|(_, job)| job));
+        let count = unfinished.len();
+
+        #[cfg(feature = "redis")]
+        let persisted = if let Some(redis) = &self.redis_persistence {
+            use persistence::PersistJob;
+            for job in unfinished {
+                redis.send(PersistJob { job }).await;
+            }
+            true
+        } else {
+            false
+        };
+        #[cfg(not(feature = "redis"))]
+        let persisted = {
+            drop(unfinished);
+            false
+        };
+
+        if count > 0 && !persisted {
+            warn!("Drain left {} unfinished jobs with no Redis persistence", count);
+        }
+        let status = DrainStatus::Drained {
+            finished_at: Utc::now(),
+            unfinished: count,
+            persisted,
+        };
+        info!("Job drain complete: {} unfinished jobs", count);
+        *self.drain.write() = status.clone();
+        status
+    }
+
+    /// Send drain status response via oneshot channel.
+    ///
+    /// Helper method for web handler pattern responses (drain operations).
+    async fn send_drain_response(response_tx: ResponseChannel<DrainStatus>, status: DrainStatus) {
+        let mut guard = response_tx.lock().await;
+        if let Some(tx) = guard.take() {
+            let _ = tx.send(status);
+        }
+    }
+
     /// Send metrics response via oneshot channel.
     ///
     /// Helper method for web handler pattern responses.
@@ -475,5 +892,17 @@ impl JobAgent {
             let _ = tx.send(history);
         }
     }
-}
 
+    /// Send dead letter queue listing via oneshot channel.
+    ///
+    /// Helper method for web handler pattern responses (DLQ listings).
+    async fn send_dead_letter_response(
+        response_tx: ResponseChannel<Vec<DeadLetterEntry>>,
+        entries: Vec<DeadLetterEntry>,
+    ) {
+        let mut guard = response_tx.lock().await;
+        if let Some(tx) = guard.take() {
+            let _ = tx.send(entries);
+        }
+    }
+}
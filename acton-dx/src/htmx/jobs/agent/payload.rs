@@ -0,0 +1,156 @@
+//! Payload encryption and redaction for persisted jobs.
+//!
+//! Job payloads often carry personal data (email addresses, reset tokens),
+//! so when a [`Keyring`] is configured the payload is encrypted before the
+//! job is written to Redis and decrypted again when it is restored. Job
+//! metadata (type, priority, attempts) stays readable for operators.
+//!
+//! Records written without a keyring are still readable, so encryption can
+//! be enabled on a running deployment.
+
+#[cfg(feature = "redis")]
+use super::queue::QueuedJob;
+#[cfg(feature = "redis")]
+use crate::htmx::encryption::Keyring;
+#[cfg(feature = "redis")]
+use crate::htmx::jobs::{JobError, JobResult};
+#[cfg(feature = "redis")]
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of characters shown in a revealed payload preview.
+pub const PREVIEW_CHARS: usize = 256;
+
+/// A job as stored in Redis.
+#[cfg(feature = "redis")]
+#[derive(Serialize, Deserialize)]
+struct StoredJob {
+    #[serde(flatten)]
+    job: QueuedJob,
+    /// Encrypted payload; `job.payload` is empty when this is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_payload: Option<String>,
+}
+
+/// Serialize a job for Redis, encrypting its payload if a keyring is given.
+///
+/// # Errors
+///
+/// Returns an error if serialization or encryption fails.
+#[cfg(feature = "redis")]
+pub(crate) fn seal_job(job: &QueuedJob, keyring: Option<&Keyring>) -> JobResult<String> {
+    let stored = match keyring {
+        Some(keyring) => StoredJob {
+            encrypted_payload: Some(keyring.encrypt(&job.payload)?),
+            job: QueuedJob {
+                payload: Vec::new(),
+                ..job.clone()
+            },
+        },
+        None => StoredJob {
+            job: job.clone(),
+            encrypted_payload: None,
+        },
+    };
+    Ok(serde_json::to_string(&stored)?)
+}
+
+/// Deserialize a job read from Redis, decrypting its payload if needed.
+///
+/// # Errors
+///
+/// Returns an error if the record is malformed, or if the payload is
+/// encrypted and no keyring (or not the right key) is configured.
+#[cfg(feature = "redis")]
+pub(crate) fn open_job(json: &str, keyring: Option<&Keyring>) -> JobResult<QueuedJob> {
+    let StoredJob {
+        mut job,
+        encrypted_payload,
+    } = serde_json::from_str(json)?;
+    if let Some(ciphertext) = encrypted_payload {
+        let keyring = keyring.ok_or_else(|| {
+            JobError::Other(format!("job {} has an encrypted payload but no key is configured", job.id))
+        })?;
+        job.payload = keyring.decrypt(&ciphertext)?;
+    }
+    Ok(job)
+}
+
+/// Preview of a payload for admin listings.
+///
+/// Payloads are redacted unless `reveal` is set, in which case the first
+/// [`PREVIEW_CHARS`] characters are shown.
+#[must_use]
+pub fn payload_preview(payload: &[u8], reveal: bool) -> String {
+    if !reveal {
+        return format!("[redacted, {} bytes]", payload.len());
+    }
+    let text = String::from_utf8_lossy(payload);
+    let mut preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    if preview.len() < text.len() {
+        preview.push('…');
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::jobs::JobId;
+    use chrono::Utc;
+    use std::time::Duration;
+
+    const KEY: &str = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+
+    fn job() -> QueuedJob {
+        QueuedJob {
+            id: JobId::new(),
+            job_type: "WelcomeEmail".to_string(),
+            payload: br#"{"email":"ada@example.com"}"#.to_vec(),
+            priority: 1,
+            max_retries: 3,
+            timeout: Duration::from_secs(30),
+            enqueued_at: Utc::now(),
+            attempt: 0,
+            tenant_id: None,
+        }
+    }
+
+    #[cfg(feature = "redis")]
+    #[test]
+    fn test_sealed_payload_is_encrypted() {
+        let keyring = Keyring::new(1, KEY).unwrap();
+        let job = job();
+
+        let sealed = seal_job(&job, Some(&keyring)).unwrap();
+        assert!(!sealed.contains("ada@example.com"));
+        assert!(sealed.contains("WelcomeEmail"));
+
+        let opened = open_job(&sealed, Some(&keyring)).unwrap();
+        assert_eq!(opened.id, job.id);
+        assert_eq!(opened.payload, job.payload);
+        assert!(open_job(&sealed, None).is_err());
+    }
+
+    #[cfg(feature = "redis")]
+    #[test]
+    fn test_plaintext_records_still_open() {
+        let keyring = Keyring::new(1, KEY).unwrap();
+        let job = job();
+
+        let plain = seal_job(&job, None).unwrap();
+        assert_eq!(open_job(&plain, Some(&keyring)).unwrap().payload, job.payload);
+        assert_eq!(open_job(&plain, None).unwrap().payload, job.payload);
+    }
+
+    #[test]
+    fn test_payload_preview() {
+        let payload = br#"{"email":"ada@example.com"}"#;
+        assert_eq!(payload_preview(payload, false), "[redacted, 27 bytes]");
+        assert_eq!(payload_preview(payload, true), r#"{"email":"ada@example.com"}"#);
+
+        let long = "x".repeat(PREVIEW_CHARS + 1);
+        let preview = payload_preview(long.as_bytes(), true);
+        assert_eq!(preview.chars().count(), PREVIEW_CHARS + 1);
+        assert!(preview.ends_with('…'));
+    }
+}
@@ -1,10 +1,16 @@
 //! Priority queue for jobs.
+//!
+//! Each tenant gets its own priority lane, and lanes are served round-robin
+//! so tenants share workers fairly. Jobs without a tenant share one lane.
+//!
+//! [`JobAgent`](super::JobAgent) owns the queue. The `bench-internals`
+//! feature exposes it to the hot path benchmarks.
 
 use crate::htmx::jobs::JobId;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::time::Duration;
 
 /// A job in the queue.
@@ -26,6 +32,9 @@ pub struct QueuedJob {
     pub enqueued_at: DateTime<Utc>,
     /// Current attempt number (0 = first attempt).
     pub attempt: u32,
+    /// Tenant that owns the job, for quotas and fair scheduling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
 }
 
 /// Wrapper for priority queue ordering.
@@ -51,22 +60,24 @@ impl PartialOrd for QueueEntry {
 
 impl Ord for QueueEntry {
     fn cmp(&self, other: &Self) -> Ordering {
-        // Higher priority first
-        match other.job.priority.cmp(&self.job.priority) {
+        // BinaryHeap pops the greatest entry: higher priority first
+        match self.job.priority.cmp(&other.job.priority) {
             Ordering::Equal => {
                 // If same priority, older jobs first (FIFO)
-                self.job.enqueued_at.cmp(&other.job.enqueued_at)
+                other.job.enqueued_at.cmp(&self.job.enqueued_at)
             }
             ord => ord,
         }
     }
 }
 
-/// Priority-based job queue.
+/// Priority-based job queue with one lane per tenant.
 #[derive(Debug)]
-pub(super) struct JobQueue {
-    /// Binary heap for priority ordering.
-    heap: BinaryHeap<QueueEntry>,
+pub struct JobQueue {
+    /// Priority heap per tenant (`None` holds jobs without a tenant).
+    lanes: HashMap<Option<String>, BinaryHeap<QueueEntry>>,
+    /// Round-robin order of non-empty lanes.
+    order: VecDeque<Option<String>>,
     /// Set of job IDs for O(1) contains check.
     ids: HashSet<JobId>,
     /// Maximum queue size.
@@ -76,9 +87,10 @@ pub(super) struct JobQueue {
 impl JobQueue {
     /// Create a new job queue with maximum size.
     #[must_use]
-    pub(super) fn new(max_size: usize) -> Self {
+    pub fn new(max_size: usize) -> Self {
         Self {
-            heap: BinaryHeap::new(),
+            lanes: HashMap::new(),
+            order: VecDeque::new(),
             ids: HashSet::new(),
             max_size,
         }
@@ -89,8 +101,8 @@ impl JobQueue {
     /// # Errors
     ///
     /// Returns an error if the queue is full or the job is already queued.
-    pub(super) fn enqueue(&mut self, job: QueuedJob) -> Result<(), String> {
-        if self.heap.len() >= self.max_size {
+    pub fn enqueue(&mut self, job: QueuedJob) -> Result<(), String> {
+        if self.ids.len() >= self.max_size {
             return Err(format!("Queue is full (max: {})", self.max_size));
         }
 
@@ -99,13 +111,58 @@ impl JobQueue {
         }
 
         self.ids.insert(job.id);
-        self.heap.push(QueueEntry { job });
+        let tenant = job.tenant_id.clone();
+        let lane = self.lanes.entry(tenant.clone()).or_default();
+        if lane.is_empty() {
+            self.order.push_back(tenant);
+        }
+        lane.push(QueueEntry { job });
         Ok(())
     }
 
+    /// Take the next job, rotating through tenants.
+    ///
+    /// Lanes are visited round-robin and the highest-priority job of the
+    /// first lane whose tenant `can_start` is returned. Returns `None` if
+    /// the queue is empty or every tenant with queued jobs is at its limit.
+    pub fn dequeue(
+        &mut self,
+        can_start: impl Fn(Option<&str>) -> bool,
+    ) -> Option<QueuedJob> {
+        for _ in 0..self.order.len() {
+            let tenant = self.order.pop_front()?;
+            if !can_start(tenant.as_deref()) {
+                self.order.push_back(tenant);
+                continue;
+            }
+
+            let lane = self.lanes.get_mut(&tenant)?;
+            let entry = lane.pop()?;
+            if lane.is_empty() {
+                self.lanes.remove(&tenant);
+            } else {
+                self.order.push_back(tenant);
+            }
+            self.ids.remove(&entry.job.id);
+            return Some(entry.job);
+        }
+        None
+    }
+
+    /// Remove and return every queued job.
+    pub fn drain_all(&mut self) -> Vec<QueuedJob> {
+        self.order.clear();
+        self.ids.clear();
+        self.lanes
+            .drain()
+            .flat_map(|(_, lane)| lane.into_vec())
+            .map(|entry| entry.job)
+            .collect()
+    }
+
     /// Check if a job is in the queue.
     #[must_use]
-    pub(super) fn contains(&self, id: &JobId) -> bool {
+    pub fn contains(&self, id: &JobId) -> bool {
         self.ids.contains(id)
     }
 
@@ -115,8 +172,8 @@ impl JobQueue {
     ///
     /// # Performance
     ///
-    /// This operation is O(n) as it requires rebuilding the heap without the target job.
-    pub(super) fn remove(&mut self, id: &JobId) -> Option<QueuedJob> {
+    /// This operation is O(n) as it requires rebuilding the lane without the target job.
+    pub fn remove(&mut self, id: &JobId) -> Option<QueuedJob> {
         if !self.ids.contains(id) {
             return None;
         }
@@ -124,29 +181,150 @@ impl JobQueue {
         // Remove from ID set
         self.ids.remove(id);
 
-        // Rebuild heap without the target job
-        let jobs: Vec<QueueEntry> = std::mem::take(&mut self.heap).into_vec();
+        // Find the lane holding the job
+        let tenant = self
+            .lanes
+            .iter()
+            .find(|(_, lane)| lane.iter().any(|entry| entry.job.id == *id))
+            .map(|(tenant, _)| tenant.clone())?;
+        let lane = self.lanes.get_mut(&tenant)?;
+
+        // Rebuild lane without the target job
+        let jobs: Vec<QueueEntry> = std::mem::take(lane).into_vec();
         let (removed, remaining): (Vec<_>, Vec<_>) = jobs.into_iter().partition(|entry| entry.job.id == *id);
 
-        // Rebuild heap with remaining jobs
-        self.heap = remaining.into_iter().collect();
+        // Rebuild lane with remaining jobs
+        *lane = remaining.into_iter().collect();
+        if lane.is_empty() {
+            self.lanes.remove(&tenant);
+            self.order.retain(|t| *t != tenant);
+        }
 
         // Return the removed job
         removed.into_iter().next().map(|entry| entry.job)
     }
 
+    /// When the longest-waiting job was enqueued.
+    ///
+    /// Scans every queued job; meant for periodic checks, not hot paths.
+    #[must_use]
+    pub fn oldest_enqueued_at(&self) -> Option<DateTime<Utc>> {
+        self.lanes
+            .values()
+            .flat_map(BinaryHeap::iter)
+            .map(|entry| entry.job.enqueued_at)
+            .min()
+    }
+
     /// Get current queue size.
     #[must_use]
     #[allow(dead_code)] // May be used in future features
-    pub(super) fn len(&self) -> usize {
-        self.heap.len()
+    pub fn len(&self) -> usize {
+        self.ids.len()
     }
 
     /// Check if queue is empty.
     #[must_use]
     #[allow(dead_code)] // May be used in future features
-    pub(super) fn is_empty(&self) -> bool {
-        self.heap.is_empty()
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(tenant: Option<&str>, priority: i32) -> QueuedJob {
+        QueuedJob {
+            id: JobId::new(),
+            job_type: "TestJob".to_string(),
+            payload: Vec::new(),
+            priority,
+            max_retries: 3,
+            timeout: Duration::from_secs(30),
+            enqueued_at: Utc::now(),
+            attempt: 0,
+            tenant_id: tenant.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_dequeue_interleaves_tenants() {
+        let mut queue = JobQueue::new(100);
+        for _ in 0..5 {
+            queue.enqueue(job(Some("bulk"), 0)).unwrap();
+        }
+        queue.enqueue(job(Some("shop"), 0)).unwrap();
+        queue.enqueue(job(None, 0)).unwrap();
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.dequeue(|_| true))
+            .map(|job| job.tenant_id)
+            .collect();
+        assert_eq!(order.len(), 7);
+        assert_eq!(order[0].as_deref(), Some("bulk"));
+        assert_eq!(order[1].as_deref(), Some("shop"));
+        assert_eq!(order[2], None);
+        assert!(order[3..].iter().all(|t| t.as_deref() == Some("bulk")));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_dequeue_priority_within_tenant() {
+        let mut queue = JobQueue::new(100);
+        queue.enqueue(job(Some("acme"), 1)).unwrap();
+        queue.enqueue(job(Some("acme"), 10)).unwrap();
+
+        assert_eq!(queue.dequeue(|_| true).unwrap().priority, 10);
+        assert_eq!(queue.dequeue(|_| true).unwrap().priority, 1);
+    }
+
+    #[test]
+    fn test_dequeue_skips_tenants_at_limit() {
+        let mut queue = JobQueue::new(100);
+        queue.enqueue(job(Some("bulk"), 0)).unwrap();
+        queue.enqueue(job(Some("shop"), 0)).unwrap();
+
+        let next = queue.dequeue(|tenant| tenant != Some("bulk")).unwrap();
+        assert_eq!(next.tenant_id.as_deref(), Some("shop"));
+        assert!(queue.dequeue(|tenant| tenant != Some("bulk")).is_none());
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_oldest_enqueued_at_spans_lanes() {
+        let mut queue = JobQueue::new(100);
+        assert!(queue.oldest_enqueued_at().is_none());
+
+        let mut oldest = job(Some("acme"), 0);
+        oldest.enqueued_at -= chrono::Duration::minutes(5);
+        let enqueued_at = oldest.enqueued_at;
+        queue.enqueue(job(None, 10)).unwrap();
+        queue.enqueue(oldest).unwrap();
+
+        assert_eq!(queue.oldest_enqueued_at(), Some(enqueued_at));
+    }
+
+    #[test]
+    fn test_drain_all() {
+        let mut queue = JobQueue::new(100);
+        queue.enqueue(job(Some("acme"), 0)).unwrap();
+        queue.enqueue(job(None, 0)).unwrap();
+
+        assert_eq!(queue.drain_all().len(), 2);
+        assert!(queue.is_empty());
+        assert!(queue.dequeue(|_| true).is_none());
+    }
+
+    #[test]
+    fn test_remove_drops_empty_lane() {
+        let mut queue = JobQueue::new(100);
+        let only = job(Some("acme"), 0);
+        let id = only.id;
+        queue.enqueue(only).unwrap();
+
+        assert!(queue.remove(&id).is_some());
+        assert!(queue.dequeue(|_| true).is_none());
+        assert!(!queue.contains(&id));
+    }
+}
@@ -5,12 +5,18 @@
 //!
 //! All handlers use `act_on` for concurrent execution since Redis operations
 //! only modify external state (the Redis database), not agent state.
+//!
+//! When spawned with a [`Keyring`], job payloads are encrypted before they
+//! reach Redis (see [`payload`](super::payload)).
 
 use super::persistence::{
     MarkJobCompleted, MarkJobFailed, MoveToDeadLetterQueue, PersistJob,
 };
+use super::messages::EnqueueJob;
+use super::payload::{open_job, seal_job};
 use super::queue::QueuedJob;
-use crate::htmx::jobs::{JobId, JobStatus};
+use crate::htmx::encryption::Keyring;
+use crate::htmx::jobs::{JobId, JobResult, JobStatus};
 use acton_reactive::prelude::*;
 use redis::AsyncCommands;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -42,6 +48,8 @@ pub struct RedisPersistenceAgent {
     ///
     /// None in Default impl - always set via spawn().
     redis_conn: Option<redis::aio::MultiplexedConnection>,
+    /// Keyring for payload encryption (None stores payloads in plaintext).
+    keyring: Option<Arc<Keyring>>,
     /// Count of operations performed (for metrics).
     operations_count: Arc<AtomicUsize>,
 }
@@ -50,6 +58,7 @@ impl std::fmt::Debug for RedisPersistenceAgent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("RedisPersistenceAgent")
             .field("redis_conn", &self.redis_conn.is_some())
+            .field("encrypted", &self.keyring.is_some())
             .field("operations_count", &self.operations_count.load(Ordering::Relaxed))
             .finish()
     }
@@ -91,6 +100,46 @@ impl RedisPersistenceAgent {
     pub async fn spawn(
         redis_url: &str,
         runtime: &mut ActorRuntime,
+    ) -> anyhow::Result<ActorHandle> {
+        Self::spawn_with_keyring(redis_url, None, runtime).await
+    }
+
+    /// Create and spawn a Redis persistence agent that encrypts payloads.
+    ///
+    /// Job payloads are encrypted with the keyring's primary key before they
+    /// are written; metadata stays readable. Pass `None` to store plaintext,
+    /// as [`spawn`](Self::spawn) does.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the Redis connection or agent spawning fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if handler configuration fails, which should not occur in normal operation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use acton_reactive::prelude::*;
+    /// use acton_htmx::config::ActonHtmxConfig;
+    /// use acton_htmx::jobs::agent::redis_agent::RedisPersistenceAgent;
+    ///
+    /// # async fn example(config: ActonHtmxConfig) -> anyhow::Result<()> {
+    /// let mut runtime = ActorRuntime::new().await?;
+    /// let keyring = config.jobs.payload_keyring()?;
+    /// let handle = RedisPersistenceAgent::spawn_with_keyring(
+    ///     "redis://localhost:6379",
+    ///     keyring,
+    ///     &mut runtime
+    /// ).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn spawn_with_keyring(
+        redis_url: &str,
+        keyring: Option<Keyring>,
+        runtime: &mut ActorRuntime,
     ) -> anyhow::Result<ActorHandle> {
         // Create Redis connection
         let client = redis::Client::open(redis_url)?;
@@ -104,6 +153,7 @@ impl RedisPersistenceAgent {
                 // Set model with Redis connection
                 actor.model = Self {
                     redis_conn: Some(conn),
+                    keyring: keyring.map(Arc::new),
                     operations_count: Arc::new(AtomicUsize::new(0)),
                 };
 
@@ -114,6 +164,46 @@ impl RedisPersistenceAgent {
             .await
     }
 
+    /// Load the jobs still pending in Redis, decrypting their payloads.
+    ///
+    /// Used on startup to re-enqueue work that was persisted before a
+    /// restart: send each returned [`EnqueueJob`] to the `JobAgent`. Records
+    /// whose payload cannot be decrypted are skipped with an error log, so
+    /// one bad record does not block the rest.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if Redis cannot be reached.
+    pub async fn restore_pending(
+        redis_url: &str,
+        keyring: Option<&Keyring>,
+    ) -> JobResult<Vec<EnqueueJob>> {
+        let client = redis::Client::open(redis_url)?;
+        let mut conn = client.get_multiplexed_async_connection().await?;
+
+        let ids: Vec<String> = conn.lrange("queue:pending", 0, -1).await?;
+        let mut jobs = Vec::with_capacity(ids.len());
+        for id in ids.iter().rev() {
+            let json: Option<String> = conn.get(format!("job:{id}")).await?;
+            let Some(json) = json else { continue };
+            match open_job(&json, keyring) {
+                Ok(job) => jobs.push(EnqueueJob {
+                    id: job.id,
+                    job_type: job.job_type,
+                    payload: job.payload,
+                    priority: job.priority,
+                    max_retries: job.max_retries,
+                    timeout: job.timeout,
+                    tenant_id: job.tenant_id,
+                }),
+                Err(e) => error!("Failed to restore job {}: {}", id, e),
+            }
+        }
+
+        debug!("Restored {} pending jobs from Redis", jobs.len());
+        Ok(jobs)
+    }
+
     /// Configure all message handlers for the persistence actor.
     ///
     /// All handlers use `act_on` for concurrent execution since they only
@@ -125,6 +215,7 @@ impl RedisPersistenceAgent {
             // Persist job to Redis (fire-and-forget)
             .act_on::<PersistJob>(|actor, context| {
                 let conn_opt = actor.model.redis_conn.clone();
+                let keyring = actor.model.keyring.clone();
                 let job = context.message().job.clone();
                 let ops_count = actor.model.operations_count.clone();
 
@@ -132,7 +223,7 @@ impl RedisPersistenceAgent {
                 Reply::pending(async move {
                     tokio::spawn(async move {
                         if let Some(mut conn) = conn_opt {
-                            match persist_job_impl(&mut conn, &job).await {
+                            match persist_job_impl(&mut conn, &job, keyring.as_deref()).await {
                                 Ok(()) => {
                                     ops_count.fetch_add(1, Ordering::Relaxed);
                                     debug!("Successfully persisted job {}", job.id);
@@ -197,6 +288,7 @@ impl RedisPersistenceAgent {
             // Move job to dead letter queue (fire-and-forget)
             .act_on::<MoveToDeadLetterQueue>(|actor, context| {
                 let conn_opt = actor.model.redis_conn.clone();
+                let keyring = actor.model.keyring.clone();
                 let msg = context.message().clone();
                 let ops_count = actor.model.operations_count.clone();
 
@@ -204,7 +296,8 @@ impl RedisPersistenceAgent {
                 Reply::pending(async move {
                     tokio::spawn(async move {
                         if let Some(mut conn) = conn_opt {
-                            match move_to_dlq_impl(&mut conn, msg.id, &msg.job, &msg.error).await {
+                            match move_to_dlq_impl(&mut conn, msg.id, &msg.job, &msg.error, keyring.as_deref())
+                                .await {
                                 Ok(()) => {
                                     ops_count.fetch_add(1, Ordering::Relaxed);
                                     warn!("Moved job {} to DLQ: {}", msg.id, msg.error);
@@ -226,9 +319,10 @@ impl RedisPersistenceAgent {
 async fn persist_job_impl(
     redis: &mut redis::aio::MultiplexedConnection,
     job: &QueuedJob,
+    keyring: Option<&Keyring>,
 ) -> Result<(), redis::RedisError> {
     let key = format!("job:{}", job.id);
-    let json = serde_json::to_string(job).map_err(|e| {
+    let json = seal_job(job, keyring).map_err(|e| {
         redis::RedisError::from((
             redis::ErrorKind::TypeError,
             "serialization error",
@@ -239,7 +333,8 @@ async fn persist_job_impl(
     // Store job data with 7 day expiry
     let _: () = redis.set_ex(&key, json, 604_800).await?;
 
-    // Add to pending queue
+    // Add to pending queue (once, even when a restored job is persisted again)
+    let _: usize = redis.lrem("queue:pending", 0, job.id.to_string()).await?;
     let _: usize = redis.lpush("queue:pending", job.id.to_string()).await?;
 
     Ok(())
@@ -310,9 +405,10 @@ async fn move_to_dlq_impl(
     id: JobId,
     job: &QueuedJob,
     error: &str,
+    keyring: Option<&Keyring>,
 ) -> Result<(), redis::RedisError> {
     let dlq_key = format!("dlq:{id}");
-    let json = serde_json::to_string(job).map_err(|e| {
+    let json = seal_job(job, keyring).map_err(|e| {
         redis::RedisError::from((
             redis::ErrorKind::TypeError,
             "serialization error",
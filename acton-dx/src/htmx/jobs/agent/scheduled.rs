@@ -1,6 +1,12 @@
 //! Scheduled job management agent.
-
-use super::messages::EnqueueJob;
+//!
+//! Schedules can be inspected and changed at runtime: list them with their
+//! next-run times and last outcome, pause and resume them, or replace their
+//! cron expression or interval. Agents send [`ScheduledJobMessage`] directly;
+//! web handlers wrap it in a [`ScheduledJobRequest`] to get the reply over a
+//! oneshot channel (see [`schedule_admin`](crate::htmx::handlers::schedule_admin)).
+
+use super::messages::{EnqueueJob, ResponseChannel};
 use crate::htmx::jobs::{JobError, JobId, JobSchedule};
 use acton_reactive::prelude::*;
 use chrono::{DateTime, Utc};
@@ -9,6 +15,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::{oneshot, Mutex};
 use tracing::{debug, error, info};
 
 /// A scheduled job entry.
@@ -34,6 +41,35 @@ pub struct ScheduledJobEntry {
     pub execution_count: u64,
     /// Whether this scheduled job is enabled.
     pub enabled: bool,
+    /// Most recent run, if the job has run.
+    #[serde(default)]
+    pub last_run: Option<ScheduledRun>,
+}
+
+/// A run of a scheduled job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledRun {
+    /// ID of the job enqueued for this run.
+    pub job_id: JobId,
+    /// When the job was enqueued.
+    pub enqueued_at: DateTime<Utc>,
+    /// How the run went.
+    pub outcome: ScheduledRunOutcome,
+}
+
+/// Outcome of a scheduled job run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ScheduledRunOutcome {
+    /// Enqueued; no outcome reported yet.
+    Enqueued,
+    /// The job completed successfully.
+    Succeeded,
+    /// The job failed.
+    Failed {
+        /// Error message.
+        error: String,
+    },
 }
 
 /// Messages for the scheduled job agent.
@@ -69,10 +105,28 @@ pub enum ScheduledJobMessage {
         enabled: bool,
     },
 
+    /// Replace a scheduled job's schedule (cron expression or interval).
+    ///
+    /// The next run is recalculated from now.
+    UpdateSchedule {
+        /// Scheduled job ID.
+        id: JobId,
+        /// New schedule.
+        schedule: JobSchedule,
+    },
+
+    /// Report how a job enqueued by the scheduler went.
+    RecordRunOutcome {
+        /// ID of the enqueued job.
+        job_id: JobId,
+        /// Outcome of the run.
+        outcome: ScheduledRunOutcome,
+    },
+
     /// Trigger scheduled job processing (internal, sent by timer).
     ProcessScheduledJobs,
 
-    /// Get all scheduled jobs.
+    /// Get all scheduled jobs, ordered by next execution time.
     GetScheduledJobs,
 }
 
@@ -93,6 +147,57 @@ pub enum ScheduledJobResponse {
 
     /// List of scheduled jobs.
     ScheduledJobs(Vec<ScheduledJobEntry>),
+
+    /// Schedule was replaced.
+    ScheduleUpdated(Box<ScheduledJobEntry>),
+
+    /// Run outcome was recorded.
+    OutcomeRecorded,
+
+    /// Scheduled jobs were processed.
+    Processed,
+
+    /// No scheduled job with this ID.
+    NotFound {
+        /// The ID that was not found.
+        id: JobId,
+    },
+}
+
+/// Send a [`ScheduledJobMessage`] and receive the reply over a oneshot
+/// channel (web handler pattern).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use acton_htmx::jobs::agent::{ScheduledJobMessage, ScheduledJobRequest};
+///
+/// let (request, rx) = ScheduledJobRequest::new(ScheduledJobMessage::GetScheduledJobs);
+/// scheduler.send(request).await;
+/// let response = tokio::time::timeout(Duration::from_millis(100), rx).await??;
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScheduledJobRequest {
+    /// Message to handle.
+    pub message: ScheduledJobMessage,
+    /// Response channel for the reply.
+    pub response_tx: ResponseChannel<ScheduledJobResponse>,
+}
+
+impl ScheduledJobRequest {
+    /// Create a new request with response channel.
+    ///
+    /// Returns a tuple of (request, receiver) where the request should be
+    /// sent to the agent and the receiver awaited for the response.
+    #[must_use]
+    pub fn new(message: ScheduledJobMessage) -> (Self, oneshot::Receiver<ScheduledJobResponse>) {
+        let (tx, rx) = oneshot::channel();
+        let request = Self {
+            message,
+            response_tx: Arc::new(Mutex::new(Some(tx))),
+        };
+        (request, rx)
+    }
 }
 
 /// Scheduled job management actor.
@@ -144,93 +249,150 @@ impl ScheduledJobAgent {
         builder.model.job_agent_handle = Some(job_agent_handle);
 
         // Configure message handlers
-        builder.mutate_on::<ScheduledJobMessage>(|actor, context| {
-            let msg = context.message().clone();
-            let reply_envelope = context.reply_envelope();
+        builder
+            .mutate_on::<ScheduledJobMessage>(|actor, context| {
+                let msg = context.message().clone();
+                let reply_envelope = context.reply_envelope();
+
+                if matches!(msg, ScheduledJobMessage::ProcessScheduledJobs) {
+                    // Clone what we need for processing
+                    let scheduled_jobs = actor.model.scheduled_jobs.clone();
+                    let job_handle = actor.model.job_agent_handle.clone();
 
-            match msg {
-                ScheduledJobMessage::RegisterScheduledJob {
+                    // Process in async block
+                    return Reply::pending(async move {
+                        Self::process_scheduled_jobs_async(scheduled_jobs, job_handle).await;
+                    });
+                }
+
+                let response = actor.model.apply(msg);
+                Reply::pending(async move {
+                    if let Some(response) = response {
+                        let _: () = reply_envelope.send(response).await;
+                    }
+                })
+            })
+            .mutate_on::<ScheduledJobRequest>(|actor, context| {
+                let msg = context.message().message.clone();
+                let response_tx = context.message().response_tx.clone();
+                let scheduled_jobs = actor.model.scheduled_jobs.clone();
+                let job_handle = actor.model.job_agent_handle.clone();
+                let response = actor.model.apply(msg);
+
+                Reply::pending(async move {
+                    // Only ProcessScheduledJobs is left unanswered by apply
+                    let response = if let Some(response) = response {
+                        response
+                    } else {
+                        Self::process_scheduled_jobs_async(scheduled_jobs, job_handle).await;
+                        ScheduledJobResponse::Processed
+                    };
+                    let mut guard = response_tx.lock().await;
+                    if let Some(tx) = guard.take() {
+                        let _ = tx.send(response);
+                    }
+                })
+            });
+
+        Ok(builder.start().await)
+    }
+
+    /// Apply a message to the schedule table.
+    ///
+    /// Returns `None` for [`ScheduledJobMessage::ProcessScheduledJobs`],
+    /// which needs async processing by the caller.
+    fn apply(&self, msg: ScheduledJobMessage) -> Option<ScheduledJobResponse> {
+        let response = match msg {
+            ScheduledJobMessage::RegisterScheduledJob {
+                job_type,
+                payload,
+                schedule,
+                priority,
+                max_retries,
+                timeout,
+            } => {
+                let id = JobId::new();
+                let next_execution = schedule
+                    .next_execution(Utc::now())
+                    .unwrap_or_else(Utc::now);
+
+                let entry = ScheduledJobEntry {
+                    id,
                     job_type,
                     payload,
                     schedule,
                     priority,
                     max_retries,
                     timeout,
-                } => {
-                    let id = JobId::new();
-                    let next_execution = schedule
-                        .next_execution(Utc::now())
-                        .unwrap_or_else(Utc::now);
-
-                    let entry = ScheduledJobEntry {
-                        id,
-                        job_type,
-                        payload,
-                        schedule,
-                        priority,
-                        max_retries,
-                        timeout,
-                        next_execution,
-                        execution_count: 0,
-                        enabled: true,
-                    };
-
-                    actor.model.scheduled_jobs.write().insert(id, entry);
-                    info!("Registered scheduled job: {}", id);
-
-                    Reply::pending(async move {
-                        let response = ScheduledJobResponse::JobRegistered { id };
-                        let _: () = reply_envelope.send(response).await;
-                    })
-                }
-                ScheduledJobMessage::UnregisterScheduledJob { id } => {
-                    actor.model.scheduled_jobs.write().remove(&id);
-                    info!("Unregistered scheduled job: {}", id);
+                    next_execution,
+                    execution_count: 0,
+                    enabled: true,
+                    last_run: None,
+                };
 
-                    Reply::pending(async move {
-                        let response = ScheduledJobResponse::JobUnregistered;
-                        let _: () = reply_envelope.send(response).await;
-                    })
-                }
-                ScheduledJobMessage::SetScheduledJobEnabled { id, enabled } => {
-                    if let Some(entry) = actor.model.scheduled_jobs.write().get_mut(&id) {
-                        entry.enabled = enabled;
-                        info!("Set scheduled job {} enabled={}", id, enabled);
+                self.scheduled_jobs.write().insert(id, entry);
+                info!("Registered scheduled job: {}", id);
+                ScheduledJobResponse::JobRegistered { id }
+            }
+            ScheduledJobMessage::UnregisterScheduledJob { id } => {
+                self.scheduled_jobs.write().remove(&id);
+                info!("Unregistered scheduled job: {}", id);
+                ScheduledJobResponse::JobUnregistered
+            }
+            ScheduledJobMessage::SetScheduledJobEnabled { id, enabled } => {
+                if let Some(entry) = self.scheduled_jobs.write().get_mut(&id) {
+                    // Resuming skips runs missed while paused
+                    let now = Utc::now();
+                    if enabled && !entry.enabled && entry.next_execution < now {
+                        entry.next_execution =
+                            entry.schedule.next_execution(now).unwrap_or(now);
                     }
-
-                    Reply::pending(async move {
-                        let response = ScheduledJobResponse::EnabledUpdated;
-                        let _: () = reply_envelope.send(response).await;
-                    })
+                    entry.enabled = enabled;
+                    info!("Set scheduled job {} enabled={}", id, enabled);
                 }
-                ScheduledJobMessage::ProcessScheduledJobs => {
-                    // Clone what we need for processing
-                    let scheduled_jobs = actor.model.scheduled_jobs.clone();
-                    let job_handle = actor.model.job_agent_handle.clone();
-
-                    // Process in async block
-                    Reply::pending(async move {
-                        Self::process_scheduled_jobs_async(scheduled_jobs, job_handle).await;
-                    })
-                }
-                ScheduledJobMessage::GetScheduledJobs => {
-                    let jobs = actor
-                        .model
-                        .scheduled_jobs
-                        .read()
-                        .values()
-                        .cloned()
-                        .collect();
-
-                    Reply::pending(async move {
-                        let response = ScheduledJobResponse::ScheduledJobs(jobs);
-                        let _: () = reply_envelope.send(response).await;
-                    })
+                ScheduledJobResponse::EnabledUpdated
+            }
+            ScheduledJobMessage::UpdateSchedule { id, schedule } => {
+                let now = Utc::now();
+                let updated = self.scheduled_jobs.write().get_mut(&id).map(|entry| {
+                    entry.next_execution = schedule.next_execution(now).unwrap_or(now);
+                    entry.schedule = schedule;
+                    entry.execution_count = 0;
+                    entry.clone()
+                });
+                let Some(entry) = updated else {
+                    return Some(ScheduledJobResponse::NotFound { id });
+                };
+                info!(
+                    "Updated schedule of job {} to {}",
+                    id,
+                    entry.schedule.description()
+                );
+                ScheduledJobResponse::ScheduleUpdated(Box::new(entry))
+            }
+            ScheduledJobMessage::RecordRunOutcome { job_id, outcome } => {
+                let recorded = self
+                    .scheduled_jobs
+                    .write()
+                    .values_mut()
+                    .filter_map(|entry| entry.last_run.as_mut())
+                    .find(|run| run.job_id == job_id)
+                    .map(|run| run.outcome = outcome)
+                    .is_some();
+                if recorded {
+                    ScheduledJobResponse::OutcomeRecorded
+                } else {
+                    ScheduledJobResponse::NotFound { id: job_id }
                 }
             }
-        });
-
-        Ok(builder.start().await)
+            ScheduledJobMessage::GetScheduledJobs => {
+                let mut jobs: Vec<_> = self.scheduled_jobs.read().values().cloned().collect();
+                jobs.sort_by_key(|entry| entry.next_execution);
+                ScheduledJobResponse::ScheduledJobs(jobs)
+            }
+            ScheduledJobMessage::ProcessScheduledJobs => return None,
+        };
+        Some(response)
     }
 
     /// Process all scheduled jobs and enqueue those that are ready (async).
@@ -258,7 +420,13 @@ impl ScheduledJobAgent {
                         continue;
                     }
 
-                    jobs_to_enqueue.push(entry.clone());
+                    let job_id = JobId::new(); // New ID for each execution
+                    entry.last_run = Some(ScheduledRun {
+                        job_id,
+                        enqueued_at: now,
+                        outcome: ScheduledRunOutcome::Enqueued,
+                    });
+                    jobs_to_enqueue.push((job_id, entry.clone()));
 
                     // Update execution count and next execution time
                     entry.execution_count += 1;
@@ -274,16 +442,17 @@ impl ScheduledJobAgent {
 
         // Enqueue jobs
         if let Some(job_agent) = job_handle {
-            for entry in jobs_to_enqueue {
+            for (job_id, entry) in jobs_to_enqueue {
                 debug!("Enqueueing scheduled job: {}", entry.id);
 
                 let enqueue_msg = EnqueueJob {
-                    id: JobId::new(), // New ID for each execution
+                    id: job_id,
                     job_type: entry.job_type.clone(),
                     payload: entry.payload.clone(),
                     priority: entry.priority,
                     max_retries: entry.max_retries,
                     timeout: entry.timeout,
+                    tenant_id: None,
                 };
 
                 // Send message to job agent using handle
@@ -335,6 +504,7 @@ mod tests {
             next_execution: Utc::now(),
             execution_count: 0,
             enabled: true,
+            last_run: None,
         };
 
         assert_eq!(entry.job_type, "TestJob");
@@ -355,6 +525,7 @@ mod tests {
             next_execution: Utc::now(),
             execution_count: 5,
             enabled: true,
+            last_run: None,
         };
 
         let json = serde_json::to_string(&entry).unwrap();
@@ -363,4 +534,98 @@ mod tests {
         assert_eq!(entry.job_type, deserialized.job_type);
         assert_eq!(entry.execution_count, deserialized.execution_count);
     }
+
+    fn register(agent: &ScheduledJobAgent, schedule: JobSchedule) -> JobId {
+        let response = agent.apply(ScheduledJobMessage::RegisterScheduledJob {
+            job_type: "TestJob".to_string(),
+            payload: Vec::new(),
+            schedule,
+            priority: 0,
+            max_retries: 3,
+            timeout: Duration::from_secs(300),
+        });
+        match response {
+            Some(ScheduledJobResponse::JobRegistered { id }) => id,
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_list_orders_by_next_execution() {
+        let agent = ScheduledJobAgent::new();
+        let hourly = register(&agent, JobSchedule::every(Duration::from_secs(3600)));
+        let minutely = register(&agent, JobSchedule::every(Duration::from_secs(60)));
+
+        let Some(ScheduledJobResponse::ScheduledJobs(jobs)) =
+            agent.apply(ScheduledJobMessage::GetScheduledJobs)
+        else {
+            panic!("expected scheduled jobs");
+        };
+        let ids: Vec<_> = jobs.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![minutely, hourly]);
+    }
+
+    #[test]
+    fn test_update_schedule() {
+        let agent = ScheduledJobAgent::new();
+        let id = register(&agent, JobSchedule::every(Duration::from_secs(3600)));
+
+        let response = agent.apply(ScheduledJobMessage::UpdateSchedule {
+            id,
+            schedule: JobSchedule::every(Duration::from_secs(60)),
+        });
+        let Some(ScheduledJobResponse::ScheduleUpdated(entry)) = response else {
+            panic!("expected updated entry");
+        };
+        assert!(entry.next_execution <= Utc::now() + chrono::Duration::seconds(60));
+        assert_eq!(entry.schedule.description(), "every 60s");
+
+        let missing = JobId::new();
+        assert!(matches!(
+            agent.apply(ScheduledJobMessage::UpdateSchedule {
+                id: missing,
+                schedule: JobSchedule::every(Duration::from_secs(60)),
+            }),
+            Some(ScheduledJobResponse::NotFound { id }) if id == missing
+        ));
+    }
+
+    #[test]
+    fn test_resume_skips_missed_runs() {
+        let agent = ScheduledJobAgent::new();
+        let id = register(&agent, JobSchedule::every(Duration::from_secs(60)));
+        agent.apply(ScheduledJobMessage::SetScheduledJobEnabled { id, enabled: false });
+        agent.scheduled_jobs.write().get_mut(&id).unwrap().next_execution =
+            Utc::now() - chrono::Duration::hours(1);
+
+        agent.apply(ScheduledJobMessage::SetScheduledJobEnabled { id, enabled: true });
+        let entry = agent.scheduled_jobs.read()[&id].clone();
+        assert!(entry.enabled);
+        assert!(entry.next_execution > Utc::now());
+    }
+
+    #[test]
+    fn test_record_run_outcome() {
+        let agent = ScheduledJobAgent::new();
+        let id = register(&agent, JobSchedule::every(Duration::from_secs(60)));
+        let job_id = JobId::new();
+        agent.scheduled_jobs.write().get_mut(&id).unwrap().last_run = Some(ScheduledRun {
+            job_id,
+            enqueued_at: Utc::now(),
+            outcome: ScheduledRunOutcome::Enqueued,
+        });
+
+        let outcome = ScheduledRunOutcome::Failed {
+            error: "smtp timeout".to_string(),
+        };
+        assert!(matches!(
+            agent.apply(ScheduledJobMessage::RecordRunOutcome {
+                job_id,
+                outcome: outcome.clone(),
+            }),
+            Some(ScheduledJobResponse::OutcomeRecorded)
+        ));
+        let last_run = agent.scheduled_jobs.read()[&id].last_run.clone().unwrap();
+        assert_eq!(last_run.outcome, outcome);
+    }
 }
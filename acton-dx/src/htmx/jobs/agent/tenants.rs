@@ -0,0 +1,194 @@
+//! Per-tenant job quotas.
+//!
+//! Jobs may carry a tenant ID. Each tenant gets a concurrency limit and a
+//! daily quota, and the queue interleaves tenants when dequeuing (see
+//! [`JobQueue::dequeue`](super::queue::JobQueue)), so one tenant's bulk
+//! import cannot hold up everyone else's transactional jobs.
+//!
+//! Jobs without a tenant are not subject to quotas.
+
+use crate::htmx::jobs::JobId;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Limits applied to one tenant's jobs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TenantLimits {
+    /// Maximum jobs running at once (`None` = unlimited).
+    pub max_concurrent: Option<usize>,
+    /// Maximum jobs accepted per UTC day (`None` = unlimited).
+    pub max_daily: Option<u64>,
+}
+
+/// Tenant quota configuration.
+///
+/// # Example Configuration
+///
+/// ```toml
+/// [jobs.tenant_quotas.default]
+/// max_concurrent = 4
+/// max_daily = 10000
+///
+/// [jobs.tenant_quotas.tenants.acme]
+/// max_concurrent = 16
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TenantQuotas {
+    /// Limits for tenants without an override.
+    pub default: TenantLimits,
+    /// Per-tenant overrides, keyed by tenant ID.
+    pub tenants: HashMap<String, TenantLimits>,
+}
+
+impl TenantQuotas {
+    /// Limits that apply to `tenant`.
+    #[must_use]
+    pub fn limits(&self, tenant: &str) -> TenantLimits {
+        self.tenants.get(tenant).copied().unwrap_or(self.default)
+    }
+}
+
+/// Tracks each tenant's usage against its quotas.
+#[derive(Debug, Default)]
+pub(super) struct TenantUsage {
+    /// Configured quotas.
+    quotas: TenantQuotas,
+    /// Running job count per tenant.
+    running: HashMap<String, usize>,
+    /// Tenant of each running job, to release its slot on finish.
+    running_jobs: HashMap<JobId, String>,
+    /// Jobs accepted today per tenant.
+    accepted_today: HashMap<String, u64>,
+    /// Day the `accepted_today` counts belong to.
+    day: Option<NaiveDate>,
+}
+
+impl TenantUsage {
+    /// Create usage tracking for the given quotas.
+    #[must_use]
+    pub(super) fn new(quotas: TenantQuotas) -> Self {
+        Self {
+            quotas,
+            ..Self::default()
+        }
+    }
+
+    /// Record a newly accepted job, unless it exceeds the daily quota.
+    ///
+    /// Returns `false` if the tenant has used up today's quota.
+    pub(super) fn try_accept(&mut self, tenant: Option<&str>, today: NaiveDate) -> bool {
+        let Some(tenant) = tenant else {
+            return true;
+        };
+        if self.day != Some(today) {
+            self.accepted_today.clear();
+            self.day = Some(today);
+        }
+        let accepted = self.accepted_today.entry(tenant.to_string()).or_default();
+        if self
+            .quotas
+            .limits(tenant)
+            .max_daily
+            .is_some_and(|max| *accepted >= max)
+        {
+            return false;
+        }
+        *accepted += 1;
+        true
+    }
+
+    /// Whether the tenant has a free concurrency slot.
+    #[must_use]
+    pub(super) fn can_start(&self, tenant: Option<&str>) -> bool {
+        let Some(tenant) = tenant else {
+            return true;
+        };
+        self.quotas
+            .limits(tenant)
+            .max_concurrent
+            .is_none_or(|max| self.running.get(tenant).copied().unwrap_or(0) < max)
+    }
+
+    /// Take a concurrency slot for a job that is starting.
+    pub(super) fn start(&mut self, id: JobId, tenant: Option<&str>) {
+        if let Some(tenant) = tenant {
+            *self.running.entry(tenant.to_string()).or_default() += 1;
+            self.running_jobs.insert(id, tenant.to_string());
+        }
+    }
+
+    /// Release the concurrency slot of a job that has finished.
+    pub(super) fn finish(&mut self, id: &JobId) {
+        if let Some(tenant) = self.running_jobs.remove(id) {
+            if let Some(count) = self.running.get_mut(&tenant) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    self.running.remove(&tenant);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quotas() -> TenantQuotas {
+        TenantQuotas {
+            default: TenantLimits {
+                max_concurrent: Some(1),
+                max_daily: Some(2),
+            },
+            tenants: HashMap::from([(
+                "acme".to_string(),
+                TenantLimits {
+                    max_concurrent: Some(2),
+                    max_daily: None,
+                },
+            )]),
+        }
+    }
+
+    #[test]
+    fn test_daily_quota_resets_each_day() {
+        let mut usage = TenantUsage::new(quotas());
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+
+        assert!(usage.try_accept(Some("globex"), monday));
+        assert!(usage.try_accept(Some("globex"), monday));
+        assert!(!usage.try_accept(Some("globex"), monday));
+        assert!(usage.try_accept(Some("globex"), tuesday));
+
+        // Overrides and untenanted jobs are unlimited
+        for _ in 0..5 {
+            assert!(usage.try_accept(Some("acme"), monday));
+            assert!(usage.try_accept(None, monday));
+        }
+    }
+
+    #[test]
+    fn test_concurrency_slots() {
+        let mut usage = TenantUsage::new(quotas());
+        let (first, second) = (JobId::new(), JobId::new());
+
+        assert!(usage.can_start(Some("globex")));
+        usage.start(first, Some("globex"));
+        assert!(!usage.can_start(Some("globex")));
+        assert!(usage.can_start(Some("acme")));
+        assert!(usage.can_start(None));
+
+        usage.finish(&first);
+        assert!(usage.can_start(Some("globex")));
+
+        usage.start(first, Some("acme"));
+        usage.start(second, Some("acme"));
+        assert!(!usage.can_start(Some("acme")));
+        usage.finish(&second);
+        assert!(usage.can_start(Some("acme")));
+    }
+}
@@ -46,6 +46,8 @@
 //! }
 //! ```
 
+use super::operations::{OperationHandle, OperationTracker};
+use super::JobId;
 use crate::htmx::email::EmailSender;
 use crate::htmx::storage::FileStorage;
 use sqlx::PgPool;
@@ -61,6 +63,7 @@ use deadpool_redis::Pool as RedisPool;
 /// - Database pool for database queries
 /// - File storage for file operations
 /// - Redis pool for caching (optional, feature-gated)
+/// - Operation tracker for reporting progress to the user
 ///
 /// All fields are optional to support different deployment scenarios.
 /// Jobs should gracefully handle missing services.
@@ -78,6 +81,12 @@ pub struct JobContext {
     /// Redis connection pool (optional, for caching and distributed operations)
     #[cfg(feature = "redis")]
     redis_pool: Option<RedisPool>,
+
+    /// ID of the job being executed
+    job_id: Option<JobId>,
+
+    /// Progress store for user-facing operations
+    operations: Option<OperationTracker>,
 }
 
 impl JobContext {
@@ -92,6 +101,8 @@ impl JobContext {
             file_storage: None,
             #[cfg(feature = "redis")]
             redis_pool: None,
+            job_id: None,
+            operations: None,
         }
     }
 
@@ -124,6 +135,22 @@ impl JobContext {
         self
     }
 
+    /// Set the ID of the job this context is passed to.
+    ///
+    /// Workers set this per job so the job can report progress.
+    #[must_use]
+    pub const fn with_job_id(mut self, id: JobId) -> Self {
+        self.job_id = Some(id);
+        self
+    }
+
+    /// Set the operation tracker for this context.
+    #[must_use]
+    pub fn with_operations(mut self, tracker: OperationTracker) -> Self {
+        self.operations = Some(tracker);
+        self
+    }
+
     /// Get the email sender if available.
     #[must_use]
     pub fn email_sender(&self) -> Option<&Arc<dyn EmailSender>> {
@@ -148,6 +175,20 @@ impl JobContext {
     pub const fn redis_pool(&self) -> Option<&RedisPool> {
         self.redis_pool.as_ref()
     }
+
+    /// Get the ID of the job being executed, if set.
+    #[must_use]
+    pub const fn job_id(&self) -> Option<JobId> {
+        self.job_id
+    }
+
+    /// Get a handle for reporting this job's progress to the user.
+    ///
+    /// Available when both the job ID and an operation tracker are set.
+    #[must_use]
+    pub fn operation(&self) -> Option<OperationHandle> {
+        Some(self.operations.as_ref()?.handle(self.job_id?))
+    }
 }
 
 impl Default for JobContext {
@@ -167,7 +208,10 @@ impl std::fmt::Debug for JobContext {
         #[cfg(feature = "redis")]
         debug_struct.field("redis_pool", &self.redis_pool.is_some());
 
-        debug_struct.finish()
+        debug_struct
+            .field("job_id", &self.job_id)
+            .field("operations", &self.operations.is_some())
+            .finish()
     }
 }
 
@@ -198,4 +242,16 @@ mod tests {
         assert!(debug_output.contains("JobContext"));
         assert!(debug_output.contains("email_sender"));
     }
+
+    #[test]
+    fn test_job_context_operation_requires_job_id() {
+        let tracker = OperationTracker::new();
+        let ctx = JobContext::new().with_operations(tracker.clone());
+        assert!(ctx.operation().is_none());
+
+        let id = JobId::new();
+        let ctx = ctx.with_job_id(id);
+        ctx.operation().unwrap().message("Working");
+        assert_eq!(tracker.get(&id).unwrap().message.as_deref(), Some("Working"));
+    }
 }
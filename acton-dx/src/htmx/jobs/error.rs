@@ -29,6 +29,18 @@ pub enum JobError {
     #[error("redis error: {0}")]
     RedisError(#[from] redis::RedisError),
 
+    /// Payload encryption or decryption failed.
+    #[error("payload encryption error: {0}")]
+    EncryptionError(#[from] crate::htmx::encryption::EncryptionError),
+
+    /// Job history archive could not be written or read.
+    #[error("job history archive error: {0}")]
+    ArchiveError(String),
+
+    /// A job queue alert could not be delivered.
+    #[error("job alert delivery error: {0}")]
+    AlertError(String),
+
     /// Job not found.
     #[error("job not found: {0}")]
     NotFound(String),
@@ -68,6 +68,7 @@ mod error;
 pub mod examples;
 mod job;
 mod observability;
+mod operations;
 mod schedule;
 mod status;
 
@@ -80,6 +81,7 @@ pub use job::{Job, JobId};
 pub use observability::{JobExecutionContext, JobPerformanceRecorder, JobQueueObserver};
 #[cfg(feature = "otel-metrics")]
 pub use observability::JobMetricsCollector;
+pub use operations::{OperationHandle, OperationOutcome, OperationProgress, OperationTracker};
 pub use schedule::JobSchedule;
 pub use status::JobStatus;
 
@@ -0,0 +1,228 @@
+//! Progress and results of user-facing background operations.
+//!
+//! A handler that kicks off a long-running job (an export, an import, a
+//! report) needs somewhere to read the job's progress and final result
+//! from while the browser polls. The [`OperationTracker`] is that place:
+//! jobs report through the [`OperationHandle`] on their [`JobContext`],
+//! and the status endpoint in [`handlers::operations`] renders it.
+//!
+//! [`JobContext`]: super::JobContext
+//! [`handlers::operations`]: crate::htmx::handlers::operations
+
+use super::JobId;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// How an operation ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum OperationOutcome {
+    /// Finished; `html` is swapped in as the result.
+    Succeeded {
+        /// Result partial (trusted HTML rendered by the job).
+        html: String,
+    },
+    /// Failed; `error` is shown to the user.
+    Failed {
+        /// User-facing error message.
+        error: String,
+    },
+}
+
+/// Progress reported by an operation so far.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationProgress {
+    /// Completion percentage (0-100), if the job knows it.
+    pub percent: Option<u8>,
+    /// Short description of the current step.
+    pub message: Option<String>,
+    /// Final outcome, once the job has finished.
+    pub outcome: Option<OperationOutcome>,
+    /// When the job last reported.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for OperationProgress {
+    fn default() -> Self {
+        Self {
+            percent: None,
+            message: None,
+            outcome: None,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// Shared store of operation progress, keyed by job ID.
+///
+/// Cheap to clone; all clones share the same store.
+#[derive(Debug, Clone, Default)]
+pub struct OperationTracker {
+    operations: Arc<RwLock<HashMap<JobId, OperationProgress>>>,
+}
+
+impl OperationTracker {
+    /// Create an empty tracker.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking an operation with no progress yet.
+    pub fn track(&self, id: JobId) {
+        self.operations.write().insert(id, OperationProgress::default());
+    }
+
+    /// Handle for reporting the progress of `id`.
+    #[must_use]
+    pub fn handle(&self, id: JobId) -> OperationHandle {
+        OperationHandle {
+            id,
+            tracker: self.clone(),
+        }
+    }
+
+    /// Current progress of `id`, if it is tracked.
+    #[must_use]
+    pub fn get(&self, id: &JobId) -> Option<OperationProgress> {
+        self.operations.read().get(id).cloned()
+    }
+
+    /// Stop tracking `id`.
+    pub fn remove(&self, id: &JobId) {
+        self.operations.write().remove(id);
+    }
+
+    /// Stop tracking operations that have not reported since `before`.
+    ///
+    /// Returns the number of operations removed.
+    #[must_use]
+    pub fn prune(&self, before: DateTime<Utc>) -> usize {
+        let mut operations = self.operations.write();
+        let count = operations.len();
+        operations.retain(|_, progress| progress.updated_at >= before);
+        count - operations.len()
+    }
+
+    fn update(&self, id: JobId, apply: impl FnOnce(&mut OperationProgress)) {
+        let mut operations = self.operations.write();
+        let progress = operations.entry(id).or_default();
+        apply(progress);
+        progress.updated_at = Utc::now();
+        drop(operations);
+    }
+}
+
+/// Reports one operation's progress from inside its job.
+///
+/// ```rust,ignore
+/// async fn execute(&self, ctx: &JobContext) -> JobResult<()> {
+///     let op = ctx.operation();
+///     for (i, chunk) in self.rows.chunks(100).enumerate() {
+///         import(chunk).await?;
+///         if let Some(op) = &op {
+///             op.progress(percent(i), format!("Imported {} rows", (i + 1) * 100));
+///         }
+///     }
+///     if let Some(op) = op {
+///         op.succeed(r#"<p>Import finished. <a href="/contacts">View contacts</a></p>"#);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OperationHandle {
+    id: JobId,
+    tracker: OperationTracker,
+}
+
+impl OperationHandle {
+    /// The operation's job ID.
+    #[must_use]
+    pub const fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// Report progress; `percent` is clamped to 100.
+    pub fn progress(&self, percent: u8, message: impl Into<String>) {
+        let message = message.into();
+        self.tracker.update(self.id, |progress| {
+            progress.percent = Some(percent.min(100));
+            progress.message = Some(message);
+        });
+    }
+
+    /// Report the current step without a percentage.
+    pub fn message(&self, message: impl Into<String>) {
+        let message = message.into();
+        self.tracker.update(self.id, |progress| {
+            progress.message = Some(message);
+        });
+    }
+
+    /// Finish the operation with a result partial.
+    ///
+    /// The HTML is swapped in as-is; escape any user data in it.
+    pub fn succeed(&self, html: impl Into<String>) {
+        let html = html.into();
+        self.tracker.update(self.id, |progress| {
+            progress.percent = Some(100);
+            progress.outcome = Some(OperationOutcome::Succeeded { html });
+        });
+    }
+
+    /// Finish the operation with a user-facing error.
+    pub fn fail(&self, error: impl Into<String>) {
+        let error = error.into();
+        self.tracker.update(self.id, |progress| {
+            progress.outcome = Some(OperationOutcome::Failed { error });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_reports_progress_and_outcome() {
+        let tracker = OperationTracker::new();
+        let id = JobId::new();
+        tracker.track(id);
+        assert_eq!(tracker.get(&id).unwrap().percent, None);
+
+        let op = tracker.handle(id);
+        op.progress(250, "Exporting");
+        let progress = tracker.get(&id).unwrap();
+        assert_eq!(progress.percent, Some(100));
+        assert_eq!(progress.message.as_deref(), Some("Exporting"));
+        assert!(progress.outcome.is_none());
+
+        op.fail("Disk full");
+        assert_eq!(
+            tracker.get(&id).unwrap().outcome,
+            Some(OperationOutcome::Failed {
+                error: "Disk full".to_string()
+            })
+        );
+        tracker.remove(&id);
+        assert!(tracker.get(&id).is_none());
+    }
+
+    #[test]
+    fn test_prune_removes_stale_operations() {
+        let tracker = OperationTracker::new();
+        let (stale, fresh) = (JobId::new(), JobId::new());
+        tracker.track(stale);
+        let cutoff = Utc::now() + chrono::Duration::milliseconds(1);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        tracker.track(fresh);
+
+        assert_eq!(tracker.prune(cutoff), 1);
+        assert!(tracker.get(&stale).is_none());
+        assert!(tracker.get(&fresh).is_some());
+    }
+}
@@ -0,0 +1,97 @@
+//! Lock store backed by cache-service
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use super::{EditLock, LockError, LockStore};
+use crate::htmx::clients::CacheClient;
+use crate::htmx::util::unix_now;
+
+/// Lock store shared between instances through cache-service
+///
+/// Each lock is a JSON value at `lock:{resource}` with a TTL matching the
+/// lock lifetime. Acquisition is check-then-set: two users acquiring within
+/// the same round trip can both succeed, which is acceptable for advisory
+/// locks.
+#[derive(Debug, Clone)]
+pub struct CacheLockStore {
+    client: Arc<RwLock<CacheClient>>,
+    prefix: String,
+}
+
+impl CacheLockStore {
+    /// Create a store using the `lock:` key prefix
+    #[must_use]
+    pub fn new(client: Arc<RwLock<CacheClient>>) -> Self {
+        Self {
+            client,
+            prefix: "lock:".to_string(),
+        }
+    }
+
+    /// Use a different key prefix
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, resource: &str) -> String {
+        format!("{}{resource}", self.prefix)
+    }
+}
+
+#[async_trait]
+impl LockStore for CacheLockStore {
+    async fn get(&self, resource: &str) -> Result<Option<EditLock>, LockError> {
+        let raw = self
+            .client
+            .write()
+            .await
+            .get(&self.key(resource))
+            .await
+            .map_err(|e| LockError::Store(e.to_string()))?;
+        let now = unix_now();
+        Ok(raw
+            .and_then(|bytes| serde_json::from_slice::<EditLock>(&bytes).ok())
+            .filter(|lock| lock.is_live(now)))
+    }
+
+    async fn put_if_free(
+        &self,
+        lock: EditLock,
+        ttl: Duration,
+    ) -> Result<Option<EditLock>, LockError> {
+        if let Some(existing) = self.get(&lock.resource).await? {
+            if !existing.is_held_by(&lock.holder) {
+                return Ok(Some(existing));
+            }
+        }
+        self.put(lock, ttl).await?;
+        Ok(None)
+    }
+
+    async fn put(&self, lock: EditLock, ttl: Duration) -> Result<(), LockError> {
+        let bytes = serde_json::to_vec(&lock).map_err(|e| LockError::Store(e.to_string()))?;
+        let ttl_seconds = i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX).max(1);
+        self.client
+            .write()
+            .await
+            .set(&self.key(&lock.resource), &bytes, Some(ttl_seconds))
+            .await
+            .map_err(|e| LockError::Store(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn remove(&self, resource: &str) -> Result<(), LockError> {
+        self.client
+            .write()
+            .await
+            .delete(&self.key(resource))
+            .await
+            .map_err(|e| LockError::Store(e.to_string()))?;
+        Ok(())
+    }
+}
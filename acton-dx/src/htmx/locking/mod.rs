@@ -0,0 +1,694 @@
+//! Advisory edit locks for collaborative resources
+//!
+//! Before opening an editor, a handler acquires a lock on the resource
+//! (`doc:42`). Other users see "Locked by Alice, expires in 2m" instead of an
+//! editor and may take the lock over explicitly. The editor renews the lock
+//! with an HTMX poll; an abandoned lock expires after its TTL.
+//!
+//! Locks are advisory: they coordinate people, not database writes. Saves
+//! should still use optimistic concurrency (version columns) as a backstop.
+//!
+//! - [`EditLocks`] acquires, renews, releases and takes over locks in a
+//!   [`LockStore`] (in-memory, or cache-service with `microservices`)
+//! - [`EditLocks::middleware`] looks up the lock for matching routes and
+//!   exposes it to handlers through the [`LockStatus`] extractor
+//! - [`EditLocks::routes`] serves HTMX renew/release/takeover endpoints that
+//!   return the [`lock_banner`] partial
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::locking::{EditLocks, LockHolder, LockStatus, MemoryLockStore};
+//! use axum::middleware::from_fn_with_state;
+//!
+//! let locks = EditLocks::new(Arc::new(MemoryLockStore::new())).with_route("/docs/", "doc");
+//!
+//! async fn edit(State(locks): State<EditLocks>, status: LockStatus, session: Session) -> Response {
+//!     let holder = LockHolder::from_session(&session).unwrap();
+//!     match locks.acquire(status.resource(), &holder).await {
+//!         Ok(lock) => EditForm { lock }.into_response(),
+//!         Err(LockError::Held(lock)) => Html(lock_banner(&lock, &holder)).into_response(),
+//!         Err(e) => e.into_response(),
+//!     }
+//! }
+//!
+//! let app = Router::new()
+//!     .route("/docs/{id}/edit", get(edit))
+//!     .layer(from_fn_with_state(locks.clone(), EditLocks::middleware))
+//!     .merge(locks.routes());
+//! ```
+//!
+//! ```html
+//! <!-- inside the editor: keep the lock alive -->
+//! <div hx-post="/locks/doc:42/renew" hx-trigger="every 30s" hx-swap="outerHTML"></div>
+//! ```
+
+#[cfg(feature = "microservices")]
+mod cache_store;
+
+#[cfg(feature = "microservices")]
+pub use cache_store::CacheLockStore;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Path, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+    routing::post,
+    Extension, Router,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::htmx::auth::session::SessionData;
+use crate::htmx::template::helpers::escape_html;
+use crate::htmx::util::unix_now;
+
+/// Default lock lifetime without renewal
+pub const DEFAULT_LOCK_TTL: Duration = Duration::from_secs(300);
+
+/// Lock errors
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    /// Someone else holds the lock
+    #[error("Resource is locked by {}", .0.holder.display_name())]
+    Held(Box<EditLock>),
+
+    /// Caller does not hold the lock it tried to renew or release
+    #[error("Lock is not held by the caller")]
+    NotHolder,
+
+    /// Request has no authenticated user to hold the lock
+    #[error("Locking requires an authenticated user")]
+    Unauthenticated,
+
+    /// Backing store failed
+    #[error("Lock store error: {0}")]
+    Store(String),
+}
+
+impl IntoResponse for LockError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Held(_) | Self::NotHolder => StatusCode::CONFLICT.into_response(),
+            Self::Unauthenticated => StatusCode::UNAUTHORIZED.into_response(),
+            Self::Store(ref e) => {
+                warn!(error = %e, "Lock store failed");
+                StatusCode::SERVICE_UNAVAILABLE.into_response()
+            }
+        }
+    }
+}
+
+/// Identity of a lock holder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockHolder {
+    /// Stable identifier (user ID)
+    pub id: String,
+    /// Display name shown to other users
+    pub name: Option<String>,
+}
+
+impl LockHolder {
+    /// Create a holder
+    #[must_use]
+    pub fn new(id: impl Into<String>, name: Option<String>) -> Self {
+        Self {
+            id: id.into(),
+            name,
+        }
+    }
+
+    /// Holder for the session's authenticated user
+    #[must_use]
+    pub fn from_session(session: &SessionData) -> Option<Self> {
+        session
+            .user_id
+            .map(|id| Self::new(id.to_string(), session.user_name.clone()))
+    }
+
+    /// Name to show to other users
+    #[must_use]
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("another user")
+    }
+}
+
+/// A held lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EditLock {
+    /// Locked resource identifier
+    pub resource: String,
+    /// Current holder
+    pub holder: LockHolder,
+    /// Unix time (seconds) when the lock was first acquired
+    pub acquired_at: u64,
+    /// Unix time (seconds) when the lock lapses without renewal
+    pub expires_at: u64,
+}
+
+impl EditLock {
+    fn new(resource: &str, holder: &LockHolder, ttl: Duration) -> Self {
+        let now = unix_now();
+        Self {
+            resource: resource.to_string(),
+            holder: holder.clone(),
+            acquired_at: now,
+            expires_at: now.saturating_add(ttl.as_secs()),
+        }
+    }
+
+    /// Whether the lock is still in force at `now` (Unix seconds)
+    #[must_use]
+    pub const fn is_live(&self, now: u64) -> bool {
+        self.expires_at > now
+    }
+
+    /// Whether `holder` holds this lock
+    #[must_use]
+    pub fn is_held_by(&self, holder: &LockHolder) -> bool {
+        self.holder.id == holder.id
+    }
+
+    /// Time left before the lock lapses
+    #[must_use]
+    pub fn expires_in(&self) -> Duration {
+        Duration::from_secs(self.expires_at.saturating_sub(unix_now()))
+    }
+}
+
+/// Storage for locks
+///
+/// Implementations must ignore expired locks.
+#[async_trait]
+pub trait LockStore: Send + Sync {
+    /// Current live lock on a resource
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LockError::Store`] if the store cannot be read.
+    async fn get(&self, resource: &str) -> Result<Option<EditLock>, LockError>;
+
+    /// Store `lock` if the resource is free or already held by the same holder
+    ///
+    /// Returns the existing lock when someone else holds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LockError::Store`] if the store cannot be written.
+    async fn put_if_free(
+        &self,
+        lock: EditLock,
+        ttl: Duration,
+    ) -> Result<Option<EditLock>, LockError>;
+
+    /// Store `lock` unconditionally (takeover)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LockError::Store`] if the store cannot be written.
+    async fn put(&self, lock: EditLock, ttl: Duration) -> Result<(), LockError>;
+
+    /// Remove the lock on a resource
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LockError::Store`] if the store cannot be written.
+    async fn remove(&self, resource: &str) -> Result<(), LockError>;
+}
+
+/// In-process lock store
+#[derive(Debug, Default)]
+pub struct MemoryLockStore {
+    locks: Mutex<HashMap<String, EditLock>>,
+}
+
+impl MemoryLockStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl LockStore for MemoryLockStore {
+    async fn get(&self, resource: &str) -> Result<Option<EditLock>, LockError> {
+        let now = unix_now();
+        Ok(self
+            .locks
+            .lock()
+            .get(resource)
+            .filter(|lock| lock.is_live(now))
+            .cloned())
+    }
+
+    async fn put_if_free(
+        &self,
+        lock: EditLock,
+        _ttl: Duration,
+    ) -> Result<Option<EditLock>, LockError> {
+        let now = unix_now();
+        let mut locks = self.locks.lock();
+        if let Some(existing) = locks.get(&lock.resource) {
+            if existing.is_live(now) && !existing.is_held_by(&lock.holder) {
+                return Ok(Some(existing.clone()));
+            }
+        }
+        locks.insert(lock.resource.clone(), lock);
+        drop(locks);
+        Ok(None)
+    }
+
+    async fn put(&self, lock: EditLock, _ttl: Duration) -> Result<(), LockError> {
+        self.locks.lock().insert(lock.resource.clone(), lock);
+        Ok(())
+    }
+
+    async fn remove(&self, resource: &str) -> Result<(), LockError> {
+        self.locks.lock().remove(resource);
+        Ok(())
+    }
+}
+
+/// Edit lock manager
+#[derive(Clone)]
+pub struct EditLocks {
+    store: Arc<dyn LockStore>,
+    ttl: Duration,
+    routes: Arc<Vec<(String, String)>>,
+}
+
+impl std::fmt::Debug for EditLocks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EditLocks")
+            .field("ttl", &self.ttl)
+            .field("routes", &self.routes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EditLocks {
+    /// Create a lock manager backed by `store`
+    #[must_use]
+    pub fn new(store: Arc<dyn LockStore>) -> Self {
+        Self {
+            store,
+            ttl: DEFAULT_LOCK_TTL,
+            routes: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Set the lock lifetime without renewal (default 5 minutes)
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Expose lock state for paths under `prefix` as `{kind}:{id}`
+    ///
+    /// `with_route("/docs/", "doc")` maps `/docs/42/edit` to resource `doc:42`.
+    #[must_use]
+    pub fn with_route(mut self, prefix: impl Into<String>, kind: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.routes).push((prefix.into(), kind.into()));
+        self
+    }
+
+    /// Acquire a lock, or refresh it if `holder` already holds it
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LockError::Held`] with the current lock if someone else
+    /// holds it.
+    pub async fn acquire(
+        &self,
+        resource: &str,
+        holder: &LockHolder,
+    ) -> Result<EditLock, LockError> {
+        let mut lock = EditLock::new(resource, holder, self.ttl);
+        if let Some(existing) = self.store.get(resource).await? {
+            if existing.is_held_by(holder) {
+                lock.acquired_at = existing.acquired_at;
+            }
+        }
+        self.store
+            .put_if_free(lock.clone(), self.ttl)
+            .await?
+            .map_or(Ok(lock), |existing| {
+                Err(LockError::Held(Box::new(existing)))
+            })
+    }
+
+    /// Extend a lock held by `holder`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LockError::NotHolder`] if the lock lapsed or was taken over.
+    pub async fn renew(&self, resource: &str, holder: &LockHolder) -> Result<EditLock, LockError> {
+        match self.store.get(resource).await? {
+            Some(existing) if existing.is_held_by(holder) => {
+                let lock = EditLock {
+                    expires_at: unix_now().saturating_add(self.ttl.as_secs()),
+                    ..existing
+                };
+                self.store.put(lock.clone(), self.ttl).await?;
+                Ok(lock)
+            }
+            _ => Err(LockError::NotHolder),
+        }
+    }
+
+    /// Release a lock held by `holder`
+    ///
+    /// Releasing a lock that already lapsed is not an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LockError::NotHolder`] if someone else holds the lock.
+    pub async fn release(&self, resource: &str, holder: &LockHolder) -> Result<(), LockError> {
+        match self.store.get(resource).await? {
+            Some(existing) if !existing.is_held_by(holder) => Err(LockError::NotHolder),
+            Some(_) => self.store.remove(resource).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Take a lock over from its current holder
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be written.
+    pub async fn take_over(
+        &self,
+        resource: &str,
+        holder: &LockHolder,
+    ) -> Result<EditLock, LockError> {
+        let lock = EditLock::new(resource, holder, self.ttl);
+        self.store.put(lock.clone(), self.ttl).await?;
+        Ok(lock)
+    }
+
+    /// Current lock on a resource
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read.
+    pub async fn status(&self, resource: &str) -> Result<Option<EditLock>, LockError> {
+        self.store.get(resource).await
+    }
+
+    /// Middleware inserting [`LockStatus`] for routes registered with
+    /// [`EditLocks::with_route`]
+    ///
+    /// Use with `axum::middleware::from_fn_with_state`. Store failures are
+    /// logged and reported as unlocked.
+    pub async fn middleware(
+        State(locks): State<Self>,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        if let Some(resource) = locks.resource_for(request.uri().path()) {
+            let lock = locks.status(&resource).await.unwrap_or_else(|e| {
+                warn!(error = %e, resource = %resource, "Lock lookup failed");
+                None
+            });
+            let holder = request
+                .extensions()
+                .get::<SessionData>()
+                .and_then(LockHolder::from_session);
+            request.extensions_mut().insert(LockStatus {
+                resource,
+                lock,
+                viewer: holder,
+            });
+        }
+        next.run(request).await
+    }
+
+    /// HTMX routes under `/locks/{resource}`: `renew`, `release` and `takeover`
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/locks/{resource}/renew", post(renew_handler))
+            .route("/locks/{resource}/release", post(release_handler))
+            .route("/locks/{resource}/takeover", post(takeover_handler))
+            .with_state(self.clone())
+    }
+
+    fn resource_for(&self, path: &str) -> Option<String> {
+        self.routes.iter().find_map(|(prefix, kind)| {
+            let id = path.strip_prefix(prefix.as_str())?.split('/').next()?;
+            (!id.is_empty()).then(|| format!("{kind}:{id}"))
+        })
+    }
+}
+
+/// Lock state of the current request's resource
+///
+/// Inserted by [`EditLocks::middleware`]; extracting it on a route that the
+/// middleware did not match yields an unlocked status with an empty resource.
+#[derive(Debug, Clone, Default)]
+pub struct LockStatus {
+    resource: String,
+    lock: Option<EditLock>,
+    viewer: Option<LockHolder>,
+}
+
+impl LockStatus {
+    /// Resource identifier (`doc:42`)
+    #[must_use]
+    pub fn resource(&self) -> &str {
+        &self.resource
+    }
+
+    /// Current lock, if any
+    #[must_use]
+    pub const fn lock(&self) -> Option<&EditLock> {
+        self.lock.as_ref()
+    }
+
+    /// The viewer as a lock holder, if authenticated
+    #[must_use]
+    pub const fn viewer(&self) -> Option<&LockHolder> {
+        self.viewer.as_ref()
+    }
+
+    /// Whether someone other than the viewer holds the lock
+    #[must_use]
+    pub fn is_locked_by_other(&self) -> bool {
+        match (&self.lock, &self.viewer) {
+            (Some(lock), Some(viewer)) => !lock.is_held_by(viewer),
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for LockStatus
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_default())
+    }
+}
+
+/// Banner partial describing a lock from `viewer`'s point of view
+///
+/// Shows "You are editing" for the holder; everyone else sees who holds the
+/// lock, when it expires, and a takeover button posting to
+/// `/locks/{resource}/takeover`.
+#[must_use]
+pub fn lock_banner(lock: &EditLock, viewer: &LockHolder) -> String {
+    let resource = escape_html(&lock.resource);
+    let expires = format_remaining(lock.expires_in());
+    let mut html =
+        format!(r#"<div id="lock-banner" class="lock-banner" data-resource="{resource}">"#);
+    if lock.is_held_by(viewer) {
+        let _ = write!(
+            html,
+            r##"<span>You are editing. Lock expires in {expires}.</span><button hx-post="/locks/{resource}/release" hx-target="#lock-banner" hx-swap="outerHTML">Stop editing</button>"##
+        );
+    } else {
+        let name = escape_html(lock.holder.display_name());
+        let _ = write!(
+            html,
+            r##"<span>Locked by {name}, expires in {expires}.</span><button hx-post="/locks/{resource}/takeover" hx-target="#lock-banner" hx-swap="outerHTML" hx-confirm="Take over editing from {name}? Their unsaved changes may be lost.">Take over</button>"##
+        );
+    }
+    html.push_str("</div>");
+    html
+}
+
+/// Human-readable remaining time (`45s`, `2m`, `1h 5m`)
+fn format_remaining(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3_599 => format!("{}m", secs.div_ceil(60)),
+        _ => format!("{}h {}m", secs / 3_600, (secs % 3_600) / 60),
+    }
+}
+
+fn holder(session: Option<&Extension<SessionData>>) -> Result<LockHolder, LockError> {
+    session
+        .and_then(|Extension(session)| LockHolder::from_session(session))
+        .ok_or(LockError::Unauthenticated)
+}
+
+async fn renew_handler(
+    State(locks): State<EditLocks>,
+    Path(resource): Path<String>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Response, LockError> {
+    let holder = holder(session.as_ref())?;
+    match locks.renew(&resource, &holder).await {
+        Ok(lock) => Ok(Html(lock_banner(&lock, &holder)).into_response()),
+        // Lost the lock: show who has it now so the editor can react
+        Err(LockError::NotHolder) => locks
+            .status(&resource)
+            .await?
+            .map_or(Err(LockError::NotHolder), |lock| {
+                Ok((StatusCode::CONFLICT, Html(lock_banner(&lock, &holder))).into_response())
+            }),
+        Err(e) => Err(e),
+    }
+}
+
+async fn release_handler(
+    State(locks): State<EditLocks>,
+    Path(resource): Path<String>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Response, LockError> {
+    let holder = holder(session.as_ref())?;
+    locks.release(&resource, &holder).await?;
+    Ok(Html(String::new()).into_response())
+}
+
+async fn takeover_handler(
+    State(locks): State<EditLocks>,
+    Path(resource): Path<String>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Response, LockError> {
+    let holder = holder(session.as_ref())?;
+    let lock = locks.take_over(&resource, &holder).await?;
+    Ok((
+        [("HX-Trigger", "lock-acquired")],
+        Html(lock_banner(&lock, &holder)),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware::from_fn_with_state, routing::get};
+    use tower::ServiceExt;
+
+    fn alice() -> LockHolder {
+        LockHolder::new("1", Some("Alice".into()))
+    }
+
+    fn bob() -> LockHolder {
+        LockHolder::new("2", Some("Bob".into()))
+    }
+
+    fn locks() -> EditLocks {
+        EditLocks::new(Arc::new(MemoryLockStore::new()))
+    }
+
+    #[tokio::test]
+    async fn test_acquire_renew_release() {
+        let locks = locks();
+        let lock = locks.acquire("doc:1", &alice()).await.unwrap();
+        assert!(lock.is_held_by(&alice()));
+
+        // Re-acquiring by the holder keeps the original acquisition time
+        let again = locks.acquire("doc:1", &alice()).await.unwrap();
+        assert_eq!(again.acquired_at, lock.acquired_at);
+
+        match locks.acquire("doc:1", &bob()).await {
+            Err(LockError::Held(existing)) => assert_eq!(existing.holder, alice()),
+            other => panic!("expected Held, got {other:?}"),
+        }
+        assert!(matches!(
+            locks.renew("doc:1", &bob()).await,
+            Err(LockError::NotHolder)
+        ));
+        assert!(locks.renew("doc:1", &alice()).await.is_ok());
+
+        locks.release("doc:1", &alice()).await.unwrap();
+        assert!(locks.acquire("doc:1", &bob()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_expired_lock_is_free_and_takeover() {
+        let locks = locks().with_ttl(Duration::ZERO);
+        locks.acquire("doc:1", &alice()).await.unwrap();
+        assert!(locks.status("doc:1").await.unwrap().is_none());
+
+        let locks = locks.with_ttl(DEFAULT_LOCK_TTL);
+        locks.acquire("doc:2", &alice()).await.unwrap();
+        let lock = locks.take_over("doc:2", &bob()).await.unwrap();
+        assert!(lock.is_held_by(&bob()));
+        assert!(matches!(
+            locks.release("doc:2", &alice()).await,
+            Err(LockError::NotHolder)
+        ));
+    }
+
+    #[test]
+    fn test_lock_banner() {
+        let lock = EditLock::new(
+            "doc:1",
+            &LockHolder::new("1", Some("<Alice>".into())),
+            Duration::from_secs(120),
+        );
+        let html = lock_banner(&lock, &bob());
+        assert!(html.contains("Locked by &lt;Alice&gt;, expires in 2m."));
+        assert!(html.contains(r#"hx-post="/locks/doc:1/takeover""#));
+
+        let html = lock_banner(&lock, &LockHolder::new("1", None));
+        assert!(html.contains("You are editing"));
+        assert!(!html.contains("takeover"));
+    }
+
+    #[tokio::test]
+    async fn test_middleware_exposes_status() {
+        let locks = locks().with_route("/docs/", "doc");
+        locks.acquire("doc:42", &alice()).await.unwrap();
+
+        let mut session = SessionData::new();
+        session.user_id = Some(2);
+        let app = Router::new()
+            .route(
+                "/docs/{id}/edit",
+                get(|status: LockStatus| async move {
+                    format!("{} {}", status.resource(), status.is_locked_by_other())
+                }),
+            )
+            .layer(from_fn_with_state(locks, EditLocks::middleware))
+            .layer(Extension(session));
+
+        let response = app
+            .oneshot(Request::get("/docs/42/edit").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"doc:42 true");
+    }
+}
@@ -0,0 +1,303 @@
+//! Markdown rendering with sanitization
+//!
+//! Renders CommonMark (with GitHub extensions) through `comrak`, highlights
+//! fenced code blocks with `syntect`, and passes the result through a strict
+//! `ammonia` allowlist so user-generated markdown is safe to embed:
+//!
+//! - Raw HTML in the source is dropped, and the output is sanitized again
+//! - Links get `rel="nofollow noopener noreferrer ugc"` by default
+//! - Only `http`, `https` and `mailto` URLs survive
+//! - `class` attributes are limited to highlighting (`hl-*`) and
+//!   `language-*` classes
+//!
+//! Stored content is usually rendered many more times than it is edited, so
+//! [`MarkdownRenderer::render_cached`] memoizes output by content hash.
+//!
+//! # Templates
+//!
+//! Bring the filter into scope next to the template struct:
+//!
+//! ```rust,ignore
+//! mod filters {
+//!     pub use acton_htmx::markdown::filters::*;
+//! }
+//!
+//! #[derive(askama::Template)]
+//! #[template(source = "<article>{{ post.body|markdown }}</article>", ext = "html")]
+//! struct PostTemplate { post: Post }
+//! ```
+//!
+//! Highlighting uses CSS classes (`hl-keyword`, ...); generate a stylesheet
+//! with syntect's `css_for_theme_with_class_style` or ship your own.
+
+use comrak::options::Plugins;
+use comrak::plugins::syntect::{SyntectAdapter, SyntectAdapterBuilder};
+use comrak::{markdown_to_html_with_plugins, Options};
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, OnceLock};
+
+/// CSS class prefix for syntax highlighting spans
+pub const HIGHLIGHT_CLASS_PREFIX: &str = "hl-";
+
+/// Default number of cached renders
+const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// Markdown renderer with sanitizer, highlighter and render cache
+pub struct MarkdownRenderer {
+    options: Options<'static>,
+    highlighter: Option<SyntectAdapter>,
+    sanitizer: ammonia::Builder<'static>,
+    cache: Mutex<RenderCache>,
+}
+
+impl std::fmt::Debug for MarkdownRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MarkdownRenderer")
+            .field("highlighting", &self.highlighter.is_some())
+            .field("cache_capacity", &self.cache.lock().capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for MarkdownRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MarkdownRenderer {
+    /// Create a renderer with highlighting, nofollow links and a 1024 entry cache
+    #[must_use]
+    pub fn new() -> Self {
+        let mut options = Options::default();
+        options.extension.strikethrough = true;
+        options.extension.table = true;
+        options.extension.autolink = true;
+        options.extension.footnotes = true;
+        options.render.r#unsafe = false;
+
+        Self {
+            options,
+            highlighter: Some(
+                SyntectAdapterBuilder::new()
+                    .css_with_class_prefix(HIGHLIGHT_CLASS_PREFIX)
+                    .build(),
+            ),
+            sanitizer: sanitizer(true),
+            cache: Mutex::new(RenderCache::new(DEFAULT_CACHE_CAPACITY)),
+        }
+    }
+
+    /// Shared renderer used by the template filter
+    pub fn global() -> &'static Self {
+        static RENDERER: OnceLock<MarkdownRenderer> = OnceLock::new();
+        RENDERER.get_or_init(Self::new)
+    }
+
+    /// Enable or disable code block syntax highlighting
+    #[must_use]
+    pub fn with_syntax_highlighting(mut self, enabled: bool) -> Self {
+        self.highlighter = enabled.then(|| {
+            SyntectAdapterBuilder::new()
+                .css_with_class_prefix(HIGHLIGHT_CLASS_PREFIX)
+                .build()
+        });
+        self
+    }
+
+    /// Add `nofollow ugc` to links (on by default, disable for trusted authors)
+    #[must_use]
+    pub fn with_nofollow(mut self, nofollow: bool) -> Self {
+        self.sanitizer = sanitizer(nofollow);
+        self
+    }
+
+    /// Set the number of renders kept in the cache (0 disables caching)
+    #[must_use]
+    pub fn with_cache_capacity(self, capacity: usize) -> Self {
+        *self.cache.lock() = RenderCache::new(capacity);
+        self
+    }
+
+    /// Render markdown to sanitized HTML
+    #[must_use]
+    pub fn render(&self, source: &str) -> String {
+        let mut plugins = Plugins::default();
+        if let Some(highlighter) = &self.highlighter {
+            plugins.render.codefence_syntax_highlighter = Some(highlighter);
+        }
+        let html = markdown_to_html_with_plugins(source, &self.options, &plugins);
+        self.sanitizer.clean(&html).to_string()
+    }
+
+    /// Render markdown, reusing a previous render of identical content
+    #[must_use]
+    pub fn render_cached(&self, source: &str) -> Arc<str> {
+        let key: [u8; 32] = Sha256::digest(source.as_bytes()).into();
+        let cached = self.cache.lock().get(&key);
+        if let Some(html) = cached {
+            return html;
+        }
+
+        let html: Arc<str> = self.render(source).into();
+        self.cache.lock().insert(key, Arc::clone(&html));
+        html
+    }
+
+    /// Drop all cached renders
+    pub fn clear_cache(&self) {
+        self.cache.lock().clear();
+    }
+}
+
+/// Build the HTML allowlist applied to rendered markdown
+fn sanitizer(nofollow: bool) -> ammonia::Builder<'static> {
+    let mut builder = ammonia::Builder::default();
+    builder
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .link_rel(Some(if nofollow {
+            "nofollow noopener noreferrer ugc"
+        } else {
+            "noopener noreferrer"
+        }))
+        .add_tag_attributes("pre", &["class"])
+        .add_tag_attributes("code", &["class"])
+        .add_tag_attributes("span", &["class"])
+        .attribute_filter(|_element, attribute, value| {
+            if attribute != "class" {
+                return Some(value.into());
+            }
+            let allowed = value.split_whitespace().all(|class| {
+                class.starts_with(HIGHLIGHT_CLASS_PREFIX) || class.starts_with("language-")
+            });
+            allowed.then(|| value.into())
+        });
+    builder
+}
+
+/// Bounded render cache with FIFO eviction
+struct RenderCache {
+    capacity: usize,
+    entries: HashMap<[u8; 32], Arc<str>>,
+    order: VecDeque<[u8; 32]>,
+}
+
+impl RenderCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, key: &[u8; 32]) -> Option<Arc<str>> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: [u8; 32], html: Arc<str>) {
+        if self.capacity == 0 || self.entries.contains_key(&key) {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.entries.insert(key, html);
+        self.order.push_back(key);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// Askama filters
+///
+/// Re-export from a `filters` module next to your template structs. The
+/// filter never fails; the `Result` is part of Askama's filter signature.
+#[allow(clippy::unnecessary_wraps, clippy::missing_errors_doc)]
+pub mod filters {
+    use super::MarkdownRenderer;
+    use askama::filters::Safe;
+    use std::fmt::Display;
+
+    /// Render markdown to sanitized HTML (`{{ body|markdown }}`)
+    ///
+    /// Uses the cached render path of the shared [`MarkdownRenderer`].
+    pub fn markdown<T: Display>(
+        source: T,
+        _values: &dyn askama::Values,
+    ) -> askama::Result<Safe<String>> {
+        let html = MarkdownRenderer::global().render_cached(&source.to_string());
+        Ok(Safe(html.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renders_gfm() {
+        let html =
+            MarkdownRenderer::new().render("# Title\n\n~~old~~ **new**\n\n| a |\n|---|\n| 1 |\n");
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("<del>old</del>"));
+        assert!(html.contains("<table>"));
+    }
+
+    #[test]
+    fn test_strips_raw_html_and_dangerous_links() {
+        let renderer = MarkdownRenderer::new();
+        let html = renderer.render(
+            "<script>alert(1)</script>\n\n<img src=x onerror=alert(1)>\n\n[click](javascript:alert(1))",
+        );
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("onerror"));
+        assert!(!html.contains("javascript:"));
+    }
+
+    #[test]
+    fn test_links_get_nofollow() {
+        let html = MarkdownRenderer::new().render("[site](https://example.com)");
+        assert!(html.contains(r#"rel="nofollow noopener noreferrer ugc""#));
+
+        let html = MarkdownRenderer::new()
+            .with_nofollow(false)
+            .render("[site](https://example.com)");
+        assert!(html.contains(r#"rel="noopener noreferrer""#));
+    }
+
+    #[test]
+    fn test_code_blocks_highlighted_with_prefixed_classes() {
+        let html = MarkdownRenderer::new().render("```rust\nfn main() {}\n```\n");
+        assert!(html.contains("class=\"hl-"));
+
+        let plain = MarkdownRenderer::new()
+            .with_syntax_highlighting(false)
+            .render("```rust\nfn main() {}\n```\n");
+        assert!(plain.contains(r#"<code class="language-rust">"#));
+    }
+
+    #[test]
+    fn test_render_cache_reuses_and_evicts() {
+        let renderer = MarkdownRenderer::new().with_cache_capacity(1);
+        let first = renderer.render_cached("*a*");
+        assert!(Arc::ptr_eq(&first, &renderer.render_cached("*a*")));
+
+        let _ = renderer.render_cached("*b*");
+        assert!(!Arc::ptr_eq(&first, &renderer.render_cached("*a*")));
+    }
+
+    #[test]
+    fn test_askama_filter() {
+        let html = filters::markdown("**hi**", askama::NO_VALUES).unwrap();
+        assert_eq!(html.0.trim(), "<p><strong>hi</strong></p>");
+    }
+}
@@ -26,19 +26,16 @@
 use axum::{
     body::Body,
     extract::{MatchedPath, Request, State},
-    http::{HeaderMap, Method, StatusCode},
+    http::{Method, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 
 #[cfg(feature = "cedar")]
 use cedar_policy::{
-    Authorizer, Context, Decision, Entities, EntityUid, PolicySet, Request as CedarRequest,
+    Authorizer, Decision, Entities, EntityUid, PolicySet, Request as CedarRequest,
 };
 
-#[cfg(feature = "cedar")]
-use chrono::{Datelike, Timelike};
-
 #[cfg(feature = "cedar")]
 use serde_json::json;
 
@@ -49,7 +46,10 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 #[cfg(feature = "cedar")]
-use crate::htmx::{auth::user::User, config::{CedarConfig, FailureMode}};
+use crate::htmx::{auth::{session::SessionData, user::User}, config::{CedarConfig, FailureMode}};
+
+#[cfg(feature = "cedar")]
+use super::cedar_context::{CedarContextConfig, CedarRequestContext};
 
 #[cfg(feature = "cedar")]
 use thiserror::Error;
@@ -343,7 +343,23 @@ impl CedarAuthz {
         // Build Cedar authorization request
         let principal = build_principal(user)?;
         let action = build_action_http(&method, &request, authz.path_normalizer)?;
-        let context = build_context_http(request.headers(), user)?;
+        // Reuse the enriched context from CedarContextLayer when installed
+        let context = request
+            .extensions()
+            .get::<CedarRequestContext>()
+            .map_or_else(
+                || {
+                    CedarRequestContext::from_request(
+                        request.headers(),
+                        request.extensions().get::<SessionData>(),
+                        Some(user),
+                        super::forwarded::client_ip(request.extensions()),
+                        &CedarContextConfig::default(),
+                    )
+                },
+                Clone::clone,
+            )
+            .to_context()?;
 
         // Build resource (generic default for now)
         let resource = build_resource()?;
@@ -476,12 +492,38 @@ impl CedarAuthz {
     ///     // User can update this specific post
     /// }
     /// ```
-    #[allow(clippy::cognitive_complexity)] // Multiple Cedar request building steps
     pub async fn can_perform(
         &self,
         user: &User,
         action: &str,
         #[allow(unused_variables)] resource_id: Option<i64>,
+    ) -> bool {
+        self.can_perform_with_context(user, action, &CedarRequestContext::for_user(user))
+            .await
+    }
+
+    /// Check if a user can perform an action using a shared request context
+    ///
+    /// Prefer this over [`CedarAuthz::can_perform`] inside request handlers so the
+    /// check sees the same tenant, MFA status, and IP reputation as the
+    /// middleware. Extract the context with the [`CedarRequestContext`] extractor
+    /// (requires [`CedarContextLayer`](super::cedar_context::CedarContextLayer)).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// async fn handler(ctx: CedarRequestContext, /* ... */) {
+    ///     if cedar.can_perform_with_context(&user, "DELETE /posts/{id}", &ctx).await {
+    ///         // User can delete posts
+    ///     }
+    /// }
+    /// ```
+    #[allow(clippy::cognitive_complexity)] // Multiple Cedar request building steps
+    pub async fn can_perform_with_context(
+        &self,
+        user: &User,
+        action: &str,
+        request_context: &CedarRequestContext,
     ) -> bool {
         // If Cedar is disabled, allow all actions
         if !self.config.enabled {
@@ -515,7 +557,7 @@ impl CedarAuthz {
         };
 
         // Build context with user attributes
-        let context = match build_context_for_user(user) {
+        let context = match request_context.to_context() {
             Ok(c) => c,
             Err(e) => {
                 tracing::error!(error = ?e, "Failed to build context for can_perform");
@@ -720,80 +762,6 @@ fn normalize_path_generic(path: &str) -> String {
     path.to_string()
 }
 
-/// Build Cedar context from HTTP headers and user
-#[cfg(feature = "cedar")]
-fn build_context_http(headers: &HeaderMap, user: &User) -> Result<Context, CedarError> {
-    let mut context_map = serde_json::Map::new();
-
-    // Add user roles
-    context_map.insert("roles".to_string(), json!(user.roles));
-
-    // Add user permissions
-    context_map.insert("permissions".to_string(), json!(user.permissions));
-
-    // Add user email
-    context_map.insert("email".to_string(), json!(user.email.as_str()));
-
-    // Add user ID
-    context_map.insert("user_id".to_string(), json!(user.id));
-
-    // Add email verification status
-    context_map.insert("verified".to_string(), json!(user.email_verified));
-
-    // Add timestamp
-    let now = chrono::Utc::now();
-    context_map.insert(
-        "timestamp".to_string(),
-        json!({
-            "unix": now.timestamp(),
-            "hour": now.hour(),
-            "dayOfWeek": now.weekday().to_string(),
-        }),
-    );
-
-    // Add IP address (from X-Forwarded-For or X-Real-IP)
-    if let Some(ip) = extract_client_ip(headers) {
-        context_map.insert("ip".to_string(), json!(ip));
-    }
-
-    // Add request ID if present
-    if let Some(request_id) = headers
-        .get("x-request-id")
-        .and_then(|v| v.to_str().ok())
-    {
-        context_map.insert("requestId".to_string(), json!(request_id));
-    }
-
-    // Add user-agent if present
-    if let Some(user_agent) = headers.get("user-agent").and_then(|v| v.to_str().ok()) {
-        context_map.insert("userAgent".to_string(), json!(user_agent));
-    }
-
-    Context::from_json_value(serde_json::Value::Object(context_map), None)
-        .map_err(|e| CedarError::Internal(format!("Failed to build context: {e}")))
-}
-
-/// Extract client IP from headers
-#[cfg(feature = "cedar")]
-fn extract_client_ip(headers: &HeaderMap) -> Option<String> {
-    // Try X-Forwarded-For header first (for proxied requests)
-    if let Some(xff) = headers.get("x-forwarded-for") {
-        if let Ok(xff_str) = xff.to_str() {
-            // Take first IP in comma-separated list
-            return xff_str.split(',').next().map(|s| s.trim().to_string());
-        }
-    }
-
-    // Try X-Real-IP header
-    if let Some(xri) = headers.get("x-real-ip") {
-        if let Ok(xri_str) = xri.to_str() {
-            return Some(xri_str.to_string());
-        }
-    }
-
-    None
-}
-
 /// Build entity hierarchy from user
 ///
 /// Creates the principal entity (User) with roles and permissions.
@@ -801,8 +769,14 @@ fn extract_client_ip(headers: &HeaderMap) -> Option<String> {
 fn build_entities(user: &User) -> Result<Entities, CedarError> {
     use serde_json::Value;
 
-    // Create principal entity (User) with attributes
-    let entity = json!({
+    Entities::from_json_value(Value::Array(vec![user_entity_json(user)]), None)
+        .map_err(|e| CedarError::Internal(format!("Failed to build entities: {e}")))
+}
+
+/// Cedar JSON of the principal entity (User) for a user
+#[cfg(feature = "cedar")]
+pub(crate) fn user_entity_json(user: &User) -> serde_json::Value {
+    json!({
         "uid": {
             "type": "User",
             "id": user.id.to_string()
@@ -815,10 +789,7 @@ fn build_entities(user: &User) -> Result<Entities, CedarError> {
             "verified": user.email_verified,
         },
         "parents": []
-    });
-
-    Entities::from_json_value(Value::Array(vec![entity]), None)
-        .map_err(|e| CedarError::Internal(format!("Failed to build entities: {e}")))
+    })
 }
 
 /// Parse action string into Cedar EntityUid
@@ -832,44 +803,6 @@ fn parse_action_string(action: &str) -> Result<EntityUid, CedarError> {
         .map_err(|e| CedarError::Internal(format!("Failed to parse action '{action}': {e}")))
 }
 
-/// Build Cedar context from user alone (for programmatic checks)
-///
-/// Similar to `build_context_http` but without HTTP headers.
-/// Used by `can_perform` and template helpers.
-#[cfg(feature = "cedar")]
-fn build_context_for_user(user: &User) -> Result<Context, CedarError> {
-    let mut context_map = serde_json::Map::new();
-
-    // Add user roles
-    context_map.insert("roles".to_string(), json!(user.roles));
-
-    // Add user permissions
-    context_map.insert("permissions".to_string(), json!(user.permissions));
-
-    // Add user email
-    context_map.insert("email".to_string(), json!(user.email.as_str()));
-
-    // Add user ID
-    context_map.insert("user_id".to_string(), json!(user.id));
-
-    // Add email verification status
-    context_map.insert("verified".to_string(), json!(user.email_verified));
-
-    // Add timestamp (current time for programmatic checks)
-    let now = chrono::Utc::now();
-    context_map.insert(
-        "timestamp".to_string(),
-        json!({
-            "unix": now.timestamp(),
-            "hour": now.hour(),
-            "dayOfWeek": now.weekday().to_string(),
-        }),
-    );
-
-    Context::from_json_value(serde_json::Value::Object(context_map), None)
-        .map_err(|e| CedarError::Internal(format!("Failed to build context for user: {e}")))
-}
-
 #[cfg(test)]
 #[cfg(feature = "cedar")]
 mod tests {
@@ -919,6 +852,8 @@ mod tests {
             roles: vec!["user".to_string()],
             permissions: vec![],
             email_verified: true,
+            timezone: None,
+            active: true,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -930,25 +865,6 @@ mod tests {
         assert_eq!(principal.to_string(), r#"User::"123""#);
     }
 
-    #[test]
-    fn test_build_context_for_user() {
-        use crate::htmx::auth::user::EmailAddress;
-
-        let user = User {
-            id: 123,
-            email: EmailAddress::parse("test@example.com").unwrap(),
-            password_hash: "hash".to_string(),
-            roles: vec!["user".to_string(), "moderator".to_string()],
-            permissions: vec!["read:posts".to_string()],
-            email_verified: true,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-        };
-
-        let context = build_context_for_user(&user);
-        assert!(context.is_ok());
-    }
-
     #[test]
     fn test_build_entities() {
         use crate::htmx::auth::user::EmailAddress;
@@ -960,6 +876,8 @@ mod tests {
             roles: vec!["user".to_string(), "admin".to_string()],
             permissions: vec!["write:posts".to_string()],
             email_verified: true,
+            timezone: None,
+            active: true,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
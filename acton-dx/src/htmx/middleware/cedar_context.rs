@@ -0,0 +1,524 @@
+//! Session-bound Cedar context enrichment
+//!
+//! Builds a single [`CedarRequestContext`] per request from the session, the
+//! authenticated user, and request headers, then stores it in the request
+//! extensions. [`CedarAuthz`](super::cedar::CedarAuthz) and any handler-level
+//! authorization checks reuse this context, so every policy evaluation for a
+//! request sees the same roles, tenant, MFA status, and IP reputation.
+//!
+//! # Example Usage
+//!
+//! ```rust,ignore
+//! use acton_htmx::middleware::cedar_context::{CedarContextConfig, CedarContextLayer};
+//!
+//! let app = Router::new()
+//!     .route("/posts/:id", put(update_post))
+//!     .layer(axum::middleware::from_fn_with_state(cedar.clone(), CedarAuthz::middleware))
+//!     .layer(CedarContextLayer::new(CedarContextConfig::default()))
+//!     .layer(SessionLayer::new(&state));
+//! ```
+//!
+//! Handlers can extract the same context for programmatic checks:
+//!
+//! ```rust,ignore
+//! async fn update_post(
+//!     State(cedar): State<CedarAuthz>,
+//!     Authenticated(user): Authenticated<User>,
+//!     ctx: CedarRequestContext,
+//! ) -> impl IntoResponse {
+//!     if cedar.can_perform_with_context(&user, "PUT /posts/{id}", &ctx).await {
+//!         // ...
+//!     }
+//! }
+//! ```
+
+use axum::{
+    body::Body,
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderMap, Request, Response, StatusCode},
+};
+use cedar_policy::Context;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use super::cedar::CedarError;
+use super::forwarded::client_ip;
+use crate::htmx::auth::{session::SessionData, user::User};
+
+/// Default session key holding the active tenant identifier
+pub const TENANT_SESSION_KEY: &str = "tenant_id";
+
+/// Default session key holding the MFA verification flag
+pub const MFA_SESSION_KEY: &str = "mfa_verified";
+
+/// Reputation classification for the client IP address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpReputation {
+    /// Known-good address (office network, VPN egress)
+    Trusted,
+    /// No signal either way
+    #[default]
+    Unknown,
+    /// Address flagged by abuse heuristics
+    Suspicious,
+    /// Address on a deny list
+    Blocked,
+}
+
+impl IpReputation {
+    /// Get the reputation as the string exposed to Cedar policies
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Trusted => "trusted",
+            Self::Unknown => "unknown",
+            Self::Suspicious => "suspicious",
+            Self::Blocked => "blocked",
+        }
+    }
+}
+
+/// Source of IP reputation data
+///
+/// Implementations must be cheap and synchronous; they run once per request.
+/// Back them with an in-memory list that is refreshed out of band.
+pub trait IpReputationProvider: Send + Sync {
+    /// Classify the given client IP address
+    fn reputation(&self, ip: &str) -> IpReputation;
+}
+
+/// Configuration for [`CedarContextLayer`]
+#[derive(Clone)]
+pub struct CedarContextConfig {
+    /// Session key holding the tenant identifier
+    pub tenant_key: String,
+
+    /// Session key holding the MFA verification flag
+    pub mfa_key: String,
+
+    /// Optional IP reputation provider
+    pub ip_reputation: Option<Arc<dyn IpReputationProvider>>,
+}
+
+impl std::fmt::Debug for CedarContextConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CedarContextConfig")
+            .field("tenant_key", &self.tenant_key)
+            .field("mfa_key", &self.mfa_key)
+            .field("ip_reputation", &self.ip_reputation.is_some())
+            .finish()
+    }
+}
+
+impl Default for CedarContextConfig {
+    fn default() -> Self {
+        Self {
+            tenant_key: TENANT_SESSION_KEY.to_string(),
+            mfa_key: MFA_SESSION_KEY.to_string(),
+            ip_reputation: None,
+        }
+    }
+}
+
+impl CedarContextConfig {
+    /// Use a custom session key for the tenant identifier
+    #[must_use]
+    pub fn with_tenant_key(mut self, key: impl Into<String>) -> Self {
+        self.tenant_key = key.into();
+        self
+    }
+
+    /// Use a custom session key for the MFA flag
+    #[must_use]
+    pub fn with_mfa_key(mut self, key: impl Into<String>) -> Self {
+        self.mfa_key = key.into();
+        self
+    }
+
+    /// Attach an IP reputation provider
+    #[must_use]
+    pub fn with_ip_reputation(mut self, provider: Arc<dyn IpReputationProvider>) -> Self {
+        self.ip_reputation = Some(provider);
+        self
+    }
+}
+
+/// Per-request Cedar context shared by all authorization calls
+///
+/// Built once by [`CedarContextLayer`] and stored in request extensions.
+/// When the layer is not installed, [`CedarRequestContext::for_user`] produces
+/// the same shape from the user alone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CedarRequestContext {
+    /// Authenticated user ID
+    pub user_id: Option<i64>,
+
+    /// User email address
+    pub email: Option<String>,
+
+    /// User roles
+    pub roles: Vec<String>,
+
+    /// User permissions
+    pub permissions: Vec<String>,
+
+    /// Email verification status
+    pub verified: bool,
+
+    /// Active tenant identifier (from session)
+    pub tenant: Option<String>,
+
+    /// Whether the session completed multi-factor authentication
+    pub mfa_verified: bool,
+
+    /// Client IP address
+    pub ip: Option<String>,
+
+    /// Client IP reputation
+    pub ip_reputation: IpReputation,
+
+    /// Request ID header value
+    pub request_id: Option<String>,
+
+    /// User-Agent header value
+    pub user_agent: Option<String>,
+
+    /// Time the context was built
+    pub timestamp: DateTime<Utc>,
+}
+
+impl CedarRequestContext {
+    /// Build a context from the user alone (no session or HTTP data)
+    ///
+    /// Used for programmatic checks outside of a request.
+    #[must_use]
+    pub fn for_user(user: &User) -> Self {
+        Self {
+            user_id: Some(user.id),
+            email: Some(user.email.as_str().to_string()),
+            roles: user.roles.clone(),
+            permissions: user.permissions.clone(),
+            verified: user.email_verified,
+            tenant: None,
+            mfa_verified: false,
+            ip: None,
+            ip_reputation: IpReputation::Unknown,
+            request_id: None,
+            user_agent: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Build a context from request data
+    ///
+    /// The user supplies roles and identity, the session supplies tenant and
+    /// MFA status, and headers supply request ID and user agent. The client
+    /// IP comes from [`client_ip`], which only trusts forwarding headers sent
+    /// by configured proxies.
+    #[must_use]
+    pub fn from_request(
+        headers: &HeaderMap,
+        session: Option<&SessionData>,
+        user: Option<&User>,
+        client_ip: Option<IpAddr>,
+        config: &CedarContextConfig,
+    ) -> Self {
+        let mut context = Self {
+            user_id: session.and_then(|s| s.user_id),
+            email: None,
+            roles: Vec::new(),
+            permissions: Vec::new(),
+            verified: false,
+            tenant: None,
+            mfa_verified: false,
+            ip: None,
+            ip_reputation: IpReputation::Unknown,
+            request_id: None,
+            user_agent: None,
+            timestamp: Utc::now(),
+        };
+
+        if let Some(user) = user {
+            context = Self {
+                timestamp: context.timestamp,
+                ..Self::for_user(user)
+            };
+        }
+
+        if let Some(session) = session {
+            context.tenant = session.get::<serde_json::Value>(&config.tenant_key).and_then(
+                |value| match value {
+                    serde_json::Value::String(s) => Some(s),
+                    serde_json::Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                },
+            );
+            context.mfa_verified = session.get::<bool>(&config.mfa_key).unwrap_or(false);
+        }
+
+        context.ip = client_ip.map(|ip| ip.to_string());
+        if let (Some(ip), Some(provider)) = (&context.ip, &config.ip_reputation) {
+            context.ip_reputation = provider.reputation(ip);
+        }
+
+        context.request_id = header_string(headers, "x-request-id");
+        context.user_agent = header_string(headers, "user-agent");
+
+        context
+    }
+
+    /// Render the context as the JSON object passed to Cedar
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut map = serde_json::Map::new();
+
+        map.insert("roles".to_string(), json!(self.roles));
+        map.insert("permissions".to_string(), json!(self.permissions));
+        map.insert("verified".to_string(), json!(self.verified));
+        map.insert("mfa".to_string(), json!(self.mfa_verified));
+        map.insert(
+            "ipReputation".to_string(),
+            json!(self.ip_reputation.as_str()),
+        );
+        map.insert(
+            "timestamp".to_string(),
+            json!({
+                "unix": self.timestamp.timestamp(),
+                "hour": self.timestamp.hour(),
+                "dayOfWeek": self.timestamp.weekday().to_string(),
+            }),
+        );
+
+        // Cedar has no null type, so optional attributes are omitted when absent
+        if let Some(user_id) = self.user_id {
+            map.insert("user_id".to_string(), json!(user_id));
+        }
+        if let Some(email) = &self.email {
+            map.insert("email".to_string(), json!(email));
+        }
+        if let Some(tenant) = &self.tenant {
+            map.insert("tenant".to_string(), json!(tenant));
+        }
+        if let Some(ip) = &self.ip {
+            map.insert("ip".to_string(), json!(ip));
+        }
+        if let Some(request_id) = &self.request_id {
+            map.insert("requestId".to_string(), json!(request_id));
+        }
+        if let Some(user_agent) = &self.user_agent {
+            map.insert("userAgent".to_string(), json!(user_agent));
+        }
+
+        serde_json::Value::Object(map)
+    }
+
+    /// Convert into a Cedar [`Context`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CedarError::Internal`] if Cedar rejects the context JSON.
+    pub fn to_context(&self) -> Result<Context, CedarError> {
+        Context::from_json_value(self.to_json(), None)
+            .map_err(|e| CedarError::Internal(format!("Failed to build context: {e}")))
+    }
+}
+
+impl<S> FromRequestParts<S> for CedarRequestContext
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Self>().cloned().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Cedar context not initialized",
+        ))
+    }
+}
+
+fn header_string(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string)
+}
+
+/// Cedar context enrichment layer
+///
+/// Must run after the session middleware (so `SessionData` is available) and
+/// before `CedarAuthz::middleware`.
+#[derive(Clone, Debug, Default)]
+pub struct CedarContextLayer {
+    config: CedarContextConfig,
+}
+
+impl CedarContextLayer {
+    /// Create a new context enrichment layer
+    #[must_use]
+    pub const fn new(config: CedarContextConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl<S> tower::Layer<S> for CedarContextLayer {
+    type Service = CedarContextMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CedarContextMiddleware {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Cedar context enrichment middleware service
+#[derive(Clone, Debug)]
+pub struct CedarContextMiddleware<S> {
+    inner: S,
+    config: CedarContextConfig,
+}
+
+impl<S> tower::Service<Request<Body>> for CedarContextMiddleware<S>
+where
+    S: tower::Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let context = CedarRequestContext::from_request(
+            request.headers(),
+            request.extensions().get::<SessionData>(),
+            request.extensions().get::<User>(),
+            client_ip(request.extensions()),
+            &self.config,
+        );
+        request.extensions_mut().insert(context);
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::auth::user::EmailAddress;
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    fn test_user() -> User {
+        User {
+            id: 42,
+            email: EmailAddress::parse("test@example.com").unwrap(),
+            password_hash: "hash".to_string(),
+            roles: vec!["admin".to_string()],
+            permissions: vec!["write:posts".to_string()],
+            email_verified: true,
+            timezone: None,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    struct DenyAll;
+
+    impl IpReputationProvider for DenyAll {
+        fn reputation(&self, _ip: &str) -> IpReputation {
+            IpReputation::Blocked
+        }
+    }
+
+    #[test]
+    fn test_for_user_context() {
+        let ctx = CedarRequestContext::for_user(&test_user());
+        assert_eq!(ctx.user_id, Some(42));
+        assert_eq!(ctx.roles, vec!["admin"]);
+        assert!(!ctx.mfa_verified);
+        assert!(ctx.tenant.is_none());
+        assert!(ctx.to_context().is_ok());
+    }
+
+    #[test]
+    fn test_from_request_reads_session_and_headers() {
+        let mut session = SessionData::new();
+        session.set(TENANT_SESSION_KEY.to_string(), "acme").unwrap();
+        session.set(MFA_SESSION_KEY.to_string(), true).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "198.51.100.1".parse().unwrap());
+        headers.insert("x-request-id", "req-1".parse().unwrap());
+
+        let config = CedarContextConfig::default().with_ip_reputation(Arc::new(DenyAll));
+        let user = test_user();
+        let ctx = CedarRequestContext::from_request(
+            &headers,
+            Some(&session),
+            Some(&user),
+            Some("203.0.113.7".parse().unwrap()),
+            &config,
+        );
+
+        assert_eq!(ctx.tenant.as_deref(), Some("acme"));
+        assert!(ctx.mfa_verified);
+        assert_eq!(ctx.ip.as_deref(), Some("203.0.113.7"));
+        assert_eq!(ctx.ip_reputation, IpReputation::Blocked);
+        assert_eq!(ctx.request_id.as_deref(), Some("req-1"));
+
+        let json = ctx.to_json();
+        assert_eq!(json["tenant"], "acme");
+        assert_eq!(json["mfa"], true);
+        assert_eq!(json["ipReputation"], "blocked");
+    }
+
+    #[test]
+    fn test_numeric_tenant_and_custom_keys() {
+        let mut session = SessionData::new();
+        session.set("org".to_string(), 7).unwrap();
+
+        let config = CedarContextConfig::default().with_tenant_key("org");
+        let ctx = CedarRequestContext::from_request(&HeaderMap::new(), Some(&session), None, None, &config);
+
+        assert_eq!(ctx.tenant.as_deref(), Some("7"));
+        assert!(ctx.user_id.is_none());
+        assert!(ctx.to_json().get("user_id").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_layer_inserts_context() {
+        async fn handler(ctx: CedarRequestContext) -> String {
+            ctx.user_agent.unwrap_or_default()
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(CedarContextLayer::default());
+
+        let request = Request::builder()
+            .uri("/")
+            .header("user-agent", "test-agent")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"test-agent");
+    }
+}
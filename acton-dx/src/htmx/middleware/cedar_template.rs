@@ -53,6 +53,9 @@ use std::collections::HashMap;
 #[cfg(feature = "cedar")]
 use super::cedar::CedarAuthz;
 
+#[cfg(feature = "cedar")]
+use super::cedar_context::CedarRequestContext;
+
 #[cfg(feature = "cedar")]
 use crate::htmx::auth::user::User;
 
@@ -103,6 +106,7 @@ pub struct AuthzContextBuilder<'a> {
     create_path: Option<String>,
     read_path: Option<String>,
     custom_checks: Vec<(String, String)>, // (name, action)
+    request_context: Option<&'a CedarRequestContext>,
 }
 
 #[cfg(feature = "cedar")]
@@ -117,9 +121,20 @@ impl<'a> AuthzContextBuilder<'a> {
             create_path: None,
             read_path: None,
             custom_checks: Vec::new(),
+            request_context: None,
         }
     }
 
+    /// Evaluate all checks against the shared per-request Cedar context
+    ///
+    /// Without this, checks use a context built from the user alone and
+    /// policies depending on tenant, MFA, or IP attributes will not match.
+    #[must_use]
+    pub const fn with_request_context(mut self, context: &'a CedarRequestContext) -> Self {
+        self.request_context = Some(context);
+        self
+    }
+
     /// Check if user can update the resource at the given path
     #[must_use]
     pub fn can_update(mut self, path: impl Into<String>) -> Self {
@@ -174,35 +189,52 @@ impl<'a> AuthzContextBuilder<'a> {
     /// Build the AuthzContext by evaluating all authorization checks
     pub async fn build(self) -> AuthzContext {
         let mut context = AuthzContext::default();
+        let owned_context;
+        let request_context = if let Some(ctx) = self.request_context {
+            ctx
+        } else {
+            owned_context = CedarRequestContext::for_user(self.user);
+            &owned_context
+        };
 
         // Check update permission
-        if let Some(path) = self.update_path {
-            context.can_update = self.cedar.can_update(self.user, &path).await;
+        if let Some(path) = &self.update_path {
+            context.can_update = self.check_action(&format!("PUT {path}"), request_context).await;
         }
 
         // Check delete permission
-        if let Some(path) = self.delete_path {
-            context.can_delete = self.cedar.can_delete(self.user, &path).await;
+        if let Some(path) = &self.delete_path {
+            context.can_delete = self
+                .check_action(&format!("DELETE {path}"), request_context)
+                .await;
         }
 
         // Check create permission
-        if let Some(path) = self.create_path {
-            context.can_create = self.cedar.can_create(self.user, &path).await;
+        if let Some(path) = &self.create_path {
+            context.can_create = self
+                .check_action(&format!("POST {path}"), request_context)
+                .await;
         }
 
         // Check read permission
-        if let Some(path) = self.read_path {
-            context.can_read = self.cedar.can_read(self.user, &path).await;
+        if let Some(path) = &self.read_path {
+            context.can_read = self.check_action(&format!("GET {path}"), request_context).await;
         }
 
         // Evaluate custom checks
-        for (name, action) in self.custom_checks {
-            let allowed = self.cedar.can_perform(self.user, &action, None).await;
-            context.permissions.insert(name, allowed);
+        for (name, action) in &self.custom_checks {
+            let allowed = self.check_action(action, request_context).await;
+            context.permissions.insert(name.clone(), allowed);
         }
 
         context
     }
+
+    async fn check_action(&self, action: &str, request_context: &CedarRequestContext) -> bool {
+        self.cedar
+            .can_perform_with_context(self.user, action, request_context)
+            .await
+    }
 }
 
 #[cfg(feature = "cedar")]
@@ -0,0 +1,253 @@
+//! Response compression middleware
+//!
+//! Wraps `tower-http` compression with a preset tuned for HTMX apps:
+//! - Brotli (and gzip) for text responses above a size threshold
+//! - Already-compressed types (images, video, archives) are never recompressed
+//! - BREACH-aware: responses that embed a CSRF token are sent uncompressed
+//!   unless [`CompressionConfig::compress_csrf_responses`] is set
+//!
+//! Handlers that render a CSRF token through
+//! [`CsrfTokenExtractor`](crate::htmx::extractors::CsrfTokenExtractor) are
+//! detected automatically. Handlers that embed other secrets can opt out by
+//! inserting a [`BreachSensitive`] response extension.
+//!
+//! The extractor hands out [masked](crate::htmx::agents::CsrfToken::masked)
+//! tokens, which differ on every render, so compression cannot leak them.
+//! Keeping such responses uncompressed is a second line of defence for pages
+//! that also render the raw token or other secrets; set
+//! `compress_csrf_responses` once they do not.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::config::ActonHtmxConfig;
+//! use acton_htmx::middleware::ResponseCompressionLayer;
+//! use axum::Router;
+//!
+//! let config = ActonHtmxConfig::default();
+//! let app: Router<()> = Router::new()
+//!     .layer(ResponseCompressionLayer::new(&config.compression));
+//! ```
+
+use axum::{
+    body::{Body, HttpBody},
+    http::{header, Request, Response},
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tower_http::compression::{
+    predicate::{Predicate, SizeAbove},
+    Compression, CompressionLayer,
+};
+
+use crate::htmx::config::CompressionConfig;
+
+/// Response extension marking a body as unsafe to compress
+///
+/// Insert this into a response that reflects user input alongside a secret
+/// (CSRF token, API key) to keep it out of compression.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BreachSensitive;
+
+/// Request extension that records whether the handler touched a secret
+///
+/// Inserted by [`ResponseCompressionLayer`]; extractors that hand secrets to
+/// templates call [`BreachGuard::mark`].
+#[derive(Debug, Clone, Default)]
+pub struct BreachGuard(Arc<AtomicBool>);
+
+impl BreachGuard {
+    /// Record that the response will embed a secret
+    pub fn mark(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether a secret was recorded
+    #[must_use]
+    pub fn is_marked(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Decides which responses are compressed
+#[derive(Debug, Clone)]
+pub struct CompressionPredicate {
+    size_above: SizeAbove,
+    content_types: Arc<[String]>,
+    compress_sensitive: bool,
+}
+
+impl CompressionPredicate {
+    /// Build a predicate from configuration
+    #[must_use]
+    pub fn new(config: &CompressionConfig) -> Self {
+        Self {
+            size_above: SizeAbove::new(config.min_size),
+            content_types: config.content_types.clone().into(),
+            compress_sensitive: config.compress_csrf_responses,
+        }
+    }
+}
+
+impl Predicate for CompressionPredicate {
+    fn should_compress<B>(&self, response: &Response<B>) -> bool
+    where
+        B: HttpBody,
+    {
+        if !self.compress_sensitive && response.extensions().get::<BreachSensitive>().is_some() {
+            return false;
+        }
+
+        let content_type_allowed = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| self.content_types.iter().any(|prefix| ct.starts_with(prefix.as_str())));
+
+        content_type_allowed && self.size_above.should_compress(response)
+    }
+}
+
+/// Compression layer preset
+///
+/// Applies brotli/gzip compression per [`CompressionConfig`] and tracks
+/// whether each response embeds a CSRF token.
+#[derive(Debug, Clone)]
+pub struct ResponseCompressionLayer {
+    compression: CompressionLayer<CompressionPredicate>,
+}
+
+impl ResponseCompressionLayer {
+    /// Create a compression layer from configuration
+    #[must_use]
+    pub fn new(config: &CompressionConfig) -> Self {
+        let compression = CompressionLayer::new()
+            .br(config.brotli)
+            .gzip(config.gzip)
+            .no_deflate()
+            .no_zstd()
+            .compress_when(CompressionPredicate::new(config));
+        Self { compression }
+    }
+}
+
+impl<S> tower::Layer<S> for ResponseCompressionLayer {
+    type Service = Compression<BreachGuardMiddleware<S>, CompressionPredicate>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        self.compression.layer(BreachGuardMiddleware { inner })
+    }
+}
+
+/// Inner service that converts a marked [`BreachGuard`] into [`BreachSensitive`]
+#[derive(Debug, Clone)]
+pub struct BreachGuardMiddleware<S> {
+    inner: S,
+}
+
+impl<S> tower::Service<Request<Body>> for BreachGuardMiddleware<S>
+where
+    S: tower::Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let guard = BreachGuard::default();
+        request.extensions_mut().insert(guard.clone());
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            if guard.is_marked() {
+                response.extensions_mut().insert(BreachSensitive);
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::Request as AxumRequest,
+        response::{Html, IntoResponse},
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn large_html() -> String {
+        "<p>hello</p>".repeat(500)
+    }
+
+    fn app(config: &CompressionConfig) -> Router {
+        Router::new()
+            .route("/page", get(|| async { Html(large_html()) }))
+            .route("/small", get(|| async { Html("<p>hi</p>") }))
+            .route(
+                "/image",
+                get(|| async { ([(header::CONTENT_TYPE, "image/png")], vec![0_u8; 4096]) }),
+            )
+            .route(
+                "/form",
+                get(|request: AxumRequest| async move {
+                    if let Some(guard) = request.extensions().get::<BreachGuard>() {
+                        guard.mark();
+                    }
+                    Html(large_html()).into_response()
+                }),
+            )
+            .layer(ResponseCompressionLayer::new(config))
+    }
+
+    async fn encoding(config: &CompressionConfig, uri: &str) -> Option<String> {
+        let request = Request::builder()
+            .uri(uri)
+            .header(header::ACCEPT_ENCODING, "br, gzip")
+            .body(Body::empty())
+            .unwrap();
+        let response = app(config).oneshot(request).await.unwrap();
+        response
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_compresses_large_html_with_brotli() {
+        let config = CompressionConfig::default();
+        assert_eq!(encoding(&config, "/page").await.as_deref(), Some("br"));
+    }
+
+    #[tokio::test]
+    async fn test_skips_small_and_precompressed_bodies() {
+        let config = CompressionConfig::default();
+        assert_eq!(encoding(&config, "/small").await, None);
+        assert_eq!(encoding(&config, "/image").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_csrf_responses_excluded_unless_allowed() {
+        let config = CompressionConfig::default();
+        assert_eq!(encoding(&config, "/form").await, None);
+
+        let config = CompressionConfig {
+            compress_csrf_responses: true,
+            ..CompressionConfig::default()
+        };
+        assert_eq!(encoding(&config, "/form").await.as_deref(), Some("br"));
+    }
+}
@@ -11,8 +11,12 @@
 //! - 403 Forbidden response on validation failure
 //! - Support for both form data and custom headers
 //! - Session-based token storage
+//!
+//! JSON API routes called with `fetch()` can be exempted per route group
+//! with [`RouteGroup::verify_origin`](crate::htmx::routing::RouteGroup::verify_origin),
+//! which validates `Origin`/`Sec-Fetch-Site` instead of a token.
 
-use crate::htmx::agents::{CsrfToken, ValidateToken};
+use crate::htmx::agents::{CsrfToken, SupervisedAgent, ValidateToken};
 use crate::htmx::auth::session::SessionId;
 use crate::htmx::state::ActonHtmxState;
 use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
@@ -43,6 +47,9 @@ pub struct CsrfConfig {
     /// Timeout for agent communication in milliseconds
     pub agent_timeout_ms: u64,
     /// Skip CSRF validation for these paths (e.g., webhooks, health checks)
+    ///
+    /// Paths may contain route parameters: `/webhooks/{provider}` skips
+    /// `/webhooks/github` and `/webhooks/stripe`.
     pub skip_paths: Vec<String>,
 }
 
@@ -77,6 +84,14 @@ impl CsrfConfig {
         self.skip_paths.extend(paths);
         self
     }
+
+    /// Whether CSRF validation is skipped for `path`
+    #[must_use]
+    pub fn is_skipped(&self, path: &str) -> bool {
+        self.skip_paths
+            .iter()
+            .any(|skip| crate::htmx::routing::path_matches(skip, path))
+    }
 }
 
 /// Layer for CSRF middleware
@@ -85,14 +100,14 @@ impl CsrfConfig {
 #[derive(Clone)]
 pub struct CsrfLayer {
     config: CsrfConfig,
-    csrf_manager: ActorHandle,
+    csrf_manager: SupervisedAgent,
 }
 
 impl std::fmt::Debug for CsrfLayer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CsrfLayer")
             .field("config", &self.config)
-            .field("csrf_manager", &"ActorHandle")
+            .field("csrf_manager", &self.csrf_manager)
             .finish()
     }
 }
@@ -103,7 +118,7 @@ impl CsrfLayer {
     pub fn new(state: &ActonHtmxState) -> Self {
         Self {
             config: CsrfConfig::default(),
-            csrf_manager: state.csrf_manager().clone(),
+            csrf_manager: state.supervised_csrf_manager().clone(),
         }
     }
 
@@ -112,7 +127,7 @@ impl CsrfLayer {
     pub fn with_config(state: &ActonHtmxState, config: CsrfConfig) -> Self {
         Self {
             config,
-            csrf_manager: state.csrf_manager().clone(),
+            csrf_manager: state.supervised_csrf_manager().clone(),
         }
     }
 
@@ -121,16 +136,16 @@ impl CsrfLayer {
     pub fn from_handle(csrf_manager: ActorHandle) -> Self {
         Self {
             config: CsrfConfig::default(),
-            csrf_manager,
+            csrf_manager: SupervisedAgent::new("csrf_manager", csrf_manager),
         }
     }
 
     /// Create CSRF layer from handle with custom configuration
     #[must_use]
-    pub const fn from_handle_with_config(csrf_manager: ActorHandle, config: CsrfConfig) -> Self {
+    pub fn from_handle_with_config(csrf_manager: ActorHandle, config: CsrfConfig) -> Self {
         Self {
             config,
-            csrf_manager,
+            csrf_manager: SupervisedAgent::new("csrf_manager", csrf_manager),
         }
     }
 }
@@ -155,7 +170,7 @@ impl<S> Layer<S> for CsrfLayer {
 pub struct CsrfMiddleware<S> {
     inner: S,
     config: Arc<CsrfConfig>,
-    csrf_manager: ActorHandle,
+    csrf_manager: SupervisedAgent,
 }
 
 impl<S: std::fmt::Debug> std::fmt::Debug for CsrfMiddleware<S> {
@@ -163,7 +178,7 @@ impl<S: std::fmt::Debug> std::fmt::Debug for CsrfMiddleware<S> {
         f.debug_struct("CsrfMiddleware")
             .field("inner", &self.inner)
             .field("config", &self.config)
-            .field("csrf_manager", &"ActorHandle")
+            .field("csrf_manager", &self.csrf_manager)
             .finish()
     }
 }
@@ -185,7 +200,7 @@ where
 
     fn call(&mut self, req: Request) -> Self::Future {
         let config = self.config.clone();
-        let csrf_manager = self.csrf_manager.clone();
+        let csrf_manager = self.csrf_manager.handle();
         let mut inner = self.inner.clone();
         let timeout = Duration::from_millis(config.agent_timeout_ms);
 
@@ -196,7 +211,7 @@ where
 
         // Skip CSRF validation for configured paths
         let path = req.uri().path().to_string();
-        if config.skip_paths.iter().any(|skip| skip == &path) {
+        if config.is_skipped(&path) {
             return Box::pin(inner.call(req));
         }
 
@@ -456,7 +471,7 @@ where
 
         // Skip CSRF validation for configured paths
         let path = req.uri().path().to_string();
-        if config.skip_paths.iter().any(|skip| skip == &path) {
+        if config.is_skipped(&path) {
             return Box::pin(inner.call(req));
         }
 
@@ -550,6 +565,14 @@ mod tests {
         assert!(config.skip_paths.contains(&"/metrics".to_string()));
     }
 
+    #[test]
+    fn test_csrf_config_skip_path_patterns() {
+        let config = CsrfConfig::new().skip_path("/webhooks/{provider}");
+        assert!(config.is_skipped("/webhooks/github"));
+        assert!(!config.is_skipped("/webhooks"));
+        assert!(!config.is_skipped("/admin/users"));
+    }
+
     #[test]
     fn test_is_method_safe() {
         assert!(is_method_safe(&Method::GET));
@@ -0,0 +1,200 @@
+//! Development debug toolbar with query plans
+//!
+//! Captures every [`DataClient`] query issued while handling a request (see
+//! [`QueryLog`]) and, for full-page HTML responses, appends a collapsible
+//! panel listing each query with its timing and `EXPLAIN` output, so missing
+//! indexes show up without leaving the browser. Read-only statements are
+//! explained with `ANALYZE` for actual row counts and timings; writes only
+//! get their estimated plan.
+//!
+//! The toolbar is inert in release builds, and the data service only answers
+//! `ExplainQuery` when started with `service.explain_enabled = true`.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::middleware::DebugToolbar;
+//!
+//! let toolbar = DebugToolbar::new(registry.data()?);
+//! let app = Router::new()
+//!     .route("/orders", get(list_orders))
+//!     .layer(axum::middleware::from_fn_with_state(
+//!         toolbar,
+//!         DebugToolbar::middleware,
+//!     ));
+//! ```
+
+use crate::htmx::clients::{tables_written, CapturedQuery, DataClient, QueryLog};
+use crate::htmx::middleware::helpers::is_htmx_request;
+use crate::htmx::template::helpers::escape_html;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::fmt::Write as _;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Largest page the toolbar will buffer to inject its panel
+const MAX_PAGE_SIZE: usize = 8 * 1024 * 1024;
+
+/// Query plan toolbar for development builds
+#[derive(Debug, Clone)]
+pub struct DebugToolbar {
+    data: Arc<RwLock<DataClient>>,
+    analyze: bool,
+    max_queries: usize,
+}
+
+impl DebugToolbar {
+    /// Explain queries through `data`, e.g. `registry.data()?`
+    #[must_use]
+    pub const fn new(data: Arc<RwLock<DataClient>>) -> Self {
+        Self {
+            data,
+            analyze: true,
+            max_queries: 50,
+        }
+    }
+
+    /// Run `EXPLAIN ANALYZE` for read-only queries (default: true)
+    #[must_use]
+    pub const fn with_analyze(mut self, analyze: bool) -> Self {
+        self.analyze = analyze;
+        self
+    }
+
+    /// Explain at most this many queries per request (default: 50)
+    #[must_use]
+    pub const fn with_max_queries(mut self, max_queries: usize) -> Self {
+        self.max_queries = max_queries;
+        self
+    }
+
+    /// Middleware capturing queries and injecting the toolbar
+    ///
+    /// Use with `axum::middleware::from_fn_with_state`. Passes requests
+    /// straight through in release builds.
+    pub async fn middleware(
+        State(toolbar): State<Self>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        if !cfg!(debug_assertions) {
+            return next.run(request).await;
+        }
+
+        let full_page = !is_htmx_request(request.headers());
+        let log = QueryLog::new();
+        let response = log.scope(next.run(request)).await;
+        let queries = log.entries();
+        if !full_page || queries.is_empty() || !is_html(response.headers()) {
+            return response;
+        }
+
+        let (mut parts, body) = response.into_parts();
+        let Ok(bytes) = axum::body::to_bytes(body, MAX_PAGE_SIZE).await else {
+            tracing::warn!("Failed to buffer response body for debug toolbar");
+            return Response::from_parts(parts, Body::empty());
+        };
+        let Ok(page) = String::from_utf8(bytes.to_vec()) else {
+            return Response::from_parts(parts, Body::from(bytes));
+        };
+
+        let panel = toolbar.render(&queries).await;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        Response::from_parts(parts, Body::from(inject(&page, &panel)))
+    }
+
+    /// Render the panel, explaining each captured query
+    async fn render(&self, queries: &[CapturedQuery]) -> String {
+        let mut data = self.data.read().await.clone();
+        let total: f64 = queries.iter().map(|q| q.elapsed.as_secs_f64()).sum();
+
+        let mut html = format!(
+            "<details id=\"acton-debug-toolbar\" style=\"position:fixed;bottom:0;right:0;\
+             max-width:60rem;max-height:60vh;overflow:auto;z-index:99999;background:#111;\
+             color:#eee;font:12px/1.4 monospace;padding:.5rem 1rem\">\
+             <summary>{} queries, {:.1} ms</summary>",
+            queries.len(),
+            total * 1000.0
+        );
+        for (i, query) in queries.iter().enumerate() {
+            let _ = write!(
+                html,
+                "<hr><p><strong>{:.1} ms</strong> {}</p>",
+                query.elapsed.as_secs_f64() * 1000.0,
+                escape_html(&query.sql)
+            );
+            if i >= self.max_queries {
+                html.push_str("<pre>(not explained: query limit reached)</pre>");
+                continue;
+            }
+            let analyze = self.analyze && tables_written(&query.sql).is_empty();
+            let plan = match data.explain(&query.sql, query.params.clone(), analyze).await {
+                Ok(plan) => plan.join("\n"),
+                Err(e) => format!("EXPLAIN failed: {e}"),
+            };
+            let _ = write!(html, "<pre>{}</pre>", escape_html(&plan));
+        }
+        html.push_str("</details>");
+        html
+    }
+}
+
+fn is_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"))
+}
+
+/// Insert the panel before the last `</body>`, or append it
+fn inject(page: &str, panel: &str) -> String {
+    page.rfind("</body>").map_or_else(
+        || format!("{page}{panel}"),
+        |at| format!("{}{panel}{}", &page[..at], &page[at..]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, response::Html, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_inject() {
+        assert_eq!(
+            inject("<html><body>hi</body></html>", "<p>x</p>"),
+            "<html><body>hi<p>x</p></body></html>"
+        );
+        assert_eq!(inject("fragment", "<p>x</p>"), "fragment<p>x</p>");
+        assert_eq!(escape_html("a < b && c"), "a &lt; b &amp;&amp; c");
+    }
+
+    #[tokio::test]
+    async fn test_pages_without_queries_are_untouched() {
+        let data = DataClient::connect_lazy("http://127.0.0.1:1").unwrap();
+        let toolbar = DebugToolbar::new(Arc::new(RwLock::new(data)));
+        let app = Router::new()
+            .route("/", get(|| async { Html("<body>hi</body>") }))
+            .layer(axum::middleware::from_fn_with_state(
+                toolbar,
+                DebugToolbar::middleware,
+            ));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"<body>hi</body>");
+    }
+}
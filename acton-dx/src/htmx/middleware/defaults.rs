@@ -0,0 +1,93 @@
+//! Default middleware stack
+//!
+//! Applies the framework's standard response layers to a router according to
+//! [`ActonHtmxConfig`]: security headers, response compression, trusted proxy
+//! client derivation and, with the `http3` feature, the `Alt-Svc`
+//! advertisement for the HTTP/3 listener.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::config::ActonHtmxConfig;
+//! use acton_htmx::middleware::apply_default_layers;
+//! use axum::{routing::get, Router};
+//!
+//! let config = ActonHtmxConfig::load_for_service("my-app")?;
+//! let app = apply_default_layers(Router::new().route("/", get(index)), &config);
+//! ```
+
+use axum::Router;
+
+use super::compression::ResponseCompressionLayer;
+use super::forwarded::{ForwardedLayer, TrustedProxies};
+use super::security_headers::{SecurityHeadersConfig, SecurityHeadersLayer};
+use crate::htmx::config::ActonHtmxConfig;
+
+/// Apply the default response layers enabled in `config`
+///
+/// - Security headers when `security.security_headers_enabled` is set
+///   (strict in release builds, relaxed in debug builds)
+/// - Compression when `compression.enabled` is set
+/// - `Alt-Svc` advertisement when `http3.enabled` is set (requires `http3` feature)
+/// - Client IP, scheme and host derivation from `proxy.trusted_proxies`
+///
+/// Compression is applied innermost so security headers are added to the
+/// final, encoded response. The forwarded header layer is outermost so every
+/// layer sees the derived client. Invalid trusted proxy entries are logged and
+/// no proxies are trusted.
+pub fn apply_default_layers<S>(router: Router<S>, config: &ActonHtmxConfig) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let mut router = router;
+
+    if config.compression.enabled {
+        router = router.layer(ResponseCompressionLayer::new(&config.compression));
+    }
+
+    if config.security.security_headers_enabled {
+        let headers = if cfg!(debug_assertions) {
+            SecurityHeadersConfig::development()
+        } else {
+            SecurityHeadersConfig::strict()
+        };
+        router = router.layer(SecurityHeadersLayer::new(headers));
+    }
+
+    #[cfg(feature = "http3")]
+    if let Some(alt_svc) = crate::htmx::http3::AltSvcLayer::from_config(&config.http3) {
+        router = router.layer(alt_svc);
+    }
+
+    let trusted = TrustedProxies::new(&config.proxy.trusted_proxies).unwrap_or_else(|e| {
+        tracing::error!(error = %e, "Ignoring invalid trusted proxy configuration");
+        TrustedProxies::default()
+    });
+    router.layer(ForwardedLayer::new(trusted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, response::Html, routing::get};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_default_layers_compress_and_add_headers() {
+        let config = ActonHtmxConfig::default();
+        let app = apply_default_layers(
+            Router::new().route("/", get(|| async { Html("<p>x</p>".repeat(500)) })),
+            &config,
+        );
+
+        let request = Request::builder()
+            .uri("/")
+            .header("accept-encoding", "gzip")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.headers().get("content-encoding").unwrap(), "gzip");
+        assert!(response.headers().contains_key("x-content-type-options"));
+    }
+}
@@ -0,0 +1,360 @@
+//! ETag and conditional response middleware
+//!
+//! Computes a content hash ETag for rendered `GET`/`HEAD` responses and answers
+//! `If-None-Match` revalidations with `304 Not Modified`. HTMX polling endpoints
+//! (notification badges, status widgets) re-render identical HTML every few
+//! seconds; with this layer the browser only downloads the fragment when it
+//! actually changes.
+//!
+//! Only buffered responses with a known size up to `max_body_size` are hashed,
+//! so streaming bodies pass through untouched. Responses that already carry an
+//! `ETag`, are not `200 OK`, or are marked `Cache-Control: no-store` are left
+//! alone.
+//!
+//! Apply the layer to the route group that should be revalidated:
+//!
+//! ```rust,ignore
+//! use acton_htmx::middleware::{EtagConfig, EtagLayer};
+//! use axum::{routing::get, Router};
+//!
+//! let widgets: Router<()> = Router::new()
+//!     .route("/widgets/notifications", get(notification_badge))
+//!     .layer(EtagLayer::new(EtagConfig::default().with_htmx_only(true)));
+//! ```
+
+use axum::{
+    body::{Body, HttpBody},
+    http::{header, HeaderMap, HeaderValue, Method, Request, Response, StatusCode},
+};
+use sha2::{Digest, Sha256};
+
+/// Default maximum response size to hash (1 MiB)
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Configuration for ETag middleware
+#[derive(Debug, Clone)]
+pub struct EtagConfig {
+    /// Emit weak (`W/"..."`) validators
+    ///
+    /// Weak validators stay valid across transfer encodings such as compression.
+    pub weak: bool,
+    /// Largest response body that will be buffered and hashed
+    pub max_body_size: usize,
+    /// Content type prefixes eligible for ETags (e.g. `text/html`)
+    pub content_types: Vec<String>,
+    /// Only tag HTMX requests (those with an `HX-Request` header)
+    pub htmx_only: bool,
+    /// `Cache-Control` value added when the response does not set one
+    ///
+    /// Defaults to `no-cache`, which lets the browser store the fragment but
+    /// forces revalidation on every request.
+    pub cache_control: Option<String>,
+}
+
+impl Default for EtagConfig {
+    fn default() -> Self {
+        Self {
+            weak: true,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            content_types: vec!["text/html".to_string()],
+            htmx_only: false,
+            cache_control: Some("no-cache".to_string()),
+        }
+    }
+}
+
+impl EtagConfig {
+    /// Emit strong validators instead of weak ones
+    #[must_use]
+    pub const fn with_strong(mut self) -> Self {
+        self.weak = false;
+        self
+    }
+
+    /// Set the largest response body that will be hashed
+    #[must_use]
+    pub const fn with_max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+
+    /// Also tag responses with the given content type prefix
+    #[must_use]
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_types.push(content_type.into());
+        self
+    }
+
+    /// Only tag HTMX requests
+    #[must_use]
+    pub const fn with_htmx_only(mut self, htmx_only: bool) -> Self {
+        self.htmx_only = htmx_only;
+        self
+    }
+
+    /// Set the `Cache-Control` value added to tagged responses (`None` to leave it unset)
+    #[must_use]
+    pub fn with_cache_control(mut self, value: Option<String>) -> Self {
+        self.cache_control = value;
+        self
+    }
+
+    /// Whether a response with this content type should be tagged
+    fn matches_content_type(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| self.content_types.iter().any(|prefix| ct.starts_with(prefix.as_str())))
+    }
+}
+
+/// ETag middleware layer
+///
+/// Creates a tower layer that tags responses and answers conditional requests.
+#[derive(Clone, Debug)]
+pub struct EtagLayer {
+    config: EtagConfig,
+}
+
+impl EtagLayer {
+    /// Create a new ETag layer with the given configuration
+    #[must_use]
+    pub const fn new(config: EtagConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl<S> tower::Layer<S> for EtagLayer {
+    type Service = EtagMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        EtagMiddleware {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// ETag middleware service
+#[derive(Clone, Debug)]
+pub struct EtagMiddleware<S> {
+    inner: S,
+    config: EtagConfig,
+}
+
+impl<S> tower::Service<Request<Body>> for EtagMiddleware<S>
+where
+    S: tower::Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let eligible = matches!(*request.method(), Method::GET | Method::HEAD)
+            && (!self.config.htmx_only || request.headers().contains_key("hx-request"));
+        let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+        let config = self.config.clone();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = future.await?;
+            if !eligible {
+                return Ok(response);
+            }
+            Ok(apply_etag(response, if_none_match.as_ref(), &config).await)
+        })
+    }
+}
+
+/// Tag the response, or replace it with `304 Not Modified` if the client's copy is current
+async fn apply_etag(
+    response: Response<Body>,
+    if_none_match: Option<&HeaderValue>,
+    config: &EtagConfig,
+) -> Response<Body> {
+    if !should_tag(&response, config) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    // Size was checked against the hint, so this only fails on a body error
+    let Ok(bytes) = axum::body::to_bytes(body, config.max_body_size).await else {
+        tracing::warn!("Failed to buffer response body for ETag");
+        return Response::from_parts(parts, Body::empty());
+    };
+
+    let etag = compute_etag(&bytes, config.weak);
+    let Ok(etag_value) = HeaderValue::from_str(&etag) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    parts.headers.insert(header::ETAG, etag_value);
+    parts.headers.append(header::VARY, HeaderValue::from_static("HX-Request"));
+    if let Some(cache_control) = &config.cache_control {
+        if !parts.headers.contains_key(header::CACHE_CONTROL) {
+            if let Ok(value) = HeaderValue::from_str(cache_control) {
+                parts.headers.insert(header::CACHE_CONTROL, value);
+            }
+        }
+    }
+
+    if if_none_match.is_some_and(|v| etag_matches(v, &etag)) {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        parts.headers.remove(header::CONTENT_TYPE);
+        return Response::from_parts(parts, Body::empty());
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Whether a response is eligible for tagging
+fn should_tag(response: &Response<Body>, config: &EtagConfig) -> bool {
+    if response.status() != StatusCode::OK || response.headers().contains_key(header::ETAG) {
+        return false;
+    }
+
+    let no_store = response
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("no-store"));
+    if no_store || !config.matches_content_type(response.headers()) {
+        return false;
+    }
+
+    // Only buffer bodies whose size is known up front
+    response
+        .body()
+        .size_hint()
+        .upper()
+        .and_then(|upper| usize::try_from(upper).ok())
+        .is_some_and(|upper| upper <= config.max_body_size)
+}
+
+/// Compute an ETag from the response body
+#[must_use]
+pub fn compute_etag(body: &[u8], weak: bool) -> String {
+    let digest = Sha256::digest(body);
+    let tag = hex::encode(&digest[..16]);
+    if weak {
+        format!("W/\"{tag}\"")
+    } else {
+        format!("\"{tag}\"")
+    }
+}
+
+/// Weak comparison of an `If-None-Match` header against an ETag (RFC 9110 §13.1.2)
+fn etag_matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+    let opaque = etag.trim_start_matches("W/");
+    value.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.trim_start_matches("W/") == opaque
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{response::Html, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(config: EtagConfig) -> Router {
+        Router::new()
+            .route("/badge", get(|| async { Html("<span>3</span>") }))
+            .route("/text", get(|| async { "plain" }))
+            .layer(EtagLayer::new(config))
+    }
+
+    fn get_request(uri: &str, if_none_match: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(tag) = if_none_match {
+            builder = builder.header(header::IF_NONE_MATCH, tag);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_etag_added_and_304_on_match() {
+        let response = app(EtagConfig::default())
+            .oneshot(get_request("/badge", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers().get(header::ETAG).unwrap().to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/\""));
+        assert_eq!(response.headers().get(header::CACHE_CONTROL).unwrap(), "no-cache");
+
+        let response = app(EtagConfig::default())
+            .oneshot(get_request("/badge", Some(&etag)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stale_etag_returns_body() {
+        let response = app(EtagConfig::default())
+            .oneshot(get_request("/badge", Some("W/\"stale\"")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"<span>3</span>");
+    }
+
+    #[tokio::test]
+    async fn test_non_matching_content_type_untouched() {
+        let response = app(EtagConfig::default())
+            .oneshot(get_request("/text", None))
+            .await
+            .unwrap();
+        assert!(!response.headers().contains_key(header::ETAG));
+    }
+
+    #[tokio::test]
+    async fn test_htmx_only_skips_full_page_requests() {
+        let config = EtagConfig::default().with_htmx_only(true);
+        let response = app(config.clone())
+            .oneshot(get_request("/badge", None))
+            .await
+            .unwrap();
+        assert!(!response.headers().contains_key(header::ETAG));
+
+        let request = Request::builder()
+            .uri("/badge")
+            .header("hx-request", "true")
+            .body(Body::empty())
+            .unwrap();
+        let response = app(config).oneshot(request).await.unwrap();
+        assert!(response.headers().contains_key(header::ETAG));
+    }
+
+    #[test]
+    fn test_etag_matches_weak_and_list() {
+        let etag = compute_etag(b"hello", true);
+        let strong = etag.trim_start_matches("W/").to_string();
+        assert!(etag_matches(&HeaderValue::from_str(&strong).unwrap(), &etag));
+        assert!(etag_matches(
+            &HeaderValue::from_str(&format!("\"other\", {etag}")).unwrap(),
+            &etag
+        ));
+        assert!(etag_matches(&HeaderValue::from_static("*"), &etag));
+        assert!(!etag_matches(&HeaderValue::from_static("\"other\""), &etag));
+    }
+}
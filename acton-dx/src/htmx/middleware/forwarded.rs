@@ -0,0 +1,458 @@
+//! Trusted proxy and forwarded header handling
+//!
+//! Derives the client IP, scheme and host for every request in one place so
+//! rate limiting, request queuing, authorization context and redirects agree
+//! on who the client is. Forwarding headers are only honoured when the direct
+//! peer is a configured trusted proxy:
+//!
+//! - RFC 7239 `Forwarded` (`for`, `proto`, `host`) takes precedence
+//! - Otherwise `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host`
+//!
+//! The client address is the rightmost hop that is not itself a trusted proxy,
+//! so a client cannot spoof its IP by prepending entries to the header.
+//!
+//! The layer inserts a [`ClientInfo`] extension and rewrites
+//! `ConnectInfo<SocketAddr>` to the derived client address, so downstream
+//! middleware that reads the connection address sees the real client.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::config::ActonHtmxConfig;
+//! use acton_htmx::middleware::ForwardedLayer;
+//! use axum::Router;
+//!
+//! let config = ActonHtmxConfig::load_for_service("my-app")?;
+//! let app: Router<()> = Router::new()
+//!     .layer(ForwardedLayer::from_config(&config.proxy)?);
+//! ```
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, Extensions, HeaderMap, Request, Response},
+};
+use ipnet::IpNet;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use crate::htmx::config::ProxyConfig;
+
+/// Error parsing the trusted proxy list
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid trusted proxy entry '{0}': expected an IP address or CIDR range")]
+pub struct InvalidProxyRange(pub String);
+
+/// Set of trusted proxy networks
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Arc<[IpNet]>,
+}
+
+impl TrustedProxies {
+    /// Parse a list of IP addresses and CIDR ranges
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidProxyRange`] for the first entry that is neither an IP
+    /// address nor a CIDR range.
+    pub fn new<I, T>(entries: I) -> Result<Self, InvalidProxyRange>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let networks = entries
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.as_ref().trim();
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| InvalidProxyRange(entry.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            networks: networks.into(),
+        })
+    }
+
+    /// Whether `ip` belongs to a trusted proxy
+    #[must_use]
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.networks.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Whether no proxies are trusted
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+    }
+}
+
+/// Client connection details derived from the peer and trusted forwarding headers
+///
+/// Available as a request extension and as an extractor once
+/// [`ForwardedLayer`] is installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInfo {
+    /// Client IP address (`None` when the connection address is unknown)
+    pub ip: Option<IpAddr>,
+    /// Address of the direct peer
+    pub peer: Option<SocketAddr>,
+    /// Request scheme as seen by the client (`http` or `https`)
+    pub scheme: String,
+    /// Host as requested by the client
+    pub host: Option<String>,
+    /// Whether the values were taken from trusted forwarding headers
+    pub forwarded: bool,
+}
+
+impl ClientInfo {
+    /// Whether the client connected over HTTPS
+    #[must_use]
+    pub fn is_secure(&self) -> bool {
+        self.scheme.eq_ignore_ascii_case("https")
+    }
+
+    /// Client-facing origin (`https://example.com`), for building absolute redirects
+    #[must_use]
+    pub fn origin(&self) -> Option<String> {
+        self.host
+            .as_ref()
+            .map(|host| format!("{}://{host}", self.scheme))
+    }
+
+    /// Derive client details from the peer address and request headers
+    #[must_use]
+    pub fn derive(peer: Option<SocketAddr>, headers: &HeaderMap, trusted: &TrustedProxies) -> Self {
+        let direct_host = header_str(headers, header::HOST.as_str()).map(ToString::to_string);
+        let mut info = Self {
+            ip: peer.map(|addr| addr.ip().to_canonical()),
+            peer,
+            scheme: "http".to_string(),
+            host: direct_host,
+            forwarded: false,
+        };
+
+        let Some(peer) = peer else {
+            return info;
+        };
+        if !trusted.contains(peer.ip()) {
+            return info;
+        }
+
+        if let Some(value) = header_str(headers, "forwarded") {
+            let elements = parse_forwarded(value);
+            if let Some(element) = select_hop(&elements, |e| e.for_ip, trusted) {
+                info.ip = element.for_ip;
+                info.forwarded = true;
+                if let Some(proto) = &element.proto {
+                    info.scheme.clone_from(proto);
+                }
+                if element.host.is_some() {
+                    info.host.clone_from(&element.host);
+                }
+            }
+            return info;
+        }
+
+        if let Some(value) = header_str(headers, "x-forwarded-for") {
+            let hops: Vec<Option<IpAddr>> = value.split(',').map(parse_node).collect();
+            if let Some(ip) = select_hop(&hops, |hop| *hop, trusted) {
+                info.ip = *ip;
+                info.forwarded = true;
+            }
+        }
+        if let Some(proto) = first_value(headers, "x-forwarded-proto") {
+            info.scheme = proto.to_ascii_lowercase();
+            info.forwarded = true;
+        }
+        if let Some(host) = first_value(headers, "x-forwarded-host") {
+            info.host = Some(host.to_string());
+            info.forwarded = true;
+        }
+
+        info
+    }
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_else(|| {
+            let peer = parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| *addr);
+            Self::derive(peer, &parts.headers, &TrustedProxies::default())
+        }))
+    }
+}
+
+/// Client IP for a request
+///
+/// Uses [`ClientInfo`] when [`ForwardedLayer`] is installed and falls back to
+/// the connection address otherwise. Never reads forwarding headers directly.
+#[must_use]
+pub fn client_ip(extensions: &Extensions) -> Option<IpAddr> {
+    extensions.get::<ClientInfo>().map_or_else(
+        || {
+            extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_canonical())
+        },
+        |info| info.ip,
+    )
+}
+
+/// Forwarded header layer
+///
+/// Install outermost so every other layer sees the derived client details.
+#[derive(Debug, Clone, Default)]
+pub struct ForwardedLayer {
+    trusted: TrustedProxies,
+}
+
+impl ForwardedLayer {
+    /// Create a layer trusting the given proxies
+    #[must_use]
+    pub const fn new(trusted: TrustedProxies) -> Self {
+        Self { trusted }
+    }
+
+    /// Create a layer from the `[proxy]` configuration section
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidProxyRange`] if a trusted proxy entry cannot be parsed.
+    pub fn from_config(config: &ProxyConfig) -> Result<Self, InvalidProxyRange> {
+        TrustedProxies::new(&config.trusted_proxies).map(Self::new)
+    }
+}
+
+impl<S> tower::Layer<S> for ForwardedLayer {
+    type Service = ForwardedMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ForwardedMiddleware {
+            inner,
+            trusted: self.trusted.clone(),
+        }
+    }
+}
+
+/// Forwarded header middleware service
+#[derive(Debug, Clone)]
+pub struct ForwardedMiddleware<S> {
+    inner: S,
+    trusted: TrustedProxies,
+}
+
+impl<S> tower::Service<Request<Body>> for ForwardedMiddleware<S>
+where
+    S: tower::Service<Request<Body>, Response = Response<Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+        let info = ClientInfo::derive(peer, request.headers(), &self.trusted);
+
+        if let (true, Some(ip)) = (info.forwarded, info.ip) {
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::new(ip, 0)));
+        }
+        request.extensions_mut().insert(info);
+        self.inner.call(request)
+    }
+}
+
+/// One element of an RFC 7239 `Forwarded` header
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ForwardedElement {
+    for_ip: Option<IpAddr>,
+    proto: Option<String>,
+    host: Option<String>,
+}
+
+/// Parse an RFC 7239 `Forwarded` header value
+fn parse_forwarded(value: &str) -> Vec<ForwardedElement> {
+    value
+        .split(',')
+        .map(|element| {
+            let mut parsed = ForwardedElement::default();
+            for pair in element.split(';') {
+                let Some((key, value)) = pair.split_once('=') else {
+                    continue;
+                };
+                let value = value.trim().trim_matches('"');
+                match key.trim().to_ascii_lowercase().as_str() {
+                    "for" => parsed.for_ip = parse_node(value),
+                    "proto" => parsed.proto = Some(value.to_ascii_lowercase()),
+                    "host" => parsed.host = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// Parse a forwarded node (`203.0.113.7`, `203.0.113.7:80`, `[2001:db8::1]:443`)
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    let ip = if let Some(rest) = node.strip_prefix('[') {
+        rest.split(']').next()?.parse::<IpAddr>().ok()
+    } else {
+        node.parse::<IpAddr>()
+            .ok()
+            .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+    };
+    ip.map(|ip| ip.to_canonical())
+}
+
+/// Walk hops right to left and pick the first one that is not a trusted proxy
+///
+/// Unknown or obfuscated hops stop the walk, since nothing to their left can
+/// be verified. When every hop is trusted the leftmost one is used.
+fn select_hop<'a, T>(
+    hops: &'a [T],
+    ip_of: impl Fn(&T) -> Option<IpAddr>,
+    trusted: &TrustedProxies,
+) -> Option<&'a T> {
+    for hop in hops.iter().rev() {
+        match ip_of(hop) {
+            Some(ip) if trusted.contains(ip) => {}
+            Some(_) => return Some(hop),
+            None => return None,
+        }
+    }
+    hops.first()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn first_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    header_str(headers, name)
+        .and_then(|v| v.split(',').next())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    fn trusted() -> TrustedProxies {
+        TrustedProxies::new(["10.0.0.0/8", "127.0.0.1"]).unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_trusted_proxies_parse() {
+        let proxies = trusted();
+        assert!(proxies.contains("10.1.2.3".parse().unwrap()));
+        assert!(proxies.contains("::ffff:127.0.0.1".parse().unwrap()));
+        assert!(!proxies.contains("192.168.1.1".parse().unwrap()));
+        assert!(TrustedProxies::new(["not-an-ip"]).is_err());
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_headers() {
+        let peer: SocketAddr = "198.51.100.9:5000".parse().unwrap();
+        let headers = headers(&[
+            ("x-forwarded-for", "203.0.113.7"),
+            ("x-forwarded-proto", "https"),
+            ("host", "app.local"),
+        ]);
+        let info = ClientInfo::derive(Some(peer), &headers, &trusted());
+        assert_eq!(info.ip, Some(peer.ip()));
+        assert_eq!(info.scheme, "http");
+        assert!(!info.forwarded);
+    }
+
+    #[test]
+    fn test_x_forwarded_for_uses_rightmost_untrusted_hop() {
+        let peer: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+        let headers = headers(&[
+            ("x-forwarded-for", "1.1.1.1, 203.0.113.7, 10.0.0.1"),
+            ("x-forwarded-proto", "https"),
+            ("x-forwarded-host", "example.com"),
+        ]);
+        let info = ClientInfo::derive(Some(peer), &headers, &trusted());
+        assert_eq!(info.ip, Some("203.0.113.7".parse().unwrap()));
+        assert!(info.is_secure());
+        assert_eq!(info.origin().as_deref(), Some("https://example.com"));
+    }
+
+    #[test]
+    fn test_forwarded_header_takes_precedence() {
+        let peer: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let headers = headers(&[
+            (
+                "forwarded",
+                "for=\"[2001:db8::7]:4711\";proto=https;host=example.com, for=10.0.0.5",
+            ),
+            ("x-forwarded-for", "198.51.100.1"),
+        ]);
+        let info = ClientInfo::derive(Some(peer), &headers, &trusted());
+        assert_eq!(info.ip, Some("2001:db8::7".parse().unwrap()));
+        assert_eq!(info.scheme, "https");
+        assert_eq!(info.host.as_deref(), Some("example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_layer_rewrites_connect_info() {
+        async fn handler(ConnectInfo(addr): ConnectInfo<SocketAddr>, info: ClientInfo) -> String {
+            format!("{} {}", addr.ip(), info.scheme)
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(ForwardedLayer::new(trusted()));
+
+        let mut request = Request::builder()
+            .uri("/")
+            .header("x-forwarded-for", "203.0.113.7")
+            .header("x-forwarded-proto", "https")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo("10.0.0.2:5000".parse::<SocketAddr>().unwrap()));
+
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"203.0.113.7 https");
+    }
+}
@@ -0,0 +1,160 @@
+//! Identity tokens for calls to internal services
+//!
+//! Exchanges the signed-in user's session for a short-lived access token
+//! and scopes it to the request (see [`IdentityToken`]), so every
+//! [`DataClient`](crate::htmx::clients::DataClient) and
+//! [`FileClient`](crate::htmx::clients::FileClient) call the handler makes
+//! carries the user's identity. With `identity.auth_endpoint` configured,
+//! the data service sets `app.user_id` from the verified token for
+//! row-level security, and the file service keeps users to their own files.
+//!
+//! Tokens are cached per session and renewed shortly before they expire.
+//! Requests without a signed-in user pass through without a token.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::middleware::IdentityTokens;
+//!
+//! let identity = IdentityTokens::new(registry.auth()?);
+//! let app = Router::new()
+//!     .route("/documents", get(list_documents))
+//!     .layer(axum::middleware::from_fn_with_state(
+//!         identity,
+//!         IdentityTokens::middleware,
+//!     ))
+//!     // The session layer must run first
+//!     .layer(session_layer);
+//! ```
+
+use crate::htmx::auth::{SessionData, SessionId};
+use crate::htmx::clients::{AuthClient, IdentityToken};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// How long before expiry a cached token is renewed
+const RENEW_BEFORE_EXPIRY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+struct CachedToken {
+    token: IdentityToken,
+    renew_at: Instant,
+}
+
+/// Attaches the signed-in user's identity to internal service calls
+#[derive(Debug, Clone)]
+pub struct IdentityTokens {
+    auth: Arc<RwLock<AuthClient>>,
+    audience: String,
+    scopes: Vec<String>,
+    cache: Arc<Mutex<HashMap<(String, i64), CachedToken>>>,
+}
+
+impl IdentityTokens {
+    /// Mint tokens through `auth`, e.g. `registry.auth()?`
+    #[must_use]
+    pub fn new(auth: Arc<RwLock<AuthClient>>) -> Self {
+        Self {
+            auth,
+            audience: "internal".to_string(),
+            scopes: Vec::new(),
+            cache: Arc::default(),
+        }
+    }
+
+    /// Audience of the tokens (default: `internal`)
+    ///
+    /// Must be one of the auth service's `token.audiences` and match the
+    /// services' `identity.audience`.
+    #[must_use]
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = audience.into();
+        self
+    }
+
+    /// Scopes to request (default: the auth service's default scopes)
+    #[must_use]
+    pub fn with_scopes(mut self, scopes: Vec<String>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Middleware scoping the user's identity token to the request
+    ///
+    /// Use with `axum::middleware::from_fn_with_state`, inside the session
+    /// layer. If no token can be minted the request proceeds without one,
+    /// and services that require identities reject its calls.
+    pub async fn middleware(
+        State(identity): State<Self>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let session_id = request.extensions().get::<SessionId>().cloned();
+        let user_id = request
+            .extensions()
+            .get::<SessionData>()
+            .and_then(|data| data.user_id);
+        let (Some(session_id), Some(user_id)) = (session_id, user_id) else {
+            return next.run(request).await;
+        };
+
+        match identity.token(&session_id, user_id).await {
+            Some(token) => token.scope(next.run(request)).await,
+            None => next.run(request).await,
+        }
+    }
+
+    /// Cached token for the session, minting a new one when due
+    async fn token(&self, session_id: &SessionId, user_id: i64) -> Option<IdentityToken> {
+        let key = (session_id.as_str().to_string(), user_id);
+        let now = Instant::now();
+        let cached = self
+            .cache
+            .lock()
+            .get(&key)
+            .filter(|cached| cached.renew_at > now)
+            .map(|cached| cached.token.clone());
+        if cached.is_some() {
+            return cached;
+        }
+
+        let issued = self
+            .auth
+            .write()
+            .await
+            .issue_access_token(
+                session_id.as_str(),
+                Some(&self.audience),
+                self.scopes.clone(),
+            )
+            .await
+            .map_err(|e| tracing::warn!(user_id, error = %e, "Failed to mint identity token"))
+            .ok()?;
+        let token = IdentityToken::new(&issued.access_token)
+            .map_err(|e| tracing::warn!(user_id, error = %e, "Unusable identity token"))
+            .ok()?;
+
+        let lifetime = Duration::from_secs(u64::try_from(issued.expires_in).unwrap_or_default());
+        let renew_at = now + lifetime.saturating_sub(RENEW_BEFORE_EXPIRY);
+        let mut cache = self.cache.lock();
+        cache.retain(|_, cached| cached.renew_at > now);
+        cache.insert(
+            key,
+            CachedToken {
+                token: token.clone(),
+                renew_at,
+            },
+        );
+        drop(cache);
+
+        Some(token)
+    }
+}
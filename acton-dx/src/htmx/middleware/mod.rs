@@ -4,24 +4,51 @@
 //! - Session management (cookie-based sessions with agent backend)
 //! - Authentication (route protection)
 //! - CSRF protection (token-based CSRF validation)
+//! - Origin validation (`Origin`/`Sec-Fetch-Site` checks for CSRF-exempt API routes)
 //! - Security headers (automatic security header injection)
+//! - ETags (conditional `304 Not Modified` responses for rendered fragments)
+//! - Compression (brotli/gzip with BREACH-aware exclusions)
+//! - Forwarded headers (trusted proxy client IP, scheme and host derivation)
+//! - Default stack (applies the configured standard layers to a router)
 //! - File serving (range requests, caching, access control)
 //! - Cedar authorization (policy-based access control, requires cedar feature)
+//! - Cedar context enrichment (per-request policy context from session, requires cedar feature)
 //! - Rate limiting (Redis-backed or in-memory, per-user/IP/route limits)
+//! - Request queuing (bounded, per-user fair queue for expensive endpoints)
+//! - Debug toolbar (per-request query plans in development, requires microservices feature)
+//! - Identity tokens (user identity on internal service calls, requires microservices feature)
+//! - API keys (machine-to-machine authentication, requires microservices feature)
 
+#[cfg(feature = "microservices")]
+pub mod api_key;
 pub mod auth;
 #[cfg(feature = "cedar")]
 pub mod cedar;
 #[cfg(feature = "cedar")]
+pub mod cedar_context;
+#[cfg(feature = "cedar")]
 pub mod cedar_template;
+pub mod compression;
 pub mod csrf;
+#[cfg(feature = "microservices")]
+pub mod debug_toolbar;
+pub mod defaults;
+pub mod etag;
 pub mod file_serving;
+pub mod forwarded;
 pub mod helpers;
+#[cfg(feature = "microservices")]
+pub mod identity;
+pub mod origin;
 pub mod rate_limit;
+pub mod request_queue;
 pub mod security_headers;
 pub mod session;
 
 // Re-exports are intentionally public even if not used within the crate itself
+#[cfg(feature = "microservices")]
+#[allow(unused_imports)]
+pub use api_key::{ApiKeyAuth, ApiKeyRejection, API_KEY_HEADER};
 #[allow(unused_imports)]
 pub use auth::{AuthMiddleware, AuthMiddlewareError};
 #[cfg(feature = "cedar")]
@@ -29,27 +56,61 @@ pub use auth::{AuthMiddleware, AuthMiddlewareError};
 pub use cedar::{CedarAuthz, CedarAuthzBuilder, CedarError};
 #[cfg(feature = "cedar")]
 #[allow(unused_imports)]
+pub use cedar_context::{
+    CedarContextConfig, CedarContextLayer, CedarContextMiddleware, CedarRequestContext,
+    IpReputation, IpReputationProvider,
+};
+#[cfg(feature = "cedar")]
+#[allow(unused_imports)]
 pub use cedar_template::{AuthzContext, AuthzContextBuilder};
 #[allow(unused_imports)]
+pub use compression::{
+    BreachGuard, BreachSensitive, CompressionPredicate, ResponseCompressionLayer,
+};
+#[allow(unused_imports)]
 pub use csrf::{
     CsrfConfig, CsrfLayer, CsrfMiddleware, CSRF_FORM_FIELD, CSRF_HEADER_NAME,
 };
 #[cfg(feature = "microservices")]
 #[allow(unused_imports)]
 pub use csrf::{MicroservicesCsrfLayer, MicroservicesCsrfMiddleware};
+#[cfg(feature = "microservices")]
+#[allow(unused_imports)]
+pub use debug_toolbar::DebugToolbar;
+#[allow(unused_imports)]
+pub use defaults::apply_default_layers;
+#[allow(unused_imports)]
+pub use etag::{EtagConfig, EtagLayer, EtagMiddleware};
 #[allow(unused_imports)]
 pub use file_serving::{
     serve_file, FileAccessControl, FileServingError, FileServingMiddleware,
 };
 #[allow(unused_imports)]
+pub use forwarded::{
+    client_ip, ClientInfo, ForwardedLayer, ForwardedMiddleware, InvalidProxyRange,
+    TrustedProxies,
+};
+#[cfg(feature = "microservices")]
+#[allow(unused_imports)]
+pub use identity::IdentityTokens;
+#[allow(unused_imports)]
+pub use origin::OriginCheck;
+#[allow(unused_imports)]
 pub use rate_limit::{RateLimit, RateLimitError};
 #[allow(unused_imports)]
+pub use request_queue::{
+    QueueError, QueuePermit, QueueStatus, RequestQueue, RequestQueueConfig,
+};
+#[allow(unused_imports)]
 pub use security_headers::{
     FrameOptions, HstsConfig, ReferrerPolicy, SecurityHeadersConfig, SecurityHeadersLayer,
     SecurityHeadersMiddleware,
 };
 #[allow(unused_imports)]
-pub use session::{SameSite, SessionConfig, SessionLayer, SessionMiddleware, SESSION_COOKIE_NAME};
+pub use session::{
+    RegenerateSession, SameSite, SessionConfig, SessionLayer, SessionMiddleware,
+    SESSION_COOKIE_NAME,
+};
 #[cfg(feature = "microservices")]
 #[allow(unused_imports)]
 pub use session::{MicroservicesSessionLayer, MicroservicesSessionMiddleware};
@@ -0,0 +1,243 @@
+//! Origin validation for CSRF-exempt API routes
+//!
+//! JSON endpoints called with `fetch()` rarely carry a CSRF token, which
+//! tempts people to drop the CSRF layer altogether. [`OriginCheck`] is the
+//! sanctioned alternative for such routes: instead of a token, it rejects
+//! state-changing requests that a browser reports as coming from another
+//! site, using headers that pages cannot set themselves:
+//!
+//! - `Sec-Fetch-Site`: `same-origin` and `none` (typed URLs, bookmarks)
+//!   pass; `same-site` passes only with
+//!   [`allow_same_site`](OriginCheck::allow_same_site); `cross-site` passes
+//!   only from an [allowed origin](OriginCheck::allow_origin).
+//! - Without `Sec-Fetch-Site` (older browsers), `Origin` must be the
+//!   request's own origin or an allowed one.
+//! - Requests with neither header did not come from a browser and cannot be
+//!   forged by one, so they pass unless
+//!   [`require_browser_headers`](OriginCheck::require_browser_headers) is set.
+//!
+//! Apply it per route group with
+//! [`RouteGroup::verify_origin`](crate::htmx::routing::RouteGroup::verify_origin),
+//! which also exempts the group from CSRF token validation. The request's
+//! own origin comes from [`ClientInfo`], so install [`ForwardedLayer`] behind
+//! a proxy.
+//!
+//! [`ForwardedLayer`]: super::ForwardedLayer
+
+use super::forwarded::{ClientInfo, TrustedProxies};
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::net::SocketAddr;
+
+/// Origin and `Sec-Fetch-Site` validation for state-changing requests
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OriginCheck {
+    allowed_origins: Vec<String>,
+    allow_same_site: bool,
+    require_browser_headers: bool,
+}
+
+impl OriginCheck {
+    /// Accept same-origin requests only
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also accept requests from `origin`, e.g. `https://app.example.com`
+    #[must_use]
+    pub fn allow_origin(mut self, origin: impl AsRef<str>) -> Self {
+        self.allowed_origins.push(normalize(origin.as_ref()));
+        self
+    }
+
+    /// Also accept requests the browser marks `same-site` (other subdomains
+    /// of the same registrable domain)
+    #[must_use]
+    pub const fn allow_same_site(mut self) -> Self {
+        self.allow_same_site = true;
+        self
+    }
+
+    /// Reject requests carrying neither `Sec-Fetch-Site` nor `Origin`
+    ///
+    /// Use when the routes are only ever called from your own pages.
+    #[must_use]
+    pub const fn require_browser_headers(mut self) -> Self {
+        self.require_browser_headers = true;
+        self
+    }
+
+    /// Check a request; safe methods always pass
+    ///
+    /// `own_origin` is the origin the client addressed, e.g.
+    /// `https://example.com`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the request was rejected.
+    pub fn check(
+        &self,
+        method: &Method,
+        headers: &HeaderMap,
+        own_origin: Option<&str>,
+    ) -> Result<(), &'static str> {
+        if matches!(
+            *method,
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+        ) {
+            return Ok(());
+        }
+
+        let origin = header(headers, "origin").map(normalize);
+        let allowed = |origin: &str| {
+            own_origin.is_some_and(|own| normalize(own) == origin)
+                || self.allowed_origins.iter().any(|allowed| allowed == origin)
+        };
+
+        match header(headers, "sec-fetch-site") {
+            Some("same-origin" | "none") => Ok(()),
+            Some("same-site") if self.allow_same_site => Ok(()),
+            Some(_) => match origin {
+                Some(origin) if self.allowed_origins.contains(&origin) => Ok(()),
+                _ => Err("cross-site request"),
+            },
+            None => match origin {
+                Some(origin) if allowed(&origin) => Ok(()),
+                Some(_) => Err("origin not allowed"),
+                None if self.require_browser_headers => Err("missing Origin header"),
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Axum middleware answering `403 Forbidden` to rejected requests
+    ///
+    /// Use with [`axum::middleware::from_fn_with_state`].
+    pub async fn middleware(State(check): State<Self>, request: Request, next: Next) -> Response {
+        let client = request
+            .extensions()
+            .get::<ClientInfo>()
+            .cloned()
+            .unwrap_or_else(|| {
+                let peer = request
+                    .extensions()
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| *addr);
+                ClientInfo::derive(peer, request.headers(), &TrustedProxies::default())
+            });
+
+        match check.check(request.method(), request.headers(), client.origin().as_deref()) {
+            Ok(()) => next.run(request).await,
+            Err(reason) => {
+                tracing::warn!(
+                    method = %request.method(),
+                    path = %request.uri().path(),
+                    origin = ?request.headers().get("origin"),
+                    reason,
+                    "Rejected request failing origin validation"
+                );
+                rejection(reason)
+            }
+        }
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn normalize(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_ascii_lowercase()
+}
+
+/// `403 Forbidden`, with the reason in development builds
+fn rejection(reason: &str) -> Response<Body> {
+    let body = if cfg!(debug_assertions) {
+        format!("Origin validation failed: {reason}")
+    } else {
+        "Forbidden".to_string()
+    };
+
+    (StatusCode::FORBIDDEN, body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    const OWN: Option<&str> = Some("https://example.com");
+
+    #[test]
+    fn test_sec_fetch_site() {
+        let check = OriginCheck::new().allow_origin("https://partner.example.org/");
+        let post = |pairs: &[(&'static str, &'static str)]| {
+            check.check(&Method::POST, &headers(pairs), OWN)
+        };
+
+        assert!(post(&[("sec-fetch-site", "same-origin")]).is_ok());
+        assert!(post(&[("sec-fetch-site", "none")]).is_ok());
+        assert!(post(&[("sec-fetch-site", "same-site")]).is_err());
+        assert!(post(&[
+            ("sec-fetch-site", "cross-site"),
+            ("origin", "https://evil.example.net")
+        ])
+        .is_err());
+        assert!(post(&[
+            ("sec-fetch-site", "cross-site"),
+            ("origin", "https://partner.example.org")
+        ])
+        .is_ok());
+
+        let same_site = OriginCheck::new().allow_same_site();
+        assert!(same_site
+            .check(&Method::POST, &headers(&[("sec-fetch-site", "same-site")]), OWN)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_origin_fallback() {
+        let check = OriginCheck::new();
+        let post = |pairs: &[(&'static str, &'static str)]| {
+            check.check(&Method::POST, &headers(pairs), OWN)
+        };
+
+        assert!(post(&[("origin", "https://EXAMPLE.com")]).is_ok());
+        assert!(post(&[("origin", "https://evil.example.net")]).is_err());
+        assert!(post(&[("origin", "null")]).is_err());
+        // Not a browser: nothing to forge
+        assert!(post(&[]).is_ok());
+        assert!(OriginCheck::new()
+            .require_browser_headers()
+            .check(&Method::POST, &HeaderMap::new(), OWN)
+            .is_err());
+    }
+
+    #[test]
+    fn test_safe_methods_pass() {
+        let cross_site = headers(&[
+            ("sec-fetch-site", "cross-site"),
+            ("origin", "https://evil.example.net"),
+        ]);
+        assert!(OriginCheck::new()
+            .check(&Method::GET, &cross_site, OWN)
+            .is_ok());
+        assert!(OriginCheck::new()
+            .check(&Method::DELETE, &cross_site, OWN)
+            .is_err());
+    }
+}
@@ -46,18 +46,18 @@
 use deadpool_redis::Pool as RedisPool;
 
 use axum::{
-    extract::{ConnectInfo, Request, State},
+    extract::{Request, State},
     http::StatusCode,
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use std::collections::HashMap;
-use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::{debug, warn};
 
+use super::forwarded::client_ip;
 use crate::htmx::config::RateLimitConfig;
 
 /// In-memory rate limit entry
@@ -157,11 +157,8 @@ impl RateLimit {
         // Extract user ID from request extensions (set by session middleware)
         let user_id: Option<i64> = request.extensions().get::<i64>().copied();
 
-        // Extract IP address from connection info
-        let ip_addr = request
-            .extensions()
-            .get::<ConnectInfo<SocketAddr>>()
-            .map(|ConnectInfo(addr)| addr.ip().to_string());
+        // Client IP as derived by the trusted proxy configuration
+        let ip_addr = client_ip(request.extensions()).map(|ip| ip.to_string());
 
         // Determine rate limit key and limit
         let path = request.uri().path();
@@ -0,0 +1,651 @@
+//! Request queuing middleware for expensive endpoints
+//!
+//! Bounds how many heavy requests (report generation, exports, ...) run at
+//! once. Requests beyond the concurrency limit wait in a bounded queue that is
+//! drained round-robin per user, so one user submitting many exports cannot
+//! starve everyone else. When the queue is full, or a request waits longer than
+//! the configured maximum, it is shed with a `503 Service Unavailable` page and
+//! a `Retry-After` header instead of piling up unbounded futures.
+//!
+//! Users are identified by session user ID, then client IP, then a shared
+//! anonymous bucket.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::middleware::request_queue::{RequestQueue, RequestQueueConfig};
+//! use axum::{routing::{get, post}, Router};
+//! use std::time::Duration;
+//!
+//! let queue = RequestQueue::new(
+//!     RequestQueueConfig::default()
+//!         .with_max_concurrent(2)
+//!         .with_max_wait(Duration::from_secs(60)),
+//! );
+//!
+//! let heavy = Router::new()
+//!     .route("/reports/export", post(export_report))
+//!     .layer(axum::middleware::from_fn_with_state(
+//!         queue.clone(),
+//!         RequestQueue::middleware,
+//!     ));
+//!
+//! let app = Router::new()
+//!     .merge(heavy)
+//!     .route(
+//!         "/queue/status",
+//!         get(RequestQueue::status_handler).with_state(queue),
+//!     );
+//! ```
+//!
+//! The status partial polls itself every two seconds while the user has a
+//! queued request, showing their position and estimated wait:
+//!
+//! ```html
+//! <div hx-get="/queue/status" hx-trigger="every 2s" hx-swap="outerHTML"></div>
+//! ```
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+
+use super::forwarded::client_ip;
+use crate::htmx::auth::session::SessionData;
+use crate::htmx::template::helpers::escape_html;
+
+/// Weight of the newest sample in the service time moving average
+const SERVICE_TIME_SMOOTHING: f64 = 0.2;
+
+/// Configuration for [`RequestQueue`]
+#[derive(Debug, Clone)]
+pub struct RequestQueueConfig {
+    /// Maximum number of requests running at once
+    pub max_concurrent: usize,
+    /// Maximum number of requests waiting across all users
+    pub max_queue_depth: usize,
+    /// Maximum number of requests a single user may have waiting
+    pub max_per_user: usize,
+    /// Longest a request may wait before it is shed
+    pub max_wait: Duration,
+    /// Initial guess at how long one request takes, refined as requests finish
+    pub expected_service_time: Duration,
+    /// URL the status partial polls (where [`RequestQueue::status_handler`] is mounted)
+    pub status_url: String,
+}
+
+impl Default for RequestQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 4,
+            max_queue_depth: 64,
+            max_per_user: 2,
+            max_wait: Duration::from_secs(30),
+            expected_service_time: Duration::from_secs(5),
+            status_url: "/queue/status".to_string(),
+        }
+    }
+}
+
+impl RequestQueueConfig {
+    /// Set the maximum number of concurrently running requests
+    #[must_use]
+    pub const fn with_max_concurrent(mut self, max: usize) -> Self {
+        self.max_concurrent = max;
+        self
+    }
+
+    /// Set the maximum number of waiting requests across all users
+    #[must_use]
+    pub const fn with_max_queue_depth(mut self, depth: usize) -> Self {
+        self.max_queue_depth = depth;
+        self
+    }
+
+    /// Set the maximum number of waiting requests per user
+    #[must_use]
+    pub const fn with_max_per_user(mut self, max: usize) -> Self {
+        self.max_per_user = max;
+        self
+    }
+
+    /// Set how long a request may wait before it is shed
+    #[must_use]
+    pub const fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Set the initial per-request service time estimate
+    #[must_use]
+    pub const fn with_expected_service_time(mut self, duration: Duration) -> Self {
+        self.expected_service_time = duration;
+        self
+    }
+
+    /// Set the URL polled by the status partial
+    #[must_use]
+    pub fn with_status_url(mut self, url: impl Into<String>) -> Self {
+        self.status_url = url.into();
+        self
+    }
+}
+
+/// A queued request waiting for a slot
+#[derive(Debug)]
+struct Waiter {
+    ticket: u64,
+    grant: oneshot::Sender<()>,
+}
+
+/// Mutable queue state, guarded by a mutex
+#[derive(Debug)]
+struct QueueState {
+    /// Requests currently holding a slot
+    active: usize,
+    /// Waiting requests per user, oldest first
+    waiting: HashMap<String, VecDeque<Waiter>>,
+    /// Users with waiting requests, in round-robin order
+    rotation: VecDeque<String>,
+    /// Total waiting requests
+    queued: usize,
+    next_ticket: u64,
+    /// Moving average of request service time in seconds
+    avg_service_secs: f64,
+}
+
+impl QueueState {
+    /// Hand a freed slot to the next user in rotation, or release it
+    fn hand_off(&mut self) {
+        while let Some(user) = self.rotation.pop_front() {
+            let Some(waiters) = self.waiting.get_mut(&user) else {
+                continue;
+            };
+            let Some(waiter) = waiters.pop_front() else {
+                self.waiting.remove(&user);
+                continue;
+            };
+            self.queued -= 1;
+            if waiters.is_empty() {
+                self.waiting.remove(&user);
+            } else {
+                self.rotation.push_back(user);
+            }
+            if waiter.grant.send(()).is_ok() {
+                return;
+            }
+        }
+        self.active = self.active.saturating_sub(1);
+    }
+
+    /// Remove a waiting ticket, returning whether it was still queued
+    fn remove(&mut self, user: &str, ticket: u64) -> bool {
+        let Some(waiters) = self.waiting.get_mut(user) else {
+            return false;
+        };
+        let Some(index) = waiters.iter().position(|w| w.ticket == ticket) else {
+            return false;
+        };
+        waiters.remove(index);
+        self.queued -= 1;
+        if waiters.is_empty() {
+            self.waiting.remove(user);
+            self.rotation.retain(|u| u != user);
+        }
+        true
+    }
+
+    /// Estimated wait for a request at `position` in line
+    fn estimate_wait(&self, position: usize, max_concurrent: usize) -> Duration {
+        let rounds = position.div_ceil(max_concurrent.max(1));
+        #[allow(clippy::cast_precision_loss)]
+        Duration::from_secs_f64(self.avg_service_secs * rounds as f64)
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: RequestQueueConfig,
+    state: Mutex<QueueState>,
+}
+
+/// Bounded, per-user fair request queue
+///
+/// Cheap to clone; clones share the same queue.
+#[derive(Debug, Clone)]
+pub struct RequestQueue {
+    inner: Arc<Inner>,
+}
+
+/// A running request's slot in the queue
+///
+/// The slot is handed to the next waiting request when this is dropped.
+#[derive(Debug)]
+pub struct QueuePermit {
+    queue: RequestQueue,
+    started: Instant,
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        self.queue.release(self.started.elapsed());
+    }
+}
+
+/// Removes an abandoned waiter if the waiting future is dropped
+struct WaitGuard<'a> {
+    queue: &'a RequestQueue,
+    user: &'a str,
+    ticket: u64,
+    armed: bool,
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let mut state = self.queue.inner.state.lock();
+        if !state.remove(self.user, self.ticket) {
+            // A slot was granted after we stopped listening; pass it on
+            state.hand_off();
+        }
+    }
+}
+
+/// Snapshot of the queue as seen by one user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueStatus {
+    /// The user's place in line (1 = next), or `None` if nothing is waiting
+    pub position: Option<usize>,
+    /// Estimated time until the user's request starts
+    pub estimated_wait: Duration,
+    /// Requests currently running
+    pub active: usize,
+    /// Requests waiting across all users
+    pub queued: usize,
+}
+
+impl RequestQueue {
+    /// Create a new request queue
+    #[must_use]
+    pub fn new(config: RequestQueueConfig) -> Self {
+        let state = QueueState {
+            active: 0,
+            waiting: HashMap::new(),
+            rotation: VecDeque::new(),
+            queued: 0,
+            next_ticket: 0,
+            avg_service_secs: config.expected_service_time.as_secs_f64(),
+        };
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                state: Mutex::new(state),
+            }),
+        }
+    }
+
+    /// Queue configuration
+    #[must_use]
+    pub fn config(&self) -> &RequestQueueConfig {
+        &self.inner.config
+    }
+
+    /// Wait for a slot on behalf of `user`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QueueError::Saturated`] if the queue (or the user's share of it)
+    /// is full, and [`QueueError::TimedOut`] if no slot frees up within
+    /// `max_wait`.
+    pub async fn acquire(&self, user: &str) -> Result<QueuePermit, QueueError> {
+        let config = &self.inner.config;
+
+        let (ticket, mut grant) = {
+            let mut state = self.inner.state.lock();
+
+            if state.active < config.max_concurrent && state.queued == 0 {
+                state.active += 1;
+                return Ok(self.permit());
+            }
+
+            let user_queued = state.waiting.get(user).map_or(0, VecDeque::len);
+            if state.queued >= config.max_queue_depth || user_queued >= config.max_per_user {
+                let retry_after = state.estimate_wait(state.queued + 1, config.max_concurrent);
+                warn!(
+                    user = %user,
+                    queued = state.queued,
+                    user_queued = user_queued,
+                    "Request queue saturated, shedding request"
+                );
+                return Err(QueueError::Saturated { retry_after });
+            }
+
+            let ticket = state.next_ticket;
+            state.next_ticket += 1;
+            let (tx, rx) = oneshot::channel();
+            if user_queued == 0 {
+                state.rotation.push_back(user.to_string());
+            }
+            state
+                .waiting
+                .entry(user.to_string())
+                .or_default()
+                .push_back(Waiter { ticket, grant: tx });
+            state.queued += 1;
+
+            debug!(user = %user, ticket = ticket, queued = state.queued, "Request queued");
+            (ticket, rx)
+        };
+
+        let mut guard = WaitGuard {
+            queue: self,
+            user,
+            ticket,
+            armed: true,
+        };
+
+        let granted = tokio::time::timeout(config.max_wait, &mut grant).await;
+        guard.armed = false;
+
+        match granted {
+            Ok(Ok(())) => Ok(self.permit()),
+            Ok(Err(_)) => Err(QueueError::TimedOut {
+                waited: config.max_wait,
+            }),
+            Err(_) => {
+                // A slot may have been granted just as the timer fired
+                if self.inner.state.lock().remove(user, ticket) {
+                    Err(QueueError::TimedOut {
+                        waited: config.max_wait,
+                    })
+                } else {
+                    Ok(self.permit())
+                }
+            }
+        }
+    }
+
+    /// Current queue status for `user`
+    #[must_use]
+    pub fn status(&self, user: &str) -> QueueStatus {
+        let state = self.inner.state.lock();
+        let position = state
+            .rotation
+            .iter()
+            .position(|u| u == user)
+            .map(|index| index + 1);
+        let estimated_wait = position.map_or(Duration::ZERO, |p| {
+            state.estimate_wait(p, self.inner.config.max_concurrent)
+        });
+
+        QueueStatus {
+            position,
+            estimated_wait,
+            active: state.active,
+            queued: state.queued,
+        }
+    }
+
+    /// Middleware function that runs the request once a slot is available
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QueueError`] if the request is shed.
+    pub async fn middleware(
+        State(queue): State<Self>,
+        request: Request,
+        next: Next,
+    ) -> Result<Response, QueueError> {
+        let user = queue_key(&request);
+        let _permit = queue.acquire(&user).await?;
+        Ok(next.run(request).await)
+    }
+
+    /// HTMX partial showing the caller's place in line
+    ///
+    /// Polls itself every two seconds while the user has a queued request and
+    /// stops polling once nothing is waiting.
+    #[allow(clippy::unused_async)] // Axum handlers must be async
+    pub async fn status_handler(State(queue): State<Self>, request: Request) -> Html<String> {
+        let status = queue.status(&queue_key(&request));
+        Html(render_status(&status, &queue.inner.config.status_url))
+    }
+
+    fn permit(&self) -> QueuePermit {
+        QueuePermit {
+            queue: self.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    fn release(&self, elapsed: Duration) {
+        let mut state = self.inner.state.lock();
+        state.avg_service_secs = SERVICE_TIME_SMOOTHING.mul_add(
+            elapsed.as_secs_f64(),
+            (1.0 - SERVICE_TIME_SMOOTHING) * state.avg_service_secs,
+        );
+        state.hand_off();
+    }
+}
+
+/// Identify the user a request is queued under
+fn queue_key(request: &Request) -> String {
+    if let Some(user_id) = request
+        .extensions()
+        .get::<SessionData>()
+        .and_then(|session| session.user_id)
+    {
+        return format!("user:{user_id}");
+    }
+
+    client_ip(request.extensions())
+        .map_or_else(|| "anonymous".to_string(), |ip| format!("ip:{ip}"))
+}
+
+/// Render the polling status partial
+fn render_status(status: &QueueStatus, status_url: &str) -> String {
+    let url = escape_html(status_url);
+    status.position.map_or_else(
+        || {
+            format!(
+                r#"<div id="request-queue-status" class="queue-status" hx-get="{url}" hx-trigger="every 2s" hx-swap="outerHTML">Your request is being processed.</div>"#
+            )
+        },
+        |position| {
+            format!(
+                r#"<div id="request-queue-status" class="queue-status" hx-get="{url}" hx-trigger="every 2s" hx-swap="outerHTML">You are number {position} in line. Estimated wait: {}.</div>"#,
+                format_wait(status.estimated_wait)
+            )
+        },
+    )
+}
+
+/// Human-friendly rendering of a wait estimate
+fn format_wait(wait: Duration) -> String {
+    let secs = wait.as_secs();
+    match secs {
+        0 => "less than a second".to_string(),
+        1 => "about 1 second".to_string(),
+        2..=59 => format!("about {secs} seconds"),
+        60..=119 => "about 1 minute".to_string(),
+        _ => format!("about {} minutes", secs / 60),
+    }
+}
+
+/// Request queue errors
+#[derive(Debug, thiserror::Error)]
+pub enum QueueError {
+    /// The queue is full
+    #[error("Request queue is full")]
+    Saturated {
+        /// Suggested time before retrying
+        retry_after: Duration,
+    },
+
+    /// The request waited too long for a slot
+    #[error("Request waited {waited:?} without getting a slot")]
+    TimedOut {
+        /// How long the request waited
+        waited: Duration,
+    },
+}
+
+impl QueueError {
+    /// Suggested `Retry-After` in whole seconds (at least one)
+    #[must_use]
+    pub fn retry_after_secs(&self) -> u64 {
+        let wait = match self {
+            Self::Saturated { retry_after } => *retry_after,
+            Self::TimedOut { .. } => Duration::ZERO,
+        };
+        wait.as_secs().max(1)
+    }
+}
+
+impl IntoResponse for QueueError {
+    fn into_response(self) -> Response {
+        let retry_after = self.retry_after_secs();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RETRY_AFTER, retry_after.into());
+        headers.insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("text/html; charset=utf-8"),
+        );
+
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head><meta charset="utf-8"><title>503 - Busy</title></head>
+<body>
+    <div class="error-container">
+        <h1>We're a little busy</h1>
+        <p>Too many requests like this one are running right now. Please try again in {}.</p>
+        <p><a href="javascript:location.reload()">Try again</a></p>
+    </div>
+</body>
+</html>"#,
+            format_wait(Duration::from_secs(retry_after)).trim_start_matches("about ")
+        );
+
+        (StatusCode::SERVICE_UNAVAILABLE, headers, html).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(max_concurrent: usize, depth: usize, per_user: usize) -> RequestQueue {
+        RequestQueue::new(
+            RequestQueueConfig::default()
+                .with_max_concurrent(max_concurrent)
+                .with_max_queue_depth(depth)
+                .with_max_per_user(per_user)
+                .with_max_wait(Duration::from_secs(5)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_acquire_within_limit() {
+        let queue = queue(2, 4, 2);
+        let _a = queue.acquire("user:1").await.unwrap();
+        let _b = queue.acquire("user:2").await.unwrap();
+        let status = queue.status("user:1");
+        assert_eq!(status.active, 2);
+        assert_eq!(status.position, None);
+    }
+
+    #[tokio::test]
+    async fn test_saturated_when_user_share_full() {
+        let queue = queue(1, 4, 1);
+        let _running = queue.acquire("user:1").await.unwrap();
+
+        let waiting = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.acquire("user:2").await.map(drop) })
+        };
+        while queue.status("user:2").position.is_none() {
+            tokio::task::yield_now().await;
+        }
+
+        let err = queue.acquire("user:2").await.unwrap_err();
+        assert!(matches!(err, QueueError::Saturated { .. }));
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+        waiting.abort();
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_between_users() {
+        let queue = queue(1, 8, 4);
+        let running = queue.acquire("user:a").await.unwrap();
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for (queued, user) in ["user:a", "user:a", "user:b"].into_iter().enumerate() {
+            let task_queue = queue.clone();
+            let order_tx = order_tx.clone();
+            tokio::spawn(async move {
+                let _permit = task_queue.acquire(user).await.unwrap();
+                order_tx.send(user).unwrap();
+            });
+            // Let each request enqueue before the next one
+            while queue.status(user).queued <= queued {
+                tokio::task::yield_now().await;
+            }
+        }
+        assert_eq!(queue.status("user:b").position, Some(2));
+
+        drop(running);
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            order.push(order_rx.recv().await.unwrap());
+        }
+        assert_eq!(order, vec!["user:a", "user:b", "user:a"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_times_out_and_leaves_queue() {
+        let queue = queue(1, 4, 2);
+        let _running = queue.acquire("user:1").await.unwrap();
+
+        let err = queue.acquire("user:2").await.unwrap_err();
+        assert!(matches!(err, QueueError::TimedOut { .. }));
+        assert_eq!(queue.status("user:2").queued, 0);
+    }
+
+    #[test]
+    fn test_render_status_polls() {
+        let queued = QueueStatus {
+            position: Some(3),
+            estimated_wait: Duration::from_secs(12),
+            active: 1,
+            queued: 3,
+        };
+        let html = render_status(&queued, "/queue/status");
+        assert!(html.contains("number 3 in line"));
+        assert!(html.contains("about 12 seconds"));
+        assert!(html.contains(r#"hx-trigger="every 2s""#));
+
+        let done = QueueStatus {
+            position: None,
+            estimated_wait: Duration::ZERO,
+            active: 0,
+            queued: 0,
+        };
+        let html = render_status(&done, "/queue/status");
+        assert!(html.contains("being processed"));
+        assert!(html.contains(r#"hx-trigger="every 2s""#));
+    }
+}
@@ -4,7 +4,9 @@
 //! and persistence across requests. Integrates with the `SessionManagerAgent`
 //! for session storage.
 
-use crate::htmx::agents::{LoadSession, SaveSession};
+use crate::htmx::agents::{
+    DeleteSession, LoadSession, Mailbox, MailboxError, SaveSession, SupervisedAgent,
+};
 use crate::htmx::auth::session::{SessionData, SessionId};
 use crate::htmx::state::ActonHtmxState;
 use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
@@ -12,7 +14,8 @@ use axum::{
     body::Body,
     extract::Request,
     http::header::{COOKIE, SET_COOKIE},
-    response::Response,
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
 use std::str::FromStr;
 use std::sync::Arc;
@@ -23,6 +26,20 @@ use tower::{Layer, Service};
 /// Session cookie name
 pub const SESSION_COOKIE_NAME: &str = "acton_session";
 
+/// Response extension asking the session middleware to move the session to
+/// a fresh ID
+///
+/// Insert it when privileges change (login, logout, password change) so a
+/// session ID planted in the browser before login cannot be used after it.
+/// The middleware saves the session data under a new ID, deletes the old
+/// session and sends the new cookie.
+///
+/// ```rust,ignore
+/// response.extensions_mut().insert(RegenerateSession);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RegenerateSession;
+
 /// Session configuration for middleware
 #[derive(Clone, Debug)]
 pub struct SessionConfig {
@@ -87,14 +104,16 @@ impl SameSite {
 #[derive(Clone)]
 pub struct SessionLayer {
     config: SessionConfig,
-    session_manager: ActorHandle,
+    session_manager: SupervisedAgent,
+    mailbox: Option<Mailbox>,
 }
 
 impl std::fmt::Debug for SessionLayer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SessionLayer")
             .field("config", &self.config)
-            .field("session_manager", &"ActorHandle")
+            .field("session_manager", &self.session_manager)
+            .field("mailbox", &self.mailbox)
             .finish()
     }
 }
@@ -105,7 +124,8 @@ impl SessionLayer {
     pub fn new(state: &ActonHtmxState) -> Self {
         Self {
             config: SessionConfig::default(),
-            session_manager: state.session_manager().clone(),
+            session_manager: state.supervised_session_manager().clone(),
+            mailbox: Some(state.session_mailbox().clone()),
         }
     }
 
@@ -114,16 +134,20 @@ impl SessionLayer {
     pub fn with_config(state: &ActonHtmxState, config: SessionConfig) -> Self {
         Self {
             config,
-            session_manager: state.session_manager().clone(),
+            session_manager: state.supervised_session_manager().clone(),
+            mailbox: Some(state.session_mailbox().clone()),
         }
     }
 
     /// Create session layer from an existing agent handle
+    ///
+    /// Messages go straight to the agent, without a bounded mailbox.
     #[must_use]
     pub fn from_handle(session_manager: ActorHandle) -> Self {
         Self {
             config: SessionConfig::default(),
-            session_manager,
+            session_manager: SupervisedAgent::new("session_manager", session_manager),
+            mailbox: None,
         }
     }
 }
@@ -136,6 +160,7 @@ impl<S> Layer<S> for SessionLayer {
             inner,
             config: Arc::new(self.config.clone()),
             session_manager: self.session_manager.clone(),
+            mailbox: self.mailbox.clone(),
         }
     }
 }
@@ -148,7 +173,8 @@ impl<S> Layer<S> for SessionLayer {
 pub struct SessionMiddleware<S> {
     inner: S,
     config: Arc<SessionConfig>,
-    session_manager: ActorHandle,
+    session_manager: SupervisedAgent,
+    mailbox: Option<Mailbox>,
 }
 
 impl<S: std::fmt::Debug> std::fmt::Debug for SessionMiddleware<S> {
@@ -156,7 +182,8 @@ impl<S: std::fmt::Debug> std::fmt::Debug for SessionMiddleware<S> {
         f.debug_struct("SessionMiddleware")
             .field("inner", &self.inner)
             .field("config", &self.config)
-            .field("session_manager", &"ActorHandle")
+            .field("session_manager", &self.session_manager)
+            .field("mailbox", &self.mailbox)
             .finish()
     }
 }
@@ -179,6 +206,7 @@ where
     fn call(&mut self, mut req: Request) -> Self::Future {
         let config = self.config.clone();
         let session_manager = self.session_manager.clone();
+        let mailbox = self.mailbox.clone();
         let mut inner = self.inner.clone();
         let timeout = Duration::from_millis(config.agent_timeout_ms);
 
@@ -190,7 +218,11 @@ where
             let (session_id, session_data, is_new) = if let Some(id) = existing_session_id {
                 // Try to load existing session from agent
                 let (request, rx) = LoadSession::with_response(id.clone());
-                session_manager.send(request).await;
+                if let Err(e) = send(&session_manager, mailbox.as_ref(), request).await {
+                    // Starting a fresh session would sign the user out
+                    tracing::warn!(error = %e, "Session manager overloaded, shedding request");
+                    return Ok(StatusCode::SERVICE_UNAVAILABLE.into_response());
+                }
 
                 // Wait for response with timeout
                 if let Ok(Ok(Some(data))) = tokio::time::timeout(timeout, rx).await {
@@ -221,12 +253,28 @@ where
                 .cloned()
                 .unwrap_or(session_data);
 
+            // Move the session to a fresh ID when the handler asked for it
+            let regenerate = response.extensions_mut().remove::<RegenerateSession>().is_some();
+            let session_id = if regenerate {
+                if !is_new {
+                    let delete = DeleteSession { session_id };
+                    if let Err(e) = send(&session_manager, mailbox.as_ref(), delete).await {
+                        tracing::warn!(error = %e, "Session manager overloaded, old session kept");
+                    }
+                }
+                SessionId::generate()
+            } else {
+                session_id
+            };
+
             // Save session to agent (fire-and-forget for performance)
             let save_request = SaveSession::new(session_id.clone(), final_session_data);
-            session_manager.send(save_request).await;
+            if let Err(e) = send(&session_manager, mailbox.as_ref(), save_request).await {
+                tracing::warn!(error = %e, "Session manager overloaded, session changes lost");
+            }
 
             // Set session cookie if new
-            if is_new {
+            if is_new || regenerate {
                 set_session_cookie(&mut response, &session_id, &config);
             }
 
@@ -253,6 +301,19 @@ fn extract_session_id(req: &Request, cookie_name: &str) -> Option<SessionId> {
     None
 }
 
+/// Send to the session manager through its mailbox, if it has one
+async fn send<M: acton_reactive::prelude::ActonMessage + 'static>(
+    session_manager: &SupervisedAgent,
+    mailbox: Option<&Mailbox>,
+    message: M,
+) -> Result<(), MailboxError> {
+    if let Some(mailbox) = mailbox {
+        return mailbox.send(message).await;
+    }
+    session_manager.handle().send(message).await;
+    Ok(())
+}
+
 /// Set session cookie on response
 fn set_session_cookie(
     response: &mut Response<Body>,
@@ -461,18 +522,38 @@ where
                 .cloned()
                 .unwrap_or(session_data);
 
-            // Save session to auth-service (fire-and-forget for performance)
-            let _ = save_session_via_service(
-                &services,
-                &session_id,
-                &final_session_data,
-                timeout_duration,
-            )
-            .await;
+            // Move the session to a fresh ID when the handler asked for it
+            let regenerated = if response.extensions_mut().remove::<RegenerateSession>().is_some() {
+                regenerate_session_via_service(
+                    &services,
+                    &session_id,
+                    &final_session_data,
+                    timeout_duration,
+                    i64::try_from(config.max_age_secs).unwrap_or(86400),
+                )
+                .await
+                .inspect_err(|e| tracing::warn!(error = %e, "Session regeneration failed"))
+                .ok()
+            } else {
+                None
+            };
 
-            // Set session cookie if new
-            if is_new {
-                set_session_cookie(&mut response, &session_id, &config);
+            if let Some(new_id) = regenerated {
+                set_session_cookie(&mut response, &new_id, &config);
+            } else {
+                // Save session to auth-service (fire-and-forget for performance)
+                let _ = save_session_via_service(
+                    &services,
+                    &session_id,
+                    &final_session_data,
+                    timeout_duration,
+                )
+                .await;
+
+                // Set session cookie if new
+                if is_new {
+                    set_session_cookie(&mut response, &session_id, &config);
+                }
             }
 
             Ok(response)
@@ -552,6 +633,35 @@ async fn save_session_via_service(
     Ok(())
 }
 
+/// Create a session holding `session_data` via the auth-service and destroy
+/// the old one, returning the new ID
+#[cfg(feature = "microservices")]
+async fn regenerate_session_via_service(
+    services: &crate::htmx::clients::ServiceRegistry,
+    old_id: &SessionId,
+    session_data: &SessionData,
+    timeout: Duration,
+    ttl_seconds: i64,
+) -> Result<SessionId, crate::htmx::clients::ClientError> {
+    let auth = services.auth()?;
+    let data = session_data_to_hashmap(session_data);
+
+    tokio::time::timeout(timeout, async {
+        let mut client = auth.write().await;
+        let session = client
+            .create_session(session_data.user_id, ttl_seconds, data)
+            .await?;
+        let new_id = SessionId::from_str(&session.session_id).map_err(|_| {
+            crate::htmx::clients::ClientError::ResponseError("Invalid session ID".to_string())
+        })?;
+        client.destroy_session(old_id.as_str()).await?;
+        drop(client);
+        Ok(new_id)
+    })
+    .await
+    .map_err(|_| crate::htmx::clients::ClientError::RequestFailed("timeout".to_string()))?
+}
+
 /// Convert proto Session to local SessionData
 #[cfg(feature = "microservices")]
 fn proto_session_to_session_data(
@@ -574,7 +684,11 @@ fn proto_session_to_session_data(
 
     // Copy additional data fields
     for (key, value) in &proto.data {
-        let _ = session_data.set(key.clone(), value.clone());
+        if key == "timezone" {
+            session_data.timezone = Some(value.clone());
+        } else {
+            let _ = session_data.set(key.clone(), value.clone());
+        }
     }
 
     session_data
@@ -582,7 +696,7 @@ fn proto_session_to_session_data(
 
 /// Convert SessionData to HashMap for proto
 #[cfg(feature = "microservices")]
-fn session_data_to_hashmap(session_data: &SessionData) -> std::collections::HashMap<String, String> {
+pub(crate) fn session_data_to_hashmap(session_data: &SessionData) -> std::collections::HashMap<String, String> {
     let mut map = std::collections::HashMap::new();
 
     // Add user_name if present
@@ -590,6 +704,10 @@ fn session_data_to_hashmap(session_data: &SessionData) -> std::collections::Hash
         map.insert("user_name".to_string(), name.clone());
     }
 
+    if let Some(ref timezone) = session_data.timezone {
+        map.insert("timezone".to_string(), timezone.clone());
+    }
+
     // Add all custom data from session
     for (key, value) in &session_data.data {
         if let Some(s) = value.as_str() {
@@ -47,22 +47,54 @@
 
 // Public modules
 pub mod agents;
+pub mod analytics;
 pub mod auth;
+#[cfg(feature = "billing")]
+pub mod billing;
+pub mod bulk;
 pub mod config;
+pub mod consent;
 pub mod email;
+pub mod encryption;
 pub mod error;
+pub mod experiments;
+pub mod export;
 pub mod extractors;
+pub mod feedback;
 pub mod forms;
 pub mod handlers;
 pub mod health;
+#[cfg(feature = "http3")]
+pub mod http3;
+pub mod invitations;
 pub mod jobs;
+pub mod locking;
+#[cfg(feature = "markdown")]
+pub mod markdown;
 pub mod middleware;
+pub mod money;
 pub mod oauth2;
 pub mod observability;
+pub mod pagination;
+pub mod presence;
+pub mod privacy;
+pub mod proxy_protocol;
 pub mod responses;
+pub mod routing;
+#[cfg(feature = "sanitize")]
+pub mod sanitize;
+#[cfg(feature = "scim")]
+pub mod scim;
+pub mod sitemap;
+pub mod slug;
+pub mod social;
 pub mod state;
 pub mod storage;
+pub mod teams;
 pub mod template;
+pub mod timezone;
+pub mod typeahead;
+pub(crate) mod util;
 
 // Microservices clients (available with microservices feature)
 #[cfg(feature = "microservices")]
@@ -72,8 +104,8 @@ pub mod clients;
 #[cfg(feature = "microservices")]
 pub mod embedded;
 
-// Testing utilities module (available in test builds)
-#[cfg(test)]
+// Testing utilities module (available in test builds, fixtures with the testing feature)
+#[cfg(any(test, feature = "testing"))]
 pub mod testing;
 
 pub mod prelude {
@@ -173,6 +205,7 @@ pub mod prelude {
     pub use acton_reactive;
     pub use anyhow;
     pub use askama;
+    pub use async_trait;
     pub use axum;
     pub use serde;
     pub use serde_json;
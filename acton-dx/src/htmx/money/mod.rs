@@ -0,0 +1,762 @@
+//! Money amounts without floating point
+//!
+//! [`Money`] stores an integer number of minor units (cents, pence, yen)
+//! together with its [`Currency`], so totals never drift the way `f64`
+//! arithmetic does. All arithmetic is checked and refuses to mix currencies.
+//!
+//! - serde: `{"amount": 1999, "currency": "USD"}`
+//! - sqlx: [`Currency`] maps to a text column; `Money` implements `FromRow`
+//!   for `amount` (BIGINT) and `currency` (TEXT) columns, so it can be
+//!   embedded with `#[sqlx(flatten)]`
+//! - data-service (`microservices` feature): [`Money::to_params`] and
+//!   [`Money::from_proto_row`]
+//! - validator: [`validate_positive`] and [`validate_non_negative`]
+//! - Askama: [`filters::money`], [`filters::money_code`] and
+//!   [`filters::money_plain`] format without building intermediate strings
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::money::{Currency, Money};
+//!
+//! let price = Money::parse("19.99", Currency::USD)?;
+//! let total = price.checked_mul(3)?.checked_add(Money::new(500, Currency::USD))?;
+//! assert_eq!(total.to_string(), "$64.97");
+//!
+//! // Split a bill without losing a cent
+//! let shares = total.allocate(&[1, 1, 1])?;
+//! ```
+//!
+//! ```html
+//! <td>{{ line.total|money }}</td>   <!-- $1,234.50 -->
+//! <td>{{ line.total|money_code }}</td>   <!-- 1,234.50 USD -->
+//! <input name="amount" value="{{ line.total|money_plain }}">   <!-- 1234.50 -->
+//! ```
+
+#[cfg(feature = "microservices")]
+mod proto;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Money errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MoneyError {
+    /// Operands have different currencies
+    #[error("Currency mismatch: {0} and {1}")]
+    CurrencyMismatch(Currency, Currency),
+
+    /// Result does not fit in the minor unit range
+    #[error("Money amount overflow")]
+    Overflow,
+
+    /// Currency code is not supported
+    #[error("Unknown currency '{0}'")]
+    UnknownCurrency(String),
+
+    /// Amount string could not be parsed
+    #[error("Invalid amount '{0}'")]
+    InvalidAmount(String),
+
+    /// Allocation ratios are empty or sum to zero
+    #[error("Allocation ratios must not be empty or all zero")]
+    InvalidRatios,
+
+    /// Stored value has the wrong shape
+    #[error("Invalid stored money value: {0}")]
+    InvalidValue(String),
+}
+
+/// ISO 4217 currency with its minor unit exponent
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency {
+    code: [u8; 3],
+    exponent: u8,
+}
+
+/// Supported currencies: code, minor unit exponent, symbol
+const CURRENCIES: &[(&str, u8, Option<&str>)] = &[
+    ("AED", 2, None),
+    ("AUD", 2, Some("A$")),
+    ("BHD", 3, None),
+    ("BRL", 2, Some("R$")),
+    ("CAD", 2, Some("CA$")),
+    ("CHF", 2, None),
+    ("CLP", 0, None),
+    ("CNY", 2, Some("CN¥")),
+    ("CZK", 2, None),
+    ("DKK", 2, None),
+    ("EUR", 2, Some("€")),
+    ("GBP", 2, Some("£")),
+    ("HKD", 2, Some("HK$")),
+    ("HUF", 2, None),
+    ("IDR", 2, None),
+    ("ILS", 2, Some("₪")),
+    ("INR", 2, Some("₹")),
+    ("ISK", 0, None),
+    ("JPY", 0, Some("¥")),
+    ("KRW", 0, Some("₩")),
+    ("KWD", 3, None),
+    ("MXN", 2, Some("MX$")),
+    ("NOK", 2, None),
+    ("NZD", 2, Some("NZ$")),
+    ("OMR", 3, None),
+    ("PHP", 2, Some("₱")),
+    ("PLN", 2, None),
+    ("SAR", 2, None),
+    ("SEK", 2, None),
+    ("SGD", 2, None),
+    ("THB", 2, Some("฿")),
+    ("TRY", 2, None),
+    ("TWD", 2, Some("NT$")),
+    ("USD", 2, Some("$")),
+    ("VND", 0, Some("₫")),
+    ("ZAR", 2, None),
+];
+
+impl Currency {
+    /// US dollar
+    pub const USD: Self = Self::from_parts(*b"USD", 2);
+    /// Euro
+    pub const EUR: Self = Self::from_parts(*b"EUR", 2);
+    /// Pound sterling
+    pub const GBP: Self = Self::from_parts(*b"GBP", 2);
+    /// Japanese yen
+    pub const JPY: Self = Self::from_parts(*b"JPY", 0);
+
+    const fn from_parts(code: [u8; 3], exponent: u8) -> Self {
+        Self { code, exponent }
+    }
+
+    /// Look up a currency by ISO 4217 code (case-insensitive)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MoneyError::UnknownCurrency`] for unsupported codes.
+    pub fn from_code(code: &str) -> Result<Self, MoneyError> {
+        let upper = code.trim().to_ascii_uppercase();
+        CURRENCIES
+            .iter()
+            .find(|(known, _, _)| *known == upper)
+            .map(|(known, exponent, _)| {
+                let bytes = known.as_bytes();
+                Self::from_parts([bytes[0], bytes[1], bytes[2]], *exponent)
+            })
+            .ok_or_else(|| MoneyError::UnknownCurrency(code.to_string()))
+    }
+
+    /// Three-letter code
+    #[must_use]
+    pub fn code(&self) -> &str {
+        // Codes are built from the ASCII table above
+        std::str::from_utf8(&self.code).unwrap_or("XXX")
+    }
+
+    /// Number of minor unit digits (2 for USD, 0 for JPY)
+    #[must_use]
+    pub const fn exponent(&self) -> u8 {
+        self.exponent
+    }
+
+    /// Display symbol, if the currency has an unambiguous one
+    #[must_use]
+    pub fn symbol(&self) -> Option<&'static str> {
+        CURRENCIES
+            .iter()
+            .find(|(known, _, _)| known.as_bytes() == self.code)
+            .and_then(|(_, _, symbol)| *symbol)
+    }
+
+    /// Minor units per major unit
+    const fn scale(self) -> i64 {
+        10_i64.pow(self.exponent as u32)
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Currency({})", self.code())
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl std::str::FromStr for Currency {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_code(s)
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Self::from_code(&code).map_err(serde::de::Error::custom)
+    }
+}
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for Currency
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'q, DB: sqlx::Database> sqlx::Encode<'q, DB> for Currency
+where
+    String: sqlx::Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        self.code().to_string().encode_by_ref(buf)
+    }
+}
+
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for Currency
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(
+        value: <DB as sqlx::Database>::ValueRef<'r>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let code = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(Self::from_code(&code)?)
+    }
+}
+
+/// Amount of money in minor units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    /// Amount in minor units (cents for USD)
+    amount: i64,
+    /// Currency of the amount
+    currency: Currency,
+}
+
+impl Money {
+    /// Create from minor units
+    #[must_use]
+    pub const fn new(minor_units: i64, currency: Currency) -> Self {
+        Self {
+            amount: minor_units,
+            currency,
+        }
+    }
+
+    /// Zero in a currency
+    #[must_use]
+    pub const fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Create from whole major units (dollars for USD)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MoneyError::Overflow`] if the amount does not fit.
+    pub const fn from_major(major_units: i64, currency: Currency) -> Result<Self, MoneyError> {
+        match major_units.checked_mul(currency.scale()) {
+            Some(amount) => Ok(Self::new(amount, currency)),
+            None => Err(MoneyError::Overflow),
+        }
+    }
+
+    /// Parse a decimal amount such as `1,234.50` or `-3.5`
+    ///
+    /// Thousands separators are ignored. More fractional digits than the
+    /// currency allows are rejected rather than rounded.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MoneyError::InvalidAmount`] for malformed input and
+    /// [`MoneyError::Overflow`] for amounts that do not fit.
+    pub fn parse(input: &str, currency: Currency) -> Result<Self, MoneyError> {
+        let invalid = || MoneyError::InvalidAmount(input.to_string());
+        let trimmed = input.trim();
+        let (negative, digits) = trimmed
+            .strip_prefix('-')
+            .map_or((false, trimmed), |rest| (true, rest));
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let exponent = usize::from(currency.exponent);
+        if fraction.len() > exponent
+            || (whole.is_empty() && fraction.is_empty())
+            || !fraction.bytes().all(|b| b.is_ascii_digit())
+            || !whole.bytes().all(|b| b.is_ascii_digit() || b == b',')
+        {
+            return Err(invalid());
+        }
+
+        let mut amount: i64 = 0;
+        let padding = std::iter::repeat_n(b'0', exponent - fraction.len());
+        for digit in whole
+            .bytes()
+            .filter(u8::is_ascii_digit)
+            .chain(fraction.bytes())
+            .chain(padding)
+        {
+            amount = amount
+                .checked_mul(10)
+                .and_then(|a| a.checked_add(i64::from(digit - b'0')))
+                .ok_or(MoneyError::Overflow)?;
+        }
+        Ok(Self::new(if negative { -amount } else { amount }, currency))
+    }
+
+    /// Amount in minor units
+    #[must_use]
+    pub const fn minor_units(&self) -> i64 {
+        self.amount
+    }
+
+    /// Currency of the amount
+    #[must_use]
+    pub const fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Whether the amount is zero
+    #[must_use]
+    pub const fn is_zero(&self) -> bool {
+        self.amount == 0
+    }
+
+    /// Whether the amount is greater than zero
+    #[must_use]
+    pub const fn is_positive(&self) -> bool {
+        self.amount > 0
+    }
+
+    /// Whether the amount is less than zero
+    #[must_use]
+    pub const fn is_negative(&self) -> bool {
+        self.amount < 0
+    }
+
+    const fn same_currency(self, other: Self) -> Result<(), MoneyError> {
+        if self.currency.code[0] == other.currency.code[0]
+            && self.currency.code[1] == other.currency.code[1]
+            && self.currency.code[2] == other.currency.code[2]
+        {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch(self.currency, other.currency))
+        }
+    }
+
+    /// Add two amounts of the same currency
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on currency mismatch or overflow.
+    pub fn checked_add(self, other: Self) -> Result<Self, MoneyError> {
+        self.same_currency(other)?;
+        self.amount
+            .checked_add(other.amount)
+            .map(|amount| Self::new(amount, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Subtract an amount of the same currency
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on currency mismatch or overflow.
+    pub fn checked_sub(self, other: Self) -> Result<Self, MoneyError> {
+        self.same_currency(other)?;
+        self.amount
+            .checked_sub(other.amount)
+            .map(|amount| Self::new(amount, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Multiply by a quantity
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MoneyError::Overflow`] if the result does not fit.
+    pub fn checked_mul(self, quantity: i64) -> Result<Self, MoneyError> {
+        self.amount
+            .checked_mul(quantity)
+            .map(|amount| Self::new(amount, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Negate the amount
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MoneyError::Overflow`] for the minimum amount.
+    pub fn checked_neg(self) -> Result<Self, MoneyError> {
+        self.amount
+            .checked_neg()
+            .map(|amount| Self::new(amount, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Compare two amounts of the same currency
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MoneyError::CurrencyMismatch`] for different currencies.
+    pub fn checked_cmp(&self, other: &Self) -> Result<Ordering, MoneyError> {
+        self.same_currency(*other)?;
+        Ok(self.amount.cmp(&other.amount))
+    }
+
+    /// Sum amounts of one currency
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on currency mismatch or overflow.
+    pub fn checked_sum<I>(currency: Currency, amounts: I) -> Result<Self, MoneyError>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        amounts
+            .into_iter()
+            .try_fold(Self::zero(currency), Self::checked_add)
+    }
+
+    /// Split by ratios without losing minor units
+    ///
+    /// Leftover minor units go to the first shares, one each, so the parts
+    /// always add up to the original amount.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MoneyError::InvalidRatios`] if `ratios` is empty or all zero.
+    pub fn allocate(self, ratios: &[u32]) -> Result<Vec<Self>, MoneyError> {
+        let total: i128 = ratios.iter().map(|&r| i128::from(r)).sum();
+        if total == 0 {
+            return Err(MoneyError::InvalidRatios);
+        }
+
+        let amount = i128::from(self.amount);
+        let mut shares: Vec<i128> = ratios
+            .iter()
+            .map(|&r| amount * i128::from(r) / total)
+            .collect();
+        let mut remainder = amount - shares.iter().sum::<i128>();
+        let step = remainder.signum();
+        for share in shares.iter_mut().zip(ratios).filter(|(_, &r)| r > 0) {
+            if remainder == 0 {
+                break;
+            }
+            *share.0 += step;
+            remainder -= step;
+        }
+
+        shares
+            .into_iter()
+            .map(|share| {
+                i64::try_from(share)
+                    .map(|amount| Self::new(amount, self.currency))
+                    .map_err(|_| MoneyError::Overflow)
+            })
+            .collect()
+    }
+
+    /// Formatter with a currency symbol and thousands separators (`$1,234.50`)
+    #[must_use]
+    pub const fn display(&self) -> MoneyFormat {
+        MoneyFormat::new(*self, MoneyStyle::Symbol)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display().fmt(f)
+    }
+}
+
+impl<'r, R> sqlx::FromRow<'r, R> for Money
+where
+    R: sqlx::Row,
+    &'static str: sqlx::ColumnIndex<R>,
+    i64: sqlx::Type<R::Database> + sqlx::Decode<'r, R::Database>,
+    String: sqlx::Type<R::Database> + sqlx::Decode<'r, R::Database>,
+{
+    fn from_row(row: &'r R) -> Result<Self, sqlx::Error> {
+        let amount: i64 = row.try_get("amount")?;
+        let code: String = row.try_get("currency")?;
+        let currency = Currency::from_code(&code).map_err(|e| sqlx::Error::ColumnDecode {
+            index: "currency".to_string(),
+            source: Box::new(e),
+        })?;
+        Ok(Self::new(amount, currency))
+    }
+}
+
+/// How [`MoneyFormat`] renders an amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoneyStyle {
+    /// `$1,234.50`, falling back to `USD 1,234.50` without a symbol
+    Symbol,
+    /// `1,234.50 USD`
+    Code,
+    /// `1234.50`, suitable for form inputs
+    Plain,
+}
+
+/// Allocation-free formatter for [`Money`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoneyFormat {
+    money: Money,
+    style: MoneyStyle,
+}
+
+impl MoneyFormat {
+    /// Format `money` in `style`
+    #[must_use]
+    pub const fn new(money: Money, style: MoneyStyle) -> Self {
+        Self { money, style }
+    }
+}
+
+impl fmt::Display for MoneyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let currency = self.money.currency;
+        let magnitude = self.money.amount.unsigned_abs();
+        let scale = currency.scale().unsigned_abs();
+        let (major, minor) = (magnitude / scale, magnitude % scale);
+
+        if self.money.is_negative() {
+            f.write_str("-")?;
+        }
+        if self.style == MoneyStyle::Symbol {
+            match currency.symbol() {
+                Some(symbol) => f.write_str(symbol)?,
+                None => write!(f, "{} ", currency.code())?,
+            }
+        }
+        if self.style == MoneyStyle::Plain {
+            write!(f, "{major}")?;
+        } else {
+            write_grouped(f, major)?;
+        }
+        if currency.exponent > 0 {
+            write!(
+                f,
+                ".{minor:0width$}",
+                width = usize::from(currency.exponent)
+            )?;
+        }
+        if self.style == MoneyStyle::Code {
+            write!(f, " {}", currency.code())?;
+        }
+        Ok(())
+    }
+}
+
+/// Write an integer with `,` every three digits
+fn write_grouped(f: &mut fmt::Formatter<'_>, value: u64) -> fmt::Result {
+    if value < 1_000 {
+        write!(f, "{value}")
+    } else {
+        write_grouped(f, value / 1_000)?;
+        write!(f, ",{:03}", value % 1_000)
+    }
+}
+
+/// Validator check that an amount is greater than zero
+///
+/// Use with `#[validate(custom(function = "acton_htmx::money::validate_positive"))]`.
+///
+/// # Errors
+///
+/// Returns a `money_positive` validation error for zero or negative amounts.
+pub fn validate_positive(money: &Money) -> Result<(), validator::ValidationError> {
+    if money.is_positive() {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("money_positive")
+            .with_message("Amount must be greater than zero".into()))
+    }
+}
+
+/// Validator check that an amount is not negative
+///
+/// # Errors
+///
+/// Returns a `money_non_negative` validation error for negative amounts.
+pub fn validate_non_negative(money: &Money) -> Result<(), validator::ValidationError> {
+    if money.is_negative() {
+        Err(validator::ValidationError::new("money_non_negative")
+            .with_message("Amount cannot be negative".into()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Askama filters for money
+///
+/// Filters return a [`MoneyFormat`] that writes straight into the template
+/// output. None of them fail; the `Result` is part of Askama's filter
+/// signature.
+#[allow(clippy::unnecessary_wraps, clippy::missing_errors_doc)]
+pub mod filters {
+    use super::{Money, MoneyFormat, MoneyStyle};
+    use std::borrow::Borrow;
+
+    /// Symbol and separators (`{{ price|money }}` → `$1,234.50`)
+    pub fn money<M: Borrow<Money>>(
+        value: M,
+        _values: &dyn askama::Values,
+    ) -> askama::Result<MoneyFormat> {
+        Ok(MoneyFormat::new(*value.borrow(), MoneyStyle::Symbol))
+    }
+
+    /// Separators and code (`{{ price|money_code }}` → `1,234.50 USD`)
+    pub fn money_code<M: Borrow<Money>>(
+        value: M,
+        _values: &dyn askama::Values,
+    ) -> askama::Result<MoneyFormat> {
+        Ok(MoneyFormat::new(*value.borrow(), MoneyStyle::Code))
+    }
+
+    /// Bare decimal (`{{ price|money_plain }}` → `1234.50`)
+    pub fn money_plain<M: Borrow<Money>>(
+        value: M,
+        _values: &dyn askama::Values,
+    ) -> askama::Result<MoneyFormat> {
+        Ok(MoneyFormat::new(*value.borrow(), MoneyStyle::Plain))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd(minor: i64) -> Money {
+        Money::new(minor, Currency::USD)
+    }
+
+    #[test]
+    fn test_parse_without_floats() {
+        assert_eq!(
+            Money::parse("1,234.5", Currency::USD).unwrap(),
+            usd(123_450)
+        );
+        assert_eq!(Money::parse("-0.07", Currency::USD).unwrap(), usd(-7));
+        assert_eq!(
+            Money::parse("500", Currency::JPY).unwrap().minor_units(),
+            500
+        );
+        assert!(Money::parse("1.234", Currency::USD).is_err());
+        assert!(Money::parse("1.5", Currency::JPY).is_err());
+        assert!(Money::parse("abc", Currency::USD).is_err());
+        assert_eq!(
+            Money::parse("99999999999999999999", Currency::USD),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let a = usd(1_000);
+        assert_eq!(a.checked_add(usd(1)).unwrap(), usd(1_001));
+        assert_eq!(a.checked_sub(usd(1_500)).unwrap(), usd(-500));
+        assert_eq!(a.checked_mul(3).unwrap(), usd(3_000));
+        assert!(matches!(
+            a.checked_add(Money::new(1, Currency::EUR)),
+            Err(MoneyError::CurrencyMismatch(_, _))
+        ));
+        assert_eq!(usd(i64::MAX).checked_add(usd(1)), Err(MoneyError::Overflow));
+        assert_eq!(
+            Money::checked_sum(Currency::USD, [usd(10), usd(20), usd(30)]).unwrap(),
+            usd(60)
+        );
+    }
+
+    #[test]
+    fn test_allocate_keeps_every_cent() {
+        let shares = usd(100).allocate(&[1, 1, 1]).unwrap();
+        assert_eq!(shares, vec![usd(34), usd(33), usd(33)]);
+
+        let shares = usd(-5).allocate(&[70, 30]).unwrap();
+        assert_eq!(shares, vec![usd(-4), usd(-1)]);
+
+        let shares = usd(1).allocate(&[0, 1]).unwrap();
+        assert_eq!(shares, vec![usd(0), usd(1)]);
+        assert_eq!(usd(1).allocate(&[]), Err(MoneyError::InvalidRatios));
+    }
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(usd(123_456_789).to_string(), "$1,234,567.89");
+        assert_eq!(usd(-5).to_string(), "-$0.05");
+        assert_eq!(Money::new(1_500, Currency::JPY).to_string(), "¥1,500");
+        let chf = Money::new(250, Currency::from_code("chf").unwrap());
+        assert_eq!(chf.to_string(), "CHF 2.50");
+        assert_eq!(
+            MoneyFormat::new(usd(123_450), MoneyStyle::Code).to_string(),
+            "1,234.50 USD"
+        );
+        assert_eq!(
+            MoneyFormat::new(usd(123_450), MoneyStyle::Plain).to_string(),
+            "1234.50"
+        );
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let json = serde_json::to_string(&usd(1_999)).unwrap();
+        assert_eq!(json, r#"{"amount":1999,"currency":"USD"}"#);
+        assert_eq!(serde_json::from_str::<Money>(&json).unwrap(), usd(1_999));
+        assert!(serde_json::from_str::<Money>(r#"{"amount":1,"currency":"XYZ"}"#).is_err());
+    }
+
+    #[test]
+    fn test_validators() {
+        assert!(validate_positive(&usd(1)).is_ok());
+        assert!(validate_positive(&usd(0)).is_err());
+        assert!(validate_non_negative(&usd(0)).is_ok());
+        assert!(validate_non_negative(&usd(-1)).is_err());
+    }
+
+    #[derive(askama::Template)]
+    #[template(source = "{{ total|money }} / {{ total|money_plain }}", ext = "html")]
+    struct Invoice {
+        total: Money,
+    }
+
+    #[test]
+    fn test_filters_in_template() {
+        use askama::Template;
+        let invoice = Invoice {
+            total: usd(123_450),
+        };
+        assert_eq!(invoice.render().unwrap(), "$1,234.50 / 1234.50");
+    }
+
+    #[tokio::test]
+    async fn test_sqlx_roundtrip() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let money: Money = sqlx::query_as("SELECT 1999 AS amount, ? AS currency")
+            .bind(Currency::EUR)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(money, Money::new(1_999, Currency::EUR));
+    }
+}
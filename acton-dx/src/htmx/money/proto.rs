@@ -0,0 +1,96 @@
+//! Conversions to and from data-service values
+
+use super::{Currency, Money, MoneyError};
+use crate::htmx::clients::{Row, Value};
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+impl From<Currency> for Value {
+    fn from(currency: Currency) -> Self {
+        Self {
+            value: Some(ValueKind::StringValue(currency.code().to_string())),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Currency {
+    type Error = MoneyError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match &value.value {
+            Some(ValueKind::StringValue(code)) => Self::from_code(code),
+            other => Err(MoneyError::InvalidValue(format!(
+                "expected currency code, got {other:?}"
+            ))),
+        }
+    }
+}
+
+impl Money {
+    /// Query parameters for the amount and currency columns, in that order
+    #[must_use]
+    pub fn to_params(&self) -> [Value; 2] {
+        [
+            Value {
+                value: Some(ValueKind::IntValue(self.amount)),
+            },
+            self.currency.into(),
+        ]
+    }
+
+    /// Read an amount from two columns of a data-service row
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MoneyError::InvalidValue`] if a column is missing or has the
+    /// wrong type, or [`MoneyError::UnknownCurrency`] for unknown codes.
+    pub fn from_proto_row(
+        row: &Row,
+        amount_column: &str,
+        currency_column: &str,
+    ) -> Result<Self, MoneyError> {
+        let missing = |column: &str| MoneyError::InvalidValue(format!("missing column {column}"));
+        let amount = match row
+            .columns
+            .get(amount_column)
+            .ok_or_else(|| missing(amount_column))?
+            .value
+        {
+            Some(ValueKind::IntValue(amount)) => amount,
+            ref other => {
+                return Err(MoneyError::InvalidValue(format!(
+                    "expected integer amount, got {other:?}"
+                )))
+            }
+        };
+        let currency = Currency::try_from(
+            row.columns
+                .get(currency_column)
+                .ok_or_else(|| missing(currency_column))?,
+        )?;
+        Ok(Self::new(amount, currency))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_roundtrip_through_row() {
+        let money = Money::new(4_250, Currency::GBP);
+        let [amount, currency] = money.to_params();
+        let row = Row {
+            columns: [
+                ("price".to_string(), amount),
+                ("price_currency".to_string(), currency),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        assert_eq!(
+            Money::from_proto_row(&row, "price", "price_currency").unwrap(),
+            money
+        );
+        assert!(Money::from_proto_row(&row, "cost", "price_currency").is_err());
+    }
+}
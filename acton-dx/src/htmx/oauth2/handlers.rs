@@ -5,18 +5,23 @@
 //! - Handle OAuth callback
 //! - Link OAuth account to existing user
 //! - Unlink OAuth account
+//!
+//! [`routes`] mounts all of them for every configured provider.
 
 use axum::{
     extract::{Path, Query, State},
-    response::{IntoResponse, Redirect},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
 };
 use acton_reactive::prelude::ActorHandleInterface;
 use serde::Deserialize;
 use sqlx::PgPool;
 
 use crate::htmx::{
-    auth::{password::hash_password, Session},
+    auth::{password::hash_password, user::User, FlashMessage, SessionData},
     error::ActonHtmxError,
+    extractors::SessionExtractor,
     responses::{HxRedirect, HxResponseTrigger},
     oauth2::{
         agent::{GenerateState, ValidateState, RemoveState},
@@ -27,12 +32,47 @@ use crate::htmx::{
     state::ActonHtmxState,
 };
 
+/// Session key holding the page to return to after signing in
+const RETURN_URL_SESSION_KEY: &str = "return_url";
+
+/// Where users land after signing in without a `next` page
+const DEFAULT_RETURN_URL: &str = "/dashboard";
+
+/// OAuth2 routes
+///
+/// - `GET /auth/{provider}` redirects to the provider's consent page;
+///   `?next=/path` sets the local page to return to afterwards
+/// - `GET /auth/{provider}/callback` signs the user in, creating an account
+///   on first login or linking the provider when already signed in
+/// - `POST /auth/{provider}/unlink` unlinks the provider from the signed-in user
+///
+/// `{provider}` is `google`, `github` or `oidc`; each must be configured under
+/// `[oauth2.<provider>]` with its `redirect_uri` pointing at the callback.
+pub fn routes() -> Router<ActonHtmxState> {
+    Router::new()
+        .route("/auth/{provider}", get(initiate_oauth))
+        .route("/auth/{provider}/callback", get(handle_oauth_callback))
+        .route("/auth/{provider}/unlink", post(unlink_oauth_account))
+}
+
+/// OAuth2 initiation query parameters
+#[derive(Debug, Default, Deserialize)]
+pub struct OAuthInitiate {
+    /// Local path to return to after signing in
+    pub next: Option<String>,
+}
+
 /// OAuth2 callback query parameters
+///
+/// Providers send only `error` when the user denies access, so `code` and
+/// `state` default to empty.
 #[derive(Debug, Deserialize)]
 pub struct OAuthCallback {
     /// Authorization code from provider
+    #[serde(default)]
     pub code: String,
     /// CSRF state token
+    #[serde(default)]
     pub state: String,
     /// Optional error from provider
     pub error: Option<String>,
@@ -40,11 +80,23 @@ pub struct OAuthCallback {
     pub error_description: Option<String>,
 }
 
+/// Only same-site paths are followed after signing in, never other hosts
+fn is_local_path(url: &str) -> bool {
+    url.starts_with('/') && !url.starts_with("//") && !url.contains('\\')
+}
+
+/// The session middleware saves the session from the response
+fn with_session(response: impl IntoResponse, session: SessionData) -> Response {
+    let mut response = response.into_response();
+    response.extensions_mut().insert(session);
+    response
+}
+
 /// Initiate OAuth2 flow
 ///
 /// This handler initiates the OAuth2 authorization code flow by:
 /// 1. Generating a CSRF state token
-/// 2. Storing the state token and PKCE verifier in session
+/// 2. Storing the state token, PKCE verifier and return page in session
 /// 3. Redirecting to the provider's authorization endpoint
 ///
 /// # Errors
@@ -53,8 +105,9 @@ pub struct OAuthCallback {
 pub async fn initiate_oauth(
     State(state): State<ActonHtmxState>,
     Path(provider_name): Path<String>,
-    mut session: Session,
-) -> Result<impl IntoResponse, ActonHtmxError> {
+    Query(params): Query<OAuthInitiate>,
+    SessionExtractor(_, mut session): SessionExtractor,
+) -> Result<Response, ActonHtmxError> {
     // Parse provider
     let provider = provider_name.parse::<OAuthProvider>()
         .map_err(|_| ActonHtmxError::BadRequest(format!("Unknown provider: {provider_name}")))?;
@@ -97,9 +150,15 @@ pub async fn initiate_oauth(
     session.set("oauth2_state".to_string(), &oauth_state.token)?;
     session.set("oauth2_pkce_verifier".to_string(), &pkce_verifier)?;
     session.set("oauth2_provider".to_string(), &provider_name)?;
+    match params.next.filter(|next| is_local_path(next)) {
+        Some(next) => session.set(RETURN_URL_SESSION_KEY.to_string(), next)?,
+        None => {
+            session.remove(RETURN_URL_SESSION_KEY);
+        }
+    }
 
     // Redirect to provider's authorization endpoint
-    Ok(Redirect::to(&auth_url))
+    Ok(with_session(Redirect::to(&auth_url), session))
 }
 
 /// Validate CSRF state token from session and OAuth2 agent
@@ -109,7 +168,7 @@ pub async fn initiate_oauth(
 /// Returns error if state token is missing, mismatched, or expired
 async fn validate_oauth_state(
     state: &ActonHtmxState,
-    session: &Session,
+    session: &SessionData,
     params: &OAuthCallback,
     provider_name: &str,
 ) -> Result<(), ActonHtmxError> {
@@ -209,12 +268,15 @@ async fn exchange_code_and_fetch_user(
 
 /// Find or create OAuth account and return user ID
 ///
+/// A provider account seen before signs in its user; otherwise it is linked
+/// to the signed-in user, or to a new user when nobody is signed in.
+///
 /// # Errors
 ///
 /// Returns error if database operations fail
 async fn find_or_create_oauth_user(
     pool: &PgPool,
-    session: &Session,
+    current_user: Option<i64>,
     provider: OAuthProvider,
     user_info: &OAuthUserInfo,
 ) -> Result<i64, ActonHtmxError> {
@@ -225,7 +287,7 @@ async fn find_or_create_oauth_user(
         // Existing OAuth account - update info and use existing user_id
         account.update_info(pool, user_info).await?;
         Ok(account.user_id)
-    } else if let Some(user_id) = session.get::<i64>("user_id") {
+    } else if let Some(user_id) = current_user {
         // Link to existing authenticated user
         let account = OAuthAccount::link_account(pool, user_id, provider, user_info).await?;
         Ok(account.user_id)
@@ -237,16 +299,17 @@ async fn find_or_create_oauth_user(
     }
 }
 
-/// Complete OAuth authentication by updating session
+/// Complete OAuth authentication by signing the user in to the session
 fn complete_oauth_authentication(
-    session: &mut Session,
+    session: &mut SessionData,
     user_id: i64,
-) -> Result<(), ActonHtmxError> {
-    session.set("user_id".to_string(), user_id)?;
+    user_name: Option<String>,
+) {
+    session.user_id = Some(user_id);
+    session.user_name = user_name;
     session.remove("oauth2_state");
     session.remove("oauth2_pkce_verifier");
     session.remove("oauth2_provider");
-    Ok(())
 }
 
 /// Handle OAuth2 callback
@@ -256,7 +319,8 @@ fn complete_oauth_authentication(
 /// 2. Exchanging the authorization code for an access token
 /// 3. Fetching user information from the provider
 /// 4. Creating or linking the OAuth account
-/// 5. Authenticating the user
+/// 5. Refusing users deactivated over SCIM
+/// 6. Authenticating the user and redirecting to the page they started from
 ///
 /// # Errors
 ///
@@ -265,8 +329,8 @@ pub async fn handle_oauth_callback(
     State(state): State<ActonHtmxState>,
     Path(provider_name): Path<String>,
     Query(params): Query<OAuthCallback>,
-    mut session: Session,
-) -> Result<impl IntoResponse, ActonHtmxError> {
+    SessionExtractor(_, mut session): SessionExtractor,
+) -> Result<Response, ActonHtmxError> {
     // Check for OAuth error
     if let Some(error) = params.error {
         let description = params.error_description.unwrap_or_default();
@@ -309,10 +373,26 @@ pub async fn handle_oauth_callback(
 
     // Find or create OAuth account
     let pool = state.database_pool();
-    let user_id = find_or_create_oauth_user(pool, &session, provider, &user_info).await?;
+    let user_id = find_or_create_oauth_user(pool, session.user_id, provider, &user_info).await?;
+    if User::is_deactivated(user_id, pool)
+        .await
+        .map_err(|e| ActonHtmxError::ServerError(e.to_string()))?
+    {
+        tracing::warn!(
+            user_id,
+            provider = %provider_name,
+            "Refused OAuth2 login for a deactivated user"
+        );
+        return Err(ActonHtmxError::Forbidden(
+            "This account has been deactivated".to_string(),
+        ));
+    }
 
     // Authenticate user
-    complete_oauth_authentication(&mut session, user_id)?;
+    complete_oauth_authentication(&mut session, user_id, user_info.name.clone());
+    session
+        .flash_messages
+        .push(FlashMessage::success("Successfully logged in!"));
 
     tracing::info!(
         provider = %provider_name,
@@ -320,13 +400,14 @@ pub async fn handle_oauth_callback(
         "User authenticated via OAuth2"
     );
 
-    // Redirect to dashboard or return URL
+    // The provider redirected the browser here, so this is a plain redirect
     let return_url = session
-        .get::<String>("return_url")
-        .unwrap_or_else(|| "/dashboard".to_string());
-    session.remove("return_url");
+        .get::<String>(RETURN_URL_SESSION_KEY)
+        .filter(|url| is_local_path(url))
+        .unwrap_or_else(|| DEFAULT_RETURN_URL.to_string());
+    session.remove(RETURN_URL_SESSION_KEY);
 
-    Ok((HxRedirect(return_url), ()))
+    Ok(with_session(Redirect::to(&return_url), session))
 }
 
 /// Unlink OAuth account
@@ -339,11 +420,11 @@ pub async fn handle_oauth_callback(
 pub async fn unlink_oauth_account(
     State(state): State<ActonHtmxState>,
     Path(provider_name): Path<String>,
-    session: Session,
-) -> Result<impl IntoResponse, ActonHtmxError> {
+    SessionExtractor(_, session): SessionExtractor,
+) -> Result<Response, ActonHtmxError> {
     // Require authentication
-    let user_id: i64 = session
-        .get("user_id")
+    let user_id = session
+        .user_id
         .ok_or_else(|| ActonHtmxError::Unauthorized("Not authenticated".to_string()))?;
 
     // Parse provider
@@ -365,7 +446,8 @@ pub async fn unlink_oauth_account(
             HxResponseTrigger::normal(vec!["oauth-account-unlinked"]),
             HxRedirect("/settings/accounts".to_string()),
             (),
-        ))
+        )
+            .into_response())
     } else {
         Err(ActonHtmxError::NotFound(
             "OAuth account not found".to_string(),
@@ -429,4 +511,21 @@ mod tests {
             Some("User denied access".to_string())
         );
     }
+
+    #[test]
+    fn test_oauth_callback_with_only_error() {
+        let json = r#"{"error": "access_denied"}"#;
+        let callback: OAuthCallback = serde_json::from_str(json).unwrap();
+        assert!(callback.code.is_empty());
+        assert_eq!(callback.error, Some("access_denied".to_string()));
+    }
+
+    #[test]
+    fn test_return_url_must_be_local() {
+        assert!(is_local_path("/settings/accounts?tab=oauth"));
+        assert!(!is_local_path("https://evil.example/phish"));
+        assert!(!is_local_path("//evil.example"));
+        assert!(!is_local_path("/\\evil.example"));
+        assert!(!is_local_path("settings"));
+    }
 }
@@ -16,43 +16,20 @@
 //!
 //! # Example Usage
 //!
-//! ```rust,no_run
-//! use acton_htmx::oauth2::{OAuthConfig, ProviderConfig};
-//! use axum::{Router, routing::get};
-//!
-//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-//! // Configure OAuth2 providers
-//! let oauth_config = OAuthConfig {
-//!     google: Some(ProviderConfig {
-//!         client_id: std::env::var("GOOGLE_CLIENT_ID")?,
-//!         client_secret: std::env::var("GOOGLE_CLIENT_SECRET")?,
-//!         redirect_uri: "http://localhost:3000/auth/google/callback".to_string(),
-//!         scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
-//!         auth_url: None,
-//!         token_url: None,
-//!         userinfo_url: None,
-//!     }),
-//!     github: Some(ProviderConfig {
-//!         client_id: std::env::var("GITHUB_CLIENT_ID")?,
-//!         client_secret: std::env::var("GITHUB_CLIENT_SECRET")?,
-//!         redirect_uri: "http://localhost:3000/auth/github/callback".to_string(),
-//!         scopes: vec!["read:user".to_string(), "user:email".to_string()],
-//!         auth_url: None,
-//!         token_url: None,
-//!         userinfo_url: None,
-//!     }),
-//!     oidc: None,
-//! };
-//!
-//! // Add OAuth2 routes to your router
+//! Configure providers under `[oauth2]` (see below), then mount the routes:
+//!
+//! ```rust,ignore
+//! use acton_htmx::auth::oauth2;
+//!
 //! let app = Router::new()
-//!     .route("/auth/:provider", get(acton_htmx::oauth2::handlers::initiate_oauth))
-//!     .route("/auth/:provider/callback", get(acton_htmx::oauth2::handlers::handle_oauth_callback))
-//!     .route("/auth/:provider/unlink", get(acton_htmx::oauth2::handlers::unlink_oauth_account));
-//! # Ok(())
-//! # }
+//!     .merge(oauth2::routes())
+//!     .with_state(state);
 //! ```
 //!
+//! Link to `/auth/google?next=/settings` to sign in and come back to
+//! `/settings`. Signing in while logged in links the provider to the
+//! current account instead of creating a new one.
+//!
 //! # Configuration
 //!
 //! OAuth2 providers are configured via the `OAuthConfig` struct, which should be
@@ -81,6 +58,7 @@
 //! - **State Validation**: State tokens are validated server-side using the OAuth2Agent
 //! - **One-Time Use**: State tokens are removed after successful validation
 //! - **Session Storage**: PKCE verifiers are stored in secure HTTP-only session cookies
+//! - **Return Pages**: `next` must be a local path, so sign-in cannot redirect off-site
 //!
 //! # Database Schema
 //!
@@ -113,7 +91,7 @@ pub mod types;
 
 pub use agent::{OAuth2Agent, GenerateState, ValidateState, RemoveState, CleanupExpired};
 #[cfg(feature = "postgres")]
-pub use handlers::{initiate_oauth, handle_oauth_callback, unlink_oauth_account};
+pub use handlers::{initiate_oauth, handle_oauth_callback, routes, unlink_oauth_account};
 #[cfg(feature = "postgres")]
 pub use models::OAuthAccount;
 pub use providers::{GitHubProvider, GoogleProvider, OidcProvider};
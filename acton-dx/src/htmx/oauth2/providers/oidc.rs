@@ -24,25 +24,15 @@ impl OidcProvider {
     /// # Errors
     ///
     /// Returns error if the configuration is invalid or if discovery fails
-    ///
-    /// # Panics
-    ///
-    /// This function should not panic as all unwrap() calls are guarded by is_some() checks
     pub async fn new(config: &ProviderConfig) -> Result<Self, OAuthError> {
         // For generic OIDC, we require either:
         // 1. Manual configuration (all three URLs: auth_url, token_url, userinfo_url)
         // 2. Discovery via issuer URL (only auth_url provided)
 
-        let base = if config.auth_url.is_some()
-            && config.token_url.is_some()
-            && config.userinfo_url.is_some()
+        let base = if let (Some(auth_url), Some(token_url), Some(userinfo_url)) =
+            (&config.auth_url, &config.token_url, &config.userinfo_url)
         {
             // Manual configuration - all URLs provided
-            // SAFETY: These unwraps are safe because we just checked is_some() above
-            let auth_url = config.auth_url.as_ref().unwrap();
-            let token_url = config.token_url.as_ref().unwrap();
-            let userinfo_url = config.userinfo_url.as_ref().unwrap();
-
             BaseOAuthProvider::new(auth_url, token_url, config, userinfo_url.clone())?
         } else if let Some(issuer_url) = &config.auth_url {
             // Discovery - only issuer URL provided
@@ -0,0 +1,606 @@
+//! Autoscaling load report
+//!
+//! Exposes the framework's load signals (job queue depth, request queue
+//! depth, connection pool saturation, p95 latencies and rate-limit
+//! rejections) so deployments can scale job workers and web replicas on
+//! them instead of CPU alone.
+//!
+//! Two formats are served behind a bearer token:
+//!
+//! - `GET /autoscaling/metrics` returns a flat JSON object of metric name to
+//!   value, for the KEDA `metrics-api` scaler (`valueLocation: jobs_queue_depth`)
+//! - `GET /autoscaling/metrics/{name}` returns a single metric as an
+//!   `ExternalMetricValueList`, the shape of the Kubernetes external metrics
+//!   API, for adapters that feed the HPA
+//!
+//! Latencies and rejections are measured by [`LoadReporter::middleware`]
+//! over a sliding window, so the report follows current load rather than
+//! totals since startup.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::observability::autoscaling::{LoadReporter, LoadReporterConfig};
+//! use axum::Router;
+//!
+//! let reporter = LoadReporter::new(
+//!     LoadReporterConfig::default().with_token(std::env::var("AUTOSCALING_TOKEN")?),
+//! )
+//! .with_request_queue(export_queue.clone());
+//!
+//! let app = Router::new()
+//!     .merge(routes)
+//!     .merge(reporter.routes())
+//!     .layer(axum::middleware::from_fn_with_state(
+//!         reporter.clone(),
+//!         LoadReporter::middleware,
+//!     ))
+//!     .with_state(state);
+//! ```
+//!
+//! A KEDA trigger scaling job workers on queue depth:
+//!
+//! ```yaml
+//! triggers:
+//!   - type: metrics-api
+//!     metadata:
+//!       url: "http://web.default.svc:3000/autoscaling/metrics"
+//!       valueLocation: "jobs_queue_depth"
+//!       targetValue: "50"
+//!       authMode: "bearer"
+//!     authenticationRef:
+//!       name: acton-autoscaling
+//! ```
+
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, StatusCode},
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::htmx::middleware::request_queue::RequestQueue;
+use crate::htmx::state::ActonHtmxState;
+
+/// Path of the JSON report
+pub const METRICS_PATH: &str = "/autoscaling/metrics";
+
+/// Path of a single metric in external metrics format; `{name}` is the metric
+pub const METRIC_PATH: &str = "/autoscaling/metrics/{name}";
+
+/// Configuration for [`LoadReporter`]
+#[derive(Clone)]
+pub struct LoadReporterConfig {
+    /// Bearer tokens accepted from scalers
+    ///
+    /// Requests are rejected while the list is empty. Keep the old token
+    /// listed while rotating to a new one.
+    pub tokens: Vec<String>,
+    /// How far back latencies and rejections are measured
+    pub window: Duration,
+    /// Most request samples kept in the window
+    pub max_samples: usize,
+}
+
+impl std::fmt::Debug for LoadReporterConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoadReporterConfig")
+            .field("tokens", &format_args!("[{} redacted]", self.tokens.len()))
+            .field("window", &self.window)
+            .field("max_samples", &self.max_samples)
+            .finish()
+    }
+}
+
+impl Default for LoadReporterConfig {
+    fn default() -> Self {
+        Self {
+            tokens: Vec::new(),
+            window: Duration::from_secs(60),
+            max_samples: 10_000,
+        }
+    }
+}
+
+impl LoadReporterConfig {
+    /// Accept a bearer token
+    #[must_use]
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.tokens.push(token.into());
+        self
+    }
+
+    /// Set how far back latencies and rejections are measured
+    #[must_use]
+    pub const fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set the most request samples kept in the window
+    #[must_use]
+    pub const fn with_max_samples(mut self, max_samples: usize) -> Self {
+        self.max_samples = max_samples;
+        self
+    }
+}
+
+/// Requests seen within the window
+#[derive(Debug, Default)]
+struct Samples {
+    /// Finish time and duration in milliseconds, oldest first
+    requests: VecDeque<(Instant, u64)>,
+    /// Times of rate-limited responses, oldest first
+    rejections: VecDeque<Instant>,
+}
+
+impl Samples {
+    fn prune(&mut self, now: Instant, window: Duration) {
+        let Some(cutoff) = now.checked_sub(window) else {
+            return;
+        };
+        while self.requests.front().is_some_and(|(at, _)| *at < cutoff) {
+            self.requests.pop_front();
+        }
+        while self.rejections.front().is_some_and(|at| *at < cutoff) {
+            self.rejections.pop_front();
+        }
+    }
+
+    /// 95th percentile duration in milliseconds, 0 without samples
+    fn p95_ms(&self) -> u64 {
+        let mut durations: Vec<u64> = self.requests.iter().map(|(_, ms)| *ms).collect();
+        if durations.is_empty() {
+            return 0;
+        }
+        durations.sort_unstable();
+        let index = (durations.len() * 95).div_ceil(100).saturating_sub(1);
+        durations[index]
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: LoadReporterConfig,
+    samples: Mutex<Samples>,
+    /// Request queues included in the report
+    queues: Mutex<Vec<RequestQueue>>,
+}
+
+/// Collects load signals and serves them to autoscalers
+///
+/// Cheap to clone; clones share the same samples.
+#[derive(Debug, Clone)]
+pub struct LoadReporter {
+    inner: Arc<Inner>,
+}
+
+/// Load signals at one point in time
+///
+/// Signals that are not available (no database pool, job agent not
+/// answering) are left out of the report rather than reported as zero, so
+/// a scaler does not scale in because a signal went missing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LoadReport {
+    /// When the report was taken
+    #[serde(skip)]
+    pub generated_at: DateTime<Utc>,
+    /// Jobs waiting to run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs_queue_depth: Option<usize>,
+    /// Jobs running now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs_running: Option<usize>,
+    /// 95th percentile job execution time in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs_p95_ms: Option<u64>,
+    /// Requests waiting in request queues
+    pub request_queue_depth: usize,
+    /// Requests holding a request queue slot
+    pub request_queue_active: usize,
+    /// Share of database connections in use (0.0 to 1.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub db_pool_saturation: Option<f64>,
+    /// Share of Redis connections in use (0.0 to 1.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redis_pool_saturation: Option<f64>,
+    /// 95th percentile HTTP request duration in milliseconds over the window
+    pub http_p95_ms: u64,
+    /// HTTP requests per second over the window
+    pub http_requests_per_second: f64,
+    /// Requests rejected with `429 Too Many Requests` over the window
+    pub rate_limit_rejections: usize,
+}
+
+impl LoadReport {
+    /// Metrics present in the report, by name
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // Counts stay far below 2^52
+    pub fn metrics(&self) -> BTreeMap<&'static str, f64> {
+        let mut metrics = BTreeMap::new();
+        let optional = [
+            ("jobs_queue_depth", self.jobs_queue_depth.map(|v| v as f64)),
+            ("jobs_running", self.jobs_running.map(|v| v as f64)),
+            ("jobs_p95_ms", self.jobs_p95_ms.map(|v| v as f64)),
+            ("db_pool_saturation", self.db_pool_saturation),
+            ("redis_pool_saturation", self.redis_pool_saturation),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                metrics.insert(name, value);
+            }
+        }
+        metrics.insert("request_queue_depth", self.request_queue_depth as f64);
+        metrics.insert("request_queue_active", self.request_queue_active as f64);
+        metrics.insert("http_p95_ms", self.http_p95_ms as f64);
+        metrics.insert("http_requests_per_second", self.http_requests_per_second);
+        metrics.insert("rate_limit_rejections", self.rate_limit_rejections as f64);
+        metrics
+    }
+
+    /// One metric as a Kubernetes `ExternalMetricValueList`, or `None` if
+    /// the report does not have it
+    #[must_use]
+    pub fn external_metric(&self, name: &str) -> Option<serde_json::Value> {
+        let value = *self.metrics().get(name)?;
+        Some(json!({
+            "kind": "ExternalMetricValueList",
+            "apiVersion": "external.metrics.k8s.io/v1beta1",
+            "metadata": {},
+            "items": [{
+                "metricName": name,
+                "metricLabels": {},
+                "timestamp": self.generated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                "value": quantity(value),
+            }],
+        }))
+    }
+}
+
+/// Format a value as a Kubernetes quantity, in milli-units when fractional
+#[allow(clippy::cast_possible_truncation)] // Rounded, and load values are small
+fn quantity(value: f64) -> String {
+    let milli = (value * 1000.0).round() as i64;
+    if milli % 1000 == 0 {
+        (milli / 1000).to_string()
+    } else {
+        format!("{milli}m")
+    }
+}
+
+/// Share of `max` in use, `None` for an unsized pool
+#[allow(clippy::cast_precision_loss)] // Pool sizes are small
+fn saturation(in_use: usize, max: usize) -> Option<f64> {
+    (max > 0).then(|| (in_use as f64 / max as f64).min(1.0))
+}
+
+impl LoadReporter {
+    /// Create a new load reporter
+    #[must_use]
+    pub fn new(config: LoadReporterConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                samples: Mutex::new(Samples::default()),
+                queues: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Include a request queue's depth in the report
+    #[must_use]
+    pub fn with_request_queue(self, queue: RequestQueue) -> Self {
+        self.inner.queues.lock().push(queue);
+        self
+    }
+
+    /// Reporter configuration
+    #[must_use]
+    pub fn config(&self) -> &LoadReporterConfig {
+        &self.inner.config
+    }
+
+    /// Whether `token` is one of the configured bearer tokens
+    #[must_use]
+    pub fn accepts(&self, token: &str) -> bool {
+        self.inner
+            .config
+            .tokens
+            .iter()
+            .any(|expected| token_matches(expected, token))
+    }
+
+    /// Record a finished request
+    pub fn record(&self, duration: Duration, status: StatusCode) {
+        let now = Instant::now();
+        let millis = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        let mut samples = self.inner.samples.lock();
+        samples.prune(now, self.inner.config.window);
+        if samples.requests.len() >= self.inner.config.max_samples {
+            samples.requests.pop_front();
+        }
+        samples.requests.push_back((now, millis));
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            samples.rejections.push_back(now);
+        }
+    }
+
+    /// Middleware function that times requests and counts rejections
+    ///
+    /// Layer it outside the rate limiter so rejected requests are seen.
+    pub async fn middleware(
+        State(reporter): State<Self>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let started = Instant::now();
+        let response = next.run(request).await;
+        reporter.record(started.elapsed(), response.status());
+        response
+    }
+
+    /// Take a report from the reporter's samples and the application state
+    pub async fn report(&self, state: &ActonHtmxState) -> LoadReport {
+        let mut report = self.sample();
+
+        match state.get_job_metrics().await {
+            Ok(metrics) => {
+                report.jobs_queue_depth = Some(metrics.current_queue_size);
+                report.jobs_running = Some(metrics.current_running);
+                report.jobs_p95_ms = Some(metrics.p95_execution_time_ms);
+            }
+            Err(e) => warn!(error = %e, "Job agent did not report metrics"),
+        }
+
+        #[cfg(feature = "postgres")]
+        if let Some(pool) = state.pg_pool() {
+            report.db_pool_saturation = saturation(
+                (pool.size() as usize).saturating_sub(pool.num_idle()),
+                pool.options().get_max_connections() as usize,
+            );
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(pool) = state.sqlite_pool() {
+            report.db_pool_saturation = saturation(
+                (pool.size() as usize).saturating_sub(pool.num_idle()),
+                pool.options().get_max_connections() as usize,
+            );
+        }
+        #[cfg(feature = "redis")]
+        if let Some(pool) = state.redis_pool() {
+            let status = pool.status();
+            report.redis_pool_saturation = saturation(
+                status.size.saturating_sub(status.available),
+                status.max_size,
+            );
+        }
+
+        report
+    }
+
+    /// Report of the signals the reporter measures itself
+    fn sample(&self) -> LoadReport {
+        let now = Instant::now();
+        let window = self.inner.config.window;
+        let (http_p95_ms, requests, rate_limit_rejections) = {
+            let mut samples = self.inner.samples.lock();
+            samples.prune(now, window);
+            (
+                samples.p95_ms(),
+                samples.requests.len(),
+                samples.rejections.len(),
+            )
+        };
+
+        let (request_queue_depth, request_queue_active) = self
+            .inner
+            .queues
+            .lock()
+            .iter()
+            .map(|queue| queue.status(""))
+            .fold((0, 0), |(queued, active), status| {
+                (queued + status.queued, active + status.active)
+            });
+
+        #[allow(clippy::cast_precision_loss)] // Sample counts are capped
+        let http_requests_per_second = requests as f64 / window.as_secs_f64().max(1.0);
+
+        LoadReport {
+            generated_at: Utc::now(),
+            request_queue_depth,
+            request_queue_active,
+            http_p95_ms,
+            http_requests_per_second,
+            rate_limit_rejections,
+            ..LoadReport::default()
+        }
+    }
+
+    /// Report routes, behind the bearer token
+    ///
+    /// - `GET /autoscaling/metrics` returns every metric as a JSON object
+    /// - `GET /autoscaling/metrics/{name}` returns one metric as an
+    ///   `ExternalMetricValueList`, or `404` for unknown metrics
+    pub fn routes(&self) -> Router<ActonHtmxState> {
+        Router::new()
+            .route(METRICS_PATH, get(load_report))
+            .route(METRIC_PATH, get(external_metric))
+            .route_layer(from_fn_with_state(self.clone(), authenticate))
+            .layer(Extension(self.clone()))
+    }
+}
+
+/// Compare digests so timing reveals neither the token's length nor a matching prefix
+pub(crate) fn token_matches(expected: &str, provided: &str) -> bool {
+    let expected = Sha256::digest(expected.as_bytes());
+    let provided = Sha256::digest(provided.as_bytes());
+    expected
+        .iter()
+        .zip(provided.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+async fn authenticate(
+    State(reporter): State<LoadReporter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if token.is_some_and(|token| reporter.accepts(token.trim())) {
+        next.run(request).await
+    } else {
+        warn!("Rejected load report request with invalid bearer token");
+        (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+        )
+            .into_response()
+    }
+}
+
+async fn load_report(
+    State(state): State<ActonHtmxState>,
+    Extension(reporter): Extension<LoadReporter>,
+) -> Json<LoadReport> {
+    Json(reporter.report(&state).await)
+}
+
+async fn external_metric(
+    State(state): State<ActonHtmxState>,
+    Extension(reporter): Extension<LoadReporter>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    reporter
+        .report(&state)
+        .await
+        .external_metric(&name)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "scaler-token";
+
+    fn reporter() -> LoadReporter {
+        LoadReporter::new(LoadReporterConfig::default().with_token(TOKEN))
+    }
+
+    #[test]
+    fn test_p95_over_window() {
+        let reporter = reporter();
+        for millis in 1..=100 {
+            reporter.record(Duration::from_millis(millis), StatusCode::OK);
+        }
+        reporter.record(Duration::from_millis(1), StatusCode::TOO_MANY_REQUESTS);
+
+        let report = reporter.sample();
+        assert_eq!(report.http_p95_ms, 95);
+        assert_eq!(report.rate_limit_rejections, 1);
+        assert!((report.http_requests_per_second - 101.0 / 60.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_samples_are_capped_and_pruned() {
+        let reporter = LoadReporter::new(
+            LoadReporterConfig::default()
+                .with_window(Duration::from_millis(20))
+                .with_max_samples(3),
+        );
+        for _ in 0..5 {
+            reporter.record(Duration::from_millis(5), StatusCode::OK);
+        }
+        assert_eq!(reporter.inner.samples.lock().requests.len(), 3);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(reporter.sample().http_p95_ms, 0);
+    }
+
+    #[test]
+    fn test_request_queue_depth_is_summed() {
+        use crate::htmx::middleware::request_queue::RequestQueueConfig;
+
+        let queue = RequestQueue::new(RequestQueueConfig::default());
+        let reporter = reporter()
+            .with_request_queue(queue.clone())
+            .with_request_queue(queue);
+        let report = reporter.sample();
+        assert_eq!(report.request_queue_depth, 0);
+        assert_eq!(reporter.inner.queues.lock().len(), 2);
+    }
+
+    #[test]
+    fn test_missing_signals_are_left_out() {
+        let report = LoadReport {
+            jobs_queue_depth: Some(12),
+            db_pool_saturation: Some(0.25),
+            ..LoadReport::default()
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["jobs_queue_depth"], 12);
+        assert!(json.get("jobs_running").is_none());
+        assert!(json.get("redis_pool_saturation").is_none());
+        assert!(json.get("generated_at").is_none());
+
+        assert!(report.external_metric("jobs_running").is_none());
+        let metric = report.external_metric("db_pool_saturation").unwrap();
+        assert_eq!(metric["kind"], "ExternalMetricValueList");
+        assert_eq!(metric["items"][0]["metricName"], "db_pool_saturation");
+        assert_eq!(metric["items"][0]["value"], "250m");
+    }
+
+    #[test]
+    fn test_quantity() {
+        assert_eq!(quantity(12.0), "12");
+        assert_eq!(quantity(0.25), "250m");
+        assert_eq!(quantity(1.5), "1500m");
+        assert_eq!(saturation(3, 4), Some(0.75));
+        assert_eq!(saturation(0, 0), None);
+    }
+
+    #[tokio::test]
+    async fn test_middleware_counts_rejections() {
+        let reporter = reporter();
+        let app = Router::new()
+            .route("/limited", get(|| async { StatusCode::TOO_MANY_REQUESTS }))
+            .layer(from_fn_with_state(
+                reporter.clone(),
+                LoadReporter::middleware,
+            ));
+
+        let request = axum::http::Request::builder()
+            .uri("/limited")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(reporter.sample().rate_limit_rejections, 1);
+    }
+
+    #[test]
+    fn test_requires_configured_token() {
+        assert!(reporter().accepts(TOKEN));
+        assert!(!reporter().accepts("wrong"));
+        assert!(!LoadReporter::new(LoadReporterConfig::default()).accepts(""));
+    }
+}
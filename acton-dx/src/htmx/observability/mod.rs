@@ -1,8 +1,9 @@
 //! Observability (logging, tracing, metrics)
 //!
 //! Provides structured logging, distributed tracing, and metrics collection
-//! via OpenTelemetry integration.
+//! via OpenTelemetry integration, and a load report for autoscalers.
 
+pub mod autoscaling;
 pub mod metrics;
 
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
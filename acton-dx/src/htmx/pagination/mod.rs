@@ -0,0 +1,387 @@
+//! Cursor pagination for load-more and infinite-scroll lists
+//!
+//! Offset pagination skips or repeats rows when the list changes between
+//! requests and gets slower the deeper the page. Cursor (keyset) pagination
+//! instead remembers the sort keys of the last row shown and asks for rows
+//! after them:
+//!
+//! - [`Cursor`] is an opaque, URL-safe token encoding the sort keys
+//! - [`CursorParams`] reads `?after=<cursor>&limit=<n>` from the query string
+//! - [`CursorPage`] is a page of items and the cursor of the next page,
+//!   built from a query that fetched one row more than the limit
+//! - [`LoadMore`] renders the element that requests the next page, either
+//!   when scrolled into view or when its button is clicked, and
+//!   [`CursorPage::fragment`] appends it to the rendered items
+//!
+//! With the `microservices` feature, `DataClient::query_page` runs the
+//! keyset query through the data service.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_dx::htmx::pagination::{CursorPage, CursorParams, LoadMore};
+//!
+//! async fn orders(
+//!     State(state): State<AppState>,
+//!     Query(params): Query<CursorParams>,
+//! ) -> Result<Response, PaginationError> {
+//!     let (after_at, after_id) = params
+//!         .cursor::<(i64, i64)>()?
+//!         .unwrap_or((i64::MAX, i64::MAX));
+//!     let limit = params.limit(25, 100);
+//!     let orders: Vec<Order> = sqlx::query_as(
+//!         "SELECT * FROM orders WHERE (created_at, id) < ($1, $2) \
+//!          ORDER BY created_at DESC, id DESC LIMIT $3",
+//!     )
+//!     .bind(after_at)
+//!     .bind(after_id)
+//!     .bind(limit as i64 + 1)
+//!     .fetch_all(state.db())
+//!     .await?;
+//!
+//!     let page = CursorPage::from_overfetch(orders, limit, |o| (o.created_at, o.id));
+//!     let rows = OrderRows { orders: &page.items }.render()?;
+//!     Ok(page.fragment(rows, LoadMore::scroll("/orders").rows(4)))
+//! }
+//! ```
+
+use askama::Template;
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
+use tracing::warn;
+
+/// Query parameter carrying the cursor
+pub const CURSOR_PARAM: &str = "after";
+
+/// Pagination errors
+#[derive(Debug, thiserror::Error)]
+pub enum PaginationError {
+    /// The cursor was not produced by [`Cursor::encode`] for these keys
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+}
+
+impl IntoResponse for PaginationError {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}
+
+/// Opaque position in a sorted list
+///
+/// Encodes the sort keys of the last item of a page as URL-safe base64
+/// JSON. Cursors are not signed: treat the decoded keys as untrusted input
+/// and only ever bind them as query parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Cursor(String);
+
+impl Cursor {
+    /// Encode sort keys
+    ///
+    /// Keys that cannot be serialized to JSON encode as `null`.
+    #[must_use]
+    pub fn encode<K: Serialize>(keys: &K) -> Self {
+        let json = serde_json::to_vec(keys).unwrap_or_else(|_| b"null".to_vec());
+        Self(URL_SAFE_NO_PAD.encode(json))
+    }
+
+    /// Decode the sort keys
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PaginationError::InvalidCursor`] if the token is malformed
+    /// or encodes keys of a different shape.
+    pub fn decode<K: DeserializeOwned>(&self) -> Result<K, PaginationError> {
+        let json = URL_SAFE_NO_PAD
+            .decode(&self.0)
+            .map_err(|e| PaginationError::InvalidCursor(e.to_string()))?;
+        serde_json::from_slice(&json).map_err(|e| PaginationError::InvalidCursor(e.to_string()))
+    }
+
+    /// Wrap a token received from a client
+    #[must_use]
+    pub fn from_token(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+
+    /// The token
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Column a keyset-paginated query is sorted by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    /// Column name as selected by the query
+    pub column: String,
+    /// Sort in descending order
+    pub descending: bool,
+}
+
+impl SortKey {
+    /// Sort by `column` in ascending order
+    #[must_use]
+    pub fn asc(column: impl Into<String>) -> Self {
+        Self {
+            column: column.into(),
+            descending: false,
+        }
+    }
+
+    /// Sort by `column` in descending order
+    #[must_use]
+    pub fn desc(column: impl Into<String>) -> Self {
+        Self {
+            column: column.into(),
+            descending: true,
+        }
+    }
+}
+
+/// `?after=<cursor>&limit=<n>` query parameters
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CursorParams {
+    /// Cursor of the page to continue after, absent for the first page
+    #[serde(default)]
+    pub after: Option<Cursor>,
+    /// Requested page size
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl CursorParams {
+    /// Decode the cursor, `None` for the first page
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PaginationError::InvalidCursor`] if the cursor is malformed.
+    pub fn cursor<K: DeserializeOwned>(&self) -> Result<Option<K>, PaginationError> {
+        self.after.as_ref().map(Cursor::decode).transpose()
+    }
+
+    /// Page size: the requested limit clamped to `1..=max`, or `default`
+    #[must_use]
+    pub fn limit(&self, default: usize, max: usize) -> usize {
+        self.limit.unwrap_or(default).clamp(1, max.max(1))
+    }
+}
+
+/// A page of items and the cursor of the page after it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CursorPage<T> {
+    /// Items in list order
+    pub items: Vec<T>,
+    /// Cursor of the next page, `None` on the last page
+    pub next: Option<Cursor>,
+}
+
+impl<T> CursorPage<T> {
+    /// Build a page from up to `limit + 1` items
+    ///
+    /// Fetch one item more than the page size: if it arrives there is a
+    /// next page, which starts after the sort keys `key` returns for the
+    /// last item kept.
+    #[must_use]
+    pub fn from_overfetch<K, F>(mut items: Vec<T>, limit: usize, key: F) -> Self
+    where
+        K: Serialize,
+        F: FnOnce(&T) -> K,
+    {
+        if items.len() <= limit {
+            return Self { items, next: None };
+        }
+        items.truncate(limit);
+        let next = items.last().map(|last| Cursor::encode(&key(last)));
+        Self { items, next }
+    }
+
+    /// Whether this is the last page
+    #[must_use]
+    pub const fn is_last(&self) -> bool {
+        self.next.is_none()
+    }
+
+    /// Convert the items, keeping the cursor
+    #[must_use]
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> CursorPage<U> {
+        CursorPage {
+            items: self.items.into_iter().map(f).collect(),
+            next: self.next,
+        }
+    }
+
+    /// `base` with the next page's cursor added to its query string
+    #[must_use]
+    pub fn next_url(&self, base: &str) -> Option<String> {
+        self.next.as_ref().map(|cursor| {
+            let separator = if base.contains('?') { '&' } else { '?' };
+            format!("{base}{separator}{CURSOR_PARAM}={cursor}")
+        })
+    }
+
+    /// HTML fragment of the rendered `items` followed by the trigger for
+    /// the next page, if there is one
+    ///
+    /// Serve the first page inside the list container; later pages replace
+    /// the trigger that requested them, so the list grows in place.
+    pub fn fragment(&self, items: impl Into<String>, more: LoadMore) -> Response {
+        let mut html = items.into();
+        if let Some(url) = self.next_url(&more.url) {
+            match (LoadMore { url, ..more }).render() {
+                Ok(trigger) => html.push_str(&trigger),
+                Err(e) => {
+                    warn!(error = %e, "Failed to render load-more trigger");
+                    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            }
+        }
+        Html(html).into_response()
+    }
+}
+
+/// Element requesting the next page (`partials/load_more.html`)
+///
+/// Use [`CursorPage::fragment`] to point it at the next page; rendered
+/// directly, `url` is requested as-is.
+#[derive(Debug, Clone, Template)]
+#[template(path = "partials/load_more.html")]
+pub struct LoadMore {
+    /// URL of the next page
+    pub url: String,
+    /// Load when scrolled into view rather than on a button click
+    pub infinite: bool,
+    /// Button text
+    pub label: String,
+    /// Indicator text shown while the page loads
+    pub loading: String,
+    /// Render as a table row spanning this many columns
+    pub colspan: Option<u32>,
+}
+
+impl LoadMore {
+    /// Load the next page when the trigger scrolls into view
+    #[must_use]
+    pub fn scroll(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            infinite: true,
+            label: "Load more".to_string(),
+            loading: "Loading…".to_string(),
+            colspan: None,
+        }
+    }
+
+    /// Load the next page when a "Load more" button is clicked
+    #[must_use]
+    pub fn button(url: impl Into<String>) -> Self {
+        Self {
+            infinite: false,
+            ..Self::scroll(url)
+        }
+    }
+
+    /// Set the button text
+    #[must_use]
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Set the indicator text
+    #[must_use]
+    pub fn loading(mut self, loading: impl Into<String>) -> Self {
+        self.loading = loading.into();
+        self
+    }
+
+    /// Render as a `<tr>` for lists that are table bodies
+    #[must_use]
+    pub const fn rows(mut self, colspan: u32) -> Self {
+        self.colspan = Some(colspan);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor::encode(&(1_700_000_000_i64, "order/42?&"));
+        assert!(cursor
+            .as_str()
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        let keys: (i64, String) = cursor.decode().unwrap();
+        assert_eq!(keys, (1_700_000_000, "order/42?&".to_string()));
+
+        assert!(Cursor::from_token("not base64!").decode::<i64>().is_err());
+        assert!(cursor.decode::<i64>().is_err());
+
+        let params: CursorParams =
+            serde_json::from_value(serde_json::json!({ "after": cursor.as_str(), "limit": 500 }))
+                .unwrap();
+        assert_eq!(
+            params.cursor::<(i64, String)>().unwrap().unwrap().0,
+            1_700_000_000
+        );
+        assert_eq!(params.limit(25, 100), 100);
+        assert_eq!(CursorParams::default().limit(25, 100), 25);
+        assert_eq!(CursorParams::default().cursor::<i64>().unwrap(), None);
+    }
+
+    #[test]
+    fn test_page_from_overfetch() {
+        let page = CursorPage::from_overfetch(vec![1, 2, 3, 4], 3, |n| *n);
+        assert_eq!(page.items, [1, 2, 3]);
+        assert_eq!(page.next.as_ref().unwrap().decode::<i32>().unwrap(), 3);
+        let url = page.next_url("/items?sort=new").unwrap();
+        assert!(url.starts_with("/items?sort=new&after="));
+
+        let last = CursorPage::from_overfetch(vec![1, 2, 3], 3, |n| *n);
+        assert!(last.is_last());
+        assert_eq!(last.next_url("/items"), None);
+        assert_eq!(last.map(|n| n * 10).items, [10, 20, 30]);
+    }
+
+    #[tokio::test]
+    async fn test_fragment_appends_trigger() {
+        let page = CursorPage::from_overfetch(vec![1, 2], 1, |n| *n);
+        let html = body(page.fragment("<li>1</li>", LoadMore::scroll("/items"))).await;
+        let next = page.next_url("/items").unwrap();
+        assert!(html.starts_with("<li>1</li>"));
+        assert!(html.contains(&format!(r#"hx-get="{next}""#)), "{html}");
+        assert!(html.contains(r#"hx-trigger="revealed""#), "{html}");
+
+        let html = body(page.fragment("", LoadMore::button("/items").rows(3))).await;
+        assert!(html.contains("<tr class=\"load-more\">"), "{html}");
+        assert!(html.contains(r#"<td colspan="3">"#), "{html}");
+        assert!(html.contains(">Load more</button>"), "{html}");
+
+        let last = CursorPage::from_overfetch(vec![1], 1, |n| *n);
+        let html = body(last.fragment("<li>1</li>", LoadMore::scroll("/items"))).await;
+        assert_eq!(html, "<li>1</li>");
+    }
+}
@@ -0,0 +1,117 @@
+//! Presence store backed by cache-service
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{PresenceError, PresenceMember, PresenceStore};
+use crate::htmx::clients::CacheClient;
+use crate::htmx::util::unix_now;
+
+/// Presence store shared between instances through cache-service
+///
+/// Each resource is a hash (`presence:{resource}`) of member ID to a JSON
+/// entry carrying its expiry time. Stale entries are ignored on read and
+/// overwritten by the next heartbeat; leaving writes an already-expired
+/// entry, since cache-service has no per-field delete.
+#[derive(Debug, Clone)]
+pub struct CachePresenceStore {
+    client: Arc<RwLock<CacheClient>>,
+    prefix: String,
+}
+
+impl CachePresenceStore {
+    /// Create a store using the `presence:` key prefix
+    #[must_use]
+    pub fn new(client: Arc<RwLock<CacheClient>>) -> Self {
+        Self {
+            client,
+            prefix: "presence:".to_string(),
+        }
+    }
+
+    /// Use a different key prefix
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, resource: &str) -> String {
+        format!("{}{resource}", self.prefix)
+    }
+
+    async fn entry(
+        &self,
+        key: &str,
+        member_id: &str,
+    ) -> Result<Option<PresenceMember>, PresenceError> {
+        let raw = self
+            .client
+            .write()
+            .await
+            .hget(key, member_id)
+            .await
+            .map_err(|e| PresenceError::Store(e.to_string()))?;
+        Ok(raw.and_then(|bytes| serde_json::from_slice(&bytes).ok()))
+    }
+
+    async fn write(&self, key: &str, member: &PresenceMember) -> Result<(), PresenceError> {
+        let bytes = serde_json::to_vec(member).map_err(|e| PresenceError::Store(e.to_string()))?;
+        self.client
+            .write()
+            .await
+            .hset(key, &member.id, &bytes)
+            .await
+            .map_err(|e| PresenceError::Store(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PresenceStore for CachePresenceStore {
+    async fn heartbeat(
+        &self,
+        resource: &str,
+        member: PresenceMember,
+    ) -> Result<bool, PresenceError> {
+        let key = self.key(resource);
+        let now = unix_now();
+        let was_live = self
+            .entry(&key, &member.id)
+            .await?
+            .is_some_and(|m| m.is_live(now));
+        self.write(&key, &member).await?;
+        Ok(!was_live)
+    }
+
+    async fn leave(&self, resource: &str, member_id: &str) -> Result<bool, PresenceError> {
+        let key = self.key(resource);
+        let now = unix_now();
+        let Some(mut member) = self.entry(&key, member_id).await? else {
+            return Ok(false);
+        };
+        let was_live = member.is_live(now);
+        member.expires_at = 0;
+        self.write(&key, &member).await?;
+        Ok(was_live)
+    }
+
+    async fn members(&self, resource: &str) -> Result<Vec<PresenceMember>, PresenceError> {
+        let fields = self
+            .client
+            .write()
+            .await
+            .hgetall(&self.key(resource))
+            .await
+            .map_err(|e| PresenceError::Store(e.to_string()))?;
+        let now = unix_now();
+        let mut members: Vec<PresenceMember> = fields
+            .values()
+            .filter_map(|bytes| serde_json::from_slice::<PresenceMember>(bytes).ok())
+            .filter(|m| m.is_live(now))
+            .collect();
+        members.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(members)
+    }
+}
@@ -0,0 +1,608 @@
+//! Who's online: soft real-time presence tracking
+//!
+//! Browsers send a heartbeat while a page is open, either by HTMX polling or
+//! from a WebSocket handler. Each heartbeat refreshes a TTL-limited entry for
+//! the viewer on a resource (`doc:42`, `ticket:7`), so closed tabs drop out on
+//! their own after the TTL.
+//!
+//! [`Presence`] records heartbeats in a [`PresenceStore`], answers "who is
+//! viewing this" queries, and broadcasts [`PresenceEvent`]s when viewers join
+//! or leave. [`Presence::routes`] wires it up for HTMX:
+//!
+//! - `POST /presence/{resource}/heartbeat` records the viewer and returns the
+//!   viewer count fragment
+//! - `POST /presence/{resource}/leave` removes the viewer (send with
+//!   `hx-trigger="unload"` or `navigator.sendBeacon`)
+//! - `GET /presence/{resource}/events` streams join/leave events as SSE
+//!
+//! With the `microservices` feature, `CachePresenceStore` shares presence
+//! between instances through cache-service.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::presence::{MemoryPresenceStore, Presence};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let presence = Presence::new(Arc::new(MemoryPresenceStore::new()))
+//!     .with_ttl(Duration::from_secs(30));
+//! presence.spawn_sweeper(Duration::from_secs(10));
+//!
+//! let app = Router::new().merge(presence.routes());
+//! ```
+//!
+//! ```html
+//! <span hx-post="/presence/doc:42/heartbeat" hx-trigger="load, every 15s"></span>
+//! <div hx-ext="sse" sse-connect="/presence/doc:42/events" sse-swap="presence"></div>
+//! ```
+
+#[cfg(feature = "microservices")]
+mod cache_store;
+
+#[cfg(feature = "microservices")]
+pub use cache_store::CachePresenceStore;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
+    routing::{get, post},
+    Extension, Router,
+};
+use futures_util::stream::Stream;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::htmx::auth::session::{SessionData, SessionId};
+use crate::htmx::util::unix_now;
+
+/// Default time a heartbeat keeps a viewer present
+pub const DEFAULT_TTL: Duration = Duration::from_secs(45);
+
+/// Buffered events per subscriber before slow receivers start lagging
+const EVENT_CAPACITY: usize = 256;
+
+/// Presence errors
+#[derive(Debug, thiserror::Error)]
+pub enum PresenceError {
+    /// Backing store failed
+    #[error("Presence store error: {0}")]
+    Store(String),
+}
+
+impl IntoResponse for PresenceError {
+    fn into_response(self) -> Response {
+        warn!(error = %self, "Presence request failed");
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+/// A viewer of a resource
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresenceMember {
+    /// Stable viewer identifier (user ID, or session ID for anonymous viewers)
+    pub id: String,
+    /// Display name, if known
+    pub name: Option<String>,
+    /// Unix time (seconds) after which the entry is stale
+    pub expires_at: u64,
+}
+
+impl PresenceMember {
+    /// Create a member entry that expires `ttl` from now
+    #[must_use]
+    pub fn new(id: impl Into<String>, name: Option<String>, ttl: Duration) -> Self {
+        Self {
+            id: id.into(),
+            name,
+            expires_at: unix_now().saturating_add(ttl.as_secs()),
+        }
+    }
+
+    /// Whether the entry is still live at `now` (Unix seconds)
+    #[must_use]
+    pub const fn is_live(&self, now: u64) -> bool {
+        self.expires_at > now
+    }
+}
+
+/// Join or leave notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PresenceEvent {
+    /// A viewer started viewing a resource
+    Joined {
+        /// Resource identifier
+        resource: String,
+        /// Viewer that joined
+        member: PresenceMember,
+    },
+    /// A viewer left or timed out
+    Left {
+        /// Resource identifier
+        resource: String,
+        /// Identifier of the viewer that left
+        member_id: String,
+    },
+}
+
+impl PresenceEvent {
+    /// Resource the event belongs to
+    #[must_use]
+    pub fn resource(&self) -> &str {
+        match self {
+            Self::Joined { resource, .. } | Self::Left { resource, .. } => resource,
+        }
+    }
+}
+
+/// Storage for presence entries
+#[async_trait]
+pub trait PresenceStore: Send + Sync {
+    /// Insert or refresh a member; returns `true` if the member was not live
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PresenceError::Store`] if the entry cannot be written.
+    async fn heartbeat(
+        &self,
+        resource: &str,
+        member: PresenceMember,
+    ) -> Result<bool, PresenceError>;
+
+    /// Remove a member; returns `true` if the member was live
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PresenceError::Store`] if the entry cannot be removed.
+    async fn leave(&self, resource: &str, member_id: &str) -> Result<bool, PresenceError>;
+
+    /// Live members of a resource
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PresenceError::Store`] if the store cannot be read.
+    async fn members(&self, resource: &str) -> Result<Vec<PresenceMember>, PresenceError>;
+}
+
+/// In-process presence store for single-instance deployments and tests
+#[derive(Debug, Default)]
+pub struct MemoryPresenceStore {
+    resources: RwLock<HashMap<String, HashMap<String, PresenceMember>>>,
+}
+
+impl MemoryPresenceStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PresenceStore for MemoryPresenceStore {
+    async fn heartbeat(
+        &self,
+        resource: &str,
+        member: PresenceMember,
+    ) -> Result<bool, PresenceError> {
+        let now = unix_now();
+        let mut resources = self.resources.write();
+        let members = resources.entry(resource.to_string()).or_default();
+        members.retain(|_, m| m.is_live(now));
+        let joined = members.insert(member.id.clone(), member).is_none();
+        drop(resources);
+        Ok(joined)
+    }
+
+    async fn leave(&self, resource: &str, member_id: &str) -> Result<bool, PresenceError> {
+        let now = unix_now();
+        let mut resources = self.resources.write();
+        let removed = resources
+            .get_mut(resource)
+            .and_then(|members| members.remove(member_id))
+            .is_some_and(|m| m.is_live(now));
+        if resources.get(resource).is_some_and(HashMap::is_empty) {
+            resources.remove(resource);
+        }
+        drop(resources);
+        Ok(removed)
+    }
+
+    async fn members(&self, resource: &str) -> Result<Vec<PresenceMember>, PresenceError> {
+        let now = unix_now();
+        let mut members: Vec<PresenceMember> = self
+            .resources
+            .read()
+            .get(resource)
+            .map(|members| {
+                members
+                    .values()
+                    .filter(|m| m.is_live(now))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        members.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(members)
+    }
+}
+
+/// Presence tracker
+///
+/// Cheap to clone; clones share the store, event channel and the set of
+/// watched resources.
+#[derive(Clone)]
+pub struct Presence {
+    store: Arc<dyn PresenceStore>,
+    ttl: Duration,
+    events: broadcast::Sender<PresenceEvent>,
+    /// Members last seen per resource, used to detect timeouts
+    watched: Arc<Mutex<HashMap<String, HashSet<String>>>>,
+}
+
+impl std::fmt::Debug for Presence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Presence")
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Presence {
+    /// Create a tracker backed by `store`
+    #[must_use]
+    pub fn new(store: Arc<dyn PresenceStore>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            store,
+            ttl: DEFAULT_TTL,
+            events,
+            watched: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Set how long a heartbeat keeps a viewer present (default 45s)
+    ///
+    /// Poll at roughly a third of this so one dropped request does not
+    /// flicker the viewer out.
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Record a heartbeat, broadcasting [`PresenceEvent::Joined`] for new viewers
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be written.
+    pub async fn heartbeat(
+        &self,
+        resource: &str,
+        member_id: &str,
+        name: Option<String>,
+    ) -> Result<(), PresenceError> {
+        let member = PresenceMember::new(member_id, name, self.ttl);
+        let joined = self.store.heartbeat(resource, member.clone()).await?;
+        self.watched
+            .lock()
+            .entry(resource.to_string())
+            .or_default()
+            .insert(member_id.to_string());
+        if joined {
+            let _ = self.events.send(PresenceEvent::Joined {
+                resource: resource.to_string(),
+                member,
+            });
+        }
+        Ok(())
+    }
+
+    /// Remove a viewer, broadcasting [`PresenceEvent::Left`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be written.
+    pub async fn leave(&self, resource: &str, member_id: &str) -> Result<(), PresenceError> {
+        let removed = self.store.leave(resource, member_id).await?;
+        if let Some(members) = self.watched.lock().get_mut(resource) {
+            members.remove(member_id);
+        }
+        if removed {
+            self.emit_left(resource, member_id);
+        }
+        Ok(())
+    }
+
+    /// Live viewers of a resource
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read.
+    pub async fn members(&self, resource: &str) -> Result<Vec<PresenceMember>, PresenceError> {
+        self.store.members(resource).await
+    }
+
+    /// Number of live viewers of a resource
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read.
+    pub async fn count(&self, resource: &str) -> Result<usize, PresenceError> {
+        Ok(self.members(resource).await?.len())
+    }
+
+    /// Subscribe to join/leave events for all resources
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<PresenceEvent> {
+        self.events.subscribe()
+    }
+
+    /// Broadcast [`PresenceEvent::Left`] for viewers whose heartbeats expired
+    ///
+    /// Called periodically by [`Presence::spawn_sweeper`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read.
+    pub async fn sweep(&self) -> Result<(), PresenceError> {
+        let resources: Vec<String> = self.watched.lock().keys().cloned().collect();
+        for resource in resources {
+            let live: HashSet<String> = self
+                .store
+                .members(&resource)
+                .await?
+                .into_iter()
+                .map(|m| m.id)
+                .collect();
+
+            let expired: Vec<String> = {
+                let mut watched = self.watched.lock();
+                let Some(known) = watched.get_mut(&resource) else {
+                    continue;
+                };
+                let expired = known.difference(&live).cloned().collect();
+                known.retain(|id| live.contains(id));
+                if known.is_empty() {
+                    watched.remove(&resource);
+                }
+                expired
+            };
+            for member_id in expired {
+                self.emit_left(&resource, &member_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run [`Presence::sweep`] every `interval` on a background task
+    #[allow(clippy::must_use_candidate)] // the task runs detached
+    pub fn spawn_sweeper(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let presence = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = presence.sweep().await {
+                    warn!(error = %e, "Presence sweep failed");
+                }
+            }
+        })
+    }
+
+    /// SSE stream of events for one resource
+    ///
+    /// Each event is named `presence` and carries the updated viewer count
+    /// fragment, ready for `sse-swap="presence"`. The JSON event is available
+    /// from [`Presence::subscribe`] for custom rendering.
+    pub fn sse(&self, resource: &str) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+        let receiver = self.subscribe();
+        let presence = self.clone();
+        let resource = resource.to_string();
+        let stream = futures_util::stream::unfold(receiver, move |mut receiver| {
+            let presence = presence.clone();
+            let resource = resource.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) if event.resource() == resource => {
+                            let count = presence.count(&resource).await.unwrap_or(0);
+                            let event = Event::default()
+                                .event("presence")
+                                .data(viewer_count_fragment(count));
+                            return Some((Ok(event), receiver));
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        Sse::new(stream).keep_alive(KeepAlive::default())
+    }
+
+    /// HTMX routes under `/presence/{resource}`
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/presence/{resource}/heartbeat", post(heartbeat_handler))
+            .route("/presence/{resource}/leave", post(leave_handler))
+            .route("/presence/{resource}/events", get(events_handler))
+            .with_state(self.clone())
+    }
+
+    fn emit_left(&self, resource: &str, member_id: &str) {
+        let _ = self.events.send(PresenceEvent::Left {
+            resource: resource.to_string(),
+            member_id: member_id.to_string(),
+        });
+    }
+}
+
+/// Viewer count fragment (`1 person viewing`, `3 people viewing`)
+#[must_use]
+pub fn viewer_count_fragment(count: usize) -> String {
+    let noun = if count == 1 { "person" } else { "people" };
+    format!(r#"<span class="presence-count" data-count="{count}">{count} {noun} viewing</span>"#)
+}
+
+/// Viewer identity from the session: user ID when logged in, else session ID
+fn viewer(
+    session_id: Option<&SessionId>,
+    session: Option<&SessionData>,
+) -> Option<(String, Option<String>)> {
+    session.and_then(|s| s.user_id).map_or_else(
+        || session_id.map(|id| (format!("session:{id}"), None)),
+        |user_id| {
+            Some((
+                format!("user:{user_id}"),
+                session.and_then(|s| s.user_name.clone()),
+            ))
+        },
+    )
+}
+
+async fn heartbeat_handler(
+    State(presence): State<Presence>,
+    Path(resource): Path<String>,
+    session_id: Option<Extension<SessionId>>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Response, PresenceError> {
+    let Some((member_id, name)) = viewer(
+        session_id.as_ref().map(|e| &e.0),
+        session.as_ref().map(|e| &e.0),
+    ) else {
+        return Ok(StatusCode::UNAUTHORIZED.into_response());
+    };
+    presence.heartbeat(&resource, &member_id, name).await?;
+    let count = presence.count(&resource).await?;
+    Ok(Html(viewer_count_fragment(count)).into_response())
+}
+
+async fn leave_handler(
+    State(presence): State<Presence>,
+    Path(resource): Path<String>,
+    session_id: Option<Extension<SessionId>>,
+    session: Option<Extension<SessionData>>,
+) -> Result<StatusCode, PresenceError> {
+    if let Some((member_id, _)) = viewer(
+        session_id.as_ref().map(|e| &e.0),
+        session.as_ref().map(|e| &e.0),
+    ) {
+        presence.leave(&resource, &member_id).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[allow(clippy::unused_async)] // axum handler
+async fn events_handler(
+    State(presence): State<Presence>,
+    Path(resource): Path<String>,
+) -> impl IntoResponse {
+    presence.sse(&resource)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn presence() -> Presence {
+        Presence::new(Arc::new(MemoryPresenceStore::new()))
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_joins_once_and_counts() {
+        let presence = presence();
+        let mut events = presence.subscribe();
+
+        presence
+            .heartbeat("doc:1", "user:1", Some("Ada".into()))
+            .await
+            .unwrap();
+        presence
+            .heartbeat("doc:1", "user:1", Some("Ada".into()))
+            .await
+            .unwrap();
+        presence.heartbeat("doc:1", "user:2", None).await.unwrap();
+        presence.heartbeat("doc:2", "user:3", None).await.unwrap();
+
+        assert_eq!(presence.count("doc:1").await.unwrap(), 2);
+        let first = events.recv().await.unwrap();
+        assert!(matches!(first, PresenceEvent::Joined { ref member, .. } if member.id == "user:1"));
+        let second = events.recv().await.unwrap();
+        assert!(
+            matches!(second, PresenceEvent::Joined { ref member, .. } if member.id == "user:2")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_leave_and_expiry_emit_left() {
+        let presence = presence().with_ttl(Duration::ZERO);
+        presence.heartbeat("doc:1", "user:1", None).await.unwrap();
+        let mut events = presence.subscribe();
+
+        // Zero TTL: the entry is already stale, so the sweep reports it
+        presence.sweep().await.unwrap();
+        assert_eq!(
+            events.recv().await.unwrap(),
+            PresenceEvent::Left {
+                resource: "doc:1".into(),
+                member_id: "user:1".into()
+            }
+        );
+        assert_eq!(presence.count("doc:1").await.unwrap(), 0);
+
+        let presence = presence.with_ttl(DEFAULT_TTL);
+        presence.heartbeat("doc:1", "user:2", None).await.unwrap();
+        presence.leave("doc:1", "user:2").await.unwrap();
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            PresenceEvent::Joined { .. }
+        ));
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            PresenceEvent::Left { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_route_returns_count() {
+        let presence = presence();
+        let mut session = SessionData::new();
+        session.user_id = Some(7);
+        let app = presence
+            .routes()
+            .layer(Extension(SessionId::generate()))
+            .layer(Extension(session));
+
+        let request = Request::post("/presence/doc:9/heartbeat")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("1 person viewing"));
+        assert_eq!(presence.members("doc:9").await.unwrap()[0].id, "user:7");
+    }
+}
@@ -0,0 +1,827 @@
+//! GDPR data export and account deletion
+//!
+//! Personal data lives in many places: application tables, uploaded files,
+//! the auth service. Each place registers a [`PrivacyModule`] that knows how
+//! to export and erase one user's data there. [`Privacy`] orchestrates them:
+//!
+//! - [`Privacy::export`] gathers every module's records into a zip archive
+//!   (run it in the background with [`DataExportJob`])
+//! - [`Privacy::request_deletion`] schedules erasure after a grace period
+//!   during which the user can [cancel](Privacy::cancel_deletion);
+//!   [`Privacy::process_due`] (or [`ProcessDeletionsJob`]) runs every
+//!   module's erasure hook once the grace period ends
+//! - every step is written to an audit trail in the [`PrivacyStore`]
+//!
+//! With the `microservices` feature, [`DataServiceModule`], [`FileServiceModule`]
+//! and [`AuthServiceModule`] cover the built-in services, and
+//! [`DataServicePrivacyStore`] persists requests and the audit trail.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::privacy::{self, DataExportJob, MemoryPrivacyStore, Privacy};
+//!
+//! let privacy = Privacy::new(Arc::new(MemoryPrivacyStore::new()))
+//!     .with_module(DataServiceModule::new(registry.data()?).with_table("orders", "user_id")?)
+//!     .with_module(FileServiceModule::new(registry.file()?))
+//!     .with_module(AuthServiceModule::new(registry.auth()?))
+//!     .with_grace_period(chrono::Duration::days(14));
+//! privacy::install(privacy.clone())?;
+//!
+//! // "Download my data"
+//! queue.enqueue(DataExportJob::new(user.id)).await?;
+//!
+//! // "Delete my account"
+//! let request = privacy.request_deletion(user.id, "user").await?;
+//! ```
+
+#[cfg(feature = "microservices")]
+mod services;
+
+#[cfg(feature = "microservices")]
+pub use services::{
+    AuthServiceModule, DataServiceModule, DataServicePrivacyStore, FileServiceModule,
+    PRIVACY_MIGRATION,
+};
+
+use async_trait::async_trait;
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::sync::{Arc, OnceLock};
+use tracing::{info, warn};
+
+use crate::htmx::jobs::{Job, JobContext, JobError, JobResult};
+use crate::htmx::storage::{StoredFile, UploadedFile};
+
+/// Default time between a deletion request and erasure
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::days(30);
+
+static PRIVACY: OnceLock<Privacy> = OnceLock::new();
+
+/// Privacy errors
+#[derive(Debug, thiserror::Error)]
+pub enum PrivacyError {
+    /// A module failed to export or erase data
+    #[error("Privacy module {module} failed: {message}")]
+    Module {
+        /// Module name
+        module: String,
+        /// Failure description
+        message: String,
+    },
+
+    /// The privacy store failed
+    #[error("Privacy store error: {0}")]
+    Store(String),
+
+    /// The export archive could not be written
+    #[error("Archive error: {0}")]
+    Archive(String),
+
+    /// Invalid configuration (for example a bad table name)
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    /// [`install`] has not been called
+    #[error("Privacy service is not installed")]
+    NotInstalled,
+
+    /// [`install`] was called more than once
+    #[error("Privacy service is already installed")]
+    AlreadyInstalled,
+}
+
+impl PrivacyError {
+    /// Module failure with a message
+    pub fn module(module: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Module {
+            module: module.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// One file in an export archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportEntry {
+    /// Path inside the module's directory (`orders.json`)
+    pub path: String,
+    /// File contents
+    pub contents: Vec<u8>,
+}
+
+impl ExportEntry {
+    /// Entry with raw contents
+    #[must_use]
+    pub fn new(path: impl Into<String>, contents: Vec<u8>) -> Self {
+        Self {
+            path: path.into(),
+            contents,
+        }
+    }
+
+    /// Entry containing pretty-printed JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Archive`] if `value` cannot be serialized.
+    pub fn json<T: Serialize>(path: impl Into<String>, value: &T) -> Result<Self, PrivacyError> {
+        serde_json::to_vec_pretty(value)
+            .map(|contents| Self::new(path, contents))
+            .map_err(|e| PrivacyError::Archive(e.to_string()))
+    }
+}
+
+/// Export and erasure hooks for one place that holds personal data
+#[async_trait]
+pub trait PrivacyModule: Send + Sync {
+    /// Short unique name, used as the directory in export archives
+    fn name(&self) -> &str;
+
+    /// Everything this module holds about the user
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data cannot be read.
+    async fn export(&self, user_id: i64) -> Result<Vec<ExportEntry>, PrivacyError>;
+
+    /// Erase or anonymize the user's data; returns the number of records
+    /// affected
+    ///
+    /// Must be idempotent: a failed deletion is retried from the start.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data cannot be erased.
+    async fn erase(&self, user_id: i64) -> Result<u64, PrivacyError>;
+}
+
+/// State of a deletion request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionStatus {
+    /// Waiting for the grace period to end
+    Pending,
+    /// Withdrawn by the user or an administrator
+    Cancelled,
+    /// All modules erased their data
+    Completed,
+    /// At least one module failed; retried on the next run
+    Failed,
+}
+
+impl DeletionStatus {
+    /// Status name as stored
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Cancelled => "cancelled",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+
+    /// Parse a stored status name
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(Self::Pending),
+            "cancelled" => Some(Self::Cancelled),
+            "completed" => Some(Self::Completed),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A scheduled account deletion
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeletionRequest {
+    /// User to erase
+    pub user_id: i64,
+    /// When the request was made
+    pub requested_at: DateTime<Utc>,
+    /// When erasure runs
+    pub scheduled_for: DateTime<Utc>,
+    /// Current state
+    pub status: DeletionStatus,
+}
+
+impl DeletionRequest {
+    /// Whether erasure should run at `now`
+    #[must_use]
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        matches!(
+            self.status,
+            DeletionStatus::Pending | DeletionStatus::Failed
+        ) && self.scheduled_for <= now
+    }
+}
+
+/// One audit trail record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyAuditEntry {
+    /// Subject of the action
+    pub user_id: i64,
+    /// What happened (`export_generated`, `deletion_requested`, `module_erased`, ...)
+    pub action: String,
+    /// Module involved, if any
+    pub module: Option<String>,
+    /// Who triggered it or what resulted (`user`, `admin:7`, `12 records`)
+    pub detail: Option<String>,
+    /// When it happened
+    pub at: DateTime<Utc>,
+}
+
+impl PrivacyAuditEntry {
+    /// Entry timestamped now
+    #[must_use]
+    pub fn new(user_id: i64, action: impl Into<String>) -> Self {
+        Self {
+            user_id,
+            action: action.into(),
+            module: None,
+            detail: None,
+            at: Utc::now(),
+        }
+    }
+
+    /// Attach the module name
+    #[must_use]
+    pub fn with_module(mut self, module: impl Into<String>) -> Self {
+        self.module = Some(module.into());
+        self
+    }
+
+    /// Attach a detail string
+    #[must_use]
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Storage for deletion requests and the audit trail
+///
+/// The audit trail must outlive the user's data: it records that erasure
+/// happened, not what was erased.
+#[async_trait]
+pub trait PrivacyStore: Send + Sync {
+    /// Insert or replace the request for `request.user_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] on failure.
+    async fn save_request(&self, request: &DeletionRequest) -> Result<(), PrivacyError>;
+
+    /// The user's latest request
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] on failure.
+    async fn get_request(&self, user_id: i64) -> Result<Option<DeletionRequest>, PrivacyError>;
+
+    /// Requests that should run at `now`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] on failure.
+    async fn due_requests(&self, now: DateTime<Utc>) -> Result<Vec<DeletionRequest>, PrivacyError>;
+
+    /// Append an audit record
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] on failure.
+    async fn append_audit(&self, entry: &PrivacyAuditEntry) -> Result<(), PrivacyError>;
+
+    /// Audit records for a user, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] on failure.
+    async fn audit_trail(&self, user_id: i64) -> Result<Vec<PrivacyAuditEntry>, PrivacyError>;
+}
+
+/// In-process privacy store
+#[derive(Debug, Default)]
+pub struct MemoryPrivacyStore {
+    requests: Mutex<HashMap<i64, DeletionRequest>>,
+    audit: Mutex<Vec<PrivacyAuditEntry>>,
+}
+
+impl MemoryPrivacyStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PrivacyStore for MemoryPrivacyStore {
+    async fn save_request(&self, request: &DeletionRequest) -> Result<(), PrivacyError> {
+        self.requests
+            .lock()
+            .insert(request.user_id, request.clone());
+        Ok(())
+    }
+
+    async fn get_request(&self, user_id: i64) -> Result<Option<DeletionRequest>, PrivacyError> {
+        Ok(self.requests.lock().get(&user_id).cloned())
+    }
+
+    async fn due_requests(&self, now: DateTime<Utc>) -> Result<Vec<DeletionRequest>, PrivacyError> {
+        let mut due: Vec<_> = self
+            .requests
+            .lock()
+            .values()
+            .filter(|r| r.is_due(now))
+            .cloned()
+            .collect();
+        due.sort_by_key(|r| r.scheduled_for);
+        Ok(due)
+    }
+
+    async fn append_audit(&self, entry: &PrivacyAuditEntry) -> Result<(), PrivacyError> {
+        self.audit.lock().push(entry.clone());
+        Ok(())
+    }
+
+    async fn audit_trail(&self, user_id: i64) -> Result<Vec<PrivacyAuditEntry>, PrivacyError> {
+        Ok(self
+            .audit
+            .lock()
+            .iter()
+            .filter(|e| e.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Export and deletion orchestrator
+#[derive(Clone)]
+pub struct Privacy {
+    modules: Arc<Vec<Arc<dyn PrivacyModule>>>,
+    store: Arc<dyn PrivacyStore>,
+    grace_period: Duration,
+}
+
+impl std::fmt::Debug for Privacy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Privacy")
+            .field(
+                "modules",
+                &self.modules.iter().map(|m| m.name()).collect::<Vec<_>>(),
+            )
+            .field("grace_period", &self.grace_period)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Privacy {
+    /// Create an orchestrator with no modules
+    #[must_use]
+    pub fn new(store: Arc<dyn PrivacyStore>) -> Self {
+        Self {
+            modules: Arc::new(Vec::new()),
+            store,
+            grace_period: DEFAULT_GRACE_PERIOD,
+        }
+    }
+
+    /// Register a module
+    #[must_use]
+    pub fn with_module(mut self, module: impl PrivacyModule + 'static) -> Self {
+        Arc::make_mut(&mut self.modules).push(Arc::new(module));
+        self
+    }
+
+    /// Set the time between a deletion request and erasure (default 30 days)
+    #[must_use]
+    pub const fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Build a zip archive of everything the modules hold about the user
+    ///
+    /// The archive contains `manifest.json` plus one directory per module.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first module failure, or [`PrivacyError::Archive`].
+    pub async fn export(&self, user_id: i64) -> Result<Vec<u8>, PrivacyError> {
+        let mut sections = Vec::with_capacity(self.modules.len());
+        for module in self.modules.iter() {
+            sections.push((module.name().to_string(), module.export(user_id).await?));
+        }
+        let archive = build_archive(user_id, &sections)?;
+        self.audit(
+            PrivacyAuditEntry::new(user_id, "export_generated")
+                .with_detail(format!("{} bytes", archive.len())),
+        )
+        .await?;
+        Ok(archive)
+    }
+
+    /// Schedule the user's data for erasure after the grace period
+    ///
+    /// Repeating the request while one is pending returns the existing
+    /// request unchanged. `requested_by` is recorded in the audit trail.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] on failure.
+    pub async fn request_deletion(
+        &self,
+        user_id: i64,
+        requested_by: &str,
+    ) -> Result<DeletionRequest, PrivacyError> {
+        if let Some(existing) = self.store.get_request(user_id).await? {
+            if existing.status == DeletionStatus::Pending {
+                return Ok(existing);
+            }
+        }
+        let now = Utc::now();
+        let request = DeletionRequest {
+            user_id,
+            requested_at: now,
+            scheduled_for: now + self.grace_period,
+            status: DeletionStatus::Pending,
+        };
+        self.store.save_request(&request).await?;
+        self.audit(PrivacyAuditEntry::new(user_id, "deletion_requested").with_detail(requested_by))
+            .await?;
+        Ok(request)
+    }
+
+    /// Withdraw a pending deletion; returns `false` if none was pending
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] on failure.
+    pub async fn cancel_deletion(
+        &self,
+        user_id: i64,
+        cancelled_by: &str,
+    ) -> Result<bool, PrivacyError> {
+        let Some(mut request) = self.store.get_request(user_id).await? else {
+            return Ok(false);
+        };
+        if request.status != DeletionStatus::Pending {
+            return Ok(false);
+        }
+        request.status = DeletionStatus::Cancelled;
+        self.store.save_request(&request).await?;
+        self.audit(PrivacyAuditEntry::new(user_id, "deletion_cancelled").with_detail(cancelled_by))
+            .await?;
+        Ok(true)
+    }
+
+    /// The user's latest deletion request
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] on failure.
+    pub async fn deletion_status(
+        &self,
+        user_id: i64,
+    ) -> Result<Option<DeletionRequest>, PrivacyError> {
+        self.store.get_request(user_id).await
+    }
+
+    /// Run erasure for every request whose grace period has ended
+    ///
+    /// Each module's hook runs even if an earlier one failed; a request with
+    /// any failure is marked [`DeletionStatus::Failed`] and retried on the
+    /// next run. Returns the processed requests with their new status.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] if the store fails.
+    pub async fn process_due(&self) -> Result<Vec<DeletionRequest>, PrivacyError> {
+        let mut processed = Vec::new();
+        for mut request in self.store.due_requests(Utc::now()).await? {
+            let user_id = request.user_id;
+            let mut failed = false;
+            for module in self.modules.iter() {
+                let entry =
+                    PrivacyAuditEntry::new(user_id, "module_erased").with_module(module.name());
+                match module.erase(user_id).await {
+                    Ok(count) => {
+                        self.audit(entry.with_detail(format!("{count} records")))
+                            .await?;
+                    }
+                    Err(e) => {
+                        warn!(user_id, module = module.name(), error = %e, "Erasure failed");
+                        failed = true;
+                        self.audit(PrivacyAuditEntry {
+                            action: "module_erase_failed".to_string(),
+                            ..entry.with_detail(e.to_string())
+                        })
+                        .await?;
+                    }
+                }
+            }
+            request.status = if failed {
+                DeletionStatus::Failed
+            } else {
+                info!(user_id, "Account data erased");
+                self.audit(PrivacyAuditEntry::new(user_id, "deletion_completed"))
+                    .await?;
+                DeletionStatus::Completed
+            };
+            self.store.save_request(&request).await?;
+            processed.push(request);
+        }
+        Ok(processed)
+    }
+
+    /// Audit records for a user, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Store`] on failure.
+    pub async fn audit_trail(&self, user_id: i64) -> Result<Vec<PrivacyAuditEntry>, PrivacyError> {
+        self.store.audit_trail(user_id).await
+    }
+
+    async fn audit(&self, entry: PrivacyAuditEntry) -> Result<(), PrivacyError> {
+        self.store.append_audit(&entry).await
+    }
+}
+
+/// Register the process-wide orchestrator used by the privacy jobs
+///
+/// # Errors
+///
+/// Returns [`PrivacyError::AlreadyInstalled`] if called twice.
+pub fn install(privacy: Privacy) -> Result<(), PrivacyError> {
+    PRIVACY
+        .set(privacy)
+        .map_err(|_| PrivacyError::AlreadyInstalled)
+}
+
+/// The orchestrator registered with [`install`]
+///
+/// # Errors
+///
+/// Returns [`PrivacyError::NotInstalled`] before [`install`] is called.
+pub fn privacy() -> Result<&'static Privacy, PrivacyError> {
+    PRIVACY.get().ok_or(PrivacyError::NotInstalled)
+}
+
+/// Write module sections into a zip archive with a manifest
+fn build_archive(
+    user_id: i64,
+    sections: &[(String, Vec<ExportEntry>)],
+) -> Result<Vec<u8>, PrivacyError> {
+    let archive_error = |e: &dyn std::fmt::Display| PrivacyError::Archive(e.to_string());
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+
+    let manifest = serde_json::json!({
+        "user_id": user_id,
+        "generated_at": Utc::now(),
+        "modules": sections
+            .iter()
+            .map(|(name, entries)| serde_json::json!({ "name": name, "files": entries.len() }))
+            .collect::<Vec<_>>(),
+    });
+    zip.start_file("manifest.json", options)
+        .map_err(|e| archive_error(&e))?;
+    zip.write_all(manifest.to_string().as_bytes())
+        .map_err(|e| archive_error(&e))?;
+
+    for (module, entries) in sections {
+        for entry in entries {
+            let path = entry.path.trim_start_matches('/').replace("..", "_");
+            zip.start_file(format!("{module}/{path}"), options)
+                .map_err(|e| archive_error(&e))?;
+            zip.write_all(&entry.contents)
+                .map_err(|e| archive_error(&e))?;
+        }
+    }
+    Ok(zip.finish().map_err(|e| archive_error(&e))?.into_inner())
+}
+
+/// Download response for an export archive
+#[must_use]
+pub fn archive_response(user_id: i64, archive: Vec<u8>) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", archive_filename(user_id)),
+            ),
+        ],
+        archive,
+    )
+        .into_response()
+}
+
+fn archive_filename(user_id: i64) -> String {
+    format!("data-export-{user_id}-{}.zip", Utc::now().format("%Y%m%d"))
+}
+
+/// Background job building a user's export archive into file storage
+///
+/// Uses the orchestrator registered with [`install`] and the job context's
+/// file storage; returns the stored archive for a download link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataExportJob {
+    /// User to export
+    pub user_id: i64,
+}
+
+impl DataExportJob {
+    /// Export job for a user
+    #[must_use]
+    pub const fn new(user_id: i64) -> Self {
+        Self { user_id }
+    }
+}
+
+#[async_trait]
+impl Job for DataExportJob {
+    type Result = StoredFile;
+
+    async fn execute(&self, ctx: &JobContext) -> JobResult<Self::Result> {
+        let storage = ctx
+            .file_storage()
+            .ok_or_else(|| JobError::ExecutionFailed("file storage not configured".to_string()))?;
+        let privacy = privacy().map_err(|e| JobError::ExecutionFailed(e.to_string()))?;
+        let archive = privacy
+            .export(self.user_id)
+            .await
+            .map_err(|e| JobError::ExecutionFailed(e.to_string()))?;
+        storage
+            .store(UploadedFile::new(
+                archive_filename(self.user_id),
+                "application/zip",
+                archive,
+            ))
+            .await
+            .map_err(|e| JobError::ExecutionFailed(e.to_string()))
+    }
+
+    fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(1800)
+    }
+}
+
+/// Background job running [`Privacy::process_due`]; schedule it daily
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessDeletionsJob;
+
+#[async_trait]
+impl Job for ProcessDeletionsJob {
+    type Result = usize;
+
+    async fn execute(&self, _ctx: &JobContext) -> JobResult<Self::Result> {
+        let privacy = privacy().map_err(|e| JobError::ExecutionFailed(e.to_string()))?;
+        privacy
+            .process_due()
+            .await
+            .map(|processed| processed.len())
+            .map_err(|e| JobError::ExecutionFailed(e.to_string()))
+    }
+
+    fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(1800)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read as _;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    struct Orders {
+        erased: AtomicU64,
+        fail: bool,
+    }
+
+    impl Orders {
+        fn new(fail: bool) -> Self {
+            Self {
+                erased: AtomicU64::new(0),
+                fail,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl PrivacyModule for Orders {
+        fn name(&self) -> &'static str {
+            "orders"
+        }
+
+        async fn export(&self, user_id: i64) -> Result<Vec<ExportEntry>, PrivacyError> {
+            Ok(vec![ExportEntry::json(
+                "orders.json",
+                &serde_json::json!([{ "user_id": user_id, "total": 42 }]),
+            )?])
+        }
+
+        async fn erase(&self, _user_id: i64) -> Result<u64, PrivacyError> {
+            if self.fail {
+                return Err(PrivacyError::module("orders", "database unavailable"));
+            }
+            self.erased.fetch_add(1, Ordering::SeqCst);
+            Ok(3)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_archive() {
+        let privacy =
+            Privacy::new(Arc::new(MemoryPrivacyStore::new())).with_module(Orders::new(false));
+        let archive = privacy.export(7).await.unwrap();
+
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+        let mut contents = String::new();
+        zip.by_name("orders/orders.json")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert!(contents.contains("\"total\": 42"));
+        assert!(zip.by_name("manifest.json").is_ok());
+
+        let trail = privacy.audit_trail(7).await.unwrap();
+        assert_eq!(trail[0].action, "export_generated");
+    }
+
+    #[tokio::test]
+    async fn test_deletion_grace_period_and_cancel() {
+        let privacy =
+            Privacy::new(Arc::new(MemoryPrivacyStore::new())).with_module(Orders::new(false));
+        let request = privacy.request_deletion(7, "user").await.unwrap();
+        assert_eq!(request.status, DeletionStatus::Pending);
+        assert_eq!(
+            request.scheduled_for - request.requested_at,
+            DEFAULT_GRACE_PERIOD
+        );
+
+        // Still within the grace period
+        assert!(privacy.process_due().await.unwrap().is_empty());
+
+        assert!(privacy.cancel_deletion(7, "user").await.unwrap());
+        assert!(!privacy.cancel_deletion(7, "user").await.unwrap());
+        let actions: Vec<_> = privacy
+            .audit_trail(7)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.action)
+            .collect();
+        assert_eq!(actions, ["deletion_requested", "deletion_cancelled"]);
+    }
+
+    #[tokio::test]
+    async fn test_process_due_runs_hooks() {
+        let privacy = Privacy::new(Arc::new(MemoryPrivacyStore::new()))
+            .with_module(Orders::new(false))
+            .with_grace_period(Duration::zero());
+        privacy.request_deletion(7, "admin:1").await.unwrap();
+
+        let processed = privacy.process_due().await.unwrap();
+        assert_eq!(processed[0].status, DeletionStatus::Completed);
+        assert!(privacy.process_due().await.unwrap().is_empty());
+
+        let trail = privacy.audit_trail(7).await.unwrap();
+        assert_eq!(trail[1].module.as_deref(), Some("orders"));
+        assert_eq!(trail[1].detail.as_deref(), Some("3 records"));
+        assert_eq!(trail[2].action, "deletion_completed");
+    }
+
+    #[tokio::test]
+    async fn test_failed_erasure_is_retried() {
+        let privacy = Privacy::new(Arc::new(MemoryPrivacyStore::new()))
+            .with_module(Orders::new(true))
+            .with_grace_period(Duration::zero());
+        privacy.request_deletion(7, "user").await.unwrap();
+
+        assert_eq!(
+            privacy.process_due().await.unwrap()[0].status,
+            DeletionStatus::Failed
+        );
+        assert_eq!(privacy.process_due().await.unwrap().len(), 1);
+        assert_eq!(
+            privacy.deletion_status(7).await.unwrap().unwrap().status,
+            DeletionStatus::Failed
+        );
+    }
+}
@@ -0,0 +1,495 @@
+//! Privacy modules and store backed by the built-in services
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{
+    DeletionRequest, DeletionStatus, ExportEntry, PrivacyAuditEntry, PrivacyError, PrivacyModule,
+    PrivacyStore,
+};
+use crate::htmx::clients::json::JsonMessage;
+use crate::htmx::clients::{AuthClient, DataClient, FileClient, Row, StoredFileInfo, Value};
+use crate::htmx::util::validate_identifier;
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+/// Migration creating the deletion request and audit tables
+pub const PRIVACY_MIGRATION: &str = r"CREATE TABLE IF NOT EXISTS privacy_deletion_requests (
+    user_id BIGINT PRIMARY KEY,
+    requested_at BIGINT NOT NULL,
+    scheduled_for BIGINT NOT NULL,
+    status TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS privacy_audit_log (
+    user_id BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    module TEXT,
+    detail TEXT,
+    at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_privacy_audit_user ON privacy_audit_log (user_id, at);";
+
+const FILE_PAGE_SIZE: i32 = 100;
+
+/// Exports and deletes rows in data-service tables keyed by user ID
+#[derive(Debug, Clone)]
+pub struct DataServiceModule {
+    client: Arc<RwLock<DataClient>>,
+    tables: Vec<(String, String)>,
+}
+
+impl DataServiceModule {
+    /// Create a module with no tables
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<DataClient>>) -> Self {
+        Self {
+            client,
+            tables: Vec::new(),
+        }
+    }
+
+    /// Include rows of `table` whose `user_column` equals the user ID
+    ///
+    /// Tables are erased in registration order, so register child tables
+    /// before the tables they reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PrivacyError::Config`] if either name is not a plain SQL
+    /// identifier.
+    pub fn with_table(
+        mut self,
+        table: impl Into<String>,
+        user_column: impl Into<String>,
+    ) -> Result<Self, PrivacyError> {
+        let (table, user_column) = (table.into(), user_column.into());
+        validate_identifier(&table).map_err(PrivacyError::Config)?;
+        validate_identifier(&user_column).map_err(PrivacyError::Config)?;
+        self.tables.push((table, user_column));
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl PrivacyModule for DataServiceModule {
+    fn name(&self) -> &'static str {
+        "data"
+    }
+
+    async fn export(&self, user_id: i64) -> Result<Vec<ExportEntry>, PrivacyError> {
+        let mut entries = Vec::with_capacity(self.tables.len());
+        for (table, column) in &self.tables {
+            let rows = self
+                .client
+                .write()
+                .await
+                .query(
+                    &format!("SELECT * FROM {table} WHERE {column} = $1"),
+                    vec![int(user_id)],
+                    None,
+                )
+                .await
+                .map_err(|e| PrivacyError::module("data", e.to_string()))?;
+            let rows: Vec<_> = rows.iter().map(row_to_json).collect();
+            entries.push(ExportEntry::json(format!("{table}.json"), &rows)?);
+        }
+        Ok(entries)
+    }
+
+    async fn erase(&self, user_id: i64) -> Result<u64, PrivacyError> {
+        let mut erased = 0;
+        for (table, column) in &self.tables {
+            let result = self
+                .client
+                .write()
+                .await
+                .execute(
+                    &format!("DELETE FROM {table} WHERE {column} = $1"),
+                    vec![int(user_id)],
+                    None,
+                )
+                .await
+                .map_err(|e| PrivacyError::module("data", e.to_string()))?;
+            erased += result.rows_affected.unsigned_abs();
+        }
+        Ok(erased)
+    }
+}
+
+/// Exports and deletes file-service files owned by the user
+///
+/// Ownership comes from the file's metadata (`user_id` by default), so
+/// uploads must record it. Listing scans every file; run exports as
+/// background jobs.
+#[derive(Debug, Clone)]
+pub struct FileServiceModule {
+    client: Arc<RwLock<FileClient>>,
+    owner_key: String,
+}
+
+impl FileServiceModule {
+    /// Create a module matching the `user_id` metadata key
+    #[must_use]
+    pub fn new(client: Arc<RwLock<FileClient>>) -> Self {
+        Self {
+            client,
+            owner_key: "user_id".to_string(),
+        }
+    }
+
+    /// Use a different metadata key for the owner's user ID
+    #[must_use]
+    pub fn with_owner_key(mut self, key: impl Into<String>) -> Self {
+        self.owner_key = key.into();
+        self
+    }
+
+    async fn owned_files(&self, user_id: i64) -> Result<Vec<StoredFileInfo>, PrivacyError> {
+        let owner_id = user_id.to_string();
+        let mut owned = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self
+                .client
+                .write()
+                .await
+                .list_files(None, Some(FILE_PAGE_SIZE), cursor)
+                .await
+                .map_err(|e| PrivacyError::module("files", e.to_string()))?;
+            owned.extend(
+                page.files
+                    .into_iter()
+                    .filter(|f| f.metadata.get(&self.owner_key) == Some(&owner_id)),
+            );
+            match page.next_cursor.filter(|c| !c.is_empty()) {
+                Some(next) => cursor = Some(next),
+                None => return Ok(owned),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl PrivacyModule for FileServiceModule {
+    fn name(&self) -> &'static str {
+        "files"
+    }
+
+    async fn export(&self, user_id: i64) -> Result<Vec<ExportEntry>, PrivacyError> {
+        let files = self.owned_files(user_id).await?;
+        let index: Vec<_> = files
+            .iter()
+            .map(|f| {
+                serde_json::json!({
+                    "id": f.id,
+                    "filename": f.filename,
+                    "content_type": f.content_type,
+                    "size": f.size,
+                    "created_at": f.created_at,
+                })
+            })
+            .collect();
+        let mut entries = vec![ExportEntry::json("index.json", &index)?];
+        for file in files {
+            let download = self
+                .client
+                .write()
+                .await
+                .download(&file.id)
+                .await
+                .map_err(|e| PrivacyError::module("files", e.to_string()))?;
+            entries.push(ExportEntry::new(
+                format!("{}-{}", file.id, file.filename.replace('/', "_")),
+                download.data,
+            ));
+        }
+        Ok(entries)
+    }
+
+    async fn erase(&self, user_id: i64) -> Result<u64, PrivacyError> {
+        let mut erased = 0;
+        for file in self.owned_files(user_id).await? {
+            if self
+                .client
+                .write()
+                .await
+                .delete(&file.id)
+                .await
+                .map_err(|e| PrivacyError::module("files", e.to_string()))?
+            {
+                erased += 1;
+            }
+        }
+        Ok(erased)
+    }
+}
+
+/// Exports and deletes the auth-service account
+///
+/// Deleting the account makes its sessions fail validation; auth-service
+/// has no per-user session listing, so session records themselves expire
+/// on their normal TTL and are not part of the export.
+#[derive(Debug, Clone)]
+pub struct AuthServiceModule {
+    client: Arc<RwLock<AuthClient>>,
+}
+
+impl AuthServiceModule {
+    /// Create the module
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<AuthClient>>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl PrivacyModule for AuthServiceModule {
+    fn name(&self) -> &'static str {
+        "account"
+    }
+
+    async fn export(&self, user_id: i64) -> Result<Vec<ExportEntry>, PrivacyError> {
+        let user = self
+            .client
+            .write()
+            .await
+            .get_user(user_id)
+            .await
+            .map_err(|e| PrivacyError::module("account", e.to_string()))?;
+        let Some(user) = user else {
+            return Ok(Vec::new());
+        };
+        // The password hash is a credential, not personal data
+        Ok(vec![ExportEntry::json(
+            "account.json",
+            &serde_json::json!({
+                "id": user.id,
+                "email": user.email,
+                "name": user.name,
+                "created_at": user.created_at,
+                "updated_at": user.updated_at,
+            }),
+        )?])
+    }
+
+    async fn erase(&self, user_id: i64) -> Result<u64, PrivacyError> {
+        let deleted = self
+            .client
+            .write()
+            .await
+            .delete_user(user_id)
+            .await
+            .map_err(|e| PrivacyError::module("account", e.to_string()))?;
+        Ok(u64::from(deleted))
+    }
+}
+
+/// Privacy store persisting requests and the audit trail in data-service
+///
+/// Apply [`PRIVACY_MIGRATION`] first.
+#[derive(Debug, Clone)]
+pub struct DataServicePrivacyStore {
+    client: Arc<RwLock<DataClient>>,
+}
+
+impl DataServicePrivacyStore {
+    /// Create a store
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<DataClient>>) -> Self {
+        Self { client }
+    }
+
+    async fn requests(
+        &self,
+        sql: &str,
+        params: Vec<Value>,
+    ) -> Result<Vec<DeletionRequest>, PrivacyError> {
+        let rows = self
+            .client
+            .write()
+            .await
+            .query(sql, params, None)
+            .await
+            .map_err(|e| PrivacyError::Store(e.to_string()))?;
+        rows.iter().map(request_from_row).collect()
+    }
+}
+
+#[async_trait]
+impl PrivacyStore for DataServicePrivacyStore {
+    async fn save_request(&self, request: &DeletionRequest) -> Result<(), PrivacyError> {
+        self.client
+            .write()
+            .await
+            .execute(
+                "INSERT INTO privacy_deletion_requests (user_id, requested_at, scheduled_for, status) \
+                 VALUES ($1, $2, $3, $4) \
+                 ON CONFLICT (user_id) DO UPDATE SET requested_at = $2, scheduled_for = $3, status = $4",
+                vec![
+                    int(request.user_id),
+                    int(request.requested_at.timestamp()),
+                    int(request.scheduled_for.timestamp()),
+                    text(request.status.as_str()),
+                ],
+                None,
+            )
+            .await
+            .map_err(|e| PrivacyError::Store(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_request(&self, user_id: i64) -> Result<Option<DeletionRequest>, PrivacyError> {
+        Ok(self
+            .requests(
+                "SELECT user_id, requested_at, scheduled_for, status \
+                 FROM privacy_deletion_requests WHERE user_id = $1",
+                vec![int(user_id)],
+            )
+            .await?
+            .pop())
+    }
+
+    async fn due_requests(&self, now: DateTime<Utc>) -> Result<Vec<DeletionRequest>, PrivacyError> {
+        self.requests(
+            "SELECT user_id, requested_at, scheduled_for, status FROM privacy_deletion_requests \
+             WHERE status IN ('pending', 'failed') AND scheduled_for <= $1 ORDER BY scheduled_for",
+            vec![int(now.timestamp())],
+        )
+        .await
+    }
+
+    async fn append_audit(&self, entry: &PrivacyAuditEntry) -> Result<(), PrivacyError> {
+        let optional = |value: Option<&String>| value.map_or_else(null, |v| text(v));
+        self.client
+            .write()
+            .await
+            .execute(
+                "INSERT INTO privacy_audit_log (user_id, action, module, detail, at) \
+                 VALUES ($1, $2, $3, $4, $5)",
+                vec![
+                    int(entry.user_id),
+                    text(&entry.action),
+                    optional(entry.module.as_ref()),
+                    optional(entry.detail.as_ref()),
+                    int(entry.at.timestamp()),
+                ],
+                None,
+            )
+            .await
+            .map_err(|e| PrivacyError::Store(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn audit_trail(&self, user_id: i64) -> Result<Vec<PrivacyAuditEntry>, PrivacyError> {
+        let rows = self
+            .client
+            .write()
+            .await
+            .query(
+                "SELECT user_id, action, module, detail, at FROM privacy_audit_log \
+                 WHERE user_id = $1 ORDER BY at",
+                vec![int(user_id)],
+                None,
+            )
+            .await
+            .map_err(|e| PrivacyError::Store(e.to_string()))?;
+        rows.iter()
+            .map(|row| {
+                Ok(PrivacyAuditEntry {
+                    user_id: column_int(row, "user_id")?,
+                    action: column_text(row, "action")?,
+                    module: column_text(row, "module").ok(),
+                    detail: column_text(row, "detail").ok(),
+                    at: timestamp(column_int(row, "at")?)?,
+                })
+            })
+            .collect()
+    }
+}
+
+fn request_from_row(row: &Row) -> Result<DeletionRequest, PrivacyError> {
+    let status = column_text(row, "status")?;
+    Ok(DeletionRequest {
+        user_id: column_int(row, "user_id")?,
+        requested_at: timestamp(column_int(row, "requested_at")?)?,
+        scheduled_for: timestamp(column_int(row, "scheduled_for")?)?,
+        status: DeletionStatus::parse(&status)
+            .ok_or_else(|| PrivacyError::Store(format!("unknown deletion status {status:?}")))?,
+    })
+}
+
+/// Convert a data-service row to a JSON object for export
+fn row_to_json(row: &Row) -> serde_json::Value {
+    serde_json::Value::Object(row.to_json())
+}
+
+fn column_int(row: &Row, name: &str) -> Result<i64, PrivacyError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::IntValue(v)) => Ok(*v),
+        other => Err(PrivacyError::Store(format!(
+            "expected integer {name}, got {other:?}"
+        ))),
+    }
+}
+
+fn column_text(row: &Row, name: &str) -> Result<String, PrivacyError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::StringValue(v)) => Ok(v.clone()),
+        other => Err(PrivacyError::Store(format!(
+            "expected text {name}, got {other:?}"
+        ))),
+    }
+}
+
+fn timestamp(seconds: i64) -> Result<DateTime<Utc>, PrivacyError> {
+    Utc.timestamp_opt(seconds, 0)
+        .single()
+        .ok_or_else(|| PrivacyError::Store(format!("invalid timestamp {seconds}")))
+}
+
+const fn int(value: i64) -> Value {
+    Value {
+        value: Some(ValueKind::IntValue(value)),
+    }
+}
+
+fn text(value: &str) -> Value {
+    Value {
+        value: Some(ValueKind::StringValue(value.to_string())),
+    }
+}
+
+const fn null() -> Value {
+    Value {
+        value: Some(ValueKind::NullValue(true)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_conversion() {
+        let row = Row {
+            columns: [
+                ("user_id".to_string(), int(7)),
+                ("requested_at".to_string(), int(1_700_000_000)),
+                ("scheduled_for".to_string(), int(1_702_592_000)),
+                ("status".to_string(), text("pending")),
+                ("note".to_string(), null()),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let request = request_from_row(&row).unwrap();
+        assert_eq!(request.user_id, 7);
+        assert_eq!(request.status, DeletionStatus::Pending);
+
+        let json = row_to_json(&row);
+        assert_eq!(json["status"], "pending");
+        assert!(json["note"].is_null());
+        assert!(validate_identifier("orders; --").is_err());
+    }
+}
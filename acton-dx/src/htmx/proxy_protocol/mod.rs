@@ -0,0 +1,339 @@
+//! PROXY protocol v2 listener
+//!
+//! Load balancers that terminate TCP (AWS NLB, `HAProxy`) forward the original
+//! client address in a binary PROXY protocol header at the start of each
+//! connection. [`ProxyProtocolListener`] wraps a [`TcpListener`], strips that
+//! header from connections made by trusted proxies and reports the client
+//! address to axum as `ConnectInfo<SocketAddr>`.
+//!
+//! Connections from peers that are not trusted are passed through untouched,
+//! so a client cannot inject a header of its own.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::config::ActonHtmxConfig;
+//! use acton_htmx::proxy_protocol::ProxyProtocolListener;
+//! use std::net::SocketAddr;
+//!
+//! let config = ActonHtmxConfig::load_for_service("my-app")?;
+//! let tcp = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
+//! let listener = ProxyProtocolListener::from_config(tcp, &config.proxy)?;
+//! axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+//! ```
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::htmx::config::ProxyConfig;
+use crate::htmx::middleware::forwarded::{InvalidProxyRange, TrustedProxies};
+
+/// PROXY protocol v2 signature
+const SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Fixed header length (signature, version/command, family, length)
+const HEADER_LEN: usize = 16;
+
+/// Connections waiting for `accept` after their header was read
+const ACCEPT_BACKLOG: usize = 128;
+
+/// PROXY protocol parse errors
+#[derive(Debug, thiserror::Error)]
+pub enum ProxyProtocolError {
+    /// Connection did not start with the v2 signature
+    #[error("Missing PROXY protocol v2 signature")]
+    InvalidSignature,
+
+    /// Version or command byte is not supported
+    #[error("Unsupported PROXY protocol version/command byte {0:#04x}")]
+    UnsupportedVersion(u8),
+
+    /// Address block is shorter than the address family requires
+    #[error("Truncated PROXY protocol address block")]
+    Truncated,
+
+    /// Header did not arrive in time
+    #[error("Timed out waiting for PROXY protocol header")]
+    Timeout,
+
+    /// Reading the header failed
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Parse the address block of a v2 header
+///
+/// `header` is the 16-byte fixed header and `block` the address block that
+/// follows it. Returns `None` for `LOCAL` connections (health checks from the
+/// proxy itself) and for address families that carry no client address.
+///
+/// # Errors
+///
+/// Returns an error if the signature, version or address block is invalid.
+pub fn parse_v2(
+    header: &[u8; HEADER_LEN],
+    block: &[u8],
+) -> Result<Option<SocketAddr>, ProxyProtocolError> {
+    if header[..12] != SIGNATURE {
+        return Err(ProxyProtocolError::InvalidSignature);
+    }
+
+    let version_command = header[12];
+    if version_command >> 4 != 2 {
+        return Err(ProxyProtocolError::UnsupportedVersion(version_command));
+    }
+    match version_command & 0x0F {
+        0 => return Ok(None),
+        1 => {}
+        _ => return Err(ProxyProtocolError::UnsupportedVersion(version_command)),
+    }
+
+    match header[13] {
+        // TCP over IPv4: src addr, dst addr, src port, dst port
+        0x11 => {
+            let bytes: &[u8; 12] = block
+                .get(..12)
+                .and_then(|b| b.try_into().ok())
+                .ok_or(ProxyProtocolError::Truncated)?;
+            let ip = Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]);
+            let port = u16::from_be_bytes([bytes[8], bytes[9]]);
+            Ok(Some(SocketAddr::new(IpAddr::V4(ip), port)))
+        }
+        // TCP over IPv6
+        0x21 => {
+            let bytes: &[u8; 36] = block
+                .get(..36)
+                .and_then(|b| b.try_into().ok())
+                .ok_or(ProxyProtocolError::Truncated)?;
+            let mut octets = [0_u8; 16];
+            octets.copy_from_slice(&bytes[..16]);
+            let port = u16::from_be_bytes([bytes[32], bytes[33]]);
+            Ok(Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port)))
+        }
+        // UNSPEC, UDP or UNIX sockets: no usable client address
+        _ => Ok(None),
+    }
+}
+
+/// Read and strip a v2 header from the start of a stream
+///
+/// Reads exactly the header bytes, leaving the HTTP request in the stream.
+///
+/// # Errors
+///
+/// Returns an error if the header is missing, malformed or the read fails.
+pub async fn read_v2_header(
+    stream: &mut TcpStream,
+) -> Result<Option<SocketAddr>, ProxyProtocolError> {
+    let mut header = [0_u8; HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+    if header[..12] != SIGNATURE {
+        return Err(ProxyProtocolError::InvalidSignature);
+    }
+
+    let len = usize::from(u16::from_be_bytes([header[14], header[15]]));
+    let mut block = vec![0_u8; len];
+    stream.read_exact(&mut block).await?;
+    parse_v2(&header, &block)
+}
+
+/// TCP listener that decodes PROXY protocol v2 headers from trusted peers
+///
+/// Implements [`axum::serve::Listener`]. Headers are read on a background
+/// task so a slow or idle connection cannot block `accept`.
+#[derive(Debug)]
+pub struct ProxyProtocolListener {
+    connections: mpsc::Receiver<(TcpStream, SocketAddr)>,
+    local_addr: SocketAddr,
+    acceptor: JoinHandle<()>,
+}
+
+impl ProxyProtocolListener {
+    /// Wrap a TCP listener
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the listener's local address cannot be read.
+    pub fn new(
+        listener: TcpListener,
+        trusted: TrustedProxies,
+        header_timeout: Duration,
+    ) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (tx, connections) = mpsc::channel(ACCEPT_BACKLOG);
+        let acceptor = tokio::spawn(accept_loop(listener, trusted, header_timeout, tx));
+        Ok(Self {
+            connections,
+            local_addr,
+            acceptor,
+        })
+    }
+
+    /// Wrap a TCP listener using the `[proxy]` configuration section
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a trusted proxy entry is invalid or the listener's
+    /// local address cannot be read.
+    pub fn from_config(listener: TcpListener, config: &ProxyConfig) -> io::Result<Self> {
+        let trusted = TrustedProxies::new(&config.trusted_proxies)
+            .map_err(|e: InvalidProxyRange| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Self::new(
+            listener,
+            trusted,
+            Duration::from_millis(config.proxy_protocol_timeout_ms),
+        )
+    }
+}
+
+impl Drop for ProxyProtocolListener {
+    fn drop(&mut self) {
+        self.acceptor.abort();
+    }
+}
+
+impl axum::serve::Listener for ProxyProtocolListener {
+    type Io = TcpStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The acceptor only exits when the listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    trusted: TrustedProxies,
+    header_timeout: Duration,
+    tx: mpsc::Sender<(TcpStream, SocketAddr)>,
+) {
+    loop {
+        let (mut stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                // Usually fd exhaustion; back off instead of spinning
+                warn!(error = %e, "Failed to accept connection");
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                continue;
+            }
+        };
+
+        if !trusted.contains(peer.ip()) {
+            if tx.send((stream, peer)).await.is_err() {
+                return;
+            }
+            continue;
+        }
+
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let header = tokio::time::timeout(header_timeout, read_v2_header(&mut stream))
+                .await
+                .unwrap_or(Err(ProxyProtocolError::Timeout));
+            match header {
+                Ok(client) => {
+                    let _ = tx.send((stream, client.unwrap_or(peer))).await;
+                }
+                Err(e) => debug!(peer = %peer, error = %e, "Dropping connection with invalid PROXY header"),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::serve::Listener;
+    use tokio::io::AsyncWriteExt;
+
+    fn v2_header(command: u8, family: u8, block: &[u8]) -> Vec<u8> {
+        let mut bytes = SIGNATURE.to_vec();
+        bytes.push(0x20 | command);
+        bytes.push(family);
+        bytes.extend_from_slice(&u16::try_from(block.len()).unwrap().to_be_bytes());
+        bytes.extend_from_slice(block);
+        bytes
+    }
+
+    fn split(bytes: &[u8]) -> ([u8; HEADER_LEN], &[u8]) {
+        (bytes[..HEADER_LEN].try_into().unwrap(), &bytes[HEADER_LEN..])
+    }
+
+    #[test]
+    fn test_parse_ipv4() {
+        let block = [203, 0, 113, 7, 10, 0, 0, 1, 0x1F, 0x90, 0x01, 0xBB];
+        let bytes = v2_header(1, 0x11, &block);
+        let (header, block) = split(&bytes);
+        assert_eq!(
+            parse_v2(&header, block).unwrap(),
+            Some("203.0.113.7:8080".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_ipv6_and_local() {
+        let mut block = Vec::new();
+        block.extend_from_slice(&"2001:db8::7".parse::<Ipv6Addr>().unwrap().octets());
+        block.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        block.extend_from_slice(&[0x00, 0x50, 0x01, 0xBB]);
+        let bytes = v2_header(1, 0x21, &block);
+        let (header, block) = split(&bytes);
+        assert_eq!(
+            parse_v2(&header, block).unwrap(),
+            Some("[2001:db8::7]:80".parse().unwrap())
+        );
+
+        let bytes = v2_header(0, 0x00, &[]);
+        let (header, block) = split(&bytes);
+        assert_eq!(parse_v2(&header, block).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_input() {
+        let bytes = v2_header(1, 0x11, &[1, 2, 3]);
+        let (header, block) = split(&bytes);
+        assert!(matches!(parse_v2(&header, block), Err(ProxyProtocolError::Truncated)));
+
+        let mut bytes = v2_header(1, 0x11, &[0; 12]);
+        bytes[0] = b'G';
+        let (header, block) = split(&bytes);
+        assert!(matches!(
+            parse_v2(&header, block),
+            Err(ProxyProtocolError::InvalidSignature)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_listener_reports_client_address() {
+        let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let trusted = TrustedProxies::new(["127.0.0.1"]).unwrap();
+        let mut listener = ProxyProtocolListener::new(tcp, trusted, Duration::from_secs(1)).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let block = [198, 51, 100, 4, 127, 0, 0, 1, 0x30, 0x39, 0x0B, 0xB8];
+        client.write_all(&v2_header(1, 0x11, &block)).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+
+        let (mut stream, remote) = listener.accept().await;
+        assert_eq!(remote, "198.51.100.4:12345".parse().unwrap());
+
+        let mut request_line = [0_u8; 16];
+        stream.read_exact(&mut request_line).await.unwrap();
+        assert_eq!(&request_line, b"GET / HTTP/1.1\r\n");
+    }
+}
@@ -0,0 +1,778 @@
+//! Declarative routing with a route metadata registry
+//!
+//! [`AppRouter`] declares every route in one place, in [`RouteGroup`]s that
+//! share a path prefix and policy: authentication, required roles, a rate
+//! limit class, CSRF exemption (optionally with origin validation in its
+//! place), sitemap listing and htmx [`SwapDefaults`]. Building it produces the
+//! axum [`Router`] with each group's layers applied to its routes only, and
+//! a [`RouteRegistry`] of route names and metadata:
+//!
+//! - [`RouteRegistry::url_for`] builds URLs from route names, so paths are
+//!   written down once. The built router carries the registry as an
+//!   [`Extension`], so handlers can take `Extension<RouteRegistry>`.
+//! - [`RouteRegistry::csrf_skip_paths`] feeds [`CsrfConfig::skip_paths`].
+//! - [`RouteRegistry::sitemap_paths`] feeds [`Sitemap::with_paths`].
+//!
+//! In development, a [`RouteTable`] serves module routers that are rebuilt
+//! and re-mounted as their sources change, without restarting the server.
+//!
+//! [`CsrfConfig::skip_paths`]: crate::htmx::middleware::CsrfConfig::skip_paths
+//! [`Sitemap::with_paths`]: crate::htmx::sitemap::Sitemap::with_paths
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::routing::{AppRouter, RouteGroup};
+//! use acton_htmx::middleware::{CsrfConfig, CsrfLayer, OriginCheck, RateLimit};
+//! use axum::routing::{get, post};
+//!
+//! let (router, routes) = AppRouter::new()
+//!     .with_rate_limit("strict", RateLimit::new(strict_config, None))
+//!     .group(
+//!         RouteGroup::new("")
+//!             .in_sitemap()
+//!             .route("home", "/", get(home))
+//!             .route("pricing", "/pricing", get(pricing)),
+//!     )
+//!     .group(
+//!         RouteGroup::new("")
+//!             .with_rate_limit("strict")
+//!             .route("login", "/login", get(login_form).post(login)),
+//!     )
+//!     .group(
+//!         RouteGroup::new("/admin")
+//!             .require_role("admin")
+//!             .route("admin.users", "/users/{id}", get(show_user)),
+//!     )
+//!     .group(
+//!         RouteGroup::new("/webhooks")
+//!             .csrf_exempt()
+//!             .route("webhooks.provider", "/{provider}", post(webhook)),
+//!     )
+//!     .group(
+//!         RouteGroup::new("/api")
+//!             .verify_origin(OriginCheck::new())
+//!             .route("api.todos", "/todos", get(list_todos).post(create_todo)),
+//!     )
+//!     .build(&state)?;
+//!
+//! let csrf = CsrfConfig::new().skip_paths(routes.csrf_skip_paths());
+//! let sitemap = Sitemap::new(config.sitemap.clone()).with_paths(routes.sitemap_paths());
+//!
+//! assert_eq!(routes.url_for("admin.users", &[("id", "42")])?, "/admin/users/42");
+//! ```
+
+mod swap;
+mod table;
+
+pub use swap::{SwapDefaults, SwapScroll};
+pub use table::{ModuleRoutes, RouteTable, RouteWatcher};
+
+use axum::{
+    extract::{Request, State},
+    middleware::{from_fn, from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    routing::MethodRouter,
+    Extension, Router,
+};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use crate::htmx::middleware::{AuthMiddleware, OriginCheck, RateLimit};
+use crate::htmx::state::ActonHtmxState;
+
+/// Routing errors
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum RouteError {
+    /// Two routes were declared with the same name
+    #[error("Route name '{0}' is declared more than once")]
+    DuplicateName(String),
+
+    /// A group uses a rate limit class that was not registered
+    #[error("Unknown rate limit class '{0}'")]
+    UnknownRateLimitClass(String),
+
+    /// No route has the requested name
+    #[error("No route named '{0}'")]
+    UnknownRoute(String),
+
+    /// A path parameter was not given a value
+    #[error("Route '{route}' needs a value for '{param}'")]
+    MissingParam {
+        /// Route name
+        route: String,
+        /// Parameter name
+        param: String,
+    },
+}
+
+/// Metadata of a declared route
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)] // Independent per-route flags
+pub struct RouteMeta {
+    /// Name used with [`RouteRegistry::url_for`]
+    pub name: String,
+    /// Full path pattern, e.g. `/admin/users/{id}`
+    pub path: String,
+    /// Whether a signed-in user is required
+    pub requires_auth: bool,
+    /// Roles of which the user must hold at least one
+    pub roles: Vec<String>,
+    /// Rate limit class applied to the route
+    pub rate_limit: Option<String>,
+    /// Whether CSRF validation is skipped
+    pub csrf_exempt: bool,
+    /// Whether `Origin`/`Sec-Fetch-Site` are validated instead
+    pub origin_checked: bool,
+    /// Whether the route is listed in the sitemap
+    pub sitemap: bool,
+    /// `HX-Reswap` value sent with its htmx responses
+    pub swap: Option<String>,
+}
+
+impl RouteMeta {
+    /// Whether the path has parameters
+    #[must_use]
+    pub fn is_static(&self) -> bool {
+        !self.path.contains('{')
+    }
+}
+
+/// Routes sharing a path prefix and policy
+pub struct RouteGroup {
+    prefix: String,
+    requires_auth: bool,
+    roles: Vec<String>,
+    rate_limit: Option<String>,
+    csrf_exempt: bool,
+    origin_check: Option<OriginCheck>,
+    sitemap: bool,
+    swap: Option<SwapDefaults>,
+    routes: Vec<(String, String, MethodRouter<ActonHtmxState>)>,
+}
+
+impl std::fmt::Debug for RouteGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouteGroup")
+            .field("prefix", &self.prefix)
+            .field("requires_auth", &self.requires_auth)
+            .field("roles", &self.roles)
+            .field("rate_limit", &self.rate_limit)
+            .field("csrf_exempt", &self.csrf_exempt)
+            .field("origin_check", &self.origin_check)
+            .field("sitemap", &self.sitemap)
+            .field("swap", &self.swap)
+            .field(
+                "routes",
+                &self
+                    .routes
+                    .iter()
+                    .map(|(name, ..)| name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl RouteGroup {
+    /// Create a group whose paths start with `prefix` (may be empty)
+    #[must_use]
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into().trim_end_matches('/').to_string(),
+            requires_auth: false,
+            roles: Vec::new(),
+            rate_limit: None,
+            csrf_exempt: false,
+            origin_check: None,
+            sitemap: false,
+            swap: None,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Require a signed-in user
+    ///
+    /// Anonymous requests are redirected to the login page, or get a 401
+    /// with `HX-Redirect` when made by htmx.
+    #[must_use]
+    pub const fn require_auth(mut self) -> Self {
+        self.requires_auth = true;
+        self
+    }
+
+    /// Require the signed-in user to hold `role`
+    ///
+    /// Implies [`require_auth`](Self::require_auth). With several roles the
+    /// user needs any one of them; others get `403 Forbidden`.
+    #[cfg(any(feature = "postgres", feature = "sqlite"))]
+    #[must_use]
+    pub fn require_role(mut self, role: impl Into<String>) -> Self {
+        self.requires_auth = true;
+        self.roles.push(role.into());
+        self
+    }
+
+    /// Apply the rate limit registered as `class` with
+    /// [`AppRouter::with_rate_limit`]
+    #[must_use]
+    pub fn with_rate_limit(mut self, class: impl Into<String>) -> Self {
+        self.rate_limit = Some(class.into());
+        self
+    }
+
+    /// Skip CSRF validation, e.g. for webhooks authenticated by signature
+    #[must_use]
+    pub const fn csrf_exempt(mut self) -> Self {
+        self.csrf_exempt = true;
+        self
+    }
+
+    /// Skip CSRF token validation and reject cross-site requests by their
+    /// `Origin` and `Sec-Fetch-Site` headers instead
+    ///
+    /// For JSON endpoints called with `fetch()`, which cannot easily send a
+    /// token. See [`OriginCheck`].
+    #[must_use]
+    pub fn verify_origin(mut self, check: OriginCheck) -> Self {
+        self.csrf_exempt = true;
+        self.origin_check = Some(check);
+        self
+    }
+
+    /// List the group's parameterless routes in the sitemap
+    #[must_use]
+    pub const fn in_sitemap(mut self) -> Self {
+        self.sitemap = true;
+        self
+    }
+
+    /// Swap, transition and scroll behavior for the group's htmx responses
+    ///
+    /// Overrides [`AppRouter::with_swap_defaults`]. See [`SwapDefaults`].
+    #[must_use]
+    pub fn with_swap_defaults(mut self, swap: SwapDefaults) -> Self {
+        self.swap = Some(swap);
+        self
+    }
+
+    /// Add a route named `name` at `path` below the group prefix
+    #[must_use]
+    pub fn route(
+        mut self,
+        name: impl Into<String>,
+        path: impl AsRef<str>,
+        method_router: MethodRouter<ActonHtmxState>,
+    ) -> Self {
+        let path = match (self.prefix.as_str(), path.as_ref()) {
+            ("", path) => path.to_string(),
+            (prefix, "/") => prefix.to_string(),
+            (prefix, path) => format!("{prefix}{path}"),
+        };
+        self.routes.push((name.into(), path, method_router));
+        self
+    }
+
+    fn meta(&self, name: &str, path: &str, swap: Option<&SwapDefaults>) -> RouteMeta {
+        RouteMeta {
+            name: name.to_string(),
+            path: path.to_string(),
+            requires_auth: self.requires_auth,
+            roles: self.roles.clone(),
+            rate_limit: self.rate_limit.clone(),
+            csrf_exempt: self.csrf_exempt,
+            origin_checked: self.origin_check.is_some(),
+            sitemap: self.sitemap,
+            swap: self.swap.as_ref().or(swap).map(SwapDefaults::swap_spec),
+        }
+    }
+}
+
+/// Builder for the application router
+pub struct AppRouter {
+    groups: Vec<RouteGroup>,
+    rate_limits: HashMap<String, RateLimit>,
+    login_path: String,
+    swap: Option<SwapDefaults>,
+}
+
+impl std::fmt::Debug for AppRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AppRouter")
+            .field("groups", &self.groups)
+            .field("rate_limits", &self.rate_limits.keys().collect::<Vec<_>>())
+            .field("login_path", &self.login_path)
+            .field("swap", &self.swap)
+            .finish()
+    }
+}
+
+impl Default for AppRouter {
+    fn default() -> Self {
+        Self {
+            groups: Vec::new(),
+            rate_limits: HashMap::new(),
+            login_path: "/login".to_string(),
+            swap: None,
+        }
+    }
+}
+
+impl AppRouter {
+    /// Create an empty router builder
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where unauthenticated users are sent (default: `/login`)
+    #[must_use]
+    pub fn with_login_path(mut self, login_path: impl Into<String>) -> Self {
+        self.login_path = login_path.into();
+        self
+    }
+
+    /// Register a rate limit class that groups can opt into
+    #[must_use]
+    pub fn with_rate_limit(mut self, class: impl Into<String>, rate_limit: RateLimit) -> Self {
+        self.rate_limits.insert(class.into(), rate_limit);
+        self
+    }
+
+    /// Swap, transition and scroll behavior for htmx responses of groups
+    /// without their own [`RouteGroup::with_swap_defaults`]
+    #[must_use]
+    pub fn with_swap_defaults(mut self, swap: SwapDefaults) -> Self {
+        self.swap = Some(swap);
+        self
+    }
+
+    /// Add a group of routes
+    #[must_use]
+    pub fn group(mut self, group: RouteGroup) -> Self {
+        self.groups.push(group);
+        self
+    }
+
+    /// Metadata of every declared route, in declaration order
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RouteError::DuplicateName`] if a route name is reused, or
+    /// [`RouteError::UnknownRateLimitClass`] if a group uses a class that
+    /// was never registered.
+    pub fn registry(&self) -> Result<RouteRegistry, RouteError> {
+        let mut routes: Vec<RouteMeta> = Vec::new();
+        let mut by_name = HashMap::new();
+        for group in &self.groups {
+            if let Some(class) = &group.rate_limit {
+                if !self.rate_limits.contains_key(class) {
+                    return Err(RouteError::UnknownRateLimitClass(class.clone()));
+                }
+            }
+            for (name, path, _) in &group.routes {
+                if by_name.insert(name.clone(), routes.len()).is_some() {
+                    return Err(RouteError::DuplicateName(name.clone()));
+                }
+                routes.push(group.meta(name, path, self.swap.as_ref()));
+            }
+        }
+        Ok(RouteRegistry {
+            inner: Arc::new(RegistryInner { routes, by_name }),
+        })
+    }
+
+    /// Build the router and the registry of its routes
+    ///
+    /// Each group's layers apply only to its own routes. The outermost is
+    /// the rate limit, then origin validation, then authentication, then the
+    /// role check, then the swap defaults. The
+    /// registry is also added to the router as an [`Extension`].
+    ///
+    /// `state` is used to load the signed-in user for role checks; the
+    /// router still needs `.with_state(state)`.
+    ///
+    /// # Errors
+    ///
+    /// See [`AppRouter::registry`].
+    pub fn build(
+        self,
+        state: &ActonHtmxState,
+    ) -> Result<(Router<ActonHtmxState>, RouteRegistry), RouteError> {
+        let registry = self.registry()?;
+        let mut app = Router::new();
+
+        for group in self.groups {
+            let mut router = Router::new();
+            for (_, path, method_router) in group.routes {
+                router = router.route(&path, method_router);
+            }
+
+            if let Some(swap) = group.swap.or_else(|| self.swap.clone()) {
+                router = router.route_layer(from_fn_with_state(swap, SwapDefaults::middleware));
+            }
+
+            #[cfg(any(feature = "postgres", feature = "sqlite"))]
+            if !group.roles.is_empty() {
+                let roles: Arc<[String]> = group.roles.into();
+                router =
+                    router.route_layer(from_fn_with_state((state.clone(), roles), require_roles));
+            }
+            #[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+            let _ = state;
+
+            if group.requires_auth {
+                let auth = AuthMiddleware::with_login_path(self.login_path.clone());
+                router = router.route_layer(from_fn(move |request, next| {
+                    auth.clone().handle_with_config(request, next)
+                }));
+            }
+
+            if let Some(check) = group.origin_check {
+                router = router.route_layer(from_fn_with_state(check, OriginCheck::middleware));
+            }
+
+            if let Some(class) = group.rate_limit {
+                // Checked by `registry()` above
+                if let Some(rate_limit) = self.rate_limits.get(&class) {
+                    router = router.route_layer(from_fn_with_state(
+                        rate_limit.clone(),
+                        RateLimit::middleware,
+                    ));
+                }
+            }
+
+            app = app.merge(router);
+        }
+
+        Ok((app.layer(Extension(registry.clone())), registry))
+    }
+}
+
+/// Answer `403 Forbidden` unless the signed-in user holds one of the roles
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+async fn require_roles(
+    State((state, roles)): State<(ActonHtmxState, Arc<[String]>)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    use crate::htmx::auth::{Authenticated, User};
+    use axum::extract::FromRequestParts;
+
+    let (mut parts, body) = request.into_parts();
+    let user = match Authenticated::<User>::from_request_parts(&mut parts, &state).await {
+        Ok(Authenticated(user)) => user,
+        Err(e) => return e.into_response(),
+    };
+    if !roles.iter().any(|role| user.roles.contains(role)) {
+        tracing::debug!(user_id = user.id, path = %parts.uri.path(), "Missing required role");
+        return axum::http::StatusCode::FORBIDDEN.into_response();
+    }
+    next.run(Request::from_parts(parts, body)).await
+}
+
+#[derive(Debug)]
+struct RegistryInner {
+    routes: Vec<RouteMeta>,
+    by_name: HashMap<String, usize>,
+}
+
+/// Named routes and their metadata
+#[derive(Debug, Clone)]
+pub struct RouteRegistry {
+    inner: Arc<RegistryInner>,
+}
+
+impl RouteRegistry {
+    /// Metadata of the route named `name`
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&RouteMeta> {
+        self.inner
+            .by_name
+            .get(name)
+            .map(|&index| &self.inner.routes[index])
+    }
+
+    /// All routes, in declaration order
+    pub fn iter(&self) -> impl Iterator<Item = &RouteMeta> {
+        self.inner.routes.iter()
+    }
+
+    /// URL of the route named `name`, with `params` filling its path
+    /// parameters
+    ///
+    /// Values are percent-encoded, except that a catch-all parameter
+    /// (`{*rest}`) keeps its slashes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RouteError::UnknownRoute`] for an undeclared name, or
+    /// [`RouteError::MissingParam`] if a path parameter has no value.
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, RouteError> {
+        let route = self
+            .get(name)
+            .ok_or_else(|| RouteError::UnknownRoute(name.to_string()))?;
+
+        let mut url = String::with_capacity(route.path.len());
+        let mut rest = route.path.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .map_or(rest.len(), |end| start + end);
+            url.push_str(&rest[..start]);
+            let param = &rest[start + 1..end];
+            let (param, catch_all) = param
+                .strip_prefix('*')
+                .map_or((param, false), |param| (param, true));
+            let value = params
+                .iter()
+                .find(|(key, _)| *key == param)
+                .map(|(_, value)| *value)
+                .ok_or_else(|| RouteError::MissingParam {
+                    route: name.to_string(),
+                    param: param.to_string(),
+                })?;
+            encode_into(&mut url, value, catch_all);
+            rest = rest.get(end + 1..).unwrap_or_default();
+        }
+        url.push_str(rest);
+        Ok(url)
+    }
+
+    /// Path patterns of CSRF-exempt routes, for [`CsrfConfig::skip_paths`]
+    ///
+    /// [`CsrfConfig::skip_paths`]: crate::htmx::middleware::CsrfConfig::skip_paths
+    #[must_use]
+    pub fn csrf_skip_paths(&self) -> Vec<String> {
+        self.iter()
+            .filter(|route| route.csrf_exempt)
+            .map(|route| route.path.clone())
+            .collect()
+    }
+
+    /// Parameterless paths listed in the sitemap, for
+    /// [`Sitemap::with_paths`](crate::htmx::sitemap::Sitemap::with_paths)
+    #[must_use]
+    pub fn sitemap_paths(&self) -> Vec<String> {
+        self.iter()
+            .filter(|route| route.sitemap && route.is_static())
+            .map(|route| route.path.clone())
+            .collect()
+    }
+}
+
+/// Percent-encode `value` as a path segment or query component
+pub(crate) fn encode_into(url: &mut String, value: &str, keep_slashes: bool) {
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                url.push(char::from(byte));
+            }
+            b'/' if keep_slashes => url.push('/'),
+            _ => {
+                let _ = write!(url, "%{byte:02X}");
+            }
+        }
+    }
+}
+
+/// Whether `path` matches the route pattern `pattern`
+///
+/// `{param}` matches one path segment and `{*rest}` the remainder of the
+/// path; everything else must match exactly.
+#[must_use]
+pub fn path_matches(pattern: &str, path: &str) -> bool {
+    let mut patterns = pattern.split('/');
+    let mut segments = path.split('/');
+    loop {
+        match (patterns.next(), segments.next()) {
+            (None, None) => return true,
+            (Some(p), Some(_)) if p.starts_with("{*") && p.ends_with('}') => return true,
+            (Some(p), Some(s)) if p.starts_with('{') && p.ends_with('}') => {
+                if s.is_empty() {
+                    return false;
+                }
+            }
+            (Some(p), Some(s)) if p == s => {}
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::responses::SwapStrategy;
+    use axum::routing::{get, post};
+
+    fn app() -> AppRouter {
+        AppRouter::new()
+            .group(
+                RouteGroup::new("")
+                    .in_sitemap()
+                    .route("home", "/", get(|| async { "home" }))
+                    .route("post", "/posts/{slug}", get(|| async { "post" })),
+            )
+            .group(
+                RouteGroup::new("/account")
+                    .require_auth()
+                    .route("account", "/", get(|| async { "account" }))
+                    .route("files", "/files/{*path}", get(|| async { "file" })),
+            )
+            .group(RouteGroup::new("/webhooks/").csrf_exempt().route(
+                "webhook",
+                "/{provider}",
+                post(|| async { "ok" }),
+            ))
+            .group(
+                RouteGroup::new("/api")
+                    .verify_origin(OriginCheck::new())
+                    .route("api.todos", "/todos", post(|| async { "created" })),
+            )
+    }
+
+    #[test]
+    fn test_registry_metadata() {
+        let routes = app().registry().unwrap();
+        let account = routes.get("account").unwrap();
+        assert_eq!(account.path, "/account");
+        assert!(account.requires_auth);
+        assert!(!routes.get("home").unwrap().requires_auth);
+        assert_eq!(
+            routes.csrf_skip_paths(),
+            vec!["/webhooks/{provider}", "/api/todos"]
+        );
+        assert!(routes.get("api.todos").unwrap().origin_checked);
+        assert!(!routes.get("webhook").unwrap().origin_checked);
+        assert_eq!(routes.sitemap_paths(), vec!["/"]);
+        assert_eq!(routes.iter().count(), 6);
+    }
+
+    #[test]
+    fn test_url_for() {
+        let routes = app().registry().unwrap();
+        assert_eq!(routes.url_for("home", &[]).unwrap(), "/");
+        assert_eq!(
+            routes.url_for("post", &[("slug", "hello world")]).unwrap(),
+            "/posts/hello%20world"
+        );
+        assert_eq!(
+            routes
+                .url_for("files", &[("path", "docs/a b.pdf")])
+                .unwrap(),
+            "/account/files/docs/a%20b.pdf"
+        );
+        assert_eq!(
+            routes.url_for("post", &[]),
+            Err(RouteError::MissingParam {
+                route: "post".to_string(),
+                param: "slug".to_string(),
+            })
+        );
+        assert_eq!(
+            routes.url_for("missing", &[]),
+            Err(RouteError::UnknownRoute("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_invalid_declarations() {
+        let duplicate = AppRouter::new().group(
+            RouteGroup::new("")
+                .route("home", "/", get(|| async { "a" }))
+                .route("home", "/home", get(|| async { "b" })),
+        );
+        assert_eq!(
+            duplicate.registry().unwrap_err(),
+            RouteError::DuplicateName("home".to_string())
+        );
+
+        let unknown_class =
+            AppRouter::new().group(RouteGroup::new("").with_rate_limit("strict").route(
+                "login",
+                "/login",
+                post(|| async { "ok" }),
+            ));
+        assert_eq!(
+            unknown_class.registry().unwrap_err(),
+            RouteError::UnknownRateLimitClass("strict".to_string())
+        );
+    }
+
+    #[test]
+    fn test_swap_defaults_metadata() {
+        let routes = app()
+            .with_swap_defaults(SwapDefaults::default().with_view_transition())
+            .group(
+                RouteGroup::new("/feed")
+                    .with_swap_defaults(SwapDefaults::new(SwapStrategy::BeforeEnd))
+                    .route("feed", "/", get(|| async { "feed" })),
+            )
+            .registry()
+            .unwrap();
+        assert_eq!(
+            routes.get("home").unwrap().swap.as_deref(),
+            Some("innerHTML transition:true")
+        );
+        assert_eq!(routes.get("feed").unwrap().swap.as_deref(), Some("beforeend"));
+        assert_eq!(app().registry().unwrap().get("home").unwrap().swap, None);
+    }
+
+    #[test]
+    fn test_path_matches() {
+        assert!(path_matches("/webhooks/{provider}", "/webhooks/github"));
+        assert!(!path_matches("/webhooks/{provider}", "/webhooks/"));
+        assert!(!path_matches(
+            "/webhooks/{provider}",
+            "/webhooks/github/extra"
+        ));
+        assert!(path_matches("/files/{*path}", "/files/a/b"));
+        assert!(path_matches("/health", "/health"));
+        assert!(!path_matches("/health", "/healthz"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_build_applies_group_layers() {
+        use acton_reactive::prelude::ActonApp;
+        use axum::{body::Body, http::StatusCode};
+        use tower::ServiceExt;
+
+        let mut runtime = ActonApp::launch_async().await;
+        let state = ActonHtmxState::new(&mut runtime).await.unwrap();
+        let (router, _) = app().build(&state).unwrap();
+        let app = router.with_state(state);
+
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let home = app.clone().oneshot(get("/")).await.unwrap();
+        assert_eq!(home.status(), StatusCode::OK);
+
+        let account = app.clone().oneshot(get("/account")).await.unwrap();
+        assert_eq!(account.status(), StatusCode::SEE_OTHER);
+
+        let api_post = |origin: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/todos")
+                .header("host", "example.com")
+                .header("origin", origin)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let same_origin = app
+            .clone()
+            .oneshot(api_post("http://example.com"))
+            .await
+            .unwrap();
+        assert_eq!(same_origin.status(), StatusCode::OK);
+        let cross_site = app
+            .clone()
+            .oneshot(api_post("https://evil.example.net"))
+            .await
+            .unwrap();
+        assert_eq!(cross_site.status(), StatusCode::FORBIDDEN);
+
+        let missing = app.oneshot(get("/nowhere")).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,261 @@
+//! Per-group defaults for how htmx swaps responses in
+//!
+//! A [`SwapDefaults`] set on a [`RouteGroup`](super::RouteGroup) (or for the
+//! whole app with [`AppRouter::with_swap_defaults`](super::AppRouter::with_swap_defaults))
+//! reaches the browser two ways:
+//!
+//! - successful htmx responses get an `HX-Reswap` header with the swap
+//!   style, view transition, delays and scroll behavior, unless the handler
+//!   set one itself
+//! - handlers can extract the group's [`SwapDefaults`] and pass it to
+//!   templates, which render [`SwapDefaults::attrs`] on links and forms and
+//!   [`SwapDefaults::transition_class`] on swapped content
+//!
+//! `HX-Reswap` takes precedence over `hx-swap` attributes, so a handler
+//! whose element needs a different swap should set `HX-Reswap` itself.
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::convert::Infallible;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::htmx::responses::SwapStrategy;
+
+const HX_REQUEST: &str = "hx-request";
+const HX_RESWAP: &str = "hx-reswap";
+
+/// Where the page scrolls after a swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapScroll {
+    /// Scroll the target element to its top (`scroll:top`)
+    Top,
+    /// Scroll the target element to its bottom (`scroll:bottom`)
+    Bottom,
+    /// Scroll the window to the top, as after a page load (`show:window:top`)
+    WindowTop,
+    /// Keep the scroll position (`show:none`)
+    Keep,
+}
+
+impl SwapScroll {
+    const fn modifier(self) -> &'static str {
+        match self {
+            Self::Top => "scroll:top",
+            Self::Bottom => "scroll:bottom",
+            Self::WindowTop => "show:window:top",
+            Self::Keep => "show:none",
+        }
+    }
+}
+
+/// How htmx swaps responses from a group of routes
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SwapDefaults {
+    style: SwapStrategy,
+    transition: bool,
+    swap_delay: Option<Duration>,
+    settle_delay: Option<Duration>,
+    scroll: Option<SwapScroll>,
+    transition_class: Option<String>,
+}
+
+impl SwapDefaults {
+    /// Swap with `style` and htmx's default timing and scrolling
+    #[must_use]
+    pub fn new(style: SwapStrategy) -> Self {
+        Self {
+            style,
+            ..Self::default()
+        }
+    }
+
+    /// Animate swaps with the browser's View Transitions API
+    #[must_use]
+    pub const fn with_view_transition(mut self) -> Self {
+        self.transition = true;
+        self
+    }
+
+    /// Wait `delay` between removing old content and inserting new content,
+    /// so CSS exit animations on `.htmx-swapping` can run
+    #[must_use]
+    pub const fn with_swap_delay(mut self, delay: Duration) -> Self {
+        self.swap_delay = Some(delay);
+        self
+    }
+
+    /// Wait `delay` before settling, so CSS enter animations on
+    /// `.htmx-added` can run
+    #[must_use]
+    pub const fn with_settle_delay(mut self, delay: Duration) -> Self {
+        self.settle_delay = Some(delay);
+        self
+    }
+
+    /// Scroll after every swap
+    #[must_use]
+    pub const fn with_scroll(mut self, scroll: SwapScroll) -> Self {
+        self.scroll = Some(scroll);
+        self
+    }
+
+    /// CSS class templates put on swapped-in content, e.g. `fade-in`
+    #[must_use]
+    pub fn with_transition_class(mut self, class: impl Into<String>) -> Self {
+        self.transition_class = Some(class.into());
+        self
+    }
+
+    /// The `hx-swap` / `HX-Reswap` value, e.g. `innerHTML transition:true show:window:top`
+    #[must_use]
+    pub fn swap_spec(&self) -> String {
+        let mut spec = self.style.as_str().to_string();
+        if self.transition {
+            spec.push_str(" transition:true");
+        }
+        if let Some(delay) = self.swap_delay {
+            let _ = write!(spec, " swap:{}ms", delay.as_millis());
+        }
+        if let Some(delay) = self.settle_delay {
+            let _ = write!(spec, " settle:{}ms", delay.as_millis());
+        }
+        if let Some(scroll) = self.scroll {
+            spec.push(' ');
+            spec.push_str(scroll.modifier());
+        }
+        spec
+    }
+
+    /// `hx-swap="…"` attribute for templates
+    #[must_use]
+    pub fn attrs(&self) -> String {
+        format!(r#"hx-swap="{}""#, self.swap_spec())
+    }
+
+    /// Class for swapped-in content, or an empty string
+    #[must_use]
+    pub fn transition_class(&self) -> &str {
+        self.transition_class.as_deref().unwrap_or_default()
+    }
+
+    /// Middleware applying the defaults to a group's routes
+    ///
+    /// Makes the defaults extractable and adds `HX-Reswap` to successful
+    /// htmx responses that don't carry one.
+    pub async fn middleware(
+        State(defaults): State<Self>,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        let is_htmx = request
+            .headers()
+            .get(HX_REQUEST)
+            .is_some_and(|value| value == "true");
+        let spec = HeaderValue::from_str(&defaults.swap_spec()).ok();
+        request.extensions_mut().insert(defaults);
+
+        let mut response = next.run(request).await;
+        if let Some(spec) = spec.filter(|_| is_htmx && response.status().is_success()) {
+            response.headers_mut().entry(HX_RESWAP).or_insert(spec);
+        }
+        response
+    }
+}
+
+/// The defaults of the route's group, or htmx's own defaults when it has
+/// none
+impl<S> FromRequestParts<S> for SwapDefaults
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{header, StatusCode},
+        middleware::from_fn_with_state,
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_swap_spec() {
+        assert_eq!(SwapDefaults::default().swap_spec(), "innerHTML");
+
+        let defaults = SwapDefaults::new(SwapStrategy::OuterHTML)
+            .with_view_transition()
+            .with_swap_delay(Duration::from_millis(100))
+            .with_settle_delay(Duration::from_millis(250))
+            .with_scroll(SwapScroll::WindowTop)
+            .with_transition_class("fade-in");
+        assert_eq!(
+            defaults.swap_spec(),
+            "outerHTML transition:true swap:100ms settle:250ms show:window:top"
+        );
+        assert_eq!(
+            defaults.attrs(),
+            r#"hx-swap="outerHTML transition:true swap:100ms settle:250ms show:window:top""#
+        );
+        assert_eq!(defaults.transition_class(), "fade-in");
+        assert_eq!(SwapDefaults::default().transition_class(), "");
+    }
+
+    #[tokio::test]
+    async fn test_middleware_sets_reswap_on_htmx_responses() {
+        let defaults = SwapDefaults::new(SwapStrategy::OuterHTML).with_scroll(SwapScroll::Top);
+        let app = Router::new()
+            .route(
+                "/",
+                get(|defaults: SwapDefaults| async move { defaults.transition_class().to_string() }),
+            )
+            .route(
+                "/custom",
+                get(|| async { ([(HX_RESWAP, "none")], "custom").into_response() }),
+            )
+            .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+            .route_layer(from_fn_with_state(
+                defaults.with_transition_class("fade-in"),
+                SwapDefaults::middleware,
+            ));
+
+        let request = |uri: &str, htmx: bool| {
+            let mut builder = Request::builder().uri(uri);
+            if htmx {
+                builder = builder.header(HX_REQUEST, "true");
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let htmx = app.clone().oneshot(request("/", true)).await.unwrap();
+        assert_eq!(htmx.headers()[HX_RESWAP], "outerHTML scroll:top");
+        let body = axum::body::to_bytes(htmx.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"fade-in");
+
+        let page = app.clone().oneshot(request("/", false)).await.unwrap();
+        assert!(!page.headers().contains_key(HX_RESWAP));
+        assert!(page.headers().contains_key(header::CONTENT_TYPE));
+
+        let custom = app.clone().oneshot(request("/custom", true)).await.unwrap();
+        assert_eq!(custom.headers()[HX_RESWAP], "none");
+
+        let missing = app.oneshot(request("/missing", true)).await.unwrap();
+        assert!(!missing.headers().contains_key(HX_RESWAP));
+    }
+}
@@ -0,0 +1,543 @@
+//! Hot-reloadable route table for development
+//!
+//! A [`RouteTable`] serves module routers, such as the CRUD modules written
+//! by `acton-dx htmx scaffold`, that can be mounted, re-mounted and
+//! unmounted while the server keeps running. Each [`ModuleRoutes`] names
+//! its source files, an optional build command and a factory producing its
+//! router. [`RouteTable::watch`] reports changes to those sources to the
+//! [`HotReloadCoordinatorAgent`], and on each debounced
+//! [`ReloadType::Routes`] event rebuilds only the affected modules and swaps
+//! in their new routers. Modules that fail to build keep serving their
+//! previous router.
+//!
+//! Compiled handlers only change when their code is rebuilt, so a module
+//! whose handlers should change without a restart builds itself as a
+//! dynamic library (`with_build_command("cargo", ["build", "-p", "posts"])`)
+//! and loads it in its factory, e.g. with `libloading`. Modules whose
+//! factory reads runtime state, such as templates, need no build command.
+//!
+//! A discovery hook mounts modules generated after the server started: a
+//! change below the discovery root that belongs to no mounted module is
+//! offered to the hook, which may return a module to build and mount.
+//!
+//! [`HotReloadCoordinatorAgent`]: crate::htmx::agents::HotReloadCoordinatorAgent
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::routing::{ModuleRoutes, RouteTable};
+//!
+//! let table = RouteTable::new();
+//! table.mount(
+//!     ModuleRoutes::new("posts", "/posts", || Ok(handlers::posts::routes().with_state(state.clone())))
+//!         .with_sources(["src/handlers/posts.rs", "templates/posts"]),
+//! )?;
+//!
+//! let app = Router::new()
+//!     .route("/", get(index))
+//!     .fallback_service(table.router());
+//!
+//! // Keep the watcher alive as long as the server runs
+//! let hot_reload = HotReloadCoordinatorAgent::spawn(&mut runtime).await?;
+//! let _watcher = table.watch(&hot_reload).await?;
+//! ```
+
+use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Router,
+};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tower::ServiceExt;
+
+use crate::htmx::agents::{FileChanged, HotReloadSubscribe, ReloadType, TriggerPendingReloads};
+
+/// How often pending changes are offered to the hot reload agent
+const TRIGGER_INTERVAL: Duration = Duration::from_millis(50);
+
+type RouterFactory = dyn Fn() -> anyhow::Result<Router> + Send + Sync;
+type DiscoverFn = dyn Fn(&Path) -> Option<ModuleRoutes> + Send + Sync;
+
+/// A module router that can be rebuilt and re-mounted at runtime
+#[derive(Clone)]
+pub struct ModuleRoutes {
+    name: String,
+    prefix: String,
+    sources: Vec<PathBuf>,
+    build_command: Option<(String, Vec<String>)>,
+    factory: Arc<RouterFactory>,
+}
+
+impl std::fmt::Debug for ModuleRoutes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModuleRoutes")
+            .field("name", &self.name)
+            .field("prefix", &self.prefix)
+            .field("sources", &self.sources)
+            .field("build_command", &self.build_command)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ModuleRoutes {
+    /// Create a module serving paths under `prefix` with the router built
+    /// by `factory`
+    ///
+    /// The router receives full request paths, so its routes include the
+    /// prefix (`/posts/{id}`).
+    #[must_use]
+    pub fn new(
+        name: impl Into<String>,
+        prefix: impl Into<String>,
+        factory: impl Fn() -> anyhow::Result<Router> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            prefix: prefix.into().trim_end_matches('/').to_string(),
+            sources: Vec::new(),
+            build_command: None,
+            factory: Arc::new(factory),
+        }
+    }
+
+    /// Files and directories whose changes rebuild the module
+    #[must_use]
+    pub fn with_sources<I, P>(mut self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.sources.extend(sources.into_iter().map(Into::into));
+        self
+    }
+
+    /// Command run before the factory when the module is rebuilt
+    #[must_use]
+    pub fn with_build_command<I, S>(mut self, program: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.build_command = Some((program.into(), args.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Module name
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether any of `paths` is one of the module's sources or below one
+    fn is_affected_by(&self, paths: &[PathBuf]) -> bool {
+        paths.iter().any(|path| {
+            self.sources
+                .iter()
+                .any(|source| path.starts_with(source) || path.ends_with(source))
+        })
+    }
+
+    /// Whether the module serves `path`
+    fn serves(&self, path: &str) -> bool {
+        path.strip_prefix(self.prefix.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    /// Run the build command, if any, then the factory
+    async fn build(&self) -> anyhow::Result<Router> {
+        if let Some((program, args)) = &self.build_command {
+            let status = tokio::process::Command::new(program)
+                .args(args)
+                .status()
+                .await?;
+            anyhow::ensure!(status.success(), "`{program}` exited with {status}");
+        }
+        (self.factory)()
+    }
+}
+
+struct Mounted {
+    module: ModuleRoutes,
+    router: Router,
+}
+
+/// Module routers that can be swapped while the server runs
+#[derive(Clone, Default)]
+pub struct RouteTable {
+    mounted: Arc<RwLock<Vec<Mounted>>>,
+    discovery: Option<(PathBuf, Arc<DiscoverFn>)>,
+}
+
+impl std::fmt::Debug for RouteTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouteTable")
+            .field("modules", &self.modules())
+            .field("discovery", &self.discovery.as_ref().map(|(root, _)| root))
+            .finish_non_exhaustive()
+    }
+}
+
+impl RouteTable {
+    /// Create an empty table
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer changes below `root` that belong to no mounted module to
+    /// `discover`, mounting the module it returns
+    #[must_use]
+    pub fn with_discovery(
+        mut self,
+        root: impl Into<PathBuf>,
+        discover: impl Fn(&Path) -> Option<ModuleRoutes> + Send + Sync + 'static,
+    ) -> Self {
+        self.discovery = Some((root.into(), Arc::new(discover)));
+        self
+    }
+
+    /// Mount `module`, replacing a mounted module of the same name
+    ///
+    /// The build command is not run; the module is expected to be built.
+    ///
+    /// # Errors
+    ///
+    /// Returns the factory's error; the table is left unchanged.
+    pub fn mount(&self, module: ModuleRoutes) -> anyhow::Result<()> {
+        let router = (module.factory)()?;
+        self.swap(module, router);
+        Ok(())
+    }
+
+    /// Unmount the module named `name`, returning whether it was mounted
+    #[allow(clippy::must_use_candidate)] // usually called for its effect
+    pub fn unmount(&self, name: &str) -> bool {
+        let mut mounted = self.mounted.write();
+        let before = mounted.len();
+        mounted.retain(|entry| entry.module.name != name);
+        before != mounted.len()
+    }
+
+    /// Names of the mounted modules
+    #[must_use]
+    pub fn modules(&self) -> Vec<String> {
+        self.mounted
+            .read()
+            .iter()
+            .map(|entry| entry.module.name.clone())
+            .collect()
+    }
+
+    /// Rebuild and re-mount the modules affected by changes to `paths`
+    ///
+    /// Returns the names of the modules that were re-mounted. A module that
+    /// fails to build is logged and keeps its current router.
+    pub async fn reload(&self, paths: &[PathBuf]) -> Vec<String> {
+        let mut affected: Vec<ModuleRoutes> = self
+            .mounted
+            .read()
+            .iter()
+            .filter(|entry| entry.module.is_affected_by(paths))
+            .map(|entry| entry.module.clone())
+            .collect();
+        affected.extend(self.discover(paths));
+
+        let mut remounted = Vec::new();
+        for module in affected {
+            match module.build().await {
+                Ok(router) => {
+                    tracing::info!(module = %module.name, "Re-mounted routes");
+                    remounted.push(module.name.clone());
+                    self.swap(module, router);
+                }
+                Err(e) => {
+                    tracing::error!(module = %module.name, error = %e, "Module rebuild failed");
+                }
+            }
+        }
+        remounted
+    }
+
+    /// Router dispatching requests to the mounted modules
+    ///
+    /// Use it as the application's fallback service, so that routes
+    /// declared directly on the application take precedence. Requests no
+    /// module serves get `404 Not Found`.
+    pub fn router(&self) -> Router {
+        let table = self.clone();
+        Router::new().fallback(move |request: Request| {
+            let table = table.clone();
+            async move { table.dispatch(request).await }
+        })
+    }
+
+    /// Report changes to module sources to `hot_reload` and re-mount
+    /// modules on its [`ReloadType::Routes`] events
+    ///
+    /// Sources are those of the modules mounted now, plus the discovery
+    /// root. Watching stops when the returned [`RouteWatcher`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file watcher cannot be created or the hot
+    /// reload agent does not answer the subscription.
+    pub async fn watch(&self, hot_reload: &ActorHandle) -> anyhow::Result<RouteWatcher> {
+        let (subscribe, rx) = HotReloadSubscribe::new();
+        hot_reload.send(subscribe).await;
+        let mut events = rx.await?;
+
+        let runtime = tokio::runtime::Handle::current();
+        let agent = hot_reload.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else { return };
+                let agent = agent.clone();
+                runtime.spawn(async move {
+                    for path in event.paths {
+                        agent.send(FileChanged::new(ReloadType::Routes, path)).await;
+                    }
+                });
+            })?;
+        for path in self.watched_paths() {
+            if path.exists() {
+                watcher.watch(&path, RecursiveMode::Recursive)?;
+            } else {
+                tracing::warn!(path = %path.display(), "Route source does not exist");
+            }
+        }
+
+        let agent = hot_reload.clone();
+        let ticker = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TRIGGER_INTERVAL);
+            loop {
+                interval.tick().await;
+                agent.send(TriggerPendingReloads).await;
+            }
+        });
+
+        let table = self.clone();
+        let listener = tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) if event.reload_type == ReloadType::Routes => {
+                        let paths: Vec<PathBuf> =
+                            event.paths.iter().map(|path| relative(path)).collect();
+                        table.reload(&paths).await;
+                    }
+                    Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Ok(RouteWatcher {
+            _watcher: watcher,
+            tasks: vec![ticker, listener],
+        })
+    }
+
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .mounted
+            .read()
+            .iter()
+            .flat_map(|entry| entry.module.sources.clone())
+            .chain(self.discovery.iter().map(|(root, _)| root.clone()))
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// New modules for changed paths below the discovery root
+    fn discover(&self, paths: &[PathBuf]) -> Vec<ModuleRoutes> {
+        let Some((root, discover)) = &self.discovery else {
+            return Vec::new();
+        };
+        let mounted = self.mounted.read();
+        let mut found: Vec<ModuleRoutes> = Vec::new();
+        for path in paths.iter().filter(|path| path.starts_with(root)) {
+            let known = mounted
+                .iter()
+                .map(|entry| &entry.module)
+                .chain(found.iter())
+                .any(|module| module.is_affected_by(std::slice::from_ref(path)));
+            if !known {
+                found.extend(discover(path));
+            }
+        }
+        drop(mounted);
+        found
+    }
+
+    fn swap(&self, module: ModuleRoutes, router: Router) {
+        let mut mounted = self.mounted.write();
+        mounted.retain(|entry| entry.module.name != module.name);
+        mounted.push(Mounted { module, router });
+        // Longest prefix first, so nested modules win
+        mounted.sort_by_key(|entry| std::cmp::Reverse(entry.module.prefix.len()));
+    }
+
+    async fn dispatch(&self, request: Request<Body>) -> Response {
+        let router = self
+            .mounted
+            .read()
+            .iter()
+            .find(|entry| entry.module.serves(request.uri().path()))
+            .map(|entry| entry.router.clone());
+        match router {
+            Some(router) => match router.oneshot(request).await {
+                Ok(response) => response,
+                Err(infallible) => match infallible {},
+            },
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+}
+
+/// Paths reported by the watcher are absolute; sources are usually given
+/// relative to the working directory
+fn relative(path: &Path) -> PathBuf {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok())
+        .map_or_else(|| path.to_path_buf(), Path::to_path_buf)
+}
+
+/// Keeps a [`RouteTable`] watching its sources
+pub struct RouteWatcher {
+    _watcher: RecommendedWatcher,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for RouteWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouteWatcher").finish_non_exhaustive()
+    }
+}
+
+impl Drop for RouteWatcher {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn body(table: &RouteTable, uri: &str) -> (StatusCode, String) {
+        let response = table
+            .router()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    fn counting_module(builds: Arc<AtomicUsize>) -> ModuleRoutes {
+        ModuleRoutes::new("posts", "/posts", move || {
+            let build = builds.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(Router::new().route(
+                "/posts/{id}",
+                get(move || async move { format!("build {build}") }),
+            ))
+        })
+        .with_sources(["src/handlers/posts.rs", "templates/posts"])
+    }
+
+    #[tokio::test]
+    async fn test_mount_dispatch_and_unmount() {
+        let table = RouteTable::new();
+        table
+            .mount(counting_module(Arc::new(AtomicUsize::new(0))))
+            .unwrap();
+        assert_eq!(table.modules(), vec!["posts"]);
+
+        assert_eq!(
+            body(&table, "/posts/1").await,
+            (StatusCode::OK, "build 1".to_string())
+        );
+        assert_eq!(body(&table, "/postsx").await.0, StatusCode::NOT_FOUND);
+
+        assert!(table.unmount("posts"));
+        assert!(!table.unmount("posts"));
+        assert_eq!(body(&table, "/posts/1").await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_reload_rebuilds_only_affected_modules() {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let table = RouteTable::new();
+        table.mount(counting_module(builds.clone())).unwrap();
+
+        let unrelated = table
+            .reload(&[PathBuf::from("src/handlers/users.rs")])
+            .await;
+        assert!(unrelated.is_empty());
+        assert_eq!(builds.load(Ordering::SeqCst), 1);
+
+        let remounted = table
+            .reload(&[PathBuf::from("templates/posts/list.html")])
+            .await;
+        assert_eq!(remounted, vec!["posts"]);
+        assert_eq!(body(&table, "/posts/1").await.1, "build 2");
+    }
+
+    #[tokio::test]
+    async fn test_failed_rebuild_keeps_previous_router() {
+        let table = RouteTable::new();
+        table
+            .mount(
+                counting_module(Arc::new(AtomicUsize::new(0)))
+                    .with_build_command("false", Vec::<String>::new()),
+            )
+            .unwrap();
+
+        let remounted = table
+            .reload(&[PathBuf::from("src/handlers/posts.rs")])
+            .await;
+        assert!(remounted.is_empty());
+        assert_eq!(body(&table, "/posts/1").await.1, "build 1");
+    }
+
+    #[tokio::test]
+    async fn test_discovery_mounts_new_modules() {
+        let table = RouteTable::new().with_discovery("src/handlers", |path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            let prefix = format!("/{name}");
+            let route = format!("{prefix}/{{id}}");
+            Some(
+                ModuleRoutes::new(name, prefix, move || {
+                    Ok(Router::new().route(&route, get(|| async { "discovered" })))
+                })
+                .with_sources([path.to_path_buf()]),
+            )
+        });
+
+        let remounted = table
+            .reload(&[PathBuf::from("src/handlers/comments.rs")])
+            .await;
+        assert_eq!(remounted, vec!["comments"]);
+        assert_eq!(body(&table, "/comments/7").await.1, "discovered");
+
+        // Outside the discovery root
+        assert!(table.reload(&[PathBuf::from("README.md")]).await.is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+//! HTML sanitization for rich-text input
+//!
+//! Rich-text editors (Trix, TipTap, Quill) submit HTML. This module cleans
+//! that HTML against an allowlist before it is validated and stored, so
+//! handlers never hand-roll XSS filtering.
+//!
+//! Policies are built with [`SanitizePolicy`]; two profiles cover the common
+//! cases:
+//!
+//! - [`SanitizeProfile::Comment`]: inline formatting, links and lists; links
+//!   are marked `nofollow ugc`
+//! - [`SanitizeProfile::RichText`]: CMS bodies with headings, tables, images
+//!   and figures
+//!
+//! Forms implement [`Sanitize`] to pick a profile per field and are extracted
+//! with [`SanitizedForm`](crate::htmx::extractors::SanitizedForm), which
+//! sanitizes before running `validator` rules.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::extractors::SanitizedForm;
+//! use acton_htmx::sanitize::{Sanitize, SanitizeProfile};
+//!
+//! #[derive(Deserialize, Validate)]
+//! struct ArticleForm {
+//!     #[validate(length(min = 1, max = 200))]
+//!     title: String,
+//!     #[validate(length(min = 1))]
+//!     body: String,
+//!     summary: String,
+//! }
+//!
+//! impl Sanitize for ArticleForm {
+//!     fn sanitize(&mut self) {
+//!         SanitizeProfile::RichText.apply(&mut self.body);
+//!         SanitizeProfile::Comment.apply(&mut self.summary);
+//!     }
+//! }
+//!
+//! async fn create(SanitizedForm(form): SanitizedForm<ArticleForm>) -> impl IntoResponse {
+//!     // form.body only contains allowlisted markup
+//! }
+//! ```
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Built-in sanitization profiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanitizeProfile {
+    /// User comments: inline formatting, links, lists, quotes and code
+    Comment,
+    /// CMS bodies: comment markup plus headings, tables, images and figures
+    RichText,
+    /// Strip every tag, keeping only text
+    PlainText,
+}
+
+impl SanitizeProfile {
+    /// Shared policy for this profile
+    #[must_use]
+    pub fn policy(self) -> &'static SanitizePolicy {
+        static COMMENT: OnceLock<SanitizePolicy> = OnceLock::new();
+        static RICH_TEXT: OnceLock<SanitizePolicy> = OnceLock::new();
+        static PLAIN_TEXT: OnceLock<SanitizePolicy> = OnceLock::new();
+
+        match self {
+            Self::Comment => COMMENT.get_or_init(SanitizePolicy::comment),
+            Self::RichText => RICH_TEXT.get_or_init(SanitizePolicy::rich_text),
+            Self::PlainText => PLAIN_TEXT.get_or_init(SanitizePolicy::plain_text),
+        }
+    }
+
+    /// Sanitize HTML with this profile
+    #[must_use]
+    pub fn clean(self, html: &str) -> String {
+        self.policy().clean(html)
+    }
+
+    /// Sanitize a form field in place
+    pub fn apply(self, field: &mut String) {
+        *field = self.clean(field);
+    }
+}
+
+/// Tags allowed in comments
+const COMMENT_TAGS: &[&str] = &[
+    "a",
+    "b",
+    "blockquote",
+    "br",
+    "code",
+    "del",
+    "em",
+    "i",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "s",
+    "strong",
+    "u",
+    "ul",
+];
+
+/// Additional tags allowed in rich-text bodies
+const RICH_TEXT_TAGS: &[&str] = &[
+    "caption",
+    "figcaption",
+    "figure",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "img",
+    "mark",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+];
+
+/// HTML allowlist policy
+///
+/// Wraps an `ammonia` builder. Start from a profile constructor and adjust:
+///
+/// ```rust,ignore
+/// let policy = SanitizePolicy::rich_text()
+///     .allow_tags(&["video"])
+///     .allow_attributes("video", &["src", "controls"]);
+/// ```
+pub struct SanitizePolicy {
+    builder: ammonia::Builder<'static>,
+}
+
+impl std::fmt::Debug for SanitizePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SanitizePolicy").finish_non_exhaustive()
+    }
+}
+
+impl SanitizePolicy {
+    /// Policy for user comments
+    #[must_use]
+    pub fn comment() -> Self {
+        let mut builder = ammonia::Builder::empty();
+        builder
+            .tags(COMMENT_TAGS.iter().copied().collect())
+            .add_tag_attributes("a", &["href", "title"])
+            .url_schemes(HashSet::from(["http", "https", "mailto"]))
+            .link_rel(Some("nofollow noopener noreferrer ugc"));
+        Self { builder }
+    }
+
+    /// Policy for CMS bodies written by trusted editors
+    #[must_use]
+    pub fn rich_text() -> Self {
+        let mut builder = ammonia::Builder::empty();
+        builder
+            .tags(COMMENT_TAGS.iter().chain(RICH_TEXT_TAGS).copied().collect())
+            .add_tag_attributes("a", &["href", "title"])
+            .add_tag_attributes("img", &["src", "alt", "title", "width", "height"])
+            .add_tag_attributes("td", &["colspan", "rowspan"])
+            .add_tag_attributes("th", &["colspan", "rowspan", "scope"])
+            .url_schemes(HashSet::from(["http", "https", "mailto"]))
+            .link_rel(Some("noopener noreferrer"));
+        Self { builder }
+    }
+
+    /// Policy that removes all markup
+    #[must_use]
+    pub fn plain_text() -> Self {
+        let mut builder = ammonia::Builder::empty();
+        builder.tags(HashSet::new());
+        Self { builder }
+    }
+
+    /// Allow additional tags
+    #[must_use]
+    pub fn allow_tags(mut self, tags: &[&'static str]) -> Self {
+        self.builder.add_tags(tags.iter().copied());
+        self
+    }
+
+    /// Allow additional attributes on a tag
+    #[must_use]
+    pub fn allow_attributes(mut self, tag: &'static str, attributes: &[&'static str]) -> Self {
+        self.builder
+            .add_tag_attributes(tag, attributes.iter().copied());
+        self
+    }
+
+    /// Allow additional URL schemes in links and images
+    #[must_use]
+    pub fn allow_url_schemes(mut self, schemes: &[&'static str]) -> Self {
+        self.builder.add_url_schemes(schemes.iter().copied());
+        self
+    }
+
+    /// Set the `rel` attribute added to links (`None` to leave links untouched)
+    #[must_use]
+    pub fn link_rel(mut self, rel: Option<&'static str>) -> Self {
+        self.builder.link_rel(rel);
+        self
+    }
+
+    /// Sanitize HTML
+    #[must_use]
+    pub fn clean(&self, html: &str) -> String {
+        self.builder.clean(html).to_string()
+    }
+}
+
+/// Form types that sanitize their rich-text fields
+///
+/// Called by [`SanitizedForm`](crate::htmx::extractors::SanitizedForm) after
+/// deserialization and before validation.
+pub trait Sanitize {
+    /// Sanitize fields in place
+    fn sanitize(&mut self);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTACK: &str = r#"<p onclick="x()">Hi <script>alert(1)</script><a href="javascript:alert(1)">bad</a> <a href="https://example.com">ok</a></p>"#;
+
+    #[test]
+    fn test_comment_profile_strips_scripts_and_marks_links() {
+        let clean = SanitizeProfile::Comment.clean(ATTACK);
+        assert!(!clean.contains("script"));
+        assert!(!clean.contains("onclick"));
+        assert!(!clean.contains("javascript:"));
+        assert!(clean.contains(r#"rel="nofollow noopener noreferrer ugc""#));
+    }
+
+    #[test]
+    fn test_profiles_differ_on_structure() {
+        let html = r#"<h2>Heading</h2><img src="https://cdn.example.com/a.png" alt="a">"#;
+        let comment = SanitizeProfile::Comment.clean(html);
+        assert!(!comment.contains("<h2>"));
+        assert!(!comment.contains("<img"));
+
+        let rich = SanitizeProfile::RichText.clean(html);
+        assert!(rich.contains("<h2>Heading</h2>"));
+        assert!(rich.contains(r#"<img src="https://cdn.example.com/a.png" alt="a">"#));
+
+        assert_eq!(SanitizeProfile::PlainText.clean(html), "Heading");
+    }
+
+    #[test]
+    fn test_custom_policy_extends_profile() {
+        let policy = SanitizePolicy::comment()
+            .allow_tags(&["span"])
+            .allow_attributes("span", &["lang"]);
+        assert_eq!(
+            policy.clean(r#"<span lang="fr" style="color:red">bonjour</span>"#),
+            r#"<span lang="fr">bonjour</span>"#
+        );
+    }
+
+    #[test]
+    fn test_apply_in_place() {
+        let mut field = "<b>bold</b><iframe src=x></iframe>".to_string();
+        SanitizeProfile::Comment.apply(&mut field);
+        assert_eq!(field, "<b>bold</b>");
+    }
+}
@@ -0,0 +1,141 @@
+//! SCIM filter expressions
+//!
+//! Identity providers only look up resources by a single equality before
+//! creating them (`userName eq "ada@example.com"`), so that is all this
+//! supports. Anything else is rejected with `invalidFilter` rather than
+//! silently matching everything.
+
+use super::{ScimError, ScimGroup, ScimUser};
+
+/// An `<attribute> eq "<value>"` filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// `userName eq "..."` (case-insensitive)
+    UserName(String),
+    /// `externalId eq "..."`
+    ExternalId(String),
+    /// `emails eq "..."` or `emails.value eq "..."` (case-insensitive)
+    Email(String),
+    /// `displayName eq "..."` (case-insensitive)
+    DisplayName(String),
+}
+
+impl Filter {
+    /// Parse a `filter` query parameter
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::InvalidFilter`] for anything but a single
+    /// equality on a supported attribute.
+    pub fn parse(filter: &str) -> Result<Self, ScimError> {
+        let (attribute, value) = equality(filter)?;
+        match attribute.to_ascii_lowercase().as_str() {
+            "username" => Ok(Self::UserName(value)),
+            "externalid" => Ok(Self::ExternalId(value)),
+            "emails" | "emails.value" => Ok(Self::Email(value)),
+            "displayname" => Ok(Self::DisplayName(value)),
+            _ => Err(ScimError::InvalidFilter(format!(
+                "unsupported attribute {attribute}"
+            ))),
+        }
+    }
+
+    /// Whether the filter applies to users
+    #[must_use]
+    pub const fn is_user_filter(&self) -> bool {
+        matches!(
+            self,
+            Self::UserName(_) | Self::ExternalId(_) | Self::Email(_)
+        )
+    }
+
+    /// Whether the filter applies to groups
+    #[must_use]
+    pub const fn is_group_filter(&self) -> bool {
+        matches!(self, Self::ExternalId(_) | Self::DisplayName(_))
+    }
+
+    /// Whether a user matches
+    #[must_use]
+    pub fn matches_user(&self, user: &ScimUser) -> bool {
+        match self {
+            Self::UserName(value) => user.user_name.eq_ignore_ascii_case(value),
+            Self::ExternalId(value) => user.external_id.as_deref() == Some(value.as_str()),
+            Self::Email(value) => user.email.eq_ignore_ascii_case(value),
+            Self::DisplayName(_) => false,
+        }
+    }
+
+    /// Whether a group matches
+    #[must_use]
+    pub fn matches_group(&self, group: &ScimGroup) -> bool {
+        match self {
+            Self::ExternalId(value) => group.external_id.as_deref() == Some(value.as_str()),
+            Self::DisplayName(value) => group.display_name.eq_ignore_ascii_case(value),
+            Self::UserName(_) | Self::Email(_) => false,
+        }
+    }
+}
+
+/// Split `<attribute> eq "<value>"` into its attribute and unquoted value
+pub(super) fn equality(expression: &str) -> Result<(String, String), ScimError> {
+    let invalid = || ScimError::InvalidFilter(expression.to_string());
+
+    let expression = expression.trim();
+    let (attribute, rest) = expression
+        .split_once(char::is_whitespace)
+        .ok_or_else(invalid)?;
+    let (operator, value) = rest
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .ok_or_else(invalid)?;
+    if !operator.eq_ignore_ascii_case("eq") {
+        return Err(ScimError::InvalidFilter(format!(
+            "unsupported operator {operator}"
+        )));
+    }
+    // Values are JSON strings, so escapes such as \" decode the same way
+    let value: String = serde_json::from_str(value.trim()).map_err(|_| invalid())?;
+    Ok((attribute.to_string(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filters() {
+        assert_eq!(
+            Filter::parse(r#"userName eq "ada@example.com""#).unwrap(),
+            Filter::UserName("ada@example.com".to_string())
+        );
+        assert_eq!(
+            Filter::parse(r#"  externalId EQ "00u1\"x"  "#).unwrap(),
+            Filter::ExternalId("00u1\"x".to_string())
+        );
+        assert_eq!(
+            Filter::parse(r#"emails.value eq "a@b.c""#).unwrap(),
+            Filter::Email("a@b.c".to_string())
+        );
+        assert_eq!(
+            Filter::parse(r#"displayName eq "Admins""#).unwrap(),
+            Filter::DisplayName("Admins".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsupported_filters() {
+        for filter in [
+            r#"userName sw "ada""#,
+            r#"title eq "CEO""#,
+            r#"userName eq "a" and active eq true"#,
+            "userName eq ada",
+            "userName",
+        ] {
+            assert!(
+                matches!(Filter::parse(filter), Err(ScimError::InvalidFilter(_))),
+                "{filter}"
+            );
+        }
+    }
+}
@@ -0,0 +1,1432 @@
+//! SCIM 2.0 user provisioning
+//!
+//! Lets an enterprise identity provider (Okta, Entra ID, OneLogin, ...)
+//! create, update and deactivate application users and push its groups:
+//!
+//! - [`Scim::routes`] serves `/scim/v2/Users`, `/scim/v2/Groups`,
+//!   `/scim/v2/ServiceProviderConfig` and `/scim/v2/ResourceTypes`
+//! - Every request must carry one of the configured bearer tokens
+//! - [`ScimConfig::mapping`] picks the attribute used as login email, the
+//!   roles new users get, and the roles granted by group membership
+//! - Deactivating a user (`active: false`) keeps the user's row but stops
+//!   them signing in: password, OAuth2 and passkey logins refuse inactive
+//!   users, and so do the [`Authenticated`](crate::htmx::auth::Authenticated)
+//!   extractors
+//! - Deactivating or deleting a user signs out their sessions when the
+//!   endpoints have the session manager ([`Scim::with_session_manager`])
+//!
+//! Users and groups live in a [`ScimStore`]: in-memory, or the `users` table
+//! with `postgres` (apply `migrations/005_add_scim_provisioning.sql`).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::scim::{PgScimStore, Scim};
+//!
+//! let scim = Scim::new(Arc::new(PgScimStore::new(pool.clone())), config.scim.clone())
+//!     .with_base_url("https://app.example.com")
+//!     .with_session_manager(state.session_manager());
+//!
+//! let app = Router::new().merge(scim.routes());
+//! ```
+//!
+//! Point the identity provider at `https://app.example.com/scim/v2` with one
+//! of the tokens from `[scim] tokens`.
+
+mod filter;
+mod patch;
+#[cfg(feature = "postgres")]
+mod postgres;
+
+pub use crate::htmx::config::{ScimAttributeMapping, ScimConfig, ScimEmailSource};
+pub use filter::Filter;
+pub use patch::{PatchOperation, PatchRequest};
+#[cfg(feature = "postgres")]
+pub use postgres::PgScimStore;
+
+use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value as Json};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::htmx::agents::DeleteUserSessions;
+use crate::htmx::auth::user::EmailAddress;
+
+/// Core user schema
+pub const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+/// Core group schema
+pub const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+const LIST_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+const CONTENT_TYPE: &str = "application/scim+json";
+
+/// SCIM errors
+#[derive(Debug, thiserror::Error)]
+pub enum ScimError {
+    /// Bearer token missing or not configured
+    #[error("Invalid or missing bearer token")]
+    Unauthorized,
+
+    /// Unknown user or group
+    #[error("{0} not found")]
+    NotFound(String),
+
+    /// `userName`, email or group `displayName` already taken
+    #[error("{0}")]
+    Uniqueness(String),
+
+    /// Unsupported or malformed `filter`
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+
+    /// Malformed resource or attribute value
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+
+    /// Unsupported patch path
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+
+    /// Backing store failed
+    #[error("SCIM store error: {0}")]
+    Store(String),
+}
+
+impl ScimError {
+    /// `scimType` of the error response
+    const fn scim_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Uniqueness(_) => Some("uniqueness"),
+            Self::InvalidFilter(_) => Some("invalidFilter"),
+            Self::InvalidValue(_) => Some("invalidValue"),
+            Self::InvalidPath(_) => Some("invalidPath"),
+            Self::Unauthorized | Self::NotFound(_) | Self::Store(_) => None,
+        }
+    }
+}
+
+impl IntoResponse for ScimError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Uniqueness(_) => StatusCode::CONFLICT,
+            Self::InvalidFilter(_) | Self::InvalidValue(_) | Self::InvalidPath(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            Self::Store(_) => {
+                warn!(error = %self, "SCIM request failed");
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+        };
+        let detail = if matches!(self, Self::Store(_)) {
+            "Service unavailable".to_string()
+        } else {
+            self.to_string()
+        };
+
+        let mut body = json!({
+            "schemas": [ERROR_SCHEMA],
+            "status": status.as_u16().to_string(),
+            "detail": detail,
+        });
+        if let Some(scim_type) = self.scim_type() {
+            body["scimType"] = json!(scim_type);
+        }
+        let mut response = scim_json(status, &body);
+        if status == StatusCode::UNAUTHORIZED {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        response
+    }
+}
+
+/// A provisioned application user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScimUser {
+    /// Application user ID
+    pub id: i64,
+    /// Identity provider's ID for the user
+    pub external_id: Option<String>,
+    /// SCIM `userName`, unique ignoring case
+    pub user_name: String,
+    /// Login email, normalized to lowercase
+    pub email: String,
+    /// `name.givenName`
+    pub given_name: Option<String>,
+    /// `name.familyName`
+    pub family_name: Option<String>,
+    /// `displayName`
+    pub display_name: Option<String>,
+    /// `false` once the identity provider deactivates the user
+    pub active: bool,
+    /// Application roles
+    pub roles: Vec<String>,
+    /// When the user was created
+    pub created_at: DateTime<Utc>,
+    /// When the user last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A group pushed by the identity provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScimGroup {
+    /// Group ID
+    pub id: String,
+    /// Identity provider's ID for the group
+    pub external_id: Option<String>,
+    /// `displayName`, unique ignoring case
+    pub display_name: String,
+    /// Member user IDs
+    pub members: Vec<i64>,
+    /// When the group was created
+    pub created_at: DateTime<Utc>,
+    /// When the group last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Storage for provisioned users and groups
+#[async_trait]
+pub trait ScimStore: Send + Sync {
+    /// Look up a user
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::Store`] if the store cannot be read.
+    async fn user(&self, id: i64) -> Result<Option<ScimUser>, ScimError>;
+
+    /// A page of users matching `filter`, ordered by ID, and the total match count
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::Store`] if the store cannot be read.
+    async fn users(
+        &self,
+        filter: Option<&Filter>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<ScimUser>, usize), ScimError>;
+
+    /// Create a user, returning it with its assigned ID and timestamps
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::Uniqueness`] if the `userName` or email is taken,
+    /// or [`ScimError::Store`] if the store cannot be written.
+    async fn create_user(&self, user: &ScimUser) -> Result<ScimUser, ScimError>;
+
+    /// Replace a user's attributes and roles
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::NotFound`] for an unknown user,
+    /// [`ScimError::Uniqueness`] if the `userName` or email is taken, or
+    /// [`ScimError::Store`] if the store cannot be written.
+    async fn update_user(&self, user: &ScimUser) -> Result<(), ScimError>;
+
+    /// Delete a user and their group memberships, returning `false` if unknown
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::Store`] if the store cannot be written.
+    async fn delete_user(&self, id: i64) -> Result<bool, ScimError>;
+
+    /// Look up a group
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::Store`] if the store cannot be read.
+    async fn group(&self, id: &str) -> Result<Option<ScimGroup>, ScimError>;
+
+    /// A page of groups matching `filter`, ordered by name, and the total match count
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::Store`] if the store cannot be read.
+    async fn groups(
+        &self,
+        filter: Option<&Filter>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<ScimGroup>, usize), ScimError>;
+
+    /// Groups a user belongs to
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::Store`] if the store cannot be read.
+    async fn groups_of(&self, user_id: i64) -> Result<Vec<ScimGroup>, ScimError>;
+
+    /// Store a group and its members, replacing any group with the same ID
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::Uniqueness`] if another group has the same
+    /// `displayName`, or [`ScimError::Store`] if the store cannot be written.
+    async fn put_group(&self, group: &ScimGroup) -> Result<(), ScimError>;
+
+    /// Delete a group, returning `false` if unknown
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScimError::Store`] if the store cannot be written.
+    async fn delete_group(&self, id: &str) -> Result<bool, ScimError>;
+}
+
+/// In-process SCIM store
+#[derive(Debug, Default)]
+pub struct MemoryScimStore {
+    users: Mutex<BTreeMap<i64, ScimUser>>,
+    groups: Mutex<BTreeMap<String, ScimGroup>>,
+}
+
+impl MemoryScimStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn ensure_unique_user(users: &BTreeMap<i64, ScimUser>, user: &ScimUser) -> Result<(), ScimError> {
+    let taken = users.values().any(|other| {
+        other.id != user.id
+            && (other.user_name.eq_ignore_ascii_case(&user.user_name)
+                || other.email.eq_ignore_ascii_case(&user.email))
+    });
+    if taken {
+        return Err(ScimError::Uniqueness(format!(
+            "User {} already exists",
+            user.user_name
+        )));
+    }
+    Ok(())
+}
+
+fn page<T: Clone>(matches: Vec<&T>, offset: usize, limit: usize) -> (Vec<T>, usize) {
+    let total = matches.len();
+    let page = matches
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .cloned()
+        .collect();
+    (page, total)
+}
+
+#[async_trait]
+impl ScimStore for MemoryScimStore {
+    async fn user(&self, id: i64) -> Result<Option<ScimUser>, ScimError> {
+        Ok(self.users.lock().get(&id).cloned())
+    }
+
+    async fn users(
+        &self,
+        filter: Option<&Filter>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<ScimUser>, usize), ScimError> {
+        let users = self.users.lock();
+        let page = page(
+            users
+                .values()
+                .filter(|user| filter.is_none_or(|filter| filter.matches_user(user)))
+                .collect(),
+            offset,
+            limit,
+        );
+        drop(users);
+        Ok(page)
+    }
+
+    async fn create_user(&self, user: &ScimUser) -> Result<ScimUser, ScimError> {
+        let mut users = self.users.lock();
+        let id = users.last_key_value().map_or(1, |(id, _)| id + 1);
+        let now = Utc::now();
+        let user = ScimUser {
+            id,
+            created_at: now,
+            updated_at: now,
+            ..user.clone()
+        };
+        ensure_unique_user(&users, &user)?;
+        users.insert(id, user.clone());
+        drop(users);
+        Ok(user)
+    }
+
+    async fn update_user(&self, user: &ScimUser) -> Result<(), ScimError> {
+        let mut users = self.users.lock();
+        if !users.contains_key(&user.id) {
+            return Err(ScimError::NotFound(format!("User {}", user.id)));
+        }
+        ensure_unique_user(&users, user)?;
+        users.insert(user.id, user.clone());
+        drop(users);
+        Ok(())
+    }
+
+    async fn delete_user(&self, id: i64) -> Result<bool, ScimError> {
+        if self.users.lock().remove(&id).is_none() {
+            return Ok(false);
+        }
+        for group in self.groups.lock().values_mut() {
+            group.members.retain(|member| *member != id);
+        }
+        Ok(true)
+    }
+
+    async fn group(&self, id: &str) -> Result<Option<ScimGroup>, ScimError> {
+        Ok(self.groups.lock().get(id).cloned())
+    }
+
+    async fn groups(
+        &self,
+        filter: Option<&Filter>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<ScimGroup>, usize), ScimError> {
+        let groups = self.groups.lock();
+        let mut matches: Vec<_> = groups
+            .values()
+            .filter(|group| filter.is_none_or(|filter| filter.matches_group(group)))
+            .collect();
+        matches.sort_by_key(|group| group.display_name.to_lowercase());
+        let page = page(matches, offset, limit);
+        drop(groups);
+        Ok(page)
+    }
+
+    async fn groups_of(&self, user_id: i64) -> Result<Vec<ScimGroup>, ScimError> {
+        Ok(self
+            .groups
+            .lock()
+            .values()
+            .filter(|group| group.members.contains(&user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn put_group(&self, group: &ScimGroup) -> Result<(), ScimError> {
+        let mut groups = self.groups.lock();
+        let taken = groups.values().any(|other| {
+            other.id != group.id && other.display_name.eq_ignore_ascii_case(&group.display_name)
+        });
+        if taken {
+            return Err(ScimError::Uniqueness(format!(
+                "Group {} already exists",
+                group.display_name
+            )));
+        }
+        groups.insert(group.id.clone(), group.clone());
+        drop(groups);
+        Ok(())
+    }
+
+    async fn delete_group(&self, id: &str) -> Result<bool, ScimError> {
+        Ok(self.groups.lock().remove(id).is_some())
+    }
+}
+
+/// SCIM provisioning endpoints
+#[derive(Clone)]
+pub struct Scim {
+    store: Arc<dyn ScimStore>,
+    config: Arc<ScimConfig>,
+    base_url: Arc<str>,
+    sessions: Option<ActorHandle>,
+}
+
+impl std::fmt::Debug for Scim {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scim")
+            .field("config", &self.config)
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Scim {
+    /// Create the endpoints
+    #[must_use]
+    pub fn new(store: Arc<dyn ScimStore>, config: ScimConfig) -> Self {
+        if config.tokens.iter().all(String::is_empty) {
+            warn!("No SCIM bearer tokens configured; all SCIM requests will be rejected");
+        }
+        Self {
+            store,
+            config: Arc::new(config),
+            base_url: "".into(),
+            sessions: None,
+        }
+    }
+
+    /// Sign users out of every session when they are deactivated or deleted
+    ///
+    /// Without it, existing sessions stay valid until they expire, though
+    /// the [`Authenticated`](crate::htmx::auth::Authenticated) extractors
+    /// still turn deactivated users away.
+    #[must_use]
+    pub fn with_session_manager(mut self, sessions: ActorHandle) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Set the public origin used for `meta.location`, e.g. `https://app.example.com`
+    ///
+    /// Without it, locations are relative paths.
+    #[must_use]
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').into();
+        self
+    }
+
+    /// SCIM routes, all behind bearer-token authentication
+    ///
+    /// - `GET /scim/v2/ServiceProviderConfig` and `GET /scim/v2/ResourceTypes`
+    /// - `GET`/`POST /scim/v2/Users` lists (with `filter`, `startIndex`,
+    ///   `count`) and creates users
+    /// - `GET`/`PUT`/`PATCH`/`DELETE /scim/v2/Users/{id}`
+    /// - The same for `/scim/v2/Groups`
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route(
+                "/scim/v2/ServiceProviderConfig",
+                get(service_provider_config),
+            )
+            .route("/scim/v2/ResourceTypes", get(resource_types))
+            .route("/scim/v2/Users", get(list_users).post(create_user))
+            .route(
+                "/scim/v2/Users/{id}",
+                get(get_user)
+                    .put(replace_user)
+                    .patch(patch_user)
+                    .delete(delete_user),
+            )
+            .route("/scim/v2/Groups", get(list_groups).post(create_group))
+            .route(
+                "/scim/v2/Groups/{id}",
+                get(get_group)
+                    .put(replace_group)
+                    .patch(patch_group)
+                    .delete(delete_group),
+            )
+            .route_layer(from_fn_with_state(self.clone(), authenticate))
+            .with_state(self.clone())
+    }
+
+    /// Whether `token` is one of the configured bearer tokens
+    fn accepts(&self, token: &str) -> bool {
+        self.config
+            .tokens
+            .iter()
+            .filter(|expected| !expected.is_empty())
+            .any(|expected| token_matches(expected, token))
+    }
+
+    fn location(&self, resource: &str, id: &str) -> String {
+        format!("{}/scim/v2/{resource}/{id}", self.base_url)
+    }
+
+    /// Zero-based offset and page size for a list request
+    fn page(&self, query: &ListQuery) -> (usize, usize) {
+        let start_index = query.start_index.unwrap_or(1).max(1);
+        let count = query
+            .count
+            .unwrap_or(self.config.max_results)
+            .min(self.config.max_results);
+        (start_index - 1, count)
+    }
+
+    fn email(&self, resource: &UserResource) -> Result<String, ScimError> {
+        let email = match self.config.mapping.email {
+            ScimEmailSource::UserName => resource.user_name.as_str(),
+            ScimEmailSource::PrimaryEmail => resource
+                .emails
+                .iter()
+                .find(|email| email.primary)
+                .or_else(|| resource.emails.first())
+                .map_or(resource.user_name.as_str(), |email| email.value.as_str()),
+        };
+        user_email(email)
+    }
+
+    fn apply_user_resource(
+        &self,
+        user: &mut ScimUser,
+        resource: UserResource,
+    ) -> Result<(), ScimError> {
+        user.email = self.email(&resource)?;
+        user.user_name = resource.user_name;
+        user.external_id = resource.external_id;
+        user.display_name = resource.display_name;
+        let name = resource.name.unwrap_or_default();
+        user.given_name = name.given_name;
+        user.family_name = name.family_name;
+        user.active = resource.active.unwrap_or(true);
+        Ok(())
+    }
+
+    fn user_json(&self, user: &ScimUser) -> Json {
+        let id = user.id.to_string();
+        let mut resource = json!({
+            "schemas": [USER_SCHEMA],
+            "id": id,
+            "userName": user.user_name,
+            "active": user.active,
+            "emails": [{"value": user.email, "type": "work", "primary": true}],
+            "roles": user.roles.iter().map(|role| json!({"value": role})).collect::<Vec<_>>(),
+            "meta": meta("User", user.created_at, user.updated_at, &self.location("Users", &id)),
+        });
+        if let Some(external_id) = &user.external_id {
+            resource["externalId"] = json!(external_id);
+        }
+        if let Some(display_name) = &user.display_name {
+            resource["displayName"] = json!(display_name);
+        }
+        if user.given_name.is_some() || user.family_name.is_some() {
+            resource["name"] = json!({
+                "givenName": user.given_name,
+                "familyName": user.family_name,
+            });
+        }
+        resource
+    }
+
+    fn group_json(&self, group: &ScimGroup) -> Json {
+        let members: Vec<_> = group
+            .members
+            .iter()
+            .map(|id| {
+                let id = id.to_string();
+                json!({"value": id, "$ref": self.location("Users", &id)})
+            })
+            .collect();
+        let mut resource = json!({
+            "schemas": [GROUP_SCHEMA],
+            "id": group.id,
+            "displayName": group.display_name,
+            "members": members,
+            "meta": meta("Group", group.created_at, group.updated_at, &self.location("Groups", &group.id)),
+        });
+        if let Some(external_id) = &group.external_id {
+            resource["externalId"] = json!(external_id);
+        }
+        resource
+    }
+
+    /// Delete every session signed in as the user
+    async fn sign_out(&self, user_id: i64) {
+        if let Some(sessions) = &self.sessions {
+            sessions.send(DeleteUserSessions { user_id }).await;
+            info!(user_id, "Signed out sessions of SCIM deprovisioned user");
+        }
+    }
+
+    async fn load_user(&self, id: &str) -> Result<ScimUser, ScimError> {
+        let not_found = || ScimError::NotFound(format!("User {id}"));
+        let id = id.parse().map_err(|_| not_found())?;
+        self.store.user(id).await?.ok_or_else(not_found)
+    }
+
+    async fn load_group(&self, id: &str) -> Result<ScimGroup, ScimError> {
+        self.store
+            .group(id)
+            .await?
+            .ok_or_else(|| ScimError::NotFound(format!("Group {id}")))
+    }
+
+    /// Reject memberships of unknown users
+    async fn check_members(&self, members: &[i64]) -> Result<(), ScimError> {
+        for id in members {
+            if self.store.user(*id).await?.is_none() {
+                return Err(ScimError::InvalidValue(format!("unknown member {id}")));
+            }
+        }
+        Ok(())
+    }
+
+    /// Role mapped to a group name
+    fn group_role(&self, display_name: &str) -> Option<&str> {
+        self.config
+            .mapping
+            .group_roles
+            .iter()
+            .find(|(group, _)| group.eq_ignore_ascii_case(display_name))
+            .map(|(_, role)| role.as_str())
+    }
+
+    /// Recompute the group-managed roles of users whose memberships changed
+    async fn sync_roles(&self, user_ids: BTreeSet<i64>) -> Result<(), ScimError> {
+        let group_roles = &self.config.mapping.group_roles;
+        if group_roles.is_empty() {
+            return Ok(());
+        }
+        let managed: BTreeSet<&str> = group_roles.values().map(String::as_str).collect();
+
+        for id in user_ids {
+            let Some(mut user) = self.store.user(id).await? else {
+                continue;
+            };
+            let granted: BTreeSet<&str> = self
+                .store
+                .groups_of(id)
+                .await?
+                .iter()
+                .filter_map(|group| self.group_role(&group.display_name))
+                .collect();
+            let mut roles: Vec<String> = user
+                .roles
+                .iter()
+                .filter(|role| !managed.contains(role.as_str()))
+                .cloned()
+                .collect();
+            roles.extend(granted.into_iter().map(str::to_string));
+
+            if roles != user.roles {
+                info!(user_id = id, roles = ?roles, "SCIM group roles changed");
+                user.roles = roles;
+                user.updated_at = Utc::now();
+                self.store.update_user(&user).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate and store a group, then resync its old and new members' roles
+    async fn save_group(
+        &self,
+        group: &ScimGroup,
+        previous_members: &[i64],
+    ) -> Result<(), ScimError> {
+        self.check_members(&group.members).await?;
+        self.store.put_group(group).await?;
+        let affected = previous_members
+            .iter()
+            .chain(&group.members)
+            .copied()
+            .collect();
+        self.sync_roles(affected).await
+    }
+}
+
+/// Login email from a SCIM value
+fn user_email(value: &str) -> Result<String, ScimError> {
+    EmailAddress::parse(value)
+        .map(|email| email.as_str().to_string())
+        .map_err(|_| ScimError::InvalidValue(format!("{value} is not an email address")))
+}
+
+/// Compare digests so timing reveals neither the token's length nor a matching prefix
+fn token_matches(expected: &str, provided: &str) -> bool {
+    let expected = Sha256::digest(expected.as_bytes());
+    let provided = Sha256::digest(provided.as_bytes());
+    expected
+        .iter()
+        .zip(provided.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+async fn authenticate(State(scim): State<Scim>, request: Request, next: Next) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if token.is_some_and(|token| scim.accepts(token.trim())) {
+        next.run(request).await
+    } else {
+        warn!("Rejected SCIM request with invalid bearer token");
+        ScimError::Unauthorized.into_response()
+    }
+}
+
+fn meta(
+    resource_type: &str,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+    location: &str,
+) -> Json {
+    json!({
+        "resourceType": resource_type,
+        "created": created.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "lastModified": modified.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "location": location,
+    })
+}
+
+fn scim_json(status: StatusCode, body: &Json) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+fn created(location: &str, body: &Json) -> Response {
+    let mut response = scim_json(StatusCode::CREATED, body);
+    if let Ok(location) = HeaderValue::from_str(location) {
+        response.headers_mut().insert(header::LOCATION, location);
+    }
+    response
+}
+
+fn list_json(resources: &[Json], total: usize, offset: usize) -> Json {
+    json!({
+        "schemas": [LIST_SCHEMA],
+        "totalResults": total,
+        "startIndex": offset + 1,
+        "itemsPerPage": resources.len(),
+        "Resources": resources,
+    })
+}
+
+/// Bodies arrive as `application/scim+json`, which `Json` would reject
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, ScimError> {
+    serde_json::from_slice(body).map_err(|e| ScimError::InvalidValue(e.to_string()))
+}
+
+/// Query of list requests
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListQuery {
+    filter: Option<String>,
+    start_index: Option<usize>,
+    count: Option<usize>,
+}
+
+impl ListQuery {
+    fn filter(&self, accepts: fn(&Filter) -> bool) -> Result<Option<Filter>, ScimError> {
+        let Some(filter) = &self.filter else {
+            return Ok(None);
+        };
+        let parsed = Filter::parse(filter)?;
+        if !accepts(&parsed) {
+            return Err(ScimError::InvalidFilter(format!(
+                "unsupported attribute in {filter}"
+            )));
+        }
+        Ok(Some(parsed))
+    }
+}
+
+/// User resource in create and replace requests
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserResource {
+    user_name: String,
+    external_id: Option<String>,
+    name: Option<NameResource>,
+    display_name: Option<String>,
+    #[serde(default)]
+    emails: Vec<EmailResource>,
+    active: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NameResource {
+    given_name: Option<String>,
+    family_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmailResource {
+    value: String,
+    #[serde(default)]
+    primary: bool,
+}
+
+/// Group resource in create and replace requests
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupResource {
+    display_name: String,
+    external_id: Option<String>,
+    #[serde(default)]
+    members: Vec<MemberResource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MemberResource {
+    value: String,
+}
+
+impl GroupResource {
+    fn members(&self) -> Result<Vec<i64>, ScimError> {
+        let mut members = Vec::new();
+        for member in &self.members {
+            let id = member
+                .value
+                .parse()
+                .map_err(|_| ScimError::InvalidValue(format!("unknown member {}", member.value)))?;
+            if !members.contains(&id) {
+                members.push(id);
+            }
+        }
+        Ok(members)
+    }
+}
+
+async fn service_provider_config(State(scim): State<Scim>) -> Response {
+    scim_json(
+        StatusCode::OK,
+        &json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig"],
+            "patch": {"supported": true},
+            "bulk": {"supported": false, "maxOperations": 0, "maxPayloadSize": 0},
+            "filter": {"supported": true, "maxResults": scim.config.max_results},
+            "changePassword": {"supported": false},
+            "sort": {"supported": false},
+            "etag": {"supported": false},
+            "authenticationSchemes": [{
+                "type": "oauthbearertoken",
+                "name": "OAuth Bearer Token",
+                "description": "Authentication with a bearer token configured in the application",
+                "primary": true,
+            }],
+        }),
+    )
+}
+
+async fn resource_types() -> Response {
+    let resources = vec![
+        json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ResourceType"],
+            "id": "User",
+            "name": "User",
+            "endpoint": "/Users",
+            "schema": USER_SCHEMA,
+        }),
+        json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ResourceType"],
+            "id": "Group",
+            "name": "Group",
+            "endpoint": "/Groups",
+            "schema": GROUP_SCHEMA,
+        }),
+    ];
+    let total = resources.len();
+    scim_json(StatusCode::OK, &list_json(&resources, total, 0))
+}
+
+async fn list_users(
+    State(scim): State<Scim>,
+    Query(query): Query<ListQuery>,
+) -> Result<Response, ScimError> {
+    let filter = query.filter(Filter::is_user_filter)?;
+    let (offset, limit) = scim.page(&query);
+    let (users, total) = scim.store.users(filter.as_ref(), offset, limit).await?;
+    let resources: Vec<_> = users.iter().map(|user| scim.user_json(user)).collect();
+    Ok(scim_json(
+        StatusCode::OK,
+        &list_json(&resources, total, offset),
+    ))
+}
+
+async fn get_user(State(scim): State<Scim>, Path(id): Path<String>) -> Result<Response, ScimError> {
+    let user = scim.load_user(&id).await?;
+    Ok(scim_json(StatusCode::OK, &scim.user_json(&user)))
+}
+
+async fn create_user(State(scim): State<Scim>, body: Bytes) -> Result<Response, ScimError> {
+    let resource: UserResource = parse(&body)?;
+    let now = Utc::now();
+    let mut user = ScimUser {
+        id: 0,
+        external_id: None,
+        user_name: String::new(),
+        email: String::new(),
+        given_name: None,
+        family_name: None,
+        display_name: None,
+        active: true,
+        roles: scim.config.mapping.default_roles.clone(),
+        created_at: now,
+        updated_at: now,
+    };
+    scim.apply_user_resource(&mut user, resource)?;
+    let user = scim.store.create_user(&user).await?;
+
+    info!(user_id = user.id, user_name = %user.user_name, "SCIM user provisioned");
+    Ok(created(
+        &scim.location("Users", &user.id.to_string()),
+        &scim.user_json(&user),
+    ))
+}
+
+async fn replace_user(
+    State(scim): State<Scim>,
+    Path(id): Path<String>,
+    body: Bytes,
+) -> Result<Response, ScimError> {
+    let mut user = scim.load_user(&id).await?;
+    let was_active = user.active;
+    scim.apply_user_resource(&mut user, parse(&body)?)?;
+    save_user(&scim, &mut user, was_active).await
+}
+
+async fn patch_user(
+    State(scim): State<Scim>,
+    Path(id): Path<String>,
+    body: Bytes,
+) -> Result<Response, ScimError> {
+    let mut user = scim.load_user(&id).await?;
+    let was_active = user.active;
+    let request: PatchRequest = parse(&body)?;
+    patch::apply_user(&mut user, &request.operations, scim.config.mapping.email)?;
+    save_user(&scim, &mut user, was_active).await
+}
+
+async fn save_user(
+    scim: &Scim,
+    user: &mut ScimUser,
+    was_active: bool,
+) -> Result<Response, ScimError> {
+    user.updated_at = Utc::now();
+    scim.store.update_user(user).await?;
+    if was_active != user.active {
+        info!(
+            user_id = user.id,
+            active = user.active,
+            "SCIM user activation changed"
+        );
+        if !user.active {
+            scim.sign_out(user.id).await;
+        }
+    }
+    Ok(scim_json(StatusCode::OK, &scim.user_json(user)))
+}
+
+async fn delete_user(
+    State(scim): State<Scim>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ScimError> {
+    let user = scim.load_user(&id).await?;
+    if !scim.store.delete_user(user.id).await? {
+        return Err(ScimError::NotFound(format!("User {id}")));
+    }
+    info!(user_id = user.id, "SCIM user deleted");
+    scim.sign_out(user.id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_groups(
+    State(scim): State<Scim>,
+    Query(query): Query<ListQuery>,
+) -> Result<Response, ScimError> {
+    let filter = query.filter(Filter::is_group_filter)?;
+    let (offset, limit) = scim.page(&query);
+    let (groups, total) = scim.store.groups(filter.as_ref(), offset, limit).await?;
+    let resources: Vec<_> = groups.iter().map(|group| scim.group_json(group)).collect();
+    Ok(scim_json(
+        StatusCode::OK,
+        &list_json(&resources, total, offset),
+    ))
+}
+
+async fn get_group(
+    State(scim): State<Scim>,
+    Path(id): Path<String>,
+) -> Result<Response, ScimError> {
+    let group = scim.load_group(&id).await?;
+    Ok(scim_json(StatusCode::OK, &scim.group_json(&group)))
+}
+
+async fn create_group(State(scim): State<Scim>, body: Bytes) -> Result<Response, ScimError> {
+    let resource: GroupResource = parse(&body)?;
+    let now = Utc::now();
+    let group = ScimGroup {
+        id: uuid::Uuid::new_v4().to_string(),
+        members: resource.members()?,
+        external_id: resource.external_id,
+        display_name: resource.display_name,
+        created_at: now,
+        updated_at: now,
+    };
+    scim.save_group(&group, &[]).await?;
+
+    info!(group_id = %group.id, display_name = %group.display_name, "SCIM group provisioned");
+    Ok(created(
+        &scim.location("Groups", &group.id),
+        &scim.group_json(&group),
+    ))
+}
+
+async fn replace_group(
+    State(scim): State<Scim>,
+    Path(id): Path<String>,
+    body: Bytes,
+) -> Result<Response, ScimError> {
+    let mut group = scim.load_group(&id).await?;
+    let previous = group.members.clone();
+    let resource: GroupResource = parse(&body)?;
+    group.members = resource.members()?;
+    group.external_id = resource.external_id;
+    group.display_name = resource.display_name;
+    group.updated_at = Utc::now();
+    scim.save_group(&group, &previous).await?;
+    Ok(scim_json(StatusCode::OK, &scim.group_json(&group)))
+}
+
+async fn patch_group(
+    State(scim): State<Scim>,
+    Path(id): Path<String>,
+    body: Bytes,
+) -> Result<Response, ScimError> {
+    let mut group = scim.load_group(&id).await?;
+    let previous = group.members.clone();
+    let request: PatchRequest = parse(&body)?;
+    patch::apply_group(&mut group, &request.operations)?;
+    group.updated_at = Utc::now();
+    scim.save_group(&group, &previous).await?;
+    Ok(scim_json(StatusCode::OK, &scim.group_json(&group)))
+}
+
+async fn delete_group(
+    State(scim): State<Scim>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ScimError> {
+    let group = scim.load_group(&id).await?;
+    if !scim.store.delete_group(&group.id).await? {
+        return Err(ScimError::NotFound(format!("Group {id}")));
+    }
+    scim.sync_roles(group.members.into_iter().collect()).await?;
+    info!(group_id = %id, "SCIM group deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Method;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "scim-test-token";
+
+    fn scim(mapping: ScimAttributeMapping) -> Scim {
+        let config = ScimConfig {
+            tokens: vec![TOKEN.to_string()],
+            max_results: 2,
+            mapping,
+        };
+        Scim::new(Arc::new(MemoryScimStore::new()), config)
+            .with_base_url("https://app.example.com/")
+    }
+
+    async fn call(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        body: Option<Json>,
+    ) -> (StatusCode, Json) {
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {TOKEN}"))
+            .header(header::CONTENT_TYPE, CONTENT_TYPE)
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Json::Null);
+        (status, body)
+    }
+
+    fn ada() -> Json {
+        json!({
+            "schemas": [USER_SCHEMA],
+            "userName": "Ada@Example.com",
+            "externalId": "00u1",
+            "name": {"givenName": "Ada", "familyName": "Lovelace"},
+            "emails": [{"value": "ada@work.example.com", "primary": true}],
+            "active": true
+        })
+    }
+
+    #[tokio::test]
+    async fn test_requires_bearer_token() {
+        let app = scim(ScimAttributeMapping::default()).routes();
+        for authorization in [None, Some("Bearer wrong"), Some("Basic c2NpbQ==")] {
+            let mut request = axum::http::Request::builder().uri("/scim/v2/Users");
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            let response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        }
+
+        let unconfigured = Scim::new(Arc::new(MemoryScimStore::new()), ScimConfig::default());
+        assert!(!unconfigured.accepts(""));
+    }
+
+    #[tokio::test]
+    async fn test_user_lifecycle() {
+        let app = scim(ScimAttributeMapping::default()).routes();
+
+        let (status, user) = call(&app, Method::POST, "/scim/v2/Users", Some(ada())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = user["id"].as_str().unwrap().to_string();
+        assert_eq!(user["emails"][0]["value"], "ada@example.com");
+        assert_eq!(user["roles"][0]["value"], "user");
+        assert_eq!(
+            user["meta"]["location"],
+            format!("https://app.example.com/scim/v2/Users/{id}")
+        );
+
+        // IdPs look users up before creating them, and retry on conflict
+        let (status, list) = call(
+            &app,
+            Method::GET,
+            "/scim/v2/Users?filter=userName%20eq%20%22ada%40example.com%22",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(list["totalResults"], 1);
+        assert_eq!(list["Resources"][0]["id"], id.as_str());
+        let (status, error) = call(&app, Method::POST, "/scim/v2/Users", Some(ada())).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(error["scimType"], "uniqueness");
+        assert_eq!(error["schemas"][0], ERROR_SCHEMA);
+
+        let uri = format!("/scim/v2/Users/{id}");
+        let deactivate = json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+            "Operations": [{"op": "replace", "value": {"active": false}}]
+        });
+        let (status, user) = call(&app, Method::PATCH, &uri, Some(deactivate)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(user["active"], false);
+
+        let mut replacement = ada();
+        replacement["displayName"] = json!("Countess");
+        let (status, user) = call(&app, Method::PUT, &uri, Some(replacement)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(user["displayName"], "Countess");
+        assert_eq!(user["active"], true);
+
+        let (status, _) = call(&app, Method::DELETE, &uri, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, error) = call(&app, Method::GET, &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error["status"], "404");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_deprovisioning_signs_out_sessions() {
+        use crate::htmx::agents::{LoadSession, SaveSession, SessionManagerAgent};
+        use crate::htmx::auth::session::{SessionData, SessionId};
+        use acton_reactive::prelude::ActonApp;
+
+        let mut runtime = ActonApp::launch_async().await;
+        let sessions = SessionManagerAgent::spawn(&mut runtime).await.unwrap();
+        let app = scim(ScimAttributeMapping::default())
+            .with_session_manager(sessions.clone())
+            .routes();
+        let signed_in = |user_id: i64| {
+            let sessions = sessions.clone();
+            async move {
+                let session_id = SessionId::generate();
+                let mut data = SessionData::new();
+                data.user_id = Some(user_id);
+                let (save, rx) = SaveSession::with_confirmation(session_id.clone(), data);
+                sessions.send(save).await;
+                assert!(rx.await.unwrap());
+                session_id
+            }
+        };
+        let exists = |session_id: SessionId| {
+            let sessions = sessions.clone();
+            async move {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                let (load, rx) = LoadSession::with_response(session_id);
+                sessions.send(load).await;
+                rx.await.unwrap().is_some()
+            }
+        };
+
+        let (_, user) = call(&app, Method::POST, "/scim/v2/Users", Some(ada())).await;
+        let ada_id: i64 = user["id"].as_str().unwrap().parse().unwrap();
+        let mut grace = ada();
+        grace["userName"] = json!("grace@example.com");
+        grace["externalId"] = json!("00u2");
+        let (_, user) = call(&app, Method::POST, "/scim/v2/Users", Some(grace)).await;
+        let grace_id: i64 = user["id"].as_str().unwrap().parse().unwrap();
+
+        let ada_session = signed_in(ada_id).await;
+        let grace_session = signed_in(grace_id).await;
+
+        // Other changes leave sessions alone
+        let rename = json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+            "Operations": [{"op": "replace", "value": {"displayName": "Countess"}}]
+        });
+        let uri = format!("/scim/v2/Users/{ada_id}");
+        call(&app, Method::PATCH, &uri, Some(rename)).await;
+        assert!(exists(ada_session.clone()).await);
+
+        let deactivate = json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+            "Operations": [{"op": "replace", "value": {"active": false}}]
+        });
+        let (status, _) = call(&app, Method::PATCH, &uri, Some(deactivate)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!exists(ada_session).await);
+        assert!(exists(grace_session.clone()).await);
+
+        let (status, _) = call(
+            &app,
+            Method::DELETE,
+            &format!("/scim/v2/Users/{grace_id}"),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(!exists(grace_session).await);
+
+        runtime.shutdown_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_email_mapping() {
+        let mapping = ScimAttributeMapping {
+            email: ScimEmailSource::PrimaryEmail,
+            ..ScimAttributeMapping::default()
+        };
+        let app = scim(mapping).routes();
+        let (_, user) = call(&app, Method::POST, "/scim/v2/Users", Some(ada())).await;
+        assert_eq!(user["emails"][0]["value"], "ada@work.example.com");
+
+        let (status, error) = call(
+            &app,
+            Method::POST,
+            "/scim/v2/Users",
+            Some(json!({"userName": "not an email"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["scimType"], "invalidValue");
+    }
+
+    #[tokio::test]
+    async fn test_pagination_and_filters() {
+        let app = scim(ScimAttributeMapping::default()).routes();
+        for name in ["a", "b", "c"] {
+            let body = json!({"userName": format!("{name}@example.com")});
+            call(&app, Method::POST, "/scim/v2/Users", Some(body)).await;
+        }
+
+        let (_, page) = call(
+            &app,
+            Method::GET,
+            "/scim/v2/Users?startIndex=2&count=10",
+            None,
+        )
+        .await;
+        assert_eq!(page["totalResults"], 3);
+        assert_eq!(page["startIndex"], 2);
+        assert_eq!(page["itemsPerPage"], 2);
+        assert_eq!(page["Resources"][0]["userName"], "b@example.com");
+
+        let (status, error) = call(
+            &app,
+            Method::GET,
+            "/scim/v2/Users?filter=displayName%20eq%20%22x%22",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["scimType"], "invalidFilter");
+    }
+
+    #[tokio::test]
+    async fn test_group_membership_maps_roles() {
+        let mapping = ScimAttributeMapping {
+            group_roles: [("Acme Admins".to_string(), "admin".to_string())].into(),
+            ..ScimAttributeMapping::default()
+        };
+        let app = scim(mapping).routes();
+        let (_, user) = call(&app, Method::POST, "/scim/v2/Users", Some(ada())).await;
+        let user_id = user["id"].as_str().unwrap().to_string();
+        let user_uri = format!("/scim/v2/Users/{user_id}");
+
+        let (status, group) = call(
+            &app,
+            Method::POST,
+            "/scim/v2/Groups",
+            Some(json!({"displayName": "acme admins", "members": [{"value": user_id}]})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let group_uri = format!("/scim/v2/Groups/{}", group["id"].as_str().unwrap());
+        let (_, user) = call(&app, Method::GET, &user_uri, None).await;
+        assert_eq!(
+            user["roles"],
+            json!([{"value": "user"}, {"value": "admin"}])
+        );
+
+        let remove = json!({"Operations": [
+            {"op": "remove", "path": format!("members[value eq \"{user_id}\"]")}
+        ]});
+        let (status, group) = call(&app, Method::PATCH, &group_uri, Some(remove)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(group["members"], json!([]));
+        let (_, user) = call(&app, Method::GET, &user_uri, None).await;
+        assert_eq!(user["roles"], json!([{"value": "user"}]));
+
+        let (status, error) = call(
+            &app,
+            Method::PUT,
+            &group_uri,
+            Some(json!({"displayName": "Acme Admins", "members": [{"value": "999"}]})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["scimType"], "invalidValue");
+
+        let (status, _) = call(&app, Method::DELETE, &group_uri, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_service_provider_config() {
+        let app = scim(ScimAttributeMapping::default()).routes();
+        let (status, config) =
+            call(&app, Method::GET, "/scim/v2/ServiceProviderConfig", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(config["patch"]["supported"], true);
+        assert_eq!(config["filter"]["maxResults"], 2);
+    }
+}
@@ -0,0 +1,389 @@
+//! SCIM `PATCH` operations
+//!
+//! Covers what Okta and Entra ID send: attribute replacement (with or
+//! without a `path`), deactivation through `active`, and group membership
+//! changes including `members[value eq "..."]` removals. Unknown attributes
+//! are ignored, as they are on create.
+
+use serde::Deserialize;
+use serde_json::Value as Json;
+
+use super::filter::equality;
+use super::{user_email, ScimError, ScimGroup, ScimUser};
+use crate::htmx::config::ScimEmailSource;
+
+/// `urn:ietf:params:scim:api:messages:2.0:PatchOp` request body
+#[derive(Debug, Clone, Deserialize)]
+pub struct PatchRequest {
+    /// Operations, applied in order
+    #[serde(rename = "Operations")]
+    pub operations: Vec<PatchOperation>,
+}
+
+/// A single patch operation
+#[derive(Debug, Clone, Deserialize)]
+pub struct PatchOperation {
+    /// `add`, `replace` or `remove` (case-insensitive)
+    pub op: String,
+    /// Attribute to change; the value holds attributes when absent
+    #[serde(default)]
+    pub path: Option<String>,
+    /// New value
+    #[serde(default)]
+    pub value: Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Replace,
+    Remove,
+}
+
+impl PatchOperation {
+    fn op(&self) -> Result<Op, ScimError> {
+        match self.op.to_ascii_lowercase().as_str() {
+            "add" => Ok(Op::Add),
+            "replace" => Ok(Op::Replace),
+            "remove" => Ok(Op::Remove),
+            other => Err(ScimError::InvalidValue(format!("unknown op {other}"))),
+        }
+    }
+}
+
+/// Apply operations to a user
+pub(super) fn apply_user(
+    user: &mut ScimUser,
+    operations: &[PatchOperation],
+    email_source: ScimEmailSource,
+) -> Result<(), ScimError> {
+    for operation in operations {
+        let op = operation.op()?;
+        match (&operation.path, op) {
+            (Some(path), Op::Remove) => remove_user_attribute(user, path)?,
+            (Some(path), _) => set_user_attribute(user, path, &operation.value, email_source)?,
+            (None, Op::Remove) => {
+                return Err(ScimError::InvalidPath("remove requires a path".to_string()))
+            }
+            (None, _) => {
+                let attributes = operation.value.as_object().ok_or_else(|| {
+                    ScimError::InvalidValue("value must be an object".to_string())
+                })?;
+                for (path, value) in attributes {
+                    set_user_attribute(user, path, value, email_source)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn set_user_attribute(
+    user: &mut ScimUser,
+    path: &str,
+    value: &Json,
+    email_source: ScimEmailSource,
+) -> Result<(), ScimError> {
+    let lower = path.to_ascii_lowercase();
+    match lower.as_str() {
+        "active" => user.active = boolean(value)?,
+        "username" => {
+            user.user_name = string(value)?;
+            if email_source == ScimEmailSource::UserName {
+                user.email = user_email(&user.user_name)?;
+            }
+        }
+        "externalid" => user.external_id = Some(string(value)?),
+        "displayname" => user.display_name = Some(string(value)?),
+        "name.givenname" => user.given_name = Some(string(value)?),
+        "name.familyname" => user.family_name = Some(string(value)?),
+        "name" => {
+            if let Some(given) = value.get("givenName") {
+                user.given_name = Some(string(given)?);
+            }
+            if let Some(family) = value.get("familyName") {
+                user.family_name = Some(string(family)?);
+            }
+        }
+        // `emails`, `emails[type eq "work"].value`, ...
+        _ if lower.starts_with("emails") && email_source == ScimEmailSource::PrimaryEmail => {
+            if let Some(email) = email_value(value) {
+                user.email = user_email(&email)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn remove_user_attribute(user: &mut ScimUser, path: &str) -> Result<(), ScimError> {
+    match path.to_ascii_lowercase().as_str() {
+        "externalid" => user.external_id = None,
+        "displayname" => user.display_name = None,
+        "name.givenname" => user.given_name = None,
+        "name.familyname" => user.family_name = None,
+        "name" => {
+            user.given_name = None;
+            user.family_name = None;
+        }
+        "username" | "active" => {
+            return Err(ScimError::InvalidValue(format!("{path} cannot be removed")))
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Apply operations to a group
+pub(super) fn apply_group(
+    group: &mut ScimGroup,
+    operations: &[PatchOperation],
+) -> Result<(), ScimError> {
+    for operation in operations {
+        let op = operation.op()?;
+        let Some(path) = &operation.path else {
+            if op == Op::Remove {
+                return Err(ScimError::InvalidPath("remove requires a path".to_string()));
+            }
+            let attributes = operation
+                .value
+                .as_object()
+                .ok_or_else(|| ScimError::InvalidValue("value must be an object".to_string()))?;
+            for (path, value) in attributes {
+                set_group_attribute(group, path, value, op)?;
+            }
+            continue;
+        };
+
+        if op != Op::Remove {
+            set_group_attribute(group, path, &operation.value, op)?;
+        } else if let Some(filter) = path
+            .strip_prefix("members[")
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            let (attribute, value) = equality(filter)?;
+            if !attribute.eq_ignore_ascii_case("value") {
+                return Err(ScimError::InvalidPath(path.clone()));
+            }
+            let id = member_id(&value)?;
+            group.members.retain(|member| *member != id);
+        } else {
+            match path.to_ascii_lowercase().as_str() {
+                "members" if operation.value.is_null() => group.members.clear(),
+                "members" => {
+                    let removed = members(&operation.value)?;
+                    group.members.retain(|member| !removed.contains(member));
+                }
+                "externalid" => group.external_id = None,
+                "displayname" => {
+                    return Err(ScimError::InvalidValue(
+                        "displayName cannot be removed".to_string(),
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn set_group_attribute(
+    group: &mut ScimGroup,
+    path: &str,
+    value: &Json,
+    op: Op,
+) -> Result<(), ScimError> {
+    match path.to_ascii_lowercase().as_str() {
+        "displayname" => group.display_name = string(value)?,
+        "externalid" => group.external_id = Some(string(value)?),
+        "members" => {
+            let members = members(value)?;
+            if op == Op::Replace {
+                group.members.clear();
+            }
+            for member in members {
+                if !group.members.contains(&member) {
+                    group.members.push(member);
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// User IDs from `[{"value": "42"}, ...]`
+pub(super) fn members(value: &Json) -> Result<Vec<i64>, ScimError> {
+    let entries = match value {
+        Json::Array(entries) => entries.as_slice(),
+        Json::Object(_) => std::slice::from_ref(value),
+        _ => {
+            return Err(ScimError::InvalidValue(
+                "members must be an array".to_string(),
+            ))
+        }
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            entry
+                .get("value")
+                .and_then(Json::as_str)
+                .ok_or_else(|| ScimError::InvalidValue("member without value".to_string()))
+                .and_then(member_id)
+        })
+        .collect()
+}
+
+fn member_id(value: &str) -> Result<i64, ScimError> {
+    value
+        .parse()
+        .map_err(|_| ScimError::InvalidValue(format!("unknown member {value}")))
+}
+
+/// Email from a string, an email object, or a list of them (primary first)
+fn email_value(value: &Json) -> Option<String> {
+    match value {
+        Json::String(email) => Some(email.clone()),
+        Json::Object(_) => value
+            .get("value")
+            .and_then(Json::as_str)
+            .map(str::to_string),
+        Json::Array(emails) => emails
+            .iter()
+            .find(|email| email.get("primary").and_then(Json::as_bool) == Some(true))
+            .or_else(|| emails.first())
+            .and_then(email_value),
+        _ => None,
+    }
+}
+
+fn string(value: &Json) -> Result<String, ScimError> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| ScimError::InvalidValue(format!("expected a string, got {value}")))
+}
+
+/// Entra ID sends booleans as `"True"`/`"False"`
+fn boolean(value: &Json) -> Result<bool, ScimError> {
+    match value {
+        Json::Bool(value) => Ok(*value),
+        Json::String(value) if value.eq_ignore_ascii_case("true") => Ok(true),
+        Json::String(value) if value.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(ScimError::InvalidValue(format!(
+            "expected a boolean, got {value}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn user() -> ScimUser {
+        ScimUser {
+            id: 1,
+            external_id: Some("00u1".to_string()),
+            user_name: "ada@example.com".to_string(),
+            email: "ada@example.com".to_string(),
+            given_name: Some("Ada".to_string()),
+            family_name: None,
+            display_name: None,
+            active: true,
+            roles: vec!["user".to_string()],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn operations(value: Json) -> Vec<PatchOperation> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_deactivate_user() {
+        // Okta
+        let mut okta = user();
+        let ops = operations(json!([{"op": "replace", "value": {"active": false}}]));
+        apply_user(&mut okta, &ops, ScimEmailSource::UserName).unwrap();
+        assert!(!okta.active);
+
+        // Entra ID
+        let mut entra = user();
+        let ops = operations(json!([{"op": "Replace", "path": "active", "value": "False"}]));
+        apply_user(&mut entra, &ops, ScimEmailSource::UserName).unwrap();
+        assert!(!entra.active);
+    }
+
+    #[test]
+    fn test_patch_user_attributes() {
+        let mut user = user();
+        let ops = operations(json!([
+            {"op": "replace", "path": "userName", "value": "Ada.L@Example.com"},
+            {"op": "add", "path": "name.familyName", "value": "Lovelace"},
+            {"op": "remove", "path": "externalId"},
+            {"op": "replace", "path": "emails[type eq \"work\"].value", "value": "ignored@example.com"},
+            {"op": "add", "path": "title", "value": "Countess"}
+        ]));
+        apply_user(&mut user, &ops, ScimEmailSource::UserName).unwrap();
+
+        assert_eq!(user.user_name, "Ada.L@Example.com");
+        assert_eq!(user.email, "ada.l@example.com");
+        assert_eq!(user.family_name.as_deref(), Some("Lovelace"));
+        assert_eq!(user.external_id, None);
+
+        let ops = operations(json!([{"op": "remove", "path": "userName"}]));
+        assert!(apply_user(&mut user, &ops, ScimEmailSource::UserName).is_err());
+    }
+
+    #[test]
+    fn test_patch_email_from_emails() {
+        let mut user = user();
+        let ops = operations(json!([{"op": "replace", "path": "emails", "value": [
+            {"value": "home@example.com"},
+            {"value": "work@example.com", "primary": true}
+        ]}]));
+        apply_user(&mut user, &ops, ScimEmailSource::PrimaryEmail).unwrap();
+        assert_eq!(user.email, "work@example.com");
+    }
+
+    #[test]
+    fn test_patch_group_members() {
+        let mut group = ScimGroup {
+            id: "g1".to_string(),
+            external_id: None,
+            display_name: "Admins".to_string(),
+            members: vec![1],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let ops = operations(json!([
+            {"op": "add", "path": "members", "value": [{"value": "2"}, {"value": "3"}, {"value": "1"}]},
+            {"op": "remove", "path": "members[value eq \"1\"]"},
+            {"op": "replace", "value": {"displayName": "Owners"}}
+        ]));
+        apply_group(&mut group, &ops).unwrap();
+        assert_eq!(group.members, vec![2, 3]);
+        assert_eq!(group.display_name, "Owners");
+
+        let ops =
+            operations(json!([{"op": "remove", "path": "members", "value": [{"value": "3"}]}]));
+        apply_group(&mut group, &ops).unwrap();
+        assert_eq!(group.members, vec![2]);
+
+        let ops =
+            operations(json!([{"op": "replace", "path": "members", "value": [{"value": "4"}]}]));
+        apply_group(&mut group, &ops).unwrap();
+        assert_eq!(group.members, vec![4]);
+
+        let ops = operations(json!([{"op": "add", "path": "members", "value": [{"value": "x"}]}]));
+        assert!(matches!(
+            apply_group(&mut group, &ops),
+            Err(ScimError::InvalidValue(_))
+        ));
+    }
+}
@@ -0,0 +1,323 @@
+//! SCIM store backed by the PostgreSQL `users` table
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+
+use super::{Filter, ScimError, ScimGroup, ScimStore, ScimUser};
+
+const USER_COLUMNS: &str = "id, email, scim_user_name, scim_external_id, given_name, \
+     family_name, display_name, active, roles, created_at, updated_at";
+
+const GROUP_SELECT: &str =
+    "SELECT g.id, g.external_id, g.display_name, g.created_at, g.updated_at, \
+     COALESCE(ARRAY_AGG(m.user_id ORDER BY m.user_id) FILTER (WHERE m.user_id IS NOT NULL), \
+     '{}'::BIGINT[]) AS members \
+     FROM scim_groups g LEFT JOIN scim_group_members m ON m.group_id = g.id";
+
+/// SCIM store persisting to the application's `users` table
+///
+/// Apply `migrations/005_add_scim_provisioning.sql` first. Users created
+/// outside SCIM show up with their email as `userName`. Provisioned users
+/// get an empty password hash, so they cannot sign in with a password until
+/// they set one.
+#[derive(Debug, Clone)]
+pub struct PgScimStore {
+    pool: PgPool,
+}
+
+impl PgScimStore {
+    /// Create a store
+    #[must_use]
+    pub const fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[derive(FromRow)]
+struct UserRow {
+    id: i64,
+    email: String,
+    scim_user_name: Option<String>,
+    scim_external_id: Option<String>,
+    given_name: Option<String>,
+    family_name: Option<String>,
+    display_name: Option<String>,
+    active: bool,
+    roles: Vec<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<UserRow> for ScimUser {
+    fn from(row: UserRow) -> Self {
+        Self {
+            id: row.id,
+            user_name: row.scim_user_name.unwrap_or_else(|| row.email.clone()),
+            email: row.email,
+            external_id: row.scim_external_id,
+            given_name: row.given_name,
+            family_name: row.family_name,
+            display_name: row.display_name,
+            active: row.active,
+            roles: row.roles,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct GroupRow {
+    id: String,
+    external_id: Option<String>,
+    display_name: String,
+    members: Vec<i64>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<GroupRow> for ScimGroup {
+    fn from(row: GroupRow) -> Self {
+        Self {
+            id: row.id,
+            external_id: row.external_id,
+            display_name: row.display_name,
+            members: row.members,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// `WHERE` condition for a user filter; the value is bound as `$1`
+fn user_condition(filter: Option<&Filter>) -> (&'static str, Option<&str>) {
+    match filter {
+        None => ("$1::TEXT IS NULL", None),
+        Some(Filter::UserName(value)) => (
+            "LOWER(COALESCE(scim_user_name, email)) = LOWER($1)",
+            Some(value),
+        ),
+        Some(Filter::ExternalId(value)) => ("scim_external_id = $1", Some(value)),
+        Some(Filter::Email(value)) => ("email = LOWER($1)", Some(value)),
+        Some(Filter::DisplayName(value)) => ("FALSE AND $1::TEXT IS NOT NULL", Some(value)),
+    }
+}
+
+/// `WHERE` condition for a group filter; the value is bound as `$1`
+fn group_condition(filter: Option<&Filter>) -> (&'static str, Option<&str>) {
+    match filter {
+        None => ("$1::TEXT IS NULL", None),
+        Some(Filter::ExternalId(value)) => ("g.external_id = $1", Some(value)),
+        Some(Filter::DisplayName(value)) => ("LOWER(g.display_name) = LOWER($1)", Some(value)),
+        Some(Filter::UserName(value) | Filter::Email(value)) => {
+            ("FALSE AND $1::TEXT IS NOT NULL", Some(value))
+        }
+    }
+}
+
+fn store_error(error: &sqlx::Error, conflict: impl FnOnce() -> String) -> ScimError {
+    if error
+        .as_database_error()
+        .is_some_and(sqlx::error::DatabaseError::is_unique_violation)
+    {
+        ScimError::Uniqueness(conflict())
+    } else {
+        ScimError::Store(error.to_string())
+    }
+}
+
+impl From<sqlx::Error> for ScimError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Store(error.to_string())
+    }
+}
+
+fn bigint(value: usize) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+fn count(total: i64) -> usize {
+    usize::try_from(total).unwrap_or_default()
+}
+
+#[async_trait]
+impl ScimStore for PgScimStore {
+    async fn user(&self, id: i64) -> Result<Option<ScimUser>, ScimError> {
+        let row = sqlx::query_as::<_, UserRow>(&format!(
+            "SELECT {USER_COLUMNS} FROM users WHERE id = $1"
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(ScimUser::from))
+    }
+
+    async fn users(
+        &self,
+        filter: Option<&Filter>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<ScimUser>, usize), ScimError> {
+        let (condition, value) = user_condition(filter);
+        let total: i64 =
+            sqlx::query_scalar(&format!("SELECT COUNT(*) FROM users WHERE {condition}"))
+                .bind(value)
+                .fetch_one(&self.pool)
+                .await?;
+        let rows = sqlx::query_as::<_, UserRow>(&format!(
+            "SELECT {USER_COLUMNS} FROM users WHERE {condition} ORDER BY id LIMIT $2 OFFSET $3"
+        ))
+        .bind(value)
+        .bind(bigint(limit))
+        .bind(bigint(offset))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok((rows.into_iter().map(ScimUser::from).collect(), count(total)))
+    }
+
+    async fn create_user(&self, user: &ScimUser) -> Result<ScimUser, ScimError> {
+        // The identity provider vouches for the address
+        let row = sqlx::query_as::<_, UserRow>(&format!(
+            "INSERT INTO users (email, password_hash, roles, email_verified, active, \
+             scim_user_name, scim_external_id, given_name, family_name, display_name) \
+             VALUES ($1, '', $2, TRUE, $3, $4, $5, $6, $7, $8) \
+             RETURNING {USER_COLUMNS}"
+        ))
+        .bind(&user.email)
+        .bind(&user.roles)
+        .bind(user.active)
+        .bind(&user.user_name)
+        .bind(&user.external_id)
+        .bind(&user.given_name)
+        .bind(&user.family_name)
+        .bind(&user.display_name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| store_error(&e, || format!("User {} already exists", user.user_name)))?;
+        Ok(row.into())
+    }
+
+    async fn update_user(&self, user: &ScimUser) -> Result<(), ScimError> {
+        let result = sqlx::query(
+            "UPDATE users SET email = $2, roles = $3, active = $4, scim_user_name = $5, \
+             scim_external_id = $6, given_name = $7, family_name = $8, display_name = $9, \
+             updated_at = NOW() WHERE id = $1",
+        )
+        .bind(user.id)
+        .bind(&user.email)
+        .bind(&user.roles)
+        .bind(user.active)
+        .bind(&user.user_name)
+        .bind(&user.external_id)
+        .bind(&user.given_name)
+        .bind(&user.family_name)
+        .bind(&user.display_name)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| store_error(&e, || format!("User {} already exists", user.user_name)))?;
+        if result.rows_affected() == 0 {
+            return Err(ScimError::NotFound(format!("User {}", user.id)));
+        }
+        Ok(())
+    }
+
+    async fn delete_user(&self, id: i64) -> Result<bool, ScimError> {
+        // Memberships go with the user (ON DELETE CASCADE)
+        let result = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn group(&self, id: &str) -> Result<Option<ScimGroup>, ScimError> {
+        let row =
+            sqlx::query_as::<_, GroupRow>(&format!("{GROUP_SELECT} WHERE g.id = $1 GROUP BY g.id"))
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(ScimGroup::from))
+    }
+
+    async fn groups(
+        &self,
+        filter: Option<&Filter>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<ScimGroup>, usize), ScimError> {
+        let (condition, value) = group_condition(filter);
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM scim_groups g WHERE {condition}"
+        ))
+        .bind(value)
+        .fetch_one(&self.pool)
+        .await?;
+        let rows = sqlx::query_as::<_, GroupRow>(&format!(
+            "{GROUP_SELECT} WHERE {condition} GROUP BY g.id \
+             ORDER BY LOWER(g.display_name) LIMIT $2 OFFSET $3"
+        ))
+        .bind(value)
+        .bind(bigint(limit))
+        .bind(bigint(offset))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok((
+            rows.into_iter().map(ScimGroup::from).collect(),
+            count(total),
+        ))
+    }
+
+    async fn groups_of(&self, user_id: i64) -> Result<Vec<ScimGroup>, ScimError> {
+        let rows = sqlx::query_as::<_, GroupRow>(&format!(
+            "{GROUP_SELECT} WHERE g.id IN \
+             (SELECT group_id FROM scim_group_members WHERE user_id = $1) GROUP BY g.id"
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(ScimGroup::from).collect())
+    }
+
+    async fn put_group(&self, group: &ScimGroup) -> Result<(), ScimError> {
+        let conflict = || format!("Group {} already exists", group.display_name);
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO scim_groups (id, external_id, display_name, created_at, updated_at) \
+             VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT (id) DO UPDATE SET external_id = $2, display_name = $3, updated_at = $5",
+        )
+        .bind(&group.id)
+        .bind(&group.external_id)
+        .bind(&group.display_name)
+        .bind(group.created_at)
+        .bind(group.updated_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| store_error(&e, conflict))?;
+
+        sqlx::query("DELETE FROM scim_group_members WHERE group_id = $1")
+            .bind(&group.id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO scim_group_members (group_id, user_id) \
+             SELECT $1, UNNEST($2::BIGINT[])",
+        )
+        .bind(&group.id)
+        .bind(&group.members)
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(tx.commit().await?)
+    }
+
+    async fn delete_group(&self, id: &str) -> Result<bool, ScimError> {
+        let result = sqlx::query("DELETE FROM scim_groups WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
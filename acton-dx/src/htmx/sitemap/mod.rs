@@ -0,0 +1,549 @@
+//! Sitemap and robots.txt generation
+//!
+//! Routes and dynamic resources register [`UrlProvider`]s with a [`Sitemap`].
+//! The sitemap collects their URLs on demand, caches the result for
+//! `sitemap.cache_ttl_secs`, and serves it as `sitemap.xml`. Once the URL
+//! count exceeds `sitemap.urls_per_file`, `sitemap.xml` becomes a sitemap
+//! index pointing at `/sitemap/1.xml`, `/sitemap/2.xml`, ...
+//!
+//! `robots.txt` is generated from [`RobotsConfig`]: in environments listed
+//! in `disallow_all_environments` (staging by default) every crawler is
+//! blocked so pre-production deployments never get indexed.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::config::ActonHtmxConfig;
+//! use acton_htmx::sitemap::{Sitemap, SitemapUrl, UrlProvider, SitemapError};
+//! use async_trait::async_trait;
+//!
+//! struct PostUrls { pool: sqlx::PgPool }
+//!
+//! #[async_trait]
+//! impl UrlProvider for PostUrls {
+//!     async fn urls(&self) -> Result<Vec<SitemapUrl>, SitemapError> {
+//!         let posts = load_published_posts(&self.pool).await?;
+//!         Ok(posts
+//!             .into_iter()
+//!             .map(|p| SitemapUrl::new(format!("/posts/{}", p.slug)).with_lastmod(p.updated_at))
+//!             .collect())
+//!     }
+//! }
+//!
+//! let config = ActonHtmxConfig::load_for_service("my-app")?;
+//! let sitemap = Sitemap::new(config.sitemap.clone())
+//!     .with_paths(["/", "/about", "/pricing"])
+//!     .with_provider(PostUrls { pool });
+//!
+//! let app = Router::new().merge(sitemap.routes());
+//! ```
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::htmx::config::{RobotsConfig, SitemapConfig};
+use crate::htmx::template::helpers::escape_html;
+
+/// Sitemap errors
+#[derive(Debug, thiserror::Error)]
+pub enum SitemapError {
+    /// A URL provider failed to load its URLs
+    #[error("URL provider failed: {0}")]
+    Provider(String),
+
+    /// Requested sitemap page does not exist
+    #[error("Sitemap page {0} not found")]
+    PageNotFound(usize),
+}
+
+impl IntoResponse for SitemapError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::Provider(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::PageNotFound(_) => StatusCode::NOT_FOUND,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// How often a page is expected to change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeFreq {
+    /// Changes on every access
+    Always,
+    /// Changes hourly
+    Hourly,
+    /// Changes daily
+    Daily,
+    /// Changes weekly
+    Weekly,
+    /// Changes monthly
+    Monthly,
+    /// Changes yearly
+    Yearly,
+    /// Archived content
+    Never,
+}
+
+impl ChangeFreq {
+    /// Protocol value
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Hourly => "hourly",
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+            Self::Yearly => "yearly",
+            Self::Never => "never",
+        }
+    }
+}
+
+/// A single sitemap entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapUrl {
+    /// Path (`/about`) or absolute URL
+    pub loc: String,
+    /// Last modification time
+    pub lastmod: Option<DateTime<Utc>>,
+    /// Expected change frequency
+    pub changefreq: Option<ChangeFreq>,
+    /// Relative priority between 0.0 and 1.0
+    pub priority: Option<f32>,
+}
+
+impl SitemapUrl {
+    /// Create an entry for a path or absolute URL
+    #[must_use]
+    pub fn new(loc: impl Into<String>) -> Self {
+        Self {
+            loc: loc.into(),
+            lastmod: None,
+            changefreq: None,
+            priority: None,
+        }
+    }
+
+    /// Set the last modification time
+    #[must_use]
+    pub const fn with_lastmod(mut self, lastmod: DateTime<Utc>) -> Self {
+        self.lastmod = Some(lastmod);
+        self
+    }
+
+    /// Set the change frequency
+    #[must_use]
+    pub const fn with_changefreq(mut self, changefreq: ChangeFreq) -> Self {
+        self.changefreq = Some(changefreq);
+        self
+    }
+
+    /// Set the priority (clamped to 0.0..=1.0)
+    #[must_use]
+    pub fn with_priority(mut self, priority: f32) -> Self {
+        self.priority = Some(priority.clamp(0.0, 1.0));
+        self
+    }
+}
+
+/// Source of sitemap URLs
+///
+/// Implement for dynamic resources (blog posts, products) that should be
+/// listed in the sitemap.
+#[async_trait]
+pub trait UrlProvider: Send + Sync {
+    /// Load the URLs this provider contributes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SitemapError::Provider`] if the URLs cannot be loaded.
+    async fn urls(&self) -> Result<Vec<SitemapUrl>, SitemapError>;
+}
+
+/// Fixed list of URLs, used for static routes
+#[derive(Debug, Clone, Default)]
+pub struct StaticUrls(pub Vec<SitemapUrl>);
+
+#[async_trait]
+impl UrlProvider for StaticUrls {
+    async fn urls(&self) -> Result<Vec<SitemapUrl>, SitemapError> {
+        Ok(self.0.clone())
+    }
+}
+
+struct CachedUrls {
+    generated_at: Instant,
+    urls: Arc<Vec<SitemapUrl>>,
+}
+
+struct SitemapInner {
+    config: SitemapConfig,
+    providers: Vec<Arc<dyn UrlProvider>>,
+    cache: Mutex<Option<CachedUrls>>,
+}
+
+/// Sitemap registry and handlers
+#[derive(Clone)]
+pub struct Sitemap {
+    inner: Arc<SitemapInner>,
+}
+
+impl std::fmt::Debug for Sitemap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sitemap")
+            .field("config", &self.inner.config)
+            .field("providers", &self.inner.providers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Sitemap {
+    /// Create an empty sitemap
+    #[must_use]
+    pub fn new(config: SitemapConfig) -> Self {
+        Self {
+            inner: Arc::new(SitemapInner {
+                config,
+                providers: Vec::new(),
+                cache: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Register a URL provider
+    ///
+    /// Providers must be registered before the sitemap is cloned into routes.
+    #[must_use]
+    pub fn with_provider(mut self, provider: impl UrlProvider + 'static) -> Self {
+        if let Some(inner) = Arc::get_mut(&mut self.inner) {
+            inner.providers.push(Arc::new(provider));
+        } else {
+            warn!("Sitemap provider registered after the sitemap was shared; ignoring");
+        }
+        self
+    }
+
+    /// Register static paths
+    #[must_use]
+    pub fn with_paths<I, T>(self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.with_provider(StaticUrls(paths.into_iter().map(SitemapUrl::new).collect()))
+    }
+
+    /// Routes serving `/sitemap.xml`, `/sitemap/{n}.xml` and `/robots.txt`
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/sitemap.xml", get(sitemap_handler))
+            .route("/sitemap/{file}", get(sitemap_page_handler))
+            .route("/robots.txt", get(robots_handler))
+            .with_state(self.clone())
+    }
+
+    /// Collect URLs from all providers, using the cache when fresh
+    ///
+    /// A failing provider is logged and skipped so one broken source does not
+    /// take down the whole sitemap; such partial results are not cached.
+    pub async fn urls(&self) -> Arc<Vec<SitemapUrl>> {
+        let ttl = Duration::from_secs(self.inner.config.cache_ttl_secs);
+        let mut cache = self.inner.cache.lock().await;
+        if let Some(cached) = cache.as_ref() {
+            if cached.generated_at.elapsed() < ttl {
+                return Arc::clone(&cached.urls);
+            }
+        }
+
+        let mut urls = Vec::new();
+        let mut complete = true;
+        for provider in &self.inner.providers {
+            match provider.urls().await {
+                Ok(mut provided) => urls.append(&mut provided),
+                Err(e) => {
+                    warn!(error = %e, "Sitemap URL provider failed");
+                    complete = false;
+                }
+            }
+        }
+
+        let urls = Arc::new(urls);
+        if complete {
+            *cache = Some(CachedUrls {
+                generated_at: Instant::now(),
+                urls: Arc::clone(&urls),
+            });
+        }
+        urls
+    }
+
+    /// Drop cached URLs so the next request regenerates them
+    pub async fn invalidate(&self) {
+        *self.inner.cache.lock().await = None;
+    }
+
+    /// Render `sitemap.xml` (a URL set, or an index when paginated)
+    pub async fn render(&self) -> String {
+        let urls = self.urls().await;
+        let per_file = self.inner.config.urls_per_file.max(1);
+        if urls.len() <= per_file {
+            return self.render_urlset(&urls);
+        }
+
+        let pages = urls.len().div_ceil(per_file);
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+        for page in 1..=pages {
+            let loc = self.absolute(&format!("/sitemap/{page}.xml"));
+            let _ = writeln!(xml, "  <sitemap><loc>{}</loc></sitemap>", escape_html(&loc));
+        }
+        xml.push_str("</sitemapindex>\n");
+        xml
+    }
+
+    /// Render one page of a paginated sitemap (1-based)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SitemapError::PageNotFound`] if the page is out of range.
+    pub async fn render_page(&self, page: usize) -> Result<String, SitemapError> {
+        let urls = self.urls().await;
+        let per_file = self.inner.config.urls_per_file.max(1);
+        let start = page
+            .checked_sub(1)
+            .map(|index| index * per_file)
+            .filter(|start| *start < urls.len())
+            .ok_or(SitemapError::PageNotFound(page))?;
+        let end = (start + per_file).min(urls.len());
+        Ok(self.render_urlset(&urls[start..end]))
+    }
+
+    /// Render `robots.txt`
+    #[must_use]
+    pub fn render_robots(&self) -> String {
+        render_robots(&self.inner.config.robots, &self.absolute("/sitemap.xml"))
+    }
+
+    fn render_urlset(&self, urls: &[SitemapUrl]) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+        for url in urls {
+            let _ = write!(
+                xml,
+                "  <url><loc>{}</loc>",
+                escape_html(&self.absolute(&url.loc))
+            );
+            if let Some(lastmod) = url.lastmod {
+                let _ = write!(
+                    xml,
+                    "<lastmod>{}</lastmod>",
+                    lastmod.to_rfc3339_opts(SecondsFormat::Secs, true)
+                );
+            }
+            if let Some(changefreq) = url.changefreq {
+                let _ = write!(xml, "<changefreq>{}</changefreq>", changefreq.as_str());
+            }
+            if let Some(priority) = url.priority {
+                let _ = write!(xml, "<priority>{priority:.1}</priority>");
+            }
+            xml.push_str("</url>\n");
+        }
+        xml.push_str("</urlset>\n");
+        xml
+    }
+
+    fn absolute(&self, loc: &str) -> String {
+        if loc.starts_with("http://") || loc.starts_with("https://") {
+            return loc.to_string();
+        }
+        format!("{}{loc}", self.inner.config.base_url.trim_end_matches('/'))
+    }
+
+    fn cache_control(&self) -> String {
+        format!("public, max-age={}", self.inner.config.cache_ttl_secs)
+    }
+}
+
+/// Render robots.txt for the given configuration
+#[must_use]
+pub fn render_robots(config: &RobotsConfig, sitemap_url: &str) -> String {
+    let mut robots = String::from("User-agent: *\n");
+    if config.blocks_all() {
+        robots.push_str("Disallow: /\n");
+        return robots;
+    }
+
+    for path in &config.allow {
+        let _ = writeln!(robots, "Allow: {path}");
+    }
+    if config.disallow.is_empty() {
+        robots.push_str("Disallow:\n");
+    }
+    for path in &config.disallow {
+        let _ = writeln!(robots, "Disallow: {path}");
+    }
+    if let Some(delay) = config.crawl_delay {
+        let _ = writeln!(robots, "Crawl-delay: {delay}");
+    }
+    let _ = write!(robots, "\nSitemap: {sitemap_url}\n");
+    robots
+}
+
+async fn sitemap_handler(State(sitemap): State<Sitemap>) -> Response {
+    let xml = sitemap.render().await;
+    xml_response(&sitemap, xml)
+}
+
+async fn sitemap_page_handler(
+    State(sitemap): State<Sitemap>,
+    Path(file): Path<String>,
+) -> Result<Response, SitemapError> {
+    let page = file
+        .strip_suffix(".xml")
+        .and_then(|n| n.parse::<usize>().ok())
+        .ok_or(SitemapError::PageNotFound(0))?;
+    let xml = sitemap.render_page(page).await?;
+    Ok(xml_response(&sitemap, xml))
+}
+
+#[allow(clippy::unused_async)] // axum handler
+async fn robots_handler(State(sitemap): State<Sitemap>) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+            (header::CACHE_CONTROL, sitemap.cache_control()),
+        ],
+        sitemap.render_robots(),
+    )
+        .into_response()
+}
+
+fn xml_response(sitemap: &Sitemap, xml: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "application/xml; charset=utf-8".to_string()),
+            (header::CACHE_CONTROL, sitemap.cache_control()),
+        ],
+        xml,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    fn config(per_file: usize) -> SitemapConfig {
+        SitemapConfig {
+            base_url: "https://example.com/".to_string(),
+            urls_per_file: per_file,
+            ..SitemapConfig::default()
+        }
+    }
+
+    struct CountingProvider(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl UrlProvider for CountingProvider {
+        async fn urls(&self) -> Result<Vec<SitemapUrl>, SitemapError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![SitemapUrl::new("/posts/a&b")
+                .with_changefreq(ChangeFreq::Weekly)
+                .with_priority(0.8)])
+        }
+    }
+
+    async fn get_body(app: Router, uri: &str) -> (StatusCode, String) {
+        let response = app
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_urlset_rendering_and_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let sitemap = Sitemap::new(config(100))
+            .with_paths(["/"])
+            .with_provider(CountingProvider(Arc::clone(&calls)));
+
+        let xml = sitemap.render().await;
+        assert!(xml.contains("<urlset"));
+        assert!(xml.contains("<loc>https://example.com/</loc>"));
+        assert!(xml.contains("<loc>https://example.com/posts/a&amp;b</loc>"));
+        assert!(xml.contains("<changefreq>weekly</changefreq><priority>0.8</priority>"));
+
+        let _ = sitemap.render().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        sitemap.invalidate().await;
+        let _ = sitemap.render().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_paginated_sitemap_index() {
+        let sitemap = Sitemap::new(config(2)).with_paths(["/a", "/b", "/c"]);
+        let app: Router = sitemap.routes();
+
+        let (status, index) = get_body(app.clone(), "/sitemap.xml").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(index.contains("<sitemapindex"));
+        assert!(index.contains("https://example.com/sitemap/2.xml"));
+
+        let (_, page) = get_body(app.clone(), "/sitemap/2.xml").await;
+        assert!(page.contains("https://example.com/c"));
+        assert!(!page.contains("https://example.com/a"));
+
+        let (status, _) = get_body(app, "/sitemap/3.xml").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_robots_rules_and_staging_block() {
+        let robots = RobotsConfig {
+            disallow: vec!["/admin".to_string()],
+            environment: Some("production".to_string()),
+            ..RobotsConfig::default()
+        };
+        let txt = render_robots(&robots, "https://example.com/sitemap.xml");
+        assert!(txt.contains("Disallow: /admin\n"));
+        assert!(txt.contains("Sitemap: https://example.com/sitemap.xml"));
+
+        let staging = RobotsConfig {
+            environment: Some("Staging".to_string()),
+            ..robots
+        };
+        assert_eq!(
+            render_robots(&staging, "https://example.com/sitemap.xml"),
+            "User-agent: *\nDisallow: /\n"
+        );
+    }
+}
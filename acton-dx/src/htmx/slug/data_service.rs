@@ -0,0 +1,148 @@
+//! Slug store backed by data-service
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{SlugError, SlugStore};
+use crate::htmx::clients::{DataClient, Row, Value};
+use crate::htmx::util::validate_identifier;
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+/// Migration creating the slug history table
+pub const SLUG_HISTORY_MIGRATION: &str = r"CREATE TABLE IF NOT EXISTS slug_history (
+    scope TEXT NOT NULL,
+    old_slug TEXT NOT NULL,
+    new_slug TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (scope, old_slug)
+);
+CREATE INDEX IF NOT EXISTS idx_slug_history_new ON slug_history (scope, new_slug);";
+
+/// Slug store that checks current slugs in a table and keeps history in
+/// `slug_history`
+///
+/// The scope passed to [`SlugStore`] methods is used as the table name, so
+/// it must be a plain SQL identifier.
+#[derive(Debug, Clone)]
+pub struct DataServiceSlugStore {
+    client: Arc<RwLock<DataClient>>,
+    column: String,
+}
+
+impl DataServiceSlugStore {
+    /// Create a store reading current slugs from the `slug` column
+    #[must_use]
+    pub fn new(client: Arc<RwLock<DataClient>>) -> Self {
+        Self {
+            client,
+            column: "slug".to_string(),
+        }
+    }
+
+    /// Use a different column for current slugs
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SlugError::InvalidIdentifier`] if `column` is not a plain
+    /// SQL identifier.
+    pub fn with_column(mut self, column: impl Into<String>) -> Result<Self, SlugError> {
+        let column = column.into();
+        validate_identifier(&column)
+            .map_err(|_| SlugError::InvalidIdentifier(column.clone()))?;
+        self.column = column;
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl SlugStore for DataServiceSlugStore {
+    async fn is_taken(&self, scope: &str, slug: &str) -> Result<bool, SlugError> {
+        validate_identifier(scope)
+            .map_err(|_| SlugError::InvalidIdentifier(scope.to_string()))?;
+        let sql = format!(
+            "SELECT 1 AS taken FROM {scope} WHERE {column} = $1 \
+             UNION ALL SELECT 1 FROM slug_history WHERE scope = $2 AND old_slug = $1 LIMIT 1",
+            column = self.column
+        );
+        let row = self
+            .client
+            .write()
+            .await
+            .query_one(&sql, vec![text(slug), text(scope)], None)
+            .await
+            .map_err(|e| SlugError::Store(e.to_string()))?;
+        Ok(row.is_some())
+    }
+
+    async fn renamed_to(&self, scope: &str, old_slug: &str) -> Result<Option<String>, SlugError> {
+        let row = self
+            .client
+            .write()
+            .await
+            .query_one(
+                "SELECT new_slug FROM slug_history WHERE scope = $1 AND old_slug = $2",
+                vec![text(scope), text(old_slug)],
+                None,
+            )
+            .await
+            .map_err(|e| SlugError::Store(e.to_string()))?;
+        Ok(row.as_ref().and_then(|row| string_column(row, "new_slug")))
+    }
+
+    async fn record_rename(
+        &self,
+        scope: &str,
+        old_slug: &str,
+        new_slug: &str,
+    ) -> Result<(), SlugError> {
+        let statements = [
+            (
+                // Renaming back to a previous slug makes it current again
+                "DELETE FROM slug_history WHERE scope = $1 AND old_slug = $2",
+                vec![text(scope), text(new_slug)],
+            ),
+            (
+                // Point earlier names straight at the new slug
+                "UPDATE slug_history SET new_slug = $3 WHERE scope = $1 AND new_slug = $2",
+                vec![text(scope), text(old_slug), text(new_slug)],
+            ),
+            (
+                "INSERT INTO slug_history (scope, old_slug, new_slug) VALUES ($1, $2, $3) \
+                 ON CONFLICT (scope, old_slug) DO UPDATE SET new_slug = EXCLUDED.new_slug",
+                vec![text(scope), text(old_slug), text(new_slug)],
+            ),
+        ];
+
+        let mut client = self.client.write().await;
+        let tx = client
+            .begin_transaction()
+            .await
+            .map_err(|e| SlugError::Store(e.to_string()))?;
+        for (sql, params) in statements {
+            if let Err(e) = client.execute(sql, params, Some(tx.clone())).await {
+                let _ = client.rollback_transaction(&tx).await;
+                return Err(SlugError::Store(e.to_string()));
+            }
+        }
+        client
+            .commit_transaction(&tx)
+            .await
+            .map_err(|e| SlugError::Store(e.to_string()))?;
+        drop(client);
+        Ok(())
+    }
+}
+
+fn text(value: &str) -> Value {
+    Value {
+        value: Some(ValueKind::StringValue(value.to_string())),
+    }
+}
+
+fn string_column(row: &Row, name: &str) -> Option<String> {
+    match row.columns.get(name)?.value.as_ref()? {
+        ValueKind::StringValue(s) => Some(s.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,486 @@
+//! URL slug generation, uniqueness and rename redirects
+//!
+//! - [`slugify`] turns titles into lowercase, hyphen-separated slugs. Latin
+//!   diacritics are folded (`Crème Brûlée` → `creme-brulee`) while other
+//!   scripts are kept (`東京 Guide` → `東京-guide`).
+//! - [`unique_slug`] checks a [`SlugStore`] and appends `-2`, `-3`, ... until
+//!   the slug is free.
+//! - [`SlugStore::record_rename`] remembers previous slugs, and
+//!   [`SlugRedirect`] answers requests for an old slug with a
+//!   `301 Moved Permanently` to the current one.
+//!
+//! With the `microservices` feature, [`DataServiceSlugStore`] keeps slug
+//! history in the database through data-service.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::slug::{unique_slug, MemorySlugStore, SlugRedirect};
+//! use axum::middleware::from_fn_with_state;
+//! use std::sync::Arc;
+//!
+//! let store = Arc::new(MemorySlugStore::new());
+//! let slug = unique_slug(store.as_ref(), "posts", "Hello, World!").await?;
+//!
+//! let redirects = SlugRedirect::new(store).with_route("/posts/", "posts");
+//! let app = Router::new()
+//!     .route("/posts/{slug}", get(show_post))
+//!     .layer(from_fn_with_state(redirects, SlugRedirect::middleware));
+//! ```
+
+#[cfg(feature = "microservices")]
+mod data_service;
+
+#[cfg(feature = "microservices")]
+pub use data_service::{DataServiceSlugStore, SLUG_HISTORY_MIGRATION};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Default maximum slug length in characters
+pub const DEFAULT_MAX_LENGTH: usize = 80;
+
+/// Maximum numeric suffix tried by [`unique_slug`]
+const MAX_SUFFIX: u32 = 100;
+
+/// Maximum rename hops followed when resolving an old slug
+const MAX_REDIRECT_HOPS: usize = 10;
+
+/// Slug errors
+#[derive(Debug, thiserror::Error)]
+pub enum SlugError {
+    /// Input produced an empty slug
+    #[error("Cannot build a slug from '{0}'")]
+    Empty(String),
+
+    /// Every suffix up to the limit is taken
+    #[error("No free slug for '{0}' after {MAX_SUFFIX} attempts")]
+    Exhausted(String),
+
+    /// Scope or column name is not a plain SQL identifier
+    #[error("Invalid identifier '{0}'")]
+    InvalidIdentifier(String),
+
+    /// Backing store failed
+    #[error("Slug store error: {0}")]
+    Store(String),
+}
+
+/// Convert text to a URL slug
+///
+/// Equivalent to [`slugify_with_max`] with [`DEFAULT_MAX_LENGTH`].
+#[must_use]
+pub fn slugify(input: &str) -> String {
+    slugify_with_max(input, DEFAULT_MAX_LENGTH)
+}
+
+/// Convert text to a URL slug of at most `max_length` characters
+///
+/// Letters and digits of any script are kept and lowercased, diacritics are
+/// removed, and runs of anything else become a single `-`. Truncation happens
+/// at a separator when possible so words are not cut in half.
+#[must_use]
+pub fn slugify_with_max(input: &str, max_length: usize) -> String {
+    let mut slug = String::with_capacity(input.len());
+    let mut pending_separator = false;
+
+    for c in input.nfkd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_alphanumeric() {
+            if pending_separator && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_separator = false;
+            slug.extend(c.to_lowercase());
+        } else {
+            pending_separator = true;
+        }
+    }
+
+    if slug.chars().count() <= max_length {
+        return slug;
+    }
+    let truncated: String = slug.chars().take(max_length).collect();
+    match truncated.rfind('-') {
+        Some(cut) if cut > 0 => truncated[..cut].to_string(),
+        _ => truncated,
+    }
+}
+
+/// Storage for current slugs and rename history
+///
+/// `scope` identifies the collection (usually the table name) so different
+/// resource types can reuse the same slug.
+#[async_trait]
+pub trait SlugStore: Send + Sync {
+    /// Whether `slug` is in use, either currently or as a previous slug
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SlugError::Store`] if the store cannot be queried.
+    async fn is_taken(&self, scope: &str, slug: &str) -> Result<bool, SlugError>;
+
+    /// The slug that replaced `old_slug`, if it was renamed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SlugError::Store`] if the store cannot be queried.
+    async fn renamed_to(&self, scope: &str, old_slug: &str) -> Result<Option<String>, SlugError>;
+
+    /// Record that `old_slug` was renamed to `new_slug`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SlugError::Store`] if the rename cannot be stored.
+    async fn record_rename(
+        &self,
+        scope: &str,
+        old_slug: &str,
+        new_slug: &str,
+    ) -> Result<(), SlugError>;
+}
+
+/// Generate a slug for `input` that is free in `scope`
+///
+/// # Errors
+///
+/// Returns [`SlugError::Empty`] if `input` has no letters or digits,
+/// [`SlugError::Exhausted`] if every suffix up to 100 is taken, or a store error.
+pub async fn unique_slug(
+    store: &dyn SlugStore,
+    scope: &str,
+    input: &str,
+) -> Result<String, SlugError> {
+    let base = slugify(input);
+    if base.is_empty() {
+        return Err(SlugError::Empty(input.to_string()));
+    }
+    if !store.is_taken(scope, &base).await? {
+        return Ok(base);
+    }
+
+    for suffix in 2..=MAX_SUFFIX {
+        let candidate = format!("{base}-{suffix}");
+        if !store.is_taken(scope, &candidate).await? {
+            return Ok(candidate);
+        }
+    }
+    Err(SlugError::Exhausted(base))
+}
+
+/// Follow rename history from `slug` to the current slug
+///
+/// Returns `None` if `slug` was never renamed.
+///
+/// # Errors
+///
+/// Returns a store error if the history cannot be read.
+pub async fn resolve_current(
+    store: &dyn SlugStore,
+    scope: &str,
+    slug: &str,
+) -> Result<Option<String>, SlugError> {
+    let mut current: Option<String> = None;
+    for _ in 0..MAX_REDIRECT_HOPS {
+        let lookup = current.as_deref().unwrap_or(slug);
+        match store.renamed_to(scope, lookup).await? {
+            Some(next) if next != slug => current = Some(next),
+            _ => break,
+        }
+    }
+    Ok(current)
+}
+
+/// In-memory slug store for tests and single-node deployments
+#[derive(Debug, Default)]
+pub struct MemorySlugStore {
+    current: RwLock<HashMap<String, HashSet<String>>>,
+    history: RwLock<HashMap<(String, String), String>>,
+}
+
+impl MemorySlugStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark `slug` as in use
+    pub fn insert(&self, scope: &str, slug: &str) {
+        self.current
+            .write()
+            .entry(scope.to_string())
+            .or_default()
+            .insert(slug.to_string());
+    }
+}
+
+#[async_trait]
+impl SlugStore for MemorySlugStore {
+    async fn is_taken(&self, scope: &str, slug: &str) -> Result<bool, SlugError> {
+        let in_use = self
+            .current
+            .read()
+            .get(scope)
+            .is_some_and(|slugs| slugs.contains(slug));
+        let in_history = self
+            .history
+            .read()
+            .contains_key(&(scope.to_string(), slug.to_string()));
+        Ok(in_use || in_history)
+    }
+
+    async fn renamed_to(&self, scope: &str, old_slug: &str) -> Result<Option<String>, SlugError> {
+        Ok(self
+            .history
+            .read()
+            .get(&(scope.to_string(), old_slug.to_string()))
+            .cloned())
+    }
+
+    async fn record_rename(
+        &self,
+        scope: &str,
+        old_slug: &str,
+        new_slug: &str,
+    ) -> Result<(), SlugError> {
+        let mut history = self.history.write();
+        // Renaming back to a previous slug makes it current again
+        history.remove(&(scope.to_string(), new_slug.to_string()));
+        // Point earlier names straight at the new slug
+        for (key, target) in history.iter_mut() {
+            if key.0 == scope && target == old_slug {
+                *target = new_slug.to_string();
+            }
+        }
+        history.insert(
+            (scope.to_string(), old_slug.to_string()),
+            new_slug.to_string(),
+        );
+        drop(history);
+
+        let mut current = self.current.write();
+        let slugs = current.entry(scope.to_string()).or_default();
+        slugs.remove(old_slug);
+        slugs.insert(new_slug.to_string());
+        drop(current);
+        Ok(())
+    }
+}
+
+/// Redirect middleware for renamed slugs
+///
+/// Only consulted when the inner router answers `404 Not Found`, so requests
+/// for current slugs cost nothing extra.
+#[derive(Clone)]
+pub struct SlugRedirect {
+    store: Arc<dyn SlugStore>,
+    routes: Arc<Vec<(String, String)>>,
+}
+
+impl std::fmt::Debug for SlugRedirect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SlugRedirect")
+            .field("routes", &self.routes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SlugRedirect {
+    /// Create redirect middleware backed by `store`
+    #[must_use]
+    pub fn new(store: Arc<dyn SlugStore>) -> Self {
+        Self {
+            store,
+            routes: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Redirect slugs under `prefix` (e.g. `/posts/`) using history in `scope`
+    #[must_use]
+    pub fn with_route(mut self, prefix: impl Into<String>, scope: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.routes).push((prefix.into(), scope.into()));
+        self
+    }
+
+    /// Middleware function for `axum::middleware::from_fn_with_state`
+    pub async fn middleware(
+        State(redirect): State<Self>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let path = request.uri().path().to_string();
+        let query = request.uri().query().map(ToString::to_string);
+        let response = next.run(request).await;
+        if response.status() != StatusCode::NOT_FOUND {
+            return response;
+        }
+
+        let Some((prefix, scope, slug)) = redirect.match_route(&path) else {
+            return response;
+        };
+        match resolve_current(redirect.store.as_ref(), scope, slug).await {
+            Ok(Some(current)) => {
+                let location = query.map_or_else(
+                    || format!("{prefix}{current}"),
+                    |q| format!("{prefix}{current}?{q}"),
+                );
+                (
+                    StatusCode::MOVED_PERMANENTLY,
+                    [(header::LOCATION, location)],
+                )
+                    .into_response()
+            }
+            Ok(None) => response,
+            Err(e) => {
+                tracing::warn!(error = %e, path = %path, "Slug redirect lookup failed");
+                response
+            }
+        }
+    }
+
+    /// Find the route whose prefix matches and extract the slug segment
+    fn match_route<'a>(&'a self, path: &'a str) -> Option<(&'a str, &'a str, &'a str)> {
+        self.routes.iter().find_map(|(prefix, scope)| {
+            let slug = path.strip_prefix(prefix.as_str())?;
+            (!slug.is_empty() && !slug.contains('/')).then_some((
+                prefix.as_str(),
+                scope.as_str(),
+                slug,
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware::from_fn_with_state, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_slugify_ascii_and_unicode() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Crème Brûlée -- Recipe  "), "creme-brulee-recipe");
+        assert_eq!(slugify("東京 Guide"), "東京-guide");
+        assert_eq!(slugify("Straße №5"), "straße-no5");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn test_slugify_truncates_at_word_boundary() {
+        assert_eq!(slugify_with_max("alpha beta gamma", 12), "alpha-beta");
+        assert_eq!(slugify_with_max("abcdefghij", 4), "abcd");
+    }
+
+    #[tokio::test]
+    async fn test_unique_slug_appends_suffix() {
+        let store = MemorySlugStore::new();
+        store.insert("posts", "hello");
+        store.insert("posts", "hello-2");
+
+        assert_eq!(
+            unique_slug(&store, "posts", "Hello").await.unwrap(),
+            "hello-3"
+        );
+        assert_eq!(
+            unique_slug(&store, "pages", "Hello").await.unwrap(),
+            "hello"
+        );
+        assert!(matches!(
+            unique_slug(&store, "posts", "???").await,
+            Err(SlugError::Empty(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rename_history_flattens_and_reserves_old_slugs() {
+        let store = MemorySlugStore::new();
+        store.insert("posts", "draft");
+        store
+            .record_rename("posts", "draft", "launch")
+            .await
+            .unwrap();
+        store
+            .record_rename("posts", "launch", "launch-day")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            resolve_current(&store, "posts", "draft")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("launch-day")
+        );
+        assert!(store.is_taken("posts", "draft").await.unwrap());
+
+        store
+            .record_rename("posts", "launch-day", "draft")
+            .await
+            .unwrap();
+        assert_eq!(
+            resolve_current(&store, "posts", "draft").await.unwrap(),
+            None
+        );
+        assert_eq!(
+            resolve_current(&store, "posts", "launch")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("draft")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirect_middleware() {
+        let store = Arc::new(MemorySlugStore::new());
+        store.insert("posts", "new-title");
+        store
+            .record_rename("posts", "old-title", "new-title")
+            .await
+            .unwrap();
+
+        let redirects = SlugRedirect::new(store).with_route("/posts/", "posts");
+        let app = Router::new()
+            .route(
+                "/posts/{slug}",
+                get(
+                    |axum::extract::Path(slug): axum::extract::Path<String>| async move {
+                        if slug == "new-title" {
+                            (StatusCode::OK, "post").into_response()
+                        } else {
+                            StatusCode::NOT_FOUND.into_response()
+                        }
+                    },
+                ),
+            )
+            .layer(from_fn_with_state(redirects, SlugRedirect::middleware));
+
+        let request = Request::builder()
+            .uri("/posts/old-title?page=2")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            response.headers().get(header::LOCATION).unwrap(),
+            "/posts/new-title?page=2"
+        );
+
+        let request = Request::builder()
+            .uri("/posts/missing")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
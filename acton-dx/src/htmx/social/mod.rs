@@ -0,0 +1,273 @@
+//! Social metadata (OpenGraph and Twitter cards)
+//!
+//! Handlers describe a page with a typed [`PageMeta`] and pass it to their
+//! template; the base layout renders it inside `<head>`:
+//!
+//! ```html
+//! {% block head %}{{ page_meta|safe }}{% endblock %}
+//! ```
+//!
+//! With the `og-image` feature, [`OgImageJob`] renders an SVG template to a
+//! PNG preview image in the background and stores it through the configured
+//! file storage (local or file-service).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::social::{PageMeta, TwitterCard};
+//!
+//! let page_meta = PageMeta::new("Release notes", "What shipped in 1.2")
+//!     .with_url("https://example.com/blog/1-2")
+//!     .with_image("https://cdn.example.com/og/1-2.png")
+//!     .with_site_name("Example")
+//!     .with_type("article")
+//!     .with_twitter_card(TwitterCard::SummaryLargeImage);
+//! ```
+
+#[cfg(feature = "og-image")]
+mod og_image;
+
+#[cfg(feature = "og-image")]
+pub use og_image::{OgImageError, OgImageJob};
+
+use std::fmt::{self, Write as _};
+
+use crate::htmx::template::helpers::escape_html;
+
+/// Twitter card layout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TwitterCard {
+    /// Small square thumbnail
+    #[default]
+    Summary,
+    /// Full-width preview image
+    SummaryLargeImage,
+}
+
+impl TwitterCard {
+    /// Value of the `twitter:card` tag
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Summary => "summary",
+            Self::SummaryLargeImage => "summary_large_image",
+        }
+    }
+}
+
+/// Page metadata rendered as OpenGraph and Twitter card tags
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageMeta {
+    /// Page title (`og:title`)
+    pub title: String,
+    /// Short description (`og:description`, `<meta name="description">`)
+    pub description: String,
+    /// Canonical absolute URL (`og:url`, `<link rel="canonical">`)
+    pub url: Option<String>,
+    /// Absolute URL of the preview image
+    pub image: Option<String>,
+    /// Alt text for the preview image
+    pub image_alt: Option<String>,
+    /// Preview image dimensions in pixels
+    pub image_size: Option<(u32, u32)>,
+    /// Site name (`og:site_name`)
+    pub site_name: Option<String>,
+    /// OpenGraph object type (`website`, `article`, ...)
+    pub og_type: String,
+    /// Content locale (`en_US`)
+    pub locale: Option<String>,
+    /// Twitter card layout
+    pub twitter_card: TwitterCard,
+    /// Twitter handle of the site (`@example`)
+    pub twitter_site: Option<String>,
+    /// Twitter handle of the author
+    pub twitter_creator: Option<String>,
+}
+
+impl PageMeta {
+    /// Create metadata with a title and description
+    #[must_use]
+    pub fn new(title: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            description: description.into(),
+            og_type: "website".to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Set the canonical URL
+    #[must_use]
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the preview image URL
+    ///
+    /// Also switches the Twitter card to the large image layout.
+    #[must_use]
+    pub fn with_image(mut self, image: impl Into<String>) -> Self {
+        self.image = Some(image.into());
+        self.twitter_card = TwitterCard::SummaryLargeImage;
+        self
+    }
+
+    /// Set the preview image alt text
+    #[must_use]
+    pub fn with_image_alt(mut self, alt: impl Into<String>) -> Self {
+        self.image_alt = Some(alt.into());
+        self
+    }
+
+    /// Set the preview image dimensions
+    #[must_use]
+    pub const fn with_image_size(mut self, width: u32, height: u32) -> Self {
+        self.image_size = Some((width, height));
+        self
+    }
+
+    /// Set the site name
+    #[must_use]
+    pub fn with_site_name(mut self, name: impl Into<String>) -> Self {
+        self.site_name = Some(name.into());
+        self
+    }
+
+    /// Set the OpenGraph object type
+    #[must_use]
+    pub fn with_type(mut self, og_type: impl Into<String>) -> Self {
+        self.og_type = og_type.into();
+        self
+    }
+
+    /// Set the content locale
+    #[must_use]
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// Set the Twitter card layout
+    #[must_use]
+    pub const fn with_twitter_card(mut self, card: TwitterCard) -> Self {
+        self.twitter_card = card;
+        self
+    }
+
+    /// Set the Twitter handle of the site
+    #[must_use]
+    pub fn with_twitter_site(mut self, handle: impl Into<String>) -> Self {
+        self.twitter_site = Some(handle.into());
+        self
+    }
+
+    /// Set the Twitter handle of the author
+    #[must_use]
+    pub fn with_twitter_creator(mut self, handle: impl Into<String>) -> Self {
+        self.twitter_creator = Some(handle.into());
+        self
+    }
+
+    /// Render the `<meta>` and `<link>` tags
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut html = String::new();
+        meta_name(&mut html, "description", &self.description);
+        if let Some(url) = &self.url {
+            let _ = writeln!(
+                html,
+                r#"<link rel="canonical" href="{}">"#,
+                escape_html(url)
+            );
+        }
+
+        meta_property(&mut html, "og:title", &self.title);
+        meta_property(&mut html, "og:description", &self.description);
+        meta_property(&mut html, "og:type", &self.og_type);
+        for (property, value) in [
+            ("og:url", &self.url),
+            ("og:site_name", &self.site_name),
+            ("og:locale", &self.locale),
+            ("og:image", &self.image),
+            ("og:image:alt", &self.image_alt),
+        ] {
+            if let Some(value) = value {
+                meta_property(&mut html, property, value);
+            }
+        }
+        if let (Some(_), Some((width, height))) = (&self.image, self.image_size) {
+            meta_property(&mut html, "og:image:width", &width.to_string());
+            meta_property(&mut html, "og:image:height", &height.to_string());
+        }
+
+        meta_name(&mut html, "twitter:card", self.twitter_card.as_str());
+        meta_name(&mut html, "twitter:title", &self.title);
+        meta_name(&mut html, "twitter:description", &self.description);
+        for (name, value) in [
+            ("twitter:image", &self.image),
+            ("twitter:image:alt", &self.image_alt),
+            ("twitter:site", &self.twitter_site),
+            ("twitter:creator", &self.twitter_creator),
+        ] {
+            if let Some(value) = value {
+                meta_name(&mut html, name, value);
+            }
+        }
+        html
+    }
+}
+
+impl fmt::Display for PageMeta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+fn meta_property(html: &mut String, property: &str, content: &str) {
+    let _ = writeln!(
+        html,
+        r#"<meta property="{property}" content="{}">"#,
+        escape_html(content)
+    );
+}
+
+fn meta_name(html: &mut String, name: &str, content: &str) {
+    let _ = writeln!(
+        html,
+        r#"<meta name="{name}" content="{}">"#,
+        escape_html(content)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_basic_tags() {
+        let html = PageMeta::new("Home", "Welcome").render();
+        assert!(html.contains(r#"<meta property="og:title" content="Home">"#));
+        assert!(html.contains(r#"<meta property="og:type" content="website">"#));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary">"#));
+        assert!(!html.contains("og:image"));
+        assert!(!html.contains("canonical"));
+    }
+
+    #[test]
+    fn test_render_image_and_escaping() {
+        let meta = PageMeta::new("Tom & \"Jerry\"", "<b>cartoon</b>")
+            .with_url("https://example.com/?a=1&b=2")
+            .with_image("https://cdn.example.com/og.png")
+            .with_image_size(1200, 630)
+            .with_twitter_site("@example");
+        let html = meta.to_string();
+
+        assert!(html.contains(r#"content="Tom &amp; &quot;Jerry&quot;""#));
+        assert!(html.contains(r#"content="&lt;b&gt;cartoon&lt;/b&gt;""#));
+        assert!(html.contains(r#"<link rel="canonical" href="https://example.com/?a=1&amp;b=2">"#));
+        assert!(html.contains(r#"<meta property="og:image:width" content="1200">"#));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary_large_image">"#));
+        assert!(html.contains(r#"<meta name="twitter:site" content="@example">"#));
+    }
+}
@@ -0,0 +1,204 @@
+//! OpenGraph preview image generation job
+
+use async_trait::async_trait;
+use minijinja::{AutoEscape, Environment};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::htmx::jobs::{Job, JobContext, JobError, JobResult};
+use crate::htmx::storage::{StoredFile, UploadedFile};
+
+/// Default OpenGraph image width
+const DEFAULT_WIDTH: u32 = 1200;
+
+/// Default OpenGraph image height
+const DEFAULT_HEIGHT: u32 = 630;
+
+/// OpenGraph image rendering errors
+#[derive(Debug, thiserror::Error)]
+pub enum OgImageError {
+    /// Template failed to render
+    #[error("Template error: {0}")]
+    Template(#[from] minijinja::Error),
+
+    /// Rendered output is not valid SVG
+    #[error("Invalid SVG: {0}")]
+    Svg(String),
+
+    /// Image could not be rasterized or encoded
+    #[error("Rasterization failed: {0}")]
+    Raster(String),
+}
+
+/// Background job that renders an SVG template to a PNG preview image
+///
+/// The template is rendered with minijinja (values are XML-escaped), the
+/// resulting SVG is rasterized with `resvg` using system fonts, and the PNG
+/// is stored through the job context's file storage. The job result is the
+/// [`StoredFile`], whose ID can be saved on the resource and exposed as
+/// [`PageMeta::image`](super::PageMeta::image).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use acton_htmx::social::OgImageJob;
+///
+/// let job = OgImageJob::new(
+///     include_str!("../templates/og/post.svg"),
+///     serde_json::json!({ "title": post.title, "author": post.author }),
+/// )
+/// .with_filename(format!("og-post-{}.png", post.id));
+/// state.jobs().enqueue(job).await?;
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OgImageJob {
+    /// SVG template source
+    pub template: String,
+    /// Template context
+    pub context: serde_json::Value,
+    /// Output width in pixels
+    pub width: u32,
+    /// Output height in pixels
+    pub height: u32,
+    /// Filename for the stored PNG
+    pub filename: String,
+}
+
+impl OgImageJob {
+    /// Create a job for a template and context at the standard 1200x630 size
+    #[must_use]
+    pub fn new(template: impl Into<String>, context: serde_json::Value) -> Self {
+        Self {
+            template: template.into(),
+            context,
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            filename: "og-image.png".to_string(),
+        }
+    }
+
+    /// Set the output size
+    #[must_use]
+    pub const fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Set the stored filename
+    #[must_use]
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = filename.into();
+        self
+    }
+
+    /// Render the template to SVG markup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OgImageError::Template`] if the template fails to render.
+    pub fn render_svg(&self) -> Result<String, OgImageError> {
+        let mut env = Environment::new();
+        env.set_auto_escape_callback(|_| AutoEscape::Html);
+        Ok(env.render_str(&self.template, &self.context)?)
+    }
+
+    /// Render the template to PNG bytes
+    ///
+    /// The SVG is scaled to fill the configured output size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template, SVG parsing, or PNG encoding fails.
+    pub fn render_png(&self) -> Result<Vec<u8>, OgImageError> {
+        use resvg::{tiny_skia, usvg};
+
+        let svg = self.render_svg()?;
+        let mut options = usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        let tree = usvg::Tree::from_str(&svg, &options).map_err(|e| OgImageError::Svg(e.to_string()))?;
+
+        let mut pixmap = tiny_skia::Pixmap::new(self.width, self.height)
+            .ok_or_else(|| OgImageError::Raster("invalid image size".to_string()))?;
+        let size = tree.size();
+        #[allow(clippy::cast_precision_loss)] // image dimensions are far below f32 precision limits
+        let transform = tiny_skia::Transform::from_scale(
+            self.width as f32 / size.width(),
+            self.height as f32 / size.height(),
+        );
+        resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+        pixmap
+            .encode_png()
+            .map_err(|e| OgImageError::Raster(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl Job for OgImageJob {
+    type Result = StoredFile;
+
+    async fn execute(&self, ctx: &JobContext) -> JobResult<Self::Result> {
+        let storage = ctx
+            .file_storage()
+            .ok_or_else(|| JobError::ExecutionFailed("File storage not configured".to_string()))?;
+
+        // Rasterization is CPU bound
+        let job = self.clone();
+        let png = tokio::task::spawn_blocking(move || job.render_png())
+            .await
+            .map_err(|e| JobError::ExecutionFailed(format!("Render task failed: {e}")))?
+            .map_err(|e| JobError::ExecutionFailed(e.to_string()))?;
+
+        let stored = storage
+            .store(UploadedFile::new(&self.filename, "image/png", png))
+            .await
+            .map_err(|e| JobError::ExecutionFailed(format!("Failed to store OG image: {e}")))?;
+
+        tracing::info!(file_id = %stored.id, filename = %self.filename, "OG image generated");
+        Ok(stored)
+    }
+
+    fn max_retries(&self) -> u32 {
+        // Template and SVG errors are deterministic; only storage failures benefit from retries
+        2
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="120" height="63">
+<rect width="120" height="63" fill="{{ color }}"/>
+<text x="10" y="40">{{ title }}</text>
+</svg>"#;
+
+    #[test]
+    fn test_render_svg_escapes_values() {
+        let job = OgImageJob::new(TEMPLATE, serde_json::json!({ "color": "#123456", "title": "A < B & C" }));
+        let svg = job.render_svg().unwrap();
+        assert!(svg.contains("A &lt; B &amp; C"));
+    }
+
+    #[test]
+    fn test_render_png_at_requested_size() {
+        let job = OgImageJob::new(TEMPLATE, serde_json::json!({ "color": "#123456", "title": "Hi" }))
+            .with_size(240, 126);
+        let png = job.render_png().unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (240, 126));
+    }
+
+    #[test]
+    fn test_invalid_svg_rejected() {
+        let job = OgImageJob::new("<not-svg>", serde_json::Value::Null);
+        assert!(matches!(job.render_png(), Err(OgImageError::Svg(_))));
+    }
+}
@@ -3,7 +3,9 @@
 //! Combines acton-service infrastructure with acton-reactive actors and
 //! HTMX-specific components.
 
-use crate::htmx::agents::{CsrfManagerAgent, SessionManagerAgent};
+use crate::htmx::agents::{
+    CsrfManagerAgent, Mailbox, MailboxStats, SessionManagerAgent, SupervisedAgent, Supervisor,
+};
 use crate::htmx::jobs::JobAgent;
 use crate::htmx::oauth2::OAuth2Agent;
 use crate::htmx::template::FrameworkTemplates;
@@ -32,6 +34,8 @@ use crate::htmx::clients::{ServiceRegistry, ServicesConfig};
 /// - CSRF protection agent (from acton-reactive)
 /// - OAuth2 manager agent (from acton-reactive)
 /// - Job processing agent (from acton-reactive)
+/// - Bounded mailboxes in front of the session and job agents
+/// - A supervisor that restarts the session, CSRF and job agents
 /// - Database connection pool (PostgreSQL via SQLx)
 /// - Redis cache (optional, for distributed sessions and job persistence)
 /// - Framework templates (runtime-loadable HTML templates)
@@ -70,25 +74,28 @@ pub struct ActonHtmxState {
     /// Observability configuration
     observability: Arc<ObservabilityConfig>,
 
-    /// Session manager agent handle
-    ///
-    /// Clone this freely - `ActorHandle` is designed for concurrent access
-    session_manager: ActorHandle,
+    /// Session manager agent, followed across restarts
+    session_manager: SupervisedAgent,
 
-    /// CSRF manager agent handle
-    ///
-    /// Clone this freely - `ActorHandle` is designed for concurrent access
-    csrf_manager: ActorHandle,
+    /// CSRF manager agent, followed across restarts
+    csrf_manager: SupervisedAgent,
 
     /// OAuth2 manager agent handle
     ///
     /// Clone this freely - `ActorHandle` is designed for concurrent access
     oauth2_manager: ActorHandle,
 
-    /// Job processing agent handle
-    ///
-    /// Clone this freely - `ActorHandle` is designed for concurrent access
-    job_agent: ActorHandle,
+    /// Job processing agent, followed across restarts
+    job_agent: SupervisedAgent,
+
+    /// Supervisor of the session, CSRF and job agents, unless disabled
+    supervisor: Option<Supervisor>,
+
+    /// Bounded mailbox in front of the session manager agent
+    session_mailbox: Mailbox,
+
+    /// Bounded mailbox in front of the job agent
+    job_mailbox: Mailbox,
 
     /// PostgreSQL database connection pool
     ///
@@ -145,31 +152,7 @@ impl ActonHtmxState {
     /// let state = ActonHtmxState::new(&mut runtime).await?;
     /// ```
     pub async fn new(runtime: &mut ActorRuntime) -> anyhow::Result<Self> {
-        let config = ActonHtmxConfig::default();
-        let observability = ObservabilityConfig::default();
-        let session_manager = SessionManagerAgent::spawn(runtime).await?;
-        let csrf_manager = CsrfManagerAgent::spawn(runtime).await?;
-        let oauth2_manager = OAuth2Agent::spawn(runtime).await?;
-        let job_agent = JobAgent::spawn(runtime).await?;
-        let templates = FrameworkTemplates::new()?;
-
-        Ok(Self {
-            config: Arc::new(config),
-            observability: Arc::new(observability),
-            session_manager,
-            csrf_manager,
-            oauth2_manager,
-            job_agent,
-            #[cfg(feature = "postgres")]
-            pg_pool: None,
-            #[cfg(feature = "sqlite")]
-            sqlite_pool: None,
-            #[cfg(feature = "redis")]
-            redis_pool: None,
-            templates,
-            #[cfg(feature = "microservices")]
-            services: None,
-        })
+        Self::build(runtime, ActonHtmxConfig::default(), ObservabilityConfig::default()).await
     }
 
     /// Create application state with custom configuration
@@ -197,11 +180,19 @@ impl ActonHtmxState {
         runtime: &mut ActorRuntime,
         config: ActonHtmxConfig,
     ) -> anyhow::Result<Self> {
-        let observability = ObservabilityConfig::new("acton-dx");
-        let session_manager = SessionManagerAgent::spawn(runtime).await?;
-        let csrf_manager = CsrfManagerAgent::spawn(runtime).await?;
+        Self::build(runtime, config, ObservabilityConfig::new("acton-dx")).await
+    }
+
+    /// Spawn the framework's agents and assemble the state
+    async fn build(
+        runtime: &mut ActorRuntime,
+        config: ActonHtmxConfig,
+        observability: ObservabilityConfig,
+    ) -> anyhow::Result<Self> {
+        let (supervisor, session_manager, csrf_manager, job_agent) =
+            Box::pin(spawn_agents(runtime, &config)).await?;
         let oauth2_manager = OAuth2Agent::spawn(runtime).await?;
-        let job_agent = JobAgent::spawn(runtime).await?;
+        let (session_mailbox, job_mailbox) = spawn_mailboxes(&config, &session_manager, &job_agent);
         let templates = FrameworkTemplates::new()?;
 
         Ok(Self {
@@ -211,6 +202,9 @@ impl ActonHtmxState {
             csrf_manager,
             oauth2_manager,
             job_agent,
+            supervisor,
+            session_mailbox,
+            job_mailbox,
             #[cfg(feature = "postgres")]
             pg_pool: None,
             #[cfg(feature = "sqlite")]
@@ -267,6 +261,8 @@ impl ActonHtmxState {
 
     /// Get the session manager agent handle
     ///
+    /// The handle changes when the agent is restarted; fetch it again
+    /// instead of keeping it, or keep [`Self::supervised_session_manager`].
     /// Use this to send session-related messages directly to the agent.
     /// For most use cases, prefer using the `SessionExtractor` in handlers.
     ///
@@ -282,12 +278,20 @@ impl ActonHtmxState {
     /// }
     /// ```
     #[must_use]
-    pub const fn session_manager(&self) -> &ActorHandle {
+    pub fn session_manager(&self) -> ActorHandle {
+        self.session_manager.handle()
+    }
+
+    /// Get the session manager agent, followed across restarts
+    #[must_use]
+    pub const fn supervised_session_manager(&self) -> &SupervisedAgent {
         &self.session_manager
     }
 
     /// Get the CSRF manager agent handle
     ///
+    /// The handle changes when the agent is restarted; fetch it again
+    /// instead of keeping it, or keep [`Self::supervised_csrf_manager`].
     /// Use this to send CSRF-related messages directly to the agent.
     /// For most use cases, prefer using the `CsrfMiddleware` and extractors.
     ///
@@ -303,7 +307,13 @@ impl ActonHtmxState {
     /// }
     /// ```
     #[must_use]
-    pub const fn csrf_manager(&self) -> &ActorHandle {
+    pub fn csrf_manager(&self) -> ActorHandle {
+        self.csrf_manager.handle()
+    }
+
+    /// Get the CSRF manager agent, followed across restarts
+    #[must_use]
+    pub const fn supervised_csrf_manager(&self) -> &SupervisedAgent {
         &self.csrf_manager
     }
 
@@ -345,10 +355,62 @@ impl ActonHtmxState {
     /// }
     /// ```
     #[must_use]
-    pub const fn job_agent(&self) -> &ActorHandle {
+    pub fn job_agent(&self) -> ActorHandle {
+        self.job_agent.handle()
+    }
+
+    /// Get the job processing agent, followed across restarts
+    #[must_use]
+    pub const fn supervised_job_agent(&self) -> &SupervisedAgent {
         &self.job_agent
     }
 
+    /// Get the supervisor of the session, CSRF and job agents
+    ///
+    /// `None` when `supervision.enabled` is off. Register escalation
+    /// handlers here to hear about agents that could not be restarted.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// if let Some(supervisor) = state.supervisor() {
+    ///     supervisor
+    ///         .on_escalate(|e| tracing::error!(agent = %e.agent, "Agent is down"))
+    ///         .await;
+    /// }
+    /// ```
+    #[must_use]
+    pub const fn supervisor(&self) -> Option<&Supervisor> {
+        self.supervisor.as_ref()
+    }
+
+    /// Get the session manager's bounded mailbox
+    ///
+    /// Unlike sending to [`Self::session_manager`], sending here never
+    /// waits on a flooded agent; see [`Mailbox::send`].
+    #[must_use]
+    pub const fn session_mailbox(&self) -> &Mailbox {
+        &self.session_mailbox
+    }
+
+    /// Get the job agent's bounded mailbox
+    ///
+    /// Unlike sending to [`Self::job_agent`], sending here never waits on a
+    /// flooded agent; see [`Mailbox::send`].
+    #[must_use]
+    pub const fn job_mailbox(&self) -> &Mailbox {
+        &self.job_mailbox
+    }
+
+    /// Queue depth and counters of every agent mailbox
+    ///
+    /// Render them for Prometheus with
+    /// [`render_mailbox_metrics`](crate::htmx::agents::render_mailbox_metrics).
+    #[must_use]
+    pub fn mailbox_stats(&self) -> Vec<MailboxStats> {
+        vec![self.session_mailbox.stats(), self.job_mailbox.stats()]
+    }
+
     /// Get the PostgreSQL database connection pool
     ///
     /// # Panics
@@ -479,6 +541,8 @@ impl ActonHtmxState {
     /// ```
     #[cfg(feature = "redis")]
     pub fn set_redis_pool(&mut self, pool: RedisPool) {
+        self.session_mailbox.set_redis_pool(pool.clone());
+        self.job_mailbox.set_redis_pool(pool.clone());
         self.redis_pool = Some(pool);
     }
 
@@ -510,6 +574,9 @@ impl ActonHtmxState {
 
     /// Set the microservices registry
     ///
+    /// Starts copying session changes to the auth service when
+    /// `session_migration.dual_write` is on.
+    ///
     /// # Example
     ///
     /// ```rust,ignore
@@ -522,6 +589,7 @@ impl ActonHtmxState {
     /// ```
     #[cfg(feature = "microservices")]
     pub fn set_services(&mut self, registry: ServiceRegistry) {
+        self.start_session_dual_write(&registry);
         self.services = Some(registry);
     }
 
@@ -549,10 +617,29 @@ impl ActonHtmxState {
         config: &ServicesConfig,
     ) -> Result<(), crate::htmx::clients::ClientError> {
         let registry = ServiceRegistry::from_config(config).await?;
-        self.services = Some(registry);
+        self.set_services(registry);
         Ok(())
     }
 
+    /// Copy session changes to the auth service if dual-write is on
+    #[cfg(feature = "microservices")]
+    fn start_session_dual_write(&self, registry: &ServiceRegistry) {
+        if !self.config.session_migration.dual_write {
+            return;
+        }
+        match registry.auth() {
+            Ok(auth) => {
+                crate::htmx::auth::session_migration::spawn_dual_write(
+                    self.session_manager(),
+                    auth,
+                );
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Session dual-write needs the auth service");
+            }
+        }
+    }
+
     /// Check if the auth service is available
     ///
     /// Returns true if microservices are configured and auth service is connected.
@@ -626,12 +713,11 @@ impl ActonHtmxState {
     /// }
     /// ```
     pub async fn get_job_metrics(&self) -> Result<super::jobs::agent::JobMetrics, anyhow::Error> {
-        use acton_reactive::prelude::ActorHandleInterface;
         use super::jobs::agent::GetMetricsRequest;
         use std::time::Duration;
 
         let (request, rx) = GetMetricsRequest::new();
-        self.job_agent().send(request).await;
+        self.job_mailbox().send(request).await?;
 
         let timeout = Duration::from_millis(100);
         Ok(tokio::time::timeout(timeout, rx).await??)
@@ -673,18 +759,85 @@ impl ActonHtmxState {
         &self,
         id: super::jobs::JobId,
     ) -> Result<Option<super::jobs::JobStatus>, anyhow::Error> {
-        use acton_reactive::prelude::ActorHandleInterface;
         use super::jobs::agent::GetJobStatusRequest;
         use std::time::Duration;
 
         let (request, rx) = GetJobStatusRequest::new(id);
-        self.job_agent().send(request).await;
+        self.job_mailbox().send(request).await?;
+
+        let timeout = Duration::from_millis(100);
+        Ok(tokio::time::timeout(timeout, rx).await??)
+    }
+
+    /// Get the job agent's drain status with timeout.
+    ///
+    /// Deploy tooling can poll this (through an endpoint of your choosing)
+    /// and stop the process once the status is drained.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Agent doesn't respond within timeout
+    /// - Response channel is closed (agent stopped)
+    pub async fn get_job_drain_status(
+        &self,
+    ) -> Result<super::jobs::agent::DrainStatus, anyhow::Error> {
+        use super::jobs::agent::GetDrainStatusRequest;
+        use std::time::Duration;
+
+        let (request, rx) = GetDrainStatusRequest::new();
+        self.job_mailbox().send(request).await?;
 
         let timeout = Duration::from_millis(100);
         Ok(tokio::time::timeout(timeout, rx).await??)
     }
 }
 
+/// Spawn the session, CSRF and job agents, under a supervisor unless
+/// supervision is disabled
+async fn spawn_agents(
+    runtime: &mut ActorRuntime,
+    config: &ActonHtmxConfig,
+) -> anyhow::Result<(Option<Supervisor>, SupervisedAgent, SupervisedAgent, SupervisedAgent)> {
+    if !config.supervision.enabled {
+        return Ok((
+            None,
+            SupervisedAgent::new("session_manager", SessionManagerAgent::spawn(runtime).await?),
+            SupervisedAgent::new("csrf_manager", CsrfManagerAgent::spawn(runtime).await?),
+            SupervisedAgent::new("job_manager", JobAgent::spawn(runtime).await?),
+        ));
+    }
+
+    let supervisor = Supervisor::spawn(runtime, config.supervision.restart_limits.clone()).await?;
+    let session_manager = supervisor
+        .supervise(runtime, SessionManagerAgent::child_spec())
+        .await?;
+    let csrf_manager = supervisor
+        .supervise(runtime, CsrfManagerAgent::child_spec())
+        .await?;
+    let job_agent = supervisor.supervise(runtime, JobAgent::child_spec()).await?;
+    Ok((Some(supervisor), session_manager, csrf_manager, job_agent))
+}
+
+/// Spawn the mailboxes in front of the session and job agents
+fn spawn_mailboxes(
+    config: &ActonHtmxConfig,
+    session_manager: &SupervisedAgent,
+    job_agent: &SupervisedAgent,
+) -> (Mailbox, Mailbox) {
+    let session_mailbox = Mailbox::spawn(
+        "session_manager",
+        config.mailboxes.session_manager,
+        session_manager.handle(),
+    );
+    session_manager.attach(&session_mailbox);
+    let job_mailbox = Mailbox::spawn("job_agent", config.mailboxes.job_agent, job_agent.handle());
+    #[cfg(feature = "redis")]
+    let job_mailbox = job_mailbox.spillable::<crate::htmx::jobs::agent::EnqueueJob>("enqueue_job");
+    job_agent.attach(&job_mailbox);
+    (session_mailbox, job_mailbox)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
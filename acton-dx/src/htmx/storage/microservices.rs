@@ -2,6 +2,8 @@
 //!
 //! Stores files via the file-service gRPC endpoint instead of local filesystem.
 
+#[cfg(feature = "microservices")]
+use super::presets::CheckedUpload;
 #[cfg(feature = "microservices")]
 use super::traits::FileStorage;
 #[cfg(feature = "microservices")]
@@ -75,16 +77,29 @@ impl MicroservicesFileStorage {
     pub const fn from_client(client: Arc<RwLock<FileClient>>) -> Self {
         Self { client }
     }
-}
 
-#[cfg(feature = "microservices")]
-#[async_trait]
-impl FileStorage for MicroservicesFileStorage {
-    async fn store(&self, file: UploadedFile) -> StorageResult<StoredFile> {
+    /// Store an upload checked against a preset
+    ///
+    /// Sends the preset name and scan verdict as file metadata so
+    /// file-service enforces its policy of the same name.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if file-service rejects or fails the upload.
+    pub async fn store_checked(&self, upload: CheckedUpload) -> StorageResult<StoredFile> {
+        let metadata = upload.metadata();
+        self.upload(upload.file, metadata).await
+    }
+
+    async fn upload(
+        &self,
+        file: UploadedFile,
+        metadata: HashMap<String, String>,
+    ) -> StorageResult<StoredFile> {
         let result = {
-            let mut client = self.client.write();
+            let mut client = self.client.write().await;
             client
-                .upload(&file.filename, &file.content_type, file.data, HashMap::new())
+                .upload(&file.filename, &file.content_type, file.data, metadata)
                 .await
                 .map_err(|e| StorageError::Other(format!("File service error: {e}")))?
         };
@@ -107,10 +122,18 @@ impl FileStorage for MicroservicesFileStorage {
             ))
         }
     }
+}
+
+#[cfg(feature = "microservices")]
+#[async_trait]
+impl FileStorage for MicroservicesFileStorage {
+    async fn store(&self, file: UploadedFile) -> StorageResult<StoredFile> {
+        self.upload(file, HashMap::new()).await
+    }
 
     async fn retrieve(&self, id: &str) -> StorageResult<Vec<u8>> {
         let result = {
-            let mut client = self.client.write();
+            let mut client = self.client.write().await;
             client
                 .download(id)
                 .await
@@ -122,7 +145,7 @@ impl FileStorage for MicroservicesFileStorage {
 
     async fn delete(&self, id: &str) -> StorageResult<()> {
         let success = {
-            let mut client = self.client.write();
+            let mut client = self.client.write().await;
             client
                 .delete(id)
                 .await
@@ -137,7 +160,7 @@ impl FileStorage for MicroservicesFileStorage {
 
     async fn url(&self, id: &str) -> StorageResult<String> {
         let url = {
-            let mut client = self.client.write();
+            let mut client = self.client.write().await;
             client
                 .get_public_url(id)
                 .await
@@ -150,7 +173,7 @@ impl FileStorage for MicroservicesFileStorage {
     async fn exists(&self, id: &str) -> StorageResult<bool> {
         // Try to get metadata - if it succeeds, file exists
         let result = {
-            let mut client = self.client.write();
+            let mut client = self.client.write().await;
             client.get_metadata(id).await
         };
 
@@ -165,7 +188,7 @@ impl FileStorage for MicroservicesFileStorage {
 
     async fn get_metadata(&self, id: &str) -> StorageResult<StoredFile> {
         let info = {
-            let mut client = self.client.write();
+            let mut client = self.client.write().await;
             client
                 .get_metadata(id)
                 .await
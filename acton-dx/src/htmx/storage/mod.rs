@@ -46,6 +46,7 @@ mod local;
 #[cfg(feature = "microservices")]
 mod microservices;
 pub mod policy;
+pub mod presets;
 pub mod processing;
 pub mod scanning;
 mod traits;
@@ -56,6 +57,7 @@ pub use local::LocalFileStorage;
 #[cfg(feature = "microservices")]
 pub use microservices::MicroservicesFileStorage;
 pub use policy::{PolicyBuilder, UploadPolicy};
+pub use presets::{CheckedUpload, UploadPreset, UploadPresets};
 pub use processing::ImageProcessor;
 pub use scanning::{ClamAvScanner, NoOpScanner, QuarantineScanner, ScanResult, VirusScanner};
 #[cfg(feature = "clamav")]
@@ -0,0 +1,528 @@
+//! Named upload presets per upload context
+//!
+//! A preset bundles everything an upload context needs in one place: a size
+//! limit, a MIME allowlist, whether the content must match its declared type,
+//! whether a virus scan is required, and how images are normalized. Handlers
+//! select a preset by name (or through the `PresetUpload` extractor) instead
+//! of repeating limits and checks inline.
+//!
+//! The built-in presets are [`UploadPreset::avatar`],
+//! [`UploadPreset::document`] and [`UploadPreset::import_csv`]. Override or
+//! add presets by registering an [`UploadPresets`] set as a request
+//! extension.
+//!
+//! Checked uploads carry the preset name and scan verdict in their
+//! [`CheckedUpload::metadata`], which file-service uses to apply the policy
+//! of the same name on its side.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use acton_htmx::extractors::PresetUpload;
+//! use acton_htmx::storage::presets::{Avatar, UploadPreset, UploadPresets};
+//!
+//! let presets = UploadPresets::new()
+//!     .with_scanner(Arc::new(ClamAvScanner::default_tcp()))
+//!     .with_preset(UploadPreset::document().with_max_file_size(50 * 1024 * 1024));
+//!
+//! async fn upload_avatar(
+//!     State(storage): State<MicroservicesFileStorage>,
+//!     PresetUpload(upload, ..): PresetUpload<Avatar>,
+//! ) -> Result<impl IntoResponse, StorageError> {
+//!     let stored = storage.store_checked(upload).await?;
+//!     Ok(stored.id)
+//! }
+//!
+//! let app = Router::new()
+//!     .route("/avatar", post(upload_avatar))
+//!     .layer(Extension(presets));
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::policy::UploadPolicy;
+use super::processing::ImageProcessor;
+use super::scanning::{ScanResult, VirusScanner};
+use super::types::{StorageError, StorageResult, UploadedFile};
+use super::validation::MimeValidator;
+
+/// Upload metadata key naming the preset a file was checked against
+pub const UPLOAD_POLICY_METADATA_KEY: &str = "upload_policy";
+
+/// Upload metadata key recording the virus scan verdict (`clean`)
+pub const SCAN_METADATA_KEY: &str = "scan";
+
+/// Image normalization applied to image uploads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePreset {
+    /// Maximum width; larger images are scaled down to fit
+    pub max_width: u32,
+    /// Maximum height; larger images are scaled down to fit
+    pub max_height: u32,
+    /// Remove EXIF metadata (location, camera details)
+    pub strip_exif: bool,
+    /// Re-encode to this MIME type, e.g. `image/png`
+    pub format: Option<String>,
+}
+
+impl ImagePreset {
+    /// Scale images down to fit `max_width` x `max_height` and strip EXIF
+    #[must_use]
+    pub const fn fit(max_width: u32, max_height: u32) -> Self {
+        Self {
+            max_width,
+            max_height,
+            strip_exif: true,
+            format: None,
+        }
+    }
+
+    /// Re-encode images to `mime_type`
+    #[must_use]
+    pub fn with_format(mut self, mime_type: impl Into<String>) -> Self {
+        self.format = Some(mime_type.into());
+        self
+    }
+
+    /// Keep EXIF metadata
+    #[must_use]
+    pub const fn keep_exif(mut self) -> Self {
+        self.strip_exif = false;
+        self
+    }
+
+    fn apply(&self, file: UploadedFile) -> StorageResult<UploadedFile> {
+        let processor = ImageProcessor::new();
+        let mut file = if self.strip_exif {
+            processor.strip_exif(&file)?
+        } else {
+            file
+        };
+        let (width, height) = processor.get_dimensions(&file)?;
+        if width > self.max_width || height > self.max_height {
+            let filename = file.filename.clone();
+            file = processor.generate_thumbnail(&file, self.max_width, self.max_height)?;
+            file.filename = filename;
+        }
+        match &self.format {
+            Some(format) if *format != file.content_type => processor.convert_format(&file, format),
+            _ => Ok(file),
+        }
+    }
+}
+
+/// A named upload policy for one upload context
+#[derive(Debug, Clone)]
+pub struct UploadPreset {
+    name: String,
+    max_file_size: u64,
+    allowed_mime_types: Vec<String>,
+    verify_content: bool,
+    require_scan: bool,
+    image: Option<ImagePreset>,
+}
+
+impl UploadPreset {
+    /// A preset accepting any type up to `max_file_size` bytes
+    #[must_use]
+    pub fn new(name: impl Into<String>, max_file_size: u64) -> Self {
+        Self {
+            name: name.into(),
+            max_file_size,
+            allowed_mime_types: Vec::new(),
+            verify_content: false,
+            require_scan: false,
+            image: None,
+        }
+    }
+
+    /// Profile pictures: 2MB JPEG/PNG/WebP/GIF, content-checked, scanned,
+    /// scaled to fit 512x512 without EXIF
+    #[must_use]
+    pub fn avatar() -> Self {
+        Self::new(Avatar::NAME, 2 * 1024 * 1024)
+            .with_mime_types(["image/jpeg", "image/png", "image/webp", "image/gif"])
+            .with_content_check()
+            .with_scan_required()
+            .with_image(ImagePreset::fit(512, 512))
+    }
+
+    /// Office documents and PDFs: 25MB, content-checked, scanned
+    #[must_use]
+    pub fn document() -> Self {
+        Self::new(Document::NAME, 25 * 1024 * 1024)
+            .with_mime_types([
+                "application/pdf",
+                "application/msword",
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+                "application/vnd.ms-excel",
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                "text/plain",
+            ])
+            .with_content_check()
+            .with_scan_required()
+    }
+
+    /// CSV imports: 5MB of text
+    ///
+    /// Includes `application/vnd.ms-excel`, which some browsers send for
+    /// `.csv` files.
+    #[must_use]
+    pub fn import_csv() -> Self {
+        Self::new(ImportCsv::NAME, 5 * 1024 * 1024).with_mime_types([
+            "text/csv",
+            "text/plain",
+            "application/vnd.ms-excel",
+        ])
+    }
+
+    /// Set the size limit in bytes
+    #[must_use]
+    pub const fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Restrict uploads to these MIME types
+    #[must_use]
+    pub fn with_mime_types<I, S>(mut self, types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_mime_types = types.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Check file content against the allowlist, not just the declared type
+    #[must_use]
+    pub const fn with_content_check(mut self) -> Self {
+        self.verify_content = true;
+        self
+    }
+
+    /// Reject uploads that were not scanned clean
+    #[must_use]
+    pub const fn with_scan_required(mut self) -> Self {
+        self.require_scan = true;
+        self
+    }
+
+    /// Normalize image uploads
+    #[must_use]
+    pub fn with_image(mut self, image: ImagePreset) -> Self {
+        self.image = Some(image);
+        self
+    }
+
+    /// Preset name
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Size limit in bytes
+    #[must_use]
+    pub const fn max_file_size(&self) -> u64 {
+        self.max_file_size
+    }
+
+    /// Allowed MIME types (empty = any)
+    #[must_use]
+    pub fn allowed_mime_types(&self) -> &[String] {
+        &self.allowed_mime_types
+    }
+
+    /// Whether uploads must be scanned clean
+    #[must_use]
+    pub const fn requires_scan(&self) -> bool {
+        self.require_scan
+    }
+
+    /// The size and type limits as an [`UploadPolicy`]
+    #[must_use]
+    pub fn policy(&self) -> UploadPolicy {
+        let builder = UploadPolicy::builder().max_file_size(self.max_file_size);
+        if self.allowed_mime_types.is_empty() {
+            builder.build()
+        } else {
+            builder
+                .allowed_mime_types(self.allowed_mime_types.clone())
+                .build()
+        }
+    }
+
+    /// Check and normalize an upload
+    ///
+    /// Applies the size and type limits, the content check, the virus scan
+    /// (whenever a scanner is given) and image normalization, in that order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StorageError::FileSizeExceeded`] or
+    /// [`StorageError::InvalidMimeType`] for files outside the limits, and
+    /// [`StorageError::Other`] for infected files, failed scans, a required
+    /// scan without a scanner, or failed image processing.
+    pub async fn check(
+        &self,
+        file: UploadedFile,
+        scanner: Option<&dyn VirusScanner>,
+    ) -> StorageResult<CheckedUpload> {
+        self.policy()
+            .allows_upload(file.size(), &file.content_type, 0)?;
+
+        if self.verify_content && !self.allowed_mime_types.is_empty() {
+            let allowed: Vec<&str> = self.allowed_mime_types.iter().map(String::as_str).collect();
+            MimeValidator::permissive().validate_against_magic(&file, &allowed)?;
+        }
+
+        let clean = match scanner {
+            Some(scanner) => match scanner.scan(&file).await? {
+                ScanResult::Clean => true,
+                ScanResult::Infected { threat } => {
+                    return Err(StorageError::Other(format!(
+                        "Upload rejected: threat detected ({threat})"
+                    )));
+                }
+                ScanResult::Error { message } => {
+                    return Err(StorageError::Other(format!("Virus scan failed: {message}")));
+                }
+            },
+            None if self.require_scan => {
+                return Err(StorageError::Other(format!(
+                    "{} uploads require a virus scanner",
+                    self.name
+                )));
+            }
+            None => false,
+        };
+
+        let file = match &self.image {
+            Some(image) if file.content_type.starts_with("image/") => image.apply(file)?,
+            _ => file,
+        };
+
+        Ok(CheckedUpload {
+            file,
+            preset: self.name.clone(),
+            scanned: clean,
+        })
+    }
+}
+
+/// An upload that passed a preset's checks
+#[derive(Debug, Clone)]
+pub struct CheckedUpload {
+    /// The file, after image normalization
+    pub file: UploadedFile,
+    /// Name of the preset it was checked against
+    pub preset: String,
+    /// Whether a virus scan found it clean
+    pub scanned: bool,
+}
+
+impl CheckedUpload {
+    /// Metadata to store with the file
+    ///
+    /// Records the preset under [`UPLOAD_POLICY_METADATA_KEY`] and, for
+    /// scanned files, `clean` under [`SCAN_METADATA_KEY`].
+    #[must_use]
+    pub fn metadata(&self) -> HashMap<String, String> {
+        let mut metadata =
+            HashMap::from([(UPLOAD_POLICY_METADATA_KEY.to_string(), self.preset.clone())]);
+        if self.scanned {
+            metadata.insert(SCAN_METADATA_KEY.to_string(), "clean".to_string());
+        }
+        metadata
+    }
+}
+
+/// The presets and scanner available to upload handlers
+///
+/// Starts with the built-in presets. Register it with
+/// `.layer(Extension(presets))` for the `PresetUpload` extractor to use.
+#[derive(Clone)]
+pub struct UploadPresets {
+    presets: HashMap<String, UploadPreset>,
+    scanner: Option<Arc<dyn VirusScanner>>,
+}
+
+impl std::fmt::Debug for UploadPresets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadPresets")
+            .field("presets", &self.presets)
+            .field("scanner", &self.scanner.as_ref().map(|s| s.name()))
+            .finish()
+    }
+}
+
+impl Default for UploadPresets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UploadPresets {
+    /// The built-in presets, without a scanner
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            presets: [
+                UploadPreset::avatar(),
+                UploadPreset::document(),
+                UploadPreset::import_csv(),
+            ]
+            .into_iter()
+            .map(|preset| (preset.name.clone(), preset))
+            .collect(),
+            scanner: None,
+        }
+    }
+
+    /// Add a preset, replacing any preset with the same name
+    #[must_use]
+    pub fn with_preset(mut self, preset: UploadPreset) -> Self {
+        self.presets.insert(preset.name.clone(), preset);
+        self
+    }
+
+    /// Scan uploads with `scanner`
+    #[must_use]
+    pub fn with_scanner(mut self, scanner: Arc<dyn VirusScanner>) -> Self {
+        self.scanner = Some(scanner);
+        self
+    }
+
+    /// Preset by name
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&UploadPreset> {
+        self.presets.get(name)
+    }
+
+    /// The configured scanner
+    #[must_use]
+    pub fn scanner(&self) -> Option<&dyn VirusScanner> {
+        self.scanner.as_deref()
+    }
+
+    /// Check an upload against the named preset
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StorageError::Other`] for unknown presets, or any error
+    /// from [`UploadPreset::check`].
+    pub async fn check(&self, name: &str, file: UploadedFile) -> StorageResult<CheckedUpload> {
+        let preset = self
+            .get(name)
+            .ok_or_else(|| StorageError::Other(format!("Unknown upload preset: {name}")))?;
+        preset.check(file, self.scanner()).await
+    }
+}
+
+/// Selects a preset by type for the `PresetUpload` extractor
+pub trait NamedPreset: Send + Sync + 'static {
+    /// Preset name
+    const NAME: &'static str;
+
+    /// The preset used when none is registered under [`Self::NAME`]
+    fn preset() -> UploadPreset;
+}
+
+/// The [`UploadPreset::avatar`] preset
+#[derive(Debug, Clone, Copy)]
+pub struct Avatar;
+
+impl NamedPreset for Avatar {
+    const NAME: &'static str = "avatar";
+
+    fn preset() -> UploadPreset {
+        UploadPreset::avatar()
+    }
+}
+
+/// The [`UploadPreset::document`] preset
+#[derive(Debug, Clone, Copy)]
+pub struct Document;
+
+impl NamedPreset for Document {
+    const NAME: &'static str = "document";
+
+    fn preset() -> UploadPreset {
+        UploadPreset::document()
+    }
+}
+
+/// The [`UploadPreset::import_csv`] preset
+#[derive(Debug, Clone, Copy)]
+pub struct ImportCsv;
+
+impl NamedPreset for ImportCsv {
+    const NAME: &'static str = "import-csv";
+
+    fn preset() -> UploadPreset {
+        UploadPreset::import_csv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::storage::scanning::MockVirusScanner;
+
+    fn png(width: u32, height: u32) -> UploadedFile {
+        let image = image::RgbImage::new(width, height);
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        UploadedFile::new("me.png", "image/png", data.into_inner())
+    }
+
+    fn scanner(result: ScanResult) -> MockVirusScanner {
+        let mut scanner = MockVirusScanner::new();
+        scanner.expect_scan().returning(move |_| Ok(result.clone()));
+        scanner
+    }
+
+    #[tokio::test]
+    async fn test_avatar_preset() {
+        let preset = UploadPreset::avatar();
+        let clean = scanner(ScanResult::Clean);
+
+        let upload = preset.check(png(1024, 512), Some(&clean)).await.unwrap();
+        assert_eq!(upload.file.filename, "me.png");
+        let dimensions = ImageProcessor::new().get_dimensions(&upload.file).unwrap();
+        assert_eq!(dimensions, (512, 256));
+        assert_eq!(upload.metadata()[SCAN_METADATA_KEY], "clean");
+        assert_eq!(upload.metadata()[UPLOAD_POLICY_METADATA_KEY], "avatar");
+
+        // Declared as an image but not one
+        let fake = UploadedFile::new("me.png", "image/png", b"%PDF-1.4 ...".to_vec());
+        assert!(matches!(
+            preset.check(fake, Some(&clean)).await,
+            Err(StorageError::InvalidMimeType { .. })
+        ));
+        // Scanning is required
+        assert!(preset.check(png(8, 8), None).await.is_err());
+        let infected = scanner(ScanResult::Infected {
+            threat: "Eicar".to_string(),
+        });
+        assert!(preset.check(png(8, 8), Some(&infected)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registered_presets() {
+        let presets =
+            UploadPresets::new().with_preset(UploadPreset::import_csv().with_max_file_size(4));
+        let csv = |data: &[u8]| UploadedFile::new("rows.csv", "text/csv", data.to_vec());
+
+        let upload = presets.check(ImportCsv::NAME, csv(b"a,b")).await.unwrap();
+        assert!(!upload.scanned);
+        assert!(!upload.metadata().contains_key(SCAN_METADATA_KEY));
+        assert!(matches!(
+            presets.check(ImportCsv::NAME, csv(b"a,b,c")).await,
+            Err(StorageError::FileSizeExceeded { .. })
+        ));
+        assert!(presets.check("unknown", csv(b"a")).await.is_err());
+        assert!(presets.get(Document::NAME).unwrap().requires_scan());
+    }
+}
@@ -359,20 +359,7 @@ const fn format_extension(format: ImageFormat) -> &'static str {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use image::{ImageBuffer, Rgb};
-
-    /// Helper to create a test PNG image
-    fn create_test_png(width: u32, height: u32) -> Vec<u8> {
-        let img: ImageBuffer<Rgb<u8>, Vec<u8>> = ImageBuffer::from_fn(width, height, |_, _| {
-            Rgb([255, 0, 0]) // Red pixel
-        });
-
-        let mut buffer = Vec::new();
-        DynamicImage::ImageRgb8(img)
-            .write_to(&mut Cursor::new(&mut buffer), ImageFormat::Png)
-            .unwrap();
-        buffer
-    }
+    use crate::htmx::testing::fixtures::png_bytes as create_test_png;
 
     #[test]
     fn test_get_dimensions() {
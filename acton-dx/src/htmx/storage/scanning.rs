@@ -688,6 +688,7 @@ impl<S: VirusScanner> QuarantineScanner<S> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::htmx::testing::fixtures::{eicar_upload, EICAR_TEST_STRING};
 
     #[tokio::test]
     async fn test_noop_scanner_always_clean() {
@@ -849,11 +850,7 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         let quarantine_path = temp_dir.path().to_path_buf();
 
-        let file = UploadedFile::new(
-            "malware.exe",
-            "application/octet-stream",
-            b"EICAR test file".to_vec(),
-        );
+        let file = eicar_upload();
 
         let scanner = QuarantineScanner::new(
             MockInfectedScanner::new("EICAR.Test.Signature"),
@@ -886,9 +883,9 @@ mod tests {
         let metadata: QuarantineMetadata = serde_json::from_str(&metadata_json).unwrap();
 
         assert_eq!(metadata.threat_name, "EICAR.Test.Signature");
-        assert_eq!(metadata.original_filename, "malware.exe");
+        assert_eq!(metadata.original_filename, "eicar.com");
         assert_eq!(metadata.original_mime_type, "application/octet-stream");
-        assert_eq!(metadata.file_size, b"EICAR test file".len());
+        assert_eq!(metadata.file_size, EICAR_TEST_STRING.len());
 
         // Verify file data was written
         let data_file = entries
@@ -896,7 +893,7 @@ mod tests {
             .find(|e| e.path().extension().is_none())
             .expect("Should have quarantine data file");
         let quarantined_data = std::fs::read(data_file.path()).unwrap();
-        assert_eq!(quarantined_data, b"EICAR test file");
+        assert_eq!(quarantined_data, EICAR_TEST_STRING.as_bytes());
     }
 
     #[tokio::test]
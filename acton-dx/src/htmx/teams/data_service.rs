@@ -0,0 +1,332 @@
+//! Team store and team-scoped queries backed by data-service
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{CurrentTeam, Team, TeamError, TeamScope, TeamStore};
+use crate::htmx::clients::{DataClient, Row, Value};
+use crate::htmx::util::validate_identifier;
+use acton_dx_proto::data::v1::value::Value as ValueKind;
+
+/// Migration creating the teams table
+///
+/// Memberships live in `team_memberships` from `INVITATIONS_MIGRATION`.
+pub const TEAMS_MIGRATION: &str = r"CREATE TABLE IF NOT EXISTS teams (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_by BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);";
+
+/// Team store persisting to data-service
+///
+/// Apply [`TEAMS_MIGRATION`] first.
+#[derive(Debug, Clone)]
+pub struct DataServiceTeamStore {
+    client: Arc<RwLock<DataClient>>,
+}
+
+impl DataServiceTeamStore {
+    /// Create a store
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<DataClient>>) -> Self {
+        Self { client }
+    }
+
+    async fn execute(&self, sql: &str, params: Vec<Value>) -> Result<i64, TeamError> {
+        let result = self
+            .client
+            .write()
+            .await
+            .execute(sql, params, None)
+            .await
+            .map_err(|e| TeamError::Store(e.to_string()))?;
+        Ok(result.rows_affected)
+    }
+}
+
+#[async_trait]
+impl TeamStore for DataServiceTeamStore {
+    async fn insert(&self, team: &Team) -> Result<(), TeamError> {
+        self.execute(
+            "INSERT INTO teams (id, name, created_by, created_at) VALUES ($1, $2, $3, $4)",
+            team_params(team),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Team>, TeamError> {
+        let row = self
+            .client
+            .write()
+            .await
+            .query_one(
+                "SELECT id, name, created_by, created_at FROM teams WHERE id = $1",
+                vec![text(id)],
+                None,
+            )
+            .await
+            .map_err(|e| TeamError::Store(e.to_string()))?;
+        row.as_ref().map(team_from_row).transpose()
+    }
+
+    async fn update(&self, team: &Team) -> Result<(), TeamError> {
+        self.execute(
+            "UPDATE teams SET name = $1 WHERE id = $2",
+            vec![text(&team.name), text(&team.id)],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<bool, TeamError> {
+        Ok(self
+            .execute("DELETE FROM teams WHERE id = $1", vec![text(id)])
+            .await?
+            > 0)
+    }
+}
+
+/// Data-service access limited to one team's rows
+///
+/// Every statement gets the team condition from [`TeamScope`], and inserts
+/// set the team column, so rows of other teams are neither read nor written.
+/// Table and column names must be trusted identifiers; values are always
+/// bound as parameters.
+#[derive(Debug, Clone)]
+pub struct TeamScopedData {
+    client: Arc<RwLock<DataClient>>,
+    scope: TeamScope,
+}
+
+impl TeamScopedData {
+    /// Scope `client` to `scope`
+    #[must_use]
+    pub const fn new(client: Arc<RwLock<DataClient>>, scope: TeamScope) -> Self {
+        Self { client, scope }
+    }
+
+    /// Scope `client` to the request's team
+    #[must_use]
+    pub fn for_team(client: Arc<RwLock<DataClient>>, team: &CurrentTeam) -> Self {
+        Self::new(client, team.scope())
+    }
+
+    /// The applied scope
+    #[must_use]
+    pub const fn scope(&self) -> &TeamScope {
+        &self.scope
+    }
+
+    /// `SELECT * FROM table` for the team's rows matching `filter`
+    ///
+    /// `filter` may be empty and may use `$1..$n` for `params`; append
+    /// `ORDER BY`/`LIMIT` through `suffix`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Invalid`] for a bad table name, or
+    /// [`TeamError::Store`] if the query fails.
+    pub async fn select(
+        &self,
+        table: &str,
+        filter: &str,
+        params: Vec<Value>,
+        suffix: &str,
+    ) -> Result<Vec<Row>, TeamError> {
+        validate_identifier(table).map_err(TeamError::Invalid)?;
+        let (sql, params) = self.scoped(&format!("SELECT * FROM {table}"), filter, params);
+        let sql = if suffix.is_empty() {
+            sql
+        } else {
+            format!("{sql} {suffix}")
+        };
+        self.client
+            .write()
+            .await
+            .query(&sql, params, None)
+            .await
+            .map_err(|e| TeamError::Store(e.to_string()))
+    }
+
+    /// Insert a row owned by the team
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Invalid`] for bad identifiers or a caller-supplied
+    /// team column, or [`TeamError::Store`] if the statement fails.
+    pub async fn insert(&self, table: &str, columns: Vec<(&str, Value)>) -> Result<i64, TeamError> {
+        let (sql, params) = self.insert_statement(table, columns)?;
+        self.execute(&sql, params).await
+    }
+
+    /// Update the team's rows matching `filter`
+    ///
+    /// `assignments` is a `SET` list using `$1..$n` for the leading `params`;
+    /// `filter` continues the numbering.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Invalid`] for a bad table name, or
+    /// [`TeamError::Store`] if the statement fails.
+    pub async fn update(
+        &self,
+        table: &str,
+        assignments: &str,
+        filter: &str,
+        params: Vec<Value>,
+    ) -> Result<i64, TeamError> {
+        validate_identifier(table).map_err(TeamError::Invalid)?;
+        let (sql, params) =
+            self.scoped(&format!("UPDATE {table} SET {assignments}"), filter, params);
+        self.execute(&sql, params).await
+    }
+
+    /// Delete the team's rows matching `filter`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Invalid`] for a bad table name, or
+    /// [`TeamError::Store`] if the statement fails.
+    pub async fn delete(
+        &self,
+        table: &str,
+        filter: &str,
+        params: Vec<Value>,
+    ) -> Result<i64, TeamError> {
+        validate_identifier(table).map_err(TeamError::Invalid)?;
+        let (sql, params) = self.scoped(&format!("DELETE FROM {table}"), filter, params);
+        self.execute(&sql, params).await
+    }
+
+    async fn execute(&self, sql: &str, params: Vec<Value>) -> Result<i64, TeamError> {
+        let result = self
+            .client
+            .write()
+            .await
+            .execute(sql, params, None)
+            .await
+            .map_err(|e| TeamError::Store(e.to_string()))?;
+        Ok(result.rows_affected)
+    }
+
+    fn scoped(
+        &self,
+        statement: &str,
+        filter: &str,
+        mut params: Vec<Value>,
+    ) -> (String, Vec<Value>) {
+        let sql = format!(
+            "{statement} {}",
+            self.scope.where_clause(filter, params.len())
+        );
+        params.push(text(self.scope.team_id()));
+        (sql, params)
+    }
+
+    fn insert_statement(
+        &self,
+        table: &str,
+        columns: Vec<(&str, Value)>,
+    ) -> Result<(String, Vec<Value>), TeamError> {
+        validate_identifier(table).map_err(TeamError::Invalid)?;
+        let mut names = Vec::with_capacity(columns.len() + 1);
+        let mut params = Vec::with_capacity(columns.len() + 1);
+        for (name, value) in columns {
+            validate_identifier(name).map_err(TeamError::Invalid)?;
+            if name == self.scope.column() {
+                return Err(TeamError::Invalid(format!(
+                    "{name} is set from the team scope"
+                )));
+            }
+            names.push(name.to_string());
+            params.push(value);
+        }
+        names.push(self.scope.column().to_string());
+        params.push(text(self.scope.team_id()));
+        let placeholders = (1..=params.len())
+            .map(|i| format!("${i}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "INSERT INTO {table} ({}) VALUES ({placeholders})",
+            names.join(", ")
+        );
+        Ok((sql, params))
+    }
+}
+
+fn team_params(team: &Team) -> Vec<Value> {
+    vec![
+        text(&team.id),
+        text(&team.name),
+        int(team.created_by),
+        int(i64::try_from(team.created_at).unwrap_or(i64::MAX)),
+    ]
+}
+
+fn team_from_row(row: &Row) -> Result<Team, TeamError> {
+    let created_at = column_int(row, "created_at")?;
+    Ok(Team {
+        id: column_text(row, "id")?,
+        name: column_text(row, "name")?,
+        created_by: column_int(row, "created_by")?,
+        created_at: u64::try_from(created_at)
+            .map_err(|_| TeamError::Store("negative created_at".to_string()))?,
+    })
+}
+
+fn column_int(row: &Row, name: &str) -> Result<i64, TeamError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::IntValue(v)) => Ok(*v),
+        other => Err(TeamError::Store(format!(
+            "expected integer {name}, got {other:?}"
+        ))),
+    }
+}
+
+fn column_text(row: &Row, name: &str) -> Result<String, TeamError> {
+    match row.columns.get(name).and_then(|v| v.value.as_ref()) {
+        Some(ValueKind::StringValue(v)) => Ok(v.clone()),
+        other => Err(TeamError::Store(format!(
+            "expected text {name}, got {other:?}"
+        ))),
+    }
+}
+
+const fn int(value: i64) -> Value {
+    Value {
+        value: Some(ValueKind::IntValue(value)),
+    }
+}
+
+fn text(value: &str) -> Value {
+    Value {
+        value: Some(ValueKind::StringValue(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_team_row_round_trip() {
+        let team = Team {
+            id: "acme-1a2b3c4d".to_string(),
+            name: "Acme".to_string(),
+            created_by: 7,
+            created_at: 100,
+        };
+        let row = Row {
+            columns: ["id", "name", "created_by", "created_at"]
+                .into_iter()
+                .map(str::to_string)
+                .zip(team_params(&team))
+                .collect(),
+        };
+        assert_eq!(team_from_row(&row).unwrap(), team);
+    }
+}
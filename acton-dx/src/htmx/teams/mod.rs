@@ -0,0 +1,736 @@
+//! Teams (organizations) with per-team resource scoping
+//!
+//! A team owns data: rows carry a `team_id` column and every query made on a
+//! user's behalf is limited to the team they are working in. The active team
+//! lives in the session under [`TEAM_SESSION_KEY`], the same key Cedar's
+//! request context reads as the tenant, so policies see it too.
+//!
+//! - [`Teams`] creates, renames and deletes teams in a [`TeamStore`]
+//!   (in-memory, or data-service with `microservices`); membership and roles
+//!   are managed by [`Invitations`]
+//! - [`Teams::middleware`] checks the session's team against the user's
+//!   memberships and exposes it through the [`CurrentTeam`] extractor
+//! - [`TeamScope`] adds the team condition to SQL; with `microservices`,
+//!   `TeamScopedData` applies it to every data-service call
+//! - [`Teams::routes`] serves JSON CRUD endpoints and team switching
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_htmx::teams::{CurrentTeam, MemoryTeamStore, TeamError, TeamScopedData, Teams};
+//! use axum::middleware::from_fn_with_state;
+//!
+//! let teams = Teams::new(Arc::new(MemoryTeamStore::new()), invitations.clone());
+//!
+//! async fn orders(team: CurrentTeam, State(state): State<AppState>) -> Result<Response, TeamError> {
+//!     let data = TeamScopedData::for_team(state.data(), &team);
+//!     let rows = data.select("orders", "status = $1", vec![open], "ORDER BY id").await?;
+//!     // ...
+//! }
+//!
+//! let app = Router::new()
+//!     .route("/orders", get(orders))
+//!     .merge(teams.routes())
+//!     .merge(invitations.routes())
+//!     .layer(from_fn_with_state(teams.clone(), Teams::middleware));
+//! ```
+
+#[cfg(feature = "microservices")]
+mod data_service;
+
+#[cfg(feature = "microservices")]
+pub use data_service::{DataServiceTeamStore, TeamScopedData, TEAMS_MIGRATION};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Path, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Form, Json, Router,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::htmx::auth::session::SessionData;
+use crate::htmx::invitations::{InvitationError, Invitations, Membership, OWNER_ROLE};
+use crate::htmx::slug::slugify_with_max;
+use crate::htmx::util::unix_now;
+use crate::htmx::util::validate_identifier;
+
+/// Session key holding the active team ID
+///
+/// Matches the tenant key read by Cedar's request context.
+pub const TEAM_SESSION_KEY: &str = "tenant_id";
+
+/// Default column holding the owning team in scoped tables
+pub const DEFAULT_TEAM_COLUMN: &str = "team_id";
+
+/// Team errors
+#[derive(Debug, thiserror::Error)]
+pub enum TeamError {
+    /// No team with the given ID
+    #[error("Team not found")]
+    NotFound,
+
+    /// Request has no active team
+    #[error("No team selected")]
+    NoTeamSelected,
+
+    /// Caller is not a member of the team, or lacks the role for the action
+    #[error("Not permitted for this team")]
+    Forbidden,
+
+    /// Request has no authenticated user
+    #[error("Sign in to continue")]
+    Unauthenticated,
+
+    /// Input was rejected
+    #[error("Invalid input: {0}")]
+    Invalid(String),
+
+    /// Membership lookup or change failed
+    #[error(transparent)]
+    Membership(#[from] InvitationError),
+
+    /// Backing store failed
+    #[error("Team store error: {0}")]
+    Store(String),
+}
+
+impl IntoResponse for TeamError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::NoTeamSelected => StatusCode::CONFLICT,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Self::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Membership(e) => return e.into_response(),
+            Self::Store(ref e) => {
+                warn!(error = %e, "Team store failed");
+                return StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// A team (organization)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Team {
+    /// Stable identifier (`acme-3f9a1c2b`), used as the scoping key
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// User who created the team
+    pub created_by: i64,
+    /// Unix time (seconds) the team was created
+    pub created_at: u64,
+}
+
+/// Storage for team records
+#[async_trait]
+pub trait TeamStore: Send + Sync {
+    /// Store a new team
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Store`] if the store cannot be written.
+    async fn insert(&self, team: &Team) -> Result<(), TeamError>;
+
+    /// Look up a team
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Store`] if the store cannot be read.
+    async fn get(&self, id: &str) -> Result<Option<Team>, TeamError>;
+
+    /// Replace a stored team
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Store`] if the store cannot be written.
+    async fn update(&self, team: &Team) -> Result<(), TeamError>;
+
+    /// Delete a team, returning whether it existed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Store`] if the store cannot be written.
+    async fn delete(&self, id: &str) -> Result<bool, TeamError>;
+}
+
+/// In-process team store
+#[derive(Debug, Default)]
+pub struct MemoryTeamStore {
+    teams: Mutex<HashMap<String, Team>>,
+}
+
+impl MemoryTeamStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TeamStore for MemoryTeamStore {
+    async fn insert(&self, team: &Team) -> Result<(), TeamError> {
+        self.teams.lock().insert(team.id.clone(), team.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Team>, TeamError> {
+        Ok(self.teams.lock().get(id).cloned())
+    }
+
+    async fn update(&self, team: &Team) -> Result<(), TeamError> {
+        self.insert(team).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<bool, TeamError> {
+        Ok(self.teams.lock().remove(id).is_some())
+    }
+}
+
+/// A team together with the caller's membership
+#[derive(Debug, Clone, Serialize)]
+pub struct TeamMembership {
+    /// The team
+    #[serde(flatten)]
+    pub team: Team,
+    /// Caller's role in the team
+    pub role: String,
+}
+
+/// Team manager
+#[derive(Clone)]
+pub struct Teams {
+    store: Arc<dyn TeamStore>,
+    invitations: Invitations,
+    manager_roles: Arc<Vec<String>>,
+}
+
+impl std::fmt::Debug for Teams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Teams")
+            .field("manager_roles", &self.manager_roles)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Teams {
+    /// Create a manager backed by `store`, with memberships in `invitations`
+    ///
+    /// `owner` and `admin` may rename a team; only owners may delete it.
+    #[must_use]
+    pub fn new(store: Arc<dyn TeamStore>, invitations: Invitations) -> Self {
+        Self {
+            store,
+            invitations,
+            manager_roles: Arc::new(vec![OWNER_ROLE.to_string(), "admin".to_string()]),
+        }
+    }
+
+    /// Set the team roles allowed to rename a team
+    #[must_use]
+    pub fn with_manager_roles<I, R>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: Into<String>,
+    {
+        self.manager_roles = Arc::new(roles.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Create a team owned by `user_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Invalid`] for a blank name, or a store error.
+    pub async fn create(&self, user_id: i64, name: &str) -> Result<Team, TeamError> {
+        let name = validate_name(name)?;
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        let base = slugify_with_max(&name, 40);
+        let id = if base.is_empty() {
+            suffix[..12].to_string()
+        } else {
+            format!("{base}-{}", &suffix[..8])
+        };
+        let team = Team {
+            id,
+            name,
+            created_by: user_id,
+            created_at: unix_now(),
+        };
+        self.store.insert(&team).await?;
+        self.invitations.add_owner(&team.id, user_id).await?;
+        Ok(team)
+    }
+
+    /// Look up a team
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::NotFound`] for unknown teams.
+    pub async fn get(&self, id: &str) -> Result<Team, TeamError> {
+        self.store.get(id).await?.ok_or(TeamError::NotFound)
+    }
+
+    /// Rename a team
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::NotFound`] or [`TeamError::Invalid`].
+    pub async fn rename(&self, id: &str, name: &str) -> Result<Team, TeamError> {
+        let mut team = self.get(id).await?;
+        team.name = validate_name(name)?;
+        self.store.update(&team).await?;
+        Ok(team)
+    }
+
+    /// Delete a team and all its memberships
+    ///
+    /// Team-scoped rows are left to the application; delete them first.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::NotFound`] for unknown teams.
+    pub async fn delete(&self, id: &str) -> Result<(), TeamError> {
+        if !self.store.delete(id).await? {
+            return Err(TeamError::NotFound);
+        }
+        self.invitations.disband(id).await?;
+        Ok(())
+    }
+
+    /// Teams `user_id` belongs to, with their role in each
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either store cannot be read.
+    pub async fn teams_for(&self, user_id: i64) -> Result<Vec<TeamMembership>, TeamError> {
+        let mut teams = Vec::new();
+        for membership in self.invitations.memberships_of(user_id).await? {
+            if let Some(team) = self.store.get(&membership.team).await? {
+                teams.push(TeamMembership {
+                    team,
+                    role: membership.role,
+                });
+            }
+        }
+        Ok(teams)
+    }
+
+    /// `user_id`'s membership in `team`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Forbidden`] for non-members.
+    pub async fn require_member(&self, team: &str, user_id: i64) -> Result<Membership, TeamError> {
+        self.invitations
+            .membership(team, user_id)
+            .await?
+            .ok_or(TeamError::Forbidden)
+    }
+
+    /// Middleware inserting [`CurrentTeam`] when the session's team is one
+    /// the signed-in user belongs to
+    ///
+    /// Use with `axum::middleware::from_fn_with_state`. Store failures are
+    /// logged and treated as no team selected.
+    pub async fn middleware(
+        State(teams): State<Self>,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        let selected = request
+            .extensions()
+            .get::<SessionData>()
+            .and_then(|session| {
+                let team = session.get::<String>(TEAM_SESSION_KEY)?;
+                Some((team, session.user_id?))
+            });
+        if let Some((team, user_id)) = selected {
+            match teams.invitations.membership(&team, user_id).await {
+                Ok(Some(membership)) => {
+                    request
+                        .extensions_mut()
+                        .insert(CurrentTeam::from(membership));
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, team = %team, "Team membership lookup failed"),
+            }
+        }
+        next.run(request).await
+    }
+
+    /// Team routes
+    ///
+    /// - `POST /teams` (form `name`) creates a team and switches to it
+    /// - `GET /teams` lists the caller's teams (JSON)
+    /// - `GET /teams/{team}` shows a team to its members (JSON)
+    /// - `POST /teams/{team}/rename` (form `name`) for managers
+    /// - `DELETE /teams/{team}` for owners
+    /// - `POST /teams/{team}/switch` makes the team current and refreshes the page
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/teams", get(list_teams).post(create_team))
+            .route("/teams/{team}", get(show_team).delete(delete_team))
+            .route("/teams/{team}/rename", post(rename_team))
+            .route("/teams/{team}/switch", post(switch_team))
+            .with_state(self.clone())
+    }
+}
+
+/// The team the current request works in
+///
+/// Inserted by [`Teams::middleware`]. Extraction fails with
+/// [`TeamError::NoTeamSelected`] when the session has no team or the user
+/// is not a member of it; use `Option<CurrentTeam>` for optional scoping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrentTeam {
+    /// Team ID
+    pub id: String,
+    /// Signed-in user
+    pub user_id: i64,
+    /// User's role in the team
+    pub role: String,
+}
+
+impl CurrentTeam {
+    /// Scope for queries against tables with a `team_id` column
+    #[must_use]
+    pub fn scope(&self) -> TeamScope {
+        TeamScope::new(self.id.clone())
+    }
+
+    /// Whether the user holds `role` in the team
+    #[must_use]
+    pub fn has_role(&self, role: &str) -> bool {
+        self.role == role
+    }
+}
+
+impl From<Membership> for CurrentTeam {
+    fn from(membership: Membership) -> Self {
+        Self {
+            id: membership.team,
+            user_id: membership.user_id,
+            role: membership.role,
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for CurrentTeam
+where
+    S: Send + Sync,
+{
+    type Rejection = TeamError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Self>()
+            .cloned()
+            .ok_or(TeamError::NoTeamSelected)
+    }
+}
+
+impl<S> axum::extract::OptionalFromRequestParts<S> for CurrentTeam
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned())
+    }
+}
+
+/// Team condition for SQL on team-owned tables
+///
+/// Builds the `WHERE` clause so handlers cannot forget the team filter.
+/// Placeholders follow data-service's `$n` style; the team ID is bound as
+/// the parameter after the caller's own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TeamScope {
+    team_id: String,
+    column: String,
+}
+
+impl TeamScope {
+    /// Scope to `team_id` using the `team_id` column
+    #[must_use]
+    pub fn new(team_id: impl Into<String>) -> Self {
+        Self {
+            team_id: team_id.into(),
+            column: DEFAULT_TEAM_COLUMN.to_string(),
+        }
+    }
+
+    /// Use a different column for the owning team
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TeamError::Invalid`] if `column` is not a SQL identifier.
+    pub fn with_column(mut self, column: impl Into<String>) -> Result<Self, TeamError> {
+        let column = column.into();
+        validate_identifier(&column).map_err(TeamError::Invalid)?;
+        self.column = column;
+        Ok(self)
+    }
+
+    /// Team ID
+    #[must_use]
+    pub fn team_id(&self) -> &str {
+        &self.team_id
+    }
+
+    /// Column holding the owning team
+    #[must_use]
+    pub fn column(&self) -> &str {
+        &self.column
+    }
+
+    /// `WHERE` clause combining the team condition with `filter`
+    ///
+    /// `param_count` is the number of parameters `filter` already uses; the
+    /// team ID goes in the next slot. An empty filter yields only the team
+    /// condition.
+    ///
+    /// ```rust,ignore
+    /// assert_eq!(
+    ///     scope.where_clause("status = $1", 1),
+    ///     "WHERE team_id = $2 AND (status = $1)"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn where_clause(&self, filter: &str, param_count: usize) -> String {
+        let condition = format!("{} = ${}", self.column, param_count + 1);
+        if filter.trim().is_empty() {
+            format!("WHERE {condition}")
+        } else {
+            format!("WHERE {condition} AND ({filter})")
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NameForm {
+    name: String,
+}
+
+fn user_id(session: Option<&Extension<SessionData>>) -> Result<i64, TeamError> {
+    session
+        .and_then(|Extension(session)| session.user_id)
+        .ok_or(TeamError::Unauthenticated)
+}
+
+async fn create_team(
+    State(teams): State<Teams>,
+    session: Option<Extension<SessionData>>,
+    Form(form): Form<NameForm>,
+) -> Result<Response, TeamError> {
+    let user_id = user_id(session.as_ref())?;
+    let team = teams.create(user_id, &form.name).await?;
+    let mut response = (StatusCode::CREATED, Json(&team)).into_response();
+    if let Some(Extension(mut session)) = session {
+        select_team(&mut session, &team.id)?;
+        response.extensions_mut().insert(session);
+    }
+    Ok(response)
+}
+
+async fn list_teams(
+    State(teams): State<Teams>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Json<Vec<TeamMembership>>, TeamError> {
+    let user_id = user_id(session.as_ref())?;
+    Ok(Json(teams.teams_for(user_id).await?))
+}
+
+async fn show_team(
+    State(teams): State<Teams>,
+    Path(team): Path<String>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Json<TeamMembership>, TeamError> {
+    let membership = teams
+        .require_member(&team, user_id(session.as_ref())?)
+        .await?;
+    Ok(Json(TeamMembership {
+        team: teams.get(&team).await?,
+        role: membership.role,
+    }))
+}
+
+async fn rename_team(
+    State(teams): State<Teams>,
+    Path(team): Path<String>,
+    session: Option<Extension<SessionData>>,
+    Form(form): Form<NameForm>,
+) -> Result<Json<Team>, TeamError> {
+    let membership = teams
+        .require_member(&team, user_id(session.as_ref())?)
+        .await?;
+    if !teams.manager_roles.contains(&membership.role) {
+        return Err(TeamError::Forbidden);
+    }
+    Ok(Json(teams.rename(&team, &form.name).await?))
+}
+
+async fn delete_team(
+    State(teams): State<Teams>,
+    Path(team): Path<String>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Response, TeamError> {
+    let membership = teams
+        .require_member(&team, user_id(session.as_ref())?)
+        .await?;
+    if membership.role != OWNER_ROLE {
+        return Err(TeamError::Forbidden);
+    }
+    teams.delete(&team).await?;
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    if let Some(Extension(mut session)) = session {
+        if session.get::<String>(TEAM_SESSION_KEY).as_deref() == Some(team.as_str()) {
+            session.remove(TEAM_SESSION_KEY);
+            response.extensions_mut().insert(session);
+        }
+    }
+    Ok(response)
+}
+
+async fn switch_team(
+    State(teams): State<Teams>,
+    Path(team): Path<String>,
+    session: Option<Extension<SessionData>>,
+) -> Result<Response, TeamError> {
+    let Some(Extension(mut session)) = session else {
+        return Err(TeamError::Unauthenticated);
+    };
+    let user_id = session.user_id.ok_or(TeamError::Unauthenticated)?;
+    teams.require_member(&team, user_id).await?;
+    select_team(&mut session, &team)?;
+    let mut response = ([("HX-Refresh", "true")], StatusCode::NO_CONTENT).into_response();
+    response.extensions_mut().insert(session);
+    Ok(response)
+}
+
+fn select_team(session: &mut SessionData, team: &str) -> Result<(), TeamError> {
+    session
+        .set(TEAM_SESSION_KEY.to_string(), team)
+        .map_err(|e| TeamError::Store(e.to_string()))
+}
+
+fn validate_name(name: &str) -> Result<String, TeamError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return Err(TeamError::Invalid(
+            "team name must be 1-100 characters".to_string(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htmx::invitations::{MemoryInvitationStore, SessionAccountLinker};
+    use axum::body::Body;
+    use axum::middleware::from_fn_with_state;
+    use tower::ServiceExt;
+
+    fn teams() -> Teams {
+        let invitations = Invitations::new(
+            Arc::new(MemoryInvitationStore::new()),
+            Arc::new(SessionAccountLinker),
+        );
+        Teams::new(Arc::new(MemoryTeamStore::new()), invitations)
+    }
+
+    #[tokio::test]
+    async fn test_team_crud() {
+        let teams = teams();
+        let team = teams.create(1, " Acme Corp ").await.unwrap();
+        assert_eq!(team.name, "Acme Corp");
+        assert!(team.id.starts_with("acme-corp-"));
+        assert!(teams.create(1, "  ").await.is_err());
+
+        let mine = teams.teams_for(1).await.unwrap();
+        assert_eq!(mine.len(), 1);
+        assert_eq!(mine[0].role, OWNER_ROLE);
+
+        assert_eq!(teams.rename(&team.id, "Acme").await.unwrap().name, "Acme");
+        teams.delete(&team.id).await.unwrap();
+        assert!(teams.teams_for(1).await.unwrap().is_empty());
+        assert!(matches!(
+            teams.get(&team.id).await,
+            Err(TeamError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_scope_where_clause() {
+        let scope = TeamScope::new("acme");
+        assert_eq!(scope.where_clause("", 0), "WHERE team_id = $1");
+        assert_eq!(
+            scope.where_clause("status = $1 OR status = $2", 2),
+            "WHERE team_id = $3 AND (status = $1 OR status = $2)"
+        );
+        let scope = scope.with_column("org_id").unwrap();
+        assert_eq!(scope.where_clause("", 0), "WHERE org_id = $1");
+        assert!(TeamScope::new("acme").with_column("x; --").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_current_team_requires_membership() {
+        let teams = teams();
+        let team = teams.create(1, "Acme").await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/whoami",
+                get(|team: Option<CurrentTeam>| async move {
+                    team.map_or_else(|| "none".to_string(), |team| team.id)
+                }),
+            )
+            .layer(from_fn_with_state(teams.clone(), Teams::middleware));
+
+        let request = |user_id: i64| {
+            let mut session = SessionData::new();
+            session.user_id = Some(user_id);
+            session
+                .set(TEAM_SESSION_KEY.to_string(), team.id.clone())
+                .unwrap();
+            let mut request = Request::get("/whoami").body(Body::empty()).unwrap();
+            request.extensions_mut().insert(session);
+            request
+        };
+
+        let body = |response: Response| async move {
+            let bytes = axum::body::to_bytes(response.into_body(), 1024)
+                .await
+                .unwrap();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        let response = app.clone().oneshot(request(1)).await.unwrap();
+        assert_eq!(body(response).await, team.id);
+        // Non-members cannot select someone else's team
+        let response = app.oneshot(request(2)).await.unwrap();
+        assert_eq!(body(response).await, "none");
+    }
+}
@@ -76,13 +76,9 @@ pub fn extract_by_id<'a>(html: &'a str, id: &str) -> Option<Cow<'a, str>> {
     let pattern_single = format!(r"<div id='{id}'");
 
     // Find the start tag
-    let start_pos = if let Some(pos) = html.find(&pattern_double) {
-        pos
-    } else if let Some(pos) = html.find(&pattern_single) {
-        pos
-    } else {
-        return None;
-    };
+    let start_pos = html
+        .find(&pattern_double)
+        .or_else(|| html.find(&pattern_single))?;
 
     // Find the end of the opening tag (>)
     let tag_start = &html[start_pos..];
@@ -59,4 +59,6 @@ pub const TEMPLATE_NAMES: &[&str] = &[
     "errors/404.html",
     "errors/422.html",
     "errors/500.html",
+    // OpenID Connect provider
+    "oauth/consent.html",
 ];
@@ -378,7 +378,8 @@ impl From<&str> for SafeString {
 
 /// Escape a string for safe use in HTML content
 ///
-/// Escapes special HTML characters to prevent XSS attacks.
+/// Escapes special HTML characters to prevent XSS attacks. Quotes are
+/// escaped too, so the result is safe inside quoted attribute values.
 /// This is used internally by helpers that generate HTML.
 ///
 /// # Examples
@@ -387,7 +388,7 @@ impl From<&str> for SafeString {
 /// use acton_htmx::template::helpers::escape_html;
 ///
 /// assert_eq!(escape_html("<script>alert('xss')</script>"),
-///            "&lt;script&gt;alert('xss')&lt;/script&gt;");
+///            "&lt;script&gt;alert(&#x27;xss&#x27;)&lt;/script&gt;");
 /// assert_eq!(escape_html("Hello & goodbye"), "Hello &amp; goodbye");
 /// ```
 #[must_use]
@@ -395,6 +396,8 @@ pub fn escape_html(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
 }
 
 // =============================================================================
@@ -749,13 +752,17 @@ mod tests {
         assert_eq!(escape_html("<div>content</div>"), "&lt;div&gt;content&lt;/div&gt;");
         assert_eq!(
             escape_html("<script>alert('xss')</script>"),
-            "&lt;script&gt;alert('xss')&lt;/script&gt;"
+            "&lt;script&gt;alert(&#x27;xss&#x27;)&lt;/script&gt;"
+        );
+        assert_eq!(
+            escape_html(r#"<a title="x" data-y='z'>"#),
+            "&lt;a title=&quot;x&quot; data-y=&#x27;z&#x27;&gt;"
         );
     }
 
     #[test]
     fn test_escape_html_preserves_safe_chars() {
-        assert_eq!(escape_html("Hello 123 !@#$%^*()_+-=[]{}|;:',./? "),
-                   "Hello 123 !@#$%^*()_+-=[]{}|;:',./? ");
+        assert_eq!(escape_html("Hello 123 !@#$%^*()_+-=[]{}|;:,./? "),
+                   "Hello 123 !@#$%^*()_+-=[]{}|;:,./? ");
     }
 }
@@ -0,0 +1,663 @@
+//! Builders for the values tests construct most often
+//!
+//! Users, sessions, authenticated requests, Cedar entities, and uploaded
+//! files all have several fields that must agree with each other (a session
+//! pointing at a user, a request carrying that session the way
+//! `SessionMiddleware` does, a principal entity shaped like the one
+//! `CedarAuthz` builds). The fixtures here fill in sensible defaults so a
+//! test only spells out what it is about.
+//!
+//! Available in unit tests and, with the `testing` feature, to doctests,
+//! integration tests, and examples.
+//!
+//! # Example
+//!
+//! ```rust
+//! use acton_dx::testing::fixtures::{RequestFixture, UserFixture};
+//!
+//! let user = UserFixture::new().id(7).role("admin").build();
+//! let request = RequestFixture::post("/posts").user(&user).htmx().build();
+//!
+//! assert_eq!(request.headers()["hx-request"], "true");
+//! ```
+
+use axum::body::Body;
+use axum::http::{Method, Request};
+use chrono::{Duration, Utc};
+use image::{DynamicImage, ImageBuffer, ImageFormat, Rgb};
+use serde::Serialize;
+use std::io::Cursor;
+
+use crate::htmx::auth::{EmailAddress, FlashMessage, Session, SessionData, SessionId, User};
+use crate::htmx::storage::UploadedFile;
+
+#[cfg(feature = "cedar")]
+use crate::htmx::middleware::CedarError;
+
+/// The EICAR anti-virus test string
+///
+/// Every virus scanner reports this 68-byte string as infected, which makes
+/// it safe test data for scanning and quarantine code paths.
+pub const EICAR_TEST_STRING: &str =
+    r"X5O!P%@AP[4\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+
+/// Password of users built by [`UserFixture`]
+pub const TEST_PASSWORD: &str = "TestPassword123";
+
+/// Builder for [`User`]
+///
+/// Defaults to user 1, `test@example.com`, role `user`, and a verified
+/// email. The password hash is empty unless set with
+/// [`UserFixture::password`], so tests that never log in skip Argon2.
+#[derive(Debug, Clone)]
+pub struct UserFixture {
+    id: i64,
+    email: String,
+    password_hash: String,
+    roles: Vec<String>,
+    permissions: Vec<String>,
+    email_verified: bool,
+    timezone: Option<String>,
+    active: bool,
+}
+
+impl UserFixture {
+    /// Start from the default user
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            id: 1,
+            email: "test@example.com".to_string(),
+            password_hash: String::new(),
+            roles: vec!["user".to_string()],
+            permissions: Vec::new(),
+            email_verified: true,
+            timezone: None,
+            active: true,
+        }
+    }
+
+    /// Start from a user with the `admin` role
+    #[must_use]
+    pub fn admin() -> Self {
+        Self::new().email("admin@example.com").role("admin")
+    }
+
+    /// Set the user ID
+    #[must_use]
+    pub const fn id(mut self, id: i64) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Set the email address
+    #[must_use]
+    pub fn email(mut self, email: impl Into<String>) -> Self {
+        self.email = email.into();
+        self
+    }
+
+    /// Hash `password` as the user's password
+    ///
+    /// # Panics
+    ///
+    /// Panics if the password cannot be hashed.
+    #[must_use]
+    pub fn password(mut self, password: &str) -> Self {
+        self.password_hash =
+            crate::htmx::auth::hash_password(password).expect("Failed to hash test password");
+        self
+    }
+
+    /// Add a role
+    #[must_use]
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    /// Replace the roles
+    #[must_use]
+    pub fn roles<I, R>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: Into<String>,
+    {
+        self.roles = roles.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Add a `resource:action` permission
+    #[must_use]
+    pub fn permission(mut self, permission: impl Into<String>) -> Self {
+        self.permissions.push(permission.into());
+        self
+    }
+
+    /// Set whether the email is verified
+    #[must_use]
+    pub const fn verified(mut self, verified: bool) -> Self {
+        self.email_verified = verified;
+        self
+    }
+
+    /// Set whether the user is active, as SCIM deactivation clears it
+    #[must_use]
+    pub const fn active(mut self, active: bool) -> Self {
+        self.active = active;
+        self
+    }
+
+    /// Set the IANA timezone
+    #[must_use]
+    pub fn timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Build the user
+    ///
+    /// # Panics
+    ///
+    /// Panics if the email address is invalid.
+    #[must_use]
+    pub fn build(self) -> User {
+        let now = Utc::now();
+        User {
+            id: self.id,
+            email: EmailAddress::parse(&self.email).expect("Invalid test email address"),
+            password_hash: self.password_hash,
+            roles: self.roles,
+            permissions: self.permissions,
+            email_verified: self.email_verified,
+            timezone: self.timezone,
+            active: self.active,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+impl Default for UserFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder for [`SessionData`] and [`Session`]
+///
+/// # Example
+///
+/// ```rust
+/// use acton_dx::auth::FlashMessage;
+/// use acton_dx::testing::fixtures::{SessionFixture, UserFixture};
+///
+/// let user = UserFixture::new().build();
+/// let session = SessionFixture::new()
+///     .user(&user)
+///     .value("cart_items", 3)
+///     .flash(FlashMessage::success("Saved"))
+///     .build();
+///
+/// assert_eq!(session.user_id, Some(user.id));
+/// assert_eq!(session.get::<i32>("cart_items"), Some(3));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SessionFixture {
+    id: SessionId,
+    data: SessionData,
+}
+
+impl SessionFixture {
+    /// Start from an anonymous session expiring in 24 hours
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            id: SessionId::generate(),
+            data: SessionData::new(),
+        }
+    }
+
+    /// Sign the session in as `user`
+    #[must_use]
+    pub fn user(mut self, user: &User) -> Self {
+        self.data.user_id = Some(user.id);
+        self.data.user_name = Some(user.email.as_str().to_string());
+        self
+    }
+
+    /// Sign the session in as a user ID
+    #[must_use]
+    pub const fn user_id(mut self, user_id: i64) -> Self {
+        self.data.user_id = Some(user_id);
+        self
+    }
+
+    /// Store a value under `key`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value cannot be serialized to JSON.
+    #[must_use]
+    pub fn value<T: Serialize>(mut self, key: &str, value: T) -> Self {
+        self.data
+            .set(key.to_string(), value)
+            .expect("Failed to serialize session value");
+        self
+    }
+
+    /// Queue a flash message
+    #[must_use]
+    pub fn flash(mut self, message: FlashMessage) -> Self {
+        self.data.flash_messages.push(message);
+        self
+    }
+
+    /// Set the IANA timezone
+    #[must_use]
+    pub fn timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.data.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Make the session already expired
+    #[must_use]
+    pub fn expired(mut self) -> Self {
+        self.data.expires_at = Utc::now() - Duration::seconds(1);
+        self
+    }
+
+    /// The session ID
+    #[must_use]
+    pub const fn id(&self) -> &SessionId {
+        &self.id
+    }
+
+    /// Build the session data
+    #[must_use]
+    pub fn build(self) -> SessionData {
+        self.data
+    }
+
+    /// Build the session with its ID
+    #[must_use]
+    pub fn into_session(self) -> Session {
+        Session::new(self.id, self.data)
+    }
+}
+
+impl Default for SessionFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder for requests as handlers see them behind `SessionMiddleware`
+///
+/// The session ID, session data, and [`Session`] are placed in the request
+/// extensions, where the middleware and the authentication extractors look
+/// for them. Without a session the request is anonymous.
+///
+/// # Example
+///
+/// ```rust
+/// use acton_dx::auth::SessionData;
+/// use acton_dx::testing::fixtures::{RequestFixture, UserFixture};
+///
+/// let user = UserFixture::new().id(42).build();
+/// let request = RequestFixture::get("/dashboard").user(&user).build();
+///
+/// let session = request.extensions().get::<SessionData>().unwrap();
+/// assert_eq!(session.user_id, Some(42));
+/// ```
+#[derive(Debug)]
+pub struct RequestFixture {
+    method: Method,
+    uri: String,
+    headers: Vec<(String, String)>,
+    body: Body,
+    session: Option<SessionFixture>,
+}
+
+impl RequestFixture {
+    /// Start a request with any method
+    #[must_use]
+    pub fn new(method: Method, uri: impl Into<String>) -> Self {
+        Self {
+            method,
+            uri: uri.into(),
+            headers: Vec::new(),
+            body: Body::empty(),
+            session: None,
+        }
+    }
+
+    /// Start a `GET` request
+    #[must_use]
+    pub fn get(uri: impl Into<String>) -> Self {
+        Self::new(Method::GET, uri)
+    }
+
+    /// Start a `POST` request
+    #[must_use]
+    pub fn post(uri: impl Into<String>) -> Self {
+        Self::new(Method::POST, uri)
+    }
+
+    /// Start a `DELETE` request
+    #[must_use]
+    pub fn delete(uri: impl Into<String>) -> Self {
+        Self::new(Method::DELETE, uri)
+    }
+
+    /// Add a header
+    #[must_use]
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Mark the request as sent by HTMX (`HX-Request: true`)
+    #[must_use]
+    pub fn htmx(self) -> Self {
+        self.header("hx-request", "true")
+    }
+
+    /// Set the body
+    #[must_use]
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Send a URL-encoded form body, e.g. `"title=Hello&published=true"`
+    #[must_use]
+    pub fn form(self, encoded: impl Into<String>) -> Self {
+        self.body(encoded.into())
+            .header("content-type", "application/x-www-form-urlencoded")
+    }
+
+    /// Attach a session
+    #[must_use]
+    pub fn session(mut self, session: SessionFixture) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Attach a session signed in as `user`
+    #[must_use]
+    pub fn user(self, user: &User) -> Self {
+        self.session(SessionFixture::new().user(user))
+    }
+
+    /// Build the request
+    ///
+    /// # Panics
+    ///
+    /// Panics if the URI or a header is invalid.
+    #[must_use]
+    pub fn build(self) -> Request<Body> {
+        let mut builder = Request::builder().method(self.method).uri(self.uri);
+        for (name, value) in self.headers {
+            builder = builder.header(name, value);
+        }
+        let mut request = builder.body(self.body).expect("Invalid test request");
+
+        if let Some(session) = self.session {
+            let extensions = request.extensions_mut();
+            extensions.insert(session.id.clone());
+            extensions.insert(session.data.clone());
+            extensions.insert(session.into_session());
+        }
+        request
+    }
+}
+
+/// Builder for Cedar entities
+///
+/// Users become principal entities with the same attributes `CedarAuthz`
+/// gives them, so policies evaluated in tests see what they see in
+/// production.
+///
+/// # Example
+///
+/// ```rust
+/// use acton_dx::testing::fixtures::{CedarEntitiesFixture, UserFixture};
+/// use serde_json::json;
+///
+/// let owner = UserFixture::new().build();
+/// let entities = CedarEntitiesFixture::new()
+///     .user(&owner)
+///     .entity("Post", "1", json!({ "owner_id": owner.id }))
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(entities.iter().count(), 2);
+/// ```
+#[cfg(feature = "cedar")]
+#[derive(Debug, Clone, Default)]
+pub struct CedarEntitiesFixture {
+    entities: Vec<serde_json::Value>,
+}
+
+#[cfg(feature = "cedar")]
+impl CedarEntitiesFixture {
+    /// Start with no entities
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the principal entity of `user`
+    #[must_use]
+    pub fn user(mut self, user: &User) -> Self {
+        self.entities
+            .push(crate::htmx::middleware::cedar::user_entity_json(user));
+        self
+    }
+
+    /// Add an entity with attributes and no parents
+    #[must_use]
+    pub fn entity(self, entity_type: &str, id: &str, attrs: serde_json::Value) -> Self {
+        self.entity_in(entity_type, id, attrs, &[])
+    }
+
+    /// Add an entity that is a member of `parents` (`(type, id)` pairs)
+    #[must_use]
+    pub fn entity_in(
+        mut self,
+        entity_type: &str,
+        id: &str,
+        attrs: serde_json::Value,
+        parents: &[(&str, &str)],
+    ) -> Self {
+        let parents: Vec<_> = parents
+            .iter()
+            .map(|(parent_type, parent_id)| {
+                serde_json::json!({ "type": parent_type, "id": parent_id })
+            })
+            .collect();
+        let mut entity = serde_json::json!({
+            "uid": { "type": entity_type, "id": id },
+            "parents": parents,
+        });
+        entity["attrs"] = attrs;
+        self.entities.push(entity);
+        self
+    }
+
+    /// The entities as Cedar JSON
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::Value::Array(self.entities.clone())
+    }
+
+    /// Build the entities
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the entities are not valid Cedar entities.
+    pub fn build(self) -> Result<cedar_policy::Entities, CedarError> {
+        cedar_policy::Entities::from_json_value(serde_json::Value::Array(self.entities), None)
+            .map_err(|e| CedarError::Internal(format!("Failed to build entities: {e}")))
+    }
+}
+
+/// A real PNG image of `width` by `height` red pixels
+///
+/// # Panics
+///
+/// Panics if the image cannot be encoded.
+#[must_use]
+pub fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+    image_bytes(width, height, ImageFormat::Png)
+}
+
+/// A real JPEG image of `width` by `height` red pixels
+///
+/// # Panics
+///
+/// Panics if the image cannot be encoded.
+#[must_use]
+pub fn jpeg_bytes(width: u32, height: u32) -> Vec<u8> {
+    image_bytes(width, height, ImageFormat::Jpeg)
+}
+
+fn image_bytes(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    let img: ImageBuffer<Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_fn(width, height, |_, _| Rgb([255, 0, 0]));
+    let mut buffer = Vec::new();
+    DynamicImage::ImageRgb8(img)
+        .write_to(&mut Cursor::new(&mut buffer), format)
+        .expect("Failed to encode test image");
+    buffer
+}
+
+/// Uploaded PNG image
+#[must_use]
+pub fn png_upload(filename: &str, width: u32, height: u32) -> UploadedFile {
+    UploadedFile::new(filename, "image/png", png_bytes(width, height))
+}
+
+/// Uploaded JPEG image
+#[must_use]
+pub fn jpeg_upload(filename: &str, width: u32, height: u32) -> UploadedFile {
+    UploadedFile::new(filename, "image/jpeg", jpeg_bytes(width, height))
+}
+
+/// Uploaded minimal PDF document
+#[must_use]
+pub fn pdf_upload(filename: &str) -> UploadedFile {
+    UploadedFile::new(
+        filename,
+        "application/pdf",
+        b"%PDF-1.4\n1 0 obj\n<< /Type /Catalog >>\nendobj\ntrailer\n<< /Root 1 0 R >>\n%%EOF\n"
+            .to_vec(),
+    )
+}
+
+/// Uploaded plain text file
+#[must_use]
+pub fn text_upload(filename: &str, contents: &str) -> UploadedFile {
+    UploadedFile::new(filename, "text/plain", contents.as_bytes().to_vec())
+}
+
+/// Uploaded file of `size` zero bytes, for size limit tests
+#[must_use]
+pub fn sized_upload(filename: &str, content_type: &str, size: usize) -> UploadedFile {
+    UploadedFile::new(filename, content_type, vec![0; size])
+}
+
+/// Uploaded file containing the [`EICAR_TEST_STRING`]
+///
+/// # Example
+///
+/// ```rust
+/// use acton_dx::testing::fixtures::{eicar_upload, EICAR_TEST_STRING};
+///
+/// let file = eicar_upload();
+/// assert_eq!(file.size(), EICAR_TEST_STRING.len() as u64);
+/// ```
+#[must_use]
+pub fn eicar_upload() -> UploadedFile {
+    UploadedFile::new(
+        "eicar.com",
+        "application/octet-stream",
+        EICAR_TEST_STRING.as_bytes().to_vec(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_defaults() {
+        let user = UserFixture::new().build();
+        assert_eq!(user.id, 1);
+        assert_eq!(user.email.as_str(), "test@example.com");
+        assert_eq!(user.roles, ["user"]);
+        assert!(user.email_verified);
+    }
+
+    #[test]
+    fn test_user_password_verifies() {
+        let user = UserFixture::new().password(TEST_PASSWORD).build();
+        assert!(user.verify_password(TEST_PASSWORD).unwrap());
+    }
+
+    #[test]
+    fn test_expired_session() {
+        let session = SessionFixture::new().expired().build();
+        assert!(session.is_expired());
+    }
+
+    #[test]
+    fn test_request_carries_session() {
+        let user = UserFixture::admin().id(9).build();
+        let request = RequestFixture::get("/admin").user(&user).build();
+
+        let session = request.extensions().get::<Session>().unwrap();
+        assert_eq!(session.data().user_id, Some(9));
+        assert_eq!(request.extensions().get::<SessionId>(), Some(session.id()));
+    }
+
+    #[test]
+    fn test_anonymous_request() {
+        let request = RequestFixture::get("/").build();
+        assert!(request.extensions().get::<Session>().is_none());
+    }
+
+    #[test]
+    fn test_images_are_detected() {
+        let png = png_upload("a.png", 4, 4);
+        assert_eq!(infer::get(&png.data).unwrap().mime_type(), "image/png");
+        let jpeg = jpeg_upload("a.jpg", 4, 4);
+        assert_eq!(infer::get(&jpeg.data).unwrap().mime_type(), "image/jpeg");
+        let pdf = pdf_upload("a.pdf");
+        assert_eq!(infer::get(&pdf.data).unwrap().mime_type(), "application/pdf");
+    }
+
+    #[test]
+    fn test_eicar_string_length() {
+        assert_eq!(EICAR_TEST_STRING.len(), 68);
+    }
+
+    #[cfg(feature = "cedar")]
+    #[test]
+    fn test_cedar_entities_with_parents() {
+        let user = UserFixture::new().build();
+        let entities = CedarEntitiesFixture::new()
+            .user(&user)
+            .entity("Team", "core", serde_json::json!({}))
+            .entity_in(
+                "Post",
+                "1",
+                serde_json::json!({ "owner_id": 1 }),
+                &[("Team", "core")],
+            )
+            .build()
+            .unwrap();
+        assert_eq!(entities.iter().count(), 3);
+    }
+}
@@ -19,6 +19,12 @@
 //! - [`TestJobQueue`] - In-memory job queue for testing background jobs
 //! - [`TestJob`] - Simple test job implementation for testing job execution
 //!
+//! ## Fixtures
+//!
+//! - [`fixtures`] - Builders for users, sessions, authenticated requests,
+//!   Cedar entities, and uploaded files (also available to doctests and
+//!   integration tests with the `testing` feature)
+//!
 //! # Example
 //!
 //! ```rust,no_run
@@ -42,22 +48,37 @@
 //! }
 //! ```
 
+#[cfg(test)]
 pub mod agents;
+#[cfg(test)]
 pub mod assertions;
+#[cfg(test)]
 pub mod database;
+#[cfg(test)]
 pub mod email;
+pub mod fixtures;
+#[cfg(test)]
 pub mod jobs;
+#[cfg(test)]
 pub mod server;
 
 // Re-export for convenience
+#[cfg(test)]
 pub use agents::{await_response, await_response_with_timeout, AgentTestRuntime};
+#[cfg(test)]
 pub use assertions::*;
+#[cfg(test)]
 pub use database::TestDatabase;
+#[cfg(test)]
 pub use email::MockEmailSender;
+pub use fixtures::{RequestFixture, SessionFixture, UserFixture};
+#[cfg(test)]
 pub use jobs::{
     assert_job_completes_within, assert_job_fails, assert_job_succeeds, TestJob, TestJobQueue,
 };
+#[cfg(test)]
 pub use server::TestServer;
 
 // Re-export mockall for test usage
+#[cfg(test)]
 pub use mockall;
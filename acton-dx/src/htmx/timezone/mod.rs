@@ -0,0 +1,364 @@
+//! Per-user timezones for rendering timestamps
+//!
+//! Timestamps are stored in UTC. [`TzContext`] carries the viewer's timezone
+//! and preferred formats so templates render local times instead of raw UTC.
+//!
+//! The timezone is resolved per request by
+//! [`TimezoneExtractor`](crate::htmx::extractors::TimezoneExtractor):
+//!
+//! 1. `SessionData::timezone` (copied from the user's profile at login)
+//! 2. the `tz` cookie, typically set from the browser with
+//!    `Intl.DateTimeFormat().resolvedOptions().timeZone`
+//! 3. UTC
+//!
+//! # Template filters
+//!
+//! ```rust,ignore
+//! mod filters {
+//!     pub use acton_htmx::timezone::filters::*;
+//! }
+//!
+//! #[derive(Template)]
+//! #[template(path = "posts/show.html")]
+//! struct ShowPost {
+//!     post: Post,
+//!     tz: TzContext,
+//! }
+//! ```
+//!
+//! ```html
+//! <time datetime="{{ post.created_at|isotime(tz) }}">{{ post.created_at|localdatetime(tz) }}</time>
+//! <span>{{ post.updated_at|timeago(tz) }}</span>
+//! <span>{{ post.created_at|localformat(tz, "%A %H:%M") }}</span>
+//! ```
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// Name of the cookie holding the browser-detected timezone
+pub const TIMEZONE_COOKIE: &str = "tz";
+
+/// Relative times older than this many days fall back to a date
+const RELATIVE_CUTOFF_DAYS: i64 = 7;
+
+/// Timezone name is not in the IANA database
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown timezone '{0}'")]
+pub struct InvalidTimezone(pub String);
+
+/// Parse an IANA timezone name (`Europe/Berlin`, `America/New_York`)
+///
+/// # Errors
+///
+/// Returns [`InvalidTimezone`] if the name is not a known timezone.
+pub fn parse_timezone(name: &str) -> Result<Tz, InvalidTimezone> {
+    Tz::from_str(name.trim()).map_err(|_| InvalidTimezone(name.to_string()))
+}
+
+/// Viewer timezone and display formats
+///
+/// Formats use `chrono` strftime syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TzContext {
+    tz: Tz,
+    date_format: &'static str,
+    time_format: &'static str,
+}
+
+impl Default for TzContext {
+    fn default() -> Self {
+        Self::new(Tz::UTC)
+    }
+}
+
+impl TzContext {
+    /// Create a context for a timezone with default formats
+    #[must_use]
+    pub const fn new(tz: Tz) -> Self {
+        Self {
+            tz,
+            date_format: "%b %-d, %Y",
+            time_format: "%-I:%M %p",
+        }
+    }
+
+    /// Create a context from an IANA timezone name
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidTimezone`] if the name is not a known timezone.
+    pub fn from_name(name: &str) -> Result<Self, InvalidTimezone> {
+        parse_timezone(name).map(Self::new)
+    }
+
+    /// Set the date format (default `%b %-d, %Y`)
+    #[must_use]
+    pub const fn with_date_format(mut self, format: &'static str) -> Self {
+        self.date_format = format;
+        self
+    }
+
+    /// Set the time format (default `%-I:%M %p`; use `%H:%M` for 24-hour time)
+    #[must_use]
+    pub const fn with_time_format(mut self, format: &'static str) -> Self {
+        self.time_format = format;
+        self
+    }
+
+    /// The viewer's timezone
+    #[must_use]
+    pub const fn tz(&self) -> Tz {
+        self.tz
+    }
+
+    /// IANA name of the viewer's timezone
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        self.tz.name()
+    }
+
+    /// Convert a UTC timestamp to the viewer's timezone
+    #[must_use]
+    pub fn to_local(&self, utc: DateTime<Utc>) -> DateTime<Tz> {
+        utc.with_timezone(&self.tz)
+    }
+
+    /// Current time in the viewer's timezone
+    #[must_use]
+    pub fn now(&self) -> DateTime<Tz> {
+        self.to_local(Utc::now())
+    }
+
+    /// Format with an explicit strftime pattern in the viewer's timezone
+    #[must_use]
+    pub fn format(&self, utc: DateTime<Utc>, pattern: &str) -> String {
+        self.to_local(utc).format(pattern).to_string()
+    }
+
+    /// Local date
+    #[must_use]
+    pub fn date(&self, utc: DateTime<Utc>) -> String {
+        self.format(utc, self.date_format)
+    }
+
+    /// Local time of day
+    #[must_use]
+    pub fn time(&self, utc: DateTime<Utc>) -> String {
+        self.format(utc, self.time_format)
+    }
+
+    /// Local date and time
+    #[must_use]
+    pub fn datetime(&self, utc: DateTime<Utc>) -> String {
+        let local = self.to_local(utc);
+        format!(
+            "{} {}",
+            local.format(self.date_format),
+            local.format(self.time_format)
+        )
+    }
+
+    /// Relative time such as `5 minutes ago` or `in 2 hours`
+    ///
+    /// Anything more than a week away is shown as a local date.
+    #[must_use]
+    pub fn relative(&self, utc: DateTime<Utc>) -> String {
+        self.relative_to(utc, Utc::now())
+    }
+
+    /// Relative time measured from `now`
+    #[must_use]
+    pub fn relative_to(&self, utc: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        let delta = now.signed_duration_since(utc);
+        let past = delta.num_seconds() >= 0;
+        let seconds = delta.num_seconds().abs();
+
+        let (amount, unit) = match seconds {
+            0..=44 => return "just now".to_string(),
+            45..=3_599 => ((seconds + 30) / 60, "minute"),
+            3_600..=86_399 => ((seconds + 1_800) / 3_600, "hour"),
+            _ if seconds < RELATIVE_CUTOFF_DAYS * 86_400 => ((seconds + 43_200) / 86_400, "day"),
+            _ => return self.date(utc),
+        };
+        let plural = if amount == 1 { "" } else { "s" };
+        if past {
+            format!("{amount} {unit}{plural} ago")
+        } else {
+            format!("in {amount} {unit}{plural}")
+        }
+    }
+
+    /// ISO 8601 timestamp with the viewer's offset, for `<time datetime>`
+    #[must_use]
+    pub fn iso(&self, utc: DateTime<Utc>) -> String {
+        self.to_local(utc).to_rfc3339()
+    }
+}
+
+impl fmt::Display for TzContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Timestamp types accepted by the template filters
+pub trait AsUtc {
+    /// The instant in UTC
+    fn as_utc(&self) -> DateTime<Utc>;
+}
+
+impl AsUtc for DateTime<Utc> {
+    fn as_utc(&self) -> DateTime<Utc> {
+        *self
+    }
+}
+
+impl AsUtc for DateTime<FixedOffset> {
+    fn as_utc(&self) -> DateTime<Utc> {
+        self.with_timezone(&Utc)
+    }
+}
+
+/// Naive timestamps are assumed to be UTC, as stored by the framework
+impl AsUtc for NaiveDateTime {
+    fn as_utc(&self) -> DateTime<Utc> {
+        Utc.from_utc_datetime(self)
+    }
+}
+
+impl<T: AsUtc + ?Sized> AsUtc for &T {
+    fn as_utc(&self) -> DateTime<Utc> {
+        (**self).as_utc()
+    }
+}
+
+/// Askama filters for local timestamps
+///
+/// Each filter takes the [`TzContext`] as its first argument. None of them
+/// fail; the `Result` is part of Askama's filter signature.
+#[allow(clippy::unnecessary_wraps, clippy::missing_errors_doc)]
+pub mod filters {
+    use super::{AsUtc, TzContext};
+
+    /// Local date (`{{ ts|localdate(tz) }}`)
+    pub fn localdate<T: AsUtc>(
+        value: T,
+        _values: &dyn askama::Values,
+        tz: &TzContext,
+    ) -> askama::Result<String> {
+        Ok(tz.date(value.as_utc()))
+    }
+
+    /// Local time of day (`{{ ts|localtime(tz) }}`)
+    pub fn localtime<T: AsUtc>(
+        value: T,
+        _values: &dyn askama::Values,
+        tz: &TzContext,
+    ) -> askama::Result<String> {
+        Ok(tz.time(value.as_utc()))
+    }
+
+    /// Local date and time (`{{ ts|localdatetime(tz) }}`)
+    pub fn localdatetime<T: AsUtc>(
+        value: T,
+        _values: &dyn askama::Values,
+        tz: &TzContext,
+    ) -> askama::Result<String> {
+        Ok(tz.datetime(value.as_utc()))
+    }
+
+    /// Local time with a custom pattern (`{{ ts|localformat(tz, "%H:%M") }}`)
+    pub fn localformat<T: AsUtc>(
+        value: T,
+        _values: &dyn askama::Values,
+        tz: &TzContext,
+        pattern: &str,
+    ) -> askama::Result<String> {
+        Ok(tz.format(value.as_utc(), pattern))
+    }
+
+    /// Relative time (`{{ ts|timeago(tz) }}`)
+    pub fn timeago<T: AsUtc>(
+        value: T,
+        _values: &dyn askama::Values,
+        tz: &TzContext,
+    ) -> askama::Result<String> {
+        Ok(tz.relative(value.as_utc()))
+    }
+
+    /// ISO 8601 timestamp with offset (`{{ ts|isotime(tz) }}`)
+    pub fn isotime<T: AsUtc>(
+        value: T,
+        _values: &dyn askama::Values,
+        tz: &TzContext,
+    ) -> askama::Result<String> {
+        Ok(tz.iso(value.as_utc()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn instant() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, 4, 18, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("Europe/Berlin").unwrap(), Tz::Europe__Berlin);
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn test_local_formatting() {
+        let tz = TzContext::from_name("America/New_York").unwrap();
+        assert_eq!(tz.date(instant()), "Jul 4, 2024");
+        assert_eq!(tz.time(instant()), "2:30 PM");
+        assert_eq!(tz.iso(instant()), "2024-07-04T14:30:00-04:00");
+
+        let tz = TzContext::from_name("Asia/Tokyo")
+            .unwrap()
+            .with_time_format("%H:%M");
+        assert_eq!(tz.datetime(instant()), "Jul 5, 2024 03:30");
+    }
+
+    #[test]
+    fn test_relative_time() {
+        let tz = TzContext::default();
+        let now = instant();
+        assert_eq!(tz.relative_to(now - Duration::seconds(10), now), "just now");
+        assert_eq!(
+            tz.relative_to(now - Duration::minutes(1), now),
+            "1 minute ago"
+        );
+        assert_eq!(tz.relative_to(now + Duration::hours(3), now), "in 3 hours");
+        assert_eq!(tz.relative_to(now - Duration::days(2), now), "2 days ago");
+        assert_eq!(tz.relative_to(now - Duration::days(30), now), "Jun 4, 2024");
+    }
+
+    #[derive(askama::Template)]
+    #[template(
+        source = r#"{{ at|localformat(tz, "%H:%M %Z") }} {{ at|localdate(tz) }} {{ naive|localtime(tz) }}"#,
+        ext = "txt"
+    )]
+    struct Stamp {
+        at: DateTime<Utc>,
+        naive: NaiveDateTime,
+        tz: TzContext,
+    }
+
+    #[test]
+    fn test_filters_in_template() {
+        use askama::Template;
+        let stamp = Stamp {
+            at: instant(),
+            naive: instant().naive_utc(),
+            tz: TzContext::from_name("Europe/London").unwrap(),
+        };
+        assert_eq!(stamp.render().unwrap(), "19:30 BST Jul 4, 2024 7:30 PM");
+    }
+}
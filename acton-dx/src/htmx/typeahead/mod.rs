@@ -0,0 +1,639 @@
+//! Search-as-you-type endpoints
+//!
+//! The typeahead kit packages the debounced search box every HTMX app ends
+//! up copy-pasting:
+//!
+//! - [`TypeaheadInput`] renders the `<input>` with the `hx-trigger` debounce,
+//!   request indicator and `hx-sync` so only the latest query is in flight
+//! - [`Typeahead`] wraps a search callback with a per-client rate limit, a
+//!   minimum query length and query sanitization, and renders the results
+//!   partial with matched terms wrapped in `<mark>`
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use acton_dx::htmx::typeahead::{Typeahead, TypeaheadInput, TypeaheadResult};
+//!
+//! let search = Typeahead::new(move |query| {
+//!     let pool = pool.clone();
+//!     async move {
+//!         let rows = sqlx::query_as::<_, (i64, String)>(
+//!             "SELECT id, name FROM products WHERE name ILIKE $1 ESCAPE '\\' LIMIT 10",
+//!         )
+//!         .bind(query.like_pattern())
+//!         .fetch_all(&pool)
+//!         .await
+//!         .map_err(|e| e.to_string())?;
+//!         Ok(rows
+//!             .into_iter()
+//!             .map(|(id, name)| TypeaheadResult::new(name).url(format!("/products/{id}")))
+//!             .collect())
+//!     }
+//! })
+//! .min_length(2);
+//!
+//! let app = Router::new().merge(search.routes("/products/search"));
+//!
+//! // In a page template: {{ search_box|safe }}
+//! let search_box = TypeaheadInput::new("product-search", "/products/search")
+//!     .placeholder("Search products")
+//!     .render()?;
+//! ```
+
+use askama::Template;
+use axum::{
+    extract::{Query, Request, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::htmx::agents::TokenBucket;
+use crate::htmx::middleware::forwarded::client_ip;
+
+/// Buckets idle this long are dropped when the table is pruned
+const BUCKET_EXPIRATION: Duration = Duration::from_secs(300);
+
+/// Bucket count above which idle buckets are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Search callback type
+///
+/// Receives the sanitized query and returns results or an error message.
+pub type TypeaheadSearch = Arc<
+    dyn Fn(
+            TypeaheadQuery,
+        ) -> Pin<Box<dyn Future<Output = Result<Vec<TypeaheadResult>, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// A sanitized search query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeaheadQuery {
+    text: String,
+}
+
+impl TypeaheadQuery {
+    /// Sanitize raw input
+    ///
+    /// Control characters are dropped, whitespace runs collapse to a single
+    /// space, and the result is trimmed and truncated to `max_length`
+    /// characters.
+    #[must_use]
+    pub fn sanitize(raw: &str, max_length: usize) -> Self {
+        let text = raw
+            .split_whitespace()
+            .map(|word| word.chars().filter(|c| !c.is_control()).collect::<String>())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(max_length)
+            .collect::<String>()
+            .trim_end()
+            .to_string();
+        Self { text }
+    }
+
+    /// The sanitized text
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whitespace-separated search terms
+    pub fn terms(&self) -> impl Iterator<Item = &str> {
+        self.text.split(' ').filter(|term| !term.is_empty())
+    }
+
+    /// Length in characters
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Whether the query is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Substring `LIKE` pattern with `%`, `_` and `\` escaped
+    ///
+    /// Use with `ESCAPE '\'` so user input cannot inject wildcards.
+    #[must_use]
+    pub fn like_pattern(&self) -> String {
+        let mut pattern = String::with_capacity(self.text.len() + 2);
+        pattern.push('%');
+        for c in self.text.chars() {
+            if matches!(c, '%' | '_' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push('%');
+        pattern
+    }
+}
+
+/// A single search result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeaheadResult {
+    /// Text shown for the result; query terms are highlighted in it
+    pub label: String,
+    /// Link followed when the result is chosen
+    pub url: Option<String>,
+    /// Secondary text shown under the label
+    pub detail: Option<String>,
+}
+
+impl TypeaheadResult {
+    /// Create a result with a label
+    #[must_use]
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            url: None,
+            detail: None,
+        }
+    }
+
+    /// Link the result to `url`
+    #[must_use]
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the secondary text
+    #[must_use]
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// A run of label text, either matching a query term or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    /// The text
+    pub text: String,
+    /// Whether the text matches a query term
+    pub matched: bool,
+}
+
+/// Split `label` into runs, marking case-insensitive matches of any term
+#[must_use]
+pub fn highlight(label: &str, query: &TypeaheadQuery) -> Vec<Highlight> {
+    let terms: Vec<&str> = query.terms().collect();
+    let mut parts: Vec<Highlight> = Vec::new();
+    let mut push = |text: &str, matched: bool| match parts.last_mut() {
+        Some(last) if last.matched == matched => last.text.push_str(text),
+        _ => parts.push(Highlight {
+            text: text.to_string(),
+            matched,
+        }),
+    };
+    let mut rest = label;
+    while let Some(c) = rest.chars().next() {
+        let matched = terms
+            .iter()
+            .filter_map(|term| match_len(rest, term))
+            .max()
+            .filter(|&len| len > 0);
+        let len = matched.unwrap_or_else(|| c.len_utf8());
+        push(&rest[..len], matched.is_some());
+        rest = &rest[len..];
+    }
+    parts
+}
+
+/// Byte length of the prefix of `text` equal to `term` ignoring case
+fn match_len(text: &str, term: &str) -> Option<usize> {
+    let mut len = 0;
+    let mut chars = text.chars();
+    for expected in term.chars() {
+        let c = chars.next()?;
+        if !c.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+        len += c.len_utf8();
+    }
+    Some(len)
+}
+
+/// Debounced search input partial (`partials/typeahead.html`)
+///
+/// Results are swapped into a listbox with id `<id>-results`.
+#[derive(Debug, Clone, Template)]
+#[template(path = "partials/typeahead.html")]
+pub struct TypeaheadInput {
+    /// Element id prefix
+    pub id: String,
+    /// Search endpoint
+    pub url: String,
+    /// Initial input value
+    pub value: String,
+    /// Input placeholder
+    pub placeholder: String,
+    /// Indicator text shown while a request is in flight
+    pub loading: String,
+    /// Milliseconds to wait after the last keystroke
+    pub delay_ms: u64,
+}
+
+impl TypeaheadInput {
+    /// Create an input searching `url`, with a 300ms debounce
+    #[must_use]
+    pub fn new(id: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            url: url.into(),
+            value: String::new(),
+            placeholder: "Search".to_string(),
+            loading: "Searching…".to_string(),
+            delay_ms: 300,
+        }
+    }
+
+    /// Set the initial value
+    #[must_use]
+    pub fn value(mut self, value: impl Into<String>) -> Self {
+        self.value = value.into();
+        self
+    }
+
+    /// Set the placeholder
+    #[must_use]
+    pub fn placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = placeholder.into();
+        self
+    }
+
+    /// Set the indicator text
+    #[must_use]
+    pub fn loading(mut self, loading: impl Into<String>) -> Self {
+        self.loading = loading.into();
+        self
+    }
+
+    /// Set the debounce delay
+    #[must_use]
+    pub const fn delay_ms(mut self, delay_ms: u64) -> Self {
+        self.delay_ms = delay_ms;
+        self
+    }
+}
+
+/// A result ready to render, with its label split into highlight runs
+#[derive(Debug, Clone)]
+pub struct HighlightedResult {
+    /// Label runs
+    pub label: Vec<Highlight>,
+    /// Link followed when the result is chosen
+    pub url: Option<String>,
+    /// Secondary text
+    pub detail: Option<String>,
+}
+
+/// Search results partial (`partials/typeahead_results.html`)
+#[derive(Debug, Clone, Template)]
+#[template(path = "partials/typeahead_results.html")]
+pub struct TypeaheadResults {
+    /// Results in display order
+    pub results: Vec<HighlightedResult>,
+    /// Whether a search ran; when false an empty list renders nothing
+    pub searched: bool,
+    /// Text shown when a search found nothing
+    pub empty: String,
+}
+
+impl TypeaheadResults {
+    /// Highlight `results` for `query`
+    #[must_use]
+    pub fn new(query: &TypeaheadQuery, results: Vec<TypeaheadResult>) -> Self {
+        Self {
+            results: results
+                .into_iter()
+                .map(|result| HighlightedResult {
+                    label: highlight(&result.label, query),
+                    url: result.url,
+                    detail: result.detail,
+                })
+                .collect(),
+            searched: true,
+            empty: "No matches".to_string(),
+        }
+    }
+
+    /// Results for a query too short to search: renders nothing
+    #[must_use]
+    pub const fn cleared() -> Self {
+        Self {
+            results: Vec::new(),
+            searched: false,
+            empty: String::new(),
+        }
+    }
+
+    /// Set the text shown when nothing matched
+    #[must_use]
+    pub fn empty(mut self, empty: impl Into<String>) -> Self {
+        self.empty = empty.into();
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    #[serde(default)]
+    q: String,
+}
+
+/// Search-as-you-type endpoint
+///
+/// Defaults: queries shorter than 2 characters clear the results, queries
+/// are cut to 100 characters, at most 10 results are shown, and each client
+/// IP may search 10 times in a burst refilled at 5 per second. Requests over
+/// the limit get `429 Too Many Requests`, which htmx does not swap, so the
+/// previous results stay on screen.
+#[derive(Clone)]
+pub struct Typeahead {
+    search: TypeaheadSearch,
+    min_length: usize,
+    max_length: usize,
+    limit: usize,
+    empty: String,
+    burst: u32,
+    per_second: f64,
+    buckets: Arc<Mutex<HashMap<Option<IpAddr>, TokenBucket>>>,
+}
+
+impl std::fmt::Debug for Typeahead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Typeahead")
+            .field("min_length", &self.min_length)
+            .field("max_length", &self.max_length)
+            .field("limit", &self.limit)
+            .field("burst", &self.burst)
+            .field("per_second", &self.per_second)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Typeahead {
+    /// Create an endpoint calling `search` with each sanitized query
+    pub fn new<F, Fut>(search: F) -> Self
+    where
+        F: Fn(TypeaheadQuery) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<TypeaheadResult>, String>> + Send + 'static,
+    {
+        Self {
+            search: Arc::new(move |query| Box::pin(search(query))),
+            min_length: 2,
+            max_length: 100,
+            limit: 10,
+            empty: "No matches".to_string(),
+            burst: 10,
+            per_second: 5.0,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Set the minimum query length in characters
+    #[must_use]
+    pub const fn min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    /// Set the length queries are truncated to
+    #[must_use]
+    pub const fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Set the maximum number of results shown
+    #[must_use]
+    pub const fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Set the text shown when nothing matched
+    #[must_use]
+    pub fn empty(mut self, empty: impl Into<String>) -> Self {
+        self.empty = empty.into();
+        self
+    }
+
+    /// Allow `burst` searches per client, refilled at `per_second`
+    #[must_use]
+    pub const fn rate_limit(mut self, burst: u32, per_second: f64) -> Self {
+        self.burst = burst;
+        self.per_second = per_second;
+        self
+    }
+
+    /// Router serving the endpoint at `GET path?q=...`
+    pub fn routes<S>(&self, path: &str) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route(path, get(search_handler))
+            .with_state(self.clone())
+    }
+
+    /// Run a search for a client and render the results partial
+    ///
+    /// For handlers that extract the query themselves; [`routes`](Self::routes)
+    /// covers the common case.
+    pub async fn respond(&self, client: Option<IpAddr>, raw: &str) -> Response {
+        if !self.allow(client) {
+            return StatusCode::TOO_MANY_REQUESTS.into_response();
+        }
+        let query = TypeaheadQuery::sanitize(raw, self.max_length);
+        if query.len() < self.min_length.max(1) {
+            return render(&TypeaheadResults::cleared());
+        }
+        match (self.search)(query.clone()).await {
+            Ok(mut results) => {
+                results.truncate(self.limit);
+                render(&TypeaheadResults::new(&query, results).empty(self.empty.clone()))
+            }
+            Err(e) => {
+                warn!(error = %e, query = %query.as_str(), "Typeahead search failed");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Take a token from the client's bucket
+    fn allow(&self, client: Option<IpAddr>) -> bool {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| !bucket.is_expired(BUCKET_EXPIRATION));
+        }
+        let allowed = buckets
+            .entry(client)
+            .or_insert_with(|| TokenBucket::new(self.burst, self.per_second))
+            .try_consume(1);
+        drop(buckets);
+        allowed
+    }
+}
+
+fn render<T: Template>(template: &T) -> Response {
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            warn!(error = %e, "Failed to render typeahead partial");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn search_handler(
+    State(typeahead): State<Typeahead>,
+    Query(params): Query<SearchParams>,
+    request: Request,
+) -> Response {
+    typeahead
+        .respond(client_ip(request.extensions()), &params.q)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    fn query(text: &str) -> TypeaheadQuery {
+        TypeaheadQuery::sanitize(text, 100)
+    }
+
+    fn runs(parts: &[Highlight]) -> Vec<(&str, bool)> {
+        parts.iter().map(|p| (p.text.as_str(), p.matched)).collect()
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(query("  red \t\u{0}shoes\n ").as_str(), "red shoes");
+        assert_eq!(TypeaheadQuery::sanitize("abcdef ", 4).as_str(), "abcd");
+        assert_eq!(TypeaheadQuery::sanitize("ab cdef", 3).as_str(), "ab");
+        assert!(query(" \u{7} ").is_empty());
+        assert_eq!(query("50%_off\\").like_pattern(), r"%50\%\_off\\%");
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            runs(&highlight("Red Running Shoes", &query("re sho"))),
+            [
+                ("Re", true),
+                ("d Running ", false),
+                ("Sho", true),
+                ("es", false)
+            ]
+        );
+        assert_eq!(
+            runs(&highlight("Straße", &query("STRA"))),
+            [("Stra", true), ("ße", false)]
+        );
+        assert_eq!(runs(&highlight("abc", &query(""))), [("abc", false)]);
+    }
+
+    #[test]
+    fn test_partials_escape_and_mark() {
+        let results = TypeaheadResults::new(
+            &query("script"),
+            vec![TypeaheadResult::new("<script>")
+                .url("/x?a=1&b=2")
+                .detail("d")],
+        );
+        let html = results.render().unwrap();
+        assert!(html.contains("&#60;<mark>script</mark>&#62;"), "{html}");
+        assert!(html.contains(r#"<a href="/x?a=1&#38;b=2">"#), "{html}");
+        assert!(!TypeaheadResults::cleared()
+            .render()
+            .unwrap()
+            .contains("<li"));
+        assert!(TypeaheadResults::new(&query("zz"), vec![])
+            .render()
+            .unwrap()
+            .contains("No matches"));
+
+        let input = TypeaheadInput::new("product-search", "/search")
+            .render()
+            .unwrap();
+        assert!(
+            input.contains(r##"hx-target="#product-search-results""##),
+            "{input}"
+        );
+        assert!(input.contains("delay:300ms"), "{input}");
+    }
+
+    async fn get_status(app: &Router, uri: &str) -> (StatusCode, String) {
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_applies_min_length_limit_and_rate_limit() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let app = Typeahead::new(move |query: TypeaheadQuery| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                Ok((0..5)
+                    .map(|i| TypeaheadResult::new(format!("{} {i}", query.as_str())))
+                    .collect())
+            }
+        })
+        .limit(3)
+        .rate_limit(3, 0.0)
+        .routes::<()>("/search");
+
+        let (status, body) = get_status(&app, "/search?q=a").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!body.contains("<li"));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        let (status, body) = get_status(&app, "/search?q=shoe").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.matches("<li").count(), 3);
+        assert!(body.contains("<mark>shoe</mark>"));
+
+        let (status, _) = get_status(&app, "/search?q=boot").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get_status(&app, "/search?q=sock").await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,58 @@
+//! Small helpers shared across modules
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the Unix epoch, or 0 if the clock is before it
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Reject anything but `[A-Za-z_][A-Za-z0-9_]*`, optionally schema-qualified
+///
+/// For table and column names that have to be interpolated into SQL.
+///
+/// # Errors
+///
+/// Returns a message naming the identifier if it is not valid.
+pub fn validate_identifier(identifier: &str) -> Result<(), String> {
+    let valid = !identifier.is_empty()
+        && identifier.split('.').all(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid identifier {identifier:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unix_now() {
+        assert!(unix_now() > 1_600_000_000);
+    }
+
+    #[test]
+    fn test_validate_identifier() {
+        assert!(validate_identifier("posts").is_ok());
+        assert!(validate_identifier("blog.posts_2024").is_ok());
+        assert!(validate_identifier("_private").is_ok());
+        assert!(validate_identifier("posts; DROP TABLE users").is_err());
+        assert!(validate_identifier("1posts").is_err());
+        assert!(validate_identifier("blog.").is_err());
+        assert!(validate_identifier("").is_err());
+        assert_eq!(
+            validate_identifier("orders; --").unwrap_err(),
+            r#"invalid identifier "orders; --""#
+        );
+    }
+}
@@ -16,6 +16,8 @@
 //! - `otel-metrics` - OpenTelemetry metrics collection
 //! - `aws-ses` - AWS SES email backend
 //! - `clamav` - ClamAV virus scanning
+//! - `billing` - Stripe subscription billing
+//! - `testing` - Test fixtures for doctests and integration tests
 //!
 //! # Quick Start
 //!
@@ -77,36 +79,88 @@ pub mod cli;
 #[cfg(feature = "htmx")]
 pub use htmx::agents;
 #[cfg(feature = "htmx")]
+pub use htmx::analytics;
+#[cfg(feature = "htmx")]
 pub use htmx::auth;
+#[cfg(feature = "billing")]
+pub use htmx::billing;
+#[cfg(feature = "htmx")]
+pub use htmx::bulk;
 #[cfg(feature = "htmx")]
 pub use htmx::config;
 #[cfg(feature = "htmx")]
+pub use htmx::consent;
+#[cfg(feature = "htmx")]
 pub use htmx::email;
 #[cfg(feature = "htmx")]
+pub use htmx::encryption;
+#[cfg(feature = "htmx")]
 pub use htmx::error;
 #[cfg(feature = "htmx")]
+pub use htmx::experiments;
+#[cfg(feature = "htmx")]
+pub use htmx::export;
+#[cfg(feature = "htmx")]
 pub use htmx::extractors;
 #[cfg(feature = "htmx")]
+pub use htmx::feedback;
+#[cfg(feature = "htmx")]
 pub use htmx::forms;
 #[cfg(feature = "htmx")]
 pub use htmx::handlers;
 #[cfg(feature = "htmx")]
 pub use htmx::health;
+#[cfg(feature = "http3")]
+pub use htmx::http3;
+#[cfg(feature = "htmx")]
+pub use htmx::invitations;
 #[cfg(feature = "htmx")]
 pub use htmx::jobs;
 #[cfg(feature = "htmx")]
+pub use htmx::locking;
+#[cfg(feature = "markdown")]
+pub use htmx::markdown;
+#[cfg(feature = "htmx")]
 pub use htmx::middleware;
 #[cfg(feature = "htmx")]
+pub use htmx::money;
+#[cfg(feature = "htmx")]
 pub use htmx::oauth2;
 #[cfg(feature = "htmx")]
 pub use htmx::observability;
 #[cfg(feature = "htmx")]
+pub use htmx::pagination;
+#[cfg(feature = "htmx")]
 pub use htmx::prelude;
 #[cfg(feature = "htmx")]
+pub use htmx::presence;
+#[cfg(feature = "htmx")]
+pub use htmx::privacy;
+#[cfg(feature = "htmx")]
+pub use htmx::proxy_protocol;
+#[cfg(feature = "htmx")]
 pub use htmx::responses;
 #[cfg(feature = "htmx")]
+pub use htmx::routing;
+#[cfg(feature = "sanitize")]
+pub use htmx::sanitize;
+#[cfg(feature = "htmx")]
+pub use htmx::sitemap;
+#[cfg(feature = "htmx")]
+pub use htmx::slug;
+#[cfg(feature = "htmx")]
+pub use htmx::social;
+#[cfg(feature = "htmx")]
 pub use htmx::state;
 #[cfg(feature = "htmx")]
 pub use htmx::storage;
 #[cfg(feature = "htmx")]
+pub use htmx::teams;
+#[cfg(feature = "htmx")]
 pub use htmx::template;
+#[cfg(feature = "testing")]
+pub use htmx::testing;
+#[cfg(feature = "htmx")]
+pub use htmx::timezone;
+#[cfg(feature = "htmx")]
+pub use htmx::typeahead;
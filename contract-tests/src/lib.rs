@@ -0,0 +1,435 @@
+//! Contract tests between the Acton DX clients and services
+//!
+//! Every RPC has a golden fixture under `fixtures/<service>/<rpc>.json`
+//! holding a request and the response (or error) it must produce:
+//!
+//! ```json
+//! {
+//!   "request": { "file_id": "$file_id" },
+//!   "response": { "id": "$file_id", "created_at": "*" }
+//! }
+//! ```
+//!
+//! The tests run each fixture against the real service implementation,
+//! embedded behind a gRPC server on a local port, and again through the
+//! matching `acton-dx` client. Fixtures use the proto field names and
+//! unknown fields are rejected, so renaming or removing a field, or a
+//! `oneof` case, fails the contract instead of silently dropping data.
+//!
+//! In expected responses, `"*"` matches any value and `"$name"` captures
+//! the value into a variable (or must equal it, once captured). Requests
+//! substitute `"$name"` with the variable's value. Objects match when the
+//! expected fields match, so fixtures only list what the clients rely on.
+
+use acton_dx::htmx::clients::ClientError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::service::Routes;
+use tonic::transport::Server;
+use tonic::Status;
+
+/// Matches any value in an expected response
+const WILDCARD: &str = "*";
+
+/// Serve `routes` on an ephemeral local port and return the endpoint URL
+///
+/// The server runs until the test's runtime shuts down.
+///
+/// # Panics
+///
+/// Panics if no local port can be bound.
+pub async fn serve(routes: Routes) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind contract test server");
+    let address = listener.local_addr().expect("local address");
+    tokio::spawn(
+        Server::builder()
+            .add_routes(routes)
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    format!("http://{address}")
+}
+
+/// Error a fixture expects instead of a response
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedError {
+    /// gRPC status code, e.g. `InvalidArgument`
+    pub code: String,
+    /// Machine-readable code of the attached error detail
+    #[serde(default)]
+    pub detail: Option<String>,
+    /// Request fields reported as invalid
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// Retry delay the service asks for
+    #[serde(default)]
+    pub retry_after_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureFile {
+    request: Value,
+    #[serde(default)]
+    response: Option<Value>,
+    #[serde(default)]
+    error: Option<ExpectedError>,
+}
+
+/// A golden request with its expected response or error
+#[derive(Debug)]
+pub struct Fixture {
+    name: String,
+    request: Value,
+    response: Option<Value>,
+    error: Option<ExpectedError>,
+    vars: HashMap<String, Value>,
+}
+
+impl Fixture {
+    /// Load `fixtures/<name>.json`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fixture is missing, is not valid JSON, or has neither
+    /// a response nor an error.
+    #[must_use]
+    pub fn load(name: &str) -> Self {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join(format!("{name}.json"));
+        let text = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("read fixture {}: {e}", path.display()));
+        let file: FixtureFile = serde_json::from_str(&text)
+            .unwrap_or_else(|e| panic!("parse fixture {}: {e}", path.display()));
+        assert!(
+            file.response.is_some() != file.error.is_some(),
+            "fixture {name} needs exactly one of response and error"
+        );
+        Self {
+            name: name.to_string(),
+            request: file.request,
+            response: file.response,
+            error: file.error,
+            vars: HashMap::new(),
+        }
+    }
+
+    /// Set a variable used by the request or matched in the response
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` cannot be represented as JSON.
+    #[must_use]
+    pub fn with_var(mut self, name: &str, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).expect("variable as JSON");
+        self.vars.insert(name.to_string(), value);
+        self
+    }
+
+    /// Value of a variable set or captured so far
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variable is unknown.
+    #[must_use]
+    pub fn var(&self, name: &str) -> &Value {
+        self.vars
+            .get(name)
+            .unwrap_or_else(|| panic!("fixture {}: unknown variable ${name}", self.name))
+    }
+
+    /// The request, decoded into the generated proto message
+    ///
+    /// # Panics
+    ///
+    /// Panics if the request does not match the message, e.g. because a
+    /// field it names no longer exists.
+    #[must_use]
+    pub fn request<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_value(self.substitute(&self.request))
+            .unwrap_or_else(|e| panic!("fixture {}: request does not decode: {e}", self.name))
+    }
+
+    /// A field of the request, for calling the clients' typed methods
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field is missing or has another type.
+    #[must_use]
+    pub fn request_field<T: DeserializeOwned>(&self, pointer: &str) -> T {
+        Self::field(&self.name, &self.substitute(&self.request), pointer)
+    }
+
+    /// A field of the expected response, for checking what a client decoded
+    ///
+    /// Variables are substituted, so captured values can be compared.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fixture expects an error or the field is missing.
+    #[must_use]
+    pub fn expected<T: DeserializeOwned>(&self, pointer: &str) -> T {
+        let response = self
+            .response
+            .as_ref()
+            .unwrap_or_else(|| panic!("fixture {} expects an error", self.name));
+        Self::field(&self.name, &self.substitute(response), pointer)
+    }
+
+    /// Check a response against the fixture, capturing `$name` variables
+    ///
+    /// # Panics
+    ///
+    /// Panics if the response does not match.
+    pub fn assert_response<T: Serialize>(&mut self, actual: &T) {
+        let expected = self
+            .response
+            .clone()
+            .unwrap_or_else(|| panic!("fixture {} expects an error", self.name));
+        let actual = serde_json::to_value(actual).expect("response as JSON");
+        if let Err(mismatch) = self.matches(&expected, &actual, "") {
+            panic!(
+                "fixture {}: {mismatch}\nactual response: {actual:#}",
+                self.name
+            );
+        }
+    }
+
+    /// Check a call's outcome: the response, or the error status
+    ///
+    /// # Panics
+    ///
+    /// Panics if the outcome does not match.
+    pub fn assert_outcome<T: Serialize>(&mut self, outcome: &Result<tonic::Response<T>, Status>) {
+        match outcome {
+            Ok(response) if self.error.is_none() => self.assert_response(response.get_ref()),
+            Err(status) if self.error.is_some() => self.assert_status(status),
+            Ok(_) => panic!("fixture {}: expected an error, got a response", self.name),
+            Err(status) => panic!("fixture {}: unexpected error {status:?}", self.name),
+        }
+    }
+
+    /// Check an error status against the fixture
+    ///
+    /// # Panics
+    ///
+    /// Panics if the status does not match.
+    pub fn assert_status(&self, status: &Status) {
+        let expected = self.expected_error();
+        assert_eq!(
+            format!("{:?}", status.code()),
+            expected.code,
+            "fixture {}: status code ({status:?})",
+            self.name
+        );
+
+        let detail = acton_dx_proto::error::v1::ErrorDetail::from_status(status);
+        if let Some(code) = &expected.detail {
+            let detail = detail
+                .as_ref()
+                .unwrap_or_else(|| panic!("fixture {}: no error detail attached", self.name));
+            assert_eq!(&detail.code, code, "fixture {}: detail code", self.name);
+        }
+        let fields: Vec<&str> = detail
+            .iter()
+            .flat_map(|detail| {
+                detail
+                    .field_violations
+                    .iter()
+                    .map(|violation| violation.field.as_str())
+            })
+            .collect();
+        assert_eq!(
+            fields, expected.fields,
+            "fixture {}: invalid fields",
+            self.name
+        );
+        assert_eq!(
+            detail.and_then(|detail| detail.retry_after_ms),
+            expected.retry_after_ms,
+            "fixture {}: retry after",
+            self.name
+        );
+    }
+
+    /// Check the error an `acton-dx` client decoded against the fixture
+    ///
+    /// # Panics
+    ///
+    /// Panics if the client decoded another error.
+    pub fn assert_client_error(&self, error: &ClientError) {
+        let expected = self.expected_error();
+        if expected.fields.is_empty() {
+            assert!(
+                error.validation_errors().is_none(),
+                "fixture {}: client reported invalid fields ({error})",
+                self.name
+            );
+        } else {
+            let errors = error.validation_errors().unwrap_or_else(|| {
+                panic!(
+                    "fixture {}: client reported no invalid fields ({error})",
+                    self.name
+                )
+            });
+            let mut fields: Vec<String> = errors
+                .fields_with_errors()
+                .into_iter()
+                .map(ToString::to_string)
+                .collect();
+            fields.sort();
+            let mut expected_fields = expected.fields.clone();
+            expected_fields.sort();
+            assert_eq!(
+                fields, expected_fields,
+                "fixture {}: client fields",
+                self.name
+            );
+        }
+        assert_eq!(
+            error.retry_after(),
+            expected.retry_after_ms.map(Duration::from_millis),
+            "fixture {}: client retry after",
+            self.name
+        );
+    }
+
+    fn expected_error(&self) -> &ExpectedError {
+        self.error
+            .as_ref()
+            .unwrap_or_else(|| panic!("fixture {} expects a response", self.name))
+    }
+
+    fn field<T: DeserializeOwned>(name: &str, value: &Value, pointer: &str) -> T {
+        let field = value
+            .pointer(pointer)
+            .unwrap_or_else(|| panic!("fixture {name}: no field {pointer}"));
+        serde_json::from_value(field.clone())
+            .unwrap_or_else(|e| panic!("fixture {name}: field {pointer}: {e}"))
+    }
+
+    /// Replace `"$name"` strings with the variables' values
+    fn substitute(&self, value: &Value) -> Value {
+        match value {
+            Value::String(text) => text
+                .strip_prefix('$')
+                .map_or_else(|| value.clone(), |name| self.var(name).clone()),
+            Value::Array(items) => items.iter().map(|item| self.substitute(item)).collect(),
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(key, item)| (key.clone(), self.substitute(item)))
+                .collect(),
+            _ => value.clone(),
+        }
+    }
+
+    fn matches(&mut self, expected: &Value, actual: &Value, path: &str) -> Result<(), String> {
+        match expected {
+            Value::String(text) if text == WILDCARD => Ok(()),
+            Value::String(text) if text.starts_with('$') => {
+                let name = &text[1..];
+                match self.vars.get(name) {
+                    Some(value) if value == actual => Ok(()),
+                    Some(value) => Err(format!("{path}: expected ${name} = {value}, got {actual}")),
+                    None => {
+                        self.vars.insert(name.to_string(), actual.clone());
+                        Ok(())
+                    }
+                }
+            }
+            Value::Object(fields) => {
+                let Value::Object(actual_fields) = actual else {
+                    return Err(format!("{path}: expected an object, got {actual}"));
+                };
+                for (key, expected_field) in fields {
+                    let field_path = format!("{path}/{key}");
+                    let actual_field = actual_fields
+                        .get(key)
+                        .ok_or_else(|| format!("{field_path}: missing from the response"))?;
+                    self.matches(expected_field, actual_field, &field_path)?;
+                }
+                Ok(())
+            }
+            Value::Array(items) => {
+                let Value::Array(actual_items) = actual else {
+                    return Err(format!("{path}: expected an array, got {actual}"));
+                };
+                if items.len() != actual_items.len() {
+                    return Err(format!(
+                        "{path}: expected {} items, got {}",
+                        items.len(),
+                        actual_items.len()
+                    ));
+                }
+                for (index, (item, actual_item)) in items.iter().zip(actual_items).enumerate() {
+                    self.matches(item, actual_item, &format!("{path}/{index}"))?;
+                }
+                Ok(())
+            }
+            _ if expected == actual => Ok(()),
+            _ => Err(format!("{path}: expected {expected}, got {actual}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixture(response: Value) -> Fixture {
+        Fixture {
+            name: "test".to_string(),
+            request: json!({ "id": "$id", "tags": ["$id", "plain"] }),
+            response: Some(response),
+            error: None,
+            vars: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_partial_match_with_wildcards_and_captures() {
+        let mut fixture = fixture(json!({ "id": "$id", "created_at": "*", "items": [{ "n": 1 }] }));
+        fixture.assert_response(&json!({
+            "id": "abc",
+            "created_at": 1_700_000_000,
+            "size": 3,
+            "items": [{ "n": 1, "extra": true }],
+        }));
+
+        assert_eq!(fixture.var("id"), &json!("abc"));
+        assert_eq!(
+            fixture.request::<Value>(),
+            json!({ "id": "abc", "tags": ["abc", "plain"] })
+        );
+        assert_eq!(fixture.expected::<String>("/id"), "abc");
+    }
+
+    #[test]
+    #[should_panic(expected = "/file_id: missing from the response")]
+    fn test_renamed_response_field_fails() {
+        fixture(json!({ "file_id": "*" })).assert_response(&json!({ "id": "abc" }));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 1 items, got 2")]
+    fn test_arrays_match_exactly() {
+        fixture(json!({ "items": [1] })).assert_response(&json!({ "items": [1, 2] }));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected $id = \"abc\"")]
+    fn test_captured_variables_must_repeat() {
+        fixture(json!({ "a": "$id", "b": "$id" }))
+            .assert_response(&json!({ "a": "abc", "b": "xyz" }));
+    }
+}
@@ -0,0 +1,893 @@
+//! Auth service contracts, against the in-memory services
+
+use acton_dx::htmx::clients::AuthClient;
+use acton_dx_proto::auth::v1::{
+    api_key_service_client::ApiKeyServiceClient, api_key_service_server::ApiKeyServiceServer,
+    oidc_provider_service_client::OidcProviderServiceClient,
+    oidc_provider_service_server::OidcProviderServiceServer,
+    passkey_service_client::PasskeyServiceClient, passkey_service_server::PasskeyServiceServer,
+    session_service_client::SessionServiceClient, session_service_server::SessionServiceServer,
+    token_service_client::TokenServiceClient, token_service_server::TokenServiceServer,
+    user_service_client::UserServiceClient, AddFlashMessageRequest, CreateApiKeyRequest,
+    CreateSessionRequest, CreateUserRequest, DeleteOidcClientRequest, DeletePasskeyRequest,
+    DestroySessionRequest, FinishPasskeyAuthenticationRequest, FinishPasskeyRegistrationRequest,
+    FlashMessage, GetFlashMessagesRequest, GetJwksRequest, GetOidcMetadataRequest, GetUserRequest,
+    IssueAccessTokenRequest, ListApiKeysRequest, ListOidcClientsRequest, ListPasskeysRequest,
+    OidcAuthorizeRequest, OidcExchangeCodeRequest, OidcUserInfoRequest, RefreshSessionRequest,
+    RegisterOidcClientRequest, RevokeApiKeyRequest, StartPasskeyAuthenticationRequest,
+    StartPasskeyRegistrationRequest, UpdateSessionRequest, ValidateApiKeyRequest,
+    ValidateSessionRequest, VerifyAccessTokenRequest,
+};
+use acton_reactive::prelude::ActonApp;
+use auth_service::config::{OidcConfig, PasskeyConfig, TokenConfig};
+use auth_service::{
+    ApiKeyServiceImpl, ApiKeys, OidcProvider, OidcProviderServiceImpl, PasskeyServiceImpl,
+    SessionManagerAgent, SessionServiceImpl, TokenIssuer, TokenServiceImpl,
+};
+use contract_tests::{serve, Fixture};
+use std::collections::HashMap;
+use tonic::service::Routes;
+use tonic::transport::Channel;
+
+/// The auth services with default configuration, keeping everything in
+/// memory. No service implements `UserService`.
+async fn routes() -> Routes {
+    let mut runtime = ActonApp::launch_async().await;
+    let sessions = SessionManagerAgent::spawn(&mut runtime, 300).await.unwrap();
+    let issuer = TokenIssuer::new(&TokenConfig::default()).unwrap();
+    let provider = OidcProvider::new(&OidcConfig::default(), issuer.clone()).unwrap();
+    Routes::new(SessionServiceServer::new(SessionServiceImpl::new(
+        sessions.clone(),
+    )))
+    .add_service(TokenServiceServer::new(TokenServiceImpl::with_issuer(
+        sessions.clone(),
+        issuer,
+    )))
+    .add_service(PasskeyServiceServer::new(
+        PasskeyServiceImpl::new(&PasskeyConfig::default()).unwrap(),
+    ))
+    .add_service(ApiKeyServiceServer::new(ApiKeyServiceImpl::new(
+        ApiKeys::new(),
+    )))
+    .add_service(OidcProviderServiceServer::new(
+        OidcProviderServiceImpl::new(sessions, provider),
+    ))
+}
+
+/// Two auth services: one called through the generated clients, one
+/// through the `acton-dx` client
+async fn connect() -> (Channel, AuthClient) {
+    let raw = serve(routes().await).await;
+    let client = serve(routes().await).await;
+    (
+        Channel::from_shared(raw).unwrap().connect().await.unwrap(),
+        AuthClient::connect(client).await.unwrap(),
+    )
+}
+
+/// Sign in on both services; returns the fixture holding the raw
+/// session's `$session_id`, and the client's session ID
+async fn sign_in(channel: &Channel, client: &mut AuthClient) -> (Fixture, String) {
+    let mut raw = SessionServiceClient::new(channel.clone());
+    let mut fixture = Fixture::load("auth/create_session");
+    fixture.assert_outcome(
+        &raw.create_session(fixture.request::<CreateSessionRequest>())
+            .await,
+    );
+    let session = client
+        .create_session(
+            fixture.request_field("/user_id"),
+            fixture.request_field("/ttl_seconds"),
+            fixture.request_field("/initial_data"),
+        )
+        .await
+        .unwrap();
+    (fixture, session.session_id)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sessions() {
+    let (channel, mut client) = connect().await;
+    let mut raw = SessionServiceClient::new(channel);
+
+    let mut create = Fixture::load("auth/create_session");
+    create.assert_outcome(
+        &raw.create_session(create.request::<CreateSessionRequest>())
+            .await,
+    );
+    let session = client
+        .create_session(
+            create.request_field("/user_id"),
+            create.request_field("/ttl_seconds"),
+            create.request_field("/initial_data"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        session.user_id,
+        create.expected::<Option<i64>>("/session/user_id")
+    );
+    assert_eq!(
+        session.data,
+        create.expected::<HashMap<String, String>>("/session/data")
+    );
+    let session_id = create.var("session_id").clone();
+
+    let mut fixture = Fixture::load("auth/validate_session").with_var("session_id", &session_id);
+    fixture.assert_outcome(
+        &raw.validate_session(fixture.request::<ValidateSessionRequest>())
+            .await,
+    );
+    let validated = client
+        .validate_session(&session.session_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        validated.data,
+        fixture.expected::<HashMap<String, String>>("/session/data")
+    );
+
+    let mut fixture = Fixture::load("auth/validate_session_unknown");
+    fixture.assert_outcome(
+        &raw.validate_session(fixture.request::<ValidateSessionRequest>())
+            .await,
+    );
+    let validated = client
+        .validate_session(&fixture.request_field::<String>("/session_id"))
+        .await
+        .unwrap();
+    assert!(validated.is_none());
+
+    let mut fixture = Fixture::load("auth/update_session").with_var("session_id", &session_id);
+    fixture.assert_outcome(
+        &raw.update_session(fixture.request::<UpdateSessionRequest>())
+            .await,
+    );
+    let updated = client
+        .update_session(&session.session_id, fixture.request_field("/data"), None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        updated.data,
+        fixture.expected::<HashMap<String, String>>("/session/data")
+    );
+
+    let mut fixture = Fixture::load("auth/update_session_unknown");
+    fixture.assert_outcome(
+        &raw.update_session(fixture.request::<UpdateSessionRequest>())
+            .await,
+    );
+    let updated = client
+        .update_session(
+            &fixture.request_field::<String>("/session_id"),
+            fixture.request_field("/data"),
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(updated.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_flash_messages() {
+    let (channel, mut client) = connect().await;
+    let (signed_in, session_id) = sign_in(&channel, &mut client).await;
+    let mut raw = SessionServiceClient::new(channel);
+
+    let mut fixture =
+        Fixture::load("auth/add_flash_message").with_var("session_id", signed_in.var("session_id"));
+    fixture.assert_outcome(
+        &raw.add_flash_message(fixture.request::<AddFlashMessageRequest>())
+            .await,
+    );
+    let added = client
+        .add_flash_message(
+            &session_id,
+            &fixture.request_field::<String>("/flash/level"),
+            &fixture.request_field::<String>("/flash/message"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(added, fixture.expected::<bool>("/success"));
+
+    // The client always sends a flash message, so only the wire contract
+    // applies
+    let mut fixture = Fixture::load("auth/add_flash_message_without_flash")
+        .with_var("session_id", signed_in.var("session_id"));
+    fixture.assert_outcome(
+        &raw.add_flash_message(fixture.request::<AddFlashMessageRequest>())
+            .await,
+    );
+
+    let mut fixture = Fixture::load("auth/get_flash_messages")
+        .with_var("session_id", signed_in.var("session_id"));
+    fixture.assert_outcome(
+        &raw.get_and_clear_flash_messages(fixture.request::<GetFlashMessagesRequest>())
+            .await,
+    );
+    let messages = client
+        .get_and_clear_flash_messages(&session_id)
+        .await
+        .unwrap();
+    assert_eq!(messages, fixture.expected::<Vec<FlashMessage>>("/messages"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_session_refresh() {
+    let (channel, mut client) = connect().await;
+    let mut raw = SessionServiceClient::new(channel);
+
+    let mut create = Fixture::load("auth/create_session");
+    create.assert_outcome(
+        &raw.create_session(create.request::<CreateSessionRequest>())
+            .await,
+    );
+    let (session, refresh_token) = client
+        .create_session_with_refresh_token(
+            create.request_field("/user_id"),
+            create.request_field("/ttl_seconds"),
+            create.request_field("/initial_data"),
+        )
+        .await
+        .unwrap();
+    let session_id = create.var("session_id").clone();
+
+    let mut refresh = Fixture::load("auth/refresh_session")
+        .with_var("refresh_token", create.var("refresh_token"));
+    refresh.assert_outcome(
+        &raw.refresh_session(refresh.request::<RefreshSessionRequest>())
+            .await,
+    );
+    assert_ne!(refresh.var("refreshed_session_id"), &session_id);
+    let (refreshed, _) = client
+        .refresh_session(&refresh_token, refresh.request_field("/ttl_seconds"))
+        .await
+        .unwrap();
+    assert_ne!(refreshed.session_id, session.session_id);
+    assert_eq!(
+        refreshed.data,
+        refresh.expected::<HashMap<String, String>>("/session/data")
+    );
+
+    // The refresh token was rotated, so it cannot be used again
+    let fixture = Fixture::load("auth/refresh_session_invalid");
+    let status = raw
+        .refresh_session(fixture.request::<RefreshSessionRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    for token in [
+        fixture.request_field::<String>("/refresh_token"),
+        refresh_token,
+    ] {
+        let error = client.refresh_session(&token, 0).await.unwrap_err();
+        fixture.assert_client_error(&error);
+    }
+
+    let mut fixture = Fixture::load("auth/destroy_session")
+        .with_var("session_id", refresh.var("refreshed_session_id"));
+    fixture.assert_outcome(
+        &raw.destroy_session(fixture.request::<DestroySessionRequest>())
+            .await,
+    );
+    let destroyed = client.destroy_session(&refreshed.session_id).await.unwrap();
+    assert_eq!(destroyed, fixture.expected::<bool>("/success"));
+    assert!(client
+        .validate_session(&refreshed.session_id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_access_tokens() {
+    let (channel, mut client) = connect().await;
+    let (signed_in, session_id) = sign_in(&channel, &mut client).await;
+    let mut raw = TokenServiceClient::new(channel);
+
+    let mut issue = Fixture::load("auth/issue_access_token")
+        .with_var("session_id", signed_in.var("session_id"));
+    issue.assert_outcome(
+        &raw.issue_access_token(issue.request::<IssueAccessTokenRequest>())
+            .await,
+    );
+    let issued = client
+        .issue_access_token(
+            &session_id,
+            issue
+                .request_field::<Option<String>>("/audience")
+                .as_deref(),
+            issue.request_field("/scopes"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(issued.token_type, issue.expected::<String>("/token_type"));
+    assert_eq!(issued.expires_in, issue.expected::<i64>("/expires_in"));
+    assert_eq!(issued.scope, issue.expected::<String>("/scope"));
+
+    for name in [
+        "auth/issue_access_token_unknown_audience",
+        "auth/issue_access_token_signed_out",
+    ] {
+        let fixture = Fixture::load(name).with_var("session_id", signed_in.var("session_id"));
+        let status = raw
+            .issue_access_token(fixture.request::<IssueAccessTokenRequest>())
+            .await
+            .unwrap_err();
+        fixture.assert_status(&status);
+        let request = fixture.request::<IssueAccessTokenRequest>();
+        let session_id = if request.session_id == *signed_in.var("session_id") {
+            &session_id
+        } else {
+            &request.session_id
+        };
+        let error = client
+            .issue_access_token(session_id, request.audience.as_deref(), request.scopes)
+            .await
+            .unwrap_err();
+        fixture.assert_client_error(&error);
+    }
+
+    let mut fixture = Fixture::load("auth/verify_access_token")
+        .with_var("access_token", issue.var("access_token"))
+        .with_var("session_id", signed_in.var("session_id"));
+    fixture.assert_outcome(
+        &raw.verify_access_token(fixture.request::<VerifyAccessTokenRequest>())
+            .await,
+    );
+    let claims = client
+        .verify_access_token(
+            &issued.access_token,
+            fixture
+                .request_field::<Option<String>>("/audience")
+                .as_deref(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(claims.user_id, fixture.expected::<i64>("/claims/user_id"));
+    assert_eq!(
+        claims.audience,
+        fixture.expected::<String>("/claims/audience")
+    );
+    assert_eq!(
+        claims.scopes,
+        fixture.expected::<Vec<String>>("/claims/scopes")
+    );
+    assert_eq!(claims.session_id, session_id);
+
+    for name in [
+        "auth/verify_access_token_wrong_audience",
+        "auth/verify_access_token_invalid",
+    ] {
+        let mut fixture = Fixture::load(name).with_var("access_token", issue.var("access_token"));
+        fixture.assert_outcome(
+            &raw.verify_access_token(fixture.request::<VerifyAccessTokenRequest>())
+                .await,
+        );
+        let request = fixture.request::<VerifyAccessTokenRequest>();
+        let token = if request.access_token == *issue.var("access_token") {
+            &issued.access_token
+        } else {
+            &request.access_token
+        };
+        let claims = client
+            .verify_access_token(token, request.audience.as_deref())
+            .await
+            .unwrap();
+        assert!(claims.is_none());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_jwks() {
+    let (channel, mut client) = connect().await;
+    let mut raw = TokenServiceClient::new(channel);
+
+    let mut fixture = Fixture::load("auth/get_jwks");
+    let response = raw.get_jwks(GetJwksRequest {}).await;
+    fixture.assert_outcome(&response);
+    for jwks in [
+        response.unwrap().into_inner().jwks_json,
+        client.access_token_jwks().await.unwrap(),
+    ] {
+        let jwks: serde_json::Value = serde_json::from_str(&jwks).unwrap();
+        assert_eq!(jwks["keys"][0]["kty"], "OKP");
+        assert_eq!(jwks["keys"][0]["alg"], "EdDSA");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_passkey_registration() {
+    let (channel, mut client) = connect().await;
+    let mut raw = PasskeyServiceClient::new(channel);
+
+    let mut start = Fixture::load("auth/start_passkey_registration");
+    start.assert_outcome(
+        &raw.start_registration(start.request::<StartPasskeyRegistrationRequest>())
+            .await,
+    );
+    let started = client
+        .start_passkey_registration(
+            start.request_field("/user_id"),
+            &start.request_field::<String>("/user_name"),
+            &start.request_field::<String>("/display_name"),
+        )
+        .await
+        .unwrap();
+    let options: serde_json::Value = serde_json::from_str(&started.options_json).unwrap();
+    assert_eq!(
+        options["publicKey"]["user"]["name"],
+        start.request_field::<String>("/user_name")
+    );
+
+    let fixture = Fixture::load("auth/start_passkey_registration_without_name");
+    let status = raw
+        .start_registration(fixture.request::<StartPasskeyRegistrationRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .start_passkey_registration(
+            fixture.request_field("/user_id"),
+            &fixture.request_field::<String>("/user_name"),
+            &fixture.request_field::<String>("/display_name"),
+        )
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    // Finishing needs an authenticator; a bad credential still ends the
+    // ceremony, so each case starts its own
+    for name in [
+        "auth/finish_passkey_registration_other_user",
+        "auth/finish_passkey_registration_invalid",
+    ] {
+        let mut start = Fixture::load("auth/start_passkey_registration");
+        start.assert_outcome(
+            &raw.start_registration(start.request::<StartPasskeyRegistrationRequest>())
+                .await,
+        );
+        let fixture = Fixture::load(name).with_var("ceremony_id", start.var("ceremony_id"));
+        let status = raw
+            .finish_registration(fixture.request::<FinishPasskeyRegistrationRequest>())
+            .await
+            .unwrap_err();
+        fixture.assert_status(&status);
+
+        let started = client
+            .start_passkey_registration(
+                start.request_field("/user_id"),
+                &start.request_field::<String>("/user_name"),
+                &start.request_field::<String>("/display_name"),
+            )
+            .await
+            .unwrap();
+        let error = client
+            .finish_passkey_registration(
+                &started.ceremony_id,
+                fixture.request_field("/user_id"),
+                &fixture.request_field::<String>("/credential_json"),
+                &fixture.request_field::<String>("/name"),
+            )
+            .await
+            .unwrap_err();
+        fixture.assert_client_error(&error);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_passkeys() {
+    let (channel, mut client) = connect().await;
+    let mut raw = PasskeyServiceClient::new(channel);
+
+    let fixture = Fixture::load("auth/start_passkey_authentication_without_passkeys");
+    let status = raw
+        .start_authentication(fixture.request::<StartPasskeyAuthenticationRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .start_passkey_authentication(fixture.request_field("/user_id"))
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let fixture = Fixture::load("auth/finish_passkey_authentication_unknown");
+    let status = raw
+        .finish_authentication(fixture.request::<FinishPasskeyAuthenticationRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .finish_passkey_authentication(
+            &fixture.request_field::<String>("/ceremony_id"),
+            &fixture.request_field::<String>("/credential_json"),
+        )
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture = Fixture::load("auth/list_passkeys");
+    fixture.assert_outcome(
+        &raw.list_passkeys(fixture.request::<ListPasskeysRequest>())
+            .await,
+    );
+    let passkeys = client
+        .list_passkeys(fixture.request_field("/user_id"))
+        .await
+        .unwrap();
+    assert!(passkeys.is_empty());
+
+    let mut fixture = Fixture::load("auth/delete_passkey_unknown");
+    fixture.assert_outcome(
+        &raw.delete_passkey(fixture.request::<DeletePasskeyRequest>())
+            .await,
+    );
+    let deleted = client
+        .delete_passkey(
+            fixture.request_field("/user_id"),
+            &fixture.request_field::<String>("/credential_id"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(deleted, fixture.expected::<bool>("/success"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_api_keys() {
+    let (channel, mut client) = connect().await;
+    let mut raw = ApiKeyServiceClient::new(channel);
+
+    let mut create = Fixture::load("auth/create_api_key");
+    create.assert_outcome(
+        &raw.create_api_key(create.request::<CreateApiKeyRequest>())
+            .await,
+    );
+    let created = client
+        .create_api_key(
+            &create.request_field::<String>("/name"),
+            create.request_field("/user_id"),
+            create.request_field("/scopes"),
+            create.request_field("/ttl_seconds"),
+        )
+        .await
+        .unwrap();
+    let key = created.key.unwrap();
+    assert_eq!(key.name, create.expected::<String>("/key/name"));
+    assert_eq!(key.scopes, create.expected::<Vec<String>>("/key/scopes"));
+    assert!(key.expires_at.is_some());
+
+    let fixture = Fixture::load("auth/create_api_key_without_name");
+    let status = raw
+        .create_api_key(fixture.request::<CreateApiKeyRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .create_api_key(
+            &fixture.request_field::<String>("/name"),
+            None,
+            fixture.request_field("/scopes"),
+            None,
+        )
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture = Fixture::load("auth/list_api_keys").with_var("key_id", create.var("key_id"));
+    fixture.assert_outcome(
+        &raw.list_api_keys(fixture.request::<ListApiKeysRequest>())
+            .await,
+    );
+    let keys = client
+        .list_api_keys(
+            fixture.request_field("/user_id"),
+            fixture.request_field("/include_revoked"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].key_id, key.key_id);
+
+    for name in [
+        "auth/validate_api_key",
+        "auth/validate_api_key_missing_scope",
+    ] {
+        let mut fixture = Fixture::load(name)
+            .with_var("api_key", create.var("api_key"))
+            .with_var("key_id", create.var("key_id"));
+        fixture.assert_outcome(
+            &raw.validate_api_key(fixture.request::<ValidateApiKeyRequest>())
+                .await,
+        );
+        let validated = client
+            .validate_api_key(&created.api_key, fixture.request_field("/required_scopes"))
+            .await
+            .unwrap();
+        assert_eq!(validated.valid, fixture.expected::<bool>("/valid"));
+        assert_eq!(validated.key.unwrap().key_id, key.key_id);
+        assert_eq!(
+            validated.missing_scopes,
+            fixture.expected::<Vec<String>>("/missing_scopes")
+        );
+    }
+
+    let mut fixture = Fixture::load("auth/revoke_api_key").with_var("key_id", create.var("key_id"));
+    fixture.assert_outcome(
+        &raw.revoke_api_key(fixture.request::<RevokeApiKeyRequest>())
+            .await,
+    );
+    let revoked = client.revoke_api_key(&key.key_id).await.unwrap();
+    assert_eq!(revoked, fixture.expected::<bool>("/revoked"));
+
+    let mut fixture =
+        Fixture::load("auth/validate_api_key_revoked").with_var("api_key", create.var("api_key"));
+    fixture.assert_outcome(
+        &raw.validate_api_key(fixture.request::<ValidateApiKeyRequest>())
+            .await,
+    );
+    let validated = client
+        .validate_api_key(&created.api_key, Vec::new())
+        .await
+        .unwrap();
+    assert_eq!(validated.valid, fixture.expected::<bool>("/valid"));
+    assert!(validated.key.is_none());
+}
+
+/// Register the `Wiki` client on both services; returns the fixture
+/// holding the raw client's `$client_id` and `$client_secret`, and the
+/// client's ID and secret
+async fn register_client(channel: &Channel, client: &mut AuthClient) -> (Fixture, String, String) {
+    let mut raw = OidcProviderServiceClient::new(channel.clone());
+    let mut fixture = Fixture::load("auth/register_oidc_client");
+    fixture.assert_outcome(
+        &raw.register_client(fixture.request::<RegisterOidcClientRequest>())
+            .await,
+    );
+    let registered = client
+        .register_oidc_client(
+            &fixture.request_field::<String>("/name"),
+            fixture.request_field("/redirect_uris"),
+            fixture.request_field("/public"),
+        )
+        .await
+        .unwrap();
+    let oidc_client = registered.client.unwrap();
+    assert_eq!(oidc_client.name, fixture.expected::<String>("/client/name"));
+    (fixture, oidc_client.client_id, registered.client_secret)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oidc_clients() {
+    let (channel, mut client) = connect().await;
+    let (register, client_id, _) = register_client(&channel, &mut client).await;
+    let mut raw = OidcProviderServiceClient::new(channel);
+
+    let fixture = Fixture::load("auth/register_oidc_client_invalid_redirect");
+    let status = raw
+        .register_client(fixture.request::<RegisterOidcClientRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .register_oidc_client(
+            &fixture.request_field::<String>("/name"),
+            fixture.request_field("/redirect_uris"),
+            false,
+        )
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    // Signed-out users and invalid tokens are turned away
+    let fixture = Fixture::load("auth/oidc_authorize_signed_out")
+        .with_var("client_id", register.var("client_id"));
+    let status = raw
+        .authorize(fixture.request::<OidcAuthorizeRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .oidc_authorize(OidcAuthorizeRequest {
+            client_id: client_id.clone(),
+            ..fixture.request()
+        })
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let fixture = Fixture::load("auth/oidc_userinfo_invalid");
+    let status = raw
+        .get_user_info(fixture.request::<OidcUserInfoRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .oidc_userinfo(&fixture.request_field::<String>("/access_token"))
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture =
+        Fixture::load("auth/list_oidc_clients").with_var("client_id", register.var("client_id"));
+    fixture.assert_outcome(&raw.list_clients(ListOidcClientsRequest {}).await);
+    let clients = client.list_oidc_clients().await.unwrap();
+    assert_eq!(clients.len(), 1);
+    assert_eq!(clients[0].client_id, client_id);
+    assert_eq!(
+        clients[0].name,
+        fixture.expected::<String>("/clients/0/name")
+    );
+
+    let mut fixture =
+        Fixture::load("auth/delete_oidc_client").with_var("client_id", register.var("client_id"));
+    fixture.assert_outcome(
+        &raw.delete_client(fixture.request::<DeleteOidcClientRequest>())
+            .await,
+    );
+    let deleted = client.delete_oidc_client(&client_id).await.unwrap();
+    assert_eq!(deleted, fixture.expected::<bool>("/deleted"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oidc_flow() {
+    let (channel, mut client) = connect().await;
+    let (signed_in, session_id) = sign_in(&channel, &mut client).await;
+    let (register, client_id, client_secret) = register_client(&channel, &mut client).await;
+    let mut raw = OidcProviderServiceClient::new(channel);
+
+    // Requests in the flow name the raw service's session, client and
+    // code; the `acton-dx` client sends its own
+    let vars = |fixture: Fixture| {
+        fixture
+            .with_var("session_id", signed_in.var("session_id"))
+            .with_var("client_id", register.var("client_id"))
+            .with_var("client_secret", register.var("client_secret"))
+    };
+
+    let mut fixture = vars(Fixture::load("auth/oidc_authorize_consent_required"));
+    fixture.assert_outcome(
+        &raw.authorize(fixture.request::<OidcAuthorizeRequest>())
+            .await,
+    );
+    let authorized = client
+        .oidc_authorize(OidcAuthorizeRequest {
+            session_id: session_id.clone(),
+            client_id: client_id.clone(),
+            ..fixture.request()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        authorized.consent_required,
+        fixture.expected::<bool>("/consent_required")
+    );
+    assert_eq!(
+        authorized.scopes,
+        fixture.expected::<Vec<String>>("/scopes")
+    );
+    assert!(authorized.code.is_none());
+
+    let mut authorize = vars(Fixture::load("auth/oidc_authorize"));
+    authorize.assert_outcome(
+        &raw.authorize(authorize.request::<OidcAuthorizeRequest>())
+            .await,
+    );
+    let code = client
+        .oidc_authorize(OidcAuthorizeRequest {
+            session_id: session_id.clone(),
+            client_id: client_id.clone(),
+            ..authorize.request()
+        })
+        .await
+        .unwrap()
+        .code
+        .unwrap();
+
+    let mut exchange =
+        vars(Fixture::load("auth/oidc_exchange_code")).with_var("code", authorize.var("code"));
+    exchange.assert_outcome(
+        &raw.exchange_code(exchange.request::<OidcExchangeCodeRequest>())
+            .await,
+    );
+    let exchange_request = OidcExchangeCodeRequest {
+        client_id: client_id.clone(),
+        client_secret,
+        code,
+        ..exchange.request()
+    };
+    let tokens = client
+        .oidc_exchange_code(exchange_request.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        tokens.token_type,
+        exchange.expected::<String>("/token_type")
+    );
+    assert_eq!(tokens.expires_in, exchange.expected::<i64>("/expires_in"));
+    assert_eq!(tokens.scope, exchange.expected::<String>("/scope"));
+    assert!(!tokens.id_token.is_empty());
+
+    // Codes can be exchanged once
+    let fixture = vars(Fixture::load("auth/oidc_exchange_code_reused"))
+        .with_var("code", authorize.var("code"));
+    let status = raw
+        .exchange_code(fixture.request::<OidcExchangeCodeRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .oidc_exchange_code(exchange_request)
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture = Fixture::load("auth/oidc_userinfo")
+        .with_var("oidc_access_token", exchange.var("oidc_access_token"));
+    fixture.assert_outcome(
+        &raw.get_user_info(fixture.request::<OidcUserInfoRequest>())
+            .await,
+    );
+    let userinfo = client.oidc_userinfo(&tokens.access_token).await.unwrap();
+    assert_eq!(userinfo, fixture.expected::<String>("/claims_json"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oidc_metadata() {
+    let (channel, mut client) = connect().await;
+    let mut raw = OidcProviderServiceClient::new(channel);
+
+    let mut fixture = Fixture::load("auth/get_oidc_metadata");
+    let response = raw.get_metadata(GetOidcMetadataRequest {}).await;
+    fixture.assert_outcome(&response);
+    for metadata in [
+        response.unwrap().into_inner().metadata_json,
+        client.oidc_metadata().await.unwrap(),
+    ] {
+        let metadata: serde_json::Value = serde_json::from_str(&metadata).unwrap();
+        assert_eq!(metadata["issuer"], OidcConfig::default().issuer);
+        assert_eq!(
+            metadata["id_token_signing_alg_values_supported"][0],
+            "EdDSA"
+        );
+    }
+}
+
+/// No service implements `UserService` yet; calls must fail cleanly
+/// rather than hang or decode an empty user
+#[tokio::test(flavor = "multi_thread")]
+async fn test_users() {
+    let (channel, mut client) = connect().await;
+    let mut raw = UserServiceClient::new(channel);
+
+    let fixture = Fixture::load("auth/create_user");
+    let status = raw
+        .create_user(fixture.request::<CreateUserRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .create_user(
+            &fixture.request_field::<String>("/email"),
+            &fixture.request_field::<String>("/name"),
+            &fixture.request_field::<String>("/password"),
+        )
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let fixture = Fixture::load("auth/get_user");
+    let status = raw
+        .get_user(fixture.request::<GetUserRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .get_user(fixture.request_field("/id"))
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+}
@@ -0,0 +1,323 @@
+//! Auth service stores, against the data service on an in-memory SQLite
+//! database
+//!
+//! Each test runs the auth service on a store, then runs it again on a
+//! fresh store over the same data service, as a restarted replica would.
+
+use acton_dx_proto::auth::v1::{
+    api_key_service_client::ApiKeyServiceClient, api_key_service_server::ApiKeyServiceServer,
+    passkey_service_client::PasskeyServiceClient, passkey_service_server::PasskeyServiceServer,
+    CreateApiKeyRequest, DeletePasskeyRequest, ListApiKeysRequest, ListPasskeysRequest,
+    OidcAuthorizeRequest, OidcExchangeCodeRequest, RevokeApiKeyRequest,
+    StartPasskeyAuthenticationRequest, ValidateApiKeyRequest,
+};
+use acton_dx_proto::data::v1::{
+    data_service_client::DataServiceClient, data_service_server::DataServiceServer,
+    value::Value as ValueInner, ExecuteRequest, Value,
+};
+use auth_service::config::{OidcConfig, PasskeyConfig, TokenConfig};
+use auth_service::{
+    ApiKeyServiceImpl, ApiKeys, OidcProvider, PasskeyServiceImpl, PostgresApiKeyStore,
+    PostgresOidcStore, PostgresPasskeyStore, SessionData, TokenIssuer,
+};
+use chrono::Utc;
+use contract_tests::serve;
+use data_service::DataServiceImpl;
+use sqlx::any::AnyPoolOptions;
+use tonic::service::Routes;
+use tonic::transport::Channel;
+
+/// A data service on an empty in-memory database; returns its endpoint
+async fn data_service() -> String {
+    sqlx::any::install_default_drivers();
+    // One connection, so every call sees the same in-memory database
+    let pool = AnyPoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    serve(Routes::new(DataServiceServer::new(DataServiceImpl::new(
+        pool,
+    ))))
+    .await
+}
+
+/// An API key service keeping its keys through the data service at
+/// `data_endpoint`
+async fn api_key_service(data_endpoint: &str) -> ApiKeyServiceClient<Channel> {
+    let store = PostgresApiKeyStore::connect_lazy(data_endpoint, "auth_api_keys").unwrap();
+    store.ensure_table().await.unwrap();
+    let service = ApiKeyServiceImpl::new(ApiKeys::new().with_store(store));
+    let endpoint = serve(Routes::new(ApiKeyServiceServer::new(service))).await;
+    ApiKeyServiceClient::connect(endpoint).await.unwrap()
+}
+
+fn validate(api_key: &str) -> ValidateApiKeyRequest {
+    ValidateApiKeyRequest {
+        api_key: api_key.to_string(),
+        required_scopes: vec!["deploy".to_string()],
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_api_keys_survive_a_restart() {
+    let data = data_service().await;
+    let mut before = api_key_service(&data).await;
+    let created = before
+        .create_api_key(CreateApiKeyRequest {
+            name: "CI deploys".to_string(),
+            user_id: Some(7),
+            scopes: vec!["deploy".to_string()],
+            ttl_seconds: Some(3600),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let key = created.key.unwrap();
+
+    let mut after = api_key_service(&data).await;
+    let validated = after
+        .validate_api_key(validate(&created.api_key))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(validated.valid);
+    let loaded = validated.key.unwrap();
+    assert_eq!(loaded.key_id, key.key_id);
+    assert_eq!(loaded.user_id, Some(7));
+    assert_eq!(loaded.scopes, ["deploy"]);
+    assert_eq!(loaded.expires_at, key.expires_at);
+    assert!(loaded.last_used_at.is_some());
+
+    let mut wrong_secret = created.api_key.clone();
+    wrong_secret.push('x');
+    let validated = after
+        .validate_api_key(validate(&wrong_secret))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(!validated.valid);
+
+    let listed = after
+        .list_api_keys(ListApiKeysRequest {
+            user_id: Some(7),
+            include_revoked: false,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .keys;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].last_used_at, loaded.last_used_at);
+
+    // Revoking on one replica is seen by the other
+    let revoked = after
+        .revoke_api_key(RevokeApiKeyRequest {
+            key_id: key.key_id.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(revoked.revoked);
+    let validated = before
+        .validate_api_key(validate(&created.api_key))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(!validated.valid);
+    let listed = before
+        .list_api_keys(ListApiKeysRequest {
+            user_id: None,
+            include_revoked: true,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .keys;
+    assert!(listed[0].revoked_at.is_some());
+}
+
+/// A passkey service keeping its passkeys through the data service at
+/// `data_endpoint`
+async fn passkey_service(data_endpoint: &str) -> PasskeyServiceClient<Channel> {
+    let store = PostgresPasskeyStore::connect_lazy(data_endpoint, "auth_passkeys").unwrap();
+    store.ensure_table().await.unwrap();
+    let service = PasskeyServiceImpl::new(&PasskeyConfig::default())
+        .unwrap()
+        .with_store(store);
+    let endpoint = serve(Routes::new(PasskeyServiceServer::new(service))).await;
+    PasskeyServiceClient::connect(endpoint).await.unwrap()
+}
+
+/// Credential ID of [`PASSKEY`], base64url encoded
+const CREDENTIAL_ID: &str = "bGFwdG9w";
+
+/// A passkey as `webauthn-rs` serializes it; registering one takes an
+/// authenticator, so the test stores it as a registration would
+const PASSKEY: &str = r#"{"cred": {
+    "cred_id": "bGFwdG9w",
+    "cred": {"type_": "ES256", "key": {"EC_EC2": {"curve": "SECP256R1",
+        "x": "BwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwc",
+        "y": "BwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwc"}}},
+    "counter": 0, "transports": null, "user_verified": true,
+    "backup_eligible": false, "backup_state": false,
+    "registration_policy": "required", "extensions": {},
+    "attestation": {"data": "None", "metadata": "None"},
+    "attestation_format": "none"
+}}"#;
+
+const fn value(value: ValueInner) -> Value {
+    Value { value: Some(value) }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_passkeys_survive_a_restart() {
+    let data = data_service().await;
+    let mut before = passkey_service(&data).await;
+    DataServiceClient::connect(data.clone())
+        .await
+        .unwrap()
+        .execute(ExecuteRequest {
+            sql: "INSERT INTO auth_passkeys \
+                  (credential_id, user_id, name, passkey, created_at, last_used_at) \
+                  VALUES ($1, $2, $3, $4, $5, NULL)"
+                .to_string(),
+            params: vec![
+                value(ValueInner::StringValue(CREDENTIAL_ID.to_string())),
+                value(ValueInner::IntValue(7)),
+                value(ValueInner::StringValue("Laptop".to_string())),
+                value(ValueInner::StringValue(PASSKEY.to_string())),
+                value(ValueInner::IntValue(1_700_000_000)),
+            ],
+            transaction_id: None,
+        })
+        .await
+        .unwrap();
+
+    let mut after = passkey_service(&data).await;
+    let passkeys = after
+        .list_passkeys(ListPasskeysRequest { user_id: 7 })
+        .await
+        .unwrap()
+        .into_inner()
+        .passkeys;
+    assert_eq!(passkeys.len(), 1);
+    assert_eq!(passkeys[0].credential_id, CREDENTIAL_ID);
+    assert_eq!(passkeys[0].name, "Laptop");
+    assert_eq!(passkeys[0].created_at, 1_700_000_000);
+
+    let started = after
+        .start_authentication(StartPasskeyAuthenticationRequest { user_id: 7 })
+        .await
+        .unwrap()
+        .into_inner();
+    let options: serde_json::Value = serde_json::from_str(&started.options_json).unwrap();
+    assert_eq!(
+        options["publicKey"]["allowCredentials"][0]["id"],
+        CREDENTIAL_ID
+    );
+    let status = after
+        .start_authentication(StartPasskeyAuthenticationRequest { user_id: 8 })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+    // Deleting on one replica is seen by the other
+    let deleted = after
+        .delete_passkey(DeletePasskeyRequest {
+            user_id: 7,
+            credential_id: CREDENTIAL_ID.to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(deleted.success);
+    let passkeys = before
+        .list_passkeys(ListPasskeysRequest { user_id: 7 })
+        .await
+        .unwrap()
+        .into_inner()
+        .passkeys;
+    assert!(passkeys.is_empty());
+}
+
+/// An OIDC provider keeping its clients and consents through the data
+/// service at `data_endpoint`; every provider signs with `issuer`'s keys
+async fn oidc_provider(data_endpoint: &str, issuer: &TokenIssuer) -> OidcProvider {
+    let store =
+        PostgresOidcStore::connect_lazy(data_endpoint, "auth_oidc_clients", "auth_oidc_consents")
+            .unwrap();
+    store.ensure_tables().await.unwrap();
+    OidcProvider::new(&OidcConfig::default(), issuer.clone())
+        .unwrap()
+        .with_store(store)
+}
+
+const REDIRECT: &str = "https://wiki.example.com/callback";
+
+fn authorize(client_id: &str, consent: bool) -> OidcAuthorizeRequest {
+    OidcAuthorizeRequest {
+        session_id: String::new(),
+        client_id: client_id.to_string(),
+        redirect_uri: REDIRECT.to_string(),
+        scopes: vec!["openid".to_string(), "email".to_string()],
+        nonce: None,
+        code_challenge: None,
+        code_challenge_method: None,
+        consent,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oidc_clients_and_consents_survive_a_restart() {
+    let data = data_service().await;
+    let issuer = TokenIssuer::new(&TokenConfig::default()).unwrap();
+    let session = SessionData::new(3600, Some(7));
+    let now = Utc::now();
+    let before = oidc_provider(&data, &issuer).await;
+    let (client, secret) = before
+        .register_client("Team Wiki", vec![REDIRECT.to_string()], false)
+        .await
+        .unwrap();
+    let approved = before
+        .authorize(&session, &authorize(&client.client_id, true), now)
+        .await
+        .unwrap();
+    assert!(approved.code.is_some());
+
+    let after = oidc_provider(&data, &issuer).await;
+    assert_eq!(
+        after.clients().await.unwrap(),
+        std::slice::from_ref(&client)
+    );
+    let again = after
+        .authorize(&session, &authorize(&client.client_id, false), now)
+        .await
+        .unwrap();
+    assert!(!again.consent_required);
+    let tokens = after
+        .exchange(
+            &OidcExchangeCodeRequest {
+                client_id: client.client_id.clone(),
+                client_secret: secret.unwrap(),
+                code: again.code.unwrap(),
+                redirect_uri: REDIRECT.to_string(),
+                code_verifier: None,
+            },
+            now,
+        )
+        .await
+        .unwrap();
+    assert!(after
+        .verify_access_token(&tokens.access_token, now)
+        .await
+        .is_ok());
+
+    // Deleting on one replica is seen by the other
+    assert!(after.delete_client(&client.client_id).await.unwrap());
+    assert!(before.clients().await.unwrap().is_empty());
+    assert!(before
+        .verify_access_token(&tokens.access_token, now)
+        .await
+        .is_err());
+}
@@ -0,0 +1,214 @@
+//! Cache service contracts, against the Redis named by `REDIS_URL`
+//!
+//! Skipped when `REDIS_URL` is not set. Each test keeps its keys in its own
+//! namespace, so runs can share a Redis with other tests.
+
+use acton_dx::htmx::clients::CacheClient;
+use acton_dx_proto::cache::v1::{
+    cache_service_client::CacheServiceClient, cache_service_server::CacheServiceServer,
+    DeleteRequest, ExistsRequest, GetRequest, HGetRequest, HSetRequest, IncrementRequest,
+    InvalidatePrefixRequest, LPushRequest, LRangeRequest, RateLimitRequest, SetRequest,
+};
+use cache_service::CacheServiceImpl;
+use contract_tests::{serve, Fixture};
+use tonic::service::Routes;
+use tonic::transport::Channel;
+
+/// A service keeping its keys under a namespace unique to this run
+async fn service(url: &str, namespace: &str) -> CacheServiceImpl {
+    let conn = redis::Client::open(url)
+        .unwrap()
+        .get_connection_manager()
+        .await
+        .unwrap();
+    CacheServiceImpl::new(conn).with_namespace(&format!(
+        "contract-tests:{}:{namespace}",
+        std::process::id()
+    ))
+}
+
+/// Two services on the same Redis: one called through the generated
+/// client, one through the `acton-dx` client
+///
+/// `None` when `REDIS_URL` is not set.
+async fn connect(test: &str) -> Option<(CacheServiceClient<Channel>, CacheClient)> {
+    let Ok(url) = std::env::var("REDIS_URL") else {
+        eprintln!("REDIS_URL not set; skipping {test}");
+        return None;
+    };
+    let raw = Routes::new(CacheServiceServer::new(
+        service(&url, &format!("{test}:raw")).await,
+    ));
+    let client = Routes::new(CacheServiceServer::new(
+        service(&url, &format!("{test}:client")).await,
+    ));
+    Some((
+        CacheServiceClient::connect(serve(raw).await).await.unwrap(),
+        CacheClient::connect(serve(client).await).await.unwrap(),
+    ))
+}
+
+#[tokio::test]
+async fn test_key_values() {
+    let Some((mut raw, mut client)) = connect("key_values").await else {
+        return;
+    };
+
+    let mut fixture = Fixture::load("cache/set");
+    fixture.assert_outcome(&raw.set(fixture.request::<SetRequest>()).await);
+    let set = client
+        .set(
+            &fixture.request_field::<String>("/key"),
+            &fixture.request_field::<Vec<u8>>("/value"),
+            fixture.request_field("/ttl_seconds"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(set, fixture.expected::<bool>("/success"));
+
+    for name in ["cache/get", "cache/get_missing"] {
+        let mut fixture = Fixture::load(name);
+        fixture.assert_outcome(&raw.get(fixture.request::<GetRequest>()).await);
+        let value = client
+            .get(&fixture.request_field::<String>("/key"))
+            .await
+            .unwrap();
+        assert_eq!(value, fixture.expected::<Option<Vec<u8>>>("/value"));
+    }
+
+    let mut fixture = Fixture::load("cache/exists");
+    fixture.assert_outcome(&raw.exists(fixture.request::<ExistsRequest>()).await);
+    let exists = client
+        .exists(&fixture.request_field::<String>("/key"))
+        .await
+        .unwrap();
+    assert_eq!(exists, fixture.expected::<bool>("/exists"));
+
+    let mut fixture = Fixture::load("cache/delete");
+    fixture.assert_outcome(&raw.delete(fixture.request::<DeleteRequest>()).await);
+    let deleted = client
+        .delete(&fixture.request_field::<String>("/key"))
+        .await
+        .unwrap();
+    assert_eq!(deleted, fixture.expected::<bool>("/deleted"));
+
+    let mut fixture = Fixture::load("cache/increment");
+    fixture.assert_outcome(
+        &raw.increment_counter(fixture.request::<IncrementRequest>())
+            .await,
+    );
+    let value = client
+        .increment(
+            &fixture.request_field::<String>("/key"),
+            fixture.request_field("/amount"),
+            fixture.request_field("/ttl_seconds"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(value, fixture.expected::<i64>("/new_value"));
+}
+
+#[tokio::test]
+async fn test_rate_limits() {
+    let Some((mut raw, mut client)) = connect("rate_limits").await else {
+        return;
+    };
+
+    for name in ["cache/check_rate_limit", "cache/check_rate_limit_exceeded"] {
+        let mut fixture = Fixture::load(name);
+        fixture.assert_outcome(
+            &raw.check_rate_limit(fixture.request::<RateLimitRequest>())
+                .await,
+        );
+        let result = client
+            .check_rate_limit(
+                &fixture.request_field::<String>("/key"),
+                fixture.request_field("/limit"),
+                fixture.request_field("/window_seconds"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.allowed, fixture.expected::<bool>("/allowed"));
+        assert_eq!(result.remaining, fixture.expected::<i32>("/remaining"));
+        assert_eq!(
+            result.reset_in_seconds,
+            fixture.expected::<i32>("/reset_in_seconds")
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_hashes_lists_and_invalidation() {
+    let Some((mut raw, mut client)) = connect("hashes_lists").await else {
+        return;
+    };
+
+    let mut fixture = Fixture::load("cache/h_set");
+    fixture.assert_outcome(&raw.h_set(fixture.request::<HSetRequest>()).await);
+    let set = client
+        .hset(
+            &fixture.request_field::<String>("/key"),
+            &fixture.request_field::<String>("/field"),
+            &fixture.request_field::<Vec<u8>>("/value"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(set, fixture.expected::<bool>("/success"));
+
+    let mut fixture = Fixture::load("cache/h_get");
+    fixture.assert_outcome(&raw.h_get(fixture.request::<HGetRequest>()).await);
+    let value = client
+        .hget(
+            &fixture.request_field::<String>("/key"),
+            &fixture.request_field::<String>("/field"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(value, fixture.expected::<Option<Vec<u8>>>("/value"));
+
+    let mut fixture = Fixture::load("cache/l_push");
+    fixture.assert_outcome(&raw.l_push(fixture.request::<LPushRequest>()).await);
+    let length = client
+        .lpush(
+            &fixture.request_field::<String>("/key"),
+            &fixture.request_field::<Vec<u8>>("/value"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(length, fixture.expected::<i64>("/length"));
+
+    let mut fixture = Fixture::load("cache/l_range");
+    fixture.assert_outcome(&raw.l_range(fixture.request::<LRangeRequest>()).await);
+    let values = client
+        .lrange(
+            &fixture.request_field::<String>("/key"),
+            fixture.request_field("/start"),
+            fixture.request_field("/stop"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(values, fixture.expected::<Vec<Vec<u8>>>("/values"));
+
+    let mut fixture = Fixture::load("cache/invalidate_prefix");
+    fixture.assert_outcome(
+        &raw.invalidate_prefix(fixture.request::<InvalidatePrefixRequest>())
+            .await,
+    );
+    let deleted = client
+        .invalidate_prefix(&fixture.request_field::<String>("/prefix"), None)
+        .await
+        .unwrap();
+    assert_eq!(deleted, fixture.expected::<u64>("/deleted"));
+
+    let fixture = Fixture::load("cache/invalidate_prefix_empty");
+    let status = raw
+        .invalidate_prefix(fixture.request::<InvalidatePrefixRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .invalidate_prefix(&fixture.request_field::<String>("/prefix"), None)
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+}
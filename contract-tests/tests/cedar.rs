@@ -0,0 +1,310 @@
+//! Cedar service contracts
+
+use acton_dx::htmx::clients::{AuthorizationRequest, CedarClient};
+use acton_dx_proto::cedar::v1::{
+    cedar_service_client::CedarServiceClient, cedar_service_server::CedarServiceServer,
+    ActivatePolicyVersionRequest, AuthzRequest, BatchAuthzRequest, GetPolicyVersionRequest,
+    ListPolicyVersionsRequest, ReloadPoliciesRequest, RollbackPoliciesRequest,
+    SavePolicyVersionRequest, ValidatePolicyRequest,
+};
+use cedar_service::CedarServiceImpl;
+use contract_tests::{serve, Fixture};
+use std::collections::HashMap;
+use tempfile::TempDir;
+use tonic::service::Routes;
+use tonic::transport::Channel;
+
+const POLICIES: &str = r#"permit(principal == User::"alice", action == Action::"read", resource);"#;
+
+/// Two services loaded from the same policies: one called through the
+/// generated client, one through the `acton-dx` client, so stateful calls
+/// can be replayed on both
+async fn connect() -> (CedarServiceClient<Channel>, CedarClient, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("policies.cedar");
+    std::fs::write(&path, POLICIES).unwrap();
+    let path = path.to_str().unwrap();
+
+    let raw = serve(Routes::new(CedarServiceServer::new(
+        CedarServiceImpl::new(path).unwrap(),
+    )))
+    .await;
+    let client = serve(Routes::new(CedarServiceServer::new(
+        CedarServiceImpl::new(path).unwrap(),
+    )))
+    .await;
+    (
+        CedarServiceClient::connect(raw).await.unwrap(),
+        CedarClient::connect(client).await.unwrap(),
+        dir,
+    )
+}
+
+fn authorization_request(fixture: &Fixture, pointer: &str) -> AuthorizationRequest {
+    AuthorizationRequest {
+        principal_type: fixture.request_field(&format!("{pointer}/principal/entity_type")),
+        principal_id: fixture.request_field(&format!("{pointer}/principal/entity_id")),
+        action: fixture.request_field(&format!("{pointer}/action")),
+        resource_type: fixture.request_field(&format!("{pointer}/resource/entity_type")),
+        resource_id: fixture.request_field(&format!("{pointer}/resource/entity_id")),
+        context: HashMap::new(),
+    }
+}
+
+#[tokio::test]
+async fn test_authorization() {
+    let (mut raw, mut client, _dir) = connect().await;
+
+    let mut fixture = Fixture::load("cedar/is_authorized");
+    fixture.assert_outcome(&raw.is_authorized(fixture.request::<AuthzRequest>()).await);
+    let request = authorization_request(&fixture, "");
+    let result = client
+        .is_authorized(
+            &request.principal_type,
+            &request.principal_id,
+            &request.action,
+            &request.resource_type,
+            &request.resource_id,
+            fixture.request_field("/context"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.allowed, fixture.expected::<bool>("/allowed"));
+    assert_eq!(
+        result.decision_reason,
+        fixture.expected::<String>("/decision_reason")
+    );
+    assert_eq!(
+        result.policy_version,
+        fixture.expected::<u64>("/policy_version")
+    );
+
+    let mut fixture = Fixture::load("cedar/batch_authorize");
+    fixture.assert_outcome(
+        &raw.batch_authorize(fixture.request::<BatchAuthzRequest>())
+            .await,
+    );
+    let results = client
+        .batch_authorize(vec![
+            authorization_request(&fixture, "/requests/0"),
+            authorization_request(&fixture, "/requests/1"),
+        ])
+        .await
+        .unwrap();
+    let allowed: Vec<bool> = results.iter().map(|result| result.allowed).collect();
+    assert_eq!(
+        allowed,
+        [
+            fixture.expected::<bool>("/responses/0/allowed"),
+            fixture.expected::<bool>("/responses/1/allowed"),
+        ]
+    );
+
+    for name in ["cedar/validate_policy", "cedar/validate_policy_invalid"] {
+        let mut fixture = Fixture::load(name);
+        fixture.assert_outcome(
+            &raw.validate_policy(fixture.request::<ValidatePolicyRequest>())
+                .await,
+        );
+        let result = client
+            .validate_policy(&fixture.request_field::<String>("/policy_text"))
+            .await
+            .unwrap();
+        assert_eq!(result.valid, fixture.expected::<bool>("/valid"));
+        assert_eq!(
+            result.errors.len(),
+            fixture.expected::<Vec<String>>("/errors").len()
+        );
+    }
+
+    let mut fixture = Fixture::load("cedar/rollback_policies_nothing_to_roll_back");
+    fixture.assert_outcome(
+        &raw.rollback_policies(fixture.request::<RollbackPoliciesRequest>())
+            .await,
+    );
+    let error = client
+        .rollback_policies(&fixture.request_field::<String>("/actor"))
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture = Fixture::load("cedar/reload_policies");
+    fixture.assert_outcome(
+        &raw.reload_policies(fixture.request::<ReloadPoliciesRequest>())
+            .await,
+    );
+    let reloaded = client.reload_policies().await.unwrap();
+    assert_eq!(reloaded.success, fixture.expected::<bool>("/success"));
+    assert_eq!(
+        reloaded.policies_loaded,
+        fixture.expected::<i32>("/policies_loaded")
+    );
+    assert_eq!(reloaded.message, fixture.expected::<String>("/message"));
+    assert_eq!(
+        reloaded.active_version,
+        fixture.expected::<u64>("/active_version")
+    );
+}
+
+/// Save a new version, and check the versions the service refuses,
+/// returning the saved version number
+async fn save_versions(
+    raw: &mut CedarServiceClient<Channel>,
+    client: &mut CedarClient,
+) -> serde_json::Value {
+    let mut save = Fixture::load("cedar/save_policy_version");
+    save.assert_outcome(
+        &raw.save_policy_version(save.request::<SavePolicyVersionRequest>())
+            .await,
+    );
+    let saved = client
+        .save_policy_version(
+            &save.request_field::<String>("/policy_text"),
+            &save.request_field::<String>("/author"),
+            &save.request_field::<String>("/comment"),
+        )
+        .await
+        .unwrap();
+    let version = saved.version.expect("saved version");
+    assert_eq!(version.version, save.expected::<u64>("/version/version"));
+    assert_eq!(
+        version.policies_count,
+        save.expected::<i32>("/version/policies_count")
+    );
+    assert_eq!(version.active, save.expected::<bool>("/version/active"));
+
+    let mut fixture = Fixture::load("cedar/save_policy_version_invalid");
+    fixture.assert_outcome(
+        &raw.save_policy_version(fixture.request::<SavePolicyVersionRequest>())
+            .await,
+    );
+    let result = client
+        .save_policy_version(
+            &fixture.request_field::<String>("/policy_text"),
+            &fixture.request_field::<String>("/author"),
+            &fixture.request_field::<String>("/comment"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.valid, fixture.expected::<bool>("/valid"));
+    assert!(result.version.is_none());
+
+    let mut fixture = Fixture::load("cedar/save_policy_version_without_author");
+    fixture.assert_outcome(
+        &raw.save_policy_version(fixture.request::<SavePolicyVersionRequest>())
+            .await,
+    );
+    let error = client
+        .save_policy_version(
+            &fixture.request_field::<String>("/policy_text"),
+            "",
+            &fixture.request_field::<String>("/comment"),
+        )
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    save.var("version").clone()
+}
+
+#[tokio::test]
+async fn test_policy_versions() {
+    let (mut raw, mut client, _dir) = connect().await;
+
+    let saved_version = save_versions(&mut raw, &mut client).await;
+
+    let mut fixture = Fixture::load("cedar/get_policy_version").with_var("version", &saved_version);
+    fixture.assert_outcome(
+        &raw.get_policy_version(fixture.request::<GetPolicyVersionRequest>())
+            .await,
+    );
+    let stored = client
+        .get_policy_version(fixture.request_field("/version"))
+        .await
+        .unwrap();
+    assert_eq!(stored.version, fixture.expected::<u64>("/version"));
+    assert_eq!(stored.author, fixture.expected::<String>("/author"));
+    assert_eq!(stored.active, fixture.expected::<bool>("/active"));
+
+    let mut fixture =
+        Fixture::load("cedar/activate_policy_version").with_var("version", &saved_version);
+    fixture.assert_outcome(
+        &raw.activate_policy_version(fixture.request::<ActivatePolicyVersionRequest>())
+            .await,
+    );
+    let activation = client
+        .activate_policy_version(
+            fixture.request_field("/version"),
+            &fixture.request_field::<String>("/actor"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        activation.active_version,
+        fixture.expected::<u64>("/active_version")
+    );
+    assert_eq!(
+        activation.previous_version,
+        fixture.expected::<u64>("/previous_version")
+    );
+    assert_eq!(
+        activation.policies_loaded,
+        fixture.expected::<i32>("/policies_loaded")
+    );
+
+    let mut fixture = Fixture::load("cedar/activate_policy_version_not_found");
+    fixture.assert_outcome(
+        &raw.activate_policy_version(fixture.request::<ActivatePolicyVersionRequest>())
+            .await,
+    );
+    let error = client
+        .activate_policy_version(
+            fixture.request_field("/version"),
+            &fixture.request_field::<String>("/actor"),
+        )
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture =
+        Fixture::load("cedar/list_policy_versions").with_var("version", &saved_version);
+    fixture.assert_outcome(
+        &raw.list_policy_versions(fixture.request::<ListPolicyVersionsRequest>())
+            .await,
+    );
+    let history = client.list_policy_versions().await.unwrap();
+    assert_eq!(
+        history.active_version,
+        fixture.expected::<u64>("/active_version")
+    );
+    let versions: Vec<u64> = history.versions.iter().map(|v| v.version).collect();
+    assert_eq!(
+        versions,
+        [
+            fixture.expected::<u64>("/versions/0/version"),
+            fixture.expected::<u64>("/versions/1/version"),
+        ]
+    );
+    assert_eq!(
+        history.activations[0].actor,
+        fixture.expected::<String>("/activations/0/actor")
+    );
+
+    let mut fixture = Fixture::load("cedar/rollback_policies").with_var("version", &saved_version);
+    fixture.assert_outcome(
+        &raw.rollback_policies(fixture.request::<RollbackPoliciesRequest>())
+            .await,
+    );
+    let activation = client
+        .rollback_policies(&fixture.request_field::<String>("/actor"))
+        .await
+        .unwrap();
+    assert_eq!(
+        activation.active_version,
+        fixture.expected::<u64>("/active_version")
+    );
+    assert_eq!(
+        activation.previous_version,
+        fixture.expected::<u64>("/previous_version")
+    );
+}
@@ -0,0 +1,456 @@
+//! Data service contracts, against an in-memory SQLite database
+
+use acton_dx::htmx::clients::{json, DataClient, Row, Value};
+use acton_dx_proto::data::v1::{
+    data_service_client::DataServiceClient, data_service_server::DataServiceServer,
+    ApplyMigrationsRequest, BeginTransactionRequest, CommitTransactionRequest,
+    DescribeSchemaRequest, ExecuteRequest, ExplainQueryRequest, MigrationPlan,
+    MigrationStatusRequest, NamedQueryRequest, PingRequest, PlanMigrationsRequest, QueryRequest,
+    QueryStreamRequest, RollbackMigrationRequest, RollbackTransactionRequest, RowChunk,
+    RunMigrationsRequest, TransactionExecuteRequest,
+};
+use contract_tests::{serve, Fixture};
+use data_service::{DataServiceImpl, MigrationSet, QueryRegistry};
+use sqlx::any::AnyPoolOptions;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio_stream::StreamExt;
+use tonic::service::Routes;
+use tonic::transport::Channel;
+
+const SCHEMA: &str =
+    "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL, avatar BLOB)";
+
+const QUERIES: &str = r#"
+[user_by_id]
+sql = "SELECT name FROM users WHERE id = ?"
+params = ["int"]
+
+[rename_user]
+sql = "UPDATE users SET name = ? WHERE id = ?"
+params = ["string", "int"]
+"#;
+
+async fn service() -> DataServiceImpl {
+    sqlx::any::install_default_drivers();
+    // One connection, so every call sees the same in-memory database
+    let pool = AnyPoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::query(SCHEMA).execute(&pool).await.unwrap();
+    let mut queries = QueryRegistry::new();
+    queries.add_toml(QUERIES).unwrap();
+    queries.validate(&pool).await.unwrap();
+    DataServiceImpl::new(pool)
+        .with_queries(queries)
+        .with_explain(true)
+}
+
+/// Two databases: one called through the generated client, one through
+/// the `acton-dx` client, so writes can be replayed on both
+async fn connect() -> (DataServiceClient<Channel>, DataClient) {
+    let raw = serve(Routes::new(DataServiceServer::new(service().await))).await;
+    let client = serve(Routes::new(DataServiceServer::new(service().await))).await;
+    (
+        DataServiceClient::connect(raw).await.unwrap(),
+        DataClient::connect(client).await.unwrap(),
+    )
+}
+
+/// Migrations the migration RPCs are served from
+const MIGRATIONS: [(&str, &str); 4] = [
+    (
+        "1_create_posts.up.sql",
+        "CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT NOT NULL)",
+    ),
+    ("1_create_posts.down.sql", "DROP TABLE posts"),
+    (
+        "2_index_post_titles.up.sql",
+        "CREATE INDEX posts_title ON posts (title)",
+    ),
+    ("2_index_post_titles.down.sql", "DROP INDEX posts_title"),
+];
+
+/// As [`connect`], with both services serving [`MIGRATIONS`]
+async fn connect_with_migrations() -> (DataServiceClient<Channel>, DataClient, TempDir) {
+    let dir = TempDir::new().unwrap();
+    for (name, sql) in MIGRATIONS {
+        std::fs::write(dir.path().join(name), sql).unwrap();
+    }
+    let migrations = MigrationSet::load(dir.path()).await.unwrap();
+
+    let raw = serve(Routes::new(DataServiceServer::new(
+        service().await.with_migrations(migrations.clone()),
+    )))
+    .await;
+    let client = serve(Routes::new(DataServiceServer::new(
+        service().await.with_migrations(migrations),
+    )))
+    .await;
+    (
+        DataServiceClient::connect(raw).await.unwrap(),
+        DataClient::connect(client).await.unwrap(),
+        dir,
+    )
+}
+
+/// A row as the `acton-dx` client hands it to applications
+fn row_json(row: &Row) -> serde_json::Value {
+    json::to_value(row).unwrap()
+}
+
+async fn insert_users(raw: &mut DataServiceClient<Channel>, client: &mut DataClient) {
+    for name in ["data/execute", "data/execute_with_null"] {
+        let mut fixture = Fixture::load(name);
+        fixture.assert_outcome(&raw.execute(fixture.request::<ExecuteRequest>()).await);
+        let result = client
+            .execute(
+                &fixture.request_field::<String>("/sql"),
+                fixture.request_field("/params"),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows_affected,
+            fixture.expected::<i64>("/rows_affected")
+        );
+        assert_eq!(
+            result.last_insert_id,
+            fixture.expected::<Option<i64>>("/last_insert_id")
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_queries() {
+    let (mut raw, mut client) = connect().await;
+    insert_users(&mut raw, &mut client).await;
+
+    let mut fixture = Fixture::load("data/query");
+    fixture.assert_outcome(&raw.query(fixture.request::<QueryRequest>()).await);
+    let rows = client
+        .query(&fixture.request_field::<String>("/sql"), vec![], None)
+        .await
+        .unwrap();
+    let expected: Vec<Row> = fixture.expected("/rows");
+    assert_eq!(
+        rows.iter().map(row_json).collect::<Vec<_>>(),
+        expected.iter().map(row_json).collect::<Vec<_>>()
+    );
+
+    for name in ["data/query_one", "data/query_one_missing"] {
+        let mut fixture = Fixture::load(name);
+        fixture.assert_outcome(&raw.query_one(fixture.request::<QueryRequest>()).await);
+        let row = client
+            .query_one(
+                &fixture.request_field::<String>("/sql"),
+                fixture.request_field::<Vec<Value>>("/params"),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            row.as_ref().map(row_json),
+            fixture
+                .expected::<Option<Row>>("/row")
+                .as_ref()
+                .map(row_json)
+        );
+    }
+
+    let mut fixture = Fixture::load("data/query_stream");
+    let chunks: Vec<_> = raw
+        .query_stream(fixture.request::<QueryStreamRequest>())
+        .await
+        .unwrap()
+        .into_inner()
+        .collect::<Result<_, _>>()
+        .await
+        .unwrap();
+    fixture.assert_response(&chunks);
+    let rows: Vec<Row> = client
+        .query_stream(
+            &fixture.request_field::<String>("/sql"),
+            vec![],
+            fixture.request_field("/chunk_rows"),
+        )
+        .await
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .await
+        .unwrap();
+    let expected: Vec<Row> = fixture
+        .expected::<Vec<RowChunk>>("")
+        .into_iter()
+        .flat_map(|chunk| chunk.rows)
+        .collect();
+    assert_eq!(
+        rows.iter().map(row_json).collect::<Vec<_>>(),
+        expected.iter().map(row_json).collect::<Vec<_>>()
+    );
+
+    let mut fixture = Fixture::load("data/query_invalid_sql");
+    fixture.assert_outcome(&raw.query(fixture.request::<QueryRequest>()).await);
+    let error = client
+        .query(&fixture.request_field::<String>("/sql"), vec![], None)
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+}
+
+#[tokio::test]
+async fn test_named_queries() {
+    let (mut raw, mut client) = connect().await;
+    insert_users(&mut raw, &mut client).await;
+
+    let mut fixture = Fixture::load("data/query_named");
+    fixture.assert_outcome(&raw.query_named(fixture.request::<NamedQueryRequest>()).await);
+    let rows = client
+        .query_named(
+            &fixture.request_field::<String>("/name"),
+            fixture.request_field("/params"),
+        )
+        .await
+        .unwrap();
+    let expected: Vec<Row> = fixture.expected("/rows");
+    assert_eq!(
+        rows.iter().map(row_json).collect::<Vec<_>>(),
+        expected.iter().map(row_json).collect::<Vec<_>>()
+    );
+
+    for name in ["data/query_named_unknown", "data/query_named_wrong_type"] {
+        let mut fixture = Fixture::load(name);
+        fixture.assert_outcome(&raw.query_named(fixture.request::<NamedQueryRequest>()).await);
+        let error = client
+            .query_named(
+                &fixture.request_field::<String>("/name"),
+                fixture.request_field("/params"),
+            )
+            .await
+            .unwrap_err();
+        fixture.assert_client_error(&error);
+    }
+
+    let mut fixture = Fixture::load("data/execute_named");
+    fixture.assert_outcome(
+        &raw.execute_named(fixture.request::<NamedQueryRequest>())
+            .await,
+    );
+    let result = client
+        .execute_named(
+            &fixture.request_field::<String>("/name"),
+            fixture.request_field("/params"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        result.rows_affected,
+        fixture.expected::<i64>("/rows_affected")
+    );
+}
+
+#[tokio::test]
+async fn test_transactions() {
+    let (mut raw, mut client) = connect().await;
+    insert_users(&mut raw, &mut client).await;
+
+    let mut begin = Fixture::load("data/begin_transaction");
+    begin.assert_outcome(
+        &raw.begin_transaction(begin.request::<BeginTransactionRequest>())
+            .await,
+    );
+    let transaction_id = client.begin_transaction().await.unwrap();
+
+    let mut fixture = Fixture::load("data/execute_in_transaction")
+        .with_var("transaction_id", begin.var("transaction_id"));
+    fixture.assert_outcome(
+        &raw.execute_in_transaction(fixture.request::<TransactionExecuteRequest>())
+            .await,
+    );
+    let result = client
+        .execute_in_transaction(
+            &transaction_id,
+            &fixture.request_field::<String>("/sql"),
+            fixture.request_field("/params"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        result.rows_affected,
+        fixture.expected::<i64>("/rows_affected")
+    );
+
+    let mut fixture = Fixture::load("data/commit_transaction")
+        .with_var("transaction_id", begin.var("transaction_id"));
+    fixture.assert_outcome(
+        &raw.commit_transaction(fixture.request::<CommitTransactionRequest>())
+            .await,
+    );
+    let committed = client.commit_transaction(&transaction_id).await.unwrap();
+    assert_eq!(committed, fixture.expected::<bool>("/success"));
+
+    let mut fixture = Fixture::load("data/commit_transaction_not_found");
+    fixture.assert_outcome(
+        &raw.commit_transaction(fixture.request::<CommitTransactionRequest>())
+            .await,
+    );
+    let error = client
+        .commit_transaction(&fixture.request_field::<String>("/transaction_id"))
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture = Fixture::load("data/begin_transaction_timeout_too_long");
+    fixture.assert_outcome(
+        &raw.begin_transaction(fixture.request::<BeginTransactionRequest>())
+            .await,
+    );
+    let error = client
+        .begin_transaction_with_timeout(Duration::from_millis(
+            fixture.request_field("/timeout_ms"),
+        ))
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut begin = Fixture::load("data/begin_transaction");
+    begin.assert_outcome(
+        &raw.begin_transaction(begin.request::<BeginTransactionRequest>())
+            .await,
+    );
+    let transaction_id = client.begin_transaction().await.unwrap();
+    let mut fixture = Fixture::load("data/rollback_transaction")
+        .with_var("transaction_id", begin.var("transaction_id"));
+    fixture.assert_outcome(
+        &raw.rollback_transaction(fixture.request::<RollbackTransactionRequest>())
+            .await,
+    );
+    let rolled_back = client.rollback_transaction(&transaction_id).await.unwrap();
+    assert_eq!(rolled_back, fixture.expected::<bool>("/success"));
+}
+
+#[tokio::test]
+async fn test_schema_and_operations() {
+    let (mut raw, mut client) = connect().await;
+
+    let mut fixture = Fixture::load("data/describe_schema");
+    fixture.assert_outcome(
+        &raw.describe_schema(fixture.request::<DescribeSchemaRequest>())
+            .await,
+    );
+    let (dialect, tables) = client
+        .describe_schema(fixture.request_field("/tables"))
+        .await
+        .unwrap();
+    assert_eq!(dialect, fixture.expected::<String>("/dialect"));
+    let columns: Vec<&str> = tables[0]
+        .columns
+        .iter()
+        .map(|column| column.name.as_str())
+        .collect();
+    assert_eq!(columns, ["id", "name", "score", "avatar"]);
+
+    let mut fixture = Fixture::load("data/explain_query");
+    fixture.assert_outcome(
+        &raw.explain_query(fixture.request::<ExplainQueryRequest>())
+            .await,
+    );
+    let plan = client
+        .explain(
+            &fixture.request_field::<String>("/sql"),
+            fixture.request_field("/params"),
+            fixture.request_field("/analyze"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(plan.len(), fixture.expected::<Vec<String>>("/plan").len());
+
+    let mut fixture = Fixture::load("data/run_migrations");
+    fixture.assert_outcome(
+        &raw.run_migrations(fixture.request::<RunMigrationsRequest>())
+            .await,
+    );
+    let result = client
+        .run_migrations(&fixture.request_field::<String>("/migrations_path"))
+        .await
+        .unwrap();
+    assert_eq!(result.success, fixture.expected::<bool>("/success"));
+    assert_eq!(
+        result.migrations_run,
+        fixture.expected::<i32>("/migrations_run")
+    );
+
+    let mut fixture = Fixture::load("data/migration_status");
+    fixture.assert_outcome(
+        &raw.migration_status(fixture.request::<MigrationStatusRequest>())
+            .await,
+    );
+    let migrations = client.migration_status().await.unwrap();
+    assert_eq!(
+        migrations.len(),
+        fixture
+            .expected::<Vec<serde_json::Value>>("/migrations")
+            .len()
+    );
+
+    let mut fixture = Fixture::load("data/ping");
+    fixture.assert_outcome(&raw.ping(fixture.request::<PingRequest>()).await);
+    let ping = client.ping().await.unwrap();
+    assert_eq!(ping.healthy, fixture.expected::<bool>("/healthy"));
+}
+
+#[tokio::test]
+async fn test_migrations() {
+    let (mut raw, mut client) = connect().await;
+    let mut fixture = Fixture::load("data/apply_migrations_not_configured");
+    fixture.assert_outcome(
+        &raw.apply_migrations(fixture.request::<ApplyMigrationsRequest>())
+            .await,
+    );
+    let error = client.apply_migrations(None).await.unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let (mut raw, mut client, _dir) = connect_with_migrations().await;
+    let mut fixture = Fixture::load("data/plan_migrations");
+    fixture.assert_outcome(
+        &raw.plan_migrations(fixture.request::<PlanMigrationsRequest>())
+            .await,
+    );
+    let plan = client
+        .plan_migrations(fixture.request_field("/target_version"))
+        .await
+        .unwrap();
+    assert_eq!(plan, fixture.expected::<MigrationPlan>(""));
+
+    let mut fixture = Fixture::load("data/plan_migrations_unknown_target");
+    fixture.assert_outcome(
+        &raw.plan_migrations(fixture.request::<PlanMigrationsRequest>())
+            .await,
+    );
+    let error = client
+        .plan_migrations(fixture.request_field("/target_version"))
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture = Fixture::load("data/apply_migrations");
+    fixture.assert_outcome(
+        &raw.apply_migrations(fixture.request::<ApplyMigrationsRequest>())
+            .await,
+    );
+    let applied = client.apply_migrations(None).await.unwrap();
+    assert_eq!(applied, fixture.expected::<MigrationPlan>(""));
+    let migrations = client.migration_status().await.unwrap();
+    assert!(migrations.iter().all(|migration| migration.applied));
+
+    let mut fixture = Fixture::load("data/rollback_migration");
+    fixture.assert_outcome(
+        &raw.rollback_migration(fixture.request::<RollbackMigrationRequest>())
+            .await,
+    );
+    let reverted = client.rollback_migration(None).await.unwrap();
+    assert_eq!(reverted, fixture.expected::<MigrationPlan>(""));
+}
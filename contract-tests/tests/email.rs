@@ -0,0 +1,339 @@
+//! Email service contracts
+
+use acton_dx::htmx::clients::{DeliveryState, EmailClient, EmailMessage, EmailTemplateSource};
+use acton_dx_proto::email::v1::{
+    email_service_client::EmailServiceClient, email_service_server::EmailServiceServer,
+    EmailTemplate, GetEmailStatsRequest, GetEmailStatusRequest, ListDevMailboxRequest,
+    ListSuppressedRequest, PreviewEmailRequest, SendBatchRequest, SendEmailRequest,
+    SendTemplatedEmailRequest, SuppressAddressRequest, UnsuppressAddressRequest,
+    ValidateAddressRequest,
+};
+use contract_tests::{serve, Fixture};
+use email_service::EmailServiceImpl;
+use tonic::service::Routes;
+use tonic::transport::Channel;
+
+/// A dry-run service with the `shipped` template registered
+fn service() -> EmailServiceImpl {
+    let service = EmailServiceImpl::mock().with_dry_run(10);
+    service
+        .templates()
+        .register(
+            "shipped",
+            &EmailTemplate {
+                subject: "Order {{ order }} shipped".to_string(),
+                html_body: None,
+                text_body: Some("On its way".to_string()),
+            },
+        )
+        .unwrap();
+    service
+}
+
+/// Two dry-run services: one called through the generated client, one
+/// through the `acton-dx` client
+async fn connect() -> (EmailServiceClient<Channel>, EmailClient) {
+    let raw = serve(Routes::new(EmailServiceServer::new(service()))).await;
+    let client = serve(Routes::new(EmailServiceServer::new(service()))).await;
+    (
+        EmailServiceClient::connect(raw).await.unwrap(),
+        EmailClient::connect(client).await.unwrap(),
+    )
+}
+
+/// The `acton-dx` message for an email in a fixture request
+fn message(fixture: &Fixture, pointer: &str) -> EmailMessage {
+    let mut message = EmailMessage::new()
+        .from(fixture.request_field::<String>(&format!("{pointer}/from/email")))
+        .subject(fixture.request_field::<String>(&format!("{pointer}/subject")))
+        .text(fixture.request_field::<String>(&format!("{pointer}/text_body")))
+        .template(fixture.request_field::<String>(&format!("{pointer}/template")));
+    let to: Vec<serde_json::Value> = fixture.request_field(&format!("{pointer}/to"));
+    for recipient in to {
+        let email = recipient["email"].as_str().unwrap();
+        message = match recipient["name"].as_str() {
+            Some(name) => message.to_named(email, name),
+            None => message.to(email),
+        };
+    }
+    message
+}
+
+#[tokio::test]
+async fn test_sending() {
+    let (mut raw, mut client) = connect().await;
+
+    let mut send = Fixture::load("email/send_email");
+    send.assert_outcome(&raw.send_email(send.request::<SendEmailRequest>()).await);
+    let delivered = client.send(message(&send, "/email")).await.unwrap();
+    assert_eq!(delivered.success, send.expected::<bool>("/success"));
+    assert!(delivered.message_id.is_some());
+
+    // The client always sends an email, so only the wire contract applies
+    let mut fixture = Fixture::load("email/send_email_without_email");
+    fixture.assert_outcome(&raw.send_email(fixture.request::<SendEmailRequest>()).await);
+
+    let mut fixture = Fixture::load("email/send_batch");
+    fixture.assert_outcome(&raw.send_batch(fixture.request::<SendBatchRequest>()).await);
+    let batch = client
+        .send_batch(vec![
+            message(&fixture, "/emails/0"),
+            message(&fixture, "/emails/1"),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(batch.total, fixture.expected::<i32>("/total"));
+    assert_eq!(batch.succeeded, fixture.expected::<i32>("/succeeded"));
+    assert_eq!(batch.failed, fixture.expected::<i32>("/failed"));
+    let successes: Vec<bool> = batch.results.iter().map(|result| result.success).collect();
+    assert_eq!(
+        successes,
+        [
+            fixture.expected::<bool>("/results/0/success"),
+            fixture.expected::<bool>("/results/1/success"),
+        ]
+    );
+
+    let mut fixture =
+        Fixture::load("email/list_dev_mailbox").with_var("message_id", send.var("message_id"));
+    fixture.assert_outcome(
+        &raw.list_dev_mailbox(fixture.request::<ListDevMailboxRequest>())
+            .await,
+    );
+    let mailbox = client
+        .list_dev_mailbox(fixture.request_field("/limit"))
+        .await
+        .unwrap();
+    assert_eq!(mailbox.dry_run, fixture.expected::<bool>("/dry_run"));
+    assert_eq!(mailbox.messages.len(), 2);
+    let captured = &mailbox.messages[1];
+    assert_eq!(captured.message_id, delivered.message_id.unwrap());
+    assert_eq!(
+        captured.email.subject,
+        fixture.expected::<String>("/messages/1/email/subject")
+    );
+    assert_eq!(
+        captured.email.to[0].name,
+        fixture.expected::<Option<String>>("/messages/1/email/to/0/name")
+    );
+    assert_eq!(
+        captured.email.template,
+        fixture.expected::<Option<String>>("/messages/1/email/template")
+    );
+
+    let mut fixture = Fixture::load("email/get_email_stats");
+    fixture.assert_outcome(
+        &raw.get_email_stats(fixture.request::<GetEmailStatsRequest>())
+            .await,
+    );
+    let stats = client.stats().await.unwrap();
+    assert_eq!(stats.sent, fixture.expected::<u64>("/sent"));
+    assert_eq!(stats.failed, fixture.expected::<u64>("/failed"));
+    assert_eq!(
+        stats.entries[0].template,
+        fixture.expected::<String>("/entries/0/template")
+    );
+    assert_eq!(
+        stats.entries[0].domain,
+        fixture.expected::<String>("/entries/0/domain")
+    );
+}
+
+#[tokio::test]
+async fn test_delivery_status() {
+    let (mut raw, mut client) = connect().await;
+
+    let mut send = Fixture::load("email/send_email");
+    send.assert_outcome(&raw.send_email(send.request::<SendEmailRequest>()).await);
+    let mut fixture =
+        Fixture::load("email/get_email_status").with_var("message_id", send.var("message_id"));
+    fixture.assert_outcome(
+        &raw.get_email_status(fixture.request::<GetEmailStatusRequest>())
+            .await,
+    );
+    let delivered = client.send(message(&send, "/email")).await.unwrap();
+    let message_id = delivered.message_id.unwrap();
+    let status = client.status(&message_id).await.unwrap();
+    assert_eq!(status.message_id, message_id);
+    assert_eq!(status.state, DeliveryState::Sent);
+    assert_eq!(status.attempts, fixture.expected::<u32>("/attempts"));
+
+    let fixture = Fixture::load("email/get_email_status_unknown");
+    let status = raw
+        .get_email_status(fixture.request::<GetEmailStatusRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .status(&fixture.request_field::<String>("/message_id"))
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+}
+
+#[tokio::test]
+async fn test_suppression() {
+    let (mut raw, mut client) = connect().await;
+
+    let mut fixture = Fixture::load("email/suppress_address");
+    fixture.assert_outcome(
+        &raw.suppress_address(fixture.request::<SuppressAddressRequest>())
+            .await,
+    );
+    let email = fixture.request_field::<String>("/email");
+    let reason = fixture.request_field::<String>("/reason");
+    let added = client.suppress(&email, &reason).await.unwrap();
+    assert_eq!(added, fixture.expected::<bool>("/added"));
+
+    let fixture = Fixture::load("email/suppress_address_invalid");
+    let status = raw
+        .suppress_address(fixture.request::<SuppressAddressRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .suppress(&fixture.request_field::<String>("/email"), "manual")
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture = Fixture::load("email/list_suppressed");
+    fixture.assert_outcome(
+        &raw.list_suppressed(fixture.request::<ListSuppressedRequest>())
+            .await,
+    );
+    let page = client
+        .list_suppressed(
+            fixture.request_field("/limit"),
+            fixture.request_field("/offset"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.total, fixture.expected::<u64>("/total"));
+    assert_eq!(
+        page.addresses[0].email,
+        fixture.expected::<String>("/addresses/0/email")
+    );
+    assert_eq!(
+        page.addresses[0].reason,
+        fixture.expected::<String>("/addresses/0/reason")
+    );
+
+    // Suppressed recipients are never emailed
+    let sent = client
+        .send(
+            EmailMessage::new()
+                .from("noreply@example.com")
+                .to(&email)
+                .subject("Hello")
+                .text("Hi"),
+        )
+        .await
+        .unwrap();
+    assert!(!sent.success);
+
+    let mut fixture = Fixture::load("email/unsuppress_address");
+    fixture.assert_outcome(
+        &raw.unsuppress_address(fixture.request::<UnsuppressAddressRequest>())
+            .await,
+    );
+    let removed = client
+        .unsuppress(&fixture.request_field::<String>("/email"))
+        .await
+        .unwrap();
+    assert_eq!(removed, fixture.expected::<bool>("/removed"));
+}
+
+#[tokio::test]
+async fn test_templated_sending() {
+    let (mut raw, mut client) = connect().await;
+
+    let mut fixture = Fixture::load("email/send_templated_email");
+    fixture.assert_outcome(
+        &raw.send_templated_email(fixture.request::<SendTemplatedEmailRequest>())
+            .await,
+    );
+    let context: serde_json::Value =
+        serde_json::from_str(&fixture.request_field::<String>("/context_json")).unwrap();
+    let envelope = EmailMessage::new()
+        .from(fixture.request_field::<String>("/email/from/email"))
+        .to(fixture.request_field::<String>("/email/to/0/email"));
+    let sent = client
+        .send_templated(
+            &fixture.request_field::<String>("/template"),
+            &context,
+            envelope.clone(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(sent.success, fixture.expected::<bool>("/success"));
+    let mailbox = client.list_dev_mailbox(0).await.unwrap();
+    assert_eq!(mailbox.messages[0].email.subject, "Order 42 shipped");
+
+    let fixture = Fixture::load("email/send_templated_email_unknown");
+    let status = raw
+        .send_templated_email(fixture.request::<SendTemplatedEmailRequest>())
+        .await
+        .unwrap_err();
+    fixture.assert_status(&status);
+    let error = client
+        .send_templated(
+            &fixture.request_field::<String>("/template"),
+            &serde_json::json!({}),
+            envelope,
+        )
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+}
+
+#[tokio::test]
+async fn test_validation_and_preview() {
+    let (mut raw, mut client) = connect().await;
+
+    for name in ["email/validate_address", "email/validate_address_invalid"] {
+        let mut fixture = Fixture::load(name);
+        fixture.assert_outcome(
+            &raw.validate_address(fixture.request::<ValidateAddressRequest>())
+                .await,
+        );
+        let result = client
+            .validate_address(&fixture.request_field::<String>("/email"))
+            .await
+            .unwrap();
+        assert_eq!(result.valid, fixture.expected::<bool>("/valid"));
+        assert_eq!(result.reason, fixture.expected::<Option<String>>("/reason"));
+    }
+
+    let mut fixture = Fixture::load("email/preview_email");
+    fixture.assert_outcome(
+        &raw.preview_email(fixture.request::<PreviewEmailRequest>())
+            .await,
+    );
+    let context: serde_json::Value =
+        serde_json::from_str(&fixture.request_field::<String>("/context_json")).unwrap();
+    let preview = client
+        .preview(
+            EmailTemplateSource {
+                subject: fixture.request_field("/template/subject"),
+                html_body: None,
+                text_body: fixture.request_field("/template/text_body"),
+            },
+            &context,
+        )
+        .await
+        .unwrap();
+    assert_eq!(preview.success, fixture.expected::<bool>("/success"));
+    assert_eq!(preview.subject, fixture.expected::<String>("/subject"));
+    assert_eq!(
+        preview.text_body,
+        fixture.expected::<Option<String>>("/text_body")
+    );
+
+    // The client always sends a template, so only the wire contract applies
+    let mut fixture = Fixture::load("email/preview_email_without_template");
+    fixture.assert_outcome(
+        &raw.preview_email(fixture.request::<PreviewEmailRequest>())
+            .await,
+    );
+}
@@ -0,0 +1,271 @@
+//! File service contracts, against local storage in a temporary directory
+//!
+//! Calls are made as a trusted caller acting for the `acme` tenant
+
+use acton_dx::htmx::clients::{ClientError, FileClient, SignedUrlVerification, StoredFileInfo};
+use acton_dx_proto::file::v1::{
+    file_service_client::FileServiceClient, file_service_server::FileServiceServer, DeleteRequest,
+    DownloadRequest, GetMetadataRequest, GetSignedUrlRequest, GetUrlRequest, GetUsageRequest,
+    InitiateUploadRequest, ListFilesRequest, UploadRequest, VerifySignedUrlRequest,
+};
+use acton_dx_proto::identity::TENANT_METADATA_KEY;
+use contract_tests::{serve, Fixture};
+use file_service::FileServiceImpl;
+use std::collections::HashMap;
+use tempfile::TempDir;
+use tokio_stream::StreamExt;
+use tonic::metadata::MetadataValue;
+use tonic::service::Routes;
+use tonic::transport::Channel;
+use tonic::Request;
+
+async fn service(dir: &TempDir, name: &str) -> FileServiceImpl {
+    FileServiceImpl::new(
+        dir.path().join(name),
+        "http://files.test".to_string(),
+        Some("contract-test-signing-key".to_string()),
+        64 * 1024,
+    )
+    .await
+    .unwrap()
+}
+
+/// Two stores: one called through the generated client, one through the
+/// `acton-dx` client, so uploads can be replayed on both
+async fn connect() -> (FileServiceClient<Channel>, FileClient, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let raw = serve(Routes::new(FileServiceServer::new(
+        service(&dir, "raw").await,
+    )))
+    .await;
+    let client = serve(Routes::new(FileServiceServer::new(
+        service(&dir, "client").await,
+    )))
+    .await;
+    (
+        FileServiceClient::connect(raw).await.unwrap(),
+        FileClient::connect(client).await.unwrap(),
+        dir,
+    )
+}
+
+/// Wrap a message in a request acting for the `acme` tenant
+fn acme<T>(message: T) -> Request<T> {
+    let mut request = Request::new(message);
+    request
+        .metadata_mut()
+        .insert(TENANT_METADATA_KEY, MetadataValue::from_static("acme"));
+    request
+}
+
+/// Upload the fixture's stream of messages through the generated client
+async fn upload(raw: &mut FileServiceClient<Channel>, fixture: &mut Fixture) {
+    let messages: Vec<UploadRequest> = fixture.request();
+    fixture.assert_outcome(&raw.upload(acme(tokio_stream::iter(messages))).await);
+}
+
+/// Upload the fixture's file through the `acton-dx` client
+async fn upload_file(client: &mut FileClient, fixture: &Fixture) -> StoredFileInfo {
+    let result = client
+        .upload(
+            &fixture.request_field::<String>("/0/data/metadata/filename"),
+            &fixture.request_field::<String>("/0/data/metadata/content_type"),
+            fixture.request_field("/1/data/chunk"),
+            fixture.request_field::<HashMap<String, String>>("/0/data/metadata/metadata"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.success, fixture.expected::<bool>("/success"));
+    result.file.expect("uploaded file")
+}
+
+#[tokio::test]
+async fn test_files() {
+    let (mut raw, untenanted, _dir) = connect().await;
+    let mut client = untenanted.clone().with_tenant("acme").unwrap();
+
+    let mut fixture = Fixture::load("file/upload");
+    upload(&mut raw, &mut fixture).await;
+    let file = upload_file(&mut client, &fixture).await;
+    assert_eq!(file.filename, fixture.expected::<String>("/file/filename"));
+    assert_eq!(file.size, fixture.expected::<i64>("/file/size"));
+    assert_eq!(file.checksum, fixture.expected::<String>("/file/checksum"));
+    assert_eq!(
+        file.metadata,
+        fixture.expected::<HashMap<String, String>>("/file/metadata")
+    );
+    let file_id = fixture.var("file_id").clone();
+
+    let mut fixture = Fixture::load("file/upload_without_metadata");
+    upload(&mut raw, &mut fixture).await;
+
+    let mut fixture = Fixture::load("file/download").with_var("file_id", &file_id);
+    let messages: Vec<_> = raw
+        .download(acme(fixture.request::<DownloadRequest>()))
+        .await
+        .unwrap()
+        .into_inner()
+        .collect::<Result<_, _>>()
+        .await
+        .unwrap();
+    fixture.assert_response(&messages);
+    let download = client.download(&file.id).await.unwrap();
+    assert_eq!(download.data, fixture.expected::<Vec<u8>>("/1/data/chunk"));
+    assert_eq!(
+        download.metadata.filename,
+        fixture.expected::<String>("/0/data/metadata/filename")
+    );
+
+    let mut fixture = Fixture::load("file/get_metadata").with_var("file_id", &file_id);
+    fixture.assert_outcome(
+        &raw.get_metadata(acme(fixture.request::<GetMetadataRequest>()))
+            .await,
+    );
+    let metadata = client.get_metadata(&file.id).await.unwrap();
+    assert_eq!(
+        metadata.content_type,
+        fixture.expected::<String>("/content_type")
+    );
+    assert_eq!(metadata.checksum, fixture.expected::<String>("/checksum"));
+
+    // Trusted callers reach a tenant's files only by naming the tenant
+    let status = raw
+        .get_metadata(fixture.request::<GetMetadataRequest>())
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    let error = untenanted.clone().get_metadata(&file.id).await.unwrap_err();
+    assert!(
+        matches!(error, ClientError::ServiceError { ref code, .. } if *code == status.code().to_string())
+    );
+
+    let mut fixture = Fixture::load("file/get_metadata_not_found");
+    fixture.assert_outcome(
+        &raw.get_metadata(acme(fixture.request::<GetMetadataRequest>()))
+            .await,
+    );
+    let error = client
+        .get_metadata(&fixture.request_field::<String>("/file_id"))
+        .await
+        .unwrap_err();
+    fixture.assert_client_error(&error);
+
+    let mut fixture = Fixture::load("file/list_files").with_var("file_id", &file_id);
+    fixture.assert_outcome(
+        &raw.list_files(acme(fixture.request::<ListFilesRequest>()))
+            .await,
+    );
+    let listed = client
+        .list_files(
+            fixture.request_field("/path_prefix"),
+            fixture.request_field("/limit"),
+            None,
+        )
+        .await
+        .unwrap();
+    let ids: Vec<&str> = listed.files.iter().map(|file| file.id.as_str()).collect();
+    assert_eq!(ids, [file.id.as_str()]);
+
+    let mut fixture = Fixture::load("file/get_usage");
+    fixture.assert_outcome(
+        &raw.get_usage(acme(fixture.request::<GetUsageRequest>()))
+            .await,
+    );
+    let usage = client
+        .get_usage(fixture.request_field("/tenant_id"))
+        .await
+        .unwrap();
+    assert_eq!(usage.used_bytes, fixture.expected::<i64>("/used_bytes"));
+    assert_eq!(usage.file_count, fixture.expected::<i64>("/file_count"));
+
+    let mut fixture = Fixture::load("file/get_usage_without_tenant");
+    fixture.assert_outcome(&raw.get_usage(fixture.request::<GetUsageRequest>()).await);
+    let error = untenanted.clone().get_usage(None).await.unwrap_err();
+    fixture.assert_client_error(&error);
+
+    // The client has no multipart uploads, so only the wire contract applies
+    let mut fixture = Fixture::load("file/initiate_upload_without_metadata");
+    fixture.assert_outcome(
+        &raw.initiate_upload(acme(fixture.request::<InitiateUploadRequest>()))
+            .await,
+    );
+
+    let mut fixture = Fixture::load("file/delete").with_var("file_id", &file_id);
+    fixture.assert_outcome(&raw.delete(acme(fixture.request::<DeleteRequest>())).await);
+    let deleted = client.delete(&file.id).await.unwrap();
+    assert_eq!(deleted, fixture.expected::<bool>("/success"));
+}
+
+#[tokio::test]
+async fn test_urls() {
+    let (mut raw, client, _dir) = connect().await;
+    let mut client = client.with_tenant("acme").unwrap();
+    let mut fixture = Fixture::load("file/upload");
+    upload(&mut raw, &mut fixture).await;
+    let file = upload_file(&mut client, &fixture).await;
+    let file_id = fixture.var("file_id").clone();
+
+    let mut fixture = Fixture::load("file/get_public_url").with_var("file_id", &file_id);
+    fixture.assert_outcome(
+        &raw.get_public_url(acme(fixture.request::<GetUrlRequest>()))
+            .await,
+    );
+    let url = client.get_public_url(&file.id).await.unwrap();
+    assert_eq!(url, format!("http://files.test/{}", file.id));
+
+    let mut signed = Fixture::load("file/get_signed_url").with_var("file_id", &file_id);
+    signed.assert_outcome(
+        &raw.get_signed_url(acme(signed.request::<GetSignedUrlRequest>()))
+            .await,
+    );
+    let signed_url = client
+        .get_scoped_signed_url(
+            &file.id,
+            signed.request_field("/expires_in_seconds"),
+            &signed.request_field::<String>("/method"),
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(signed_url.expires_at.is_some());
+
+    let mut fixture = Fixture::load("file/verify_signed_url")
+        .with_var("file_id", &file_id)
+        .with_var("signed_url", signed.var("signed_url"));
+    fixture.assert_outcome(
+        &raw.verify_signed_url(acme(fixture.request::<VerifySignedUrlRequest>()))
+            .await,
+    );
+    let verification = client
+        .verify_signed_url(
+            &signed_url.url,
+            &fixture.request_field::<String>("/method"),
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(
+        matches!(verification, SignedUrlVerification::Valid { ref file_id, .. } if *file_id == file.id)
+    );
+
+    let mut fixture = Fixture::load("file/verify_signed_url_wrong_method")
+        .with_var("signed_url", signed.var("signed_url"));
+    fixture.assert_outcome(
+        &raw.verify_signed_url(acme(fixture.request::<VerifySignedUrlRequest>()))
+            .await,
+    );
+    let verification = client
+        .verify_signed_url(
+            &signed_url.url,
+            &fixture.request_field::<String>("/method"),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        verification,
+        SignedUrlVerification::Invalid {
+            reason: fixture.expected("/reason"),
+        }
+    );
+}
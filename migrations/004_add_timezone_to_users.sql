@@ -0,0 +1,12 @@
+-- Add per-user timezone to users table
+-- Migration: 004_add_timezone_to_users
+-- Purpose: Store the IANA timezone used to render timestamps for each user
+
+-- Add timezone column (NULL follows the browser-detected timezone)
+ALTER TABLE users
+ADD COLUMN timezone TEXT;
+
+COMMENT ON COLUMN users.timezone IS 'IANA timezone name (e.g. Europe/Berlin)';
+
+-- ROLLBACK INSTRUCTIONS (if needed):
+-- ALTER TABLE users DROP COLUMN IF EXISTS timezone;
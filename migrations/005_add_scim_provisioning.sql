@@ -0,0 +1,54 @@
+-- Add SCIM provisioning fields to users table
+-- Migration: 005_add_scim_provisioning
+-- Purpose: Store IdP-managed user attributes and groups for SCIM 2.0 provisioning
+
+-- Deactivated users keep their row so their data stays attributable
+ALTER TABLE users
+ADD COLUMN active BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- SCIM attributes (NULL for users created outside SCIM)
+ALTER TABLE users
+ADD COLUMN scim_user_name TEXT,
+ADD COLUMN scim_external_id TEXT,
+ADD COLUMN given_name TEXT,
+ADD COLUMN family_name TEXT,
+ADD COLUMN display_name TEXT;
+
+-- userName is unique and case-insensitive
+CREATE UNIQUE INDEX idx_users_scim_user_name ON users(LOWER(scim_user_name));
+CREATE INDEX idx_users_scim_external_id ON users(scim_external_id);
+
+-- Groups pushed by the identity provider
+CREATE TABLE IF NOT EXISTS scim_groups (
+    id TEXT PRIMARY KEY,
+    external_id TEXT,
+    display_name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_scim_groups_display_name ON scim_groups(LOWER(display_name));
+
+CREATE TABLE IF NOT EXISTS scim_group_members (
+    group_id TEXT NOT NULL REFERENCES scim_groups(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (group_id, user_id)
+);
+
+CREATE INDEX idx_scim_group_members_user ON scim_group_members(user_id);
+
+COMMENT ON COLUMN users.active IS 'FALSE once the identity provider deactivates the user';
+COMMENT ON COLUMN users.scim_user_name IS 'SCIM userName (unique, case-insensitive)';
+COMMENT ON COLUMN users.scim_external_id IS 'Identity provider ID of the user';
+
+-- ROLLBACK INSTRUCTIONS (if needed):
+-- DROP TABLE IF EXISTS scim_group_members;
+-- DROP TABLE IF EXISTS scim_groups;
+-- DROP INDEX IF EXISTS idx_users_scim_external_id;
+-- DROP INDEX IF EXISTS idx_users_scim_user_name;
+-- ALTER TABLE users DROP COLUMN IF EXISTS display_name;
+-- ALTER TABLE users DROP COLUMN IF EXISTS family_name;
+-- ALTER TABLE users DROP COLUMN IF EXISTS given_name;
+-- ALTER TABLE users DROP COLUMN IF EXISTS scim_external_id;
+-- ALTER TABLE users DROP COLUMN IF EXISTS scim_user_name;
+-- ALTER TABLE users DROP COLUMN IF EXISTS active;
@@ -3,6 +3,6 @@
 pub mod session_manager;
 
 pub use session_manager::{
-    AddFlash, CleanupExpired, CreateSession, DeleteSession, LoadSession, SessionManagerAgent,
-    TakeFlashes, UpdateSession,
+    AddFlash, CleanupExpired, CreateSession, DeleteSession, ExportSessions, ImportSession,
+    LoadSession, RefreshSession, SessionManagerAgent, TakeFlashes, TouchSession, UpdateSession,
 };
@@ -2,13 +2,27 @@
 //!
 //! Uses acton-reactive for concurrent session state management with
 //! proper isolation between reads and writes.
+//!
+//! Sessions are served from memory. With a [`PostgresSessionStore`] every
+//! change is written through to the store before the request is answered,
+//! and a session that is not in memory is loaded from the store before the
+//! request that needs it runs.
 
+use crate::store::PostgresSessionStore;
 use crate::{FlashMessage, SessionData};
 use acton_reactive::prelude::*;
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
+use subtle::ConstantTimeEq;
 use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tonic::Status;
+
+/// Boxed future returned by message handlers.
+type FutureBox = Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>>;
 
 /// Type alias for response channels (cloneable for actor message requirements).
 pub type ResponseChannel<T> = Arc<Mutex<Option<oneshot::Sender<T>>>>;
@@ -39,6 +53,8 @@ pub async fn send_response<T>(response_tx: ResponseChannel<T>, value: T) -> Resu
 pub struct SessionManagerAgent {
     /// In-memory session storage.
     sessions: HashMap<String, SessionData>,
+    /// Persistent store sessions are written through to.
+    store: Option<PostgresSessionStore>,
     /// Cleanup interval in seconds.
     cleanup_interval_secs: u64,
 }
@@ -49,10 +65,18 @@ impl SessionManagerAgent {
     pub fn new(cleanup_interval_secs: u64) -> Self {
         Self {
             sessions: HashMap::new(),
+            store: None,
             cleanup_interval_secs,
         }
     }
 
+    /// Persist sessions to `store`.
+    #[must_use]
+    pub fn with_store(mut self, store: PostgresSessionStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
     /// Spawn the session manager agent.
     ///
     /// # Errors
@@ -66,13 +90,34 @@ impl SessionManagerAgent {
         runtime: &mut ActorRuntime,
         cleanup_interval_secs: u64,
     ) -> anyhow::Result<ActorHandle> {
+        Self::spawn_with(runtime, Self::new(cleanup_interval_secs)).await
+    }
+
+    /// Spawn the session manager agent with sessions persisted to `store`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if agent initialization fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ERN "auth-service" is invalid (which should not happen).
+    pub async fn spawn_with_store(
+        runtime: &mut ActorRuntime,
+        cleanup_interval_secs: u64,
+        store: PostgresSessionStore,
+    ) -> anyhow::Result<ActorHandle> {
+        Self::spawn_with(runtime, Self::new(cleanup_interval_secs).with_store(store)).await
+    }
+
+    async fn spawn_with(runtime: &mut ActorRuntime, model: Self) -> anyhow::Result<ActorHandle> {
         let config = ActorConfig::new(
             Ern::with_root("auth-service").expect("auth-service is a valid ERN"),
             None,
             None,
         )?;
         let mut builder = runtime.new_actor_with_config::<Self>(config);
-        builder.model = Self::new(cleanup_interval_secs);
+        builder.model = model;
         let cleanup_interval = builder.model.cleanup_interval_secs;
 
         Self::configure_handlers(&mut builder);
@@ -87,46 +132,77 @@ impl SessionManagerAgent {
         builder
             .mutate_on::<CreateSession>(|agent, ctx| {
                 let msg = ctx.message();
-                let session = SessionData::new(msg.ttl_seconds, msg.user_id);
-                let response_session = session.clone();
+                let mut session = SessionData::new(msg.ttl_seconds, msg.user_id);
+                session.data.clone_from(&msg.initial_data);
+                if let Some(absolute_ttl_seconds) = msg.absolute_ttl_seconds {
+                    session = session.with_absolute_ttl(absolute_ttl_seconds);
+                }
+                session.refresh_token_hash.clone_from(&msg.refresh_token_hash);
+                let saved = agent.model.save(&session);
                 let response_tx = msg.response_tx.clone();
-                agent.model.sessions.insert(session.session_id.clone(), session);
-                Reply::pending(send_optional_response(response_tx, response_session))
+                agent.model.sessions.insert(session.session_id.clone(), session.clone());
+                Reply::pending(async move {
+                    finish(saved).await;
+                    send_optional_response(response_tx, session).await;
+                })
             })
             .act_on::<LoadSession>(|agent, ctx| {
-                let msg = ctx.message();
-                let session = agent.model.sessions.get(&msg.session_id).cloned();
-                let response_tx = msg.response_tx.clone();
-                Reply::pending(send_optional_response(response_tx, session))
+                let op = SessionOp::Load(ctx.message().clone());
+                agent
+                    .model
+                    .fetch_missing(&op, agent.handle())
+                    .unwrap_or_else(|| agent.model.read(op))
             })
             .mutate_on::<UpdateSession>(|agent, ctx| {
-                let msg = ctx.message();
-                let result = update_session_data(&mut agent.model.sessions, msg);
-                let response_tx = msg.response_tx.clone();
-                Reply::pending(send_optional_response(response_tx, result))
-            })
-            .mutate_on::<DeleteSession>(|agent, ctx| {
-                let msg = ctx.message();
-                let deleted = agent.model.sessions.remove(&msg.session_id).is_some();
-                let response_tx = msg.response_tx.clone();
-                Reply::pending(send_optional_response(response_tx, deleted))
+                let handle = agent.handle().clone();
+                agent.model.dispatch(SessionOp::Update(ctx.message().clone()), &handle)
             })
+            .mutate_on::<DeleteSession>(|agent, ctx| agent.model.delete(ctx.message()))
             .mutate_on::<AddFlash>(|agent, ctx| {
-                let msg = ctx.message();
-                let success = add_flash_to_session(&mut agent.model.sessions, msg);
-                let response_tx = msg.response_tx.clone();
-                Reply::pending(send_optional_response(response_tx, success))
+                let handle = agent.handle().clone();
+                agent.model.dispatch(SessionOp::AddFlash(ctx.message().clone()), &handle)
             })
             .mutate_on::<TakeFlashes>(|agent, ctx| {
-                let msg = ctx.message();
-                let flashes = take_flashes_from_session(&mut agent.model.sessions, &msg.session_id);
-                let response_tx = msg.response_tx.clone();
-                Reply::pending(send_optional_response(response_tx, flashes))
+                let handle = agent.handle().clone();
+                agent.model.dispatch(SessionOp::TakeFlashes(ctx.message().clone()), &handle)
+            })
+            .mutate_on::<TouchSession>(|agent, ctx| {
+                let handle = agent.handle().clone();
+                agent.model.dispatch(SessionOp::Touch(ctx.message().clone()), &handle)
+            })
+            .mutate_on::<RefreshSession>(|agent, ctx| {
+                let handle = agent.handle().clone();
+                agent.model.dispatch(SessionOp::Refresh(ctx.message().clone()), &handle)
+            })
+            .mutate_on::<SessionFetched>(|agent, ctx| {
+                let msg = ctx.message().clone();
+                if let Some(session) = msg.session.filter(|session| !session.is_dead()) {
+                    // A copy loaded or created meanwhile is newer
+                    agent
+                        .model
+                        .sessions
+                        .entry(session.session_id.clone())
+                        .or_insert(session);
+                }
+                agent.model.apply(msg.op)
+            })
+            .mutate_on::<ImportSession>(|agent, ctx| agent.model.import(ctx.message()))
+            .act_on::<ExportSessions>(|agent, ctx| {
+                agent.model.export(ctx.message().response_tx.clone())
             })
             .mutate_on::<CleanupExpired>(|agent, _ctx| {
-                agent.model.sessions.retain(|_, session| !session.is_expired());
+                agent.model.sessions.retain(|_, session| !session.is_dead());
                 tracing::debug!("Cleaned up sessions, remaining: {}", agent.model.sessions.len());
-                Reply::ready()
+                let purged = agent
+                    .model
+                    .store
+                    .clone()
+                    .map(|store| spawn_store_op(async move { store.delete_expired().await }));
+                Reply::pending(async move {
+                    if let Some(purged) = finish(purged).await {
+                        tracing::debug!("Deleted {purged} expired sessions from the store");
+                    }
+                })
             });
     }
 
@@ -140,6 +216,176 @@ impl SessionManagerAgent {
             }
         });
     }
+
+    /// Run `op` now, or once its session has been loaded from the store.
+    fn dispatch(&mut self, op: SessionOp, handle: &ActorHandle) -> FutureBox {
+        self.fetch_missing(&op, handle)
+            .unwrap_or_else(|| self.apply(op))
+    }
+
+    /// Load the session `op` needs from the store if it is not in memory.
+    ///
+    /// The loaded session comes back as [`SessionFetched`], which runs `op`.
+    fn fetch_missing(&self, op: &SessionOp, handle: &ActorHandle) -> Option<FutureBox> {
+        if self.sessions.contains_key(op.session_id()) {
+            return None;
+        }
+        let store = self.store.clone()?;
+        let session_id = op.session_id().to_string();
+        let fetched = spawn_store_op(async move { store.load(&session_id).await });
+        let (op, handle) = (op.clone(), handle.clone());
+        Some(Box::pin(async move {
+            let session = finish(Some(fetched)).await.flatten();
+            handle.send(SessionFetched { session, op }).await;
+        }))
+    }
+
+    /// Answer a load from memory.
+    fn read(&self, op: SessionOp) -> FutureBox {
+        match op {
+            SessionOp::Load(msg) => {
+                let session = self.sessions.get(&msg.session_id).cloned();
+                Box::pin(send_optional_response(msg.response_tx, session))
+            }
+            op => {
+                tracing::error!(?op, "Session change routed to a read handler");
+                Box::pin(async {})
+            }
+        }
+    }
+
+    /// Run `op` against the sessions in memory, writing changes through.
+    fn apply(&mut self, op: SessionOp) -> FutureBox {
+        match op {
+            SessionOp::Load(_) => self.read(op),
+            SessionOp::Update(msg) => {
+                let result = update_session_data(&mut self.sessions, &msg);
+                let saved = result.as_ref().and_then(|session| self.save(session));
+                Box::pin(async move {
+                    finish(saved).await;
+                    send_optional_response(msg.response_tx, result).await;
+                })
+            }
+            SessionOp::AddFlash(msg) => {
+                let success = add_flash_to_session(&mut self.sessions, &msg);
+                let saved = self.save_stored(success, &msg.session_id);
+                Box::pin(async move {
+                    finish(saved).await;
+                    send_optional_response(msg.response_tx, success).await;
+                })
+            }
+            SessionOp::TakeFlashes(msg) => {
+                let flashes = take_flashes_from_session(&mut self.sessions, &msg.session_id);
+                let saved = self.save_stored(!flashes.is_empty(), &msg.session_id);
+                Box::pin(async move {
+                    finish(saved).await;
+                    send_optional_response(msg.response_tx, flashes).await;
+                })
+            }
+            SessionOp::Touch(msg) => {
+                let session = touch_session(&mut self.sessions, &msg);
+                let saved = session.as_ref().and_then(|session| self.save(session));
+                Box::pin(async move {
+                    finish(saved).await;
+                    send_optional_response(msg.response_tx, session).await;
+                })
+            }
+            SessionOp::Refresh(msg) => self.refresh(msg),
+        }
+    }
+
+    /// Move a session to a new ID, revoking the old ID in the store too.
+    fn refresh(&mut self, msg: RefreshSession) -> FutureBox {
+        let session = refresh_session(&mut self.sessions, &msg);
+        let deleted = self
+            .store
+            .clone()
+            .filter(|_| session.is_some())
+            .map(|store| {
+                let session_id = msg.session_id.clone();
+                spawn_store_op(async move { store.delete(&session_id).await })
+            });
+        let saved = session.as_ref().and_then(|session| self.save(session));
+        Box::pin(async move {
+            finish(deleted).await;
+            finish(saved).await;
+            send_optional_response(msg.response_tx, session).await;
+        })
+    }
+
+    /// Delete a session from memory and the store.
+    fn delete(&mut self, msg: &DeleteSession) -> FutureBox {
+        let removed = self.sessions.remove(&msg.session_id).is_some();
+        let deleted = self.store.clone().map(|store| {
+            let session_id = msg.session_id.clone();
+            spawn_store_op(async move { store.delete(&session_id).await })
+        });
+        let response_tx = msg.response_tx.clone();
+        Box::pin(async move {
+            let deleted = finish(deleted).await.unwrap_or(false) || removed;
+            send_optional_response(response_tx, deleted).await;
+        })
+    }
+
+    /// Store an imported session.
+    ///
+    /// A session that is not in memory and must not replace a stored one is
+    /// only inserted into the store, which knows whether it already has it.
+    fn import(&mut self, msg: &ImportSession) -> FutureBox {
+        let response_tx = msg.response_tx.clone();
+        let insert_only = !msg.replace_existing
+            && !msg.session.is_expired()
+            && !self.sessions.contains_key(&msg.session.session_id);
+        if let Some(store) = self.store.clone().filter(|_| insert_only) {
+            let session = msg.session.clone();
+            let inserted = spawn_store_op(async move { store.insert_new(&session).await });
+            return Box::pin(async move {
+                let imported = finish(Some(inserted)).await.unwrap_or(false);
+                send_optional_response(response_tx, imported).await;
+            });
+        }
+
+        let imported = import_session(&mut self.sessions, msg);
+        let saved = self.save_stored(imported, &msg.session.session_id);
+        Box::pin(async move {
+            finish(saved).await;
+            send_optional_response(response_tx, imported).await;
+        })
+    }
+
+    /// List every unexpired session, from the store if there is one.
+    fn export(&self, response_tx: Option<ResponseChannel<Vec<SessionData>>>) -> FutureBox {
+        if let Some(store) = self.store.clone() {
+            let loaded = spawn_store_op(async move { store.load_all().await });
+            return Box::pin(async move {
+                let sessions = finish(Some(loaded)).await.unwrap_or_default();
+                send_optional_response(response_tx, sessions).await;
+            });
+        }
+
+        let sessions: Vec<SessionData> = self
+            .sessions
+            .values()
+            .filter(|session| !session.is_expired())
+            .cloned()
+            .collect();
+        Box::pin(send_optional_response(response_tx, sessions))
+    }
+
+    /// Write `session` through to the store, if there is one.
+    fn save(&self, session: &SessionData) -> Option<JoinHandle<Option<()>>> {
+        let store = self.store.clone()?;
+        let session = session.clone();
+        Some(spawn_store_op(async move { store.save(&session).await }))
+    }
+
+    /// Write the session in memory under `session_id` through, if `changed`.
+    fn save_stored(&self, changed: bool, session_id: &str) -> Option<JoinHandle<Option<()>>> {
+        self.sessions
+            .get(session_id)
+            .filter(|_| changed)
+            .and_then(|session| self.save(session))
+    }
 }
 
 // ============================================================================
@@ -153,6 +399,27 @@ async fn send_optional_response<T>(response_tx: Option<ResponseChannel<T>>, valu
     }
 }
 
+/// Run a store operation on its own task.
+///
+/// Handler futures must be `Sync`, which gRPC calls are not; a task's join
+/// handle is. Failures are logged and yield `None`.
+fn spawn_store_op<T: Send + 'static>(
+    op: impl Future<Output = Result<T, Status>> + Send + 'static,
+) -> JoinHandle<Option<T>> {
+    tokio::spawn(async move {
+        op.await
+            .map_err(|status| {
+                tracing::error!(error = %status.message(), "Session store operation failed");
+            })
+            .ok()
+    })
+}
+
+/// Wait for a store operation, if one was started.
+async fn finish<T>(op: Option<JoinHandle<Option<T>>>) -> Option<T> {
+    op?.await.ok().flatten()
+}
+
 /// Update session data and return the updated session.
 fn update_session_data(
     sessions: &mut HashMap<String, SessionData>,
@@ -191,6 +458,51 @@ fn take_flashes_from_session(
         .unwrap_or_default()
 }
 
+/// Slide an unexpired session's expiry forward.
+fn touch_session(
+    sessions: &mut HashMap<String, SessionData>,
+    msg: &TouchSession,
+) -> Option<SessionData> {
+    let session = sessions
+        .get_mut(&msg.session_id)
+        .filter(|session| !session.is_expired())?;
+    session.touch(msg.idle_timeout_seconds);
+    Some(session.clone())
+}
+
+/// Move a session to a new ID if the refresh token's secret matches.
+fn refresh_session(
+    sessions: &mut HashMap<String, SessionData>,
+    msg: &RefreshSession,
+) -> Option<SessionData> {
+    let session = sessions.get(&msg.session_id)?;
+    let matches = session.refresh_token_hash.as_ref().is_some_and(|hash| {
+        bool::from(hash.as_bytes().ct_eq(msg.refresh_token_hash.as_bytes()))
+    });
+    if !matches || !session.is_refreshable() {
+        return None;
+    }
+
+    let mut session = sessions.remove(&msg.session_id)?;
+    session.rotate(msg.ttl_seconds, msg.new_refresh_token_hash.clone());
+    sessions.insert(session.session_id.clone(), session.clone());
+    Some(session)
+}
+
+/// Store an imported session under its own ID.
+///
+/// Expired sessions are skipped, as are sessions already stored unless the
+/// import replaces them.
+fn import_session(sessions: &mut HashMap<String, SessionData>, msg: &ImportSession) -> bool {
+    if msg.session.is_expired()
+        || (!msg.replace_existing && sessions.contains_key(&msg.session.session_id))
+    {
+        return false;
+    }
+    sessions.insert(msg.session.session_id.clone(), msg.session.clone());
+    true
+}
+
 // ============================================================================
 // Messages
 // ============================================================================
@@ -204,6 +516,10 @@ pub struct CreateSession {
     pub ttl_seconds: u64,
     /// Initial data for the session.
     pub initial_data: std::collections::HashMap<String, String>,
+    /// Cap on the session's lifetime, including uses and refreshes.
+    pub absolute_ttl_seconds: Option<u64>,
+    /// Hash of the secret of the refresh token issued with the session.
+    pub refresh_token_hash: Option<String>,
     /// Response channel for the created session.
     pub response_tx: Option<ResponseChannel<SessionData>>,
 }
@@ -220,10 +536,36 @@ impl CreateSession {
             user_id,
             ttl_seconds,
             initial_data: std::collections::HashMap::new(),
+            absolute_ttl_seconds: None,
+            refresh_token_hash: None,
             response_tx: Some(response_tx),
         };
         (request, rx)
     }
+
+    /// Cap the session's lifetime, including uses and refreshes.
+    #[must_use]
+    pub const fn with_absolute_ttl(mut self, absolute_ttl_seconds: u64) -> Self {
+        self.absolute_ttl_seconds = Some(absolute_ttl_seconds);
+        self
+    }
+
+    /// Start the session with `initial_data`.
+    #[must_use]
+    pub fn with_initial_data(
+        mut self,
+        initial_data: std::collections::HashMap<String, String>,
+    ) -> Self {
+        self.initial_data = initial_data;
+        self
+    }
+
+    /// Issue a refresh token whose secret hashes to `refresh_token_hash`.
+    #[must_use]
+    pub fn with_refresh_token_hash(mut self, refresh_token_hash: String) -> Self {
+        self.refresh_token_hash = Some(refresh_token_hash);
+        self
+    }
 }
 
 /// Load a session by ID.
@@ -352,6 +694,151 @@ impl TakeFlashes {
     }
 }
 
+/// Slide a session's expiry forward on use.
+#[derive(Clone, Debug)]
+pub struct TouchSession {
+    /// Session ID.
+    pub session_id: String,
+    /// Seconds from now the session should stay valid without further use.
+    pub idle_timeout_seconds: u64,
+    /// Response channel, `None` if the session is missing or expired.
+    pub response_tx: Option<ResponseChannel<Option<SessionData>>>,
+}
+
+impl TouchSession {
+    /// Create a new touch session request with response channel.
+    #[must_use]
+    pub fn with_response(
+        session_id: String,
+        idle_timeout_seconds: u64,
+    ) -> (Self, oneshot::Receiver<Option<SessionData>>) {
+        let (response_tx, rx) = create_request_reply();
+        let request = Self {
+            session_id,
+            idle_timeout_seconds,
+            response_tx: Some(response_tx),
+        };
+        (request, rx)
+    }
+}
+
+/// Move a session to a new ID and refresh token.
+#[derive(Clone, Debug)]
+pub struct RefreshSession {
+    /// Session ID the refresh token was issued for.
+    pub session_id: String,
+    /// Hash of the refresh token's secret.
+    pub refresh_token_hash: String,
+    /// Hash of the secret of the refresh token replacing it.
+    pub new_refresh_token_hash: String,
+    /// Lifetime of the refreshed session, capped at its absolute expiry.
+    pub ttl_seconds: u64,
+    /// Response channel, `None` if the token is invalid or the session can
+    /// no longer be refreshed.
+    pub response_tx: Option<ResponseChannel<Option<SessionData>>>,
+}
+
+impl RefreshSession {
+    /// Create a new refresh session request with response channel.
+    #[must_use]
+    pub fn with_response(
+        session_id: String,
+        refresh_token_hash: String,
+        new_refresh_token_hash: String,
+        ttl_seconds: u64,
+    ) -> (Self, oneshot::Receiver<Option<SessionData>>) {
+        let (response_tx, rx) = create_request_reply();
+        let request = Self {
+            session_id,
+            refresh_token_hash,
+            new_refresh_token_hash,
+            ttl_seconds,
+            response_tx: Some(response_tx),
+        };
+        (request, rx)
+    }
+}
+
+/// A request that needs its session in memory.
+#[derive(Clone, Debug)]
+enum SessionOp {
+    Load(LoadSession),
+    Update(UpdateSession),
+    AddFlash(AddFlash),
+    TakeFlashes(TakeFlashes),
+    Touch(TouchSession),
+    Refresh(RefreshSession),
+}
+
+impl SessionOp {
+    fn session_id(&self) -> &str {
+        match self {
+            Self::Load(msg) => &msg.session_id,
+            Self::Update(msg) => &msg.session_id,
+            Self::AddFlash(msg) => &msg.session_id,
+            Self::TakeFlashes(msg) => &msg.session_id,
+            Self::Touch(msg) => &msg.session_id,
+            Self::Refresh(msg) => &msg.session_id,
+        }
+    }
+}
+
+/// A session loaded from the store, and the request waiting for it.
+#[derive(Clone, Debug)]
+struct SessionFetched {
+    session: Option<SessionData>,
+    op: SessionOp,
+}
+
+/// Store a session moved from another session store, keeping its ID.
+#[derive(Clone, Debug)]
+pub struct ImportSession {
+    /// Session to store.
+    pub session: SessionData,
+    /// Replace a session already stored under the same ID.
+    pub replace_existing: bool,
+    /// Response channel, `true` if the session was stored.
+    pub response_tx: Option<ResponseChannel<bool>>,
+}
+
+impl ImportSession {
+    /// Create a new import session request with response channel.
+    #[must_use]
+    pub fn with_response(
+        session: SessionData,
+        replace_existing: bool,
+    ) -> (Self, oneshot::Receiver<bool>) {
+        let (response_tx, rx) = create_request_reply();
+        let request = Self {
+            session,
+            replace_existing,
+            response_tx: Some(response_tx),
+        };
+        (request, rx)
+    }
+}
+
+/// List every unexpired session.
+#[derive(Clone, Debug)]
+pub struct ExportSessions {
+    /// Response channel.
+    pub response_tx: Option<ResponseChannel<Vec<SessionData>>>,
+}
+
+impl ExportSessions {
+    /// Create a new export sessions request with response channel.
+    #[must_use]
+    pub fn with_response() -> (Self, oneshot::Receiver<Vec<SessionData>>) {
+        let (response_tx, rx) = create_request_reply();
+        (
+            Self {
+                response_tx: Some(response_tx),
+            },
+            rx,
+        )
+    }
+}
+
 /// Trigger cleanup of expired sessions.
 #[derive(Clone, Debug)]
 pub struct CleanupExpired;
@@ -519,4 +1006,115 @@ mod tests {
 
         runtime.shutdown_all().await.expect("Failed to shutdown");
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_import_and_export_sessions() {
+        let mut runtime = ActonApp::launch_async().await;
+        let agent = SessionManagerAgent::spawn(&mut runtime, 300).await.unwrap();
+
+        let mut session = SessionData::new(3600, Some(7));
+        session.session_id = "embedded-session".to_string();
+
+        // Import keeps the session's ID
+        let (request, rx) = ImportSession::with_response(session.clone(), false);
+        agent.send(request).await;
+        assert!(tokio::time::timeout(Duration::from_secs(1), rx)
+            .await
+            .expect("Timeout")
+            .expect("Channel closed"));
+
+        // A second import keeps the stored session unless it replaces it
+        let mut newer = session.clone();
+        newer.user_id = Some(8);
+        let (request, rx) = ImportSession::with_response(newer.clone(), false);
+        agent.send(request).await;
+        assert!(!rx.await.expect("Channel closed"));
+
+        let (request, rx) = ImportSession::with_response(newer, true);
+        agent.send(request).await;
+        assert!(rx.await.expect("Channel closed"));
+
+        // Expired sessions are skipped
+        let mut expired = SessionData::new(3600, None);
+        expired.expires_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+        let (request, rx) = ImportSession::with_response(expired, true);
+        agent.send(request).await;
+        assert!(!rx.await.expect("Channel closed"));
+
+        let (request, rx) = ExportSessions::with_response();
+        agent.send(request).await;
+        let exported = tokio::time::timeout(Duration::from_secs(1), rx)
+            .await
+            .expect("Timeout")
+            .expect("Channel closed");
+
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].session_id, "embedded-session");
+        assert_eq!(exported[0].user_id, Some(8));
+
+        runtime.shutdown_all().await.expect("Failed to shutdown");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_touch_and_refresh_session() {
+        let mut runtime = ActonApp::launch_async().await;
+        let agent = SessionManagerAgent::spawn(&mut runtime, 300).await.unwrap();
+
+        let (request, rx) = CreateSession::with_response(Some(7), 60);
+        let request = request
+            .with_absolute_ttl(120)
+            .with_refresh_token_hash("old-hash".to_string());
+        agent.send(request).await;
+        let session = tokio::time::timeout(Duration::from_secs(1), rx)
+            .await
+            .expect("Timeout")
+            .expect("Channel closed");
+        assert!(session.is_refreshable());
+
+        // Touching slides the expiry forward, but never past the absolute expiry
+        let (request, rx) = TouchSession::with_response(session.session_id.clone(), 600);
+        agent.send(request).await;
+        let touched = rx.await.expect("Channel closed").expect("Session exists");
+        assert_eq!(touched.expires_at, session.absolute_expires_at);
+
+        // A wrong refresh token is rejected
+        let (request, rx) = RefreshSession::with_response(
+            session.session_id.clone(),
+            "wrong-hash".to_string(),
+            "new-hash".to_string(),
+            60,
+        );
+        agent.send(request).await;
+        assert!(rx.await.expect("Channel closed").is_none());
+
+        // Refreshing moves the session to a new ID and token
+        let (request, rx) = RefreshSession::with_response(
+            session.session_id.clone(),
+            "old-hash".to_string(),
+            "new-hash".to_string(),
+            60,
+        );
+        agent.send(request).await;
+        let refreshed = rx.await.expect("Channel closed").expect("Refreshed");
+        assert_ne!(refreshed.session_id, session.session_id);
+        assert_ne!(refreshed.csrf_token, session.csrf_token);
+        assert_eq!(refreshed.user_id, Some(7));
+        assert_eq!(refreshed.refresh_token_hash.as_deref(), Some("new-hash"));
+
+        // The old ID and token no longer work
+        let (request, rx) = LoadSession::with_response(session.session_id.clone());
+        agent.send(request).await;
+        assert!(rx.await.expect("Channel closed").is_none());
+
+        let (request, rx) = RefreshSession::with_response(
+            refreshed.session_id.clone(),
+            "old-hash".to_string(),
+            "newer-hash".to_string(),
+            60,
+        );
+        agent.send(request).await;
+        assert!(rx.await.expect("Channel closed").is_none());
+
+        runtime.shutdown_all().await.expect("Failed to shutdown");
+    }
 }
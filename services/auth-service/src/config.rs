@@ -5,6 +5,7 @@ use figment::{
     Figment,
 };
 use serde::Deserialize;
+use std::path::PathBuf;
 
 /// Auth service configuration.
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -17,6 +18,16 @@ pub struct AuthServiceConfig {
     pub csrf: CsrfConfig,
     /// Password hashing configuration.
     pub password: PasswordConfig,
+    /// Security event configuration.
+    pub security: SecurityConfig,
+    /// Passkey (WebAuthn) configuration.
+    pub passkey: PasskeyConfig,
+    /// JWT access token configuration.
+    pub token: TokenConfig,
+    /// OpenID Connect provider configuration.
+    pub oidc: OidcConfig,
+    /// API key configuration.
+    pub api_keys: ApiKeyConfig,
 }
 
 /// Service endpoint configuration.
@@ -42,6 +53,72 @@ pub struct SessionConfig {
     /// Cleanup interval in seconds.
     #[serde(default = "default_cleanup_interval")]
     pub cleanup_interval_seconds: u64,
+    /// Seconds a session stays valid after its last use; each validation
+    /// moves the expiry forward. Unset keeps the expiry a session was
+    /// created with.
+    #[serde(default)]
+    pub idle_timeout_seconds: Option<u64>,
+    /// Seconds after creation a session expires however it is used or
+    /// refreshed.
+    #[serde(default = "default_absolute_timeout")]
+    pub absolute_timeout_seconds: u64,
+    /// Where sessions are kept.
+    #[serde(default)]
+    pub store: SessionStoreConfig,
+}
+
+/// Where sessions, API keys, passkeys or OIDC clients are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    /// Keep them in memory only; they are lost on restart.
+    #[default]
+    Memory,
+    /// Persist them to Postgres through the data service.
+    Postgres,
+}
+
+/// Session storage configuration.
+///
+/// With a persistent backend the session manager still serves sessions from
+/// memory, but writes every change through to the store and loads sessions
+/// it does not hold on first use.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionStoreConfig {
+    /// Storage backend.
+    #[serde(default)]
+    pub backend: StoreBackend,
+    /// Data service endpoint, for the Postgres backend.
+    #[serde(default = "default_data_endpoint")]
+    pub data_endpoint: String,
+    /// Table sessions are stored in; created if missing.
+    #[serde(default = "default_session_table")]
+    pub table: String,
+}
+
+/// API key configuration.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Where keys are kept.
+    #[serde(default)]
+    pub store: ApiKeyStoreConfig,
+}
+
+/// API key storage configuration.
+///
+/// With a persistent backend every call reads and writes the store, so
+/// replicas share keys and revocations.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyStoreConfig {
+    /// Storage backend.
+    #[serde(default)]
+    pub backend: StoreBackend,
+    /// Data service endpoint, for the Postgres backend.
+    #[serde(default = "default_data_endpoint")]
+    pub data_endpoint: String,
+    /// Table keys are stored in; created if missing.
+    #[serde(default = "default_api_key_table")]
+    pub table: String,
 }
 
 /// CSRF configuration.
@@ -72,6 +149,158 @@ pub struct PasswordConfig {
     pub hash_length: usize,
 }
 
+/// Security event configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SecurityConfig {
+    /// Failed logins within the window that flag a brute-force attempt.
+    #[serde(default = "default_max_failed_logins")]
+    pub max_failed_logins: u32,
+    /// Window for counting failed logins in seconds.
+    #[serde(default = "default_failure_window")]
+    pub failure_window_seconds: u64,
+    /// Logins from different IPs closer together than this are flagged, in seconds.
+    #[serde(default = "default_ip_change_window")]
+    pub ip_change_window_seconds: u64,
+    /// Events buffered per subscriber before slow subscribers miss events.
+    #[serde(default = "default_event_buffer")]
+    pub event_buffer: usize,
+}
+
+/// Passkey (WebAuthn) relying party configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PasskeyConfig {
+    /// Relying party ID; the site's domain, e.g. `example.com`.
+    #[serde(default = "default_rp_id")]
+    pub rp_id: String,
+    /// Origin the browser reports, e.g. `https://app.example.com`.
+    #[serde(default = "default_rp_origin")]
+    pub rp_origin: String,
+    /// Name shown by authenticators.
+    #[serde(default = "default_rp_name")]
+    pub rp_name: String,
+    /// Seconds the browser has to answer a challenge.
+    #[serde(default = "default_ceremony_ttl")]
+    pub ceremony_ttl_seconds: u64,
+    /// Where registered passkeys are kept.
+    #[serde(default)]
+    pub store: PasskeyStoreConfig,
+}
+
+/// Passkey storage configuration.
+///
+/// With a persistent backend every call reads and writes the store, so
+/// passkeys survive a restart.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PasskeyStoreConfig {
+    /// Storage backend.
+    #[serde(default)]
+    pub backend: StoreBackend,
+    /// Data service endpoint, for the Postgres backend.
+    #[serde(default = "default_data_endpoint")]
+    pub data_endpoint: String,
+    /// Table passkeys are stored in; created if missing.
+    #[serde(default = "default_passkey_table")]
+    pub table: String,
+}
+
+/// JWT access token configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenConfig {
+    /// `iss` claim of issued tokens.
+    #[serde(default = "default_token_issuer")]
+    pub issuer: String,
+    /// Audiences tokens may be issued for; the first is the default.
+    #[serde(default = "default_token_audiences")]
+    pub audiences: Vec<String>,
+    /// Scopes clients may request.
+    #[serde(default = "default_token_scopes")]
+    pub scopes: Vec<String>,
+    /// Scopes granted when a client requests none.
+    #[serde(default = "default_token_default_scopes")]
+    pub default_scopes: Vec<String>,
+    /// Token lifetime in seconds.
+    #[serde(default = "default_token_ttl")]
+    pub ttl_seconds: u64,
+    /// Seconds between signing key rotations.
+    #[serde(default = "default_key_rotation")]
+    pub key_rotation_seconds: u64,
+    /// Directory the signing keys are kept in, so tokens outlive a restart
+    /// and replicas sharing the directory sign with the same keys. Unset
+    /// keeps keys in memory.
+    #[serde(default)]
+    pub key_dir: Option<PathBuf>,
+}
+
+/// OpenID Connect provider configuration.
+///
+/// Lets internal tools offer "Sign in with" the application. ID tokens are
+/// signed with the access token keys, so the token service's JWKS verifies
+/// them.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcConfig {
+    /// Serve the provider endpoints.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Public URL of the application; the `iss` claim of ID tokens and the
+    /// base of the discovery document's endpoints.
+    #[serde(default = "default_oidc_issuer")]
+    pub issuer: String,
+    /// Seconds a client has to exchange an authorization code.
+    #[serde(default = "default_oidc_code_ttl")]
+    pub code_ttl_seconds: u64,
+    /// Clients registered at startup.
+    #[serde(default)]
+    pub clients: Vec<OidcClientConfig>,
+    /// Where clients registered through the API, and consents, are kept.
+    #[serde(default)]
+    pub store: OidcStoreConfig,
+}
+
+/// OIDC client and consent storage configuration.
+///
+/// Clients registered at startup always stay in memory. Authorization codes
+/// do too, so a code must be exchanged on the replica that issued it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcStoreConfig {
+    /// Storage backend.
+    #[serde(default)]
+    pub backend: StoreBackend,
+    /// Data service endpoint, for the Postgres backend.
+    #[serde(default = "default_data_endpoint")]
+    pub data_endpoint: String,
+    /// Table registered clients are stored in; created if missing.
+    #[serde(default = "default_oidc_clients_table")]
+    pub clients_table: String,
+    /// Table consents are stored in; created if missing.
+    #[serde(default = "default_oidc_consents_table")]
+    pub consents_table: String,
+}
+
+/// A client registered at startup.
+#[derive(Clone, Deserialize)]
+pub struct OidcClientConfig {
+    /// Client ID.
+    pub client_id: String,
+    /// Name shown on the consent screen.
+    pub name: String,
+    /// Client secret; public clients (SPAs, native apps) have none and must
+    /// use PKCE.
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// Redirect URIs the client may use, matched exactly.
+    pub redirect_uris: Vec<String>,
+}
+
+impl std::fmt::Debug for OidcClientConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcClientConfig")
+            .field("client_id", &self.client_id)
+            .field("name", &self.name)
+            .field("redirect_uris", &self.redirect_uris)
+            .finish_non_exhaustive()
+    }
+}
+
 // Default value functions
 const fn default_port() -> u16 {
     9001
@@ -93,6 +322,22 @@ const fn default_cleanup_interval() -> u64 {
     300 // 5 minutes
 }
 
+const fn default_absolute_timeout() -> u64 {
+    604_800 // 7 days
+}
+
+fn default_data_endpoint() -> String {
+    "http://localhost:50052".to_string()
+}
+
+fn default_session_table() -> String {
+    "auth_sessions".to_string()
+}
+
+fn default_api_key_table() -> String {
+    "auth_api_keys".to_string()
+}
+
 const fn default_csrf_ttl() -> u64 {
     3600 // 1 hour
 }
@@ -117,6 +362,82 @@ const fn default_hash_length() -> usize {
     32
 }
 
+const fn default_max_failed_logins() -> u32 {
+    5
+}
+
+const fn default_failure_window() -> u64 {
+    900 // 15 minutes
+}
+
+const fn default_ip_change_window() -> u64 {
+    3600 // 1 hour
+}
+
+const fn default_event_buffer() -> usize {
+    1024
+}
+
+fn default_rp_id() -> String {
+    "localhost".to_string()
+}
+
+fn default_rp_origin() -> String {
+    "http://localhost:3000".to_string()
+}
+
+fn default_rp_name() -> String {
+    "Acton DX".to_string()
+}
+
+const fn default_ceremony_ttl() -> u64 {
+    300 // 5 minutes
+}
+
+fn default_passkey_table() -> String {
+    "auth_passkeys".to_string()
+}
+
+fn default_token_issuer() -> String {
+    "acton-dx".to_string()
+}
+
+fn default_token_audiences() -> Vec<String> {
+    vec!["api".to_string(), "internal".to_string()]
+}
+
+fn default_token_scopes() -> Vec<String> {
+    vec!["read".to_string(), "write".to_string()]
+}
+
+fn default_token_default_scopes() -> Vec<String> {
+    vec!["read".to_string()]
+}
+
+const fn default_token_ttl() -> u64 {
+    900 // 15 minutes
+}
+
+const fn default_key_rotation() -> u64 {
+    86400 // 24 hours
+}
+
+fn default_oidc_issuer() -> String {
+    "http://localhost:3000".to_string()
+}
+
+const fn default_oidc_code_ttl() -> u64 {
+    60 // 1 minute
+}
+
+fn default_oidc_clients_table() -> String {
+    "auth_oidc_clients".to_string()
+}
+
+fn default_oidc_consents_table() -> String {
+    "auth_oidc_consents".to_string()
+}
+
 impl Default for ServiceConfig {
     fn default() -> Self {
         Self {
@@ -132,6 +453,29 @@ impl Default for SessionConfig {
             default_ttl_seconds: default_session_ttl(),
             max_ttl_seconds: default_max_session_ttl(),
             cleanup_interval_seconds: default_cleanup_interval(),
+            idle_timeout_seconds: None,
+            absolute_timeout_seconds: default_absolute_timeout(),
+            store: SessionStoreConfig::default(),
+        }
+    }
+}
+
+impl Default for SessionStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: StoreBackend::default(),
+            data_endpoint: default_data_endpoint(),
+            table: default_session_table(),
+        }
+    }
+}
+
+impl Default for ApiKeyStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: StoreBackend::default(),
+            data_endpoint: default_data_endpoint(),
+            table: default_api_key_table(),
         }
     }
 }
@@ -156,6 +500,76 @@ impl Default for PasswordConfig {
     }
 }
 
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            max_failed_logins: default_max_failed_logins(),
+            failure_window_seconds: default_failure_window(),
+            ip_change_window_seconds: default_ip_change_window(),
+            event_buffer: default_event_buffer(),
+        }
+    }
+}
+
+impl Default for PasskeyConfig {
+    fn default() -> Self {
+        Self {
+            rp_id: default_rp_id(),
+            rp_origin: default_rp_origin(),
+            rp_name: default_rp_name(),
+            ceremony_ttl_seconds: default_ceremony_ttl(),
+            store: PasskeyStoreConfig::default(),
+        }
+    }
+}
+
+impl Default for PasskeyStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: StoreBackend::default(),
+            data_endpoint: default_data_endpoint(),
+            table: default_passkey_table(),
+        }
+    }
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        Self {
+            issuer: default_token_issuer(),
+            audiences: default_token_audiences(),
+            scopes: default_token_scopes(),
+            default_scopes: default_token_default_scopes(),
+            ttl_seconds: default_token_ttl(),
+            key_rotation_seconds: default_key_rotation(),
+            key_dir: None,
+        }
+    }
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            issuer: default_oidc_issuer(),
+            code_ttl_seconds: default_oidc_code_ttl(),
+            clients: Vec::new(),
+            store: OidcStoreConfig::default(),
+        }
+    }
+}
+
+impl Default for OidcStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: StoreBackend::default(),
+            data_endpoint: default_data_endpoint(),
+            clients_table: default_oidc_clients_table(),
+            consents_table: default_oidc_consents_table(),
+        }
+    }
+}
+
 impl AuthServiceConfig {
     /// Load configuration from files and environment.
     ///
@@ -181,7 +595,20 @@ mod tests {
         let config = AuthServiceConfig::default();
         assert_eq!(config.service.port, 9001);
         assert_eq!(config.session.default_ttl_seconds, 3600);
+        assert_eq!(config.session.idle_timeout_seconds, None);
+        assert_eq!(config.session.absolute_timeout_seconds, 604_800);
+        assert_eq!(config.session.store.backend, StoreBackend::Memory);
+        assert_eq!(config.session.store.table, "auth_sessions");
         assert_eq!(config.csrf.token_bytes, 32);
         assert_eq!(config.password.memory_cost, 19456);
+        assert_eq!(config.security.max_failed_logins, 5);
+        assert_eq!(config.passkey.rp_id, "localhost");
+        assert_eq!(config.passkey.store.table, "auth_passkeys");
+        assert_eq!(config.token.audiences, ["api", "internal"]);
+        assert!(!config.oidc.enabled);
+        assert_eq!(config.oidc.code_ttl_seconds, 60);
+        assert_eq!(config.oidc.store.clients_table, "auth_oidc_clients");
+        assert_eq!(config.api_keys.store.backend, StoreBackend::Memory);
+        assert_eq!(config.api_keys.store.table, "auth_api_keys");
     }
 }
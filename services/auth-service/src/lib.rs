@@ -1,6 +1,8 @@
 //! Auth service for Acton DX.
 //!
-//! Provides session management, password hashing, and CSRF protection.
+//! Provides session management, password hashing, passkeys, CSRF protection,
+//! JWT access tokens, API keys, an OpenID Connect provider, and a security
+//! event stream.
 
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
@@ -8,8 +10,10 @@
 pub mod agents;
 pub mod config;
 pub mod services;
+pub mod store;
 
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Session data stored in the session manager.
@@ -31,47 +35,109 @@ pub struct SessionData {
     pub csrf_token: String,
     /// Session creation timestamp.
     pub created_at: DateTime<Utc>,
-    /// Session expiration timestamp.
+    /// Session expiration timestamp; moved forward on use when idle
+    /// timeouts are enabled.
     pub expires_at: DateTime<Utc>,
+    /// Latest expiry the session can reach by being used or refreshed.
+    pub absolute_expires_at: DateTime<Utc>,
+    /// SHA-256 of the secret part of the session's refresh token, if one was
+    /// issued.
+    pub refresh_token_hash: Option<String>,
 }
 
 impl SessionData {
     /// Create a new session with the given TTL.
     #[must_use]
     pub fn new(ttl_seconds: u64, user_id: Option<i64>) -> Self {
-        use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
-        use rand::Rng;
-
-        let mut session_id_bytes = [0u8; 32];
-        let mut csrf_bytes = [0u8; 32];
-        rand::rng().fill(&mut session_id_bytes);
-        rand::rng().fill(&mut csrf_bytes);
-
         let now = Utc::now();
-        let ttl = chrono::Duration::seconds(i64::try_from(ttl_seconds).unwrap_or(i64::MAX));
+        let ttl = seconds(ttl_seconds);
 
         Self {
-            session_id: URL_SAFE_NO_PAD.encode(session_id_bytes),
+            session_id: random_token(),
             user_id,
             user_email: None,
             user_name: None,
             data: HashMap::new(),
             flash_messages: Vec::new(),
-            csrf_token: URL_SAFE_NO_PAD.encode(csrf_bytes),
+            csrf_token: random_token(),
             created_at: now,
             expires_at: now + ttl,
+            absolute_expires_at: now + ttl,
+            refresh_token_hash: None,
         }
     }
 
+    /// Cap the session's lifetime, including uses and refreshes, at
+    /// `absolute_ttl_seconds` from creation.
+    #[must_use]
+    pub fn with_absolute_ttl(mut self, absolute_ttl_seconds: u64) -> Self {
+        self.absolute_expires_at = self.created_at + seconds(absolute_ttl_seconds);
+        self.expires_at = self.expires_at.min(self.absolute_expires_at);
+        self
+    }
+
+    /// Generate a CSRF token, for sessions imported without one.
+    #[must_use]
+    pub fn generate_csrf_token() -> String {
+        random_token()
+    }
+
     /// Check if the session has expired.
     #[must_use]
     pub fn is_expired(&self) -> bool {
         Utc::now() > self.expires_at
     }
+
+    /// Whether the session's refresh token can still be exchanged, even if
+    /// the session has expired.
+    #[must_use]
+    pub fn is_refreshable(&self) -> bool {
+        self.refresh_token_hash.is_some() && Utc::now() <= self.absolute_expires_at
+    }
+
+    /// Whether the session can no longer be used or refreshed.
+    #[must_use]
+    pub fn is_dead(&self) -> bool {
+        self.is_expired() && !self.is_refreshable()
+    }
+
+    /// Move the expiry to `idle_timeout_seconds` from now, if that is later,
+    /// without passing the absolute expiry.
+    pub fn touch(&mut self, idle_timeout_seconds: u64) {
+        let idle_expiry =
+            (Utc::now() + seconds(idle_timeout_seconds)).min(self.absolute_expires_at);
+        self.expires_at = self.expires_at.max(idle_expiry);
+    }
+
+    /// Move the session to a new ID and CSRF token, expiring
+    /// `ttl_seconds` from now but no later than the absolute expiry.
+    pub fn rotate(&mut self, ttl_seconds: u64, refresh_token_hash: String) {
+        self.session_id = random_token();
+        self.csrf_token = random_token();
+        self.expires_at = (Utc::now() + seconds(ttl_seconds)).min(self.absolute_expires_at);
+        self.refresh_token_hash = Some(refresh_token_hash);
+    }
+}
+
+/// A duration of `value` seconds, saturating at the longest one chrono can
+/// represent.
+pub(crate) fn seconds(value: u64) -> chrono::TimeDelta {
+    let max = chrono::TimeDelta::MAX.num_seconds();
+    chrono::TimeDelta::seconds(i64::try_from(value).unwrap_or(max).min(max))
+}
+
+/// 32 random bytes, URL-safe base64 encoded.
+pub(crate) fn random_token() -> String {
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use rand::Rng;
+
+    let mut bytes = [0u8; 32];
+    rand::rng().fill(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
 }
 
 /// Flash message for one-time display.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FlashMessage {
     /// Message level (e.g., "success", "error", "info", "warning").
     pub level: String,
@@ -82,4 +148,10 @@ pub struct FlashMessage {
 // Re-export key types for convenience
 pub use agents::SessionManagerAgent;
 pub use config::AuthServiceConfig;
-pub use services::{CsrfServiceImpl, PasswordServiceImpl, SessionServiceImpl};
+pub use services::{
+    ApiKeyServiceImpl, ApiKeys, CsrfServiceImpl, OidcProvider, OidcProviderServiceImpl,
+    PasskeyServiceImpl, PasswordServiceImpl, PostgresApiKeyStore, PostgresOidcStore,
+    PostgresPasskeyStore, SecurityEventServiceImpl, SecurityEvents, SessionServiceImpl,
+    TokenIssuer, TokenServiceImpl, TENANT_SESSION_KEY,
+};
+pub use store::PostgresSessionStore;
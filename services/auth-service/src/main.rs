@@ -1,15 +1,22 @@
 //! Auth service binary entry point.
 
 use acton_dx_proto::auth::v1::{
-    csrf_service_server::CsrfServiceServer, password_service_server::PasswordServiceServer,
-    session_service_server::SessionServiceServer,
+    api_key_service_server::ApiKeyServiceServer, csrf_service_server::CsrfServiceServer,
+    oidc_provider_service_server::OidcProviderServiceServer,
+    passkey_service_server::PasskeyServiceServer, password_service_server::PasswordServiceServer,
+    security_event_service_server::SecurityEventServiceServer,
+    session_service_server::SessionServiceServer, token_service_server::TokenServiceServer,
 };
 use acton_reactive::prelude::ActonApp;
 use auth_service::{
-    AuthServiceConfig, CsrfServiceImpl, PasswordServiceImpl, SessionManagerAgent,
-    SessionServiceImpl,
+    config::StoreBackend, ApiKeyServiceImpl, ApiKeys, AuthServiceConfig, CsrfServiceImpl,
+    OidcProvider, OidcProviderServiceImpl, PasskeyServiceImpl, PasswordServiceImpl,
+    PostgresApiKeyStore, PostgresOidcStore, PostgresPasskeyStore, PostgresSessionStore,
+    SecurityEventServiceImpl, SecurityEvents, SessionManagerAgent, SessionServiceImpl, TokenIssuer,
+    TokenServiceImpl,
 };
 use std::net::SocketAddr;
+use std::time::Duration;
 use tonic::transport::Server;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -36,26 +43,74 @@ async fn main() -> anyhow::Result<()> {
     let mut runtime = ActonApp::launch();
 
     // Spawn session manager agent
-    let session_agent = SessionManagerAgent::spawn(
-        &mut runtime,
-        config.session.cleanup_interval_seconds,
-    )
-    .await?;
+    let session_agent = match config.session.store.backend {
+        StoreBackend::Memory => {
+            SessionManagerAgent::spawn(&mut runtime, config.session.cleanup_interval_seconds)
+                .await?
+        }
+        StoreBackend::Postgres => {
+            let store = PostgresSessionStore::connect_lazy(
+                &config.session.store.data_endpoint,
+                &config.session.store.table,
+            )?;
+            store.ensure_table().await?;
+            tracing::info!(
+                table = %config.session.store.table,
+                "Persisting sessions through the data service"
+            );
+            SessionManagerAgent::spawn_with_store(
+                &mut runtime,
+                config.session.cleanup_interval_seconds,
+                store,
+            )
+            .await?
+        }
+    };
 
     tracing::info!("Session manager agent started");
 
-    // Create gRPC services
-    let session_service = SessionServiceImpl::new(session_agent);
+    // Security events are shared by every service that observes them
+    let security_events = SecurityEvents::new(&config.security);
+    let pruned_events = security_events.clone();
+    let prune_interval = config.session.cleanup_interval_seconds;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(prune_interval));
+        loop {
+            interval.tick().await;
+            pruned_events.prune_expired();
+        }
+    });
+
+    // Create gRPC services; ID tokens share the access token keys so the
+    // JWKS verifies both
+    let token_issuer = TokenIssuer::new(&config.token)?;
+    let token_service = TokenServiceImpl::with_issuer(session_agent.clone(), token_issuer.clone());
+    let oidc_service = if config.oidc.enabled {
+        let provider = oidc_provider(&config, token_issuer).await?;
+        tracing::info!(issuer = %config.oidc.issuer, "OpenID Connect provider enabled");
+        Some(OidcProviderServiceServer::new(
+            OidcProviderServiceImpl::new(session_agent.clone(), provider),
+        ))
+    } else {
+        None
+    };
+    let session_service = SessionServiceImpl::new(session_agent)
+        .with_expiration(&config.session)
+        .with_events(security_events.clone());
     let password_service = PasswordServiceImpl::with_params(
         config.password.memory_cost,
         config.password.time_cost,
         config.password.parallelism,
         Some(config.password.hash_length),
-    );
+    )
+    .with_events(security_events.clone());
+    let security_service = SecurityEventServiceImpl::new(security_events);
+    let passkey_service = passkey_service(&config).await?;
     let csrf_service = CsrfServiceImpl::with_config(
         config.csrf.token_ttl_seconds,
         config.csrf.token_bytes,
     );
+    let api_key_service = ApiKeyServiceImpl::new(api_keys(&config).await?);
 
     // Build server address
     let addr: SocketAddr = format!("{}:{}", config.service.host, config.service.port).parse()?;
@@ -67,6 +122,11 @@ async fn main() -> anyhow::Result<()> {
         .add_service(SessionServiceServer::new(session_service))
         .add_service(PasswordServiceServer::new(password_service))
         .add_service(CsrfServiceServer::new(csrf_service))
+        .add_service(PasskeyServiceServer::new(passkey_service))
+        .add_service(SecurityEventServiceServer::new(security_service))
+        .add_service(TokenServiceServer::new(token_service))
+        .add_service(ApiKeyServiceServer::new(api_key_service))
+        .add_optional_service(oidc_service)
         .serve(addr)
         .await?;
 
@@ -75,3 +135,59 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// API keys, kept where the configuration says.
+async fn api_keys(config: &AuthServiceConfig) -> anyhow::Result<ApiKeys> {
+    let store = &config.api_keys.store;
+    match store.backend {
+        StoreBackend::Memory => Ok(ApiKeys::new()),
+        StoreBackend::Postgres => {
+            let keys = PostgresApiKeyStore::connect_lazy(&store.data_endpoint, &store.table)?;
+            keys.ensure_table().await?;
+            tracing::info!(table = %store.table, "Persisting API keys through the data service");
+            Ok(ApiKeys::new().with_store(keys))
+        }
+    }
+}
+
+/// The passkey service, keeping passkeys where the configuration says.
+async fn passkey_service(config: &AuthServiceConfig) -> anyhow::Result<PasskeyServiceImpl> {
+    let service = PasskeyServiceImpl::new(&config.passkey)?;
+    let store = &config.passkey.store;
+    match store.backend {
+        StoreBackend::Memory => Ok(service),
+        StoreBackend::Postgres => {
+            let passkeys = PostgresPasskeyStore::connect_lazy(&store.data_endpoint, &store.table)?;
+            passkeys.ensure_table().await?;
+            tracing::info!(table = %store.table, "Persisting passkeys through the data service");
+            Ok(service.with_store(passkeys))
+        }
+    }
+}
+
+/// The OIDC provider, keeping registered clients and consents where the
+/// configuration says.
+async fn oidc_provider(
+    config: &AuthServiceConfig,
+    issuer: TokenIssuer,
+) -> anyhow::Result<OidcProvider> {
+    let provider = OidcProvider::new(&config.oidc, issuer)?;
+    let store = &config.oidc.store;
+    match store.backend {
+        StoreBackend::Memory => Ok(provider),
+        StoreBackend::Postgres => {
+            let oidc = PostgresOidcStore::connect_lazy(
+                &store.data_endpoint,
+                &store.clients_table,
+                &store.consents_table,
+            )?;
+            oidc.ensure_tables().await?;
+            tracing::info!(
+                clients_table = %store.clients_table,
+                consents_table = %store.consents_table,
+                "Persisting OIDC clients and consents through the data service"
+            );
+            Ok(provider.with_store(oidc))
+        }
+    }
+}
@@ -0,0 +1,787 @@
+//! gRPC API Key Service implementation.
+//!
+//! API keys authenticate machines rather than people: scripts, other
+//! services and integrations calling the application's API. A key reads
+//! `adx_<key_id>_<secret>`. The key ID finds the key and is safe to log;
+//! only a SHA-256 of the secret is kept, so the stored keys cannot be
+//! replayed. Secrets are random and high-entropy, so a fast hash is enough.
+//!
+//! Each successful validation records when the key was last used. Revoked
+//! keys stay listed, with their revocation time.
+//!
+//! Keys live in memory unless a [`PostgresApiKeyStore`] is configured. With
+//! one, every call reads and writes the store, so keys survive a restart and
+//! a key revoked on one replica is refused by all of them.
+
+use acton_dx_proto::auth::v1::{
+    api_key_service_server::ApiKeyService, ApiKey, CreateApiKeyRequest, CreateApiKeyResponse,
+    ListApiKeysRequest, ListApiKeysResponse, RevokeApiKeyRequest, RevokeApiKeyResponse,
+    ValidateApiKeyRequest, ValidateApiKeyResponse,
+};
+use acton_dx_proto::data::v1::{Row, Value};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, TimeDelta, Utc};
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tonic::{Request, Response, Status};
+
+use crate::store::{int, null, number, string, text, DataTable};
+
+/// Prefix of every API key, so leaked keys are easy to scan for.
+pub const API_KEY_PREFIX: &str = "adx_";
+
+/// Why an API key request was refused.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ApiKeyError {
+    /// The request is missing or has an invalid parameter.
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    /// A scope is empty or contains whitespace.
+    #[error("invalid scope {0:?}")]
+    InvalidScope(String),
+    /// The key store could not be reached.
+    #[error("API key store unavailable: {0}")]
+    Store(String),
+}
+
+impl From<ApiKeyError> for Status {
+    fn from(error: ApiKeyError) -> Self {
+        match error {
+            ApiKeyError::Store(_) => Self::unavailable(error.to_string()),
+            _ => Self::invalid_argument(error.to_string()),
+        }
+    }
+}
+
+impl From<Status> for ApiKeyError {
+    fn from(status: Status) -> Self {
+        Self::Store(status.message().to_string())
+    }
+}
+
+/// A key as stored: everything but the secret itself.
+#[derive(Clone)]
+struct StoredKey {
+    name: String,
+    user_id: Option<i64>,
+    scopes: Vec<String>,
+    secret_hash: [u8; 32],
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl StoredKey {
+    fn is_live(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+
+    fn to_proto(&self, key_id: &str) -> ApiKey {
+        ApiKey {
+            key_id: key_id.to_string(),
+            name: self.name.clone(),
+            user_id: self.user_id,
+            scopes: self.scopes.clone(),
+            created_at: self.created_at.timestamp(),
+            expires_at: self.expires_at.map(|at| at.timestamp()),
+            last_used_at: self.last_used_at.map(|at| at.timestamp()),
+            revoked_at: self.revoked_at.map(|at| at.timestamp()),
+        }
+    }
+}
+
+/// Outcome of validating a presented key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiKeyValidation {
+    /// The key is live and grants every required scope.
+    Valid(ApiKey),
+    /// The key is live but lacks some required scopes.
+    MissingScopes(ApiKey, Vec<String>),
+    /// The key is malformed, unknown, expired or revoked.
+    Invalid,
+}
+
+/// Issued API keys, by key ID.
+#[derive(Clone, Default)]
+pub struct ApiKeys {
+    keys: Arc<DashMap<String, StoredKey>>,
+    store: Option<PostgresApiKeyStore>,
+}
+
+impl std::fmt::Debug for ApiKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKeys")
+            .field("keys", &self.keys.len())
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+impl ApiKeys {
+    /// No keys yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep keys in `store` instead of in memory.
+    #[must_use]
+    pub fn with_store(mut self, store: PostgresApiKeyStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Create a key and return it with the full key, which is only shown
+    /// once.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is empty, a scope is invalid, the TTL
+    /// is not positive or the store fails.
+    pub async fn create(
+        &self,
+        name: &str,
+        user_id: Option<i64>,
+        scopes: &[String],
+        ttl_seconds: Option<i64>,
+        now: DateTime<Utc>,
+    ) -> Result<(ApiKey, String), ApiKeyError> {
+        if name.trim().is_empty() {
+            return Err(ApiKeyError::InvalidRequest(
+                "name cannot be empty".to_string(),
+            ));
+        }
+        let scopes = validate_scopes(scopes)?;
+        let expires_at = match ttl_seconds {
+            Some(ttl) if ttl <= 0 => {
+                return Err(ApiKeyError::InvalidRequest(
+                    "ttl_seconds must be positive".to_string(),
+                ));
+            }
+            Some(ttl) => Some(
+                TimeDelta::try_seconds(ttl)
+                    .and_then(|ttl| now.checked_add_signed(ttl))
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            ),
+            None => None,
+        };
+
+        let key_id = uuid::Uuid::new_v4().simple().to_string();
+        let secret = crate::random_token();
+        let key = StoredKey {
+            name: name.trim().to_string(),
+            user_id,
+            scopes,
+            secret_hash: hash_secret(&secret),
+            created_at: now,
+            expires_at,
+            last_used_at: None,
+            revoked_at: None,
+        };
+        let info = key.to_proto(&key_id);
+        let api_key = format!("{API_KEY_PREFIX}{key_id}_{secret}");
+        if let Some(store) = &self.store {
+            store.insert(&key_id, &key).await?;
+        } else {
+            self.keys.insert(key_id, key);
+        }
+        Ok((info, api_key))
+    }
+
+    /// Keys, oldest first, optionally only those of one user.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    pub async fn list(
+        &self,
+        user_id: Option<i64>,
+        include_revoked: bool,
+    ) -> Result<Vec<ApiKey>, ApiKeyError> {
+        let mut keys: Vec<_> = if let Some(store) = &self.store {
+            store
+                .list(user_id, include_revoked)
+                .await?
+                .iter()
+                .map(|(key_id, key)| key.to_proto(key_id))
+                .collect()
+        } else {
+            self.keys
+                .iter()
+                .filter(|entry| user_id.is_none() || entry.user_id == user_id)
+                .filter(|entry| include_revoked || entry.revoked_at.is_none())
+                .map(|entry| entry.value().to_proto(entry.key()))
+                .collect()
+        };
+        keys.sort_by(|a, b| (a.created_at, &a.key_id).cmp(&(b.created_at, &b.key_id)));
+        Ok(keys)
+    }
+
+    /// Revoke a key. Returns `false` for unknown or already revoked keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    pub async fn revoke(&self, key_id: &str, now: DateTime<Utc>) -> Result<bool, ApiKeyError> {
+        if let Some(store) = &self.store {
+            return Ok(store.revoke(key_id, now).await?);
+        }
+        Ok(self.keys.get_mut(key_id).is_some_and(|mut key| {
+            let revoked = key.revoked_at.is_none();
+            key.revoked_at.get_or_insert(now);
+            revoked
+        }))
+    }
+
+    /// Check a presented key and the scopes it must grant, recording the
+    /// use of live keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    pub async fn validate(
+        &self,
+        api_key: &str,
+        required_scopes: &[String],
+        now: DateTime<Utc>,
+    ) -> Result<ApiKeyValidation, ApiKeyError> {
+        let Some((key_id, secret)) = parse_api_key(api_key) else {
+            return Ok(ApiKeyValidation::Invalid);
+        };
+        let key = if let Some(store) = &self.store {
+            store.load(key_id).await?
+        } else {
+            self.keys.get(key_id).map(|key| key.clone())
+        };
+        let Some(mut key) = key else {
+            return Ok(ApiKeyValidation::Invalid);
+        };
+        if !bool::from(hash_secret(secret).ct_eq(&key.secret_hash)) || !key.is_live(now) {
+            return Ok(ApiKeyValidation::Invalid);
+        }
+
+        key.last_used_at = Some(now);
+        if let Some(store) = &self.store {
+            store.record_use(key_id, now).await?;
+        } else if let Some(mut stored) = self.keys.get_mut(key_id) {
+            stored.last_used_at = Some(now);
+        }
+        let missing: Vec<String> = required_scopes
+            .iter()
+            .filter(|scope| !key.scopes.contains(*scope))
+            .cloned()
+            .collect();
+        let info = key.to_proto(key_id);
+        Ok(if missing.is_empty() {
+            ApiKeyValidation::Valid(info)
+        } else {
+            ApiKeyValidation::MissingScopes(info, missing)
+        })
+    }
+}
+
+/// Columns of the API key table, in parameter order.
+const COLUMNS: &str = "key_id, name, user_id, scopes, secret_hash, created_at, expires_at, \
+                       last_used_at, revoked_at";
+
+/// Placeholders for [`COLUMNS`]. Nullable integers are cast because a null
+/// parameter is sent as text.
+const PLACEHOLDERS: &str = "$1, $2, CAST($3 AS BIGINT), $4, $5, $6, CAST($7 AS BIGINT), \
+                            CAST($8 AS BIGINT), CAST($9 AS BIGINT)";
+
+/// Stores API keys in a Postgres table through the data service.
+///
+/// Scopes are stored as a JSON array, the secret hash as URL-safe base64
+/// and timestamps as Unix seconds:
+///
+/// ```sql
+/// CREATE TABLE auth_api_keys (
+///     key_id TEXT PRIMARY KEY,
+///     name TEXT NOT NULL,
+///     user_id BIGINT,
+///     scopes TEXT NOT NULL,
+///     secret_hash TEXT NOT NULL,
+///     created_at BIGINT NOT NULL,
+///     expires_at BIGINT,
+///     last_used_at BIGINT,
+///     revoked_at BIGINT
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct PostgresApiKeyStore {
+    table: DataTable,
+}
+
+impl PostgresApiKeyStore {
+    /// Store keys in `table`, connecting to the data service at `endpoint`
+    /// on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoint is not a valid URI or the table name
+    /// is not a plain SQL identifier.
+    pub fn connect_lazy(endpoint: &str, table: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            table: DataTable::connect_lazy(endpoint, table)?,
+        })
+    }
+
+    /// Create the key table and its user index if they are missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn ensure_table(&self) -> Result<(), Status> {
+        let table = &self.table;
+        self.table
+            .execute(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {table} (\
+                     key_id TEXT PRIMARY KEY, name TEXT NOT NULL, user_id BIGINT, \
+                     scopes TEXT NOT NULL, secret_hash TEXT NOT NULL, \
+                     created_at BIGINT NOT NULL, expires_at BIGINT, last_used_at BIGINT, \
+                     revoked_at BIGINT)"
+                ),
+                Vec::new(),
+            )
+            .await?;
+        self.table
+            .execute(
+                format!("CREATE INDEX IF NOT EXISTS {table}_user_id_idx ON {table} (user_id)"),
+                Vec::new(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn insert(&self, key_id: &str, key: &StoredKey) -> Result<(), Status> {
+        self.table
+            .execute(
+                format!(
+                    "INSERT INTO {} ({COLUMNS}) VALUES ({PLACEHOLDERS})",
+                    self.table
+                ),
+                key_params(key_id, key),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn load(&self, key_id: &str) -> Result<Option<StoredKey>, Status> {
+        let rows = self
+            .table
+            .query(
+                format!("SELECT {COLUMNS} FROM {} WHERE key_id = $1", self.table),
+                vec![string(key_id)],
+            )
+            .await?;
+        Ok(rows.iter().find_map(key_from_row).map(|(_, key)| key))
+    }
+
+    async fn list(
+        &self,
+        user_id: Option<i64>,
+        include_revoked: bool,
+    ) -> Result<Vec<(String, StoredKey)>, Status> {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        if let Some(user_id) = user_id {
+            conditions.push("user_id = $1");
+            params.push(int(user_id));
+        }
+        if !include_revoked {
+            conditions.push("revoked_at IS NULL");
+        }
+        let mut sql = format!("SELECT {COLUMNS} FROM {}", self.table);
+        if !conditions.is_empty() {
+            sql = format!("{sql} WHERE {}", conditions.join(" AND "));
+        }
+        let rows = self.table.query(sql, params).await?;
+        Ok(rows.iter().filter_map(key_from_row).collect())
+    }
+
+    async fn revoke(&self, key_id: &str, now: DateTime<Utc>) -> Result<bool, Status> {
+        let rows_affected = self
+            .table
+            .execute(
+                format!(
+                    "UPDATE {} SET revoked_at = $2 WHERE key_id = $1 AND revoked_at IS NULL",
+                    self.table
+                ),
+                vec![string(key_id), int(now.timestamp())],
+            )
+            .await?;
+        Ok(rows_affected > 0)
+    }
+
+    async fn record_use(&self, key_id: &str, now: DateTime<Utc>) -> Result<(), Status> {
+        self.table
+            .execute(
+                format!(
+                    "UPDATE {} SET last_used_at = $2 WHERE key_id = $1",
+                    self.table
+                ),
+                vec![string(key_id), int(now.timestamp())],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Query parameters for a key, in [`COLUMNS`] order.
+fn key_params(key_id: &str, key: &StoredKey) -> Vec<Value> {
+    let timestamp = |at: Option<DateTime<Utc>>| at.map_or_else(null, |at| int(at.timestamp()));
+    vec![
+        string(key_id),
+        string(&key.name),
+        key.user_id.map_or_else(null, int),
+        string(&serde_json::to_string(&key.scopes).unwrap_or_default()),
+        string(&URL_SAFE_NO_PAD.encode(key.secret_hash)),
+        int(key.created_at.timestamp()),
+        timestamp(key.expires_at),
+        timestamp(key.last_used_at),
+        timestamp(key.revoked_at),
+    ]
+}
+
+/// Key stored in a row, with its ID, if the row is complete.
+fn key_from_row(row: &Row) -> Option<(String, StoredKey)> {
+    let timestamp = |name: &str| number(row, name).and_then(|at| DateTime::from_timestamp(at, 0));
+    let secret_hash = URL_SAFE_NO_PAD.decode(text(row, "secret_hash")?).ok()?;
+    let key = StoredKey {
+        name: text(row, "name")?,
+        user_id: number(row, "user_id"),
+        scopes: serde_json::from_str(&text(row, "scopes")?).ok()?,
+        secret_hash: secret_hash.try_into().ok()?,
+        created_at: timestamp("created_at")?,
+        expires_at: timestamp("expires_at"),
+        last_used_at: timestamp("last_used_at"),
+        revoked_at: timestamp("revoked_at"),
+    };
+    Some((text(row, "key_id")?, key))
+}
+
+/// Split `adx_<key_id>_<secret>` into the key ID and secret.
+fn parse_api_key(api_key: &str) -> Option<(&str, &str)> {
+    let (key_id, secret) = api_key.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+    (!key_id.is_empty() && !secret.is_empty()).then_some((key_id, secret))
+}
+
+/// Deduplicated scopes, in the order given.
+fn validate_scopes(requested: &[String]) -> Result<Vec<String>, ApiKeyError> {
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in requested {
+        if scope.is_empty() || scope.chars().any(char::is_whitespace) {
+            return Err(ApiKeyError::InvalidScope(scope.clone()));
+        }
+        if !scopes.contains(scope) {
+            scopes.push(scope.clone());
+        }
+    }
+    Ok(scopes)
+}
+
+fn hash_secret(secret: &str) -> [u8; 32] {
+    Sha256::digest(secret.as_bytes()).into()
+}
+
+/// gRPC API Key Service implementation.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeyServiceImpl {
+    keys: ApiKeys,
+}
+
+impl ApiKeyServiceImpl {
+    /// Create a new API key service implementation.
+    #[must_use]
+    pub const fn new(keys: ApiKeys) -> Self {
+        Self { keys }
+    }
+}
+
+#[tonic::async_trait]
+impl ApiKeyService for ApiKeyServiceImpl {
+    async fn create_api_key(
+        &self,
+        request: Request<CreateApiKeyRequest>,
+    ) -> Result<Response<CreateApiKeyResponse>, Status> {
+        let req = request.into_inner();
+
+        let (key, api_key) = self
+            .keys
+            .create(
+                &req.name,
+                req.user_id,
+                &req.scopes,
+                req.ttl_seconds,
+                Utc::now(),
+            )
+            .await?;
+        tracing::info!(key_id = %key.key_id, name = %key.name, "API key created");
+
+        Ok(Response::new(CreateApiKeyResponse {
+            key: Some(key),
+            api_key,
+        }))
+    }
+
+    async fn list_api_keys(
+        &self,
+        request: Request<ListApiKeysRequest>,
+    ) -> Result<Response<ListApiKeysResponse>, Status> {
+        let req = request.into_inner();
+
+        Ok(Response::new(ListApiKeysResponse {
+            keys: self.keys.list(req.user_id, req.include_revoked).await?,
+        }))
+    }
+
+    async fn revoke_api_key(
+        &self,
+        request: Request<RevokeApiKeyRequest>,
+    ) -> Result<Response<RevokeApiKeyResponse>, Status> {
+        let req = request.into_inner();
+
+        let revoked = self.keys.revoke(&req.key_id, Utc::now()).await?;
+        if revoked {
+            tracing::info!(key_id = %req.key_id, "API key revoked");
+        }
+
+        Ok(Response::new(RevokeApiKeyResponse { revoked }))
+    }
+
+    async fn validate_api_key(
+        &self,
+        request: Request<ValidateApiKeyRequest>,
+    ) -> Result<Response<ValidateApiKeyResponse>, Status> {
+        let req = request.into_inner();
+
+        let response = match self
+            .keys
+            .validate(&req.api_key, &req.required_scopes, Utc::now())
+            .await?
+        {
+            ApiKeyValidation::Valid(key) => ValidateApiKeyResponse {
+                valid: true,
+                key: Some(key),
+                missing_scopes: Vec::new(),
+            },
+            ApiKeyValidation::MissingScopes(key, missing_scopes) => ValidateApiKeyResponse {
+                valid: false,
+                key: Some(key),
+                missing_scopes,
+            },
+            ApiKeyValidation::Invalid => ValidateApiKeyResponse {
+                valid: false,
+                key: None,
+                missing_scopes: Vec::new(),
+            },
+        };
+
+        Ok(Response::new(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scopes(scopes: &[&str]) -> Vec<String> {
+        scopes.iter().map(ToString::to_string).collect()
+    }
+
+    #[tokio::test]
+    async fn test_created_key_validates_and_records_use() {
+        let keys = ApiKeys::new();
+        let now = Utc::now();
+        let (info, api_key) = keys
+            .create(
+                "CI deploys",
+                Some(7),
+                &scopes(&["deploy", "read"]),
+                None,
+                now,
+            )
+            .await
+            .unwrap();
+        assert!(api_key.starts_with(&format!("adx_{}_", info.key_id)));
+        assert_eq!(info.last_used_at, None);
+
+        let later = now + TimeDelta::minutes(5);
+        let ApiKeyValidation::Valid(key) = keys
+            .validate(&api_key, &scopes(&["deploy"]), later)
+            .await
+            .unwrap()
+        else {
+            panic!("key should be valid");
+        };
+        assert_eq!(key.user_id, Some(7));
+        assert_eq!(key.last_used_at, Some(later.timestamp()));
+        assert_eq!(
+            keys.list(None, false).await.unwrap()[0].last_used_at,
+            Some(later.timestamp())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_secret_is_hashed_at_rest() {
+        let keys = ApiKeys::new();
+        let (info, api_key) = keys
+            .create("Backups", None, &[], None, Utc::now())
+            .await
+            .unwrap();
+        let secret = api_key
+            .strip_prefix(&format!("adx_{}_", info.key_id))
+            .unwrap();
+
+        let secret_hash = keys.keys.get(&info.key_id).unwrap().secret_hash;
+        assert_eq!(secret_hash, hash_secret(secret));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_wrong_malformed_and_unknown_keys() {
+        let keys = ApiKeys::new();
+        let now = Utc::now();
+        let (info, _) = keys.create("Backups", None, &[], None, now).await.unwrap();
+
+        for api_key in [
+            format!("adx_{}_wrong-secret", info.key_id),
+            "adx_unknown_secret".to_string(),
+            format!("{}_secret", info.key_id),
+            format!("adx_{}_", info.key_id),
+            String::new(),
+        ] {
+            assert_eq!(
+                keys.validate(&api_key, &[], now).await.unwrap(),
+                ApiKeyValidation::Invalid
+            );
+        }
+        assert_eq!(keys.list(None, false).await.unwrap()[0].last_used_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_missing_scopes_are_reported() {
+        let keys = ApiKeys::new();
+        let now = Utc::now();
+        let (_, api_key) = keys
+            .create("Reports", None, &scopes(&["read"]), None, now)
+            .await
+            .unwrap();
+
+        let ApiKeyValidation::MissingScopes(key, missing) = keys
+            .validate(&api_key, &scopes(&["read", "write", "admin"]), now)
+            .await
+            .unwrap()
+        else {
+            panic!("scopes should be missing");
+        };
+        assert_eq!(key.scopes, ["read"]);
+        assert_eq!(missing, ["write", "admin"]);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_and_expired_keys_are_invalid() {
+        let keys = ApiKeys::new();
+        let now = Utc::now();
+        let (revoked, revoked_key) = keys.create("Old", None, &[], None, now).await.unwrap();
+        let (_, expiring_key) = keys.create("Temp", None, &[], Some(60), now).await.unwrap();
+
+        assert!(keys.revoke(&revoked.key_id, now).await.unwrap());
+        assert!(!keys.revoke(&revoked.key_id, now).await.unwrap());
+        assert!(!keys.revoke("unknown", now).await.unwrap());
+        assert_eq!(
+            keys.validate(&revoked_key, &[], now).await.unwrap(),
+            ApiKeyValidation::Invalid
+        );
+
+        assert!(matches!(
+            keys.validate(&expiring_key, &[], now).await.unwrap(),
+            ApiKeyValidation::Valid(_)
+        ));
+        assert_eq!(
+            keys.validate(&expiring_key, &[], now + TimeDelta::seconds(60))
+                .await
+                .unwrap(),
+            ApiKeyValidation::Invalid
+        );
+
+        assert_eq!(keys.list(None, false).await.unwrap().len(), 1);
+        let all = keys.list(None, true).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all
+            .iter()
+            .any(|key| key.revoked_at == Some(now.timestamp())));
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_by_user() {
+        let keys = ApiKeys::new();
+        let now = Utc::now();
+        for (name, user_id) in [
+            ("Alice's script", Some(1)),
+            ("Bob's script", Some(2)),
+            ("Nightly sync", None),
+        ] {
+            keys.create(name, user_id, &[], None, now).await.unwrap();
+        }
+
+        let alice = keys.list(Some(1), false).await.unwrap();
+        assert_eq!(alice.len(), 1);
+        assert_eq!(alice[0].name, "Alice's script");
+        assert_eq!(keys.list(None, false).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_requests() {
+        let keys = ApiKeys::new();
+        let now = Utc::now();
+        assert!(matches!(
+            keys.create(" ", None, &[], None, now).await,
+            Err(ApiKeyError::InvalidRequest(_))
+        ));
+        assert_eq!(
+            keys.create("Key", None, &scopes(&["read write"]), None, now)
+                .await
+                .unwrap_err(),
+            ApiKeyError::InvalidScope("read write".to_string())
+        );
+        assert!(matches!(
+            keys.create("Key", None, &[], Some(0), now).await,
+            Err(ApiKeyError::InvalidRequest(_))
+        ));
+
+        let (info, _) = keys
+            .create("Key", None, &scopes(&["read", "read"]), None, now)
+            .await
+            .unwrap();
+        assert_eq!(info.scopes, ["read"]);
+    }
+
+    #[test]
+    fn test_key_row_round_trip() {
+        let now = DateTime::from_timestamp(Utc::now().timestamp(), 0).unwrap();
+        let key = StoredKey {
+            name: "CI deploys".to_string(),
+            user_id: None,
+            scopes: scopes(&["deploy", "read"]),
+            secret_hash: hash_secret("secret"),
+            created_at: now,
+            expires_at: Some(now + TimeDelta::days(30)),
+            last_used_at: None,
+            revoked_at: Some(now),
+        };
+
+        let names = COLUMNS.split(", ").map(|name| name.trim().to_string());
+        let row = Row {
+            columns: names.zip(key_params("abc", &key)).collect(),
+        };
+        let (key_id, loaded) = key_from_row(&row).expect("complete row");
+
+        assert_eq!(key_id, "abc");
+        assert_eq!(loaded.to_proto(&key_id), key.to_proto("abc"));
+        assert_eq!(loaded.secret_hash, key.secret_hash);
+    }
+}
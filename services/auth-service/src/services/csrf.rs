@@ -4,6 +4,7 @@ use acton_dx_proto::auth::v1::{
     csrf_service_server::CsrfService, GenerateTokenRequest, GenerateTokenResponse,
     ValidateTokenRequest, ValidateTokenResponse,
 };
+use acton_dx_proto::error::invalid_field;
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use dashmap::DashMap;
 use rand::Rng;
@@ -85,7 +86,7 @@ impl CsrfService for CsrfServiceImpl {
         let req = request.into_inner();
 
         if req.session_id.is_empty() {
-            return Err(Status::invalid_argument("session_id cannot be empty"));
+            return Err(invalid_field("session_id", "session_id cannot be empty"));
         }
 
         let token = self.create_random_token();
@@ -106,11 +107,11 @@ impl CsrfService for CsrfServiceImpl {
         let req = request.into_inner();
 
         if req.session_id.is_empty() {
-            return Err(Status::invalid_argument("session_id cannot be empty"));
+            return Err(invalid_field("session_id", "session_id cannot be empty"));
         }
 
         if req.token.is_empty() {
-            return Err(Status::invalid_argument("token cannot be empty"));
+            return Err(invalid_field("token", "token cannot be empty"));
         }
 
         let valid = self.tokens.get(&req.session_id).is_some_and(|entry| {
@@ -1,9 +1,21 @@
 //! gRPC service implementations for auth-service.
 
+mod api_key;
 mod csrf;
+mod oidc;
+mod passkey;
 mod password;
+pub mod security;
 mod session;
+mod token;
 
+pub use api_key::{
+    ApiKeyError, ApiKeyServiceImpl, ApiKeyValidation, ApiKeys, PostgresApiKeyStore, API_KEY_PREFIX,
+};
 pub use csrf::CsrfServiceImpl;
+pub use oidc::{OidcError, OidcProvider, OidcProviderServiceImpl, PostgresOidcStore};
+pub use passkey::{PasskeyServiceImpl, PostgresPasskeyStore};
 pub use password::PasswordServiceImpl;
+pub use security::{SecurityEventServiceImpl, SecurityEvents};
 pub use session::SessionServiceImpl;
+pub use token::{Claims, TokenError, TokenIssuer, TokenServiceImpl, TENANT_SESSION_KEY};
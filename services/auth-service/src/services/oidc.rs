@@ -0,0 +1,1312 @@
+//! gRPC OpenID Connect provider implementation.
+//!
+//! Lets small internal tools offer "Sign in with" the application using the
+//! authorization code flow. The web tier serves the browser-facing
+//! endpoints and the consent screen; this service owns clients,
+//! authorization codes and consents, and signs ID tokens with the access
+//! token keys so the token service's JWKS verifies them.
+//!
+//! Clients from the configuration are registered again on startup. Other
+//! clients and consents live in memory unless a [`PostgresOidcStore`] is
+//! configured; with one, every call reads and writes the store, so they
+//! survive a restart and replicas share them. Authorization codes stay in
+//! memory either way: they are exchanged within a minute of being issued.
+
+use crate::agents::session_manager::LoadSession;
+use crate::config::OidcConfig;
+use crate::services::token::{Claims, TokenError, TokenIssuer};
+use crate::store::{int, null, number, string, text, DataTable};
+use crate::{random_token, seconds, SessionData};
+use acton_dx_proto::auth::v1::{
+    oidc_provider_service_server::OidcProviderService, DeleteOidcClientRequest,
+    DeleteOidcClientResponse, GetOidcMetadataRequest, GetOidcMetadataResponse,
+    ListOidcClientsRequest, ListOidcClientsResponse, OidcAuthorizeRequest, OidcAuthorizeResponse,
+    OidcClient, OidcExchangeCodeRequest, OidcTokenResponse, OidcUserInfoRequest,
+    OidcUserInfoResponse, RegisterOidcClientRequest, RegisterOidcClientResponse,
+};
+use acton_dx_proto::data::v1::{Row, Value};
+use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use tonic::{Request, Response, Status};
+use webauthn_rs::prelude::Url;
+
+/// Scopes clients may request.
+const SCOPES: [&str; 3] = ["openid", "profile", "email"];
+
+/// Why an OpenID Connect request was refused.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OidcError {
+    /// The request is missing or has an invalid parameter.
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    /// A requested scope is not supported.
+    #[error("scope {0:?} is not supported")]
+    InvalidScope(String),
+    /// The client is unknown or its credentials are wrong.
+    #[error("client authentication failed")]
+    InvalidClient,
+    /// The authorization code is unknown, expired or was issued for
+    /// another client or redirect URI.
+    #[error("invalid grant: {0}")]
+    InvalidGrant(String),
+    /// The access token is not valid for userinfo.
+    #[error("invalid access token")]
+    InvalidToken,
+    /// The client and consent store could not be reached.
+    #[error("OIDC store unavailable: {0}")]
+    Store(String),
+}
+
+impl From<OidcError> for Status {
+    fn from(error: OidcError) -> Self {
+        match error {
+            OidcError::InvalidRequest(_) | OidcError::InvalidScope(_) => {
+                Self::invalid_argument(error.to_string())
+            }
+            OidcError::InvalidClient | OidcError::InvalidToken => {
+                Self::unauthenticated(error.to_string())
+            }
+            OidcError::InvalidGrant(_) => Self::failed_precondition(error.to_string()),
+            OidcError::Store(_) => Self::unavailable(error.to_string()),
+        }
+    }
+}
+
+impl From<Status> for OidcError {
+    fn from(status: Status) -> Self {
+        Self::Store(status.message().to_string())
+    }
+}
+
+impl From<TokenError> for OidcError {
+    fn from(_: TokenError) -> Self {
+        Self::InvalidToken
+    }
+}
+
+/// A registered client.
+#[derive(Clone)]
+struct Client {
+    name: String,
+    redirect_uris: Vec<String>,
+    /// SHA-256 of the secret; `None` for public clients. Secrets are random
+    /// and high-entropy, so a fast hash is enough.
+    secret_hash: Option<[u8; 32]>,
+    created_at: DateTime<Utc>,
+}
+
+impl Client {
+    fn to_proto(&self, client_id: &str) -> OidcClient {
+        OidcClient {
+            client_id: client_id.to_string(),
+            name: self.name.clone(),
+            redirect_uris: self.redirect_uris.clone(),
+            public: self.secret_hash.is_none(),
+            created_at: self.created_at.timestamp(),
+        }
+    }
+}
+
+/// An authorization code waiting to be exchanged.
+struct PendingCode {
+    client_id: String,
+    redirect_uri: String,
+    user_id: i64,
+    session_id: String,
+    email: Option<String>,
+    name: Option<String>,
+    scopes: Vec<String>,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+    auth_time: DateTime<Utc>,
+    session_expires_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+/// Clients, authorization codes and consents of the provider.
+#[derive(Clone)]
+pub struct OidcProvider {
+    config: Arc<OidcConfig>,
+    issuer: TokenIssuer,
+    /// Clients from the configuration, and registered clients when there
+    /// is no store.
+    clients: Arc<DashMap<String, Client>>,
+    codes: Arc<DashMap<String, PendingCode>>,
+    /// Scopes each user has approved, by user and client ID, when there is
+    /// no store.
+    consents: Arc<DashMap<(i64, String), Vec<String>>>,
+    /// Persistent store registered clients and consents are kept in.
+    store: Option<PostgresOidcStore>,
+}
+
+impl std::fmt::Debug for OidcProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcProvider")
+            .field("config", &self.config)
+            .field("clients", &self.clients.len())
+            .field("store", &self.store)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OidcProvider {
+    /// Create a provider signing with `issuer` and register the configured
+    /// clients.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a configured client has an invalid redirect URI.
+    pub fn new(config: &OidcConfig, issuer: TokenIssuer) -> Result<Self, OidcError> {
+        let provider = Self {
+            config: Arc::new(config.clone()),
+            issuer,
+            clients: Arc::new(DashMap::new()),
+            codes: Arc::new(DashMap::new()),
+            consents: Arc::new(DashMap::new()),
+            store: None,
+        };
+        for client in &config.clients {
+            validate_redirect_uris(&client.redirect_uris)?;
+            provider.clients.insert(
+                client.client_id.clone(),
+                Client {
+                    name: client.name.clone(),
+                    redirect_uris: client.redirect_uris.clone(),
+                    secret_hash: client.client_secret.as_deref().map(hash_secret),
+                    created_at: Utc::now(),
+                },
+            );
+        }
+        Ok(provider)
+    }
+
+    /// Keep registered clients and consents in `store` instead of in
+    /// memory.
+    #[must_use]
+    pub fn with_store(mut self, store: PostgresOidcStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Register a client and return it with its secret, which is only
+    /// shown once. Public clients get no secret.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is empty, a redirect URI is invalid or
+    /// the store fails.
+    pub async fn register_client(
+        &self,
+        name: &str,
+        redirect_uris: Vec<String>,
+        public: bool,
+    ) -> Result<(OidcClient, Option<String>), OidcError> {
+        if name.trim().is_empty() {
+            return Err(OidcError::InvalidRequest(
+                "name cannot be empty".to_string(),
+            ));
+        }
+        validate_redirect_uris(&redirect_uris)?;
+
+        let client_id = uuid::Uuid::new_v4().simple().to_string();
+        let secret = (!public).then(random_token);
+        let client = Client {
+            name: name.trim().to_string(),
+            redirect_uris,
+            secret_hash: secret.as_deref().map(hash_secret),
+            created_at: Utc::now(),
+        };
+        let info = client.to_proto(&client_id);
+        if let Some(store) = &self.store {
+            store.insert_client(&client_id, &client).await?;
+        } else {
+            self.clients.insert(client_id, client);
+        }
+        Ok((info, secret))
+    }
+
+    /// Registered clients, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    pub async fn clients(&self) -> Result<Vec<OidcClient>, OidcError> {
+        let mut clients: Vec<_> = self
+            .clients
+            .iter()
+            .map(|entry| entry.value().to_proto(entry.key()))
+            .collect();
+        if let Some(store) = &self.store {
+            let stored = store.clients().await?;
+            clients.extend(
+                stored
+                    .iter()
+                    .map(|(client_id, client)| client.to_proto(client_id)),
+            );
+        }
+        clients.sort_by(|a, b| (a.created_at, &a.name).cmp(&(b.created_at, &b.name)));
+        Ok(clients)
+    }
+
+    /// Delete a client with its pending codes and consents.
+    ///
+    /// Access tokens already issued to it stop working for userinfo.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store fails.
+    pub async fn delete_client(&self, client_id: &str) -> Result<bool, OidcError> {
+        self.codes.retain(|_, code| code.client_id != client_id);
+        self.consents.retain(|(_, client), _| client != client_id);
+        let mut deleted = self.clients.remove(client_id).is_some();
+        if let Some(store) = &self.store {
+            deleted |= store.delete_client(client_id).await?;
+        }
+        Ok(deleted)
+    }
+
+    /// A client from the configuration or registered since.
+    async fn client(&self, client_id: &str) -> Result<Option<Client>, OidcError> {
+        if let Some(client) = self.clients.get(client_id) {
+            return Ok(Some(client.clone()));
+        }
+        match &self.store {
+            Some(store) => Ok(store.client(client_id).await?),
+            None => Ok(None),
+        }
+    }
+
+    /// Scopes a user has approved for a client.
+    async fn approved_scopes(
+        &self,
+        user_id: i64,
+        client_id: &str,
+    ) -> Result<Vec<String>, OidcError> {
+        if let Some(store) = &self.store {
+            return Ok(store.consent(user_id, client_id).await?);
+        }
+        Ok(self
+            .consents
+            .get(&(user_id, client_id.to_string()))
+            .map(|approved| approved.clone())
+            .unwrap_or_default())
+    }
+
+    /// Add `scopes` to those a user has approved for a client.
+    async fn approve(
+        &self,
+        user_id: i64,
+        client_id: &str,
+        scopes: &[String],
+    ) -> Result<(), OidcError> {
+        let Some(store) = &self.store else {
+            let mut approved = self
+                .consents
+                .entry((user_id, client_id.to_string()))
+                .or_default();
+            merge_scopes(&mut approved, scopes);
+            return Ok(());
+        };
+        let mut approved = store.consent(user_id, client_id).await?;
+        merge_scopes(&mut approved, scopes);
+        store.save_consent(user_id, client_id, &approved).await?;
+        Ok(())
+    }
+
+    /// Validate an authorization request for a signed-in session.
+    ///
+    /// Returns a code once the user has approved the requested scopes,
+    /// either now (`consent`) or before; otherwise the response asks for
+    /// the consent screen.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client, redirect URI, scopes or PKCE
+    /// parameters are invalid, the session is not signed in or the store
+    /// fails.
+    pub async fn authorize(
+        &self,
+        session: &SessionData,
+        req: &OidcAuthorizeRequest,
+        now: DateTime<Utc>,
+    ) -> Result<OidcAuthorizeResponse, OidcError> {
+        let user_id = session
+            .user_id
+            .ok_or_else(|| OidcError::InvalidRequest("session is not signed in".to_string()))?;
+        let client = self
+            .client(&req.client_id)
+            .await?
+            .ok_or(OidcError::InvalidClient)?;
+        if !client.redirect_uris.contains(&req.redirect_uri) {
+            return Err(OidcError::InvalidRequest(
+                "redirect_uri is not registered for the client".to_string(),
+            ));
+        }
+
+        let scopes = validate_scopes(&req.scopes)?;
+        match (
+            req.code_challenge.as_deref(),
+            req.code_challenge_method.as_deref(),
+        ) {
+            (Some(_), Some("S256")) => {}
+            (Some(_), _) => {
+                return Err(OidcError::InvalidRequest(
+                    "code_challenge_method must be S256".to_string(),
+                ))
+            }
+            (None, _) if client.secret_hash.is_none() => {
+                return Err(OidcError::InvalidRequest(
+                    "public clients must use PKCE".to_string(),
+                ))
+            }
+            (None, _) => {}
+        }
+
+        if req.consent {
+            self.approve(user_id, &req.client_id, &scopes).await?;
+        } else {
+            let approved = self.approved_scopes(user_id, &req.client_id).await?;
+            if !scopes.iter().all(|scope| approved.contains(scope)) {
+                return Ok(OidcAuthorizeResponse {
+                    consent_required: true,
+                    client_name: client.name,
+                    scopes,
+                    code: None,
+                });
+            }
+        }
+
+        self.codes.retain(|_, code| code.expires_at > now);
+        let code = random_token();
+        self.codes.insert(
+            code.clone(),
+            PendingCode {
+                client_id: req.client_id.clone(),
+                redirect_uri: req.redirect_uri.clone(),
+                user_id,
+                session_id: session.session_id.clone(),
+                email: session.user_email.clone(),
+                name: session.user_name.clone(),
+                scopes: scopes.clone(),
+                nonce: req.nonce.clone(),
+                code_challenge: req.code_challenge.clone(),
+                auth_time: session.created_at,
+                session_expires_at: session.expires_at,
+                expires_at: now + seconds(self.config.code_ttl_seconds),
+            },
+        );
+
+        Ok(OidcAuthorizeResponse {
+            consent_required: false,
+            client_name: client.name,
+            scopes,
+            code: Some(code),
+        })
+    }
+
+    /// Exchange an authorization code for an access token and ID token.
+    ///
+    /// Codes can be exchanged once. Tokens never outlive the session the
+    /// user approved the request from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client cannot be authenticated or the code
+    /// is not valid for it.
+    pub async fn exchange(
+        &self,
+        req: &OidcExchangeCodeRequest,
+        now: DateTime<Utc>,
+    ) -> Result<OidcTokenResponse, OidcError> {
+        self.authenticate_client(&req.client_id, &req.client_secret)
+            .await?;
+
+        let (_, code) = self
+            .codes
+            .remove(&req.code)
+            .ok_or_else(|| OidcError::InvalidGrant("unknown authorization code".to_string()))?;
+        if code.expires_at <= now {
+            return Err(OidcError::InvalidGrant(
+                "authorization code expired".to_string(),
+            ));
+        }
+        if code.client_id != req.client_id || code.redirect_uri != req.redirect_uri {
+            return Err(OidcError::InvalidGrant(
+                "authorization code was issued for another client".to_string(),
+            ));
+        }
+        if let Some(challenge) = &code.code_challenge {
+            let verified = req
+                .code_verifier
+                .as_deref()
+                .is_some_and(|verifier| pkce_challenge(verifier) == *challenge);
+            if !verified {
+                return Err(OidcError::InvalidGrant(
+                    "code_verifier does not match".to_string(),
+                ));
+            }
+        }
+
+        let expires_at = (now + self.issuer.ttl()).min(code.session_expires_at);
+        if expires_at <= now {
+            return Err(OidcError::InvalidGrant("session has expired".to_string()));
+        }
+
+        let scope = code.scopes.join(" ");
+        let claims = Claims {
+            iss: self.config.issuer.clone(),
+            sub: code.user_id.to_string(),
+            aud: code.client_id.clone(),
+            scope: scope.clone(),
+            sid: code.session_id.clone(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            iat: now.timestamp(),
+            exp: expires_at.timestamp(),
+            tid: None,
+        };
+        let mut id_claims = serde_json::json!({
+            "iss": claims.iss,
+            "sub": claims.sub,
+            "aud": claims.aud,
+            "iat": claims.iat,
+            "exp": claims.exp,
+            "auth_time": code.auth_time.timestamp(),
+        });
+        if let Some(nonce) = &code.nonce {
+            id_claims["nonce"] = nonce.clone().into();
+        }
+        add_profile_claims(&mut id_claims, &code.scopes, code.email, code.name);
+
+        let access_token = self.issuer.sign(&claims, now)?;
+        let id_token = self.issuer.sign(&id_claims, now)?;
+
+        tracing::info!(
+            user_id = code.user_id,
+            client_id = %code.client_id,
+            scope = %scope,
+            "OpenID Connect tokens issued"
+        );
+
+        Ok(OidcTokenResponse {
+            access_token,
+            id_token,
+            token_type: "Bearer".to_string(),
+            expires_in: claims.exp - claims.iat,
+            scope,
+        })
+    }
+
+    /// Verify an access token issued by [`Self::exchange`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token is not a live `openid` token of a
+    /// registered client.
+    pub async fn verify_access_token(
+        &self,
+        access_token: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Claims, OidcError> {
+        let claims: Claims = self.issuer.decode_verified(access_token)?;
+        let valid = claims.iss == self.config.issuer
+            && claims.exp > now.timestamp()
+            && claims.user_id().is_some()
+            && claims.scopes().any(|scope| scope == "openid")
+            && self.client(&claims.aud).await?.is_some();
+        if !valid {
+            return Err(OidcError::InvalidToken);
+        }
+        Ok(claims)
+    }
+
+    /// UserInfo claims for a verified access token and its session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session has ended or belongs to another user.
+    pub fn userinfo(
+        &self,
+        claims: &Claims,
+        session: &SessionData,
+    ) -> Result<serde_json::Value, OidcError> {
+        if session.is_expired() || session.user_id != claims.user_id() {
+            return Err(OidcError::InvalidToken);
+        }
+        let scopes: Vec<String> = claims.scopes().map(str::to_string).collect();
+        let mut userinfo = serde_json::json!({ "sub": claims.sub });
+        add_profile_claims(
+            &mut userinfo,
+            &scopes,
+            session.user_email.clone(),
+            session.user_name.clone(),
+        );
+        Ok(userinfo)
+    }
+
+    /// OpenID Provider Configuration served at
+    /// `/.well-known/openid-configuration`.
+    #[must_use]
+    pub fn metadata(&self) -> serde_json::Value {
+        let issuer = self.config.issuer.trim_end_matches('/');
+        serde_json::json!({
+            "issuer": self.config.issuer,
+            "authorization_endpoint": format!("{issuer}/oauth/authorize"),
+            "token_endpoint": format!("{issuer}/oauth/token"),
+            "userinfo_endpoint": format!("{issuer}/oauth/userinfo"),
+            "jwks_uri": format!("{issuer}/.well-known/jwks.json"),
+            "response_types_supported": ["code"],
+            "grant_types_supported": ["authorization_code"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["EdDSA"],
+            "scopes_supported": SCOPES,
+            "claims_supported": ["sub", "email", "name"],
+            "token_endpoint_auth_methods_supported": [
+                "client_secret_basic",
+                "client_secret_post",
+                "none",
+            ],
+            "code_challenge_methods_supported": ["S256"],
+        })
+    }
+
+    async fn authenticate_client(&self, client_id: &str, secret: &str) -> Result<(), OidcError> {
+        let client = self
+            .client(client_id)
+            .await?
+            .ok_or(OidcError::InvalidClient)?;
+        match &client.secret_hash {
+            Some(hash) if bool::from(hash_secret(secret).ct_eq(hash)) => Ok(()),
+            Some(_) => Err(OidcError::InvalidClient),
+            // Public clients prove possession of the code with PKCE instead
+            None => Ok(()),
+        }
+    }
+}
+
+fn validate_redirect_uris(redirect_uris: &[String]) -> Result<(), OidcError> {
+    if redirect_uris.is_empty() {
+        return Err(OidcError::InvalidRequest(
+            "at least one redirect URI is required".to_string(),
+        ));
+    }
+    for uri in redirect_uris {
+        let valid = Url::parse(uri)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.fragment().is_none());
+        if !valid {
+            return Err(OidcError::InvalidRequest(format!(
+                "invalid redirect URI {uri:?}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Deduplicated requested scopes; `openid` is required.
+fn validate_scopes(requested: &[String]) -> Result<Vec<String>, OidcError> {
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in requested {
+        if !SCOPES.contains(&scope.as_str()) {
+            return Err(OidcError::InvalidScope(scope.clone()));
+        }
+        if !scopes.contains(scope) {
+            scopes.push(scope.clone());
+        }
+    }
+    if !scopes.iter().any(|scope| scope == "openid") {
+        return Err(OidcError::InvalidRequest(
+            "the openid scope is required".to_string(),
+        ));
+    }
+    Ok(scopes)
+}
+
+fn add_profile_claims(
+    claims: &mut serde_json::Value,
+    scopes: &[String],
+    email: Option<String>,
+    name: Option<String>,
+) {
+    let granted = |scope: &str| scopes.iter().any(|s| s == scope);
+    if let Some(email) = email.filter(|_| granted("email")) {
+        claims["email"] = email.into();
+    }
+    if let Some(name) = name.filter(|_| granted("profile")) {
+        claims["name"] = name.into();
+    }
+}
+
+fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn hash_secret(secret: &str) -> [u8; 32] {
+    Sha256::digest(secret.as_bytes()).into()
+}
+
+fn merge_scopes(approved: &mut Vec<String>, scopes: &[String]) {
+    for scope in scopes {
+        if !approved.contains(scope) {
+            approved.push(scope.clone());
+        }
+    }
+}
+
+/// Columns of the client table, in parameter order.
+const CLIENT_COLUMNS: &str = "client_id, name, redirect_uris, secret_hash, created_at";
+
+/// Stores registered clients and consents in two Postgres tables through
+/// the data service.
+///
+/// Redirect URIs and scopes are stored as JSON arrays, secret hashes as
+/// URL-safe base64 and timestamps as Unix seconds:
+///
+/// ```sql
+/// CREATE TABLE auth_oidc_clients (
+///     client_id TEXT PRIMARY KEY,
+///     name TEXT NOT NULL,
+///     redirect_uris TEXT NOT NULL,
+///     secret_hash TEXT,
+///     created_at BIGINT NOT NULL
+/// );
+/// CREATE TABLE auth_oidc_consents (
+///     user_id BIGINT NOT NULL,
+///     client_id TEXT NOT NULL,
+///     scopes TEXT NOT NULL,
+///     PRIMARY KEY (user_id, client_id)
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct PostgresOidcStore {
+    clients: DataTable,
+    consents: DataTable,
+}
+
+impl PostgresOidcStore {
+    /// Store clients in `clients_table` and consents in `consents_table`,
+    /// connecting to the data service at `endpoint` on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoint is not a valid URI or a table name
+    /// is not a plain SQL identifier.
+    pub fn connect_lazy(
+        endpoint: &str,
+        clients_table: &str,
+        consents_table: &str,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            clients: DataTable::connect_lazy(endpoint, clients_table)?,
+            consents: DataTable::connect_lazy(endpoint, consents_table)?,
+        })
+    }
+
+    /// Create the client and consent tables if they are missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn ensure_tables(&self) -> Result<(), Status> {
+        self.clients
+            .execute(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {} (\
+                     client_id TEXT PRIMARY KEY, name TEXT NOT NULL, \
+                     redirect_uris TEXT NOT NULL, secret_hash TEXT, \
+                     created_at BIGINT NOT NULL)",
+                    self.clients
+                ),
+                Vec::new(),
+            )
+            .await?;
+        self.consents
+            .execute(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {} (\
+                     user_id BIGINT NOT NULL, client_id TEXT NOT NULL, \
+                     scopes TEXT NOT NULL, PRIMARY KEY (user_id, client_id))",
+                    self.consents
+                ),
+                Vec::new(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_client(&self, client_id: &str, client: &Client) -> Result<(), Status> {
+        self.clients
+            .execute(
+                format!(
+                    "INSERT INTO {} ({CLIENT_COLUMNS}) VALUES ($1, $2, $3, $4, $5)",
+                    self.clients
+                ),
+                client_params(client_id, client),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn client(&self, client_id: &str) -> Result<Option<Client>, Status> {
+        let rows = self
+            .clients
+            .query(
+                format!(
+                    "SELECT {CLIENT_COLUMNS} FROM {} WHERE client_id = $1",
+                    self.clients
+                ),
+                vec![string(client_id)],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .find_map(client_from_row)
+            .map(|(_, client)| client))
+    }
+
+    async fn clients(&self) -> Result<Vec<(String, Client)>, Status> {
+        let rows = self
+            .clients
+            .query(
+                format!("SELECT {CLIENT_COLUMNS} FROM {}", self.clients),
+                Vec::new(),
+            )
+            .await?;
+        Ok(rows.iter().filter_map(client_from_row).collect())
+    }
+
+    async fn delete_client(&self, client_id: &str) -> Result<bool, Status> {
+        self.consents
+            .execute(
+                format!("DELETE FROM {} WHERE client_id = $1", self.consents),
+                vec![string(client_id)],
+            )
+            .await?;
+        let rows_affected = self
+            .clients
+            .execute(
+                format!("DELETE FROM {} WHERE client_id = $1", self.clients),
+                vec![string(client_id)],
+            )
+            .await?;
+        Ok(rows_affected > 0)
+    }
+
+    async fn consent(&self, user_id: i64, client_id: &str) -> Result<Vec<String>, Status> {
+        let rows = self
+            .consents
+            .query(
+                format!(
+                    "SELECT scopes FROM {} WHERE user_id = $1 AND client_id = $2",
+                    self.consents
+                ),
+                vec![int(user_id), string(client_id)],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .find_map(|row| serde_json::from_str(&text(row, "scopes")?).ok())
+            .unwrap_or_default())
+    }
+
+    async fn save_consent(
+        &self,
+        user_id: i64,
+        client_id: &str,
+        scopes: &[String],
+    ) -> Result<(), Status> {
+        self.consents
+            .execute(
+                format!(
+                    "INSERT INTO {} (user_id, client_id, scopes) VALUES ($1, $2, $3) \
+                     ON CONFLICT (user_id, client_id) DO UPDATE SET scopes = EXCLUDED.scopes",
+                    self.consents
+                ),
+                vec![
+                    int(user_id),
+                    string(client_id),
+                    string(&serde_json::to_string(scopes).unwrap_or_default()),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Query parameters for a client, in [`CLIENT_COLUMNS`] order.
+fn client_params(client_id: &str, client: &Client) -> Vec<Value> {
+    vec![
+        string(client_id),
+        string(&client.name),
+        string(&serde_json::to_string(&client.redirect_uris).unwrap_or_default()),
+        client
+            .secret_hash
+            .map_or_else(null, |hash| string(&URL_SAFE_NO_PAD.encode(hash))),
+        int(client.created_at.timestamp()),
+    ]
+}
+
+/// Client stored in a row, with its ID, if the row is complete.
+fn client_from_row(row: &Row) -> Option<(String, Client)> {
+    let secret_hash = match text(row, "secret_hash") {
+        Some(hash) => Some(URL_SAFE_NO_PAD.decode(hash).ok()?.try_into().ok()?),
+        None => None,
+    };
+    let client = Client {
+        name: text(row, "name")?,
+        redirect_uris: serde_json::from_str(&text(row, "redirect_uris")?).ok()?,
+        secret_hash,
+        created_at: DateTime::from_timestamp(number(row, "created_at")?, 0)?,
+    };
+    Some((text(row, "client_id")?, client))
+}
+
+/// gRPC OpenID Connect Provider Service implementation.
+#[derive(Debug, Clone)]
+pub struct OidcProviderServiceImpl {
+    session_agent: ActorHandle,
+    provider: OidcProvider,
+}
+
+impl OidcProviderServiceImpl {
+    /// Create a new OpenID Connect provider service implementation.
+    #[must_use]
+    pub const fn new(session_agent: ActorHandle, provider: OidcProvider) -> Self {
+        Self {
+            session_agent,
+            provider,
+        }
+    }
+
+    async fn load_session(&self, session_id: String) -> Result<Option<SessionData>, Status> {
+        let (msg, rx) = LoadSession::with_response(session_id);
+        self.session_agent.send(msg).await;
+
+        tokio::time::timeout(Duration::from_secs(5), rx)
+            .await
+            .map_err(|_| Status::deadline_exceeded("Session lookup timed out"))?
+            .map_err(|_| Status::internal("Session agent channel closed"))
+    }
+}
+
+#[tonic::async_trait]
+impl OidcProviderService for OidcProviderServiceImpl {
+    async fn register_client(
+        &self,
+        request: Request<RegisterOidcClientRequest>,
+    ) -> Result<Response<RegisterOidcClientResponse>, Status> {
+        let req = request.into_inner();
+
+        let (client, secret) = self
+            .provider
+            .register_client(&req.name, req.redirect_uris, req.public)
+            .await?;
+        tracing::info!(client_id = %client.client_id, name = %client.name, "OIDC client registered");
+
+        Ok(Response::new(RegisterOidcClientResponse {
+            client: Some(client),
+            client_secret: secret.unwrap_or_default(),
+        }))
+    }
+
+    async fn list_clients(
+        &self,
+        _request: Request<ListOidcClientsRequest>,
+    ) -> Result<Response<ListOidcClientsResponse>, Status> {
+        Ok(Response::new(ListOidcClientsResponse {
+            clients: self.provider.clients().await?,
+        }))
+    }
+
+    async fn delete_client(
+        &self,
+        request: Request<DeleteOidcClientRequest>,
+    ) -> Result<Response<DeleteOidcClientResponse>, Status> {
+        let req = request.into_inner();
+
+        let deleted = self.provider.delete_client(&req.client_id).await?;
+        if deleted {
+            tracing::info!(client_id = %req.client_id, "OIDC client deleted");
+        }
+
+        Ok(Response::new(DeleteOidcClientResponse { deleted }))
+    }
+
+    async fn authorize(
+        &self,
+        request: Request<OidcAuthorizeRequest>,
+    ) -> Result<Response<OidcAuthorizeResponse>, Status> {
+        let req = request.into_inner();
+
+        let session = self
+            .load_session(req.session_id.clone())
+            .await?
+            .filter(|session| !session.is_expired() && session.user_id.is_some())
+            .ok_or_else(|| Status::unauthenticated("Session is not signed in"))?;
+
+        Ok(Response::new(
+            self.provider.authorize(&session, &req, Utc::now()).await?,
+        ))
+    }
+
+    async fn exchange_code(
+        &self,
+        request: Request<OidcExchangeCodeRequest>,
+    ) -> Result<Response<OidcTokenResponse>, Status> {
+        let req = request.into_inner();
+
+        Ok(Response::new(
+            self.provider.exchange(&req, Utc::now()).await?,
+        ))
+    }
+
+    async fn get_user_info(
+        &self,
+        request: Request<OidcUserInfoRequest>,
+    ) -> Result<Response<OidcUserInfoResponse>, Status> {
+        let req = request.into_inner();
+
+        let claims = self
+            .provider
+            .verify_access_token(&req.access_token, Utc::now())
+            .await?;
+        let session = self
+            .load_session(claims.sid.clone())
+            .await?
+            .filter(|session| !session.is_expired())
+            .ok_or(OidcError::InvalidToken)?;
+        let userinfo = self.provider.userinfo(&claims, &session)?;
+
+        Ok(Response::new(OidcUserInfoResponse {
+            claims_json: userinfo.to_string(),
+        }))
+    }
+
+    async fn get_metadata(
+        &self,
+        _request: Request<GetOidcMetadataRequest>,
+    ) -> Result<Response<GetOidcMetadataResponse>, Status> {
+        Ok(Response::new(GetOidcMetadataResponse {
+            metadata_json: self.provider.metadata().to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{OidcClientConfig, TokenConfig};
+    use chrono::TimeDelta;
+
+    const REDIRECT: &str = "https://wiki.example.com/callback";
+
+    fn provider() -> OidcProvider {
+        let config = OidcConfig {
+            issuer: "https://app.example.com".to_string(),
+            clients: vec![OidcClientConfig {
+                client_id: "wiki".to_string(),
+                name: "Team Wiki".to_string(),
+                client_secret: Some("wiki-secret".to_string()),
+                redirect_uris: vec![REDIRECT.to_string()],
+            }],
+            ..OidcConfig::default()
+        };
+        OidcProvider::new(&config, TokenIssuer::new(&TokenConfig::default()).unwrap()).unwrap()
+    }
+
+    fn session() -> SessionData {
+        let mut session = SessionData::new(3600, Some(42));
+        session.user_email = Some("ada@example.com".to_string());
+        session.user_name = Some("Ada".to_string());
+        session
+    }
+
+    fn authorize_request(consent: bool) -> OidcAuthorizeRequest {
+        OidcAuthorizeRequest {
+            session_id: String::new(),
+            client_id: "wiki".to_string(),
+            redirect_uri: REDIRECT.to_string(),
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            nonce: Some("n-1".to_string()),
+            code_challenge: None,
+            code_challenge_method: None,
+            consent,
+        }
+    }
+
+    fn exchange_request(code: String) -> OidcExchangeCodeRequest {
+        OidcExchangeCodeRequest {
+            client_id: "wiki".to_string(),
+            client_secret: "wiki-secret".to_string(),
+            code,
+            redirect_uri: REDIRECT.to_string(),
+            code_verifier: None,
+        }
+    }
+
+    /// A code for the `wiki` client, approved by [`session`].
+    async fn code(provider: &OidcProvider, now: DateTime<Utc>) -> String {
+        provider
+            .authorize(&session(), &authorize_request(true), now)
+            .await
+            .unwrap()
+            .code
+            .unwrap()
+    }
+
+    fn payload(token: &str) -> serde_json::Value {
+        let part = token.split('.').nth(1).unwrap();
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(part).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_consent_is_asked_once() {
+        let provider = provider();
+        let session = session();
+        let now = Utc::now();
+
+        let asked = provider
+            .authorize(&session, &authorize_request(false), now)
+            .await
+            .unwrap();
+        assert!(asked.consent_required);
+        assert_eq!(asked.client_name, "Team Wiki");
+        assert_eq!(asked.code, None);
+
+        let approved = provider
+            .authorize(&session, &authorize_request(true), now)
+            .await
+            .unwrap();
+        assert!(approved.code.is_some());
+
+        let again = provider
+            .authorize(&session, &authorize_request(false), now)
+            .await
+            .unwrap();
+        assert!(!again.consent_required);
+        assert!(again.code.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_code_exchange_issues_tokens_once() {
+        let provider = provider();
+        let session = session();
+        let now = Utc::now();
+        let code = provider
+            .authorize(&session, &authorize_request(true), now)
+            .await
+            .unwrap()
+            .code
+            .unwrap();
+
+        let tokens = provider
+            .exchange(&exchange_request(code.clone()), now)
+            .await
+            .unwrap();
+        assert_eq!(tokens.token_type, "Bearer");
+        assert_eq!(tokens.scope, "openid email");
+
+        let id_token = payload(&tokens.id_token);
+        assert_eq!(id_token["iss"], "https://app.example.com");
+        assert_eq!(id_token["sub"], "42");
+        assert_eq!(id_token["aud"], "wiki");
+        assert_eq!(id_token["nonce"], "n-1");
+        assert_eq!(id_token["email"], "ada@example.com");
+        assert!(id_token.get("name").is_none());
+
+        let claims = provider
+            .verify_access_token(&tokens.access_token, now)
+            .await
+            .unwrap();
+        let userinfo = provider.userinfo(&claims, &session).unwrap();
+        assert_eq!(
+            userinfo,
+            serde_json::json!({ "sub": "42", "email": "ada@example.com" })
+        );
+
+        assert!(matches!(
+            provider.exchange(&exchange_request(code), now).await,
+            Err(OidcError::InvalidGrant(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_checks_client_and_redirect() {
+        let provider = provider();
+        let now = Utc::now();
+
+        let wrong_secret = OidcExchangeCodeRequest {
+            client_secret: "guess".to_string(),
+            ..exchange_request(code(&provider, now).await)
+        };
+        assert_eq!(
+            provider.exchange(&wrong_secret, now).await,
+            Err(OidcError::InvalidClient)
+        );
+
+        let wrong_redirect = OidcExchangeCodeRequest {
+            redirect_uri: "https://evil.example.com/callback".to_string(),
+            ..exchange_request(code(&provider, now).await)
+        };
+        assert!(matches!(
+            provider.exchange(&wrong_redirect, now).await,
+            Err(OidcError::InvalidGrant(_))
+        ));
+
+        let expired = exchange_request(code(&provider, now).await);
+        assert!(matches!(
+            provider
+                .exchange(&expired, now + TimeDelta::seconds(61))
+                .await,
+            Err(OidcError::InvalidGrant(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_public_clients_must_use_pkce() {
+        let provider = provider();
+        let (client, secret) = provider
+            .register_client("CLI", vec!["http://127.0.0.1:8400/".to_string()], true)
+            .await
+            .unwrap();
+        assert!(client.public);
+        assert_eq!(secret, None);
+
+        let request = OidcAuthorizeRequest {
+            client_id: client.client_id.clone(),
+            redirect_uri: "http://127.0.0.1:8400/".to_string(),
+            nonce: None,
+            ..authorize_request(true)
+        };
+        let now = Utc::now();
+        assert!(matches!(
+            provider.authorize(&session(), &request, now).await,
+            Err(OidcError::InvalidRequest(_))
+        ));
+
+        let verifier = "a-long-random-verifier-string-for-the-test";
+        let with_pkce = OidcAuthorizeRequest {
+            code_challenge: Some(pkce_challenge(verifier)),
+            code_challenge_method: Some("S256".to_string()),
+            ..request
+        };
+        let code = provider
+            .authorize(&session(), &with_pkce, now)
+            .await
+            .unwrap()
+            .code
+            .unwrap();
+        let exchange = OidcExchangeCodeRequest {
+            client_id: client.client_id,
+            client_secret: String::new(),
+            code,
+            redirect_uri: "http://127.0.0.1:8400/".to_string(),
+            code_verifier: Some(verifier.to_string()),
+        };
+        assert!(provider.exchange(&exchange, now).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_requests() {
+        let provider = provider();
+        let now = Utc::now();
+
+        let no_openid = OidcAuthorizeRequest {
+            scopes: vec!["email".to_string()],
+            ..authorize_request(true)
+        };
+        assert!(matches!(
+            provider.authorize(&session(), &no_openid, now).await,
+            Err(OidcError::InvalidRequest(_))
+        ));
+        let unknown_scope = OidcAuthorizeRequest {
+            scopes: vec!["openid".to_string(), "admin".to_string()],
+            ..authorize_request(true)
+        };
+        assert_eq!(
+            provider.authorize(&session(), &unknown_scope, now).await,
+            Err(OidcError::InvalidScope("admin".to_string()))
+        );
+        let unregistered = OidcAuthorizeRequest {
+            redirect_uri: "https://wiki.example.com/other".to_string(),
+            ..authorize_request(true)
+        };
+        assert!(matches!(
+            provider.authorize(&session(), &unregistered, now).await,
+            Err(OidcError::InvalidRequest(_))
+        ));
+        assert!(provider
+            .register_client("Bad", vec!["javascript:alert(1)".to_string()], false)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deleting_client_revokes_tokens() {
+        let provider = provider();
+        let now = Utc::now();
+        let code = provider
+            .authorize(&session(), &authorize_request(true), now)
+            .await
+            .unwrap()
+            .code
+            .unwrap();
+        let tokens = provider
+            .exchange(&exchange_request(code), now)
+            .await
+            .unwrap();
+
+        assert!(provider.delete_client("wiki").await.unwrap());
+        assert!(provider.clients().await.unwrap().is_empty());
+        assert_eq!(
+            provider
+                .verify_access_token(&tokens.access_token, now)
+                .await,
+            Err(OidcError::InvalidToken)
+        );
+    }
+
+    #[test]
+    fn test_metadata_endpoints() {
+        let metadata = provider().metadata();
+        assert_eq!(metadata["issuer"], "https://app.example.com");
+        assert_eq!(
+            metadata["token_endpoint"],
+            "https://app.example.com/oauth/token"
+        );
+        assert_eq!(
+            metadata["jwks_uri"],
+            "https://app.example.com/.well-known/jwks.json"
+        );
+    }
+
+    #[test]
+    fn test_client_row_round_trip() {
+        let client = Client {
+            name: "Team Wiki".to_string(),
+            redirect_uris: vec![REDIRECT.to_string()],
+            secret_hash: Some(hash_secret("wiki-secret")),
+            created_at: DateTime::from_timestamp(Utc::now().timestamp(), 0).unwrap(),
+        };
+        let public = Client {
+            secret_hash: None,
+            ..client.clone()
+        };
+
+        for client in [client, public] {
+            let names = CLIENT_COLUMNS.split(", ").map(str::to_string);
+            let row = Row {
+                columns: names.zip(client_params("wiki", &client)).collect(),
+            };
+            let (client_id, loaded) = client_from_row(&row).expect("complete row");
+
+            assert_eq!(client_id, "wiki");
+            assert_eq!(loaded.to_proto(&client_id), client.to_proto("wiki"));
+            assert_eq!(loaded.secret_hash, client.secret_hash);
+        }
+    }
+}
@@ -0,0 +1,770 @@
+//! gRPC Passkey Service implementation.
+//!
+//! Runs WebAuthn registration and authentication ceremonies with
+//! `webauthn-rs`. Ceremony state stays in the service between the start and
+//! finish calls; the browser only ever sees the challenge options.
+//!
+//! Registered passkeys live in memory unless a [`PostgresPasskeyStore`] is
+//! configured. With one, every call reads and writes the store, so passkeys
+//! survive a restart. Ceremonies stay in memory either way: they last a few
+//! minutes and must finish on the replica that started them.
+
+use crate::config::PasskeyConfig;
+use crate::store::{int, null, number, string, text, DataTable};
+use acton_dx_proto::auth::v1::{
+    passkey_service_server::PasskeyService, DeletePasskeyRequest, DeletePasskeyResponse,
+    FinishPasskeyAuthenticationRequest, FinishPasskeyAuthenticationResponse,
+    FinishPasskeyRegistrationRequest, FinishPasskeyRegistrationResponse, ListPasskeysRequest,
+    ListPasskeysResponse, PasskeyInfo, StartPasskeyAuthenticationRequest,
+    StartPasskeyAuthenticationResponse, StartPasskeyRegistrationRequest,
+    StartPasskeyRegistrationResponse,
+};
+use acton_dx_proto::data::v1::{Row, Value};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tonic::{Request, Response, Status};
+use webauthn_rs::prelude::{
+    AuthenticationResult, Passkey, PasskeyAuthentication, PasskeyRegistration, PublicKeyCredential,
+    RegisterPublicKeyCredential, Url, Uuid, Webauthn, WebauthnBuilder,
+};
+
+/// A registered passkey with its display metadata.
+#[derive(Debug, Clone)]
+struct StoredPasskey {
+    passkey: Passkey,
+    name: String,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+impl StoredPasskey {
+    fn credential_id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.passkey.cred_id())
+    }
+
+    fn to_proto(&self) -> PasskeyInfo {
+        PasskeyInfo {
+            credential_id: self.credential_id(),
+            name: self.name.clone(),
+            created_at: self.created_at.timestamp(),
+            last_used_at: self.last_used_at.map(|at| at.timestamp()),
+        }
+    }
+}
+
+/// State of a started ceremony.
+enum Ceremony {
+    Registration {
+        user_id: i64,
+        state: PasskeyRegistration,
+    },
+    Authentication {
+        user_id: i64,
+        state: PasskeyAuthentication,
+    },
+}
+
+/// A ceremony waiting for the browser's response.
+struct PendingCeremony {
+    ceremony: Ceremony,
+    expires_at: Instant,
+}
+
+/// gRPC Passkey Service implementation.
+#[derive(Clone)]
+pub struct PasskeyServiceImpl {
+    /// Relying party configuration.
+    webauthn: Arc<Webauthn>,
+    /// Passkeys by user ID, when there is no store.
+    passkeys: Arc<DashMap<i64, Vec<StoredPasskey>>>,
+    /// Persistent store passkeys are kept in.
+    store: Option<PostgresPasskeyStore>,
+    /// Started ceremonies by ceremony ID.
+    ceremonies: Arc<DashMap<String, PendingCeremony>>,
+    /// How long the browser has to answer a challenge.
+    ceremony_ttl: Duration,
+}
+
+impl std::fmt::Debug for PasskeyServiceImpl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasskeyServiceImpl")
+            .field("store", &self.store)
+            .field("ceremony_ttl", &self.ceremony_ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PasskeyServiceImpl {
+    /// Create a passkey service for the configured relying party.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the origin is not a valid URL or the relying party
+    /// ID is not a suffix of the origin's host.
+    pub fn new(config: &PasskeyConfig) -> anyhow::Result<Self> {
+        let origin = Url::parse(&config.rp_origin)?;
+        let webauthn = WebauthnBuilder::new(&config.rp_id, &origin)?
+            .rp_name(&config.rp_name)
+            .build()?;
+
+        Ok(Self {
+            webauthn: Arc::new(webauthn),
+            passkeys: Arc::new(DashMap::new()),
+            store: None,
+            ceremonies: Arc::new(DashMap::new()),
+            ceremony_ttl: Duration::from_secs(config.ceremony_ttl_seconds),
+        })
+    }
+
+    /// Keep passkeys in `store` instead of in memory.
+    #[must_use]
+    pub fn with_store(mut self, store: PostgresPasskeyStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// A user's passkeys, oldest first.
+    async fn passkeys_of(&self, user_id: i64) -> Result<Vec<StoredPasskey>, Status> {
+        if let Some(store) = &self.store {
+            return store.for_user(user_id).await;
+        }
+        Ok(self
+            .passkeys
+            .get(&user_id)
+            .map(|passkeys| passkeys.clone())
+            .unwrap_or_default())
+    }
+
+    /// Add a user's passkey. Returns `false` if the credential is already
+    /// registered, to this or another account.
+    async fn register(&self, user_id: i64, stored: StoredPasskey) -> Result<bool, Status> {
+        if let Some(store) = &self.store {
+            return store.insert(user_id, &stored).await;
+        }
+        let registered = self.passkeys.iter().any(|entry| {
+            entry
+                .value()
+                .iter()
+                .any(|other| other.passkey.cred_id() == stored.passkey.cred_id())
+        });
+        if !registered {
+            self.passkeys.entry(user_id).or_default().push(stored);
+        }
+        Ok(!registered)
+    }
+
+    /// Record a successful authentication: the signature counter, backup
+    /// state and time of use. Returns the ID of the credential used.
+    async fn record_use(
+        &self,
+        user_id: i64,
+        result: &AuthenticationResult,
+    ) -> Result<String, Status> {
+        let mut credential_id = String::new();
+        if let Some(store) = &self.store {
+            for mut stored in store.for_user(user_id).await? {
+                if stored.passkey.update_credential(result).is_some() {
+                    stored.last_used_at = Some(Utc::now());
+                    store.update(user_id, &stored).await?;
+                    credential_id = stored.credential_id();
+                }
+            }
+        } else if let Some(mut passkeys) = self.passkeys.get_mut(&user_id) {
+            for stored in passkeys.iter_mut() {
+                if stored.passkey.update_credential(result).is_some() {
+                    stored.last_used_at = Some(Utc::now());
+                    credential_id = stored.credential_id();
+                }
+            }
+        }
+        Ok(credential_id)
+    }
+
+    /// Remove a user's passkey. Returns `false` if they have none with that
+    /// credential ID.
+    async fn remove(&self, user_id: i64, credential_id: &str) -> Result<bool, Status> {
+        if let Some(store) = &self.store {
+            return store.delete(user_id, credential_id).await;
+        }
+        Ok(self.passkeys.get_mut(&user_id).is_some_and(|mut passkeys| {
+            let before = passkeys.len();
+            passkeys.retain(|stored| stored.credential_id() != credential_id);
+            passkeys.len() < before
+        }))
+    }
+
+    /// Remember a started ceremony and return its ID.
+    fn begin(&self, ceremony: Ceremony) -> String {
+        let now = Instant::now();
+        self.ceremonies
+            .retain(|_, pending| pending.expires_at > now);
+
+        let ceremony_id = Uuid::new_v4().to_string();
+        self.ceremonies.insert(
+            ceremony_id.clone(),
+            PendingCeremony {
+                ceremony,
+                expires_at: now + self.ceremony_ttl,
+            },
+        );
+        ceremony_id
+    }
+
+    /// Take a started ceremony; each one can be finished once.
+    #[allow(clippy::result_large_err)]
+    fn take(&self, ceremony_id: &str) -> Result<Ceremony, Status> {
+        let (_, pending) = self
+            .ceremonies
+            .remove(ceremony_id)
+            .ok_or_else(|| Status::not_found("Unknown passkey ceremony"))?;
+
+        if pending.expires_at <= Instant::now() {
+            return Err(Status::deadline_exceeded("Passkey ceremony expired"));
+        }
+        Ok(pending.ceremony)
+    }
+}
+
+/// Columns of the passkey table, in parameter order.
+const COLUMNS: &str = "credential_id, user_id, name, passkey, created_at, last_used_at";
+
+/// Stores passkeys in a Postgres table through the data service.
+///
+/// The `webauthn-rs` passkey, with its public key and signature counter, is
+/// stored as JSON text and timestamps as Unix seconds:
+///
+/// ```sql
+/// CREATE TABLE auth_passkeys (
+///     credential_id TEXT PRIMARY KEY,
+///     user_id BIGINT NOT NULL,
+///     name TEXT NOT NULL,
+///     passkey TEXT NOT NULL,
+///     created_at BIGINT NOT NULL,
+///     last_used_at BIGINT
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct PostgresPasskeyStore {
+    table: DataTable,
+}
+
+impl PostgresPasskeyStore {
+    /// Store passkeys in `table`, connecting to the data service at
+    /// `endpoint` on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoint is not a valid URI or the table name
+    /// is not a plain SQL identifier.
+    pub fn connect_lazy(endpoint: &str, table: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            table: DataTable::connect_lazy(endpoint, table)?,
+        })
+    }
+
+    /// Create the passkey table and its user index if they are missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn ensure_table(&self) -> Result<(), Status> {
+        let table = &self.table;
+        self.table
+            .execute(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {table} (\
+                     credential_id TEXT PRIMARY KEY, user_id BIGINT NOT NULL, \
+                     name TEXT NOT NULL, passkey TEXT NOT NULL, \
+                     created_at BIGINT NOT NULL, last_used_at BIGINT)"
+                ),
+                Vec::new(),
+            )
+            .await?;
+        self.table
+            .execute(
+                format!("CREATE INDEX IF NOT EXISTS {table}_user_id_idx ON {table} (user_id)"),
+                Vec::new(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn for_user(&self, user_id: i64) -> Result<Vec<StoredPasskey>, Status> {
+        let rows = self
+            .table
+            .query(
+                format!(
+                    "SELECT {COLUMNS} FROM {} WHERE user_id = $1 \
+                     ORDER BY created_at, credential_id",
+                    self.table
+                ),
+                vec![int(user_id)],
+            )
+            .await?;
+        Ok(rows.iter().filter_map(passkey_from_row).collect())
+    }
+
+    async fn insert(&self, user_id: i64, stored: &StoredPasskey) -> Result<bool, Status> {
+        let rows_affected = self
+            .table
+            .execute(
+                format!(
+                    "INSERT INTO {} ({COLUMNS}) \
+                     VALUES ($1, $2, $3, $4, $5, CAST($6 AS BIGINT)) \
+                     ON CONFLICT (credential_id) DO NOTHING",
+                    self.table
+                ),
+                passkey_params(user_id, stored)?,
+            )
+            .await?;
+        Ok(rows_affected > 0)
+    }
+
+    async fn update(&self, user_id: i64, stored: &StoredPasskey) -> Result<(), Status> {
+        let params = passkey_params(user_id, stored)?;
+        self.table
+            .execute(
+                format!(
+                    "UPDATE {} SET passkey = $4, last_used_at = CAST($6 AS BIGINT) \
+                     WHERE credential_id = $1 AND user_id = $2",
+                    self.table
+                ),
+                params,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: i64, credential_id: &str) -> Result<bool, Status> {
+        let rows_affected = self
+            .table
+            .execute(
+                format!(
+                    "DELETE FROM {} WHERE credential_id = $1 AND user_id = $2",
+                    self.table
+                ),
+                vec![string(credential_id), int(user_id)],
+            )
+            .await?;
+        Ok(rows_affected > 0)
+    }
+}
+
+/// Query parameters for a passkey, in [`COLUMNS`] order.
+#[allow(clippy::result_large_err)]
+fn passkey_params(user_id: i64, stored: &StoredPasskey) -> Result<Vec<Value>, Status> {
+    let passkey = serde_json::to_string(&stored.passkey)
+        .map_err(|e| Status::internal(format!("Failed to encode passkey: {e}")))?;
+    Ok(vec![
+        string(&stored.credential_id()),
+        int(user_id),
+        string(&stored.name),
+        string(&passkey),
+        int(stored.created_at.timestamp()),
+        stored
+            .last_used_at
+            .map_or_else(null, |at| int(at.timestamp())),
+    ])
+}
+
+/// Passkey stored in a row, if the row is complete.
+fn passkey_from_row(row: &Row) -> Option<StoredPasskey> {
+    let timestamp = |name: &str| number(row, name).and_then(|at| DateTime::from_timestamp(at, 0));
+    Some(StoredPasskey {
+        passkey: serde_json::from_str(&text(row, "passkey")?).ok()?,
+        name: text(row, "name")?,
+        created_at: timestamp("created_at")?,
+        last_used_at: timestamp("last_used_at"),
+    })
+}
+
+/// Stable WebAuthn user handle for a numeric user ID.
+const fn user_handle(user_id: i64) -> Uuid {
+    Uuid::from_u64_pair(0, u64::from_be_bytes(user_id.to_be_bytes()))
+}
+
+#[allow(clippy::result_large_err)]
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, Status> {
+    serde_json::to_string(value)
+        .map_err(|e| Status::internal(format!("Failed to encode options: {e}")))
+}
+
+#[allow(clippy::result_large_err)]
+fn from_json<T: serde::de::DeserializeOwned>(json: &str) -> Result<T, Status> {
+    serde_json::from_str(json)
+        .map_err(|e| Status::invalid_argument(format!("Invalid credential: {e}")))
+}
+
+#[tonic::async_trait]
+impl PasskeyService for PasskeyServiceImpl {
+    async fn start_registration(
+        &self,
+        request: Request<StartPasskeyRegistrationRequest>,
+    ) -> Result<Response<StartPasskeyRegistrationResponse>, Status> {
+        let req = request.into_inner();
+
+        if req.user_name.is_empty() {
+            return Err(Status::invalid_argument("user_name cannot be empty"));
+        }
+
+        // Keep authenticators from registering the same credential twice
+        let passkeys = self.passkeys_of(req.user_id).await?;
+        let exclude = (!passkeys.is_empty()).then(|| {
+            passkeys
+                .iter()
+                .map(|stored| stored.passkey.cred_id().clone())
+                .collect()
+        });
+        let display_name = if req.display_name.is_empty() {
+            &req.user_name
+        } else {
+            &req.display_name
+        };
+
+        let (options, state) = self
+            .webauthn
+            .start_passkey_registration(
+                user_handle(req.user_id),
+                &req.user_name,
+                display_name,
+                exclude,
+            )
+            .map_err(|e| Status::internal(format!("Failed to start registration: {e}")))?;
+
+        let options_json = to_json(&options)?;
+        let ceremony_id = self.begin(Ceremony::Registration {
+            user_id: req.user_id,
+            state,
+        });
+
+        Ok(Response::new(StartPasskeyRegistrationResponse {
+            ceremony_id,
+            options_json,
+        }))
+    }
+
+    async fn finish_registration(
+        &self,
+        request: Request<FinishPasskeyRegistrationRequest>,
+    ) -> Result<Response<FinishPasskeyRegistrationResponse>, Status> {
+        let req = request.into_inner();
+
+        let Ceremony::Registration { user_id, state } = self.take(&req.ceremony_id)? else {
+            return Err(Status::invalid_argument("Not a registration ceremony"));
+        };
+        if user_id != req.user_id {
+            return Err(Status::permission_denied(
+                "Passkey ceremony belongs to another user",
+            ));
+        }
+
+        let credential: RegisterPublicKeyCredential = from_json(&req.credential_json)?;
+        let passkey = self
+            .webauthn
+            .finish_passkey_registration(&credential, &state)
+            .map_err(|e| Status::invalid_argument(format!("Passkey registration failed: {e}")))?;
+
+        let stored = StoredPasskey {
+            passkey,
+            name: if req.name.is_empty() {
+                "Passkey".to_string()
+            } else {
+                req.name
+            },
+            created_at: Utc::now(),
+            last_used_at: None,
+        };
+        let info = stored.to_proto();
+        // A credential belongs to exactly one account
+        if !self.register(user_id, stored).await? {
+            return Err(Status::already_exists("Passkey is already registered"));
+        }
+
+        Ok(Response::new(FinishPasskeyRegistrationResponse {
+            passkey: Some(info),
+        }))
+    }
+
+    async fn start_authentication(
+        &self,
+        request: Request<StartPasskeyAuthenticationRequest>,
+    ) -> Result<Response<StartPasskeyAuthenticationResponse>, Status> {
+        let req = request.into_inner();
+
+        let passkeys: Vec<Passkey> = self
+            .passkeys_of(req.user_id)
+            .await?
+            .into_iter()
+            .map(|stored| stored.passkey)
+            .collect();
+        if passkeys.is_empty() {
+            return Err(Status::failed_precondition("User has no passkeys"));
+        }
+
+        let (options, state) = self
+            .webauthn
+            .start_passkey_authentication(&passkeys)
+            .map_err(|e| Status::internal(format!("Failed to start authentication: {e}")))?;
+
+        let options_json = to_json(&options)?;
+        let ceremony_id = self.begin(Ceremony::Authentication {
+            user_id: req.user_id,
+            state,
+        });
+
+        Ok(Response::new(StartPasskeyAuthenticationResponse {
+            ceremony_id,
+            options_json,
+        }))
+    }
+
+    async fn finish_authentication(
+        &self,
+        request: Request<FinishPasskeyAuthenticationRequest>,
+    ) -> Result<Response<FinishPasskeyAuthenticationResponse>, Status> {
+        let req = request.into_inner();
+
+        let Ceremony::Authentication { user_id, state } = self.take(&req.ceremony_id)? else {
+            return Err(Status::invalid_argument("Not an authentication ceremony"));
+        };
+
+        let credential: PublicKeyCredential = from_json(&req.credential_json)?;
+        let result = self
+            .webauthn
+            .finish_passkey_authentication(&credential, &state)
+            .map_err(|e| Status::unauthenticated(format!("Passkey authentication failed: {e}")))?;
+
+        let credential_id = self.record_use(user_id, &result).await?;
+
+        Ok(Response::new(FinishPasskeyAuthenticationResponse {
+            user_id,
+            credential_id,
+            user_verified: result.user_verified(),
+        }))
+    }
+
+    async fn list_passkeys(
+        &self,
+        request: Request<ListPasskeysRequest>,
+    ) -> Result<Response<ListPasskeysResponse>, Status> {
+        let req = request.into_inner();
+
+        let passkeys = self
+            .passkeys_of(req.user_id)
+            .await?
+            .iter()
+            .map(StoredPasskey::to_proto)
+            .collect();
+
+        Ok(Response::new(ListPasskeysResponse { passkeys }))
+    }
+
+    async fn delete_passkey(
+        &self,
+        request: Request<DeletePasskeyRequest>,
+    ) -> Result<Response<DeletePasskeyResponse>, Status> {
+        let req = request.into_inner();
+
+        let success = self.remove(req.user_id, &req.credential_id).await?;
+
+        Ok(Response::new(DeletePasskeyResponse { success }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> PasskeyServiceImpl {
+        PasskeyServiceImpl::new(&PasskeyConfig::default()).unwrap()
+    }
+
+    async fn start_registration(service: &PasskeyServiceImpl) -> StartPasskeyRegistrationResponse {
+        service
+            .start_registration(Request::new(StartPasskeyRegistrationRequest {
+                user_id: 7,
+                user_name: "ada@example.com".to_string(),
+                display_name: "Ada".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+    }
+
+    /// A registered passkey, as `webauthn-rs` serializes it.
+    fn passkey(credential_id: &[u8]) -> Passkey {
+        let coordinate = URL_SAFE_NO_PAD.encode([7u8; 32]);
+        serde_json::from_value(serde_json::json!({
+            "cred": {
+                "cred_id": URL_SAFE_NO_PAD.encode(credential_id),
+                "cred": {
+                    "type_": "ES256",
+                    "key": {"EC_EC2": {"curve": "SECP256R1", "x": coordinate, "y": coordinate}},
+                },
+                "counter": 0,
+                "transports": null,
+                "user_verified": true,
+                "backup_eligible": false,
+                "backup_state": false,
+                "registration_policy": "required",
+                "extensions": {},
+                "attestation": {"data": "None", "metadata": "None"},
+                "attestation_format": "none",
+            }
+        }))
+        .unwrap()
+    }
+
+    fn stored(credential_id: &[u8]) -> StoredPasskey {
+        StoredPasskey {
+            passkey: passkey(credential_id),
+            name: "Laptop".to_string(),
+            created_at: DateTime::from_timestamp(Utc::now().timestamp(), 0).unwrap(),
+            last_used_at: None,
+        }
+    }
+
+    #[test]
+    fn test_invalid_origin_is_rejected() {
+        let config = PasskeyConfig {
+            rp_id: "example.com".to_string(),
+            rp_origin: "https://other.test".to_string(),
+            ..PasskeyConfig::default()
+        };
+        assert!(PasskeyServiceImpl::new(&config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_start_registration_returns_options() {
+        let service = service();
+        let response = start_registration(&service).await;
+
+        assert!(!response.ceremony_id.is_empty());
+        let options: serde_json::Value = serde_json::from_str(&response.options_json).unwrap();
+        assert_eq!(options["publicKey"]["rp"]["id"], "localhost");
+        assert_eq!(options["publicKey"]["user"]["name"], "ada@example.com");
+        assert!(options["publicKey"]["challenge"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_ceremony_is_bound_to_user() {
+        let service = service();
+        let response = start_registration(&service).await;
+
+        let result = service
+            .finish_registration(Request::new(FinishPasskeyRegistrationRequest {
+                ceremony_id: response.ceremony_id.clone(),
+                user_id: 8,
+                credential_json: "{}".to_string(),
+                name: String::new(),
+            }))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::PermissionDenied);
+
+        // A ceremony can only be finished once
+        let result = service
+            .finish_registration(Request::new(FinishPasskeyRegistrationRequest {
+                ceremony_id: response.ceremony_id,
+                user_id: 7,
+                credential_json: "{}".to_string(),
+                name: String::new(),
+            }))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_expired_ceremony_is_rejected() {
+        let service = PasskeyServiceImpl::new(&PasskeyConfig {
+            ceremony_ttl_seconds: 0,
+            ..PasskeyConfig::default()
+        })
+        .unwrap();
+        let response = start_registration(&service).await;
+
+        let result = service
+            .finish_registration(Request::new(FinishPasskeyRegistrationRequest {
+                ceremony_id: response.ceremony_id,
+                user_id: 7,
+                credential_json: "{}".to_string(),
+                name: String::new(),
+            }))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::DeadlineExceeded);
+    }
+
+    #[tokio::test]
+    async fn test_authentication_requires_passkeys() {
+        let service = service();
+
+        let result = service
+            .start_authentication(Request::new(StartPasskeyAuthenticationRequest {
+                user_id: 7,
+            }))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::FailedPrecondition);
+
+        let listed = service
+            .list_passkeys(Request::new(ListPasskeysRequest { user_id: 7 }))
+            .await
+            .unwrap();
+        assert!(listed.into_inner().passkeys.is_empty());
+
+        let deleted = service
+            .delete_passkey(Request::new(DeletePasskeyRequest {
+                user_id: 7,
+                credential_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap();
+        assert!(!deleted.into_inner().success);
+    }
+
+    #[tokio::test]
+    async fn test_credential_belongs_to_one_account() {
+        let service = service();
+        assert!(service.register(7, stored(b"laptop")).await.unwrap());
+        assert!(!service.register(8, stored(b"laptop")).await.unwrap());
+
+        let options = service
+            .start_authentication(Request::new(StartPasskeyAuthenticationRequest {
+                user_id: 7,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .options_json;
+        let options: serde_json::Value = serde_json::from_str(&options).unwrap();
+        assert_eq!(
+            options["publicKey"]["allowCredentials"][0]["id"],
+            URL_SAFE_NO_PAD.encode(b"laptop")
+        );
+
+        let credential_id = URL_SAFE_NO_PAD.encode(b"laptop");
+        assert!(!service.remove(8, &credential_id).await.unwrap());
+        assert!(service.remove(7, &credential_id).await.unwrap());
+        assert!(service.passkeys_of(7).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_passkey_row_round_trip() {
+        let mut passkey = stored(b"laptop");
+        passkey.last_used_at = Some(passkey.created_at);
+
+        let names = COLUMNS.split(", ").map(str::to_string);
+        let row = Row {
+            columns: names.zip(passkey_params(7, &passkey).unwrap()).collect(),
+        };
+        let loaded = passkey_from_row(&row).expect("complete row");
+
+        assert_eq!(loaded.passkey, passkey.passkey);
+        assert_eq!(loaded.to_proto(), passkey.to_proto());
+        assert_eq!(
+            loaded.to_proto().credential_id,
+            URL_SAFE_NO_PAD.encode(b"laptop")
+        );
+    }
+}
@@ -1,9 +1,11 @@
 //! gRPC Password Service implementation.
 
+use crate::services::SecurityEvents;
 use acton_dx_proto::auth::v1::{
     password_service_server::PasswordService, HashPasswordRequest, HashPasswordResponse,
     VerifyPasswordRequest, VerifyPasswordResponse,
 };
+use acton_dx_proto::error::invalid_field;
 use argon2::{
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
     Argon2, Params,
@@ -15,6 +17,8 @@ use tonic::{Request, Response, Status};
 pub struct PasswordServiceImpl {
     /// Argon2 hasher configuration.
     argon2: Argon2<'static>,
+    /// Publisher for login security events.
+    events: Option<SecurityEvents>,
 }
 
 impl PasswordServiceImpl {
@@ -23,6 +27,7 @@ impl PasswordServiceImpl {
     pub fn new() -> Self {
         Self {
             argon2: Argon2::default(),
+            events: None,
         }
     }
 
@@ -41,7 +46,17 @@ impl PasswordServiceImpl {
         let params = Params::new(memory_cost, time_cost, parallelism, output_len)
             .expect("Invalid argon2 parameters");
         let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
-        Self { argon2 }
+        Self {
+            argon2,
+            events: None,
+        }
+    }
+
+    /// Publish login security events for verifications that name a subject.
+    #[must_use]
+    pub fn with_events(mut self, events: SecurityEvents) -> Self {
+        self.events = Some(events);
+        self
     }
 }
 
@@ -60,7 +75,7 @@ impl PasswordService for PasswordServiceImpl {
         let req = request.into_inner();
 
         if req.password.is_empty() {
-            return Err(Status::invalid_argument("password cannot be empty"));
+            return Err(invalid_field("password", "password cannot be empty"));
         }
 
         // Generate a random salt
@@ -83,24 +98,29 @@ impl PasswordService for PasswordServiceImpl {
         let req = request.into_inner();
 
         if req.password.is_empty() {
-            return Err(Status::invalid_argument("password cannot be empty"));
+            return Err(invalid_field("password", "password cannot be empty"));
         }
 
         if req.hash.is_empty() {
-            return Err(Status::invalid_argument("hash cannot be empty"));
+            return Err(invalid_field("hash", "hash cannot be empty"));
         }
 
-        // Parse the stored hash
-        let Ok(parsed_hash) = PasswordHash::new(&req.hash) else {
-            // Invalid hash format - return false rather than error
-            return Ok(Response::new(VerifyPasswordResponse { valid: false }));
-        };
+        // Parse the stored hash; an invalid format is a failed check rather than an error
+        let valid = PasswordHash::new(&req.hash).is_ok_and(|parsed_hash| {
+            // Verify using constant-time comparison
+            self.argon2
+                .verify_password(req.password.as_bytes(), &parsed_hash)
+                .is_ok()
+        });
 
-        // Verify using constant-time comparison
-        let valid = self
-            .argon2
-            .verify_password(req.password.as_bytes(), &parsed_hash)
-            .is_ok();
+        if let (Some(events), Some(subject)) = (&self.events, &req.subject) {
+            let ip_address = req.ip_address.as_deref();
+            if valid {
+                events.login_succeeded(subject, req.user_id, ip_address);
+            } else {
+                events.login_failed(subject, req.user_id, ip_address);
+            }
+        }
 
         Ok(Response::new(VerifyPasswordResponse { valid }))
     }
@@ -125,6 +145,7 @@ mod tests {
         let verify_req = Request::new(VerifyPasswordRequest {
             password: "mysecretpassword".to_string(),
             hash: hash.clone(),
+            ..VerifyPasswordRequest::default()
         });
         let verify_resp = service.verify_password(verify_req).await.unwrap();
         assert!(verify_resp.into_inner().valid);
@@ -133,6 +154,7 @@ mod tests {
         let verify_req = Request::new(VerifyPasswordRequest {
             password: "wrongpassword".to_string(),
             hash,
+            ..VerifyPasswordRequest::default()
         });
         let verify_resp = service.verify_password(verify_req).await.unwrap();
         assert!(!verify_resp.into_inner().valid);
@@ -157,6 +179,7 @@ mod tests {
         let verify_req = Request::new(VerifyPasswordRequest {
             password: "password".to_string(),
             hash: "invalid-hash-format".to_string(),
+            ..VerifyPasswordRequest::default()
         });
         let verify_resp = service.verify_password(verify_req).await.unwrap();
         assert!(!verify_resp.into_inner().valid);
@@ -180,4 +203,25 @@ mod tests {
         // Hash should start with argon2id identifier
         assert!(hash.starts_with("$argon2id$"));
     }
+
+    #[tokio::test]
+    async fn test_failed_verification_publishes_event() {
+        let events = SecurityEvents::new(&crate::config::SecurityConfig::default());
+        let mut rx = events.subscribe();
+        let service = PasswordServiceImpl::new().with_events(events);
+
+        let verify_req = Request::new(VerifyPasswordRequest {
+            password: "password".to_string(),
+            hash: "invalid-hash-format".to_string(),
+            subject: Some("ada@example.com".to_string()),
+            user_id: Some(7),
+            ip_address: Some("10.0.0.1".to_string()),
+        });
+        service.verify_password(verify_req).await.unwrap();
+
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.kind, crate::services::security::LOGIN_FAILED);
+        assert_eq!(event.user_id, Some(7));
+        assert_eq!(event.ip_address.as_deref(), Some("10.0.0.1"));
+    }
 }
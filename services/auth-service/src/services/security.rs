@@ -0,0 +1,399 @@
+//! Security event stream for SIEM integrations and security notifications.
+
+use crate::config::SecurityConfig;
+use acton_dx_proto::auth::v1::{
+    security_event_service_server::SecurityEventService, ReportSecurityEventRequest,
+    ReportSecurityEventResponse, SecurityEvent, SubscribeSecurityEventsRequest,
+};
+use acton_dx_proto::error::invalid_field;
+use chrono::Utc;
+use dashmap::DashMap;
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+/// A password check failed for a known subject.
+pub const LOGIN_FAILED: &str = "login_failed";
+/// A subject reached the failed-login threshold within the failure window.
+pub const BRUTE_FORCE_SUSPECTED: &str = "brute_force_suspected";
+/// A subject logged in from a new IP address shortly after another login.
+pub const IP_CHANGED: &str = "ip_changed";
+/// A user's password was changed.
+pub const PASSWORD_CHANGED: &str = "password_changed";
+/// A session was destroyed.
+pub const SESSION_REVOKED: &str = "session_revoked";
+
+/// Event kinds that clients may report through the `Report` RPC.
+const REPORTABLE_KINDS: [&str; 5] = [
+    LOGIN_FAILED,
+    BRUTE_FORCE_SUSPECTED,
+    IP_CHANGED,
+    PASSWORD_CHANGED,
+    SESSION_REVOKED,
+];
+
+/// Last successful login of a subject.
+#[derive(Debug, Clone)]
+struct LastLogin {
+    ip_address: String,
+    at: Instant,
+}
+
+/// Publishes security events and tracks login history to detect anomalies.
+///
+/// Clones share the same subscribers and history, so one instance can be
+/// handed to every gRPC service.
+#[derive(Debug, Clone)]
+pub struct SecurityEvents {
+    /// Event fan-out to subscribers.
+    sender: broadcast::Sender<SecurityEvent>,
+    /// Recent failed logins per subject.
+    failures: Arc<DashMap<String, VecDeque<Instant>>>,
+    /// Last successful login per subject.
+    logins: Arc<DashMap<String, LastLogin>>,
+    /// Failed logins within the window that trigger a brute-force event.
+    max_failed_logins: usize,
+    /// Window for counting failed logins.
+    failure_window: Duration,
+    /// Logins from different IPs closer together than this are anomalous.
+    ip_change_window: Duration,
+}
+
+impl SecurityEvents {
+    /// Create a security event publisher from configuration.
+    #[must_use]
+    pub fn new(config: &SecurityConfig) -> Self {
+        let (sender, _) = broadcast::channel(config.event_buffer.max(1));
+        Self {
+            sender,
+            failures: Arc::new(DashMap::new()),
+            logins: Arc::new(DashMap::new()),
+            max_failed_logins: usize::try_from(config.max_failed_logins)
+                .unwrap_or(usize::MAX)
+                .max(1),
+            failure_window: Duration::from_secs(config.failure_window_seconds),
+            ip_change_window: Duration::from_secs(config.ip_change_window_seconds),
+        }
+    }
+
+    /// Subscribe to all events published from now on.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<SecurityEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Publish an event, filling in its ID and timestamp.
+    ///
+    /// Events published while nobody is subscribed are dropped.
+    pub fn publish(&self, mut event: SecurityEvent) -> SecurityEvent {
+        event.id = uuid::Uuid::new_v4().to_string();
+        event.occurred_at = Utc::now().timestamp();
+        tracing::info!(
+            kind = %event.kind,
+            user_id = ?event.user_id,
+            subject = ?event.subject,
+            ip_address = ?event.ip_address,
+            "Security event"
+        );
+        let _ = self.sender.send(event.clone());
+        event
+    }
+
+    /// Record a failed password check.
+    ///
+    /// Publishes a `login_failed` event, plus a `brute_force_suspected` event
+    /// when the subject reaches the failed-login threshold within the window.
+    pub fn login_failed(&self, subject: &str, user_id: Option<i64>, ip_address: Option<&str>) {
+        let now = Instant::now();
+        let attempts = {
+            let mut failures = self.failures.entry(subject.to_string()).or_default();
+            while failures
+                .front()
+                .is_some_and(|at| now.duration_since(*at) > self.failure_window)
+            {
+                failures.pop_front();
+            }
+            failures.push_back(now);
+            failures.len()
+        };
+
+        let event = SecurityEvent {
+            user_id,
+            subject: Some(subject.to_string()),
+            ip_address: ip_address.map(str::to_string),
+            ..SecurityEvent::default()
+        };
+        self.publish(SecurityEvent {
+            kind: LOGIN_FAILED.to_string(),
+            ..event.clone()
+        });
+        if attempts == self.max_failed_logins {
+            self.publish(SecurityEvent {
+                kind: BRUTE_FORCE_SUSPECTED.to_string(),
+                details: HashMap::from([
+                    ("attempts".to_string(), attempts.to_string()),
+                    (
+                        "window_seconds".to_string(),
+                        self.failure_window.as_secs().to_string(),
+                    ),
+                ]),
+                ..event
+            });
+        }
+    }
+
+    /// Record a successful password check.
+    ///
+    /// Clears the subject's failed logins and publishes an `ip_changed` event
+    /// when the previous login came from another IP within the window.
+    pub fn login_succeeded(&self, subject: &str, user_id: Option<i64>, ip_address: Option<&str>) {
+        self.failures.remove(subject);
+        let Some(ip_address) = ip_address else {
+            return;
+        };
+
+        let now = Instant::now();
+        let previous = self.logins.insert(
+            subject.to_string(),
+            LastLogin {
+                ip_address: ip_address.to_string(),
+                at: now,
+            },
+        );
+        let Some(previous) = previous else {
+            return;
+        };
+        let elapsed = now.duration_since(previous.at);
+        if previous.ip_address != ip_address && elapsed <= self.ip_change_window {
+            self.publish(SecurityEvent {
+                kind: IP_CHANGED.to_string(),
+                user_id,
+                subject: Some(subject.to_string()),
+                ip_address: Some(ip_address.to_string()),
+                details: HashMap::from([
+                    ("previous_ip_address".to_string(), previous.ip_address),
+                    (
+                        "seconds_since_previous_login".to_string(),
+                        elapsed.as_secs().to_string(),
+                    ),
+                ]),
+                ..SecurityEvent::default()
+            });
+        }
+    }
+
+    /// Forget failed logins and logins older than their detection windows.
+    pub fn prune_expired(&self) {
+        let now = Instant::now();
+        self.failures.retain(|_, failures| {
+            failures
+                .back()
+                .is_some_and(|at| now.duration_since(*at) <= self.failure_window)
+        });
+        self.logins
+            .retain(|_, login| now.duration_since(login.at) <= self.ip_change_window);
+    }
+
+    /// Record a destroyed session.
+    pub fn session_revoked(&self, session_id: &str, user_id: Option<i64>) {
+        self.publish(SecurityEvent {
+            kind: SESSION_REVOKED.to_string(),
+            user_id,
+            session_id: Some(session_id.to_string()),
+            ..SecurityEvent::default()
+        });
+    }
+}
+
+/// Whether an event passes a subscription's filters.
+fn matches(filter: &SubscribeSecurityEventsRequest, event: &SecurityEvent) -> bool {
+    (filter.kinds.is_empty() || filter.kinds.contains(&event.kind))
+        && filter.user_id.is_none_or(|id| event.user_id == Some(id))
+}
+
+/// gRPC Security Event Service implementation.
+#[derive(Debug, Clone)]
+pub struct SecurityEventServiceImpl {
+    events: SecurityEvents,
+}
+
+impl SecurityEventServiceImpl {
+    /// Create a new security event service.
+    #[must_use]
+    pub const fn new(events: SecurityEvents) -> Self {
+        Self { events }
+    }
+}
+
+#[tonic::async_trait]
+impl SecurityEventService for SecurityEventServiceImpl {
+    type SubscribeStream = Pin<Box<dyn Stream<Item = Result<SecurityEvent, Status>> + Send>>;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeSecurityEventsRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let filter = request.into_inner();
+        let mut receiver = self.events.subscribe();
+
+        let output_stream = async_stream::stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if matches(&filter, &event) => yield Ok(event),
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "Security event subscriber lagged");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        };
+
+        Ok(Response::new(Box::pin(output_stream)))
+    }
+
+    async fn report(
+        &self,
+        request: Request<ReportSecurityEventRequest>,
+    ) -> Result<Response<ReportSecurityEventResponse>, Status> {
+        let req = request.into_inner();
+
+        if !REPORTABLE_KINDS.contains(&req.kind.as_str()) {
+            return Err(invalid_field(
+                "kind",
+                format!("unknown security event kind: {}", req.kind),
+            ));
+        }
+
+        let event = self.events.publish(SecurityEvent {
+            kind: req.kind,
+            user_id: req.user_id,
+            subject: req.subject,
+            ip_address: req.ip_address,
+            details: req.details,
+            ..SecurityEvent::default()
+        });
+
+        Ok(Response::new(ReportSecurityEventResponse { id: event.id }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    fn events(max_failed_logins: u32) -> SecurityEvents {
+        SecurityEvents::new(&SecurityConfig {
+            max_failed_logins,
+            ..SecurityConfig::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_failed_logins_flag_brute_force_once() {
+        let events = events(3);
+        let mut rx = events.subscribe();
+
+        for _ in 0..4 {
+            events.login_failed("ada@example.com", Some(7), Some("10.0.0.1"));
+        }
+
+        let mut kinds = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            assert_eq!(event.subject.as_deref(), Some("ada@example.com"));
+            assert!(!event.id.is_empty());
+            kinds.push(event.kind);
+        }
+        assert_eq!(
+            kinds,
+            [
+                LOGIN_FAILED,
+                LOGIN_FAILED,
+                LOGIN_FAILED,
+                BRUTE_FORCE_SUSPECTED,
+                LOGIN_FAILED
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_failures() {
+        let events = events(2);
+        let mut rx = events.subscribe();
+
+        events.login_failed("ada@example.com", None, None);
+        events.login_succeeded("ada@example.com", None, None);
+        events.login_failed("ada@example.com", None, None);
+
+        while let Ok(event) = rx.try_recv() {
+            assert_eq!(event.kind, LOGIN_FAILED);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ip_change_is_flagged() {
+        let events = events(5);
+        let mut rx = events.subscribe();
+
+        events.login_succeeded("ada@example.com", Some(7), Some("10.0.0.1"));
+        events.login_succeeded("ada@example.com", Some(7), Some("10.0.0.1"));
+        assert!(rx.try_recv().is_err());
+
+        events.login_succeeded("ada@example.com", Some(7), Some("192.0.2.9"));
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.kind, IP_CHANGED);
+        assert_eq!(event.ip_address.as_deref(), Some("192.0.2.9"));
+        assert_eq!(event.details["previous_ip_address"], "10.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_filters_events() {
+        let events = events(5);
+        let service = SecurityEventServiceImpl::new(events.clone());
+
+        let mut stream = service
+            .subscribe(Request::new(SubscribeSecurityEventsRequest {
+                kinds: vec![SESSION_REVOKED.to_string()],
+                user_id: Some(7),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        events.login_failed("ada@example.com", Some(7), None);
+        events.session_revoked("other", Some(8));
+        events.session_revoked("mine", Some(7));
+
+        let event = stream.next().await.unwrap().unwrap();
+        assert_eq!(event.kind, SESSION_REVOKED);
+        assert_eq!(event.session_id.as_deref(), Some("mine"));
+    }
+
+    #[tokio::test]
+    async fn test_report_rejects_unknown_kinds() {
+        let service = SecurityEventServiceImpl::new(events(5));
+
+        let result = service
+            .report(Request::new(ReportSecurityEventRequest {
+                kind: "coffee_spilled".to_string(),
+                ..ReportSecurityEventRequest::default()
+            }))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+
+        let response = service
+            .report(Request::new(ReportSecurityEventRequest {
+                kind: PASSWORD_CHANGED.to_string(),
+                user_id: Some(7),
+                ..ReportSecurityEventRequest::default()
+            }))
+            .await
+            .unwrap();
+        assert!(!response.into_inner().id.is_empty());
+    }
+}
@@ -1,31 +1,65 @@
 //! gRPC Session Service implementation.
 
 use crate::agents::session_manager::{
-    AddFlash, CreateSession, DeleteSession, LoadSession, TakeFlashes, UpdateSession,
+    AddFlash, CreateSession, DeleteSession, ExportSessions, ImportSession, LoadSession,
+    RefreshSession, TakeFlashes, TouchSession, UpdateSession,
 };
+use crate::config::SessionConfig;
+use crate::services::SecurityEvents;
 use crate::{FlashMessage, SessionData};
 use acton_dx_proto::auth::v1::{
     session_service_server::SessionService, AddFlashMessageRequest, AddFlashMessageResponse,
     CreateSessionRequest, CreateSessionResponse, DestroySessionRequest, DestroySessionResponse,
-    FlashMessage as ProtoFlashMessage, GetFlashMessagesRequest, GetFlashMessagesResponse,
-    Session as ProtoSession, UpdateSessionRequest, UpdateSessionResponse, ValidateSessionRequest,
-    ValidateSessionResponse,
+    ExportSessionsRequest, FlashMessage as ProtoFlashMessage, GetFlashMessagesRequest,
+    GetFlashMessagesResponse, ImportSessionsRequest, ImportSessionsResponse,
+    RefreshSessionRequest, RefreshSessionResponse, Session as ProtoSession, SessionRecord,
+    UpdateSessionRequest, UpdateSessionResponse, ValidateSessionRequest, ValidateSessionResponse,
 };
 use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::pin::Pin;
 use std::time::Duration;
-use tonic::{Request, Response, Status};
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status, Streaming};
 
 /// gRPC Session Service implementation.
 #[derive(Debug, Clone)]
 pub struct SessionServiceImpl {
     session_agent: ActorHandle,
+    events: Option<SecurityEvents>,
+    default_ttl_seconds: u64,
+    idle_timeout_seconds: Option<u64>,
+    absolute_timeout_seconds: Option<u64>,
 }
 
 impl SessionServiceImpl {
     /// Create a new session service implementation.
     #[must_use]
     pub const fn new(session_agent: ActorHandle) -> Self {
-        Self { session_agent }
+        Self {
+            session_agent,
+            events: None,
+            default_ttl_seconds: 3600,
+            idle_timeout_seconds: None,
+            absolute_timeout_seconds: None,
+        }
+    }
+
+    /// Apply the configured default TTL and idle and absolute timeouts.
+    #[must_use]
+    pub const fn with_expiration(mut self, config: &SessionConfig) -> Self {
+        self.default_ttl_seconds = config.default_ttl_seconds;
+        self.idle_timeout_seconds = config.idle_timeout_seconds;
+        self.absolute_timeout_seconds = Some(config.absolute_timeout_seconds);
+        self
+    }
+
+    /// Publish a security event whenever a session is destroyed.
+    #[must_use]
+    pub fn with_events(mut self, events: SecurityEvents) -> Self {
+        self.events = Some(events);
+        self
     }
 }
 
@@ -39,6 +73,7 @@ fn session_data_to_proto(session: &SessionData) -> ProtoSession {
         csrf_token: session.csrf_token.clone(),
         created_at: session.created_at.timestamp(),
         expires_at: session.expires_at.timestamp(),
+        absolute_expires_at: session.absolute_expires_at.timestamp(),
     }
 }
 
@@ -49,16 +84,90 @@ fn flash_to_proto(flash: &FlashMessage) -> ProtoFlashMessage {
     }
 }
 
+/// A new refresh token secret and its hash.
+///
+/// Refresh tokens are `{session_id}.{secret}`; only the secret's hash is
+/// stored with the session.
+fn new_refresh_secret() -> (String, String) {
+    let secret = crate::random_token();
+    let hash = hash_refresh_secret(&secret);
+    (secret, hash)
+}
+
+fn hash_refresh_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+#[allow(clippy::result_large_err)]
+fn timestamp(seconds: i64, field: &str) -> Result<DateTime<Utc>, Status> {
+    DateTime::from_timestamp(seconds, 0)
+        .ok_or_else(|| Status::invalid_argument(format!("{field} is out of range")))
+}
+
+/// Convert an imported record, keeping its session ID.
+#[allow(clippy::result_large_err)]
+fn session_data_from_record(record: SessionRecord) -> Result<SessionData, Status> {
+    let session = record
+        .session
+        .ok_or_else(|| Status::invalid_argument("session is required"))?;
+    if session.session_id.is_empty() {
+        return Err(Status::invalid_argument("session_id is required"));
+    }
+
+    let expires_at = timestamp(session.expires_at, "expires_at")?;
+    Ok(SessionData {
+        created_at: timestamp(session.created_at, "created_at")?,
+        expires_at,
+        absolute_expires_at: if session.absolute_expires_at == 0 {
+            expires_at
+        } else {
+            timestamp(session.absolute_expires_at, "absolute_expires_at")?
+        },
+        refresh_token_hash: None,
+        session_id: session.session_id,
+        user_id: session.user_id,
+        user_email: session.user_email,
+        user_name: session.user_name,
+        data: session.data,
+        flash_messages: record
+            .flash_messages
+            .into_iter()
+            .map(|flash| FlashMessage {
+                level: flash.level,
+                message: flash.message,
+            })
+            .collect(),
+        csrf_token: if session.csrf_token.is_empty() {
+            SessionData::generate_csrf_token()
+        } else {
+            session.csrf_token
+        },
+    })
+}
+
 #[tonic::async_trait]
 impl SessionService for SessionServiceImpl {
+    type ExportSessionsStream = Pin<Box<dyn Stream<Item = Result<SessionRecord, Status>> + Send>>;
+
     async fn create_session(
         &self,
         request: Request<CreateSessionRequest>,
     ) -> Result<Response<CreateSessionResponse>, Status> {
         let req = request.into_inner();
-        let ttl_seconds = u64::try_from(req.ttl_seconds).unwrap_or(3600);
+        let ttl_seconds = u64::try_from(req.ttl_seconds).unwrap_or(self.default_ttl_seconds);
 
         let (msg, rx) = CreateSession::with_response(req.user_id, ttl_seconds);
+        let mut msg = msg.with_initial_data(req.initial_data);
+        if let Some(absolute_timeout_seconds) = self.absolute_timeout_seconds {
+            msg = msg.with_absolute_ttl(absolute_timeout_seconds);
+        }
+        let refresh_secret = if req.issue_refresh_token {
+            let (secret, hash) = new_refresh_secret();
+            msg = msg.with_refresh_token_hash(hash);
+            Some(secret)
+        } else {
+            None
+        };
         self.session_agent.send(msg).await;
 
         let session = tokio::time::timeout(Duration::from_secs(5), rx)
@@ -68,6 +177,7 @@ impl SessionService for SessionServiceImpl {
 
         Ok(Response::new(CreateSessionResponse {
             session: Some(session_data_to_proto(&session)),
+            refresh_token: refresh_secret.map(|secret| format!("{}.{secret}", session.session_id)),
         }))
     }
 
@@ -77,8 +187,16 @@ impl SessionService for SessionServiceImpl {
     ) -> Result<Response<ValidateSessionResponse>, Status> {
         let req = request.into_inner();
 
-        let (msg, rx) = LoadSession::with_response(req.session_id);
-        self.session_agent.send(msg).await;
+        // With idle timeouts every validation counts as a use
+        let rx = if let Some(idle_timeout_seconds) = self.idle_timeout_seconds {
+            let (msg, rx) = TouchSession::with_response(req.session_id, idle_timeout_seconds);
+            self.session_agent.send(msg).await;
+            rx
+        } else {
+            let (msg, rx) = LoadSession::with_response(req.session_id);
+            self.session_agent.send(msg).await;
+            rx
+        };
 
         let session = tokio::time::timeout(Duration::from_secs(5), rx)
             .await
@@ -97,6 +215,41 @@ impl SessionService for SessionServiceImpl {
         }
     }
 
+    async fn refresh_session(
+        &self,
+        request: Request<RefreshSessionRequest>,
+    ) -> Result<Response<RefreshSessionResponse>, Status> {
+        let req = request.into_inner();
+        let (session_id, secret) = req
+            .refresh_token
+            .split_once('.')
+            .ok_or_else(|| Status::unauthenticated("Invalid refresh token"))?;
+        let ttl_seconds = match u64::try_from(req.ttl_seconds) {
+            Ok(0) | Err(_) => self.default_ttl_seconds,
+            Ok(ttl_seconds) => ttl_seconds,
+        };
+
+        let (new_secret, new_hash) = new_refresh_secret();
+        let (msg, rx) = RefreshSession::with_response(
+            session_id.to_string(),
+            hash_refresh_secret(secret),
+            new_hash,
+            ttl_seconds,
+        );
+        self.session_agent.send(msg).await;
+
+        let session = tokio::time::timeout(Duration::from_secs(5), rx)
+            .await
+            .map_err(|_| Status::deadline_exceeded("Session refresh timed out"))?
+            .map_err(|_| Status::internal("Session agent channel closed"))?
+            .ok_or_else(|| Status::unauthenticated("Invalid refresh token"))?;
+
+        Ok(Response::new(RefreshSessionResponse {
+            refresh_token: format!("{}.{new_secret}", session.session_id),
+            session: Some(session_data_to_proto(&session)),
+        }))
+    }
+
     async fn update_session(
         &self,
         request: Request<UpdateSessionRequest>,
@@ -111,20 +264,10 @@ impl SessionService for SessionServiceImpl {
             .map_err(|_| Status::deadline_exceeded("Session update timed out"))?
             .map_err(|_| Status::internal("Session agent channel closed"))?;
 
-        session.map_or_else(
-            || {
-                Ok(Response::new(UpdateSessionResponse {
-                    success: false,
-                    session: None,
-                }))
-            },
-            |s| {
-                Ok(Response::new(UpdateSessionResponse {
-                    success: true,
-                    session: Some(session_data_to_proto(&s)),
-                }))
-            },
-        )
+        Ok(Response::new(UpdateSessionResponse {
+            success: session.is_some(),
+            session: session.as_ref().map(session_data_to_proto),
+        }))
     }
 
     async fn destroy_session(
@@ -133,7 +276,20 @@ impl SessionService for SessionServiceImpl {
     ) -> Result<Response<DestroySessionResponse>, Status> {
         let req = request.into_inner();
 
-        let (msg, rx) = DeleteSession::with_response(req.session_id);
+        // Look up the owner first so the revocation event names the user
+        let user_id = if self.events.is_some() {
+            let (msg, rx) = LoadSession::with_response(req.session_id.clone());
+            self.session_agent.send(msg).await;
+            tokio::time::timeout(Duration::from_secs(5), rx)
+                .await
+                .map_err(|_| Status::deadline_exceeded("Session destruction timed out"))?
+                .map_err(|_| Status::internal("Session agent channel closed"))?
+                .and_then(|session| session.user_id)
+        } else {
+            None
+        };
+
+        let (msg, rx) = DeleteSession::with_response(req.session_id.clone());
         self.session_agent.send(msg).await;
 
         let deleted = tokio::time::timeout(Duration::from_secs(5), rx)
@@ -141,6 +297,10 @@ impl SessionService for SessionServiceImpl {
             .map_err(|_| Status::deadline_exceeded("Session destruction timed out"))?
             .map_err(|_| Status::internal("Session agent channel closed"))?;
 
+        if let (true, Some(events)) = (deleted, &self.events) {
+            events.session_revoked(&req.session_id, user_id);
+        }
+
         Ok(Response::new(DestroySessionResponse { success: deleted }))
     }
 
@@ -188,4 +348,66 @@ impl SessionService for SessionServiceImpl {
 
         Ok(Response::new(GetFlashMessagesResponse { messages }))
     }
+
+    #[allow(clippy::result_large_err)]
+    async fn export_sessions(
+        &self,
+        _request: Request<ExportSessionsRequest>,
+    ) -> Result<Response<Self::ExportSessionsStream>, Status> {
+        let (msg, rx) = ExportSessions::with_response();
+        self.session_agent.send(msg).await;
+
+        let sessions = tokio::time::timeout(Duration::from_secs(5), rx)
+            .await
+            .map_err(|_| Status::deadline_exceeded("Session export timed out"))?
+            .map_err(|_| Status::internal("Session agent channel closed"))?;
+
+        let records: Vec<_> = sessions
+            .iter()
+            .map(|session| {
+                Ok::<_, Status>(SessionRecord {
+                    session: Some(session_data_to_proto(session)),
+                    flash_messages: session.flash_messages.iter().map(flash_to_proto).collect(),
+                })
+            })
+            .collect();
+
+        Ok(Response::new(Box::pin(tokio_stream::iter(records))))
+    }
+
+    async fn import_sessions(
+        &self,
+        request: Request<Streaming<ImportSessionsRequest>>,
+    ) -> Result<Response<ImportSessionsResponse>, Status> {
+        let mut stream = request.into_inner();
+        let mut response = ImportSessionsResponse::default();
+
+        while let Some(req) = stream.message().await? {
+            let record = req
+                .record
+                .ok_or_else(|| Status::invalid_argument("record is required"))?;
+            let session = session_data_from_record(record)?;
+
+            let (msg, rx) = ImportSession::with_response(session, req.replace_existing);
+            self.session_agent.send(msg).await;
+
+            let imported = tokio::time::timeout(Duration::from_secs(5), rx)
+                .await
+                .map_err(|_| Status::deadline_exceeded("Session import timed out"))?
+                .map_err(|_| Status::internal("Session agent channel closed"))?;
+
+            if imported {
+                response.imported += 1;
+            } else {
+                response.skipped += 1;
+            }
+        }
+
+        tracing::info!(
+            imported = response.imported,
+            skipped = response.skipped,
+            "Imported sessions"
+        );
+        Ok(Response::new(response))
+    }
 }
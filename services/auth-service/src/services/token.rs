@@ -0,0 +1,917 @@
+//! JWT access tokens for first-party API clients.
+//!
+//! Tokens are minted from a signed-in session, so SPAs and mobile apps of the
+//! same product can call the API with `Authorization: Bearer` instead of a
+//! session cookie. They are `EdDSA` (Ed25519) JWTs; other services verify them
+//! with the keys from the JWKS.
+//!
+//! Signing keys rotate every `key_rotation_seconds` and stay in the JWKS
+//! until the last token they signed has expired. With a `key_dir` the key
+//! ring is kept in `keys.json` in that directory: it is loaded at startup,
+//! and rotation happens under a lock on `keys.lock`, so replicas sharing the
+//! directory rotate once and verify each other's tokens. Without one, keys
+//! only live in memory and restarting the service invalidates outstanding
+//! tokens.
+
+use crate::agents::session_manager::LoadSession;
+use crate::config::TokenConfig;
+use crate::seconds;
+use acton_dx_proto::auth::v1::{
+    token_service_server::TokenService, AccessTokenClaims, GetJwksRequest, GetJwksResponse,
+    IssueAccessTokenRequest, IssueAccessTokenResponse, VerifyAccessTokenRequest,
+    VerifyAccessTokenResponse,
+};
+use acton_reactive::prelude::{ActorHandle, ActorHandleInterface};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, TimeDelta, Utc};
+use ed25519_dalek::SigningKey;
+use jsonwebtoken::{errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use tonic::{Request, Response, Status};
+
+/// Session data key naming the tenant the signed-in user acts for.
+pub const TENANT_SESSION_KEY: &str = "tenant_id";
+
+/// Key ring file in the key directory.
+const KEYS_FILE: &str = "keys.json";
+
+/// File locked while the key ring is rotated.
+const LOCK_FILE: &str = "keys.lock";
+
+/// PKCS#8 v1 encoding of an Ed25519 private key (RFC 8410), up to the seed.
+const PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+/// Why a token could not be issued or verified.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TokenError {
+    /// The requested audience is not configured.
+    #[error("audience {0:?} is not configured")]
+    UnknownAudience(String),
+    /// The requested scope is not configured.
+    #[error("scope {0:?} is not allowed")]
+    ScopeNotAllowed(String),
+    /// The token is not a JWT issued by this service.
+    #[error("malformed token")]
+    Malformed,
+    /// The signing key is unknown or was retired.
+    #[error("token signed by an unknown key")]
+    UnknownKey,
+    /// The signature does not match.
+    #[error("invalid signature")]
+    InvalidSignature,
+    /// The token has expired.
+    #[error("token expired")]
+    Expired,
+    /// The token was issued by someone else.
+    #[error("token issued by {0:?}")]
+    WrongIssuer(String),
+    /// The token was issued for another audience.
+    #[error("token issued for {0:?}")]
+    WrongAudience(String),
+}
+
+/// Claims of an access token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    /// Issuer.
+    pub iss: String,
+    /// User ID.
+    pub sub: String,
+    /// Audience.
+    pub aud: String,
+    /// Granted scopes, space separated.
+    pub scope: String,
+    /// Session the token was minted from.
+    pub sid: String,
+    /// Token ID.
+    pub jti: String,
+    /// Issue time (Unix seconds).
+    pub iat: i64,
+    /// Expiry (Unix seconds).
+    pub exp: i64,
+    /// Tenant the user acts for, if the session names one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tid: Option<String>,
+}
+
+impl Claims {
+    /// User the token was issued to.
+    #[must_use]
+    pub fn user_id(&self) -> Option<i64> {
+        self.sub.parse().ok()
+    }
+
+    /// Granted scopes.
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scope.split_whitespace()
+    }
+}
+
+struct Key {
+    kid: String,
+    signing: SigningKey,
+    created_at: DateTime<Utc>,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl Key {
+    fn new(kid: String, secret: [u8; 32], created_at: DateTime<Utc>) -> Self {
+        let signing = SigningKey::from_bytes(&secret);
+        let mut pkcs8 = PKCS8_PREFIX.to_vec();
+        pkcs8.extend_from_slice(&secret);
+        Self {
+            kid,
+            encoding: EncodingKey::from_ed_der(&pkcs8),
+            decoding: DecodingKey::from_ed_der(signing.verifying_key().as_bytes()),
+            signing,
+            created_at,
+        }
+    }
+
+    fn generate(created_at: DateTime<Utc>) -> Self {
+        let mut secret = [0u8; 32];
+        rand::rng().fill(&mut secret);
+        Self::new(
+            uuid::Uuid::new_v4().simple().to_string(),
+            secret,
+            created_at,
+        )
+    }
+}
+
+/// A key as stored in the key directory.
+#[derive(Serialize, Deserialize)]
+struct StoredKey {
+    kid: String,
+    /// Unix seconds.
+    created_at: i64,
+    /// Ed25519 seed, base64url.
+    secret: String,
+}
+
+impl From<&Key> for StoredKey {
+    fn from(key: &Key) -> Self {
+        Self {
+            kid: key.kid.clone(),
+            created_at: key.created_at.timestamp(),
+            secret: URL_SAFE_NO_PAD.encode(key.signing.to_bytes()),
+        }
+    }
+}
+
+impl TryFrom<StoredKey> for Key {
+    type Error = io::Error;
+
+    fn try_from(stored: StoredKey) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid signing key");
+        let secret = URL_SAFE_NO_PAD
+            .decode(&stored.secret)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(invalid)?;
+        let created_at = DateTime::from_timestamp(stored.created_at, 0).ok_or_else(invalid)?;
+        Ok(Self::new(stored.kid, secret, created_at))
+    }
+}
+
+/// Directory holding the key ring.
+#[derive(Debug)]
+struct KeyDir {
+    path: PathBuf,
+}
+
+impl KeyDir {
+    /// Keys in the directory, oldest first.
+    fn load(&self) -> io::Result<Vec<Key>> {
+        let json = match fs::read(self.path.join(KEYS_FILE)) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let stored: Vec<StoredKey> = serde_json::from_slice(&json)?;
+        stored.into_iter().map(Key::try_from).collect()
+    }
+
+    /// Replace the key ring. The file is swapped in whole, so readers never
+    /// see a partial write.
+    fn save(&self, keys: &[Key]) -> io::Result<()> {
+        let stored: Vec<StoredKey> = keys.iter().map(StoredKey::from).collect();
+        let tmp = self.path.join(format!("{KEYS_FILE}.tmp"));
+        write_private(&tmp, &serde_json::to_vec_pretty(&stored)?)?;
+        fs::rename(tmp, self.path.join(KEYS_FILE))
+    }
+
+    /// Rotate the key ring if it is due, holding the directory lock so only
+    /// one replica creates the next key.
+    fn rotate(
+        &self,
+        now: DateTime<Utc>,
+        rotation: TimeDelta,
+        ttl: TimeDelta,
+    ) -> io::Result<Vec<Key>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.join(LOCK_FILE))?;
+        let mut lock = fd_lock::RwLock::new(file);
+        let _guard = lock.write()?;
+
+        // Another replica may have rotated since we last looked
+        let mut keys = self.load()?;
+        if rotation_due(&keys, now, rotation) {
+            keys.push(Key::generate(now));
+            retire(&mut keys, now, ttl);
+            self.save(&keys)?;
+            tracing::info!("Rotated access token signing key");
+        }
+        Ok(keys)
+    }
+}
+
+/// Write a file only the service's user can read.
+fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.create(true).truncate(true).write(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    io::Write::write_all(&mut options.open(path)?, contents)
+}
+
+/// Mints and verifies access tokens with rotating signing keys.
+#[derive(Clone)]
+pub struct TokenIssuer {
+    config: Arc<TokenConfig>,
+    keys: Arc<RwLock<Vec<Key>>>,
+    dir: Option<Arc<KeyDir>>,
+}
+
+impl std::fmt::Debug for TokenIssuer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenIssuer")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TokenIssuer {
+    /// Create an issuer, loading the key ring from the key directory if one
+    /// is configured and creating a signing key if none is current.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key directory cannot be created, read or
+    /// written, or holds an invalid key ring.
+    pub fn new(config: &TokenConfig) -> io::Result<Self> {
+        let dir = match &config.key_dir {
+            Some(path) => {
+                fs::create_dir_all(path)?;
+                Some(Arc::new(KeyDir { path: path.clone() }))
+            }
+            None => None,
+        };
+        let issuer = Self {
+            config: Arc::new(config.clone()),
+            keys: Arc::new(RwLock::new(Vec::new())),
+            dir,
+        };
+        issuer.try_rotate(Utc::now())?;
+        Ok(issuer)
+    }
+
+    /// Mint a token for a signed-in session.
+    ///
+    /// Tokens never outlive the session they were minted from. Without an
+    /// audience the first configured one is used; without scopes the
+    /// default scopes are granted. The tenant the session names under
+    /// [`TENANT_SESSION_KEY`] is carried in the `tid` claim.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the audience or a scope is not configured.
+    pub fn issue(
+        &self,
+        user_id: i64,
+        tenant: Option<&str>,
+        session_id: &str,
+        session_expires_at: DateTime<Utc>,
+        audience: Option<&str>,
+        scopes: &[String],
+    ) -> Result<(String, Claims), TokenError> {
+        self.issue_at(
+            user_id,
+            tenant,
+            session_id,
+            session_expires_at,
+            audience,
+            scopes,
+            Utc::now(),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn issue_at(
+        &self,
+        user_id: i64,
+        tenant: Option<&str>,
+        session_id: &str,
+        session_expires_at: DateTime<Utc>,
+        audience: Option<&str>,
+        scopes: &[String],
+        now: DateTime<Utc>,
+    ) -> Result<(String, Claims), TokenError> {
+        let audience = match audience {
+            Some(audience) if self.config.audiences.iter().any(|a| a == audience) => audience,
+            Some(audience) => return Err(TokenError::UnknownAudience(audience.to_string())),
+            None => self
+                .config
+                .audiences
+                .first()
+                .ok_or_else(|| TokenError::UnknownAudience(String::new()))?,
+        };
+
+        let requested = if scopes.is_empty() {
+            &self.config.default_scopes
+        } else {
+            scopes
+        };
+        let mut granted: Vec<&str> = Vec::new();
+        for scope in requested {
+            if !self.config.scopes.contains(scope) {
+                return Err(TokenError::ScopeNotAllowed(scope.clone()));
+            }
+            if !granted.contains(&scope.as_str()) {
+                granted.push(scope);
+            }
+        }
+
+        let expires_at = (now + seconds(self.config.ttl_seconds)).min(session_expires_at);
+        let claims = Claims {
+            iss: self.config.issuer.clone(),
+            sub: user_id.to_string(),
+            aud: audience.to_string(),
+            scope: granted.join(" "),
+            sid: session_id.to_string(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            iat: now.timestamp(),
+            exp: expires_at.timestamp(),
+            tid: tenant.map(str::to_string),
+        };
+
+        let token = self.sign(&claims, now)?;
+        Ok((token, claims))
+    }
+
+    /// Verify a token's signature, issuer, expiry and audience.
+    ///
+    /// Without an audience, any configured audience is accepted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the token is not valid.
+    pub fn verify(&self, token: &str, audience: Option<&str>) -> Result<Claims, TokenError> {
+        self.verify_at(token, audience, Utc::now())
+    }
+
+    fn verify_at(
+        &self,
+        token: &str,
+        audience: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<Claims, TokenError> {
+        let claims: Claims = self.decode_verified(token)?;
+        if claims.user_id().is_none() {
+            return Err(TokenError::Malformed);
+        }
+        if claims.iss != self.config.issuer {
+            return Err(TokenError::WrongIssuer(claims.iss));
+        }
+        if claims.exp <= now.timestamp() {
+            return Err(TokenError::Expired);
+        }
+        let audience_ok = audience.map_or_else(
+            || self.config.audiences.contains(&claims.aud),
+            |audience| claims.aud == audience,
+        );
+        if !audience_ok {
+            return Err(TokenError::WrongAudience(claims.aud));
+        }
+        Ok(claims)
+    }
+
+    /// Sign `claims` with the current key, rotating first if it is due.
+    pub(crate) fn sign<C: Serialize>(
+        &self,
+        claims: &C,
+        now: DateTime<Utc>,
+    ) -> Result<String, TokenError> {
+        self.rotate(now);
+        let keys = self.keys.read().unwrap_or_else(PoisonError::into_inner);
+        let key = keys.last().ok_or(TokenError::UnknownKey)?;
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(key.kid.clone());
+        let token = jsonwebtoken::encode(&header, claims, &key.encoding);
+        drop(keys);
+        token.map_err(|_| TokenError::UnknownKey)
+    }
+
+    /// Claims of a token signed by one of our keys.
+    ///
+    /// Only the signature is checked; issuer, expiry and audience are up to
+    /// the caller.
+    pub(crate) fn decode_verified<C: DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<C, TokenError> {
+        let kid = jsonwebtoken::decode_header(token)
+            .ok()
+            .filter(|header| header.alg == Algorithm::EdDSA)
+            .and_then(|header| header.kid)
+            .ok_or(TokenError::Malformed)?;
+
+        let mut decoding = self.decoding_key(&kid);
+        if decoding.is_none() && self.dir.is_some() {
+            // Another replica may have rotated to a key we have not loaded
+            self.reload();
+            decoding = self.decoding_key(&kid);
+        }
+        let decoding = decoding.ok_or(TokenError::UnknownKey)?;
+
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+        jsonwebtoken::decode(token, &decoding, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| match e.kind() {
+                ErrorKind::InvalidSignature => TokenError::InvalidSignature,
+                _ => TokenError::Malformed,
+            })
+    }
+
+    fn decoding_key(&self, kid: &str) -> Option<DecodingKey> {
+        self.keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .find(|key| key.kid == kid)
+            .map(|key| key.decoding.clone())
+    }
+
+    /// Lifetime of issued tokens.
+    pub(crate) fn ttl(&self) -> TimeDelta {
+        seconds(self.config.ttl_seconds)
+    }
+
+    /// JSON Web Key Set of every key that may have signed a live token.
+    #[must_use]
+    pub fn jwks(&self) -> serde_json::Value {
+        let keys: Vec<_> = self
+            .keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|key| {
+                serde_json::json!({
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "alg": "EdDSA",
+                    "use": "sig",
+                    "kid": key.kid,
+                    "x": URL_SAFE_NO_PAD.encode(key.signing.verifying_key().as_bytes()),
+                })
+            })
+            .collect();
+        serde_json::json!({ "keys": keys })
+    }
+
+    /// Start a new signing key when the current one is due, and drop keys
+    /// whose tokens have all expired.
+    ///
+    /// If the key directory cannot be rotated, the current key keeps
+    /// signing and rotation is tried again on the next token.
+    fn rotate(&self, now: DateTime<Utc>) {
+        if let Err(e) = self.try_rotate(now) {
+            tracing::warn!(error = %e, "Failed to rotate access token signing key");
+        }
+    }
+
+    fn try_rotate(&self, now: DateTime<Utc>) -> io::Result<()> {
+        let rotation = seconds(self.config.key_rotation_seconds);
+        let ttl = seconds(self.config.ttl_seconds);
+        let mut keys = self.keys.write().unwrap_or_else(PoisonError::into_inner);
+
+        if rotation_due(&keys, now, rotation) {
+            if let Some(dir) = &self.dir {
+                *keys = dir.rotate(now, rotation, ttl)?;
+            } else {
+                keys.push(Key::generate(now));
+                tracing::info!("Rotated access token signing key");
+            }
+        }
+        retire(&mut keys, now, ttl);
+        drop(keys);
+        Ok(())
+    }
+
+    /// Load the key ring from the key directory again.
+    fn reload(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        match dir.load() {
+            Ok(mut loaded) => {
+                retire(&mut loaded, Utc::now(), self.ttl());
+                *self.keys.write().unwrap_or_else(PoisonError::into_inner) = loaded;
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to reload access token signing keys"),
+        }
+    }
+}
+
+/// Whether the newest key is due for rotation.
+fn rotation_due(keys: &[Key], now: DateTime<Utc>, rotation: TimeDelta) -> bool {
+    keys.last()
+        .is_none_or(|key| now - key.created_at >= rotation)
+}
+
+/// Drop keys whose tokens have all expired.
+fn retire(keys: &mut Vec<Key>, now: DateTime<Utc>, ttl: TimeDelta) {
+    // A key stops signing when its successor is created
+    let retired_at: Vec<_> = keys.iter().skip(1).map(|key| key.created_at).collect();
+    let mut index = 0;
+    keys.retain(|_| {
+        let keep = retired_at.get(index).is_none_or(|at| now < *at + ttl);
+        index += 1;
+        keep
+    });
+}
+
+/// gRPC Token Service implementation.
+#[derive(Debug, Clone)]
+pub struct TokenServiceImpl {
+    session_agent: ActorHandle,
+    issuer: TokenIssuer,
+}
+
+impl TokenServiceImpl {
+    /// Create a new token service implementation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signing keys cannot be loaded.
+    pub fn new(session_agent: ActorHandle, config: &TokenConfig) -> io::Result<Self> {
+        Ok(Self::with_issuer(session_agent, TokenIssuer::new(config)?))
+    }
+
+    /// Create a token service around an existing issuer, so tokens signed
+    /// elsewhere (e.g. OIDC ID tokens) are covered by its JWKS.
+    #[must_use]
+    pub const fn with_issuer(session_agent: ActorHandle, issuer: TokenIssuer) -> Self {
+        Self {
+            session_agent,
+            issuer,
+        }
+    }
+}
+
+fn claims_to_proto(claims: &Claims) -> AccessTokenClaims {
+    AccessTokenClaims {
+        user_id: claims.user_id().unwrap_or_default(),
+        audience: claims.aud.clone(),
+        scopes: claims.scopes().map(str::to_string).collect(),
+        session_id: claims.sid.clone(),
+        token_id: claims.jti.clone(),
+        issued_at: claims.iat,
+        expires_at: claims.exp,
+        tenant_id: claims.tid.clone(),
+    }
+}
+
+#[tonic::async_trait]
+impl TokenService for TokenServiceImpl {
+    async fn issue_access_token(
+        &self,
+        request: Request<IssueAccessTokenRequest>,
+    ) -> Result<Response<IssueAccessTokenResponse>, Status> {
+        let req = request.into_inner();
+
+        let (msg, rx) = LoadSession::with_response(req.session_id.clone());
+        self.session_agent.send(msg).await;
+
+        let session = tokio::time::timeout(Duration::from_secs(5), rx)
+            .await
+            .map_err(|_| Status::deadline_exceeded("Session lookup timed out"))?
+            .map_err(|_| Status::internal("Session agent channel closed"))?
+            .filter(|session| !session.is_expired())
+            .ok_or_else(|| Status::unauthenticated("Session is invalid or expired"))?;
+        let user_id = session
+            .user_id
+            .ok_or_else(|| Status::unauthenticated("Session is not signed in"))?;
+
+        let (access_token, claims) = self
+            .issuer
+            .issue(
+                user_id,
+                session.data.get(TENANT_SESSION_KEY).map(String::as_str),
+                &session.session_id,
+                session.expires_at,
+                req.audience.as_deref(),
+                &req.scopes,
+            )
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        tracing::info!(
+            user_id,
+            audience = %claims.aud,
+            scope = %claims.scope,
+            token_id = %claims.jti,
+            "Access token issued"
+        );
+
+        Ok(Response::new(IssueAccessTokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: claims.exp - claims.iat,
+            scope: claims.scope,
+        }))
+    }
+
+    async fn verify_access_token(
+        &self,
+        request: Request<VerifyAccessTokenRequest>,
+    ) -> Result<Response<VerifyAccessTokenResponse>, Status> {
+        let req = request.into_inner();
+
+        match self
+            .issuer
+            .verify(&req.access_token, req.audience.as_deref())
+        {
+            Ok(claims) => Ok(Response::new(VerifyAccessTokenResponse {
+                valid: true,
+                claims: Some(claims_to_proto(&claims)),
+            })),
+            Err(e) => {
+                tracing::debug!(error = %e, "Access token rejected");
+                Ok(Response::new(VerifyAccessTokenResponse {
+                    valid: false,
+                    claims: None,
+                }))
+            }
+        }
+    }
+
+    async fn get_jwks(
+        &self,
+        _request: Request<GetJwksRequest>,
+    ) -> Result<Response<GetJwksResponse>, Status> {
+        Ok(Response::new(GetJwksResponse {
+            jwks_json: self.issuer.jwks().to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issuer() -> TokenIssuer {
+        TokenIssuer::new(&TokenConfig {
+            audiences: vec!["api".to_string(), "mobile".to_string()],
+            ..TokenConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn far_future() -> DateTime<Utc> {
+        Utc::now() + TimeDelta::days(1)
+    }
+
+    #[test]
+    fn test_issue_and_verify() {
+        let issuer = issuer();
+        let (token, claims) = issuer
+            .issue(42, None, "sess-1", far_future(), None, &[])
+            .unwrap();
+
+        assert_eq!(claims.aud, "api");
+        assert_eq!(claims.scope, "read");
+        assert_eq!(claims.exp - claims.iat, 900);
+
+        let verified = issuer.verify(&token, Some("api")).unwrap();
+        assert_eq!(verified, claims);
+        assert_eq!(verified.user_id(), Some(42));
+        assert_eq!(claims_to_proto(&verified).tenant_id, None);
+        assert_eq!(
+            issuer.verify(&token, Some("mobile")),
+            Err(TokenError::WrongAudience("api".to_string()))
+        );
+
+        let (token, _) = issuer
+            .issue(42, Some("acme"), "sess-1", far_future(), None, &[])
+            .unwrap();
+        let verified = issuer.verify(&token, None).unwrap();
+        assert_eq!(verified.tid.as_deref(), Some("acme"));
+        assert_eq!(
+            claims_to_proto(&verified).tenant_id.as_deref(),
+            Some("acme")
+        );
+    }
+
+    #[test]
+    fn test_audience_and_scopes_must_be_configured() {
+        let issuer = issuer();
+        let scopes = ["write".to_string(), "read".to_string(), "write".to_string()];
+        let (_, claims) = issuer
+            .issue(1, None, "s", far_future(), Some("mobile"), &scopes)
+            .unwrap();
+        assert_eq!(claims.aud, "mobile");
+        assert_eq!(claims.scope, "write read");
+
+        assert_eq!(
+            issuer
+                .issue(1, None, "s", far_future(), Some("admin"), &[])
+                .map(|_| ()),
+            Err(TokenError::UnknownAudience("admin".to_string()))
+        );
+        assert_eq!(
+            issuer
+                .issue(1, None, "s", far_future(), None, &["delete".to_string()])
+                .map(|_| ()),
+            Err(TokenError::ScopeNotAllowed("delete".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_rejects_tampered_and_foreign_tokens() {
+        let issuer = issuer();
+        let (token, _) = issuer.issue(1, None, "s", far_future(), None, &[]).unwrap();
+
+        let mut parts: Vec<&str> = token.split('.').collect();
+        let forged = URL_SAFE_NO_PAD.encode(
+            serde_json::to_vec(&Claims {
+                sub: "2".to_string(),
+                ..issuer.verify(&token, None).unwrap()
+            })
+            .unwrap(),
+        );
+        parts[1] = &forged;
+        assert_eq!(
+            issuer.verify(&parts.join("."), None),
+            Err(TokenError::InvalidSignature)
+        );
+
+        let (other, _) = self::issuer()
+            .issue(1, None, "s", far_future(), None, &[])
+            .unwrap();
+        assert_eq!(issuer.verify(&other, None), Err(TokenError::UnknownKey));
+        assert_eq!(
+            issuer.verify("not.a.jwt.at.all", None),
+            Err(TokenError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_token_expiry_is_capped_by_session() {
+        let issuer = issuer();
+        let now = Utc::now();
+        let session_expires_at = now + TimeDelta::seconds(60);
+        let (token, claims) = issuer
+            .issue_at(1, None, "s", session_expires_at, None, &[], now)
+            .unwrap();
+
+        assert_eq!(claims.exp, session_expires_at.timestamp());
+        assert!(issuer.verify_at(&token, None, now).is_ok());
+        assert_eq!(
+            issuer.verify_at(&token, None, now + TimeDelta::seconds(61)),
+            Err(TokenError::Expired)
+        );
+    }
+
+    #[test]
+    fn test_keys_rotate_and_retire() {
+        let issuer = issuer();
+        let now = Utc::now();
+        let (old_token, _) = issuer
+            .issue_at(1, None, "s", far_future(), None, &[], now)
+            .unwrap();
+
+        // Rotation publishes a second key and keeps the old one for its tokens
+        let later = now + TimeDelta::days(1);
+        let (new_token, _) = issuer
+            .issue_at(
+                1,
+                None,
+                "s",
+                far_future() + TimeDelta::days(1),
+                None,
+                &[],
+                later,
+            )
+            .unwrap();
+        assert_eq!(issuer.jwks()["keys"].as_array().unwrap().len(), 2);
+        assert!(issuer.verify_at(&old_token, None, now).is_ok());
+        assert!(issuer.verify_at(&new_token, None, later).is_ok());
+
+        // Once every token it signed has expired, the old key is dropped
+        issuer.rotate(later + TimeDelta::seconds(901));
+        let jwks = issuer.jwks();
+        let keys = jwks["keys"].as_array().unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0]["kty"], "OKP");
+        assert_eq!(keys[0]["crv"], "Ed25519");
+        assert_eq!(keys[0]["x"].as_str().unwrap().len(), 43);
+        assert_eq!(
+            issuer.verify_at(&old_token, None, now),
+            Err(TokenError::UnknownKey)
+        );
+    }
+
+    #[test]
+    fn test_key_ring_survives_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = TokenConfig {
+            key_dir: Some(dir.path().to_path_buf()),
+            ..TokenConfig::default()
+        };
+        let issuer = TokenIssuer::new(&config).unwrap();
+        let now = Utc::now();
+        let (old_token, _) = issuer
+            .issue_at(1, None, "s", far_future(), None, &[], now)
+            .unwrap();
+        let later = now + TimeDelta::days(1);
+        let (new_token, _) = issuer
+            .issue_at(
+                1,
+                None,
+                "s",
+                far_future() + TimeDelta::days(1),
+                None,
+                &[],
+                later,
+            )
+            .unwrap();
+
+        // A restarted service loads both keys and keeps signing with the
+        // rotated one instead of starting its own
+        let reloaded = TokenIssuer::new(&config).unwrap();
+        assert_eq!(reloaded.jwks(), issuer.jwks());
+        assert!(reloaded.verify_at(&old_token, None, now).is_ok());
+        assert!(reloaded.verify_at(&new_token, None, later).is_ok());
+        let (token, _) = reloaded
+            .issue_at(
+                1,
+                None,
+                "s",
+                far_future() + TimeDelta::days(1),
+                None,
+                &[],
+                later,
+            )
+            .unwrap();
+        assert!(issuer.verify_at(&token, None, later).is_ok());
+
+        // A replica that rotates first is followed, not raced
+        let day_after = later + TimeDelta::days(1);
+        let (rotated, _) = reloaded
+            .issue_at(
+                1,
+                None,
+                "s",
+                far_future() + TimeDelta::days(2),
+                None,
+                &[],
+                day_after,
+            )
+            .unwrap();
+        assert!(issuer.verify_at(&rotated, None, day_after).is_ok());
+        issuer
+            .issue_at(
+                1,
+                None,
+                "s",
+                far_future() + TimeDelta::days(2),
+                None,
+                &[],
+                day_after,
+            )
+            .unwrap();
+        assert_eq!(issuer.jwks(), reloaded.jwks());
+        assert_eq!(issuer.jwks()["keys"].as_array().unwrap().len(), 2);
+
+        let other = TokenIssuer::new(&TokenConfig::default()).unwrap();
+        assert_eq!(
+            other.verify_at(&rotated, None, day_after),
+            Err(TokenError::UnknownKey)
+        );
+    }
+}
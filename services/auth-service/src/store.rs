@@ -0,0 +1,405 @@
+//! Persistent storage through the data service.
+//!
+//! [`DataTable`] is one table reached through the data service; the session
+//! store here and the API key, passkey and OIDC stores build on it. Queries
+//! are written to run on Postgres and on the SQLite databases the data
+//! service is tested against.
+//!
+//! The session manager serves sessions from memory. With a store configured
+//! it writes every change through to the store, loads sessions it does not
+//! hold on first use, and deletes expired sessions from the store on each
+//! cleanup, so sessions survive a restart.
+//!
+//! [`PostgresSessionStore`] keeps sessions in one table, reached through the
+//! data service. Session data and flash messages are stored as JSON text and
+//! timestamps as Unix seconds:
+//!
+//! ```sql
+//! CREATE TABLE auth_sessions (
+//!     session_id TEXT PRIMARY KEY,
+//!     user_id BIGINT,
+//!     user_email TEXT,
+//!     user_name TEXT,
+//!     data TEXT NOT NULL,
+//!     flash_messages TEXT NOT NULL,
+//!     csrf_token TEXT NOT NULL,
+//!     created_at BIGINT NOT NULL,
+//!     expires_at BIGINT NOT NULL,
+//!     absolute_expires_at BIGINT NOT NULL,
+//!     refresh_token_hash TEXT
+//! );
+//! ```
+
+use crate::{FlashMessage, SessionData};
+use acton_dx_proto::data::v1::{
+    data_service_client::DataServiceClient, value::Value as ValueInner, ExecuteRequest,
+    QueryRequest, Row, Value,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use tonic::transport::Channel;
+use tonic::Status;
+
+/// Columns of the session table, in parameter order.
+const COLUMNS: &str = "session_id, user_id, user_email, user_name, data, flash_messages, \
+                       csrf_token, created_at, expires_at, absolute_expires_at, refresh_token_hash";
+
+/// Placeholders for [`COLUMNS`]. `user_id` is cast because a null parameter
+/// is sent as text.
+const PLACEHOLDERS: &str = "$1, $2::BIGINT, $3, $4, $5, $6, $7, $8, $9, $10, $11";
+
+/// Sessions that can still be used or refreshed at `$n`.
+fn live(n: usize) -> String {
+    format!(
+        "(expires_at > ${n} OR (refresh_token_hash IS NOT NULL AND absolute_expires_at > ${n}))"
+    )
+}
+
+/// Stores sessions in a Postgres table through the data service.
+#[derive(Debug, Clone)]
+pub struct PostgresSessionStore {
+    table: DataTable,
+}
+
+impl PostgresSessionStore {
+    /// Store sessions in `table`, connecting to the data service at
+    /// `endpoint` on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoint is not a valid URI or the table name
+    /// is not a plain SQL identifier.
+    pub fn connect_lazy(endpoint: &str, table: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            table: DataTable::connect_lazy(endpoint, table)?,
+        })
+    }
+
+    /// Create the session table and its expiry index if they are missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn ensure_table(&self) -> Result<(), Status> {
+        let table = &self.table;
+        self.table
+            .execute(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {table} (\
+                 session_id TEXT PRIMARY KEY, user_id BIGINT, user_email TEXT, user_name TEXT, \
+                 data TEXT NOT NULL, flash_messages TEXT NOT NULL, csrf_token TEXT NOT NULL, \
+                 created_at BIGINT NOT NULL, expires_at BIGINT NOT NULL, \
+                 absolute_expires_at BIGINT NOT NULL, refresh_token_hash TEXT)"
+                ),
+                Vec::new(),
+            )
+            .await?;
+        self.table
+            .execute(
+                format!(
+                    "CREATE INDEX IF NOT EXISTS {table}_expires_at_idx ON {table} (expires_at)"
+                ),
+                Vec::new(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Load a session that can still be used or refreshed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn load(&self, session_id: &str) -> Result<Option<SessionData>, Status> {
+        let rows = self
+            .table
+            .query(
+                format!(
+                    "SELECT {COLUMNS} FROM {} WHERE session_id = $1 AND {}",
+                    self.table,
+                    live(2)
+                ),
+                vec![string(session_id), int(Utc::now().timestamp())],
+            )
+            .await?;
+        Ok(rows.iter().find_map(session_from_row))
+    }
+
+    /// Load every unexpired session.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn load_all(&self) -> Result<Vec<SessionData>, Status> {
+        let rows = self
+            .table
+            .query(
+                format!("SELECT {COLUMNS} FROM {} WHERE expires_at > $1", self.table),
+                vec![int(Utc::now().timestamp())],
+            )
+            .await?;
+        Ok(rows.iter().filter_map(session_from_row).collect())
+    }
+
+    /// Store a session, replacing any stored under the same ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn save(&self, session: &SessionData) -> Result<(), Status> {
+        self.table
+            .execute(
+                format!(
+                    "INSERT INTO {} ({COLUMNS}) VALUES ({PLACEHOLDERS}) \
+                 ON CONFLICT (session_id) DO UPDATE SET \
+                 user_id = EXCLUDED.user_id, user_email = EXCLUDED.user_email, \
+                 user_name = EXCLUDED.user_name, data = EXCLUDED.data, \
+                 flash_messages = EXCLUDED.flash_messages, csrf_token = EXCLUDED.csrf_token, \
+                 created_at = EXCLUDED.created_at, expires_at = EXCLUDED.expires_at, \
+                 absolute_expires_at = EXCLUDED.absolute_expires_at, \
+                 refresh_token_hash = EXCLUDED.refresh_token_hash",
+                    self.table
+                ),
+                session_params(session),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Store a session unless one is already stored under its ID.
+    ///
+    /// Returns `true` if the session was stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn insert_new(&self, session: &SessionData) -> Result<bool, Status> {
+        let rows_affected = self
+            .table
+            .execute(
+                format!(
+                    "INSERT INTO {} ({COLUMNS}) VALUES ({PLACEHOLDERS}) \
+                     ON CONFLICT (session_id) DO NOTHING",
+                    self.table
+                ),
+                session_params(session),
+            )
+            .await?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Delete a session. Returns `true` if one was stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn delete(&self, session_id: &str) -> Result<bool, Status> {
+        let rows_affected = self
+            .table
+            .execute(
+                format!("DELETE FROM {} WHERE session_id = $1", self.table),
+                vec![string(session_id)],
+            )
+            .await?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Delete every session that can no longer be used or refreshed.
+    /// Returns how many were deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the data service's error.
+    pub async fn delete_expired(&self) -> Result<i64, Status> {
+        self.table
+            .execute(
+                format!("DELETE FROM {} WHERE NOT {}", self.table, live(1)),
+                vec![int(Utc::now().timestamp())],
+            )
+            .await
+    }
+}
+
+/// One table reached through the data service.
+#[derive(Debug, Clone)]
+pub(crate) struct DataTable {
+    client: DataServiceClient<Channel>,
+    name: String,
+}
+
+impl DataTable {
+    /// Reach `table` through the data service at `endpoint`, connecting on
+    /// first use.
+    pub(crate) fn connect_lazy(endpoint: &str, table: &str) -> anyhow::Result<Self> {
+        if !is_identifier(table) {
+            anyhow::bail!("Invalid table name: {table}");
+        }
+        let channel = Channel::from_shared(endpoint.to_string())?.connect_lazy();
+        Ok(Self {
+            client: DataServiceClient::new(channel),
+            name: table.to_string(),
+        })
+    }
+
+    pub(crate) async fn query(&self, sql: String, params: Vec<Value>) -> Result<Vec<Row>, Status> {
+        let response = self
+            .client
+            .clone()
+            .query(QueryRequest {
+                sql,
+                params,
+                transaction_id: None,
+                // Rows are read straight after they are written
+                read_primary: true,
+            })
+            .await?;
+        Ok(response.into_inner().rows)
+    }
+
+    pub(crate) async fn execute(&self, sql: String, params: Vec<Value>) -> Result<i64, Status> {
+        let response = self
+            .client
+            .clone()
+            .execute(ExecuteRequest {
+                sql,
+                params,
+                transaction_id: None,
+            })
+            .await?;
+        Ok(response.into_inner().rows_affected)
+    }
+}
+
+impl std::fmt::Display for DataTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Whether `name` can be used as a table name without quoting.
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Query parameters for a session, in [`COLUMNS`] order.
+fn session_params(session: &SessionData) -> Vec<Value> {
+    let optional = |value: Option<&String>| value.map_or_else(null, |value| string(value));
+    vec![
+        string(&session.session_id),
+        session.user_id.map_or_else(null, int),
+        optional(session.user_email.as_ref()),
+        optional(session.user_name.as_ref()),
+        string(&serde_json::to_string(&session.data).unwrap_or_default()),
+        string(&serde_json::to_string(&session.flash_messages).unwrap_or_default()),
+        string(&session.csrf_token),
+        int(session.created_at.timestamp()),
+        int(session.expires_at.timestamp()),
+        int(session.absolute_expires_at.timestamp()),
+        optional(session.refresh_token_hash.as_ref()),
+    ]
+}
+
+/// Session stored in a row, if the row is complete.
+fn session_from_row(row: &Row) -> Option<SessionData> {
+    let data: HashMap<String, String> = serde_json::from_str(&text(row, "data")?).ok()?;
+    let flash_messages: Vec<FlashMessage> =
+        serde_json::from_str(&text(row, "flash_messages")?).ok()?;
+    Some(SessionData {
+        session_id: text(row, "session_id")?,
+        user_id: number(row, "user_id"),
+        user_email: text(row, "user_email"),
+        user_name: text(row, "user_name"),
+        data,
+        flash_messages,
+        csrf_token: text(row, "csrf_token")?,
+        created_at: DateTime::from_timestamp(number(row, "created_at")?, 0)?,
+        expires_at: DateTime::from_timestamp(number(row, "expires_at")?, 0)?,
+        absolute_expires_at: DateTime::from_timestamp(number(row, "absolute_expires_at")?, 0)?,
+        refresh_token_hash: text(row, "refresh_token_hash"),
+    })
+}
+
+/// Text column of a row; `None` if it is null or missing.
+pub(crate) fn text(row: &Row, name: &str) -> Option<String> {
+    match row.columns.get(name).and_then(|value| value.value.as_ref()) {
+        Some(ValueInner::StringValue(value)) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// Integer column of a row; `None` if it is null or missing.
+pub(crate) fn number(row: &Row, name: &str) -> Option<i64> {
+    match row.columns.get(name).and_then(|value| value.value.as_ref()) {
+        Some(ValueInner::IntValue(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+pub(crate) fn string(value: &str) -> Value {
+    Value {
+        value: Some(ValueInner::StringValue(value.to_string())),
+    }
+}
+
+pub(crate) const fn int(value: i64) -> Value {
+    Value {
+        value: Some(ValueInner::IntValue(value)),
+    }
+}
+
+pub(crate) const fn null() -> Value {
+    Value {
+        value: Some(ValueInner::NullValue(true)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_row_round_trip() {
+        let mut session = SessionData::new(3600, Some(7));
+        session.user_email = Some("ada@example.com".to_string());
+        session.data.insert("theme".to_string(), "dark".to_string());
+        session.flash_messages.push(FlashMessage {
+            level: "success".to_string(),
+            message: "Saved".to_string(),
+        });
+
+        session.refresh_token_hash = Some("hash".to_string());
+
+        let names = COLUMNS.split(", ").map(str::to_string);
+        let row = Row {
+            columns: names.zip(session_params(&session)).collect(),
+        };
+        let loaded = session_from_row(&row).expect("complete row");
+
+        assert_eq!(loaded.session_id, session.session_id);
+        assert_eq!(loaded.user_id, Some(7));
+        assert_eq!(loaded.user_email.as_deref(), Some("ada@example.com"));
+        assert_eq!(loaded.user_name, None);
+        assert_eq!(loaded.data, session.data);
+        assert_eq!(loaded.flash_messages.len(), 1);
+        assert_eq!(loaded.flash_messages[0].message, "Saved");
+        assert_eq!(loaded.csrf_token, session.csrf_token);
+        assert_eq!(loaded.refresh_token_hash.as_deref(), Some("hash"));
+        assert_eq!(
+            loaded.expires_at.timestamp(),
+            session.expires_at.timestamp()
+        );
+    }
+
+    #[test]
+    fn test_table_name_must_be_identifier() {
+        assert!(is_identifier("auth_sessions"));
+        assert!(is_identifier("_sessions2"));
+        assert!(!is_identifier("2sessions"));
+        assert!(!is_identifier("sessions; DROP TABLE users"));
+        assert!(!is_identifier(""));
+        assert!(PostgresSessionStore::connect_lazy("http://localhost:50052", "a.b").is_err());
+    }
+}
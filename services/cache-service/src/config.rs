@@ -26,6 +26,10 @@ pub struct RedisConfig {
     /// Connection timeout in seconds.
     #[serde(default = "default_connect_timeout")]
     pub connect_timeout_seconds: u64,
+    /// Namespace prepended to every key as `"{namespace}:"`, so several
+    /// applications can share one Redis without colliding.
+    #[serde(default)]
+    pub namespace: Option<String>,
 }
 
 /// Service network configuration.
@@ -1,6 +1,7 @@
 //! Cache service for Acton DX.
 //!
-//! Provides Redis caching, rate limiting, and distributed session storage.
+//! Provides Redis caching, rate limiting, pub/sub messaging, and distributed
+//! session storage.
 
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
@@ -31,7 +31,11 @@ async fn main() -> anyhow::Result<()> {
     info!(url = %config.redis.url, "Connected to Redis");
 
     // Create the service
-    let service = CacheServiceImpl::new(conn);
+    let mut service = CacheServiceImpl::new(conn).with_pubsub(client);
+    if let Some(namespace) = config.redis.namespace.as_deref() {
+        info!(%namespace, "Namespacing cache keys");
+        service = service.with_namespace(namespace);
+    }
 
     // Build the address
     let addr: SocketAddr = format!("{}:{}", config.service.host, config.service.port).parse()?;
@@ -1,30 +1,119 @@
 //! Cache service gRPC implementation.
 
 use acton_dx_proto::cache::v1::{
-    cache_service_server::CacheService, DeleteRequest, DeleteResponse, ExistsRequest,
-    ExistsResponse, GetRequest, GetResponse, HGetAllRequest, HGetAllResponse, HGetRequest,
-    HGetResponse, HSetRequest, HSetResponse, IncrementRequest, IncrementResponse, LPushRequest,
-    LPushResponse, LRangeRequest, LRangeResponse, RPopRequest, RPopResponse, RateLimitRequest,
-    RateLimitResponse, SetRequest, SetResponse,
+    cache_service_server::CacheService, ConsumeTokensRequest, ConsumeTokensResponse, DeleteRequest,
+    DeleteResponse, ExistsRequest, ExistsResponse, GetRequest, GetResponse, HGetAllRequest,
+    HGetAllResponse, HGetRequest, HGetResponse, HSetRequest, HSetResponse, IncrementRequest,
+    IncrementResponse, InvalidatePrefixRequest, InvalidatePrefixResponse, LPushRequest,
+    LPushResponse, LRangeRequest, LRangeResponse, PublishRequest, PublishResponse, RPopRequest,
+    RPopResponse, RateLimitRequest, RateLimitResponse, ScoredMember, SetRequest, SetResponse,
+    SubscribeRequest, ZAddRequest, ZAddResponse, ZIncrByRequest, ZIncrByResponse, ZRangeRequest,
+    ZRangeResponse, ZRemRangeByScoreRequest, ZRemRangeByScoreResponse,
 };
+use acton_dx_proto::error::invalid_field;
+use acton_dx_proto::error::v1::ErrorDetail;
 use redis::aio::ConnectionManager;
-use redis::AsyncCommands;
+use redis::{AsyncCommands, Client, RedisError};
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tonic::{Request, Response, Status};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tonic::{Code, Request, Response, Status};
 use tracing::{debug, error};
 
+use super::pubsub::{self, MessageStream};
+use super::sorted_set;
+
+/// Token bucket consume-and-refill script.
+///
+/// Uses the Redis server clock so every node refills the bucket identically,
+/// and runs atomically so concurrent consumers cannot overdraw it.
+///
+/// KEYS[1] = bucket key, ARGV = capacity, refill per second, tokens requested.
+/// Returns `{allowed, remaining, retry_after_ms}`.
+const TOKEN_BUCKET_SCRIPT: &str = r"
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local requested = tonumber(ARGV[3])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(state[1])
+local ts = tonumber(state[2])
+if tokens == nil or ts == nil then
+  tokens = capacity
+  ts = now
+end
+tokens = math.min(capacity, tokens + (math.max(0, now - ts) / 1000) * rate)
+local allowed = 0
+local retry_after = 0
+if tokens >= requested then
+  tokens = tokens - requested
+  allowed = 1
+elseif rate > 0 then
+  retry_after = math.ceil((requested - tokens) / rate * 1000)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+local ttl = 3600000
+if rate > 0 then
+  ttl = math.ceil(capacity / rate * 1000) + 1000
+end
+redis.call('PEXPIRE', KEYS[1], ttl)
+return {allowed, math.floor(tokens), retry_after}
+";
+
+/// Keys scanned and unlinked per round trip when the request has no batch size.
+const DEFAULT_INVALIDATE_BATCH: u32 = 500;
+
+/// Upper bound on the batch size a client may request.
+const MAX_INVALIDATE_BATCH: u32 = 10_000;
+
+/// How long clients should wait before retrying when Redis is unreachable.
+const REDIS_RETRY_AFTER: Duration = Duration::from_secs(1);
+
 /// Cache service implementation.
 pub struct CacheServiceImpl {
     /// Redis connection manager.
     conn: ConnectionManager,
+    /// Prefix applied to every key, empty or ending in `:`.
+    namespace: String,
+    /// Client opening a pub/sub connection per subscriber.
+    pubsub: Option<Client>,
 }
 
 impl CacheServiceImpl {
     /// Create a new cache service with the given Redis connection.
     #[must_use]
     pub const fn new(conn: ConnectionManager) -> Self {
-        Self { conn }
+        Self {
+            conn,
+            namespace: String::new(),
+            pubsub: None,
+        }
+    }
+
+    /// Serve `Subscribe`, opening a pub/sub connection per subscriber
+    /// through `client`.
+    ///
+    /// Without it, `Subscribe` fails with `FAILED_PRECONDITION`; `Publish`
+    /// works either way.
+    #[must_use]
+    pub fn with_pubsub(mut self, client: Client) -> Self {
+        self.pubsub = Some(client);
+        self
+    }
+
+    /// Prefix every key with `"{namespace}:"`.
+    ///
+    /// Clients only ever see keys inside the namespace, including through
+    /// `InvalidatePrefix`, so one Redis can back several applications.
+    #[must_use]
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace_prefix(namespace);
+        self
+    }
+
+    /// Qualify a client key with the service namespace.
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.namespace)
     }
 
     /// Get current unix timestamp.
@@ -34,6 +123,23 @@ impl CacheServiceImpl {
             .map_or(0, |d| d.as_secs())
     }
 
+    /// Status for a failed Redis command. Connection failures are
+    /// `UNAVAILABLE` with a retry hint; anything else is `INTERNAL`.
+    fn redis_error(e: &RedisError) -> Status {
+        let message = format!("Redis error: {e}");
+        if e.is_io_error()
+            || e.is_connection_refusal()
+            || e.is_connection_dropped()
+            || e.is_timeout()
+        {
+            ErrorDetail::new("CACHE_UNAVAILABLE", message)
+                .with_retry_after(REDIS_RETRY_AFTER)
+                .into_status(Code::Unavailable)
+        } else {
+            Status::internal(message)
+        }
+    }
+
     /// Safely convert i64 to i32.
     fn i64_to_i32(value: i64) -> i32 {
         i32::try_from(value).unwrap_or(i32::MAX)
@@ -47,18 +153,28 @@ impl CacheServiceImpl {
 
 #[tonic::async_trait]
 impl CacheService for CacheServiceImpl {
+    type SubscribeStream = MessageStream;
+
     async fn get(&self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
         let req = request.into_inner();
         debug!(key = %req.key, "GET");
 
+        let key = self.key(&req.key);
         let mut conn = self.conn.clone();
-        let result: Option<Vec<u8>> = conn.get(&req.key).await.map_err(|e| {
-            error!(error = %e, key = %req.key, "GET failed");
-            Status::internal(format!("Redis error: {e}"))
-        })?;
+        // PTTL is -1 for keys without an expiry and -2 for missing keys
+        let (result, ttl_ms): (Option<Vec<u8>>, i64) = redis::pipe()
+            .get(&key)
+            .pttl(&key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!(error = %e, key = %req.key, "GET failed");
+                Self::redis_error(&e)
+            })?;
 
         Ok(Response::new(GetResponse {
             found: result.is_some(),
+            ttl_ms: (result.is_some() && ttl_ms >= 0).then_some(ttl_ms),
             value: result,
         }))
     }
@@ -71,18 +187,18 @@ impl CacheService for CacheServiceImpl {
 
         if let Some(ttl) = req.ttl_seconds {
             let ttl_u64 = u64::try_from(ttl).unwrap_or(u64::MAX);
-            conn.set_ex::<_, _, ()>(&req.key, &req.value, ttl_u64)
+            conn.set_ex::<_, _, ()>(self.key(&req.key), &req.value, ttl_u64)
                 .await
                 .map_err(|e| {
                     error!(error = %e, key = %req.key, "SET failed");
-                    Status::internal(format!("Redis error: {e}"))
+                    Self::redis_error(&e)
                 })?;
         } else {
-            conn.set::<_, _, ()>(&req.key, &req.value)
+            conn.set::<_, _, ()>(self.key(&req.key), &req.value)
                 .await
                 .map_err(|e| {
                     error!(error = %e, key = %req.key, "SET failed");
-                    Status::internal(format!("Redis error: {e}"))
+                    Self::redis_error(&e)
                 })?;
         }
 
@@ -97,9 +213,9 @@ impl CacheService for CacheServiceImpl {
         debug!(key = %req.key, "DELETE");
 
         let mut conn = self.conn.clone();
-        let deleted: i64 = conn.del(&req.key).await.map_err(|e| {
+        let deleted: i64 = conn.del(self.key(&req.key)).await.map_err(|e| {
             error!(error = %e, key = %req.key, "DELETE failed");
-            Status::internal(format!("Redis error: {e}"))
+            Self::redis_error(&e)
         })?;
 
         Ok(Response::new(DeleteResponse { deleted: deleted > 0 }))
@@ -113,9 +229,9 @@ impl CacheService for CacheServiceImpl {
         debug!(key = %req.key, "EXISTS");
 
         let mut conn = self.conn.clone();
-        let exists: bool = conn.exists(&req.key).await.map_err(|e| {
+        let exists: bool = conn.exists(self.key(&req.key)).await.map_err(|e| {
             error!(error = %e, key = %req.key, "EXISTS failed");
-            Status::internal(format!("Redis error: {e}"))
+            Self::redis_error(&e)
         })?;
 
         Ok(Response::new(ExistsResponse { exists }))
@@ -138,20 +254,20 @@ impl CacheService for CacheServiceImpl {
         let window_start = now - u64::from(req.window_seconds.unsigned_abs());
 
         // Use sorted set for sliding window rate limiting
-        let rate_key = format!("ratelimit:{}", req.key);
+        let rate_key = self.key(&format!("ratelimit:{}", req.key));
 
         // Remove old entries
         conn.zrembyscore::<_, _, _, ()>(&rate_key, 0_u64, window_start)
             .await
             .map_err(|e| {
                 error!(error = %e, "ZREMRANGEBYSCORE failed");
-                Status::internal(format!("Redis error: {e}"))
+                Self::redis_error(&e)
             })?;
 
         // Count current entries
         let count: i64 = conn.zcard(&rate_key).await.map_err(|e| {
             error!(error = %e, "ZCARD failed");
-            Status::internal(format!("Redis error: {e}"))
+            Self::redis_error(&e)
         })?;
 
         let limit_i64 = i64::from(req.limit);
@@ -165,7 +281,7 @@ impl CacheService for CacheServiceImpl {
                 .await
                 .map_err(|e| {
                     error!(error = %e, "ZADD failed");
-                    Status::internal(format!("Redis error: {e}"))
+                    Self::redis_error(&e)
                 })?;
 
             // Set expiry on the key
@@ -174,7 +290,7 @@ impl CacheService for CacheServiceImpl {
                 .await
                 .map_err(|e| {
                     error!(error = %e, "EXPIRE failed");
-                    Status::internal(format!("Redis error: {e}"))
+                    Self::redis_error(&e)
                 })?;
         }
 
@@ -185,6 +301,49 @@ impl CacheService for CacheServiceImpl {
         }))
     }
 
+    async fn consume_tokens(
+        &self,
+        request: Request<ConsumeTokensRequest>,
+    ) -> Result<Response<ConsumeTokensResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            key = %req.key,
+            capacity = req.capacity,
+            refill = req.refill_per_second,
+            tokens = req.tokens,
+            "CONSUME_TOKENS"
+        );
+
+        if !req.refill_per_second.is_finite() || req.refill_per_second < 0.0 {
+            return Err(invalid_field(
+                "refill_per_second",
+                "refill_per_second must be a non-negative number",
+            ));
+        }
+
+        let mut conn = self.conn.clone();
+        let bucket_key = self.key(&format!("tokenbucket:{}", req.key));
+
+        let (allowed, remaining, retry_after_ms): (i64, i64, i64) =
+            redis::Script::new(TOKEN_BUCKET_SCRIPT)
+                .key(&bucket_key)
+                .arg(req.capacity)
+                .arg(req.refill_per_second)
+                .arg(req.tokens)
+                .invoke_async(&mut conn)
+                .await
+                .map_err(|e| {
+                    error!(error = %e, key = %req.key, "Token bucket script failed");
+                    Self::redis_error(&e)
+                })?;
+
+        Ok(Response::new(ConsumeTokensResponse {
+            allowed: allowed == 1,
+            remaining: u32::try_from(remaining).unwrap_or(0),
+            retry_after_ms: u64::try_from(retry_after_ms).unwrap_or(0),
+        }))
+    }
+
     async fn increment_counter(
         &self,
         request: Request<IncrementRequest>,
@@ -193,15 +352,15 @@ impl CacheService for CacheServiceImpl {
         debug!(key = %req.key, amount = req.amount, "INCREMENT");
 
         let mut conn = self.conn.clone();
-        let new_value: i64 = conn.incr(&req.key, req.amount).await.map_err(|e| {
+        let new_value: i64 = conn.incr(self.key(&req.key), req.amount).await.map_err(|e| {
             error!(error = %e, key = %req.key, "INCRBY failed");
-            Status::internal(format!("Redis error: {e}"))
+            Self::redis_error(&e)
         })?;
 
         if let Some(ttl) = req.ttl_seconds {
-            conn.expire::<_, ()>(&req.key, ttl).await.map_err(|e| {
+            conn.expire::<_, ()>(self.key(&req.key), ttl).await.map_err(|e| {
                 error!(error = %e, key = %req.key, "EXPIRE failed");
-                Status::internal(format!("Redis error: {e}"))
+                Self::redis_error(&e)
             })?;
         }
 
@@ -213,9 +372,9 @@ impl CacheService for CacheServiceImpl {
         debug!(key = %req.key, field = %req.field, "HGET");
 
         let mut conn = self.conn.clone();
-        let result: Option<Vec<u8>> = conn.hget(&req.key, &req.field).await.map_err(|e| {
+        let result: Option<Vec<u8>> = conn.hget(self.key(&req.key), &req.field).await.map_err(|e| {
             error!(error = %e, key = %req.key, "HGET failed");
-            Status::internal(format!("Redis error: {e}"))
+            Self::redis_error(&e)
         })?;
 
         Ok(Response::new(HGetResponse {
@@ -229,11 +388,11 @@ impl CacheService for CacheServiceImpl {
         debug!(key = %req.key, field = %req.field, "HSET");
 
         let mut conn = self.conn.clone();
-        conn.hset::<_, _, _, ()>(&req.key, &req.field, &req.value)
+        conn.hset::<_, _, _, ()>(self.key(&req.key), &req.field, &req.value)
             .await
             .map_err(|e| {
                 error!(error = %e, key = %req.key, "HSET failed");
-                Status::internal(format!("Redis error: {e}"))
+                Self::redis_error(&e)
             })?;
 
         Ok(Response::new(HSetResponse { success: true }))
@@ -247,9 +406,9 @@ impl CacheService for CacheServiceImpl {
         debug!(key = %req.key, "HGETALL");
 
         let mut conn = self.conn.clone();
-        let result: HashMap<String, Vec<u8>> = conn.hgetall(&req.key).await.map_err(|e| {
+        let result: HashMap<String, Vec<u8>> = conn.hgetall(self.key(&req.key)).await.map_err(|e| {
             error!(error = %e, key = %req.key, "HGETALL failed");
-            Status::internal(format!("Redis error: {e}"))
+            Self::redis_error(&e)
         })?;
 
         Ok(Response::new(HGetAllResponse { fields: result }))
@@ -263,9 +422,9 @@ impl CacheService for CacheServiceImpl {
         debug!(key = %req.key, "LPUSH");
 
         let mut conn = self.conn.clone();
-        let length: i64 = conn.lpush(&req.key, &req.value).await.map_err(|e| {
+        let length: i64 = conn.lpush(self.key(&req.key), &req.value).await.map_err(|e| {
             error!(error = %e, key = %req.key, "LPUSH failed");
-            Status::internal(format!("Redis error: {e}"))
+            Self::redis_error(&e)
         })?;
 
         Ok(Response::new(LPushResponse { length }))
@@ -276,9 +435,9 @@ impl CacheService for CacheServiceImpl {
         debug!(key = %req.key, "RPOP");
 
         let mut conn = self.conn.clone();
-        let value: Option<Vec<u8>> = conn.rpop(&req.key, None).await.map_err(|e| {
+        let value: Option<Vec<u8>> = conn.rpop(self.key(&req.key), None).await.map_err(|e| {
             error!(error = %e, key = %req.key, "RPOP failed");
-            Status::internal(format!("Redis error: {e}"))
+            Self::redis_error(&e)
         })?;
 
         Ok(Response::new(RPopResponse { value }))
@@ -295,15 +454,263 @@ impl CacheService for CacheServiceImpl {
         let start = Self::i64_to_isize(req.start);
         let stop = Self::i64_to_isize(req.stop);
         let values: Vec<Vec<u8>> = conn
-            .lrange(&req.key, start, stop)
+            .lrange(self.key(&req.key), start, stop)
             .await
             .map_err(|e| {
                 error!(error = %e, key = %req.key, "LRANGE failed");
-                Status::internal(format!("Redis error: {e}"))
+                Self::redis_error(&e)
             })?;
 
         Ok(Response::new(LRangeResponse { values }))
     }
+
+    async fn z_add(&self, request: Request<ZAddRequest>) -> Result<Response<ZAddResponse>, Status> {
+        let req = request.into_inner();
+        debug!(key = %req.key, members = req.members.len(), "ZADD");
+
+        sorted_set::validate_members(&req.members)?;
+        let items: Vec<(f64, &[u8])> = req
+            .members
+            .iter()
+            .map(|member| (member.score, member.member.as_slice()))
+            .collect();
+
+        let key = self.key(&req.key);
+        let mut pipe = redis::pipe();
+        pipe.atomic().zadd_multiple(&key, &items);
+        if let Some(ttl) = req.ttl_seconds {
+            pipe.expire(&key, ttl).ignore();
+        }
+
+        let mut conn = self.conn.clone();
+        let (added,): (i64,) = pipe.query_async(&mut conn).await.map_err(|e| {
+            error!(error = %e, key = %req.key, "ZADD failed");
+            Self::redis_error(&e)
+        })?;
+
+        Ok(Response::new(ZAddResponse { added }))
+    }
+
+    async fn z_range(
+        &self,
+        request: Request<ZRangeRequest>,
+    ) -> Result<Response<ZRangeResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            key = %req.key,
+            reverse = req.reverse,
+            offset = req.offset,
+            limit = req.limit,
+            "ZRANGE"
+        );
+
+        let key = self.key(&req.key);
+        let range = redis::cmd("ZRANGE")
+            .arg(&key)
+            .arg(sorted_set::range_args(&req)?)
+            .clone();
+        let count = sorted_set::count_command(&key, &req)?;
+
+        let mut conn = self.conn.clone();
+        let (members, total): (Vec<(Vec<u8>, f64)>, u64) = redis::pipe()
+            .add_command(range)
+            .add_command(count)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!(error = %e, key = %req.key, "ZRANGE failed");
+                Self::redis_error(&e)
+            })?;
+
+        Ok(Response::new(ZRangeResponse {
+            members: members
+                .into_iter()
+                .map(|(member, score)| ScoredMember { member, score })
+                .collect(),
+            total,
+        }))
+    }
+
+    async fn z_incr_by(
+        &self,
+        request: Request<ZIncrByRequest>,
+    ) -> Result<Response<ZIncrByResponse>, Status> {
+        let req = request.into_inner();
+        debug!(key = %req.key, increment = req.increment, "ZINCRBY");
+
+        if !req.increment.is_finite() {
+            return Err(invalid_field("increment", "increment must be a finite number"));
+        }
+
+        let mut conn = self.conn.clone();
+        let score: f64 = conn
+            .zincr(self.key(&req.key), &req.member, req.increment)
+            .await
+            .map_err(|e| {
+                error!(error = %e, key = %req.key, "ZINCRBY failed");
+                Self::redis_error(&e)
+            })?;
+
+        Ok(Response::new(ZIncrByResponse { score }))
+    }
+
+    async fn z_rem_range_by_score(
+        &self,
+        request: Request<ZRemRangeByScoreRequest>,
+    ) -> Result<Response<ZRemRangeByScoreResponse>, Status> {
+        let req = request.into_inner();
+        debug!(key = %req.key, scores = ?req.scores, "ZREMRANGEBYSCORE");
+
+        let Some(scores) = &req.scores else {
+            return Err(invalid_field("scores", "scores are required"));
+        };
+        let (min, max) = sorted_set::bounds(scores, "scores")?;
+
+        let mut conn = self.conn.clone();
+        let removed: i64 = conn
+            .zrembyscore(self.key(&req.key), min, max)
+            .await
+            .map_err(|e| {
+                error!(error = %e, key = %req.key, "ZREMRANGEBYSCORE failed");
+                Self::redis_error(&e)
+            })?;
+
+        Ok(Response::new(ZRemRangeByScoreResponse { removed }))
+    }
+
+    async fn invalidate_prefix(
+        &self,
+        request: Request<InvalidatePrefixRequest>,
+    ) -> Result<Response<InvalidatePrefixResponse>, Status> {
+        let req = request.into_inner();
+        debug!(prefix = %req.prefix, "INVALIDATE_PREFIX");
+
+        if req.prefix.is_empty() {
+            return Err(invalid_field("prefix", "prefix must not be empty"));
+        }
+        let batch_size = req
+            .batch_size
+            .unwrap_or(DEFAULT_INVALIDATE_BATCH)
+            .clamp(1, MAX_INVALIDATE_BATCH);
+        let pattern = scan_pattern(&self.key(&req.prefix));
+
+        // SCAN walks the keyspace incrementally and UNLINK frees memory off
+        // the main thread, so neither blocks Redis the way KEYS or FLUSHALL do.
+        let mut conn = self.conn.clone();
+        let mut cursor = 0_u64;
+        let mut deleted = 0_u64;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(batch_size)
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| {
+                    error!(error = %e, prefix = %req.prefix, "SCAN failed");
+                    Self::redis_error(&e)
+                })?;
+
+            if !keys.is_empty() {
+                let unlinked: u64 = conn.unlink(&keys).await.map_err(|e| {
+                    error!(error = %e, prefix = %req.prefix, "UNLINK failed");
+                    Self::redis_error(&e)
+                })?;
+                deleted += unlinked;
+            }
+
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+
+        debug!(prefix = %req.prefix, deleted, "Prefix invalidated");
+        Ok(Response::new(InvalidatePrefixResponse { deleted }))
+    }
+
+    async fn publish(
+        &self,
+        request: Request<PublishRequest>,
+    ) -> Result<Response<PublishResponse>, Status> {
+        let req = request.into_inner();
+        debug!(channel = %req.channel, "PUBLISH");
+
+        if req.channel.is_empty() {
+            return Err(invalid_field("channel", "channel must not be empty"));
+        }
+
+        let mut conn = self.conn.clone();
+        let receivers: i64 = conn
+            .publish(self.key(&req.channel), &req.payload)
+            .await
+            .map_err(|e| {
+                error!(error = %e, channel = %req.channel, "PUBLISH failed");
+                Self::redis_error(&e)
+            })?;
+
+        Ok(Response::new(PublishResponse { receivers }))
+    }
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let req = request.into_inner();
+        debug!(channels = ?req.channels, patterns = ?req.patterns, "SUBSCRIBE");
+
+        pubsub::validate(&req)?;
+        let Some(client) = &self.pubsub else {
+            return Err(ErrorDetail::new(
+                "PUBSUB_NOT_CONFIGURED",
+                "this cache service does not serve subscriptions",
+            )
+            .into_status(Code::FailedPrecondition));
+        };
+
+        let messages = pubsub::subscribe(client, &self.namespace, &req)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "SUBSCRIBE failed");
+                Self::redis_error(&e)
+            })?;
+
+        Ok(Response::new(messages))
+    }
+}
+
+/// Normalize a namespace into a key prefix ending in `:`.
+fn namespace_prefix(namespace: &str) -> String {
+    let namespace = namespace.trim_end_matches(':');
+    if namespace.is_empty() {
+        String::new()
+    } else {
+        format!("{namespace}:")
+    }
+}
+
+/// Build a `SCAN MATCH` pattern matching keys that start with `prefix`.
+///
+/// Glob metacharacters in the prefix are escaped so they match literally.
+fn scan_pattern(prefix: &str) -> String {
+    let mut pattern = escape_glob(prefix);
+    pattern.push('*');
+    pattern
+}
+
+/// Escape glob metacharacters so `value` matches literally in a Redis
+/// pattern.
+pub(super) fn escape_glob(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 1);
+    for c in value.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
 #[cfg(test)]
@@ -321,4 +728,29 @@ mod tests {
         assert_eq!(CacheServiceImpl::i64_to_i32(100), 100);
         assert_eq!(CacheServiceImpl::i64_to_i32(i64::MAX), i32::MAX);
     }
+
+    #[test]
+    fn test_redis_connection_errors_are_retryable() {
+        let refused = RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        let status = CacheServiceImpl::redis_error(&refused);
+        assert_eq!(status.code(), Code::Unavailable);
+        let detail = ErrorDetail::from_status(&status).expect("detail attached");
+        assert_eq!(detail.retry_after(), Some(REDIS_RETRY_AFTER));
+
+        let wrong_type = RedisError::from((redis::ErrorKind::TypeError, "wrong type"));
+        assert_eq!(CacheServiceImpl::redis_error(&wrong_type).code(), Code::Internal);
+    }
+
+    #[test]
+    fn test_namespace_prefix() {
+        assert_eq!(namespace_prefix("app"), "app:");
+        assert_eq!(namespace_prefix("app:"), "app:");
+        assert_eq!(namespace_prefix(""), "");
+    }
+
+    #[test]
+    fn test_scan_pattern_escapes_globs() {
+        assert_eq!(scan_pattern("app:tenant:7:"), "app:tenant:7:*");
+        assert_eq!(scan_pattern("a*b?[c]\\"), "a\\*b\\?\\[c\\]\\\\*");
+    }
 }
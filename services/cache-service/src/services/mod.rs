@@ -1,5 +1,7 @@
 //! Cache service implementations.
 
 mod cache;
+mod pubsub;
+mod sorted_set;
 
 pub use cache::CacheServiceImpl;
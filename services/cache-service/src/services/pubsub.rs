@@ -0,0 +1,162 @@
+//! Redis pub/sub for the `Publish` and `Subscribe` RPCs.
+//!
+//! Every `Subscribe` call opens its own Redis pub/sub connection, since a
+//! subscribed connection cannot run other commands. The connection lives as
+//! long as the gRPC stream: when the client goes away the stream is dropped
+//! and Redis unsubscribes it. If Redis drops the connection the stream ends
+//! and the client should subscribe again.
+//!
+//! Channels are namespaced like keys, and the namespace is stripped from
+//! the channel names and patterns sent back to subscribers.
+//!
+//! Keyspace subscriptions listen on Redis keyspace notification channels
+//! (`__keyspace@<db>__:<key>`) for the requested keys and key patterns
+//! instead, and send the key back as the channel.
+
+use acton_dx_proto::cache::v1::{PubSubMessage, SubscribeRequest};
+use acton_dx_proto::error::invalid_field;
+use futures_util::{Stream, StreamExt};
+use redis::{Client, Msg, RedisResult};
+use std::pin::Pin;
+use tonic::Status;
+
+use super::cache::escape_glob;
+
+/// Messages for one `Subscribe` call.
+pub(super) type MessageStream = Pin<Box<dyn Stream<Item = Result<PubSubMessage, Status>> + Send>>;
+
+/// Reject subscriptions without channels or with empty names.
+#[allow(clippy::result_large_err)]
+pub(super) fn validate(req: &SubscribeRequest) -> Result<(), Status> {
+    if req.channels.is_empty() && req.patterns.is_empty() {
+        return Err(invalid_field(
+            "channels",
+            "subscribe to at least one channel or pattern",
+        ));
+    }
+    if req.channels.iter().any(String::is_empty) {
+        return Err(invalid_field("channels", "channel names must not be empty"));
+    }
+    if req.patterns.iter().any(String::is_empty) {
+        return Err(invalid_field("patterns", "patterns must not be empty"));
+    }
+    Ok(())
+}
+
+/// Open a pub/sub connection subscribed to the request's channels and
+/// patterns inside `namespace`.
+#[allow(clippy::result_large_err)]
+pub(super) async fn subscribe(
+    client: &Client,
+    namespace: &str,
+    req: &SubscribeRequest,
+) -> RedisResult<MessageStream> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    if req.keyspace {
+        // Notifications arrive on one channel per key and database, so
+        // exact keys are subscribed as escaped patterns too
+        let keys = req.channels.iter().map(|key| escape_glob(key));
+        for pattern in keys.chain(req.patterns.iter().cloned()) {
+            pubsub
+                .psubscribe(format!("{KEYSPACE_PATTERN}{}{pattern}", escape_glob(namespace)))
+                .await?;
+        }
+    } else {
+        for channel in &req.channels {
+            pubsub.subscribe(format!("{namespace}{channel}")).await?;
+        }
+        for pattern in &req.patterns {
+            pubsub
+                .psubscribe(format!("{}{pattern}", escape_glob(namespace)))
+                .await?;
+        }
+    }
+
+    let namespace = namespace.to_string();
+    let keyspace = req.keyspace;
+    Ok(Box::pin(
+        pubsub
+            .into_on_message()
+            .map(move |msg| Ok(to_message(&msg, &namespace, keyspace))),
+    ))
+}
+
+/// Pattern prefix matching keyspace notifications in every database.
+const KEYSPACE_PATTERN: &str = "__keyspace@*__:";
+
+/// Convert a Redis message, stripping the namespace and, for keyspace
+/// notifications, the notification channel prefix.
+fn to_message(msg: &Msg, namespace: &str, keyspace: bool) -> PubSubMessage {
+    let mut channel = msg.get_channel_name();
+    if keyspace {
+        channel = strip_keyspace(channel);
+    }
+    PubSubMessage {
+        channel: channel.strip_prefix(namespace).unwrap_or(channel).to_string(),
+        payload: msg.get_payload_bytes().to_vec(),
+        pattern: msg
+            .get_pattern::<Option<String>>()
+            .ok()
+            .flatten()
+            .map(|pattern| {
+                let pattern = if keyspace {
+                    pattern.strip_prefix(KEYSPACE_PATTERN).unwrap_or(&pattern)
+                } else {
+                    &pattern
+                };
+                strip_namespace_pattern(pattern, namespace)
+            }),
+    }
+}
+
+/// The key of a `__keyspace@<db>__:<key>` notification channel.
+fn strip_keyspace(channel: &str) -> &str {
+    channel
+        .strip_prefix("__keyspace@")
+        .and_then(|rest| rest.split_once("__:"))
+        .map_or(channel, |(_, key)| key)
+}
+
+/// Strip the escaped namespace from a pattern Redis reports back.
+fn strip_namespace_pattern(pattern: &str, namespace: &str) -> String {
+    let escaped = escape_glob(namespace);
+    pattern
+        .strip_prefix(escaped.as_str())
+        .unwrap_or(pattern)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    #[test]
+    fn test_validate() {
+        let request = |channels: &[&str], patterns: &[&str]| SubscribeRequest {
+            channels: channels.iter().map(ToString::to_string).collect(),
+            patterns: patterns.iter().map(ToString::to_string).collect(),
+            keyspace: false,
+        };
+        assert!(validate(&request(&["orders"], &[])).is_ok());
+        assert!(validate(&request(&[], &["invalidate:*"])).is_ok());
+
+        for invalid in [request(&[], &[]), request(&[""], &[]), request(&[], &[""])] {
+            assert_eq!(validate(&invalid).unwrap_err().code(), Code::InvalidArgument);
+        }
+    }
+
+    #[test]
+    fn test_strip_namespace_pattern() {
+        assert_eq!(strip_namespace_pattern("app:invalidate:*", "app:"), "invalidate:*");
+        assert_eq!(strip_namespace_pattern("a\\*b:x*", "a*b:"), "x*");
+        assert_eq!(strip_namespace_pattern("x*", ""), "x*");
+    }
+
+    #[test]
+    fn test_strip_keyspace() {
+        assert_eq!(strip_keyspace("__keyspace@0__:app:user:1"), "app:user:1");
+        assert_eq!(strip_keyspace("__keyspace@12__:a__:b"), "a__:b");
+        assert_eq!(strip_keyspace("orders"), "orders");
+    }
+}
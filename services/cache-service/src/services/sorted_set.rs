@@ -0,0 +1,175 @@
+//! Sorted set requests for the `ZAdd`, `ZRange`, `ZIncrBy` and
+//! `ZRemRangeByScore` RPCs.
+//!
+//! `ZRange` pages by rank, or by score when the request has score bounds,
+//! using Redis 6.2's unified `ZRANGE` so both support `REV` and paging.
+
+use acton_dx_proto::cache::v1::{ScoreRange, ScoredMember, ZRangeRequest};
+use acton_dx_proto::error::invalid_field;
+use redis::Cmd;
+use tonic::Status;
+
+/// Reject empty additions and `NaN` scores, which Redis refuses.
+#[allow(clippy::result_large_err)]
+pub(super) fn validate_members(members: &[ScoredMember]) -> Result<(), Status> {
+    if members.is_empty() {
+        return Err(invalid_field("members", "add at least one member"));
+    }
+    if members.iter().any(|member| member.score.is_nan()) {
+        return Err(invalid_field("members", "scores must be numbers"));
+    }
+    Ok(())
+}
+
+/// `ZRANGE` arguments after the key, including `WITHSCORES`.
+#[allow(clippy::result_large_err)]
+pub(super) fn range_args(req: &ZRangeRequest) -> Result<Vec<String>, Status> {
+    let mut args = Vec::with_capacity(8);
+    if let Some(scores) = &req.scores {
+        let (min, max) = bounds(scores, "scores")?;
+        // With REV the higher bound comes first
+        if req.reverse {
+            args.extend([max, min]);
+        } else {
+            args.extend([min, max]);
+        }
+        args.push("BYSCORE".to_string());
+        if req.reverse {
+            args.push("REV".to_string());
+        }
+        if req.offset > 0 || req.limit > 0 {
+            let count = if req.limit == 0 {
+                "-1".to_string()
+            } else {
+                req.limit.to_string()
+            };
+            args.extend(["LIMIT".to_string(), req.offset.to_string(), count]);
+        }
+    } else {
+        let start = i64::try_from(req.offset).unwrap_or(i64::MAX);
+        let stop = if req.limit == 0 {
+            -1
+        } else {
+            start.saturating_add(i64::from(req.limit) - 1)
+        };
+        args.extend([start.to_string(), stop.to_string()]);
+        if req.reverse {
+            args.push("REV".to_string());
+        }
+    }
+    args.push("WITHSCORES".to_string());
+    Ok(args)
+}
+
+/// Command counting the members a `ZRange` request pages through.
+#[allow(clippy::result_large_err)]
+pub(super) fn count_command(key: &str, req: &ZRangeRequest) -> Result<Cmd, Status> {
+    Ok(match &req.scores {
+        Some(scores) => {
+            let (min, max) = bounds(scores, "scores")?;
+            redis::cmd("ZCOUNT").arg(key).arg(min).arg(max).clone()
+        }
+        None => redis::cmd("ZCARD").arg(key).clone(),
+    })
+}
+
+/// Redis `min`/`max` arguments for a score range.
+#[allow(clippy::result_large_err)]
+pub(super) fn bounds(range: &ScoreRange, field: &str) -> Result<(String, String), Status> {
+    if range.min.is_some_and(f64::is_nan) || range.max.is_some_and(f64::is_nan) {
+        return Err(invalid_field(field, "score bounds must be numbers"));
+    }
+    Ok((
+        bound(range.min, range.min_exclusive, "-inf"),
+        bound(range.max, range.max_exclusive, "+inf"),
+    ))
+}
+
+fn bound(value: Option<f64>, exclusive: bool, unbounded: &str) -> String {
+    match value {
+        None => unbounded.to_string(),
+        Some(value) if value.is_infinite() => {
+            if value > 0.0 { "+inf" } else { "-inf" }.to_string()
+        }
+        Some(value) if exclusive => format!("({value}"),
+        Some(value) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    fn range(min: Option<f64>, max: Option<f64>, min_exclusive: bool) -> ScoreRange {
+        ScoreRange {
+            min,
+            max,
+            min_exclusive,
+            max_exclusive: false,
+        }
+    }
+
+    #[test]
+    fn test_validate_members() {
+        let member = |score| ScoredMember {
+            member: b"alice".to_vec(),
+            score,
+        };
+        assert!(validate_members(&[member(1.5), member(f64::INFINITY)]).is_ok());
+        assert_eq!(validate_members(&[]).unwrap_err().code(), Code::InvalidArgument);
+        assert_eq!(
+            validate_members(&[member(f64::NAN)]).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_range_args_by_rank() {
+        let mut req = ZRangeRequest {
+            key: "board".to_string(),
+            ..ZRangeRequest::default()
+        };
+        assert_eq!(range_args(&req).unwrap(), ["0", "-1", "WITHSCORES"]);
+
+        req.reverse = true;
+        req.offset = 20;
+        req.limit = 10;
+        assert_eq!(range_args(&req).unwrap(), ["20", "29", "REV", "WITHSCORES"]);
+    }
+
+    #[test]
+    fn test_range_args_by_score() {
+        let mut req = ZRangeRequest {
+            key: "events".to_string(),
+            scores: Some(range(Some(100.0), None, true)),
+            ..ZRangeRequest::default()
+        };
+        assert_eq!(
+            range_args(&req).unwrap(),
+            ["(100", "+inf", "BYSCORE", "WITHSCORES"]
+        );
+
+        req.reverse = true;
+        req.offset = 5;
+        assert_eq!(
+            range_args(&req).unwrap(),
+            ["+inf", "(100", "BYSCORE", "REV", "LIMIT", "5", "-1", "WITHSCORES"]
+        );
+
+        req.scores = Some(range(Some(f64::NAN), None, false));
+        assert_eq!(range_args(&req).unwrap_err().code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(
+            bounds(&range(None, None, false), "scores").unwrap(),
+            ("-inf".to_string(), "+inf".to_string())
+        );
+        assert_eq!(
+            bounds(&range(Some(f64::NEG_INFINITY), Some(2.5), true), "scores").unwrap(),
+            ("-inf".to_string(), "2.5".to_string())
+        );
+    }
+}
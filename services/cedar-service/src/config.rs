@@ -12,6 +12,9 @@ pub struct CedarServiceConfig {
     /// Service configuration.
     #[serde(default)]
     pub service: ServiceConfig,
+    /// Decision audit log configuration.
+    #[serde(default)]
+    pub audit: AuditConfig,
 }
 
 /// Policy configuration.
@@ -23,6 +26,15 @@ pub struct PolicyConfig {
     /// Whether to watch for policy changes.
     #[serde(default)]
     pub watch: bool,
+    /// URL to load policies from instead of `path`.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// How often a policy URL is fetched while watching, in seconds.
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    /// How long file changes must settle before a reload, in milliseconds.
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
 }
 
 /// Service network configuration.
@@ -36,6 +48,60 @@ pub struct ServiceConfig {
     pub port: u16,
 }
 
+/// Where decision audit records are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSinkKind {
+    /// JSON lines on stdout.
+    #[default]
+    Stdout,
+    /// JSON lines appended to `path`.
+    File,
+    /// Rows in `table`, through the data service.
+    Data,
+}
+
+/// Decision audit log configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditConfig {
+    /// Whether decisions are recorded.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where records are written.
+    #[serde(default)]
+    pub sink: AuditSinkKind,
+    /// File records are appended to, for the file sink.
+    #[serde(default = "default_audit_path")]
+    pub path: String,
+    /// Data service endpoint, for the data sink.
+    #[serde(default = "default_data_endpoint")]
+    pub data_endpoint: String,
+    /// Table records are inserted into, for the data sink; created if missing.
+    #[serde(default = "default_audit_table")]
+    pub table: String,
+    /// Fraction of allowed decisions recorded, from 0.0 to 1.0.
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+    /// Fraction of denied decisions recorded, from 0.0 to 1.0.
+    #[serde(default = "default_sample_rate")]
+    pub deny_sample_rate: f64,
+    /// Records queued for the writer before new ones are dropped.
+    #[serde(default = "default_audit_buffer")]
+    pub buffer: usize,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            path: default_policies_path(),
+            watch: false,
+            url: None,
+            poll_interval_secs: default_poll_interval_secs(),
+            debounce_ms: default_debounce_ms(),
+        }
+    }
+}
+
 impl Default for ServiceConfig {
     fn default() -> Self {
         Self {
@@ -45,6 +111,21 @@ impl Default for ServiceConfig {
     }
 }
 
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sink: AuditSinkKind::default(),
+            path: default_audit_path(),
+            data_endpoint: default_data_endpoint(),
+            table: default_audit_table(),
+            sample_rate: default_sample_rate(),
+            deny_sample_rate: default_sample_rate(),
+            buffer: default_audit_buffer(),
+        }
+    }
+}
+
 fn default_host() -> String {
     "0.0.0.0".to_string()
 }
@@ -57,6 +138,34 @@ fn default_policies_path() -> String {
     "policies".to_string()
 }
 
+const fn default_poll_interval_secs() -> u64 {
+    30
+}
+
+const fn default_debounce_ms() -> u64 {
+    200
+}
+
+fn default_audit_path() -> String {
+    "logs/cedar-audit.jsonl".to_string()
+}
+
+fn default_data_endpoint() -> String {
+    "http://localhost:50052".to_string()
+}
+
+fn default_audit_table() -> String {
+    "cedar_audit_log".to_string()
+}
+
+const fn default_sample_rate() -> f64 {
+    1.0
+}
+
+const fn default_audit_buffer() -> usize {
+    1024
+}
+
 impl CedarServiceConfig {
     /// Load configuration from files and environment.
     ///
@@ -84,4 +193,19 @@ mod tests {
         assert_eq!(config.host, "0.0.0.0");
         assert_eq!(config.port, 50053);
     }
+
+    #[test]
+    fn test_audit_config_from_toml() {
+        let config: AuditConfig = Figment::new()
+            .merge(Toml::string(
+                "enabled = true\nsink = \"data\"\nsample_rate = 0.1\n",
+            ))
+            .extract()
+            .unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.sink, AuditSinkKind::Data);
+        assert!((config.sample_rate - 0.1).abs() < f64::EPSILON);
+        assert!((config.deny_sample_rate - 1.0).abs() < f64::EPSILON);
+        assert_eq!(config.table, "cedar_audit_log");
+    }
 }
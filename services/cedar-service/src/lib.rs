@@ -8,5 +8,5 @@
 pub mod config;
 pub mod services;
 
-pub use config::{CedarServiceConfig, PolicyConfig, ServiceConfig};
-pub use services::CedarServiceImpl;
+pub use config::{AuditConfig, AuditSinkKind, CedarServiceConfig, PolicyConfig, ServiceConfig};
+pub use services::{AuditLog, CedarServiceImpl, PolicySource, PolicyWatcher};
@@ -1,7 +1,7 @@
 //! Cedar authorization service entry point.
 
 use acton_dx_proto::cedar::v1::cedar_service_server::CedarServiceServer;
-use cedar_service::{CedarServiceConfig, CedarServiceImpl};
+use cedar_service::{AuditLog, CedarServiceConfig, CedarServiceImpl};
 use std::net::SocketAddr;
 use tonic::transport::Server;
 use tracing::{info, Level};
@@ -24,7 +24,19 @@ async fn main() -> anyhow::Result<()> {
     let config = CedarServiceConfig::load()?;
 
     // Create the service
-    let service = CedarServiceImpl::new(&config.policies.path)?;
+    let mut service = CedarServiceImpl::from_config(&config.policies).await?;
+
+    // Record decisions if auditing is enabled
+    if let Some(audit) = AuditLog::from_config(&config.audit).await? {
+        service = service.with_audit(audit);
+    }
+
+    // Reload policies when they change; stops when the server does
+    let _watcher = if config.policies.watch {
+        service.watch(&config.policies)?
+    } else {
+        None
+    };
 
     // Build the address
     let addr: SocketAddr = format!("{}:{}", config.service.host, config.service.port).parse()?;
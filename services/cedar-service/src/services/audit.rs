@@ -0,0 +1,510 @@
+//! Authorization decision audit log.
+//!
+//! With `audit.enabled` on, every decision (principal, action, resource,
+//! allow or deny, the policies that determined it, and how long it took) is
+//! recorded to a sink: JSON lines on stdout, JSON lines appended to a file,
+//! or rows in a table reached through the data service.
+//!
+//! Decisions are handed to a background writer over a bounded channel, so
+//! a slow sink never delays an authorization. When the channel is full the
+//! record is dropped and counted. Sampling rates keep the volume of allowed
+//! decisions down while still recording every denial.
+//!
+//! The data service sink writes to one table:
+//!
+//! ```sql
+//! CREATE TABLE cedar_audit_log (
+//!     id BIGSERIAL PRIMARY KEY,
+//!     decided_at BIGINT NOT NULL,
+//!     principal TEXT NOT NULL,
+//!     action TEXT NOT NULL,
+//!     resource TEXT NOT NULL,
+//!     decision TEXT NOT NULL,
+//!     determining_policies TEXT NOT NULL,
+//!     errors TEXT NOT NULL,
+//!     policy_version BIGINT NOT NULL,
+//!     latency_us BIGINT NOT NULL
+//! );
+//! ```
+
+use crate::config::{AuditConfig, AuditSinkKind};
+use acton_dx_proto::cedar::v1::{AuthzRequest, AuthzResponse, Entity};
+use acton_dx_proto::data::v1::{
+    data_service_client::DataServiceClient, value::Value as ValueInner, ExecuteRequest, Value,
+};
+use acton_dx_proto::time::unix_millis;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tonic::transport::Channel;
+use tracing::{error, info, warn};
+
+/// Outcome of an authorization decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditDecision {
+    /// The request was allowed.
+    Allow,
+    /// The request was denied, by policy or because it was invalid.
+    Deny,
+}
+
+impl AuditDecision {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+        }
+    }
+}
+
+/// One recorded authorization decision.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditRecord {
+    /// When the decision was made, in Unix milliseconds.
+    pub decided_at: i64,
+    /// Principal, e.g. `User::"alice"`.
+    pub principal: String,
+    /// Action name.
+    pub action: String,
+    /// Resource, e.g. `Document::"doc1"`.
+    pub resource: String,
+    /// Allow or deny.
+    pub decision: AuditDecision,
+    /// IDs of the policies that determined the decision.
+    pub determining_policies: Vec<String>,
+    /// Evaluation errors, or why the request was invalid.
+    pub errors: Vec<String>,
+    /// Policy set version the decision was made against.
+    pub policy_version: u64,
+    /// Time taken to decide, in microseconds.
+    pub latency_us: u64,
+}
+
+impl AuditRecord {
+    /// Record the decision `response` made for `req`.
+    #[must_use]
+    pub fn new(
+        req: &AuthzRequest,
+        response: &AuthzResponse,
+        determining_policies: Vec<String>,
+        errors: Vec<String>,
+        latency: Duration,
+    ) -> Self {
+        Self {
+            decided_at: unix_millis(),
+            principal: entity_label(req.principal.as_ref()),
+            action: req.action.clone(),
+            resource: entity_label(req.resource.as_ref()),
+            decision: if response.allowed {
+                AuditDecision::Allow
+            } else {
+                AuditDecision::Deny
+            },
+            determining_policies,
+            errors,
+            policy_version: response.policy_version,
+            latency_us: u64::try_from(latency.as_micros()).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+/// Where audit records are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditSink {
+    /// JSON lines on stdout.
+    Stdout,
+    /// JSON lines appended to a file.
+    File(PathBuf),
+    /// Rows in a table, through the data service at an endpoint.
+    Data {
+        /// Data service endpoint.
+        endpoint: String,
+        /// Table name.
+        table: String,
+    },
+}
+
+impl AuditSink {
+    /// The configured sink.
+    #[must_use]
+    pub fn from_config(config: &AuditConfig) -> Self {
+        match config.sink {
+            AuditSinkKind::Stdout => Self::Stdout,
+            AuditSinkKind::File => Self::File(PathBuf::from(&config.path)),
+            AuditSinkKind::Data => Self::Data {
+                endpoint: config.data_endpoint.clone(),
+                table: config.table.clone(),
+            },
+        }
+    }
+}
+
+/// Fractions of decisions that are recorded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AuditSampling {
+    /// Fraction of allowed decisions recorded, from 0.0 to 1.0.
+    pub allow_rate: f64,
+    /// Fraction of denied decisions recorded, from 0.0 to 1.0.
+    pub deny_rate: f64,
+}
+
+impl AuditSampling {
+    /// Record every decision.
+    pub const ALL: Self = Self {
+        allow_rate: 1.0,
+        deny_rate: 1.0,
+    };
+
+    fn keep(self, decision: AuditDecision) -> bool {
+        let rate = match decision {
+            AuditDecision::Allow => self.allow_rate,
+            AuditDecision::Deny => self.deny_rate,
+        };
+        rate >= 1.0 || (rate > 0.0 && rand::random::<f64>() < rate)
+    }
+}
+
+/// Records authorization decisions to a sink.
+///
+/// Cloning shares the writer; it stops once every clone is dropped and the
+/// queued records are written.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    tx: mpsc::Sender<AuditRecord>,
+    sampling: AuditSampling,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AuditLog {
+    /// Start the configured audit log, or `None` if auditing is disabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the sink cannot be opened.
+    pub async fn from_config(config: &AuditConfig) -> anyhow::Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let sampling = AuditSampling {
+            allow_rate: config.sample_rate,
+            deny_rate: config.deny_sample_rate,
+        };
+        let log = Self::spawn(AuditSink::from_config(config), sampling, config.buffer).await?;
+        Ok(Some(log))
+    }
+
+    /// Open `sink` and start writing records to it, queueing up to `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file cannot be opened, the endpoint or table
+    /// name is invalid, or the table cannot be created.
+    pub async fn spawn(
+        sink: AuditSink,
+        sampling: AuditSampling,
+        buffer: usize,
+    ) -> anyhow::Result<Self> {
+        let mut writer = SinkWriter::open(&sink).await?;
+        info!(sink = ?sink, ?sampling, "Auditing Cedar decisions");
+
+        let (tx, mut rx) = mpsc::channel::<AuditRecord>(buffer.max(1));
+        tokio::spawn(async move {
+            while let Some(record) = rx.recv().await {
+                if let Err(e) = writer.write(&record).await {
+                    error!(error = %e, "Failed to write Cedar audit record");
+                }
+            }
+            if let Err(e) = writer.flush().await {
+                error!(error = %e, "Failed to flush Cedar audit log");
+            }
+        });
+
+        Ok(Self {
+            tx,
+            sampling,
+            dropped: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Queue a record, subject to sampling.
+    ///
+    /// Never waits: if the writer has fallen behind, the record is dropped.
+    pub fn record(&self, record: AuditRecord) {
+        if !self.sampling.keep(record.decision) {
+            return;
+        }
+        if self.tx.try_send(record).is_err() {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped.is_power_of_two() {
+                warn!(dropped, "Cedar audit log is full, dropping records");
+            }
+        }
+    }
+
+    /// Number of records dropped because the writer fell behind.
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// An open sink.
+enum SinkWriter {
+    Stdout,
+    File(tokio::fs::File),
+    Data(DataAuditTable),
+}
+
+impl SinkWriter {
+    async fn open(sink: &AuditSink) -> anyhow::Result<Self> {
+        match sink {
+            AuditSink::Stdout => Ok(Self::Stdout),
+            AuditSink::File(path) => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                let file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                Ok(Self::File(file))
+            }
+            AuditSink::Data { endpoint, table } => {
+                let table = DataAuditTable::connect_lazy(endpoint, table)?;
+                table.ensure_table().await?;
+                Ok(Self::Data(table))
+            }
+        }
+    }
+
+    async fn write(&mut self, record: &AuditRecord) -> anyhow::Result<()> {
+        match self {
+            Self::Stdout => {
+                println!("{}", serde_json::to_string(record)?);
+                Ok(())
+            }
+            Self::File(file) => {
+                let mut line = serde_json::to_vec(record)?;
+                line.push(b'\n');
+                file.write_all(&line).await?;
+                Ok(())
+            }
+            Self::Data(table) => Ok(table.insert(record).await?),
+        }
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        if let Self::File(file) = self {
+            file.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Audit table reached through the data service.
+#[derive(Debug, Clone)]
+struct DataAuditTable {
+    client: DataServiceClient<Channel>,
+    table: String,
+}
+
+impl DataAuditTable {
+    fn connect_lazy(endpoint: &str, table: &str) -> anyhow::Result<Self> {
+        if !is_identifier(table) {
+            anyhow::bail!("Invalid audit table name: {table}");
+        }
+        let channel = Channel::from_shared(endpoint.to_string())?.connect_lazy();
+        Ok(Self {
+            client: DataServiceClient::new(channel),
+            table: table.to_string(),
+        })
+    }
+
+    async fn ensure_table(&self) -> Result<(), tonic::Status> {
+        let table = &self.table;
+        self.execute(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {table} (\
+                 id BIGSERIAL PRIMARY KEY, decided_at BIGINT NOT NULL, principal TEXT NOT NULL, \
+                 action TEXT NOT NULL, resource TEXT NOT NULL, decision TEXT NOT NULL, \
+                 determining_policies TEXT NOT NULL, errors TEXT NOT NULL, \
+                 policy_version BIGINT NOT NULL, latency_us BIGINT NOT NULL)"
+            ),
+            Vec::new(),
+        )
+        .await?;
+        self.execute(
+            format!("CREATE INDEX IF NOT EXISTS {table}_decided_at_idx ON {table} (decided_at)"),
+            Vec::new(),
+        )
+        .await
+    }
+
+    async fn insert(&self, record: &AuditRecord) -> Result<(), tonic::Status> {
+        let json = |values: &[String]| serde_json::to_string(values).unwrap_or_default();
+        self.execute(
+            format!(
+                "INSERT INTO {} (decided_at, principal, action, resource, decision, \
+                 determining_policies, errors, policy_version, latency_us) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                self.table
+            ),
+            vec![
+                int(record.decided_at),
+                string(&record.principal),
+                string(&record.action),
+                string(&record.resource),
+                string(record.decision.as_str()),
+                string(&json(&record.determining_policies)),
+                string(&json(&record.errors)),
+                int(i64::try_from(record.policy_version).unwrap_or(i64::MAX)),
+                int(i64::try_from(record.latency_us).unwrap_or(i64::MAX)),
+            ],
+        )
+        .await
+    }
+
+    async fn execute(&self, sql: String, params: Vec<Value>) -> Result<(), tonic::Status> {
+        self.client
+            .clone()
+            .execute(ExecuteRequest {
+                sql,
+                params,
+                transaction_id: None,
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+/// `Type::"id"`, or an empty string for a missing entity.
+fn entity_label(entity: Option<&Entity>) -> String {
+    entity.map_or_else(String::new, |entity| {
+        format!("{}::\"{}\"", entity.entity_type, entity.entity_id)
+    })
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn string(value: &str) -> Value {
+    Value {
+        value: Some(ValueInner::StringValue(value.to_string())),
+    }
+}
+
+const fn int(value: i64) -> Value {
+    Value {
+        value: Some(ValueInner::IntValue(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn request() -> AuthzRequest {
+        AuthzRequest {
+            principal: Some(Entity {
+                entity_type: "User".to_string(),
+                entity_id: "alice".to_string(),
+            }),
+            action: "read".to_string(),
+            resource: Some(Entity {
+                entity_type: "Document".to_string(),
+                entity_id: "doc1".to_string(),
+            }),
+            context: HashMap::new(),
+        }
+    }
+
+    fn response(allowed: bool) -> AuthzResponse {
+        AuthzResponse {
+            allowed,
+            decision_reason: String::new(),
+            diagnostics: vec![],
+            policy_version: 3,
+        }
+    }
+
+    fn record(allowed: bool) -> AuditRecord {
+        AuditRecord::new(
+            &request(),
+            &response(allowed),
+            vec!["policy0".to_string()],
+            vec![],
+            Duration::from_micros(42),
+        )
+    }
+
+    #[test]
+    fn test_record_from_decision() {
+        let record = record(true);
+        assert_eq!(record.principal, "User::\"alice\"");
+        assert_eq!(record.resource, "Document::\"doc1\"");
+        assert_eq!(record.decision, AuditDecision::Allow);
+        assert_eq!(record.policy_version, 3);
+        assert_eq!(record.latency_us, 42);
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["decision"], "allow");
+        assert_eq!(json["determining_policies"][0], "policy0");
+    }
+
+    #[test]
+    fn test_sampling_rates() {
+        let denials_only = AuditSampling {
+            allow_rate: 0.0,
+            deny_rate: 1.0,
+        };
+        assert!(!denials_only.keep(AuditDecision::Allow));
+        assert!(denials_only.keep(AuditDecision::Deny));
+        assert!(AuditSampling::ALL.keep(AuditDecision::Allow));
+    }
+
+    #[test]
+    fn test_rejects_invalid_table_name() {
+        assert!(DataAuditTable::connect_lazy("http://localhost:50052", "audit; DROP").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_sink_appends_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit/cedar.jsonl");
+        let sampling = AuditSampling {
+            allow_rate: 0.0,
+            deny_rate: 1.0,
+        };
+        let log = AuditLog::spawn(AuditSink::File(path.clone()), sampling, 16)
+            .await
+            .unwrap();
+
+        log.record(record(true));
+        log.record(record(false));
+        drop(log);
+
+        let mut lines = Vec::new();
+        for _ in 0..100 {
+            let contents = std::fs::read_to_string(&path).unwrap_or_default();
+            lines = contents.lines().map(ToString::to_string).collect();
+            if !lines.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(lines.len(), 1);
+        let json: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(json["decision"], "deny");
+    }
+}
@@ -1,17 +1,27 @@
 //! Cedar authorization service gRPC implementation.
 
+use super::audit::{AuditLog, AuditRecord};
+use super::store::{Activation, PolicyStore, StoreError, StoredVersion};
+use super::watcher::{self, PolicyReloader, PolicySource, PolicyWatcher, SOURCE_AUTHOR};
+use crate::config::PolicyConfig;
 use acton_dx_proto::cedar::v1::{
-    cedar_service_server::CedarService, AuthzRequest, AuthzResponse, BatchAuthzRequest,
-    BatchAuthzResponse, Entity, ReloadPoliciesRequest, ReloadPoliciesResponse,
-    ValidatePolicyRequest, ValidatePolicyResponse,
+    cedar_service_server::CedarService, ActivatePolicyVersionRequest,
+    ActivatePolicyVersionResponse, AuthzRequest, AuthzResponse, BatchAuthzRequest,
+    BatchAuthzResponse, Entity, GetPolicyVersionRequest, ListPolicyVersionsRequest,
+    ListPolicyVersionsResponse, PolicyActivation, PolicyVersion, ReloadPoliciesRequest,
+    ReloadPoliciesResponse, RollbackPoliciesRequest, SavePolicyVersionRequest,
+    SavePolicyVersionResponse, ValidatePolicyRequest, ValidatePolicyResponse,
 };
+use acton_dx_proto::error::invalid_field;
+use acton_dx_proto::time::unix_now;
 use cedar_policy::{Authorizer, Context, Entities, EntityUid, PolicySet, Request};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tonic::{Request as TonicRequest, Response, Status};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info};
 
 /// Cedar authorization service implementation.
 pub struct CedarServiceImpl {
@@ -21,8 +31,12 @@ pub struct CedarServiceImpl {
     policies: Arc<RwLock<PolicySet>>,
     /// The entities (protected by RwLock).
     entities: Arc<RwLock<Entities>>,
-    /// Path to policies directory.
-    policies_path: String,
+    /// Versions of the policy set.
+    store: Arc<PolicyStore>,
+    /// Reloads the store from the policy source, if there is one.
+    reloader: Option<PolicyReloader>,
+    /// Records decisions, if auditing is enabled.
+    audit: Option<AuditLog>,
 }
 
 /// Error creating an authorization response.
@@ -43,6 +57,7 @@ impl AuthzError {
             allowed: false,
             decision_reason: self.reason,
             diagnostics: vec![],
+            policy_version: 0,
         }
     }
 }
@@ -54,66 +69,93 @@ impl CedarServiceImpl {
     ///
     /// Returns error if policies cannot be loaded.
     pub fn new(policies_path: &str) -> anyhow::Result<Self> {
-        let policies = Self::load_policies_from_path(policies_path)?;
+        let policy_text = watcher::read_path(Path::new(policies_path))?;
+        Self::with_policies(&policy_text, PolicySource::Disk(policies_path.into()))
+    }
+
+    /// Create a new Cedar service with policies from the configured source.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if policies cannot be loaded.
+    pub async fn from_config(config: &PolicyConfig) -> anyhow::Result<Self> {
+        let source = PolicySource::from_config(config);
+        let policy_text = source.read().await?;
+        Self::with_policies(&policy_text, source)
+    }
+
+    fn with_policies(policy_text: &str, source: PolicySource) -> anyhow::Result<Self> {
+        let policies: PolicySet = policy_text.parse()?;
         let policy_count = policies.policies().count();
 
         info!(
-            path = %policies_path,
+            source = %source,
             policies = policy_count,
             "Loaded Cedar policies"
         );
 
+        let policies = Arc::new(RwLock::new(policies));
+        let store = Arc::new(PolicyStore::new(
+            policies.clone(),
+            policy_text.to_string(),
+            SOURCE_AUTHOR,
+            unix_now(),
+        ));
         Ok(Self {
             authorizer: Authorizer::new(),
-            policies: Arc::new(RwLock::new(policies)),
+            reloader: Some(PolicyReloader::new(store.clone(), source)),
+            store,
+            policies,
             entities: Arc::new(RwLock::new(Entities::empty())),
-            policies_path: policies_path.to_string(),
+            audit: None,
         })
     }
 
     /// Create a new Cedar service with an empty policy set.
     #[must_use]
     pub fn empty() -> Self {
+        let policies = Arc::new(RwLock::new(PolicySet::new()));
         Self {
             authorizer: Authorizer::new(),
-            policies: Arc::new(RwLock::new(PolicySet::new())),
+            store: Arc::new(PolicyStore::new(
+                policies.clone(),
+                String::new(),
+                SOURCE_AUTHOR,
+                unix_now(),
+            )),
+            reloader: None,
+            policies,
             entities: Arc::new(RwLock::new(Entities::empty())),
-            policies_path: String::new(),
+            audit: None,
         }
     }
 
-    /// Load policies from a directory path.
-    fn load_policies_from_path(path: &str) -> anyhow::Result<PolicySet> {
-        let path = Path::new(path);
-
-        if !path.exists() {
-            warn!(path = %path.display(), "Policies path does not exist, using empty policy set");
-            return Ok(PolicySet::new());
-        }
-
-        if path.is_file() {
-            let content = std::fs::read_to_string(path)?;
-            return Ok(content.parse()?);
-        }
-
-        Self::load_policies_from_directory(path)
+    /// Record every decision to `audit`.
+    #[must_use]
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
     }
 
-    /// Load policies from a directory.
-    fn load_policies_from_directory(path: &Path) -> anyhow::Result<PolicySet> {
-        let mut all_content = String::new();
-
-        for entry in std::fs::read_dir(path)? {
-            let entry = entry?;
-            let file_path = entry.path();
-            if file_path.extension().is_some_and(|ext| ext == "cedar") {
-                let content = std::fs::read_to_string(&file_path)?;
-                all_content.push_str(&content);
-                all_content.push('\n');
-            }
-        }
-
-        Ok(all_content.parse()?)
+    /// Reload the policy set whenever its source changes.
+    ///
+    /// Returns `None` for services without a policy source. Watching stops
+    /// when the returned watcher is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file system watcher cannot be created.
+    pub fn watch(&self, config: &PolicyConfig) -> anyhow::Result<Option<PolicyWatcher>> {
+        self.reloader
+            .clone()
+            .map(|reloader| {
+                PolicyWatcher::spawn(
+                    reloader,
+                    Duration::from_millis(config.debounce_ms),
+                    Duration::from_secs(config.poll_interval_secs),
+                )
+            })
+            .transpose()
     }
 
     /// Convert a proto Entity to a Cedar `EntityUid`.
@@ -178,10 +220,19 @@ impl CedarServiceImpl {
     }
 
     /// Execute authorization and build response.
-    fn execute_authorization(&self, cedar_request: &Request, req: &AuthzRequest) -> AuthzResponse {
+    ///
+    /// Also returns the IDs of the policies that determined the decision.
+    fn execute_authorization(
+        &self,
+        cedar_request: &Request,
+        req: &AuthzRequest,
+    ) -> (AuthzResponse, Vec<String>) {
         let policies = self.policies.read();
+        let policy_version = self.store.live_version();
         let entities = self.entities.read();
-        let response = self.authorizer.is_authorized(cedar_request, &policies, &entities);
+        let response = self
+            .authorizer
+            .is_authorized(cedar_request, &policies, &entities);
         drop(policies);
         drop(entities);
 
@@ -191,16 +242,22 @@ impl CedarServiceImpl {
             .errors()
             .map(ToString::to_string)
             .collect();
+        let determining_policies: Vec<String> = response
+            .diagnostics()
+            .reason()
+            .map(ToString::to_string)
+            .collect();
 
         debug!(
             principal = %req.principal.as_ref().map_or("none", |p| p.entity_id.as_str()),
             action = %req.action,
             resource = %req.resource.as_ref().map_or("none", |r| r.entity_id.as_str()),
             allowed = %allowed,
+            policy_version,
             "Authorization decision"
         );
 
-        AuthzResponse {
+        let response = AuthzResponse {
             allowed,
             decision_reason: if allowed {
                 "Allowed by policy".to_string()
@@ -208,21 +265,74 @@ impl CedarServiceImpl {
                 "Denied by policy".to_string()
             },
             diagnostics,
-        }
+            policy_version,
+        };
+        (response, determining_policies)
     }
 
     /// Perform a single authorization check.
     fn authorize_single(&self, req: &AuthzRequest) -> AuthzResponse {
-        match Self::build_cedar_request(req) {
-            Ok(cedar_request) => self.execute_authorization(&cedar_request, req),
-            Err(e) => e.into_response(),
+        let started = Instant::now();
+        let (response, determining_policies, errors) = match Self::build_cedar_request(req) {
+            Ok(cedar_request) => {
+                let (response, determining_policies) =
+                    self.execute_authorization(&cedar_request, req);
+                let errors = response.diagnostics.clone();
+                (response, determining_policies, errors)
+            }
+            Err(e) => {
+                let errors = vec![e.reason.clone()];
+                (e.into_response(), Vec::new(), errors)
+            }
+        };
+
+        if let Some(audit) = &self.audit {
+            audit.record(AuditRecord::new(
+                req,
+                &response,
+                determining_policies,
+                errors,
+                started.elapsed(),
+            ));
         }
+        response
     }
 
     /// Safely convert usize to i32.
     fn usize_to_i32(value: usize) -> i32 {
         i32::try_from(value).unwrap_or(i32::MAX)
     }
+
+    /// Convert a stored version to its proto message.
+    fn version_to_proto(stored: StoredVersion, active_version: u64) -> PolicyVersion {
+        PolicyVersion {
+            version: stored.version,
+            policy_text: stored.policy_text,
+            author: stored.author,
+            comment: stored.comment,
+            created_at: stored.created_at,
+            policies_count: Self::usize_to_i32(stored.policies_count),
+            active: stored.version == active_version,
+        }
+    }
+
+    /// Build the response to an activation or rollback.
+    fn activation_response(&self, activation: &Activation) -> ActivatePolicyVersionResponse {
+        let count = self.policies.read().policies().count();
+        info!(
+            version = activation.version,
+            previous_version = activation.previous_version,
+            actor = %activation.actor,
+            rollback = activation.rollback,
+            policies = count,
+            "Activated Cedar policy version"
+        );
+        ActivatePolicyVersionResponse {
+            active_version: activation.version,
+            previous_version: activation.previous_version,
+            policies_loaded: Self::usize_to_i32(count),
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -254,23 +364,27 @@ impl CedarService for CedarServiceImpl {
         &self,
         _request: TonicRequest<ReloadPoliciesRequest>,
     ) -> Result<Response<ReloadPoliciesResponse>, Status> {
-        if self.policies_path.is_empty() {
+        let Some(reloader) = &self.reloader else {
             return Ok(Response::new(ReloadPoliciesResponse {
                 success: false,
                 policies_loaded: 0,
                 message: "No policies path configured".to_string(),
+                active_version: self.store.active_version(),
             }));
-        }
+        };
 
-        match Self::load_policies_from_path(&self.policies_path) {
-            Ok(new_policies) => {
-                let count = new_policies.policies().count();
-                *self.policies.write() = new_policies;
+        match reloader.reload(false).await {
+            Ok(stored) => {
+                let count = stored.map_or_else(
+                    || self.policies.read().policies().count(),
+                    |stored| stored.policies_count,
+                );
                 info!(policies = count, "Reloaded Cedar policies");
                 Ok(Response::new(ReloadPoliciesResponse {
                     success: true,
                     policies_loaded: Self::usize_to_i32(count),
                     message: format!("Loaded {count} policies"),
+                    active_version: self.store.active_version(),
                 }))
             }
             Err(e) => {
@@ -279,6 +393,7 @@ impl CedarService for CedarServiceImpl {
                     success: false,
                     policies_loaded: 0,
                     message: format!("Failed to reload: {e}"),
+                    active_version: self.store.active_version(),
                 }))
             }
         }
@@ -301,6 +416,112 @@ impl CedarService for CedarServiceImpl {
             })),
         }
     }
+
+    async fn list_policy_versions(
+        &self,
+        _request: TonicRequest<ListPolicyVersionsRequest>,
+    ) -> Result<Response<ListPolicyVersionsResponse>, Status> {
+        let listing = self.store.list();
+        let active_version = listing.active_version;
+        let versions = listing
+            .versions
+            .into_iter()
+            .map(|stored| PolicyVersion {
+                policy_text: String::new(),
+                ..Self::version_to_proto(stored, active_version)
+            })
+            .collect();
+        let activations = listing
+            .activations
+            .into_iter()
+            .map(|activation| PolicyActivation {
+                version: activation.version,
+                previous_version: activation.previous_version,
+                actor: activation.actor,
+                activated_at: activation.activated_at,
+                rollback: activation.rollback,
+            })
+            .collect();
+
+        Ok(Response::new(ListPolicyVersionsResponse {
+            versions,
+            active_version,
+            activations,
+        }))
+    }
+
+    async fn get_policy_version(
+        &self,
+        request: TonicRequest<GetPolicyVersionRequest>,
+    ) -> Result<Response<PolicyVersion>, Status> {
+        let req = request.into_inner();
+        let stored = self.store.get(req.version)?;
+        Ok(Response::new(Self::version_to_proto(
+            stored,
+            self.store.active_version(),
+        )))
+    }
+
+    async fn save_policy_version(
+        &self,
+        request: TonicRequest<SavePolicyVersionRequest>,
+    ) -> Result<Response<SavePolicyVersionResponse>, Status> {
+        let req = request.into_inner();
+        if req.author.is_empty() {
+            return Err(invalid_field("author", "author is required"));
+        }
+
+        match self
+            .store
+            .save(req.policy_text, &req.author, &req.comment, unix_now())
+        {
+            Ok(stored) => {
+                info!(
+                    version = stored.version,
+                    author = %stored.author,
+                    policies = stored.policies_count,
+                    "Saved Cedar policy version"
+                );
+                Ok(Response::new(SavePolicyVersionResponse {
+                    valid: true,
+                    errors: vec![],
+                    version: Some(Self::version_to_proto(stored, self.store.active_version())),
+                }))
+            }
+            Err(StoreError::Invalid(errors)) => Ok(Response::new(SavePolicyVersionResponse {
+                valid: false,
+                errors,
+                version: None,
+            })),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn activate_policy_version(
+        &self,
+        request: TonicRequest<ActivatePolicyVersionRequest>,
+    ) -> Result<Response<ActivatePolicyVersionResponse>, Status> {
+        let req = request.into_inner();
+        if req.actor.is_empty() {
+            return Err(invalid_field("actor", "actor is required"));
+        }
+
+        let activation = self.store.activate(req.version, &req.actor, unix_now())?;
+        Ok(Response::new(self.activation_response(&activation)))
+    }
+
+    async fn rollback_policies(
+        &self,
+        request: TonicRequest<RollbackPoliciesRequest>,
+    ) -> Result<Response<ActivatePolicyVersionResponse>, Status> {
+        let req = request.into_inner();
+        if req.actor.is_empty() {
+            return Err(invalid_field("actor", "actor is required"));
+        }
+
+        let activation = self.store.rollback(&req.actor, unix_now())?;
+        Ok(Response::new(self.activation_response(&activation)))
+    }
 }
 
 #[cfg(test)]
@@ -340,6 +561,99 @@ mod tests {
         assert!(response.decision_reason.contains("Missing principal"));
     }
 
+    #[test]
+    fn test_decisions_report_policy_version() {
+        let service = CedarServiceImpl::empty();
+        let req = AuthzRequest {
+            principal: Some(Entity {
+                entity_type: "User".to_string(),
+                entity_id: "alice".to_string(),
+            }),
+            action: "read".to_string(),
+            resource: Some(Entity {
+                entity_type: "Document".to_string(),
+                entity_id: "doc1".to_string(),
+            }),
+            context: HashMap::new(),
+        };
+        let response = service.authorize_single(&req);
+        assert!(!response.allowed);
+        assert_eq!(response.policy_version, 1);
+
+        let stored = service
+            .store
+            .save(
+                "permit(principal, action, resource);".to_string(),
+                "alice",
+                "open up",
+                unix_now(),
+            )
+            .unwrap();
+        service.store.activate(stored.version, "alice", unix_now()).unwrap();
+        let response = service.authorize_single(&req);
+        assert!(response.allowed);
+        assert_eq!(response.policy_version, 2);
+    }
+
+    #[tokio::test]
+    async fn test_reload_without_source() {
+        let service = CedarServiceImpl::empty();
+        let response = service
+            .reload_policies(TonicRequest::new(ReloadPoliciesRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!response.success);
+        assert_eq!(response.active_version, 1);
+        assert!(service.watch(&PolicyConfig::default()).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_decisions_are_audited() {
+        use super::super::audit::{AuditSampling, AuditSink};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let audit = AuditLog::spawn(AuditSink::File(path.clone()), AuditSampling::ALL, 16)
+            .await
+            .unwrap();
+        let service = CedarServiceImpl::with_policies(
+            "permit(principal, action, resource);",
+            PolicySource::Disk("policies".into()),
+        )
+        .unwrap()
+        .with_audit(audit);
+
+        let req = AuthzRequest {
+            principal: Some(Entity {
+                entity_type: "User".to_string(),
+                entity_id: "alice".to_string(),
+            }),
+            action: "read".to_string(),
+            resource: Some(Entity {
+                entity_type: "Document".to_string(),
+                entity_id: "doc1".to_string(),
+            }),
+            context: HashMap::new(),
+        };
+        assert!(service.authorize_single(&req).allowed);
+        drop(service);
+
+        let mut contents = String::new();
+        for _ in 0..100 {
+            contents = std::fs::read_to_string(&path).unwrap_or_default();
+            if !contents.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let record: serde_json::Value = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(record["principal"], "User::\"alice\"");
+        assert_eq!(record["decision"], "allow");
+        assert_eq!(record["determining_policies"][0], "policy0");
+        assert_eq!(record["policy_version"], 1);
+    }
+
     #[test]
     fn test_safe_conversion() {
         assert_eq!(CedarServiceImpl::usize_to_i32(100), 100);
@@ -1,5 +1,11 @@
 //! Cedar service implementations.
 
+mod audit;
 mod cedar;
+mod store;
+mod watcher;
 
+pub use audit::{AuditDecision, AuditLog, AuditRecord, AuditSampling, AuditSink};
 pub use cedar::CedarServiceImpl;
+pub use store::{Activation, Listing, PolicyStore, StoreError, StoredVersion};
+pub use watcher::{PolicyReloader, PolicySource, PolicyWatcher};
@@ -0,0 +1,367 @@
+//! Versioned Cedar policy store.
+//!
+//! Every edit of the policy set is saved as a new version, and one version
+//! is active at a time. Activations are recorded with who made them, so the
+//! set that was active before can be restored with a rollback. Versions are
+//! kept in memory: after a restart the store starts again from the policies
+//! on disk.
+
+use acton_dx_proto::error::v1::ErrorDetail;
+use cedar_policy::PolicySet;
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tonic::{Code, Status};
+
+/// A stored version of the policy set.
+#[derive(Debug, Clone)]
+pub struct StoredVersion {
+    /// Version number, starting at 1.
+    pub version: u64,
+    /// Cedar source of the policy set.
+    pub policy_text: String,
+    /// Who saved the version.
+    pub author: String,
+    /// Why the version was saved.
+    pub comment: String,
+    /// When the version was saved (Unix seconds).
+    pub created_at: i64,
+    /// Number of policies in the set.
+    pub policies_count: usize,
+}
+
+/// An activation of a policy version.
+#[derive(Debug, Clone)]
+pub struct Activation {
+    /// Version that became active.
+    pub version: u64,
+    /// Version that was active before.
+    pub previous_version: u64,
+    /// Who activated the version.
+    pub actor: String,
+    /// When the version was activated (Unix seconds).
+    pub activated_at: i64,
+    /// Whether this was a rollback.
+    pub rollback: bool,
+}
+
+/// Errors from the policy store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreError {
+    /// The policy text does not parse.
+    Invalid(Vec<String>),
+    /// No such version.
+    NotFound(u64),
+    /// Nothing was active before the current version.
+    NothingToRollBack,
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(errors) => write!(f, "Invalid policies: {}", errors.join("; ")),
+            Self::NotFound(version) => write!(f, "Policy version {version} not found"),
+            Self::NothingToRollBack => write!(f, "No earlier policy version to roll back to"),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<StoreError> for Status {
+    fn from(error: StoreError) -> Self {
+        let message = error.to_string();
+        match error {
+            StoreError::Invalid(errors) => errors
+                .into_iter()
+                .fold(
+                    ErrorDetail::new("INVALID_POLICIES", message),
+                    |detail, error| detail.with_field_violation("policy_text", error),
+                )
+                .into_status(Code::InvalidArgument),
+            StoreError::NotFound(_) => {
+                ErrorDetail::new("POLICY_VERSION_NOT_FOUND", message).into_status(Code::NotFound)
+            }
+            StoreError::NothingToRollBack => ErrorDetail::new("NOTHING_TO_ROLL_BACK", message)
+                .into_status(Code::FailedPrecondition),
+        }
+    }
+}
+
+/// Snapshot of the store for listings.
+#[derive(Debug, Clone)]
+pub struct Listing {
+    /// Versions, newest first.
+    pub versions: Vec<StoredVersion>,
+    /// The active version.
+    pub active_version: u64,
+    /// Activations, newest first.
+    pub activations: Vec<Activation>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    versions: Vec<StoredVersion>,
+    active_version: u64,
+    activations: Vec<Activation>,
+}
+
+/// Versioned policy store.
+///
+/// Activating a version swaps the live policy set used for authorization.
+#[derive(Debug)]
+pub struct PolicyStore {
+    inner: Mutex<Inner>,
+    live: Arc<RwLock<PolicySet>>,
+    /// Version of the live set, only changed under its write lock.
+    live_version: AtomicU64,
+}
+
+impl PolicyStore {
+    /// Create a store whose first version is the live policy set.
+    #[must_use]
+    pub fn new(live: Arc<RwLock<PolicySet>>, policy_text: String, author: &str, now: i64) -> Self {
+        let policies_count = live.read().policies().count();
+        let first = StoredVersion {
+            version: 1,
+            policy_text,
+            author: author.to_string(),
+            comment: "Initial policies".to_string(),
+            created_at: now,
+            policies_count,
+        };
+        Self {
+            inner: Mutex::new(Inner {
+                versions: vec![first],
+                active_version: 1,
+                activations: Vec::new(),
+            }),
+            live,
+            live_version: AtomicU64::new(1),
+        }
+    }
+
+    /// Versions and activations, newest first.
+    pub fn list(&self) -> Listing {
+        let inner = self.inner.lock();
+        Listing {
+            versions: inner.versions.iter().rev().cloned().collect(),
+            active_version: inner.active_version,
+            activations: inner.activations.iter().rev().cloned().collect(),
+        }
+    }
+
+    /// The active version number.
+    pub fn active_version(&self) -> u64 {
+        self.inner.lock().active_version
+    }
+
+    /// The version of the live policy set.
+    ///
+    /// Read while holding the live set's read lock, this is the version of
+    /// the set being read, even if an activation is in progress.
+    pub fn live_version(&self) -> u64 {
+        self.live_version.load(Ordering::Acquire)
+    }
+
+    /// A version, or the active version when `version` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoreError::NotFound`] for unknown versions.
+    pub fn get(&self, version: Option<u64>) -> Result<StoredVersion, StoreError> {
+        let inner = self.inner.lock();
+        let version = version.unwrap_or(inner.active_version);
+        inner
+            .versions
+            .iter()
+            .find(|stored| stored.version == version)
+            .cloned()
+            .ok_or(StoreError::NotFound(version))
+    }
+
+    /// Save `policy_text` as a new, inactive version.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoreError::Invalid`] if the text does not parse.
+    pub fn save(
+        &self,
+        policy_text: String,
+        author: &str,
+        comment: &str,
+        now: i64,
+    ) -> Result<StoredVersion, StoreError> {
+        let policies: PolicySet = policy_text
+            .parse()
+            .map_err(|e: cedar_policy::ParseErrors| StoreError::Invalid(vec![e.to_string()]))?;
+
+        let mut inner = self.inner.lock();
+        let version = inner.versions.last().map_or(1, |last| last.version + 1);
+        let stored = StoredVersion {
+            version,
+            policy_text,
+            author: author.to_string(),
+            comment: comment.to_string(),
+            created_at: now,
+            policies_count: policies.policies().count(),
+        };
+        inner.versions.push(stored.clone());
+        drop(inner);
+        Ok(stored)
+    }
+
+    /// Make `version` the live policy set.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoreError::NotFound`] for unknown versions.
+    pub fn activate(&self, version: u64, actor: &str, now: i64) -> Result<Activation, StoreError> {
+        self.activate_locked(self.inner.lock(), version, actor, now, false)
+    }
+
+    /// Re-activate the version that was active before the current one.
+    ///
+    /// Rolling back twice in a row returns to the set that was rolled back.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoreError::NothingToRollBack`] if no version was active
+    /// before the current one.
+    pub fn rollback(&self, actor: &str, now: i64) -> Result<Activation, StoreError> {
+        let inner = self.inner.lock();
+        let previous = inner
+            .activations
+            .last()
+            .map(|activation| activation.previous_version)
+            .ok_or(StoreError::NothingToRollBack)?;
+        self.activate_locked(inner, previous, actor, now, true)
+    }
+
+    fn activate_locked(
+        &self,
+        mut inner: MutexGuard<'_, Inner>,
+        version: u64,
+        actor: &str,
+        now: i64,
+        rollback: bool,
+    ) -> Result<Activation, StoreError> {
+        let stored = inner
+            .versions
+            .iter()
+            .find(|stored| stored.version == version)
+            .ok_or(StoreError::NotFound(version))?;
+        // Stored versions were validated when saved
+        let policies: PolicySet = stored
+            .policy_text
+            .parse()
+            .map_err(|e: cedar_policy::ParseErrors| StoreError::Invalid(vec![e.to_string()]))?;
+
+        // Swapped while the store is locked, so activations apply in order
+        let mut live = self.live.write();
+        *live = policies;
+        self.live_version.store(version, Ordering::Release);
+        drop(live);
+        let activation = Activation {
+            version,
+            previous_version: inner.active_version,
+            actor: actor.to_string(),
+            activated_at: now,
+            rollback,
+        };
+        inner.active_version = version;
+        inner.activations.push(activation.clone());
+        Ok(activation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERMIT_READ: &str = r#"permit(principal, action == Action::"read", resource);"#;
+    const PERMIT_ALL: &str = r"permit(principal, action, resource);";
+
+    fn store() -> (PolicyStore, Arc<RwLock<PolicySet>>) {
+        let live = Arc::new(RwLock::new(PERMIT_READ.parse().unwrap()));
+        let store = PolicyStore::new(live.clone(), PERMIT_READ.to_string(), "disk", 100);
+        (store, live)
+    }
+
+    #[test]
+    fn test_save_keeps_active_version() {
+        let (store, _) = store();
+        let saved = store
+            .save(PERMIT_ALL.to_string(), "alice", "open up", 200)
+            .unwrap();
+        assert_eq!(saved.version, 2);
+        assert_eq!(saved.policies_count, 1);
+
+        let listing = store.list();
+        assert_eq!(listing.active_version, 1);
+        assert_eq!(listing.versions[0].version, 2);
+        assert_eq!(store.get(None).unwrap().policy_text, PERMIT_READ);
+    }
+
+    #[test]
+    fn test_save_rejects_invalid_policies() {
+        let (store, _) = store();
+        let result = store.save("permit(".to_string(), "alice", "", 200);
+        assert!(matches!(result, Err(StoreError::Invalid(_))));
+        assert_eq!(store.list().versions.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_policies_status_lists_each_error() {
+        let status = Status::from(StoreError::Invalid(vec![
+            "unexpected token".to_string(),
+            "unknown entity type".to_string(),
+        ]));
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let detail = ErrorDetail::from_status(&status).expect("detail attached");
+        assert_eq!(detail.code, "INVALID_POLICIES");
+        assert_eq!(detail.field_violations.len(), 2);
+        assert_eq!(
+            detail.field_violations[1].description,
+            "unknown entity type"
+        );
+    }
+
+    #[test]
+    fn test_activate_and_rollback() {
+        let (store, live) = store();
+        store
+            .save(PERMIT_ALL.to_string(), "alice", "open up", 200)
+            .unwrap();
+
+        let activation = store.activate(2, "alice", 300).unwrap();
+        assert_eq!(activation.previous_version, 1);
+        assert_eq!(store.get(None).unwrap().version, 2);
+        assert_eq!(store.live_version(), 2);
+        assert!(live
+            .read()
+            .policies()
+            .any(|p| p.to_string().contains("action,")));
+
+        let rollback = store.rollback("bob", 400).unwrap();
+        assert!(rollback.rollback);
+        assert_eq!(rollback.version, 1);
+        assert_eq!(store.list().active_version, 1);
+        assert_eq!(store.list().activations[0].actor, "bob");
+    }
+
+    #[test]
+    fn test_rollback_needs_previous_activation() {
+        let (store, _) = store();
+        assert_eq!(
+            store.rollback("alice", 200).unwrap_err(),
+            StoreError::NothingToRollBack
+        );
+        assert_eq!(
+            store.activate(9, "alice", 200).unwrap_err(),
+            StoreError::NotFound(9)
+        );
+    }
+}
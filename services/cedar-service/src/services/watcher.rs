@@ -0,0 +1,380 @@
+//! Policy sources and hot reloading.
+//!
+//! Policies come from a `.cedar` file, a directory of them, or a URL. With
+//! `policies.watch` on, a [`PolicyWatcher`] reloads the policy set when the
+//! source changes: file system events are debounced, so an editor saving
+//! several files triggers a single reload, and URLs are polled. A reload
+//! whose text matches the active version is skipped, so every version in
+//! the store is a real change.
+
+use super::store::{PolicyStore, StoredVersion};
+use crate::config::PolicyConfig;
+use acton_dx_proto::time::unix_now;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// Author recorded for versions loaded from the policy source.
+pub const SOURCE_AUTHOR: &str = "disk";
+
+/// Where policies are loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicySource {
+    /// A `.cedar` file, or a directory of them.
+    Disk(PathBuf),
+    /// A URL serving the policy text.
+    Remote(String),
+}
+
+impl PolicySource {
+    /// The configured source: `url` if set, otherwise `path`.
+    #[must_use]
+    pub fn from_config(config: &PolicyConfig) -> Self {
+        config.url.as_ref().map_or_else(
+            || Self::Disk(PathBuf::from(&config.path)),
+            |url| Self::Remote(url.clone()),
+        )
+    }
+
+    /// Read the policy source text.
+    ///
+    /// A missing path reads as an empty policy set.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the files cannot be read, or the URL cannot be
+    /// fetched or answers with an error status.
+    pub async fn read(&self) -> anyhow::Result<String> {
+        match self {
+            Self::Disk(path) => read_path(path),
+            Self::Remote(url) => Ok(reqwest::get(url)
+                .await?
+                .error_for_status()?
+                .text()
+                .await?),
+        }
+    }
+}
+
+impl fmt::Display for PolicySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disk(path) => write!(f, "{}", path.display()),
+            Self::Remote(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Read the policy source from a file or directory path.
+pub fn read_path(path: &Path) -> anyhow::Result<String> {
+    if !path.exists() {
+        warn!(path = %path.display(), "Policies path does not exist, using empty policy set");
+        return Ok(String::new());
+    }
+
+    if path.is_file() {
+        return Ok(std::fs::read_to_string(path)?);
+    }
+
+    read_directory(path)
+}
+
+/// Read the policy source of the `.cedar` files in a directory.
+///
+/// Files are read in name order, so the source of an unchanged directory is
+/// the same on every reload.
+fn read_directory(path: &Path) -> anyhow::Result<String> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let file_path = entry?.path();
+        if is_policy_file(&file_path) {
+            files.push(file_path);
+        }
+    }
+    files.sort();
+
+    let mut all_content = String::new();
+    for file_path in files {
+        let content = std::fs::read_to_string(&file_path)?;
+        all_content.push_str(&content);
+        all_content.push('\n');
+    }
+
+    Ok(all_content)
+}
+
+fn is_policy_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "cedar")
+}
+
+/// Reloads the policy store from its source.
+#[derive(Debug, Clone)]
+pub struct PolicyReloader {
+    store: Arc<PolicyStore>,
+    source: PolicySource,
+}
+
+impl PolicyReloader {
+    /// Create a reloader activating versions of `store` read from `source`.
+    #[must_use]
+    pub const fn new(store: Arc<PolicyStore>, source: PolicySource) -> Self {
+        Self { store, source }
+    }
+
+    /// The source policies are reloaded from.
+    #[must_use]
+    pub const fn source(&self) -> &PolicySource {
+        &self.source
+    }
+
+    /// Save the source's policies as a new version and activate it.
+    ///
+    /// With `only_if_changed`, nothing is saved when the text matches the
+    /// active version, and `None` is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the source cannot be read or its policies do not
+    /// parse; the active version is left in place.
+    pub async fn reload(&self, only_if_changed: bool) -> anyhow::Result<Option<StoredVersion>> {
+        let policy_text = self.source.read().await?;
+        if only_if_changed && self.store.get(None)?.policy_text == policy_text {
+            return Ok(None);
+        }
+
+        let comment = format!("Reloaded from {}", self.source);
+        let now = unix_now();
+        let stored = self.store.save(policy_text, SOURCE_AUTHOR, &comment, now)?;
+        self.store.activate(stored.version, SOURCE_AUTHOR, now)?;
+        Ok(Some(stored))
+    }
+
+    /// Reload after a change, logging the outcome.
+    async fn reload_changed(&self) {
+        match self.reload(true).await {
+            Ok(Some(stored)) => info!(
+                source = %self.source,
+                version = stored.version,
+                policies = stored.policies_count,
+                "Reloaded changed Cedar policies"
+            ),
+            Ok(None) => debug!(source = %self.source, "Cedar policies unchanged"),
+            Err(e) => error!(
+                source = %self.source,
+                error = %e,
+                "Failed to reload Cedar policies, keeping the active version"
+            ),
+        }
+    }
+}
+
+/// Watches a policy source, reloading the policy set when it changes.
+///
+/// Watching stops when the watcher is dropped.
+#[derive(Debug)]
+pub struct PolicyWatcher {
+    /// File system watcher, for disk sources.
+    _watcher: Option<RecommendedWatcher>,
+    task: JoinHandle<()>,
+}
+
+impl PolicyWatcher {
+    /// Start watching the reloader's source.
+    ///
+    /// Disk sources reload `debounce` after the last change; remote sources
+    /// are fetched every `poll_interval`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file system watcher cannot be created.
+    pub fn spawn(
+        reloader: PolicyReloader,
+        debounce: Duration,
+        poll_interval: Duration,
+    ) -> anyhow::Result<Self> {
+        match reloader.source().clone() {
+            PolicySource::Disk(path) => Self::watch_disk(reloader, &path, debounce),
+            PolicySource::Remote(url) => {
+                info!(%url, interval = ?poll_interval, "Polling Cedar policies");
+                let task = tokio::spawn(async move {
+                    let mut interval = tokio::time::interval(poll_interval);
+                    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                    // The first tick completes immediately; policies were
+                    // just loaded
+                    interval.tick().await;
+                    loop {
+                        interval.tick().await;
+                        reloader.reload_changed().await;
+                    }
+                });
+                Ok(Self {
+                    _watcher: None,
+                    task,
+                })
+            }
+        }
+    }
+
+    fn watch_disk(reloader: PolicyReloader, path: &Path, debounce: Duration) -> anyhow::Result<Self> {
+        // Editors often replace files rather than write them, which ends a
+        // watch on the file itself, so the directory holding it is watched
+        let (watch_dir, file) = if path.is_dir() {
+            (path.to_path_buf(), None)
+        } else {
+            let parent = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or_else(|| Path::new("."));
+            (parent.to_path_buf(), path.file_name().map(ToOwned::to_owned))
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else { return };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            let relevant = event.paths.iter().any(|changed| {
+                file.as_ref().map_or_else(
+                    || is_policy_file(changed),
+                    |file| changed.file_name() == Some(file.as_os_str()),
+                )
+            });
+            if relevant {
+                let _ = tx.send(());
+            }
+        })?;
+        if watch_dir.exists() {
+            watcher.watch(&watch_dir, RecursiveMode::NonRecursive)?;
+            info!(path = %path.display(), "Watching Cedar policies");
+        } else {
+            warn!(path = %watch_dir.display(), "Policies directory does not exist, not watching");
+        }
+
+        let task = tokio::spawn(async move {
+            while rx.recv().await.is_some() {
+                // Wait until changes stop arriving
+                loop {
+                    match tokio::time::timeout(debounce, rx.recv()).await {
+                        Ok(Some(())) => {}
+                        Ok(None) => return,
+                        Err(_) => break,
+                    }
+                }
+                reloader.reload_changed().await;
+            }
+        });
+
+        Ok(Self {
+            _watcher: Some(watcher),
+            task,
+        })
+    }
+}
+
+impl Drop for PolicyWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cedar_policy::PolicySet;
+    use parking_lot::RwLock;
+
+    const PERMIT_READ: &str = r#"permit(principal, action == Action::"read", resource);"#;
+    const PERMIT_ALL: &str = r"permit(principal, action, resource);";
+
+    fn reloader(source: PolicySource, text: &str) -> PolicyReloader {
+        let live = Arc::new(RwLock::new(text.parse::<PolicySet>().unwrap()));
+        let store = PolicyStore::new(live, text.to_string(), SOURCE_AUTHOR, 100);
+        PolicyReloader::new(Arc::new(store), source)
+    }
+
+    #[test]
+    fn test_source_from_config() {
+        let mut config = PolicyConfig::default();
+        assert_eq!(
+            PolicySource::from_config(&config),
+            PolicySource::Disk(PathBuf::from("policies"))
+        );
+
+        config.url = Some("https://example.com/policies.cedar".to_string());
+        assert_eq!(
+            PolicySource::from_config(&config),
+            PolicySource::Remote("https://example.com/policies.cedar".to_string())
+        );
+    }
+
+    #[test]
+    fn test_read_directory_in_name_order() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.cedar"), PERMIT_ALL).unwrap();
+        std::fs::write(dir.path().join("a.cedar"), PERMIT_READ).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a policy").unwrap();
+
+        let text = read_path(dir.path()).unwrap();
+        assert_eq!(text, format!("{PERMIT_READ}\n{PERMIT_ALL}\n"));
+    }
+
+    #[tokio::test]
+    async fn test_reload_skips_unchanged_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policies.cedar");
+        std::fs::write(&path, PERMIT_READ).unwrap();
+        let reloader = reloader(PolicySource::Disk(path.clone()), PERMIT_READ);
+
+        assert!(reloader.reload(true).await.unwrap().is_none());
+        assert_eq!(reloader.store.active_version(), 1);
+
+        std::fs::write(&path, PERMIT_ALL).unwrap();
+        let stored = reloader.reload(true).await.unwrap().unwrap();
+        assert_eq!(stored.version, 2);
+        assert_eq!(reloader.store.active_version(), 2);
+
+        // Forced reloads always save a version
+        let stored = reloader.reload(false).await.unwrap().unwrap();
+        assert_eq!(stored.version, 3);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_policies_keep_active_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policies.cedar");
+        std::fs::write(&path, "permit(").unwrap();
+        let reloader = reloader(PolicySource::Disk(path), PERMIT_READ);
+
+        assert!(reloader.reload(true).await.is_err());
+        assert_eq!(reloader.store.active_version(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_reloads_changed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policies.cedar");
+        std::fs::write(&path, PERMIT_READ).unwrap();
+        let reloader = reloader(PolicySource::Disk(path.clone()), PERMIT_READ);
+        let store = reloader.store.clone();
+
+        let _watcher =
+            PolicyWatcher::spawn(reloader, Duration::from_millis(50), Duration::from_secs(30))
+                .unwrap();
+        std::fs::write(&path, PERMIT_ALL).unwrap();
+
+        for _ in 0..100 {
+            if store.active_version() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(store.active_version(), 2);
+        assert_eq!(store.get(None).unwrap().policy_text, PERMIT_ALL);
+    }
+}
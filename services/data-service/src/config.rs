@@ -1,8 +1,11 @@
 //! Configuration for the data service.
 
+use crate::services::{StreamLimits, TransactionLimits};
 use figment::providers::{Env, Format, Toml};
 use figment::Figment;
 use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// Service configuration.
 #[derive(Debug, Deserialize)]
@@ -12,6 +15,15 @@ pub struct DataServiceConfig {
     /// Service configuration.
     #[serde(default)]
     pub service: ServiceConfig,
+    /// Identity token verification.
+    #[serde(default)]
+    pub identity: IdentityConfig,
+    /// Named queries.
+    #[serde(default)]
+    pub queries: QueriesConfig,
+    /// Migrations served over the API.
+    #[serde(default)]
+    pub migrations: MigrationsConfig,
 }
 
 /// Database configuration.
@@ -28,6 +40,56 @@ pub struct DatabaseConfig {
     /// Connection timeout in seconds.
     #[serde(default = "default_connect_timeout")]
     pub connect_timeout_seconds: u64,
+    /// Postgres session variables clients may set per request for
+    /// row-level security policies (e.g. `app.user_id`).
+    #[serde(default)]
+    pub session_variables: Vec<String>,
+    /// Idle timeout for interactive transactions that do not ask for one.
+    #[serde(default = "default_transaction_timeout")]
+    pub transaction_timeout_seconds: u64,
+    /// Longest idle timeout an interactive transaction may ask for.
+    #[serde(default = "default_max_transaction_timeout")]
+    pub max_transaction_timeout_seconds: u64,
+    /// Most interactive transactions open at once.
+    #[serde(default = "default_max_transactions")]
+    pub max_transactions: usize,
+    /// Read replica URLs; read-only queries go to these.
+    #[serde(default)]
+    pub replicas: Vec<String>,
+    /// Seconds between read replica health checks.
+    #[serde(default = "default_replica_health_check")]
+    pub replica_health_check_seconds: u64,
+    /// Rows per `QueryStream` chunk when the request does not ask for a size.
+    #[serde(default = "default_stream_chunk_rows")]
+    pub stream_chunk_rows: usize,
+    /// Largest `QueryStream` chunk a request may ask for.
+    #[serde(default = "default_max_stream_chunk_rows")]
+    pub max_stream_chunk_rows: usize,
+    /// `QueryStream` chunks read ahead of a slow client.
+    #[serde(default = "default_stream_buffer_chunks")]
+    pub stream_buffer_chunks: usize,
+}
+
+impl DatabaseConfig {
+    /// Limits for interactive transactions.
+    #[must_use]
+    pub const fn transaction_limits(&self) -> TransactionLimits {
+        TransactionLimits {
+            default_timeout: Duration::from_secs(self.transaction_timeout_seconds),
+            max_timeout: Duration::from_secs(self.max_transaction_timeout_seconds),
+            max_open: self.max_transactions,
+        }
+    }
+
+    /// Chunk sizes and read-ahead for streamed queries.
+    #[must_use]
+    pub const fn stream_limits(&self) -> StreamLimits {
+        StreamLimits {
+            default_chunk_rows: self.stream_chunk_rows,
+            max_chunk_rows: self.max_stream_chunk_rows,
+            buffered_chunks: self.stream_buffer_chunks,
+        }
+    }
 }
 
 /// Service network configuration.
@@ -39,6 +101,9 @@ pub struct ServiceConfig {
     /// Port to listen on.
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Serve the `ExplainQuery` RPC; for development only.
+    #[serde(default)]
+    pub explain_enabled: bool,
 }
 
 impl Default for ServiceConfig {
@@ -46,10 +111,70 @@ impl Default for ServiceConfig {
         Self {
             host: default_host(),
             port: default_port(),
+            explain_enabled: false,
         }
     }
 }
 
+/// Identity token verification, plus where the verified user goes.
+#[derive(Debug, Deserialize)]
+pub struct IdentityConfig {
+    /// Auth service, audience and whether a token is required.
+    #[serde(flatten)]
+    pub verification: acton_dx_proto::identity::IdentityConfig,
+    /// Session variable set to the verified user's ID for row-level
+    /// security policies.
+    #[serde(default = "default_user_id_variable")]
+    pub user_id_variable: Option<String>,
+}
+
+impl Default for IdentityConfig {
+    fn default() -> Self {
+        Self {
+            verification: acton_dx_proto::identity::IdentityConfig::default(),
+            user_id_variable: default_user_id_variable(),
+        }
+    }
+}
+
+/// Named queries callers invoke by name instead of sending SQL.
+#[derive(Debug, Deserialize)]
+pub struct QueriesConfig {
+    /// Directory of `.toml` and `.sql` query files; unset serves none.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+    /// Accept SQL text from callers alongside named queries.
+    #[serde(default = "default_allow_raw_sql")]
+    pub allow_raw_sql: bool,
+}
+
+impl Default for QueriesConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            allow_raw_sql: default_allow_raw_sql(),
+        }
+    }
+}
+
+/// Migrations callers plan, apply and roll back through the service.
+#[derive(Debug, Default, Deserialize)]
+pub struct MigrationsConfig {
+    /// Directory of sqlx-style migrations; unset disables the migration
+    /// RPCs.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+}
+
+const fn default_allow_raw_sql() -> bool {
+    true
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn default_user_id_variable() -> Option<String> {
+    Some("app.user_id".to_string())
+}
+
 fn default_host() -> String {
     "0.0.0.0".to_string()
 }
@@ -70,6 +195,34 @@ const fn default_connect_timeout() -> u64 {
     30
 }
 
+const fn default_transaction_timeout() -> u64 {
+    30
+}
+
+const fn default_max_transaction_timeout() -> u64 {
+    300
+}
+
+const fn default_max_transactions() -> usize {
+    100
+}
+
+const fn default_replica_health_check() -> u64 {
+    10
+}
+
+const fn default_stream_chunk_rows() -> usize {
+    500
+}
+
+const fn default_max_stream_chunk_rows() -> usize {
+    10_000
+}
+
+const fn default_stream_buffer_chunks() -> usize {
+    4
+}
+
 impl DataServiceConfig {
     /// Load configuration from files and environment.
     ///
@@ -97,4 +250,18 @@ mod tests {
         assert_eq!(config.host, "0.0.0.0");
         assert_eq!(config.port, 50052);
     }
+
+    #[test]
+    fn test_default_identity_config() {
+        let config = IdentityConfig::default();
+        assert!(config.verification.auth_endpoint.is_none());
+        assert_eq!(config.user_id_variable.as_deref(), Some("app.user_id"));
+    }
+
+    #[test]
+    fn test_default_queries_config() {
+        let config = QueriesConfig::default();
+        assert!(config.dir.is_none());
+        assert!(config.allow_raw_sql);
+    }
 }
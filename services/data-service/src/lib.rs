@@ -8,5 +8,11 @@
 pub mod config;
 pub mod services;
 
-pub use config::{DataServiceConfig, DatabaseConfig, ServiceConfig};
-pub use services::DataServiceImpl;
+pub use config::{
+    DataServiceConfig, DatabaseConfig, IdentityConfig, MigrationsConfig, QueriesConfig,
+    ServiceConfig,
+};
+pub use services::{
+    DataServiceImpl, MigrationSet, NamedQuery, ParamSpec, ParamType, QueryRegistry, ReplicaSet,
+    StreamLimits, TransactionLimits, SESSION_VAR_METADATA_KEY,
+};
@@ -1,7 +1,9 @@
 //! Data service binary entry point.
 
 use acton_dx_proto::data::v1::data_service_server::DataServiceServer;
-use data_service::{DataServiceConfig, DataServiceImpl};
+use anyhow::Context;
+use acton_dx_proto::identity::IdentityVerifier;
+use data_service::{DataServiceConfig, DataServiceImpl, MigrationSet, QueryRegistry, ReplicaSet};
 use sqlx::any::AnyPoolOptions;
 use std::net::SocketAddr;
 use std::time::Duration;
@@ -31,8 +33,20 @@ async fn main() -> anyhow::Result<()> {
                 max_connections: 10,
                 min_connections: 1,
                 connect_timeout_seconds: 30,
+                session_variables: Vec::new(),
+                transaction_timeout_seconds: 30,
+                max_transaction_timeout_seconds: 300,
+                max_transactions: 100,
+                replicas: Vec::new(),
+                replica_health_check_seconds: 10,
+                stream_chunk_rows: 500,
+                max_stream_chunk_rows: 10_000,
+                stream_buffer_chunks: 4,
             },
             service: data_service::ServiceConfig::default(),
+            identity: data_service::IdentityConfig::default(),
+            queries: data_service::QueriesConfig::default(),
+            migrations: data_service::MigrationsConfig::default(),
         }
     });
 
@@ -40,17 +54,66 @@ async fn main() -> anyhow::Result<()> {
     sqlx::any::install_default_drivers();
 
     // Create database connection pool
-    let pool = AnyPoolOptions::new()
+    let pool_options = AnyPoolOptions::new()
         .max_connections(config.database.max_connections)
         .min_connections(config.database.min_connections)
-        .acquire_timeout(Duration::from_secs(config.database.connect_timeout_seconds))
-        .connect(&config.database.url)
-        .await?;
+        .acquire_timeout(Duration::from_secs(config.database.connect_timeout_seconds));
+    let pool = pool_options.clone().connect(&config.database.url).await?;
 
     tracing::info!("Database connection pool established");
 
+    // Read replicas connect on first use and are health-checked from here on
+    let replicas = ReplicaSet::connect_lazy(&config.database.replicas, &pool_options)?;
+    if !replicas.is_empty() {
+        replicas.start_health_checks(Duration::from_secs(
+            config.database.replica_health_check_seconds,
+        ));
+        tracing::info!(count = replicas.len(), "Read replicas configured");
+    }
+
+    // Verify identity tokens from the web tier when an auth service is configured
+    let identity = IdentityVerifier::from_config(&config.identity.verification)?;
+
+    // Load named queries and check them against the live schema
+    let queries = match &config.queries.dir {
+        Some(dir) => {
+            let queries = QueryRegistry::load(dir)?;
+            queries
+                .validate(&pool)
+                .await
+                .context("Named query validation failed")?;
+            tracing::info!(count = queries.len(), dir = %dir.display(), "Named queries loaded");
+            queries
+        }
+        None => QueryRegistry::new(),
+    };
+    if !config.queries.allow_raw_sql {
+        tracing::info!("Raw SQL disabled; only named queries are served");
+    }
+
     // Create gRPC service
-    let data_service = DataServiceImpl::new(pool);
+    let transaction_limits = config.database.transaction_limits();
+    let stream_limits = config.database.stream_limits();
+    let mut data_service = DataServiceImpl::new(pool)
+        .with_transaction_limits(transaction_limits)
+        .with_stream_limits(stream_limits)
+        .with_replicas(replicas)
+        .with_session_variables(config.database.session_variables)
+        .with_identity(identity, config.identity.user_id_variable)
+        .with_queries(queries)
+        .with_raw_sql(config.queries.allow_raw_sql)
+        .with_explain(config.service.explain_enabled);
+
+    // Serve PlanMigrations, ApplyMigrations and RollbackMigration
+    if let Some(dir) = &config.migrations.dir {
+        let migrations = MigrationSet::load(dir).await?;
+        tracing::info!(count = migrations.len(), dir = %dir.display(), "Migrations loaded");
+        data_service = data_service.with_migrations(migrations);
+    }
+
+    if config.service.explain_enabled {
+        tracing::warn!("ExplainQuery is enabled; do not expose this service in production");
+    }
 
     // Build server address
     let addr: SocketAddr = format!("{}:{}", config.service.host, config.service.port).parse()?;
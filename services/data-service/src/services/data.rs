@@ -1,32 +1,67 @@
 //! Data service gRPC implementation.
 
+use super::explain;
+use super::migrations::MigrationSet;
+use super::queries::QueryRegistry;
+use super::replicas::{is_read_only, Replica, ReplicaSet};
+use super::schema::{self, Dialect};
+use super::session_vars::{is_custom_setting, SessionVarError, SessionVars};
+use super::streaming::{self, StreamLimits};
+use super::transactions::{TransactionLimits, Transactions};
 use acton_dx_proto::data::v1::{
-    data_service_server::DataService, value::Value as ProtoValueInner, BeginTransactionRequest,
-    CommitTransactionRequest, ExecuteRequest, ExecuteResponse, MigrationResponse,
-    MigrationStatusRequest, MigrationStatusResponse, PingRequest, PingResponse, QueryOneResponse,
-    QueryRequest, QueryResponse, RollbackTransactionRequest, Row, RunMigrationsRequest,
-    TransactionExecuteRequest, TransactionResponse, Value as ProtoValue,
+    data_service_server::DataService, value::Value as ProtoValueInner, ApplyMigrationsRequest,
+    BeginTransactionRequest, CommitTransactionRequest, DescribeSchemaRequest,
+    DescribeSchemaResponse, ExecuteRequest, ExecuteResponse, ExplainQueryRequest,
+    ExplainQueryResponse, MigrationPlan, MigrationResponse, MigrationStatusRequest,
+    MigrationStatusResponse, NamedQueryRequest, PingRequest, PingResponse, PlanMigrationsRequest,
+    QueryOneResponse, QueryRequest, QueryResponse, QueryStreamRequest, RollbackMigrationRequest,
+    RollbackTransactionRequest, Row, RowChunk, RunMigrationsRequest, TransactionExecuteRequest,
+    TransactionResponse, Value as ProtoValue,
 };
-use dashmap::DashMap;
-use sqlx::any::{AnyArguments, AnyRow};
-use sqlx::{AnyPool, Arguments, Column, Row as SqlxRow, TypeInfo};
-use std::sync::Arc;
-use std::time::Instant;
-use tonic::{Request, Response, Status};
+use acton_dx_proto::error::v1::ErrorDetail;
+use acton_dx_proto::identity::IdentityVerifier;
+use sqlx::any::{AnyArguments, AnyQueryResult, AnyRow};
+use sqlx::pool::PoolConnection;
+use sqlx::{Any, AnyPool, Arguments, Column, Row as SqlxRow, TypeInfo};
+use futures_util::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Code, Request, Response, Status};
 use tracing::{debug, error, info, warn};
 
-/// Active transaction wrapper.
-struct ActiveTransaction {
-    /// The SQLx transaction (held as pool reference for simplicity).
-    _created_at: std::time::Instant,
-}
+/// How long clients should wait before retrying when the database is
+/// unreachable.
+const DATABASE_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+type RowChunkStream = Pin<Box<dyn Stream<Item = Result<RowChunk, Status>> + Send>>;
 
 /// Data service implementation.
 pub struct DataServiceImpl {
     /// Database connection pool.
     pool: AnyPool,
-    /// Active transactions by ID.
-    transactions: Arc<DashMap<String, ActiveTransaction>>,
+    /// Read replicas for read-only queries.
+    replicas: ReplicaSet,
+    /// Interactive transactions open across calls.
+    transactions: Transactions,
+    /// Chunk sizes and read-ahead for `QueryStream`.
+    stream_limits: StreamLimits,
+    /// Session variables clients may set for row-level security.
+    session_variables: Vec<String>,
+    /// Whether `ExplainQuery` is served.
+    explain_enabled: bool,
+    /// Verifies identity tokens attached by the web tier.
+    identity: IdentityVerifier,
+    /// Session variable set to the verified user's ID.
+    identity_variable: Option<String>,
+    /// Queries callers may invoke by name.
+    queries: QueryRegistry,
+    /// Whether callers may send SQL text.
+    raw_sql_enabled: bool,
+    /// Migrations served by the migration RPCs.
+    migrations: Option<MigrationSet>,
 }
 
 impl DataServiceImpl {
@@ -35,10 +70,387 @@ impl DataServiceImpl {
     pub fn new(pool: AnyPool) -> Self {
         Self {
             pool,
-            transactions: Arc::new(DashMap::new()),
+            replicas: ReplicaSet::default(),
+            transactions: Transactions::new(TransactionLimits::default()),
+            stream_limits: StreamLimits::default(),
+            session_variables: Vec::new(),
+            explain_enabled: false,
+            identity: IdentityVerifier::disabled(),
+            identity_variable: None,
+            queries: QueryRegistry::new(),
+            raw_sql_enabled: true,
+            migrations: None,
+        }
+    }
+
+    /// Serve `QueryNamed` and `ExecuteNamed` from these queries.
+    ///
+    /// Validate the registry against the pool first; see
+    /// [`QueryRegistry::validate`].
+    #[must_use]
+    pub fn with_queries(mut self, queries: QueryRegistry) -> Self {
+        self.queries = queries;
+        self
+    }
+
+    /// Accept SQL text from callers.
+    ///
+    /// Disable once every caller uses named queries, so `Query`, `QueryOne`,
+    /// `Execute` and `ExecuteInTransaction` are rejected and SQL can only
+    /// come from the query directory.
+    #[must_use]
+    pub const fn with_raw_sql(mut self, enabled: bool) -> Self {
+        self.raw_sql_enabled = enabled;
+        self
+    }
+
+    /// Reject raw SQL when only named queries are served.
+    #[allow(clippy::result_large_err)]
+    fn check_raw_sql(&self) -> Result<(), Status> {
+        if self.raw_sql_enabled {
+            return Ok(());
+        }
+        Err(ErrorDetail::new(
+            "RAW_SQL_DISABLED",
+            "raw SQL is disabled; call a named query",
+        )
+        .into_status(Code::PermissionDenied))
+    }
+
+    /// Send read-only queries to these replicas unless a caller asks for
+    /// the primary.
+    ///
+    /// Start their health checks with [`ReplicaSet::start_health_checks`].
+    #[must_use]
+    pub fn with_replicas(mut self, replicas: ReplicaSet) -> Self {
+        self.replicas = replicas;
+        self
+    }
+
+    /// Set the idle timeouts and the number of interactive transactions
+    /// that may be open at once.
+    #[must_use]
+    pub fn with_transaction_limits(mut self, limits: TransactionLimits) -> Self {
+        self.transactions = Transactions::new(limits);
+        self
+    }
+
+    /// Set the chunk sizes and read-ahead of streamed queries.
+    #[must_use]
+    pub const fn with_stream_limits(mut self, limits: StreamLimits) -> Self {
+        self.stream_limits = limits;
+        self
+    }
+
+    /// Serve `PlanMigrations`, `ApplyMigrations` and `RollbackMigration`
+    /// from these migrations.
+    #[must_use]
+    pub fn with_migrations(mut self, migrations: MigrationSet) -> Self {
+        self.migrations = Some(migrations);
+        self
+    }
+
+    /// The configured migrations, and a connection to run them on.
+    async fn migrations(&self) -> Result<(&MigrationSet, PoolConnection<Any>), Status> {
+        let migrations = self.migrations.as_ref().ok_or_else(|| {
+            ErrorDetail::new(
+                "MIGRATIONS_NOT_CONFIGURED",
+                "the data service has no migrations directory",
+            )
+            .into_status(Code::FailedPrecondition)
+        })?;
+        let conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| Self::database_error("Failed to acquire connection", &e))?;
+        Ok((migrations, conn))
+    }
+
+    /// Serve the development-only `ExplainQuery` RPC.
+    #[must_use]
+    pub const fn with_explain(mut self, enabled: bool) -> Self {
+        self.explain_enabled = enabled;
+        self
+    }
+
+    /// Allow clients to set these Postgres session variables per request.
+    ///
+    /// Names must be custom settings such as `app.user_id`; anything else
+    /// is dropped with a warning so built-ins like `role` stay off limits.
+    #[must_use]
+    pub fn with_session_variables(mut self, names: Vec<String>) -> Self {
+        self.session_variables = names
+            .into_iter()
+            .filter(|name| {
+                let valid = is_custom_setting(name);
+                if !valid {
+                    warn!(name = %name, "Ignoring session variable that is not a custom setting");
+                }
+                valid
+            })
+            .collect();
+        self
+    }
+
+    /// Verify identity tokens, setting `user_id_variable` to the verified
+    /// user's ID so row-level security policies see who the call is for.
+    ///
+    /// The variable need not be allowlisted. Callers can never set it, with
+    /// or without a token: a call that sends it is refused, so a call
+    /// without a token runs with the variable unset.
+    #[must_use]
+    pub fn with_identity(
+        mut self,
+        identity: IdentityVerifier,
+        user_id_variable: Option<String>,
+    ) -> Self {
+        self.identity = identity;
+        self.identity_variable = user_id_variable.filter(|name| {
+            let valid = is_custom_setting(name);
+            if !valid {
+                warn!(name = %name, "Ignoring identity variable that is not a custom setting");
+            }
+            valid
+        });
+        self
+    }
+
+    /// Session variables requested by a call, if the database applies them.
+    ///
+    /// Only Postgres has row-level security; on other databases the
+    /// variables are validated and then ignored.
+    async fn session_vars<T: Sync>(
+        &self,
+        request: &Request<T>,
+    ) -> Result<Option<SessionVars>, Status> {
+        let identity = self.identity.authenticate(request.metadata()).await?;
+        let mut vars = SessionVars::from_metadata(request.metadata(), &self.session_variables)?;
+        if let Some(name) = &self.identity_variable {
+            if vars.contains(name) {
+                return Err(SessionVarError::NotAllowed(name.clone()).into());
+            }
+        }
+        if let (Some(identity), Some(name)) = (identity, &self.identity_variable) {
+            vars.set(name, identity.user_id.to_string());
+        }
+        if vars.is_empty() || Dialect::of(&self.pool) != Some(Dialect::Postgres) {
+            return Ok(None);
+        }
+        Ok(Some(vars))
+    }
+
+    /// Map a failure to begin, configure or commit a session transaction.
+    fn session_error(e: &sqlx::Error) -> Status {
+        error!(error = %e, "Session transaction failed");
+        Self::database_error("Session transaction failed", e)
+    }
+
+    /// Status for a failed database call. An exhausted pool or a lost
+    /// connection is `UNAVAILABLE` with a retry hint; anything else is
+    /// `INTERNAL`.
+    fn database_error(context: &str, e: &sqlx::Error) -> Status {
+        let message = format!("{context}: {e}");
+        if Self::is_connection_error(e) {
+            Self::database_unavailable(message)
+        } else {
+            Status::internal(message)
+        }
+    }
+
+    /// Whether `e` means the database could not be reached, rather than
+    /// that the statement failed.
+    const fn is_connection_error(e: &sqlx::Error) -> bool {
+        matches!(
+            e,
+            sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_)
+        )
+    }
+
+    fn database_unavailable(message: impl Into<String>) -> Status {
+        ErrorDetail::new("DATABASE_UNAVAILABLE", message)
+            .with_retry_after(DATABASE_RETRY_AFTER)
+            .into_status(Code::Unavailable)
+    }
+
+    /// Fetch every row, inside the caller's interactive transaction if one is
+    /// named, else inside a session transaction when variables are set.
+    ///
+    /// An interactive transaction keeps the session variables it was begun
+    /// with. Outside one, read-only SQL runs on a replica unless
+    /// `read_primary` is set.
+    async fn fetch_all(
+        &self,
+        vars: Option<SessionVars>,
+        transaction_id: Option<&str>,
+        read_primary: bool,
+        sql: &str,
+        params: &[ProtoValue],
+    ) -> Result<Vec<AnyRow>, Status> {
+        let rows = if let Some(id) = transaction_id {
+            let mut active = self.transactions.get(id).await?;
+            sqlx::query_with(sql, Self::bind_params(params))
+                .fetch_all(active.connection())
+                .await
+        } else {
+            let vars = vars.as_ref();
+            self.read(read_primary, sql, |pool| {
+                Self::fetch_all_on(pool, vars, sql, params)
+            })
+            .await
+        };
+        rows.map_err(|e| {
+            error!(error = %e, "Query execution failed");
+            Self::database_error("Query failed", &e)
+        })
+    }
+
+    /// Fetch every row from `pool`, in a session transaction when variables
+    /// are set.
+    async fn fetch_all_on(
+        pool: &AnyPool,
+        vars: Option<&SessionVars>,
+        sql: &str,
+        params: &[ProtoValue],
+    ) -> Result<Vec<AnyRow>, sqlx::Error> {
+        let query = sqlx::query_with(sql, Self::bind_params(params));
+        let Some(vars) = vars else {
+            return query.fetch_all(pool).await;
+        };
+        let mut tx = vars.begin(pool).await?;
+        let rows = query.fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Fetch at most one row from `pool`, in a session transaction when
+    /// variables are set.
+    async fn fetch_optional_on(
+        pool: &AnyPool,
+        vars: Option<&SessionVars>,
+        sql: &str,
+        params: &[ProtoValue],
+    ) -> Result<Option<AnyRow>, sqlx::Error> {
+        let query = sqlx::query_with(sql, Self::bind_params(params));
+        let Some(vars) = vars else {
+            return query.fetch_optional(pool).await;
+        };
+        let mut tx = vars.begin(pool).await?;
+        let row = query.fetch_optional(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Replica to run `sql` on, if it only reads and one is healthy.
+    fn read_replica(&self, read_primary: bool, sql: &str) -> Option<&Replica> {
+        if read_primary || self.replicas.is_empty() || !is_read_only(sql) {
+            return None;
+        }
+        self.replicas.pick()
+    }
+
+    /// Run a read on a replica when `sql` allows it, else on the primary.
+    ///
+    /// A replica that cannot be reached is marked down and the read retried
+    /// on the primary.
+    async fn read<'a, T, F, Fut>(
+        &'a self,
+        read_primary: bool,
+        sql: &str,
+        run: F,
+    ) -> Result<T, sqlx::Error>
+    where
+        F: Fn(&'a AnyPool) -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        if let Some(replica) = self.read_replica(read_primary, sql) {
+            match run(replica.pool()).await {
+                Err(e) if Self::is_connection_error(&e) => {
+                    warn!(error = %e, "Read replica unreachable; retrying on the primary");
+                    replica.mark_down();
+                }
+                result => return result,
+            }
+        }
+        run(&self.pool).await
+    }
+
+    /// Stream the rows of `sql` from `pool` into `tx`, in a session
+    /// transaction when variables are set.
+    ///
+    /// A failure after the first chunk ends the stream with its status.
+    async fn stream_rows(
+        pool: AnyPool,
+        vars: Option<SessionVars>,
+        sql: String,
+        params: Vec<ProtoValue>,
+        chunk_rows: usize,
+        tx: mpsc::Sender<Result<RowChunk, Status>>,
+    ) {
+        let query = sqlx::query_with(&sql, Self::bind_params(&params));
+        let result = if let Some(vars) = vars {
+            match vars.begin(&pool).await {
+                Ok(mut session) => {
+                    let rows = query.fetch(&mut *session);
+                    let sent =
+                        streaming::send_chunks(rows, chunk_rows, &tx, Self::row_to_proto).await;
+                    match sent {
+                        Ok(sent) => session.commit().await.map(|()| sent),
+                        Err(e) => Err(e),
+                    }
+                }
+                Err(e) => Err(e),
+            }
+        } else {
+            let rows = query.fetch(&pool);
+            streaming::send_chunks(rows, chunk_rows, &tx, Self::row_to_proto).await
+        };
+        match result {
+            Ok(sent) => debug!(rows = sent, "Query stream finished"),
+            Err(e) => {
+                error!(error = %e, "Query stream failed");
+                // The client may be gone already; nothing left to tell
+                let _ = tx.send(Err(Self::database_error("Query failed", &e))).await;
+            }
         }
     }
 
+    /// Execute a statement, inside the caller's interactive transaction if
+    /// one is named, else inside a session transaction when variables are
+    /// set.
+    async fn execute_statement(
+        &self,
+        vars: Option<SessionVars>,
+        transaction_id: Option<&str>,
+        sql: &str,
+        params: &[ProtoValue],
+    ) -> Result<AnyQueryResult, Status> {
+        let query = sqlx::query_with(sql, Self::bind_params(params));
+
+        let result = match (transaction_id, vars) {
+            (Some(id), _) => {
+                let mut active = self.transactions.get(id).await?;
+                query.execute(active.connection()).await
+            }
+            (None, Some(vars)) => {
+                let mut tx = vars
+                    .begin(&self.pool)
+                    .await
+                    .map_err(|e| Self::session_error(&e))?;
+                let result = query.execute(&mut *tx).await;
+                if result.is_ok() {
+                    tx.commit().await.map_err(|e| Self::session_error(&e))?;
+                }
+                result
+            }
+            (None, None) => query.execute(&self.pool).await,
+        };
+        result.map_err(|e| {
+            error!(error = %e, "Execute failed");
+            Self::database_error("Execute failed", &e)
+        })
+    }
+
     /// Convert proto values to SQLx arguments.
     fn bind_params(params: &[ProtoValue]) -> AnyArguments<'_> {
         let mut args = AnyArguments::default();
@@ -164,19 +576,26 @@ impl DataServiceImpl {
 
 #[tonic::async_trait]
 impl DataService for DataServiceImpl {
+    type QueryStreamStream = RowChunkStream;
+
     async fn query(
         &self,
         request: Request<QueryRequest>,
     ) -> Result<Response<QueryResponse>, Status> {
+        self.check_raw_sql()?;
+        let vars = self.session_vars(&request).await?;
         let req = request.into_inner();
         debug!(sql = %req.sql, "Executing query");
 
-        let query = sqlx::query_with(&req.sql, Self::bind_params(&req.params));
-
-        let rows: Vec<AnyRow> = query.fetch_all(&self.pool).await.map_err(|e| {
-            error!(error = %e, "Query execution failed");
-            Status::internal(format!("Query failed: {e}"))
-        })?;
+        let rows = self
+            .fetch_all(
+                vars,
+                req.transaction_id.as_deref(),
+                req.read_primary,
+                &req.sql,
+                &req.params,
+            )
+            .await?;
 
         let proto_rows: Vec<Row> = rows.iter().map(Self::row_to_proto).collect();
         let rows_returned = Self::usize_to_i64(proto_rows.len());
@@ -191,15 +610,14 @@ impl DataService for DataServiceImpl {
         &self,
         request: Request<ExecuteRequest>,
     ) -> Result<Response<ExecuteResponse>, Status> {
+        self.check_raw_sql()?;
+        let vars = self.session_vars(&request).await?;
         let req = request.into_inner();
         debug!(sql = %req.sql, "Executing statement");
 
-        let query = sqlx::query_with(&req.sql, Self::bind_params(&req.params));
-
-        let result = query.execute(&self.pool).await.map_err(|e| {
-            error!(error = %e, "Execute failed");
-            Status::internal(format!("Execute failed: {e}"))
-        })?;
+        let result = self
+            .execute_statement(vars, req.transaction_id.as_deref(), &req.sql, &req.params)
+            .await?;
 
         let rows_affected = Self::u64_to_i64(result.rows_affected());
 
@@ -213,14 +631,26 @@ impl DataService for DataServiceImpl {
         &self,
         request: Request<QueryRequest>,
     ) -> Result<Response<QueryOneResponse>, Status> {
+        self.check_raw_sql()?;
+        let vars = self.session_vars(&request).await?;
         let req = request.into_inner();
         debug!(sql = %req.sql, "Executing query_one");
 
-        let query = sqlx::query_with(&req.sql, Self::bind_params(&req.params));
-
-        let row: Option<AnyRow> = query.fetch_optional(&self.pool).await.map_err(|e| {
+        let row = if let Some(id) = req.transaction_id.as_deref() {
+            let mut active = self.transactions.get(id).await?;
+            sqlx::query_with(&req.sql, Self::bind_params(&req.params))
+                .fetch_optional(active.connection())
+                .await
+        } else {
+            let vars = vars.as_ref();
+            self.read(req.read_primary, &req.sql, |pool| {
+                Self::fetch_optional_on(pool, vars, &req.sql, &req.params)
+            })
+            .await
+        };
+        let row: Option<AnyRow> = row.map_err(|e| {
             error!(error = %e, "Query one failed");
-            Status::internal(format!("Query failed: {e}"))
+            Self::database_error("Query failed", &e)
         })?;
 
         let proto_row = row.as_ref().map(Self::row_to_proto);
@@ -228,22 +658,97 @@ impl DataService for DataServiceImpl {
         Ok(Response::new(QueryOneResponse { row: proto_row }))
     }
 
+    async fn query_stream(
+        &self,
+        request: Request<QueryStreamRequest>,
+    ) -> Result<Response<Self::QueryStreamStream>, Status> {
+        self.check_raw_sql()?;
+        let vars = self.session_vars(&request).await?;
+        let req = request.into_inner();
+        let chunk_rows = self.stream_limits.chunk_rows_for(req.chunk_rows)?;
+        debug!(sql = %req.sql, chunk_rows, "Streaming query");
+
+        // A replica that fails mid-stream cannot be retried on the primary,
+        // since rows have already been sent
+        let pool = self
+            .read_replica(req.read_primary, &req.sql)
+            .map_or_else(|| self.pool.clone(), |replica| replica.pool().clone());
+        let (tx, rx) = mpsc::channel(self.stream_limits.buffered_chunks.max(1));
+        tokio::spawn(Self::stream_rows(
+            pool, vars, req.sql, req.params, chunk_rows, tx,
+        ));
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn query_named(
+        &self,
+        request: Request<NamedQueryRequest>,
+    ) -> Result<Response<QueryResponse>, Status> {
+        let vars = self.session_vars(&request).await?;
+        let req = request.into_inner();
+        let query = self.queries.get(&req.name)?;
+        query.check_params(&req.params)?;
+        debug!(name = %req.name, "Executing named query");
+
+        let rows = self
+            .fetch_all(vars, None, req.read_primary, &query.sql, &req.params)
+            .await?;
+
+        let proto_rows: Vec<Row> = rows.iter().map(Self::row_to_proto).collect();
+        let rows_returned = Self::usize_to_i64(proto_rows.len());
+
+        Ok(Response::new(QueryResponse {
+            rows: proto_rows,
+            rows_returned,
+        }))
+    }
+
+    async fn execute_named(
+        &self,
+        request: Request<NamedQueryRequest>,
+    ) -> Result<Response<ExecuteResponse>, Status> {
+        let vars = self.session_vars(&request).await?;
+        let req = request.into_inner();
+        let query = self.queries.get(&req.name)?;
+        query.check_params(&req.params)?;
+        debug!(name = %req.name, "Executing named statement");
+
+        let result = self
+            .execute_statement(vars, None, &query.sql, &req.params)
+            .await?;
+
+        Ok(Response::new(ExecuteResponse {
+            rows_affected: Self::u64_to_i64(result.rows_affected()),
+            last_insert_id: None,
+        }))
+    }
+
     async fn begin_transaction(
         &self,
-        _request: Request<BeginTransactionRequest>,
+        request: Request<BeginTransactionRequest>,
     ) -> Result<Response<TransactionResponse>, Status> {
-        // Generate unique transaction ID
-        let transaction_id = uuid::Uuid::new_v4().to_string();
-
-        // Store the transaction
-        self.transactions.insert(
-            transaction_id.clone(),
-            ActiveTransaction {
-                _created_at: Instant::now(),
-            },
-        );
+        let vars = self.session_vars(&request).await?;
+        let req = request.into_inner();
+        let timeout = self.transactions.timeout_for(req.timeout_ms)?;
+        self.transactions.check_capacity()?;
+
+        let tx = match vars {
+            Some(vars) => vars.begin(&self.pool).await,
+            None => self.pool.begin().await,
+        }
+        .map_err(|e| {
+            error!(error = %e, "Begin transaction failed");
+            Self::database_error("Begin transaction failed", &e)
+        })?;
+        let transaction_id = self.transactions.insert(tx, timeout);
 
-        info!(transaction_id = %transaction_id, "Transaction started");
+        info!(
+            transaction_id = %transaction_id,
+            timeout_ms = %timeout.as_millis(),
+            open = self.transactions.len(),
+            "Transaction started"
+        );
 
         Ok(Response::new(TransactionResponse {
             transaction_id,
@@ -258,16 +763,19 @@ impl DataService for DataServiceImpl {
         let req = request.into_inner();
         let transaction_id = req.transaction_id;
 
-        if self.transactions.remove(&transaction_id).is_some() {
-            info!(transaction_id = %transaction_id, "Transaction committed");
-            Ok(Response::new(TransactionResponse {
-                transaction_id,
-                success: true,
-            }))
-        } else {
+        let tx = self.transactions.take(&transaction_id).await.inspect_err(|_| {
             warn!(transaction_id = %transaction_id, "Transaction not found");
-            Err(Status::not_found("Transaction not found"))
-        }
+        })?;
+        tx.commit().await.map_err(|e| {
+            error!(transaction_id = %transaction_id, error = %e, "Commit failed");
+            Self::database_error("Commit failed", &e)
+        })?;
+
+        info!(transaction_id = %transaction_id, "Transaction committed");
+        Ok(Response::new(TransactionResponse {
+            transaction_id,
+            success: true,
+        }))
     }
 
     async fn rollback_transaction(
@@ -277,41 +785,38 @@ impl DataService for DataServiceImpl {
         let req = request.into_inner();
         let transaction_id = req.transaction_id;
 
-        if self.transactions.remove(&transaction_id).is_some() {
-            info!(transaction_id = %transaction_id, "Transaction rolled back");
-            Ok(Response::new(TransactionResponse {
-                transaction_id,
-                success: true,
-            }))
-        } else {
+        let tx = self.transactions.take(&transaction_id).await.inspect_err(|_| {
             warn!(transaction_id = %transaction_id, "Transaction not found");
-            Err(Status::not_found("Transaction not found"))
-        }
+        })?;
+        tx.rollback().await.map_err(|e| {
+            error!(transaction_id = %transaction_id, error = %e, "Rollback failed");
+            Self::database_error("Rollback failed", &e)
+        })?;
+
+        info!(transaction_id = %transaction_id, "Transaction rolled back");
+        Ok(Response::new(TransactionResponse {
+            transaction_id,
+            success: true,
+        }))
     }
 
     async fn execute_in_transaction(
         &self,
         request: Request<TransactionExecuteRequest>,
     ) -> Result<Response<ExecuteResponse>, Status> {
+        self.check_raw_sql()?;
+        self.identity.authenticate(request.metadata()).await?;
         let req = request.into_inner();
 
-        if !self.transactions.contains_key(&req.transaction_id) {
-            return Err(Status::not_found("Transaction not found"));
-        }
-
         debug!(
             transaction_id = %req.transaction_id,
             sql = %req.sql,
             "Executing in transaction"
         );
 
-        // Execute the query (simplified - in production you'd use actual transaction)
-        let query = sqlx::query_with(&req.sql, Self::bind_params(&req.params));
-
-        let result = query.execute(&self.pool).await.map_err(|e| {
-            error!(error = %e, "Transaction execute failed");
-            Status::internal(format!("Execute failed: {e}"))
-        })?;
+        let result = self
+            .execute_statement(None, Some(&req.transaction_id), &req.sql, &req.params)
+            .await?;
 
         Ok(Response::new(ExecuteResponse {
             rows_affected: Self::u64_to_i64(result.rows_affected()),
@@ -342,9 +847,108 @@ impl DataService for DataServiceImpl {
         &self,
         _request: Request<MigrationStatusRequest>,
     ) -> Result<Response<MigrationStatusResponse>, Status> {
-        // Note: In production, you'd query the _sqlx_migrations table
+        let Some(migrations) = &self.migrations else {
+            return Ok(Response::new(MigrationStatusResponse { migrations: vec![] }));
+        };
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| Self::database_error("Failed to acquire connection", &e))?;
         Ok(Response::new(MigrationStatusResponse {
-            migrations: vec![],
+            migrations: migrations.status(&mut conn).await?,
+        }))
+    }
+
+    async fn plan_migrations(
+        &self,
+        request: Request<PlanMigrationsRequest>,
+    ) -> Result<Response<MigrationPlan>, Status> {
+        let req = request.into_inner();
+        debug!(target_version = ?req.target_version, "Planning migrations");
+        let (migrations, mut conn) = self.migrations().await?;
+        let plan = migrations.plan(&mut conn, req.target_version).await?;
+        Ok(Response::new(plan))
+    }
+
+    async fn apply_migrations(
+        &self,
+        request: Request<ApplyMigrationsRequest>,
+    ) -> Result<Response<MigrationPlan>, Status> {
+        let req = request.into_inner();
+        info!(target_version = ?req.target_version, "Applying migrations");
+        let (migrations, mut conn) = self.migrations().await?;
+        let plan = migrations.apply(&mut conn, req.target_version).await?;
+        Ok(Response::new(plan))
+    }
+
+    async fn rollback_migration(
+        &self,
+        request: Request<RollbackMigrationRequest>,
+    ) -> Result<Response<MigrationPlan>, Status> {
+        let req = request.into_inner();
+        info!(target_version = ?req.target_version, "Rolling back migrations");
+        let (migrations, mut conn) = self.migrations().await?;
+        let plan = migrations.rollback(&mut conn, req.target_version).await?;
+        Ok(Response::new(plan))
+    }
+
+    async fn describe_schema(
+        &self,
+        request: Request<DescribeSchemaRequest>,
+    ) -> Result<Response<DescribeSchemaResponse>, Status> {
+        let req = request.into_inner();
+        debug!(tables = ?req.tables, "Describing schema");
+
+        let dialect = Dialect::of(&self.pool).ok_or_else(|| {
+            Status::unimplemented("Schema introspection not supported for this database")
+        })?;
+
+        let tables = schema::describe(&self.pool, dialect, &req.tables)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Schema introspection failed");
+                Status::internal(format!("Describe schema failed: {e}"))
+            })?;
+
+        Ok(Response::new(DescribeSchemaResponse {
+            dialect: dialect.as_str().to_string(),
+            tables,
+        }))
+    }
+
+    async fn explain_query(
+        &self,
+        request: Request<ExplainQueryRequest>,
+    ) -> Result<Response<ExplainQueryResponse>, Status> {
+        if !self.explain_enabled {
+            return Err(Status::failed_precondition(
+                "ExplainQuery is disabled; set service.explain_enabled for development",
+            ));
+        }
+        let req = request.into_inner();
+        debug!(sql = %req.sql, analyze = req.analyze, "Explaining query");
+
+        let dialect = Dialect::of(&self.pool).ok_or_else(|| {
+            Status::unimplemented("EXPLAIN not supported for this database")
+        })?;
+
+        let plan = explain::explain(
+            &self.pool,
+            dialect,
+            &req.sql,
+            Self::bind_params(&req.params),
+            req.analyze,
+        )
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Explain failed");
+            Status::invalid_argument(format!("Explain failed: {e}"))
+        })?;
+
+        Ok(Response::new(ExplainQueryResponse {
+            dialect: dialect.as_str().to_string(),
+            plan,
         }))
     }
 
@@ -357,7 +961,7 @@ impl DataService for DataServiceImpl {
             .await
             .map_err(|e| {
                 error!(error = %e, "Database ping failed");
-                Status::unavailable("Database unavailable")
+                Self::database_unavailable("Database unavailable")
             })?;
 
         let latency_ms = Self::u128_to_i64(start.elapsed().as_millis());
@@ -373,6 +977,18 @@ impl DataService for DataServiceImpl {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_database_errors() {
+        let status = DataServiceImpl::database_error("Query failed", &sqlx::Error::PoolTimedOut);
+        assert_eq!(status.code(), Code::Unavailable);
+        let detail = ErrorDetail::from_status(&status).expect("detail attached");
+        assert_eq!(detail.code, "DATABASE_UNAVAILABLE");
+        assert_eq!(detail.retry_after(), Some(DATABASE_RETRY_AFTER));
+
+        let status = DataServiceImpl::database_error("Query failed", &sqlx::Error::RowNotFound);
+        assert_eq!(status.code(), Code::Internal);
+    }
+
     #[test]
     fn test_proto_value_conversion() {
         // Test null value
@@ -400,6 +1016,208 @@ mod tests {
         assert!(matches!(int_val.value, Some(ProtoValueInner::IntValue(42))));
     }
 
+    #[tokio::test]
+    async fn test_raw_sql_disabled() {
+        sqlx::any::install_default_drivers();
+        let pool = sqlx::any::AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let mut queries = QueryRegistry::new();
+        queries.add_sql("one", "SELECT 1 AS one").unwrap();
+        let service = DataServiceImpl::new(pool)
+            .with_queries(queries)
+            .with_raw_sql(false);
+
+        let status = service
+            .query(Request::new(QueryRequest {
+                sql: "SELECT 1 AS one".to_string(),
+                params: vec![],
+                transaction_id: None,
+                read_primary: false,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        let detail = ErrorDetail::from_status(&status).expect("detail attached");
+        assert_eq!(detail.code, "RAW_SQL_DISABLED");
+
+        let response = service
+            .query_named(Request::new(NamedQueryRequest {
+                name: "one".to_string(),
+                params: vec![],
+                read_primary: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.rows_returned, 1);
+    }
+
+    #[tokio::test]
+    async fn test_identity_variable_refused_without_token() {
+        use super::super::session_vars::SESSION_VAR_METADATA_KEY;
+        use tonic::metadata::MetadataValue;
+
+        sqlx::any::install_default_drivers();
+        let pool = sqlx::any::AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        // Even when allowlisted, as in the shipped example config
+        let service = DataServiceImpl::new(pool)
+            .with_session_variables(vec!["app.user_id".to_string(), "app.tenant_id".to_string()])
+            .with_identity(
+                IdentityVerifier::disabled(),
+                Some("app.user_id".to_string()),
+            );
+        let query = |var: &'static str| {
+            let mut request = Request::new(QueryRequest {
+                sql: "SELECT 1 AS one".to_string(),
+                params: vec![],
+                transaction_id: None,
+                read_primary: false,
+            });
+            request
+                .metadata_mut()
+                .append(SESSION_VAR_METADATA_KEY, MetadataValue::from_static(var));
+            service.query(request)
+        };
+
+        let status = query("app.user_id=7").await.unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        let detail = ErrorDetail::from_status(&status).expect("detail attached");
+        assert_eq!(detail.code, "SESSION_VAR_NOT_ALLOWED");
+
+        assert!(query("app.tenant_id=3").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_interactive_transaction() {
+        sqlx::any::install_default_drivers();
+        // One connection, so every call sees the same in-memory database
+        let pool = sqlx::any::AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE items (id INTEGER PRIMARY KEY)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let service = DataServiceImpl::new(pool);
+        let count = |transaction_id: Option<String>| {
+            let service = &service;
+            async move {
+                service
+                    .query(Request::new(QueryRequest {
+                        sql: "SELECT id FROM items".to_string(),
+                        params: vec![],
+                        transaction_id,
+                        read_primary: false,
+                    }))
+                    .await
+                    .unwrap()
+                    .into_inner()
+                    .rows_returned
+            }
+        };
+
+        let transaction_id = service
+            .begin_transaction(Request::new(BeginTransactionRequest { timeout_ms: None }))
+            .await
+            .unwrap()
+            .into_inner()
+            .transaction_id;
+        service
+            .execute_in_transaction(Request::new(TransactionExecuteRequest {
+                transaction_id: transaction_id.clone(),
+                sql: "INSERT INTO items (id) VALUES (1)".to_string(),
+                params: vec![],
+            }))
+            .await
+            .unwrap();
+
+        // The insert is visible inside the transaction only
+        assert_eq!(count(Some(transaction_id.clone())).await, 1);
+
+        service
+            .rollback_transaction(Request::new(RollbackTransactionRequest {
+                transaction_id: transaction_id.clone(),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(count(None).await, 0);
+
+        let status = service
+            .commit_transaction(Request::new(CommitTransactionRequest { transaction_id }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_query_stream_chunks() {
+        use tokio_stream::StreamExt;
+
+        sqlx::any::install_default_drivers();
+        let pool = sqlx::any::AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE items (id INTEGER PRIMARY KEY)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO items (id) VALUES (1), (2), (3), (4), (5)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let service = DataServiceImpl::new(pool);
+        let request = |chunk_rows| {
+            Request::new(QueryStreamRequest {
+                sql: "SELECT id FROM items ORDER BY id".to_string(),
+                params: vec![],
+                read_primary: false,
+                chunk_rows,
+            })
+        };
+
+        let chunks: Vec<RowChunk> = service
+            .query_stream(request(2))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect::<Result<_, _>>()
+            .await
+            .unwrap();
+        let sizes: Vec<usize> = chunks.iter().map(|chunk| chunk.rows.len()).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+        assert!(matches!(
+            chunks[2].rows[0].columns["id"].value,
+            Some(ProtoValueInner::IntValue(5))
+        ));
+
+        let status = service.query_stream(request(100_000)).await.err().unwrap();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let mut stream = service
+            .query_stream(Request::new(QueryStreamRequest {
+                sql: "SELECT id FROM missing".to_string(),
+                params: vec![],
+                read_primary: false,
+                chunk_rows: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let status = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Internal);
+    }
+
     #[test]
     fn test_safe_conversions() {
         assert_eq!(DataServiceImpl::usize_to_i64(100), 100);
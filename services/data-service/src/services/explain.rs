@@ -0,0 +1,152 @@
+//! Query plans for the development-only `ExplainQuery` RPC.
+//!
+//! Plans are captured inside a transaction that is always rolled back, so
+//! `EXPLAIN ANALYZE` of an `INSERT` or `UPDATE` reports real timings without
+//! keeping its writes.
+
+use super::schema::Dialect;
+use sqlx::any::{AnyArguments, AnyRow};
+use sqlx::{AnyPool, Row};
+
+/// Capture the plan for `sql` with its bound `args`, one line per entry.
+///
+/// SQLite has no `ANALYZE` form, so `analyze` only applies to Postgres.
+///
+/// # Errors
+///
+/// Returns error if the statement cannot be planned or executed.
+pub async fn explain(
+    pool: &AnyPool,
+    dialect: Dialect,
+    sql: &str,
+    args: AnyArguments<'_>,
+    analyze: bool,
+) -> Result<Vec<String>, sqlx::Error> {
+    let statement = explain_sql(dialect, sql, analyze);
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query_with(&statement, args).fetch_all(&mut *tx).await;
+    tx.rollback().await?;
+    let rows = rows?;
+
+    match dialect {
+        Dialect::Postgres => rows.iter().map(|row| row.try_get::<String, _>(0)).collect(),
+        Dialect::Sqlite => sqlite_plan(&rows),
+    }
+}
+
+/// Prefix a statement with the dialect's `EXPLAIN` form.
+fn explain_sql(dialect: Dialect, sql: &str, analyze: bool) -> String {
+    let sql = sql.trim().trim_end_matches(';');
+    match dialect {
+        Dialect::Postgres if analyze => format!("EXPLAIN (ANALYZE, BUFFERS) {sql}"),
+        Dialect::Postgres => format!("EXPLAIN {sql}"),
+        Dialect::Sqlite => format!("EXPLAIN QUERY PLAN {sql}"),
+    }
+}
+
+/// Indent SQLite's `(id, parent, notused, detail)` rows into a tree.
+fn sqlite_plan(rows: &[AnyRow]) -> Result<Vec<String>, sqlx::Error> {
+    let mut nodes = Vec::with_capacity(rows.len());
+    for row in rows {
+        nodes.push((
+            row.try_get::<i64, _>(0)?,
+            row.try_get::<i64, _>(1)?,
+            row.try_get::<String, _>(3)?,
+        ));
+    }
+    Ok(indent_tree(&nodes))
+}
+
+fn indent_tree(nodes: &[(i64, i64, String)]) -> Vec<String> {
+    let mut depths: Vec<(i64, usize)> = Vec::with_capacity(nodes.len());
+    nodes
+        .iter()
+        .map(|(id, parent, detail)| {
+            let depth = depths
+                .iter()
+                .find(|(node, _)| node == parent)
+                .map_or(0, |(_, depth)| depth + 1);
+            depths.push((*id, depth));
+            format!("{}{detail}", "  ".repeat(depth))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::any::AnyPoolOptions;
+
+    #[test]
+    fn test_explain_sql() {
+        assert_eq!(
+            explain_sql(Dialect::Postgres, "SELECT 1;", true),
+            "EXPLAIN (ANALYZE, BUFFERS) SELECT 1"
+        );
+        assert_eq!(
+            explain_sql(Dialect::Postgres, "SELECT 1", false),
+            "EXPLAIN SELECT 1"
+        );
+        assert_eq!(
+            explain_sql(Dialect::Sqlite, " SELECT 1 ", true),
+            "EXPLAIN QUERY PLAN SELECT 1"
+        );
+    }
+
+    #[test]
+    fn test_indent_tree() {
+        let nodes = [
+            (2, 0, "SCAN orders".to_string()),
+            (5, 0, "SCALAR SUBQUERY 1".to_string()),
+            (9, 5, "SEARCH items USING INDEX idx_items_order".to_string()),
+        ];
+        assert_eq!(
+            indent_tree(&nodes),
+            [
+                "SCAN orders",
+                "SCALAR SUBQUERY 1",
+                "  SEARCH items USING INDEX idx_items_order",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_explain_sqlite() {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE orders (id INTEGER PRIMARY KEY, status TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let scan = explain(
+            &pool,
+            Dialect::Sqlite,
+            "SELECT * FROM orders WHERE status = 'open'",
+            AnyArguments::default(),
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(scan[0].starts_with("SCAN orders"), "{scan:?}");
+
+        sqlx::query("CREATE INDEX idx_orders_status ON orders (status)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let search = explain(
+            &pool,
+            Dialect::Sqlite,
+            "SELECT * FROM orders WHERE status = 'open'",
+            AnyArguments::default(),
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(search[0].contains("idx_orders_status"), "{search:?}");
+    }
+}
@@ -0,0 +1,396 @@
+//! Migrations for the `PlanMigrations`, `ApplyMigrations` and
+//! `RollbackMigration` RPCs.
+//!
+//! The directory uses the sqlx layout: `<version>_<description>.sql` for
+//! one-way migrations, or `.up.sql` / `.down.sql` pairs for reversible
+//! ones. Applied versions are recorded in `_sqlx_migrations`, so a database
+//! migrated here can still be migrated with `sqlx migrate`, and the other
+//! way around.
+
+use acton_dx_proto::data::v1::{MigrationInfo, MigrationPlan, MigrationStep};
+use acton_dx_proto::error::invalid_field;
+use acton_dx_proto::error::v1::ErrorDetail;
+use anyhow::Context;
+use sqlx::migrate::{AppliedMigration, Migrate, MigrateError, Migration, Migrator};
+use sqlx::AnyConnection;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tonic::{Code, Status};
+use tracing::info;
+
+/// Migrations loaded from a directory at startup.
+#[derive(Debug, Clone)]
+pub struct MigrationSet {
+    migrator: Arc<Migrator>,
+}
+
+impl MigrationSet {
+    /// Load the migrations in `dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the directory cannot be read or holds a file that
+    /// is not a valid migration.
+    pub async fn load(dir: &Path) -> anyhow::Result<Self> {
+        let migrator = Migrator::new(dir)
+            .await
+            .with_context(|| format!("Failed to load migrations from {}", dir.display()))?;
+        Ok(Self {
+            migrator: Arc::new(migrator),
+        })
+    }
+
+    /// Number of migrations, counting a reversible pair once.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ups().count()
+    }
+
+    /// Whether the directory held no migrations.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every migration, with whether it has been applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error reading the migrations table.
+    pub(super) async fn status(
+        &self,
+        conn: &mut AnyConnection,
+    ) -> Result<Vec<MigrationInfo>, Status> {
+        let applied = applied_migrations(conn).await?;
+        Ok(self
+            .ups()
+            .map(|migration| MigrationInfo {
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: applied.contains_key(&migration.version),
+                applied_at: None,
+            })
+            .collect())
+    }
+
+    /// Migrations `apply` would run for `target`, without running them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `INVALID_ARGUMENT` for an unknown target and
+    /// `FAILED_PRECONDITION` if applied migrations no longer match the
+    /// directory.
+    pub(super) async fn plan(
+        &self,
+        conn: &mut AnyConnection,
+        target: Option<i64>,
+    ) -> Result<MigrationPlan, Status> {
+        let applied = self.checked_applied(conn).await?;
+        let steps = self.pending(&applied, target)?;
+        Ok(plan(&steps, latest_after(&applied, &steps)))
+    }
+
+    /// Apply pending migrations up to and including `target`, or all of
+    /// them.
+    ///
+    /// Each migration runs in its own transaction, so a failure leaves the
+    /// ones before it applied.
+    ///
+    /// # Errors
+    ///
+    /// As [`plan`](Self::plan), and returns the error that stopped a
+    /// migration.
+    pub(super) async fn apply(
+        &self,
+        conn: &mut AnyConnection,
+        target: Option<i64>,
+    ) -> Result<MigrationPlan, Status> {
+        locked(conn, async |conn| {
+            let applied = self.checked_applied(conn).await?;
+            let steps = self.pending(&applied, target)?;
+            for migration in &steps {
+                let elapsed = conn.apply(migration).await.map_err(|e| migrate_error(&e))?;
+                info!(
+                    version = migration.version,
+                    description = %migration.description,
+                    ?elapsed,
+                    "Migration applied"
+                );
+            }
+            Ok(plan(&steps, latest_after(&applied, &steps)))
+        })
+        .await
+    }
+
+    /// Revert applied migrations above `target`, or the latest one.
+    ///
+    /// Target zero reverts every migration. Nothing is reverted unless
+    /// every migration above the target has a down script.
+    ///
+    /// # Errors
+    ///
+    /// Returns `INVALID_ARGUMENT` for an unknown target,
+    /// `FAILED_PRECONDITION` for an irreversible migration or applied
+    /// migrations that no longer match the directory, and the error that
+    /// stopped a down script.
+    #[allow(clippy::result_large_err)]
+    pub(super) async fn rollback(
+        &self,
+        conn: &mut AnyConnection,
+        target: Option<i64>,
+    ) -> Result<MigrationPlan, Status> {
+        locked(conn, async |conn| {
+            let applied = self.checked_applied(conn).await?;
+            let mut versions: Vec<i64> = applied.keys().copied().collect();
+            versions.sort_unstable_by(|a, b| b.cmp(a));
+
+            let target = match target {
+                Some(target) => self.check_target(target, true)?,
+                None => versions.get(1).copied().unwrap_or(0),
+            };
+            let steps = versions
+                .iter()
+                .take_while(|version| **version > target)
+                .map(|version| self.down(*version).ok_or_else(|| not_reversible(*version)))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            for migration in &steps {
+                let elapsed = conn
+                    .revert(migration)
+                    .await
+                    .map_err(|e| migrate_error(&e))?;
+                info!(
+                    version = migration.version,
+                    description = %migration.description,
+                    ?elapsed,
+                    "Migration reverted"
+                );
+            }
+            let resulting_version = versions.into_iter().find(|version| *version <= target);
+            Ok(plan(&steps, resulting_version))
+        })
+        .await
+    }
+
+    /// Up and one-way migrations, oldest first.
+    fn ups(&self) -> impl Iterator<Item = &Migration> {
+        self.migrator
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+    }
+
+    fn down(&self, version: i64) -> Option<&Migration> {
+        self.migrator.iter().find(|migration| {
+            migration.version == version && migration.migration_type.is_down_migration()
+        })
+    }
+
+    /// Check `target` names a migration; zero is allowed when `allow_zero`.
+    #[allow(clippy::result_large_err)]
+    fn check_target(&self, target: i64, allow_zero: bool) -> Result<i64, Status> {
+        if (allow_zero && target == 0) || self.ups().any(|migration| migration.version == target) {
+            return Ok(target);
+        }
+        Err(invalid_field(
+            "target_version",
+            format!("no migration has version {target}"),
+        ))
+    }
+
+    /// Migrations not yet applied, up to and including `target`.
+    #[allow(clippy::result_large_err)]
+    fn pending(
+        &self,
+        applied: &HashMap<i64, AppliedMigration>,
+        target: Option<i64>,
+    ) -> Result<Vec<&Migration>, Status> {
+        let target = target
+            .map(|target| self.check_target(target, false))
+            .transpose()?;
+        Ok(self
+            .ups()
+            .filter(|migration| !applied.contains_key(&migration.version))
+            .filter(|migration| target.is_none_or(|target| migration.version <= target))
+            .collect())
+    }
+
+    /// Applied migrations, after checking none was changed or removed from
+    /// the directory since it ran.
+    async fn checked_applied(
+        &self,
+        conn: &mut AnyConnection,
+    ) -> Result<HashMap<i64, AppliedMigration>, Status> {
+        let applied = applied_migrations(conn).await?;
+        for (version, migration) in &applied {
+            match self.ups().find(|source| source.version == *version) {
+                None => return Err(migrate_error(&MigrateError::VersionMissing(*version))),
+                Some(source) if source.checksum != migration.checksum => {
+                    return Err(migrate_error(&MigrateError::VersionMismatch(*version)));
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(applied)
+    }
+}
+
+/// Run `f` holding the migration lock, releasing it even if `f` fails,
+/// since the connection goes back to the pool.
+async fn locked<T>(
+    conn: &mut AnyConnection,
+    f: impl AsyncFnOnce(&mut AnyConnection) -> Result<T, Status>,
+) -> Result<T, Status> {
+    conn.lock().await.map_err(|e| migrate_error(&e))?;
+    let result = f(conn).await;
+    let unlocked = conn.unlock().await.map_err(|e| migrate_error(&e));
+    let value = result?;
+    unlocked?;
+    Ok(value)
+}
+
+/// Applied migrations by version, creating the migrations table if needed.
+async fn applied_migrations(
+    conn: &mut AnyConnection,
+) -> Result<HashMap<i64, AppliedMigration>, Status> {
+    conn.ensure_migrations_table()
+        .await
+        .map_err(|e| migrate_error(&e))?;
+    if let Some(version) = conn.dirty_version().await.map_err(|e| migrate_error(&e))? {
+        return Err(migrate_error(&MigrateError::Dirty(version)));
+    }
+    Ok(conn
+        .list_applied_migrations()
+        .await
+        .map_err(|e| migrate_error(&e))?
+        .into_iter()
+        .map(|migration| (migration.version, migration))
+        .collect())
+}
+
+/// Latest version once `steps` are applied after `applied`.
+fn latest_after(applied: &HashMap<i64, AppliedMigration>, steps: &[&Migration]) -> Option<i64> {
+    applied
+        .keys()
+        .copied()
+        .chain(steps.iter().map(|migration| migration.version))
+        .max()
+}
+
+fn plan(steps: &[&Migration], resulting_version: Option<i64>) -> MigrationPlan {
+    MigrationPlan {
+        steps: steps
+            .iter()
+            .map(|migration| MigrationStep {
+                version: migration.version,
+                description: migration.description.to_string(),
+                sql: migration.sql.to_string(),
+            })
+            .collect(),
+        resulting_version,
+    }
+}
+
+fn not_reversible(version: i64) -> Status {
+    ErrorDetail::new(
+        "MIGRATION_NOT_REVERSIBLE",
+        format!("migration {version} has no down script"),
+    )
+    .into_status(Code::FailedPrecondition)
+}
+
+fn migrate_error(e: &MigrateError) -> Status {
+    let code = match e {
+        MigrateError::Dirty(_) => "MIGRATION_DIRTY",
+        MigrateError::VersionMissing(_) => "MIGRATION_MISSING",
+        MigrateError::VersionMismatch(_) => "MIGRATION_MODIFIED",
+        _ => return Status::internal(format!("Migration failed: {e}")),
+    };
+    ErrorDetail::new(code, e.to_string()).into_status(Code::FailedPrecondition)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::any::AnyPoolOptions;
+    use sqlx::Executor;
+
+    fn write(dir: &Path, name: &str, sql: &str) {
+        std::fs::write(dir.join(name), sql).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_plan_apply_rollback() {
+        let dir = std::env::temp_dir().join(format!("acton-migrations-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write(
+            &dir,
+            "1_users.up.sql",
+            "CREATE TABLE users (id INTEGER PRIMARY KEY)",
+        );
+        write(&dir, "1_users.down.sql", "DROP TABLE users");
+        write(
+            &dir,
+            "2_posts.up.sql",
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY)",
+        );
+        write(&dir, "2_posts.down.sql", "DROP TABLE posts");
+        write(&dir, "3_seed.sql", "INSERT INTO users (id) VALUES (1)");
+        let migrations = MigrationSet::load(&dir).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(migrations.len(), 3);
+
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+
+        let planned = migrations.plan(&mut conn, Some(2)).await.unwrap();
+        let versions: Vec<i64> = planned.steps.iter().map(|step| step.version).collect();
+        assert_eq!(versions, [1, 2]);
+        assert_eq!(
+            planned.steps[0].sql,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY)"
+        );
+        assert_eq!(planned.resulting_version, Some(2));
+        // Planning is a dry run
+        assert!(conn.execute("SELECT id FROM users").await.is_err());
+
+        let status = migrations.plan(&mut conn, Some(7)).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let applied = migrations.apply(&mut conn, Some(2)).await.unwrap();
+        assert_eq!(applied.steps.len(), 2);
+        let applied = migrations.apply(&mut conn, None).await.unwrap();
+        assert_eq!(applied.steps[0].version, 3);
+        assert_eq!(applied.resulting_version, Some(3));
+        assert!(migrations
+            .plan(&mut conn, None)
+            .await
+            .unwrap()
+            .steps
+            .is_empty());
+
+        // The seed migration has no down script, so nothing is reverted
+        let status = migrations.rollback(&mut conn, None).await.unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert!(status.message().contains("migration 3"));
+        let info = migrations.status(&mut conn).await.unwrap();
+        assert!(info.iter().all(|migration| migration.applied));
+
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = 3")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        let reverted = migrations.rollback(&mut conn, None).await.unwrap();
+        assert_eq!(reverted.steps[0].sql, "DROP TABLE posts");
+        assert_eq!(reverted.resulting_version, Some(1));
+        let reverted = migrations.rollback(&mut conn, Some(0)).await.unwrap();
+        assert_eq!(reverted.steps[0].version, 1);
+        assert_eq!(reverted.resulting_version, None);
+        assert!(conn.execute("SELECT id FROM users").await.is_err());
+    }
+}
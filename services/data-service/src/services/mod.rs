@@ -1,5 +1,19 @@
 //! gRPC service implementations.
 
 mod data;
+mod explain;
+mod migrations;
+mod queries;
+mod replicas;
+mod schema;
+mod session_vars;
+mod streaming;
+mod transactions;
 
 pub use data::DataServiceImpl;
+pub use migrations::MigrationSet;
+pub use queries::{NamedQuery, ParamSpec, ParamType, QueryRegistry};
+pub use replicas::ReplicaSet;
+pub use session_vars::SESSION_VAR_METADATA_KEY;
+pub use streaming::StreamLimits;
+pub use transactions::TransactionLimits;
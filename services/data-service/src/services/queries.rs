@@ -0,0 +1,488 @@
+//! Named queries for the `QueryNamed` and `ExecuteNamed` RPCs.
+//!
+//! Queries live in a directory loaded at startup. A `.toml` file holds any
+//! number of queries keyed by name:
+//!
+//! ```toml
+//! [user_by_email]
+//! sql = "SELECT id, name FROM users WHERE email = $1"
+//! params = ["string"]
+//! ```
+//!
+//! A `.sql` file holds one query named after the file, with its parameter
+//! types in a leading `-- params:` comment:
+//!
+//! ```sql
+//! -- params: string, int?
+//! UPDATE users SET name = $1, manager_id = $2 WHERE id = $3
+//! ```
+//!
+//! Parameter types are `bool`, `int`, `float`, `string` and `bytes`; a
+//! trailing `?` also accepts null. Every query is prepared against the live
+//! database before the service starts, so a query naming a missing table or
+//! column, or declaring the wrong number of parameters, fails startup
+//! instead of the first request. Callers can only pick a query and supply
+//! typed values, never SQL text.
+
+use acton_dx_proto::data::v1::{value::Value as ProtoValueInner, Value as ProtoValue};
+use acton_dx_proto::error::invalid_field;
+use acton_dx_proto::error::v1::ErrorDetail;
+use anyhow::{bail, Context};
+use figment::providers::{Format, Toml};
+use figment::Figment;
+use serde::Deserialize;
+use sqlx::{AnyPool, Executor, Statement};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use tonic::{Code, Status};
+
+/// Type of a named query parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamType {
+    /// Boolean
+    Bool,
+    /// 64-bit integer
+    Int,
+    /// Double precision float
+    Float,
+    /// Text
+    String,
+    /// Binary data
+    Bytes,
+}
+
+impl ParamType {
+    /// Type of a proto value, or `None` for null.
+    const fn of(value: &ProtoValue) -> Option<Self> {
+        match &value.value {
+            Some(ProtoValueInner::BoolValue(_)) => Some(Self::Bool),
+            Some(ProtoValueInner::IntValue(_)) => Some(Self::Int),
+            Some(ProtoValueInner::FloatValue(_)) => Some(Self::Float),
+            Some(ProtoValueInner::StringValue(_)) => Some(Self::String),
+            Some(ProtoValueInner::BytesValue(_)) => Some(Self::Bytes),
+            Some(ProtoValueInner::NullValue(_)) | None => None,
+        }
+    }
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Bool => "bool",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::String => "string",
+            Self::Bytes => "bytes",
+        }
+    }
+}
+
+/// Declared type of one parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamSpec {
+    /// Value type
+    pub kind: ParamType,
+    /// Whether null is accepted
+    pub nullable: bool,
+}
+
+impl FromStr for ParamSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        let (name, nullable) = spec
+            .strip_suffix('?')
+            .map_or((spec, false), |name| (name, true));
+        let kind = match name {
+            "bool" => ParamType::Bool,
+            "int" => ParamType::Int,
+            "float" => ParamType::Float,
+            "string" => ParamType::String,
+            "bytes" => ParamType::Bytes,
+            _ => bail!("unknown parameter type {spec:?}"),
+        };
+        Ok(Self { kind, nullable })
+    }
+}
+
+impl fmt::Display for ParamSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.kind.as_str())?;
+        if self.nullable {
+            f.write_str("?")?;
+        }
+        Ok(())
+    }
+}
+
+/// A query callers invoke by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedQuery {
+    /// Name callers use
+    pub name: String,
+    /// SQL text
+    pub sql: String,
+    /// Declared parameter types, in bind order
+    pub params: Vec<ParamSpec>,
+}
+
+impl NamedQuery {
+    /// Check call parameters against the declared types.
+    ///
+    /// # Errors
+    ///
+    /// Returns `INVALID_ARGUMENT` naming the first parameter that is
+    /// missing, extra, null where not allowed, or of the wrong type.
+    #[allow(clippy::result_large_err)]
+    pub fn check_params(&self, params: &[ProtoValue]) -> Result<(), Status> {
+        if params.len() != self.params.len() {
+            return Err(invalid_field(
+                "params",
+                format!(
+                    "query {} takes {} parameters, got {}",
+                    self.name,
+                    self.params.len(),
+                    params.len()
+                ),
+            ));
+        }
+        for (index, (spec, value)) in self.params.iter().zip(params).enumerate() {
+            let accepted = ParamType::of(value).map_or(spec.nullable, |kind| kind == spec.kind);
+            if !accepted {
+                let got = ParamType::of(value).map_or("null", ParamType::as_str);
+                return Err(invalid_field(
+                    format!("params[{index}]"),
+                    format!("expected {spec}, got {got}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One query in a `.toml` file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QueryEntry {
+    sql: String,
+    #[serde(default)]
+    params: Vec<String>,
+}
+
+/// Named queries loaded at startup.
+#[derive(Debug, Clone, Default)]
+pub struct QueryRegistry {
+    queries: HashMap<String, NamedQuery>,
+}
+
+impl QueryRegistry {
+    /// Create an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every `.toml` and `.sql` file in `dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the directory or a file cannot be read, a file does
+    /// not parse, or two queries share a name.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read query directory {}", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        paths.sort();
+
+        let mut registry = Self::new();
+        for path in paths {
+            let extension = path.extension().and_then(|ext| ext.to_str());
+            if !matches!(extension, Some("toml" | "sql")) {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let parsed = if extension == Some("toml") {
+                registry.add_toml(&contents)
+            } else {
+                let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+                registry.add_sql(name, &contents)
+            };
+            parsed.with_context(|| format!("Invalid named query file {}", path.display()))?;
+        }
+        Ok(registry)
+    }
+
+    /// Add the queries in a `.toml` document.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the document does not parse, declares an unknown
+    /// parameter type, or reuses a name.
+    pub fn add_toml(&mut self, contents: &str) -> anyhow::Result<()> {
+        let entries: BTreeMap<String, QueryEntry> = Figment::from(Toml::string(contents))
+            .extract()
+            .context("Failed to parse queries")?;
+        for (name, entry) in entries {
+            let params = entry
+                .params
+                .iter()
+                .map(|spec| spec.parse())
+                .collect::<anyhow::Result<_>>()
+                .with_context(|| format!("Query {name}"))?;
+            self.insert(NamedQuery {
+                name,
+                sql: entry.sql,
+                params,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Add a query written as a `.sql` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the `-- params:` comment declares an unknown type or
+    /// the name is taken.
+    pub fn add_sql(&mut self, name: &str, contents: &str) -> anyhow::Result<()> {
+        let mut params = Vec::new();
+        for line in contents.lines().map(str::trim) {
+            let Some(comment) = line.strip_prefix("--") else {
+                break;
+            };
+            if let Some(specs) = comment.trim().strip_prefix("params:") {
+                params = specs
+                    .split(',')
+                    .filter(|spec| !spec.trim().is_empty())
+                    .map(str::parse)
+                    .collect::<anyhow::Result<_>>()
+                    .with_context(|| format!("Query {name}"))?;
+            }
+        }
+        self.insert(NamedQuery {
+            name: name.to_string(),
+            sql: contents.trim().to_string(),
+            params,
+        })
+    }
+
+    /// Add a query.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the name is empty, contains characters other than
+    /// ASCII letters, digits, `_`, `.` and `-`, or is already taken.
+    pub fn insert(&mut self, query: NamedQuery) -> anyhow::Result<()> {
+        let valid = !query.name.is_empty()
+            && query
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        if !valid {
+            bail!("invalid query name {:?}", query.name);
+        }
+        if self.queries.contains_key(&query.name) {
+            bail!("query {} is defined more than once", query.name);
+        }
+        self.queries.insert(query.name.clone(), query);
+        Ok(())
+    }
+
+    /// Prepare every query against the database.
+    ///
+    /// Preparing makes the database resolve each table and column, so this
+    /// catches queries that no longer match the schema. Where the driver
+    /// reports how many parameters a statement takes, it must match the
+    /// declared count.
+    ///
+    /// # Errors
+    ///
+    /// Returns error naming the first query that fails to prepare or
+    /// declares the wrong number of parameters.
+    pub async fn validate(&self, pool: &AnyPool) -> anyhow::Result<()> {
+        let mut names: Vec<&String> = self.queries.keys().collect();
+        names.sort();
+        for name in names {
+            let query = &self.queries[name];
+            let statement = pool
+                .prepare(&query.sql)
+                .await
+                .with_context(|| format!("Named query {name} does not match the schema"))?;
+            if let Some(expected) = statement
+                .parameters()
+                .map(|params| params.either(<[_]>::len, |count| count))
+            {
+                if expected != query.params.len() {
+                    bail!(
+                        "Named query {name} declares {} parameters but its SQL takes {expected}",
+                        query.params.len()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Look up a query by name.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NOT_FOUND` with an `UNKNOWN_QUERY` detail if no query has
+    /// that name.
+    #[allow(clippy::result_large_err)]
+    pub fn get(&self, name: &str) -> Result<&NamedQuery, Status> {
+        self.queries.get(name).ok_or_else(|| {
+            ErrorDetail::new("UNKNOWN_QUERY", format!("no named query {name}"))
+                .into_status(Code::NotFound)
+        })
+    }
+
+    /// Number of queries.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Whether no queries are loaded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::any::AnyPoolOptions;
+
+    const QUERIES: &str = r#"
+[user_by_id]
+sql = "SELECT id, name FROM users WHERE id = ?"
+params = ["int"]
+
+[all_users]
+sql = "SELECT id, name FROM users ORDER BY id"
+"#;
+
+    fn value(value: ProtoValueInner) -> ProtoValue {
+        ProtoValue { value: Some(value) }
+    }
+
+    async fn pool() -> AnyPool {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, manager_id INTEGER)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_param_specs() {
+        let spec: ParamSpec = "int?".parse().unwrap();
+        assert_eq!(spec.kind, ParamType::Int);
+        assert!(spec.nullable);
+        assert_eq!(spec.to_string(), "int?");
+        assert!(" string ".parse::<ParamSpec>().is_ok());
+        assert!("uuid".parse::<ParamSpec>().is_err());
+    }
+
+    #[test]
+    fn test_check_params() {
+        let mut registry = QueryRegistry::new();
+        registry
+            .add_sql(
+                "rename_user",
+                "-- Rename a user\n-- params: string, int?, int\n\
+                 UPDATE users SET name = ?, manager_id = ? WHERE id = ?",
+            )
+            .unwrap();
+        let query = registry.get("rename_user").unwrap();
+        assert!(query.sql.starts_with("-- Rename"));
+
+        let name = value(ProtoValueInner::StringValue("Ada".to_string()));
+        let null = value(ProtoValueInner::NullValue(true));
+        let id = value(ProtoValueInner::IntValue(1));
+        assert!(query
+            .check_params(&[name.clone(), null.clone(), id.clone()])
+            .is_ok());
+
+        let status = query
+            .check_params(&[name.clone(), id.clone(), null])
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let detail = ErrorDetail::from_status(&status).unwrap();
+        assert_eq!(detail.field_violations[0].field, "params[2]");
+
+        let status = query.check_params(&[id.clone(), id.clone(), id]).unwrap_err();
+        assert!(status.message().contains("expected string, got int"));
+
+        let status = query.check_params(&[name]).unwrap_err();
+        assert!(status.message().contains("takes 3 parameters"));
+    }
+
+    #[test]
+    fn test_names() {
+        let mut registry = QueryRegistry::new();
+        registry.add_toml(QUERIES).unwrap();
+        assert_eq!(registry.len(), 2);
+        assert!(registry.add_sql("all_users", "SELECT 1").is_err());
+        assert!(registry.add_sql("drop table", "SELECT 1").is_err());
+        assert!(registry.add_toml("[x]\nsql = \"SELECT 1\"\nparams = [\"date\"]").is_err());
+
+        let status = registry.get("missing").unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        let detail = ErrorDetail::from_status(&status).unwrap();
+        assert_eq!(detail.code, "UNKNOWN_QUERY");
+    }
+
+    #[test]
+    fn test_load_directory() {
+        let dir = std::env::temp_dir().join(format!("named-queries-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("users.toml"), QUERIES).unwrap();
+        std::fs::write(
+            dir.join("delete_user.sql"),
+            "-- params: int\nDELETE FROM users WHERE id = ?\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("README.md"), "not a query").unwrap();
+
+        let registry = QueryRegistry::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(registry.len(), 3);
+        let delete = registry.get("delete_user").unwrap();
+        assert_eq!(delete.sql, "-- params: int\nDELETE FROM users WHERE id = ?");
+        assert_eq!(delete.params.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_validate_against_schema() {
+        let pool = pool().await;
+        let mut registry = QueryRegistry::new();
+        registry.add_toml(QUERIES).unwrap();
+        registry.validate(&pool).await.unwrap();
+
+        let mut missing_column = QueryRegistry::new();
+        missing_column
+            .add_sql("by_email", "-- params: string\nSELECT id FROM users WHERE email = ?")
+            .unwrap();
+        let error = missing_column.validate(&pool).await.unwrap_err();
+        assert!(error.to_string().contains("by_email"));
+
+        let mut wrong_count = QueryRegistry::new();
+        wrong_count
+            .add_sql("by_id", "SELECT id FROM users WHERE id = ?")
+            .unwrap();
+        let error = wrong_count.validate(&pool).await.unwrap_err();
+        assert!(error.to_string().contains("declares 0 parameters"));
+    }
+}
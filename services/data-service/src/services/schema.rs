@@ -0,0 +1,378 @@
+//! Schema introspection for the `DescribeSchema` RPC.
+//!
+//! Each supported backend is queried through its own catalog (the Postgres
+//! system catalogs, SQLite pragmas) and normalized into the same messages.
+
+use acton_dx_proto::data::v1::{ColumnSchema, ForeignKeySchema, IndexSchema, TableSchema};
+use sqlx::any::AnyRow;
+use sqlx::{AnyPool, Row};
+use std::collections::BTreeMap;
+
+/// Database dialect behind the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// PostgreSQL
+    Postgres,
+    /// SQLite
+    Sqlite,
+}
+
+impl Dialect {
+    /// Detect the dialect from a pool's connection URL.
+    #[must_use]
+    pub fn of(pool: &AnyPool) -> Option<Self> {
+        match pool.connect_options().database_url.scheme() {
+            "postgres" | "postgresql" => Some(Self::Postgres),
+            "sqlite" => Some(Self::Sqlite),
+            _ => None,
+        }
+    }
+
+    /// Dialect name reported to clients.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Postgres => "postgres",
+            Self::Sqlite => "sqlite",
+        }
+    }
+}
+
+/// Describe the tables in the current schema, or only `only` when non-empty.
+///
+/// Tables are sorted by name and columns keep their declared order.
+///
+/// # Errors
+///
+/// Returns error if a catalog query fails.
+pub async fn describe(
+    pool: &AnyPool,
+    dialect: Dialect,
+    only: &[String],
+) -> Result<Vec<TableSchema>, sqlx::Error> {
+    let tables = match dialect {
+        Dialect::Postgres => describe_postgres(pool).await?,
+        Dialect::Sqlite => describe_sqlite(pool, only).await?,
+    };
+    Ok(tables
+        .into_values()
+        .filter(|table| only.is_empty() || only.contains(&table.name))
+        .collect())
+}
+
+const POSTGRES_COLUMNS: &str = r"
+SELECT c.table_name::text AS table_name,
+       c.column_name::text AS column_name,
+       c.data_type::text AS data_type,
+       (c.is_nullable = 'YES') AS nullable,
+       c.column_default::text AS default_value,
+       EXISTS (
+           SELECT 1
+           FROM information_schema.table_constraints tc
+           JOIN information_schema.key_column_usage k
+             ON k.constraint_name = tc.constraint_name
+            AND k.table_schema = tc.table_schema
+            AND k.table_name = tc.table_name
+           WHERE tc.constraint_type = 'PRIMARY KEY'
+             AND tc.table_schema = c.table_schema
+             AND tc.table_name = c.table_name
+             AND k.column_name = c.column_name
+       ) AS primary_key
+FROM information_schema.columns c
+JOIN information_schema.tables t
+  ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+WHERE c.table_schema = current_schema() AND t.table_type = 'BASE TABLE'
+ORDER BY c.table_name, c.ordinal_position";
+
+const POSTGRES_INDEXES: &str = r"
+SELECT t.relname::text AS table_name,
+       i.relname::text AS index_name,
+       ix.indisunique AS is_unique,
+       array_to_string(ARRAY(
+           SELECT a.attname
+           FROM unnest(ix.indkey::int2[]) WITH ORDINALITY k(attnum, ord)
+           JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = k.attnum
+           ORDER BY k.ord
+       ), ',') AS columns
+FROM pg_index ix
+JOIN pg_class t ON t.oid = ix.indrelid
+JOIN pg_class i ON i.oid = ix.indexrelid
+JOIN pg_namespace n ON n.oid = t.relnamespace
+WHERE n.nspname = current_schema() AND NOT ix.indisprimary
+ORDER BY 1, 2";
+
+const POSTGRES_FOREIGN_KEYS: &str = r"
+SELECT t.relname::text AS table_name,
+       r.relname::text AS referenced_table,
+       array_to_string(ARRAY(
+           SELECT a.attname
+           FROM unnest(c.conkey) WITH ORDINALITY k(attnum, ord)
+           JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+           ORDER BY k.ord
+       ), ',') AS columns,
+       array_to_string(ARRAY(
+           SELECT a.attname
+           FROM unnest(c.confkey) WITH ORDINALITY k(attnum, ord)
+           JOIN pg_attribute a ON a.attrelid = c.confrelid AND a.attnum = k.attnum
+           ORDER BY k.ord
+       ), ',') AS referenced_columns,
+       CASE c.confdeltype
+           WHEN 'c' THEN 'CASCADE'
+           WHEN 'n' THEN 'SET NULL'
+           WHEN 'd' THEN 'SET DEFAULT'
+           WHEN 'r' THEN 'RESTRICT'
+           ELSE 'NO ACTION'
+       END AS on_delete
+FROM pg_constraint c
+JOIN pg_class t ON t.oid = c.conrelid
+JOIN pg_class r ON r.oid = c.confrelid
+JOIN pg_namespace n ON n.oid = t.relnamespace
+WHERE c.contype = 'f' AND n.nspname = current_schema()
+ORDER BY 1, c.conname";
+
+async fn describe_postgres(pool: &AnyPool) -> Result<BTreeMap<String, TableSchema>, sqlx::Error> {
+    let mut tables: BTreeMap<String, TableSchema> = BTreeMap::new();
+
+    for row in sqlx::query(POSTGRES_COLUMNS).fetch_all(pool).await? {
+        let table: String = row.try_get("table_name")?;
+        table_entry(&mut tables, table).columns.push(ColumnSchema {
+            name: row.try_get("column_name")?,
+            data_type: row.try_get("data_type")?,
+            nullable: row.try_get("nullable")?,
+            default_value: row.try_get("default_value")?,
+            primary_key: row.try_get("primary_key")?,
+        });
+    }
+
+    for row in sqlx::query(POSTGRES_INDEXES).fetch_all(pool).await? {
+        let table: String = row.try_get("table_name")?;
+        let columns: String = row.try_get("columns")?;
+        if let Some(entry) = tables.get_mut(&table) {
+            entry.indexes.push(IndexSchema {
+                name: row.try_get("index_name")?,
+                columns: split_list(&columns),
+                unique: row.try_get("is_unique")?,
+            });
+        }
+    }
+
+    for row in sqlx::query(POSTGRES_FOREIGN_KEYS).fetch_all(pool).await? {
+        let table: String = row.try_get("table_name")?;
+        let columns: String = row.try_get("columns")?;
+        let referenced_columns: String = row.try_get("referenced_columns")?;
+        if let Some(entry) = tables.get_mut(&table) {
+            entry.foreign_keys.push(ForeignKeySchema {
+                columns: split_list(&columns),
+                referenced_table: row.try_get("referenced_table")?,
+                referenced_columns: split_list(&referenced_columns),
+                on_delete: row.try_get("on_delete")?,
+            });
+        }
+    }
+
+    Ok(tables)
+}
+
+async fn describe_sqlite(
+    pool: &AnyPool,
+    only: &[String],
+) -> Result<BTreeMap<String, TableSchema>, sqlx::Error> {
+    let names: Vec<String> = sqlx::query(
+        "SELECT name FROM sqlite_master \
+         WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|row| row.try_get("name"))
+    .collect::<Result<_, _>>()?;
+
+    let mut tables = BTreeMap::new();
+    for name in names {
+        if !only.is_empty() && !only.contains(&name) {
+            continue;
+        }
+        let table = TableSchema {
+            columns: sqlite_columns(pool, &name).await?,
+            indexes: sqlite_indexes(pool, &name).await?,
+            foreign_keys: sqlite_foreign_keys(pool, &name).await?,
+            name: name.clone(),
+        };
+        tables.insert(name, table);
+    }
+    Ok(tables)
+}
+
+async fn sqlite_columns(pool: &AnyPool, table: &str) -> Result<Vec<ColumnSchema>, sqlx::Error> {
+    sqlx::query(
+        "SELECT name, type, \"notnull\" AS not_null, dflt_value, pk \
+         FROM pragma_table_info(?) ORDER BY cid",
+    )
+    .bind(table)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|row| {
+        let primary_key = row.try_get::<i64, _>("pk")? > 0;
+        Ok(ColumnSchema {
+            name: row.try_get("name")?,
+            data_type: row.try_get("type")?,
+            // SQLite lets primary key columns hold NULL unless declared NOT NULL,
+            // but INTEGER PRIMARY KEY is the rowid and never does
+            nullable: row.try_get::<i64, _>("not_null")? == 0 && !primary_key,
+            default_value: row.try_get("dflt_value")?,
+            primary_key,
+        })
+    })
+    .collect()
+}
+
+async fn sqlite_indexes(pool: &AnyPool, table: &str) -> Result<Vec<IndexSchema>, sqlx::Error> {
+    let rows: Vec<AnyRow> = sqlx::query(
+        "SELECT name, \"unique\" AS is_unique FROM pragma_index_list(?) \
+         WHERE origin <> 'pk' ORDER BY name",
+    )
+    .bind(table)
+    .fetch_all(pool)
+    .await?;
+
+    let mut indexes = Vec::with_capacity(rows.len());
+    for row in rows {
+        let name: String = row.try_get("name")?;
+        let columns = sqlx::query("SELECT name FROM pragma_index_info(?) ORDER BY seqno")
+            .bind(&name)
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|row| row.try_get("name"))
+            .collect::<Result<_, _>>()?;
+        indexes.push(IndexSchema {
+            name,
+            columns,
+            unique: row.try_get::<i64, _>("is_unique")? != 0,
+        });
+    }
+    Ok(indexes)
+}
+
+async fn sqlite_foreign_keys(
+    pool: &AnyPool,
+    table: &str,
+) -> Result<Vec<ForeignKeySchema>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT id, \"table\" AS referenced_table, \"from\" AS column_name, \
+         \"to\" AS referenced_column, on_delete \
+         FROM pragma_foreign_key_list(?) ORDER BY id, seq",
+    )
+    .bind(table)
+    .fetch_all(pool)
+    .await?;
+
+    // One row per column; composite keys share an id
+    let mut keys: BTreeMap<i64, ForeignKeySchema> = BTreeMap::new();
+    for row in rows {
+        let key = keys
+            .entry(row.try_get("id")?)
+            .or_insert_with(|| ForeignKeySchema {
+                columns: Vec::new(),
+                referenced_table: String::new(),
+                referenced_columns: Vec::new(),
+                on_delete: String::new(),
+            });
+        key.referenced_table = row.try_get("referenced_table")?;
+        key.on_delete = row.try_get("on_delete")?;
+        key.columns.push(row.try_get("column_name")?);
+        // `to` is NULL when the key references the parent's primary key
+        if let Some(column) = row.try_get::<Option<String>, _>("referenced_column")? {
+            key.referenced_columns.push(column);
+        }
+    }
+    Ok(keys.into_values().collect())
+}
+
+fn table_entry(tables: &mut BTreeMap<String, TableSchema>, name: String) -> &mut TableSchema {
+    tables.entry(name.clone()).or_insert_with(|| TableSchema {
+        name,
+        columns: Vec::new(),
+        indexes: Vec::new(),
+        foreign_keys: Vec::new(),
+    })
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::any::AnyPoolOptions;
+
+    #[tokio::test]
+    async fn test_describe_sqlite() {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        for sql in [
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE)",
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY, \
+             author_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE, \
+             title TEXT, status TEXT NOT NULL DEFAULT 'draft')",
+            "CREATE INDEX idx_posts_author ON posts (author_id, status)",
+        ] {
+            sqlx::query(sql).execute(&pool).await.unwrap();
+        }
+
+        let dialect = Dialect::of(&pool).unwrap();
+        assert_eq!(dialect, Dialect::Sqlite);
+        let tables = describe(&pool, dialect, &[]).await.unwrap();
+        assert_eq!(
+            tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+            ["posts", "users"]
+        );
+
+        let posts = &tables[0];
+        let columns: Vec<_> = posts
+            .columns
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.data_type.as_str(),
+                    c.nullable,
+                    c.primary_key,
+                )
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("id", "INTEGER", false, true),
+                ("author_id", "INTEGER", false, false),
+                ("title", "TEXT", true, false),
+                ("status", "TEXT", false, false),
+            ]
+        );
+        assert_eq!(posts.columns[3].default_value.as_deref(), Some("'draft'"));
+        assert_eq!(posts.indexes.len(), 1);
+        assert_eq!(posts.indexes[0].columns, ["author_id", "status"]);
+        assert!(!posts.indexes[0].unique);
+        assert_eq!(posts.foreign_keys.len(), 1);
+        assert_eq!(posts.foreign_keys[0].referenced_table, "users");
+        assert_eq!(posts.foreign_keys[0].columns, ["author_id"]);
+        assert_eq!(posts.foreign_keys[0].referenced_columns, ["id"]);
+        assert_eq!(posts.foreign_keys[0].on_delete, "CASCADE");
+
+        let users = describe(&pool, dialect, &["users".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 1);
+        assert!(users[0].indexes[0].unique);
+    }
+}
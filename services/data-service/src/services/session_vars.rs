@@ -0,0 +1,187 @@
+//! Per-request session variables for Postgres row-level security.
+//!
+//! The web tier sends `x-acton-session-var: name=value` metadata entries
+//! (e.g. `app.user_id=42`). Allowlisted variables are applied with
+//! `set_config(name, value, true)` inside a transaction wrapping the
+//! statement, so they are scoped to that transaction and cleared when it
+//! commits or rolls back; a pooled connection never carries one request's
+//! identity into the next.
+
+use acton_dx_proto::error::invalid_field;
+use acton_dx_proto::error::v1::ErrorDetail;
+use sqlx::{Any, AnyPool, Transaction};
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Status};
+
+/// gRPC metadata key carrying one `name=value` session variable per entry.
+pub const SESSION_VAR_METADATA_KEY: &str = "x-acton-session-var";
+
+/// Why request metadata could not be turned into session variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionVarError {
+    /// An entry is not ASCII `name=value`.
+    Malformed,
+    /// The variable is not allowlisted.
+    NotAllowed(String),
+}
+
+impl From<SessionVarError> for Status {
+    fn from(error: SessionVarError) -> Self {
+        match error {
+            SessionVarError::Malformed => invalid_field(
+                SESSION_VAR_METADATA_KEY,
+                "session variable must be ASCII name=value",
+            ),
+            SessionVarError::NotAllowed(name) => ErrorDetail::new(
+                "SESSION_VAR_NOT_ALLOWED",
+                format!("session variable {name} is not allowed"),
+            )
+            .into_status(Code::PermissionDenied),
+        }
+    }
+}
+
+/// Session variables requested for one call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionVars(Vec<(String, String)>);
+
+impl SessionVars {
+    /// Parse variables from request metadata, accepting only `allowed` names.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SessionVarError::Malformed`] for a bad entry and
+    /// [`SessionVarError::NotAllowed`] for a variable that is not
+    /// allowlisted, so a client can never set `role`, `search_path` or
+    /// similar.
+    pub fn from_metadata(
+        metadata: &MetadataMap,
+        allowed: &[String],
+    ) -> Result<Self, SessionVarError> {
+        let mut vars: Vec<(String, String)> = Vec::new();
+        for value in metadata.get_all(SESSION_VAR_METADATA_KEY) {
+            let (name, value) = value
+                .to_str()
+                .ok()
+                .and_then(|entry| entry.split_once('='))
+                .ok_or(SessionVarError::Malformed)?;
+            if !allowed.iter().any(|allowed| allowed == name) {
+                return Err(SessionVarError::NotAllowed(name.to_string()));
+            }
+            vars.retain(|(existing, _)| existing != name);
+            vars.push((name.to_string(), value.to_string()));
+        }
+        Ok(Self(vars))
+    }
+
+    /// Set a variable, replacing any value the caller sent.
+    pub fn set(&mut self, name: &str, value: String) {
+        self.0.retain(|(existing, _)| existing != name);
+        self.0.push((name.to_string(), value));
+    }
+
+    /// Whether the caller sent a variable.
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|(existing, _)| existing == name)
+    }
+
+    /// Whether no variables were requested.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Begin a transaction with the variables set for its duration.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the transaction cannot begin or a variable cannot be set.
+    pub async fn begin(&self, pool: &AnyPool) -> Result<Transaction<'static, Any>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        for (name, value) in &self.0 {
+            sqlx::query("SELECT set_config($1, $2, true)")
+                .bind(name.as_str())
+                .bind(value.as_str())
+                .execute(&mut *tx)
+                .await?;
+        }
+        Ok(tx)
+    }
+}
+
+/// Whether a name is a valid custom setting (`prefix.name`) for allowlisting.
+#[must_use]
+pub fn is_custom_setting(name: &str) -> bool {
+    let valid_part = |part: &str| {
+        part.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+            && part
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    };
+    name.split_once('.')
+        .is_some_and(|(prefix, rest)| valid_part(prefix) && valid_part(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::metadata::MetadataValue;
+
+    fn metadata(entries: &[&'static str]) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        for entry in entries {
+            metadata.append(SESSION_VAR_METADATA_KEY, MetadataValue::from_static(entry));
+        }
+        metadata
+    }
+
+    #[test]
+    fn test_from_metadata() {
+        let allowed = vec!["app.user_id".to_string(), "app.tenant_id".to_string()];
+
+        let vars = SessionVars::from_metadata(
+            &metadata(&["app.user_id=1", "app.tenant_id=7", "app.user_id=42"]),
+            &allowed,
+        )
+        .unwrap();
+        assert_eq!(
+            vars.0,
+            [
+                ("app.tenant_id".to_string(), "7".to_string()),
+                ("app.user_id".to_string(), "42".to_string()),
+            ]
+        );
+        assert!(SessionVars::from_metadata(&MetadataMap::new(), &allowed)
+            .unwrap()
+            .is_empty());
+
+        let denied = SessionVars::from_metadata(&metadata(&["role=admin"]), &allowed).unwrap_err();
+        assert_eq!(denied, SessionVarError::NotAllowed("role".to_string()));
+        assert_eq!(Status::from(denied).code(), tonic::Code::PermissionDenied);
+        let malformed =
+            SessionVars::from_metadata(&metadata(&["app.user_id"]), &allowed).unwrap_err();
+        assert_eq!(Status::from(malformed).code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_set_replaces_caller_value() {
+        let allowed = vec!["app.user_id".to_string()];
+        let mut vars =
+            SessionVars::from_metadata(&metadata(&["app.user_id=1"]), &allowed).unwrap();
+        vars.set("app.user_id", "42".to_string());
+        assert_eq!(vars.0, [("app.user_id".to_string(), "42".to_string())]);
+    }
+
+    #[test]
+    fn test_is_custom_setting() {
+        assert!(is_custom_setting("app.user_id"));
+        assert!(is_custom_setting("app.tenant_id"));
+        assert!(!is_custom_setting("role"));
+        assert!(!is_custom_setting("search_path"));
+        assert!(!is_custom_setting("app.user-id"));
+        assert!(!is_custom_setting(".user_id"));
+    }
+}
@@ -0,0 +1,119 @@
+//! Row streaming for the `QueryStream` RPC.
+//!
+//! Rows are read from the database as it produces them and sent in chunks
+//! through a bounded channel. Once the channel holds `buffered_chunks`
+//! chunks the reader waits for the client to take one, so a slow client
+//! slows the query down instead of the service buffering the whole result.
+//! A client that goes away closes the channel, which stops the read and
+//! releases the connection.
+
+use acton_dx_proto::data::v1::{Row, RowChunk};
+use acton_dx_proto::error::invalid_field;
+use futures_util::{Stream, TryStreamExt};
+use sqlx::any::AnyRow;
+use tokio::sync::mpsc;
+use tonic::Status;
+use tracing::debug;
+
+/// Limits for streamed queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamLimits {
+    /// Rows per chunk for requests that do not ask for a size.
+    pub default_chunk_rows: usize,
+    /// Largest chunk a request may ask for.
+    pub max_chunk_rows: usize,
+    /// Chunks read ahead of the client.
+    pub buffered_chunks: usize,
+}
+
+impl Default for StreamLimits {
+    fn default() -> Self {
+        Self {
+            default_chunk_rows: 500,
+            max_chunk_rows: 10_000,
+            buffered_chunks: 4,
+        }
+    }
+}
+
+impl StreamLimits {
+    /// Chunk size for a request asking for `requested` rows.
+    ///
+    /// Zero uses the default.
+    ///
+    /// # Errors
+    ///
+    /// Returns `INVALID_ARGUMENT` if the request exceeds the maximum.
+    #[allow(clippy::result_large_err)]
+    pub(super) fn chunk_rows_for(&self, requested: u32) -> Result<usize, Status> {
+        let requested = usize::try_from(requested).unwrap_or(usize::MAX);
+        match requested {
+            0 => Ok(self.default_chunk_rows.max(1)),
+            rows if rows > self.max_chunk_rows => Err(invalid_field(
+                "chunk_rows",
+                format!("chunks may hold at most {} rows", self.max_chunk_rows),
+            )),
+            rows => Ok(rows),
+        }
+    }
+}
+
+/// Send `rows` to `tx` in chunks of `chunk_rows`, converting each row with
+/// `convert`.
+///
+/// Returns the number of rows sent; stops early, without error, once the
+/// client has gone away.
+///
+/// # Errors
+///
+/// Returns the database error that ended the read. Rows read before it
+/// have already been sent.
+pub(super) async fn send_chunks<S, F>(
+    mut rows: S,
+    chunk_rows: usize,
+    tx: &mpsc::Sender<Result<RowChunk, Status>>,
+    convert: F,
+) -> Result<usize, sqlx::Error>
+where
+    S: Stream<Item = Result<AnyRow, sqlx::Error>> + Unpin,
+    F: Fn(&AnyRow) -> Row,
+{
+    let mut sent = 0;
+    let mut chunk = Vec::with_capacity(chunk_rows);
+    while let Some(row) = rows.try_next().await? {
+        chunk.push(convert(&row));
+        if chunk.len() == chunk_rows {
+            let full = std::mem::replace(&mut chunk, Vec::with_capacity(chunk_rows));
+            sent += full.len();
+            if tx.send(Ok(RowChunk { rows: full })).await.is_err() {
+                debug!(sent, "Stream client went away; stopping query");
+                return Ok(sent);
+            }
+        }
+    }
+    if !chunk.is_empty() {
+        sent += chunk.len();
+        if tx.send(Ok(RowChunk { rows: chunk })).await.is_err() {
+            debug!(sent, "Stream client went away; stopping query");
+        }
+    }
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    #[test]
+    fn test_chunk_rows_for() {
+        let limits = StreamLimits::default();
+        assert_eq!(limits.chunk_rows_for(0).unwrap(), 500);
+        assert_eq!(limits.chunk_rows_for(50).unwrap(), 50);
+        assert_eq!(limits.chunk_rows_for(10_000).unwrap(), 10_000);
+
+        let status = limits.chunk_rows_for(10_001).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("at most 10000 rows"));
+    }
+}
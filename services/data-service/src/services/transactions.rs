@@ -0,0 +1,353 @@
+//! Interactive transactions held open across calls.
+//!
+//! `BeginTransaction` takes a pooled connection and keeps it, with the
+//! transaction open, under a generated ID. Statements sent with that ID run
+//! on the connection until `CommitTransaction` or `RollbackTransaction`
+//! releases it. A transaction that sees no call for its timeout is treated
+//! as abandoned and rolled back by a background reaper, so a crashed client
+//! cannot hold a connection and its locks forever.
+
+use acton_dx_proto::error::invalid_field;
+use acton_dx_proto::error::v1::ErrorDetail;
+use dashmap::DashMap;
+use sqlx::{Any, AnyConnection, Transaction};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tonic::{Code, Status};
+use tracing::{debug, warn};
+
+/// How often the reaper looks for abandoned transactions.
+const REAP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Limits for interactive transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionLimits {
+    /// Idle timeout for transactions that do not ask for one.
+    pub default_timeout: Duration,
+    /// Longest idle timeout a transaction may ask for.
+    pub max_timeout: Duration,
+    /// Most transactions open at once; each holds a pooled connection.
+    pub max_open: usize,
+}
+
+impl Default for TransactionLimits {
+    fn default() -> Self {
+        Self {
+            default_timeout: Duration::from_secs(30),
+            max_timeout: Duration::from_secs(300),
+            max_open: 100,
+        }
+    }
+}
+
+/// An open transaction; `None` once committed, rolled back or reaped.
+type Slot = Arc<Mutex<Option<OpenTransaction>>>;
+
+struct OpenTransaction {
+    tx: Transaction<'static, Any>,
+    timeout: Duration,
+    expires_at: Instant,
+}
+
+/// Open transactions by ID.
+#[derive(Clone)]
+pub(super) struct Transactions {
+    open: Arc<DashMap<String, Slot>>,
+    limits: TransactionLimits,
+    reaper: Arc<OnceLock<()>>,
+}
+
+impl std::fmt::Debug for Transactions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transactions")
+            .field("open", &self.open.len())
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Status for an ID that names no open transaction.
+fn not_found() -> Status {
+    ErrorDetail::new(
+        "TRANSACTION_NOT_FOUND",
+        "Transaction not found; it may have timed out and been rolled back",
+    )
+    .into_status(Code::NotFound)
+}
+
+impl Transactions {
+    pub(super) fn new(limits: TransactionLimits) -> Self {
+        Self {
+            open: Arc::new(DashMap::new()),
+            limits,
+            reaper: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Idle timeout for a transaction asking for `requested_ms`.
+    ///
+    /// Unset or zero uses the default.
+    ///
+    /// # Errors
+    ///
+    /// Returns `INVALID_ARGUMENT` if the request exceeds the maximum.
+    #[allow(clippy::result_large_err)]
+    pub(super) fn timeout_for(&self, requested_ms: Option<u64>) -> Result<Duration, Status> {
+        let timeout = match requested_ms {
+            None | Some(0) => return Ok(self.limits.default_timeout),
+            Some(ms) => Duration::from_millis(ms),
+        };
+        if timeout > self.limits.max_timeout {
+            return Err(invalid_field(
+                "timeout_ms",
+                format!(
+                    "transaction timeout must be at most {} ms",
+                    self.limits.max_timeout.as_millis()
+                ),
+            ));
+        }
+        Ok(timeout)
+    }
+
+    /// Check there is room for another transaction before taking a
+    /// connection for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RESOURCE_EXHAUSTED` when `max_open` transactions are open.
+    #[allow(clippy::result_large_err)]
+    pub(super) fn check_capacity(&self) -> Result<(), Status> {
+        if self.open.len() >= self.limits.max_open {
+            return Err(ErrorDetail::new(
+                "TOO_MANY_TRANSACTIONS",
+                format!("{} transactions are already open", self.limits.max_open),
+            )
+            .into_status(Code::ResourceExhausted));
+        }
+        Ok(())
+    }
+
+    /// Keep `tx` open and return its ID.
+    ///
+    /// Must be called from within a Tokio runtime, which runs the reaper.
+    pub(super) fn insert(&self, tx: Transaction<'static, Any>, timeout: Duration) -> String {
+        self.ensure_reaper();
+        let id = uuid::Uuid::new_v4().to_string();
+        let open = OpenTransaction {
+            tx,
+            timeout,
+            expires_at: Instant::now() + timeout,
+        };
+        self.open
+            .insert(id.clone(), Arc::new(Mutex::new(Some(open))));
+        id
+    }
+
+    /// Lock a transaction to run statements on it.
+    ///
+    /// Calls on the same transaction run one at a time. The idle timeout
+    /// restarts when the returned guard is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NOT_FOUND` if no transaction is open under `id`.
+    pub(super) async fn get(&self, id: &str) -> Result<ActiveTransaction, Status> {
+        let slot = self.slot(id)?;
+        let guard = slot.lock_owned().await;
+        if guard.is_none() {
+            return Err(not_found());
+        }
+        Ok(ActiveTransaction(guard))
+    }
+
+    /// Remove a transaction to commit or roll it back.
+    ///
+    /// Waits for a statement in flight on it to finish.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NOT_FOUND` if no transaction is open under `id`.
+    pub(super) async fn take(&self, id: &str) -> Result<Transaction<'static, Any>, Status> {
+        let slot = self.slot(id)?;
+        self.open.remove(id);
+        let open = slot.lock().await.take();
+        open.map(|open| open.tx).ok_or_else(not_found)
+    }
+
+    /// Number of open transactions.
+    pub(super) fn len(&self) -> usize {
+        self.open.len()
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn slot(&self, id: &str) -> Result<Slot, Status> {
+        self.open
+            .get(id)
+            .map(|slot| Arc::clone(slot.value()))
+            .ok_or_else(not_found)
+    }
+
+    fn ensure_reaper(&self) {
+        self.reaper.get_or_init(|| {
+            let open = Arc::downgrade(&self.open);
+            tokio::spawn(reap(open));
+        });
+    }
+}
+
+/// Roll back abandoned transactions until the service is dropped.
+async fn reap(open: Weak<DashMap<String, Slot>>) {
+    let mut interval = tokio::time::interval(REAP_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let Some(open) = open.upgrade() else {
+            return;
+        };
+        for (id, tx) in take_expired(&open, Instant::now()) {
+            warn!(transaction_id = %id, "Rolling back abandoned transaction");
+            if let Err(e) = tx.rollback().await {
+                warn!(transaction_id = %id, error = %e, "Rollback of abandoned transaction failed");
+            }
+        }
+    }
+}
+
+/// Remove transactions idle past their timeout.
+///
+/// Transactions locked by a call in flight are skipped; their timeout
+/// restarts when the call finishes.
+fn take_expired(
+    open: &DashMap<String, Slot>,
+    now: Instant,
+) -> Vec<(String, Transaction<'static, Any>)> {
+    let mut expired = Vec::new();
+    for entry in open {
+        let Ok(mut slot) = entry.value().try_lock() else {
+            continue;
+        };
+        if slot.as_ref().is_some_and(|open| open.expires_at <= now) {
+            if let Some(open) = slot.take() {
+                expired.push((entry.key().clone(), open.tx));
+            }
+        }
+    }
+    for (id, _) in &expired {
+        open.remove(id);
+    }
+    if !expired.is_empty() {
+        debug!(count = expired.len(), "Reaped abandoned transactions");
+    }
+    expired
+}
+
+/// A transaction locked for one call.
+pub(super) struct ActiveTransaction(OwnedMutexGuard<Option<OpenTransaction>>);
+
+impl ActiveTransaction {
+    /// Connection the transaction runs on.
+    pub(super) fn connection(&mut self) -> &mut AnyConnection {
+        let open = self
+            .0
+            .as_mut()
+            .expect("ActiveTransaction is only created for an open transaction");
+        &mut open.tx
+    }
+}
+
+impl Drop for ActiveTransaction {
+    fn drop(&mut self) {
+        if let Some(open) = self.0.as_mut() {
+            open.expires_at = Instant::now() + open.timeout;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::any::AnyPoolOptions;
+    use sqlx::AnyPool;
+
+    async fn pool() -> AnyPool {
+        sqlx::any::install_default_drivers();
+        AnyPoolOptions::new()
+            .max_connections(2)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    fn limits(max_open: usize) -> TransactionLimits {
+        TransactionLimits {
+            default_timeout: Duration::from_secs(30),
+            max_timeout: Duration::from_secs(60),
+            max_open,
+        }
+    }
+
+    #[test]
+    fn test_timeout_for() {
+        let transactions = Transactions::new(limits(1));
+        assert_eq!(transactions.timeout_for(None).unwrap(), Duration::from_secs(30));
+        assert_eq!(transactions.timeout_for(Some(0)).unwrap(), Duration::from_secs(30));
+        assert_eq!(
+            transactions.timeout_for(Some(5_000)).unwrap(),
+            Duration::from_secs(5)
+        );
+        let status = transactions.timeout_for(Some(61_000)).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_capacity_and_take() {
+        let pool = pool().await;
+        let transactions = Transactions::new(limits(1));
+        let id = transactions.insert(pool.begin().await.unwrap(), Duration::from_secs(30));
+
+        let status = transactions.check_capacity().unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        let mut active = transactions.get(&id).await.unwrap();
+        sqlx::query("SELECT 1")
+            .execute(active.connection())
+            .await
+            .unwrap();
+        drop(active);
+
+        transactions.take(&id).await.unwrap().commit().await.unwrap();
+        assert_eq!(transactions.len(), 0);
+        assert!(transactions.check_capacity().is_ok());
+
+        let status = transactions.get(&id).await.err().unwrap();
+        assert_eq!(status.code(), Code::NotFound);
+        let detail = ErrorDetail::from_status(&status).unwrap();
+        assert_eq!(detail.code, "TRANSACTION_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_expired_transactions_are_reaped() {
+        let pool = pool().await;
+        let transactions = Transactions::new(limits(2));
+        let idle = transactions.insert(pool.begin().await.unwrap(), Duration::from_secs(5));
+        let busy = transactions.insert(pool.begin().await.unwrap(), Duration::from_secs(5));
+
+        let active = transactions.get(&busy).await.unwrap();
+        let later = Instant::now() + Duration::from_secs(10);
+        let expired = take_expired(&transactions.open, later);
+        assert_eq!(
+            expired.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>(),
+            [idle.as_str()]
+        );
+        for (_, tx) in expired {
+            tx.rollback().await.unwrap();
+        }
+
+        // The busy transaction's timeout restarts once its call finishes
+        drop(active);
+        assert!(take_expired(&transactions.open, Instant::now()).is_empty());
+        assert!(transactions.get(&idle).await.is_err());
+        assert!(transactions.get(&busy).await.is_ok());
+    }
+}
@@ -3,6 +3,7 @@
 use figment::providers::{Env, Format, Toml};
 use figment::Figment;
 use serde::Deserialize;
+use std::path::PathBuf;
 
 /// Service configuration.
 #[derive(Debug, Deserialize)]
@@ -12,6 +13,24 @@ pub struct EmailServiceConfig {
     /// Service configuration.
     #[serde(default)]
     pub service: ServiceConfig,
+    /// Dry-run configuration.
+    #[serde(default)]
+    pub dry_run: DryRunConfig,
+    /// Recipient safety net for non-production environments.
+    #[serde(default)]
+    pub recipients: RecipientPolicyConfig,
+    /// Virus scanning of outgoing attachments.
+    #[serde(default)]
+    pub scanning: ScanningConfig,
+    /// Named templates for `SendTemplatedEmail`.
+    #[serde(default)]
+    pub templates: TemplatesConfig,
+    /// Outbound queue with retries.
+    #[serde(default)]
+    pub queue: QueueConfig,
+    /// Addresses that are never emailed.
+    #[serde(default)]
+    pub suppression: SuppressionConfig,
 }
 
 /// SMTP configuration.
@@ -33,6 +52,36 @@ pub struct SmtpConfig {
     pub from_address: Option<String>,
     /// Default from name.
     pub from_name: Option<String>,
+    /// Retries for transient SMTP failures.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Connection pool configuration.
+    #[serde(default)]
+    pub pool: SmtpPoolConfig,
+}
+
+/// SMTP connection pool configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmtpPoolConfig {
+    /// Connections, and so messages sent concurrently.
+    #[serde(default = "default_pool_connections")]
+    pub connections: usize,
+    /// Messages sent on a connection before it is replaced.
+    #[serde(default = "default_max_messages_per_connection")]
+    pub max_messages_per_connection: usize,
+    /// Seconds an idle connection is kept open.
+    #[serde(default = "default_keep_alive_secs")]
+    pub keep_alive_secs: u64,
+}
+
+impl Default for SmtpPoolConfig {
+    fn default() -> Self {
+        Self {
+            connections: default_pool_connections(),
+            max_messages_per_connection: default_max_messages_per_connection(),
+            keep_alive_secs: default_keep_alive_secs(),
+        }
+    }
 }
 
 /// Service network configuration.
@@ -55,6 +104,201 @@ impl Default for ServiceConfig {
     }
 }
 
+/// Dry-run configuration.
+///
+/// When enabled, sends are logged and captured in an in-memory dev mailbox
+/// (see the `ListDevMailbox` RPC) instead of being handed to SMTP, so
+/// staging environments never email real customers.
+#[derive(Debug, Deserialize)]
+pub struct DryRunConfig {
+    /// Capture emails instead of sending them.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Maximum captured emails kept; older ones are dropped.
+    #[serde(default = "default_mailbox_capacity")]
+    pub mailbox_capacity: usize,
+}
+
+impl Default for DryRunConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mailbox_capacity: default_mailbox_capacity(),
+        }
+    }
+}
+
+const fn default_mailbox_capacity() -> usize {
+    500
+}
+
+/// Recipient safety net configuration.
+///
+/// Outside production, recipients in `allowed_domains` are delivered as
+/// addressed; everyone else is redirected to `redirect_to` (with the
+/// original recipients noted in `X-Original-*` headers), or dropped when no
+/// redirect is set. With neither option set, mail is delivered unchanged.
+#[derive(Debug, Deserialize)]
+pub struct RecipientPolicyConfig {
+    /// Deployment environment; the policy is not applied in `production`.
+    #[serde(default = "default_environment")]
+    pub environment: String,
+    /// Address receiving mail for recipients outside the allowlist.
+    pub redirect_to: Option<String>,
+    /// Domains whose recipients receive mail directly.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+impl Default for RecipientPolicyConfig {
+    fn default() -> Self {
+        Self {
+            environment: default_environment(),
+            redirect_to: None,
+            allowed_domains: Vec::new(),
+        }
+    }
+}
+
+/// What to do with an attachment that is infected or could not be scanned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanAction {
+    /// Refuse to send the email.
+    Block,
+    /// Send the email without the attachment.
+    Strip,
+}
+
+/// Attachment virus scanning configuration.
+///
+/// When enabled, every attachment is streamed to `clamd` before sending.
+#[derive(Debug, Deserialize)]
+pub struct ScanningConfig {
+    /// Scan attachments before sending.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address of `clamd` (`host:port`).
+    #[serde(default = "default_clamd_address")]
+    pub clamd_address: String,
+    /// Block the email or strip the attachment.
+    #[serde(default = "default_scan_action")]
+    pub action: ScanAction,
+    /// Send attachments unscanned when `clamd` fails, instead of treating
+    /// them as infected.
+    #[serde(default)]
+    pub fail_open: bool,
+    /// Seconds to wait for a scan.
+    #[serde(default = "default_scan_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for ScanningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            clamd_address: default_clamd_address(),
+            action: default_scan_action(),
+            fail_open: false,
+            timeout_secs: default_scan_timeout_secs(),
+        }
+    }
+}
+
+/// Named email template configuration.
+///
+/// Each template is `NAME.subject.txt` plus `NAME.html` and/or `NAME.txt`
+/// in `directory`.
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplatesConfig {
+    /// Directory templates are loaded from (`None` = registered in code only).
+    pub directory: Option<PathBuf>,
+    /// Read template files again for every send; enable in development.
+    #[serde(default)]
+    pub hot_reload: bool,
+}
+
+/// Outbound queue configuration.
+///
+/// When enabled, sends are accepted once validated and delivered in the
+/// background, retrying transient SMTP failures with exponential backoff.
+/// Without a directory the queue is lost on restart.
+#[derive(Debug, Deserialize)]
+pub struct QueueConfig {
+    /// Queue sends instead of delivering them before responding.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory queued emails are saved in (`None` = memory only).
+    pub directory: Option<PathBuf>,
+    /// Attempts before an email is given up on.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Seconds before the first retry; doubles per attempt.
+    #[serde(default = "default_initial_backoff_secs")]
+    pub initial_backoff_secs: u64,
+    /// Longest wait between attempts, in seconds.
+    #[serde(default = "default_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+    /// Milliseconds between checks for due emails.
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: None,
+            max_attempts: default_max_attempts(),
+            initial_backoff_secs: default_initial_backoff_secs(),
+            max_backoff_secs: default_max_backoff_secs(),
+            poll_interval_ms: default_poll_interval_ms(),
+        }
+    }
+}
+
+const fn default_max_attempts() -> u32 {
+    8
+}
+
+const fn default_initial_backoff_secs() -> u64 {
+    30
+}
+
+const fn default_max_backoff_secs() -> u64 {
+    3600
+}
+
+const fn default_poll_interval_ms() -> u64 {
+    1000
+}
+
+/// Suppression list configuration.
+///
+/// Recipients that hard-bounce are added automatically; others can be
+/// added with the `SuppressAddress` RPC.
+#[derive(Debug, Default, Deserialize)]
+pub struct SuppressionConfig {
+    /// File the list is saved to (`None` = memory only).
+    pub path: Option<PathBuf>,
+}
+
+fn default_clamd_address() -> String {
+    "127.0.0.1:3310".to_string()
+}
+
+const fn default_scan_action() -> ScanAction {
+    ScanAction::Block
+}
+
+const fn default_scan_timeout_secs() -> u64 {
+    30
+}
+
+fn default_environment() -> String {
+    "development".to_string()
+}
+
 fn default_host() -> String {
     "0.0.0.0".to_string()
 }
@@ -71,6 +315,22 @@ const fn default_tls() -> bool {
     true
 }
 
+const fn default_max_retries() -> u32 {
+    2
+}
+
+const fn default_pool_connections() -> usize {
+    4
+}
+
+const fn default_max_messages_per_connection() -> usize {
+    100
+}
+
+const fn default_keep_alive_secs() -> u64 {
+    60
+}
+
 impl EmailServiceConfig {
     /// Load configuration from files and environment.
     ///
@@ -98,4 +358,66 @@ mod tests {
         assert_eq!(config.host, "0.0.0.0");
         assert_eq!(config.port, 50055);
     }
+
+    #[test]
+    fn test_default_pool_config() {
+        let config = SmtpPoolConfig::default();
+        assert_eq!(config.connections, 4);
+        assert_eq!(config.max_messages_per_connection, 100);
+        assert_eq!(config.keep_alive_secs, 60);
+    }
+
+    #[test]
+    fn test_default_dry_run_config() {
+        let config = DryRunConfig::default();
+        assert!(!config.enabled);
+        assert_eq!(config.mailbox_capacity, 500);
+    }
+
+    #[test]
+    fn test_scanning_config() {
+        let config = ScanningConfig::default();
+        assert!(!config.enabled);
+        assert_eq!(config.action, ScanAction::Block);
+
+        let config: ScanningConfig = Figment::new()
+            .merge(Toml::string("enabled = true\naction = \"strip\""))
+            .extract()
+            .unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.action, ScanAction::Strip);
+        assert_eq!(config.clamd_address, "127.0.0.1:3310");
+    }
+
+    #[test]
+    fn test_templates_config() {
+        let config = TemplatesConfig::default();
+        assert!(config.directory.is_none());
+        assert!(!config.hot_reload);
+
+        let config: TemplatesConfig = Figment::new()
+            .merge(Toml::string("directory = \"templates\"\nhot_reload = true"))
+            .extract()
+            .unwrap();
+        assert_eq!(config.directory, Some(PathBuf::from("templates")));
+        assert!(config.hot_reload);
+    }
+
+    #[test]
+    fn test_queue_config() {
+        let config = QueueConfig::default();
+        assert!(!config.enabled);
+        assert_eq!(config.max_attempts, 8);
+        assert_eq!(config.initial_backoff_secs, 30);
+        assert_eq!(config.max_backoff_secs, 3600);
+
+        let config: QueueConfig = Figment::new()
+            .merge(Toml::string("enabled = true\nmax_attempts = 3"))
+            .extract()
+            .unwrap();
+        assert!(config.enabled);
+        assert!(config.directory.is_none());
+        assert_eq!(config.max_attempts, 3);
+        assert_eq!(config.poll_interval_ms, 1000);
+    }
 }
@@ -9,4 +9,7 @@ pub mod config;
 pub mod services;
 
 pub use config::EmailServiceConfig;
-pub use services::EmailServiceImpl;
\ No newline at end of file
+pub use services::{
+    AttachmentScanning, EmailServiceImpl, EmailTemplates, OutboundQueue, RecipientPolicy,
+    SuppressionList,
+};
\ No newline at end of file
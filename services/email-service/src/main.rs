@@ -1,9 +1,13 @@
 //! Email service entry point.
 
 use acton_dx_proto::email::v1::email_service_server::EmailServiceServer;
-use email_service::{EmailServiceConfig, EmailServiceImpl};
+use email_service::{
+    AttachmentScanning, EmailServiceConfig, EmailServiceImpl, EmailTemplates, OutboundQueue,
+    RecipientPolicy, SuppressionList,
+};
 use lettre::message::Mailbox;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tonic::transport::Server;
 use tracing::{info, Level};
 use tracing_subscriber::EnvFilter;
@@ -38,14 +42,33 @@ async fn main() -> anyhow::Result<()> {
     };
 
     // Create the service
-    let service = EmailServiceImpl::new(
+    let mut service = EmailServiceImpl::new(
         &config.smtp.host,
         config.smtp.port,
         config.smtp.username.as_deref(),
         config.smtp.password.as_deref(),
         config.smtp.tls,
         default_from,
-    )?;
+    )?
+    .with_pool_config(&config.smtp.pool)?
+    .with_max_retries(config.smtp.max_retries)
+    .with_recipient_policy(RecipientPolicy::from_config(&config.recipients)?)
+    .with_templates(EmailTemplates::from_config(&config.templates)?);
+
+    if config.dry_run.enabled {
+        service = service.with_dry_run(config.dry_run.mailbox_capacity);
+    }
+    if let Some(scanning) = AttachmentScanning::from_config(&config.scanning) {
+        service = service.with_scanning(scanning);
+    }
+    if let Some(path) = &config.suppression.path {
+        service = service.with_suppression(SuppressionList::open(path.clone())?);
+    }
+    if config.queue.enabled {
+        service = service.with_queue(OutboundQueue::from_config(&config.queue)?);
+    }
+    let service = Arc::new(service);
+    let _queue_worker = service.spawn_queue_worker();
 
     info!(
         host = %config.smtp.host,
@@ -61,7 +84,7 @@ async fn main() -> anyhow::Result<()> {
 
     // Start the gRPC server
     Server::builder()
-        .add_service(EmailServiceServer::new(service))
+        .add_service(EmailServiceServer::from_arc(service))
         .serve(addr)
         .await?;
 
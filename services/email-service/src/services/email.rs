@@ -1,16 +1,42 @@
 //! Email service gRPC implementation.
 
+use super::mailbox::DevMailbox;
+use super::pool::{Security, SmtpPool, SmtpSettings};
+use super::preview::render_template;
+use super::queue::{OutboundQueue, Retry};
+use super::recipients::RecipientPolicy;
+use super::scanning::AttachmentScanning;
+use super::stats::{Delivery, EmailStats};
+use super::suppression::SuppressionList;
+use super::templates::{EmailTemplates, TemplateError};
+use crate::config::SmtpPoolConfig;
 use acton_dx_proto::email::v1::{
-    email_service_server::EmailService, Attachment, Email, EmailAddress, SendBatchRequest,
-    SendBatchResponse, SendEmailRequest, SendEmailResponse, ValidateAddressRequest,
+    email_service_server::EmailService, Attachment, Email, EmailAddress, EmailStatus,
+    GetEmailStatsRequest, GetEmailStatsResponse, GetEmailStatusRequest, ListDevMailboxRequest,
+    ListDevMailboxResponse, ListSuppressedRequest, ListSuppressedResponse, PreviewEmailRequest,
+    PreviewEmailResponse, SendBatchRequest, SendBatchResponse, SendEmailRequest, SendEmailResponse,
+    SendTemplatedEmailRequest, SuppressAddressRequest, SuppressAddressResponse,
+    UnsuppressAddressRequest, UnsuppressAddressResponse, ValidateAddressRequest,
     ValidateAddressResponse,
 };
-use lettre::message::{header::ContentType, Mailbox, MultiPart, SinglePart};
+use acton_dx_proto::error::invalid_field;
+use acton_dx_proto::error::v1::ErrorDetail;
+use acton_dx_proto::time::unix_now;
+use futures::stream::{self, StreamExt};
+use lettre::message::header::{ContentType, HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MessageBuilder, MultiPart, SinglePart};
 use lettre::transport::smtp::authentication::Credentials;
-use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use lettre::transport::smtp::response::Code as SmtpCode;
+use lettre::transport::smtp::Error as SmtpError;
+use lettre::Message;
 use std::sync::Arc;
-use tonic::{Request, Response, Status};
-use tracing::{debug, error, info};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tonic::{Code, Request, Response, Status};
+use tracing::{debug, error, info, warn};
+
+/// Delay before the first retry of a transient SMTP failure; doubles per attempt.
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
 
 /// Internal error type to avoid large error sizes.
 #[derive(Debug)]
@@ -28,15 +54,35 @@ impl EmailError {
 
 /// Email service implementation.
 pub struct EmailServiceImpl {
-    /// SMTP transport.
-    transport: Arc<AsyncSmtpTransport<Tokio1Executor>>,
+    /// Pooled SMTP connections.
+    pool: Arc<SmtpPool>,
     /// Default from address.
     default_from: Option<Mailbox>,
+    /// Dev mailbox capturing emails in dry-run mode (`None` = send normally).
+    mailbox: Option<Arc<DevMailbox>>,
+    /// Recipient restrictions applied before sending.
+    recipient_policy: RecipientPolicy,
+    /// Attachment virus scanning (`None` = attachments are not scanned).
+    scanning: Option<AttachmentScanning>,
+    /// Retries for transient SMTP failures.
+    max_retries: u32,
+    /// Delivery counters.
+    stats: Arc<EmailStats>,
+    /// Named templates for `SendTemplatedEmail`.
+    templates: Arc<EmailTemplates>,
+    /// Outbound queue and delivery statuses.
+    queue: Arc<OutboundQueue>,
+    /// Queue sends instead of delivering them before responding.
+    queue_enabled: bool,
+    /// Addresses that are never emailed.
+    suppression: Arc<SuppressionList>,
 }
 
 impl EmailServiceImpl {
     /// Create a new email service with SMTP transport.
     ///
+    /// Uses the default connection pool; see [`with_pool_config`](Self::with_pool_config).
+    ///
     /// # Errors
     ///
     /// Returns error if SMTP transport cannot be created.
@@ -48,40 +94,178 @@ impl EmailServiceImpl {
         tls: bool,
         default_from: Option<Mailbox>,
     ) -> anyhow::Result<Self> {
-        let mut transport_builder = if tls {
-            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
-        } else {
-            AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+        let settings = SmtpSettings {
+            host: host.to_string(),
+            port,
+            credentials: username
+                .zip(password)
+                .map(|(user, pass)| Credentials::new(user.to_string(), pass.to_string())),
+            security: if tls {
+                Security::StartTls
+            } else {
+                Security::Plain
+            },
         };
-
-        transport_builder = transport_builder.port(port);
-
-        if let (Some(user), Some(pass)) = (username, password) {
-            let credentials = Credentials::new(user.to_string(), pass.to_string());
-            transport_builder = transport_builder.credentials(credentials);
-        }
-
-        let transport = transport_builder.build();
+        let pool = SmtpPool::new(settings, &SmtpPoolConfig::default())?;
 
         info!(host = %host, port = %port, tls = %tls, "Created SMTP transport");
 
         Ok(Self {
-            transport: Arc::new(transport),
+            pool: Arc::new(pool),
             default_from,
+            mailbox: None,
+            recipient_policy: RecipientPolicy::default(),
+            scanning: None,
+            max_retries: 0,
+            stats: Arc::new(EmailStats::new()),
+            templates: Arc::new(EmailTemplates::default()),
+            queue: Arc::new(OutboundQueue::default()),
+            queue_enabled: false,
+            suppression: Arc::new(SuppressionList::new()),
         })
     }
 
+    /// Replace the connection pool with one using `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the pool cannot be created.
+    pub fn with_pool_config(mut self, config: &SmtpPoolConfig) -> anyhow::Result<Self> {
+        let pool = SmtpPool::new(self.pool.settings().clone(), config)?;
+        info!(
+            connections = config.connections,
+            max_messages_per_connection = config.max_messages_per_connection,
+            keep_alive_secs = config.keep_alive_secs,
+            "Configured SMTP connection pool"
+        );
+        self.pool = Arc::new(pool);
+        Ok(self)
+    }
+
+    /// Retry sends that fail with a transient SMTP error up to `max_retries` times.
+    #[must_use]
+    pub const fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Restrict or redirect recipients before sending.
+    #[must_use]
+    pub fn with_recipient_policy(mut self, policy: RecipientPolicy) -> Self {
+        self.recipient_policy = policy;
+        self
+    }
+
+    /// Scan attachments for viruses before sending.
+    #[must_use]
+    pub fn with_scanning(mut self, scanning: AttachmentScanning) -> Self {
+        self.scanning = Some(scanning);
+        self
+    }
+
+    /// Render `SendTemplatedEmail` requests with `templates`.
+    #[must_use]
+    pub fn with_templates(mut self, templates: EmailTemplates) -> Self {
+        self.templates = Arc::new(templates);
+        self
+    }
+
+    /// Named templates available to `SendTemplatedEmail`.
+    #[must_use]
+    pub fn templates(&self) -> &EmailTemplates {
+        &self.templates
+    }
+
+    /// Queue sends and deliver them in the background, retrying transient
+    /// failures; run [`spawn_queue_worker`](Self::spawn_queue_worker) to
+    /// deliver them.
+    #[must_use]
+    pub fn with_queue(mut self, queue: OutboundQueue) -> Self {
+        info!(pending = queue.pending(), "Outbound queue enabled");
+        self.queue = Arc::new(queue);
+        self.queue_enabled = true;
+        self
+    }
+
+    /// Never email the addresses on `suppression`.
+    #[must_use]
+    pub fn with_suppression(mut self, suppression: SuppressionList) -> Self {
+        self.suppression = Arc::new(suppression);
+        self
+    }
+
+    /// Addresses that are never emailed.
+    #[must_use]
+    pub fn suppression(&self) -> &SuppressionList {
+        &self.suppression
+    }
+
+    /// Deliver queued emails on a background task, or `None` if sends are
+    /// not queued.
+    #[must_use]
+    pub fn spawn_queue_worker(self: &Arc<Self>) -> Option<JoinHandle<()>> {
+        if !self.queue_enabled {
+            return None;
+        }
+        let service = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                let due = service
+                    .queue
+                    .claim_due(unix_now(), service.pool.connections());
+                if due.is_empty() {
+                    tokio::select! {
+                        () = service.queue.notified() => {}
+                        () = tokio::time::sleep(service.queue.poll_interval()) => {}
+                    }
+                    continue;
+                }
+                stream::iter(due)
+                    .for_each_concurrent(service.pool.connections(), |(message_id, email)| {
+                        service.deliver_queued(message_id, email)
+                    })
+                    .await;
+            }
+        }))
+    }
+
+    /// Enable dry-run mode: capture emails in a dev mailbox instead of sending.
+    #[must_use]
+    pub fn with_dry_run(mut self, mailbox_capacity: usize) -> Self {
+        warn!(mailbox_capacity, "Dry-run mode enabled; emails will not be sent");
+        self.mailbox = Some(Arc::new(DevMailbox::new(mailbox_capacity)));
+        self
+    }
+
     /// Create a service for testing (no actual sending).
+    ///
+    /// # Panics
+    ///
+    /// Never in practice; the placeholder relay settings are always valid.
     #[must_use]
     pub fn mock() -> Self {
         // Use localhost as a placeholder - won't actually connect
-        let transport = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous("localhost")
-            .port(25)
-            .build();
+        let settings = SmtpSettings {
+            host: "localhost".to_string(),
+            port: 25,
+            credentials: None,
+            security: Security::Dangerous,
+        };
+        let pool = SmtpPool::new(settings, &SmtpPoolConfig::default())
+            .expect("dangerous relay settings are always valid");
 
         Self {
-            transport: Arc::new(transport),
+            pool: Arc::new(pool),
             default_from: None,
+            mailbox: None,
+            recipient_policy: RecipientPolicy::default(),
+            scanning: None,
+            max_retries: 0,
+            stats: Arc::new(EmailStats::new()),
+            templates: Arc::new(EmailTemplates::default()),
+            queue: Arc::new(OutboundQueue::default()),
+            queue_enabled: false,
+            suppression: Arc::new(SuppressionList::new()),
         }
     }
 
@@ -132,6 +316,18 @@ impl EmailServiceImpl {
 
         builder = builder.subject(&email.subject);
 
+        for (name, value) in &email.headers {
+            let header_name = HeaderName::new_from_ascii(name.clone()).map_err(|e| {
+                error!(error = %e, header = %name, "Invalid header name");
+                EmailError::new(format!("Invalid header name {name}: {e}"))
+            })?;
+            builder = builder.raw_header(HeaderValue::new(header_name, value.clone()));
+        }
+
+        if let Some(ref calendar) = email.calendar {
+            return Self::build_invite(builder, email, calendar);
+        }
+
         // Build body
         let message = match (&email.text_body, &email.html_body) {
             (Some(text), Some(html)) => {
@@ -199,6 +395,54 @@ impl EmailServiceImpl {
         })
     }
 
+    /// Build a meeting invite: the bodies and a `text/calendar` part as
+    /// alternatives, so clients render it natively, plus the same calendar
+    /// as an `invite.ics` attachment for clients that only look there.
+    fn build_invite(
+        builder: MessageBuilder,
+        email: &Email,
+        calendar: &str,
+    ) -> Result<Message, EmailError> {
+        let calendar_type = ContentType::parse("text/calendar; method=REQUEST; charset=UTF-8")
+            .map_err(|e| EmailError::new(format!("Invalid calendar content type: {e}")))?;
+
+        let mut alternative = MultiPart::alternative().build();
+        if let Some(ref text) = email.text_body {
+            alternative = alternative.singlepart(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(text.clone()),
+            );
+        }
+        if let Some(ref html) = email.html_body {
+            alternative = alternative.singlepart(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_HTML)
+                    .body(html.clone()),
+            );
+        }
+        alternative = alternative.singlepart(
+            SinglePart::builder()
+                .header(calendar_type)
+                .body(calendar.to_string()),
+        );
+
+        let mut mixed = MultiPart::mixed().multipart(alternative);
+        for attachment in &email.attachments {
+            mixed = mixed.singlepart(Self::build_attachment(attachment)?);
+        }
+        mixed = mixed.singlepart(Self::build_attachment(&Attachment {
+            filename: "invite.ics".to_string(),
+            content: calendar.as_bytes().to_vec(),
+            content_type: "application/ics".to_string(),
+        })?);
+
+        builder.multipart(mixed).map_err(|e| {
+            error!(error = %e, "Failed to build invite message");
+            EmailError::new(format!("Failed to build message: {e}"))
+        })
+    }
+
     /// Build an attachment `SinglePart`.
     fn build_attachment(attachment: &Attachment) -> Result<SinglePart, EmailError> {
         let content_type: ContentType = attachment.content_type.parse().map_err(|e| {
@@ -216,9 +460,50 @@ impl EmailServiceImpl {
 
     /// Send a single email and return the response.
     async fn send_single(&self, email: &Email) -> SendEmailResponse {
-        let message = match self.build_message(email) {
+        let (email, suppressed) = self.suppression.filter(email);
+        if !suppressed.is_empty() {
+            debug!(recipients = ?suppressed, "Skipping suppressed recipients");
+            if email.to.is_empty() && email.cc.is_empty() && email.bcc.is_empty() {
+                self.stats.record(&email, Delivery::Failed);
+                return SendEmailResponse {
+                    success: false,
+                    message_id: None,
+                    error: Some("All recipients are suppressed".to_string()),
+                };
+            }
+        }
+
+        let email = match self.recipient_policy.apply(&email) {
+            Ok(email) => email,
+            Err(e) => {
+                self.stats.record(&email, Delivery::Failed);
+                return SendEmailResponse {
+                    success: false,
+                    message_id: None,
+                    error: Some(e),
+                };
+            }
+        };
+
+        let email = match &self.scanning {
+            Some(scanning) => match scanning.apply(&email).await {
+                Ok(email) => email,
+                Err(e) => {
+                    self.stats.record(&email, Delivery::Failed);
+                    return SendEmailResponse {
+                        success: false,
+                        message_id: None,
+                        error: Some(e),
+                    };
+                }
+            },
+            None => email,
+        };
+
+        let message = match self.build_message(&email) {
             Ok(m) => m,
             Err(e) => {
+                self.stats.record(&email, Delivery::Failed);
                 return SendEmailResponse {
                     success: false,
                     message_id: None,
@@ -227,27 +512,143 @@ impl EmailServiceImpl {
             }
         };
 
-        match self.transport.send(message).await {
-            Ok(response) => {
-                let message_id = uuid::Uuid::new_v4().to_string();
-                debug!(message_id = %message_id, "Email sent successfully");
-                SendEmailResponse {
-                    success: response.is_positive(),
-                    message_id: Some(message_id),
-                    error: None,
+        if let Some(mailbox) = &self.mailbox {
+            let message_id = uuid::Uuid::new_v4().to_string();
+            let recipients: Vec<&str> = email.to.iter().map(|a| a.email.as_str()).collect();
+            info!(
+                message_id = %message_id,
+                to = ?recipients,
+                subject = %email.subject,
+                "Dry run: captured email instead of sending"
+            );
+            self.queue.record(message_id.clone(), 0, None);
+            mailbox.store(message_id.clone(), email);
+            return SendEmailResponse {
+                success: true,
+                message_id: Some(message_id),
+                error: None,
+            };
+        }
+
+        if self.queue_enabled {
+            let message_id = uuid::Uuid::new_v4().to_string();
+            debug!(message_id = %message_id, "Email queued");
+            self.queue.enqueue(message_id.clone(), email);
+            return SendEmailResponse {
+                success: true,
+                message_id: Some(message_id),
+                error: None,
+            };
+        }
+
+        self.deliver(&email, message).await
+    }
+
+    /// Deliver an email before responding, retrying transient failures.
+    async fn deliver(&self, email: &Email, message: Message) -> SendEmailResponse {
+        let mut attempt = 0;
+        loop {
+            match self.pool.send(message.clone()).await {
+                Ok(response) => {
+                    let success = response.is_positive();
+                    let delivery = if success {
+                        Delivery::Sent
+                    } else {
+                        Delivery::Failed
+                    };
+                    self.stats.record(email, delivery);
+                    let message_id = uuid::Uuid::new_v4().to_string();
+                    let error = (!success).then(|| format!("Rejected with {}", response.code()));
+                    self.queue.record(message_id.clone(), attempt + 1, error);
+                    debug!(message_id = %message_id, "Email sent successfully");
+                    return SendEmailResponse {
+                        success,
+                        message_id: Some(message_id),
+                        error: None,
+                    };
+                }
+                Err(e) if e.is_transient() && attempt < self.max_retries => {
+                    attempt += 1;
+                    self.stats.record(email, Delivery::Retried);
+                    warn!(error = %e, attempt, "Transient SMTP failure, retrying");
+                    tokio::time::sleep(RETRY_BACKOFF * 2u32.saturating_pow(attempt - 1)).await;
+                }
+                Err(e) => {
+                    self.stats.record(email, Delivery::Failed);
+                    self.suppress_bounced(email, &e);
+                    error!(error = %e, "Failed to send email");
+                    return SendEmailResponse {
+                        success: false,
+                        message_id: None,
+                        error: Some(e.to_string()),
+                    };
                 }
             }
+        }
+    }
+
+    /// Make one attempt at delivering a queued email.
+    async fn deliver_queued(&self, message_id: String, email: Email) {
+        let message = match self.build_message(&email) {
+            Ok(message) => message,
             Err(e) => {
-                error!(error = %e, "Failed to send email");
-                SendEmailResponse {
-                    success: false,
-                    message_id: None,
-                    error: Some(e.to_string()),
+                self.stats.record(&email, Delivery::Failed);
+                self.queue.failed(&message_id, e.message, false);
+                return;
+            }
+        };
+        match self.pool.send(message).await {
+            Ok(response) if response.is_positive() => {
+                self.stats.record(&email, Delivery::Sent);
+                self.queue.sent(&message_id);
+                debug!(message_id = %message_id, "Queued email sent");
+            }
+            Ok(response) => {
+                self.stats.record(&email, Delivery::Failed);
+                self.queue.failed(
+                    &message_id,
+                    format!("Rejected with {}", response.code()),
+                    false,
+                );
+            }
+            Err(e) => {
+                self.suppress_bounced(&email, &e);
+                match self
+                    .queue
+                    .failed(&message_id, e.to_string(), e.is_transient())
+                {
+                    Retry::At(at) => {
+                        self.stats.record(&email, Delivery::Retried);
+                        warn!(
+                            message_id = %message_id,
+                            error = %e,
+                            next_attempt_at = at,
+                            "Queued email failed, retrying"
+                        );
+                    }
+                    Retry::GaveUp => {
+                        self.stats.record(&email, Delivery::Failed);
+                        error!(message_id = %message_id, error = %e, "Queued email failed");
+                    }
                 }
             }
         }
     }
 
+    /// Suppress the recipient of a single-recipient email that hard-bounced.
+    ///
+    /// With several recipients the server does not say which one bounced.
+    fn suppress_bounced(&self, email: &Email, error: &SmtpError) {
+        if !is_hard_bounce(error.is_permanent(), error.status()) {
+            return;
+        }
+        let mut recipients = email.to.iter().chain(&email.cc).chain(&email.bcc);
+        if let (Some(recipient), None) = (recipients.next(), recipients.next()) {
+            self.suppression
+                .add(&recipient.email, &format!("hard bounce: {error}"));
+        }
+    }
+
     /// Validate an email address.
     fn validate_email(email: &str) -> (bool, Option<String>) {
         // Basic email validation
@@ -292,6 +693,12 @@ impl EmailServiceImpl {
     }
 }
 
+/// Whether a failure means the mailbox does not exist or cannot receive
+/// mail: a permanent 550, 551 or 553 reply.
+fn is_hard_bounce(permanent: bool, code: Option<SmtpCode>) -> bool {
+    permanent && code.is_some_and(|code| matches!(code.to_string().as_str(), "550" | "551" | "553"))
+}
+
 #[tonic::async_trait]
 impl EmailService for EmailServiceImpl {
     async fn send_email(
@@ -302,7 +709,7 @@ impl EmailService for EmailServiceImpl {
 
         let email = req
             .email
-            .ok_or_else(|| Status::invalid_argument("Missing email"))?;
+            .ok_or_else(|| invalid_field("email", "Missing email"))?;
 
         let response = self.send_single(&email).await;
         Ok(Response::new(response))
@@ -314,28 +721,68 @@ impl EmailService for EmailServiceImpl {
     ) -> Result<Response<SendBatchResponse>, Status> {
         let req = request.into_inner();
 
-        let mut results = Vec::with_capacity(req.emails.len());
-        let mut succeeded = 0;
-        let mut failed = 0;
+        let total = Self::usize_to_i32(req.emails.len());
 
-        for email in &req.emails {
-            let result = self.send_single(email).await;
-            if result.success {
-                succeeded += 1;
-            } else {
-                failed += 1;
-            }
-            results.push(result);
-        }
+        // Send on all pooled connections at once, keeping results in order
+        let results: Vec<SendEmailResponse> = stream::iter(req.emails)
+            .map(|email| async move { self.send_single(&email).await })
+            .buffered(self.pool.connections())
+            .collect()
+            .await;
+        let succeeded = Self::usize_to_i32(results.iter().filter(|r| r.success).count());
+        let failed = Self::usize_to_i32(results.len()) - succeeded;
 
         Ok(Response::new(SendBatchResponse {
-            total: Self::usize_to_i32(req.emails.len()),
+            total,
             succeeded,
             failed,
             results,
         }))
     }
 
+    async fn send_templated_email(
+        &self,
+        request: Request<SendTemplatedEmailRequest>,
+    ) -> Result<Response<SendEmailResponse>, Status> {
+        let req = request.into_inner();
+
+        let mut email = req
+            .email
+            .ok_or_else(|| invalid_field("email", "Missing email"))?;
+        email.template = Some(req.template.clone());
+
+        let rendered = match self.templates.render(&req.template, &req.context_json) {
+            Ok(rendered) => rendered,
+            Err(TemplateError::NotFound(name)) => {
+                return Err(ErrorDetail::new(
+                    "UNKNOWN_TEMPLATE",
+                    format!("no email template {name}"),
+                )
+                .into_status(Code::NotFound));
+            }
+            Err(TemplateError::Context(e)) => return Err(invalid_field("context_json", e)),
+            Err(e @ TemplateError::Render(_)) => {
+                error!(
+                    error = %e,
+                    template = %req.template,
+                    "Failed to render email template"
+                );
+                self.stats.record(&email, Delivery::Failed);
+                return Ok(Response::new(SendEmailResponse {
+                    success: false,
+                    message_id: None,
+                    error: Some(e.to_string()),
+                }));
+            }
+        };
+        email.subject = rendered.subject;
+        email.html_body = rendered.html_body;
+        email.text_body = rendered.text_body;
+
+        let response = self.send_single(&email).await;
+        Ok(Response::new(response))
+    }
+
     async fn validate_address(
         &self,
         request: Request<ValidateAddressRequest>,
@@ -345,6 +792,119 @@ impl EmailService for EmailServiceImpl {
 
         Ok(Response::new(ValidateAddressResponse { valid, reason }))
     }
+
+    async fn preview_email(
+        &self,
+        request: Request<PreviewEmailRequest>,
+    ) -> Result<Response<PreviewEmailResponse>, Status> {
+        let req = request.into_inner();
+
+        let template = req
+            .template
+            .ok_or_else(|| invalid_field("template", "Missing template"))?;
+
+        let response = match render_template(&template, &req.context_json) {
+            Ok(rendered) => PreviewEmailResponse {
+                success: true,
+                subject: rendered.subject,
+                html_body: rendered.html_body,
+                text_body: rendered.text_body,
+                error: None,
+            },
+            Err(e) => {
+                debug!(error = %e, "Email preview failed");
+                PreviewEmailResponse {
+                    success: false,
+                    subject: String::new(),
+                    html_body: None,
+                    text_body: None,
+                    error: Some(e),
+                }
+            }
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn get_email_status(
+        &self,
+        request: Request<GetEmailStatusRequest>,
+    ) -> Result<Response<EmailStatus>, Status> {
+        let req = request.into_inner();
+
+        let status = self.queue.status(&req.message_id).ok_or_else(|| {
+            ErrorDetail::new(
+                "UNKNOWN_MESSAGE",
+                format!("no email with message ID {}", req.message_id),
+            )
+            .into_status(Code::NotFound)
+        })?;
+        Ok(Response::new(status))
+    }
+
+    async fn list_suppressed(
+        &self,
+        request: Request<ListSuppressedRequest>,
+    ) -> Result<Response<ListSuppressedResponse>, Status> {
+        let req = request.into_inner();
+        let limit = usize::try_from(req.limit).unwrap_or(usize::MAX);
+        let offset = usize::try_from(req.offset).unwrap_or(usize::MAX);
+
+        let (addresses, total) = self.suppression.list(limit, offset);
+        Ok(Response::new(ListSuppressedResponse {
+            addresses,
+            total: total as u64,
+        }))
+    }
+
+    async fn suppress_address(
+        &self,
+        request: Request<SuppressAddressRequest>,
+    ) -> Result<Response<SuppressAddressResponse>, Status> {
+        let req = request.into_inner();
+
+        if let (false, Some(reason)) = Self::validate_email(&req.email) {
+            return Err(invalid_field("email", reason));
+        }
+        let added = self.suppression.add(&req.email, &req.reason);
+        Ok(Response::new(SuppressAddressResponse { added }))
+    }
+
+    async fn unsuppress_address(
+        &self,
+        request: Request<UnsuppressAddressRequest>,
+    ) -> Result<Response<UnsuppressAddressResponse>, Status> {
+        let req = request.into_inner();
+
+        let removed = self.suppression.remove(&req.email);
+        if removed {
+            info!(email = %req.email, "Unsuppressed address");
+        }
+        Ok(Response::new(UnsuppressAddressResponse { removed }))
+    }
+
+    async fn get_email_stats(
+        &self,
+        _request: Request<GetEmailStatsRequest>,
+    ) -> Result<Response<GetEmailStatsResponse>, Status> {
+        Ok(Response::new(self.stats.snapshot()))
+    }
+
+    async fn list_dev_mailbox(
+        &self,
+        request: Request<ListDevMailboxRequest>,
+    ) -> Result<Response<ListDevMailboxResponse>, Status> {
+        let req = request.into_inner();
+        let limit = usize::try_from(req.limit).unwrap_or(usize::MAX);
+
+        Ok(Response::new(ListDevMailboxResponse {
+            dry_run: self.mailbox.is_some(),
+            messages: self
+                .mailbox
+                .as_ref()
+                .map(|mailbox| mailbox.list(limit))
+                .unwrap_or_default(),
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -379,6 +939,358 @@ mod tests {
         assert!(reason.is_some());
     }
 
+    fn email() -> Email {
+        Email {
+            from: Some(EmailAddress {
+                email: "noreply@example.com".to_string(),
+                name: None,
+            }),
+            to: vec![EmailAddress {
+                email: "customer@example.com".to_string(),
+                name: Some("Customer".to_string()),
+            }],
+            subject: "Your invoice".to_string(),
+            text_body: Some("Thanks!".to_string()),
+            ..Email::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_captures_instead_of_sending() {
+        let service = EmailServiceImpl::mock().with_dry_run(10);
+
+        let response = service.send_single(&email()).await;
+        assert!(response.success);
+
+        let listing = service
+            .list_dev_mailbox(Request::new(ListDevMailboxRequest { limit: 0 }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(listing.dry_run);
+        assert_eq!(listing.messages.len(), 1);
+        assert_eq!(listing.messages[0].message_id, response.message_id.unwrap());
+        assert_eq!(listing.messages[0].email.as_ref().unwrap().subject, "Your invoice");
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_still_validates_messages() {
+        let service = EmailServiceImpl::mock().with_dry_run(10);
+        let mut invalid = email();
+        invalid.to[0].email = "not-an-address".to_string();
+
+        let response = service.send_single(&invalid).await;
+        assert!(!response.success);
+        assert!(service.mailbox.as_ref().unwrap().list(0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recipient_policy_applies_before_transport() {
+        use crate::config::RecipientPolicyConfig;
+
+        let policy = RecipientPolicy::from_config(&RecipientPolicyConfig {
+            environment: "staging".to_string(),
+            redirect_to: Some("qa@example.com".to_string()),
+            allowed_domains: Vec::new(),
+        })
+        .unwrap();
+        let service = EmailServiceImpl::mock()
+            .with_dry_run(10)
+            .with_recipient_policy(policy);
+
+        assert!(service.send_single(&email()).await.success);
+        let captured = service.mailbox.as_ref().unwrap().list(0);
+        let sent = captured[0].email.as_ref().unwrap();
+        assert_eq!(sent.to[0].email, "qa@example.com");
+        assert_eq!(sent.headers["X-Original-To"], "customer@example.com");
+        assert!(service.build_message(sent).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_unscannable_attachments_block_send() {
+        use crate::config::ScanAction;
+        use crate::services::ClamAvScanner;
+        use acton_dx_proto::email::v1::Attachment;
+
+        // Nothing listens on the scanner's port, so every scan fails
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let scanner = ClamAvScanner::new(address, Duration::from_secs(5));
+        let scanning = AttachmentScanning::new(Arc::new(scanner), ScanAction::Block, false);
+        let service = EmailServiceImpl::mock()
+            .with_dry_run(10)
+            .with_scanning(scanning);
+
+        assert!(service.send_single(&email()).await.success);
+        let mut with_attachment = email();
+        with_attachment.attachments.push(Attachment {
+            filename: "upload.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            content: b"%PDF".to_vec(),
+        });
+        let response = service.send_single(&with_attachment).await;
+        assert!(!response.success);
+        assert_eq!(
+            response.error.as_deref(),
+            Some("Attachment upload.pdf blocked: scan failed")
+        );
+        assert_eq!(service.mailbox.as_ref().unwrap().list(0).len(), 1);
+    }
+
+    #[test]
+    fn test_invite_is_a_calendar_alternative() {
+        let service = EmailServiceImpl::mock();
+        let mut invite = email();
+        invite.html_body = Some("<p>Join us</p>".to_string());
+        invite.calendar =
+            Some("BEGIN:VCALENDAR\r\nMETHOD:REQUEST\r\nEND:VCALENDAR\r\n".to_string());
+
+        let message = service.build_message(&invite).unwrap();
+        let raw = String::from_utf8(message.formatted()).unwrap();
+        assert!(raw.contains("multipart/mixed"));
+        assert!(raw.contains("multipart/alternative"));
+        assert!(raw.contains("Content-Type: text/calendar; method=REQUEST; charset=utf-8"));
+        assert!(raw.contains("filename=\"invite.ics\""));
+        assert!(raw.contains("METHOD:REQUEST"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_results_keep_request_order() {
+        let service = EmailServiceImpl::mock()
+            .with_pool_config(&SmtpPoolConfig {
+                connections: 2,
+                ..SmtpPoolConfig::default()
+            })
+            .unwrap()
+            .with_dry_run(10);
+        let mut invalid = email();
+        invalid.to[0].email = "not-an-address".to_string();
+
+        let response = service
+            .send_batch(Request::new(SendBatchRequest {
+                emails: vec![email(), invalid, email()],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((response.succeeded, response.failed), (2, 1));
+        let outcomes: Vec<_> = response.results.iter().map(|r| r.success).collect();
+        assert_eq!(outcomes, [true, false, true]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_sends_are_counted() {
+        let service = EmailServiceImpl::mock();
+        let mut invalid = email();
+        invalid.template = Some("invoice".to_string());
+        invalid.from = Some(EmailAddress {
+            email: "bad address".to_string(),
+            name: None,
+        });
+
+        assert!(!service.send_single(&invalid).await.success);
+        let stats = service
+            .get_email_stats(Request::new(GetEmailStatsRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.entries[0].template, "invoice");
+        assert_eq!(stats.entries[0].domain, "example.com");
+    }
+
+    #[tokio::test]
+    async fn test_preview_email_renders_without_sending() {
+        use acton_dx_proto::email::v1::EmailTemplate;
+
+        let service = EmailServiceImpl::mock();
+        let response = service
+            .preview_email(Request::new(PreviewEmailRequest {
+                template: Some(EmailTemplate {
+                    subject: "Hi {{ name }}".to_string(),
+                    html_body: None,
+                    text_body: Some("Order {{ order }} shipped".to_string()),
+                }),
+                context_json: r#"{"name": "Ada", "order": 42}"#.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.success);
+        assert_eq!(response.subject, "Hi Ada");
+        assert_eq!(response.text_body.as_deref(), Some("Order 42 shipped"));
+    }
+
+    #[tokio::test]
+    async fn test_send_templated_email() {
+        use acton_dx_proto::email::v1::EmailTemplate;
+
+        let service = EmailServiceImpl::mock().with_dry_run(10);
+        service
+            .templates()
+            .register(
+                "shipped",
+                &EmailTemplate {
+                    subject: "Order {{ order }} shipped".to_string(),
+                    html_body: Some("<p>{{ note }}</p>".to_string()),
+                    text_body: Some("{{ note }}".to_string()),
+                },
+            )
+            .unwrap();
+        let request = |template: &str, context_json: &str| {
+            Request::new(SendTemplatedEmailRequest {
+                template: template.to_string(),
+                context_json: context_json.to_string(),
+                email: Some(email()),
+            })
+        };
+
+        let response = service
+            .send_templated_email(request("shipped", r#"{"order": 42, "note": "<3"}"#))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.success);
+        let captured = service.mailbox.as_ref().unwrap().list(0);
+        let sent = captured[0].email.as_ref().unwrap();
+        assert_eq!(sent.subject, "Order 42 shipped");
+        assert_eq!(sent.html_body.as_deref(), Some("<p>&lt;3</p>"));
+        assert_eq!(sent.text_body.as_deref(), Some("<3"));
+        assert_eq!(sent.template.as_deref(), Some("shipped"));
+
+        let status = service
+            .send_templated_email(request("missing", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        let status = service
+            .send_templated_email(request("shipped", "[]"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_queued_sends_report_status() {
+        use crate::config::QueueConfig;
+        use crate::services::queue::STATE_QUEUED;
+
+        let service =
+            EmailServiceImpl::mock().with_queue(OutboundQueue::new(&QueueConfig::default()));
+        let response = service.send_single(&email()).await;
+        assert!(response.success);
+        let message_id = response.message_id.unwrap();
+
+        let status = service
+            .get_email_status(Request::new(GetEmailStatusRequest {
+                message_id: message_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.state, STATE_QUEUED);
+        assert_eq!(status.attempts, 0);
+        assert_eq!(service.queue.claim_due(unix_now(), 10)[0].0, message_id);
+
+        let status = service
+            .get_email_status(Request::new(GetEmailStatusRequest {
+                message_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_suppressed_recipients_are_never_emailed() {
+        let service = EmailServiceImpl::mock().with_dry_run(10);
+        let suppress = |email: &str| {
+            Request::new(SuppressAddressRequest {
+                email: email.to_string(),
+                reason: "hard bounce".to_string(),
+            })
+        };
+        let added = service
+            .suppress_address(suppress("Customer@example.com"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(added.added);
+        assert_eq!(
+            service
+                .suppress_address(suppress("nope"))
+                .await
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+
+        let response = service.send_single(&email()).await;
+        assert!(!response.success);
+        assert_eq!(
+            response.error.as_deref(),
+            Some("All recipients are suppressed")
+        );
+
+        let mut with_cc = email();
+        with_cc.cc.push(EmailAddress {
+            email: "accounts@example.com".to_string(),
+            name: None,
+        });
+        assert!(service.send_single(&with_cc).await.success);
+        let captured = service.mailbox.as_ref().unwrap().list(0);
+        let sent = captured[0].email.as_ref().unwrap();
+        assert!(sent.to.is_empty());
+        assert_eq!(sent.cc[0].email, "accounts@example.com");
+
+        let listed = service
+            .list_suppressed(Request::new(ListSuppressedRequest {
+                limit: 0,
+                offset: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(listed.total, 1);
+        assert_eq!(listed.addresses[0].email, "Customer@example.com");
+
+        let removed = service
+            .unsuppress_address(Request::new(UnsuppressAddressRequest {
+                email: "customer@example.com".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(removed.removed);
+        assert!(service.send_single(&email()).await.success);
+    }
+
+    #[test]
+    fn test_hard_bounces() {
+        use lettre::transport::smtp::response::{Category, Detail, Severity};
+
+        let code = |severity, detail| Some(SmtpCode::new(severity, Category::MailSystem, detail));
+        assert!(is_hard_bounce(
+            true,
+            code(Severity::PermanentNegativeCompletion, Detail::Zero)
+        ));
+        assert!(is_hard_bounce(
+            true,
+            code(Severity::PermanentNegativeCompletion, Detail::Three)
+        ));
+        // 552: mailbox full
+        assert!(!is_hard_bounce(
+            true,
+            code(Severity::PermanentNegativeCompletion, Detail::Two)
+        ));
+        assert!(!is_hard_bounce(
+            false,
+            code(Severity::TransientNegativeCompletion, Detail::Zero)
+        ));
+        assert!(!is_hard_bounce(true, None));
+    }
+
     #[test]
     fn test_safe_conversion() {
         assert_eq!(EmailServiceImpl::usize_to_i32(100), 100);
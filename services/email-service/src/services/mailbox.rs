@@ -0,0 +1,75 @@
+//! In-memory dev mailbox for dry-run mode.
+
+use acton_dx_proto::email::v1::{Email, MailboxEntry};
+use acton_dx_proto::time::unix_now;
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+
+/// Bounded store of emails captured instead of sent.
+///
+/// Holds the most recent `capacity` emails; older ones are dropped.
+#[derive(Debug)]
+pub struct DevMailbox {
+    /// Captured emails, oldest first.
+    messages: Mutex<VecDeque<MailboxEntry>>,
+    /// Maximum emails kept.
+    capacity: usize,
+}
+
+impl DevMailbox {
+    /// Create a mailbox holding up to `capacity` emails.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            messages: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Capture an email under the given message ID.
+    pub fn store(&self, message_id: String, email: Email) {
+        let captured_at = unix_now();
+
+        let mut messages = self.messages.lock().unwrap_or_else(PoisonError::into_inner);
+        while messages.len() >= self.capacity {
+            messages.pop_front();
+        }
+        messages.push_back(MailboxEntry {
+            message_id,
+            email: Some(email),
+            captured_at,
+        });
+    }
+
+    /// Captured emails, newest first; `limit` 0 returns all.
+    #[must_use]
+    pub fn list(&self, limit: usize) -> Vec<MailboxEntry> {
+        let messages = self.messages.lock().unwrap_or_else(PoisonError::into_inner);
+        let limit = if limit == 0 { messages.len() } else { limit };
+        messages.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(subject: &str) -> Email {
+        Email {
+            subject: subject.to_string(),
+            ..Email::default()
+        }
+    }
+
+    #[test]
+    fn test_mailbox_keeps_newest_first_within_capacity() {
+        let mailbox = DevMailbox::new(2);
+        mailbox.store("1".to_string(), email("first"));
+        mailbox.store("2".to_string(), email("second"));
+        mailbox.store("3".to_string(), email("third"));
+
+        let ids: Vec<_> = mailbox.list(0).into_iter().map(|m| m.message_id).collect();
+        assert_eq!(ids, ["3", "2"]);
+        assert_eq!(mailbox.list(1)[0].message_id, "3");
+    }
+}
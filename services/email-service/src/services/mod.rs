@@ -1,5 +1,22 @@
 //! Email service implementations.
 
 mod email;
+mod mailbox;
+mod pool;
+mod preview;
+mod queue;
+mod recipients;
+mod scanning;
+mod stats;
+mod suppression;
+mod templates;
 
 pub use email::EmailServiceImpl;
+pub use mailbox::DevMailbox;
+pub use preview::{render_template, RenderedEmail};
+pub use queue::{OutboundQueue, Retry, STATE_FAILED, STATE_QUEUED, STATE_SENT};
+pub use recipients::RecipientPolicy;
+pub use scanning::{AttachmentScanning, ClamAvScanner, ScanResult, VirusScanner};
+pub use stats::{Delivery, EmailStats};
+pub use suppression::SuppressionList;
+pub use templates::{EmailTemplates, TemplateError};
@@ -0,0 +1,188 @@
+//! SMTP connection pool.
+
+use crate::config::SmtpPoolConfig;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::response::Response;
+use lettre::transport::smtp::{AsyncSmtpTransportBuilder, Error as SmtpError, PoolConfig};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// How to connect to the relay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    /// Upgrade to TLS with STARTTLS.
+    StartTls,
+    /// Plain connection.
+    Plain,
+    /// Plain connection without relay validation (tests only).
+    Dangerous,
+}
+
+/// Everything needed to open a connection.
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    /// SMTP server host.
+    pub host: String,
+    /// SMTP server port.
+    pub port: u16,
+    /// Login credentials.
+    pub credentials: Option<Credentials>,
+    /// Connection security.
+    pub security: Security,
+}
+
+impl SmtpSettings {
+    /// Transport builder for the relay.
+    fn builder(&self) -> Result<AsyncSmtpTransportBuilder, SmtpError> {
+        let mut builder = match self.security {
+            Security::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.host)?,
+            Security::Plain => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host)?,
+            Security::Dangerous => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.host)
+            }
+        };
+        builder = builder.port(self.port);
+        if let Some(ref credentials) = self.credentials {
+            builder = builder.credentials(credentials.clone());
+        }
+        Ok(builder)
+    }
+
+    /// Build a transport holding a single kept-alive connection.
+    ///
+    /// Must be called within a Tokio runtime.
+    fn connect(
+        &self,
+        keep_alive: Duration,
+    ) -> Result<AsyncSmtpTransport<Tokio1Executor>, SmtpError> {
+        Ok(self
+            .builder()?
+            .pool_config(
+                PoolConfig::new()
+                    .min_idle(0)
+                    .max_size(1)
+                    .idle_timeout(keep_alive),
+            )
+            .build())
+    }
+}
+
+/// A connection and how many messages it has sent.
+struct Connection {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sent: usize,
+}
+
+/// Pool of SMTP connections shared by concurrent senders.
+///
+/// At most `connections` messages are in flight at once, each on its own
+/// connection. Idle connections are kept alive for reuse and replaced after
+/// `max_messages_per_connection` messages or after an error.
+pub struct SmtpPool {
+    settings: SmtpSettings,
+    keep_alive: Duration,
+    max_messages: usize,
+    connections: usize,
+    idle: Mutex<Vec<Connection>>,
+    permits: Semaphore,
+}
+
+impl SmtpPool {
+    /// Create a pool; connections are opened on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the relay settings are invalid.
+    pub fn new(settings: SmtpSettings, config: &SmtpPoolConfig) -> Result<Self, SmtpError> {
+        // Validate the settings up front rather than on the first send
+        settings.builder()?;
+        let connections = config.connections.max(1);
+        Ok(Self {
+            settings,
+            keep_alive: Duration::from_secs(config.keep_alive_secs),
+            max_messages: config.max_messages_per_connection.max(1),
+            connections,
+            idle: Mutex::new(Vec::with_capacity(connections)),
+            permits: Semaphore::new(connections),
+        })
+    }
+
+    /// Connection settings.
+    pub const fn settings(&self) -> &SmtpSettings {
+        &self.settings
+    }
+
+    /// Maximum messages sent concurrently.
+    pub const fn connections(&self) -> usize {
+        self.connections
+    }
+
+    /// Send a message on a pooled connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if connecting or sending fails.
+    ///
+    /// # Panics
+    ///
+    /// Never in practice; the pool's semaphore is never closed.
+    pub async fn send(&self, message: Message) -> Result<Response, SmtpError> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("pool semaphore is never closed");
+
+        let idle = self
+            .idle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+        let mut connection = match idle {
+            Some(connection) => connection,
+            None => Connection {
+                transport: self.settings.connect(self.keep_alive)?,
+                sent: 0,
+            },
+        };
+
+        let result = connection.transport.send(message).await;
+        connection.sent += 1;
+        if result.is_ok() && connection.sent < self.max_messages {
+            self.idle
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(connection);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(host: &str) -> SmtpSettings {
+        SmtpSettings {
+            host: host.to_string(),
+            port: 25,
+            credentials: None,
+            security: Security::Dangerous,
+        }
+    }
+
+    #[test]
+    fn test_pool_clamps_limits() {
+        let config = SmtpPoolConfig {
+            connections: 0,
+            max_messages_per_connection: 0,
+            keep_alive_secs: 30,
+        };
+        let pool = SmtpPool::new(settings("localhost"), &config).unwrap();
+        assert_eq!(pool.connections(), 1);
+        assert_eq!(pool.permits.available_permits(), 1);
+        assert_eq!(pool.max_messages, 1);
+    }
+}
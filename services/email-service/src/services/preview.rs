@@ -0,0 +1,108 @@
+//! Email template rendering for previews.
+
+use acton_dx_proto::email::v1::EmailTemplate;
+use minijinja::{AutoEscape, Environment};
+
+/// A rendered email template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedEmail {
+    /// Rendered subject line.
+    pub subject: String,
+    /// Rendered HTML body.
+    pub html_body: Option<String>,
+    /// Rendered plain text body.
+    pub text_body: Option<String>,
+}
+
+/// Render a template with a JSON object context.
+///
+/// Values are HTML-escaped in the HTML body only.
+///
+/// # Errors
+///
+/// Returns an error message if the context is not a JSON object or a
+/// template fails to parse or render.
+pub fn render_template(template: &EmailTemplate, context_json: &str) -> Result<RenderedEmail, String> {
+    let context = parse_context(context_json)?;
+
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|name| {
+        if name == "html_body" {
+            AutoEscape::Html
+        } else {
+            AutoEscape::None
+        }
+    });
+
+    let mut render_part = |name: &'static str, source: &str| -> Result<String, String> {
+        env.add_template_owned(name, source.to_string())
+            .map_err(|e| format!("Invalid {name} template: {e}"))?;
+        env.get_template(name)
+            .and_then(|t| t.render(&context))
+            .map_err(|e| format!("Failed to render {name}: {e}"))
+    };
+
+    Ok(RenderedEmail {
+        subject: render_part("subject", &template.subject)?.trim().to_string(),
+        html_body: template
+            .html_body
+            .as_deref()
+            .map(|source| render_part("html_body", source))
+            .transpose()?,
+        text_body: template
+            .text_body
+            .as_deref()
+            .map(|source| render_part("text_body", source))
+            .transpose()?,
+    })
+}
+
+/// Parse a template context, treating an empty string as no variables.
+///
+/// # Errors
+///
+/// Returns an error message if the context is not a JSON object.
+pub(super) fn parse_context(context_json: &str) -> Result<serde_json::Value, String> {
+    let context: serde_json::Value = if context_json.trim().is_empty() {
+        serde_json::Value::Object(serde_json::Map::new())
+    } else {
+        serde_json::from_str(context_json).map_err(|e| format!("Invalid context JSON: {e}"))?
+    };
+    if !context.is_object() {
+        return Err("Context must be a JSON object".to_string());
+    }
+    Ok(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> EmailTemplate {
+        EmailTemplate {
+            subject: "Welcome, {{ name }}".to_string(),
+            html_body: Some("<p>Hello {{ name }}</p>".to_string()),
+            text_body: Some("Hello {{ name }}".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_render_escapes_html_body_only() {
+        let rendered = render_template(&template(), r#"{"name": "<Ada>"}"#).unwrap();
+        assert_eq!(rendered.subject, "Welcome, <Ada>");
+        assert_eq!(rendered.html_body.as_deref(), Some("<p>Hello &lt;Ada&gt;</p>"));
+        assert_eq!(rendered.text_body.as_deref(), Some("Hello <Ada>"));
+    }
+
+    #[test]
+    fn test_render_rejects_bad_input() {
+        assert!(render_template(&template(), "[1, 2]").unwrap_err().contains("JSON object"));
+        assert!(render_template(&template(), "{").unwrap_err().contains("Invalid context JSON"));
+
+        let broken = EmailTemplate {
+            subject: "{% if %}".to_string(),
+            ..EmailTemplate::default()
+        };
+        assert!(render_template(&broken, "").unwrap_err().contains("subject"));
+    }
+}
@@ -0,0 +1,457 @@
+//! Outbound queue and delivery status of accepted emails.
+//!
+//! Queued emails are delivered by a background worker, one attempt at a
+//! time, and transient failures are retried with exponential backoff until
+//! `max_attempts` is reached. With a directory, each pending email is saved
+//! as `<message_id>.email` (its status and the email, length-delimited
+//! protobuf) and reloaded on startup; the file is removed once the email is
+//! sent or given up on.
+//!
+//! Statuses of finished emails are kept in memory, up to
+//! [`STATUS_CAPACITY`], for the `GetEmailStatus` RPC.
+
+use crate::config::QueueConfig;
+use acton_dx_proto::email::v1::{Email, EmailStatus};
+use acton_dx_proto::time::unix_now;
+use prost::Message;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+/// Waiting for its first or next attempt.
+pub const STATE_QUEUED: &str = "queued";
+/// Accepted by the SMTP server.
+pub const STATE_SENT: &str = "sent";
+/// Failed permanently or ran out of attempts.
+pub const STATE_FAILED: &str = "failed";
+
+/// Statuses of finished emails kept for `GetEmailStatus`.
+pub const STATUS_CAPACITY: usize = 10_000;
+
+/// Extension of saved queue entries.
+const ENTRY_EXTENSION: &str = "email";
+
+/// What happens to a queued email after a failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retry {
+    /// Attempted again at this Unix time.
+    At(i64),
+    /// Given up on.
+    GaveUp,
+}
+
+/// Outbound queue and status log.
+#[derive(Debug)]
+pub struct OutboundQueue {
+    /// Directory pending emails are saved in.
+    directory: Option<PathBuf>,
+    /// Attempts before an email is given up on.
+    max_attempts: u32,
+    /// Wait before the first retry.
+    initial_backoff: Duration,
+    /// Longest wait between attempts.
+    max_backoff: Duration,
+    /// Interval between checks for due emails.
+    poll_interval: Duration,
+    inner: Mutex<Inner>,
+    /// Woken when an email is queued.
+    queued: Notify,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Status of every known email.
+    statuses: HashMap<String, EmailStatus>,
+    /// Emails waiting to be delivered.
+    pending: HashMap<String, Email>,
+    /// Pending emails being attempted.
+    in_flight: HashSet<String>,
+    /// Finished emails, oldest first, for evicting statuses.
+    finished: VecDeque<String>,
+}
+
+impl Default for OutboundQueue {
+    fn default() -> Self {
+        Self::new(&QueueConfig::default())
+    }
+}
+
+impl OutboundQueue {
+    /// Create an empty queue kept in memory.
+    #[must_use]
+    pub fn new(config: &QueueConfig) -> Self {
+        Self {
+            directory: None,
+            max_attempts: config.max_attempts.max(1),
+            initial_backoff: Duration::from_secs(config.initial_backoff_secs),
+            max_backoff: Duration::from_secs(config.max_backoff_secs),
+            poll_interval: Duration::from_millis(config.poll_interval_ms.max(10)),
+            inner: Mutex::new(Inner::default()),
+            queued: Notify::new(),
+        }
+    }
+
+    /// Create the queue described by `config`, reloading emails saved in
+    /// its directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the directory cannot be created or read.
+    pub fn from_config(config: &QueueConfig) -> anyhow::Result<Self> {
+        let mut queue = Self::new(config);
+        if let Some(directory) = &config.directory {
+            queue.load(directory)?;
+            queue.directory = Some(directory.clone());
+        }
+        Ok(queue)
+    }
+
+    /// Interval between checks for due emails.
+    pub const fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Emails waiting to be delivered.
+    pub fn pending(&self) -> usize {
+        self.lock().pending.len()
+    }
+
+    /// Status of an email.
+    pub fn status(&self, message_id: &str) -> Option<EmailStatus> {
+        self.lock().statuses.get(message_id).cloned()
+    }
+
+    /// Wait until an email is queued.
+    pub async fn notified(&self) {
+        self.queued.notified().await;
+    }
+
+    /// Queue an email for delivery now.
+    pub fn enqueue(&self, message_id: String, email: Email) -> EmailStatus {
+        let now = unix_now();
+        let status = EmailStatus {
+            message_id: message_id.clone(),
+            state: STATE_QUEUED.to_string(),
+            attempts: 0,
+            last_error: None,
+            queued_at: now,
+            next_attempt_at: Some(now),
+            completed_at: None,
+        };
+        self.save(&status, &email);
+        let mut inner = self.lock();
+        inner.statuses.insert(message_id.clone(), status.clone());
+        inner.pending.insert(message_id, email);
+        drop(inner);
+        self.queued.notify_one();
+        status
+    }
+
+    /// Record an email delivered without queueing.
+    pub fn record(&self, message_id: String, attempts: u32, error: Option<String>) {
+        let now = unix_now();
+        let status = EmailStatus {
+            message_id: message_id.clone(),
+            state: if error.is_some() {
+                STATE_FAILED
+            } else {
+                STATE_SENT
+            }
+            .to_string(),
+            attempts,
+            last_error: error,
+            queued_at: now,
+            next_attempt_at: None,
+            completed_at: Some(now),
+        };
+        let mut inner = self.lock();
+        inner.statuses.insert(message_id.clone(), status);
+        inner.finish(message_id);
+    }
+
+    /// Claim up to `limit` emails due at `now` for an attempt.
+    pub fn claim_due(&self, now: i64, limit: usize) -> Vec<(String, Email)> {
+        let mut inner = self.lock();
+        let mut due: Vec<(i64, String)> = inner
+            .pending
+            .keys()
+            .filter(|id| !inner.in_flight.contains(*id))
+            .filter_map(|id| {
+                let at = inner.statuses.get(id)?.next_attempt_at.unwrap_or(now);
+                (at <= now).then(|| (at, id.clone()))
+            })
+            .collect();
+        due.sort_unstable();
+        due.truncate(limit);
+        let claimed = due
+            .into_iter()
+            .filter_map(|(_, id)| {
+                let email = inner.pending.get(&id)?.clone();
+                inner.in_flight.insert(id.clone());
+                Some((id, email))
+            })
+            .collect();
+        drop(inner);
+        claimed
+    }
+
+    /// Record a successful attempt.
+    pub fn sent(&self, message_id: &str) {
+        self.complete(message_id, None);
+    }
+
+    /// Record a failed attempt, scheduling a retry if it was `transient`
+    /// and attempts remain.
+    pub fn failed(&self, message_id: &str, error: String, transient: bool) -> Retry {
+        let mut inner = self.lock();
+        let Some(status) = inner.statuses.get_mut(message_id) else {
+            return Retry::GaveUp;
+        };
+        let attempts = status.attempts + 1;
+        if !transient || attempts >= self.max_attempts {
+            drop(inner);
+            self.complete(message_id, Some(error));
+            return Retry::GaveUp;
+        }
+        let at = unix_now().saturating_add(self.backoff(attempts));
+        status.attempts = attempts;
+        status.last_error = Some(error);
+        status.next_attempt_at = Some(at);
+        let status = status.clone();
+        inner.in_flight.remove(message_id);
+        let email = inner.pending.get(message_id).cloned();
+        drop(inner);
+        if let Some(email) = email {
+            self.save(&status, &email);
+        }
+        Retry::At(at)
+    }
+
+    /// Seconds to wait after `attempts` failed attempts.
+    fn backoff(&self, attempts: u32) -> i64 {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+            .min(self.max_backoff);
+        i64::try_from(backoff.as_secs()).unwrap_or(i64::MAX)
+    }
+
+    /// Mark a queued email sent (no error) or failed.
+    fn complete(&self, message_id: &str, error: Option<String>) {
+        let mut inner = self.lock();
+        inner.pending.remove(message_id);
+        inner.in_flight.remove(message_id);
+        if let Some(status) = inner.statuses.get_mut(message_id) {
+            status.attempts += 1;
+            status.state = if error.is_some() {
+                STATE_FAILED
+            } else {
+                STATE_SENT
+            }
+            .to_string();
+            if error.is_some() {
+                status.last_error = error;
+            }
+            status.next_attempt_at = None;
+            status.completed_at = Some(unix_now());
+        }
+        inner.finish(message_id.to_string());
+        drop(inner);
+        if let Some(path) = self.entry_path(message_id) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!(error = %e, path = %path.display(), "Failed to remove queued email");
+                }
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn entry_path(&self, message_id: &str) -> Option<PathBuf> {
+        self.directory
+            .as_ref()
+            .map(|directory| directory.join(format!("{message_id}.{ENTRY_EXTENSION}")))
+    }
+
+    /// Save a pending email, replacing its previous entry atomically.
+    fn save(&self, status: &EmailStatus, email: &Email) {
+        let Some(path) = self.entry_path(&status.message_id) else {
+            return;
+        };
+        let mut bytes = status.encode_length_delimited_to_vec();
+        bytes.extend(email.encode_length_delimited_to_vec());
+        let temp = path.with_extension("tmp");
+        if let Err(e) = std::fs::write(&temp, bytes).and_then(|()| std::fs::rename(&temp, &path)) {
+            warn!(error = %e, path = %path.display(), "Failed to save queued email");
+        }
+    }
+
+    /// Reload pending emails saved in `directory`, creating it if needed.
+    fn load(&self, directory: &Path) -> anyhow::Result<()> {
+        std::fs::create_dir_all(directory).map_err(|e| {
+            anyhow::anyhow!("Cannot create queue directory {}: {e}", directory.display())
+        })?;
+        let entries = std::fs::read_dir(directory).map_err(|e| {
+            anyhow::anyhow!("Cannot read queue directory {}: {e}", directory.display())
+        })?;
+        let mut inner = self.lock();
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+            match decode_entry(&path) {
+                Ok((status, email)) => {
+                    inner.pending.insert(status.message_id.clone(), email);
+                    inner.statuses.insert(status.message_id.clone(), status);
+                }
+                Err(e) => {
+                    warn!(error = %e, path = %path.display(), "Skipping unreadable queued email");
+                }
+            }
+        }
+        info!(
+            directory = %directory.display(),
+            pending = inner.pending.len(),
+            "Loaded outbound queue"
+        );
+        Ok(())
+    }
+}
+
+impl Inner {
+    /// Track a finished email, evicting the oldest finished statuses.
+    fn finish(&mut self, message_id: String) {
+        self.finished.push_back(message_id);
+        while self.finished.len() > STATUS_CAPACITY {
+            if let Some(oldest) = self.finished.pop_front() {
+                self.statuses.remove(&oldest);
+            }
+        }
+    }
+}
+
+fn decode_entry(path: &Path) -> anyhow::Result<(EmailStatus, Email)> {
+    let bytes = std::fs::read(path)?;
+    let mut buf = bytes.as_slice();
+    let status = EmailStatus::decode_length_delimited(&mut buf)?;
+    let email = Email::decode_length_delimited(&mut buf)?;
+    Ok((status, email))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> QueueConfig {
+        QueueConfig {
+            enabled: true,
+            max_attempts: 3,
+            initial_backoff_secs: 10,
+            max_backoff_secs: 15,
+            ..QueueConfig::default()
+        }
+    }
+
+    fn email(subject: &str) -> Email {
+        Email {
+            subject: subject.to_string(),
+            ..Email::default()
+        }
+    }
+
+    #[test]
+    fn test_retries_with_backoff_then_gives_up() {
+        let queue = OutboundQueue::new(&config());
+        queue.enqueue("a".to_string(), email("A"));
+        let now = unix_now();
+
+        let claimed = queue.claim_due(now, 10);
+        assert_eq!(claimed.len(), 1);
+        // Claimed emails are not handed out twice
+        assert!(queue.claim_due(now, 10).is_empty());
+
+        let Retry::At(at) = queue.failed("a", "421 busy".to_string(), true) else {
+            panic!("expected a retry");
+        };
+        assert!((now + 10..=now + 11).contains(&at));
+        assert!(queue.claim_due(now, 10).is_empty());
+
+        assert_eq!(queue.claim_due(at, 10).len(), 1);
+        let Retry::At(second) = queue.failed("a", "421 busy".to_string(), true) else {
+            panic!("expected a retry");
+        };
+        // 20 seconds, capped at 15
+        assert!(second - at <= 16);
+
+        queue.claim_due(second, 10);
+        assert_eq!(
+            queue.failed("a", "421 busy".to_string(), true),
+            Retry::GaveUp
+        );
+        let status = queue.status("a").unwrap();
+        assert_eq!(status.state, STATE_FAILED);
+        assert_eq!(status.attempts, 3);
+        assert_eq!(status.last_error.as_deref(), Some("421 busy"));
+        assert_eq!(queue.pending(), 0);
+    }
+
+    #[test]
+    fn test_permanent_failures_are_not_retried() {
+        let queue = OutboundQueue::new(&config());
+        queue.enqueue("a".to_string(), email("A"));
+        queue.enqueue("b".to_string(), email("B"));
+        assert_eq!(queue.claim_due(unix_now(), 1).len(), 1);
+        assert_eq!(queue.claim_due(unix_now(), 10).len(), 1);
+
+        assert_eq!(
+            queue.failed("a", "550 no such user".to_string(), false),
+            Retry::GaveUp
+        );
+        queue.sent("b");
+        assert_eq!(queue.status("a").unwrap().state, STATE_FAILED);
+        let sent = queue.status("b").unwrap();
+        assert_eq!((sent.state.as_str(), sent.attempts), (STATE_SENT, 1));
+        assert!(sent.completed_at.is_some());
+    }
+
+    #[test]
+    fn test_pending_emails_survive_restarts() {
+        let directory = std::env::temp_dir().join(format!("email-queue-{}", uuid::Uuid::new_v4()));
+        let config = QueueConfig {
+            directory: Some(directory.clone()),
+            ..config()
+        };
+        let queue = OutboundQueue::from_config(&config).unwrap();
+        queue.enqueue("a".to_string(), email("Kept"));
+        queue.enqueue("b".to_string(), email("Sent"));
+        queue.claim_due(unix_now(), 10);
+        queue.failed("a", "421 busy".to_string(), true);
+        queue.sent("b");
+
+        let reloaded = OutboundQueue::from_config(&config).unwrap();
+        assert_eq!(reloaded.pending(), 1);
+        let status = reloaded.status("a").unwrap();
+        assert_eq!((status.state.as_str(), status.attempts), (STATE_QUEUED, 1));
+        assert!(reloaded.status("b").is_none());
+        let due = reloaded.claim_due(i64::MAX, 10);
+        assert_eq!(due[0].1.subject, "Kept");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_finished_statuses_are_bounded() {
+        let queue = OutboundQueue::default();
+        for i in 0..=STATUS_CAPACITY {
+            queue.record(i.to_string(), 1, None);
+        }
+        assert!(queue.status("0").is_none());
+        assert_eq!(queue.status("1").unwrap().state, STATE_SENT);
+    }
+}
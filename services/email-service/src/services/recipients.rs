@@ -0,0 +1,202 @@
+//! Recipient safety net for non-production environments.
+
+use crate::config::RecipientPolicyConfig;
+use acton_dx_proto::email::v1::{Email, EmailAddress};
+use tracing::{info, warn};
+
+/// Restricts who outbound mail may reach.
+///
+/// Recipients in an allowed domain are delivered as addressed. Everyone
+/// else is replaced by the redirect address, or dropped when no redirect
+/// is configured. Replaced recipients are listed in `X-Original-To`,
+/// `X-Original-Cc` and `X-Original-Bcc` headers.
+#[derive(Debug, Clone, Default)]
+pub struct RecipientPolicy {
+    /// Address receiving mail for recipients outside the allowlist.
+    redirect_to: Option<String>,
+    /// Lowercased domains whose recipients are delivered unchanged.
+    allowed_domains: Vec<String>,
+}
+
+impl RecipientPolicy {
+    /// Build the policy for the configured environment.
+    ///
+    /// Production environments are unrestricted.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the redirect address is invalid.
+    pub fn from_config(config: &RecipientPolicyConfig) -> anyhow::Result<Self> {
+        if config.environment.eq_ignore_ascii_case("production") {
+            return Ok(Self::default());
+        }
+        if let Some(ref redirect) = config.redirect_to {
+            redirect
+                .parse::<lettre::Address>()
+                .map_err(|e| anyhow::anyhow!("Invalid redirect_to address {redirect}: {e}"))?;
+        }
+        let policy = Self {
+            redirect_to: config.redirect_to.clone(),
+            allowed_domains: config
+                .allowed_domains
+                .iter()
+                .map(|d| d.trim().trim_start_matches('@').to_ascii_lowercase())
+                .collect(),
+        };
+        if policy.is_active() {
+            info!(
+                environment = %config.environment,
+                redirect_to = ?policy.redirect_to,
+                allowed_domains = ?policy.allowed_domains,
+                "Recipient policy enabled"
+            );
+        }
+        Ok(policy)
+    }
+
+    /// Whether the policy changes any recipients.
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.redirect_to.is_some() || !self.allowed_domains.is_empty()
+    }
+
+    /// Rewrite the email's recipients according to the policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if no recipient is left to deliver to.
+    pub fn apply(&self, email: &Email) -> Result<Email, String> {
+        let mut email = email.clone();
+        if !self.is_active() {
+            return Ok(email);
+        }
+
+        let mut replaced = false;
+        for (recipients, header) in [
+            (&mut email.to, "X-Original-To"),
+            (&mut email.cc, "X-Original-Cc"),
+            (&mut email.bcc, "X-Original-Bcc"),
+        ] {
+            let (kept, removed): (Vec<_>, Vec<_>) =
+                recipients.drain(..).partition(|addr| self.is_allowed(&addr.email));
+            *recipients = kept;
+            if !removed.is_empty() {
+                replaced = true;
+                let original: Vec<&str> = removed.iter().map(|a| a.email.as_str()).collect();
+                email.headers.insert(header.to_string(), original.join(", "));
+            }
+        }
+
+        if replaced {
+            if let Some(ref redirect) = self.redirect_to {
+                if !email.to.iter().any(|a| a.email.eq_ignore_ascii_case(redirect)) {
+                    email.to.push(EmailAddress {
+                        email: redirect.clone(),
+                        name: None,
+                    });
+                }
+            } else {
+                warn!(subject = %email.subject, "Dropped recipients outside the allowed domains");
+            }
+        }
+
+        if email.to.is_empty() && email.cc.is_empty() && email.bcc.is_empty() {
+            return Err("All recipients are outside the allowed domains".to_string());
+        }
+        Ok(email)
+    }
+
+    /// Whether an address may receive mail directly.
+    fn is_allowed(&self, address: &str) -> bool {
+        address.rsplit_once('@').is_some_and(|(_, domain)| {
+            let domain = domain.to_ascii_lowercase();
+            self.allowed_domains.contains(&domain)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(redirect_to: Option<&str>, allowed_domains: &[&str]) -> RecipientPolicyConfig {
+        RecipientPolicyConfig {
+            environment: "staging".to_string(),
+            redirect_to: redirect_to.map(str::to_string),
+            allowed_domains: allowed_domains.iter().map(|d| (*d).to_string()).collect(),
+        }
+    }
+
+    fn addr(email: &str) -> EmailAddress {
+        EmailAddress {
+            email: email.to_string(),
+            name: None,
+        }
+    }
+
+    fn email() -> Email {
+        Email {
+            to: vec![addr("customer@gmail.com"), addr("dev@Example.com")],
+            bcc: vec![addr("audit@corp.net")],
+            subject: "Invoice".to_string(),
+            ..Email::default()
+        }
+    }
+
+    #[test]
+    fn test_redirect_notes_original_recipients() {
+        let policy = RecipientPolicy::from_config(&config(Some("qa@example.com"), &[])).unwrap();
+        let email = policy.apply(&email()).unwrap();
+
+        let to: Vec<_> = email.to.iter().map(|a| a.email.as_str()).collect();
+        assert_eq!(to, ["qa@example.com"]);
+        assert!(email.bcc.is_empty());
+        assert_eq!(email.headers["X-Original-To"], "customer@gmail.com, dev@Example.com");
+        assert_eq!(email.headers["X-Original-Bcc"], "audit@corp.net");
+        assert!(!email.headers.contains_key("X-Original-Cc"));
+    }
+
+    #[test]
+    fn test_allowlist_keeps_allowed_domains() {
+        let policy = RecipientPolicy::from_config(&config(None, &["@example.com"])).unwrap();
+        let email = policy.apply(&email()).unwrap();
+
+        let to: Vec<_> = email.to.iter().map(|a| a.email.as_str()).collect();
+        assert_eq!(to, ["dev@Example.com"]);
+        assert!(email.bcc.is_empty());
+
+        let mut blocked = Email {
+            to: vec![addr("customer@gmail.com")],
+            ..Email::default()
+        };
+        assert!(policy.apply(&blocked).is_err());
+        blocked.cc.push(addr("qa@example.com"));
+        assert!(policy.apply(&blocked).is_ok());
+    }
+
+    #[test]
+    fn test_allowlist_with_redirect() {
+        let policy =
+            RecipientPolicy::from_config(&config(Some("qa@example.com"), &["example.com"]))
+                .unwrap();
+        let email = policy.apply(&email()).unwrap();
+
+        let to: Vec<_> = email.to.iter().map(|a| a.email.as_str()).collect();
+        assert_eq!(to, ["dev@Example.com", "qa@example.com"]);
+        assert_eq!(email.headers["X-Original-To"], "customer@gmail.com");
+    }
+
+    #[test]
+    fn test_production_is_unrestricted() {
+        let mut production = config(Some("qa@example.com"), &["example.com"]);
+        production.environment = "Production".to_string();
+        let policy = RecipientPolicy::from_config(&production).unwrap();
+        assert!(!policy.is_active());
+        assert_eq!(policy.apply(&email()).unwrap(), email());
+    }
+
+    #[test]
+    fn test_invalid_redirect_is_rejected() {
+        assert!(RecipientPolicy::from_config(&config(Some("not-an-address"), &[])).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+//! Virus scanning of outgoing attachments.
+//!
+//! Mirrors the `VirusScanner` trait of `acton_htmx::storage::scanning`, so
+//! attachments forwarded from user uploads get the same `ClamAV` check here
+//! that they got when they were uploaded.
+
+use crate::config::{ScanAction, ScanningConfig};
+use acton_dx_proto::email::v1::Email;
+use futures::future::BoxFuture;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+
+/// Bytes sent per `INSTREAM` chunk.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Header listing attachments removed by [`ScanAction::Strip`].
+const REMOVED_HEADER: &str = "X-Attachments-Removed";
+
+/// Result of a virus scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanResult {
+    /// No threats detected.
+    Clean,
+    /// Threat detected.
+    Infected {
+        /// Name of the detected threat.
+        threat: String,
+    },
+    /// The scanner failed.
+    Error {
+        /// Error message.
+        message: String,
+    },
+}
+
+impl fmt::Display for ScanResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Clean => write!(f, "Clean"),
+            Self::Infected { threat } => write!(f, "Infected: {threat}"),
+            Self::Error { message } => write!(f, "Scan error: {message}"),
+        }
+    }
+}
+
+/// Virus scanning backend.
+pub trait VirusScanner: Send + Sync {
+    /// Scan an attachment's content.
+    fn scan<'a>(&'a self, filename: &'a str, content: &'a [u8]) -> BoxFuture<'a, ScanResult>;
+
+    /// Name of the scanner implementation.
+    fn name(&self) -> &'static str;
+}
+
+/// `ClamAV` scanner talking to `clamd` over TCP with `INSTREAM`.
+#[derive(Debug, Clone)]
+pub struct ClamAvScanner {
+    address: String,
+    timeout: Duration,
+}
+
+impl ClamAvScanner {
+    /// Create a scanner for the `clamd` listening at `address` (`host:port`).
+    #[must_use]
+    pub fn new(address: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            address: address.into(),
+            timeout,
+        }
+    }
+
+    async fn instream(&self, content: &[u8]) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(&self.address).await?;
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in content.chunks(CHUNK_SIZE) {
+            let len = u32::try_from(chunk.len()).unwrap_or(u32::MAX);
+            stream.write_all(&len.to_be_bytes()).await?;
+            stream.write_all(chunk).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        Ok(String::from_utf8_lossy(&reply).into_owned())
+    }
+}
+
+impl VirusScanner for ClamAvScanner {
+    fn scan<'a>(&'a self, _filename: &'a str, content: &'a [u8]) -> BoxFuture<'a, ScanResult> {
+        Box::pin(async move {
+            match tokio::time::timeout(self.timeout, self.instream(content)).await {
+                Ok(Ok(reply)) => parse_reply(&reply),
+                Ok(Err(e)) => ScanResult::Error {
+                    message: format!("clamd at {}: {e}", self.address),
+                },
+                Err(_) => ScanResult::Error {
+                    message: format!("clamd at {} timed out", self.address),
+                },
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "ClamAV"
+    }
+}
+
+/// Interpret a `clamd` reply such as `stream: Eicar-Signature FOUND`.
+fn parse_reply(reply: &str) -> ScanResult {
+    let reply = reply.trim_end_matches(['\0', '\n', '\r']).trim();
+    let verdict = reply.strip_prefix("stream:").map_or(reply, str::trim);
+    if verdict == "OK" {
+        ScanResult::Clean
+    } else if let Some(threat) = verdict.strip_suffix(" FOUND") {
+        ScanResult::Infected {
+            threat: threat.to_string(),
+        }
+    } else {
+        ScanResult::Error {
+            message: verdict.to_string(),
+        }
+    }
+}
+
+/// Scans attachments before send and blocks or strips unsafe ones.
+#[derive(Clone)]
+pub struct AttachmentScanning {
+    scanner: Arc<dyn VirusScanner>,
+    action: ScanAction,
+    fail_open: bool,
+}
+
+impl fmt::Debug for AttachmentScanning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttachmentScanning")
+            .field("scanner", &self.scanner.name())
+            .field("action", &self.action)
+            .field("fail_open", &self.fail_open)
+            .finish()
+    }
+}
+
+impl AttachmentScanning {
+    /// Scan with `scanner`, handling unsafe attachments with `action`.
+    ///
+    /// Attachments that could not be scanned are treated as unsafe unless
+    /// `fail_open` is set.
+    #[must_use]
+    pub fn new(scanner: Arc<dyn VirusScanner>, action: ScanAction, fail_open: bool) -> Self {
+        Self {
+            scanner,
+            action,
+            fail_open,
+        }
+    }
+
+    /// Build `ClamAV` scanning from configuration, if enabled.
+    #[must_use]
+    pub fn from_config(config: &ScanningConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        info!(
+            clamd_address = %config.clamd_address,
+            action = ?config.action,
+            fail_open = config.fail_open,
+            "Attachment virus scanning enabled"
+        );
+        let scanner = ClamAvScanner::new(
+            config.clamd_address.clone(),
+            Duration::from_secs(config.timeout_secs),
+        );
+        Some(Self::new(
+            Arc::new(scanner),
+            config.action,
+            config.fail_open,
+        ))
+    }
+
+    /// Scan the email's attachments.
+    ///
+    /// Returns the email to send: unchanged when everything is clean, or
+    /// without the unsafe attachments (listed in `X-Attachments-Removed`)
+    /// when stripping.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if an attachment is unsafe and the action
+    /// is [`ScanAction::Block`].
+    pub async fn apply(&self, email: &Email) -> Result<Email, String> {
+        let mut email = email.clone();
+        let mut kept = Vec::with_capacity(email.attachments.len());
+        let mut removed = Vec::new();
+        for attachment in std::mem::take(&mut email.attachments) {
+            let reason = match self
+                .scanner
+                .scan(&attachment.filename, &attachment.content)
+                .await
+            {
+                ScanResult::Clean => None,
+                ScanResult::Infected { threat } => Some(threat),
+                ScanResult::Error { message } if self.fail_open => {
+                    warn!(
+                        filename = %attachment.filename,
+                        error = %message,
+                        "Attachment scan failed; sending unscanned"
+                    );
+                    None
+                }
+                ScanResult::Error { message } => {
+                    warn!(filename = %attachment.filename, error = %message, "Attachment scan failed");
+                    Some("scan failed".to_string())
+                }
+            };
+            let Some(reason) = reason else {
+                kept.push(attachment);
+                continue;
+            };
+            warn!(
+                filename = %attachment.filename,
+                reason = %reason,
+                subject = %email.subject,
+                "Unsafe attachment"
+            );
+            if self.action == ScanAction::Block {
+                return Err(format!(
+                    "Attachment {} blocked: {reason}",
+                    attachment.filename
+                ));
+            }
+            removed.push(format!("{} ({reason})", attachment.filename));
+        }
+
+        email.attachments = kept;
+        if !removed.is_empty() {
+            email
+                .headers
+                .insert(REMOVED_HEADER.to_string(), removed.join(", "));
+        }
+        Ok(email)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acton_dx_proto::email::v1::Attachment;
+    use tokio::net::TcpListener;
+
+    /// Flags content containing "EICAR"; fails on content "error".
+    struct FakeScanner;
+
+    impl VirusScanner for FakeScanner {
+        fn scan<'a>(&'a self, _filename: &'a str, content: &'a [u8]) -> BoxFuture<'a, ScanResult> {
+            let result = if content == b"error" {
+                ScanResult::Error {
+                    message: "unavailable".to_string(),
+                }
+            } else if content.windows(5).any(|w| w == b"EICAR") {
+                ScanResult::Infected {
+                    threat: "Eicar-Test-Signature".to_string(),
+                }
+            } else {
+                ScanResult::Clean
+            };
+            Box::pin(async move { result })
+        }
+
+        fn name(&self) -> &'static str {
+            "Fake"
+        }
+    }
+
+    fn attachment(filename: &str, content: &[u8]) -> Attachment {
+        Attachment {
+            filename: filename.to_string(),
+            content_type: "application/octet-stream".to_string(),
+            content: content.to_vec(),
+        }
+    }
+
+    fn email(attachments: Vec<Attachment>) -> Email {
+        Email {
+            subject: "Report".to_string(),
+            attachments,
+            ..Email::default()
+        }
+    }
+
+    fn scanning(action: ScanAction, fail_open: bool) -> AttachmentScanning {
+        AttachmentScanning::new(Arc::new(FakeScanner), action, fail_open)
+    }
+
+    #[tokio::test]
+    async fn test_block_rejects_infected_email() {
+        let infected = email(vec![
+            attachment("report.pdf", b"%PDF"),
+            attachment("invoice.exe", b"X5O!EICAR"),
+        ]);
+        let error = scanning(ScanAction::Block, false)
+            .apply(&infected)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "Attachment invoice.exe blocked: Eicar-Test-Signature"
+        );
+
+        let clean = email(vec![attachment("report.pdf", b"%PDF")]);
+        let sent = scanning(ScanAction::Block, false)
+            .apply(&clean)
+            .await
+            .unwrap();
+        assert_eq!(sent, clean);
+    }
+
+    #[tokio::test]
+    async fn test_strip_removes_unsafe_attachments() {
+        let infected = email(vec![
+            attachment("report.pdf", b"%PDF"),
+            attachment("invoice.exe", b"X5O!EICAR"),
+            attachment("notes.txt", b"error"),
+        ]);
+        let sent = scanning(ScanAction::Strip, false)
+            .apply(&infected)
+            .await
+            .unwrap();
+
+        let names: Vec<_> = sent
+            .attachments
+            .iter()
+            .map(|a| a.filename.as_str())
+            .collect();
+        assert_eq!(names, ["report.pdf"]);
+        assert_eq!(
+            sent.headers[REMOVED_HEADER],
+            "invoice.exe (Eicar-Test-Signature), notes.txt (scan failed)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fail_open_sends_unscanned() {
+        let unscanned = email(vec![attachment("notes.txt", b"error")]);
+        let sent = scanning(ScanAction::Block, true)
+            .apply(&unscanned)
+            .await
+            .unwrap();
+        assert_eq!(sent, unscanned);
+        assert!(scanning(ScanAction::Block, false)
+            .apply(&unscanned)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply("stream: OK\0"), ScanResult::Clean);
+        assert_eq!(
+            parse_reply("stream: Win.Test.EICAR_HDB-1 FOUND\0"),
+            ScanResult::Infected {
+                threat: "Win.Test.EICAR_HDB-1".to_string()
+            }
+        );
+        assert_eq!(
+            parse_reply("INSTREAM size limit exceeded. ERROR\0"),
+            ScanResult::Error {
+                message: "INSTREAM size limit exceeded. ERROR".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clamav_instream_protocol() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut command = [0u8; 10];
+            socket.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+            let mut received = Vec::new();
+            loop {
+                let len = socket.read_u32().await.unwrap() as usize;
+                if len == 0 {
+                    break;
+                }
+                let mut chunk = vec![0u8; len];
+                socket.read_exact(&mut chunk).await.unwrap();
+                received.extend(chunk);
+            }
+            socket.write_all(b"stream: OK\0").await.unwrap();
+            received
+        });
+
+        let scanner = ClamAvScanner::new(address, Duration::from_secs(5));
+        let content = vec![7u8; CHUNK_SIZE + 10];
+        assert_eq!(scanner.scan("big.bin", &content).await, ScanResult::Clean);
+        assert_eq!(server.await.unwrap(), content);
+    }
+
+    #[tokio::test]
+    async fn test_clamav_unreachable_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let scanner = ClamAvScanner::new(address, Duration::from_secs(5));
+        assert!(matches!(
+            scanner.scan("a.txt", b"hello").await,
+            ScanResult::Error { .. }
+        ));
+    }
+}
@@ -0,0 +1,169 @@
+//! Delivery counters by template and recipient domain.
+
+use acton_dx_proto::email::v1::{Email, EmailStatsEntry, GetEmailStatsResponse};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Mutex, PoisonError};
+
+/// Template label for emails sent without a template name.
+const NO_TEMPLATE: &str = "none";
+
+/// Delivery outcome of one send attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Accepted by the SMTP server.
+    Sent,
+    /// Rejected or undeliverable.
+    Failed,
+    /// Failed transiently and will be attempted again.
+    Retried,
+}
+
+/// Counts for one template and domain.
+#[derive(Debug, Clone, Copy, Default)]
+struct Counts {
+    sent: u64,
+    failed: u64,
+    retried: u64,
+}
+
+impl Counts {
+    const fn record(&mut self, delivery: Delivery) {
+        match delivery {
+            Delivery::Sent => self.sent += 1,
+            Delivery::Failed => self.failed += 1,
+            Delivery::Retried => self.retried += 1,
+        }
+    }
+}
+
+/// Delivery counters since the service started.
+///
+/// Totals count emails; the breakdown counts each distinct recipient
+/// domain of an email once.
+#[derive(Debug, Default)]
+pub struct EmailStats {
+    inner: Mutex<StatsInner>,
+}
+
+#[derive(Debug, Default)]
+struct StatsInner {
+    totals: Counts,
+    by_template_domain: BTreeMap<(String, String), Counts>,
+}
+
+impl EmailStats {
+    /// Create empty counters.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the outcome of sending `email`.
+    pub fn record(&self, email: &Email, delivery: Delivery) {
+        let template = email.template.as_deref().unwrap_or(NO_TEMPLATE);
+        let domains: BTreeSet<String> = email
+            .to
+            .iter()
+            .chain(&email.cc)
+            .chain(&email.bcc)
+            .map(|addr| {
+                addr.email
+                    .rsplit_once('@')
+                    .map_or("unknown", |(_, domain)| domain)
+                    .to_ascii_lowercase()
+            })
+            .collect();
+
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.totals.record(delivery);
+        for domain in domains {
+            inner
+                .by_template_domain
+                .entry((template.to_string(), domain))
+                .or_default()
+                .record(delivery);
+        }
+    }
+
+    /// Current counters.
+    #[must_use]
+    pub fn snapshot(&self) -> GetEmailStatsResponse {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        GetEmailStatsResponse {
+            sent: inner.totals.sent,
+            failed: inner.totals.failed,
+            retried: inner.totals.retried,
+            entries: inner
+                .by_template_domain
+                .iter()
+                .map(|((template, domain), counts)| EmailStatsEntry {
+                    template: template.clone(),
+                    domain: domain.clone(),
+                    sent: counts.sent,
+                    failed: counts.failed,
+                    retried: counts.retried,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acton_dx_proto::email::v1::EmailAddress;
+
+    fn email(template: Option<&str>, recipients: &[&str]) -> Email {
+        Email {
+            to: recipients
+                .iter()
+                .map(|r| EmailAddress {
+                    email: (*r).to_string(),
+                    name: None,
+                })
+                .collect(),
+            template: template.map(str::to_string),
+            ..Email::default()
+        }
+    }
+
+    #[test]
+    fn test_stats_break_down_by_template_and_domain() {
+        let stats = EmailStats::new();
+        let welcome = email(
+            Some("welcome"),
+            &["a@gmail.com", "b@Gmail.com", "c@corp.net"],
+        );
+        stats.record(&welcome, Delivery::Retried);
+        stats.record(&welcome, Delivery::Sent);
+        stats.record(&email(None, &["d@gmail.com"]), Delivery::Failed);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(
+            (snapshot.sent, snapshot.failed, snapshot.retried),
+            (1, 1, 1)
+        );
+
+        let rows: Vec<_> = snapshot
+            .entries
+            .iter()
+            .map(|e| {
+                (
+                    e.template.as_str(),
+                    e.domain.as_str(),
+                    e.sent,
+                    e.failed,
+                    e.retried,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("none", "gmail.com", 0, 1, 0),
+                ("welcome", "corp.net", 1, 0, 1),
+                ("welcome", "gmail.com", 1, 0, 1),
+            ]
+        );
+    }
+}
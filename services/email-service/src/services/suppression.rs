@@ -0,0 +1,194 @@
+//! Suppression list of addresses that are never emailed.
+
+use acton_dx_proto::email::v1::{Email, ListSuppressedResponse, SuppressedAddress};
+use acton_dx_proto::time::unix_now;
+use prost::Message;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use tracing::{info, warn};
+
+/// Addresses that are never emailed, keyed by lowercased address.
+///
+/// When a path is set the list is loaded from it and saved after every
+/// change, encoded as a `ListSuppressedResponse`.
+#[derive(Debug, Default)]
+pub struct SuppressionList {
+    /// File the list is saved to.
+    path: Option<PathBuf>,
+    /// Suppressed addresses by lowercased address.
+    entries: Mutex<BTreeMap<String, SuppressedAddress>>,
+}
+
+impl SuppressionList {
+    /// Create an empty list kept in memory.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the list saved at `path`, starting empty if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file exists but cannot be read or decoded.
+    pub fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let entries = match std::fs::read(&path) {
+            Ok(bytes) => ListSuppressedResponse::decode(bytes.as_slice())
+                .map_err(|e| anyhow::anyhow!("Invalid suppression list {}: {e}", path.display()))?
+                .addresses
+                .into_iter()
+                .map(|address| (address.email.to_lowercase(), address))
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(anyhow::anyhow!(
+                    "Cannot read suppression list {}: {e}",
+                    path.display()
+                ))
+            }
+        };
+        info!(path = %path.display(), count = entries.len(), "Loaded suppression list");
+        Ok(Self {
+            path: Some(path),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Whether `email` is suppressed.
+    pub fn contains(&self, email: &str) -> bool {
+        self.lock().contains_key(&email.to_lowercase())
+    }
+
+    /// Suppress `email`, returning `false` if it already was.
+    pub fn add(&self, email: &str, reason: &str) -> bool {
+        let mut entries = self.lock();
+        let key = email.to_lowercase();
+        if entries.contains_key(&key) {
+            return false;
+        }
+        entries.insert(
+            key,
+            SuppressedAddress {
+                email: email.to_string(),
+                reason: reason.to_string(),
+                suppressed_at: unix_now(),
+            },
+        );
+        self.save(&entries);
+        drop(entries);
+        info!(email = %email, reason = %reason, "Suppressed address");
+        true
+    }
+
+    /// Stop suppressing `email`, returning `false` if it was not.
+    pub fn remove(&self, email: &str) -> bool {
+        let mut entries = self.lock();
+        if entries.remove(&email.to_lowercase()).is_none() {
+            return false;
+        }
+        self.save(&entries);
+        drop(entries);
+        true
+    }
+
+    /// A page of addresses in address order (`limit` 0 = all), and the
+    /// total suppressed.
+    pub fn list(&self, limit: usize, offset: usize) -> (Vec<SuppressedAddress>, usize) {
+        let entries = self.lock();
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        let page = entries.values().skip(offset).take(limit).cloned().collect();
+        (page, entries.len())
+    }
+
+    /// `email` without suppressed recipients, and the recipients removed.
+    pub fn filter(&self, email: &Email) -> (Email, Vec<String>) {
+        let entries = self.lock();
+        let mut removed = Vec::new();
+        let mut filtered = email.clone();
+        for recipients in [&mut filtered.to, &mut filtered.cc, &mut filtered.bcc] {
+            recipients.retain(|recipient| {
+                let suppressed = entries.contains_key(&recipient.email.to_lowercase());
+                if suppressed {
+                    removed.push(recipient.email.clone());
+                }
+                !suppressed
+            });
+        }
+        (filtered, removed)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, SuppressedAddress>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Write the list to its file, if any, replacing it atomically.
+    fn save(&self, entries: &BTreeMap<String, SuppressedAddress>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let list = ListSuppressedResponse {
+            addresses: entries.values().cloned().collect(),
+            total: entries.len() as u64,
+        };
+        let temp = path.with_extension("tmp");
+        if let Err(e) =
+            std::fs::write(&temp, list.encode_to_vec()).and_then(|()| std::fs::rename(&temp, path))
+        {
+            warn!(error = %e, path = %path.display(), "Failed to save suppression list");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acton_dx_proto::email::v1::EmailAddress;
+
+    fn address(email: &str) -> EmailAddress {
+        EmailAddress {
+            email: email.to_string(),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_removes_suppressed_recipients() {
+        let list = SuppressionList::new();
+        assert!(list.add("Bounced@Example.com", "hard bounce"));
+        assert!(!list.add("bounced@example.com", "again"));
+        assert!(list.contains("BOUNCED@example.com"));
+
+        let email = Email {
+            to: vec![address("bounced@example.com"), address("ok@example.com")],
+            bcc: vec![address("BOUNCED@EXAMPLE.COM")],
+            ..Email::default()
+        };
+        let (filtered, removed) = list.filter(&email);
+        assert_eq!(filtered.to, [address("ok@example.com")]);
+        assert!(filtered.bcc.is_empty());
+        assert_eq!(removed, ["bounced@example.com", "BOUNCED@EXAMPLE.COM"]);
+
+        assert!(list.remove("bounced@example.com"));
+        assert!(!list.remove("bounced@example.com"));
+        assert_eq!(list.filter(&email).1.len(), 0);
+    }
+
+    #[test]
+    fn test_list_pages_and_persists() {
+        let path = std::env::temp_dir().join(format!("suppressed-{}.pb", uuid::Uuid::new_v4()));
+        let list = SuppressionList::open(path.clone()).unwrap();
+        for email in ["c@example.com", "a@example.com", "b@example.com"] {
+            list.add(email, "manual");
+        }
+        let (page, total) = list.list(2, 1);
+        assert_eq!(total, 3);
+        let emails: Vec<_> = page.iter().map(|a| a.email.as_str()).collect();
+        assert_eq!(emails, ["b@example.com", "c@example.com"]);
+
+        let reopened = SuppressionList::open(path.clone()).unwrap();
+        assert!(reopened.contains("a@example.com"));
+        assert_eq!(reopened.list(0, 0).1, 3);
+        std::fs::remove_file(path).unwrap();
+    }
+}
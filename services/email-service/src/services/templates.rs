@@ -0,0 +1,407 @@
+//! Named email templates for the `SendTemplatedEmail` RPC.
+//!
+//! Templates are read from a directory, or registered in code, as up to
+//! three MiniJinja files per template:
+//!
+//! - `NAME.subject.txt`: the subject line (required)
+//! - `NAME.html`: the HTML body, with values HTML-escaped
+//! - `NAME.txt`: the plain text alternative
+//!
+//! At least one body is required. Templates may `{% extends %}` or
+//! `{% include %}` other files in the directory, such as a shared layout.
+//! MiniJinja follows Jinja2 syntax, as Askama does, so templates can share
+//! partials with the web app's Askama templates.
+//!
+//! With hot reload, files are read again for every render so edits show up
+//! without a restart; otherwise each file is read once.
+
+use super::preview::{parse_context, RenderedEmail};
+use crate::config::TemplatesConfig;
+use acton_dx_proto::email::v1::EmailTemplate;
+use minijinja::{path_loader, Environment, ErrorKind};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock};
+use tracing::info;
+
+/// Suffix of a template's subject file, which marks the template as present.
+const SUBJECT_SUFFIX: &str = ".subject.txt";
+
+/// Why a named template could not be rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// No template has the requested name.
+    NotFound(String),
+    /// The context is not a JSON object.
+    Context(String),
+    /// A template failed to parse or render.
+    Render(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(name) => write!(f, "Unknown email template {name}"),
+            Self::Context(message) | Self::Render(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Registry of named email templates.
+pub struct EmailTemplates {
+    /// Directory templates are loaded from.
+    directory: Option<PathBuf>,
+    /// Read files again for every render.
+    hot_reload: bool,
+    /// Sources registered in code, by file name; these win over files.
+    registered: RwLock<BTreeMap<String, String>>,
+    /// Environment shared by renders when hot reload is off.
+    env: RwLock<Arc<Environment<'static>>>,
+}
+
+impl fmt::Debug for EmailTemplates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmailTemplates")
+            .field("directory", &self.directory)
+            .field("hot_reload", &self.hot_reload)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for EmailTemplates {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl EmailTemplates {
+    /// Create a registry loading templates from `directory`, if any.
+    #[must_use]
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        let env = build_env(directory.as_ref(), &BTreeMap::new());
+        Self {
+            directory,
+            hot_reload: false,
+            registered: RwLock::new(BTreeMap::new()),
+            env: RwLock::new(Arc::new(env)),
+        }
+    }
+
+    /// Read template files again for every render.
+    #[must_use]
+    pub const fn with_hot_reload(mut self, hot_reload: bool) -> Self {
+        self.hot_reload = hot_reload;
+        self
+    }
+
+    /// Create the registry described by `config`, checking every template
+    /// in the directory compiles.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the directory cannot be read or a template is
+    /// invalid.
+    pub fn from_config(config: &TemplatesConfig) -> anyhow::Result<Self> {
+        let templates = Self::new(config.directory.clone()).with_hot_reload(config.hot_reload);
+        let count = templates.validate().map_err(|e| anyhow::anyhow!(e))?;
+        info!(
+            directory = ?config.directory,
+            hot_reload = config.hot_reload,
+            count,
+            "Loaded email templates"
+        );
+        Ok(templates)
+    }
+
+    /// Register a template in code, replacing any of the same name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the template has no body or a part
+    /// fails to parse.
+    pub fn register(&self, name: &str, template: &EmailTemplate) -> Result<(), String> {
+        if template.html_body.is_none() && template.text_body.is_none() {
+            return Err(format!("Template {name} needs an HTML or text body"));
+        }
+        let parts = [
+            (format!("{name}{SUBJECT_SUFFIX}"), Some(&template.subject)),
+            (format!("{name}.html"), template.html_body.as_ref()),
+            (format!("{name}.txt"), template.text_body.as_ref()),
+        ];
+        let mut check = Environment::new();
+        for (file, source) in &parts {
+            if let Some(source) = source {
+                check
+                    .add_template(file, source)
+                    .map_err(|e| format!("Invalid template {file}: {e}"))?;
+            }
+        }
+
+        let mut registered = self
+            .registered
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (file, source) in parts {
+            match source {
+                Some(source) => registered.insert(file, source.clone()),
+                None => registered.remove(&file),
+            };
+        }
+        let env = build_env(self.directory.as_ref(), &registered);
+        drop(registered);
+        *self.env.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(env);
+        Ok(())
+    }
+
+    /// Names of every template, registered or in the directory.
+    #[must_use]
+    pub fn names(&self) -> BTreeSet<String> {
+        let mut names: BTreeSet<String> = self
+            .registered
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .filter_map(|file| file.strip_suffix(SUBJECT_SUFFIX))
+            .map(str::to_string)
+            .collect();
+        if let Some(entries) = self
+            .directory
+            .as_ref()
+            .and_then(|directory| std::fs::read_dir(directory).ok())
+        {
+            names.extend(entries.filter_map(|entry| {
+                let file = entry.ok()?.file_name().into_string().ok()?;
+                file.strip_suffix(SUBJECT_SUFFIX).map(str::to_string)
+            }));
+        }
+        names
+    }
+
+    /// Compile every template, returning how many there are.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the directory cannot be read or a
+    /// template is invalid.
+    pub fn validate(&self) -> Result<usize, String> {
+        if let Some(directory) = &self.directory {
+            std::fs::read_dir(directory).map_err(|e| {
+                format!(
+                    "Cannot read template directory {}: {e}",
+                    directory.display()
+                )
+            })?;
+        }
+        let env = self.environment();
+        let names = self.names();
+        for name in &names {
+            // The subject plus at least one body
+            let mut parts = 0;
+            for file in [
+                format!("{name}{SUBJECT_SUFFIX}"),
+                format!("{name}.html"),
+                format!("{name}.txt"),
+            ] {
+                match env.get_template(&file) {
+                    Ok(_) => parts += 1,
+                    Err(e) if e.kind() == ErrorKind::TemplateNotFound => {}
+                    Err(e) => return Err(format!("Invalid template {file}: {e}")),
+                }
+            }
+            if parts < 2 {
+                return Err(format!("Template {name} needs an HTML or text body"));
+            }
+        }
+        Ok(names.len())
+    }
+
+    /// Render the template `name` with a JSON object context.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template does not exist, the context is not
+    /// a JSON object, or rendering fails.
+    pub fn render(&self, name: &str, context_json: &str) -> Result<RenderedEmail, TemplateError> {
+        // Keep names to a single file in the directory
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(TemplateError::NotFound(name.to_string()));
+        }
+        let context = parse_context(context_json).map_err(TemplateError::Context)?;
+        let env = self.environment();
+        let render = |file: String| -> Result<Option<String>, TemplateError> {
+            match env.get_template(&file) {
+                Ok(template) => template
+                    .render(&context)
+                    .map(Some)
+                    .map_err(|e| TemplateError::Render(format!("Failed to render {file}: {e}"))),
+                Err(e) if e.kind() == ErrorKind::TemplateNotFound => Ok(None),
+                Err(e) => Err(TemplateError::Render(format!(
+                    "Invalid template {file}: {e}"
+                ))),
+            }
+        };
+
+        let subject = render(format!("{name}{SUBJECT_SUFFIX}"))?
+            .ok_or_else(|| TemplateError::NotFound(name.to_string()))?;
+        Ok(RenderedEmail {
+            subject: subject.trim().to_string(),
+            html_body: render(format!("{name}.html"))?,
+            text_body: render(format!("{name}.txt"))?,
+        })
+    }
+
+    /// The environment to render with: shared, or rebuilt with hot reload.
+    fn environment(&self) -> Arc<Environment<'static>> {
+        if self.hot_reload {
+            let registered = self
+                .registered
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
+            return Arc::new(build_env(self.directory.as_ref(), &registered));
+        }
+        self.env
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// Environment with the registered sources, falling back to the directory.
+fn build_env(
+    directory: Option<&PathBuf>,
+    registered: &BTreeMap<String, String>,
+) -> Environment<'static> {
+    let mut env = Environment::new();
+    if let Some(directory) = directory {
+        env.set_loader(path_loader(directory.clone()));
+    }
+    for (file, source) in registered {
+        // Sources were checked when registered
+        let _ = env.add_template_owned(file.clone(), source.clone());
+    }
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("email-templates-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for (file, source) in files {
+            std::fs::write(directory.join(file), source).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn test_renders_directory_templates_with_layout() {
+        let dir = directory(&[
+            (
+                "layout.html",
+                "<main>{% block content %}{% endblock %}</main>",
+            ),
+            ("welcome.subject.txt", "Welcome, {{ name }}\n"),
+            (
+                "welcome.html",
+                r#"{% extends "layout.html" %}{% block content %}Hi {{ name }}{% endblock %}"#,
+            ),
+            ("welcome.txt", "Hi {{ name }}"),
+        ]);
+        let templates = EmailTemplates::new(Some(dir.clone()));
+        assert_eq!(templates.validate(), Ok(1));
+
+        let rendered = templates.render("welcome", r#"{"name": "<Ada>"}"#).unwrap();
+        assert_eq!(rendered.subject, "Welcome, <Ada>");
+        assert_eq!(
+            rendered.html_body.as_deref(),
+            Some("<main>Hi &lt;Ada&gt;</main>")
+        );
+        assert_eq!(rendered.text_body.as_deref(), Some("Hi <Ada>"));
+
+        assert_eq!(
+            templates.render("missing", ""),
+            Err(TemplateError::NotFound("missing".to_string()))
+        );
+        assert_eq!(
+            templates.render("../welcome", ""),
+            Err(TemplateError::NotFound("../welcome".to_string()))
+        );
+        assert!(matches!(
+            templates.render("welcome", "[]"),
+            Err(TemplateError::Context(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_hot_reload_reads_edits() {
+        let dir = directory(&[("reset.subject.txt", "Reset"), ("reset.txt", "v1")]);
+        let cached = EmailTemplates::new(Some(dir.clone()));
+        let reloading = EmailTemplates::new(Some(dir.clone())).with_hot_reload(true);
+        assert_eq!(
+            cached.render("reset", "").unwrap().text_body.as_deref(),
+            Some("v1")
+        );
+
+        std::fs::write(dir.join("reset.txt"), "v2").unwrap();
+        assert_eq!(
+            cached.render("reset", "").unwrap().text_body.as_deref(),
+            Some("v1")
+        );
+        assert_eq!(
+            reloading.render("reset", "").unwrap().text_body.as_deref(),
+            Some("v2")
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_registered_templates() {
+        let templates = EmailTemplates::default();
+        let template = EmailTemplate {
+            subject: "Order {{ order }}".to_string(),
+            html_body: None,
+            text_body: Some("Shipped".to_string()),
+        };
+        templates.register("shipped", &template).unwrap();
+        assert_eq!(
+            templates.names().into_iter().collect::<Vec<_>>(),
+            ["shipped"]
+        );
+        let rendered = templates.render("shipped", r#"{"order": 7}"#).unwrap();
+        assert_eq!(rendered.subject, "Order 7");
+        assert!(rendered.html_body.is_none());
+
+        let no_body = EmailTemplate {
+            subject: "Hi".to_string(),
+            ..EmailTemplate::default()
+        };
+        assert!(templates.register("empty", &no_body).is_err());
+        let broken = EmailTemplate {
+            text_body: Some("{% if %}".to_string()),
+            ..template
+        };
+        assert!(templates.register("broken", &broken).is_err());
+        assert_eq!(templates.validate(), Ok(1));
+    }
+
+    #[test]
+    fn test_validate_rejects_broken_directory_templates() {
+        let dir = directory(&[("bad.subject.txt", "{% if %}"), ("bad.txt", "x")]);
+        let templates = EmailTemplates::new(Some(dir.clone()));
+        assert!(templates
+            .validate()
+            .unwrap_err()
+            .contains("bad.subject.txt"));
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let missing = EmailTemplates::new(Some(PathBuf::from("/nonexistent/templates")));
+        assert!(missing.validate().is_err());
+    }
+}
@@ -1,8 +1,10 @@
 //! Configuration for the file service.
 
+use acton_dx_proto::identity::IdentityConfig;
 use figment::providers::{Env, Format, Toml};
 use figment::Figment;
 use serde::Deserialize;
+use std::collections::HashMap;
 
 /// Service configuration.
 #[derive(Debug, Deserialize)]
@@ -15,6 +17,18 @@ pub struct FileServiceConfig {
     /// URL generation configuration.
     #[serde(default)]
     pub urls: UrlConfig,
+    /// Identity token verification.
+    #[serde(default)]
+    pub identity: IdentityConfig,
+    /// Resumable multipart uploads.
+    #[serde(default)]
+    pub uploads: UploadsConfig,
+    /// Virus scanning of uploads.
+    #[serde(default)]
+    pub scanning: ScanningConfig,
+    /// Per-tenant storage quotas.
+    #[serde(default)]
+    pub quotas: QuotasConfig,
 }
 
 /// Storage configuration.
@@ -32,6 +46,24 @@ pub struct StorageConfig {
     /// Chunk size for streaming.
     #[serde(default = "default_chunk_size")]
     pub chunk_size: usize,
+    /// Named upload policies, selected by the `upload_policy` metadata key.
+    #[serde(default = "default_policies")]
+    pub policies: HashMap<String, UploadPolicyConfig>,
+}
+
+/// Limits applied to uploads that name a policy.
+///
+/// Mirrors the framework's upload presets of the same name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct UploadPolicyConfig {
+    /// Maximum file size in bytes (capped by `storage.max_file_size`).
+    pub max_file_size: Option<u64>,
+    /// Allowed MIME types (empty = any).
+    #[serde(default)]
+    pub allowed_mime_types: Vec<String>,
+    /// Reject uploads without a clean virus scan verdict.
+    #[serde(default)]
+    pub require_scan: bool,
 }
 
 /// Service network configuration.
@@ -55,6 +87,56 @@ pub struct UrlConfig {
     pub signing_key: Option<String>,
 }
 
+/// Resumable multipart uploads.
+#[derive(Debug, Deserialize)]
+pub struct UploadsConfig {
+    /// Seconds an incomplete upload is kept after its last part.
+    #[serde(default = "default_upload_ttl")]
+    pub ttl_seconds: u64,
+    /// Seconds between sweeps for expired uploads.
+    #[serde(default = "default_upload_cleanup_interval")]
+    pub cleanup_interval_seconds: u64,
+    /// Largest part accepted, in bytes.
+    #[serde(default = "default_max_part_size")]
+    pub max_part_size: usize,
+}
+
+/// Virus scanning of uploads.
+#[derive(Debug, Deserialize)]
+pub struct ScanningConfig {
+    /// Scanner type: `none` or `clamav`.
+    #[serde(default = "default_scanner")]
+    pub scanner: String,
+    /// Address of clamd: `tcp://host:port` or `unix:///path/to/clamd.sock`.
+    #[serde(default = "default_clamd_address")]
+    pub clamd_address: String,
+    /// Directory infected uploads are moved to; deleted when unset.
+    #[serde(default)]
+    pub quarantine_dir: Option<String>,
+}
+
+/// Per-tenant storage quotas.
+#[derive(Debug, Default, Deserialize)]
+pub struct QuotasConfig {
+    /// Limits of tenants without their own entry.
+    #[serde(default)]
+    pub default: QuotaLimits,
+    /// Limits by tenant ID.
+    #[serde(default)]
+    pub tenants: HashMap<String, QuotaLimits>,
+}
+
+/// Storage limits of a tenant, in bytes (unset = unlimited).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct QuotaLimits {
+    /// Usage past this is allowed but reported.
+    #[serde(default)]
+    pub soft_limit: Option<u64>,
+    /// Uploads that would pass this are rejected.
+    #[serde(default)]
+    pub hard_limit: Option<u64>,
+}
+
 impl Default for ServiceConfig {
     fn default() -> Self {
         Self {
@@ -73,6 +155,34 @@ impl Default for UrlConfig {
     }
 }
 
+impl Default for UploadsConfig {
+    fn default() -> Self {
+        Self {
+            ttl_seconds: default_upload_ttl(),
+            cleanup_interval_seconds: default_upload_cleanup_interval(),
+            max_part_size: default_max_part_size(),
+        }
+    }
+}
+
+impl Default for ScanningConfig {
+    fn default() -> Self {
+        Self {
+            scanner: default_scanner(),
+            clamd_address: default_clamd_address(),
+            quarantine_dir: None,
+        }
+    }
+}
+
+fn default_scanner() -> String {
+    "none".to_string()
+}
+
+fn default_clamd_address() -> String {
+    "tcp://127.0.0.1:3310".to_string()
+}
+
 fn default_backend() -> String {
     "local".to_string()
 }
@@ -89,6 +199,59 @@ const fn default_chunk_size() -> usize {
     64 * 1024 // 64KB
 }
 
+const fn default_upload_ttl() -> u64 {
+    24 * 60 * 60 // 1 day
+}
+
+const fn default_upload_cleanup_interval() -> u64 {
+    5 * 60
+}
+
+const fn default_max_part_size() -> usize {
+    8 * 1024 * 1024 // 8MB
+}
+
+fn default_policies() -> HashMap<String, UploadPolicyConfig> {
+    let policy = |max_file_size: u64, types: &[&str], require_scan: bool| UploadPolicyConfig {
+        max_file_size: Some(max_file_size),
+        allowed_mime_types: types.iter().map(ToString::to_string).collect(),
+        require_scan,
+    };
+    HashMap::from([
+        (
+            "avatar".to_string(),
+            policy(
+                2 * 1024 * 1024,
+                &["image/jpeg", "image/png", "image/webp", "image/gif"],
+                true,
+            ),
+        ),
+        (
+            "document".to_string(),
+            policy(
+                25 * 1024 * 1024,
+                &[
+                    "application/pdf",
+                    "application/msword",
+                    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+                    "application/vnd.ms-excel",
+                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                    "text/plain",
+                ],
+                true,
+            ),
+        ),
+        (
+            "import-csv".to_string(),
+            policy(
+                5 * 1024 * 1024,
+                &["text/csv", "text/plain", "application/vnd.ms-excel"],
+                false,
+            ),
+        ),
+    ])
+}
+
 fn default_host() -> String {
     "0.0.0.0".to_string()
 }
@@ -129,10 +292,51 @@ mod tests {
         assert_eq!(config.port, 50056);
     }
 
+    #[test]
+    fn test_default_policies() {
+        let policies = default_policies();
+        assert!(policies["avatar"].require_scan);
+        assert!(!policies["import-csv"].require_scan);
+        assert_eq!(policies["document"].max_file_size, Some(25 * 1024 * 1024));
+    }
+
     #[test]
     fn test_default_url_config() {
         let config = UrlConfig::default();
         assert!(config.public_base_url.contains("localhost"));
         assert!(config.signing_key.is_none());
     }
+
+    #[test]
+    fn test_default_uploads_config() {
+        let config = UploadsConfig::default();
+        assert_eq!(config.ttl_seconds, 86400);
+        assert_eq!(config.cleanup_interval_seconds, 300);
+        assert_eq!(config.max_part_size, 8 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_default_scanning_config() {
+        let config = ScanningConfig::default();
+        assert_eq!(config.scanner, "none");
+        assert_eq!(config.clamd_address, "tcp://127.0.0.1:3310");
+        assert!(config.quarantine_dir.is_none());
+    }
+
+    #[test]
+    fn test_quotas_config() {
+        let config: QuotasConfig = Figment::new()
+            .merge(Toml::string(
+                r"
+                default = { soft_limit = 800, hard_limit = 1000 }
+                tenants.acme = { hard_limit = 5000 }
+                ",
+            ))
+            .extract()
+            .unwrap();
+        assert_eq!(config.default.soft_limit, Some(800));
+        assert_eq!(config.tenants["acme"].hard_limit, Some(5000));
+        assert_eq!(config.tenants["acme"].soft_limit, None);
+        assert_eq!(QuotasConfig::default().default, QuotaLimits::default());
+    }
 }
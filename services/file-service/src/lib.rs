@@ -8,5 +8,8 @@
 pub mod config;
 pub mod services;
 
-pub use config::FileServiceConfig;
-pub use services::FileServiceImpl;
+pub use config::{FileServiceConfig, QuotaLimits, QuotasConfig, ScanningConfig, UploadsConfig};
+pub use services::{
+    Caller, ClamAvScanner, ClamdAddress, FileServiceImpl, MultipartUploads, Quarantine,
+    ScanVerdict, StorageQuotas, VirusScanner, TENANT_METADATA_KEY,
+};
@@ -1,9 +1,15 @@
 //! File service entry point.
 
 use acton_dx_proto::file::v1::file_service_server::FileServiceServer;
-use file_service::{FileServiceConfig, FileServiceImpl};
+use acton_dx_proto::identity::IdentityVerifier;
+use acton_dx_proto::time::unix_now;
+use file_service::{
+    ClamAvScanner, ClamdAddress, FileServiceConfig, FileServiceImpl, Quarantine, StorageQuotas,
+};
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use tonic::transport::Server;
 use tracing::{info, Level};
 use tracing_subscriber::EnvFilter;
@@ -31,12 +37,61 @@ async fn main() -> anyhow::Result<()> {
         config.urls.signing_key,
         config.storage.chunk_size,
     )
-    .await?;
+    .await?
+    .with_upload_policies(config.storage.max_file_size, config.storage.policies.clone())
+    .with_multipart_uploads(
+        Duration::from_secs(config.uploads.ttl_seconds),
+        config.uploads.max_part_size,
+    )
+    .with_quotas(StorageQuotas::new(config.quotas.default, config.quotas.tenants));
+
+    // Remove multipart uploads that were abandoned
+    let multipart = service.multipart_uploads();
+    let cleanup_interval = config.uploads.cleanup_interval_seconds;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(cleanup_interval));
+        loop {
+            interval.tick().await;
+            let removed = multipart.prune_expired(unix_now()).await;
+            if removed > 0 {
+                info!(removed, "Removed expired multipart uploads");
+            }
+        }
+    });
+
+    // Verify identity tokens from the web tier when an auth service is configured
+    let service = service.with_identity(IdentityVerifier::from_config(&config.identity)?);
+
+    // Scan uploads before they are recorded
+    let service = match config.scanning.scanner.as_str() {
+        "none" => service,
+        "clamav" => {
+            let address: ClamdAddress = config
+                .scanning
+                .clamd_address
+                .parse()
+                .map_err(anyhow::Error::msg)?;
+            info!(
+                clamd = %config.scanning.clamd_address,
+                quarantine = ?config.scanning.quarantine_dir,
+                "Virus scanning enabled"
+            );
+            service.with_scanner(
+                Arc::new(ClamAvScanner::new(address)),
+                config
+                    .scanning
+                    .quarantine_dir
+                    .map(|dir| Quarantine::new(PathBuf::from(dir))),
+            )
+        }
+        other => anyhow::bail!("Unknown virus scanner: {other}"),
+    };
 
     info!(
         path = %config.storage.base_path,
         max_size = config.storage.max_file_size,
         chunk_size = config.storage.chunk_size,
+        policies = config.storage.policies.len(),
         "File storage configured"
     );
 
@@ -45,9 +100,12 @@ async fn main() -> anyhow::Result<()> {
 
     info!(%addr, "File service listening");
 
+    // Parts arrive as single messages, so allow for the largest part
+    let max_message_size = config.uploads.max_part_size + 64 * 1024;
+
     // Start the gRPC server
     Server::builder()
-        .add_service(FileServiceServer::new(service))
+        .add_service(FileServiceServer::new(service).max_decoding_message_size(max_message_size))
         .serve(addr)
         .await?;
 
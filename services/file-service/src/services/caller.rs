@@ -0,0 +1,120 @@
+//! Who a call is made by and the tenant it acts for.
+//!
+//! Users act for the tenant their verified identity token names, or for
+//! themselves when it names none. Trusted callers send no token and act for
+//! the tenant they name with [`TENANT_METADATA_KEY`] metadata; without it they
+//! act for no tenant and reach only files stored without one.
+
+use acton_dx_proto::identity::Identity;
+use tonic::metadata::MetadataMap;
+
+pub use acton_dx_proto::identity::TENANT_METADATA_KEY;
+
+/// The user or trusted service a call is made by.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Caller {
+    /// Verified user, unset for trusted callers.
+    pub user_id: Option<i64>,
+    /// Tenant the call acts for.
+    pub tenant: Option<String>,
+}
+
+impl Caller {
+    /// Caller of a request made with `identity` and `metadata`.
+    ///
+    /// A tenant named in the metadata is ignored for users, whose tenant
+    /// comes from their token, and when it is not visible ASCII.
+    #[must_use]
+    pub fn new(identity: Option<&Identity>, metadata: &MetadataMap) -> Self {
+        if let Some(identity) = identity {
+            return Self::user(identity);
+        }
+        let tenant = metadata
+            .get(TENANT_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .filter(|tenant| !tenant.is_empty());
+        Self::trusted(tenant)
+    }
+
+    /// A verified user, acting for the tenant in their token.
+    #[must_use]
+    pub fn user(identity: &Identity) -> Self {
+        Self {
+            user_id: Some(identity.user_id),
+            tenant: Some(identity.tenant()),
+        }
+    }
+
+    /// A trusted caller acting for `tenant`.
+    #[must_use]
+    pub fn trusted(tenant: Option<&str>) -> Self {
+        Self {
+            user_id: None,
+            tenant: tenant.map(str::to_string),
+        }
+    }
+
+    /// Whether the caller may access something stored for `tenant`.
+    ///
+    /// Only callers acting for that tenant may; a user must also be its
+    /// owner, if it has one.
+    #[must_use]
+    pub fn may_access(&self, owner_id: Option<i64>, tenant: Option<&str>) -> bool {
+        let owns = match (owner_id, self.user_id) {
+            (Some(owner_id), Some(user_id)) => owner_id == user_id,
+            _ => true,
+        };
+        owns && self.tenant.as_deref() == tenant
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::metadata::MetadataValue;
+
+    fn identity(user_id: i64, tenant_id: Option<&str>) -> Identity {
+        Identity {
+            user_id,
+            scopes: Vec::new(),
+            tenant_id: tenant_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_tenant_comes_from_token_or_metadata() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(TENANT_METADATA_KEY, MetadataValue::from_static("globex"));
+
+        let user = Caller::new(Some(&identity(7, Some("acme"))), &metadata);
+        assert_eq!(user.tenant.as_deref(), Some("acme"));
+        let user = Caller::new(Some(&identity(7, None)), &metadata);
+        assert_eq!(user.tenant.as_deref(), Some("7"));
+
+        let trusted = Caller::new(None, &metadata);
+        assert_eq!(trusted, Caller::trusted(Some("globex")));
+        assert_eq!(
+            Caller::new(None, &MetadataMap::new()),
+            Caller::trusted(None)
+        );
+    }
+
+    #[test]
+    fn test_access_is_limited_to_the_tenant() {
+        let user = Caller::user(&identity(1, Some("acme")));
+        assert!(user.may_access(Some(1), Some("acme")));
+        assert!(user.may_access(None, Some("acme")));
+        assert!(!user.may_access(Some(2), Some("acme")));
+        assert!(!user.may_access(Some(1), Some("globex")));
+        assert!(!user.may_access(None, None));
+
+        let acme = Caller::trusted(Some("acme"));
+        assert!(acme.may_access(Some(1), Some("acme")));
+        assert!(!acme.may_access(None, Some("globex")));
+        assert!(!acme.may_access(None, None));
+
+        let untenanted = Caller::trusted(None);
+        assert!(untenanted.may_access(None, None));
+        assert!(!untenanted.may_access(Some(1), Some("acme")));
+    }
+}
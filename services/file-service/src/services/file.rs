@@ -1,23 +1,58 @@
 //! File service gRPC implementation.
 
+use super::caller::Caller;
+use super::multipart::{MultipartUploads, PendingUpload, MULTIPART_DIR};
+use super::quota::StorageQuotas;
+use super::scanning::{infected_status, Quarantine, QuarantineRecord, ScanVerdict, VirusScanner};
+use super::signing::{SignedUrlMethod, UrlScope, UrlSigner};
+use crate::config::UploadPolicyConfig;
+use acton_dx_proto::error::invalid_field;
+use acton_dx_proto::error::v1::ErrorDetail;
 use acton_dx_proto::file::v1::{
-    file_service_server::FileService, DeleteRequest, DeleteResponse, DownloadRequest,
-    DownloadResponse, FileMetadata, GetMetadataRequest, GetSignedUrlRequest, GetUrlRequest,
-    GetUrlResponse, ListFilesRequest, ListFilesResponse, UploadRequest, UploadResponse,
+    file_service_server::FileService, AbortUploadRequest, AbortUploadResponse,
+    CompleteUploadRequest, DeleteRequest, DeleteResponse, DownloadRequest, DownloadResponse,
+    FileMetadata, GetMetadataRequest, GetSignedUrlRequest, GetUploadStatusRequest, GetUrlRequest,
+    GetUrlResponse, GetUsageRequest, InitiateUploadRequest, InitiateUploadResponse,
+    ListFilesRequest, ListFilesResponse, StorageUsage, UploadMetadata, UploadPartRequest,
+    UploadPartResponse, UploadRequest, UploadResponse, UploadStatus, VerifySignedUrlRequest,
+    VerifySignedUrlResponse,
 };
+use acton_dx_proto::identity::IdentityVerifier;
+use acton_dx_proto::time::unix_now;
 use async_stream::try_stream;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 use tokio::fs::{self, File};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::RwLock;
 use tokio_stream::Stream;
-use tonic::{Request, Response, Status, Streaming};
-use tracing::{debug, error, info};
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Request, Response, Status, Streaming};
+use tracing::{debug, error, info, warn};
+
+/// Upload metadata key naming the policy to enforce.
+pub const UPLOAD_POLICY_METADATA_KEY: &str = "upload_policy";
+
+/// Upload metadata key carrying the virus scan verdict.
+///
+/// Set by the service when it scans uploads itself; otherwise it is the
+/// client's verdict.
+pub const SCAN_METADATA_KEY: &str = "scan";
+
+/// How long incomplete multipart uploads are kept without a new part.
+const DEFAULT_UPLOAD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Largest multipart upload part, in bytes.
+const DEFAULT_MAX_PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// How long clients should wait before retrying when the virus scanner
+/// fails.
+const SCAN_RETRY_AFTER: Duration = Duration::from_secs(5);
 
 /// Internal error type to avoid large error sizes.
 #[derive(Debug)]
@@ -45,10 +80,24 @@ pub struct FileServiceImpl {
     metadata: Arc<RwLock<HashMap<String, StoredMetadata>>>,
     /// Public base URL for file access.
     public_base_url: String,
-    /// Signing key for signed URLs.
-    signing_key: Option<String>,
+    /// Signs scoped URLs, if a signing key is configured.
+    signer: Option<UrlSigner>,
     /// Chunk size for streaming.
     chunk_size: usize,
+    /// Maximum size of any upload.
+    max_file_size: u64,
+    /// Named upload policies.
+    policies: HashMap<String, UploadPolicyConfig>,
+    /// Verifies identity tokens attached by the web tier.
+    identity: IdentityVerifier,
+    /// Incomplete multipart uploads.
+    multipart: MultipartUploads,
+    /// Scans uploads before they are recorded.
+    scanner: Option<Arc<dyn VirusScanner>>,
+    /// Where infected uploads are kept; deleted when unset.
+    quarantine: Option<Quarantine>,
+    /// Storage used per tenant and its limits.
+    quotas: StorageQuotas,
 }
 
 /// Stored file metadata.
@@ -63,6 +112,10 @@ struct StoredMetadata {
     updated_at: i64,
     path: PathBuf,
     custom_metadata: HashMap<String, String>,
+    /// User the file was uploaded for, if the upload carried an identity.
+    owner_id: Option<i64>,
+    /// Tenant the file counts against, if it was uploaded for one.
+    tenant: Option<String>,
 }
 
 impl StoredMetadata {
@@ -78,6 +131,16 @@ impl StoredMetadata {
             metadata: self.custom_metadata.clone(),
         }
     }
+
+    /// Whether a caller may access the file; see [`Caller::may_access`].
+    fn is_accessible_by(&self, caller: &Caller) -> bool {
+        caller.may_access(self.owner_id, self.tenant.as_deref())
+    }
+
+    /// Size in bytes, for quotas.
+    fn quota_size(&self) -> u64 {
+        u64::try_from(self.size).unwrap_or(0)
+    }
 }
 
 impl FileServiceImpl {
@@ -95,22 +158,144 @@ impl FileServiceImpl {
         // Ensure base directory exists
         fs::create_dir_all(&base_path).await?;
 
+        // Incomplete uploads are tracked in memory, so earlier parts are orphans
+        let multipart = MultipartUploads::new(
+            base_path.join(MULTIPART_DIR),
+            DEFAULT_UPLOAD_TTL,
+            DEFAULT_MAX_PART_SIZE,
+        );
+        multipart.clear_stale().await?;
+
         info!(path = %base_path.display(), "File storage initialized");
 
         Ok(Self {
+            multipart,
             base_path,
             metadata: Arc::new(RwLock::new(HashMap::new())),
             public_base_url,
-            signing_key,
+            signer: signing_key.map(UrlSigner::new),
             chunk_size,
+            max_file_size: u64::MAX,
+            policies: HashMap::new(),
+            identity: IdentityVerifier::disabled(),
+            scanner: None,
+            quarantine: None,
+            quotas: StorageQuotas::default(),
         })
     }
 
-    /// Get current unix timestamp.
-    fn current_timestamp() -> i64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+    /// Enforce per-tenant storage quotas.
+    #[must_use]
+    pub fn with_quotas(mut self, quotas: StorageQuotas) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Verify identity tokens and restrict users to their own files within
+    /// their tenant.
+    #[must_use]
+    pub fn with_identity(mut self, identity: IdentityVerifier) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    /// Expire incomplete multipart uploads after `ttl` without a new part,
+    /// and accept parts of up to `max_part_size` bytes.
+    #[must_use]
+    pub fn with_multipart_uploads(mut self, ttl: Duration, max_part_size: usize) -> Self {
+        self.multipart =
+            MultipartUploads::new(self.base_path.join(MULTIPART_DIR), ttl, max_part_size);
+        self
+    }
+
+    /// Scan every upload, rejecting infected files.
+    ///
+    /// Infected files are moved to `quarantine` when given and deleted
+    /// otherwise.
+    #[must_use]
+    pub fn with_scanner(
+        mut self,
+        scanner: Arc<dyn VirusScanner>,
+        quarantine: Option<Quarantine>,
+    ) -> Self {
+        self.scanner = Some(scanner);
+        self.quarantine = quarantine;
+        self
+    }
+
+    /// Incomplete multipart uploads, shared with the service.
+    ///
+    /// Call [`MultipartUploads::prune_expired`] periodically to remove
+    /// uploads that were abandoned.
+    #[must_use]
+    pub fn multipart_uploads(&self) -> MultipartUploads {
+        self.multipart.clone()
+    }
+
+    /// Caller of a request, with its identity token verified.
+    async fn caller(&self, metadata: &MetadataMap) -> Result<Caller, Status> {
+        let identity = self.identity.authenticate(metadata).await?;
+        Ok(Caller::new(identity.as_ref(), metadata))
+    }
+
+    /// Look up a file the caller may access.
+    async fn accessible(&self, file_id: &str, caller: &Caller) -> Result<StoredMetadata, Status> {
+        let metadata = self.metadata.read().await;
+        let stored = metadata
+            .get(file_id)
+            .cloned()
+            .ok_or_else(|| Status::not_found("File not found"))?;
+        drop(metadata);
+
+        if !stored.is_accessible_by(caller) {
+            return Err(Status::permission_denied("File belongs to another user"));
+        }
+        Ok(stored)
+    }
+
+    /// Enforce a size limit on every upload and the named upload policies.
+    #[must_use]
+    pub fn with_upload_policies(
+        mut self,
+        max_file_size: u64,
+        policies: HashMap<String, UploadPolicyConfig>,
+    ) -> Self {
+        self.max_file_size = max_file_size;
+        self.policies = policies;
+        self
+    }
+
+    /// Check upload metadata against its policy, returning the size limit.
+    fn upload_limit(&self, meta: &UploadMetadata) -> Result<u64, FileError> {
+        let Some(name) = meta.metadata.get(UPLOAD_POLICY_METADATA_KEY) else {
+            return Ok(self.max_file_size);
+        };
+        let policy = self
+            .policies
+            .get(name)
+            .ok_or_else(|| FileError::new(format!("Unknown upload policy: {name}")))?;
+
+        if !policy.allowed_mime_types.is_empty()
+            && !policy.allowed_mime_types.contains(&meta.content_type)
+        {
+            return Err(FileError::new(format!(
+                "Content type {} not allowed by upload policy {name}",
+                meta.content_type
+            )));
+        }
+        // With a scanner configured every upload is scanned before it is stored
+        if policy.require_scan
+            && self.scanner.is_none()
+            && meta.metadata.get(SCAN_METADATA_KEY).map(String::as_str) != Some("clean")
+        {
+            return Err(FileError::new(format!(
+                "Upload policy {name} requires a clean virus scan"
+            )));
+        }
+
+        Ok(policy
+            .max_file_size
+            .map_or(self.max_file_size, |max| max.min(self.max_file_size)))
     }
 
     /// Generate a unique file ID.
@@ -136,6 +321,7 @@ impl FileServiceImpl {
     async fn process_upload(
         &self,
         mut stream: Streaming<UploadRequest>,
+        caller: &Caller,
     ) -> Result<StoredMetadata, FileError> {
         // First message should be metadata
         let first_msg = stream
@@ -150,6 +336,7 @@ impl FileServiceImpl {
             return Err(FileError::new("First message must be metadata"));
         };
 
+        let limit = self.upload_limit(&upload_meta)?;
         let file_id = Self::generate_id();
         let storage_path = self.get_storage_path(&file_id);
 
@@ -169,6 +356,11 @@ impl FileServiceImpl {
         {
             if let Some(acton_dx_proto::file::v1::upload_request::Data::Chunk(chunk)) = msg.data {
                 file_data.extend_from_slice(&chunk);
+                if file_data.len() as u64 > limit {
+                    return Err(FileError::new(format!(
+                        "File exceeds maximum size of {limit} bytes"
+                    )));
+                }
             }
         }
 
@@ -182,7 +374,7 @@ impl FileServiceImpl {
             .map_err(|e| FileError::new(format!("Failed to write file: {e}")))?;
 
         let checksum = Self::calculate_checksum(&file_data);
-        let now = Self::current_timestamp();
+        let now = unix_now();
         let size = i64::try_from(file_data.len()).unwrap_or(i64::MAX);
 
         let stored = StoredMetadata {
@@ -195,30 +387,167 @@ impl FileServiceImpl {
             updated_at: now,
             path: storage_path,
             custom_metadata: upload_meta.metadata,
+            owner_id: caller.user_id,
+            tenant: caller.tenant.clone(),
         };
 
         Ok(stored)
     }
 
-    /// Generate signed URL.
-    fn generate_signed_url(&self, file_id: &str, expires_at: i64) -> Result<String, FileError> {
-        let key = self
-            .signing_key
-            .as_ref()
-            .ok_or_else(|| FileError::new("Signing key not configured"))?;
+    /// Assemble the parts of a completed multipart upload into a file.
+    async fn assemble_upload(
+        &self,
+        upload_id: &str,
+        upload: PendingUpload,
+        parts: &[u32],
+        expected_checksum: Option<&str>,
+    ) -> Result<StoredMetadata, FileError> {
+        let file_id = Self::generate_id();
+        let storage_path = self.get_storage_path(&file_id);
+        if let Some(parent) = storage_path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| FileError::new(format!("Failed to create directory: {e}")))?;
+        }
 
-        // Create signature: SHA256(key + file_id + expires_at)
+        let mut file = File::create(&storage_path)
+            .await
+            .map_err(|e| FileError::new(format!("Failed to create file: {e}")))?;
         let mut hasher = Sha256::new();
-        hasher.update(key.as_bytes());
-        hasher.update(file_id.as_bytes());
-        hasher.update(expires_at.to_be_bytes());
-        let signature = format!("{:x}", hasher.finalize());
+        let mut size: u64 = 0;
+        for &part_number in parts {
+            let data = fs::read(self.multipart.part_path(upload_id, part_number))
+                .await
+                .map_err(|e| FileError::new(format!("Failed to read part {part_number}: {e}")))?;
+            hasher.update(&data);
+            size += data.len() as u64;
+            file.write_all(&data)
+                .await
+                .map_err(|e| FileError::new(format!("Failed to write file: {e}")))?;
+        }
+        file.flush()
+            .await
+            .map_err(|e| FileError::new(format!("Failed to write file: {e}")))?;
+        drop(file);
+
+        let checksum = format!("{:x}", hasher.finalize());
+        if expected_checksum.is_some_and(|expected| !expected.eq_ignore_ascii_case(&checksum)) {
+            let _ = fs::remove_file(&storage_path).await;
+            return Err(FileError::new("File checksum mismatch"));
+        }
+
+        let now = unix_now();
+        Ok(StoredMetadata {
+            id: file_id,
+            filename: upload.metadata.filename,
+            content_type: upload.metadata.content_type,
+            size: i64::try_from(size).unwrap_or(i64::MAX),
+            checksum,
+            created_at: now,
+            updated_at: now,
+            path: storage_path,
+            custom_metadata: upload.metadata.metadata,
+            owner_id: upload.owner_id,
+            tenant: upload.tenant,
+        })
+    }
+
+    /// Scan a stored upload before its metadata is recorded.
+    ///
+    /// Clean files are marked as scanned. Infected files are quarantined or
+    /// deleted, and so are files that could not be scanned.
+    async fn scan_upload(&self, stored: &mut StoredMetadata) -> Result<(), Status> {
+        let Some(scanner) = &self.scanner else {
+            return Ok(());
+        };
+
+        match scanner.scan(&stored.path).await {
+            Ok(ScanVerdict::Clean) => {
+                stored
+                    .custom_metadata
+                    .insert(SCAN_METADATA_KEY.to_string(), "clean".to_string());
+                Ok(())
+            }
+            Ok(ScanVerdict::Infected { threat }) => {
+                warn!(
+                    id = %stored.id,
+                    filename = %stored.filename,
+                    threat = %threat,
+                    scanner = scanner.name(),
+                    "Rejected infected upload"
+                );
+                self.reject_infected(stored, &threat).await;
+                Err(infected_status(&threat))
+            }
+            Err(e) => {
+                error!(error = %e, scanner = scanner.name(), "Virus scan failed");
+                let _ = fs::remove_file(&stored.path).await;
+                Err(ErrorDetail::new("SCAN_UNAVAILABLE", "Virus scan failed")
+                    .with_retry_after(SCAN_RETRY_AFTER)
+                    .into_status(Code::Unavailable))
+            }
+        }
+    }
+
+    /// Move an infected upload to quarantine, or delete it.
+    async fn reject_infected(&self, stored: &StoredMetadata, threat: &str) {
+        if let Some(quarantine) = &self.quarantine {
+            let record = QuarantineRecord {
+                file_id: stored.id.clone(),
+                filename: stored.filename.clone(),
+                content_type: stored.content_type.clone(),
+                size: stored.size,
+                checksum: stored.checksum.clone(),
+                threat: threat.to_string(),
+                owner_id: stored.owner_id,
+                quarantined_at: unix_now(),
+            };
+            match quarantine.hold(&stored.path, &record).await {
+                Ok(path) => {
+                    info!(id = %stored.id, path = %path.display(), "Infected upload quarantined");
+                    return;
+                }
+                Err(e) => error!(error = %e, id = %stored.id, "Failed to quarantine upload"),
+            }
+        }
+        if let Err(e) = fs::remove_file(&stored.path).await {
+            error!(error = %e, path = %stored.path.display(), "Failed to delete infected upload");
+        }
+    }
+
+    /// Count a stored upload against its tenant's quota.
+    ///
+    /// A file that would pass the hard limit is deleted.
+    async fn reserve_quota(&self, stored: &StoredMetadata) -> Result<(), Status> {
+        let Some(tenant) = &stored.tenant else {
+            return Ok(());
+        };
+        if let Err(status) = self.quotas.reserve(tenant, stored.quota_size()).await {
+            let _ = fs::remove_file(&stored.path).await;
+            return Err(status);
+        }
+        Ok(())
+    }
 
-        Ok(format!(
-            "{}/{}?expires={}&sig={}",
-            self.public_base_url, file_id, expires_at, signature
-        ))
+    /// Stop counting a file against its tenant's quota.
+    async fn release_quota(&self, stored: &StoredMetadata) {
+        if let Some(tenant) = &stored.tenant {
+            self.quotas.release(tenant, stored.quota_size()).await;
+        }
     }
+
+    /// Signer for scoped URLs.
+    fn signer(&self) -> Result<&UrlSigner, FileError> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| FileError::new("Signing key not configured"))
+    }
+}
+
+/// Parse an optional client IP from a request.
+fn parse_ip(ip: Option<&str>) -> Result<Option<IpAddr>, String> {
+    ip.map(|ip| ip.parse().map_err(|_| format!("Invalid IP address: {ip}")))
+        .transpose()
 }
 
 type DownloadStream = Pin<Box<dyn Stream<Item = Result<DownloadResponse, Status>> + Send>>;
@@ -231,10 +560,16 @@ impl FileService for FileServiceImpl {
         &self,
         request: Request<Streaming<UploadRequest>>,
     ) -> Result<Response<UploadResponse>, Status> {
+        let caller = self.caller(request.metadata()).await?;
         let stream = request.into_inner();
 
-        match self.process_upload(stream).await {
-            Ok(stored) => {
+        match self.process_upload(stream, &caller).await {
+            Ok(mut stored) => {
+                self.reserve_quota(&stored).await?;
+                if let Err(status) = self.scan_upload(&mut stored).await {
+                    self.release_quota(&stored).await;
+                    return Err(status);
+                }
                 let proto_meta = stored.to_proto();
 
                 // Store metadata
@@ -265,15 +600,11 @@ impl FileService for FileServiceImpl {
         &self,
         request: Request<DownloadRequest>,
     ) -> Result<Response<Self::DownloadStream>, Status> {
+        let caller = self.caller(request.metadata()).await?;
         let req = request.into_inner();
         debug!(file_id = %req.file_id, "Download request");
 
-        let metadata_guard = self.metadata.read().await;
-        let stored = metadata_guard
-            .get(&req.file_id)
-            .cloned()
-            .ok_or_else(|| Status::not_found("File not found"))?;
-        drop(metadata_guard);
+        let stored = self.accessible(&req.file_id, &caller).await?;
 
         let chunk_size = self.chunk_size;
         let range_start = req.range_start.map(|v| u64::try_from(v).unwrap_or(0));
@@ -339,14 +670,23 @@ impl FileService for FileServiceImpl {
         &self,
         request: Request<DeleteRequest>,
     ) -> Result<Response<DeleteResponse>, Status> {
+        let caller = self.caller(request.metadata()).await?;
         let req = request.into_inner();
         debug!(file_id = %req.file_id, "Delete request");
 
         let mut metadata = self.metadata.write().await;
+        if metadata
+            .get(&req.file_id)
+            .is_some_and(|stored| !stored.is_accessible_by(&caller))
+        {
+            return Err(Status::permission_denied("File belongs to another user"));
+        }
         let stored = metadata.remove(&req.file_id);
         drop(metadata);
 
         if let Some(stored) = stored {
+            self.release_quota(&stored).await;
+
             // Delete the actual file
             if let Err(e) = fs::remove_file(&stored.path).await {
                 error!(error = %e, path = %stored.path.display(), "Failed to delete file");
@@ -363,24 +703,20 @@ impl FileService for FileServiceImpl {
         &self,
         request: Request<GetMetadataRequest>,
     ) -> Result<Response<FileMetadata>, Status> {
+        let caller = self.caller(request.metadata()).await?;
         let req = request.into_inner();
         debug!(file_id = %req.file_id, "GetMetadata request");
 
-        let metadata = self.metadata.read().await;
-        let stored = metadata
-            .get(&req.file_id)
-            .ok_or_else(|| Status::not_found("File not found"))?;
-
-        let result = stored.to_proto();
-        drop(metadata);
+        let stored = self.accessible(&req.file_id, &caller).await?;
 
-        Ok(Response::new(result))
+        Ok(Response::new(stored.to_proto()))
     }
 
     async fn list_files(
         &self,
         request: Request<ListFilesRequest>,
     ) -> Result<Response<ListFilesResponse>, Status> {
+        let caller = self.caller(request.metadata()).await?;
         let req = request.into_inner();
         debug!(prefix = ?req.path_prefix, limit = ?req.limit, "ListFiles request");
 
@@ -389,9 +725,11 @@ impl FileService for FileServiceImpl {
         let mut files: Vec<FileMetadata> = metadata
             .values()
             .filter(|f| {
-                req.path_prefix
-                    .as_ref()
-                    .is_none_or(|prefix| f.filename.starts_with(prefix))
+                f.is_accessible_by(&caller)
+                    && req
+                        .path_prefix
+                        .as_ref()
+                        .is_none_or(|prefix| f.filename.starts_with(prefix))
             })
             .map(StoredMetadata::to_proto)
             .collect();
@@ -399,7 +737,7 @@ impl FileService for FileServiceImpl {
         drop(metadata);
 
         // Sort by created_at descending
-        files.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        files.sort_by_key(|f| std::cmp::Reverse(f.created_at));
 
         // Apply limit with safe conversion
         let limit = usize::try_from(req.limit.unwrap_or(100)).unwrap_or(100);
@@ -415,15 +753,11 @@ impl FileService for FileServiceImpl {
         &self,
         request: Request<GetUrlRequest>,
     ) -> Result<Response<GetUrlResponse>, Status> {
+        let caller = self.caller(request.metadata()).await?;
         let req = request.into_inner();
         debug!(file_id = %req.file_id, "GetPublicUrl request");
 
-        // Verify file exists
-        let metadata = self.metadata.read().await;
-        if !metadata.contains_key(&req.file_id) {
-            return Err(Status::not_found("File not found"));
-        }
-        drop(metadata);
+        self.accessible(&req.file_id, &caller).await?;
 
         let url = format!("{}/{}", self.public_base_url, req.file_id);
 
@@ -437,26 +771,257 @@ impl FileService for FileServiceImpl {
         &self,
         request: Request<GetSignedUrlRequest>,
     ) -> Result<Response<GetUrlResponse>, Status> {
+        let caller = self.caller(request.metadata()).await?;
         let req = request.into_inner();
         debug!(file_id = %req.file_id, expires_in = req.expires_in_seconds, "GetSignedUrl request");
 
-        // Verify file exists
-        let metadata = self.metadata.read().await;
-        if !metadata.contains_key(&req.file_id) {
-            return Err(Status::not_found("File not found"));
-        }
-        drop(metadata);
-
-        let expires_at = Self::current_timestamp() + req.expires_in_seconds;
+        let method = req
+            .method
+            .as_deref()
+            .map(str::parse::<SignedUrlMethod>)
+            .transpose()
+            .map_err(Status::invalid_argument)?
+            .unwrap_or_default();
+        let ip = parse_ip(req.ip_address.as_deref()).map_err(Status::invalid_argument)?;
+
+        self.accessible(&req.file_id, &caller).await?;
+
+        let expires_at = unix_now() + req.expires_in_seconds;
+        let scope = UrlScope {
+            file_id: req.file_id,
+            expires_at,
+            method,
+            ip,
+        };
         let url = self
-            .generate_signed_url(&req.file_id, expires_at)
-            .map_err(FileError::into_status)?;
+            .signer()
+            .map_err(FileError::into_status)?
+            .sign(&self.public_base_url, &scope);
 
         Ok(Response::new(GetUrlResponse {
             url,
             expires_at: Some(expires_at),
         }))
     }
+
+    async fn verify_signed_url(
+        &self,
+        request: Request<VerifySignedUrlRequest>,
+    ) -> Result<Response<VerifySignedUrlResponse>, Status> {
+        self.identity.authenticate(request.metadata()).await?;
+        let req = request.into_inner();
+        let client_ip = parse_ip(req.ip_address.as_deref()).map_err(Status::invalid_argument)?;
+        let signer = self.signer().map_err(FileError::into_status)?;
+
+        // Methods no URL can be signed for are never allowed
+        let Ok(method) = req.method.parse::<SignedUrlMethod>() else {
+            return Ok(Response::new(VerifySignedUrlResponse {
+                reason: Some("method_not_allowed".to_string()),
+                ..VerifySignedUrlResponse::default()
+            }));
+        };
+
+        let response = match signer.verify(&req.url, method, client_ip, unix_now())
+        {
+            Ok(scope) => VerifySignedUrlResponse {
+                valid: true,
+                file_id: scope.file_id,
+                expires_at: Some(scope.expires_at),
+                reason: None,
+            },
+            Err(e) => {
+                debug!(reason = %e, "Signed URL rejected");
+                VerifySignedUrlResponse {
+                    reason: Some(e.reason().to_string()),
+                    ..VerifySignedUrlResponse::default()
+                }
+            }
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn initiate_upload(
+        &self,
+        request: Request<InitiateUploadRequest>,
+    ) -> Result<Response<InitiateUploadResponse>, Status> {
+        let caller = self.caller(request.metadata()).await?;
+        let req = request.into_inner();
+        let meta = req
+            .metadata
+            .ok_or_else(|| invalid_field("metadata", "Upload metadata is required"))?;
+
+        let limit = self
+            .upload_limit(&meta)
+            .map_err(|e| Status::invalid_argument(e.message))?;
+        if req
+            .total_size
+            .is_some_and(|size| u64::try_from(size).unwrap_or(0) > limit)
+        {
+            return Err(invalid_field(
+                "total_size",
+                format!("File exceeds maximum size of {limit} bytes"),
+            ));
+        }
+        if let (Some(tenant), Some(total_size)) = (&caller.tenant, req.total_size) {
+            self.quotas
+                .check(tenant, u64::try_from(total_size).unwrap_or(0))
+                .await?;
+        }
+
+        let filename = meta.filename.clone();
+        let (upload_id, expires_at) = self
+            .multipart
+            .initiate(meta, &caller, limit, unix_now())
+            .await?;
+        debug!(%upload_id, %filename, "Multipart upload initiated");
+
+        Ok(Response::new(InitiateUploadResponse {
+            upload_id,
+            expires_at,
+            max_part_size: i64::try_from(self.multipart.max_part_size()).unwrap_or(i64::MAX),
+        }))
+    }
+
+    async fn upload_part(
+        &self,
+        request: Request<UploadPartRequest>,
+    ) -> Result<Response<UploadPartResponse>, Status> {
+        let caller = self.caller(request.metadata()).await?;
+        let req = request.into_inner();
+
+        let now = unix_now();
+        let (size, checksum) = self
+            .multipart
+            .put_part(
+                &req.upload_id,
+                &caller,
+                req.part_number,
+                &req.data,
+                req.checksum.as_deref(),
+                now,
+            )
+            .await?;
+        let upload = self.multipart.get(&req.upload_id, &caller).await?;
+
+        Ok(Response::new(UploadPartResponse {
+            part_number: req.part_number,
+            size: i64::try_from(size).unwrap_or(i64::MAX),
+            checksum,
+            expires_at: upload.expires_at,
+        }))
+    }
+
+    async fn get_upload_status(
+        &self,
+        request: Request<GetUploadStatusRequest>,
+    ) -> Result<Response<UploadStatus>, Status> {
+        let caller = self.caller(request.metadata()).await?;
+        let req = request.into_inner();
+
+        let upload = self.multipart.get(&req.upload_id, &caller).await?;
+        Ok(Response::new(UploadStatus {
+            parts: upload.parts(),
+            uploaded_size: i64::try_from(upload.uploaded_size()).unwrap_or(i64::MAX),
+            expires_at: upload.expires_at,
+            upload_id: req.upload_id,
+        }))
+    }
+
+    async fn complete_upload(
+        &self,
+        request: Request<CompleteUploadRequest>,
+    ) -> Result<Response<UploadResponse>, Status> {
+        let caller = self.caller(request.metadata()).await?;
+        let req = request.into_inner();
+
+        let (upload, parts) = self
+            .multipart
+            .take_complete(&req.upload_id, &caller)
+            .await?;
+        if let Some(tenant) = &upload.tenant {
+            if let Err(status) = self.quotas.check(tenant, upload.uploaded_size()).await {
+                self.multipart.discard(&req.upload_id).await;
+                return Err(status);
+            }
+        }
+        let assembled = self
+            .assemble_upload(&req.upload_id, upload, &parts, req.checksum.as_deref())
+            .await;
+        self.multipart.discard(&req.upload_id).await;
+
+        match assembled {
+            Ok(mut stored) => {
+                self.reserve_quota(&stored).await?;
+                if let Err(status) = self.scan_upload(&mut stored).await {
+                    self.release_quota(&stored).await;
+                    return Err(status);
+                }
+                let proto_meta = stored.to_proto();
+
+                let mut metadata = self.metadata.write().await;
+                metadata.insert(stored.id.clone(), stored);
+                drop(metadata);
+
+                info!(
+                    id = %proto_meta.id,
+                    upload_id = %req.upload_id,
+                    parts = parts.len(),
+                    size = proto_meta.size,
+                    "Multipart upload completed"
+                );
+
+                Ok(Response::new(UploadResponse {
+                    success: true,
+                    file: Some(proto_meta),
+                    error: None,
+                }))
+            }
+            Err(e) => {
+                error!(error = %e.message, upload_id = %req.upload_id, "Multipart upload failed");
+                Ok(Response::new(UploadResponse {
+                    success: false,
+                    file: None,
+                    error: Some(e.message),
+                }))
+            }
+        }
+    }
+
+    async fn abort_upload(
+        &self,
+        request: Request<AbortUploadRequest>,
+    ) -> Result<Response<AbortUploadResponse>, Status> {
+        let caller = self.caller(request.metadata()).await?;
+        let req = request.into_inner();
+
+        let success = self.multipart.abort(&req.upload_id, &caller).await?;
+        if success {
+            debug!(upload_id = %req.upload_id, "Multipart upload aborted");
+        }
+
+        Ok(Response::new(AbortUploadResponse { success }))
+    }
+
+    async fn get_usage(
+        &self,
+        request: Request<GetUsageRequest>,
+    ) -> Result<Response<StorageUsage>, Status> {
+        let caller = self.caller(request.metadata()).await?;
+        let req = request.into_inner();
+
+        // Users see their token's tenant; trusted callers may ask about any
+        let tenant = match (caller.user_id, caller.tenant, req.tenant_id) {
+            (Some(_), Some(own), Some(tenant)) if tenant != own => {
+                return Err(Status::permission_denied(
+                    "Usage of another tenant is not visible",
+                ));
+            }
+            (None, _, Some(tenant)) | (_, Some(tenant), _) => tenant,
+            (_, None, _) => return Err(invalid_field("tenant_id", "tenant_id is required")),
+        };
+
+        Ok(Response::new(self.quotas.report(&tenant).await))
+    }
 }
 
 #[cfg(test)]
@@ -480,9 +1045,301 @@ mod tests {
         assert_eq!(checksum.len(), 64);
     }
 
+    #[tokio::test]
+    async fn test_upload_limit_enforces_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = UploadPolicyConfig {
+            max_file_size: Some(1024),
+            allowed_mime_types: vec!["image/png".to_string()],
+            require_scan: true,
+        };
+        let service =
+            FileServiceImpl::new(dir.path().to_path_buf(), String::new(), None, 64 * 1024)
+                .await
+                .unwrap()
+                .with_upload_policies(4096, HashMap::from([("avatar".to_string(), policy)]));
+        let meta = |content_type: &str, metadata: &[(&str, &str)]| UploadMetadata {
+            filename: "a".to_string(),
+            content_type: content_type.to_string(),
+            path: None,
+            metadata: metadata
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect(),
+        };
+
+        assert_eq!(service.upload_limit(&meta("text/plain", &[])).unwrap(), 4096);
+        let scanned = [(UPLOAD_POLICY_METADATA_KEY, "avatar"), (SCAN_METADATA_KEY, "clean")];
+        assert_eq!(service.upload_limit(&meta("image/png", &scanned)).unwrap(), 1024);
+        assert!(service.upload_limit(&meta("text/plain", &scanned)).is_err());
+        let unscanned = [(UPLOAD_POLICY_METADATA_KEY, "avatar")];
+        assert!(service.upload_limit(&meta("image/png", &unscanned)).is_err());
+        let unknown = [(UPLOAD_POLICY_METADATA_KEY, "banner")];
+        assert!(service.upload_limit(&meta("image/png", &unknown)).is_err());
+    }
+
     #[test]
-    fn test_current_timestamp() {
-        let ts = FileServiceImpl::current_timestamp();
-        assert!(ts > 0);
+    fn test_files_are_accessible_by_owner_within_tenant() {
+        let file = |owner_id, tenant: &str| StoredMetadata {
+            id: "f".to_string(),
+            filename: "a.txt".to_string(),
+            content_type: "text/plain".to_string(),
+            size: 0,
+            checksum: String::new(),
+            created_at: 0,
+            updated_at: 0,
+            path: PathBuf::new(),
+            custom_metadata: HashMap::new(),
+            owner_id,
+            tenant: Some(tenant.to_string()),
+        };
+        let user = |user_id| Caller {
+            user_id: Some(user_id),
+            tenant: Some("acme".to_string()),
+        };
+
+        assert!(file(Some(1), "acme").is_accessible_by(&user(1)));
+        assert!(!file(Some(1), "acme").is_accessible_by(&user(2)));
+        assert!(file(None, "acme").is_accessible_by(&user(2)));
+        assert!(!file(None, "globex").is_accessible_by(&user(2)));
+        // Trusted callers reach a tenant's files only by naming it
+        assert!(file(Some(1), "acme").is_accessible_by(&Caller::trusted(Some("acme"))));
+        assert!(!file(Some(1), "acme").is_accessible_by(&Caller::trusted(None)));
+    }
+
+    #[tokio::test]
+    async fn test_assemble_upload_joins_parts() {
+        let dir = tempfile::tempdir().unwrap();
+        let service =
+            FileServiceImpl::new(dir.path().to_path_buf(), String::new(), None, 64 * 1024)
+                .await
+                .unwrap();
+        let meta = UploadMetadata {
+            filename: "notes.txt".to_string(),
+            content_type: "text/plain".to_string(),
+            path: None,
+            metadata: HashMap::new(),
+        };
+        let owner = Caller {
+            user_id: Some(7),
+            tenant: Some("acme".to_string()),
+        };
+        let (upload_id, _) = service
+            .multipart
+            .initiate(meta, &owner, 1024, 0)
+            .await
+            .unwrap();
+        for (number, data) in [(2, &b"world"[..]), (1, &b"hello "[..])] {
+            service
+                .multipart
+                .put_part(&upload_id, &owner, number, data, None, 0)
+                .await
+                .unwrap();
+        }
+
+        let (upload, parts) = service
+            .multipart
+            .take_complete(&upload_id, &owner)
+            .await
+            .unwrap();
+        let expected = FileServiceImpl::calculate_checksum(b"hello world");
+        let stored = service
+            .assemble_upload(&upload_id, upload.clone(), &parts, Some(&expected))
+            .await
+            .unwrap();
+        assert_eq!(stored.size, 11);
+        assert_eq!(stored.owner_id, Some(7));
+        assert_eq!(stored.tenant.as_deref(), Some("acme"));
+        assert_eq!(fs::read(&stored.path).await.unwrap(), b"hello world");
+
+        let mismatch = service
+            .assemble_upload(&upload_id, upload, &parts, Some("00"))
+            .await;
+        assert!(mismatch.is_err());
+    }
+
+    /// Scanner that flags files containing `EICAR`.
+    #[derive(Debug)]
+    struct EicarScanner;
+
+    #[tonic::async_trait]
+    impl VirusScanner for EicarScanner {
+        async fn scan(&self, path: &std::path::Path) -> std::io::Result<ScanVerdict> {
+            let data = fs::read(path).await?;
+            Ok(if data.windows(5).any(|window| window == b"EICAR") {
+                ScanVerdict::Infected {
+                    threat: "Eicar-Test-Signature".to_string(),
+                }
+            } else {
+                ScanVerdict::Clean
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "eicar"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_upload_quarantines_infected_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let quarantine_dir = dir.path().join("quarantine");
+        let service =
+            FileServiceImpl::new(dir.path().join("files"), String::new(), None, 64 * 1024)
+                .await
+                .unwrap()
+                .with_scanner(
+                    Arc::new(EicarScanner),
+                    Some(Quarantine::new(quarantine_dir.clone())),
+                );
+        let stored = |id: &str, data: &[u8]| {
+            let path = dir.path().join("files").join(id);
+            std::fs::write(&path, data).unwrap();
+            StoredMetadata {
+                id: id.to_string(),
+                filename: format!("{id}.txt"),
+                content_type: "text/plain".to_string(),
+                size: 0,
+                checksum: String::new(),
+                created_at: 0,
+                updated_at: 0,
+                path,
+                custom_metadata: HashMap::from([(
+                    SCAN_METADATA_KEY.to_string(),
+                    "infected".to_string(),
+                )]),
+                owner_id: None,
+                tenant: None,
+            }
+        };
+
+        let mut clean = stored("clean", b"hello");
+        service.scan_upload(&mut clean).await.unwrap();
+        assert_eq!(clean.custom_metadata[SCAN_METADATA_KEY], "clean");
+
+        let mut infected = stored("infected", b"X5O EICAR test");
+        let status = service.scan_upload(&mut infected).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(!infected.path.exists());
+        assert!(quarantine_dir.join("infected").exists());
+        let record = fs::read_to_string(quarantine_dir.join("infected.json"))
+            .await
+            .unwrap();
+        assert!(record.contains("Eicar-Test-Signature"));
+    }
+
+    #[tokio::test]
+    async fn test_reserve_quota_rejects_files_past_hard_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = crate::config::QuotaLimits {
+            soft_limit: None,
+            hard_limit: Some(16),
+        };
+        let service =
+            FileServiceImpl::new(dir.path().to_path_buf(), String::new(), None, 64 * 1024)
+                .await
+                .unwrap()
+                .with_quotas(StorageQuotas::new(limits, HashMap::new()));
+        let stored = |id: &str, tenant: Option<&str>| {
+            let path = dir.path().join(id);
+            std::fs::write(&path, b"hello world").unwrap();
+            StoredMetadata {
+                id: id.to_string(),
+                filename: format!("{id}.txt"),
+                content_type: "text/plain".to_string(),
+                size: 11,
+                checksum: String::new(),
+                created_at: 0,
+                updated_at: 0,
+                path,
+                custom_metadata: HashMap::new(),
+                owner_id: None,
+                tenant: tenant.map(str::to_string),
+            }
+        };
+
+        let first = stored("first", Some("7"));
+        service.reserve_quota(&first).await.unwrap();
+        let second = stored("second", Some("7"));
+        let status = service.reserve_quota(&second).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(!second.path.exists());
+
+        // Tenants are counted apart; uploads without one are not counted
+        service
+            .reserve_quota(&stored("acme", Some("acme")))
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            service.reserve_quota(&stored("anon", None)).await.unwrap();
+        }
+
+        service.release_quota(&first).await;
+        service
+            .reserve_quota(&stored("third", Some("7")))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_signed_url_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = FileServiceImpl::new(
+            dir.path().to_path_buf(),
+            "http://files.test/files".to_string(),
+            Some("secret".to_string()),
+            64 * 1024,
+        )
+        .await
+        .unwrap();
+        let path = dir.path().join("f1");
+        std::fs::write(&path, b"hello world").unwrap();
+        service.metadata.write().await.insert(
+            "f1".to_string(),
+            StoredMetadata {
+                id: "f1".to_string(),
+                filename: "f1.txt".to_string(),
+                content_type: "text/plain".to_string(),
+                size: 11,
+                checksum: String::new(),
+                created_at: 0,
+                updated_at: 0,
+                path,
+                custom_metadata: HashMap::new(),
+                owner_id: None,
+                tenant: None,
+            },
+        );
+
+        let url = service
+            .get_signed_url(Request::new(GetSignedUrlRequest {
+                file_id: "f1".to_string(),
+                expires_in_seconds: 60,
+                method: Some("put".to_string()),
+                ip_address: Some("203.0.113.7".to_string()),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .url;
+
+        let verify = |method: &str, ip: &str| {
+            service.verify_signed_url(Request::new(VerifySignedUrlRequest {
+                url: url.clone(),
+                method: method.to_string(),
+                ip_address: Some(ip.to_string()),
+            }))
+        };
+        let verified = verify("PUT", "203.0.113.7").await.unwrap().into_inner();
+        assert!(verified.valid);
+        assert_eq!(verified.file_id, "f1");
+
+        let rejected = verify("GET", "203.0.113.7").await.unwrap().into_inner();
+        assert!(!rejected.valid);
+        assert_eq!(rejected.reason.as_deref(), Some("method_not_allowed"));
+        let rejected = verify("PUT", "198.51.100.1").await.unwrap().into_inner();
+        assert_eq!(rejected.reason.as_deref(), Some("ip_mismatch"));
+        let rejected = verify("DELETE", "203.0.113.7").await.unwrap().into_inner();
+        assert_eq!(rejected.reason.as_deref(), Some("method_not_allowed"));
     }
 }
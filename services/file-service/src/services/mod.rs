@@ -1,5 +1,18 @@
 //! File service implementations.
 
+mod caller;
 mod file;
+mod multipart;
+mod quota;
+mod scanning;
+mod signing;
 
+pub use caller::{Caller, TENANT_METADATA_KEY};
 pub use file::FileServiceImpl;
+pub use multipart::{MultipartUploads, PendingUpload, MAX_PART_NUMBER, MULTIPART_DIR};
+pub use quota::{StorageQuotas, Usage};
+pub use scanning::{
+    infected_status, ClamAvScanner, ClamdAddress, Quarantine, QuarantineRecord, ScanVerdict,
+    VirusScanner, SCAN_RESULT_METADATA_KEY, SCAN_THREAT_METADATA_KEY,
+};
+pub use signing::{SignedUrlError, SignedUrlMethod, UrlScope, UrlSigner};
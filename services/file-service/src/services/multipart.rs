@@ -0,0 +1,510 @@
+//! Resumable multipart uploads.
+//!
+//! A client starts an upload with the file's metadata, sends the file in
+//! numbered parts, and completes it once every part has arrived. Parts are
+//! written to disk as they arrive, so a client whose connection drops can ask
+//! which parts the service holds and send only the rest. Re-sending a part
+//! replaces it, which makes retries safe.
+//!
+//! Incomplete uploads are tracked in memory and expire when no part arrives
+//! for the configured TTL; [`MultipartUploads::prune_expired`] removes them
+//! and their parts. Parts left on disk by a previous run are removed at
+//! startup.
+
+use super::caller::Caller;
+use acton_dx_proto::error::invalid_field;
+use acton_dx_proto::file::v1::{UploadMetadata, UploadedPart};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+use tonic::Status;
+use tracing::{debug, warn};
+
+/// Directory below the storage base path holding parts of incomplete uploads.
+pub const MULTIPART_DIR: &str = ".uploads";
+
+/// Highest part number a client may send.
+pub const MAX_PART_NUMBER: u32 = 10_000;
+
+/// A part the service holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PartInfo {
+    size: u64,
+    checksum: String,
+}
+
+/// An upload that has not been completed or aborted.
+#[derive(Debug, Clone)]
+pub struct PendingUpload {
+    /// Metadata the upload was started with.
+    pub metadata: UploadMetadata,
+    /// User the upload belongs to, if it was started with an identity.
+    pub owner_id: Option<i64>,
+    /// Tenant the upload counts against, if it was started for one.
+    pub tenant: Option<String>,
+    /// Maximum size of the assembled file.
+    pub limit: u64,
+    /// When the upload expires (Unix seconds).
+    pub expires_at: i64,
+    parts: BTreeMap<u32, PartInfo>,
+}
+
+impl PendingUpload {
+    /// Total size of the parts received so far.
+    #[must_use]
+    pub fn uploaded_size(&self) -> u64 {
+        self.parts.values().map(|part| part.size).sum()
+    }
+
+    /// Parts received so far, in part number order.
+    #[must_use]
+    pub fn parts(&self) -> Vec<UploadedPart> {
+        self.parts
+            .iter()
+            .map(|(&part_number, part)| UploadedPart {
+                part_number,
+                size: i64::try_from(part.size).unwrap_or(i64::MAX),
+                checksum: part.checksum.clone(),
+            })
+            .collect()
+    }
+
+    /// Part numbers in order, if they run from 1 without gaps.
+    #[must_use]
+    pub fn contiguous_parts(&self) -> Option<Vec<u32>> {
+        let numbers: Vec<u32> = self.parts.keys().copied().collect();
+        let contiguous = !numbers.is_empty()
+            && numbers
+                .iter()
+                .zip(1..)
+                .all(|(&number, expected)| number == expected);
+        contiguous.then_some(numbers)
+    }
+
+    /// Whether a caller may use the upload; see [`Caller::may_access`].
+    fn is_accessible_by(&self, caller: &Caller) -> bool {
+        caller.may_access(self.owner_id, self.tenant.as_deref())
+    }
+}
+
+/// Tracks incomplete multipart uploads and stores their parts.
+#[derive(Debug, Clone)]
+pub struct MultipartUploads {
+    dir: PathBuf,
+    ttl_seconds: i64,
+    max_part_size: usize,
+    pending: Arc<RwLock<HashMap<String, PendingUpload>>>,
+}
+
+impl MultipartUploads {
+    /// Store parts below `dir`, expiring uploads idle for `ttl`.
+    #[must_use]
+    pub fn new(dir: PathBuf, ttl: Duration, max_part_size: usize) -> Self {
+        Self {
+            dir,
+            ttl_seconds: i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX),
+            max_part_size,
+            pending: Arc::default(),
+        }
+    }
+
+    /// Largest part accepted, in bytes.
+    #[must_use]
+    pub const fn max_part_size(&self) -> usize {
+        self.max_part_size
+    }
+
+    /// Remove parts left on disk by a previous run.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the directory exists but cannot be removed.
+    pub async fn clear_stale(&self) -> std::io::Result<()> {
+        match fs::remove_dir_all(&self.dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn upload_dir(&self, upload_id: &str) -> PathBuf {
+        self.dir.join(upload_id)
+    }
+
+    /// Path of a received part.
+    #[must_use]
+    pub fn part_path(&self, upload_id: &str, part_number: u32) -> PathBuf {
+        self.upload_dir(upload_id).join(part_number.to_string())
+    }
+
+    /// Start tracking an upload, returning its ID and expiry.
+    ///
+    /// # Errors
+    ///
+    /// Returns `INTERNAL` if the upload's directory cannot be created.
+    pub async fn initiate(
+        &self,
+        metadata: UploadMetadata,
+        caller: &Caller,
+        limit: u64,
+        now: i64,
+    ) -> Result<(String, i64), Status> {
+        let upload_id = uuid::Uuid::new_v4().to_string();
+        fs::create_dir_all(self.upload_dir(&upload_id))
+            .await
+            .map_err(|e| Status::internal(format!("Failed to create upload directory: {e}")))?;
+
+        let expires_at = now.saturating_add(self.ttl_seconds);
+        let upload = PendingUpload {
+            metadata,
+            owner_id: caller.user_id,
+            tenant: caller.tenant.clone(),
+            limit,
+            expires_at,
+            parts: BTreeMap::new(),
+        };
+        self.pending.write().await.insert(upload_id.clone(), upload);
+        Ok((upload_id, expires_at))
+    }
+
+    /// An upload the caller may use.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NOT_FOUND` for unknown or expired uploads and
+    /// `PERMISSION_DENIED` for another user's or tenant's upload.
+    pub async fn get(&self, upload_id: &str, caller: &Caller) -> Result<PendingUpload, Status> {
+        let pending = self.pending.read().await;
+        let upload = pending
+            .get(upload_id)
+            .cloned()
+            .ok_or_else(|| Status::not_found("Upload not found"))?;
+        drop(pending);
+
+        if !upload.is_accessible_by(caller) {
+            return Err(Status::permission_denied("Upload belongs to another user"));
+        }
+        Ok(upload)
+    }
+
+    /// Store a part, replacing any earlier copy, and extend the upload's
+    /// expiry. Returns the part's size and SHA-256 checksum.
+    ///
+    /// # Errors
+    ///
+    /// Returns `INVALID_ARGUMENT` for bad part numbers, oversized parts, or a
+    /// checksum mismatch, `FAILED_PRECONDITION` if the part would take the
+    /// upload past its size limit, and the errors of [`Self::get`].
+    pub async fn put_part(
+        &self,
+        upload_id: &str,
+        caller: &Caller,
+        part_number: u32,
+        data: &[u8],
+        expected_checksum: Option<&str>,
+        now: i64,
+    ) -> Result<(u64, String), Status> {
+        if !(1..=MAX_PART_NUMBER).contains(&part_number) {
+            return Err(invalid_field(
+                "part_number",
+                format!("Part number must be between 1 and {MAX_PART_NUMBER}"),
+            ));
+        }
+        if data.len() > self.max_part_size {
+            return Err(invalid_field(
+                "data",
+                format!("Part exceeds maximum size of {} bytes", self.max_part_size),
+            ));
+        }
+        let checksum = format!("{:x}", Sha256::digest(data));
+        if expected_checksum.is_some_and(|expected| !expected.eq_ignore_ascii_case(&checksum)) {
+            return Err(invalid_field("checksum", "Part checksum mismatch"));
+        }
+
+        let upload = self.get(upload_id, caller).await?;
+        let size = data.len() as u64;
+        let others: u64 = upload
+            .parts
+            .iter()
+            .filter(|(&number, _)| number != part_number)
+            .map(|(_, part)| part.size)
+            .sum();
+        if others.saturating_add(size) > upload.limit {
+            return Err(Status::failed_precondition(format!(
+                "Upload exceeds maximum size of {} bytes",
+                upload.limit
+            )));
+        }
+
+        // Written aside and renamed, so a dropped connection never leaves a
+        // torn part behind
+        let path = self.part_path(upload_id, part_number);
+        let partial = path.with_extension(uuid::Uuid::new_v4().to_string());
+        write_file(&partial, data).await?;
+        fs::rename(&partial, &path)
+            .await
+            .map_err(|e| Status::internal(format!("Failed to store part: {e}")))?;
+
+        let mut pending = self.pending.write().await;
+        let Some(upload) = pending.get_mut(upload_id) else {
+            // Completed, aborted, or expired while the part was written
+            drop(pending);
+            let _ = fs::remove_file(&path).await;
+            return Err(Status::not_found("Upload not found"));
+        };
+        upload.parts.insert(
+            part_number,
+            PartInfo {
+                size,
+                checksum: checksum.clone(),
+            },
+        );
+        upload.expires_at = now.saturating_add(self.ttl_seconds);
+        drop(pending);
+
+        debug!(upload_id, part_number, size, "Stored upload part");
+        Ok((size, checksum))
+    }
+
+    /// Stop tracking an upload so it can be assembled.
+    ///
+    /// The parts stay on disk until [`Self::discard`] is called.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FAILED_PRECONDITION` unless parts 1 to N have all arrived,
+    /// and the errors of [`Self::get`].
+    pub async fn take_complete(
+        &self,
+        upload_id: &str,
+        caller: &Caller,
+    ) -> Result<(PendingUpload, Vec<u32>), Status> {
+        let mut pending = self.pending.write().await;
+        let upload = pending
+            .get(upload_id)
+            .ok_or_else(|| Status::not_found("Upload not found"))?;
+        if !upload.is_accessible_by(caller) {
+            return Err(Status::permission_denied("Upload belongs to another user"));
+        }
+        let parts = upload.contiguous_parts().ok_or_else(|| {
+            Status::failed_precondition("Parts must be numbered from 1 without gaps")
+        })?;
+        let upload = pending
+            .remove(upload_id)
+            .ok_or_else(|| Status::not_found("Upload not found"))?;
+        drop(pending);
+        Ok((upload, parts))
+    }
+
+    /// Stop tracking an upload and remove its parts.
+    ///
+    /// Returns whether the upload existed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PERMISSION_DENIED` for another user's or tenant's upload.
+    pub async fn abort(&self, upload_id: &str, caller: &Caller) -> Result<bool, Status> {
+        let mut pending = self.pending.write().await;
+        if pending
+            .get(upload_id)
+            .is_some_and(|upload| !upload.is_accessible_by(caller))
+        {
+            return Err(Status::permission_denied("Upload belongs to another user"));
+        }
+        let removed = pending.remove(upload_id).is_some();
+        drop(pending);
+
+        if removed {
+            self.discard(upload_id).await;
+        }
+        Ok(removed)
+    }
+
+    /// Remove the parts of an upload that is no longer tracked.
+    pub async fn discard(&self, upload_id: &str) {
+        let dir = self.upload_dir(upload_id);
+        if let Err(e) = fs::remove_dir_all(&dir).await {
+            warn!(error = %e, path = %dir.display(), "Failed to remove upload parts");
+        }
+    }
+
+    /// Remove uploads that expired before `now`, returning how many.
+    pub async fn prune_expired(&self, now: i64) -> usize {
+        let mut pending = self.pending.write().await;
+        let expired: Vec<String> = pending
+            .iter()
+            .filter(|(_, upload)| upload.expires_at <= now)
+            .map(|(upload_id, _)| upload_id.clone())
+            .collect();
+        for upload_id in &expired {
+            pending.remove(upload_id);
+        }
+        drop(pending);
+
+        for upload_id in &expired {
+            self.discard(upload_id).await;
+        }
+        expired.len()
+    }
+}
+
+async fn write_file(path: &Path, data: &[u8]) -> Result<(), Status> {
+    let mut file = File::create(path)
+        .await
+        .map_err(|e| Status::internal(format!("Failed to create part: {e}")))?;
+    file.write_all(data)
+        .await
+        .map_err(|e| Status::internal(format!("Failed to write part: {e}")))?;
+    file.flush()
+        .await
+        .map_err(|e| Status::internal(format!("Failed to write part: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uploads(dir: &Path) -> MultipartUploads {
+        MultipartUploads::new(dir.join(MULTIPART_DIR), Duration::from_secs(60), 8)
+    }
+
+    fn metadata() -> UploadMetadata {
+        UploadMetadata {
+            filename: "video.mp4".to_string(),
+            content_type: "video/mp4".to_string(),
+            path: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn user(user_id: i64) -> Caller {
+        Caller {
+            user_id: Some(user_id),
+            tenant: Some(user_id.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parts_replace_and_complete_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploads = uploads(dir.path());
+        let (id, expires_at) = uploads
+            .initiate(metadata(), &Caller::default(), 100, 1_000)
+            .await
+            .unwrap();
+        assert_eq!(expires_at, 1_060);
+
+        uploads
+            .put_part(&id, &Caller::default(), 2, b"world", None, 1_010)
+            .await
+            .unwrap();
+        assert!(uploads
+            .take_complete(&id, &Caller::default())
+            .await
+            .is_err());
+
+        uploads
+            .put_part(&id, &Caller::default(), 1, b"hullo ", None, 1_020)
+            .await
+            .unwrap();
+        let checksum = format!("{:x}", Sha256::digest(b"hello "));
+        uploads
+            .put_part(
+                &id,
+                &Caller::default(),
+                1,
+                b"hello ",
+                Some(&checksum),
+                1_030,
+            )
+            .await
+            .unwrap();
+
+        let upload = uploads.get(&id, &Caller::default()).await.unwrap();
+        assert_eq!(upload.uploaded_size(), 11);
+        assert_eq!(upload.expires_at, 1_090);
+        assert_eq!(upload.parts()[0].checksum, checksum);
+
+        let (_, parts) = uploads
+            .take_complete(&id, &Caller::default())
+            .await
+            .unwrap();
+        assert_eq!(parts, vec![1, 2]);
+        let first = fs::read(uploads.part_path(&id, 1)).await.unwrap();
+        assert_eq!(first, b"hello ");
+        assert!(uploads.get(&id, &Caller::default()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_put_part_enforces_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploads = uploads(dir.path());
+        let (id, _) = uploads.initiate(metadata(), &user(1), 10, 0).await.unwrap();
+
+        let code = |result: Result<(u64, String), Status>| result.unwrap_err().code();
+        let put = |number, data: &'static [u8], checksum: Option<&'static str>| {
+            let uploads = uploads.clone();
+            let id = id.clone();
+            async move {
+                uploads
+                    .put_part(&id, &user(1), number, data, checksum, 0)
+                    .await
+            }
+        };
+
+        assert_eq!(code(put(0, b"a", None).await), tonic::Code::InvalidArgument);
+        assert_eq!(
+            code(put(1, b"too large", None).await),
+            tonic::Code::InvalidArgument
+        );
+        assert_eq!(
+            code(put(1, b"a", Some("00")).await),
+            tonic::Code::InvalidArgument
+        );
+        put(1, b"12345678", None).await.unwrap();
+        assert_eq!(
+            code(put(2, b"123", None).await),
+            tonic::Code::FailedPrecondition
+        );
+
+        let other = uploads.put_part(&id, &user(2), 2, b"1", None, 0).await;
+        assert_eq!(code(other), tonic::Code::PermissionDenied);
+        // Trusted callers must act for the user's tenant
+        let untenanted = uploads.get(&id, &Caller::default()).await.unwrap_err();
+        assert_eq!(untenanted.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_abort_and_prune_remove_parts() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploads = uploads(dir.path());
+        let (aborted, _) = uploads
+            .initiate(metadata(), &Caller::default(), 10, 0)
+            .await
+            .unwrap();
+        let (expired, _) = uploads
+            .initiate(metadata(), &Caller::default(), 10, 0)
+            .await
+            .unwrap();
+        let (active, _) = uploads
+            .initiate(metadata(), &Caller::default(), 10, 0)
+            .await
+            .unwrap();
+        uploads
+            .put_part(&active, &Caller::default(), 1, b"a", None, 30)
+            .await
+            .unwrap();
+
+        assert!(uploads.abort(&aborted, &Caller::default()).await.unwrap());
+        assert!(!uploads.abort(&aborted, &Caller::default()).await.unwrap());
+        assert!(!uploads.upload_dir(&aborted).exists());
+
+        assert_eq!(uploads.prune_expired(60).await, 1);
+        assert!(!uploads.upload_dir(&expired).exists());
+        assert!(uploads.get(&active, &Caller::default()).await.is_ok());
+    }
+}
@@ -0,0 +1,223 @@
+//! Per-tenant storage quotas.
+//!
+//! Usage is counted per tenant as files are stored and deleted, against the
+//! tenant the uploading [`Caller`](super::caller::Caller) acts for. Files
+//! uploaded for no tenant are not counted.
+//!
+//! Past the soft limit uploads still succeed and the tenant is reported as
+//! over it; an upload that would pass the hard limit is rejected with
+//! `RESOURCE_EXHAUSTED`. Like file metadata, usage is held in memory.
+
+use crate::config::QuotaLimits;
+use acton_dx_proto::error::v1::ErrorDetail;
+use acton_dx_proto::file::v1::StorageUsage;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tonic::{Code, Status};
+use tracing::warn;
+
+/// Storage a tenant uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Bytes stored.
+    pub bytes: u64,
+    /// Files stored.
+    pub files: u64,
+}
+
+/// Tracks storage per tenant and enforces its limits.
+#[derive(Debug, Clone, Default)]
+pub struct StorageQuotas {
+    default: QuotaLimits,
+    tenants: Arc<HashMap<String, QuotaLimits>>,
+    usage: Arc<RwLock<HashMap<String, Usage>>>,
+}
+
+impl StorageQuotas {
+    /// Apply `default` to every tenant without an entry in `tenants`.
+    #[must_use]
+    pub fn new(default: QuotaLimits, tenants: HashMap<String, QuotaLimits>) -> Self {
+        Self {
+            default,
+            tenants: Arc::new(tenants),
+            usage: Arc::default(),
+        }
+    }
+
+    /// Limits that apply to `tenant`.
+    #[must_use]
+    pub fn limits(&self, tenant: &str) -> QuotaLimits {
+        self.tenants.get(tenant).copied().unwrap_or(self.default)
+    }
+
+    /// Storage `tenant` uses.
+    pub async fn usage(&self, tenant: &str) -> Usage {
+        self.usage
+            .read()
+            .await
+            .get(tenant)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Check that a file of `size` bytes fits without recording it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RESOURCE_EXHAUSTED` if the file would pass the hard limit.
+    pub async fn check(&self, tenant: &str, size: u64) -> Result<(), Status> {
+        let used = self.usage(tenant).await.bytes;
+        if let Some(hard_limit) = self.exceeded_hard_limit(tenant, used, size) {
+            return Err(quota_exceeded(tenant, used, hard_limit, size));
+        }
+        Ok(())
+    }
+
+    /// Record a file of `size` bytes stored for `tenant`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RESOURCE_EXHAUSTED`, recording nothing, if the file would
+    /// pass the hard limit.
+    pub async fn reserve(&self, tenant: &str, size: u64) -> Result<(), Status> {
+        let mut usage = self.usage.write().await;
+        let before = usage.get(tenant).map_or(0, |entry| entry.bytes);
+        if let Some(hard_limit) = self.exceeded_hard_limit(tenant, before, size) {
+            return Err(quota_exceeded(tenant, before, hard_limit, size));
+        }
+
+        let entry = usage.entry(tenant.to_string()).or_default();
+        entry.bytes = entry.bytes.saturating_add(size);
+        entry.files += 1;
+        let after = entry.bytes;
+        drop(usage);
+
+        if let Some(soft_limit) = self.limits(tenant).soft_limit {
+            if before <= soft_limit && after > soft_limit {
+                warn!(%tenant, used = after, soft_limit, "Tenant passed its soft storage limit");
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget a file of `size` bytes that was deleted or rejected.
+    pub async fn release(&self, tenant: &str, size: u64) {
+        let mut usage = self.usage.write().await;
+        if let Some(entry) = usage.get_mut(tenant) {
+            entry.bytes = entry.bytes.saturating_sub(size);
+            entry.files = entry.files.saturating_sub(1);
+            if entry.files == 0 {
+                usage.remove(tenant);
+            }
+        }
+    }
+
+    /// Usage and limits of `tenant`, for `GetUsage`.
+    pub async fn report(&self, tenant: &str) -> StorageUsage {
+        let usage = self.usage(tenant).await;
+        let limits = self.limits(tenant);
+        let to_i64 = |bytes: u64| i64::try_from(bytes).unwrap_or(i64::MAX);
+        StorageUsage {
+            tenant_id: tenant.to_string(),
+            used_bytes: to_i64(usage.bytes),
+            file_count: to_i64(usage.files),
+            soft_limit: limits.soft_limit.map(to_i64),
+            hard_limit: limits.hard_limit.map(to_i64),
+            over_soft_limit: limits
+                .soft_limit
+                .is_some_and(|soft_limit| usage.bytes > soft_limit),
+        }
+    }
+
+    /// The hard limit, if `size` more bytes would pass it.
+    fn exceeded_hard_limit(&self, tenant: &str, used: u64, size: u64) -> Option<u64> {
+        self.limits(tenant)
+            .hard_limit
+            .filter(|&hard_limit| used.saturating_add(size) > hard_limit)
+    }
+}
+
+/// Error returned for an upload that would pass its tenant's hard limit.
+fn quota_exceeded(tenant: &str, used: u64, hard_limit: u64, size: u64) -> Status {
+    warn!(%tenant, used, size, hard_limit, "Upload rejected by storage quota");
+    ErrorDetail::new(
+        "QUOTA_EXCEEDED",
+        format!(
+            "Storage quota exceeded for tenant {tenant}: {used} of {hard_limit} bytes used, \
+             upload needs {size}"
+        ),
+    )
+    .into_status(Code::ResourceExhausted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quotas() -> StorageQuotas {
+        StorageQuotas::new(
+            QuotaLimits {
+                soft_limit: Some(50),
+                hard_limit: Some(100),
+            },
+            HashMap::from([(
+                "acme".to_string(),
+                QuotaLimits {
+                    soft_limit: None,
+                    hard_limit: Some(1000),
+                },
+            )]),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_reserve_enforces_hard_limit() {
+        let quotas = quotas();
+        quotas.reserve("7", 60).await.unwrap();
+        quotas.reserve("7", 40).await.unwrap();
+
+        let status = quotas.reserve("7", 1).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(
+            ErrorDetail::from_status(&status).map(|detail| detail.code),
+            Some("QUOTA_EXCEEDED".to_string())
+        );
+        assert!(quotas.check("7", 1).await.is_err());
+        assert_eq!(
+            quotas.usage("7").await,
+            Usage {
+                bytes: 100,
+                files: 2
+            }
+        );
+
+        quotas.reserve("acme", 500).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_release_frees_space() {
+        let quotas = quotas();
+        quotas.reserve("7", 100).await.unwrap();
+        quotas.release("7", 100).await;
+        assert_eq!(quotas.usage("7").await, Usage::default());
+        quotas.check("7", 100).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_report_flags_soft_limit() {
+        let quotas = quotas();
+        quotas.reserve("7", 60).await.unwrap();
+
+        let report = quotas.report("7").await;
+        assert_eq!(report.used_bytes, 60);
+        assert_eq!(report.file_count, 1);
+        assert_eq!(report.soft_limit, Some(50));
+        assert_eq!(report.hard_limit, Some(100));
+        assert!(report.over_soft_limit);
+
+        let report = quotas.report("acme").await;
+        assert_eq!(report.soft_limit, None);
+        assert!(!report.over_soft_limit);
+    }
+}
@@ -0,0 +1,314 @@
+//! Virus scanning of uploaded files.
+//!
+//! Files are scanned once they are written to storage and before their
+//! metadata is recorded, so an infected file is never served. The ClamAV
+//! scanner streams the stored file to clamd with the `INSTREAM` command;
+//! clamd's `StreamMaxLength` must be at least the largest accepted upload.
+
+use acton_dx_proto::error::v1::ErrorDetail;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::fs::{self, File};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Status};
+
+/// Response metadata key carrying the scan result of a rejected upload.
+pub const SCAN_RESULT_METADATA_KEY: &str = "x-scan-result";
+
+/// Response metadata key naming the threat found in a rejected upload.
+pub const SCAN_THREAT_METADATA_KEY: &str = "x-scan-threat";
+
+/// Bytes sent to clamd per `INSTREAM` chunk.
+const CLAMD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Outcome of scanning a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    /// No threat was found.
+    Clean,
+    /// The file contains a threat.
+    Infected {
+        /// Name of the threat reported by the scanner.
+        threat: String,
+    },
+}
+
+/// Scans stored files for malware.
+#[tonic::async_trait]
+pub trait VirusScanner: Send + Sync + fmt::Debug {
+    /// Scan the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be scanned, for example when
+    /// the scanner is unreachable.
+    async fn scan(&self, path: &Path) -> io::Result<ScanVerdict>;
+
+    /// Name of the scanner, for logs.
+    fn name(&self) -> &'static str;
+}
+
+/// Address of a clamd daemon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClamdAddress {
+    /// TCP `host:port`.
+    Tcp(String),
+    /// Unix domain socket path.
+    #[cfg(unix)]
+    Socket(PathBuf),
+}
+
+impl FromStr for ClamdAddress {
+    type Err = String;
+
+    /// Parse `tcp://host:port`, `unix:///path/to/clamd.sock` or a bare
+    /// `host:port`.
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = address.strip_prefix("unix://") {
+            #[cfg(unix)]
+            return Ok(Self::Socket(PathBuf::from(path)));
+            #[cfg(not(unix))]
+            return Err(format!("Unix sockets are not supported here: {path}"));
+        }
+        let host = address.strip_prefix("tcp://").unwrap_or(address);
+        if host
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+        {
+            Ok(Self::Tcp(host.to_string()))
+        } else {
+            Err(format!("Invalid clamd address: {address}"))
+        }
+    }
+}
+
+/// Scanner backed by a ClamAV daemon.
+#[derive(Debug, Clone)]
+pub struct ClamAvScanner {
+    address: ClamdAddress,
+}
+
+impl ClamAvScanner {
+    /// Create a scanner for the clamd daemon at `address`.
+    #[must_use]
+    pub const fn new(address: ClamdAddress) -> Self {
+        Self { address }
+    }
+}
+
+#[tonic::async_trait]
+impl VirusScanner for ClamAvScanner {
+    async fn scan(&self, path: &Path) -> io::Result<ScanVerdict> {
+        let file = File::open(path).await?;
+        let reply = match &self.address {
+            ClamdAddress::Tcp(host) => {
+                instream(tokio::net::TcpStream::connect(host).await?, file).await?
+            }
+            #[cfg(unix)]
+            ClamdAddress::Socket(socket) => {
+                instream(tokio::net::UnixStream::connect(socket).await?, file).await?
+            }
+        };
+        parse_reply(&reply)
+    }
+
+    fn name(&self) -> &'static str {
+        "clamav"
+    }
+}
+
+/// Send a file to clamd with `INSTREAM` and return its reply.
+async fn instream<S, R>(mut stream: S, mut file: R) -> io::Result<String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+{
+    stream.write_all(b"zINSTREAM\0").await?;
+    let mut buffer = vec![0u8; CLAMD_CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        let length = u32::try_from(read).map_err(io::Error::other)?;
+        stream.write_all(&length.to_be_bytes()).await?;
+        stream.write_all(&buffer[..read]).await?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await?;
+    stream.flush().await?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
+
+/// Parse a clamd reply such as `stream: OK` or `stream: Eicar-Signature FOUND`.
+fn parse_reply(reply: &str) -> io::Result<ScanVerdict> {
+    let reply = reply.trim_end_matches(['\0', '\n']).trim();
+    let result = reply.strip_prefix("stream:").map_or(reply, str::trim);
+    if result == "OK" {
+        Ok(ScanVerdict::Clean)
+    } else if let Some(threat) = result.strip_suffix(" FOUND") {
+        Ok(ScanVerdict::Infected {
+            threat: threat.trim().to_string(),
+        })
+    } else {
+        Err(io::Error::other(format!("clamd error: {reply}")))
+    }
+}
+
+/// Details recorded next to a quarantined file.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuarantineRecord {
+    /// ID the file would have been stored under.
+    pub file_id: String,
+    /// Original filename.
+    pub filename: String,
+    /// Declared content type.
+    pub content_type: String,
+    /// Size in bytes.
+    pub size: i64,
+    /// SHA-256 checksum.
+    pub checksum: String,
+    /// Threat reported by the scanner.
+    pub threat: String,
+    /// User the file was uploaded for, if any.
+    pub owner_id: Option<i64>,
+    /// When the file was quarantined (Unix seconds).
+    pub quarantined_at: i64,
+}
+
+/// Directory infected uploads are moved to for inspection.
+#[derive(Debug, Clone)]
+pub struct Quarantine {
+    dir: PathBuf,
+}
+
+impl Quarantine {
+    /// Keep infected uploads in `dir`.
+    #[must_use]
+    pub const fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Move the file at `path` into quarantine with a JSON record beside it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be moved or the record written.
+    pub async fn hold(&self, path: &Path, record: &QuarantineRecord) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir).await?;
+        let target = self.dir.join(&record.file_id);
+        if fs::rename(path, &target).await.is_err() {
+            // The quarantine may be on another filesystem
+            fs::copy(path, &target).await?;
+            fs::remove_file(path).await?;
+        }
+        let json = serde_json::to_vec_pretty(record).map_err(io::Error::other)?;
+        fs::write(target.with_extension("json"), json).await?;
+        Ok(target)
+    }
+}
+
+/// Error returned for an infected upload.
+///
+/// The code is `INVALID_ARGUMENT` with error detail code `FILE_INFECTED`,
+/// and the response metadata carries the scan result and the threat name so
+/// clients can tell it apart from other rejected uploads.
+#[must_use]
+pub fn infected_status(threat: &str) -> Status {
+    let mut status = ErrorDetail::new("FILE_INFECTED", format!("File is infected: {threat}"))
+        .into_status(Code::InvalidArgument);
+    let metadata = status.metadata_mut();
+    metadata.insert(
+        SCAN_RESULT_METADATA_KEY,
+        MetadataValue::from_static("infected"),
+    );
+    if let Ok(value) = MetadataValue::try_from(threat) {
+        metadata.insert(SCAN_THREAT_METADATA_KEY, value);
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::duplex;
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            "tcp://clamav:3310".parse(),
+            Ok(ClamdAddress::Tcp("clamav:3310".to_string()))
+        );
+        assert_eq!(
+            "127.0.0.1:3310".parse(),
+            Ok(ClamdAddress::Tcp("127.0.0.1:3310".to_string()))
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            "unix:///run/clamav/clamd.sock".parse(),
+            Ok(ClamdAddress::Socket(PathBuf::from(
+                "/run/clamav/clamd.sock"
+            )))
+        );
+        assert!("clamav".parse::<ClamdAddress>().is_err());
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply("stream: OK\0").unwrap(), ScanVerdict::Clean);
+        assert_eq!(
+            parse_reply("stream: Eicar-Test-Signature FOUND\0").unwrap(),
+            ScanVerdict::Infected {
+                threat: "Eicar-Test-Signature".to_string()
+            }
+        );
+        assert!(parse_reply("INSTREAM size limit exceeded. ERROR\0").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_instream_protocol() {
+        let (client, mut clamd) = duplex(1024);
+        let daemon = tokio::spawn(async move {
+            let mut command = [0u8; 10];
+            clamd.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+
+            let mut received = Vec::new();
+            loop {
+                let length = clamd.read_u32().await.unwrap() as usize;
+                if length == 0 {
+                    break;
+                }
+                let mut chunk = vec![0u8; length];
+                clamd.read_exact(&mut chunk).await.unwrap();
+                received.extend_from_slice(&chunk);
+            }
+            clamd.write_all(b"stream: OK\0").await.unwrap();
+            received
+        });
+
+        let reply = instream(client, &b"hello clamd"[..]).await.unwrap();
+        assert_eq!(parse_reply(&reply).unwrap(), ScanVerdict::Clean);
+        assert_eq!(daemon.await.unwrap(), b"hello clamd");
+    }
+
+    #[test]
+    fn test_infected_status_metadata() {
+        let status = infected_status("Eicar-Test-Signature");
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(
+            status.metadata().get(SCAN_RESULT_METADATA_KEY).unwrap(),
+            "infected"
+        );
+        assert_eq!(
+            status.metadata().get(SCAN_THREAT_METADATA_KEY).unwrap(),
+            "Eicar-Test-Signature"
+        );
+    }
+}
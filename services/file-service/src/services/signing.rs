@@ -0,0 +1,329 @@
+//! Signed URL scopes.
+//!
+//! A signed URL carries its scope in the query string: when it expires, the
+//! HTTP method it allows and, optionally, the client IP it is bound to. The
+//! signature is an HMAC-SHA256 over the file ID and the whole scope, so none
+//! of them can be changed without invalidating the URL:
+//!
+//! ```text
+//! {public_base_url}/{file_id}?expires=1700000000&method=GET&ip=203.0.113.7&sig=...
+//! ```
+//!
+//! The web tier checks URLs with the `VerifySignedUrl` RPC, so the signing
+//! key never leaves the file service.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// HTTP method a signed URL allows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignedUrlMethod {
+    /// Download the file.
+    #[default]
+    Get,
+    /// Upload the file's content.
+    Put,
+}
+
+impl SignedUrlMethod {
+    /// Method name as it appears in the URL.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Put => "PUT",
+        }
+    }
+}
+
+impl fmt::Display for SignedUrlMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SignedUrlMethod {
+    type Err = String;
+
+    /// Parse `GET` or `PUT`, ignoring case. `HEAD` is treated as `GET`.
+    fn from_str(method: &str) -> Result<Self, Self::Err> {
+        match method.to_ascii_uppercase().as_str() {
+            "GET" | "HEAD" => Ok(Self::Get),
+            "PUT" => Ok(Self::Put),
+            _ => Err(format!("Unsupported signed URL method: {method}")),
+        }
+    }
+}
+
+/// What a signed URL grants access to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlScope {
+    /// File the URL is for.
+    pub file_id: String,
+    /// When the URL expires (Unix seconds).
+    pub expires_at: i64,
+    /// HTTP method the URL allows.
+    pub method: SignedUrlMethod,
+    /// Client IP the URL is bound to, if any.
+    pub ip: Option<IpAddr>,
+}
+
+/// Why a signed URL was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignedUrlError {
+    /// The URL is missing part of its scope or signature.
+    Malformed,
+    /// The signature does not match the scope.
+    BadSignature,
+    /// The URL has expired.
+    Expired,
+    /// The request used a method the URL does not allow.
+    MethodNotAllowed,
+    /// The request came from an IP the URL is not bound to.
+    IpMismatch,
+}
+
+impl SignedUrlError {
+    /// Short reason reported by `VerifySignedUrl`.
+    #[must_use]
+    pub const fn reason(self) -> &'static str {
+        match self {
+            Self::Malformed => "malformed",
+            Self::BadSignature => "bad_signature",
+            Self::Expired => "expired",
+            Self::MethodNotAllowed => "method_not_allowed",
+            Self::IpMismatch => "ip_mismatch",
+        }
+    }
+}
+
+impl fmt::Display for SignedUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.reason())
+    }
+}
+
+/// Signs and verifies scoped URLs with the service's signing key.
+#[derive(Clone)]
+pub struct UrlSigner {
+    key: Vec<u8>,
+}
+
+impl fmt::Debug for UrlSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UrlSigner").finish_non_exhaustive()
+    }
+}
+
+impl UrlSigner {
+    /// Sign with `key`.
+    #[must_use]
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+        }
+    }
+
+    /// Signed URL for `scope` under `base_url`.
+    #[must_use]
+    pub fn sign(&self, base_url: &str, scope: &UrlScope) -> String {
+        let signature = format!("{:x}", self.mac(scope).finalize().into_bytes());
+        let ip = scope.ip.map(|ip| format!("&ip={ip}")).unwrap_or_default();
+        format!(
+            "{base_url}/{}?expires={}&method={}{ip}&sig={signature}",
+            scope.file_id, scope.expires_at, scope.method
+        )
+    }
+
+    /// Check a signed URL used with `method` from `client_ip` at `now`.
+    ///
+    /// `url` may be the full URL or just its path and query. The file ID is
+    /// the last path segment.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the URL does not grant the request.
+    pub fn verify(
+        &self,
+        url: &str,
+        method: SignedUrlMethod,
+        client_ip: Option<IpAddr>,
+        now: i64,
+    ) -> Result<UrlScope, SignedUrlError> {
+        let (scope, signature) = parse(url).ok_or(SignedUrlError::Malformed)?;
+        self.mac(&scope)
+            .verify_slice(&signature)
+            .map_err(|_| SignedUrlError::BadSignature)?;
+
+        if now > scope.expires_at {
+            return Err(SignedUrlError::Expired);
+        }
+        if method != scope.method {
+            return Err(SignedUrlError::MethodNotAllowed);
+        }
+        if scope.ip.is_some() && scope.ip != client_ip {
+            return Err(SignedUrlError::IpMismatch);
+        }
+        Ok(scope)
+    }
+
+    /// MAC over the file ID and scope, one field per line.
+    fn mac(&self, scope: &UrlScope) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        let ip = scope.ip.map(|ip| ip.to_string()).unwrap_or_default();
+        for field in [
+            scope.file_id.as_str(),
+            &scope.expires_at.to_string(),
+            scope.method.as_str(),
+            &ip,
+        ] {
+            mac.update(field.as_bytes());
+            mac.update(b"\n");
+        }
+        mac
+    }
+}
+
+/// Split a signed URL into its scope and signature.
+fn parse(url: &str) -> Option<(UrlScope, Vec<u8>)> {
+    let (path, query) = url.split_once('?')?;
+    let file_id = path.rsplit('/').next().filter(|id| !id.is_empty())?;
+
+    let (mut expires_at, mut method, mut ip, mut signature) = (None, None, None, None);
+    for pair in query.split('&') {
+        match pair.split_once('=')? {
+            ("expires", value) => expires_at = Some(value.parse().ok()?),
+            ("method", value) => method = Some(value.parse().ok()?),
+            ("ip", value) => ip = Some(value.parse().ok()?),
+            ("sig", value) => signature = Some(from_hex(value)?),
+            _ => {}
+        }
+    }
+
+    Some((
+        UrlScope {
+            file_id: file_id.to_string(),
+            expires_at: expires_at?,
+            method: method?,
+            ip,
+        },
+        signature?,
+    ))
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_URL: &str = "http://localhost:50056/files";
+
+    fn scope(ip: Option<&str>) -> UrlScope {
+        UrlScope {
+            file_id: "f1".to_string(),
+            expires_at: 1000,
+            method: SignedUrlMethod::Get,
+            ip: ip.map(|ip| ip.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_verify_accepts_signed_scope() {
+        let signer = UrlSigner::new("secret");
+        let url = signer.sign(BASE_URL, &scope(None));
+        assert!(url.starts_with("http://localhost:50056/files/f1?expires=1000&method=GET&sig="));
+
+        let verified = signer
+            .verify(&url, SignedUrlMethod::Get, None, 999)
+            .unwrap();
+        assert_eq!(verified, scope(None));
+
+        // The path and query alone are enough
+        let path = url.trim_start_matches("http://localhost:50056");
+        assert!(signer.verify(path, SignedUrlMethod::Get, None, 999).is_ok());
+    }
+
+    #[test]
+    fn test_verify_enforces_scope() {
+        let signer = UrlSigner::new("secret");
+        let url = signer.sign(BASE_URL, &scope(Some("203.0.113.7")));
+        let client = Some("203.0.113.7".parse().unwrap());
+
+        assert!(signer
+            .verify(&url, SignedUrlMethod::Get, client, 1000)
+            .is_ok());
+        assert_eq!(
+            signer.verify(&url, SignedUrlMethod::Get, client, 1001),
+            Err(SignedUrlError::Expired)
+        );
+        assert_eq!(
+            signer.verify(&url, SignedUrlMethod::Put, client, 1000),
+            Err(SignedUrlError::MethodNotAllowed)
+        );
+        assert_eq!(
+            signer.verify(
+                &url,
+                SignedUrlMethod::Get,
+                Some("198.51.100.1".parse().unwrap()),
+                1000
+            ),
+            Err(SignedUrlError::IpMismatch)
+        );
+        assert_eq!(
+            signer.verify(&url, SignedUrlMethod::Get, None, 1000),
+            Err(SignedUrlError::IpMismatch)
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_tampering() {
+        let signer = UrlSigner::new("secret");
+        let url = signer.sign(BASE_URL, &scope(Some("203.0.113.7")));
+
+        for tampered in [
+            url.replace("expires=1000", "expires=9999"),
+            url.replace("method=GET", "method=PUT"),
+            url.replace("&ip=203.0.113.7", ""),
+            url.replace("/f1?", "/f2?"),
+        ] {
+            assert_eq!(
+                signer.verify(&tampered, SignedUrlMethod::Put, None, 0),
+                Err(SignedUrlError::BadSignature),
+                "{tampered}"
+            );
+        }
+
+        assert_eq!(
+            UrlSigner::new("other").verify(&url, SignedUrlMethod::Get, None, 0),
+            Err(SignedUrlError::BadSignature)
+        );
+        assert_eq!(
+            signer.verify("http://localhost/files/f1", SignedUrlMethod::Get, None, 0),
+            Err(SignedUrlError::Malformed)
+        );
+        assert_eq!(
+            signer.verify(
+                &url.replace("method=GET", "method=POST"),
+                SignedUrlMethod::Get,
+                None,
+                0
+            ),
+            Err(SignedUrlError::Malformed)
+        );
+    }
+}